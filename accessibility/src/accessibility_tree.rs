@@ -1,6 +1,8 @@
+use crate::accname::{self, AccNameComputer};
 use crate::error::{Error, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use parking_lot::RwLock;
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
@@ -172,8 +174,89 @@ pub struct AccessibilityNode {
     pub valuetext: Option<String>,
 }
 
+impl AccessibilityNode {
+    /// Create a visible, enabled node with a role and no other properties
+    pub fn new(id: String, role: AccessibilityRole) -> Self {
+        Self {
+            id,
+            role,
+            name: None,
+            description: None,
+            value: None,
+            state: AccessibilityState::Hidden,
+            properties: HashMap::new(),
+            children: Vec::new(),
+            parent: None,
+            bounding_box: None,
+            is_visible: true,
+            is_focusable: false,
+            is_enabled: true,
+            is_selected: false,
+            is_expanded: false,
+            is_checked: false,
+            is_required: false,
+            is_invalid: false,
+            is_busy: false,
+            is_pressed: false,
+            is_read_only: false,
+            is_multi_line: false,
+            is_multi_selectable: false,
+            is_sorted: false,
+            is_sorted_ascending: false,
+            is_sorted_descending: false,
+            is_atomic: false,
+            is_live: false,
+            live_region: None,
+            current_value: None,
+            maximum_value: None,
+            minimum_value: None,
+            step_value: None,
+            level: None,
+            pos_in_set: None,
+            set_size: None,
+            column_index: None,
+            column_span: None,
+            row_index: None,
+            row_span: None,
+            column_count: None,
+            row_count: None,
+            column_header_cells: Vec::new(),
+            row_header_cells: Vec::new(),
+            controls: Vec::new(),
+            described_by: Vec::new(),
+            details: Vec::new(),
+            error_message: Vec::new(),
+            flow_to: Vec::new(),
+            labeled_by: Vec::new(),
+            owns: Vec::new(),
+            active_descendant: None,
+            auto_complete: None,
+            has_popup: None,
+            orientation: None,
+            sort: None,
+            current: None,
+            dropeffect: None,
+            grabbed: None,
+            keyshortcuts: None,
+            modal: None,
+            multiline: None,
+            multiselectable: None,
+            placeholder: None,
+            readonly: None,
+            required: None,
+            selected: None,
+            setsize: None,
+            posinset: None,
+            valuemax: None,
+            valuemin: None,
+            valuenow: None,
+            valuetext: None,
+        }
+    }
+}
+
 /// Accessibility Role
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AccessibilityRole {
     /// Alert role
     Alert,
@@ -474,6 +557,19 @@ pub struct FocusManager {
     focusable_nodes: Vec<String>,
     /// Focus order
     focus_order: Vec<String>,
+    /// Parent of each node, for finding a focus trap's descendants
+    node_parents: HashMap<String, String>,
+    /// Active focus traps, innermost last
+    focus_traps: Vec<FocusTrap>,
+}
+
+/// Focus trap keeping Tab navigation inside a container such as a modal dialog
+#[derive(Debug, Clone)]
+struct FocusTrap {
+    /// Container that focus is kept within
+    container_id: String,
+    /// Node that had focus before the trap, restored when it is popped
+    previous_focus: Option<String>,
 }
 
 /// Navigation Manager
@@ -564,6 +660,24 @@ pub struct AriaManager {
     aria_properties: HashMap<String, AriaProperty>,
     /// ARIA landmarks
     aria_landmarks: HashMap<String, AriaLandmark>,
+    /// Text of live regions by node ID
+    live_regions: HashMap<String, String>,
+    /// Announcements waiting for assistive technology
+    pending_announcements: Vec<PendingAnnouncement>,
+}
+
+/// Identical announcements closer together than this are announced once
+pub const ANNOUNCEMENT_DEDUP_WINDOW: Duration = Duration::from_millis(50);
+
+/// Live region announcement waiting for assistive technology
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingAnnouncement {
+    /// Text to announce
+    pub text: String,
+    /// Politeness of the live region
+    pub politeness: LiveRegion,
+    /// When the announcement was queued
+    pub timestamp: Instant,
 }
 
 /// ARIA Attribute
@@ -682,6 +796,19 @@ impl AccessibilityTree {
 
     /// Add accessibility node
     pub async fn add_node(&self, node: AccessibilityNode) -> Result<()> {
+        if node.live_region.is_some_and(|politeness| politeness != LiveRegion::Off) {
+            let text = node.name.as_deref().or(node.value.as_deref()).unwrap_or_default();
+            self.aria_manager.write().update_live_region(&node.id, text);
+        }
+        
+        {
+            let mut focus_manager = self.focus_manager.write();
+            focus_manager.set_node_parent(&node.id, node.parent.as_deref());
+            if node.is_focusable {
+                focus_manager.add_focusable_node(&node.id);
+            }
+        }
+        
         let mut nodes = self.nodes.write();
         nodes.insert(node.id.clone(), node);
         
@@ -702,6 +829,46 @@ impl AccessibilityTree {
         Ok(())
     }
 
+    /// Copy of every node, for synchronous consumers such as the auditor
+    pub(crate) fn nodes_snapshot(&self) -> Vec<AccessibilityNode> {
+        self.nodes.read().values().cloned().collect()
+    }
+
+    /// ID of the root node
+    pub fn root_id(&self) -> Option<String> {
+        self.root.read().as_ref().map(|root| root.id.clone())
+    }
+
+    /// Role of a node
+    pub fn node_role(&self, node_id: &str) -> Option<AccessibilityRole> {
+        self.nodes.read().get(node_id).map(|node| node.role)
+    }
+
+    /// Name of a node
+    pub fn node_name(&self, node_id: &str) -> Option<String> {
+        self.nodes.read().get(node_id).and_then(|node| node.name.clone())
+    }
+
+    /// Add a node for each rendered element of a document, naming them with the accname algorithm
+    pub async fn build_from_document(&self, document: &dom::Document) -> Result<()> {
+        let mut nodes = Vec::new();
+        collect_document_nodes(&document.root, &mut Vec::new(), None, &mut nodes);
+        let node_ids: Vec<String> = nodes.iter().map(|node| node.id.clone()).collect();
+        *self.root.write() = nodes.first().cloned();
+        for node in nodes {
+            self.add_node(node).await?;
+        }
+        
+        for node_id in node_ids {
+            let name = AccNameComputer::compute_name(&node_id, self, document);
+            if let Some(node) = self.nodes.write().get_mut(&node_id) {
+                node.name = (!name.is_empty()).then_some(name);
+            }
+        }
+        
+        Ok(())
+    }
+
     /// Get focused node
     pub async fn get_focused_node(&self) -> Result<Option<AccessibilityNode>> {
         let focus_manager = self.focus_manager.read();
@@ -716,17 +883,27 @@ impl AccessibilityTree {
     /// Navigate to next node
     pub async fn navigate_next(&self) -> Result<Option<AccessibilityNode>> {
         let mut navigation_manager = self.navigation_manager.write();
-        let next_node = navigation_manager.navigate_next()?;
+        let next_node = navigation_manager.navigate_next(&mut self.focus_manager.write())?;
         
-        Ok(next_node)
+        Ok(next_node.and_then(|node_id| self.nodes.read().get(&node_id).cloned()))
     }
 
     /// Navigate to previous node
     pub async fn navigate_previous(&self) -> Result<Option<AccessibilityNode>> {
         let mut navigation_manager = self.navigation_manager.write();
-        let previous_node = navigation_manager.navigate_previous()?;
+        let previous_node = navigation_manager.navigate_previous(&mut self.focus_manager.write())?;
         
-        Ok(previous_node)
+        Ok(previous_node.and_then(|node_id| self.nodes.read().get(&node_id).cloned()))
+    }
+
+    /// Keep Tab navigation within a container, such as an open modal dialog
+    pub async fn push_focus_trap(&self, container_id: &str) -> Result<()> {
+        self.focus_manager.write().push_focus_trap(container_id)
+    }
+
+    /// Release the innermost focus trap, restoring the focus from before it
+    pub async fn pop_focus_trap(&self) -> Result<()> {
+        self.focus_manager.write().pop_focus_trap()
     }
 
     /// Get accessibility tree as JSON
@@ -783,6 +960,16 @@ impl AccessibilityTree {
         Ok(aria_manager.get_attributes_for_node(node_id))
     }
 
+    /// Queue an announcement of a live region for assistive technology
+    pub async fn announce(&self, node_id: &str, politeness: LiveRegion) -> Result<()> {
+        self.aria_manager.write().announce(node_id, politeness)
+    }
+
+    /// Drain the announcements waiting for assistive technology
+    pub fn process_announcements(&self) -> Vec<PendingAnnouncement> {
+        self.aria_manager.write().take_announcements()
+    }
+
     /// Get ARIA landmarks
     pub async fn get_aria_landmarks(&self) -> Result<Vec<AriaLandmark>> {
         let aria_manager = self.aria_manager.read();
@@ -818,6 +1005,46 @@ impl AccessibilityTree {
     }
 }
 
+/// Build nodes for an element and its rendered descendants, in document order
+fn collect_document_nodes(element: &dom::Element, path: &mut Vec<usize>, parent: Option<&str>, nodes: &mut Vec<AccessibilityNode>) -> Option<String> {
+    if accname::is_hidden(element) {
+        return None;
+    }
+    
+    let role = accname::role_for_element(element).unwrap_or(AccessibilityRole::Generic);
+    let mut node = AccessibilityNode::new(accname::element_node_id(element, path), role);
+    let ids = |name: &str| element.get_attribute(name).map(|ids| ids.split_whitespace().map(str::to_string).collect()).unwrap_or_default();
+    let flag = |name: &str| element.get_attribute(name).is_some_and(|value| value == "true");
+    node.parent = parent.map(str::to_string);
+    node.description = element.get_attribute("aria-description").cloned();
+    node.value = element.get_attribute("value").cloned();
+    node.labeled_by = ids("aria-labelledby");
+    node.described_by = ids("aria-describedby");
+    node.controls = ids("aria-controls");
+    node.is_enabled = !element.has_attribute("disabled") && !flag("aria-disabled");
+    node.is_checked = element.has_attribute("checked") || flag("aria-checked");
+    node.is_required = element.has_attribute("required") || flag("aria-required");
+    node.is_read_only = element.has_attribute("readonly") || flag("aria-readonly");
+    node.is_multi_line = element.tag_name == "textarea" || flag("aria-multiline");
+    node.is_focusable = node.is_enabled && (element.get_attribute("tabindex").is_some_and(|index| index.trim().parse::<i32>().is_ok())
+        || matches!(element.tag_name.as_str(), "button" | "input" | "select" | "textarea" | "summary")
+        || (element.tag_name == "a" && element.has_attribute("href")));
+    let index = nodes.len();
+    nodes.push(node);
+    
+    let node_id = nodes[index].id.clone();
+    let mut children = Vec::new();
+    for (child_index, child) in element.children.iter().enumerate() {
+        if let dom::Node::Element(child) = child {
+            path.push(child_index);
+            children.extend(collect_document_nodes(child, path, Some(&node_id), nodes));
+            path.pop();
+        }
+    }
+    nodes[index].children = children;
+    Some(node_id)
+}
+
 impl FocusManager {
     /// Create new focus manager
     pub fn new() -> Self {
@@ -826,6 +1053,8 @@ impl FocusManager {
             focus_history: Vec::new(),
             focusable_nodes: Vec::new(),
             focus_order: Vec::new(),
+            node_parents: HashMap::new(),
+            focus_traps: Vec::new(),
         }
     }
 
@@ -862,6 +1091,78 @@ impl FocusManager {
     pub fn remove_focusable_node(&mut self, node_id: &str) {
         self.focusable_nodes.retain(|id| id != node_id);
     }
+
+    /// Record the parent of a node
+    pub fn set_node_parent(&mut self, node_id: &str, parent_id: Option<&str>) {
+        match parent_id {
+            Some(parent_id) => self.node_parents.insert(node_id.to_string(), parent_id.to_string()),
+            None => self.node_parents.remove(node_id),
+        };
+    }
+
+    /// Keep focus within the focusable descendants of a container, moving focus into it
+    pub fn push_focus_trap(&mut self, container_id: &str) -> Result<()> {
+        let trap = FocusTrap {
+            container_id: container_id.to_string(),
+            previous_focus: self.focused_node.clone(),
+        };
+        let Some(first) = self.trapped_nodes(&trap.container_id).first().cloned() else {
+            return Err(Error::focus(format!("No focusable nodes in {}", container_id)));
+        };
+        
+        let focus_inside = self.focused_node.as_deref().is_some_and(|focused| self.is_descendant(focused, container_id));
+        self.focus_traps.push(trap);
+        if !focus_inside {
+            self.set_focus(&first)?;
+        }
+        
+        Ok(())
+    }
+
+    /// Remove the innermost focus trap and restore focus to the node focused before it
+    pub fn pop_focus_trap(&mut self) -> Result<()> {
+        let trap = self.focus_traps.pop()
+            .ok_or_else(|| Error::focus("No focus trap is active".to_string()))?;
+        
+        match trap.previous_focus {
+            Some(previous_focus) => self.set_focus(&previous_focus)?,
+            None => self.focused_node = None,
+        }
+        
+        Ok(())
+    }
+
+    /// Container of the innermost focus trap
+    pub fn active_focus_trap(&self) -> Option<&String> {
+        self.focus_traps.last().map(|trap| &trap.container_id)
+    }
+
+    /// Focusable nodes that Tab navigation may visit, in focus order
+    pub fn navigable_nodes(&self) -> Vec<String> {
+        match self.active_focus_trap() {
+            Some(container_id) => self.trapped_nodes(container_id),
+            None => self.focusable_nodes.clone(),
+        }
+    }
+
+    fn trapped_nodes(&self, container_id: &str) -> Vec<String> {
+        self.focusable_nodes
+            .iter()
+            .filter(|node_id| self.is_descendant(node_id, container_id))
+            .cloned()
+            .collect()
+    }
+
+    fn is_descendant(&self, node_id: &str, container_id: &str) -> bool {
+        let mut current = self.node_parents.get(node_id);
+        while let Some(parent_id) = current {
+            if parent_id == container_id {
+                return true;
+            }
+            current = self.node_parents.get(parent_id);
+        }
+        false
+    }
 }
 
 impl NavigationManager {
@@ -874,37 +1175,44 @@ impl NavigationManager {
         }
     }
 
-    /// Navigate to next node
-    pub fn navigate_next(&mut self) -> Result<Option<AccessibilityNode>> {
-        // This is a simplified implementation
-        // In a real implementation, you would navigate to the next node based on the current mode
-        
-        let event = NavigationEvent {
-            id: Uuid::new_v4().to_string(),
-            event_type: NavigationEventType::Next,
-            source_node: "current".to_string(),
-            target_node: None,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-        };
-        
-        self.navigation_history.push(event);
-        
-        Ok(None)
+    /// Move focus to the next focusable node, as Tab does
+    ///
+    /// Inside a focus trap, focus wraps from the last node to the first.
+    pub fn navigate_next(&mut self, focus_manager: &mut FocusManager) -> Result<Option<String>> {
+        self.navigate_focus(focus_manager, NavigationEventType::Next)
     }
 
-    /// Navigate to previous node
-    pub fn navigate_previous(&mut self) -> Result<Option<AccessibilityNode>> {
-        // This is a simplified implementation
-        // In a real implementation, you would navigate to the previous node based on the current mode
+    /// Move focus to the previous focusable node, as Shift+Tab does
+    ///
+    /// Inside a focus trap, focus wraps from the first node to the last.
+    pub fn navigate_previous(&mut self, focus_manager: &mut FocusManager) -> Result<Option<String>> {
+        self.navigate_focus(focus_manager, NavigationEventType::Previous)
+    }
+
+    fn navigate_focus(&mut self, focus_manager: &mut FocusManager, event_type: NavigationEventType) -> Result<Option<String>> {
+        let candidates = focus_manager.navigable_nodes();
+        let current = focus_manager.get_focused_node().cloned();
+        let position = current.as_ref().and_then(|current| candidates.iter().position(|id| id == current));
+        let wraps = focus_manager.active_focus_trap().is_some();
+        
+        let target_index = match (event_type, position) {
+            (NavigationEventType::Previous, None) => candidates.len().checked_sub(1),
+            (NavigationEventType::Previous, Some(0)) => if wraps { candidates.len().checked_sub(1) } else { None },
+            (NavigationEventType::Previous, Some(index)) => Some(index - 1),
+            (_, None) => (!candidates.is_empty()).then_some(0),
+            (_, Some(index)) if index + 1 == candidates.len() => if wraps { Some(0) } else { None },
+            (_, Some(index)) => Some(index + 1),
+        };
+        let target = target_index.map(|index| candidates[index].clone());
+        if let Some(target) = &target {
+            focus_manager.set_focus(target)?;
+        }
         
         let event = NavigationEvent {
             id: Uuid::new_v4().to_string(),
-            event_type: NavigationEventType::Previous,
-            source_node: "current".to_string(),
-            target_node: None,
+            event_type,
+            source_node: current.unwrap_or_default(),
+            target_node: target.clone(),
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
@@ -913,7 +1221,7 @@ impl NavigationManager {
         
         self.navigation_history.push(event);
         
-        Ok(None)
+        Ok(target)
     }
 
     /// Set navigation mode
@@ -935,7 +1243,53 @@ impl AriaManager {
             aria_states: HashMap::new(),
             aria_properties: HashMap::new(),
             aria_landmarks: HashMap::new(),
+            live_regions: HashMap::new(),
+            pending_announcements: Vec::new(),
+        }
+    }
+
+    /// Set the text of a live region
+    pub fn update_live_region(&mut self, node_id: &str, text: &str) {
+        self.live_regions.insert(node_id.to_string(), text.to_string());
+    }
+
+    /// Queue an announcement of a live region's text
+    ///
+    /// An assertive announcement interrupts, dropping pending polite ones.
+    /// Announcements with politeness `Off` are ignored.
+    pub fn announce(&mut self, node_id: &str, politeness: LiveRegion) -> Result<()> {
+        let text = self.live_regions.get(node_id)
+            .ok_or_else(|| Error::Aria(format!("Node {} is not a live region", node_id)))?;
+        if politeness == LiveRegion::Off || text.trim().is_empty() {
+            return Ok(());
+        }
+
+        if politeness == LiveRegion::Assertive {
+            self.pending_announcements.retain(|announcement| announcement.politeness != LiveRegion::Polite);
         }
+        self.pending_announcements.push(PendingAnnouncement {
+            text: text.clone(),
+            politeness,
+            timestamp: Instant::now(),
+        });
+        Ok(())
+    }
+
+    /// Drain the pending announcements in the order they were queued
+    ///
+    /// A message identical to one kept less than `ANNOUNCEMENT_DEDUP_WINDOW` earlier is dropped.
+    pub fn take_announcements(&mut self) -> Vec<PendingAnnouncement> {
+        let mut announcements: Vec<PendingAnnouncement> = Vec::new();
+        for announcement in self.pending_announcements.drain(..) {
+            let duplicate = announcements.iter().any(|kept| {
+                kept.text == announcement.text
+                    && announcement.timestamp.duration_since(kept.timestamp) < ANNOUNCEMENT_DEDUP_WINDOW
+            });
+            if !duplicate {
+                announcements.push(announcement);
+            }
+        }
+        announcements
     }
 
     /// Get attributes for node
@@ -968,3 +1322,76 @@ pub struct AccessibilityStats {
     /// Roles
     pub roles: std::collections::HashSet<AccessibilityRole>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polite_announcements_in_order() {
+        let tree = AccessibilityTree::new();
+        {
+            let mut aria_manager = tree.aria_manager.write();
+            for (node_id, text) in [("status", "Saving"), ("progress", "50% done"), ("result", "Saved")] {
+                aria_manager.update_live_region(node_id, text);
+            }
+        }
+
+        for node_id in ["status", "progress", "result"] {
+            tree.aria_manager.write().announce(node_id, LiveRegion::Polite).unwrap();
+        }
+        let announcements = tree.process_announcements();
+        let texts: Vec<&str> = announcements.iter().map(|announcement| announcement.text.as_str()).collect();
+        assert_eq!(texts, vec!["Saving", "50% done", "Saved"]);
+        assert!(announcements.iter().all(|announcement| announcement.politeness == LiveRegion::Polite));
+        assert!(tree.process_announcements().is_empty());
+    }
+
+    #[test]
+    fn test_assertive_announcements_flush_polite_and_dedupe() {
+        let mut aria_manager = AriaManager::new();
+        aria_manager.update_live_region("status", "Saving");
+        aria_manager.update_live_region("alert", "Connection lost");
+
+        aria_manager.announce("status", LiveRegion::Polite).unwrap();
+        aria_manager.announce("alert", LiveRegion::Assertive).unwrap();
+        aria_manager.announce("alert", LiveRegion::Assertive).unwrap();
+        aria_manager.announce("status", LiveRegion::Off).unwrap();
+        assert!(aria_manager.announce("missing", LiveRegion::Polite).is_err());
+
+        let announcements = aria_manager.take_announcements();
+        assert_eq!(announcements.len(), 1);
+        assert_eq!(announcements[0].text, "Connection lost");
+        assert_eq!(announcements[0].politeness, LiveRegion::Assertive);
+    }
+
+    #[test]
+    fn test_focus_trap_wraps_tab_navigation() {
+        let mut focus_manager = FocusManager::new();
+        let mut navigation_manager = NavigationManager::new();
+        for (node_id, parent_id) in [("open", None), ("dialog", None), ("name", Some("dialog")), ("email", Some("dialog")), ("close", Some("dialog")), ("footer", None)] {
+            focus_manager.set_node_parent(node_id, parent_id);
+            if node_id != "dialog" {
+                focus_manager.add_focusable_node(node_id);
+            }
+        }
+        focus_manager.set_focus("open").unwrap();
+        assert!(focus_manager.push_focus_trap("footer").is_err());
+
+        focus_manager.push_focus_trap("dialog").unwrap();
+        assert_eq!(focus_manager.get_focused_node().map(String::as_str), Some("name"));
+        let mut visited = Vec::new();
+        for _ in 0..3 {
+            visited.push(navigation_manager.navigate_next(&mut focus_manager).unwrap().unwrap());
+        }
+        assert_eq!(visited, vec!["email", "close", "name"]);
+        assert_eq!(navigation_manager.navigate_previous(&mut focus_manager).unwrap().as_deref(), Some("close"));
+
+        focus_manager.pop_focus_trap().unwrap();
+        assert_eq!(focus_manager.get_focused_node().map(String::as_str), Some("open"));
+        assert!(focus_manager.pop_focus_trap().is_err());
+        assert_eq!(navigation_manager.navigate_next(&mut focus_manager).unwrap().as_deref(), Some("name"));
+        focus_manager.set_focus("footer").unwrap();
+        assert_eq!(navigation_manager.navigate_next(&mut focus_manager).unwrap(), None);
+    }
+}
@@ -0,0 +1,502 @@
+//! Accessible name computation (accname 1.2)
+//!
+//! Accessibility nodes built from a document are identified by the `id`
+//! attribute of their element, or by the path of child indices from the
+//! root element when the element has none.
+
+use crate::accessibility_tree::{AccessibilityRole, AccessibilityTree};
+use dom::{Document, Element, Node};
+
+/// Prefix of node IDs made from an element's position in the document
+const PATH_ID_PREFIX: &str = "node-";
+
+/// Inline elements whose text joins their siblings' without a space
+const INLINE_TAGS: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "cite", "code", "data", "dfn", "em", "i", "img", "kbd", "label",
+    "mark", "q", "s", "samp", "small", "span", "strong", "sub", "sup", "time", "u", "var",
+];
+
+/// Computes accessible names with the accname 1.2 text alternative algorithm
+pub struct AccNameComputer;
+
+impl AccNameComputer {
+    /// Accessible name of a node, or an empty string if it has none
+    pub fn compute_name(node_id: &str, tree: &AccessibilityTree, dom: &Document) -> String {
+        let Some(element) = find_element(&dom.root, node_id) else {
+            return tree.node_name(node_id).unwrap_or_default();
+        };
+        let role = tree.node_role(node_id).or_else(|| role_for_element(element));
+
+        let traversal = Traversal { document: dom };
+        normalize_whitespace(&traversal.text_alternative(element, role, Step::ROOT))
+    }
+}
+
+/// Where the algorithm is in its traversal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Step {
+    /// Computing the name of the node itself rather than of a descendant or reference
+    root: bool,
+    /// Inside a traversal started by `aria-labelledby`, which is not followed again
+    labelled_by: bool,
+    /// Directly referenced by `aria-labelledby`
+    referenced: bool,
+}
+
+impl Step {
+    const ROOT: Step = Step { root: true, labelled_by: false, referenced: false };
+    const REFERENCE: Step = Step { root: false, labelled_by: true, referenced: true };
+
+    /// Step for a descendant contributing to a name from content
+    fn content(self) -> Step {
+        Step { root: false, labelled_by: self.labelled_by, referenced: false }
+    }
+}
+
+struct Traversal<'a> {
+    document: &'a Document,
+}
+
+impl<'a> Traversal<'a> {
+    fn text_alternative(&self, element: &'a Element, role: Option<AccessibilityRole>, step: Step) -> String {
+        // 2A: hidden elements contribute nothing unless referenced directly
+        if is_hidden(element) && !step.referenced {
+            return String::new();
+        }
+
+        // 2B: aria-labelledby, unless already following a reference
+        if !step.labelled_by {
+            if let Some(ids) = element.get_attribute("aria-labelledby") {
+                let names: Vec<String> = ids
+                    .split_whitespace()
+                    .filter_map(|id| self.document.root.get_element_by_id(id))
+                    .map(|referenced| self.text_alternative(referenced, role_for_element(referenced), Step::REFERENCE))
+                    .filter(|name| !name.trim().is_empty())
+                    .collect();
+                if !names.is_empty() {
+                    return names.join(" ");
+                }
+            }
+        }
+
+        // 2C: embedded controls inside another name use their value
+        if !step.root {
+            if let Some(value) = embedded_control_value(element, role) {
+                return value;
+            }
+        }
+
+        // 2D: aria-label
+        if let Some(label) = element.get_attribute("aria-label").filter(|label| !label.trim().is_empty()) {
+            return label.clone();
+        }
+
+        // 2E: native host language naming
+        if !matches!(role, Some(AccessibilityRole::None | AccessibilityRole::Presentation)) {
+            if let Some(name) = self.native_name(element, step) {
+                return name;
+            }
+        }
+
+        // 2F: name from content
+        if !step.root || role.is_some_and(allows_name_from_content) {
+            let name = self.name_from_content(element, step);
+            if !name.trim().is_empty() {
+                return name;
+            }
+        }
+
+        // 2I: tooltip, then the placeholder of text fields
+        element.get_attribute("title")
+            .or_else(|| element.get_attribute("placeholder"))
+            .filter(|tooltip| !tooltip.trim().is_empty())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn native_name(&self, element: &'a Element, step: Step) -> Option<String> {
+        let input_type = element.get_attribute("type").map(|input_type| input_type.to_ascii_lowercase());
+        match (element.tag_name.as_str(), input_type.as_deref()) {
+            ("img" | "area", _) | ("input", Some("image")) => {
+                element.get_attribute("alt").filter(|alt| !alt.trim().is_empty()).cloned()
+            }
+            ("input", Some(kind @ ("button" | "submit" | "reset"))) => {
+                let default = match kind {
+                    "submit" => Some("Submit".to_string()),
+                    "reset" => Some("Reset".to_string()),
+                    _ => None,
+                };
+                element.get_attribute("value").filter(|value| !value.trim().is_empty()).cloned().or(default)
+            }
+            ("input" | "textarea" | "select" | "meter" | "progress" | "output", _) => {
+                let names: Vec<String> = self.labels_for(element)
+                    .into_iter()
+                    .map(|label| self.text_alternative(label, None, step.content()))
+                    .filter(|name| !name.trim().is_empty())
+                    .collect();
+                (!names.is_empty()).then(|| names.join(" "))
+            }
+            ("fieldset", _) => self.caption_name(element, "legend", step),
+            ("figure", _) => self.caption_name(element, "figcaption", step),
+            ("table", _) => self.caption_name(element, "caption", step),
+            _ => None,
+        }
+    }
+
+    fn caption_name(&self, element: &'a Element, caption_tag: &str, step: Step) -> Option<String> {
+        let caption = child_elements(element).find(|child| child.tag_name == caption_tag)?;
+        let name = self.text_alternative(caption, None, step.content());
+        (!name.trim().is_empty()).then_some(name)
+    }
+
+    /// `<label>` elements naming a form control: those whose `for` is its ID, then its ancestor label
+    fn labels_for(&self, control: &'a Element) -> Vec<&'a Element> {
+        let mut labels: Vec<&'a Element> = match control.get_attribute("id") {
+            Some(id) => self.document.root
+                .get_elements_by_tag_name("label")
+                .into_iter()
+                .filter(|label| label.get_attribute("for") == Some(id))
+                .collect(),
+            None => Vec::new(),
+        };
+        if let Some(ancestor) = ancestors(&self.document.root, control)
+            .into_iter()
+            .rev()
+            .find(|ancestor| ancestor.tag_name == "label" && !ancestor.has_attribute("for"))
+        {
+            labels.push(ancestor);
+        }
+        labels
+    }
+
+    fn name_from_content(&self, element: &'a Element, step: Step) -> String {
+        let mut name = String::new();
+        for child in &element.children {
+            match child {
+                Node::Text(text) => name.push_str(&text.content),
+                Node::Element(child) if child.tag_name == "br" => name.push(' '),
+                Node::Element(child) => {
+                    let text = self.text_alternative(child, role_for_element(child), step.content());
+                    if INLINE_TAGS.contains(&child.tag_name.as_str()) {
+                        name.push_str(&text);
+                    } else {
+                        name.push(' ');
+                        name.push_str(&text);
+                        name.push(' ');
+                    }
+                }
+                _ => {}
+            }
+        }
+        name
+    }
+}
+
+/// Role of an element: its `role` attribute, or the implicit role of its tag
+pub fn role_for_element(element: &Element) -> Option<AccessibilityRole> {
+    if let Some(role) = element.get_attribute("role").and_then(|roles| roles.split_whitespace().find_map(parse_role)) {
+        return Some(role);
+    }
+
+    let input_type = element.get_attribute("type").map(|input_type| input_type.to_ascii_lowercase());
+    let role = match element.tag_name.as_str() {
+        "a" | "area" if element.has_attribute("href") => AccessibilityRole::Link,
+        "article" => AccessibilityRole::Article,
+        "aside" => AccessibilityRole::Complementary,
+        "button" | "summary" => AccessibilityRole::Button,
+        "dd" => AccessibilityRole::Definition,
+        "details" | "fieldset" | "optgroup" => AccessibilityRole::Group,
+        "dialog" => AccessibilityRole::Dialog,
+        "dt" => AccessibilityRole::Term,
+        "figure" => AccessibilityRole::Figure,
+        "footer" => AccessibilityRole::ContentInfo,
+        "form" => AccessibilityRole::Form,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => AccessibilityRole::Heading,
+        "header" => AccessibilityRole::Banner,
+        "hr" => AccessibilityRole::Separator,
+        "html" => AccessibilityRole::Document,
+        "img" if element.get_attribute("alt").is_some_and(|alt| alt.is_empty()) => AccessibilityRole::Presentation,
+        "img" => AccessibilityRole::Img,
+        "input" => match input_type.as_deref() {
+            Some("hidden") => return None,
+            Some("checkbox") => AccessibilityRole::Checkbox,
+            Some("radio") => AccessibilityRole::Radio,
+            Some("button" | "submit" | "reset" | "image") => AccessibilityRole::Button,
+            Some("range") => AccessibilityRole::Slider,
+            Some("number") => AccessibilityRole::SpinButton,
+            Some("search") => AccessibilityRole::SearchBox,
+            _ => AccessibilityRole::TextBox,
+        },
+        "li" => AccessibilityRole::ListItem,
+        "main" => AccessibilityRole::Main,
+        "math" => AccessibilityRole::Math,
+        "menu" | "ol" | "ul" => AccessibilityRole::List,
+        "meter" => AccessibilityRole::Meter,
+        "nav" => AccessibilityRole::Navigation,
+        "option" => AccessibilityRole::Option,
+        "output" => AccessibilityRole::Status,
+        "progress" => AccessibilityRole::ProgressBar,
+        "section" => AccessibilityRole::Region,
+        "select" if element.has_attribute("multiple") => AccessibilityRole::ListBox,
+        "select" => AccessibilityRole::Combobox,
+        "table" => AccessibilityRole::Table,
+        "tbody" | "tfoot" | "thead" => AccessibilityRole::RowGroup,
+        "td" => AccessibilityRole::Cell,
+        "textarea" => AccessibilityRole::TextBox,
+        "th" => AccessibilityRole::ColumnHeader,
+        "tr" => AccessibilityRole::Row,
+        _ => return None,
+    };
+    Some(role)
+}
+
+/// Role named by an ARIA `role` token
+pub fn parse_role(role: &str) -> Option<AccessibilityRole> {
+    let role = match role.to_ascii_lowercase().as_str() {
+        "alert" => AccessibilityRole::Alert,
+        "alertdialog" => AccessibilityRole::AlertDialog,
+        "application" => AccessibilityRole::Application,
+        "article" => AccessibilityRole::Article,
+        "banner" => AccessibilityRole::Banner,
+        "button" => AccessibilityRole::Button,
+        "cell" => AccessibilityRole::Cell,
+        "checkbox" => AccessibilityRole::Checkbox,
+        "columnheader" => AccessibilityRole::ColumnHeader,
+        "combobox" => AccessibilityRole::Combobox,
+        "complementary" => AccessibilityRole::Complementary,
+        "contentinfo" => AccessibilityRole::ContentInfo,
+        "definition" => AccessibilityRole::Definition,
+        "dialog" => AccessibilityRole::Dialog,
+        "directory" => AccessibilityRole::Directory,
+        "document" => AccessibilityRole::Document,
+        "feed" => AccessibilityRole::Feed,
+        "figure" => AccessibilityRole::Figure,
+        "form" => AccessibilityRole::Form,
+        "generic" => AccessibilityRole::Generic,
+        "grid" => AccessibilityRole::Grid,
+        "gridcell" => AccessibilityRole::GridCell,
+        "group" => AccessibilityRole::Group,
+        "heading" => AccessibilityRole::Heading,
+        "img" | "image" => AccessibilityRole::Img,
+        "link" => AccessibilityRole::Link,
+        "list" => AccessibilityRole::List,
+        "listbox" => AccessibilityRole::ListBox,
+        "listitem" => AccessibilityRole::ListItem,
+        "log" => AccessibilityRole::Log,
+        "main" => AccessibilityRole::Main,
+        "marquee" => AccessibilityRole::Marquee,
+        "math" => AccessibilityRole::Math,
+        "menu" => AccessibilityRole::Menu,
+        "menubar" => AccessibilityRole::MenuBar,
+        "menuitem" => AccessibilityRole::MenuItem,
+        "menuitemcheckbox" => AccessibilityRole::MenuItemCheckbox,
+        "menuitemradio" => AccessibilityRole::MenuItemRadio,
+        "meter" => AccessibilityRole::Meter,
+        "navigation" => AccessibilityRole::Navigation,
+        "none" => AccessibilityRole::None,
+        "note" => AccessibilityRole::Note,
+        "option" => AccessibilityRole::Option,
+        "presentation" => AccessibilityRole::Presentation,
+        "progressbar" => AccessibilityRole::ProgressBar,
+        "radio" => AccessibilityRole::Radio,
+        "radiogroup" => AccessibilityRole::RadioGroup,
+        "region" => AccessibilityRole::Region,
+        "row" => AccessibilityRole::Row,
+        "rowgroup" => AccessibilityRole::RowGroup,
+        "rowheader" => AccessibilityRole::RowHeader,
+        "scrollbar" => AccessibilityRole::ScrollBar,
+        "search" => AccessibilityRole::Search,
+        "searchbox" => AccessibilityRole::SearchBox,
+        "section" => AccessibilityRole::Section,
+        "sectionhead" => AccessibilityRole::SectionHead,
+        "select" => AccessibilityRole::Select,
+        "separator" => AccessibilityRole::Separator,
+        "slider" => AccessibilityRole::Slider,
+        "spinbutton" => AccessibilityRole::SpinButton,
+        "status" => AccessibilityRole::Status,
+        "switch" => AccessibilityRole::Switch,
+        "tab" => AccessibilityRole::Tab,
+        "tablist" => AccessibilityRole::TabList,
+        "tabpanel" => AccessibilityRole::TabPanel,
+        "table" => AccessibilityRole::Table,
+        "term" => AccessibilityRole::Term,
+        "textbox" => AccessibilityRole::TextBox,
+        "timer" => AccessibilityRole::Timer,
+        "toolbar" => AccessibilityRole::ToolBar,
+        "tooltip" => AccessibilityRole::ToolTip,
+        "tree" => AccessibilityRole::Tree,
+        "treegrid" => AccessibilityRole::TreeGrid,
+        "treeitem" => AccessibilityRole::TreeItem,
+        _ => return None,
+    };
+    Some(role)
+}
+
+/// Whether a role takes its name from its content
+pub fn allows_name_from_content(role: AccessibilityRole) -> bool {
+    matches!(
+        role,
+        AccessibilityRole::Button
+            | AccessibilityRole::Cell
+            | AccessibilityRole::Checkbox
+            | AccessibilityRole::ColumnHeader
+            | AccessibilityRole::GridCell
+            | AccessibilityRole::Heading
+            | AccessibilityRole::Link
+            | AccessibilityRole::MenuItem
+            | AccessibilityRole::MenuItemCheckbox
+            | AccessibilityRole::MenuItemRadio
+            | AccessibilityRole::Option
+            | AccessibilityRole::Radio
+            | AccessibilityRole::Row
+            | AccessibilityRole::RowHeader
+            | AccessibilityRole::SectionHead
+            | AccessibilityRole::Switch
+            | AccessibilityRole::Tab
+            | AccessibilityRole::Term
+            | AccessibilityRole::ToolTip
+            | AccessibilityRole::TreeItem
+    )
+}
+
+/// ID of the accessibility node built for an element at a path of child indices
+pub(crate) fn element_node_id(element: &Element, path: &[usize]) -> String {
+    match element.get_attribute("id") {
+        Some(id) if !id.is_empty() => id.clone(),
+        _ => format!("{}{}", PATH_ID_PREFIX, path.iter().map(usize::to_string).collect::<Vec<_>>().join("-")),
+    }
+}
+
+/// Element an accessibility node was built for
+pub(crate) fn find_element<'a>(root: &'a Element, node_id: &str) -> Option<&'a Element> {
+    if let Some(element) = root.get_element_by_id(node_id) {
+        return Some(element);
+    }
+
+    let path = node_id.strip_prefix(PATH_ID_PREFIX)?;
+    let mut element = root;
+    for index in path.split('-').filter(|index| !index.is_empty()) {
+        element = match element.children.get(index.parse::<usize>().ok()?)? {
+            Node::Element(child) => child,
+            _ => return None,
+        };
+    }
+    Some(element)
+}
+
+/// Whether an element is excluded from the accessibility tree
+pub(crate) fn is_hidden(element: &Element) -> bool {
+    matches!(element.tag_name.as_str(), "head" | "script" | "style" | "template" | "noscript")
+        || element.has_attribute("hidden")
+        || element.get_attribute("aria-hidden").is_some_and(|hidden| hidden == "true")
+        || (element.tag_name == "input" && element.get_attribute("type").is_some_and(|input_type| input_type.eq_ignore_ascii_case("hidden")))
+}
+
+/// Value a form control contributes to the name of an element containing it
+fn embedded_control_value(element: &Element, role: Option<AccessibilityRole>) -> Option<String> {
+    match role? {
+        AccessibilityRole::TextBox | AccessibilityRole::SearchBox => Some(match element.tag_name.as_str() {
+            "textarea" => element.text_content(),
+            _ => element.get_attribute("value").cloned().unwrap_or_default(),
+        }),
+        AccessibilityRole::Combobox | AccessibilityRole::ListBox | AccessibilityRole::Select => {
+            let options = element.get_elements_by_tag_name("option");
+            let selected: Vec<String> = options
+                .iter()
+                .filter(|option| option.has_attribute("selected") || option.get_attribute("aria-selected").is_some_and(|selected| selected == "true"))
+                .map(|option| option.text_content())
+                .collect();
+            Some(if selected.is_empty() {
+                // A single select shows its first option when none is selected
+                options.first().filter(|_| !element.has_attribute("multiple")).map(|option| option.text_content()).unwrap_or_default()
+            } else {
+                selected.join(" ")
+            })
+        }
+        AccessibilityRole::Slider | AccessibilityRole::SpinButton | AccessibilityRole::ProgressBar | AccessibilityRole::Meter => {
+            element.get_attribute("aria-valuetext")
+                .or_else(|| element.get_attribute("aria-valuenow"))
+                .or_else(|| element.get_attribute("value"))
+                .cloned()
+        }
+        _ => None,
+    }
+}
+
+fn child_elements(element: &Element) -> impl Iterator<Item = &Element> {
+    element.children.iter().filter_map(|child| match child {
+        Node::Element(child) => Some(child),
+        _ => None,
+    })
+}
+
+/// Ancestors of an element from the root down, or nothing if it isn't in the tree
+fn ancestors<'a>(root: &'a Element, target: &Element) -> Vec<&'a Element> {
+    fn search<'a>(element: &'a Element, target: &Element, path: &mut Vec<&'a Element>) -> bool {
+        if std::ptr::eq(element, target) {
+            return true;
+        }
+        path.push(element);
+        if child_elements(element).any(|child| search(child, target, path)) {
+            return true;
+        }
+        path.pop();
+        false
+    }
+
+    let mut path = Vec::new();
+    search(root, target, &mut path);
+    path
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(tag_name: &str, attributes: &[(&str, &str)], children: Vec<Node>) -> Element {
+        let mut element = Element::new(tag_name.to_string());
+        for (name, value) in attributes {
+            element.set_attribute(name.to_string(), value.to_string());
+        }
+        element.children = children;
+        element
+    }
+
+    fn text(content: &str) -> Node {
+        Node::Text(dom::TextNode::new(content.to_string()))
+    }
+
+    #[test]
+    fn test_button_named_by_image_alt() {
+        let mut document = Document::new();
+        let body = element("body", &[], vec![
+            Node::Element(element("button", &[("id", "save")], vec![
+                Node::Element(element("img", &[("src", "save.png"), ("alt", "Save")], vec![])),
+            ])),
+            Node::Element(element("label", &[("for", "email")], vec![text("Email "), Node::Element(element("span", &[], vec![text("address")]))])),
+            Node::Element(element("input", &[("id", "email"), ("type", "email"), ("placeholder", "you@example.com")], vec![])),
+            Node::Element(element("span", &[("id", "prefix")], vec![text("Delete")])),
+            Node::Element(element("button", &[("id", "delete"), ("aria-labelledby", "prefix delete"), ("aria-label", "file")], vec![])),
+            Node::Element(element("img", &[("id", "logo"), ("alt", "Matte"), ("title", "Home")], vec![])),
+        ]);
+        document.root.append_child(Node::Element(body));
+
+        let tree = AccessibilityTree::new();
+        tokio_test::block_on(tree.build_from_document(&document)).unwrap();
+        assert_eq!(AccNameComputer::compute_name("save", &tree, &document), "Save");
+        assert_eq!(AccNameComputer::compute_name("email", &tree, &document), "Email address");
+        assert_eq!(AccNameComputer::compute_name("delete", &tree, &document), "Delete file");
+        assert_eq!(AccNameComputer::compute_name("logo", &tree, &document), "Matte");
+
+        let save = tokio_test::block_on(tree.get_node("save")).unwrap().unwrap();
+        assert_eq!(save.role, AccessibilityRole::Button);
+        assert_eq!(save.name.as_deref(), Some("Save"));
+        assert!(save.is_focusable);
+        let body = tokio_test::block_on(tree.get_node("node-0")).unwrap().unwrap();
+        assert_eq!(body.children.len(), 6);
+    }
+}
@@ -0,0 +1,734 @@
+//! AT-SPI2 screen reader integration for Linux
+//!
+//! The application registers on the accessibility bus and publishes an
+//! object implementing `org.a11y.atspi.Accessible` for each node of the
+//! accessibility tree, so screen readers such as Orca can read it.
+
+use crate::accessibility_tree::{AccessibilityNode, AccessibilityRole};
+use crate::error::{Error, Result};
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Object path of the application's root accessible
+pub const ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+
+/// Bus name of the AT-SPI2 registry
+pub const REGISTRY_BUS_NAME: &str = "org.a11y.atspi.Registry";
+
+/// Interface the accessible objects implement
+pub const ACCESSIBLE_INTERFACE: &str = "org.a11y.atspi.Accessible";
+
+/// Interface of object events such as state changes
+pub const OBJECT_EVENT_INTERFACE: &str = "org.a11y.atspi.Event.Object";
+
+/// Prefix of the object paths of exposed nodes
+const NODE_PATH_PREFIX: &str = "/org/a11y/atspi/accessible/";
+
+/// Name of the application as screen readers announce it
+const APPLICATION_NAME: &str = "Matte Browser";
+
+/// D-Bus object path of an exposed accessible
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AtSpiObjectPath(pub String);
+
+impl AtSpiObjectPath {
+    /// Path of the application's root accessible
+    pub fn root() -> Self {
+        Self(ROOT_PATH.to_string())
+    }
+
+    /// Path of a node, escaping characters D-Bus paths don't allow
+    pub fn for_node(node_id: &str) -> Self {
+        let mut path = NODE_PATH_PREFIX.to_string();
+        for byte in node_id.bytes() {
+            if byte.is_ascii_alphanumeric() {
+                path.push(byte as char);
+            } else {
+                path.push_str(&format!("_{:02x}", byte));
+            }
+        }
+        Self(path)
+    }
+
+    /// Path as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Reference to an accessible on the bus, as AT-SPI2 passes it in `(so)` pairs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtSpiObjectRef {
+    /// Unique name of the connection serving the object
+    pub bus_name: String,
+    /// Path of the object
+    pub path: AtSpiObjectPath,
+}
+
+/// AT-SPI2 relation types, as `AtspiRelationType` numbers them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum AtSpiRelation {
+    LabelledBy = 2,
+    ControllerFor = 3,
+    FlowsTo = 10,
+    DescribedBy = 17,
+    Details = 19,
+    ErrorMessage = 21,
+}
+
+/// AT-SPI2 states, as `AtspiStateType` numbers them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum AtSpiState {
+    Busy = 3,
+    Checked = 4,
+    Enabled = 8,
+    Expandable = 9,
+    Expanded = 10,
+    Focusable = 11,
+    Focused = 12,
+    MultiLine = 17,
+    Multiselectable = 18,
+    Pressed = 20,
+    Selectable = 22,
+    Selected = 23,
+    Sensitive = 24,
+    Showing = 25,
+    SingleLine = 26,
+    Visible = 30,
+    Required = 33,
+    InvalidEntry = 36,
+    ReadOnly = 43,
+}
+
+/// Accessible object as published over AT-SPI2
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtSpiAccessible {
+    /// Object path
+    pub path: AtSpiObjectPath,
+    /// Accessible name
+    pub name: String,
+    /// Accessible description
+    pub description: String,
+    /// `AtspiRole` number
+    pub role: u32,
+    /// Role name, as `GetRoleName` returns it
+    pub role_name: String,
+    /// State set, as the two words `GetState` returns
+    pub states: [u32; 2],
+    /// Relation types with their targets
+    pub relations: Vec<(AtSpiRelation, Vec<AtSpiObjectPath>)>,
+    /// Parent object
+    pub parent: AtSpiObjectPath,
+    /// Child objects
+    pub children: Vec<AtSpiObjectPath>,
+    /// Index among the parent's children, -1 if unknown
+    pub index_in_parent: i32,
+}
+
+impl AtSpiAccessible {
+    /// Whether the state set contains a state
+    pub fn has_state(&self, state: AtSpiState) -> bool {
+        let state = state as u32;
+        self.states[(state / 32) as usize] & (1 << (state % 32)) != 0
+    }
+
+    fn add_state(&mut self, state: AtSpiState) {
+        let state = state as u32;
+        self.states[(state / 32) as usize] |= 1 << (state % 32);
+    }
+}
+
+/// Event emitted on `org.a11y.atspi.Event.Object`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtSpiEvent {
+    /// Signal name, such as `StateChanged`
+    pub member: String,
+    /// Event detail, such as the state name `focused`
+    pub detail: String,
+    /// First detail number, 1 when a state is set and 0 when it is cleared
+    pub detail1: i32,
+    /// Second detail number
+    pub detail2: i32,
+}
+
+/// Connection to the AT-SPI2 accessibility bus
+///
+/// Implementations block until the bus replies.
+pub trait AtSpiBus: Send + Sync {
+    /// Connect to the accessibility bus, returning the application's unique bus name
+    fn connect(&self) -> Result<String>;
+
+    /// Embed the application in the registry with `org.a11y.atspi.Socket.Embed`, returning the desktop
+    fn embed(&self, application: &AtSpiObjectRef) -> Result<AtSpiObjectRef>;
+
+    /// Serve an object implementing `org.a11y.atspi.Accessible`, replacing any at its path
+    fn export_accessible(&self, accessible: AtSpiAccessible) -> Result<()>;
+
+    /// Emit an event on the `org.a11y.atspi.Event.Object` interface
+    fn emit_object_event(&self, path: &AtSpiObjectPath, event: &AtSpiEvent) -> Result<()>;
+}
+
+/// Bus recording the calls made on it, for tests
+#[derive(Default)]
+pub struct MockAtSpiBus {
+    /// Interface methods called, as `interface.Method`
+    calls: Mutex<Vec<String>>,
+    /// Exported objects by path
+    accessibles: Mutex<HashMap<AtSpiObjectPath, AtSpiAccessible>>,
+    /// Events emitted
+    events: Mutex<Vec<(AtSpiObjectPath, AtSpiEvent)>>,
+}
+
+impl MockAtSpiBus {
+    /// Create a mock bus
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interface methods called, in order
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().clone()
+    }
+
+    /// Object exported at a path
+    pub fn accessible(&self, path: &AtSpiObjectPath) -> Option<AtSpiAccessible> {
+        self.accessibles.lock().get(path).cloned()
+    }
+
+    /// Events emitted, in order
+    pub fn events(&self) -> Vec<(AtSpiObjectPath, AtSpiEvent)> {
+        self.events.lock().clone()
+    }
+}
+
+impl AtSpiBus for MockAtSpiBus {
+    fn connect(&self) -> Result<String> {
+        self.calls.lock().push("org.a11y.Bus.GetAddress".to_string());
+        Ok(":1.42".to_string())
+    }
+
+    fn embed(&self, _application: &AtSpiObjectRef) -> Result<AtSpiObjectRef> {
+        self.calls.lock().push("org.a11y.atspi.Socket.Embed".to_string());
+        Ok(AtSpiObjectRef {
+            bus_name: REGISTRY_BUS_NAME.to_string(),
+            path: AtSpiObjectPath::root(),
+        })
+    }
+
+    fn export_accessible(&self, accessible: AtSpiAccessible) -> Result<()> {
+        self.calls.lock().push(format!("export {}", ACCESSIBLE_INTERFACE));
+        self.accessibles.lock().insert(accessible.path.clone(), accessible);
+        Ok(())
+    }
+
+    fn emit_object_event(&self, path: &AtSpiObjectPath, event: &AtSpiEvent) -> Result<()> {
+        self.calls.lock().push(format!("{}.{}", OBJECT_EVENT_INTERFACE, event.member));
+        self.events.lock().push((path.clone(), event.clone()));
+        Ok(())
+    }
+}
+
+/// Publishes the accessibility tree over AT-SPI2
+pub struct AtSpiProvider {
+    /// Accessibility bus
+    bus: Arc<dyn AtSpiBus>,
+    /// Unique name of the application on the bus, once registered
+    bus_name: RwLock<Option<String>>,
+    /// Exposed objects by path
+    exposed: RwLock<HashMap<AtSpiObjectPath, AtSpiAccessible>>,
+    /// Object with keyboard focus
+    focused: RwLock<Option<AtSpiObjectPath>>,
+}
+
+impl AtSpiProvider {
+    /// Create a provider publishing on a bus
+    pub fn new(bus: Arc<dyn AtSpiBus>) -> Self {
+        Self {
+            bus,
+            bus_name: RwLock::new(None),
+            exposed: RwLock::new(HashMap::new()),
+            focused: RwLock::new(None),
+        }
+    }
+
+    /// Create a provider publishing on the session's accessibility bus
+    #[cfg(target_os = "linux")]
+    pub fn session() -> Self {
+        Self::new(Arc::new(linux::ZbusAtSpiBus::new()))
+    }
+
+    /// Register the application as an AT-SPI2 accessible
+    ///
+    /// Connects to the accessibility bus of the D-Bus session, publishes the
+    /// application's root accessible and embeds it in the desktop.
+    pub fn register(&self) -> Result<()> {
+        let bus_name = self.bus.connect()?;
+
+        let mut application = AtSpiAccessible {
+            path: AtSpiObjectPath::root(),
+            name: APPLICATION_NAME.to_string(),
+            description: String::new(),
+            role: 75,
+            role_name: "application".to_string(),
+            states: [0; 2],
+            relations: Vec::new(),
+            parent: AtSpiObjectPath::root(),
+            children: Vec::new(),
+            index_in_parent: -1,
+        };
+        application.add_state(AtSpiState::Enabled);
+        application.add_state(AtSpiState::Sensitive);
+        application.add_state(AtSpiState::Visible);
+        application.add_state(AtSpiState::Showing);
+        self.bus.export_accessible(application.clone())?;
+        self.exposed.write().insert(application.path.clone(), application);
+
+        let desktop = self.bus.embed(&AtSpiObjectRef {
+            bus_name: bus_name.clone(),
+            path: AtSpiObjectPath::root(),
+        })?;
+        log::info!("Registered with AT-SPI2 as {}, embedded in {}", bus_name, desktop.bus_name);
+
+        *self.bus_name.write() = Some(bus_name);
+        Ok(())
+    }
+
+    /// Unique name of the application on the accessibility bus, once registered
+    pub fn bus_name(&self) -> Option<String> {
+        self.bus_name.read().clone()
+    }
+
+    /// Publish a node as an object implementing `org.a11y.atspi.Accessible`
+    ///
+    /// Exposing a node again updates its object.
+    pub fn expose_node(&self, node: &AccessibilityNode) -> Result<AtSpiObjectPath> {
+        if self.bus_name.read().is_none() {
+            return Err(Error::InvalidState("AT-SPI2 provider is not registered".to_string()));
+        }
+
+        let path = AtSpiObjectPath::for_node(&node.id);
+        let parent = node.parent.as_deref().map_or_else(AtSpiObjectPath::root, AtSpiObjectPath::for_node);
+        let index_in_parent = if parent == AtSpiObjectPath::root() {
+            // Top level nodes are children of the application
+            let mut exposed = self.exposed.write();
+            let application = exposed.get_mut(&parent).expect("the application is exposed on registration");
+            if !application.children.contains(&path) {
+                application.children.push(path.clone());
+                self.bus.export_accessible(application.clone())?;
+            }
+            application.children.iter().position(|child| *child == path)
+        } else {
+            self.exposed.read().get(&parent).and_then(|parent| parent.children.iter().position(|child| *child == path))
+        };
+
+        let targets = |ids: &[String]| ids.iter().map(|id| AtSpiObjectPath::for_node(id)).collect::<Vec<_>>();
+        let relations = [
+            (AtSpiRelation::LabelledBy, targets(&node.labeled_by)),
+            (AtSpiRelation::ControllerFor, targets(&node.controls)),
+            (AtSpiRelation::FlowsTo, targets(&node.flow_to)),
+            (AtSpiRelation::DescribedBy, targets(&node.described_by)),
+            (AtSpiRelation::Details, targets(&node.details)),
+            (AtSpiRelation::ErrorMessage, targets(&node.error_message)),
+        ];
+        let (role, role_name) = atspi_role(node.role);
+
+        let mut accessible = AtSpiAccessible {
+            path: path.clone(),
+            name: node.name.clone().unwrap_or_default(),
+            description: node.description.clone().unwrap_or_default(),
+            role,
+            role_name: role_name.to_string(),
+            states: [0; 2],
+            relations: relations.into_iter().filter(|(_, targets)| !targets.is_empty()).collect(),
+            parent,
+            children: targets(&node.children),
+            index_in_parent: index_in_parent.map_or(-1, |index| index as i32),
+        };
+        for (set, state) in [
+            (node.is_visible, AtSpiState::Visible),
+            (node.is_visible, AtSpiState::Showing),
+            (node.is_enabled, AtSpiState::Enabled),
+            (node.is_enabled, AtSpiState::Sensitive),
+            (node.is_focusable, AtSpiState::Focusable),
+            (node.is_selected, AtSpiState::Selected),
+            (node.is_selected, AtSpiState::Selectable),
+            (node.is_expanded, AtSpiState::Expanded),
+            (node.is_expanded, AtSpiState::Expandable),
+            (node.is_checked, AtSpiState::Checked),
+            (node.is_required, AtSpiState::Required),
+            (node.is_invalid, AtSpiState::InvalidEntry),
+            (node.is_busy, AtSpiState::Busy),
+            (node.is_pressed, AtSpiState::Pressed),
+            (node.is_read_only, AtSpiState::ReadOnly),
+            (node.is_multi_selectable, AtSpiState::Multiselectable),
+            (self.focused.read().as_ref() == Some(&path), AtSpiState::Focused),
+        ] {
+            if set {
+                accessible.add_state(state);
+            }
+        }
+        if node.role == AccessibilityRole::TextBox {
+            accessible.add_state(if node.is_multi_line { AtSpiState::MultiLine } else { AtSpiState::SingleLine });
+        }
+
+        self.bus.export_accessible(accessible.clone())?;
+        self.exposed.write().insert(path.clone(), accessible);
+        Ok(path)
+    }
+
+    /// Tell screen readers that keyboard focus moved to a node
+    ///
+    /// Sends `object:state-changed:focused`, clearing it on the node that had focus.
+    pub fn focus_changed(&self, node_id: &str) -> Result<()> {
+        let path = AtSpiObjectPath::for_node(node_id);
+        if !self.exposed.read().contains_key(&path) {
+            return Err(Error::NodeNotFound(format!("Node {} is not exposed over AT-SPI2", node_id)));
+        }
+
+        let previous = self.focused.write().replace(path.clone());
+        if let Some(previous) = previous.filter(|previous| *previous != path) {
+            self.set_focused_state(&previous, false)?;
+        }
+        self.set_focused_state(&path, true)
+    }
+
+    fn set_focused_state(&self, path: &AtSpiObjectPath, focused: bool) -> Result<()> {
+        let updated = self.exposed.write().get_mut(path).map(|accessible| {
+            let bit = 1 << (AtSpiState::Focused as u32 % 32);
+            if focused {
+                accessible.states[0] |= bit;
+            } else {
+                accessible.states[0] &= !bit;
+            }
+            accessible.clone()
+        });
+        if let Some(accessible) = updated {
+            self.bus.export_accessible(accessible)?;
+        }
+
+        self.bus.emit_object_event(path, &AtSpiEvent {
+            member: "StateChanged".to_string(),
+            detail: "focused".to_string(),
+            detail1: i32::from(focused),
+            detail2: 0,
+        })
+    }
+}
+
+/// `AtspiRole` number and role name of an accessibility role
+fn atspi_role(role: AccessibilityRole) -> (u32, &'static str) {
+    match role {
+        AccessibilityRole::Alert => (2, "alert"),
+        AccessibilityRole::AlertDialog | AccessibilityRole::Dialog => (16, "dialog"),
+        AccessibilityRole::Application => (75, "application"),
+        AccessibilityRole::Article => (109, "article"),
+        AccessibilityRole::Banner | AccessibilityRole::Complementary | AccessibilityRole::ContentInfo
+        | AccessibilityRole::Main | AccessibilityRole::Navigation | AccessibilityRole::Region
+        | AccessibilityRole::Search => (110, "landmark"),
+        AccessibilityRole::Button => (43, "push button"),
+        AccessibilityRole::Cell | AccessibilityRole::GridCell => (56, "table cell"),
+        AccessibilityRole::Checkbox => (7, "check box"),
+        AccessibilityRole::ColumnHeader => (10, "column header"),
+        AccessibilityRole::Combobox | AccessibilityRole::Select => (11, "combo box"),
+        AccessibilityRole::Definition => (108, "definition"),
+        AccessibilityRole::Directory | AccessibilityRole::List => (31, "list"),
+        AccessibilityRole::Document => (95, "document web"),
+        AccessibilityRole::Feed | AccessibilityRole::Generic | AccessibilityRole::Section
+        | AccessibilityRole::SectionHead => (85, "section"),
+        AccessibilityRole::Figure | AccessibilityRole::Group | AccessibilityRole::RadioGroup
+        | AccessibilityRole::RowGroup => (99, "grouping"),
+        AccessibilityRole::Form => (87, "form"),
+        AccessibilityRole::Grid | AccessibilityRole::Table => (55, "table"),
+        AccessibilityRole::Heading => (83, "heading"),
+        AccessibilityRole::Img => (27, "image"),
+        AccessibilityRole::Link => (88, "link"),
+        AccessibilityRole::ListBox => (98, "list box"),
+        AccessibilityRole::ListItem | AccessibilityRole::Option => (32, "list item"),
+        AccessibilityRole::Log => (111, "log"),
+        AccessibilityRole::Marquee => (112, "marquee"),
+        AccessibilityRole::Math => (113, "math"),
+        AccessibilityRole::Menu => (33, "menu"),
+        AccessibilityRole::MenuBar => (34, "menu bar"),
+        AccessibilityRole::MenuItem => (35, "menu item"),
+        AccessibilityRole::MenuItemCheckbox => (8, "check menu item"),
+        AccessibilityRole::MenuItemRadio => (45, "radio menu item"),
+        AccessibilityRole::Meter => (103, "level bar"),
+        AccessibilityRole::None | AccessibilityRole::Presentation => (86, "redundant object"),
+        AccessibilityRole::Note => (97, "comment"),
+        AccessibilityRole::ProgressBar => (42, "progress bar"),
+        AccessibilityRole::Radio => (44, "radio button"),
+        AccessibilityRole::Row => (90, "table row"),
+        AccessibilityRole::RowHeader => (47, "row header"),
+        AccessibilityRole::ScrollBar => (48, "scroll bar"),
+        AccessibilityRole::SearchBox | AccessibilityRole::TextBox => (79, "entry"),
+        AccessibilityRole::Separator => (50, "separator"),
+        AccessibilityRole::Slider => (51, "slider"),
+        AccessibilityRole::SpinButton => (52, "spin button"),
+        AccessibilityRole::Status => (54, "status bar"),
+        AccessibilityRole::Switch => (62, "toggle button"),
+        AccessibilityRole::Tab => (37, "page tab"),
+        AccessibilityRole::TabList => (38, "page tab list"),
+        AccessibilityRole::TabPanel => (39, "panel"),
+        AccessibilityRole::Term => (122, "description term"),
+        AccessibilityRole::Timer => (115, "timer"),
+        AccessibilityRole::ToolBar => (63, "tool bar"),
+        AccessibilityRole::ToolTip => (64, "tool tip"),
+        AccessibilityRole::Tree => (65, "tree"),
+        AccessibilityRole::TreeGrid => (66, "tree table"),
+        AccessibilityRole::TreeItem => (91, "tree item"),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::*;
+    use std::sync::OnceLock;
+    use zbus::blocking::connection::Builder;
+    use zbus::blocking::Connection;
+    use zbus::zvariant::{ObjectPath, OwnedObjectPath, Value};
+
+    /// Reference as AT-SPI2 sends it, a `(so)` pair
+    type ObjectRef = (String, OwnedObjectPath);
+
+    /// Accessibility bus of the D-Bus session
+    pub struct ZbusAtSpiBus {
+        connection: OnceLock<Connection>,
+    }
+
+    impl ZbusAtSpiBus {
+        pub fn new() -> Self {
+            Self { connection: OnceLock::new() }
+        }
+
+        fn connection(&self) -> Result<&Connection> {
+            self.connection.get().ok_or_else(|| Error::InvalidState("Not connected to the accessibility bus".to_string()))
+        }
+    }
+
+    impl AtSpiBus for ZbusAtSpiBus {
+        fn connect(&self) -> Result<String> {
+            // The accessibility bus is a separate bus whose address the session bus knows
+            let session = Connection::session().map_err(dbus_error)?;
+            let reply = session.call_method(Some("org.a11y.Bus"), "/org/a11y/bus", Some("org.a11y.Bus"), "GetAddress", &())
+                .map_err(dbus_error)?;
+            let address: String = reply.body().deserialize().map_err(dbus_error)?;
+
+            let connection = Builder::address(address.as_str()).map_err(dbus_error)?.build().map_err(dbus_error)?;
+            let bus_name = connection.unique_name()
+                .map(|name| name.to_string())
+                .ok_or_else(|| Error::InvalidState("Accessibility bus assigned no name".to_string()))?;
+            self.connection.set(connection).map_err(|_| Error::InvalidState("Already connected to the accessibility bus".to_string()))?;
+            Ok(bus_name)
+        }
+
+        fn embed(&self, application: &AtSpiObjectRef) -> Result<AtSpiObjectRef> {
+            let path = ObjectPath::try_from(application.path.as_str()).map_err(dbus_error)?;
+            let reply = self.connection()?
+                .call_method(Some(REGISTRY_BUS_NAME), ROOT_PATH, Some("org.a11y.atspi.Socket"), "Embed", &((application.bus_name.as_str(), path),))
+                .map_err(dbus_error)?;
+            let (bus_name, path): ObjectRef = reply.body().deserialize().map_err(dbus_error)?;
+            Ok(AtSpiObjectRef { bus_name, path: AtSpiObjectPath(path.to_string()) })
+        }
+
+        fn export_accessible(&self, accessible: AtSpiAccessible) -> Result<()> {
+            let connection = self.connection()?;
+            let bus_name = connection.unique_name().map(|name| name.to_string()).unwrap_or_default();
+            let path = accessible.path.0.clone();
+            let object_server = connection.object_server();
+            let _ = object_server.remove::<AccessibleInterface, _>(path.as_str());
+            object_server.at(path.as_str(), AccessibleInterface { accessible, bus_name }).map_err(dbus_error)?;
+            Ok(())
+        }
+
+        fn emit_object_event(&self, path: &AtSpiObjectPath, event: &AtSpiEvent) -> Result<()> {
+            let properties: HashMap<&str, Value<'_>> = HashMap::new();
+            self.connection()?
+                .emit_signal(
+                    None::<&str>,
+                    path.as_str(),
+                    OBJECT_EVENT_INTERFACE,
+                    event.member.as_str(),
+                    &(event.detail.as_str(), event.detail1, event.detail2, Value::from(0i32), properties),
+                )
+                .map_err(dbus_error)
+        }
+    }
+
+    /// Object serving `org.a11y.atspi.Accessible` for an exposed accessible
+    struct AccessibleInterface {
+        accessible: AtSpiAccessible,
+        bus_name: String,
+    }
+
+    impl AccessibleInterface {
+        fn reference(&self, path: &AtSpiObjectPath) -> ObjectRef {
+            let path = OwnedObjectPath::try_from(path.as_str()).unwrap_or_else(|_| OwnedObjectPath::try_from(ROOT_PATH).unwrap());
+            (self.bus_name.clone(), path)
+        }
+    }
+
+    #[zbus::interface(name = "org.a11y.atspi.Accessible")]
+    impl AccessibleInterface {
+        #[zbus(property)]
+        fn name(&self) -> String {
+            self.accessible.name.clone()
+        }
+
+        #[zbus(property)]
+        fn description(&self) -> String {
+            self.accessible.description.clone()
+        }
+
+        #[zbus(property)]
+        fn parent(&self) -> ObjectRef {
+            self.reference(&self.accessible.parent)
+        }
+
+        #[zbus(property)]
+        fn child_count(&self) -> i32 {
+            self.accessible.children.len() as i32
+        }
+
+        #[zbus(property)]
+        fn locale(&self) -> String {
+            String::new()
+        }
+
+        #[zbus(property)]
+        fn accessible_id(&self) -> String {
+            self.accessible.path.0.trim_start_matches(NODE_PATH_PREFIX).to_string()
+        }
+
+        fn get_child_at_index(&self, index: i32) -> ObjectRef {
+            usize::try_from(index).ok()
+                .and_then(|index| self.accessible.children.get(index))
+                .map_or_else(|| self.reference(&AtSpiObjectPath::root()), |child| self.reference(child))
+        }
+
+        fn get_children(&self) -> Vec<ObjectRef> {
+            self.accessible.children.iter().map(|child| self.reference(child)).collect()
+        }
+
+        fn get_index_in_parent(&self) -> i32 {
+            self.accessible.index_in_parent
+        }
+
+        fn get_relation_set(&self) -> Vec<(u32, Vec<ObjectRef>)> {
+            self.accessible.relations.iter()
+                .map(|(relation, targets)| (*relation as u32, targets.iter().map(|target| self.reference(target)).collect()))
+                .collect()
+        }
+
+        fn get_role(&self) -> u32 {
+            self.accessible.role
+        }
+
+        fn get_role_name(&self) -> String {
+            self.accessible.role_name.clone()
+        }
+
+        fn get_localized_role_name(&self) -> String {
+            self.accessible.role_name.clone()
+        }
+
+        fn get_state(&self) -> Vec<u32> {
+            self.accessible.states.to_vec()
+        }
+
+        fn get_attributes(&self) -> HashMap<String, String> {
+            HashMap::new()
+        }
+
+        fn get_application(&self) -> ObjectRef {
+            self.reference(&AtSpiObjectPath::root())
+        }
+
+        fn get_interfaces(&self) -> Vec<String> {
+            vec![ACCESSIBLE_INTERFACE.to_string()]
+        }
+    }
+
+    fn dbus_error(error: impl std::fmt::Display) -> Error {
+        Error::Platform(format!("AT-SPI2 error: {}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> (Arc<MockAtSpiBus>, AtSpiProvider) {
+        let bus = Arc::new(MockAtSpiBus::new());
+        let provider = AtSpiProvider::new(bus.clone());
+        (bus, provider)
+    }
+
+    #[test]
+    fn test_register_and_expose_node() {
+        let (bus, provider) = provider();
+        let node = AccessibilityNode::new("submit".to_string(), AccessibilityRole::Button);
+        assert!(provider.expose_node(&node).is_err());
+
+        provider.register().unwrap();
+        assert_eq!(provider.bus_name().as_deref(), Some(":1.42"));
+        assert_eq!(bus.calls(), vec![
+            "org.a11y.Bus.GetAddress".to_string(),
+            format!("export {}", ACCESSIBLE_INTERFACE),
+            "org.a11y.atspi.Socket.Embed".to_string(),
+        ]);
+
+        let mut node = node;
+        node.name = Some("Send".to_string());
+        node.description = Some("Sends the form".to_string());
+        node.is_focusable = true;
+        node.is_pressed = true;
+        node.labeled_by = vec!["label-1".to_string()];
+        let path = provider.expose_node(&node).unwrap();
+        assert_eq!(path.as_str(), "/org/a11y/atspi/accessible/submit");
+
+        let accessible = bus.accessible(&path).unwrap();
+        assert_eq!(accessible.name, "Send");
+        assert_eq!(accessible.description, "Sends the form");
+        assert_eq!((accessible.role, accessible.role_name.as_str()), (43, "push button"));
+        assert!(accessible.has_state(AtSpiState::Focusable));
+        assert!(accessible.has_state(AtSpiState::Pressed));
+        assert!(accessible.has_state(AtSpiState::Enabled));
+        assert!(!accessible.has_state(AtSpiState::Checked));
+        assert_eq!(accessible.relations, vec![(AtSpiRelation::LabelledBy, vec![AtSpiObjectPath::for_node("label-1")])]);
+        assert_eq!(accessible.parent, AtSpiObjectPath::root());
+        assert_eq!(accessible.index_in_parent, 0);
+        assert_eq!(bus.accessible(&AtSpiObjectPath::root()).unwrap().children, vec![path]);
+    }
+
+    #[test]
+    fn test_focus_changed_emits_state_changed() {
+        let (bus, provider) = provider();
+        provider.register().unwrap();
+        for id in ["first", "second"] {
+            let mut node = AccessibilityNode::new(id.to_string(), AccessibilityRole::TextBox);
+            node.is_focusable = true;
+            provider.expose_node(&node).unwrap();
+        }
+        assert!(provider.focus_changed("missing").is_err());
+
+        provider.focus_changed("first").unwrap();
+        provider.focus_changed("second").unwrap();
+        let focused = |path: &str, detail1| (AtSpiObjectPath(path.to_string()), AtSpiEvent {
+            member: "StateChanged".to_string(),
+            detail: "focused".to_string(),
+            detail1,
+            detail2: 0,
+        });
+        assert_eq!(bus.events(), vec![
+            focused("/org/a11y/atspi/accessible/first", 1),
+            focused("/org/a11y/atspi/accessible/first", 0),
+            focused("/org/a11y/atspi/accessible/second", 1),
+        ]);
+        assert!(bus.calls().contains(&format!("{}.StateChanged", OBJECT_EVENT_INTERFACE)));
+        assert!(bus.accessible(&AtSpiObjectPath::for_node("second")).unwrap().has_state(AtSpiState::Focused));
+        assert!(!bus.accessible(&AtSpiObjectPath::for_node("first")).unwrap().has_state(AtSpiState::Focused));
+    }
+}
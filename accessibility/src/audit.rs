@@ -0,0 +1,292 @@
+//! Accessibility audit reporting WCAG 2.1 violations in an accessibility tree
+
+use crate::accessibility_tree::{AccessibilityNode, AccessibilityRole, AccessibilityTree};
+use serde::{Serialize, Deserialize};
+
+/// Contrast ratio normal text needs under WCAG 1.4.3
+pub const MIN_CONTRAST_RATIO: f64 = 4.5;
+
+/// Contrast ratio large text needs under WCAG 1.4.3
+pub const MIN_LARGE_TEXT_CONTRAST_RATIO: f64 = 3.0;
+
+/// WCAG success criterion an audit rule checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WcagRule {
+    /// 1.1.1 Non-text Content
+    NonTextContent,
+    /// 1.3.1 Info and Relationships
+    InfoAndRelationships,
+    /// 1.3.6 Identify Purpose
+    IdentifyPurpose,
+    /// 1.4.3 Contrast (Minimum)
+    ContrastMinimum,
+    /// 4.1.2 Name, Role, Value
+    NameRoleValue,
+}
+
+impl WcagRule {
+    /// Number of the success criterion
+    pub fn criterion(&self) -> &'static str {
+        match self {
+            WcagRule::NonTextContent => "1.1.1",
+            WcagRule::InfoAndRelationships => "1.3.1",
+            WcagRule::IdentifyPurpose => "1.3.6",
+            WcagRule::ContrastMinimum => "1.4.3",
+            WcagRule::NameRoleValue => "4.1.2",
+        }
+    }
+}
+
+/// How serious a violation is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Severity {
+    /// The content fails the criterion
+    Error,
+    /// The content likely fails the criterion
+    Warning,
+}
+
+/// WCAG violation found by an audit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditViolation {
+    /// Rule that found the violation
+    pub rule_id: WcagRule,
+    /// Node violating the rule
+    pub element_id: String,
+    /// Severity
+    pub severity: Severity,
+    /// Description of the problem
+    pub message: String,
+    /// Number of the WCAG success criterion
+    pub wcag_criterion: String,
+}
+
+impl AuditViolation {
+    fn new(rule_id: WcagRule, element_id: &str, severity: Severity, message: String) -> Self {
+        Self {
+            rule_id,
+            element_id: element_id.to_string(),
+            severity,
+            message,
+            wcag_criterion: rule_id.criterion().to_string(),
+        }
+    }
+}
+
+/// Checks an accessibility tree against WCAG 2.1
+pub struct AccessibilityAuditor;
+
+impl AccessibilityAuditor {
+    /// Audit the visible nodes of a tree, reporting violations ordered by node
+    pub fn audit(tree: &AccessibilityTree) -> Vec<AuditViolation> {
+        let mut nodes = tree.nodes_snapshot();
+        nodes.retain(|node| node.is_visible);
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut violations = Vec::new();
+        for node in &nodes {
+            Self::check_text_alternative(node, &mut violations);
+            Self::check_form_label(node, &mut violations);
+            Self::check_accessible_name(node, &mut violations);
+            Self::check_contrast(node, &mut violations);
+        }
+        if !nodes.is_empty() && !nodes.iter().any(|node| node.role == AccessibilityRole::Main) {
+            let element_id = tree.root_id()
+                .or_else(|| nodes.iter().find(|node| node.parent.is_none()).map(|node| node.id.clone()))
+                .unwrap_or_else(|| nodes[0].id.clone());
+            violations.push(AuditViolation::new(
+                WcagRule::IdentifyPurpose,
+                &element_id,
+                Severity::Warning,
+                "Page has no main landmark region".to_string(),
+            ));
+        }
+        violations
+    }
+
+    fn check_text_alternative(node: &AccessibilityNode, violations: &mut Vec<AuditViolation>) {
+        if node.role == AccessibilityRole::Img && !has_accessible_name(node) {
+            violations.push(AuditViolation::new(
+                WcagRule::NonTextContent,
+                &node.id,
+                Severity::Error,
+                "Image has no alt text".to_string(),
+            ));
+        }
+    }
+
+    fn check_form_label(node: &AccessibilityNode, violations: &mut Vec<AuditViolation>) {
+        if is_form_control(node.role) && !has_accessible_name(node) {
+            violations.push(AuditViolation::new(
+                WcagRule::InfoAndRelationships,
+                &node.id,
+                Severity::Error,
+                format!("Form control with role {:?} has no label", node.role),
+            ));
+        }
+    }
+
+    fn check_accessible_name(node: &AccessibilityNode, violations: &mut Vec<AuditViolation>) {
+        // Unlabelled form controls are already reported under 1.3.1
+        if is_interactive(node.role) && !is_form_control(node.role) && !has_accessible_name(node) {
+            violations.push(AuditViolation::new(
+                WcagRule::NameRoleValue,
+                &node.id,
+                Severity::Error,
+                format!("Interactive element with role {:?} has no accessible name", node.role),
+            ));
+        }
+    }
+
+    fn check_contrast(node: &AccessibilityNode, violations: &mut Vec<AuditViolation>) {
+        let (Some(foreground), Some(background)) = (
+            node.properties.get("color").and_then(|color| parse_color(color)),
+            node.properties.get("background-color").and_then(|color| parse_color(color)),
+        ) else {
+            return;
+        };
+
+        let ratio = contrast_ratio(foreground, background);
+        let required = if is_large_text(node) { MIN_LARGE_TEXT_CONTRAST_RATIO } else { MIN_CONTRAST_RATIO };
+        if ratio < required {
+            violations.push(AuditViolation::new(
+                WcagRule::ContrastMinimum,
+                &node.id,
+                Severity::Error,
+                format!("Text contrast ratio {:.2}:1 is below {}:1", ratio, required),
+            ));
+        }
+    }
+}
+
+/// Relative luminance of an sRGB color, as WCAG 2.1 defines it
+pub fn relative_luminance((r, g, b): (u8, u8, u8)) -> f64 {
+    let channel = |value: u8| {
+        let value = value as f64 / 255.0;
+        if value <= 0.03928 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b)
+}
+
+/// Contrast ratio between two sRGB colors, from 1 to 21
+pub fn contrast_ratio(foreground: (u8, u8, u8), background: (u8, u8, u8)) -> f64 {
+    let (foreground, background) = (relative_luminance(foreground), relative_luminance(background));
+    (foreground.max(background) + 0.05) / (foreground.min(background) + 0.05)
+}
+
+/// Parse a CSS `#rgb`, `#rrggbb`, `rgb()` or `rgba()` color, ignoring alpha
+fn parse_color(value: &str) -> Option<(u8, u8, u8)> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits: Vec<u8> = hex.chars()
+            .map(|c| c.to_digit(16).map(|digit| digit as u8))
+            .collect::<Option<_>>()?;
+        return match digits.as_slice() {
+            [r, g, b] => Some((r * 17, g * 17, b * 17)),
+            [r1, r2, g1, g2, b1, b2] => Some((r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2)),
+            _ => None,
+        };
+    }
+
+    let arguments = value.strip_prefix("rgba(").or_else(|| value.strip_prefix("rgb("))?.strip_suffix(')')?;
+    let channels: Vec<u8> = arguments.split(',')
+        .take(3)
+        .map(|channel| channel.trim().parse::<f64>().ok().map(|channel| channel.clamp(0.0, 255.0).round() as u8))
+        .collect::<Option<_>>()?;
+    match channels.as_slice() {
+        [r, g, b] => Some((*r, *g, *b)),
+        _ => None,
+    }
+}
+
+/// Whether text is large under WCAG: 18pt, or 14pt bold
+fn is_large_text(node: &AccessibilityNode) -> bool {
+    let font_size = node.properties.get("font-size")
+        .and_then(|size| size.trim().trim_end_matches("px").parse::<f64>().ok())
+        .unwrap_or(16.0);
+    let bold = node.properties.get("font-weight")
+        .is_some_and(|weight| weight == "bold" || weight.parse::<u32>().is_ok_and(|weight| weight >= 700));
+    font_size >= 24.0 || (bold && font_size >= 18.66)
+}
+
+fn has_accessible_name(node: &AccessibilityNode) -> bool {
+    node.name.as_deref().is_some_and(|name| !name.trim().is_empty()) || !node.labeled_by.is_empty()
+}
+
+fn is_form_control(role: AccessibilityRole) -> bool {
+    matches!(
+        role,
+        AccessibilityRole::TextBox
+            | AccessibilityRole::SearchBox
+            | AccessibilityRole::Checkbox
+            | AccessibilityRole::Radio
+            | AccessibilityRole::Combobox
+            | AccessibilityRole::Select
+            | AccessibilityRole::ListBox
+            | AccessibilityRole::Slider
+            | AccessibilityRole::SpinButton
+            | AccessibilityRole::Switch
+    )
+}
+
+fn is_interactive(role: AccessibilityRole) -> bool {
+    is_form_control(role)
+        || matches!(
+            role,
+            AccessibilityRole::Button
+                | AccessibilityRole::Link
+                | AccessibilityRole::MenuItem
+                | AccessibilityRole::MenuItemCheckbox
+                | AccessibilityRole::MenuItemRadio
+                | AccessibilityRole::Tab
+                | AccessibilityRole::Option
+                | AccessibilityRole::TreeItem
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nameless_button_violates_name_role_value() {
+        let tree = AccessibilityTree::new();
+        let mut main = AccessibilityNode::new("main".to_string(), AccessibilityRole::Main);
+        main.children = vec!["icon-button".to_string(), "save".to_string()];
+        let mut save = AccessibilityNode::new("save".to_string(), AccessibilityRole::Button);
+        save.name = Some("Save".to_string());
+        for node in [main, save, AccessibilityNode::new("icon-button".to_string(), AccessibilityRole::Button)] {
+            tokio_test::block_on(tree.add_node(node)).unwrap();
+        }
+
+        let violations = AccessibilityAuditor::audit(&tree);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].rule_id, WcagRule::NameRoleValue);
+        assert_eq!(violations[0].element_id, "icon-button");
+        assert_eq!(violations[0].severity, Severity::Error);
+        assert_eq!(violations[0].wcag_criterion, "4.1.2");
+    }
+
+    #[test]
+    fn test_contrast_ratio() {
+        assert!((contrast_ratio((0, 0, 0), (255, 255, 255)) - 21.0).abs() < 1e-9);
+        assert!((contrast_ratio((119, 119, 119), (255, 255, 255)) - 4.48).abs() < 0.01);
+        assert_eq!(parse_color("#777"), Some((119, 119, 119)));
+        assert_eq!(parse_color("rgba(255, 0, 10, 0.5)"), Some((255, 0, 10)));
+
+        let tree = AccessibilityTree::new();
+        let mut text = AccessibilityNode::new("main".to_string(), AccessibilityRole::Main);
+        text.properties.insert("color".to_string(), "#777777".to_string());
+        text.properties.insert("background-color".to_string(), "#fff".to_string());
+        tokio_test::block_on(tree.add_node(text.clone())).unwrap();
+        assert_eq!(AccessibilityAuditor::audit(&tree)[0].rule_id, WcagRule::ContrastMinimum);
+
+        text.properties.insert("font-size".to_string(), "24px".to_string());
+        tokio_test::block_on(tree.add_node(text)).unwrap();
+        assert!(AccessibilityAuditor::audit(&tree).is_empty());
+    }
+}
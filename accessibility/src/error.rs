@@ -71,6 +71,10 @@ pub enum Error {
     #[error("Invalid event: {0}")]
     InvalidEvent(String),
     
+    /// Platform accessibility API error
+    #[error("Platform error: {0}")]
+    Platform(String),
+    
     /// Serialization error
     #[error("Serialization error: {0}")]
     Serialization(String),
@@ -181,6 +185,11 @@ impl Error {
         Error::InvalidEvent(message)
     }
     
+    /// Create a platform accessibility API error
+    pub fn platform(message: String) -> Self {
+        Error::Platform(message)
+    }
+    
     /// Create a serialization error
     pub fn serialization(message: String) -> Self {
         Error::Serialization(message)
@@ -235,6 +244,7 @@ impl Error {
             Error::InvalidGesture(_) => "INVALID_GESTURE",
             Error::InvalidKeyBinding(_) => "INVALID_KEY_BINDING",
             Error::InvalidEvent(_) => "INVALID_EVENT",
+            Error::Platform(_) => "PLATFORM_ERROR",
             Error::Serialization(_) => "SERIALIZATION_ERROR",
             Error::Deserialization(_) => "DESERIALIZATION_ERROR",
             Error::Io(_) => "IO_ERROR",
@@ -244,59 +254,31 @@ impl Error {
     }
     
     /// Get error message
-    pub fn message(&self) -> &str {
-        match self {
-            Error::AccessibilityTree(msg) => msg,
-            Error::InputHandler(msg) => msg,
-            Error::Keyboard(msg) => msg,
-            Error::Mouse(msg) => msg,
-            Error::Touch(msg) => msg,
-            Error::Gesture(msg) => msg,
-            Error::Navigation(msg) => msg,
-            Error::Focus(msg) => msg,
-            Error::Aria(msg) => msg,
-            Error::Event(msg) => msg,
-            Error::NodeNotFound(msg) => msg,
-            Error::InvalidRole(msg) => msg,
-            Error::InvalidState(msg) => msg,
-            Error::InvalidInput(msg) => msg,
-            Error::InvalidGesture(msg) => msg,
-            Error::InvalidKeyBinding(msg) => msg,
-            Error::InvalidEvent(msg) => msg,
-            Error::Serialization(msg) => msg,
-            Error::Deserialization(msg) => msg,
-            Error::Io(err) => err.to_string().as_str(),
-            Error::Json(err) => err.to_string().as_str(),
-            Error::Uuid(err) => err.to_string().as_str(),
-        }
-    }
-}
-
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    pub fn message(&self) -> String {
         match self {
-            Error::AccessibilityTree(msg) => write!(f, "Accessibility tree error: {}", msg),
-            Error::InputHandler(msg) => write!(f, "Input handler error: {}", msg),
-            Error::Keyboard(msg) => write!(f, "Keyboard error: {}", msg),
-            Error::Mouse(msg) => write!(f, "Mouse error: {}", msg),
-            Error::Touch(msg) => write!(f, "Touch error: {}", msg),
-            Error::Gesture(msg) => write!(f, "Gesture error: {}", msg),
-            Error::Navigation(msg) => write!(f, "Navigation error: {}", msg),
-            Error::Focus(msg) => write!(f, "Focus error: {}", msg),
-            Error::Aria(msg) => write!(f, "ARIA error: {}", msg),
-            Error::Event(msg) => write!(f, "Event error: {}", msg),
-            Error::NodeNotFound(msg) => write!(f, "Node not found: {}", msg),
-            Error::InvalidRole(msg) => write!(f, "Invalid role: {}", msg),
-            Error::InvalidState(msg) => write!(f, "Invalid state: {}", msg),
-            Error::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
-            Error::InvalidGesture(msg) => write!(f, "Invalid gesture: {}", msg),
-            Error::InvalidKeyBinding(msg) => write!(f, "Invalid key binding: {}", msg),
-            Error::InvalidEvent(msg) => write!(f, "Invalid event: {}", msg),
-            Error::Serialization(msg) => write!(f, "Serialization error: {}", msg),
-            Error::Deserialization(msg) => write!(f, "Deserialization error: {}", msg),
-            Error::Io(err) => write!(f, "IO error: {}", err),
-            Error::Json(err) => write!(f, "JSON error: {}", err),
-            Error::Uuid(err) => write!(f, "UUID error: {}", err),
+            Error::AccessibilityTree(msg) => msg.clone(),
+            Error::InputHandler(msg) => msg.clone(),
+            Error::Keyboard(msg) => msg.clone(),
+            Error::Mouse(msg) => msg.clone(),
+            Error::Touch(msg) => msg.clone(),
+            Error::Gesture(msg) => msg.clone(),
+            Error::Navigation(msg) => msg.clone(),
+            Error::Focus(msg) => msg.clone(),
+            Error::Aria(msg) => msg.clone(),
+            Error::Event(msg) => msg.clone(),
+            Error::NodeNotFound(msg) => msg.clone(),
+            Error::InvalidRole(msg) => msg.clone(),
+            Error::InvalidState(msg) => msg.clone(),
+            Error::InvalidInput(msg) => msg.clone(),
+            Error::InvalidGesture(msg) => msg.clone(),
+            Error::InvalidKeyBinding(msg) => msg.clone(),
+            Error::InvalidEvent(msg) => msg.clone(),
+            Error::Platform(msg) => msg.clone(),
+            Error::Serialization(msg) => msg.clone(),
+            Error::Deserialization(msg) => msg.clone(),
+            Error::Io(err) => err.to_string(),
+            Error::Json(err) => err.to_string(),
+            Error::Uuid(err) => err.to_string(),
         }
     }
 }
@@ -31,6 +31,8 @@ pub struct KeyboardHandler {
     key_bindings: HashMap<KeyBinding, KeyAction>,
     /// Keyboard shortcuts
     keyboard_shortcuts: HashMap<String, KeyboardShortcut>,
+    /// Receives conflicts found when shortcuts are registered
+    conflict_reporter: Option<Arc<dyn ShortcutConflictReporter>>,
     /// Input method
     input_method: InputMethod,
     /// Auto-repeat settings
@@ -111,6 +113,38 @@ pub struct KeyAction {
     pub handler: String,
     /// Is enabled
     pub is_enabled: bool,
+    /// Who registered the binding, deciding which action runs when bindings collide
+    pub priority: BindingPriority,
+}
+
+/// Owner of a key binding, from highest to lowest priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum BindingPriority {
+    /// Browser default
+    Browser,
+    /// Browser extension
+    Extension,
+    /// Page script
+    Page,
+}
+
+/// Existing key binding that collides with a new one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictingBinding {
+    /// Existing binding
+    pub binding: KeyBinding,
+    /// Context the existing binding was registered in
+    pub context: String,
+    /// Name of the existing action
+    pub action_name: String,
+    /// Priority of the existing action
+    pub priority: BindingPriority,
+}
+
+/// Receives keyboard shortcut conflicts, such as the DevTools console
+pub trait ShortcutConflictReporter: Send + Sync {
+    /// Report the bindings a newly registered shortcut collides with
+    fn report_conflicts(&self, shortcut: &KeyboardShortcut, conflicts: &[ConflictingBinding]);
 }
 
 /// Keyboard Shortcut
@@ -213,7 +247,7 @@ pub struct MouseWheel {
     /// Delta X
     pub delta_x: f64,
     /// Delta Y
-    pub delta_z: f64,
+    pub delta_y: f64,
     /// Delta Z
     pub delta_z: f64,
     /// Delta mode
@@ -760,7 +794,7 @@ impl InputHandler {
     /// Handle keyboard event
     pub async fn handle_keyboard_event(&self, event_data: KeyboardEventData) -> Result<()> {
         let mut keyboard_handler = self.keyboard_handler.write();
-        keyboard_handler.handle_event(event_data)?;
+        keyboard_handler.handle_event(event_data.clone())?;
         
         // Add to event queue
         let mut event_queue = self.event_queue.write();
@@ -772,7 +806,7 @@ impl InputHandler {
     /// Handle mouse event
     pub async fn handle_mouse_event(&self, event_data: MouseEventData) -> Result<()> {
         let mut mouse_handler = self.mouse_handler.write();
-        mouse_handler.handle_event(event_data)?;
+        mouse_handler.handle_event(event_data.clone())?;
         
         // Add to event queue
         let mut event_queue = self.event_queue.write();
@@ -784,7 +818,7 @@ impl InputHandler {
     /// Handle touch event
     pub async fn handle_touch_event(&self, event_data: TouchEventData) -> Result<()> {
         let mut touch_handler = self.touch_handler.write();
-        touch_handler.handle_event(event_data)?;
+        touch_handler.handle_event(event_data.clone())?;
         
         // Add to event queue
         let mut event_queue = self.event_queue.write();
@@ -796,7 +830,7 @@ impl InputHandler {
     /// Handle gesture event
     pub async fn handle_gesture_event(&self, event_data: GestureEventData) -> Result<()> {
         let mut gesture_handler = self.gesture_handler.write();
-        gesture_handler.handle_event(event_data)?;
+        gesture_handler.handle_event(event_data.clone())?;
         
         // Add to event queue
         let mut event_queue = self.event_queue.write();
@@ -849,6 +883,7 @@ impl KeyboardHandler {
             modifier_states: HashMap::new(),
             key_bindings: HashMap::new(),
             keyboard_shortcuts: HashMap::new(),
+            conflict_reporter: None,
             input_method: InputMethod::Direct,
             auto_repeat: AutoRepeatSettings {
                 initial_delay: 500,
@@ -881,20 +916,43 @@ impl KeyboardHandler {
 
     /// Check key bindings
     fn check_key_bindings(&self, event_data: &KeyboardEventData) -> Result<()> {
-        let key_binding = KeyBinding {
+        if let Some(action) = self.resolve_key_binding(event_data) {
+            // Execute action
+            log::info!("Executing key action: {}", action.name);
+        }
+        
+        Ok(())
+    }
+
+    /// Enabled action a key event triggers, preferring browser over extension over page bindings
+    pub fn resolve_key_binding(&self, event_data: &KeyboardEventData) -> Option<&KeyAction> {
+        let pressed = KeyBinding {
             key_code: event_data.key_code,
             modifiers: event_data.modifiers.clone(),
-            context: "default".to_string(),
+            context: String::new(),
         };
         
-        if let Some(action) = self.key_bindings.get(&key_binding) {
-            if action.is_enabled {
-                // Execute action
-                log::info!("Executing key action: {}", action.name);
-            }
-        }
-        
-        Ok(())
+        self.key_bindings
+            .iter()
+            .filter(|(binding, action)| action.is_enabled && binding.key_code == pressed.key_code && same_chord(binding, &pressed))
+            .min_by(|(a, a_action), (b, b_action)| a_action.priority.cmp(&b_action.priority).then_with(|| a.context.cmp(&b.context)))
+            .map(|(_, action)| action)
+    }
+
+    /// Existing bindings for the same keys as a new binding, in any context
+    pub fn check_conflicts(&self, new_binding: &KeyBinding) -> Vec<ConflictingBinding> {
+        let mut conflicts: Vec<ConflictingBinding> = self.key_bindings
+            .iter()
+            .filter(|(binding, _)| binding.key_code == new_binding.key_code && same_chord(binding, new_binding))
+            .map(|(binding, action)| ConflictingBinding {
+                binding: binding.clone(),
+                context: binding.context.clone(),
+                action_name: action.name.clone(),
+                priority: action.priority,
+            })
+            .collect();
+        conflicts.sort_by(|a, b| a.priority.cmp(&b.priority).then_with(|| a.context.cmp(&b.context)));
+        conflicts
     }
 
     /// Add key binding
@@ -902,6 +960,30 @@ impl KeyboardHandler {
         self.key_bindings.insert(key_binding, action);
     }
 
+    /// Register a keyboard shortcut, reporting the bindings it conflicts with
+    pub fn add_keyboard_shortcut(&mut self, shortcut: KeyboardShortcut) -> Vec<ConflictingBinding> {
+        let conflicts = self.check_conflicts(&shortcut.key_binding);
+        if !conflicts.is_empty() {
+            if let Some(reporter) = &self.conflict_reporter {
+                reporter.report_conflicts(&shortcut, &conflicts);
+            }
+        }
+        
+        self.key_bindings.insert(shortcut.key_binding.clone(), shortcut.action.clone());
+        self.keyboard_shortcuts.insert(shortcut.name.clone(), shortcut);
+        conflicts
+    }
+
+    /// Set where shortcut conflicts are reported
+    pub fn set_conflict_reporter(&mut self, reporter: Arc<dyn ShortcutConflictReporter>) {
+        self.conflict_reporter = Some(reporter);
+    }
+
+    /// Get the number of key bindings
+    pub fn key_binding_count(&self) -> usize {
+        self.key_bindings.len()
+    }
+
     /// Remove key binding
     pub fn remove_key_binding(&mut self, key_binding: &KeyBinding) {
         self.key_bindings.remove(key_binding);
@@ -918,13 +1000,28 @@ impl KeyboardHandler {
     }
 }
 
+/// Whether two bindings need the same modifiers, ignoring order and lock keys
+fn same_chord(a: &KeyBinding, b: &KeyBinding) -> bool {
+    let chord = |binding: &KeyBinding| {
+        let mut modifiers: Vec<ModifierKey> = binding.modifiers
+            .iter()
+            .copied()
+            .filter(|modifier| !matches!(modifier, ModifierKey::CapsLock | ModifierKey::NumLock | ModifierKey::ScrollLock))
+            .collect();
+        modifiers.sort_by_key(|modifier| *modifier as u8);
+        modifiers.dedup();
+        modifiers
+    };
+    chord(a) == chord(b)
+}
+
 impl MouseHandler {
     /// Create new mouse handler
     pub fn new() -> Self {
         Self {
             position: MousePosition { x: 0.0, y: 0.0, screen_x: 0.0, screen_y: 0.0 },
             buttons: HashMap::new(),
-            wheel: MouseWheel { delta_x: 0.0, delta_z: 0.0, delta_z: 0.0, delta_mode: WheelDeltaMode::Pixel },
+            wheel: MouseWheel { delta_x: 0.0, delta_y: 0.0, delta_z: 0.0, delta_mode: WheelDeltaMode::Pixel },
             sensitivity: MouseSensitivity { x: 1.0, y: 1.0, is_enabled: true },
             acceleration: MouseAcceleration { factor: 1.0, threshold: 0.0, is_enabled: false },
         }
@@ -983,12 +1080,12 @@ impl TouchHandler {
     /// Handle touch event
     pub fn handle_event(&mut self, event_data: TouchEventData) -> Result<()> {
         // Update touch points
-        for touch_point in event_data.touch_points {
-            self.touch_points.insert(touch_point.id, touch_point);
+        for touch_point in &event_data.touch_points {
+            self.touch_points.insert(touch_point.id, touch_point.clone());
         }
         
         // Remove ended touch points
-        for touch_point in event_data.changed_touch_points {
+        for touch_point in &event_data.changed_touch_points {
             if touch_point.state == TouchState::Ended || touch_point.state == TouchState::Cancelled {
                 self.touch_points.remove(&touch_point.id);
             }
@@ -1164,3 +1261,74 @@ impl InputEventQueue {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    #[derive(Default)]
+    struct RecordingReporter {
+        reports: Mutex<Vec<(String, usize)>>,
+    }
+
+    impl ShortcutConflictReporter for RecordingReporter {
+        fn report_conflicts(&self, shortcut: &KeyboardShortcut, conflicts: &[ConflictingBinding]) {
+            self.reports.lock().push((shortcut.name.clone(), conflicts.len()));
+        }
+    }
+
+    fn ctrl_k(context: &str) -> KeyBinding {
+        KeyBinding { key_code: KeyCode::K, modifiers: vec![ModifierKey::Control], context: context.to_string() }
+    }
+
+    fn action(name: &str, priority: BindingPriority) -> KeyAction {
+        KeyAction {
+            name: name.to_string(),
+            description: String::new(),
+            handler: name.to_string(),
+            is_enabled: true,
+            priority,
+        }
+    }
+
+    #[test]
+    fn test_conflicting_ctrl_k_bindings() {
+        let mut keyboard_handler = KeyboardHandler::new();
+        keyboard_handler.add_key_binding(ctrl_k("page"), action("Open command palette", BindingPriority::Page));
+        keyboard_handler.add_key_binding(ctrl_k("browser"), action("Focus search", BindingPriority::Browser));
+        keyboard_handler.add_key_binding(
+            KeyBinding { key_code: KeyCode::K, modifiers: vec![ModifierKey::Control, ModifierKey::Shift], context: "browser".to_string() },
+            action("Search elsewhere", BindingPriority::Browser),
+        );
+
+        let mut extension_binding = ctrl_k("extension");
+        extension_binding.modifiers.push(ModifierKey::NumLock);
+        let conflicts = keyboard_handler.check_conflicts(&extension_binding);
+        let contexts: Vec<&str> = conflicts.iter().map(|conflict| conflict.context.as_str()).collect();
+        assert_eq!(contexts, vec!["browser", "page"]);
+        assert_eq!(conflicts[1].action_name, "Open command palette");
+
+        let pressed = KeyboardEventData {
+            key_code: KeyCode::K,
+            key_char: Some('k'),
+            modifiers: vec![ModifierKey::Control],
+            is_repeat: false,
+            is_system_key: false,
+        };
+        assert_eq!(keyboard_handler.resolve_key_binding(&pressed).unwrap().name, "Focus search");
+
+        let reporter = Arc::new(RecordingReporter::default());
+        keyboard_handler.set_conflict_reporter(reporter.clone());
+        let shortcut = KeyboardShortcut {
+            name: "Save to reading list".to_string(),
+            description: String::new(),
+            key_binding: extension_binding,
+            action: action("Save to reading list", BindingPriority::Extension),
+            is_global: false,
+        };
+        assert_eq!(keyboard_handler.add_keyboard_shortcut(shortcut).len(), 2);
+        assert_eq!(*reporter.reports.lock(), vec![("Save to reading list".to_string(), 2)]);
+        assert_eq!(keyboard_handler.resolve_key_binding(&pressed).unwrap().name, "Focus search");
+    }
+}
@@ -7,6 +7,10 @@
 pub mod error;
 pub mod accessibility_tree;
 pub mod input_handler;
+pub mod atspi;
+pub mod audit;
+pub mod accname;
+pub mod system_preferences;
 
 pub use error::{Error, Result};
 pub use accessibility_tree::{
@@ -15,11 +19,12 @@ pub use accessibility_tree::{
     DropEffect, FocusManager, NavigationManager, NavigationMode, NavigationEvent,
     NavigationEventType, NavigationAction, AriaManager, AriaAttribute,
     AriaAttributeType, AriaState, AriaStateType, AriaProperty, AriaPropertyType,
-    AriaLandmark, AccessibilityStats,
+    AriaLandmark, AccessibilityStats, PendingAnnouncement, ANNOUNCEMENT_DEDUP_WINDOW,
 };
 pub use input_handler::{
     InputHandler, KeyboardHandler, KeyCode, KeyState, ModifierKey, KeyBinding,
-    KeyAction, KeyboardShortcut, InputMethod, AutoRepeatSettings, MouseHandler,
+    KeyAction, BindingPriority, ConflictingBinding, ShortcutConflictReporter,
+    KeyboardShortcut, InputMethod, AutoRepeatSettings, MouseHandler,
     MousePosition, MouseButton, ButtonState, MouseWheel, WheelDeltaMode,
     MouseSensitivity, MouseAcceleration, TouchHandler, TouchPoint, TouchPosition,
     TouchRadius, TouchState, TouchGesture, TouchGestureType, GestureState,
@@ -30,6 +35,16 @@ pub use input_handler::{
     MouseEventData, TouchEventData, InputSource, EventHandler, EventFilter,
     EventFilterType, EventFilterCriteria, QueueSettings, InputState,
 };
+pub use atspi::{
+    AtSpiProvider, AtSpiBus, MockAtSpiBus, AtSpiObjectPath, AtSpiObjectRef,
+    AtSpiAccessible, AtSpiEvent, AtSpiRelation, AtSpiState,
+};
+pub use audit::{AccessibilityAuditor, AuditViolation, WcagRule, Severity};
+pub use accname::AccNameComputer;
+pub use system_preferences::{
+    SystemPreferences, ForcedColors, SystemColor, SystemPreferencesBackend,
+    MockSystemPreferencesBackend,
+};
 
 /// Accessibility Manager that combines accessibility tree and input handling
 pub struct AccessibilityManager {
@@ -39,11 +54,26 @@ pub struct AccessibilityManager {
     input_handler: Arc<RwLock<InputHandler>>,
     /// Accessibility state
     state: AccessibilityManagerState,
+    /// AT-SPI2 provider exposing the tree to screen readers
+    atspi_provider: Option<Arc<AtSpiProvider>>,
+    /// Source of the system high contrast and reduced motion preferences
+    preferences_backend: Arc<dyn SystemPreferencesBackend>,
 }
 
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use parking_lot::RwLock;
 
+/// How often queued live region announcements are relayed to the platform
+pub const ANNOUNCEMENT_RELAY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Platform accessibility API that delivers announcements, such as AT-SPI on Linux or UIA on Windows
+pub trait AnnouncementSink: Send + Sync {
+    /// Deliver a live region announcement to assistive technology
+    fn announce(&self, announcement: &PendingAnnouncement) -> Result<()>;
+}
+
 impl AccessibilityManager {
     /// Create new accessibility manager
     pub fn new() -> Self {
@@ -51,6 +81,8 @@ impl AccessibilityManager {
             accessibility_tree: Arc::new(RwLock::new(AccessibilityTree::new())),
             input_handler: Arc::new(RwLock::new(InputHandler::new())),
             state: AccessibilityManagerState::Enabled,
+            atspi_provider: None,
+            preferences_backend: system_preferences::platform_preferences_backend(),
         }
     }
 
@@ -97,22 +129,94 @@ impl AccessibilityManager {
         self.state = state;
     }
 
+    /// Set the AT-SPI2 provider that focus changes are reported to
+    pub fn set_atspi_provider(&mut self, provider: Arc<AtSpiProvider>) {
+        self.atspi_provider = Some(provider);
+    }
+
+    /// Move focus to a node and tell screen readers, exposing the node first if needed
+    pub async fn on_focus_changed(&self, node_id: &str) -> Result<()> {
+        let accessibility_tree = self.accessibility_tree.read();
+        accessibility_tree.set_focus(node_id).await?;
+        
+        if let Some(provider) = &self.atspi_provider {
+            if let Some(node) = accessibility_tree.get_node(node_id).await? {
+                provider.expose_node(&node)?;
+            }
+            provider.focus_changed(node_id)?;
+        }
+        
+        Ok(())
+    }
+
+    /// Set where system accessibility preferences are read from
+    pub fn set_preferences_backend(&mut self, backend: Arc<dyn SystemPreferencesBackend>) {
+        self.preferences_backend = backend;
+    }
+
+    /// Read the high contrast and reduced motion preferences of the system.
+    ///
+    /// When `high_contrast` is set the renderer should enter forced colors mode
+    /// with `StyleEngineManager::set_forced_colors` and the GPU process should
+    /// render in sRGB with `GpuConfig::set_forced_colors`.
+    pub fn detect_system_preferences(&self) -> SystemPreferences {
+        match self.preferences_backend.read() {
+            Ok(preferences) => preferences,
+            Err(error) => {
+                log::warn!("Failed to read system accessibility preferences: {}", error);
+                SystemPreferences::default()
+            }
+        }
+    }
+
+    /// Audit the accessibility tree for WCAG violations
+    pub fn audit(&self) -> Vec<AuditViolation> {
+        AccessibilityAuditor::audit(&self.accessibility_tree.read())
+    }
+
+    /// Relay queued live region announcements to the platform, returning how many were delivered
+    pub fn relay_announcements(&self, sink: &dyn AnnouncementSink) -> Result<usize> {
+        let announcements = self.accessibility_tree.read().process_announcements();
+        for announcement in &announcements {
+            sink.announce(announcement)?;
+        }
+        Ok(announcements.len())
+    }
+
+    /// Relay announcements to the platform every interval until the returned task is aborted
+    pub fn start_announcement_relay(&self, sink: Arc<dyn AnnouncementSink>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let accessibility_tree = self.accessibility_tree.clone();
+        
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let announcements = accessibility_tree.read().process_announcements();
+                for announcement in &announcements {
+                    if let Err(error) = sink.announce(announcement) {
+                        log::warn!("Failed to relay announcement: {}", error);
+                    }
+                }
+            }
+        })
+    }
+
     /// Handle input event with accessibility support
     pub async fn handle_input_event(&self, event_type: InputEventType, event_data: InputEventData) -> Result<()> {
         // Handle input event
         let input_handler = self.input_handler.read();
-        match event_data {
+        match &event_data {
             InputEventData::Keyboard(keyboard_data) => {
-                input_handler.handle_keyboard_event(keyboard_data).await?;
+                input_handler.handle_keyboard_event(keyboard_data.clone()).await?;
             }
             InputEventData::Mouse(mouse_data) => {
-                input_handler.handle_mouse_event(mouse_data).await?;
+                input_handler.handle_mouse_event(mouse_data.clone()).await?;
             }
             InputEventData::Touch(touch_data) => {
-                input_handler.handle_touch_event(touch_data).await?;
+                input_handler.handle_touch_event(touch_data.clone()).await?;
             }
             InputEventData::Gesture(gesture_data) => {
-                input_handler.handle_gesture_event(gesture_data).await?;
+                input_handler.handle_gesture_event(gesture_data.clone()).await?;
             }
         }
         
@@ -268,7 +372,8 @@ impl AccessibilityManager {
         let input_handler = self.input_handler.read();
         
         // Set up default key bindings for accessibility
-        let mut keyboard_handler = input_handler.keyboard_handler().write();
+        let keyboard = input_handler.keyboard_handler();
+        let mut keyboard_handler = keyboard.write();
         
         // Tab navigation
         let tab_binding = KeyBinding {
@@ -281,6 +386,7 @@ impl AccessibilityManager {
             description: "Navigate to next focusable element".to_string(),
             handler: "navigate_next".to_string(),
             is_enabled: true,
+            priority: BindingPriority::Browser,
         };
         keyboard_handler.add_key_binding(tab_binding, tab_action);
         
@@ -295,6 +401,7 @@ impl AccessibilityManager {
             description: "Navigate to previous focusable element".to_string(),
             handler: "navigate_previous".to_string(),
             is_enabled: true,
+            priority: BindingPriority::Browser,
         };
         keyboard_handler.add_key_binding(shift_tab_binding, shift_tab_action);
         
@@ -402,14 +509,17 @@ pub struct InputStats {
 impl InputHandler {
     /// Get input statistics
     pub async fn get_input_stats(&self) -> Result<InputStats> {
-        let keyboard_handler = self.keyboard_handler().read();
-        let touch_handler = self.touch_handler().read();
-        let event_queue = self.event_queue().read();
+        let keyboard = self.keyboard_handler();
+        let touch = self.touch_handler();
+        let queue = self.event_queue();
+        let keyboard_handler = keyboard.read();
+        let touch_handler = touch.read();
+        let event_queue = queue.read();
         
         let mut stats = InputStats::default();
         
         // Count key bindings
-        stats.active_key_bindings = keyboard_handler.key_bindings.len();
+        stats.active_key_bindings = keyboard_handler.key_binding_count();
         
         // Count touch points
         stats.active_touch_points = touch_handler.get_touch_points().len();
@@ -505,4 +615,18 @@ mod tests {
         assert_eq!(stats.accessibility.total_nodes, 0);
         assert_eq!(stats.input.total_events, 0);
     }
+
+    #[tokio::test]
+    async fn test_detect_high_contrast() {
+        let mut accessibility_manager = AccessibilityManager::new();
+        accessibility_manager.set_preferences_backend(Arc::new(MockSystemPreferencesBackend::high_contrast()));
+        
+        let preferences = accessibility_manager.detect_system_preferences();
+        assert!(preferences.high_contrast);
+        assert!(!preferences.reduced_motion);
+        assert_eq!(preferences.forced_colors.and_then(|colors| colors.resolve("ButtonFace")), Some((0, 0, 0)));
+        
+        accessibility_manager.set_preferences_backend(Arc::new(MockSystemPreferencesBackend::default()));
+        assert_eq!(accessibility_manager.detect_system_preferences(), SystemPreferences::default());
+    }
 }
@@ -0,0 +1,291 @@
+//! Accessibility display preferences read from the operating system
+
+use crate::error::{Error, Result};
+use parking_lot::Mutex;
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+
+/// sRGB color as red, green and blue channels
+pub type SystemColor = (u8, u8, u8);
+
+/// Accessibility display preferences of the user
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemPreferences {
+    /// Whether a high contrast theme is on
+    pub high_contrast: bool,
+    /// Whether the user asked for less animation
+    pub reduced_motion: bool,
+    /// System colors pages are forced to use, while high contrast is on
+    pub forced_colors: Option<ForcedColors>,
+}
+
+/// Palette behind the CSS system color keywords in forced colors mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForcedColors {
+    /// `Canvas`
+    pub canvas: SystemColor,
+    /// `CanvasText`
+    pub canvas_text: SystemColor,
+    /// `LinkText`
+    pub link_text: SystemColor,
+    /// `ButtonFace`
+    pub button_face: SystemColor,
+    /// `ButtonText`
+    pub button_text: SystemColor,
+    /// `Field`
+    pub field: SystemColor,
+    /// `FieldText`
+    pub field_text: SystemColor,
+    /// `Highlight`
+    pub highlight: SystemColor,
+    /// `HighlightText`
+    pub highlight_text: SystemColor,
+    /// `GrayText`
+    pub gray_text: SystemColor,
+}
+
+impl Default for ForcedColors {
+    /// The "High Contrast Black" theme
+    fn default() -> Self {
+        Self {
+            canvas: (0, 0, 0),
+            canvas_text: (255, 255, 255),
+            link_text: (255, 255, 0),
+            button_face: (0, 0, 0),
+            button_text: (255, 255, 255),
+            field: (0, 0, 0),
+            field_text: (255, 255, 255),
+            highlight: (26, 235, 255),
+            highlight_text: (0, 0, 0),
+            gray_text: (63, 242, 63),
+        }
+    }
+}
+
+impl ForcedColors {
+    /// Color a CSS system color keyword stands for
+    pub fn resolve(&self, keyword: &str) -> Option<SystemColor> {
+        match keyword.to_ascii_lowercase().as_str() {
+            "canvas" => Some(self.canvas),
+            "canvastext" => Some(self.canvas_text),
+            "linktext" | "visitedtext" | "activetext" => Some(self.link_text),
+            "buttonface" | "buttonborder" => Some(self.button_face),
+            "buttontext" => Some(self.button_text),
+            "field" => Some(self.field),
+            "fieldtext" => Some(self.field_text),
+            "highlight" | "selecteditem" => Some(self.highlight),
+            "highlighttext" | "selecteditemtext" => Some(self.highlight_text),
+            "graytext" => Some(self.gray_text),
+            _ => None,
+        }
+    }
+}
+
+/// Source of the system accessibility preferences
+pub trait SystemPreferencesBackend: Send + Sync {
+    /// Read the current preferences
+    fn read(&self) -> Result<SystemPreferences>;
+}
+
+/// Preferences backend returning fixed preferences, for tests and unsupported platforms
+#[derive(Debug, Default)]
+pub struct MockSystemPreferencesBackend {
+    /// Preferences returned by `read`
+    preferences: Mutex<SystemPreferences>,
+}
+
+impl MockSystemPreferencesBackend {
+    /// Create a backend returning some preferences
+    pub fn new(preferences: SystemPreferences) -> Self {
+        Self { preferences: Mutex::new(preferences) }
+    }
+
+    /// Create a backend reporting a high contrast theme with the default palette
+    pub fn high_contrast() -> Self {
+        Self::new(SystemPreferences {
+            high_contrast: true,
+            reduced_motion: false,
+            forced_colors: Some(ForcedColors::default()),
+        })
+    }
+
+    /// Change the preferences returned by `read`
+    pub fn set_preferences(&self, preferences: SystemPreferences) {
+        *self.preferences.lock() = preferences;
+    }
+}
+
+impl SystemPreferencesBackend for MockSystemPreferencesBackend {
+    fn read(&self) -> Result<SystemPreferences> {
+        Ok(self.preferences.lock().clone())
+    }
+}
+
+/// Preferences backend of the current platform
+pub fn platform_preferences_backend() -> Arc<dyn SystemPreferencesBackend> {
+    #[cfg(target_os = "linux")]
+    {
+        Arc::new(linux::PortalPreferencesBackend)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Arc::new(macos::WorkspacePreferencesBackend)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        Arc::new(windows::SystemParametersBackend)
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        Arc::new(MockSystemPreferencesBackend::default())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::*;
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::OwnedValue;
+
+    /// `contrast` value of the appearance namespace when higher contrast is asked for
+    const CONTRAST_HIGH: u32 = 1;
+
+    /// Preferences from the XDG desktop portal Settings interface
+    pub struct PortalPreferencesBackend;
+
+    impl SystemPreferencesBackend for PortalPreferencesBackend {
+        fn read(&self) -> Result<SystemPreferences> {
+            let connection = Connection::session().map_err(dbus_error)?;
+            let settings = Proxy::new(
+                &connection,
+                "org.freedesktop.portal.Desktop",
+                "/org/freedesktop/portal/desktop",
+                "org.freedesktop.portal.Settings",
+            ).map_err(dbus_error)?;
+
+            let high_contrast = settings.call::<_, _, OwnedValue>("ReadOne", &("org.freedesktop.appearance", "contrast"))
+                .map_err(dbus_error)
+                .and_then(|value| u32::try_from(value).map_err(dbus_error))?
+                == CONTRAST_HIGH;
+            // Desktops without the GNOME namespace keep animations on
+            let reduced_motion = settings.call::<_, _, OwnedValue>("ReadOne", &("org.gnome.desktop.interface", "enable-animations"))
+                .ok()
+                .and_then(|value| bool::try_from(value).ok())
+                .is_some_and(|enabled| !enabled);
+
+            Ok(SystemPreferences {
+                high_contrast,
+                reduced_motion,
+                forced_colors: high_contrast.then(ForcedColors::default),
+            })
+        }
+    }
+
+    fn dbus_error(error: impl std::fmt::Display) -> Error {
+        Error::Platform(format!("Desktop portal error: {}", error))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::*;
+    use objc2_app_kit::NSWorkspace;
+
+    /// Preferences from the accessibility display options of `NSWorkspace`
+    pub struct WorkspacePreferencesBackend;
+
+    impl SystemPreferencesBackend for WorkspacePreferencesBackend {
+        fn read(&self) -> Result<SystemPreferences> {
+            let workspace = NSWorkspace::sharedWorkspace();
+            let high_contrast = workspace.accessibilityDisplayShouldIncreaseContrast();
+
+            Ok(SystemPreferences {
+                high_contrast,
+                reduced_motion: workspace.accessibilityDisplayShouldReduceMotion(),
+                forced_colors: high_contrast.then(ForcedColors::default),
+            })
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use super::*;
+    use windows_sys::Win32::Graphics::Gdi::{
+        GetSysColor, COLOR_BTNFACE, COLOR_BTNTEXT, COLOR_GRAYTEXT, COLOR_HIGHLIGHT,
+        COLOR_HIGHLIGHTTEXT, COLOR_HOTLIGHT, COLOR_WINDOW, COLOR_WINDOWTEXT, SYS_COLOR_INDEX,
+    };
+    use windows_sys::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SPI_GETHIGHCONTRAST,
+    };
+
+    /// Preferences from `SystemParametersInfoW` and the system color table
+    pub struct SystemParametersBackend;
+
+    impl SystemPreferencesBackend for SystemParametersBackend {
+        fn read(&self) -> Result<SystemPreferences> {
+            let mut high_contrast = HIGHCONTRASTW {
+                cbSize: std::mem::size_of::<HIGHCONTRASTW>() as u32,
+                dwFlags: 0,
+                lpszDefaultScheme: std::ptr::null_mut(),
+            };
+            let mut animations = 1i32;
+
+            // SAFETY: both parameters are written into structures of the size
+            // the actions expect
+            unsafe {
+                if SystemParametersInfoW(SPI_GETHIGHCONTRAST, high_contrast.cbSize, &mut high_contrast as *mut _ as *mut _, 0) == 0 {
+                    return Err(Error::platform(format!(
+                        "SystemParametersInfoW(SPI_GETHIGHCONTRAST) failed: {}",
+                        std::io::Error::last_os_error()
+                    )));
+                }
+                if SystemParametersInfoW(SPI_GETCLIENTAREAANIMATION, 0, &mut animations as *mut _ as *mut _, 0) == 0 {
+                    animations = 1;
+                }
+            }
+
+            let high_contrast = high_contrast.dwFlags & HCF_HIGHCONTRASTON != 0;
+            Ok(SystemPreferences {
+                high_contrast,
+                reduced_motion: animations == 0,
+                forced_colors: high_contrast.then(|| ForcedColors {
+                    canvas: system_color(COLOR_WINDOW),
+                    canvas_text: system_color(COLOR_WINDOWTEXT),
+                    link_text: system_color(COLOR_HOTLIGHT),
+                    button_face: system_color(COLOR_BTNFACE),
+                    button_text: system_color(COLOR_BTNTEXT),
+                    field: system_color(COLOR_WINDOW),
+                    field_text: system_color(COLOR_WINDOWTEXT),
+                    highlight: system_color(COLOR_HIGHLIGHT),
+                    highlight_text: system_color(COLOR_HIGHLIGHTTEXT),
+                    gray_text: system_color(COLOR_GRAYTEXT),
+                }),
+            })
+        }
+    }
+
+    /// Color of the active theme, from its `0x00BBGGRR` COLORREF
+    fn system_color(index: SYS_COLOR_INDEX) -> SystemColor {
+        // SAFETY: GetSysColor has no preconditions
+        let color = unsafe { GetSysColor(index) };
+        (color as u8, (color >> 8) as u8, (color >> 16) as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forced_colors_resolve_system_keywords() {
+        let colors = ForcedColors::default();
+        assert_eq!(colors.resolve("ButtonText"), Some(colors.button_text));
+        assert_eq!(colors.resolve("canvas"), Some((0, 0, 0)));
+        assert_eq!(colors.resolve("red"), None);
+    }
+}
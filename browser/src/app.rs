@@ -1,24 +1,41 @@
 //! Main browser application
 
-use common::{error::Result, TabId, WindowInfo, BrowserSettings, BrowserStats};
+use common::{error::Result, ipc::{AudioEvent, BrowserEvent, BrowserRequest, Channel, IpcMessage, PageMargins, PaperSize}, TabId, WindowInfo, BrowserSettings, BrowserStats};
 use tracing::{debug, error, info, warn};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 use winit::{
-    event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
+    event::{ElementState, Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop, EventLoopBuilder},
+    keyboard::{Key, NamedKey},
     window::{Window, WindowBuilder},
 };
 
 use crate::{
-    window_manager::WindowManager,
+    window_manager::{FullscreenWindow, WindowManager},
     tab_manager::TabManager,
     profile_manager::ProfileManager,
     settings_manager::SettingsManager,
     extension_host::ExtensionHost,
+    permissions::PermissionsManager,
+    geolocation::GeolocationManager,
+    clipboard::ClipboardManager,
+    file_system_access::FileSystemManager,
+    download_manager::{DownloadManager, NetworkDownloadTransport},
+    history::HistoryManager,
+    session_manager::SessionManager,
+    pip_manager::{PipManager, PipWindowFactory},
+    navigation::{FaviconManager, NetworkFaviconTransport},
+    printing,
+    autocomplete::{AutocompleteEngine, AutocompleteSuggestion},
+    renderer_host::RendererHost,
 };
 
+/// ID of the window opened at startup
+const INITIAL_WINDOW_ID: u64 = 1;
+
 /// Main browser application
 pub struct BrowserApp {
     /// Window manager
@@ -36,6 +53,45 @@ pub struct BrowserApp {
     /// Extension host
     extension_host: Arc<RwLock<ExtensionHost>>,
     
+    /// Permissions manager
+    permissions_manager: Arc<PermissionsManager>,
+    
+    /// Geolocation manager
+    geolocation_manager: Arc<GeolocationManager>,
+    
+    /// Clipboard manager
+    clipboard_manager: Arc<ClipboardManager>,
+    
+    /// File System Access manager
+    file_system_manager: Arc<FileSystemManager>,
+    
+    /// Answers requests from renderer processes
+    renderer_host: Arc<RendererHost>,
+    
+    /// Window requests of renderers, until the event loop takes them
+    window_requests: Option<mpsc::UnboundedReceiver<BrowserRequest>>,
+    
+    /// Download manager
+    download_manager: Arc<DownloadManager>,
+    
+    /// History manager
+    history_manager: Arc<HistoryManager>,
+    
+    /// Session manager
+    session_manager: Arc<SessionManager>,
+    
+    /// Address bar autocomplete
+    autocomplete_engine: AutocompleteEngine,
+    
+    /// Picture-in-Picture window manager
+    pip_manager: Arc<RwLock<PipManager>>,
+    
+    /// Favicon manager
+    favicon_manager: Arc<FaviconManager>,
+    
+    /// Periodic session save task
+    session_autosave: tokio::task::JoinHandle<()>,
+    
     /// Browser statistics
     stats: Arc<RwLock<BrowserStats>>,
     
@@ -57,6 +113,34 @@ impl BrowserApp {
         let window_manager = Arc::new(RwLock::new(WindowManager::new().await?));
         let tab_manager = Arc::new(RwLock::new(TabManager::new().await?));
         let extension_host = Arc::new(RwLock::new(ExtensionHost::new().await?));
+        let data_directory = common::platform::PlatformPaths::data_directory()?;
+        let permissions_manager = Arc::new(PermissionsManager::new(&data_directory)?);
+        let geolocation_manager = Arc::new(GeolocationManager::new(permissions_manager.clone()));
+        let clipboard_manager = Arc::new(ClipboardManager::new(permissions_manager.clone()));
+        let file_system_manager = Arc::new(FileSystemManager::new(permissions_manager.clone()));
+        let (window_request_sender, window_requests) = mpsc::unbounded_channel();
+        let renderer_host = Arc::new(RendererHost::new(
+            clipboard_manager.clone(),
+            file_system_manager.clone(),
+            geolocation_manager.clone(),
+            window_request_sender,
+        ));
+        
+        let network = Arc::new(RwLock::new(network::NetworkProcessManager::new(network::NetworkConfig::default()).await?));
+        let download_dir = dirs::download_dir().unwrap_or_else(|| data_directory.join("Downloads"));
+        let favicon_manager = Arc::new(FaviconManager::new(
+            &data_directory,
+            Arc::new(NetworkFaviconTransport::new(network.clone())),
+        )?);
+        let download_manager = Arc::new(DownloadManager::new(
+            &data_directory,
+            download_dir,
+            Arc::new(NetworkDownloadTransport::new(network)),
+        )?);
+        download_manager.resume_incomplete().await?;
+        let history_manager = Arc::new(HistoryManager::new(&data_directory)?);
+        let session_manager = Arc::new(SessionManager::new(&data_directory)?);
+        let session_autosave = session_manager.clone().spawn_autosave(tab_manager.clone());
         
         // Load settings
         let settings = {
@@ -64,6 +148,8 @@ impl BrowserApp {
             settings_mgr.get_settings().await?
         };
         
+        let autocomplete_engine = AutocompleteEngine::new(history_manager.clone(), &settings.search_engine);
+        
         // Initialize statistics
         let stats = Arc::new(RwLock::new(BrowserStats::default()));
         
@@ -75,6 +161,19 @@ impl BrowserApp {
             profile_manager,
             settings_manager,
             extension_host,
+            permissions_manager,
+            geolocation_manager,
+            clipboard_manager,
+            file_system_manager,
+            renderer_host,
+            window_requests: Some(window_requests),
+            download_manager,
+            history_manager,
+            session_manager,
+            autocomplete_engine,
+            pip_manager: Arc::new(RwLock::new(PipManager::new())),
+            favicon_manager,
+            session_autosave,
             stats,
             settings,
             running: false,
@@ -82,17 +181,34 @@ impl BrowserApp {
     }
     
     /// Run the browser application
-    pub async fn run(&mut self) -> Result<()> {
+    ///
+    /// `startup_input` is opened in a tab as if typed in the address bar.
+    pub async fn run(&mut self, startup_input: Option<&str>) -> Result<()> {
         info!("Starting browser event loop");
         
-        // Create event loop
-        let event_loop = EventLoop::new()
+        // Create event loop, woken by the window requests of renderers
+        let event_loop = EventLoopBuilder::<BrowserRequest>::with_user_event().build()
             .map_err(|e| common::error::Error::PlatformError(format!("Failed to create event loop: {}", e)))?;
+        if let Some(mut window_requests) = self.window_requests.take() {
+            let proxy = event_loop.create_proxy();
+            tokio::spawn(async move {
+                while let Some(request) = window_requests.recv().await {
+                    if proxy.send_event(request).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
         
         // Create initial window
         let window = self.create_initial_window(&event_loop).await?;
+        if let Some(input) = startup_input {
+            let tab_id = self.create_tab(INITIAL_WINDOW_ID, None).await?;
+            self.navigate_from_address_bar(tab_id, input).await?;
+        }
         
         self.running = true;
+        let app = &*self;
         
         // Run the event loop
         event_loop.run(move |event, elwt| {
@@ -104,7 +220,15 @@ impl BrowserApp {
                     window_id,
                 } => {
                     info!("Window close requested: {:?}", window_id);
-                    elwt.exit();
+                    let pip_tab = block_on(app.pip_manager.read()).get_by_window(window_id).map(|handle| handle.tab_id());
+                    match pip_tab {
+                        Some(tab_id) => {
+                            if let Err(e) = block_on(app.pip_manager.write()).exit_pip(tab_id) {
+                                warn!("Failed to close the Picture-in-Picture window of tab {}: {}", tab_id, e);
+                            }
+                        }
+                        None => elwt.exit(),
+                    }
                 }
                 
                 Event::WindowEvent {
@@ -112,7 +236,9 @@ impl BrowserApp {
                     window_id,
                 } => {
                     debug!("Window resized: {:?} -> {:?}", window_id, new_size);
-                    // Handle window resize
+                    if let Some(handle) = block_on(app.pip_manager.read()).get_by_window(window_id) {
+                        handle.resize(new_size.width, new_size.height);
+                    }
                 }
                 
                 Event::WindowEvent {
@@ -128,7 +254,14 @@ impl BrowserApp {
                     window_id,
                 } => {
                     debug!("Keyboard input: {:?} -> {:?}", window_id, event);
-                    // Handle keyboard input
+                    
+                    // Escape always takes the window out of fullscreen, whatever the page does
+                    let escape = event.state == ElementState::Pressed && event.logical_key == Key::Named(NamedKey::Escape);
+                    if escape && window_id == window.id() {
+                        if let Err(e) = block_on(app.leave_fullscreen(&window)) {
+                            warn!("Failed to leave fullscreen: {}", e);
+                        }
+                    }
                 }
                 
                 Event::WindowEvent {
@@ -155,6 +288,13 @@ impl BrowserApp {
                     // Handle mouse wheel
                 }
                 
+                Event::UserEvent(request) => {
+                    debug!("Window request from a renderer: {:?}", request);
+                    if let Err(e) = block_on(app.handle_browser_request(&window, elwt, request)) {
+                        warn!("Failed to carry out a window request: {}", e);
+                    }
+                }
+                
                 Event::AboutToWait => {
                     // Handle about to wait
                 }
@@ -183,7 +323,7 @@ impl BrowserApp {
     }
     
     /// Create the initial browser window
-    async fn create_initial_window(&self, event_loop: &EventLoop<()>) -> Result<Window> {
+    async fn create_initial_window(&self, event_loop: &EventLoop<BrowserRequest>) -> Result<Window> {
         info!("Creating initial browser window");
         
         let window = WindowBuilder::new()
@@ -201,7 +341,7 @@ impl BrowserApp {
         {
             let mut window_mgr = self.window_manager.write().await;
             let window_id = window.id();
-            window_mgr.add_window(window_id, WindowInfo::new(1, 1200, 800)).await?;
+            window_mgr.add_window(window_id, WindowInfo::new(INITIAL_WINDOW_ID, 1200, 800)).await?;
         }
         
         info!("Initial browser window created successfully");
@@ -235,6 +375,8 @@ impl BrowserApp {
             let mut tab_mgr = self.tab_manager.write().await;
             tab_mgr.close_tab(tab_id).await?;
         }
+        self.permissions_manager.remove_tab(tab_id);
+        self.file_system_manager.remove_tab(tab_id);
         
         // Update statistics
         {
@@ -251,12 +393,184 @@ impl BrowserApp {
         info!("Navigating tab {} to {}", tab_id, url);
         
         let mut tab_mgr = self.tab_manager.write().await;
-        tab_mgr.navigate_tab(tab_id, url).await?;
+        tab_mgr.navigate_tab(tab_id, url.clone()).await?;
+        self.history_manager.add_visit(&url, "", std::time::SystemTime::now())?;
         
         info!("Navigated tab {} successfully", tab_id);
         Ok(())
     }
     
+    /// Navigate a tab to what was typed in the address bar
+    ///
+    /// URLs load as typed; other input goes to the best autocomplete
+    /// suggestion, a search if no visited or bookmarked page matches.
+    pub async fn navigate_from_address_bar(&self, tab_id: TabId, input: &str) -> Result<()> {
+        let url = self.autocomplete_engine.resolve(input).ok_or_else(|| {
+            common::error::Error::InvalidState("Nothing was typed in the address bar".to_string())
+        })?;
+        self.navigate_tab(tab_id, url).await
+    }
+    
+    /// Answer clipboard, file system and other requests the renderer of a tab sends over a channel
+    ///
+    /// The task ends when the renderer closes the channel.
+    pub fn serve_renderer(&self, tab_id: TabId, channel: Channel<IpcMessage>) -> tokio::task::JoinHandle<()> {
+        let renderer_host = self.renderer_host.clone();
+        tokio::spawn(async move { renderer_host.serve(tab_id, channel).await })
+    }
+    
+    /// Follow the audio output of a tab over a channel until it closes
+    ///
+    /// The tab's speaker icon follows the `AudioEvent`s the output sends,
+    /// and muting the tab sets the output's gain over the same channel.
+    pub fn serve_audio(&self, tab_id: TabId, channel: Channel<IpcMessage>) -> tokio::task::JoinHandle<()> {
+        let tab_manager = self.tab_manager.clone();
+        tokio::spawn(async move {
+            if let Err(e) = tab_manager.write().await.connect_audio(tab_id, channel.clone()).await {
+                warn!("Cannot follow the audio of tab {}: {}", tab_id, e);
+                return;
+            }
+            
+            loop {
+                let event = match channel.recv_async().await {
+                    Ok(IpcMessage::AudioEvent(event)) => event,
+                    Ok(message) => {
+                        warn!("Ignoring {:?} from the audio of tab {}", std::mem::discriminant(&message), tab_id);
+                        continue;
+                    }
+                    Err(e) => {
+                        debug!("Audio channel of tab {} closed: {}", tab_id, e);
+                        return;
+                    }
+                };
+                let (AudioEvent::Playing { tab_id: event_tab } | AudioEvent::Stopped { tab_id: event_tab }) = event;
+                if event_tab != tab_id {
+                    warn!("Audio of tab {} reported for tab {}", tab_id, event_tab);
+                    continue;
+                }
+                if let Err(e) = tab_manager.write().await.handle_audio_event(event).await {
+                    warn!("Failed to update the audio state of tab {}: {}", tab_id, e);
+                }
+            }
+        })
+    }
+    
+    /// Mute or unmute a tab, as its speaker icon does
+    pub async fn set_tab_muted(&self, tab_id: TabId, muted: bool) -> Result<()> {
+        let mut tab_mgr = self.tab_manager.write().await;
+        if muted {
+            tab_mgr.mute_tab(tab_id).await
+        } else {
+            tab_mgr.unmute_tab(tab_id).await
+        }
+    }
+    
+    /// Carry out a request a tab's renderer made for the window showing it
+    ///
+    /// Picture-in-Picture requests open or close their own window, created with `windows`.
+    pub async fn handle_browser_request<W, F>(&self, window: &W, windows: &F, request: BrowserRequest) -> Result<()>
+    where
+        W: FullscreenWindow + ?Sized,
+        F: PipWindowFactory<Window = Window>,
+    {
+        match request {
+            BrowserRequest::EnterPictureInPicture { .. } | BrowserRequest::ExitPictureInPicture { .. } => {
+                self.pip_manager.write().await.handle_browser_request(windows, request)
+            }
+            request => self.window_manager.write().await.handle_browser_request(window, request).await,
+        }
+    }
+    
+    /// Take a window out of fullscreen for the user, as Escape does
+    ///
+    /// The renderer of the tab shown fullscreen is told, so the page leaves
+    /// fullscreen too.
+    pub async fn leave_fullscreen<W: FullscreenWindow + ?Sized>(&self, window: &W) -> Result<()> {
+        let Some(tab_id) = self.window_manager.write().await.exit_fullscreen(window).await? else {
+            return Ok(());
+        };
+        let channel = self.tab_manager.read().await.renderer_channel(tab_id).await?;
+        channel.send_async(IpcMessage::BrowserEvent(BrowserEvent::FullscreenExited { tab_id })).await
+    }
+    
+    /// Fetch the favicon of the page loaded in a tab and show it in the tab
+    pub async fn update_tab_favicon(&self, tab_id: TabId, page_url: &str, dom: &dom::Document) -> Result<()> {
+        let entry = self.favicon_manager.fetch_favicon(tab_id, page_url, dom).await?;
+        self.tab_manager.write().await.set_favicon(tab_id, entry).await
+    }
+    
+    /// Print the page loaded in a tab to a PDF file
+    pub async fn print_to_pdf(&self, tab_id: TabId, output_path: PathBuf) -> Result<()> {
+        info!("Printing tab {} to {}", tab_id, output_path.display());
+        
+        let (channel, title) = {
+            let tab_mgr = self.tab_manager.read().await;
+            (tab_mgr.renderer_channel(tab_id).await?, tab_mgr.get_tab(tab_id).await?.title.clone())
+        };
+        let pages = printing::print_tab(&channel, tab_id, PaperSize::default(), PageMargins::default()).await?;
+        printing::write_pdf(&pages, &title, &output_path)
+    }
+    
+    /// Get the favicon manager
+    pub fn favicon_manager(&self) -> Arc<FaviconManager> {
+        self.favicon_manager.clone()
+    }
+    
+    /// Get the Picture-in-Picture window manager
+    pub fn pip_manager(&self) -> Arc<RwLock<PipManager>> {
+        self.pip_manager.clone()
+    }
+    
+    /// Get the permissions manager
+    pub fn permissions_manager(&self) -> Arc<PermissionsManager> {
+        self.permissions_manager.clone()
+    }
+    
+    /// Get the geolocation manager
+    pub fn geolocation_manager(&self) -> Arc<GeolocationManager> {
+        self.geolocation_manager.clone()
+    }
+    
+    /// Get the clipboard manager
+    pub fn clipboard_manager(&self) -> Arc<ClipboardManager> {
+        self.clipboard_manager.clone()
+    }
+    
+    /// Get the File System Access manager
+    pub fn file_system_manager(&self) -> Arc<FileSystemManager> {
+        self.file_system_manager.clone()
+    }
+    
+    /// Get the download manager
+    pub fn download_manager(&self) -> Arc<DownloadManager> {
+        self.download_manager.clone()
+    }
+    
+    /// Get the history manager
+    pub fn history_manager(&self) -> Arc<HistoryManager> {
+        self.history_manager.clone()
+    }
+    
+    /// Suggest URLs for what was typed in the address bar, best first
+    pub fn autocomplete(&self, query: &str, limit: usize) -> Vec<AutocompleteSuggestion> {
+        self.autocomplete_engine.suggest(query, limit)
+    }
+    
+    /// Get session manager
+    pub fn session_manager(&self) -> Arc<SessionManager> {
+        self.session_manager.clone()
+    }
+    
+    /// Reopen the tabs of the last session in a window
+    pub async fn restore_last_session(&self, window_id: u64) -> Result<Vec<TabId>> {
+        let snapshots = self.session_manager.restore_session()?;
+        let mut tab_ids = Vec::with_capacity(snapshots.len());
+        for snapshot in snapshots {
+            tab_ids.push(self.create_tab(window_id, Some(snapshot.url)).await?);
+        }
+        Ok(tab_ids)
+    }
+    
     /// Get browser statistics
     pub async fn get_stats(&self) -> BrowserStats {
         self.stats.read().await.clone()
@@ -277,6 +591,7 @@ impl BrowserApp {
             settings_mgr.update_settings(new_settings.clone()).await?;
         }
         
+        self.autocomplete_engine.set_search_engine(&new_settings.search_engine);
         self.settings = new_settings;
         
         info!("Browser settings updated successfully");
@@ -294,6 +609,12 @@ impl BrowserApp {
         
         self.running = false;
         
+        // Save the open tabs for the next start
+        self.session_autosave.abort();
+        if let Err(e) = self.session_manager.save_tabs(&self.tab_manager).await {
+            error!("Failed to save session: {}", e);
+        }
+        
         // Cleanup resources
         {
             let mut tab_mgr = self.tab_manager.write().await;
@@ -328,6 +649,11 @@ impl Drop for BrowserApp {
     }
 }
 
+/// Wait for a future from the event loop, which runs on the runtime's thread
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -346,6 +672,67 @@ mod tests {
         assert!(settings.enable_javascript);
     }
 
+    #[tokio::test]
+    async fn test_escape_tells_renderer_fullscreen_ended() {
+        struct MockWindow;
+        
+        impl FullscreenWindow for MockWindow {
+            fn id(&self) -> winit::window::WindowId {
+                unsafe { winit::window::WindowId::dummy() }
+            }
+            
+            fn enter_fullscreen(&self) {}
+            
+            fn exit_fullscreen(&self) {}
+        }
+        
+        let app = BrowserApp::new().await.unwrap();
+        let tab_id = app.create_tab(INITIAL_WINDOW_ID, None).await.unwrap();
+        let (browser_end, renderer_end) = common::ipc::ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        app.tab_manager.write().await.connect_renderer(tab_id, browser_end.clone()).await.unwrap();
+        let window = MockWindow;
+        app.window_manager.write().await.add_window(window.id(), WindowInfo::new(INITIAL_WINDOW_ID, 800, 600)).await.unwrap();
+        
+        let request = BrowserRequest::EnterFullscreen { tab_id, element_id: "player".to_string() };
+        app.window_manager.write().await.handle_browser_request(&window, request).await.unwrap();
+        app.leave_fullscreen(&window).await.unwrap();
+        
+        match renderer_end.recv_async().await.unwrap() {
+            IpcMessage::BrowserEvent(event) => assert_eq!(event, BrowserEvent::FullscreenExited { tab_id }),
+            other => panic!("Unexpected message {:?}", other),
+        }
+        assert!(!app.window_manager.read().await.get_window(window.id()).await.unwrap().fullscreen);
+        
+        // Escape outside fullscreen tells nobody, so the next message is the marker sent after it
+        app.leave_fullscreen(&window).await.unwrap();
+        let marker = BrowserEvent::FullscreenExited { tab_id: TabId::new(0) };
+        browser_end.send_async(IpcMessage::BrowserEvent(marker.clone())).await.unwrap();
+        assert!(matches!(renderer_end.recv_async().await.unwrap(), IpcMessage::BrowserEvent(event) if event == marker));
+    }
+
+    #[tokio::test]
+    async fn test_tab_audio_follows_its_output() {
+        let app = BrowserApp::new().await.unwrap();
+        let tab_id = app.create_tab(INITIAL_WINDOW_ID, None).await.unwrap();
+        let mut events = app.tab_manager.read().await.subscribe_events();
+        let (browser_end, audio_end) = common::ipc::ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let server = app.serve_audio(tab_id, browser_end);
+        
+        audio_end.send_async(IpcMessage::AudioEvent(AudioEvent::Playing { tab_id })).await.unwrap();
+        let crate::tab_manager::TabEvent::AudioStateChanged { state, .. } = events.recv().await.unwrap();
+        assert!(state.is_playing && !state.is_muted);
+        
+        app.set_tab_muted(tab_id, true).await.unwrap();
+        match audio_end.recv_async().await.unwrap() {
+            IpcMessage::AudioControl(control) => assert_eq!(control, common::ipc::AudioControl::SetGain { tab_id, gain: 0.0 }),
+            other => panic!("Unexpected message {:?}", other),
+        }
+        assert!(app.tab_manager.read().await.get_audio_state(tab_id).await.unwrap().is_muted);
+        
+        drop(audio_end);
+        server.await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_browser_app_stats() {
         let app = BrowserApp::new().await.unwrap();
@@ -0,0 +1,318 @@
+//! Address bar autocomplete
+//!
+//! Suggestions come from browsing history, bookmarks and the search engine.
+//! Pages the query prefixes score 1.0, and pages whose URL or title contain
+//! it elsewhere 0.5; other pages are not suggested. Visits in the last week
+//! add 0.1 for each day they are more recent than a week ago, and every
+//! visit adds 0.01, so frequently and recently visited pages rise to the
+//! top. The search suggestion always scores `SEARCH_SCORE`.
+
+use crate::history::HistoryManager;
+use common::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::warn;
+
+/// Score of a page the query is a prefix of
+const PREFIX_SCORE: f32 = 1.0;
+
+/// Score of a page the query matches elsewhere
+const SUBSTRING_SCORE: f32 = 0.5;
+
+/// Boost for each day a visit is more recent than `RECENCY_WINDOW_DAYS` ago
+const RECENCY_BOOST_PER_DAY: f32 = 0.1;
+
+/// Days during which visits boost a page
+const RECENCY_WINDOW_DAYS: u64 = 7;
+
+/// Boost for each visit of a page
+const VISIT_BOOST: f32 = 0.01;
+
+/// Score of searching for the query, below any page match
+const SEARCH_SCORE: f32 = 0.25;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Schemes of typed URLs the address bar loads as they are
+const NAVIGABLE_SCHEMES: [&str; 5] = ["http", "https", "file", "about", "data"];
+
+/// Where a suggestion comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchType {
+    /// A visited page
+    History,
+    /// A bookmarked page
+    Bookmark,
+    /// Searching for the query
+    Search,
+}
+
+/// Suggestion for the address bar
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutocompleteSuggestion {
+    /// URL to navigate to
+    pub url: String,
+
+    /// Page title, or the query for search suggestions
+    pub title: String,
+
+    /// Where the suggestion comes from
+    pub match_type: MatchType,
+
+    /// Relevance, higher first
+    pub score: f32,
+}
+
+/// A bookmarked page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    /// Page URL
+    pub url: String,
+
+    /// Bookmark title
+    pub title: String,
+}
+
+/// Searches bookmarks for autocomplete, implemented by the bookmark manager
+pub trait BookmarkSearch: Send + Sync {
+    /// Bookmarks whose title or URL match a query
+    fn search_bookmarks(&self, query: &str) -> Result<Vec<Bookmark>>;
+}
+
+/// Address bar autocomplete engine
+pub struct AutocompleteEngine {
+    /// History searched for visited pages
+    history: Arc<HistoryManager>,
+
+    /// Bookmarks searched for bookmarked pages
+    bookmarks: Option<Arc<dyn BookmarkSearch>>,
+
+    /// Search engine URL, with `{}` standing for the query
+    search_engine: String,
+}
+
+impl AutocompleteEngine {
+    /// Create an engine suggesting history and searches
+    pub fn new(history: Arc<HistoryManager>, search_engine: &str) -> Self {
+        Self {
+            history,
+            bookmarks: None,
+            search_engine: search_engine.to_string(),
+        }
+    }
+
+    /// Also suggest bookmarks
+    pub fn set_bookmark_search(&mut self, bookmarks: Arc<dyn BookmarkSearch>) {
+        self.bookmarks = Some(bookmarks);
+    }
+
+    /// Set the search engine URL, with `{}` standing for the query
+    pub fn set_search_engine(&mut self, search_engine: &str) {
+        self.search_engine = search_engine.to_string();
+    }
+
+    /// Suggest up to `limit` URLs for a query, best first
+    ///
+    /// A URL both visited and bookmarked is suggested once, with its best
+    /// score. Sources that fail to search are left out.
+    pub fn suggest(&self, query: &str, limit: usize) -> Vec<AutocompleteSuggestion> {
+        let query = query.trim();
+        if query.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        let mut suggestions = Vec::new();
+        let now = SystemTime::now();
+
+        match self.history.search(query) {
+            Ok(entries) => suggestions.extend(entries.into_iter().filter_map(|entry| {
+                let score = match_score(query, &entry.url, &entry.title)?
+                    + recency_boost(now, entry.visit_time)
+                    + VISIT_BOOST * entry.visit_count as f32;
+                Some(AutocompleteSuggestion { url: entry.url, title: entry.title, match_type: MatchType::History, score })
+            })),
+            Err(e) => warn!("Failed to search history for autocomplete: {}", e),
+        }
+
+        if let Some(bookmarks) = &self.bookmarks {
+            match bookmarks.search_bookmarks(query) {
+                Ok(bookmarks) => suggestions.extend(bookmarks.into_iter().filter_map(|bookmark| {
+                    let score = match_score(query, &bookmark.url, &bookmark.title)?;
+                    Some(AutocompleteSuggestion { url: bookmark.url, title: bookmark.title, match_type: MatchType::Bookmark, score })
+                })),
+                Err(e) => warn!("Failed to search bookmarks for autocomplete: {}", e),
+            }
+        }
+
+        suggestions.push(AutocompleteSuggestion {
+            url: self.search_url(query),
+            title: query.to_string(),
+            match_type: MatchType::Search,
+            score: SEARCH_SCORE,
+        });
+
+        // Keep the best suggestion of each URL, in the order URLs were first suggested
+        let mut best: HashMap<String, usize> = HashMap::new();
+        let mut deduplicated: Vec<AutocompleteSuggestion> = Vec::with_capacity(suggestions.len());
+        for suggestion in suggestions {
+            match best.get(&suggestion.url) {
+                Some(&index) if deduplicated[index].score >= suggestion.score => {}
+                Some(&index) => deduplicated[index] = suggestion,
+                None => {
+                    best.insert(suggestion.url.clone(), deduplicated.len());
+                    deduplicated.push(suggestion);
+                }
+            }
+        }
+
+        // Stable, so ties keep history before bookmarks
+        deduplicated.sort_by(|a, b| b.score.total_cmp(&a.score));
+        deduplicated.truncate(limit);
+        deduplicated
+    }
+
+    /// URL the address bar loads for what was typed in it
+    ///
+    /// A URL, or a host name with an optional path, is loaded as typed.
+    /// Anything else goes to the best suggestion, which is a search when no
+    /// visited or bookmarked page matches.
+    pub fn resolve(&self, input: &str) -> Option<String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return None;
+        }
+
+        if let Ok(url) = url::Url::parse(input) {
+            if NAVIGABLE_SCHEMES.contains(&url.scheme()) {
+                return Some(url.into());
+            }
+        }
+        if !input.contains(char::is_whitespace) && input.contains('.') {
+            if let Ok(url) = url::Url::parse(&format!("https://{}", input)) {
+                return Some(url.into());
+            }
+        }
+
+        self.suggest(input, 1).into_iter().next().map(|suggestion| suggestion.url)
+    }
+
+    /// URL searching for a query with the search engine
+    fn search_url(&self, query: &str) -> String {
+        let encoded: String = url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+        self.search_engine.replace("{}", &encoded)
+    }
+}
+
+/// Score of how a query matches a page, `None` if neither its URL nor its title contain the query
+fn match_score(query: &str, url: &str, title: &str) -> Option<f32> {
+    let query = query.to_lowercase();
+    let url = url.to_lowercase();
+    let title = title.to_lowercase();
+
+    // "exa" prefixes "https://www.example.com/"
+    let host_and_path = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+    let host_and_path = host_and_path.strip_prefix("www.").unwrap_or(host_and_path);
+
+    if url.starts_with(&query) || host_and_path.starts_with(&query) || title.starts_with(&query) {
+        Some(PREFIX_SCORE)
+    } else if url.contains(&query) || title.contains(&query) {
+        Some(SUBSTRING_SCORE)
+    } else {
+        None
+    }
+}
+
+/// Boost of a page last visited at `visit_time`
+fn recency_boost(now: SystemTime, visit_time: SystemTime) -> f32 {
+    let days = now.duration_since(visit_time).unwrap_or_default().as_secs() / DAY.as_secs();
+    RECENCY_WINDOW_DAYS.saturating_sub(days) as f32 * RECENCY_BOOST_PER_DAY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedBookmarks(Vec<Bookmark>);
+
+    impl BookmarkSearch for FixedBookmarks {
+        fn search_bookmarks(&self, query: &str) -> Result<Vec<Bookmark>> {
+            Ok(self.0.iter().filter(|bookmark| bookmark.title.to_lowercase().contains(&query.to_lowercase())).cloned().collect())
+        }
+    }
+
+    #[test]
+    fn test_suggest_ranks_recent_history_first() {
+        let history = Arc::new(HistoryManager::in_memory().unwrap());
+        let now = SystemTime::now();
+
+        // (url, title, visits, days since the last visit)
+        let pages = [
+            ("https://docs.rust-lang.org/book/", "The Rust Programming Language", 5, 6),
+            ("https://rust-lang.org/learn", "Learn Rust", 1, 0),
+            ("https://crates.io/crates/rustls", "rustls - crates.io", 3, 2),
+            ("https://blog.rust-lang.org/", "Rust Blog", 2, 20),
+            ("https://news.example/", "News", 9, 0),
+        ];
+        for (url, title, visits, age) in pages {
+            for visit in 0..visits {
+                history.add_visit(url, title, now - DAY * (age + visits - 1 - visit)).unwrap();
+            }
+        }
+
+        let mut engine = AutocompleteEngine::new(history, "https://search.example/?q={}");
+        let suggestions = engine.suggest("rust", 10);
+        let urls: Vec<&str> = suggestions.iter().map(|suggestion| suggestion.url.as_str()).collect();
+        assert_eq!(urls, [
+            "https://rust-lang.org/learn",
+            "https://crates.io/crates/rustls",
+            "https://blog.rust-lang.org/",
+            "https://docs.rust-lang.org/book/",
+            "https://search.example/?q=rust",
+        ]);
+
+        // Prefix of the host, visited today once: 1.0 + 0.7 + 0.01
+        assert!((suggestions[0].score - 1.71).abs() < 1e-5);
+        assert_eq!(suggestions[0].match_type, MatchType::History);
+        assert!(suggestions.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        assert_eq!(suggestions[4].match_type, MatchType::Search);
+        assert_eq!(engine.suggest("rust", 2).len(), 2);
+        assert!(engine.suggest("  ", 10).is_empty());
+
+        // A bookmarked page that was visited is suggested once, with its best score
+        engine.set_bookmark_search(Arc::new(FixedBookmarks(vec![
+            Bookmark { url: "https://blog.rust-lang.org/".to_string(), title: "Rust Blog".to_string() },
+            Bookmark { url: "https://rust-by-example.example/".to_string(), title: "Rust by Example".to_string() },
+            Bookmark { url: "https://rustup.rs/".to_string(), title: "rustup".to_string() },
+        ])));
+        engine.set_search_engine("https://other.example/search?q={}");
+        let suggestions = engine.suggest("rust b", 10);
+        let urls: Vec<(&str, MatchType)> = suggestions.iter().map(|suggestion| (suggestion.url.as_str(), suggestion.match_type)).collect();
+        assert_eq!(urls, [
+            ("https://blog.rust-lang.org/", MatchType::History),
+            ("https://rust-by-example.example/", MatchType::Bookmark),
+            ("https://other.example/search?q=rust+b", MatchType::Search),
+        ]);
+    }
+
+    #[test]
+    fn test_match_score_requires_the_query() {
+        assert_eq!(match_score("exa", "https://www.example.com/", ""), Some(PREFIX_SCORE));
+        assert_eq!(match_score("ample", "https://www.example.com/", ""), Some(SUBSTRING_SCORE));
+        assert_eq!(match_score("rust b", "https://docs.rust-lang.org/book/", "The Rust Programming Language"), None);
+    }
+
+    #[test]
+    fn test_resolve_typed_input() {
+        let history = Arc::new(HistoryManager::in_memory().unwrap());
+        history.add_visit("https://www.example.com/docs", "Example docs", SystemTime::now()).unwrap();
+        let engine = AutocompleteEngine::new(history, "https://search.example/?q={}");
+
+        assert_eq!(engine.resolve("http://localhost:8080/app").as_deref(), Some("http://localhost:8080/app"));
+        assert_eq!(engine.resolve("rust-lang.org/learn").as_deref(), Some("https://rust-lang.org/learn"));
+        assert_eq!(engine.resolve("exam").as_deref(), Some("https://www.example.com/docs"));
+        assert_eq!(engine.resolve("rust book").as_deref(), Some("https://search.example/?q=rust+book"));
+        assert_eq!(engine.resolve("  "), None);
+    }
+}
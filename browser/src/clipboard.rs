@@ -0,0 +1,243 @@
+//! Clipboard API for the Matte browser
+//!
+//! Only the browser process touches the system clipboard. Renderer processes
+//! ask for `readText` and `writeText` over IPC; reads need the origin's
+//! `clipboard-read` permission. Text crossing the boundary is sanitized so a
+//! page cannot plant terminal escape sequences or flood the clipboard.
+
+use common::{
+    error::{Error, Result},
+    ipc::{ClipboardResponseMessage, IpcMessage},
+    Permission, PermissionState, TabId,
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tracing::{debug, info};
+
+use crate::permissions::PermissionsManager;
+
+/// Largest text moved through the clipboard, in bytes
+pub const MAX_TEXT_BYTES: usize = 10 * 1024 * 1024;
+
+/// Text storage behind the clipboard
+pub trait ClipboardBackend: Send + Sync {
+    /// Read the current text
+    fn read_text(&self) -> Result<String>;
+
+    /// Replace the current contents with text
+    fn write_text(&self, text: &str) -> Result<()>;
+}
+
+/// The operating system clipboard
+#[derive(Default)]
+pub struct SystemClipboard {
+    /// Connection to the clipboard, opened on first use
+    clipboard: Mutex<Option<arboard::Clipboard>>,
+}
+
+impl SystemClipboard {
+    /// Create a handle to the system clipboard
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_clipboard<T>(&self, operation: impl FnOnce(&mut arboard::Clipboard) -> std::result::Result<T, arboard::Error>) -> Result<T> {
+        let mut clipboard = self.clipboard.lock();
+        if clipboard.is_none() {
+            *clipboard = Some(arboard::Clipboard::new().map_err(clipboard_error)?);
+        }
+
+        clipboard.as_mut().map(operation).expect("clipboard was just opened").map_err(clipboard_error)
+    }
+}
+
+impl ClipboardBackend for SystemClipboard {
+    fn read_text(&self) -> Result<String> {
+        self.with_clipboard(|clipboard| clipboard.get_text())
+    }
+
+    fn write_text(&self, text: &str) -> Result<()> {
+        self.with_clipboard(|clipboard| clipboard.set_text(text))
+    }
+}
+
+/// Clipboard kept in memory, for tests and headless sessions
+#[derive(Default)]
+pub struct MemoryClipboard {
+    text: Mutex<String>,
+}
+
+impl ClipboardBackend for MemoryClipboard {
+    fn read_text(&self) -> Result<String> {
+        Ok(self.text.lock().clone())
+    }
+
+    fn write_text(&self, text: &str) -> Result<()> {
+        *self.text.lock() = text.to_string();
+        Ok(())
+    }
+}
+
+/// Clipboard manager
+pub struct ClipboardManager {
+    /// Permissions manager gating reads
+    permissions: Arc<PermissionsManager>,
+
+    /// Clipboard storage
+    backend: Arc<dyn ClipboardBackend>,
+}
+
+impl ClipboardManager {
+    /// Create a clipboard manager backed by the system clipboard
+    pub fn new(permissions: Arc<PermissionsManager>) -> Self {
+        Self::with_backend(permissions, Arc::new(SystemClipboard::new()))
+    }
+
+    /// Create a clipboard manager with a specific backend
+    pub fn with_backend(permissions: Arc<PermissionsManager>, backend: Arc<dyn ClipboardBackend>) -> Self {
+        info!("Initializing clipboard manager");
+        Self { permissions, backend }
+    }
+
+    /// Write text to the clipboard for a tab
+    pub async fn write_text(&self, tab_id: TabId, text: String) -> Result<()> {
+        let text = sanitize_text(&text);
+        debug!("Tab {} writing {} bytes to the clipboard", tab_id, text.len());
+        self.backend.write_text(&text)
+    }
+
+    /// Read text from the clipboard for a tab
+    pub async fn read_text(&self, tab_id: TabId) -> Result<String> {
+        let origin = self.permissions.tab_origin(tab_id)?;
+        if self.permissions.query(&origin, &Permission::ClipboardRead) != PermissionState::Granted {
+            return Err(Error::PermissionDenied(format!("Clipboard read is not allowed for {}", origin)));
+        }
+
+        debug!("Tab {} reading the clipboard", tab_id);
+        Ok(sanitize_text(&self.backend.read_text()?))
+    }
+
+    /// Answer a clipboard request from a renderer process
+    ///
+    /// Returns `None` for messages that are not clipboard requests.
+    pub async fn handle_message(&self, message: IpcMessage) -> Option<IpcMessage> {
+        let (request_id, result) = match message {
+            IpcMessage::ClipboardRead(request) => {
+                (request.request_id, self.read_text(request.tab_id).await.map(Some))
+            }
+            IpcMessage::ClipboardWrite(request) => {
+                (request.request_id, self.write_text(request.tab_id, request.text).await.map(|_| None))
+            }
+            _ => return None,
+        };
+
+        Some(IpcMessage::ClipboardResponse(match result {
+            Ok(text) => ClipboardResponseMessage { request_id, text, error: None },
+            Err(e) => ClipboardResponseMessage { request_id, text: None, error: Some(e.to_string()) },
+        }))
+    }
+}
+
+/// Strip ANSI escape sequences and cap text at `MAX_TEXT_BYTES`
+pub fn sanitize_text(text: &str) -> String {
+    let mut sanitized = String::with_capacity(text.len().min(MAX_TEXT_BYTES));
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{1b}' => match chars.next() {
+                // CSI: parameters and intermediates up to a final byte
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('\u{40}'..='\u{7e}').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC, DCS and friends: up to BEL or the string terminator
+                Some(']' | 'P' | 'X' | '^' | '_') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\u{7}' || (c == '\u{1b}' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                // Two-character sequences
+                _ => {}
+            },
+            // 8-bit CSI
+            '\u{9b}' => {
+                for c in chars.by_ref() {
+                    if ('\u{40}'..='\u{7e}').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            c => {
+                if sanitized.len() + c.len_utf8() > MAX_TEXT_BYTES {
+                    break;
+                }
+                sanitized.push(c);
+            }
+        }
+    }
+
+    sanitized
+}
+
+fn clipboard_error(error: arboard::Error) -> Error {
+    Error::PlatformError(format!("Clipboard error: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::ipc::ClipboardReadMessage;
+
+    const ORIGIN: &str = "https://editor.example";
+
+    fn manager() -> (ClipboardManager, Arc<PermissionsManager>) {
+        let permissions = Arc::new(PermissionsManager::in_memory().unwrap());
+        permissions.set_tab_origin(TabId::new(1), ORIGIN);
+        let manager = ClipboardManager::with_backend(permissions.clone(), Arc::new(MemoryClipboard::default()));
+        (manager, permissions)
+    }
+
+    #[tokio::test]
+    async fn test_write_and_read_unicode_text() {
+        let (clipboard, permissions) = manager();
+        let tab = TabId::new(1);
+        let text = "Grüße, 世界! 👋🏽 🦀".to_string();
+
+        clipboard.write_text(tab, text.clone()).await.unwrap();
+        assert!(matches!(clipboard.read_text(tab).await, Err(Error::PermissionDenied(_))));
+
+        permissions.set_permission(ORIGIN, Permission::ClipboardRead, PermissionState::Granted).unwrap();
+        assert_eq!(clipboard.read_text(tab).await.unwrap(), text);
+
+        let response = clipboard.handle_message(IpcMessage::ClipboardRead(ClipboardReadMessage {
+            request_id: 7,
+            tab_id: tab,
+        })).await;
+        match response {
+            Some(IpcMessage::ClipboardResponse(response)) => {
+                assert_eq!(response.request_id, 7);
+                assert_eq!(response.text, Some(text));
+                assert!(response.error.is_none());
+            }
+            other => panic!("unexpected response {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_sanitize_text() {
+        assert_eq!(sanitize_text("\u{1b}[31mred\u{1b}[0m text"), "red text");
+        assert_eq!(sanitize_text("\u{1b}]0;title\u{7}ok\u{1b}]8;;http://x\u{1b}\\link"), "oklink");
+        assert_eq!(sanitize_text("tab\tand\nnewline"), "tab\tand\nnewline");
+
+        let oversized = "é".repeat(MAX_TEXT_BYTES);
+        let sanitized = sanitize_text(&oversized);
+        assert_eq!(sanitized.len(), MAX_TEXT_BYTES);
+        assert!(sanitized.chars().all(|c| c == 'é'));
+    }
+}
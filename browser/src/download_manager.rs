@@ -0,0 +1,653 @@
+//! Download manager for the Matte browser
+//!
+//! Downloads are fetched through the network process and streamed to a
+//! partial file, which is moved to the download directory once complete.
+//! Entries are kept in a `sled` database at `data_directory/downloads.sled`,
+//! so a download interrupted by a network error, a pause or a restart picks
+//! up where it stopped when the server supports ranges.
+
+use async_trait::async_trait;
+use common::{
+    error::{Error, Result},
+    TabId,
+};
+use network::NetworkProcessManager;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::storage::open_database;
+
+/// Name of the sled database inside the data directory
+const DATABASE_NAME: &str = "downloads.sled";
+
+/// Directory inside the data directory holding partial files
+const PARTIAL_DIRECTORY: &str = "partial-downloads";
+
+/// Size of the chunks a response body is streamed in
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Download identifier
+pub type DownloadId = u64;
+
+/// State of a download
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DownloadState {
+    /// Bytes are being received
+    InProgress,
+    /// Paused by the user, the partial file is kept
+    Paused,
+    /// Stopped by a network error, the partial file is kept
+    Interrupted(String),
+    /// Moved to the download directory
+    Completed,
+    /// Cancelled by the user, the partial file is deleted
+    Cancelled,
+    /// Rejected by the server
+    Failed(String),
+}
+
+/// A download and its progress
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownloadEntry {
+    /// Download ID
+    pub id: DownloadId,
+
+    /// Tab that started the download
+    pub tab_id: TabId,
+
+    /// Requested URL
+    pub url: String,
+
+    /// File name suggested by the URL
+    pub filename: String,
+
+    /// Size of the file, if the server announced it
+    pub total_bytes: Option<u64>,
+
+    /// Bytes written to the partial file
+    pub received_bytes: u64,
+
+    /// Download state
+    pub state: DownloadState,
+
+    /// `ETag` validator, required to resume
+    pub etag: Option<String>,
+
+    /// Location of the completed file
+    pub target_path: Option<PathBuf>,
+}
+
+/// Response to a download request
+pub struct DownloadResponse {
+    /// HTTP status code
+    pub status_code: u16,
+
+    /// Response headers
+    pub headers: HashMap<String, String>,
+
+    /// Body chunks, in order
+    pub body: mpsc::Receiver<Result<Vec<u8>>>,
+}
+
+/// Connection used to fetch downloads
+#[async_trait]
+pub trait DownloadTransport: Send + Sync {
+    /// Send a GET request with extra headers
+    async fn fetch(&self, tab_id: TabId, url: &str, headers: HashMap<String, String>) -> Result<DownloadResponse>;
+}
+
+/// Transport sending download requests through the network process
+pub struct NetworkDownloadTransport {
+    network: Arc<RwLock<NetworkProcessManager>>,
+}
+
+impl NetworkDownloadTransport {
+    /// Create a transport over a network process manager
+    pub fn new(network: Arc<RwLock<NetworkProcessManager>>) -> Self {
+        Self { network }
+    }
+}
+
+#[async_trait]
+impl DownloadTransport for NetworkDownloadTransport {
+    async fn fetch(&self, tab_id: TabId, url: &str, headers: HashMap<String, String>) -> Result<DownloadResponse> {
+        let response = {
+            let mut network = self.network.write().await;
+            let request_id = network.create_request(tab_id, url.to_string(), "GET".to_string()).await?;
+            if let Some(request) = network.get_request(&request_id).await {
+                request.write().await.headers.extend(headers);
+            }
+            network.execute_request(&request_id).await?
+        };
+
+        // The network process delivers whole bodies, feed them to the
+        // writer in chunks
+        let (sender, body) = mpsc::channel(16);
+        let content = response.body;
+        tokio::spawn(async move {
+            for chunk in content.chunks(CHUNK_SIZE) {
+                if sender.send(Ok(chunk.to_vec())).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(DownloadResponse {
+            status_code: response.status_code,
+            headers: response.headers,
+            body,
+        })
+    }
+}
+
+/// Download entries stored in sled
+#[derive(Clone)]
+struct DownloadStore {
+    db: Arc<sled::Db>,
+}
+
+impl DownloadStore {
+    fn get(&self, id: DownloadId) -> Result<Option<DownloadEntry>> {
+        let Some(bytes) = self.db.get(id.to_be_bytes()).map_err(db_error)? else {
+            return Ok(None);
+        };
+
+        bincode::deserialize(&bytes)
+            .map(Some)
+            .map_err(|e| Error::ParseError(format!("Invalid download entry: {}", e)))
+    }
+
+    fn put(&self, entry: &DownloadEntry) -> Result<()> {
+        let bytes = bincode::serialize(entry)
+            .map_err(|e| Error::ParseError(format!("Failed to encode download entry: {}", e)))?;
+        self.db.insert(entry.id.to_be_bytes(), bytes).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn all(&self) -> Result<Vec<DownloadEntry>> {
+        self.db.iter()
+            .map(|item| {
+                let (_, bytes) = item.map_err(db_error)?;
+                bincode::deserialize(&bytes)
+                    .map_err(|e| Error::ParseError(format!("Invalid download entry: {}", e)))
+            })
+            .collect()
+    }
+
+    fn next_id(&self) -> Result<DownloadId> {
+        // IDs start at 1
+        self.db.generate_id().map(|id| id + 1).map_err(db_error)
+    }
+}
+
+/// Download manager
+pub struct DownloadManager {
+    /// Download entries
+    store: DownloadStore,
+
+    /// Connection used to fetch downloads
+    transport: Arc<dyn DownloadTransport>,
+
+    /// Directory completed downloads are moved to
+    download_dir: PathBuf,
+
+    /// Directory holding partial files
+    partial_dir: PathBuf,
+
+    /// Running transfers
+    active: Mutex<HashMap<DownloadId, JoinHandle<()>>>,
+}
+
+impl DownloadManager {
+    /// Create a download manager
+    pub fn new(data_directory: &Path, download_dir: PathBuf, transport: Arc<dyn DownloadTransport>) -> Result<Self> {
+        let partial_dir = data_directory.join(PARTIAL_DIRECTORY);
+        std::fs::create_dir_all(&partial_dir)?;
+        let db = open_database(&data_directory.join(DATABASE_NAME))?;
+
+        info!("Initializing download manager, saving to {:?}", download_dir);
+        Ok(Self {
+            store: DownloadStore { db },
+            transport,
+            download_dir,
+            partial_dir,
+            active: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Start downloading a URL
+    pub async fn start(&self, tab_id: TabId, url: &str) -> Result<DownloadId> {
+        let entry = DownloadEntry {
+            id: self.store.next_id()?,
+            tab_id,
+            url: url.to_string(),
+            filename: filename_from_url(url),
+            total_bytes: None,
+            received_bytes: 0,
+            state: DownloadState::InProgress,
+            etag: None,
+            target_path: None,
+        };
+        self.store.put(&entry)?;
+
+        info!("Starting download {} of {}", entry.id, url);
+        let id = entry.id;
+        self.spawn(entry);
+        Ok(id)
+    }
+
+    /// Pause a download, closing its connection and keeping the partial file
+    pub async fn pause(&self, id: DownloadId) -> Result<()> {
+        self.stop_transfer(id).await;
+
+        let mut entry = self.get(id)?;
+        if entry.state == DownloadState::InProgress {
+            entry.state = DownloadState::Paused;
+            self.store.put(&entry)?;
+            info!("Paused download {} at {} bytes", id, entry.received_bytes);
+        }
+        Ok(())
+    }
+
+    /// Resume a paused or interrupted download
+    ///
+    /// Downloads without an `ETag` restart from the beginning.
+    pub async fn resume(&self, id: DownloadId) -> Result<()> {
+        if self.active.lock().get(&id).is_some_and(|handle| !handle.is_finished()) {
+            return Ok(());
+        }
+
+        let mut entry = self.get(id)?;
+        match entry.state {
+            DownloadState::InProgress | DownloadState::Paused | DownloadState::Interrupted(_) => {}
+            ref state => {
+                return Err(Error::InvalidState(format!("Download {} cannot resume from {:?}", id, state)));
+            }
+        }
+
+        info!("Resuming download {} from {} bytes", id, entry.received_bytes);
+        entry.state = DownloadState::InProgress;
+        self.store.put(&entry)?;
+        self.spawn(entry);
+        Ok(())
+    }
+
+    /// Resume downloads left incomplete by the previous session
+    ///
+    /// Returns the number of downloads resumed.
+    pub async fn resume_incomplete(&self) -> Result<usize> {
+        let mut resumed = 0;
+        for entry in self.store.all()? {
+            if matches!(entry.state, DownloadState::InProgress | DownloadState::Interrupted(_)) {
+                self.resume(entry.id).await?;
+                resumed += 1;
+            }
+        }
+        Ok(resumed)
+    }
+
+    /// Cancel a download and delete its partial file
+    pub async fn cancel(&self, id: DownloadId) -> Result<()> {
+        self.stop_transfer(id).await;
+
+        let mut entry = self.get(id)?;
+        if entry.state == DownloadState::Completed {
+            return Err(Error::InvalidState(format!("Download {} is already complete", id)));
+        }
+
+        if let Err(e) = tokio::fs::remove_file(self.partial_path(id)).await {
+            debug!("No partial file to remove for download {}: {}", id, e);
+        }
+        entry.state = DownloadState::Cancelled;
+        self.store.put(&entry)
+    }
+
+    /// Get a download
+    pub fn get(&self, id: DownloadId) -> Result<DownloadEntry> {
+        self.store.get(id)?
+            .ok_or_else(|| Error::NotFound(format!("Download {} not found", id)))
+    }
+
+    /// List all downloads
+    pub fn list(&self) -> Result<Vec<DownloadEntry>> {
+        self.store.all()
+    }
+
+    /// Wait for the running transfer of a download to stop
+    pub async fn wait(&self, id: DownloadId) -> Result<DownloadEntry> {
+        let handle = self.active.lock().remove(&id);
+        if let Some(handle) = handle {
+            let _ = handle.await;
+        }
+        self.get(id)
+    }
+
+    fn spawn(&self, entry: DownloadEntry) {
+        let transfer = Transfer {
+            store: self.store.clone(),
+            transport: self.transport.clone(),
+            partial_path: self.partial_path(entry.id),
+            download_dir: self.download_dir.clone(),
+        };
+
+        let id = entry.id;
+        let handle = tokio::spawn(transfer.run(entry));
+        if let Some(previous) = self.active.lock().insert(id, handle) {
+            previous.abort();
+        }
+    }
+
+    /// Abort the running transfer of a download, if any
+    async fn stop_transfer(&self, id: DownloadId) {
+        let handle = self.active.lock().remove(&id);
+        if let Some(handle) = handle {
+            handle.abort();
+            let _ = handle.await;
+        }
+    }
+
+    fn partial_path(&self, id: DownloadId) -> PathBuf {
+        self.partial_dir.join(format!("{}.part", id))
+    }
+}
+
+impl Drop for DownloadManager {
+    fn drop(&mut self) {
+        // Transfers stopped here stay in progress and resume next session
+        for (_, handle) in self.active.lock().drain() {
+            handle.abort();
+        }
+    }
+}
+
+/// A running transfer
+struct Transfer {
+    store: DownloadStore,
+    transport: Arc<dyn DownloadTransport>,
+    partial_path: PathBuf,
+    download_dir: PathBuf,
+}
+
+impl Transfer {
+    async fn run(self, mut entry: DownloadEntry) {
+        let result = match self.receive(&mut entry).await {
+            Ok(()) => self.complete(&mut entry).await,
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            warn!("Download {} stopped: {}", entry.id, e);
+            if entry.state == DownloadState::InProgress {
+                entry.state = DownloadState::Interrupted(e.to_string());
+            }
+        }
+        if let Err(e) = self.store.put(&entry) {
+            warn!("Failed to save download {}: {}", entry.id, e);
+        }
+    }
+
+    /// Stream the response body into the partial file
+    async fn receive(&self, entry: &mut DownloadEntry) -> Result<()> {
+        let mut headers = HashMap::new();
+        let resuming = entry.received_bytes > 0 && entry.etag.is_some();
+        if let (true, Some(etag)) = (resuming, &entry.etag) {
+            headers.insert("Range".to_string(), format!("bytes={}-", entry.received_bytes));
+            headers.insert("If-Range".to_string(), etag.clone());
+        }
+
+        let mut response = self.transport.fetch(entry.tab_id, &entry.url, headers).await?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.partial_path)
+            .await?;
+
+        match response.status_code {
+            206 if resuming => {
+                debug!("Download {} resumed at byte {}", entry.id, entry.received_bytes);
+                if let Some(total) = header(&response.headers, "content-range").and_then(|range| content_range_total(&range)) {
+                    entry.total_bytes = Some(total);
+                }
+            }
+            200..=299 => {
+                // The server sent the whole file, start over
+                entry.received_bytes = 0;
+                entry.etag = header(&response.headers, "etag");
+                entry.total_bytes = header(&response.headers, "content-length").and_then(|length| length.parse().ok());
+            }
+            status => {
+                entry.state = DownloadState::Failed(format!("HTTP status {}", status));
+                return Err(Error::NetworkError(format!("Download {} failed with status {}", entry.id, status)));
+            }
+        }
+
+        // Drop bytes written after the last saved progress
+        file.set_len(entry.received_bytes).await?;
+        file.seek(SeekFrom::Start(entry.received_bytes)).await?;
+        self.store.put(entry)?;
+
+        while let Some(chunk) = response.body.recv().await {
+            let chunk = chunk?;
+            file.write_all(&chunk).await?;
+            entry.received_bytes += chunk.len() as u64;
+            self.store.put(entry)?;
+        }
+        file.sync_all().await?;
+
+        match entry.total_bytes {
+            Some(total) if entry.received_bytes < total => Err(Error::NetworkError(format!(
+                "Connection closed after {} of {} bytes", entry.received_bytes, total
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Move the partial file to the download directory
+    async fn complete(&self, entry: &mut DownloadEntry) -> Result<()> {
+        tokio::fs::create_dir_all(&self.download_dir).await?;
+        let target = unique_path(&self.download_dir, &entry.filename);
+
+        if tokio::fs::rename(&self.partial_path, &target).await.is_err() {
+            // The download directory may be on another file system
+            tokio::fs::copy(&self.partial_path, &target).await?;
+            tokio::fs::remove_file(&self.partial_path).await?;
+        }
+
+        info!("Download {} saved to {:?}", entry.id, target);
+        entry.state = DownloadState::Completed;
+        entry.target_path = Some(target);
+        Ok(())
+    }
+}
+
+/// File name of the last path segment of a URL
+fn filename_from_url(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let path = path.split_once("://").map_or(path, |(_, rest)| rest);
+
+    path.split('/')
+        .skip(1)
+        .filter(|segment| !segment.is_empty())
+        .last()
+        .map(|segment| segment.replace(['\\', ':', '*', '?', '"', '<', '>', '|'], "_"))
+        .filter(|name| name != "." && name != "..")
+        .unwrap_or_else(|| "download".to_string())
+}
+
+/// A path in `dir` that does not exist yet, numbering the file name if needed
+fn unique_path(dir: &Path, filename: &str) -> PathBuf {
+    let candidate = dir.join(filename);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let (stem, extension) = match filename.rfind('.') {
+        Some(dot) if dot > 0 => filename.split_at(dot),
+        _ => (filename, ""),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .expect("unbounded range")
+}
+
+/// Total length from a `Content-Range: bytes start-end/total` header
+fn content_range_total(range: &str) -> Option<u64> {
+    range.rsplit_once('/').and_then(|(_, total)| total.trim().parse().ok())
+}
+
+/// Look up a header case-insensitively
+fn header(headers: &HashMap<String, String>, name: &str) -> Option<String> {
+    headers.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.clone())
+}
+
+fn db_error(err: sled::Error) -> Error {
+    Error::IoError(format!("Download store error: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    const URL: &str = "https://files.example/archive/report.pdf?token=1";
+    const ETAG: &str = "\"v1\"";
+
+    /// Server whose first response is cut off after `cut_at` bytes
+    struct FlakyServer {
+        content: Vec<u8>,
+        cut_at: usize,
+        requests: Mutex<Vec<HashMap<String, String>>>,
+        /// Keeps cut-off connections open instead of resetting them
+        stall: bool,
+        stalled: Mutex<Vec<mpsc::Sender<Result<Vec<u8>>>>>,
+    }
+
+    impl FlakyServer {
+        fn new(cut_at: usize, stall: bool) -> Arc<Self> {
+            Arc::new(Self {
+                content: (0..200_000u32).map(|i| (i % 251) as u8).collect(),
+                cut_at,
+                requests: Mutex::new(Vec::new()),
+                stall,
+                stalled: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl DownloadTransport for FlakyServer {
+        async fn fetch(&self, _tab_id: TabId, _url: &str, headers: HashMap<String, String>) -> Result<DownloadResponse> {
+            let first = {
+                let mut requests = self.requests.lock();
+                requests.push(headers.clone());
+                requests.len() == 1
+            };
+
+            let start = headers.get("Range")
+                .and_then(|range| range.strip_prefix("bytes="))
+                .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok());
+            let (status_code, body_start) = match start {
+                Some(start) if headers.get("If-Range").map(String::as_str) == Some(ETAG) => (206, start),
+                _ => (200, 0),
+            };
+
+            let mut response_headers = HashMap::from([("ETag".to_string(), ETAG.to_string())]);
+            let total = self.content.len();
+            if status_code == 206 {
+                response_headers.insert("Content-Range".to_string(), format!("bytes {}-{}/{}", body_start, total - 1, total));
+            } else {
+                response_headers.insert("Content-Length".to_string(), total.to_string());
+            }
+
+            let end = if first { self.cut_at } else { total };
+            let (sender, body) = mpsc::channel(64);
+            for chunk in self.content[body_start..end].chunks(10_000) {
+                sender.send(Ok(chunk.to_vec())).await.unwrap();
+            }
+            if first && self.stall {
+                self.stalled.lock().push(sender);
+            } else if first {
+                sender.send(Err(Error::NetworkError("Connection reset".to_string()))).await.unwrap();
+            }
+
+            Ok(DownloadResponse { status_code, headers: response_headers, body })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_download_resumes_after_restart() {
+        let data = TempDir::new().unwrap();
+        let downloads = TempDir::new().unwrap();
+        let server = FlakyServer::new(75_000, false);
+
+        let id = {
+            let manager = DownloadManager::new(data.path(), downloads.path().to_path_buf(), server.clone()).unwrap();
+            let id = manager.start(TabId::new(1), URL).await.unwrap();
+            let entry = manager.wait(id).await.unwrap();
+            assert!(matches!(entry.state, DownloadState::Interrupted(_)));
+            assert_eq!(entry.received_bytes, 75_000);
+            assert_eq!(entry.total_bytes, Some(200_000));
+            assert_eq!(entry.etag.as_deref(), Some(ETAG));
+            id
+        };
+
+        // A new session picks the download up from the saved offset
+        let manager = DownloadManager::new(data.path(), downloads.path().to_path_buf(), server.clone()).unwrap();
+        assert_eq!(manager.resume_incomplete().await.unwrap(), 1);
+        let entry = manager.wait(id).await.unwrap();
+
+        let requests = server.requests.lock().clone();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].get("Range").map(String::as_str), Some("bytes=75000-"));
+        assert_eq!(requests[1].get("If-Range").map(String::as_str), Some(ETAG));
+
+        assert_eq!(entry.state, DownloadState::Completed);
+        assert_eq!(entry.received_bytes, 200_000);
+        let target = entry.target_path.unwrap();
+        assert_eq!(target, downloads.path().join("report.pdf"));
+        assert_eq!(std::fs::read(target).unwrap(), server.content);
+        assert!(!data.path().join(PARTIAL_DIRECTORY).join(format!("{}.part", id)).exists());
+    }
+
+    #[tokio::test]
+    async fn test_pause_keeps_partial_file() {
+        let data = TempDir::new().unwrap();
+        let downloads = TempDir::new().unwrap();
+        let server = FlakyServer::new(30_000, true);
+        let manager = DownloadManager::new(data.path(), downloads.path().to_path_buf(), server.clone()).unwrap();
+
+        let id = manager.start(TabId::new(1), URL).await.unwrap();
+        for _ in 0..100 {
+            if manager.get(id).unwrap().received_bytes == 30_000 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        manager.pause(id).await.unwrap();
+        let entry = manager.get(id).unwrap();
+        assert_eq!(entry.state, DownloadState::Paused);
+        let partial = data.path().join(PARTIAL_DIRECTORY).join(format!("{}.part", id));
+        assert_eq!(std::fs::read(&partial).unwrap(), server.content[..30_000]);
+
+        manager.resume(id).await.unwrap();
+        assert_eq!(manager.wait(id).await.unwrap().state, DownloadState::Completed);
+
+        assert!(manager.cancel(id).await.is_err());
+        assert_eq!(filename_from_url("https://files.example/"), "download");
+        assert_eq!(unique_path(downloads.path(), "report.pdf"), downloads.path().join("report (1).pdf"));
+    }
+}
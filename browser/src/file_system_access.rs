@@ -0,0 +1,496 @@
+//! File System Access API for the Matte browser
+//!
+//! Pages get handles to local files through the native file pickers, shown
+//! with `rfd`. Reading a file needs the origin's `file-system-read`
+//! permission and writing it `file-system-write`. Handles can be persisted
+//! per origin in web storage and used again after a restart.
+
+use common::{
+    error::{Error, Result},
+    ipc::{FileHandleInfo, FileSystemRequest, FileSystemResponse, IpcMessage},
+    Permission, PermissionState, TabId,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+use crate::permissions::PermissionsManager;
+
+/// Suffix of the file a write goes to before it replaces the picked file
+const SWAP_SUFFIX: &str = ".matte-swap";
+
+/// File type offered by a picker, as in the `types` option
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilePickerAcceptType {
+    /// Description of the file type
+    pub description: String,
+
+    /// Accepted extensions, such as `.txt`, by MIME type
+    pub accept: HashMap<String, Vec<String>>,
+}
+
+impl FilePickerAcceptType {
+    /// Accepted extensions without their leading dot
+    fn extensions(&self) -> impl Iterator<Item = &str> {
+        self.accept.values().flatten().map(|extension| extension.trim_start_matches('.'))
+    }
+}
+
+/// Options of a `showOpenFilePicker` call
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OpenFilePickerOptions {
+    /// Allow picking several files
+    pub multiple: bool,
+
+    /// File types offered
+    pub types: Vec<FilePickerAcceptType>,
+
+    /// Leave out the choice accepting any file
+    pub exclude_accept_all_option: bool,
+}
+
+/// Options of a `showSaveFilePicker` call
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SaveFilePickerOptions {
+    /// File name the picker starts with
+    pub suggested_name: Option<String>,
+
+    /// File types offered
+    pub types: Vec<FilePickerAcceptType>,
+
+    /// Leave out the choice accepting any file
+    pub exclude_accept_all_option: bool,
+}
+
+/// Native file picker
+///
+/// Pickers block until the user closes them. A dismissed picker returns no
+/// path.
+pub trait FilePickerDialog: Send + Sync {
+    /// Let the user pick files to open
+    fn pick_open(&self, options: &OpenFilePickerOptions) -> Result<Vec<PathBuf>>;
+
+    /// Let the user pick a file to save to
+    fn pick_save(&self, options: &SaveFilePickerOptions) -> Result<Option<PathBuf>>;
+}
+
+/// Picker returning preset paths, for tests and headless sessions
+#[derive(Default)]
+pub struct MockFilePickerDialog {
+    /// Paths returned by `pick_open`
+    open_paths: Mutex<Vec<PathBuf>>,
+
+    /// Path returned by `pick_save`
+    save_path: Mutex<Option<PathBuf>>,
+}
+
+impl MockFilePickerDialog {
+    /// Create a picker returning some paths
+    pub fn new(open_paths: Vec<PathBuf>, save_path: Option<PathBuf>) -> Self {
+        Self {
+            open_paths: Mutex::new(open_paths),
+            save_path: Mutex::new(save_path),
+        }
+    }
+
+    /// Change the paths returned by `pick_open`
+    pub fn set_open_paths(&self, paths: Vec<PathBuf>) {
+        *self.open_paths.lock() = paths;
+    }
+
+    /// Change the path returned by `pick_save`
+    pub fn set_save_path(&self, path: Option<PathBuf>) {
+        *self.save_path.lock() = path;
+    }
+}
+
+impl FilePickerDialog for MockFilePickerDialog {
+    fn pick_open(&self, options: &OpenFilePickerOptions) -> Result<Vec<PathBuf>> {
+        let mut paths = self.open_paths.lock().clone();
+        if !options.multiple {
+            paths.truncate(1);
+        }
+        Ok(paths)
+    }
+
+    fn pick_save(&self, _options: &SaveFilePickerOptions) -> Result<Option<PathBuf>> {
+        Ok(self.save_path.lock().clone())
+    }
+}
+
+/// Handle to a file the user picked for an origin
+///
+/// The path never reaches the page, which only sees the file name.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FileHandle {
+    /// Origin the file was picked for
+    origin: String,
+
+    /// Path of the file
+    path: PathBuf,
+
+    /// Permissions manager gating reads and writes, bound again when a
+    /// persisted handle is restored
+    #[serde(skip)]
+    permissions: Option<Arc<PermissionsManager>>,
+}
+
+impl fmt::Debug for FileHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileHandle")
+            .field("origin", &self.origin)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl FileHandle {
+    /// Get the origin the file was picked for
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// Get the file name
+    pub fn name(&self) -> String {
+        self.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+    }
+
+    /// Get the path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read the whole file
+    pub fn read(&self) -> Result<Vec<u8>> {
+        self.check_permission(Permission::FileSystemRead)?;
+        Ok(std::fs::read(&self.path)?)
+    }
+
+    /// Replace the contents of the file
+    ///
+    /// The data goes to a swap file that then replaces the file, so a failed
+    /// write leaves the old contents in place.
+    pub fn write(&self, data: &[u8]) -> Result<()> {
+        self.check_permission(Permission::FileSystemWrite)?;
+
+        let mut swap_path = self.path.clone().into_os_string();
+        swap_path.push(SWAP_SUFFIX);
+        let swap_path = PathBuf::from(swap_path);
+
+        let result = std::fs::write(&swap_path, data).and_then(|_| std::fs::rename(&swap_path, &self.path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&swap_path);
+        }
+        Ok(result?)
+    }
+
+    /// Check the origin may use the file, write access including read access
+    fn check_permission(&self, permission: Permission) -> Result<()> {
+        let permissions = self.permissions.as_ref().ok_or_else(|| {
+            Error::InvalidState(format!("File handle for {} is not bound to a permissions manager", self.origin))
+        })?;
+
+        let granted = |permission: &Permission| permissions.query(&self.origin, permission) == PermissionState::Granted;
+        let allowed = granted(&permission)
+            || (permission == Permission::FileSystemRead && granted(&Permission::FileSystemWrite));
+        if !allowed {
+            return Err(Error::PermissionDenied(format!("{} is not allowed for {}", permission, self.origin)));
+        }
+        Ok(())
+    }
+}
+
+/// File System Access manager
+pub struct FileSystemManager {
+    /// Permissions manager gating file access
+    permissions: Arc<PermissionsManager>,
+
+    /// Picker shown to the user
+    dialog: Arc<dyn FilePickerDialog>,
+
+    /// Handles given to renderers, with the tab each belongs to, by ID
+    renderer_handles: Mutex<HashMap<u64, (TabId, FileHandle)>>,
+
+    /// ID of the next handle given to a renderer
+    next_handle_id: AtomicU64,
+}
+
+impl FileSystemManager {
+    /// Create a manager showing the platform file pickers
+    pub fn new(permissions: Arc<PermissionsManager>) -> Self {
+        Self::with_dialog(permissions, platform_dialog())
+    }
+
+    /// Create a manager with a specific file picker
+    pub fn with_dialog(permissions: Arc<PermissionsManager>, dialog: Arc<dyn FilePickerDialog>) -> Self {
+        info!("Initializing file system access manager");
+        Self {
+            permissions,
+            dialog,
+            renderer_handles: Mutex::new(HashMap::new()),
+            next_handle_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Let the user pick files to open for a tab, as `showOpenFilePicker`
+    ///
+    /// Dismissing the picker is an error, like the `AbortError` pages get.
+    pub async fn show_open_file_picker(&self, tab_id: TabId, options: OpenFilePickerOptions) -> Result<Vec<FileHandle>> {
+        let origin = self.require(tab_id, Permission::FileSystemRead).await?;
+
+        let dialog = self.dialog.clone();
+        let paths = tokio::task::spawn_blocking(move || dialog.pick_open(&options))
+            .await
+            .map_err(|e| Error::PlatformError(format!("File picker failed: {}", e)))??;
+        if paths.is_empty() {
+            return Err(Error::InvalidState("The file picker was dismissed".to_string()));
+        }
+
+        debug!("Tab {} picked {} files to open", tab_id, paths.len());
+        Ok(paths.into_iter().map(|path| self.bind(&origin, path)).collect())
+    }
+
+    /// Let the user pick a file to save to for a tab, as `showSaveFilePicker`
+    ///
+    /// Dismissing the picker is an error, like the `AbortError` pages get.
+    pub async fn show_save_file_picker(&self, tab_id: TabId, options: SaveFilePickerOptions) -> Result<FileHandle> {
+        let origin = self.require(tab_id, Permission::FileSystemWrite).await?;
+
+        let dialog = self.dialog.clone();
+        let path = tokio::task::spawn_blocking(move || dialog.pick_save(&options))
+            .await
+            .map_err(|e| Error::PlatformError(format!("File picker failed: {}", e)))??
+            .ok_or_else(|| Error::InvalidState("The file picker was dismissed".to_string()))?;
+
+        debug!("Tab {} picked {} to save to", tab_id, path.display());
+        Ok(self.bind(&origin, path))
+    }
+
+    /// Ask the user again for access to a handle, as `requestPermission`
+    pub async fn request_permission(&self, tab_id: TabId, handle: &FileHandle, writable: bool) -> Result<PermissionState> {
+        let origin = self.permissions.tab_origin(tab_id)?;
+        if origin != handle.origin {
+            return Err(Error::SecurityError(format!("File handle of {} used by {}", handle.origin, origin)));
+        }
+
+        let permission = if writable { Permission::FileSystemWrite } else { Permission::FileSystemRead };
+        self.permissions.request(tab_id, &origin, permission).await
+    }
+
+    /// Persist a handle under a key in its origin's storage
+    pub async fn persist_handle(&self, web_storage: &::storage::WebStorageManager, key: &str, handle: &FileHandle) -> Result<()> {
+        let serialized = serde_json::to_string(handle)?;
+        web_storage.set_persistent_handle(&handle.origin, key, &serialized)
+            .await
+            .map_err(|e| Error::IoError(format!("Failed to persist file handle: {}", e)))
+    }
+
+    /// Restore a handle persisted for an origin, bound to this manager's permissions
+    pub async fn restore_handle(&self, web_storage: &::storage::WebStorageManager, origin: &str, key: &str) -> Result<Option<FileHandle>> {
+        let serialized = web_storage.get_persistent_handle(origin, key)
+            .await
+            .map_err(|e| Error::IoError(format!("Failed to restore file handle: {}", e)))?;
+        let Some(serialized) = serialized else {
+            return Ok(None);
+        };
+
+        let handle: FileHandle = serde_json::from_str(&serialized)?;
+        if handle.origin != origin {
+            return Err(Error::SecurityError(format!("File handle of {} stored for {}", handle.origin, origin)));
+        }
+        Ok(Some(self.bind(origin, handle.path)))
+    }
+
+    /// Answer a File System Access request from a renderer process
+    ///
+    /// Renderers refer to picked files by handle ID and never see their
+    /// paths. Returns `None` for messages that are not file system requests.
+    pub async fn handle_message(&self, message: IpcMessage) -> Option<IpcMessage> {
+        let IpcMessage::FileSystemRequest(request) = message else {
+            return None;
+        };
+
+        let mut response = FileSystemResponse { request_id: request.request_id(), handles: Vec::new(), data: None, error: None };
+        if let Err(e) = self.answer(request, &mut response).await {
+            response.error = Some(e.to_string());
+        }
+        Some(IpcMessage::FileSystemResponse(response))
+    }
+
+    /// Forget the handles given to the renderer of a closed tab
+    pub fn remove_tab(&self, tab_id: TabId) {
+        self.renderer_handles.lock().retain(|_, (handle_tab, _)| *handle_tab != tab_id);
+    }
+
+    async fn answer(&self, request: FileSystemRequest, response: &mut FileSystemResponse) -> Result<()> {
+        match request {
+            FileSystemRequest::ShowOpenFilePicker { tab_id, options, .. } => {
+                let handles = self.show_open_file_picker(tab_id, serde_json::from_value(options)?).await?;
+                response.handles = handles.into_iter().map(|handle| self.give_to_renderer(tab_id, handle)).collect();
+            }
+            FileSystemRequest::ShowSaveFilePicker { tab_id, options, .. } => {
+                let handle = self.show_save_file_picker(tab_id, serde_json::from_value(options)?).await?;
+                response.handles = vec![self.give_to_renderer(tab_id, handle)];
+            }
+            FileSystemRequest::ReadFile { tab_id, handle_id, .. } => {
+                let handle = self.renderer_handle(tab_id, handle_id)?;
+                response.data = Some(tokio::task::spawn_blocking(move || handle.read())
+                    .await
+                    .map_err(|e| Error::IoError(format!("File read failed: {}", e)))??);
+            }
+            FileSystemRequest::WriteFile { tab_id, handle_id, data, .. } => {
+                let handle = self.renderer_handle(tab_id, handle_id)?;
+                tokio::task::spawn_blocking(move || handle.write(&data))
+                    .await
+                    .map_err(|e| Error::IoError(format!("File write failed: {}", e)))??;
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep a handle for the renderer of a tab, returning what the renderer sees of it
+    fn give_to_renderer(&self, tab_id: TabId, handle: FileHandle) -> FileHandleInfo {
+        let handle_id = self.next_handle_id.fetch_add(1, Ordering::Relaxed);
+        let info = FileHandleInfo { handle_id, name: handle.name() };
+        self.renderer_handles.lock().insert(handle_id, (tab_id, handle));
+        info
+    }
+
+    /// Get a handle given to the renderer of a tab
+    fn renderer_handle(&self, tab_id: TabId, handle_id: u64) -> Result<FileHandle> {
+        match self.renderer_handles.lock().get(&handle_id) {
+            Some((handle_tab, handle)) if *handle_tab == tab_id => Ok(handle.clone()),
+            _ => Err(Error::NotFound(format!("Tab {} has no file handle {}", tab_id, handle_id))),
+        }
+    }
+
+    /// Request a permission for the document of a tab, returning its origin
+    async fn require(&self, tab_id: TabId, permission: Permission) -> Result<String> {
+        let origin = self.permissions.tab_origin(tab_id)?;
+        let state = self.permissions.request(tab_id, &origin, permission.clone()).await?;
+        if state != PermissionState::Granted {
+            return Err(Error::PermissionDenied(format!("{} is not allowed for {}", permission, origin)));
+        }
+        Ok(origin)
+    }
+
+    fn bind(&self, origin: &str, path: PathBuf) -> FileHandle {
+        FileHandle {
+            origin: origin.to_string(),
+            path,
+            permissions: Some(self.permissions.clone()),
+        }
+    }
+}
+
+/// File picker of the current platform
+pub fn platform_dialog() -> Arc<dyn FilePickerDialog> {
+    Arc::new(NativeFileDialog)
+}
+
+/// Native file picker shown through `rfd`
+pub struct NativeFileDialog;
+
+impl NativeFileDialog {
+    /// Dialog offering some file types
+    fn dialog(types: &[FilePickerAcceptType], exclude_accept_all: bool) -> rfd::FileDialog {
+        let mut dialog = rfd::FileDialog::new();
+        for accept_type in types {
+            let extensions: Vec<&str> = accept_type.extensions().collect();
+            dialog = dialog.add_filter(&accept_type.description, &extensions);
+        }
+        if !types.is_empty() && !exclude_accept_all {
+            dialog = dialog.add_filter("All files", &["*"]);
+        }
+        dialog
+    }
+}
+
+impl FilePickerDialog for NativeFileDialog {
+    fn pick_open(&self, options: &OpenFilePickerOptions) -> Result<Vec<PathBuf>> {
+        let dialog = Self::dialog(&options.types, options.exclude_accept_all_option).set_title("Open File");
+        let paths = if options.multiple {
+            dialog.pick_files()
+        } else {
+            dialog.pick_file().map(|path| vec![path])
+        };
+        Ok(paths.unwrap_or_default())
+    }
+
+    fn pick_save(&self, options: &SaveFilePickerOptions) -> Result<Option<PathBuf>> {
+        let mut dialog = Self::dialog(&options.types, options.exclude_accept_all_option).set_title("Save File");
+        if let Some(name) = &options.suggested_name {
+            dialog = dialog.set_file_name(name);
+        }
+        Ok(dialog.save_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: &str = "https://editor.example";
+
+    fn manager(dialog: Arc<MockFilePickerDialog>) -> (FileSystemManager, Arc<PermissionsManager>) {
+        let permissions = Arc::new(PermissionsManager::in_memory().unwrap());
+        permissions.set_tab_origin(TabId::new(1), ORIGIN);
+        (FileSystemManager::with_dialog(permissions.clone(), dialog), permissions)
+    }
+
+    #[tokio::test]
+    async fn test_picked_file_read_write_round_trip() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("notes.txt");
+        std::fs::write(&path, b"first draft").unwrap();
+
+        let dialog = Arc::new(MockFilePickerDialog::new(vec![path.clone()], Some(path.clone())));
+        let (manager, permissions) = manager(dialog);
+        let tab = TabId::new(1);
+
+        // Without a prompt the permission request is denied
+        assert!(matches!(
+            manager.show_open_file_picker(tab, OpenFilePickerOptions::default()).await,
+            Err(Error::PermissionDenied(_))
+        ));
+
+        permissions.set_permission(ORIGIN, Permission::FileSystemRead, PermissionState::Granted).unwrap();
+        let handles = manager.show_open_file_picker(tab, OpenFilePickerOptions::default()).await.unwrap();
+        assert_eq!(handles.len(), 1);
+        assert_eq!(handles[0].name(), "notes.txt");
+        assert_eq!(handles[0].read().unwrap(), b"first draft");
+        assert!(matches!(handles[0].write(b"edited"), Err(Error::PermissionDenied(_))));
+
+        permissions.set_permission(ORIGIN, Permission::FileSystemWrite, PermissionState::Granted).unwrap();
+        let handle = manager.show_save_file_picker(tab, SaveFilePickerOptions::default()).await.unwrap();
+        handle.write("second draft ✍".as_bytes()).unwrap();
+        assert_eq!(handles[0].read().unwrap(), "second draft ✍".as_bytes());
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_persisted_handle_is_restored_for_its_origin() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("report.csv");
+        std::fs::write(&path, b"a,b\n1,2\n").unwrap();
+
+        let (manager, permissions) = manager(Arc::new(MockFilePickerDialog::new(vec![path], None)));
+        permissions.set_permission(ORIGIN, Permission::FileSystemRead, PermissionState::Granted).unwrap();
+        let handle = manager.show_open_file_picker(TabId::new(1), OpenFilePickerOptions::default()).await.unwrap().remove(0);
+
+        let web_storage = ::storage::WebStorageManager::new(directory.path().join("storage")).unwrap();
+        manager.persist_handle(&web_storage, "recent", &handle).await.unwrap();
+
+        let restored = manager.restore_handle(&web_storage, ORIGIN, "recent").await.unwrap().unwrap();
+        assert_eq!(restored.path(), handle.path());
+        assert_eq!(restored.read().unwrap(), b"a,b\n1,2\n");
+        assert!(manager.restore_handle(&web_storage, "https://other.example", "recent").await.unwrap().is_none());
+    }
+}
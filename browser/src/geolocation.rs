@@ -0,0 +1,558 @@
+//! Geolocation API for the Matte browser
+//!
+//! Positions come from the platform location service: GeoClue2 over D-Bus on
+//! Linux, CoreLocation on macOS and `Windows.Devices.Geolocation` on Windows.
+//! Every request is gated on the tab's `geolocation` permission.
+
+use common::{
+    error::{Error, Result},
+    ipc::{GeolocationError, GeolocationResponseMessage, IpcMessage},
+    Permission, PermissionState, TabId,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info};
+
+use crate::permissions::PermissionsManager;
+
+/// Options of a position request, as passed to `getCurrentPosition`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionOptions {
+    /// Ask the platform for its most accurate fix
+    pub enable_high_accuracy: bool,
+
+    /// Time allowed to acquire a position, `None` waits forever
+    pub timeout: Option<Duration>,
+
+    /// Maximum age of a cached position that may be returned
+    pub maximum_age: Duration,
+}
+
+impl Default for PositionOptions {
+    fn default() -> Self {
+        Self {
+            enable_high_accuracy: false,
+            timeout: None,
+            maximum_age: Duration::ZERO,
+        }
+    }
+}
+
+/// Geographic coordinates of a position
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Coords {
+    /// Latitude in decimal degrees
+    pub latitude: f64,
+
+    /// Longitude in decimal degrees
+    pub longitude: f64,
+
+    /// Accuracy of the latitude and longitude in meters
+    pub accuracy: f64,
+
+    /// Height above the WGS84 ellipsoid in meters
+    pub altitude: Option<f64>,
+
+    /// Accuracy of the altitude in meters
+    pub altitude_accuracy: Option<f64>,
+
+    /// Direction of travel in degrees clockwise from true north
+    pub heading: Option<f64>,
+
+    /// Ground speed in meters per second
+    pub speed: Option<f64>,
+}
+
+/// A position acquired from the location service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeolocationPosition {
+    /// Coordinates of the position
+    pub coords: Coords,
+
+    /// Time the position was acquired, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl GeolocationPosition {
+    /// Position acquired now
+    pub fn new(coords: Coords) -> Self {
+        Self {
+            coords,
+            timestamp: epoch_millis(SystemTime::now()),
+        }
+    }
+}
+
+/// Source of positions
+///
+/// Backends block until a position is available, the platform service fails
+/// or the timeout elapses. They are run on the blocking thread pool.
+pub trait GeolocationBackend: Send + Sync {
+    /// Acquire the current position
+    fn current_position(&self, enable_high_accuracy: bool, timeout: Option<Duration>) -> Result<GeolocationPosition>;
+}
+
+/// Backend returning a fixed position, for tests
+pub struct MockGeolocationBackend {
+    /// Position returned, `None` when the service is unavailable
+    position: Mutex<Option<Coords>>,
+
+    /// Number of positions requested
+    requests: AtomicUsize,
+}
+
+impl MockGeolocationBackend {
+    /// Create a mock backend at a position
+    pub fn new(coords: Coords) -> Self {
+        Self {
+            position: Mutex::new(Some(coords)),
+            requests: AtomicUsize::new(0),
+        }
+    }
+
+    /// Create a mock backend with no position available
+    pub fn unavailable() -> Self {
+        Self {
+            position: Mutex::new(None),
+            requests: AtomicUsize::new(0),
+        }
+    }
+
+    /// Move the mock position
+    pub fn set_position(&self, coords: Option<Coords>) {
+        *self.position.lock() = coords;
+    }
+
+    /// Number of positions requested from the backend
+    pub fn request_count(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+impl GeolocationBackend for MockGeolocationBackend {
+    fn current_position(&self, _enable_high_accuracy: bool, _timeout: Option<Duration>) -> Result<GeolocationPosition> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        self.position.lock()
+            .clone()
+            .map(GeolocationPosition::new)
+            .ok_or_else(|| Error::PlatformError("Position unavailable".to_string()))
+    }
+}
+
+/// Geolocation manager
+pub struct GeolocationManager {
+    /// Permissions manager gating every request
+    permissions: Arc<PermissionsManager>,
+
+    /// Location service
+    backend: Arc<dyn GeolocationBackend>,
+
+    /// Last position acquired, with the time it was acquired
+    last_position: Mutex<Option<(GeolocationPosition, Instant)>>,
+}
+
+impl GeolocationManager {
+    /// Create a geolocation manager backed by the platform location service
+    pub fn new(permissions: Arc<PermissionsManager>) -> Self {
+        Self::with_backend(permissions, platform_backend())
+    }
+
+    /// Create a geolocation manager with a specific backend
+    pub fn with_backend(permissions: Arc<PermissionsManager>, backend: Arc<dyn GeolocationBackend>) -> Self {
+        info!("Initializing geolocation manager");
+
+        Self {
+            permissions,
+            backend,
+            last_position: Mutex::new(None),
+        }
+    }
+
+    /// Get the current position for a tab
+    pub async fn get_current_position(&self, tab_id: TabId, options: PositionOptions) -> Result<GeolocationPosition> {
+        let origin = self.permissions.tab_origin(tab_id)?;
+        let state = self.permissions.request(tab_id, &origin, Permission::Geolocation).await?;
+        if state != PermissionState::Granted {
+            return Err(Error::PermissionDenied(format!("Geolocation is not allowed for {}", origin)));
+        }
+
+        if let Some(position) = self.cached_position(options.maximum_age) {
+            debug!("Returning cached position for tab {}", tab_id);
+            return Ok(position);
+        }
+
+        let backend = self.backend.clone();
+        let enable_high_accuracy = options.enable_high_accuracy;
+        let timeout = options.timeout;
+        let lookup = tokio::task::spawn_blocking(move || backend.current_position(enable_high_accuracy, timeout));
+
+        let result = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, lookup).await
+                .map_err(|_| Error::Timeout(format!("No position within {:?}", timeout)))?,
+            None => lookup.await,
+        };
+        let position = result.map_err(|e| Error::PlatformError(format!("Location lookup failed: {}", e)))??;
+
+        *self.last_position.lock() = Some((position.clone(), Instant::now()));
+        Ok(position)
+    }
+
+    /// Answer a position request from a renderer
+    ///
+    /// Returns `None` for messages that are not position requests.
+    pub async fn handle_message(&self, message: IpcMessage) -> Option<IpcMessage> {
+        let IpcMessage::GeolocationRequest(request) = message else {
+            return None;
+        };
+
+        let options = PositionOptions {
+            enable_high_accuracy: request.enable_high_accuracy,
+            timeout: request.timeout.map(Duration::from_millis),
+            maximum_age: Duration::from_millis(request.maximum_age),
+        };
+        let result = self.get_current_position(request.tab_id, options).await
+            .and_then(|position| serde_json::to_value(position).map_err(Error::from));
+
+        Some(IpcMessage::GeolocationResponse(match result {
+            Ok(position) => GeolocationResponseMessage { request_id: request.request_id, position: Some(position), error: None },
+            Err(e) => {
+                let code = match e {
+                    Error::PermissionDenied(_) => GeolocationError::PERMISSION_DENIED,
+                    Error::Timeout(_) => GeolocationError::TIMEOUT,
+                    _ => GeolocationError::POSITION_UNAVAILABLE,
+                };
+                let error = GeolocationError { code, message: e.to_string() };
+                GeolocationResponseMessage { request_id: request.request_id, position: None, error: Some(error) }
+            }
+        }))
+    }
+
+    /// Last position if it is no older than `maximum_age`
+    fn cached_position(&self, maximum_age: Duration) -> Option<GeolocationPosition> {
+        if maximum_age.is_zero() {
+            return None;
+        }
+
+        self.last_position.lock()
+            .as_ref()
+            .filter(|(_, acquired)| acquired.elapsed() <= maximum_age)
+            .map(|(position, _)| position.clone())
+    }
+}
+
+/// Location service of the current platform
+pub fn platform_backend() -> Arc<dyn GeolocationBackend> {
+    #[cfg(target_os = "linux")]
+    {
+        Arc::new(linux::GeoClueBackend)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Arc::new(macos::CoreLocationBackend)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        Arc::new(winrt::WinRtBackend)
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        Arc::new(MockGeolocationBackend::unavailable())
+    }
+}
+
+fn epoch_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// Interval at which backends without change notifications poll for a fix
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn deadline(timeout: Option<Duration>) -> Option<Instant> {
+    timeout.and_then(|timeout| Instant::now().checked_add(timeout))
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::*;
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::OwnedObjectPath;
+
+    const SERVICE: &str = "org.freedesktop.GeoClue2";
+    const DESKTOP_ID: &str = "matte-browser";
+
+    /// `GCLUE_ACCURACY_LEVEL_CITY`
+    const ACCURACY_CITY: u32 = 4;
+
+    /// `GCLUE_ACCURACY_LEVEL_EXACT`
+    const ACCURACY_EXACT: u32 = 8;
+
+    /// GeoClue2 location service
+    pub struct GeoClueBackend;
+
+    impl GeolocationBackend for GeoClueBackend {
+        fn current_position(&self, enable_high_accuracy: bool, timeout: Option<Duration>) -> Result<GeolocationPosition> {
+            let deadline = deadline(timeout);
+            let connection = Connection::system().map_err(dbus_error)?;
+
+            let manager = Proxy::new(
+                &connection,
+                SERVICE,
+                "/org/freedesktop/GeoClue2/Manager",
+                "org.freedesktop.GeoClue2.Manager",
+            ).map_err(dbus_error)?;
+            let client_path: OwnedObjectPath = manager.call("GetClient", &()).map_err(dbus_error)?;
+            let client = Proxy::new(&connection, SERVICE, client_path.as_str(), "org.freedesktop.GeoClue2.Client")
+                .map_err(dbus_error)?;
+
+            let accuracy = if enable_high_accuracy { ACCURACY_EXACT } else { ACCURACY_CITY };
+            client.set_property("DesktopId", DESKTOP_ID).map_err(dbus_error)?;
+            client.set_property("RequestedAccuracyLevel", accuracy).map_err(dbus_error)?;
+            client.call::<_, _, ()>("Start", &()).map_err(dbus_error)?;
+
+            let result = wait_for_location(&connection, &client, deadline);
+            let _ = client.call::<_, _, ()>("Stop", &());
+            result
+        }
+    }
+
+    /// Poll the client until GeoClue publishes a location
+    fn wait_for_location(connection: &Connection, client: &Proxy<'_>, deadline: Option<Instant>) -> Result<GeolocationPosition> {
+        loop {
+            let location_path: OwnedObjectPath = client.get_property("Location").map_err(dbus_error)?;
+            if location_path.as_str() != "/" {
+                return read_location(connection, location_path.as_str());
+            }
+
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(Error::Timeout("GeoClue did not report a location".to_string()));
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn read_location(connection: &Connection, path: &str) -> Result<GeolocationPosition> {
+        let location = Proxy::new(connection, SERVICE, path, "org.freedesktop.GeoClue2.Location")
+            .map_err(dbus_error)?;
+        let property = |name: &str| location.get_property::<f64>(name).map_err(dbus_error);
+
+        // GeoClue reports unknown values as -DBL_MAX for the altitude and
+        // as negative numbers for speed and heading
+        let altitude = property("Altitude")?;
+        let speed = property("Speed")?;
+        let heading = property("Heading")?;
+        let (seconds, micros): (u64, u64) = location.get_property("Timestamp").map_err(dbus_error)?;
+
+        Ok(GeolocationPosition {
+            coords: Coords {
+                latitude: property("Latitude")?,
+                longitude: property("Longitude")?,
+                accuracy: property("Accuracy")?,
+                altitude: (altitude > -f64::MAX).then_some(altitude),
+                altitude_accuracy: None,
+                heading: (heading >= 0.0).then_some(heading),
+                speed: (speed >= 0.0).then_some(speed),
+            },
+            timestamp: seconds * 1000 + micros / 1000,
+        })
+    }
+
+    fn dbus_error(error: impl std::fmt::Display) -> Error {
+        Error::PlatformError(format!("GeoClue error: {}", error))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::*;
+    use objc2_core_location::{
+        kCLLocationAccuracyBest, kCLLocationAccuracyHundredMeters, CLLocation, CLLocationManager,
+    };
+    use objc2_foundation::{NSDate, NSRunLoop};
+
+    /// CoreLocation location service
+    pub struct CoreLocationBackend;
+
+    impl GeolocationBackend for CoreLocationBackend {
+        fn current_position(&self, enable_high_accuracy: bool, timeout: Option<Duration>) -> Result<GeolocationPosition> {
+            let deadline = deadline(timeout);
+
+            // SAFETY: the manager is created, used and released on this
+            // thread, whose run loop receives its updates
+            unsafe {
+                let manager = CLLocationManager::new();
+                manager.setDesiredAccuracy(if enable_high_accuracy {
+                    kCLLocationAccuracyBest
+                } else {
+                    kCLLocationAccuracyHundredMeters
+                });
+                manager.requestWhenInUseAuthorization();
+                manager.startUpdatingLocation();
+
+                let result = loop {
+                    if let Some(location) = manager.location() {
+                        break Ok(position_from(&location));
+                    }
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        break Err(Error::Timeout("CoreLocation did not report a location".to_string()));
+                    }
+                    let limit = NSDate::dateWithTimeIntervalSinceNow(POLL_INTERVAL.as_secs_f64());
+                    NSRunLoop::currentRunLoop().runUntilDate(&limit);
+                };
+
+                manager.stopUpdatingLocation();
+                result
+            }
+        }
+    }
+
+    /// Convert a CoreLocation fix, where negative accuracies and speeds mark
+    /// invalid values
+    unsafe fn position_from(location: &CLLocation) -> GeolocationPosition {
+        let coordinate = location.coordinate();
+        let vertical_accuracy = location.verticalAccuracy();
+        let course = location.course();
+        let speed = location.speed();
+
+        GeolocationPosition {
+            coords: Coords {
+                latitude: coordinate.latitude,
+                longitude: coordinate.longitude,
+                accuracy: location.horizontalAccuracy(),
+                altitude: (vertical_accuracy >= 0.0).then(|| location.altitude()),
+                altitude_accuracy: (vertical_accuracy >= 0.0).then_some(vertical_accuracy),
+                heading: (course >= 0.0).then_some(course),
+                speed: (speed >= 0.0).then_some(speed),
+            },
+            timestamp: (location.timestamp().timeIntervalSince1970() * 1000.0) as u64,
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod winrt {
+    use super::*;
+    use ::windows::Devices::Geolocation::{Geolocator, PositionAccuracy};
+    use ::windows::Foundation::{IReference, TimeSpan};
+
+    /// `DateTime` ticks between 1601-01-01 and the Unix epoch
+    const UNIX_EPOCH_TICKS: i64 = 116_444_736_000_000_000;
+
+    /// `Windows.Devices.Geolocation` location service
+    pub struct WinRtBackend;
+
+    impl GeolocationBackend for WinRtBackend {
+        fn current_position(&self, enable_high_accuracy: bool, timeout: Option<Duration>) -> Result<GeolocationPosition> {
+            let locator = Geolocator::new().map_err(winrt_error)?;
+            locator.SetDesiredAccuracy(if enable_high_accuracy {
+                PositionAccuracy::High
+            } else {
+                PositionAccuracy::Default
+            }).map_err(winrt_error)?;
+
+            // TimeSpan counts 100ns ticks
+            let timeout = TimeSpan {
+                Duration: timeout.map_or(i64::MAX, |timeout| i64::try_from(timeout.as_nanos() / 100).unwrap_or(i64::MAX)),
+            };
+            let position = locator.GetGeopositionAsyncWithAgeAndTimeout(TimeSpan { Duration: 0 }, timeout)
+                .and_then(|operation| operation.join())
+                .map_err(winrt_error)?;
+
+            let coordinate = position.Coordinate().map_err(winrt_error)?;
+            let point = coordinate.Point().and_then(|point| point.Position()).map_err(winrt_error)?;
+            let ticks = coordinate.Timestamp().map_err(winrt_error)?.UniversalTime;
+
+            Ok(GeolocationPosition {
+                coords: Coords {
+                    latitude: point.Latitude,
+                    longitude: point.Longitude,
+                    accuracy: coordinate.Accuracy().map_err(winrt_error)?,
+                    altitude: optional(coordinate.AltitudeAccuracy()).map(|_| point.Altitude),
+                    altitude_accuracy: optional(coordinate.AltitudeAccuracy()),
+                    // Heading is NaN while the device is stationary
+                    heading: optional(coordinate.Heading()).filter(|heading| !heading.is_nan()),
+                    speed: optional(coordinate.Speed()),
+                },
+                timestamp: u64::try_from((ticks - UNIX_EPOCH_TICKS) / 10_000).unwrap_or(0),
+            })
+        }
+    }
+
+    /// Value of a nullable WinRT number
+    fn optional(value: ::windows::core::Result<IReference<f64>>) -> Option<f64> {
+        value.and_then(|value| value.Value()).ok()
+    }
+
+    fn winrt_error(error: ::windows::core::Error) -> Error {
+        Error::PlatformError(format!("Windows geolocation error: {}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: &str = "https://maps.example";
+
+    fn coords() -> Coords {
+        Coords {
+            latitude: 51.5007,
+            longitude: -0.1246,
+            accuracy: 25.0,
+            altitude: None,
+            altitude_accuracy: None,
+            heading: None,
+            speed: Some(1.5),
+        }
+    }
+
+    fn manager(backend: Arc<MockGeolocationBackend>) -> (GeolocationManager, Arc<PermissionsManager>) {
+        let permissions = Arc::new(PermissionsManager::in_memory().unwrap());
+        permissions.set_tab_origin(TabId::new(1), ORIGIN);
+        (GeolocationManager::with_backend(permissions.clone(), backend), permissions)
+    }
+
+    #[tokio::test]
+    async fn test_position_requires_permission() {
+        let backend = Arc::new(MockGeolocationBackend::new(coords()));
+        let (geolocation, permissions) = manager(backend.clone());
+        let tab = TabId::new(1);
+
+        let denied = geolocation.get_current_position(tab, PositionOptions::default()).await;
+        assert!(matches!(denied, Err(Error::PermissionDenied(_))));
+        assert_eq!(backend.request_count(), 0);
+
+        permissions.set_permission(ORIGIN, Permission::Geolocation, PermissionState::Granted).unwrap();
+        let position = geolocation.get_current_position(tab, PositionOptions::default()).await.unwrap();
+        assert_eq!(position.coords, coords());
+        assert!(position.timestamp > 0);
+    }
+
+    #[tokio::test]
+    async fn test_maximum_age_reuses_last_position() {
+        let backend = Arc::new(MockGeolocationBackend::new(coords()));
+        let (geolocation, permissions) = manager(backend.clone());
+        let tab = TabId::new(1);
+        permissions.set_permission(ORIGIN, Permission::Geolocation, PermissionState::Granted).unwrap();
+
+        let cached = PositionOptions { maximum_age: Duration::from_secs(60), ..Default::default() };
+        let first = geolocation.get_current_position(tab, cached.clone()).await.unwrap();
+        backend.set_position(None);
+        assert_eq!(geolocation.get_current_position(tab, cached).await.unwrap(), first);
+        assert_eq!(backend.request_count(), 1);
+
+        // A fresh position is required by default
+        let fresh = geolocation.get_current_position(tab, PositionOptions::default()).await;
+        assert!(matches!(fresh, Err(Error::PlatformError(_))));
+        assert_eq!(backend.request_count(), 2);
+    }
+}
@@ -0,0 +1,272 @@
+//! Browsing history for the Matte browser
+//!
+//! Every visited URL has one row in the `visits` table of a SQLite database
+//! at `data_directory/history.sqlite`. Titles and URLs are indexed with FTS5
+//! so the address bar can search them; results are ranked by how often and
+//! how recently a page was visited.
+
+use common::error::{Error, Result};
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info};
+
+/// Name of the database inside the data directory
+const DATABASE_NAME: &str = "history.sqlite";
+
+/// Maximum number of search results
+const MAX_SEARCH_RESULTS: usize = 50;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS visits (
+        id INTEGER PRIMARY KEY,
+        url TEXT NOT NULL UNIQUE,
+        title TEXT NOT NULL DEFAULT '',
+        visit_time INTEGER NOT NULL,
+        visit_count INTEGER NOT NULL DEFAULT 1,
+        favicon_id INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS visits_visit_time ON visits (visit_time);
+
+    CREATE VIRTUAL TABLE IF NOT EXISTS visits_fts USING fts5(
+        url, title, content = 'visits', content_rowid = 'id'
+    );
+    CREATE TRIGGER IF NOT EXISTS visits_after_insert AFTER INSERT ON visits BEGIN
+        INSERT INTO visits_fts (rowid, url, title) VALUES (new.id, new.url, new.title);
+    END;
+    CREATE TRIGGER IF NOT EXISTS visits_after_delete AFTER DELETE ON visits BEGIN
+        INSERT INTO visits_fts (visits_fts, rowid, url, title) VALUES ('delete', old.id, old.url, old.title);
+    END;
+    CREATE TRIGGER IF NOT EXISTS visits_after_update AFTER UPDATE ON visits BEGIN
+        INSERT INTO visits_fts (visits_fts, rowid, url, title) VALUES ('delete', old.id, old.url, old.title);
+        INSERT INTO visits_fts (rowid, url, title) VALUES (new.id, new.url, new.title);
+    END;
+";
+
+/// A visited page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Row ID
+    pub id: i64,
+
+    /// Page URL
+    pub url: String,
+
+    /// Page title, empty if unknown
+    pub title: String,
+
+    /// Time of the last visit
+    pub visit_time: SystemTime,
+
+    /// Number of visits
+    pub visit_count: u32,
+
+    /// Favicon of the page
+    pub favicon_id: Option<i64>,
+}
+
+/// History manager
+pub struct HistoryManager {
+    connection: Mutex<Connection>,
+}
+
+impl HistoryManager {
+    /// Open the history database in a data directory
+    pub fn new(data_directory: &Path) -> Result<Self> {
+        std::fs::create_dir_all(data_directory)?;
+        let connection = Connection::open(data_directory.join(DATABASE_NAME)).map_err(db_error)?;
+
+        info!("Opened history database in {:?}", data_directory);
+        Self::with_connection(connection)
+    }
+
+    /// Open a history database that is discarded when dropped
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory().map_err(db_error)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Record a visit to a page
+    ///
+    /// Revisits bump the visit count and keep the latest visit time. An empty
+    /// title keeps the title already known for the page.
+    pub fn add_visit(&self, url: &str, title: &str, timestamp: SystemTime) -> Result<()> {
+        debug!("Recording visit to {}", url);
+        self.connection.lock().execute(
+            "INSERT INTO visits (url, title, visit_time) VALUES (?1, ?2, ?3)
+             ON CONFLICT (url) DO UPDATE SET
+                 title = CASE WHEN excluded.title = '' THEN title ELSE excluded.title END,
+                 visit_time = max(visit_time, excluded.visit_time),
+                 visit_count = visit_count + 1",
+            params![url, title, to_millis(timestamp)],
+        ).map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Set the favicon of a visited page
+    pub fn set_favicon(&self, url: &str, favicon_id: i64) -> Result<()> {
+        self.connection.lock()
+            .execute("UPDATE visits SET favicon_id = ?2 WHERE url = ?1", params![url, favicon_id])
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Get the entry of a URL
+    pub fn get(&self, url: &str) -> Result<Option<HistoryEntry>> {
+        self.connection.lock()
+            .query_row(
+                "SELECT id, url, title, visit_time, visit_count, favicon_id FROM visits WHERE url = ?1",
+                params![url],
+                entry_from_row,
+            )
+            .optional()
+            .map_err(db_error)
+    }
+
+    /// Search page titles and URLs
+    ///
+    /// Every word of the query must prefix a word of the title or URL.
+    /// Results are ranked by visit count decayed by the days since the last
+    /// visit.
+    pub fn search(&self, query: &str) -> Result<Vec<HistoryEntry>> {
+        let Some(expression) = match_expression(query) else {
+            return Ok(Vec::new());
+        };
+
+        let connection = self.connection.lock();
+        let mut statement = connection.prepare(
+            "SELECT v.id, v.url, v.title, v.visit_time, v.visit_count, v.favicon_id
+             FROM visits_fts JOIN visits v ON v.id = visits_fts.rowid
+             WHERE visits_fts MATCH ?1
+             ORDER BY v.visit_count / (1.0 + max(?2 - v.visit_time, 0) / 86400000.0) DESC, v.visit_time DESC
+             LIMIT ?3",
+        ).map_err(db_error)?;
+
+        let entries = statement
+            .query_map(params![expression, to_millis(SystemTime::now()), MAX_SEARCH_RESULTS as i64], entry_from_row)
+            .map_err(db_error)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(db_error)?;
+        Ok(entries)
+    }
+
+    /// Delete visits made in `[start, end)`, returning the number deleted
+    pub fn delete_range(&self, start: SystemTime, end: SystemTime) -> Result<usize> {
+        let deleted = self.connection.lock()
+            .execute(
+                "DELETE FROM visits WHERE visit_time >= ?1 AND visit_time < ?2",
+                params![to_millis(start), to_millis(end)],
+            )
+            .map_err(db_error)?;
+
+        info!("Deleted {} history entries", deleted);
+        Ok(deleted)
+    }
+}
+
+/// FTS5 expression matching every word of a query as a prefix
+///
+/// Words are quoted so that FTS5 operators in user input are taken literally.
+fn match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn entry_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        title: row.get(2)?,
+        visit_time: from_millis(row.get(3)?),
+        visit_count: row.get(4)?,
+        favicon_id: row.get(5)?,
+    })
+}
+
+fn to_millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as i64).unwrap_or(0)
+}
+
+fn from_millis(millis: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis.max(0) as u64)
+}
+
+fn db_error(err: rusqlite::Error) -> Error {
+    Error::IoError(format!("History database error: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    #[test]
+    fn test_search_ranks_by_visits_and_recency() {
+        let history = HistoryManager::in_memory().unwrap();
+        let now = SystemTime::now();
+
+        // (page, visits, days since the last visit)
+        let matching = [(10, 1, 0), (20, 6, 2), (30, 3, 10), (40, 3, 1), (50, 1, 30)];
+        for i in 0..100 {
+            let url = format!("https://site{}.example/page", i);
+            let (title, visits, age) = match matching.iter().find(|(page, _, _)| *page == i) {
+                Some((_, visits, age)) => (format!("Rustacean guide part {}", i), *visits, *age),
+                None => (format!("Unrelated page {}", i), 1, i % 7),
+            };
+            for visit in 0..visits {
+                let time = now - DAY * (age + visits - 1 - visit);
+                history.add_visit(&url, &title, time).unwrap();
+            }
+        }
+
+        let results = history.search("rustacean").unwrap();
+        let pages: Vec<&str> = results.iter().map(|entry| entry.url.as_str()).collect();
+        assert_eq!(pages, [
+            "https://site20.example/page",
+            "https://site40.example/page",
+            "https://site10.example/page",
+            "https://site30.example/page",
+            "https://site50.example/page",
+        ]);
+        assert_eq!(results[0].visit_count, 6);
+
+        // Prefixes of URL words match, FTS5 syntax in queries is literal
+        assert_eq!(history.search("site4").unwrap().len(), 11);
+        assert!(history.search("\"OR page").unwrap().is_empty());
+        assert!(history.search("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_revisit_and_delete_range() {
+        let history = HistoryManager::in_memory().unwrap();
+        let now = SystemTime::now();
+        let url = "https://news.example/";
+
+        history.add_visit(url, "News", now - DAY * 3).unwrap();
+        history.add_visit(url, "", now - DAY).unwrap();
+        history.add_visit("https://old.example/", "Old", now - DAY * 10).unwrap();
+
+        let entry = history.get(url).unwrap().unwrap();
+        assert_eq!(entry.title, "News");
+        assert_eq!(entry.visit_count, 2);
+        assert_eq!(to_millis(entry.visit_time), to_millis(now - DAY));
+
+        // Clear the last week
+        assert_eq!(history.delete_range(now - DAY * 7, now).unwrap(), 1);
+        assert!(history.get(url).unwrap().is_none());
+        assert!(history.search("news").unwrap().is_empty());
+        assert_eq!(history.search("old").unwrap().len(), 1);
+    }
+}
@@ -17,6 +17,18 @@ mod tab_manager;
 mod profile_manager;
 mod settings_manager;
 mod extension_host;
+mod storage;
+mod permissions;
+mod geolocation;
+mod clipboard;
+mod file_system_access;
+mod download_manager;
+mod history;
+mod session_manager;
+mod pip_manager;
+mod printing;
+mod autocomplete;
+mod renderer_host;
 
 use app::BrowserApp;
 
@@ -41,6 +53,7 @@ async fn run_browser() -> common::Result<()> {
     };
     
     init_common(config)?;
+    let args = parse_args();
     
     info!("Starting Matte Browser v{}", Version::current());
     info!("Platform: {} {}", std::env::consts::OS, std::env::consts::ARCH);
@@ -49,7 +62,7 @@ async fn run_browser() -> common::Result<()> {
     let mut app = BrowserApp::new().await?;
     
     // Run the event loop
-    app.run().await?;
+    app.run(args.get_one::<String>("url").map(String::as_str)).await?;
     
     info!("Matte Browser shutting down");
     Ok(())
@@ -1,13 +1,23 @@
 //! Navigation system for the Matte browser.
 //! 
 //! This module provides URL parsing, navigation state management,
-//! and History API implementation.
+//! History API implementation, and fetching of page favicons.
 
 use crate::error::{Error, Result};
+use crate::storage::open_database;
+use async_trait::async_trait;
 use common::types::{TabId, Url};
+use dom::Document;
+use network::NetworkProcessManager;
+use resvg::tiny_skia::{ColorU8, FilterQuality, Pixmap, PixmapPaint, Transform};
+use resvg::usvg;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
+use tokio::sync::RwLock;
 use tracing::{debug, info};
 
 /// Navigation state for a tab
@@ -474,6 +484,301 @@ impl Default for NavigationManager {
     }
 }
 
+/// Name of the sled database of favicons inside the data directory
+const FAVICON_DATABASE_NAME: &str = "favicons.sled";
+
+/// Directory inside the data directory holding favicon images
+const FAVICON_DIRECTORY: &str = "favicons";
+
+/// Width and height favicons are stored at
+pub const FAVICON_SIZE: u32 = 32;
+
+/// Age after which a cached favicon is fetched again
+const FAVICON_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// A favicon scaled to 32x32
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaviconEntry {
+    /// URL the icon was fetched from
+    pub url: String,
+    /// 32x32 RGBA PNG image
+    pub image_data: Vec<u8>,
+    /// Time the icon was fetched
+    pub fetch_time: SystemTime,
+}
+
+/// Response to a favicon request
+pub struct FaviconResponse {
+    /// HTTP status code
+    pub status_code: u16,
+    /// `Content-Type` header, if any
+    pub content_type: Option<String>,
+    /// Response body
+    pub body: Vec<u8>,
+}
+
+/// Connection used to fetch favicons
+#[async_trait]
+pub trait FaviconTransport: Send + Sync {
+    /// Send a GET request
+    async fn fetch(&self, tab_id: TabId, url: &str) -> Result<FaviconResponse>;
+}
+
+/// Transport sending favicon requests through the network process
+pub struct NetworkFaviconTransport {
+    network: Arc<RwLock<NetworkProcessManager>>,
+}
+
+impl NetworkFaviconTransport {
+    /// Create a transport over a network process manager
+    pub fn new(network: Arc<RwLock<NetworkProcessManager>>) -> Self {
+        Self { network }
+    }
+}
+
+#[async_trait]
+impl FaviconTransport for NetworkFaviconTransport {
+    async fn fetch(&self, tab_id: TabId, url: &str) -> Result<FaviconResponse> {
+        let mut network = self.network.write().await;
+        let request_id = network.create_request(tab_id, url.to_string(), "GET".to_string()).await?;
+        let response = network.execute_request(&request_id).await?;
+
+        Ok(FaviconResponse {
+            status_code: response.status_code,
+            content_type: Some(response.content_type).filter(|content_type| !content_type.is_empty()),
+            body: response.body,
+        })
+    }
+}
+
+/// Favicons stored by icon URL, with their images in `data_directory/favicons`
+///
+/// Image files are named after the SHA-256 hash of the icon URL.
+#[derive(Clone)]
+pub struct FaviconCache {
+    /// Fetch times keyed by icon URL
+    db: Arc<sled::Db>,
+    /// Directory of the image files
+    directory: PathBuf,
+}
+
+impl FaviconCache {
+    /// Open the favicon cache of a data directory
+    pub fn new(data_directory: &Path) -> Result<Self> {
+        let directory = data_directory.join(FAVICON_DIRECTORY);
+        std::fs::create_dir_all(&directory)?;
+        let db = open_database(&data_directory.join(FAVICON_DATABASE_NAME))?;
+        Ok(Self { db, directory })
+    }
+
+    /// Get a cached favicon, if its image file is still there
+    pub fn get(&self, url: &str) -> Result<Option<FaviconEntry>> {
+        let Some(bytes) = self.db.get(url).map_err(favicon_db_error)? else {
+            return Ok(None);
+        };
+        let fetch_time: SystemTime = bincode::deserialize(&bytes)
+            .map_err(|e| Error::ParseError(format!("Invalid favicon entry: {}", e)))?;
+
+        match std::fs::read(self.image_path(url)) {
+            Ok(image_data) => Ok(Some(FaviconEntry { url: url.to_string(), image_data, fetch_time })),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Store a favicon, replacing the one cached for its URL
+    pub fn put(&self, entry: &FaviconEntry) -> Result<()> {
+        std::fs::write(self.image_path(&entry.url), &entry.image_data)?;
+        let bytes = bincode::serialize(&entry.fetch_time)
+            .map_err(|e| Error::ParseError(format!("Failed to encode favicon entry: {}", e)))?;
+        self.db.insert(entry.url.as_bytes(), bytes).map_err(favicon_db_error)?;
+        Ok(())
+    }
+
+    /// Get the path of the image file of an icon URL
+    pub fn image_path(&self, url: &str) -> PathBuf {
+        self.directory.join(format!("{:x}.png", Sha256::digest(url.as_bytes())))
+    }
+}
+
+/// Favicon manager, fetching the icons of loaded pages
+pub struct FaviconManager {
+    /// Icons fetched before
+    cache: FaviconCache,
+    /// Connection used to fetch icons
+    transport: Arc<dyn FaviconTransport>,
+}
+
+impl FaviconManager {
+    /// Create a favicon manager caching icons in a data directory
+    pub fn new(data_directory: &Path, transport: Arc<dyn FaviconTransport>) -> Result<Self> {
+        Ok(Self {
+            cache: FaviconCache::new(data_directory)?,
+            transport,
+        })
+    }
+
+    /// Get the favicon cache
+    pub fn cache(&self) -> &FaviconCache {
+        &self.cache
+    }
+
+    /// Get the favicon of a page
+    ///
+    /// The icons of the page's `<link rel="icon">` elements are tried in
+    /// document order, then `/favicon.ico` of its origin. Icons cached less
+    /// than a week ago are not fetched again. PNG, ICO and SVG icons are
+    /// scaled to 32x32 and cached as PNG.
+    pub async fn fetch_favicon(&self, tab_id: TabId, page_url: &str, dom: &Document) -> Result<FaviconEntry> {
+        let page_url = url::Url::parse(page_url)
+            .map_err(|e| Error::ParseError(format!("Invalid page URL {}: {}", page_url, e)))?;
+
+        let mut last_error = None;
+        for icon_url in favicon_candidates(&page_url, dom) {
+            match self.fetch_icon(tab_id, &icon_url).await {
+                Ok(entry) => return Ok(entry),
+                Err(e) => {
+                    debug!("Failed to fetch favicon {}: {}", icon_url, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| Error::NotFound(format!("No favicon for {}", page_url))))
+    }
+
+    async fn fetch_icon(&self, tab_id: TabId, icon_url: &str) -> Result<FaviconEntry> {
+        if let Some(entry) = self.cache.get(icon_url)? {
+            let age = SystemTime::now().duration_since(entry.fetch_time).unwrap_or_default();
+            if age < FAVICON_MAX_AGE {
+                return Ok(entry);
+            }
+        }
+
+        let response = self.transport.fetch(tab_id, icon_url).await?;
+        if !(200..300).contains(&response.status_code) {
+            return Err(Error::NetworkError(format!("Favicon request failed with status {}", response.status_code)));
+        }
+
+        let entry = FaviconEntry {
+            url: icon_url.to_string(),
+            image_data: decode_favicon(&response.body, response.content_type.as_deref())?,
+            fetch_time: SystemTime::now(),
+        };
+        self.cache.put(&entry)?;
+
+        info!("Cached favicon {} for tab {}", icon_url, tab_id);
+        Ok(entry)
+    }
+}
+
+/// URLs of the icons of a page, in the order they are tried
+fn favicon_candidates(page_url: &url::Url, dom: &Document) -> Vec<String> {
+    let mut candidates: Vec<String> = dom.get_elements_by_tag_name("link")
+        .into_iter()
+        .filter(|link| {
+            link.get_attribute("rel")
+                .is_some_and(|rel| rel.split_ascii_whitespace().any(|token| token.eq_ignore_ascii_case("icon")))
+        })
+        .filter_map(|link| page_url.join(link.get_attribute("href")?.trim()).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https" | "data"))
+        .map(String::from)
+        .collect();
+
+    if let Ok(fallback) = page_url.join("/favicon.ico") {
+        candidates.push(fallback.into());
+    }
+    candidates.dedup();
+    candidates
+}
+
+/// Decode a PNG, ICO or SVG icon and encode it as a 32x32 PNG
+///
+/// The format is taken from the content type, else sniffed from the data.
+/// Icons are scaled to fit, keeping their aspect ratio.
+pub fn decode_favicon(data: &[u8], content_type: Option<&str>) -> Result<Vec<u8>> {
+    let essence = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|essence| essence.trim().to_ascii_lowercase());
+
+    let icon = match essence.as_deref() {
+        Some("image/svg+xml") => return render_svg_favicon(data),
+        Some("image/x-icon") | Some("image/vnd.microsoft.icon") => decode_ico(data)?,
+        Some("image/png") => decode_png(data)?,
+        _ if data.starts_with(b"\x89PNG") => decode_png(data)?,
+        _ if data.starts_with(&[0, 0, 1, 0]) => decode_ico(data)?,
+        _ if String::from_utf8_lossy(&data[..data.len().min(256)]).contains("<svg") => return render_svg_favicon(data),
+        _ => return Err(Error::ParseError("Unsupported favicon format".to_string())),
+    };
+
+    let scale = (FAVICON_SIZE as f32 / icon.width() as f32).min(FAVICON_SIZE as f32 / icon.height() as f32);
+    let transform = Transform::from_row(
+        scale,
+        0.0,
+        0.0,
+        scale,
+        (FAVICON_SIZE as f32 - icon.width() as f32 * scale) / 2.0,
+        (FAVICON_SIZE as f32 - icon.height() as f32 * scale) / 2.0,
+    );
+    let paint = PixmapPaint { quality: FilterQuality::Bicubic, ..PixmapPaint::default() };
+
+    let mut favicon = favicon_pixmap()?;
+    favicon.draw_pixmap(0, 0, icon.as_ref(), &paint, transform, None);
+    encode_favicon(&favicon)
+}
+
+fn decode_png(data: &[u8]) -> Result<Pixmap> {
+    Pixmap::decode_png(data).map_err(|e| Error::ParseError(format!("Invalid PNG favicon: {}", e)))
+}
+
+/// Decode the ICO image closest to 32x32, preferring larger images
+fn decode_ico(data: &[u8]) -> Result<Pixmap> {
+    let directory = ico::IconDir::read(std::io::Cursor::new(data))
+        .map_err(|e| Error::ParseError(format!("Invalid ICO favicon: {}", e)))?;
+    let entry = directory.entries()
+        .iter()
+        .min_by_key(|entry| (entry.width() < FAVICON_SIZE, entry.width().abs_diff(FAVICON_SIZE)))
+        .ok_or_else(|| Error::ParseError("ICO favicon has no images".to_string()))?;
+    let image = entry.decode().map_err(|e| Error::ParseError(format!("Invalid ICO favicon: {}", e)))?;
+
+    let mut pixmap = Pixmap::new(image.width(), image.height())
+        .ok_or_else(|| Error::ParseError("ICO favicon is empty".to_string()))?;
+    for (pixel, rgba) in pixmap.pixels_mut().iter_mut().zip(image.rgba_data().chunks_exact(4)) {
+        *pixel = ColorU8::from_rgba(rgba[0], rgba[1], rgba[2], rgba[3]).premultiply();
+    }
+    Ok(pixmap)
+}
+
+fn render_svg_favicon(data: &[u8]) -> Result<Vec<u8>> {
+    let tree = usvg::Tree::from_data(data, &usvg::Options::default())
+        .map_err(|e| Error::ParseError(format!("Invalid SVG favicon: {}", e)))?;
+    let size = tree.size();
+    let scale = (FAVICON_SIZE as f32 / size.width()).min(FAVICON_SIZE as f32 / size.height());
+    let transform = Transform::from_row(
+        scale,
+        0.0,
+        0.0,
+        scale,
+        (FAVICON_SIZE as f32 - size.width() * scale) / 2.0,
+        (FAVICON_SIZE as f32 - size.height() * scale) / 2.0,
+    );
+
+    let mut favicon = favicon_pixmap()?;
+    resvg::render(&tree, transform, &mut favicon.as_mut());
+    encode_favicon(&favicon)
+}
+
+fn favicon_pixmap() -> Result<Pixmap> {
+    Pixmap::new(FAVICON_SIZE, FAVICON_SIZE).ok_or_else(|| Error::InvalidState("Invalid favicon size".to_string()))
+}
+
+fn encode_favicon(favicon: &Pixmap) -> Result<Vec<u8>> {
+    favicon.encode_png().map_err(|e| Error::ParseError(format!("Failed to encode favicon: {}", e)))
+}
+
+fn favicon_db_error(err: sled::Error) -> Error {
+    Error::IoError(format!("Favicon database error: {}", err))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -633,4 +938,91 @@ mod tests {
         assert!(manager.set_title(&tab_id, title.clone()).is_ok());
         assert_eq!(manager.get_current_title(&tab_id), title);
     }
+
+    /// Server returning one icon for every URL and recording the URLs requested
+    struct IconServer {
+        content_type: &'static str,
+        body: Vec<u8>,
+        requests: parking_lot::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl FaviconTransport for IconServer {
+        async fn fetch(&self, _tab_id: TabId, url: &str) -> Result<FaviconResponse> {
+            self.requests.lock().push(url.to_string());
+            Ok(FaviconResponse {
+                status_code: 200,
+                content_type: Some(self.content_type.to_string()),
+                body: self.body.clone(),
+            })
+        }
+    }
+
+    fn page_with_icon(href: &str) -> Document {
+        let mut link = dom::Element::new("link".to_string());
+        link.set_attribute("rel".to_string(), "shortcut icon".to_string());
+        link.set_attribute("href".to_string(), href.to_string());
+        let mut document = Document::new();
+        document.root.append_child(dom::Node::Element(link));
+        document
+    }
+
+    #[tokio::test]
+    async fn test_fetch_favicon_caches_linked_png() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut icon = Pixmap::new(64, 64).unwrap();
+        icon.fill(resvg::tiny_skia::Color::from_rgba8(255, 0, 0, 255));
+        let server = Arc::new(IconServer {
+            content_type: "image/png",
+            body: icon.encode_png().unwrap(),
+            requests: parking_lot::Mutex::new(Vec::new()),
+        });
+        let manager = FaviconManager::new(directory.path(), server.clone()).unwrap();
+        let dom = page_with_icon("/static/icon.png");
+
+        let entry = manager.fetch_favicon(TabId::new(1), "https://example.com/blog/post", &dom).await.unwrap();
+        assert_eq!(entry.url, "https://example.com/static/icon.png");
+        let favicon = Pixmap::decode_png(&entry.image_data).unwrap();
+        assert_eq!((favicon.width(), favicon.height()), (FAVICON_SIZE, FAVICON_SIZE));
+        assert_eq!(favicon.pixel(16, 16).unwrap().demultiply(), ColorU8::from_rgba(255, 0, 0, 255));
+
+        let path = manager.cache().image_path(&entry.url);
+        assert!(path.starts_with(directory.path().join("favicons")));
+        assert_eq!(std::fs::read(&path).unwrap(), entry.image_data);
+
+        // Served from the cache the second time
+        let cached = manager.fetch_favicon(TabId::new(2), "https://example.com/", &dom).await.unwrap();
+        assert_eq!(cached, entry);
+        assert_eq!(server.requests.lock().len(), 1);
+
+        let mut tabs = crate::tab_manager::TabManager::new().await.unwrap();
+        let tab_id = tabs.create_tab(1, Some("https://example.com/blog/post".to_string())).await.unwrap();
+        tabs.set_favicon(tab_id, entry.clone()).await.unwrap();
+        assert_eq!(tabs.get_favicon(tab_id).await, Some(entry));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_favicon_falls_back_to_favicon_ico() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut icon_dir = ico::IconDir::new(ico::ResourceType::Icon);
+        let image = ico::IconImage::from_rgba_data(16, 16, [0, 0, 255, 255].repeat(16 * 16));
+        icon_dir.add_entry(ico::IconDirEntry::encode(&image).unwrap());
+        let mut body = Vec::new();
+        icon_dir.write(&mut body).unwrap();
+
+        let server = Arc::new(IconServer {
+            content_type: "",
+            body,
+            requests: parking_lot::Mutex::new(Vec::new()),
+        });
+        let manager = FaviconManager::new(directory.path(), server.clone()).unwrap();
+
+        let entry = manager.fetch_favicon(TabId::new(1), "https://example.com/a/b", &Document::new()).await.unwrap();
+        assert_eq!(*server.requests.lock(), vec!["https://example.com/favicon.ico".to_string()]);
+        let favicon = Pixmap::decode_png(&entry.image_data).unwrap();
+        assert_eq!(favicon.width(), FAVICON_SIZE);
+        assert_eq!(favicon.pixel(0, 31).unwrap().demultiply(), ColorU8::from_rgba(0, 0, 255, 255));
+
+        assert!(decode_favicon(b"GIF89a", None).is_err());
+    }
 }
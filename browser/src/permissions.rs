@@ -0,0 +1,304 @@
+//! Permissions manager for the Matte browser
+//!
+//! Web APIs that need user consent ask the permissions manager before they
+//! run. Decisions are stored per origin in a `sled` database at
+//! `data_directory/permissions.sled`; undecided permissions are shown to the
+//! user as a browser-level prompt.
+
+use common::{
+    error::{Error, Result},
+    ipc::{IpcConnection, IpcMessage, PermissionRequestMessage, PermissionResponseMessage},
+    Permission, PermissionState, TabId,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::oneshot;
+use tracing::{debug, info, warn};
+
+use crate::storage::{open_database, open_temporary_database};
+
+/// Name of the sled database inside the data directory
+const DATABASE_NAME: &str = "permissions.sled";
+
+/// A decision made by the user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionDecision {
+    /// Granted or denied
+    pub state: PermissionState,
+
+    /// Time the decision was made
+    pub granted_at: SystemTime,
+}
+
+/// Permissions manager
+pub struct PermissionsManager {
+    /// Decisions keyed by origin and permission
+    db: Arc<sled::Db>,
+
+    /// Origin of the document loaded in each tab
+    tab_origins: Mutex<HashMap<TabId, String>>,
+
+    /// Prompts waiting for an answer from the user, with their origin
+    pending: Mutex<HashMap<u64, (String, oneshot::Sender<PermissionState>)>>,
+
+    /// IPC channel to the browser UI that shows prompts
+    ui_channel: Mutex<Option<IpcConnection>>,
+
+    /// Next prompt request ID
+    next_request_id: AtomicU64,
+}
+
+impl PermissionsManager {
+    /// Create a permissions manager storing decisions in a data directory
+    pub fn new(data_directory: &Path) -> Result<Self> {
+        std::fs::create_dir_all(data_directory)?;
+        let db = open_database(&data_directory.join(DATABASE_NAME))?;
+
+        info!("Opened permission store in {:?}", data_directory);
+        Ok(Self::with_database(db))
+    }
+
+    /// Create a permissions manager that forgets its decisions when dropped
+    pub fn in_memory() -> Result<Self> {
+        Ok(Self::with_database(open_temporary_database()?))
+    }
+
+    fn with_database(db: Arc<sled::Db>) -> Self {
+        Self {
+            db,
+            tab_origins: Mutex::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            ui_channel: Mutex::new(None),
+            next_request_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Set the channel prompts are sent to
+    ///
+    /// Every `PermissionRequest` sent on the channel must be answered with
+    /// `respond`. Until a connected channel is set, undecided permissions
+    /// are denied.
+    pub fn set_ui_channel(&self, channel: IpcConnection) {
+        *self.ui_channel.lock() = Some(channel);
+    }
+
+    /// Record the origin of the document loaded in a tab
+    pub fn set_tab_origin(&self, tab_id: TabId, origin: &str) {
+        self.tab_origins.lock().insert(tab_id, origin.to_string());
+    }
+
+    /// Forget a closed tab
+    pub fn remove_tab(&self, tab_id: TabId) {
+        self.tab_origins.lock().remove(&tab_id);
+    }
+
+    /// Origin of the document loaded in a tab
+    pub fn tab_origin(&self, tab_id: TabId) -> Result<String> {
+        self.tab_origins.lock().get(&tab_id).cloned().ok_or_else(|| {
+            Error::NotFound(format!("No document origin for tab {}", tab_id))
+        })
+    }
+
+    /// Current state of a permission for an origin, without prompting
+    pub fn query(&self, origin: &str, permission: &Permission) -> PermissionState {
+        match self.decision(origin, permission) {
+            Ok(Some(decision)) => decision.state,
+            Ok(None) => PermissionState::Prompt,
+            Err(e) => {
+                warn!("Failed to read {} permission for {}: {}", permission, origin, e);
+                PermissionState::Prompt
+            }
+        }
+    }
+
+    /// Stored decision for a permission of an origin
+    pub fn decision(&self, origin: &str, permission: &Permission) -> Result<Option<PermissionDecision>> {
+        let Some(bytes) = self.db.get(decision_key(origin, permission)).map_err(db_error)? else {
+            return Ok(None);
+        };
+
+        bincode::deserialize(&bytes)
+            .map(Some)
+            .map_err(|e| Error::ParseError(format!("Invalid permission decision: {}", e)))
+    }
+
+    /// Request a permission for an origin, prompting the user if it is undecided
+    pub async fn request(&self, tab_id: TabId, origin: &str, permission: Permission) -> Result<PermissionState> {
+        let state = self.query(origin, &permission);
+        if state != PermissionState::Prompt {
+            debug!("Permission {} for {} already {}", permission, origin, state);
+            return Ok(state);
+        }
+
+        let channel = self.ui_channel.lock().clone();
+        let Some(channel) = channel else {
+            warn!("No permission prompt available, denying {} for {}", permission, origin);
+            return Ok(PermissionState::Denied);
+        };
+
+        let request_id = self.next_request_id.fetch_add(1, Ordering::SeqCst);
+        let (answer_sender, answer) = oneshot::channel();
+        self.pending.lock().insert(request_id, (origin.to_string(), answer_sender));
+
+        let prompt = PermissionRequestMessage {
+            request_id,
+            tab_id,
+            origin: origin.to_string(),
+            description: Some(format!("Allow {} to use your {}?", origin, permission)),
+            permission,
+        };
+        if let Err(e) = channel.send_message(IpcMessage::PermissionRequest(prompt)).await {
+            self.pending.lock().remove(&request_id);
+            warn!("Failed to show permission prompt {}: {}", request_id, e);
+            return Ok(PermissionState::Denied);
+        }
+
+        // A dropped prompt counts as a dismissal
+        Ok(answer.await.unwrap_or(PermissionState::Denied))
+    }
+
+    /// Answer a prompt shown to the user
+    ///
+    /// A `Prompt` answer dismisses the prompt without storing a decision.
+    pub fn respond(&self, response: PermissionResponseMessage) -> Result<()> {
+        let (origin, answer) = self.pending.lock().remove(&response.request_id).ok_or_else(|| {
+            Error::NotFound(format!("Permission request {} not found", response.request_id))
+        })?;
+
+        let state = match response.state {
+            PermissionState::Prompt => PermissionState::Denied,
+            state => {
+                self.set_permission(&origin, response.permission, state.clone())?;
+                state
+            }
+        };
+
+        let _ = answer.send(state);
+        Ok(())
+    }
+
+    /// Store a decision for an origin
+    pub fn set_permission(&self, origin: &str, permission: Permission, state: PermissionState) -> Result<()> {
+        if state == PermissionState::Prompt {
+            return self.revoke(origin, &permission);
+        }
+
+        info!("Setting {} permission for {} to {}", permission, origin, state);
+        let decision = PermissionDecision {
+            state,
+            granted_at: SystemTime::now(),
+        };
+        let bytes = bincode::serialize(&decision)
+            .map_err(|e| Error::ParseError(format!("Failed to encode permission decision: {}", e)))?;
+
+        self.db.insert(decision_key(origin, &permission), bytes).map_err(db_error)?;
+        self.db.flush().map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Clear a stored decision so the user is asked again
+    pub fn revoke(&self, origin: &str, permission: &Permission) -> Result<()> {
+        info!("Revoking {} permission for {}", permission, origin);
+        self.db.remove(decision_key(origin, permission)).map_err(db_error)?;
+        self.db.flush().map_err(db_error)?;
+        Ok(())
+    }
+}
+
+/// Key of a decision, the origin and the permission name separated by a NUL
+fn decision_key(origin: &str, permission: &Permission) -> Vec<u8> {
+    format!("{}\0{}", origin, permission).into_bytes()
+}
+
+fn db_error(err: sled::Error) -> Error {
+    Error::IoError(format!("Permission store error: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: &str = "https://maps.example";
+
+    async fn connected_channel() -> IpcConnection {
+        let channel = IpcConnection::new("browser-ui".to_string());
+        channel.connect().await.unwrap();
+        channel
+    }
+
+    #[tokio::test]
+    async fn test_prompt_decision_is_stored_per_origin() {
+        let manager = Arc::new(PermissionsManager::in_memory().unwrap());
+        let channel = connected_channel().await;
+        manager.set_ui_channel(channel.clone());
+
+        let requester = manager.clone();
+        let request = tokio::spawn(async move {
+            requester.request(TabId::new(1), ORIGIN, Permission::Geolocation).await
+        });
+
+        let prompt = loop {
+            match channel.receive_message().await.unwrap() {
+                Some(envelope) => match envelope.message {
+                    IpcMessage::PermissionRequest(prompt) => break prompt,
+                    other => panic!("unexpected message {:?}", other),
+                },
+                None => tokio::task::yield_now().await,
+            }
+        };
+        assert_eq!(prompt.origin, ORIGIN);
+        assert_eq!(prompt.permission, Permission::Geolocation);
+
+        let before = SystemTime::now();
+        manager.respond(PermissionResponseMessage {
+            request_id: prompt.request_id,
+            tab_id: prompt.tab_id,
+            permission: Permission::Geolocation,
+            state: PermissionState::Granted,
+        }).unwrap();
+        assert_eq!(request.await.unwrap().unwrap(), PermissionState::Granted);
+
+        let decision = manager.decision(ORIGIN, &Permission::Geolocation).unwrap().unwrap();
+        assert!(decision.granted_at >= before);
+
+        // Other tabs on the origin are not asked again, other origins are
+        assert_eq!(manager.request(TabId::new(2), ORIGIN, Permission::Geolocation).await.unwrap(), PermissionState::Granted);
+        assert_eq!(manager.query("https://other.example", &Permission::Geolocation), PermissionState::Prompt);
+        assert_eq!(manager.query(ORIGIN, &Permission::Camera), PermissionState::Prompt);
+
+        manager.revoke(ORIGIN, &Permission::Geolocation).unwrap();
+        assert_eq!(manager.query(ORIGIN, &Permission::Geolocation), PermissionState::Prompt);
+    }
+
+    #[tokio::test]
+    async fn test_request_without_prompt_is_denied() {
+        let manager = PermissionsManager::in_memory().unwrap();
+        let tab = TabId::new(1);
+        assert_eq!(manager.request(tab, ORIGIN, Permission::Camera).await.unwrap(), PermissionState::Denied);
+
+        // A disconnected UI cannot show prompts either
+        manager.set_ui_channel(IpcConnection::new("browser-ui".to_string()));
+        assert_eq!(manager.request(tab, ORIGIN, Permission::Camera).await.unwrap(), PermissionState::Denied);
+
+        // Nothing is stored for a denial the user never saw
+        assert_eq!(manager.query(ORIGIN, &Permission::Camera), PermissionState::Prompt);
+    }
+
+    #[tokio::test]
+    async fn test_decisions_persist_across_managers() {
+        let dir = tempfile::TempDir::new().unwrap();
+        {
+            let manager = PermissionsManager::new(dir.path()).unwrap();
+            manager.set_permission(ORIGIN, Permission::Notifications, PermissionState::Denied).unwrap();
+        }
+
+        let manager = PermissionsManager::new(dir.path()).unwrap();
+        assert!(dir.path().join(DATABASE_NAME).exists());
+        assert_eq!(manager.query(ORIGIN, &Permission::Notifications), PermissionState::Denied);
+    }
+}
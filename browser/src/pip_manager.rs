@@ -0,0 +1,420 @@
+//! Picture-in-Picture windows for video elements
+//!
+//! A video shown Picture-in-Picture floats in its own borderless window kept
+//! above other windows. The GPU process sends the window compositor layers
+//! holding only the video's rendered content, which are composited into an
+//! RGBA surface the size of the window. One video is shown at a time, so
+//! entering Picture-in-Picture closes the window of the previous video.
+
+use common::{error::{Error, Result}, ipc::BrowserRequest, TabId};
+use gpu::{CompositorLayer, LayerContent, Rectangle};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tracing::{debug, info};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event_loop::EventLoopWindowTarget,
+    window::{Window, WindowBuilder, WindowId, WindowLevel},
+};
+
+/// Largest size of a Picture-in-Picture window opened for a video
+const MAX_PIP_SIZE: (u32, u32) = (480, 360);
+
+/// Distance between a Picture-in-Picture window and the screen edges
+const PIP_SCREEN_MARGIN: u32 = 16;
+
+/// Window showing a video, implemented by winit windows
+pub trait PipWindow {
+    /// Get the window ID
+    fn id(&self) -> WindowId;
+
+    /// Ask for the window to be redrawn from its surface
+    fn request_redraw(&self);
+}
+
+impl PipWindow for Window {
+    fn id(&self) -> WindowId {
+        Window::id(self)
+    }
+
+    fn request_redraw(&self) {
+        Window::request_redraw(self)
+    }
+}
+
+/// Creator of Picture-in-Picture windows, implemented by winit event loops
+pub trait PipWindowFactory {
+    /// Window created
+    type Window: PipWindow;
+
+    /// Create a borderless, always on top window covering a rectangle of the screen
+    fn create_pip_window(&self, bounds: &Rectangle) -> Result<Self::Window>;
+
+    /// Get the size of the primary screen, if known
+    fn screen_size(&self) -> Option<(u32, u32)>;
+}
+
+impl<T> PipWindowFactory for EventLoopWindowTarget<T> {
+    type Window = Window;
+
+    fn create_pip_window(&self, bounds: &Rectangle) -> Result<Window> {
+        WindowBuilder::new()
+            .with_title("Picture-in-Picture")
+            .with_decorations(false)
+            .with_window_level(WindowLevel::AlwaysOnTop)
+            .with_position(PhysicalPosition::new(bounds.x, bounds.y))
+            .with_inner_size(PhysicalSize::new(bounds.width, bounds.height))
+            .with_resizable(true)
+            .build(self)
+            .map_err(|e| Error::PlatformError(format!("Failed to create Picture-in-Picture window: {}", e)))
+    }
+
+    fn screen_size(&self) -> Option<(u32, u32)> {
+        let size = self.primary_monitor()?.size();
+        Some((size.width, size.height))
+    }
+}
+
+/// RGBA pixels shown in a Picture-in-Picture window
+struct PipSurface {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// Picture-in-Picture window of a video element
+///
+/// Clones share the window and its surface.
+pub struct PipHandle<W = Window> {
+    /// Tab the video belongs to
+    tab_id: TabId,
+    /// ID of the video element
+    element_id: String,
+    /// Window showing the video
+    window: Arc<W>,
+    /// Pixels of the last frame
+    surface: Arc<Mutex<PipSurface>>,
+}
+
+impl<W> Clone for PipHandle<W> {
+    fn clone(&self) -> Self {
+        Self {
+            tab_id: self.tab_id,
+            element_id: self.element_id.clone(),
+            window: self.window.clone(),
+            surface: self.surface.clone(),
+        }
+    }
+}
+
+impl<W: PipWindow> PipHandle<W> {
+    /// Get the tab the video belongs to
+    pub fn tab_id(&self) -> TabId {
+        self.tab_id
+    }
+
+    /// Get the ID of the video element
+    pub fn element_id(&self) -> &str {
+        &self.element_id
+    }
+
+    /// Get the window showing the video
+    pub fn window(&self) -> &W {
+        &self.window
+    }
+
+    /// Get the width and height of the surface, in physical pixels
+    pub fn size(&self) -> (u32, u32) {
+        let surface = self.surface.lock();
+        (surface.width, surface.height)
+    }
+
+    /// Get a copy of the surface pixels, in RGBA
+    pub fn pixels(&self) -> Vec<u8> {
+        self.surface.lock().pixels.clone()
+    }
+
+    /// Resize the surface after the window was resized, clearing it until the next frame
+    pub fn resize(&self, width: u32, height: u32) {
+        let mut surface = self.surface.lock();
+        surface.width = width;
+        surface.height = height;
+        surface.pixels = vec![0; width as usize * height as usize * 4];
+    }
+
+    /// Composite a layer holding the video content into the surface and redraw the window
+    ///
+    /// The layer is scaled to cover the whole surface over a black
+    /// background, at the layer opacity. Its transform and blend mode are
+    /// not applied, since the layer is the only content of the window.
+    pub fn update_frame(&self, layer: CompositorLayer) -> Result<()> {
+        let layer_width = layer.width.ceil().max(0.0) as usize;
+        let layer_height = layer.height.ceil().max(0.0) as usize;
+
+        let mut surface = self.surface.lock();
+        let (width, height) = (surface.width as usize, surface.height as usize);
+        let opacity = layer.opacity.clamp(0.0, 1.0);
+        surface.pixels.chunks_exact_mut(4).for_each(|pixel| pixel.copy_from_slice(&[0, 0, 0, 255]));
+
+        match &layer.content {
+            LayerContent::Solid(color) => {
+                let source = [color.r, color.g, color.b, color.a];
+                for pixel in surface.pixels.chunks_exact_mut(4) {
+                    blend_pixel(pixel, &source, opacity);
+                }
+            }
+            LayerContent::Image(data) | LayerContent::Video(gpu::VideoContent { frame_data: data, .. }) => {
+                if layer_width == 0 || layer_height == 0 || data.len() != layer_width * layer_height * 4 {
+                    return Err(Error::InvalidState(format!(
+                        "Layer {} holds {} bytes, not a {}x{} RGBA frame",
+                        layer.id, data.len(), layer_width, layer_height
+                    )));
+                }
+
+                // Nearest neighbour sampling of the layer at each surface pixel center
+                for y in 0..height {
+                    let source_y = (y * 2 + 1) * layer_height / (height * 2);
+                    for x in 0..width {
+                        let source_x = (x * 2 + 1) * layer_width / (width * 2);
+                        let source = (source_y * layer_width + source_x) * 4;
+                        let target = (y * width + x) * 4;
+                        blend_pixel(&mut surface.pixels[target..target + 4], &data[source..source + 4], opacity);
+                    }
+                }
+            }
+            LayerContent::Text(_) => {
+                return Err(Error::NotImplemented("Text layers in Picture-in-Picture windows".to_string()));
+            }
+        }
+
+        drop(surface);
+        self.window.request_redraw();
+        Ok(())
+    }
+}
+
+/// Blend an RGBA source pixel over an opaque pixel
+fn blend_pixel(target: &mut [u8], source: &[u8], opacity: f32) {
+    let alpha = source[3] as f32 / 255.0 * opacity;
+    for channel in 0..3 {
+        target[channel] = (source[channel] as f32 * alpha + target[channel] as f32 * (1.0 - alpha)).round() as u8;
+    }
+}
+
+/// Manager of the Picture-in-Picture window
+pub struct PipManager<W = Window> {
+    /// Window of the video shown Picture-in-Picture
+    active: Option<PipHandle<W>>,
+}
+
+impl<W: PipWindow> Default for PipManager<W> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: PipWindow> PipManager<W> {
+    /// Create a manager without a Picture-in-Picture window
+    pub fn new() -> Self {
+        Self { active: None }
+    }
+
+    /// Open a Picture-in-Picture window for a video element
+    ///
+    /// The window of the video shown before, if any, is closed.
+    pub fn enter_pip<F>(&mut self, windows: &F, tab_id: TabId, element_id: &str, initial_bounds: Rectangle) -> Result<PipHandle<W>>
+    where
+        F: PipWindowFactory<Window = W>,
+    {
+        if initial_bounds.width == 0 || initial_bounds.height == 0 {
+            return Err(Error::InvalidState(format!("Element {} has no size to show", element_id)));
+        }
+
+        let window = windows.create_pip_window(&initial_bounds)?;
+        let handle = PipHandle {
+            tab_id,
+            element_id: element_id.to_string(),
+            window: Arc::new(window),
+            surface: Arc::new(Mutex::new(PipSurface {
+                width: initial_bounds.width,
+                height: initial_bounds.height,
+                pixels: vec![0; initial_bounds.width as usize * initial_bounds.height as usize * 4],
+            })),
+        };
+
+        if let Some(previous) = self.active.replace(handle.clone()) {
+            debug!("Closed Picture-in-Picture window of element {} in tab {}", previous.element_id, previous.tab_id);
+        }
+        info!("Element {} of tab {} entered Picture-in-Picture", element_id, tab_id);
+        Ok(handle)
+    }
+
+    /// Close the Picture-in-Picture window if it shows a video of a tab
+    pub fn exit_pip(&mut self, tab_id: TabId) -> Result<()> {
+        match &self.active {
+            Some(handle) if handle.tab_id == tab_id => {
+                info!("Element {} of tab {} left Picture-in-Picture", handle.element_id, tab_id);
+                self.active = None;
+                Ok(())
+            }
+            _ => Err(Error::InvalidState(format!("Tab {} has no Picture-in-Picture window", tab_id))),
+        }
+    }
+
+    /// Get the Picture-in-Picture window
+    pub fn active(&self) -> Option<&PipHandle<W>> {
+        self.active.as_ref()
+    }
+
+    /// Get the Picture-in-Picture window, if it has a window ID
+    pub fn get_by_window(&self, window_id: WindowId) -> Option<&PipHandle<W>> {
+        self.active.as_ref().filter(|handle| handle.window.id() == window_id)
+    }
+
+    /// Composite a layer sent by the GPU process into the window showing a video of a tab
+    pub fn update_frame(&self, tab_id: TabId, layer: CompositorLayer) -> Result<()> {
+        match &self.active {
+            Some(handle) if handle.tab_id == tab_id => handle.update_frame(layer),
+            _ => Err(Error::InvalidState(format!("Tab {} has no Picture-in-Picture window", tab_id))),
+        }
+    }
+
+    /// Carry out a Picture-in-Picture request a renderer made
+    pub fn handle_browser_request<F>(&mut self, windows: &F, request: BrowserRequest) -> Result<()>
+    where
+        F: PipWindowFactory<Window = W>,
+    {
+        match request {
+            BrowserRequest::EnterPictureInPicture { tab_id, element_id, width, height } => {
+                let bounds = initial_bounds(width, height, windows.screen_size());
+                self.enter_pip(windows, tab_id, &element_id, bounds).map(|_| ())
+            }
+            BrowserRequest::ExitPictureInPicture { tab_id } => self.exit_pip(tab_id),
+            request => Err(Error::InvalidState(format!("{:?} is not a Picture-in-Picture request", request))),
+        }
+    }
+}
+
+/// Place a window for a video of a size in the bottom right corner of the screen
+///
+/// Videos larger than the largest window size are scaled down, keeping their aspect ratio.
+pub fn initial_bounds(width: u32, height: u32, screen_size: Option<(u32, u32)>) -> Rectangle {
+    let scale = (MAX_PIP_SIZE.0 as f32 / width.max(1) as f32)
+        .min(MAX_PIP_SIZE.1 as f32 / height.max(1) as f32)
+        .min(1.0);
+    let width = ((width as f32 * scale).round() as u32).max(1);
+    let height = ((height as f32 * scale).round() as u32).max(1);
+
+    let (x, y) = match screen_size {
+        Some((screen_width, screen_height)) => (
+            screen_width.saturating_sub(width + PIP_SCREEN_MARGIN) as i32,
+            screen_height.saturating_sub(height + PIP_SCREEN_MARGIN) as i32,
+        ),
+        None => (PIP_SCREEN_MARGIN as i32, PIP_SCREEN_MARGIN as i32),
+    };
+    Rectangle::new(x, y, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpu::{BlendMode, Transform, VideoContent};
+    use std::cell::RefCell;
+
+    /// Window recording its redraws
+    struct MockWindow {
+        redraws: Mutex<usize>,
+    }
+
+    impl PipWindow for MockWindow {
+        fn id(&self) -> WindowId {
+            unsafe { WindowId::dummy() }
+        }
+
+        fn request_redraw(&self) {
+            *self.redraws.lock() += 1;
+        }
+    }
+
+    /// Factory recording the bounds of the windows it creates
+    struct MockWindowFactory {
+        created: RefCell<Vec<Rectangle>>,
+    }
+
+    impl PipWindowFactory for MockWindowFactory {
+        type Window = MockWindow;
+
+        fn create_pip_window(&self, bounds: &Rectangle) -> Result<MockWindow> {
+            self.created.borrow_mut().push(bounds.clone());
+            Ok(MockWindow { redraws: Mutex::new(0) })
+        }
+
+        fn screen_size(&self) -> Option<(u32, u32)> {
+            Some((1920, 1080))
+        }
+    }
+
+    fn video_layer(width: u32, height: u32, pixel: [u8; 4]) -> CompositorLayer {
+        CompositorLayer {
+            id: "video".to_string(),
+            z_order: 0,
+            width: width as f32,
+            height: height as f32,
+            transform: Transform { matrix: [0.0; 16] },
+            blend_mode: BlendMode::Normal,
+            opacity: 1.0,
+            content: LayerContent::Video(VideoContent {
+                frame_data: pixel.repeat((width * height) as usize),
+                timestamp: std::time::Instant::now(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_enter_pip_creates_window_of_video_size() {
+        let factory = MockWindowFactory { created: RefCell::new(Vec::new()) };
+        let mut manager = PipManager::new();
+
+        let request = BrowserRequest::EnterPictureInPicture {
+            tab_id: TabId::new(4),
+            element_id: "player".to_string(),
+            width: 640,
+            height: 360,
+        };
+        manager.handle_browser_request(&factory, request).unwrap();
+
+        // Scaled down to the largest window size, in the bottom right corner
+        assert_eq!(*factory.created.borrow(), vec![Rectangle::new(1424, 794, 480, 270)]);
+        let handle = manager.active().unwrap();
+        assert_eq!((handle.tab_id(), handle.element_id()), (TabId::new(4), "player"));
+        assert_eq!(handle.size(), (480, 270));
+
+        assert!(manager.exit_pip(TabId::new(5)).is_err());
+        manager.handle_browser_request(&factory, BrowserRequest::ExitPictureInPicture { tab_id: TabId::new(4) }).unwrap();
+        assert!(manager.active().is_none());
+    }
+
+    #[test]
+    fn test_update_frame_composites_video_layer() {
+        let factory = MockWindowFactory { created: RefCell::new(Vec::new()) };
+        let mut manager = PipManager::new();
+        let handle = manager.enter_pip(&factory, TabId::new(1), "player", Rectangle::new(0, 0, 4, 2)).unwrap();
+
+        // A frame of twice the window size, at half opacity over black
+        let mut layer = video_layer(8, 4, [200, 100, 50, 255]);
+        layer.opacity = 0.5;
+        manager.update_frame(TabId::new(1), layer).unwrap();
+        assert_eq!(handle.pixels(), [100, 50, 25, 255].repeat(8));
+        assert_eq!(*handle.window().redraws.lock(), 1);
+
+        let mut truncated = video_layer(8, 4, [0, 0, 0, 255]);
+        truncated.width = 16.0;
+        assert!(handle.update_frame(truncated).is_err());
+        assert!(manager.update_frame(TabId::new(2), video_layer(8, 4, [0, 0, 0, 255])).is_err());
+
+        // Entering again replaces the window
+        manager.enter_pip(&factory, TabId::new(2), "clip", Rectangle::new(0, 0, 2, 2)).unwrap();
+        assert_eq!(factory.created.borrow().len(), 2);
+        assert_eq!(manager.active().unwrap().element_id(), "clip");
+    }
+}
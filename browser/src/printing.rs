@@ -0,0 +1,146 @@
+//! Printing tabs to PDF
+//!
+//! The renderer of a tab lays its document out for print and rasterizes the
+//! pages; the browser process writes them to a PDF file, one image per page.
+
+use common::{
+    error::{Error, Result},
+    ipc::{Channel, IpcMessage, PageMargins, PaperSize, PrintRequest, RenderedPage},
+    TabId,
+};
+use printpdf::{ColorBits, ColorSpace, Image, ImageTransform, ImageXObject, Mm, PdfDocument, Pt, Px};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{debug, info};
+
+/// Resolution renderers rasterize printed pages at, in pixels per inch
+const PAGE_DPI: f32 = 96.0;
+
+/// ID of the next print request
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Ask the renderer of a tab to print its document, and wait for the pages
+pub async fn print_tab(channel: &Channel<IpcMessage>, tab_id: TabId, paper_size: PaperSize, margins: PageMargins) -> Result<Vec<RenderedPage>> {
+    let request_id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+    channel.send_async(IpcMessage::PrintRequest(PrintRequest { request_id, tab_id, paper_size, margins })).await?;
+
+    loop {
+        match channel.recv_async().await? {
+            IpcMessage::PrintResponse(response) if response.request_id == request_id => {
+                return match response.error {
+                    Some(error) => Err(Error::InvalidState(format!("Tab {} could not be printed: {}", tab_id, error))),
+                    None => Ok(response.pages),
+                };
+            }
+            message => debug!("Ignoring {:?} while printing tab {}", std::mem::discriminant(&message), tab_id),
+        }
+    }
+}
+
+/// Write printed pages to a PDF file, each page the size of its paper
+pub fn write_pdf(pages: &[RenderedPage], title: &str, path: &Path) -> Result<()> {
+    let first = pages.first().ok_or_else(|| Error::InvalidState("Nothing was printed".to_string()))?;
+    let (document, page, layer) = PdfDocument::new(
+        title,
+        Mm::from(Pt(first.paper_size.width)),
+        Mm::from(Pt(first.paper_size.height)),
+        "Page 1",
+    );
+
+    for (index, rendered) in pages.iter().enumerate() {
+        let layer = match index {
+            0 => document.get_page(page).get_layer(layer),
+            _ => {
+                let (page, layer) = document.add_page(
+                    Mm::from(Pt(rendered.paper_size.width)),
+                    Mm::from(Pt(rendered.paper_size.height)),
+                    format!("Page {}", index + 1),
+                );
+                document.get_page(page).get_layer(layer)
+            }
+        };
+        Image::from(page_image(rendered)).add_to_layer(layer, ImageTransform { dpi: Some(PAGE_DPI), ..Default::default() });
+    }
+
+    let file = File::create(path).map_err(|e| Error::IoError(format!("Failed to create {}: {}", path.display(), e)))?;
+    document.save(&mut BufWriter::new(file))
+        .map_err(|e| Error::IoError(format!("Failed to write {}: {}", path.display(), e)))?;
+
+    info!("Wrote {} pages to {}", pages.len(), path.display());
+    Ok(())
+}
+
+/// RGB image of a page, with translucent pixels blended over white paper
+fn page_image(page: &RenderedPage) -> ImageXObject {
+    let image_data = page.pixels.chunks_exact(4)
+        .flat_map(|pixel| {
+            let alpha = pixel[3] as u32;
+            let blend = move |channel: u8| ((channel as u32 * alpha + 255 * (255 - alpha)) / 255) as u8;
+            [blend(pixel[0]), blend(pixel[1]), blend(pixel[2])]
+        })
+        .collect();
+
+    ImageXObject {
+        width: Px(page.width as usize),
+        height: Px(page.height as usize),
+        color_space: ColorSpace::Rgb,
+        bits_per_component: ColorBits::Bit8,
+        interpolate: false,
+        image_data,
+        image_filter: None,
+        smask: None,
+        clipping_bbox: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::ipc::{ChannelPair, PrintResponse};
+
+    /// A4 page rasterized at 96 DPI, white with a black bar
+    fn rendered_page() -> RenderedPage {
+        let (width, height) = (794, 1123);
+        let mut pixels = vec![255; width * height * 4];
+        for row in 48..66 {
+            for pixel in pixels[(row * width + 48) * 4..(row * width + 400) * 4].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&[0, 0, 0, 255]);
+            }
+        }
+        RenderedPage { paper_size: PaperSize::A4, width: width as u32, height: height as u32, pixels }
+    }
+
+    #[tokio::test]
+    async fn test_print_tab_to_pdf() {
+        let (browser, renderer) = ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+
+        // Stands in for the renderer, printing two pages
+        let renderer = tokio::spawn(async move {
+            let IpcMessage::PrintRequest(request) = renderer.recv_async().await.unwrap() else {
+                panic!("expected a print request");
+            };
+            assert_eq!((request.paper_size, request.margins), (PaperSize::A4, PageMargins::default()));
+            renderer.send_async(IpcMessage::PrintResponse(PrintResponse {
+                request_id: request.request_id,
+                tab_id: request.tab_id,
+                pages: vec![rendered_page(), rendered_page()],
+                error: None,
+            })).await.unwrap();
+        });
+
+        let pages = print_tab(&browser, TabId::new(1), PaperSize::A4, PageMargins::default()).await.unwrap();
+        renderer.await.unwrap();
+        assert_eq!(pages.len(), 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("page.pdf");
+        write_pdf(&pages, "Page", &path).unwrap();
+
+        let pdf = std::fs::read(&path).unwrap();
+        assert!(!pdf.is_empty());
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(write_pdf(&[], "Page", &dir.path().join("empty.pdf")).is_err());
+    }
+}
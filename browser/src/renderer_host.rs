@@ -0,0 +1,297 @@
+//! Browser end of a renderer's request channel
+//!
+//! Renderers ask the browser process for what they may not touch
+//! themselves, such as the system clipboard, local files and the device's
+//! location. Requests for the window showing the tab, such as entering
+//! fullscreen or Picture-in-Picture, are passed on to the event loop that owns
+//! the windows. Each renderer gets its own channel, so a request naming
+//! another tab is refused.
+
+use common::{
+    ipc::{
+        BrowserRequest, Channel, ClipboardReadMessage, ClipboardResponseMessage, ClipboardWriteMessage, FileSystemResponse,
+        GeolocationError, GeolocationResponseMessage, IpcMessage,
+    },
+    TabId,
+};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::clipboard::ClipboardManager;
+use crate::file_system_access::FileSystemManager;
+use crate::geolocation::GeolocationManager;
+
+/// Answers the requests of renderer processes
+pub struct RendererHost {
+    /// Clipboard manager
+    clipboard: Arc<ClipboardManager>,
+
+    /// File System Access manager
+    file_system: Arc<FileSystemManager>,
+
+    /// Geolocation manager
+    geolocation: Arc<GeolocationManager>,
+
+    /// Requests for the window showing a tab, carried out by the event loop
+    window_requests: mpsc::UnboundedSender<BrowserRequest>,
+}
+
+impl RendererHost {
+    /// Create a renderer host
+    pub fn new(
+        clipboard: Arc<ClipboardManager>,
+        file_system: Arc<FileSystemManager>,
+        geolocation: Arc<GeolocationManager>,
+        window_requests: mpsc::UnboundedSender<BrowserRequest>,
+    ) -> Self {
+        Self { clipboard, file_system, geolocation, window_requests }
+    }
+
+    /// Answer the requests the renderer of a tab sends over a channel until it closes
+    pub async fn serve(&self, tab_id: TabId, channel: Channel<IpcMessage>) {
+        loop {
+            let message = match channel.recv_async().await {
+                Ok(message) => message,
+                Err(e) => {
+                    debug!("Renderer channel of tab {} closed: {}", tab_id, e);
+                    return;
+                }
+            };
+
+            if let Some(response) = self.handle_message(tab_id, message).await {
+                if let Err(e) = channel.send_async(response).await {
+                    warn!("Failed to answer the renderer of tab {}: {}", tab_id, e);
+                }
+            }
+        }
+    }
+
+    /// Answer one request from the renderer of a tab
+    ///
+    /// Returns `None` for requests that have no answer.
+    pub async fn handle_message(&self, tab_id: TabId, message: IpcMessage) -> Option<IpcMessage> {
+        if request_tab(&message).is_some_and(|request_tab| request_tab != tab_id) {
+            warn!("Renderer of tab {} made a request for another tab", tab_id);
+            return refusal(&message);
+        }
+
+        match message {
+            IpcMessage::ClipboardRead(_) | IpcMessage::ClipboardWrite(_) => self.clipboard.handle_message(message).await,
+            IpcMessage::FileSystemRequest(_) => self.file_system.handle_message(message).await,
+            IpcMessage::GeolocationRequest(_) => self.geolocation.handle_message(message).await,
+            IpcMessage::BrowserRequest(request) => {
+                if self.window_requests.send(request).is_err() {
+                    warn!("Dropping a window request of tab {}, the event loop has stopped", tab_id);
+                }
+                None
+            }
+            message => {
+                warn!("Ignoring {:?} from the renderer of tab {}", std::mem::discriminant(&message), tab_id);
+                None
+            }
+        }
+    }
+}
+
+/// Get the tab a request was made for
+fn request_tab(message: &IpcMessage) -> Option<TabId> {
+    match message {
+        IpcMessage::ClipboardRead(request) => Some(request.tab_id),
+        IpcMessage::ClipboardWrite(request) => Some(request.tab_id),
+        IpcMessage::FileSystemRequest(request) => Some(request.tab_id()),
+        IpcMessage::GeolocationRequest(request) => Some(request.tab_id),
+        IpcMessage::BrowserRequest(request) => Some(request.tab_id()),
+        _ => None,
+    }
+}
+
+/// Error answer to a request made for another tab
+fn refusal(message: &IpcMessage) -> Option<IpcMessage> {
+    let error = Some("Requests must come from the tab's own renderer".to_string());
+    match message {
+        IpcMessage::ClipboardRead(ClipboardReadMessage { request_id, .. })
+        | IpcMessage::ClipboardWrite(ClipboardWriteMessage { request_id, .. }) => {
+            Some(IpcMessage::ClipboardResponse(ClipboardResponseMessage { request_id: *request_id, text: None, error }))
+        }
+        IpcMessage::FileSystemRequest(request) => Some(IpcMessage::FileSystemResponse(FileSystemResponse {
+            request_id: request.request_id(),
+            handles: Vec::new(),
+            data: None,
+            error,
+        })),
+        IpcMessage::GeolocationRequest(request) => Some(IpcMessage::GeolocationResponse(GeolocationResponseMessage {
+            request_id: request.request_id,
+            position: None,
+            error: error.map(|message| GeolocationError { code: GeolocationError::PERMISSION_DENIED, message }),
+        })),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::MemoryClipboard;
+    use crate::file_system_access::MockFilePickerDialog;
+    use crate::geolocation::{Coords, MockGeolocationBackend};
+    use crate::permissions::PermissionsManager;
+    use common::ipc::{ChannelPair, FileSystemRequest, GeolocationRequestMessage};
+    use common::{Permission, PermissionState};
+    use std::path::PathBuf;
+
+    const ORIGIN: &str = "https://editor.example";
+
+    /// Serve tab 1 of a host whose file pickers return some paths
+    ///
+    /// Returns the renderer end and the window requests passed on.
+    fn serve(
+        permissions: Vec<Permission>,
+        open_paths: Vec<PathBuf>,
+    ) -> (Channel<IpcMessage>, mpsc::UnboundedReceiver<BrowserRequest>, tokio::task::JoinHandle<()>) {
+        let permissions_manager = Arc::new(PermissionsManager::in_memory().unwrap());
+        permissions_manager.set_tab_origin(TabId::new(1), ORIGIN);
+        for permission in permissions {
+            permissions_manager.set_permission(ORIGIN, permission, PermissionState::Granted).unwrap();
+        }
+        let clipboard = Arc::new(ClipboardManager::with_backend(permissions_manager.clone(), Arc::new(MemoryClipboard::default())));
+        let dialog = Arc::new(MockFilePickerDialog::new(open_paths, None));
+        let file_system = Arc::new(FileSystemManager::with_dialog(permissions_manager.clone(), dialog));
+        let geolocation = Arc::new(GeolocationManager::with_backend(permissions_manager, Arc::new(MockGeolocationBackend::new(Coords {
+            latitude: 48.8584,
+            longitude: 2.2945,
+            accuracy: 10.0,
+            altitude: None,
+            altitude_accuracy: None,
+            heading: None,
+            speed: None,
+        }))));
+        let (window_requests, window_request_receiver) = mpsc::unbounded_channel();
+        let host = RendererHost::new(clipboard, file_system, geolocation, window_requests);
+
+        let (browser_end, renderer_end) = ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let server = tokio::spawn(async move { host.serve(TabId::new(1), browser_end).await });
+        (renderer_end, window_request_receiver, server)
+    }
+
+    /// Send a file system request and wait for its response
+    async fn file_system_request(channel: &Channel<IpcMessage>, request: FileSystemRequest) -> FileSystemResponse {
+        channel.send_async(IpcMessage::FileSystemRequest(request)).await.unwrap();
+        match channel.recv_async().await.unwrap() {
+            IpcMessage::FileSystemResponse(response) => response,
+            other => panic!("Unexpected message {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_requests_through_channel() {
+        let (renderer_end, _, server) = serve(vec![Permission::ClipboardRead], Vec::new());
+
+        let mut responses = Vec::new();
+        for request in [
+            IpcMessage::ClipboardWrite(ClipboardWriteMessage { request_id: 1, tab_id: TabId::new(1), text: "\u{1b}[1mnote".to_string() }),
+            IpcMessage::ClipboardRead(ClipboardReadMessage { request_id: 2, tab_id: TabId::new(1) }),
+            IpcMessage::ClipboardRead(ClipboardReadMessage { request_id: 3, tab_id: TabId::new(2) }),
+        ] {
+            renderer_end.send_async(request).await.unwrap();
+            match renderer_end.recv_async().await.unwrap() {
+                IpcMessage::ClipboardResponse(response) => responses.push(response),
+                other => panic!("Unexpected message {:?}", other),
+            }
+        }
+
+        assert_eq!((responses[0].request_id, responses[0].error.as_deref()), (1, None));
+        assert_eq!((responses[1].request_id, responses[1].text.as_deref()), (2, Some("note")));
+        assert_eq!(responses[2].request_id, 3);
+        assert!(responses[2].text.is_none() && responses[2].error.is_some());
+
+        drop(renderer_end);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_system_requests_through_channel() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("draft.md");
+        std::fs::write(&path, b"# Draft").unwrap();
+        let (renderer_end, _, server) = serve(vec![Permission::FileSystemRead], vec![path.clone()]);
+
+        let picked = file_system_request(&renderer_end, FileSystemRequest::ShowOpenFilePicker {
+            request_id: 1,
+            tab_id: TabId::new(1),
+            options: serde_json::json!({ "multiple": false }),
+        }).await;
+        assert_eq!(picked.request_id, 1);
+        assert_eq!(picked.handles.len(), 1);
+        assert_eq!(picked.handles[0].name, "draft.md");
+        let handle_id = picked.handles[0].handle_id;
+
+        let read = file_system_request(&renderer_end, FileSystemRequest::ReadFile { request_id: 2, tab_id: TabId::new(1), handle_id }).await;
+        assert_eq!(read.data.as_deref(), Some(&b"# Draft"[..]));
+
+        // Writing needs its own permission, and handles belong to the tab they were picked for
+        let write = file_system_request(&renderer_end, FileSystemRequest::WriteFile {
+            request_id: 3,
+            tab_id: TabId::new(1),
+            handle_id,
+            data: b"overwritten".to_vec(),
+        }).await;
+        assert!(write.error.is_some());
+        let stolen = file_system_request(&renderer_end, FileSystemRequest::ReadFile { request_id: 4, tab_id: TabId::new(2), handle_id }).await;
+        assert!(stolen.data.is_none() && stolen.error.is_some());
+        assert_eq!(std::fs::read(&path).unwrap(), b"# Draft");
+
+        drop(renderer_end);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_geolocation_requests_through_channel() {
+        let (renderer_end, _, server) = serve(vec![Permission::Geolocation], Vec::new());
+
+        let mut responses = Vec::new();
+        for (request_id, tab) in [(1, 1), (2, 2)] {
+            renderer_end.send_async(IpcMessage::GeolocationRequest(GeolocationRequestMessage {
+                request_id,
+                tab_id: TabId::new(tab),
+                enable_high_accuracy: false,
+                timeout: Some(1000),
+                maximum_age: 0,
+            })).await.unwrap();
+            match renderer_end.recv_async().await.unwrap() {
+                IpcMessage::GeolocationResponse(response) => responses.push(response),
+                other => panic!("Unexpected message {:?}", other),
+            }
+        }
+
+        assert_eq!(responses[0].request_id, 1);
+        let position = responses[0].position.as_ref().unwrap();
+        assert_eq!(position["coords"]["latitude"], 48.8584);
+        assert!(position["coords"]["altitudeAccuracy"].is_null());
+        assert_eq!(responses[1].request_id, 2);
+        assert!(responses[1].position.is_none());
+        assert_eq!(responses[1].error.as_ref().unwrap().code, GeolocationError::PERMISSION_DENIED);
+
+        drop(renderer_end);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_window_requests_go_to_event_loop() {
+        let (renderer_end, mut window_requests, server) = serve(Vec::new(), Vec::new());
+
+        let pip = BrowserRequest::EnterPictureInPicture { tab_id: TabId::new(1), element_id: "player".to_string(), width: 640, height: 360 };
+        for request in [
+            BrowserRequest::EnterFullscreen { tab_id: TabId::new(2), element_id: "player".to_string() },
+            pip.clone(),
+        ] {
+            renderer_end.send_async(IpcMessage::BrowserRequest(request)).await.unwrap();
+        }
+        drop(renderer_end);
+        server.await.unwrap();
+
+        // The request for another tab's window is refused
+        assert_eq!(window_requests.recv().await, Some(pip));
+        assert_eq!(window_requests.recv().await, None);
+    }
+}
@@ -0,0 +1,253 @@
+//! Session restore for the Matte browser
+//!
+//! The open tabs are written to `data_directory/last_session.bin` every few
+//! seconds and on shutdown, so the browser can reopen them after a crash or
+//! restart. The file is a short header with a CRC32 of the bincode payload;
+//! a torn or corrupted file is reported as an error instead of restoring
+//! garbage.
+
+use common::{
+    error::{Error, Result},
+    TabId, TabInfo,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::tab_manager::TabManager;
+
+/// Name of the session file inside the data directory
+const SESSION_FILE_NAME: &str = "last_session.bin";
+
+/// Interval between automatic saves
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Magic bytes at the start of a session file
+const MAGIC: &[u8; 4] = b"MTSS";
+
+/// Version of the session file format
+const FORMAT_VERSION: u32 = 1;
+
+/// Length of the header: magic, version, payload length and CRC32
+const HEADER_LEN: usize = 4 + 4 + 8 + 4;
+
+/// Scroll offset of a document, in CSS pixels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Saved state of a tab
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TabSnapshot {
+    /// Tab the snapshot was taken from
+    pub tab_id: TabId,
+
+    /// URL of the loaded document
+    pub url: String,
+
+    /// Document title
+    pub title: String,
+
+    /// Scroll offset of the document
+    pub scroll_position: Point,
+
+    /// Values of form fields, keyed by field name
+    pub form_data: HashMap<String, String>,
+
+    /// Whether the tab had back history
+    pub can_go_back: bool,
+
+    /// Whether the tab had forward history
+    pub can_go_forward: bool,
+}
+
+impl TabSnapshot {
+    /// Snapshot of a tab without document state
+    pub fn from_tab_info(tab: &TabInfo) -> Self {
+        Self {
+            tab_id: tab.id,
+            url: tab.url.to_string(),
+            title: tab.title.clone(),
+            scroll_position: Point::default(),
+            form_data: HashMap::new(),
+            can_go_back: tab.can_go_back,
+            can_go_forward: tab.can_go_forward,
+        }
+    }
+}
+
+/// Session manager
+pub struct SessionManager {
+    /// Path of the session file
+    path: PathBuf,
+}
+
+impl SessionManager {
+    /// Create a session manager storing the session in a data directory
+    pub fn new(data_directory: &Path) -> Result<Self> {
+        std::fs::create_dir_all(data_directory)?;
+        Ok(Self {
+            path: data_directory.join(SESSION_FILE_NAME),
+        })
+    }
+
+    /// Write the open tabs to the session file
+    ///
+    /// The file is replaced atomically, so a crash while saving leaves the
+    /// previous session intact.
+    pub fn save_session(&self, tabs: &[TabSnapshot]) -> Result<()> {
+        let payload = bincode::serialize(tabs)
+            .map_err(|e| Error::ParseError(format!("Failed to encode session: {}", e)))?;
+
+        let mut contents = Vec::with_capacity(HEADER_LEN + payload.len());
+        contents.extend_from_slice(MAGIC);
+        contents.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        contents.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        contents.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        contents.extend_from_slice(&payload);
+
+        let temporary = self.path.with_extension("bin.tmp");
+        std::fs::write(&temporary, &contents)?;
+        std::fs::rename(&temporary, &self.path)?;
+
+        debug!("Saved session with {} tabs", tabs.len());
+        Ok(())
+    }
+
+    /// Read the tabs of the last session
+    ///
+    /// Returns an empty session if none was saved.
+    pub fn restore_session(&self) -> Result<Vec<TabSnapshot>> {
+        let contents = match std::fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let payload = decode_payload(&contents)?;
+        let tabs: Vec<TabSnapshot> = bincode::deserialize(payload)
+            .map_err(|e| Error::ParseError(format!("Invalid session file: {}", e)))?;
+
+        info!("Restoring session with {} tabs", tabs.len());
+        Ok(tabs)
+    }
+
+    /// Save the tabs of a tab manager
+    pub async fn save_tabs(&self, tab_manager: &RwLock<TabManager>) -> Result<()> {
+        let tabs: Vec<TabSnapshot> = {
+            let tab_mgr = tab_manager.read().await;
+            tab_mgr.get_all_tabs().await.into_iter().map(TabSnapshot::from_tab_info).collect()
+        };
+        self.save_session(&tabs)
+    }
+
+    /// Save the tabs of a tab manager every `AUTOSAVE_INTERVAL`
+    pub fn spawn_autosave(self: Arc<Self>, tab_manager: Arc<RwLock<TabManager>>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(AUTOSAVE_INTERVAL);
+            // The first tick completes immediately
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.save_tabs(&tab_manager).await {
+                    warn!("Failed to save session: {}", e);
+                }
+            }
+        })
+    }
+}
+
+/// Check the header of a session file and return its payload
+fn decode_payload(contents: &[u8]) -> Result<&[u8]> {
+    if contents.len() < HEADER_LEN || &contents[..4] != MAGIC {
+        return Err(Error::ParseError("Not a session file".to_string()));
+    }
+
+    let version = u32::from_le_bytes(contents[4..8].try_into().expect("header length checked"));
+    if version != FORMAT_VERSION {
+        return Err(Error::ParseError(format!("Unsupported session file version {}", version)));
+    }
+
+    let length = u64::from_le_bytes(contents[8..16].try_into().expect("header length checked"));
+    let checksum = u32::from_le_bytes(contents[16..20].try_into().expect("header length checked"));
+    let payload = &contents[HEADER_LEN..];
+    if payload.len() as u64 != length {
+        return Err(Error::ParseError(format!(
+            "Truncated session file: expected {} bytes, found {}",
+            length,
+            payload.len()
+        )));
+    }
+    if crc32fast::hash(payload) != checksum {
+        return Err(Error::ParseError("Session file checksum mismatch".to_string()));
+    }
+
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Vec<TabSnapshot> {
+        (1..=3)
+            .map(|i| TabSnapshot {
+                tab_id: TabId::new(i),
+                url: format!("https://site{}.example/form?step={}", i, i),
+                title: format!("Form {}", i),
+                scroll_position: Point { x: 0.0, y: 120.5 * i as f32 },
+                form_data: HashMap::from([
+                    ("name".to_string(), "Ferris".to_string()),
+                    ("comment".to_string(), "Grüße 🦀".repeat(i as usize)),
+                ]),
+                can_go_back: i > 1,
+                can_go_forward: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_save_and_restore_session() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let manager = SessionManager::new(dir.path()).unwrap();
+        assert!(manager.restore_session().unwrap().is_empty());
+
+        manager.save_session(&session()).unwrap();
+        assert!(dir.path().join(SESSION_FILE_NAME).exists());
+        assert_eq!(SessionManager::new(dir.path()).unwrap().restore_session().unwrap(), session());
+    }
+
+    #[test]
+    fn test_corrupted_session_is_an_error() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let manager = SessionManager::new(dir.path()).unwrap();
+        manager.save_session(&session()).unwrap();
+        let path = dir.path().join(SESSION_FILE_NAME);
+        let contents = std::fs::read(&path).unwrap();
+
+        // Flip bytes in the middle of the payload
+        let mut corrupted = contents.clone();
+        let middle = HEADER_LEN + (corrupted.len() - HEADER_LEN) / 2;
+        for byte in &mut corrupted[middle..middle + 8] {
+            *byte ^= 0x5a;
+        }
+        std::fs::write(&path, &corrupted).unwrap();
+        assert!(matches!(manager.restore_session(), Err(Error::ParseError(_))));
+
+        // A torn write
+        std::fs::write(&path, &contents[..contents.len() - 10]).unwrap();
+        assert!(matches!(manager.restore_session(), Err(Error::ParseError(_))));
+
+        // A file that is not a session
+        std::fs::write(&path, b"garbage").unwrap();
+        assert!(matches!(manager.restore_session(), Err(Error::ParseError(_))));
+    }
+}
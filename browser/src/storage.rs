@@ -0,0 +1,34 @@
+//! Shared `sled` databases for browser process state
+
+use common::error::{Error, Result};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, Weak};
+
+/// Open a database, sharing handles between users in the same process
+///
+/// sled holds an exclusive lock on its directory, so every manager pointing
+/// at the same path must reuse one handle.
+pub fn open_database(path: &Path) -> Result<Arc<sled::Db>> {
+    static OPEN_DATABASES: OnceLock<Mutex<HashMap<PathBuf, Weak<sled::Db>>>> = OnceLock::new();
+
+    let mut open = OPEN_DATABASES.get_or_init(|| Mutex::new(HashMap::new())).lock();
+    if let Some(db) = open.get(path).and_then(Weak::upgrade) {
+        return Ok(db);
+    }
+
+    let db = Arc::new(sled::open(path).map_err(|e| Error::IoError(format!("Failed to open {:?}: {}", path, e)))?);
+    open.retain(|_, db| db.strong_count() > 0);
+    open.insert(path.to_path_buf(), Arc::downgrade(&db));
+    Ok(db)
+}
+
+/// Open a database that is deleted when dropped
+pub fn open_temporary_database() -> Result<Arc<sled::Db>> {
+    sled::Config::new()
+        .temporary(true)
+        .open()
+        .map(Arc::new)
+        .map_err(|e| Error::IoError(format!("Failed to open temporary database: {}", e)))
+}
@@ -1,14 +1,56 @@
 //! Tab manager for the Matte browser
 
-use common::{error::Result, TabId, TabInfo, Url};
+use common::{
+    error::Result,
+    ipc::{AudioControl, AudioEvent, Channel, IpcMessage},
+    TabId, TabInfo, Url,
+};
+use tokio::sync::broadcast;
 use tracing::{debug, error, info, warn};
 use std::collections::HashMap;
 
+use crate::navigation::FaviconEntry;
+
+/// Capacity of the tab event channel; slow subscribers miss older events
+const TAB_EVENT_CAPACITY: usize = 64;
+
+/// Whether a tab plays sound and whether it is muted, shown as its speaker icon
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AudioState {
+    /// The tab is outputting sound
+    pub is_playing: bool,
+    
+    /// The user muted the tab
+    pub is_muted: bool,
+}
+
+/// Change to a tab that the browser UI shows
+#[derive(Debug, Clone, PartialEq)]
+pub enum TabEvent {
+    /// The tab started or stopped playing sound, or was muted or unmuted
+    AudioStateChanged { tab_id: TabId, state: AudioState },
+}
+
 /// Tab manager for handling browser tabs
 pub struct TabManager {
     /// Map of tab ID to tab info
     tabs: HashMap<TabId, TabInfo>,
     
+    /// Favicons of the pages loaded in tabs
+    favicons: HashMap<TabId, FaviconEntry>,
+    
+    /// Audio state of tabs that played sound or were muted
+    tab_audio_state: HashMap<TabId, AudioState>,
+    
+    /// Channels to the audio output of tabs, for muting them
+    audio_channels: HashMap<TabId, Channel<IpcMessage>>,
+    
+    /// Channels to the renderers of tabs
+    renderer_channels: HashMap<TabId, Channel<IpcMessage>>,
+    
+    /// Tab changes for the browser UI
+    events: broadcast::Sender<TabEvent>,
+    
     /// Next tab ID
     next_tab_id: u64,
 }
@@ -20,6 +62,11 @@ impl TabManager {
         
         Ok(Self {
             tabs: HashMap::new(),
+            favicons: HashMap::new(),
+            tab_audio_state: HashMap::new(),
+            audio_channels: HashMap::new(),
+            renderer_channels: HashMap::new(),
+            events: broadcast::channel(TAB_EVENT_CAPACITY).0,
             next_tab_id: 1,
         })
     }
@@ -49,6 +96,10 @@ impl TabManager {
         info!("Closing tab {}", tab_id);
         
         if let Some(tab_info) = self.tabs.remove(&tab_id) {
+            self.favicons.remove(&tab_id);
+            self.tab_audio_state.remove(&tab_id);
+            self.renderer_channels.remove(&tab_id);
+            self.audio_channels.remove(&tab_id);
             info!("Closed tab {} successfully", tab_info.id);
             Ok(())
         } else {
@@ -83,6 +134,8 @@ impl TabManager {
         if let Some(tab_info) = self.tabs.get_mut(&tab_id) {
             tab_info.url = parsed_url;
             tab_info.loading = true;
+            tab_info.favicon = None;
+            self.favicons.remove(&tab_id);
             info!("Navigated tab {} successfully", tab_id);
             Ok(())
         } else {
@@ -131,6 +184,103 @@ impl TabManager {
         }
     }
     
+    /// Show a fetched favicon for the page loaded in a tab
+    pub async fn set_favicon(&mut self, tab_id: TabId, entry: FaviconEntry) -> Result<()> {
+        self.set_tab_favicon(tab_id, entry.url.clone()).await?;
+        self.favicons.insert(tab_id, entry);
+        Ok(())
+    }
+    
+    /// Get the favicon of the page loaded in a tab
+    pub async fn get_favicon(&self, tab_id: TabId) -> Option<FaviconEntry> {
+        self.favicons.get(&tab_id).cloned()
+    }
+    
+    /// Send gain changes over a channel to the audio output of a tab when it is muted
+    pub async fn connect_audio(&mut self, tab_id: TabId, channel: Channel<IpcMessage>) -> Result<()> {
+        self.get_tab(tab_id).await?;
+        self.audio_channels.insert(tab_id, channel);
+        Ok(())
+    }
+    
+    /// Talk to the renderer of a tab over a channel
+    pub async fn connect_renderer(&mut self, tab_id: TabId, channel: Channel<IpcMessage>) -> Result<()> {
+        self.get_tab(tab_id).await?;
+        self.renderer_channels.insert(tab_id, channel);
+        Ok(())
+    }
+    
+    /// Get the channel to the renderer of a tab
+    pub async fn renderer_channel(&self, tab_id: TabId) -> Result<Channel<IpcMessage>> {
+        self.get_tab(tab_id).await?;
+        self.renderer_channels.get(&tab_id).cloned().ok_or_else(|| {
+            common::error::Error::InvalidState(format!("Tab {} has no renderer", tab_id))
+        })
+    }
+    
+    /// Subscribe to tab changes, such as audio state changes for the speaker icon
+    pub fn subscribe_events(&self) -> broadcast::Receiver<TabEvent> {
+        self.events.subscribe()
+    }
+    
+    /// Get the audio state of a tab
+    pub async fn get_audio_state(&self, tab_id: TabId) -> Result<AudioState> {
+        self.get_tab(tab_id).await?;
+        Ok(self.tab_audio_state.get(&tab_id).copied().unwrap_or_default())
+    }
+    
+    /// Record a tab starting or stopping to play sound
+    pub async fn handle_audio_event(&mut self, event: AudioEvent) -> Result<()> {
+        let (tab_id, is_playing) = match event {
+            AudioEvent::Playing { tab_id } => (tab_id, true),
+            AudioEvent::Stopped { tab_id } => (tab_id, false),
+        };
+        
+        let mut state = self.get_audio_state(tab_id).await?;
+        state.is_playing = is_playing;
+        self.set_audio_state(tab_id, state);
+        Ok(())
+    }
+    
+    /// Silence everything a tab plays
+    pub async fn mute_tab(&mut self, tab_id: TabId) -> Result<()> {
+        self.set_tab_muted(tab_id, true).await
+    }
+    
+    /// Let a muted tab play sound again
+    pub async fn unmute_tab(&mut self, tab_id: TabId) -> Result<()> {
+        self.set_tab_muted(tab_id, false).await
+    }
+    
+    async fn set_tab_muted(&mut self, tab_id: TabId, muted: bool) -> Result<()> {
+        let mut state = self.get_audio_state(tab_id).await?;
+        if state.is_muted == muted {
+            return Ok(());
+        }
+        
+        if let Some(channel) = self.audio_channels.get(&tab_id) {
+            let gain = if muted { 0.0 } else { 1.0 };
+            channel.send_async(IpcMessage::AudioControl(AudioControl::SetGain { tab_id, gain })).await?;
+        }
+        
+        state.is_muted = muted;
+        self.set_audio_state(tab_id, state);
+        info!("{} tab {}", if muted { "Muted" } else { "Unmuted" }, tab_id);
+        Ok(())
+    }
+    
+    /// Store the audio state of a tab, notifying subscribers if it changed
+    fn set_audio_state(&mut self, tab_id: TabId, state: AudioState) {
+        if self.tab_audio_state.get(&tab_id).copied().unwrap_or_default() == state {
+            return;
+        }
+        
+        self.tab_audio_state.insert(tab_id, state);
+        // Nobody may be listening yet
+        let _ = self.events.send(TabEvent::AudioStateChanged { tab_id, state });
+        debug!("Tab {} audio state is now {:?}", tab_id, state);
+    }
+    
     /// Get all tabs
     pub async fn get_all_tabs(&self) -> Vec<&TabInfo> {
         self.tabs.values().collect()
@@ -227,4 +377,40 @@ mod tests {
         let tab_info = manager.get_tab(tab_id).await.unwrap();
         assert_eq!(tab_info.title, "New Title");
     }
+
+    #[tokio::test]
+    async fn test_audio_event_and_mute_update_tab_state() {
+        let (browser_end, audio_end) = common::ipc::ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let mut manager = TabManager::new().await.unwrap();
+        let mut events = manager.subscribe_events();
+        
+        let tab_id = manager.create_tab(1, None).await.unwrap();
+        manager.connect_audio(tab_id, browser_end).await.unwrap();
+        assert_eq!(manager.get_audio_state(tab_id).await.unwrap(), AudioState::default());
+        
+        manager.handle_audio_event(AudioEvent::Playing { tab_id }).await.unwrap();
+        let playing = AudioState { is_playing: true, is_muted: false };
+        assert_eq!(manager.get_audio_state(tab_id).await.unwrap(), playing);
+        assert_eq!(events.try_recv().unwrap(), TabEvent::AudioStateChanged { tab_id, state: playing });
+        
+        // Repeated events do not notify again
+        manager.handle_audio_event(AudioEvent::Playing { tab_id }).await.unwrap();
+        assert!(events.try_recv().is_err());
+        
+        manager.mute_tab(tab_id).await.unwrap();
+        let muted = AudioState { is_playing: true, is_muted: true };
+        assert_eq!(manager.get_audio_state(tab_id).await.unwrap(), muted);
+        assert_eq!(events.try_recv().unwrap(), TabEvent::AudioStateChanged { tab_id, state: muted });
+        match audio_end.recv_async().await.unwrap() {
+            IpcMessage::AudioControl(control) => assert_eq!(control, AudioControl::SetGain { tab_id, gain: 0.0 }),
+            other => panic!("Unexpected message {:?}", other),
+        }
+        
+        manager.handle_audio_event(AudioEvent::Stopped { tab_id }).await.unwrap();
+        assert_eq!(manager.get_audio_state(tab_id).await.unwrap(), AudioState { is_playing: false, is_muted: true });
+        
+        let missing = TabId::new(99);
+        assert!(manager.handle_audio_event(AudioEvent::Playing { tab_id: missing }).await.is_err());
+        assert!(manager.mute_tab(missing).await.is_err());
+    }
 }
@@ -1,15 +1,56 @@
 //! Window manager for the Matte browser
 
-use common::{error::Result, WindowInfo};
+use common::{error::Result, ipc::BrowserRequest, TabId, WindowInfo};
 use tracing::{debug, error, info, warn};
 use std::collections::HashMap;
-use winit::window::WindowId;
+use winit::window::{Fullscreen, Window, WindowId};
+
+/// Window that can be made fullscreen, implemented by winit windows
+pub trait FullscreenWindow {
+    /// Get the window ID
+    fn id(&self) -> WindowId;
+    
+    /// Cover the monitor the window is on
+    fn enter_fullscreen(&self);
+    
+    /// Go back to the window's previous size and position
+    fn exit_fullscreen(&self);
+}
+
+impl FullscreenWindow for Window {
+    fn id(&self) -> WindowId {
+        Window::id(self)
+    }
+    
+    /// Switch the monitor to its largest, fastest video mode, or cover it
+    /// with a borderless window if it reports no video modes
+    fn enter_fullscreen(&self) {
+        let video_mode = self.current_monitor().and_then(|monitor| {
+            monitor.video_modes().max_by_key(|mode| {
+                let size = mode.size();
+                (size.width * size.height, mode.refresh_rate_millihertz(), mode.bit_depth())
+            })
+        });
+        
+        self.set_fullscreen(Some(match video_mode {
+            Some(video_mode) => Fullscreen::Exclusive(video_mode),
+            None => Fullscreen::Borderless(None),
+        }));
+    }
+    
+    fn exit_fullscreen(&self) {
+        self.set_fullscreen(None);
+    }
+}
 
 /// Window manager for handling multiple browser windows
 pub struct WindowManager {
     /// Map of window ID to window info
     windows: HashMap<WindowId, WindowInfo>,
     
+    /// Tab that made each fullscreen window fullscreen
+    fullscreen_tabs: HashMap<WindowId, TabId>,
+    
     /// Next window ID
     next_window_id: u64,
 }
@@ -21,6 +62,7 @@ impl WindowManager {
         
         Ok(Self {
             windows: HashMap::new(),
+            fullscreen_tabs: HashMap::new(),
             next_window_id: 1,
         })
     }
@@ -46,6 +88,7 @@ impl WindowManager {
     pub async fn remove_window(&mut self, window_id: WindowId) -> Result<()> {
         info!("Removing window {:?}", window_id);
         
+        self.fullscreen_tabs.remove(&window_id);
         if let Some(window_info) = self.windows.remove(&window_id) {
             info!("Removed window {} successfully", window_info.id);
             Ok(())
@@ -102,6 +145,45 @@ impl WindowManager {
         self.windows.contains_key(&window_id)
     }
     
+    /// Make a window fullscreen for a tab
+    pub async fn enter_fullscreen<W: FullscreenWindow + ?Sized>(&mut self, window: &W, tab_id: TabId) -> Result<()> {
+        let info = self.get_window_mut(window.id()).await?;
+        window.enter_fullscreen();
+        info.fullscreen = true;
+        
+        info!("Window {} entered fullscreen", info.id);
+        self.fullscreen_tabs.insert(window.id(), tab_id);
+        Ok(())
+    }
+    
+    /// Take a window out of fullscreen, returning the tab that made it fullscreen
+    pub async fn exit_fullscreen<W: FullscreenWindow + ?Sized>(&mut self, window: &W) -> Result<Option<TabId>> {
+        let info = self.get_window_mut(window.id()).await?;
+        if info.fullscreen {
+            window.exit_fullscreen();
+            info.fullscreen = false;
+            info!("Window {} left fullscreen", info.id);
+        }
+        Ok(self.fullscreen_tabs.remove(&window.id()))
+    }
+    
+    /// Carry out a request a renderer made for the window showing its tab
+    pub async fn handle_browser_request<W: FullscreenWindow + ?Sized>(&mut self, window: &W, request: BrowserRequest) -> Result<()> {
+        match request {
+            BrowserRequest::EnterFullscreen { tab_id, element_id } => {
+                debug!("Tab {} requested fullscreen for element {}", tab_id, element_id);
+                self.enter_fullscreen(window, tab_id).await
+            }
+            BrowserRequest::ExitFullscreen { tab_id } => {
+                debug!("Tab {} exited fullscreen", tab_id);
+                self.exit_fullscreen(window).await.map(|_| ())
+            }
+            request @ (BrowserRequest::EnterPictureInPicture { .. } | BrowserRequest::ExitPictureInPicture { .. }) => {
+                Err(common::error::Error::InvalidState(format!("{:?} is not a request for a browser window", request)))
+            }
+        }
+    }
+    
     /// Get next window ID
     pub async fn next_window_id(&self) -> u64 {
         self.next_window_id
@@ -122,8 +204,29 @@ impl WindowManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::TabId;
+    use std::cell::RefCell;
     use winit::window::WindowId;
 
+    /// Window recording the fullscreen calls made on it
+    struct MockWindow {
+        calls: RefCell<Vec<&'static str>>,
+    }
+
+    impl FullscreenWindow for MockWindow {
+        fn id(&self) -> WindowId {
+            unsafe { WindowId::dummy() }
+        }
+
+        fn enter_fullscreen(&self) {
+            self.calls.borrow_mut().push("enter");
+        }
+
+        fn exit_fullscreen(&self) {
+            self.calls.borrow_mut().push("exit");
+        }
+    }
+
     #[tokio::test]
     async fn test_window_manager_creation() {
         let manager = WindowManager::new().await;
@@ -166,4 +269,27 @@ mod tests {
         assert_eq!(retrieved_info.width, 800);
         assert_eq!(retrieved_info.height, 600);
     }
+
+    #[tokio::test]
+    async fn test_request_fullscreen_enters_fullscreen() {
+        let mut manager = WindowManager::new().await.unwrap();
+        let window = MockWindow { calls: RefCell::new(Vec::new()) };
+        manager.add_window(window.id(), WindowInfo::new(1, 800, 600)).await.unwrap();
+        
+        let enter = BrowserRequest::EnterFullscreen { tab_id: TabId::new(3), element_id: "player".to_string() };
+        manager.handle_browser_request(&window, enter).await.unwrap();
+        assert_eq!(*window.calls.borrow(), vec!["enter"]);
+        assert!(manager.get_window(window.id()).await.unwrap().fullscreen);
+        
+        let exit = BrowserRequest::ExitFullscreen { tab_id: TabId::new(3) };
+        manager.handle_browser_request(&window, exit.clone()).await.unwrap();
+        manager.handle_browser_request(&window, exit).await.unwrap();
+        assert_eq!(*window.calls.borrow(), vec!["enter", "exit"]);
+        assert!(!manager.get_window(window.id()).await.unwrap().fullscreen);
+        
+        // Leaving fullscreen on the user's behalf reports the tab to tell
+        manager.enter_fullscreen(&window, TabId::new(3)).await.unwrap();
+        assert_eq!(manager.exit_fullscreen(&window).await.unwrap(), Some(TabId::new(3)));
+        assert_eq!(manager.exit_fullscreen(&window).await.unwrap(), None);
+    }
 }
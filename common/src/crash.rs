@@ -6,7 +6,7 @@
 use crate::error::{Error, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
@@ -194,22 +194,10 @@ impl CrashReporter {
 
     /// Collect register information
     async fn collect_registers(&self) -> Result<HashMap<String, u64>> {
-        // This is a simplified implementation
-        // In a real implementation, this would use platform-specific APIs
-        // to capture CPU registers at the time of the crash
-        
-        let mut registers = HashMap::new();
-        registers.insert("rax".to_string(), 0x0);
-        registers.insert("rbx".to_string(), 0x0);
-        registers.insert("rcx".to_string(), 0x0);
-        registers.insert("rdx".to_string(), 0x0);
-        registers.insert("rsi".to_string(), 0x0);
-        registers.insert("rdi".to_string(), 0x0);
-        registers.insert("rsp".to_string(), 0x0);
-        registers.insert("rbp".to_string(), 0x0);
-        registers.insert("rip".to_string(), 0x0);
-
-        Ok(registers)
+        Ok(capture_registers()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect())
     }
 
     /// Collect system information
@@ -370,6 +358,170 @@ impl CrashReporter {
     }
 }
 
+// Panic minidumps
+
+/// Name of the crash dump directory inside the temp directory
+const CRASH_DIRECTORY_NAME: &str = "crashes";
+
+/// Directory panic dumps are written to, once the hook is installed
+static DUMP_DIRECTORY: parking_lot::Mutex<Option<PathBuf>> = parking_lot::const_mutex(None);
+
+/// Metadata attached to every dump, kept sorted for stable output
+static CRASH_METADATA: parking_lot::Mutex<std::collections::BTreeMap<String, String>> =
+    parking_lot::const_mutex(std::collections::BTreeMap::new());
+
+/// Whether the panic hook has been installed
+static HOOK_INSTALLED: std::sync::Once = std::sync::Once::new();
+
+impl CrashReporter {
+    /// Install a panic hook that writes a dump to `temp_directory/crashes`
+    ///
+    /// Each panic produces a `{timestamp}.dmp` file with the panic message,
+    /// the registered metadata, the registers of the panicking thread and its
+    /// stack trace. The previous hook still runs afterwards. Installing again
+    /// only moves the dump directory.
+    pub fn install(temp_directory: &Path) -> Result<()> {
+        let directory = temp_directory.join(CRASH_DIRECTORY_NAME);
+        std::fs::create_dir_all(&directory)
+            .map_err(|e| Error::IoError(format!("Failed to create crash directory: {}", e)))?;
+        *DUMP_DIRECTORY.lock() = Some(directory);
+
+        HOOK_INSTALLED.call_once(|| {
+            let previous_hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                // A failure to write the dump must not hide the original panic
+                let _ = write_panic_dump(info);
+                previous_hook(info);
+            }));
+        });
+
+        debug!("Installed crash reporter panic hook");
+        Ok(())
+    }
+
+    /// Attach a key-value pair, such as the version or tab URL, to future dumps
+    pub fn register_metadata(key: &str, value: &str) {
+        CRASH_METADATA.lock().insert(key.to_string(), value.to_string());
+    }
+}
+
+/// Write a dump for a panic and return its path
+fn write_panic_dump(info: &std::panic::PanicHookInfo<'_>) -> std::io::Result<PathBuf> {
+    use std::fmt::Write as _;
+    use std::io::Write as _;
+
+    let Some(directory) = DUMP_DIRECTORY.lock().clone() else {
+        return Err(std::io::ErrorKind::NotFound.into());
+    };
+
+    let registers = capture_registers();
+    let backtrace = std::backtrace::Backtrace::force_capture();
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let thread = std::thread::current();
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+
+    let mut dump = String::new();
+    let _ = writeln!(dump, "MATTE CRASH DUMP");
+    let _ = writeln!(dump, "timestamp: {}", timestamp);
+    let _ = writeln!(dump, "process_id: {}", std::process::id());
+    let _ = writeln!(dump, "thread: {} ({:?})", thread.name().unwrap_or("<unnamed>"), thread.id());
+    let _ = writeln!(dump, "\n[panic]\nmessage: {}", message);
+    if let Some(location) = info.location() {
+        let _ = writeln!(dump, "location: {}", location);
+    }
+    let _ = writeln!(dump, "\n[metadata]");
+    for (key, value) in CRASH_METADATA.lock().iter() {
+        let _ = writeln!(dump, "{}: {}", key, value);
+    }
+    let _ = writeln!(dump, "\n[registers]");
+    for (name, value) in &registers {
+        let _ = writeln!(dump, "{}: {:#018x}", name, value);
+    }
+    let _ = writeln!(dump, "\n[backtrace]\n{}", backtrace);
+
+    // Panics in the same millisecond get a numbered suffix
+    let mut suffix = 0;
+    loop {
+        let name = if suffix == 0 {
+            format!("{}.dmp", timestamp)
+        } else {
+            format!("{}-{}.dmp", timestamp, suffix)
+        };
+        let path = directory.join(name);
+        match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(dump.as_bytes())?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => suffix += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// General purpose registers of the calling thread, in a stable order
+///
+/// Empty on platforms where the thread context cannot be read.
+#[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+fn capture_registers() -> Vec<(&'static str, u64)> {
+    const NAMES: [(&str, libc::c_int); 18] = [
+        ("rax", libc::REG_RAX), ("rbx", libc::REG_RBX), ("rcx", libc::REG_RCX), ("rdx", libc::REG_RDX),
+        ("rsi", libc::REG_RSI), ("rdi", libc::REG_RDI), ("rbp", libc::REG_RBP), ("rsp", libc::REG_RSP),
+        ("r8", libc::REG_R8), ("r9", libc::REG_R9), ("r10", libc::REG_R10), ("r11", libc::REG_R11),
+        ("r12", libc::REG_R12), ("r13", libc::REG_R13), ("r14", libc::REG_R14), ("r15", libc::REG_R15),
+        ("rip", libc::REG_RIP), ("eflags", libc::REG_EFL),
+    ];
+
+    // SAFETY: getcontext only writes to the zeroed context it is given
+    let mut context: libc::ucontext_t = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getcontext(&mut context) } != 0 {
+        return Vec::new();
+    }
+    NAMES
+        .iter()
+        .map(|&(name, index)| (name, context.uc_mcontext.gregs[index as usize] as u64))
+        .collect()
+}
+
+/// General purpose registers of the calling thread, in a stable order
+///
+/// Empty on platforms where the thread context cannot be read.
+#[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "aarch64"))]
+fn capture_registers() -> Vec<(&'static str, u64)> {
+    const NAMES: [&str; 31] = [
+        "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11", "x12", "x13", "x14", "x15",
+        "x16", "x17", "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27", "x28", "fp", "lr",
+    ];
+
+    // SAFETY: getcontext only writes to the zeroed context it is given
+    let mut context: libc::ucontext_t = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getcontext(&mut context) } != 0 {
+        return Vec::new();
+    }
+    let mcontext = &context.uc_mcontext;
+    let mut registers: Vec<(&'static str, u64)> = NAMES.iter().copied().zip(mcontext.regs).collect();
+    registers.push(("sp", mcontext.sp));
+    registers.push(("pc", mcontext.pc));
+    registers.push(("pstate", mcontext.pstate));
+    registers
+}
+
+/// General purpose registers of the calling thread, in a stable order
+///
+/// Empty on platforms where the thread context cannot be read.
+#[cfg(not(all(target_os = "linux", target_env = "gnu", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+fn capture_registers() -> Vec<(&'static str, u64)> {
+    Vec::new()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -465,4 +617,38 @@ mod tests {
         let remaining_reports = reporter.get_crash_reports().await;
         assert_eq!(remaining_reports.len(), 5);
     }
+
+    #[test]
+    fn test_panic_writes_dump() {
+        let temp_directory = std::env::temp_dir().join(format!("matte-crash-test-{}", std::process::id()));
+        CrashReporter::install(&temp_directory).unwrap();
+        CrashReporter::register_metadata("version", "0.1.0");
+        CrashReporter::register_metadata("tab_url", "https://example.com/crash");
+        CrashReporter::register_metadata("process_type", "renderer");
+
+        let result = std::thread::Builder::new()
+            .name("crashing".to_string())
+            .spawn(|| panic!("controlled crash {}", 42))
+            .unwrap()
+            .join();
+        assert!(result.is_err());
+
+        let crash_directory = temp_directory.join(CRASH_DIRECTORY_NAME);
+        let dumps: Vec<String> = std::fs::read_dir(&crash_directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "dmp"))
+            .map(|path| std::fs::read_to_string(path).unwrap())
+            .collect();
+        let dump = dumps.iter().find(|dump| dump.contains("controlled crash 42")).expect("no dump written");
+        assert!(dump.contains("thread: crashing"));
+        assert!(dump.contains("tab_url: https://example.com/crash"));
+        assert!(dump.contains("process_type: renderer"));
+        assert!(dump.contains("[backtrace]"));
+        if cfg!(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64")) {
+            assert!(dump.contains("rip: 0x"));
+        }
+
+        std::fs::remove_dir_all(&temp_directory).unwrap();
+    }
 }
@@ -0,0 +1,187 @@
+//! Breakpoints and step execution shared by the JavaScript engines
+//!
+//! DevTools commands are applied to a `Debugger`, which an engine consults
+//! before executing each instruction of a debuggable execution and which
+//! tells it when to suspend the current frame.
+
+use crate::ipc::{DebuggerRequest, PauseReason};
+use std::collections::HashMap;
+
+/// Script position of a breakpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// Script URL
+    pub url: String,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column number, 0 matching any column
+    pub column: usize,
+}
+
+/// Step command being executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StepMode {
+    Into,
+    Over,
+    Out,
+}
+
+/// Call depth and line of an instruction
+type Location = (usize, usize);
+
+/// Debugger attached to a JavaScript engine
+#[derive(Debug, Default)]
+pub struct Debugger {
+    breakpoints: HashMap<u64, Breakpoint>,
+    /// Step being executed and the location it started from
+    step: Option<(StepMode, Location)>,
+    /// Location of the last pause
+    ///
+    /// The remaining instructions of the paused line run without pausing again.
+    paused_at: Option<Location>,
+}
+
+impl Debugger {
+    /// Create a debugger without breakpoints
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of breakpoints set
+    pub fn breakpoint_count(&self) -> usize {
+        self.breakpoints.len()
+    }
+
+    /// Get the breakpoints, by breakpoint ID
+    pub fn breakpoints(&self) -> &HashMap<u64, Breakpoint> {
+        &self.breakpoints
+    }
+
+    /// Apply a command from DevTools
+    ///
+    /// Returns whether the command continues a paused execution.
+    /// Expressions are evaluated by the engine, so `Evaluate` changes nothing.
+    pub fn handle_request(&mut self, request: DebuggerRequest) -> bool {
+        let mode = match request {
+            DebuggerRequest::SetBreakpoint { breakpoint_id, url, line, column } => {
+                self.breakpoints.insert(breakpoint_id, Breakpoint {
+                    url,
+                    line: line as usize,
+                    column: column as usize,
+                });
+                return false;
+            }
+            DebuggerRequest::RemoveBreakpoint { breakpoint_id } => {
+                self.breakpoints.remove(&breakpoint_id);
+                return false;
+            }
+            DebuggerRequest::Evaluate { .. } => return false,
+            DebuggerRequest::Resume => {
+                self.step = None;
+                return true;
+            }
+            DebuggerRequest::StepInto => StepMode::Into,
+            DebuggerRequest::StepOver => StepMode::Over,
+            DebuggerRequest::StepOut => StepMode::Out,
+        };
+        self.step = self.paused_at.map(|origin| (mode, origin));
+        true
+    }
+
+    /// Decide whether to pause before an instruction
+    ///
+    /// `depth` is the call stack depth of the executing frame and `position`
+    /// the line and column of the instruction, when its function has a
+    /// source map.
+    pub fn check(&mut self, url: &str, depth: usize, position: Option<(usize, usize)>, is_debugger_statement: bool) -> Option<PauseReason> {
+        let location = position.map(|(line, _)| (depth, line));
+        if location.is_some() {
+            if location == self.paused_at {
+                return None;
+            }
+            self.paused_at = None;
+        }
+
+        let reason = if is_debugger_statement {
+            PauseReason::DebuggerStatement
+        } else if let Some(breakpoint_id) = position.and_then(|position| self.breakpoint_at(url, position)) {
+            PauseReason::Breakpoint { breakpoint_id }
+        } else if self.step_completed(depth, location) {
+            PauseReason::Step
+        } else {
+            return None;
+        };
+
+        self.step = None;
+        self.paused_at = location;
+        Some(reason)
+    }
+
+    /// Find the breakpoint set at a line and column
+    fn breakpoint_at(&self, url: &str, (line, column): (usize, usize)) -> Option<u64> {
+        self.breakpoints.iter()
+            .filter(|(_, breakpoint)| {
+                breakpoint.url == url
+                    && breakpoint.line == line
+                    && (breakpoint.column == 0 || breakpoint.column == column)
+            })
+            .map(|(id, _)| *id)
+            .min()
+    }
+
+    /// Check whether the pending step ends before an instruction
+    fn step_completed(&self, depth: usize, location: Option<Location>) -> bool {
+        let Some((mode, (origin_depth, origin_line))) = self.step else {
+            return false;
+        };
+        match mode {
+            StepMode::Out => depth < origin_depth,
+            StepMode::Over => {
+                depth < origin_depth || location.is_some_and(|(depth, line)| depth == origin_depth && line != origin_line)
+            }
+            StepMode::Into => location.is_some_and(|location| location != (origin_depth, origin_line)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://example.com/app.js";
+
+    #[test]
+    fn test_step_into_and_out_follow_call_depth() {
+        let line = |line| (line, 1);
+        let mut debugger = Debugger::new();
+        debugger.handle_request(DebuggerRequest::SetBreakpoint {
+            breakpoint_id: 1,
+            url: URL.to_string(),
+            line: 5,
+            column: 0,
+        });
+        assert_eq!(debugger.breakpoints()[&1].line, 5);
+        assert_eq!(debugger.check(URL, 1, Some(line(5)), false), Some(PauseReason::Breakpoint { breakpoint_id: 1 }));
+
+        // Evaluating an expression does not continue the execution
+        assert!(!debugger.handle_request(DebuggerRequest::Evaluate { request_id: 1, expression: "a".to_string() }));
+
+        // Stepping over skips the called function, stepping into stops in it
+        debugger.handle_request(DebuggerRequest::StepOver);
+        assert_eq!(debugger.check(URL, 2, Some(line(1)), false), None);
+        assert_eq!(debugger.check(URL, 1, Some(line(6)), false), Some(PauseReason::Step));
+
+        debugger.handle_request(DebuggerRequest::StepInto);
+        assert_eq!(debugger.check(URL, 2, Some(line(1)), false), Some(PauseReason::Step));
+
+        // Stepping out runs the rest of the callee
+        debugger.handle_request(DebuggerRequest::StepOut);
+        assert_eq!(debugger.check(URL, 2, Some(line(2)), false), None);
+        assert_eq!(debugger.check(URL, 1, Some(line(6)), false), Some(PauseReason::Step));
+
+        // Without a step only breakpoints pause
+        debugger.handle_request(DebuggerRequest::Resume);
+        assert_eq!(debugger.check(URL, 1, Some(line(7)), false), None);
+        assert_eq!(debugger.check("https://example.com/other.js", 1, Some(line(5)), false), None);
+    }
+}
@@ -0,0 +1,26 @@
+//! View of a JavaScript heap for memory tools
+//!
+//! Engines expose their heap through `HeapInspector`, so DevTools can take
+//! heap snapshots without depending on an engine.
+
+/// Object on the heap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeapObject {
+    /// Object ID, stable for the object's lifetime
+    pub id: u64,
+    /// Object type, such as `Array` or a constructor name
+    pub object_type: String,
+    /// Size of the object itself in bytes
+    pub size: usize,
+    /// IDs of the objects it references, in order
+    pub references: Vec<u64>,
+}
+
+/// Heap whose live objects can be listed
+pub trait HeapInspector: Send + Sync {
+    /// Get the objects that are still live, ordered by ID
+    fn live_objects(&self) -> Vec<HeapObject>;
+
+    /// Get the IDs of the objects held by GC roots
+    fn root_object_ids(&self) -> Vec<u64>;
+}
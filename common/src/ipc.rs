@@ -23,11 +23,21 @@ pub enum IpcMessage {
     RendererCrashed(RendererCrashedRequest),
     RendererResponse(RendererResponseRequest),
     
+    // Cross-process frame messages
+    DomPropertyQuery(DomPropertyQueryMessage),
+    DomPropertyResponse(DomPropertyResponseMessage),
+    
     // Network process messages
     NetworkRequest(NetworkRequestMessage),
     NetworkResponse(NetworkResponseMessage),
     NetworkError(NetworkErrorMessage),
     
+    // WebSocket messages, frames and closes flow in both directions
+    WebSocketOpen(WebSocketOpenMessage),
+    WebSocketOpened(WebSocketOpenedMessage),
+    WebSocketFrame(WebSocketFrameMessage),
+    WebSocketClosed(WebSocketClosedMessage),
+    
     // GPU process messages
     GpuCommand(GpuCommandMessage),
     GpuResponse(GpuResponseMessage),
@@ -36,6 +46,40 @@ pub enum IpcMessage {
     PermissionRequest(PermissionRequestMessage),
     PermissionResponse(PermissionResponseMessage),
     
+    // Clipboard access brokered by the browser process
+    ClipboardRead(ClipboardReadMessage),
+    ClipboardWrite(ClipboardWriteMessage),
+    ClipboardResponse(ClipboardResponseMessage),
+    
+    // File System Access brokered by the browser process
+    FileSystemRequest(FileSystemRequest),
+    FileSystemResponse(FileSystemResponse),
+    
+    // Geolocation brokered by the browser process
+    GeolocationRequest(GeolocationRequestMessage),
+    GeolocationResponse(GeolocationResponseMessage),
+    
+    // Requests from a renderer for the browser window of its tab, and changes
+    // the browser made to the window on its own
+    BrowserRequest(BrowserRequest),
+    BrowserEvent(BrowserEvent),
+    
+    // Audio output of tabs, reported to the browser and controlled by it
+    AudioEvent(AudioEvent),
+    AudioControl(AudioControl),
+    
+    // Printing, requested by the browser from the renderer of a tab
+    PrintRequest(PrintRequest),
+    PrintResponse(PrintResponse),
+    
+    // DevTools messages between an inspector and a renderer
+    DevToolsRequest(DevToolsRequest),
+    DevToolsResponse(DevToolsResponse),
+    
+    // JavaScript debugger commands to a renderer and its notifications
+    DebuggerRequest(DebuggerRequest),
+    DebuggerEvent(DebuggerEvent),
+    
     // System messages
     Ping(PingMessage),
     Pong(PongMessage),
@@ -196,6 +240,7 @@ pub struct RendererCrashedRequest {
     pub renderer_id: RendererId,
     pub tab_id: TabId,
     pub reason: String,
+    pub can_reload: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -206,6 +251,19 @@ pub struct RendererResponseRequest {
     pub data: serde_json::Value,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomPropertyQueryMessage {
+    pub query_id: u64,
+    pub element_id: String,
+    pub property: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomPropertyResponseMessage {
+    pub query_id: u64,
+    pub value: Option<serde_json::Value>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkRequestMessage {
     pub request_id: u64,
@@ -233,6 +291,43 @@ pub struct NetworkErrorMessage {
     pub error: String,
 }
 
+/// Data message sent or received on a WebSocket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WsMessage {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketOpenMessage {
+    pub request_id: u64,
+    pub tab_id: TabId,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketOpenedMessage {
+    pub request_id: u64,
+    pub tab_id: TabId,
+    pub socket_id: u64,
+    pub protocol: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketFrameMessage {
+    pub socket_id: u64,
+    pub tab_id: TabId,
+    pub message: WsMessage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketClosedMessage {
+    pub socket_id: u64,
+    pub tab_id: TabId,
+    pub code: Option<u16>,
+    pub reason: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GpuCommandMessage {
     pub command_id: u64,
@@ -265,6 +360,316 @@ pub struct PermissionResponseMessage {
     pub state: PermissionState,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardReadMessage {
+    pub request_id: u64,
+    pub tab_id: TabId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardWriteMessage {
+    pub request_id: u64,
+    pub tab_id: TabId,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardResponseMessage {
+    pub request_id: u64,
+    pub text: Option<String>,
+    pub error: Option<String>,
+}
+
+/// File System Access request from a renderer
+///
+/// Picker options are the dictionaries pages pass, in their JSON form.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FileSystemRequest {
+    /// Pick files to open, as `showOpenFilePicker(options)`
+    ShowOpenFilePicker { request_id: u64, tab_id: TabId, options: serde_json::Value },
+    /// Pick a file to save to, as `showSaveFilePicker(options)`
+    ShowSaveFilePicker { request_id: u64, tab_id: TabId, options: serde_json::Value },
+    /// Read a picked file, as `FileSystemFileHandle.getFile()`
+    ReadFile { request_id: u64, tab_id: TabId, handle_id: u64 },
+    /// Replace the contents of a picked file, as closing a writable stream does
+    WriteFile { request_id: u64, tab_id: TabId, handle_id: u64, data: Vec<u8> },
+}
+
+impl FileSystemRequest {
+    /// Get the ID the response is sent with
+    pub fn request_id(&self) -> u64 {
+        match self {
+            FileSystemRequest::ShowOpenFilePicker { request_id, .. }
+            | FileSystemRequest::ShowSaveFilePicker { request_id, .. }
+            | FileSystemRequest::ReadFile { request_id, .. }
+            | FileSystemRequest::WriteFile { request_id, .. } => *request_id,
+        }
+    }
+
+    /// Get the tab the request was made for
+    pub fn tab_id(&self) -> TabId {
+        match self {
+            FileSystemRequest::ShowOpenFilePicker { tab_id, .. }
+            | FileSystemRequest::ShowSaveFilePicker { tab_id, .. }
+            | FileSystemRequest::ReadFile { tab_id, .. }
+            | FileSystemRequest::WriteFile { tab_id, .. } => *tab_id,
+        }
+    }
+}
+
+/// Picked file as a renderer sees it, without its path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileHandleInfo {
+    pub handle_id: u64,
+    pub name: String,
+}
+
+/// Answer to a `FileSystemRequest`: picked files, the contents read, or why it failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSystemResponse {
+    pub request_id: u64,
+    pub handles: Vec<FileHandleInfo>,
+    pub data: Option<Vec<u8>>,
+    pub error: Option<String>,
+}
+
+/// Position request from a renderer, as `navigator.geolocation.getCurrentPosition(options)`
+///
+/// Durations are in milliseconds, as pages pass them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeolocationRequestMessage {
+    pub request_id: u64,
+    pub tab_id: TabId,
+    pub enable_high_accuracy: bool,
+    pub timeout: Option<u64>,
+    pub maximum_age: u64,
+}
+
+/// Failed position request, as a `GeolocationPositionError`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeolocationError {
+    pub code: u16,
+    pub message: String,
+}
+
+impl GeolocationError {
+    pub const PERMISSION_DENIED: u16 = 1;
+    pub const POSITION_UNAVAILABLE: u16 = 2;
+    pub const TIMEOUT: u16 = 3;
+}
+
+/// Answer to a `GeolocationRequestMessage`: the position in its JavaScript form, or why there is none
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeolocationResponseMessage {
+    pub request_id: u64,
+    pub position: Option<serde_json::Value>,
+    pub error: Option<GeolocationError>,
+}
+
+/// Request from a renderer to the browser window showing its tab
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BrowserRequest {
+    /// Make the window fullscreen for an element, as `Element.requestFullscreen()`
+    EnterFullscreen { tab_id: TabId, element_id: String },
+    /// Leave fullscreen, as `document.exitFullscreen()`
+    ExitFullscreen { tab_id: TabId },
+    /// Show a video element in a Picture-in-Picture window of its laid out size,
+    /// as `HTMLVideoElement.requestPictureInPicture()`
+    EnterPictureInPicture { tab_id: TabId, element_id: String, width: u32, height: u32 },
+    /// Close the Picture-in-Picture window, as `document.exitPictureInPicture()`
+    ExitPictureInPicture { tab_id: TabId },
+}
+
+impl BrowserRequest {
+    /// Get the tab the request was made for
+    pub fn tab_id(&self) -> TabId {
+        match self {
+            BrowserRequest::EnterFullscreen { tab_id, .. }
+            | BrowserRequest::ExitFullscreen { tab_id }
+            | BrowserRequest::EnterPictureInPicture { tab_id, .. }
+            | BrowserRequest::ExitPictureInPicture { tab_id } => *tab_id,
+        }
+    }
+}
+
+/// Change the browser made to the window showing a tab, sent to the tab's renderer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BrowserEvent {
+    /// The user took the window out of fullscreen, for example with Escape
+    FullscreenExited { tab_id: TabId },
+}
+
+/// Change in the audio output of a tab, sent to the browser
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioEvent {
+    /// A media element or audio context of the tab began outputting sound
+    Playing { tab_id: TabId },
+    /// The tab stopped outputting sound
+    Stopped { tab_id: TabId },
+}
+
+/// Command from the browser to the audio output of a tab
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AudioControl {
+    /// Scale the volume of everything the tab plays, 0.0 muting it
+    SetGain { tab_id: TabId, gain: f32 },
+}
+
+/// Size of a sheet of paper, in points (1/72 inch)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PaperSize {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl PaperSize {
+    /// ISO A4, 210 × 297 mm
+    pub const A4: PaperSize = PaperSize { width: 595.28, height: 841.89 };
+    /// US Letter, 8.5 × 11 in
+    pub const LETTER: PaperSize = PaperSize { width: 612.0, height: 792.0 };
+    /// US Legal, 8.5 × 14 in
+    pub const LEGAL: PaperSize = PaperSize { width: 612.0, height: 1008.0 };
+}
+
+impl Default for PaperSize {
+    fn default() -> Self {
+        PaperSize::A4
+    }
+}
+
+/// Blank space around the content of a printed page, in points
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PageMargins {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl PageMargins {
+    /// The same margin on every side
+    pub fn uniform(margin: f32) -> Self {
+        Self { top: margin, right: margin, bottom: margin, left: margin }
+    }
+}
+
+impl Default for PageMargins {
+    fn default() -> Self {
+        // Half an inch
+        Self::uniform(36.0)
+    }
+}
+
+/// A printed page, rasterized to RGBA pixels at 96 pixels per inch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenderedPage {
+    pub paper_size: PaperSize,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Request from the browser to print the document of a tab
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintRequest {
+    pub request_id: u64,
+    pub tab_id: TabId,
+    pub paper_size: PaperSize,
+    pub margins: PageMargins,
+}
+
+/// Pages printed for a `PrintRequest`, or why printing failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintResponse {
+    pub request_id: u64,
+    pub tab_id: TabId,
+    pub pages: Vec<RenderedPage>,
+    pub error: Option<String>,
+}
+
+/// Request from DevTools to the renderer of a tab
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DevToolsRequest {
+    /// Serialize the document of the tab as JSON
+    GetDomTree { request_id: u64, tab_id: TabId },
+    /// Draw the inspector overlay over an element, or remove it
+    HighlightElement { tab_id: TabId, element_id: Option<String> },
+    /// Capture the rendered page
+    CaptureScreenshot { request_id: u64, tab_id: TabId },
+}
+
+/// Reply from a renderer to a DevTools request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DevToolsResponse {
+    /// JSON-encoded DOM tree
+    DomTree { request_id: u64, tree: String },
+    /// Screenshot of the page as RGBA pixels, row by row
+    Screenshot { request_id: u64, width: u32, height: u32, pixels: Vec<u8> },
+    /// The request could not be answered
+    Error { request_id: u64, message: String },
+}
+
+/// Debugger command from DevTools to a renderer's JavaScript VM
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DebuggerRequest {
+    /// Pause before the first instruction at a script position
+    ///
+    /// A column of 0 matches any column of the line.
+    SetBreakpoint { breakpoint_id: u64, url: String, line: u32, column: u32 },
+    /// Remove a breakpoint
+    RemoveBreakpoint { breakpoint_id: u64 },
+    /// Continue until the next breakpoint
+    Resume,
+    /// Run to the next line of the paused function, stepping over calls
+    StepOver,
+    /// Run to the next line, entering calls
+    StepInto,
+    /// Run until the paused function returns
+    StepOut,
+    /// Evaluate an expression in the global scope of the page
+    Evaluate { request_id: u64, expression: String },
+}
+
+/// Notification from a renderer's JavaScript VM to DevTools
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DebuggerEvent {
+    /// Execution is suspended, innermost frame first
+    Paused { call_frames: Vec<DebuggerCallFrame>, reason: PauseReason },
+    /// Execution continued after a pause
+    Resumed,
+    /// Result of an `Evaluate` request, the value encoded as JSON
+    Evaluated { request_id: u64, value: Option<String>, error: Option<String> },
+}
+
+/// Why the JavaScript VM paused
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PauseReason {
+    /// A breakpoint was hit
+    Breakpoint { breakpoint_id: u64 },
+    /// A step command completed
+    Step,
+    /// A `debugger` statement was executed
+    DebuggerStatement,
+}
+
+/// Stack frame of paused JavaScript
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DebuggerCallFrame {
+    pub function_name: String,
+    pub url: String,
+    pub line: u32,
+    pub column: u32,
+    /// Variables in scope, with their values rendered as strings
+    pub scope: Vec<ScopeVariable>,
+}
+
+/// Variable in scope of a paused frame
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScopeVariable {
+    pub name: String,
+    pub value: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PingMessage {
     pub timestamp: std::time::SystemTime,
@@ -582,6 +987,546 @@ impl Default for IpcManager {
     }
 }
 
+// Typed Channels
+
+/// Largest message accepted from a channel, in bytes
+pub const MAX_CHANNEL_MESSAGE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Typed, framed channel to another process
+///
+/// A channel sends messages of type `S` and receives messages of type `R`.
+/// Each message is JSON preceded by its length as a 4-byte little-endian
+/// integer. Channels run over a Unix domain socket on Linux and macOS and
+/// over named pipes on Windows.
+///
+/// Clones share the underlying connection. Sends and receives may happen
+/// concurrently; dropping a pending `recv_async` loses the message it was
+/// reading.
+pub struct Channel<S, R = S> {
+    reader: std::sync::Arc<parking_lot::Mutex<Box<dyn std::io::Read + Send>>>,
+    writer: std::sync::Arc<parking_lot::Mutex<Box<dyn std::io::Write + Send>>>,
+    _messages: std::marker::PhantomData<fn(S) -> R>,
+}
+
+impl<S, R> Clone for Channel<S, R> {
+    fn clone(&self) -> Self {
+        Self {
+            reader: self.reader.clone(),
+            writer: self.writer.clone(),
+            _messages: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<S, R> std::fmt::Debug for Channel<S, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Channel").finish_non_exhaustive()
+    }
+}
+
+impl<S: Serialize, R: serde::de::DeserializeOwned> Channel<S, R> {
+    /// Channel over a read and a write stream
+    fn from_streams(reader: Box<dyn std::io::Read + Send>, writer: Box<dyn std::io::Write + Send>) -> Self {
+        Self {
+            reader: std::sync::Arc::new(parking_lot::Mutex::new(reader)),
+            writer: std::sync::Arc::new(parking_lot::Mutex::new(writer)),
+            _messages: std::marker::PhantomData,
+        }
+    }
+
+    /// Send a message, blocking until it is written
+    pub fn send(&self, message: S) -> crate::Result<()> {
+        let frame = encode_frame(&message)?;
+        write_frame(&mut **self.writer.lock(), &frame)
+    }
+
+    /// Receive a message, blocking until one arrives
+    pub fn recv(&self) -> crate::Result<R> {
+        let payload = read_frame(&mut **self.reader.lock())?;
+        Ok(serde_json::from_slice(&payload)?)
+    }
+
+    /// Send a message without blocking the async runtime
+    pub async fn send_async(&self, message: S) -> crate::Result<()> {
+        let frame = encode_frame(&message)?;
+        let writer = self.writer.clone();
+        tokio::task::spawn_blocking(move || write_frame(&mut **writer.lock(), &frame))
+            .await
+            .map_err(|e| crate::error::Error::IpcError(format!("Channel send task failed: {}", e)))?
+    }
+
+    /// Receive a message without blocking the async runtime
+    pub async fn recv_async(&self) -> crate::Result<R> {
+        let reader = self.reader.clone();
+        let payload = tokio::task::spawn_blocking(move || read_frame(&mut **reader.lock()))
+            .await
+            .map_err(|e| crate::error::Error::IpcError(format!("Channel receive task failed: {}", e)))??;
+        Ok(serde_json::from_slice(&payload)?)
+    }
+}
+
+/// Creates connected channel endpoints
+pub struct ChannelPair;
+
+impl ChannelPair {
+    /// Create two connected endpoints
+    ///
+    /// Messages of type `A` sent on the first endpoint are received on the
+    /// second, and messages of type `B` flow the other way.
+    pub fn create<A, B>() -> crate::Result<(Channel<A, B>, Channel<B, A>)>
+    where
+        A: Serialize + serde::de::DeserializeOwned,
+        B: Serialize + serde::de::DeserializeOwned,
+    {
+        #[cfg(unix)]
+        {
+            let (first, second) = std::os::unix::net::UnixStream::pair()?;
+            Ok((
+                Channel::from_streams(Box::new(first.try_clone()?), Box::new(first)),
+                Channel::from_streams(Box::new(second.try_clone()?), Box::new(second)),
+            ))
+        }
+
+        #[cfg(windows)]
+        {
+            // One pipe per direction, since synchronous reads and writes on a
+            // single pipe handle would wait for each other
+            let (first_reader, second_writer) = named_pipe::pipe()?;
+            let (second_reader, first_writer) = named_pipe::pipe()?;
+            Ok((
+                Channel::from_streams(Box::new(first_reader), Box::new(first_writer)),
+                Channel::from_streams(Box::new(second_reader), Box::new(second_writer)),
+            ))
+        }
+    }
+}
+
+/// Serialize a message into a length-prefixed frame
+fn encode_frame<T: Serialize>(message: &T) -> crate::Result<Vec<u8>> {
+    let payload = serde_json::to_vec(message)?;
+    if payload.len() > MAX_CHANNEL_MESSAGE_BYTES {
+        return Err(crate::error::Error::IpcError(format!(
+            "Message of {} bytes exceeds the channel limit", payload.len()
+        )));
+    }
+
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+/// Write a frame and flush it
+fn write_frame(writer: &mut dyn std::io::Write, frame: &[u8]) -> crate::Result<()> {
+    writer.write_all(frame).and_then(|_| writer.flush()).map_err(channel_error)
+}
+
+/// Read the payload of one length-prefixed frame
+fn read_frame(reader: &mut dyn std::io::Read) -> crate::Result<Vec<u8>> {
+    let mut header = [0u8; 4];
+    reader.read_exact(&mut header).map_err(channel_error)?;
+
+    let length = u32::from_le_bytes(header) as usize;
+    if length > MAX_CHANNEL_MESSAGE_BYTES {
+        return Err(crate::error::Error::IpcError(format!(
+            "Message of {} bytes exceeds the channel limit", length
+        )));
+    }
+
+    let mut payload = vec![0u8; length];
+    reader.read_exact(&mut payload).map_err(channel_error)?;
+    Ok(payload)
+}
+
+/// Map an I/O error on a channel to an IPC error
+fn channel_error(err: std::io::Error) -> crate::error::Error {
+    match err.kind() {
+        std::io::ErrorKind::UnexpectedEof | std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::ConnectionReset => {
+            crate::error::Error::IpcError("Channel closed by peer".to_string())
+        }
+        _ => crate::error::Error::IpcError(format!("Channel I/O error: {}", err)),
+    }
+}
+
+#[cfg(windows)]
+mod named_pipe {
+    use std::fs::{File, OpenOptions};
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::{FromRawHandle, RawHandle};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use windows_sys::Win32::Foundation::{GetLastError, ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_INBOUND};
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_WAIT,
+    };
+
+    const BUFFER_SIZE: u32 = 64 * 1024;
+
+    /// Create a one-way named pipe, returning its read and write ends
+    pub fn pipe() -> std::io::Result<(File, File)> {
+        static NEXT_PIPE: AtomicU64 = AtomicU64::new(0);
+        let name = format!(
+            r"\\.\pipe\matte-ipc-{}-{}",
+            std::process::id(),
+            NEXT_PIPE.fetch_add(1, Ordering::Relaxed)
+        );
+        let wide_name: Vec<u16> = std::ffi::OsStr::new(&name).encode_wide().chain(Some(0)).collect();
+
+        // SAFETY: the name is NUL-terminated and outlives the call
+        let handle = unsafe {
+            CreateNamedPipeW(
+                wide_name.as_ptr(),
+                PIPE_ACCESS_INBOUND | FILE_FLAG_FIRST_PIPE_INSTANCE,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                1,
+                BUFFER_SIZE,
+                BUFFER_SIZE,
+                0,
+                std::ptr::null(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: the handle is valid and owned by nothing else
+        let reader = unsafe { File::from_raw_handle(handle as RawHandle) };
+
+        let writer = OpenOptions::new().write(true).open(&name)?;
+
+        // The client is already connected, which ConnectNamedPipe reports as an error
+        // SAFETY: the handle is a valid pipe handle and the call is synchronous
+        if unsafe { ConnectNamedPipe(handle, std::ptr::null_mut()) } == 0 {
+            // SAFETY: no other call was made since ConnectNamedPipe
+            let error = unsafe { GetLastError() };
+            if error != ERROR_PIPE_CONNECTED {
+                return Err(std::io::Error::from_raw_os_error(error as i32));
+            }
+        }
+
+        Ok((reader, writer))
+    }
+}
+
+// Shared Memory
+
+/// Memory shared between processes, mapped into this one
+///
+/// The creating process names the segment and other processes map it with
+/// `open`, so large buffers such as frame pixels are passed without copying
+/// them through a channel. Segments are backed by `memfd_create` on Linux,
+/// POSIX shared memory on other Unix systems and file mappings on Windows.
+///
+/// Opening a segment by name needs file access on Unix, so sandboxed
+/// processes must open their segments before installing the sandbox.
+/// Other processes may write the mapping at any time, so the safe accessors
+/// copy in and out of it; borrowing it in place is `unsafe`.
+#[derive(Debug)]
+pub struct SharedMemorySegment {
+    /// Name other processes open the segment with
+    name: String,
+
+    /// Size of the segment, in bytes
+    size: usize,
+
+    /// Start of the mapping
+    ptr: *mut u8,
+
+    /// Descriptor or handle keeping the segment alive
+    handle: shared_memory::Handle,
+}
+
+// SAFETY: the segment owns its mapping and handle. Shared references only
+// copy out of the mapping, and in-place views are `unsafe` to create.
+unsafe impl Send for SharedMemorySegment {}
+unsafe impl Sync for SharedMemorySegment {}
+
+impl SharedMemorySegment {
+    /// Create a zeroed segment of `size` bytes
+    pub fn create(size: usize) -> crate::Result<Self> {
+        if size == 0 {
+            return Err(crate::error::Error::ConfigError("Shared memory segment cannot be empty".to_string()));
+        }
+        let (name, handle, ptr) = shared_memory::create(size).map_err(|e| {
+            crate::error::Error::IpcError(format!("Failed to create shared memory segment: {}", e))
+        })?;
+        Ok(Self { name, size, ptr, handle })
+    }
+
+    /// Map a segment created by another process, or by this one
+    pub fn open(name: &str, size: usize) -> crate::Result<Self> {
+        if size == 0 {
+            return Err(crate::error::Error::ConfigError("Shared memory segment cannot be empty".to_string()));
+        }
+        let (handle, ptr) = shared_memory::open(name, size).map_err(|e| {
+            crate::error::Error::IpcError(format!("Failed to open shared memory segment {}: {}", name, e))
+        })?;
+        Ok(Self { name: name.to_string(), size, ptr, handle })
+    }
+
+    /// Get the name other processes open the segment with
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the size of the segment, in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Copy of the contents of the segment
+    ///
+    /// The copy may be torn if another process writes the segment meanwhile.
+    pub fn read(&self) -> Vec<u8> {
+        let mut contents = vec![0; self.size];
+        // SAFETY: the mapping is `size` bytes long and lives as long as `self`.
+        // Every byte value is valid, so a concurrent write only tears the copy.
+        unsafe { std::ptr::copy_nonoverlapping(self.ptr, contents.as_mut_ptr(), self.size) };
+        contents
+    }
+
+    /// Copy bytes into the segment at an offset
+    pub fn write(&mut self, offset: usize, data: &[u8]) -> crate::Result<()> {
+        if offset.checked_add(data.len()).is_none_or(|end| end > self.size) {
+            return Err(crate::error::Error::InvalidState(format!(
+                "Writing {} bytes at {} overflows a {} byte segment", data.len(), offset, self.size
+            )));
+        }
+        // SAFETY: the range was checked to lie within the mapping
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), self.ptr.add(offset), data.len()) };
+        Ok(())
+    }
+
+    /// Contents of the segment, in place
+    ///
+    /// # Safety
+    ///
+    /// No process may write the segment while the slice is alive.
+    pub unsafe fn as_slice(&self) -> &[u8] {
+        // SAFETY: the mapping is `size` bytes long and lives as long as `self`,
+        // and the caller guarantees nothing writes it meanwhile
+        unsafe { std::slice::from_raw_parts(self.ptr, self.size) }
+    }
+
+    /// Mutable contents of the segment, in place
+    ///
+    /// # Safety
+    ///
+    /// No other process may write the segment while the slice is alive.
+    pub unsafe fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: as above, and `&mut self` makes this the only view in this process
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.size) }
+    }
+}
+
+impl Drop for SharedMemorySegment {
+    fn drop(&mut self) {
+        shared_memory::close(&self.handle, self.ptr, self.size);
+    }
+}
+
+#[cfg(unix)]
+mod shared_memory {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    /// Descriptor of the segment, and the name to unlink if this process created it
+    #[derive(Debug)]
+    pub struct Handle {
+        fd: OwnedFd,
+        unlink: Option<CString>,
+    }
+
+    /// Create a segment and return its name, handle and mapping
+    #[cfg(target_os = "linux")]
+    pub fn create(size: usize) -> io::Result<(String, Handle, *mut u8)> {
+        // SAFETY: the name is NUL-terminated
+        let fd = unsafe { libc::memfd_create(c"matte-shm".as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: memfd_create returned a new descriptor owned by nothing else
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        // Other processes reach the anonymous file through procfs
+        let name = format!("/proc/{}/fd/{}", std::process::id(), fd.as_raw_fd());
+        let handle = Handle { fd, unlink: None };
+        let ptr = resize_and_map(&handle, size)?;
+        Ok((name, handle, ptr))
+    }
+
+    /// Create a segment and return its name, handle and mapping
+    #[cfg(not(target_os = "linux"))]
+    pub fn create(size: usize) -> io::Result<(String, Handle, *mut u8)> {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static NEXT_SEGMENT: AtomicU64 = AtomicU64::new(0);
+
+        let name = format!("/matte-{}-{}", std::process::id(), NEXT_SEGMENT.fetch_add(1, Ordering::Relaxed));
+        let c_name = CString::new(name.clone()).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        // SAFETY: the name is NUL-terminated
+        let fd = unsafe { libc::shm_open(c_name.as_ptr(), libc::O_CREAT | libc::O_EXCL | libc::O_RDWR, 0o600) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: shm_open returned a new descriptor owned by nothing else
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let handle = Handle { fd, unlink: Some(c_name) };
+        let ptr = resize_and_map(&handle, size)?;
+        Ok((name, handle, ptr))
+    }
+
+    /// Open a segment by name and return its handle and mapping
+    pub fn open(name: &str, size: usize) -> io::Result<(Handle, *mut u8)> {
+        let c_name = CString::new(name).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        // SAFETY: the name is NUL-terminated
+        #[cfg(target_os = "linux")]
+        let fd = unsafe { libc::open(c_name.as_ptr(), libc::O_RDWR | libc::O_CLOEXEC) };
+        // SAFETY: the name is NUL-terminated
+        #[cfg(not(target_os = "linux"))]
+        let fd = unsafe { libc::shm_open(c_name.as_ptr(), libc::O_RDWR, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the descriptor was just opened and is owned by nothing else
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        // SAFETY: stat is plain data and fstat fills it in
+        let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+        if unsafe { libc::fstat(fd.as_raw_fd(), &mut stat) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if (stat.st_size as u64) < size as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "segment is smaller than requested"));
+        }
+
+        let handle = Handle { fd, unlink: None };
+        let ptr = map(&handle, size)?;
+        Ok((handle, ptr))
+    }
+
+    /// Unmap a segment and remove its name if this process created it
+    pub fn close(handle: &Handle, ptr: *mut u8, size: usize) {
+        // SAFETY: `ptr` is a mapping of `size` bytes that is not used afterwards
+        unsafe { libc::munmap(ptr.cast(), size) };
+        if let Some(name) = &handle.unlink {
+            // SAFETY: the name is NUL-terminated
+            unsafe { libc::shm_unlink(name.as_ptr()) };
+        }
+    }
+
+    /// Grow a new segment to `size` bytes and map it
+    fn resize_and_map(handle: &Handle, size: usize) -> io::Result<*mut u8> {
+        let length = libc::off_t::try_from(size).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        // SAFETY: ftruncate takes no pointers
+        if unsafe { libc::ftruncate(handle.fd.as_raw_fd(), length) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        map(handle, size)
+    }
+
+    /// Map `size` bytes of a segment for reading and writing
+    fn map(handle: &Handle, size: usize) -> io::Result<*mut u8> {
+        // SAFETY: a fresh shared mapping of a descriptor we own
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                handle.fd.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(ptr.cast())
+    }
+}
+
+#[cfg(windows)]
+mod shared_memory {
+    use std::io;
+    use std::os::windows::ffi::OsStrExt;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Memory::{
+        CreateFileMappingW, MapViewOfFile, OpenFileMappingW, UnmapViewOfFile, FILE_MAP_ALL_ACCESS,
+        MEMORY_MAPPED_VIEW_ADDRESS, PAGE_READWRITE,
+    };
+
+    /// File mapping handle of the segment
+    #[derive(Debug)]
+    pub struct Handle(HANDLE);
+
+    /// Create a segment and return its name, handle and mapping
+    pub fn create(size: usize) -> io::Result<(String, Handle, *mut u8)> {
+        static NEXT_SEGMENT: AtomicU64 = AtomicU64::new(0);
+        let name = format!(r"Local\matte-shm-{}-{}", std::process::id(), NEXT_SEGMENT.fetch_add(1, Ordering::Relaxed));
+        let wide_name = wide(&name);
+        let size = size as u64;
+
+        // SAFETY: the name is NUL-terminated and outlives the call
+        let mapping = unsafe {
+            CreateFileMappingW(
+                INVALID_HANDLE_VALUE,
+                std::ptr::null(),
+                PAGE_READWRITE,
+                (size >> 32) as u32,
+                size as u32,
+                wide_name.as_ptr(),
+            )
+        };
+        if mapping.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let handle = Handle(mapping);
+        let ptr = map(&handle, size as usize)?;
+        Ok((name, handle, ptr))
+    }
+
+    /// Open a segment by name and return its handle and mapping
+    pub fn open(name: &str, size: usize) -> io::Result<(Handle, *mut u8)> {
+        let wide_name = wide(name);
+        // SAFETY: the name is NUL-terminated and outlives the call
+        let mapping = unsafe { OpenFileMappingW(FILE_MAP_ALL_ACCESS, 0, wide_name.as_ptr()) };
+        if mapping.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let handle = Handle(mapping);
+        let ptr = map(&handle, size)?;
+        Ok((handle, ptr))
+    }
+
+    /// Unmap a segment and close its handle
+    pub fn close(handle: &Handle, ptr: *mut u8, _size: usize) {
+        // SAFETY: `ptr` is a view that is not used afterwards, and the handle is closed once
+        unsafe {
+            UnmapViewOfFile(MEMORY_MAPPED_VIEW_ADDRESS { Value: ptr.cast() });
+            CloseHandle(handle.0);
+        }
+    }
+
+    /// Map `size` bytes of a segment for reading and writing
+    fn map(handle: &Handle, size: usize) -> io::Result<*mut u8> {
+        // SAFETY: the handle is a valid file mapping
+        let view = unsafe { MapViewOfFile(handle.0, FILE_MAP_ALL_ACCESS, 0, 0, size) };
+        if view.Value.is_null() {
+            let error = io::Error::last_os_error();
+            // SAFETY: the handle is not used after a failed mapping
+            unsafe { CloseHandle(handle.0) };
+            return Err(error);
+        }
+        Ok(view.Value.cast())
+    }
+
+    /// NUL-terminated UTF-16 form of a name
+    fn wide(name: &str) -> Vec<u16> {
+        std::ffi::OsStr::new(name).encode_wide().chain(Some(0)).collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -663,4 +1608,79 @@ mod tests {
         manager.remove_connection("test").await.unwrap();
         assert!(manager.get_connection("test").await.is_err());
     }
+
+    #[test]
+    fn test_channel_round_trip() {
+        let (browser, renderer) = ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        browser.send(IpcMessage::Shutdown(ShutdownMessage {
+            reason: "update".to_string(),
+            graceful: true,
+        })).unwrap();
+        match renderer.recv().unwrap() {
+            IpcMessage::Shutdown(message) => assert_eq!(message.reason, "update"),
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        let (requests, replies) = ChannelPair::create::<String, u64>().unwrap();
+        replies.send(42).unwrap();
+        requests.send("ünïcødé".to_string()).unwrap();
+        assert_eq!(requests.recv().unwrap(), 42);
+        assert_eq!(replies.recv().unwrap(), "ünïcødé");
+    }
+
+    #[tokio::test]
+    async fn test_channel_async_round_trip() {
+        let (first, second) = ChannelPair::create::<Vec<u8>, Vec<u8>>().unwrap();
+        let echo = tokio::spawn(async move {
+            let message = second.recv_async().await.unwrap();
+            second.send_async(message).await.unwrap();
+        });
+
+        // Larger than a socket buffer, so the write needs the reader running
+        let message = vec![7u8; 1024 * 1024];
+        first.send_async(message.clone()).await.unwrap();
+        assert_eq!(first.recv_async().await.unwrap(), message);
+        echo.await.unwrap();
+    }
+
+    #[test]
+    fn test_channel_closed_peer() {
+        let (first, second) = ChannelPair::create::<u32, u32>().unwrap();
+        drop(second);
+        assert!(matches!(first.recv(), Err(crate::error::Error::IpcError(_))));
+    }
+
+    #[test]
+    fn test_oversized_frame_rejected() {
+        let mut frame = ((MAX_CHANNEL_MESSAGE_BYTES + 1) as u32).to_le_bytes().to_vec();
+        frame.extend_from_slice(b"{}");
+        assert!(read_frame(&mut std::io::Cursor::new(frame)).is_err());
+
+        let frame = encode_frame(&"hello").unwrap();
+        assert_eq!(&frame[..4], &7u32.to_le_bytes());
+        assert_eq!(read_frame(&mut std::io::Cursor::new(frame)).unwrap(), b"\"hello\"");
+    }
+
+    #[test]
+    fn test_shared_memory_segment() {
+        let mut segment = SharedMemorySegment::create(64 * 1024).unwrap();
+        assert_eq!(segment.size(), 64 * 1024);
+        assert!(segment.read().iter().all(|&byte| byte == 0));
+        segment.write(0, b"frame").unwrap();
+        segment.write(64 * 1024 - 1, &[0xff]).unwrap();
+        assert!(segment.write(64 * 1024 - 1, &[0xff, 0xff]).is_err());
+        assert!(segment.write(usize::MAX, &[0xff]).is_err());
+
+        // A second mapping sees writes through the first, both ways
+        let mut mapped = SharedMemorySegment::open(segment.name(), segment.size()).unwrap();
+        assert_eq!(&mapped.read()[..5], b"frame");
+        assert_eq!(mapped.read()[64 * 1024 - 1], 0xff);
+        mapped.write(0, b"F").unwrap();
+        assert_eq!(&segment.read()[..5], b"Frame");
+
+        assert!(SharedMemorySegment::open(segment.name(), segment.size() * 2).is_err());
+        assert!(SharedMemorySegment::create(0).is_err());
+        drop(segment);
+        assert_eq!(&mapped.read()[..5], b"Frame");
+    }
 }
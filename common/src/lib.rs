@@ -1,10 +1,14 @@
 //! Common utilities and types shared across the Matte browser.
 
 pub mod crash;
+pub mod debugger;
 pub mod error;
+pub mod heap;
 pub mod ipc;
+pub mod logging;
 pub mod platform;
 pub mod privilege;
+pub mod telemetry;
 pub mod types;
 pub mod utils;
 
@@ -74,6 +78,16 @@ impl fmt::Display for ProcessType {
     }
 }
 
+/// Output format of log lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// Newline-delimited JSON
+    Json,
+}
+
 /// Browser configuration
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -81,6 +95,7 @@ pub struct Config {
     pub process_type: ProcessType,
     pub enable_logging: bool,
     pub log_level: log::LevelFilter,
+    pub log_format: LogFormat,
     pub enable_crash_reporting: bool,
     pub enable_telemetry: bool,
     pub data_directory: std::path::PathBuf,
@@ -94,6 +109,7 @@ impl Default for Config {
             process_type: ProcessType::Browser,
             enable_logging: true,
             log_level: log::LevelFilter::Info,
+            log_format: LogFormat::Text,
             enable_crash_reporting: true,
             enable_telemetry: false,
             data_directory: default_data_directory(),
@@ -137,11 +153,17 @@ pub fn init(config: Config) -> Result<()> {
     // Initialize logging
     if config.enable_logging {
         // Use tracing-subscriber for logging
-        tracing_subscriber::fmt()
-            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-            .with_target(false)
-            .with_timer(tracing_subscriber::fmt::time::LocalTime::rfc_3339())
-            .init();
+        let subscriber = tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::from_default_env());
+        match config.log_format {
+            LogFormat::Text => subscriber
+                .with_target(false)
+                .with_timer(tracing_subscriber::fmt::time::LocalTime::rfc_3339())
+                .init(),
+            LogFormat::Json => subscriber
+                .event_format(logging::JsonFormat::new(config.process_type))
+                .init(),
+        }
     }
 
     // Create directories
@@ -151,6 +173,13 @@ pub fn init(config: Config) -> Result<()> {
     std::fs::create_dir_all(&config.temp_directory)
         .map_err(|e| error::Error::IoError(format!("Failed to create temp directory: {}", e)))?;
 
+    // Write a dump on panic
+    if config.enable_crash_reporting {
+        crash::CrashReporter::install(&config.temp_directory)?;
+        crash::CrashReporter::register_metadata("version", &config.version.to_string());
+        crash::CrashReporter::register_metadata("process_type", &config.process_type.to_string());
+    }
+
     tracing::info!("Matte browser initialized (version: {})", config.version);
     tracing::info!("Process type: {}", config.process_type);
     tracing::info!("Data directory: {:?}", config.data_directory);
@@ -188,6 +217,7 @@ mod tests {
         assert_eq!(config.process_type, ProcessType::Browser);
         assert!(config.enable_logging);
         assert_eq!(config.log_level, log::LevelFilter::Info);
+        assert_eq!(config.log_format, LogFormat::Text);
         assert!(config.enable_crash_reporting);
         assert!(!config.enable_telemetry);
     }
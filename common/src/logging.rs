@@ -0,0 +1,160 @@
+//! Structured log output for the Matte browser.
+//!
+//! With `LogFormat::Json` every event is written as one line of JSON:
+//!
+//! ```text
+//! {"timestamp":"2024-05-01T12:00:00.000000Z","level":"INFO","target":"renderer","message":"Loaded page","process_type":"renderer","fields":{"tab_id":3}}
+//! ```
+
+use crate::ProcessType;
+use serde_json::{Map, Value};
+use std::fmt;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::{FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+use tracing_subscriber::fmt::FmtContext;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Event formatter writing newline-delimited JSON
+#[derive(Debug, Clone)]
+pub struct JsonFormat {
+    process_type: ProcessType,
+}
+
+impl JsonFormat {
+    /// Create a formatter tagging every line with a process type
+    pub fn new(process_type: ProcessType) -> Self {
+        Self { process_type }
+    }
+}
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, _ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        // RFC 3339 in UTC
+        let mut timestamp = String::new();
+        SystemTime.format_time(&mut Writer::new(&mut timestamp))?;
+
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+
+        let metadata = event.metadata();
+        let mut line = Map::new();
+        line.insert("timestamp".to_string(), Value::String(timestamp));
+        line.insert("level".to_string(), Value::String(metadata.level().to_string()));
+        line.insert("target".to_string(), Value::String(metadata.target().to_string()));
+        line.insert("message".to_string(), Value::String(visitor.message));
+        line.insert("process_type".to_string(), Value::String(self.process_type.to_string()));
+        line.insert("fields".to_string(), Value::Object(visitor.fields));
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+/// Collects the message and fields of an event
+#[derive(Default)]
+struct JsonVisitor {
+    message: String,
+    fields: Map<String, Value>,
+}
+
+impl JsonVisitor {
+    /// Store a field value, or the message
+    fn insert(&mut self, field: &Field, value: Value) {
+        if field.name() == "message" {
+            self.message = match value {
+                Value::String(message) => message,
+                other => other.to_string(),
+            };
+        } else {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for JsonVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, Value::String(format!("{:?}", value)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    /// Writer appending to a shared buffer
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for SharedBuffer {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_log_lines() {
+        let buffer = SharedBuffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .event_format(JsonFormat::new(ProcessType::Renderer))
+            .with_writer(move || writer.clone())
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(tab_id = 3, url = "https://example.com", "Loaded page");
+            tracing::warn!(target: "matte::gpu", retry = true, "Context lost: {}", "reset");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+
+        let first = &lines[0];
+        assert_eq!(first["level"], "INFO");
+        assert_eq!(first["target"], "common::logging::tests");
+        assert_eq!(first["message"], "Loaded page");
+        assert_eq!(first["process_type"], "renderer");
+        assert_eq!(first["fields"]["tab_id"], 3);
+        assert_eq!(first["fields"]["url"], "https://example.com");
+        let timestamp = first["timestamp"].as_str().unwrap();
+        assert!(timestamp.len() >= 20 && timestamp.ends_with('Z') && &timestamp[10..11] == "T");
+
+        let second = &lines[1];
+        assert_eq!(second["level"], "WARN");
+        assert_eq!(second["target"], "matte::gpu");
+        assert_eq!(second["message"], "Context lost: reset");
+        assert_eq!(second["fields"]["retry"], true);
+    }
+}
@@ -707,6 +707,115 @@ impl PlatformSecurity {
     }
 }
 
+/// Scheduling priority of a process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProcessPriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+    Idle,
+}
+
+impl ProcessPriority {
+    /// Nice value used on Linux and macOS
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn nice_value(self) -> libc::c_int {
+        match self {
+            ProcessPriority::High => -10,
+            ProcessPriority::Normal => 0,
+            ProcessPriority::Low => 10,
+            ProcessPriority::Idle => 19,
+        }
+    }
+}
+
+/// Set the CPU scheduling priority of a process
+///
+/// Raising a priority usually needs elevated privileges, so moving a process
+/// back from `Low` to `Normal` can fail with `PermissionDenied`.
+pub fn set_process_priority(pid: u32, priority: ProcessPriority) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        // PRIO_PROCESS only covers one thread on Linux, so set every thread
+        let tasks = std::fs::read_dir(format!("/proc/{}/task", pid)).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Error::NotFound(format!("Process {} not found", pid)),
+            _ => Error::PlatformError(format!("Failed to list threads of process {}: {}", pid, e)),
+        })?;
+        for task in tasks.flatten() {
+            if let Some(tid) = task.file_name().to_str().and_then(|name| name.parse::<libc::id_t>().ok()) {
+                match set_nice(tid, priority) {
+                    // The thread exited since the directory was read
+                    Err(Error::NotFound(_)) => {}
+                    result => result?,
+                }
+            }
+        }
+        Ok(())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        set_nice(pid, priority)
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::Threading::{
+            OpenProcess, SetPriorityClass, BELOW_NORMAL_PRIORITY_CLASS, HIGH_PRIORITY_CLASS,
+            IDLE_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS, PROCESS_SET_INFORMATION,
+        };
+
+        let class = match priority {
+            ProcessPriority::High => HIGH_PRIORITY_CLASS,
+            ProcessPriority::Normal => NORMAL_PRIORITY_CLASS,
+            ProcessPriority::Low => BELOW_NORMAL_PRIORITY_CLASS,
+            ProcessPriority::Idle => IDLE_PRIORITY_CLASS,
+        };
+
+        // SAFETY: OpenProcess takes no pointers; the handle is closed below
+        let handle = unsafe { OpenProcess(PROCESS_SET_INFORMATION, 0, pid) };
+        if handle.is_null() {
+            return Err(Error::PlatformError(format!(
+                "Failed to open process {}: {}",
+                pid,
+                std::io::Error::last_os_error()
+            )));
+        }
+        // SAFETY: the handle was opened with PROCESS_SET_INFORMATION
+        let result = unsafe { SetPriorityClass(handle, class) };
+        let error = std::io::Error::last_os_error();
+        // SAFETY: the handle is valid and not used afterwards
+        unsafe { CloseHandle(handle) };
+        if result == 0 {
+            return Err(Error::PlatformError(format!("Failed to set priority of process {}: {}", pid, error)));
+        }
+        Ok(())
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        let _ = (pid, priority);
+        Err(Error::PlatformError("Process priority not supported on this platform".to_string()))
+    }
+}
+
+/// Set the nice value of a process, or of a thread on Linux
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn set_nice(id: libc::id_t, priority: ProcessPriority) -> Result<()> {
+    // SAFETY: setpriority takes no pointers
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, id, priority.nice_value()) } == 0 {
+        return Ok(());
+    }
+
+    let error = std::io::Error::last_os_error();
+    Err(match error.raw_os_error() {
+        Some(libc::ESRCH) => Error::NotFound(format!("Process {} not found", id)),
+        Some(libc::EACCES) | Some(libc::EPERM) => {
+            Error::PermissionDenied(format!("Not allowed to set priority of process {}: {}", id, error))
+        }
+        _ => Error::PlatformError(format!("Failed to set priority of process {}: {}", id, error)),
+    })
+}
+
 /// Platform-specific window management
 pub struct PlatformWindow;
 
@@ -803,6 +912,31 @@ mod tests {
         assert!(PlatformSecurity::enable_cfi().is_ok());
     }
 
+    #[test]
+    fn test_set_process_priority() {
+        // Lowering the priority never needs privileges
+        assert!(set_process_priority(std::process::id(), ProcessPriority::Low).is_ok());
+        assert!(matches!(
+            set_process_priority(u32::MAX - 1, ProcessPriority::Low),
+            Err(Error::NotFound(_) | Error::PlatformError(_))
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_set_process_priority_of_child() {
+        let mut child = std::process::Command::new("sleep").arg("10").spawn().unwrap();
+        let result = set_process_priority(child.id(), ProcessPriority::Idle);
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", child.id())).unwrap();
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        result.unwrap();
+        // The nice value is the 19th field, the command name may contain spaces
+        let fields: Vec<&str> = stat.rsplit(')').next().unwrap().split_whitespace().collect();
+        assert_eq!(fields[16], "19");
+    }
+
     #[test]
     fn test_platform_window() {
         let handle = PlatformWindow::create_window("Test", 800, 600, None, None).unwrap();
@@ -545,6 +545,171 @@ impl Default for PrivilegeBroker {
     }
 }
 
+/// Install the syscall sandbox of a renderer process
+///
+/// Afterwards the process can only use its open file descriptors, exchange IPC
+/// messages, manage anonymous memory and exit; any other syscall fails with
+/// `EPERM`. The sandbox covers every thread and cannot be lifted, so install
+/// it once all file descriptors are set up. A no-op outside Linux.
+///
+/// Only call this from the entry point of a process that runs nothing but a
+/// renderer: renderers created with `RendererProcess::new` share the browser
+/// process, and the sandbox would take the browser's runtime down with it.
+pub fn apply_renderer_sandbox() -> Result<()> {
+    sandbox::apply(PrivilegeLevel::Renderer)
+}
+
+/// Install the syscall sandbox of a GPU process
+///
+/// Like the renderer sandbox, but also allows `ioctl` for talking to the DRM
+/// driver. The same caveat applies: GPU processes created with
+/// `GpuProcess::new` run inside the browser process.
+pub fn apply_gpu_sandbox() -> Result<()> {
+    sandbox::apply(PrivilegeLevel::GPU)
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod sandbox {
+    use super::PrivilegeLevel;
+    use crate::error::{Error, Result};
+    use tracing::debug;
+
+    /// Architecture the filter is written for, as reported by `seccomp_data`
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    /// Syscalls allowed in renderer processes, besides anonymous `mmap`
+    const RENDERER_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_read,
+        libc::SYS_write,
+        libc::SYS_recvmsg,
+        libc::SYS_sendmsg,
+        libc::SYS_munmap,
+        libc::SYS_brk,
+        libc::SYS_futex,
+        libc::SYS_clock_gettime,
+        libc::SYS_exit_group,
+        libc::SYS_rt_sigreturn,
+    ];
+
+    /// Syscalls GPU processes need on top of the renderer ones
+    const GPU_SYSCALLS: &[libc::c_long] = &[libc::SYS_ioctl];
+
+    /// Room for the allowed syscalls plus the fixed instructions
+    const MAX_INSTRUCTIONS: usize = 32;
+
+    /// Install the filter for a process type on all threads
+    pub fn apply(level: PrivilegeLevel) -> Result<()> {
+        let mut allowed = [0; MAX_INSTRUCTIONS];
+        let mut count = 0;
+        let extra: &[libc::c_long] = if level == PrivilegeLevel::GPU { GPU_SYSCALLS } else { &[] };
+        for &syscall in RENDERER_SYSCALLS.iter().chain(extra) {
+            allowed[count] = syscall;
+            count += 1;
+        }
+
+        // The filter is built on the stack so this also works in a freshly
+        // forked child
+        let mut filter = [statement(0, 0); MAX_INSTRUCTIONS];
+        let length = build_filter(&allowed[..count], &mut filter);
+        let program = libc::sock_fprog {
+            len: length as u16,
+            filter: filter.as_mut_ptr(),
+        };
+
+        // SAFETY: prctl with PR_SET_NO_NEW_PRIVS takes no pointers
+        if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+            return Err(Error::SecurityError(format!(
+                "Failed to set no_new_privs: {}",
+                std::io::Error::last_os_error()
+            )));
+        }
+
+        // SAFETY: the program points at `length` initialized instructions that
+        // outlive the call; the kernel copies them
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_seccomp,
+                libc::SECCOMP_SET_MODE_FILTER,
+                libc::SECCOMP_FILTER_FLAG_TSYNC,
+                &program as *const libc::sock_fprog,
+            )
+        };
+        if result != 0 {
+            return Err(Error::SecurityError(format!(
+                "Failed to install seccomp filter: {}",
+                std::io::Error::last_os_error()
+            )));
+        }
+
+        debug!("Installed {} syscall sandbox", level);
+        Ok(())
+    }
+
+    /// Write the BPF program into `filter` and return its length
+    ///
+    /// Syscalls from another architecture kill the process, allowed syscalls
+    /// pass, `mmap` passes only with `MAP_ANONYMOUS` and everything else
+    /// fails with `EPERM`.
+    fn build_filter(allowed: &[libc::c_long], filter: &mut [libc::sock_filter; MAX_INSTRUCTIONS]) -> usize {
+        let length = allowed.len() + 9;
+        let deny = length - 2;
+        let allow = length - 1;
+        let arch_offset = std::mem::offset_of!(libc::seccomp_data, arch) as u32;
+        let nr_offset = std::mem::offset_of!(libc::seccomp_data, nr) as u32;
+        // Low half of the flags argument on little-endian targets
+        let flags_offset = (std::mem::offset_of!(libc::seccomp_data, args) + 3 * 8) as u32;
+
+        filter[0] = statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, arch_offset);
+        filter[1] = jump(libc::BPF_JEQ, AUDIT_ARCH, 1, 0);
+        filter[2] = statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS);
+        filter[3] = statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, nr_offset);
+
+        let mut index = 4;
+        for &syscall in allowed {
+            filter[index] = jump(libc::BPF_JEQ, syscall as u32, (allow - index - 1) as u8, 0);
+            index += 1;
+        }
+
+        filter[index] = jump(libc::BPF_JEQ, libc::SYS_mmap as u32, 0, (deny - index - 1) as u8);
+        filter[index + 1] = statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, flags_offset);
+        filter[index + 2] = jump(libc::BPF_JSET, libc::MAP_ANONYMOUS as u32, 1, 0);
+        filter[deny] = statement(
+            libc::BPF_RET | libc::BPF_K,
+            libc::SECCOMP_RET_ERRNO | (libc::EPERM as u32 & libc::SECCOMP_RET_DATA),
+        );
+        filter[allow] = statement(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW);
+
+        length
+    }
+
+    /// BPF instruction without a branch
+    const fn statement(code: u32, k: u32) -> libc::sock_filter {
+        libc::sock_filter { code: code as u16, jt: 0, jf: 0, k }
+    }
+
+    /// BPF conditional jump, with offsets relative to the next instruction
+    const fn jump(condition: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter { code: (libc::BPF_JMP | condition | libc::BPF_K) as u16, jt, jf, k }
+    }
+}
+
+#[cfg(not(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+mod sandbox {
+    use super::PrivilegeLevel;
+    use crate::error::Result;
+    use tracing::debug;
+
+    /// Syscall filtering is only implemented on Linux; macOS and Windows
+    /// processes rely on the privilege broker alone for now
+    pub fn apply(level: PrivilegeLevel) -> Result<()> {
+        debug!("No syscall sandbox for {} processes on this platform", level);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -607,4 +772,45 @@ mod tests {
         assert!(!response.success);
         assert!(response.error.is_some());
     }
+
+    #[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+    #[test]
+    fn test_renderer_sandbox_blocks_files_and_network() {
+        // The sandbox cannot be lifted, so install it in a forked child that
+        // reports through its exit code
+        let pid = unsafe { libc::fork() };
+        assert!(pid >= 0);
+        if pid == 0 {
+            let code = if apply_renderer_sandbox().is_err() {
+                1
+            } else if !fails_with_eperm(unsafe { libc::open(c"/etc/passwd".as_ptr(), libc::O_RDONLY) }) {
+                2
+            } else if !fails_with_eperm(unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0) }) {
+                3
+            } else {
+                let memory = unsafe {
+                    libc::mmap(
+                        std::ptr::null_mut(),
+                        4096,
+                        libc::PROT_READ | libc::PROT_WRITE,
+                        libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                        -1,
+                        0,
+                    )
+                };
+                if memory == libc::MAP_FAILED { 4 } else { 0 }
+            };
+            unsafe { libc::_exit(code) };
+        }
+
+        let mut status = 0;
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+    }
+
+    #[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+    fn fails_with_eperm(result: libc::c_int) -> bool {
+        result == -1 && std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
 }
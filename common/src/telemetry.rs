@@ -0,0 +1,337 @@
+//! Telemetry for the Matte browser.
+//!
+//! Events are queued in memory and sent in batches as a JSON array every
+//! `FLUSH_INTERVAL`. Nothing is recorded unless `Config::enable_telemetry`
+//! is set, and the queue is bounded so an unreachable endpoint cannot grow
+//! it without limit.
+
+use crate::error::{Error, Result};
+use crate::Config;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Interval between automatic flushes
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Largest number of events sent in one request
+pub const MAX_BATCH_EVENTS: usize = 500;
+
+/// Number of queued events beyond which new events are dropped
+pub const MAX_QUEUED_EVENTS: usize = 1000;
+
+/// Time allowed for sending a batch
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A telemetry event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryEvent {
+    pub name: String,
+    pub properties: HashMap<String, String>,
+    pub timestamp: SystemTime,
+}
+
+/// Connection used to send event batches
+#[async_trait]
+pub trait TelemetryTransport: Send + Sync {
+    /// POST a JSON body to an endpoint
+    async fn post_json(&self, endpoint: &str, body: Vec<u8>) -> Result<()>;
+}
+
+/// Transport sending batches over plain HTTP/1.1
+pub struct HttpTelemetryTransport;
+
+#[async_trait]
+impl TelemetryTransport for HttpTelemetryTransport {
+    async fn post_json(&self, endpoint: &str, body: Vec<u8>) -> Result<()> {
+        let url = url::Url::parse(endpoint)
+            .map_err(|e| Error::ConfigError(format!("Invalid telemetry endpoint {}: {}", endpoint, e)))?;
+        if url.scheme() != "http" {
+            return Err(Error::NotImplemented(format!(
+                "Telemetry endpoints with scheme {} are not supported",
+                url.scheme()
+            )));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| Error::ConfigError(format!("Telemetry endpoint {} has no host", endpoint)))?;
+        let port = url.port_or_known_default().unwrap_or(80);
+        let path = &url[url::Position::BeforePath..url::Position::AfterQuery];
+
+        let request = async {
+            let mut stream = tokio::net::TcpStream::connect((host, port)).await?;
+            let head = format!(
+                "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                path,
+                &url[url::Position::BeforeHost..url::Position::AfterPort],
+                body.len()
+            );
+            stream.write_all(head.as_bytes()).await?;
+            stream.write_all(&body).await?;
+
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).await?;
+            Ok::<_, std::io::Error>(response)
+        };
+        let response = tokio::time::timeout(REQUEST_TIMEOUT, request)
+            .await
+            .map_err(|_| Error::Timeout(format!("Telemetry request to {} timed out", endpoint)))?
+            .map_err(|e| Error::NetworkError(format!("Telemetry request to {} failed: {}", endpoint, e)))?;
+
+        // Status line: HTTP/1.1 204 No Content
+        let status_line = response.split(|&byte| byte == b'\n').next().unwrap_or_default();
+        let status = String::from_utf8_lossy(status_line)
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| Error::NetworkError(format!("Invalid response from telemetry endpoint {}", endpoint)))?;
+        if !(200..300).contains(&status) {
+            return Err(Error::NetworkError(format!(
+                "Telemetry endpoint {} returned status {}",
+                endpoint, status
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Telemetry manager
+pub struct TelemetryManager {
+    /// Whether events are recorded
+    enabled: bool,
+
+    /// Events waiting to be sent, oldest first
+    queue: Mutex<VecDeque<TelemetryEvent>>,
+
+    /// Events dropped because the queue was full
+    dropped_events: AtomicU64,
+
+    /// Connection used to send batches
+    transport: Arc<dyn TelemetryTransport>,
+}
+
+impl TelemetryManager {
+    /// Create a telemetry manager, enabled by `Config::enable_telemetry`
+    pub fn new(config: &Config, transport: Arc<dyn TelemetryTransport>) -> Self {
+        Self {
+            enabled: config.enable_telemetry,
+            queue: Mutex::new(VecDeque::new()),
+            dropped_events: AtomicU64::new(0),
+            transport,
+        }
+    }
+
+    /// Whether events are recorded
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Queue an event, unless telemetry is disabled or the queue is full
+    pub fn record_event(&self, name: &str, properties: HashMap<String, String>) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut queue = self.queue.lock();
+        if queue.len() >= MAX_QUEUED_EVENTS {
+            self.dropped_events.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        queue.push_back(TelemetryEvent {
+            name: name.to_string(),
+            properties,
+            timestamp: SystemTime::now(),
+        });
+    }
+
+    /// Number of events waiting to be sent
+    pub fn pending_events(&self) -> usize {
+        self.queue.lock().len()
+    }
+
+    /// Number of events dropped because the queue was full
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped_events.load(Ordering::Relaxed)
+    }
+
+    /// Send the queued events to an endpoint, in batches of `MAX_BATCH_EVENTS`
+    ///
+    /// Nothing is sent when the queue is empty. A batch that fails to send is
+    /// put back at the front of the queue for the next flush.
+    pub async fn flush(&self, endpoint: &str) -> Result<()> {
+        loop {
+            let batch: Vec<TelemetryEvent> = {
+                let mut queue = self.queue.lock();
+                let count = queue.len().min(MAX_BATCH_EVENTS);
+                queue.drain(..count).collect()
+            };
+            if batch.is_empty() {
+                return Ok(());
+            }
+
+            let body = serde_json::to_vec(&batch)?;
+            if let Err(e) = self.transport.post_json(endpoint, body).await {
+                self.requeue(batch);
+                return Err(e);
+            }
+            debug!("Sent {} telemetry events", batch.len());
+        }
+    }
+
+    /// Flush to an endpoint every `FLUSH_INTERVAL`
+    pub fn spawn_flush_task(self: Arc<Self>, endpoint: String) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            // The first tick completes immediately
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.flush(&endpoint).await {
+                    warn!("Failed to send telemetry: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Put a batch that failed to send back in front of newer events
+    fn requeue(&self, batch: Vec<TelemetryEvent>) {
+        let mut queue = self.queue.lock();
+        for event in batch.into_iter().rev() {
+            queue.push_front(event);
+        }
+        if queue.len() > MAX_QUEUED_EVENTS {
+            let excess = queue.len() - MAX_QUEUED_EVENTS;
+            queue.truncate(MAX_QUEUED_EVENTS);
+            self.dropped_events.fetch_add(excess as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Transport recording the batches it is given
+    #[derive(Default)]
+    struct RecordingTransport {
+        calls: AtomicUsize,
+        batches: Mutex<Vec<Vec<TelemetryEvent>>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl TelemetryTransport for RecordingTransport {
+        async fn post_json(&self, _endpoint: &str, body: Vec<u8>) -> Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.fail {
+                return Err(Error::NetworkError("unreachable".to_string()));
+            }
+            self.batches.lock().push(serde_json::from_slice(&body).unwrap());
+            Ok(())
+        }
+    }
+
+    fn manager(enabled: bool, transport: Arc<RecordingTransport>) -> TelemetryManager {
+        let config = Config {
+            enable_telemetry: enabled,
+            ..Config::default()
+        };
+        TelemetryManager::new(&config, transport)
+    }
+
+    #[tokio::test]
+    async fn test_disabled_telemetry_records_nothing() {
+        let transport = Arc::new(RecordingTransport::default());
+        let telemetry = manager(false, transport.clone());
+
+        telemetry.record_event("page_load", HashMap::from([("ms".to_string(), "120".to_string())]));
+        assert_eq!(telemetry.pending_events(), 0);
+
+        telemetry.flush("http://telemetry.invalid/events").await.unwrap();
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_flush_skips_empty_queue() {
+        let transport = Arc::new(RecordingTransport::default());
+        let telemetry = manager(true, transport.clone());
+
+        telemetry.flush("http://telemetry.invalid/events").await.unwrap();
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 0);
+
+        telemetry.record_event("tab_opened", HashMap::new());
+        telemetry.flush("http://telemetry.invalid/events").await.unwrap();
+        telemetry.flush("http://telemetry.invalid/events").await.unwrap();
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(transport.batches.lock()[0][0].name, "tab_opened");
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_drops_events() {
+        let transport = Arc::new(RecordingTransport::default());
+        let telemetry = manager(true, transport.clone());
+
+        for i in 0..MAX_QUEUED_EVENTS + 25 {
+            telemetry.record_event(&format!("event_{}", i), HashMap::new());
+        }
+        assert_eq!(telemetry.pending_events(), MAX_QUEUED_EVENTS);
+        assert_eq!(telemetry.dropped_events(), 25);
+
+        telemetry.flush("http://telemetry.invalid/events").await.unwrap();
+        let batches = transport.batches.lock();
+        assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), [MAX_BATCH_EVENTS, MAX_BATCH_EVENTS]);
+        assert_eq!(batches[1].last().unwrap().name, format!("event_{}", MAX_QUEUED_EVENTS - 1));
+    }
+
+    #[tokio::test]
+    async fn test_failed_flush_keeps_events() {
+        let transport = Arc::new(RecordingTransport { fail: true, ..Default::default() });
+        let telemetry = manager(true, transport.clone());
+
+        telemetry.record_event("first", HashMap::new());
+        telemetry.record_event("second", HashMap::new());
+        assert!(telemetry.flush("http://telemetry.invalid/events").await.is_err());
+        assert_eq!(telemetry.pending_events(), 2);
+        assert_eq!(telemetry.queue.lock()[0].name, "first");
+    }
+
+    #[tokio::test]
+    async fn test_http_transport_posts_json() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1/events?client=matte", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+            // Read until the JSON body is complete
+            while !request.ends_with(b"]") {
+                let read = socket.read(&mut buffer).await.unwrap();
+                assert!(read > 0);
+                request.extend_from_slice(&buffer[..read]);
+            }
+            socket.write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let body = serde_json::to_vec(&["hello"]).unwrap();
+        HttpTelemetryTransport.post_json(&endpoint, body).await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1/events?client=matte HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Type: application/json\r\n"));
+        assert!(request.ends_with("\r\n\r\n[\"hello\"]"));
+
+        let result = HttpTelemetryTransport.post_json("https://telemetry.example/events", Vec::new()).await;
+        assert!(matches!(result, Err(Error::NotImplemented(_))));
+    }
+}
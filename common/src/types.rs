@@ -295,10 +295,13 @@ pub enum Permission {
     Notifications,
     Microphone,
     Camera,
-    Clipboard,
+    ClipboardRead,
+    ClipboardWrite,
     Fullscreen,
     Payment,
     PersistentStorage,
+    FileSystemRead,
+    FileSystemWrite,
 }
 
 impl fmt::Display for Permission {
@@ -308,10 +311,13 @@ impl fmt::Display for Permission {
             Permission::Notifications => write!(f, "notifications"),
             Permission::Microphone => write!(f, "microphone"),
             Permission::Camera => write!(f, "camera"),
-            Permission::Clipboard => write!(f, "clipboard"),
+            Permission::ClipboardRead => write!(f, "clipboard-read"),
+            Permission::ClipboardWrite => write!(f, "clipboard-write"),
             Permission::Fullscreen => write!(f, "fullscreen"),
             Permission::Payment => write!(f, "payment"),
             Permission::PersistentStorage => write!(f, "persistent-storage"),
+            Permission::FileSystemRead => write!(f, "file-system-read"),
+            Permission::FileSystemWrite => write!(f, "file-system-write"),
         }
     }
 }
@@ -0,0 +1,589 @@
+//! Remote debugging over the Chrome DevTools Protocol
+//!
+//! A subset of CDP is served as JSON-RPC 2.0 messages over WebSocket. Each
+//! domain delegates to a DevTools inspector: `Runtime` to the JavaScript
+//! debugger, `DOM` and `Page` to the elements inspector and `Network` to the
+//! network inspector, whose recorded requests are pushed to clients as events.
+
+use crate::elements_inspector::{DomTree, ElementNode, ElementsInspector, NodeType, Screenshot};
+use crate::error::{Error, Result};
+use crate::js_debugger::JsDebugger;
+use crate::network_inspector::{
+    NetworkEvent, NetworkEventType, NetworkInspector, NetworkRequest, RequestStatus, RequestType,
+};
+use crate::DevToolsManager;
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use log::warn;
+use parking_lot::RwLock;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Invalid JSON was received
+const PARSE_ERROR: i64 = -32700;
+/// The message is not a valid request
+const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist
+const METHOD_NOT_FOUND: i64 = -32601;
+/// The method parameters are invalid
+const INVALID_PARAMS: i64 = -32602;
+/// The command failed, the code CDP uses for all command errors
+const SERVER_ERROR: i64 = -32000;
+
+/// Frame ID of the inspected page
+const MAIN_FRAME_ID: &str = "main";
+
+/// Chrome DevTools Protocol server
+pub struct CdpServer {
+    /// Port to listen on, 0 for any free port
+    port: u16,
+    /// Inspectors the domains delegate to
+    inspectors: Inspectors,
+    /// Address the server listens on
+    local_addr: Option<SocketAddr>,
+    /// Task accepting connections
+    accept_task: Option<JoinHandle<()>>,
+}
+
+/// Inspectors the CDP domains delegate to
+#[derive(Clone)]
+struct Inspectors {
+    elements_inspector: Arc<RwLock<ElementsInspector>>,
+    js_debugger: Arc<RwLock<JsDebugger>>,
+    network_inspector: Arc<RwLock<NetworkInspector>>,
+}
+
+/// State of one client connection
+struct CdpSession {
+    inspectors: Inspectors,
+    /// Network events, while the `Network` domain is enabled
+    network_events: Option<broadcast::Receiver<NetworkEvent>>,
+    /// CDP node IDs by element ID
+    node_ids: HashMap<String, i64>,
+}
+
+/// JSON-RPC request
+#[derive(Deserialize)]
+struct CdpRequest {
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Failed command
+struct CdpError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct EvaluateParams {
+    expression: String,
+}
+
+#[derive(Deserialize)]
+struct GetDocumentParams {
+    /// Depth of children to return, -1 for the whole tree
+    #[serde(default = "default_depth")]
+    depth: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuerySelectorAllParams {
+    node_id: i64,
+    selector: String,
+}
+
+#[derive(Deserialize)]
+struct NavigateParams {
+    url: String,
+}
+
+impl CdpServer {
+    /// Create a server for the inspectors of a DevTools manager
+    ///
+    /// A port of 0 listens on any free port.
+    pub fn new(port: u16, manager: &DevToolsManager) -> Self {
+        Self {
+            port,
+            inspectors: Inspectors {
+                elements_inspector: manager.elements_inspector(),
+                js_debugger: manager.js_debugger(),
+                network_inspector: manager.network_inspector(),
+            },
+            local_addr: None,
+            accept_task: None,
+        }
+    }
+
+    /// Start accepting connections on localhost, returning the address listened on
+    pub async fn start(&mut self) -> Result<SocketAddr> {
+        if self.accept_task.is_some() {
+            return Err(Error::inspector("CDP server is already running".to_string()));
+        }
+
+        let listener = TcpListener::bind(("127.0.0.1", self.port)).await?;
+        let local_addr = listener.local_addr()?;
+        let inspectors = self.inspectors.clone();
+        self.accept_task = Some(tokio::spawn(accept_connections(listener, inspectors)));
+        self.local_addr = Some(local_addr);
+
+        Ok(local_addr)
+    }
+
+    /// Stop the server, closing open connections
+    pub fn stop(&mut self) {
+        if let Some(accept_task) = self.accept_task.take() {
+            accept_task.abort();
+        }
+        self.local_addr = None;
+    }
+
+    /// Get the address the server listens on
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Check whether the server is running
+    pub fn is_running(&self) -> bool {
+        self.accept_task.is_some()
+    }
+}
+
+impl Drop for CdpServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Accept connections until the task is aborted
+///
+/// Connections are served by tasks owned by this one, so they end with it.
+async fn accept_connections(listener: TcpListener, inspectors: Inspectors) {
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    connections.spawn(serve_connection(stream, inspectors.clone()));
+                }
+                Err(e) => warn!("CDP server failed to accept a connection: {}", e),
+            },
+            Some(_) = connections.join_next() => {}
+        }
+    }
+}
+
+/// Answer requests on a connection and forward enabled events until it closes
+async fn serve_connection(stream: TcpStream, inspectors: Inspectors) {
+    let websocket = match tokio_tungstenite::accept_async(stream).await {
+        Ok(websocket) => websocket,
+        Err(e) => {
+            warn!("CDP WebSocket handshake failed: {}", e);
+            return;
+        }
+    };
+    let (mut sink, mut source) = websocket.split();
+    let mut session = CdpSession::new(inspectors);
+
+    loop {
+        let outgoing = tokio::select! {
+            message = source.next() => match message {
+                Some(Ok(Message::Text(text))) => session.handle_message(&text).await,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            Some(event) = next_network_event(&mut session.network_events) => network_notification(&event),
+        };
+
+        if sink.send(Message::Text(outgoing.to_string())).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Wait for the next network event, pending forever while none are subscribed
+async fn next_network_event(events: &mut Option<broadcast::Receiver<NetworkEvent>>) -> Option<NetworkEvent> {
+    let Some(receiver) = events else {
+        return std::future::pending().await;
+    };
+
+    loop {
+        match receiver.recv().await {
+            Ok(event) => return Some(event),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("CDP client missed {} network events", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                *events = None;
+                return None;
+            }
+        }
+    }
+}
+
+impl CdpSession {
+    fn new(inspectors: Inspectors) -> Self {
+        Self {
+            inspectors,
+            network_events: None,
+            node_ids: HashMap::new(),
+        }
+    }
+
+    /// Handle a JSON-RPC message, returning the response
+    async fn handle_message(&mut self, text: &str) -> Value {
+        let message: Value = match serde_json::from_str(text) {
+            Ok(message) => message,
+            Err(e) => return error_response(Value::Null, CdpError::new(PARSE_ERROR, e.to_string())),
+        };
+        let request: CdpRequest = match serde_json::from_value(message) {
+            Ok(request) => request,
+            Err(e) => return error_response(Value::Null, CdpError::new(INVALID_REQUEST, e.to_string())),
+        };
+
+        match self.dispatch(&request.method, request.params).await {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": request.id, "result": result }),
+            Err(error) => error_response(request.id, error),
+        }
+    }
+
+    /// Run a command in its domain
+    async fn dispatch(&mut self, method: &str, params: Value) -> std::result::Result<Value, CdpError> {
+        match method {
+            "Runtime.evaluate" => self.evaluate(parse_params(params)?).await,
+            "DOM.getDocument" => self.get_document(parse_params(params)?).await,
+            "DOM.querySelectorAll" => self.query_selector_all(parse_params(params)?),
+            "Network.enable" => {
+                self.network_events = Some(self.inspectors.network_inspector.read().subscribe());
+                Ok(json!({}))
+            }
+            "Network.disable" => {
+                self.network_events = None;
+                Ok(json!({}))
+            }
+            "Page.navigate" => {
+                let params: NavigateParams = parse_params(params)?;
+                self.inspectors.elements_inspector.read().navigate(&params.url)?;
+                Ok(json!({ "frameId": MAIN_FRAME_ID }))
+            }
+            "Page.captureScreenshot" => {
+                let capture = self.inspectors.elements_inspector.read().capture_screenshot();
+                let png = encode_png(&capture.await?);
+                Ok(json!({ "data": base64::engine::general_purpose::STANDARD.encode(png) }))
+            }
+            _ => Err(CdpError::new(METHOD_NOT_FOUND, format!("'{}' wasn't found", method))),
+        }
+    }
+
+    /// `Runtime.evaluate`, reporting script errors as exceptions
+    async fn evaluate(&self, params: EvaluateParams) -> std::result::Result<Value, CdpError> {
+        let evaluation = self.inspectors.js_debugger.write().evaluate(&params.expression);
+        match evaluation.await {
+            Ok(value) => Ok(json!({ "result": remote_object(value) })),
+            Err(Error::Evaluation(message)) => Ok(json!({
+                "result": { "type": "object", "subtype": "error", "description": message },
+                "exceptionDetails": { "exceptionId": 1, "text": message, "lineNumber": 0, "columnNumber": 0 },
+            })),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// `DOM.getDocument`, reloading the tree from the renderer
+    async fn get_document(&mut self, params: GetDocumentParams) -> std::result::Result<Value, CdpError> {
+        let load = self.inspectors.elements_inspector.read().load_dom_tree();
+        load.await?;
+
+        let dom_tree = self.inspectors.elements_inspector.read().dom_tree();
+        let dom_tree = dom_tree.read();
+        let root = dom_tree.root()
+            .ok_or_else(|| Error::inspector("Document is not loaded".to_string()))?;
+        Ok(json!({ "root": self.node(&dom_tree, root, params.depth) }))
+    }
+
+    /// `DOM.querySelectorAll`, matching descendants of a node in document order
+    fn query_selector_all(&mut self, params: QuerySelectorAllParams) -> std::result::Result<Value, CdpError> {
+        let element_id = self.node_ids.iter()
+            .find(|(_, node_id)| **node_id == params.node_id)
+            .map(|(element_id, _)| element_id.clone())
+            .ok_or_else(|| CdpError::new(INVALID_PARAMS, format!("Could not find node with given id {}", params.node_id)))?;
+
+        let dom_tree = self.inspectors.elements_inspector.read().dom_tree();
+        let dom_tree = dom_tree.read();
+        let node = dom_tree.get_element(&element_id)
+            .ok_or_else(|| CdpError::new(INVALID_PARAMS, format!("Could not find node with given id {}", params.node_id)))?;
+        let matches: HashSet<&str> = dom_tree.search_elements(&params.selector)?.into_iter()
+            .map(|element| element.id.as_str())
+            .collect();
+
+        let mut node_ids = Vec::new();
+        let mut stack: Vec<&str> = node.children.iter().rev().map(String::as_str).collect();
+        while let Some(id) = stack.pop() {
+            if matches.contains(id) {
+                node_ids.push(self.node_id(id));
+            }
+            if let Some(element) = dom_tree.get_element(id) {
+                stack.extend(element.children.iter().rev().map(String::as_str));
+            }
+        }
+
+        Ok(json!({ "nodeIds": node_ids }))
+    }
+
+    /// Convert an element to a CDP `DOM.Node`, with children down to `depth`
+    fn node(&mut self, dom_tree: &DomTree, element: &ElementNode, depth: i64) -> Value {
+        let mut node = json!({
+            "nodeId": self.node_id(&element.id),
+            "backendNodeId": self.node_id(&element.id),
+            "nodeType": dom_node_type(element.node_type),
+            "nodeName": element.node_name,
+            "localName": element.tag_name.as_deref().unwrap_or_default(),
+            "nodeValue": element.node_value.as_deref().unwrap_or_default(),
+            "childNodeCount": element.children.len(),
+        });
+        if element.node_type == NodeType::Element {
+            node["attributes"] = element.attributes.iter()
+                .flat_map(|attribute| [attribute.name.clone(), attribute.value.clone()])
+                .collect();
+        }
+        if depth != 0 {
+            node["children"] = element.children.iter()
+                .filter_map(|child_id| dom_tree.get_element(child_id))
+                .map(|child| self.node(dom_tree, child, depth - 1))
+                .collect();
+        }
+        node
+    }
+
+    /// Get the CDP node ID of an element, assigning one on first use
+    fn node_id(&mut self, element_id: &str) -> i64 {
+        let next_id = self.node_ids.len() as i64 + 1;
+        *self.node_ids.entry(element_id.to_string()).or_insert(next_id)
+    }
+}
+
+impl CdpError {
+    fn new(code: i64, message: String) -> Self {
+        Self { code, message }
+    }
+}
+
+impl From<Error> for CdpError {
+    fn from(error: Error) -> Self {
+        Self::new(SERVER_ERROR, error.to_string())
+    }
+}
+
+/// Build a JSON-RPC error response
+fn error_response(id: Value, error: CdpError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": error.code, "message": error.message } })
+}
+
+/// Parse command parameters, a missing object counting as empty
+fn parse_params<T: DeserializeOwned>(params: Value) -> std::result::Result<T, CdpError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| CdpError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn default_depth() -> i64 {
+    1
+}
+
+/// Convert a value returned by the page to a CDP `Runtime.RemoteObject`
+fn remote_object(value: Value) -> Value {
+    match value {
+        Value::Null => json!({ "type": "object", "subtype": "null", "value": null }),
+        Value::Bool(value) => json!({ "type": "boolean", "value": value }),
+        Value::Number(value) => json!({ "type": "number", "value": value, "description": value.to_string() }),
+        Value::String(value) => json!({ "type": "string", "value": value }),
+        Value::Array(_) => json!({ "type": "object", "subtype": "array", "value": value }),
+        Value::Object(_) => json!({ "type": "object", "value": value }),
+    }
+}
+
+/// DOM `nodeType` constant of a node
+fn dom_node_type(node_type: NodeType) -> u16 {
+    match node_type {
+        NodeType::Element => 1,
+        NodeType::Text => 3,
+        NodeType::Comment => 8,
+        NodeType::Document => 9,
+        NodeType::DocumentFragment => 11,
+    }
+}
+
+/// Encode a screenshot as a PNG image, storing the pixels uncompressed
+fn encode_png(screenshot: &Screenshot) -> Vec<u8> {
+    // Each scanline starts with filter type 0 (none)
+    let row_len = screenshot.width as usize * 4;
+    let mut scanlines = Vec::with_capacity((row_len + 1) * screenshot.height as usize);
+    for row in screenshot.pixels.chunks(row_len.max(1)) {
+        scanlines.push(0);
+        scanlines.extend_from_slice(row);
+    }
+
+    // 8-bit RGBA without interlacing
+    let mut header = Vec::with_capacity(13);
+    header.extend(screenshot.width.to_be_bytes());
+    header.extend(screenshot.height.to_be_bytes());
+    header.extend([8, 6, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    write_png_chunk(&mut png, b"IHDR", &header);
+    write_png_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    write_png_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Append a PNG chunk, its CRC covering the type and data
+fn write_png_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let crc = crc32fast::hash(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// Wrap data in a zlib stream of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK_LEN: usize = 65_535;
+
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_BLOCK_LEN).peekable();
+    if blocks.peek().is_none() {
+        stream.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        stream.push(u8::from(is_final));
+        stream.extend(len.to_le_bytes());
+        stream.extend((!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend(adler32(data).to_be_bytes());
+    stream
+}
+
+/// Adler-32 checksum of zlib streams
+fn adler32(data: &[u8]) -> u32 {
+    const MODULUS: u32 = 65_521;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % MODULUS;
+        b = (b + a) % MODULUS;
+    }
+    (b << 16) | a
+}
+
+/// Build the CDP notification for a network event
+fn network_notification(event: &NetworkEvent) -> Value {
+    let request = &event.request;
+    let started_at = request.started_at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let finished_at = started_at + Duration::from_secs_f64(request.timing.total().max(0.0) / 1000.0);
+
+    let (method, params) = match event.event_type {
+        NetworkEventType::RequestWillBeSent => ("Network.requestWillBeSent", json!({
+            "requestId": request.id,
+            "loaderId": MAIN_FRAME_ID,
+            "documentURL": request.url,
+            "request": request_object(request),
+            "timestamp": started_at.as_secs_f64(),
+            "wallTime": started_at.as_secs_f64(),
+            "initiator": { "type": "other" },
+            "type": resource_type(request.request_type),
+        })),
+        NetworkEventType::ResponseReceived => ("Network.responseReceived", json!({
+            "requestId": request.id,
+            "loaderId": MAIN_FRAME_ID,
+            "timestamp": finished_at.as_secs_f64(),
+            "type": resource_type(request.request_type),
+            "response": response_object(request),
+        })),
+        NetworkEventType::LoadingFailed => ("Network.loadingFailed", json!({
+            "requestId": request.id,
+            "timestamp": finished_at.as_secs_f64(),
+            "type": resource_type(request.request_type),
+            "errorText": match &request.status {
+                RequestStatus::Failed(reason) => reason.as_str(),
+                _ => "",
+            },
+            "canceled": request.status == RequestStatus::Cancelled,
+        })),
+    };
+
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+/// Build a CDP `Network.Request`
+fn request_object(request: &NetworkRequest) -> Value {
+    let mut object = json!({
+        "url": request.url,
+        "method": request.method.as_str(),
+        "headers": headers_object(&request.request_headers.headers),
+    });
+    if let Some(body) = &request.request_body {
+        object["postData"] = Value::String(body.clone());
+    }
+    object
+}
+
+/// Build a CDP `Network.Response`
+fn response_object(request: &NetworkRequest) -> Value {
+    json!({
+        "url": request.url,
+        "status": request.status_code.unwrap_or(0),
+        "statusText": request.status_text,
+        "headers": headers_object(&request.response_headers.headers),
+        "mimeType": request.mime_type.as_deref().unwrap_or_default(),
+        "protocol": request.http_version.to_ascii_lowercase(),
+        "encodedDataLength": request.response_size,
+    })
+}
+
+/// Convert headers to a CDP `Network.Headers` object
+///
+/// Repeated headers are joined with newlines, as CDP does.
+fn headers_object(headers: &[(String, String)]) -> Value {
+    let mut object = serde_json::Map::new();
+    for (name, value) in headers {
+        match object.get_mut(name) {
+            Some(Value::String(existing)) => {
+                existing.push('\n');
+                existing.push_str(value);
+            }
+            _ => {
+                object.insert(name.clone(), Value::String(value.clone()));
+            }
+        }
+    }
+    Value::Object(object)
+}
+
+/// CDP `Network.ResourceType` of a request
+fn resource_type(request_type: RequestType) -> &'static str {
+    match request_type {
+        RequestType::Document => "Document",
+        RequestType::Stylesheet => "Stylesheet",
+        RequestType::Script => "Script",
+        RequestType::Image => "Image",
+        RequestType::Font => "Font",
+        RequestType::Xhr => "XHR",
+        RequestType::Fetch => "Fetch",
+        RequestType::WebSocket => "WebSocket",
+        RequestType::Other => "Other",
+    }
+}
@@ -37,6 +37,8 @@ pub struct ConsoleMessage {
     pub arguments: Vec<ConsoleArgument>,
     /// Source location
     pub source: Option<SourceLocation>,
+    /// Location in the original source, when a source map covers the script
+    pub original_source: Option<SourceLocation>,
     /// Stack trace
     pub stack_trace: Option<StackTrace>,
     /// Timestamp
@@ -51,8 +53,19 @@ pub struct ConsoleMessage {
     pub group_collapsed: bool,
 }
 
+impl ConsoleMessage {
+    /// Get the location to display next to the message
+    ///
+    /// Original positions are preferred over positions in the compiled script.
+    pub fn display_location(&self) -> Option<String> {
+        self.original_source.as_ref()
+            .or(self.source.as_ref())
+            .map(|location| location.to_string())
+    }
+}
+
 /// Console message type
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ConsoleMessageType {
     /// Log message
     Log,
@@ -87,7 +100,7 @@ pub enum ConsoleMessageType {
 }
 
 /// Console level
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ConsoleLevel {
     /// Verbose level
     Verbose,
@@ -138,7 +151,7 @@ pub enum ArgumentType {
 }
 
 /// Source location
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SourceLocation {
     /// File URL
     pub file_url: String,
@@ -152,6 +165,12 @@ pub struct SourceLocation {
     pub script_id: Option<String>,
 }
 
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file_url, self.line_number, self.column_number)
+    }
+}
+
 /// Stack trace
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StackTrace {
@@ -183,6 +202,7 @@ pub struct StackFrame {
 }
 
 /// Console filters
+#[derive(Debug, Clone)]
 pub struct ConsoleFilters {
     /// Level filters
     level_filters: HashMap<ConsoleLevel, bool>,
@@ -390,6 +410,28 @@ impl ConsoleInspector {
 
     /// Add console message
     pub async fn add_message(&self, message_type: ConsoleMessageType, text: &str, arguments: Vec<ConsoleArgument>) -> Result<()> {
+        self.push_message(message_type, text, arguments, None, None);
+        Ok(())
+    }
+
+    /// Add an error thrown by a script
+    ///
+    /// `original_source` is the location in the original source when the
+    /// script has a source map, and is displayed instead of `source`.
+    pub async fn add_error_message(&self, text: &str, source: Option<SourceLocation>, original_source: Option<SourceLocation>) -> Result<()> {
+        self.push_message(ConsoleMessageType::Error, text, Vec::new(), source, original_source);
+        Ok(())
+    }
+
+    /// Build a console message and append it to the log
+    fn push_message(
+        &self,
+        message_type: ConsoleMessageType,
+        text: &str,
+        arguments: Vec<ConsoleArgument>,
+        source: Option<SourceLocation>,
+        original_source: Option<SourceLocation>,
+    ) {
         let message_id = Uuid::new_v4().to_string();
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -409,7 +451,8 @@ impl ConsoleInspector {
             level,
             text: text.to_string(),
             arguments,
-            source: None,
+            source,
+            original_source,
             stack_trace: None,
             timestamp,
             is_expanded: false,
@@ -420,8 +463,6 @@ impl ConsoleInspector {
         
         let mut messages = self.messages.write();
         messages.push(message);
-        
-        Ok(())
     }
 
     /// Get console messages
@@ -558,6 +599,30 @@ impl ConsoleInspector {
     }
 }
 
+/// Reports keyboard shortcut conflicts as console warnings
+pub struct ShortcutConflictConsole {
+    /// Console the warnings are written to
+    console: Arc<RwLock<ConsoleInspector>>,
+}
+
+impl ShortcutConflictConsole {
+    /// Create a reporter writing to a console
+    pub fn new(console: Arc<RwLock<ConsoleInspector>>) -> Self {
+        Self { console }
+    }
+}
+
+impl accessibility::ShortcutConflictReporter for ShortcutConflictConsole {
+    fn report_conflicts(&self, shortcut: &accessibility::KeyboardShortcut, conflicts: &[accessibility::ConflictingBinding]) {
+        let existing: Vec<String> = conflicts
+            .iter()
+            .map(|conflict| format!("\"{}\" ({:?}, context \"{}\")", conflict.action_name, conflict.priority, conflict.context))
+            .collect();
+        let text = format!("Keyboard shortcut \"{}\" conflicts with {}", shortcut.name, existing.join(", "));
+        self.console.read().push_message(ConsoleMessageType::Warning, &text, Vec::new(), None, None);
+    }
+}
+
 impl ConsoleFilters {
     /// Create new console filters
     pub fn new() -> Self {
@@ -794,10 +859,10 @@ impl ExpressionAutoComplete {
         ];
         
         Self {
-            keywords,
-            global_objects,
-            dom_elements,
-            css_selectors,
+            keywords: keywords.into_iter().map(String::from).collect(),
+            global_objects: global_objects.into_iter().map(String::from).collect(),
+            dom_elements: dom_elements.into_iter().map(String::from).collect(),
+            css_selectors: css_selectors.into_iter().map(String::from).collect(),
         }
     }
 
@@ -0,0 +1,154 @@
+//! CSS coverage for DevTools
+//!
+//! Records which style rules the cascade matches while a page loads and
+//! reports the CSS that never applied.
+
+use dom::{CssCascade, CssRule, CssStyleRule, RuleMatchObserver};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// CSS coverage tracker
+///
+/// Installed as the cascade's rule observer while coverage is recorded.
+pub struct CssCoverageTracker {
+    /// Style rules of each stylesheet, in cascade order
+    stylesheets: Vec<TrackedStyleSheet>,
+    /// Matched rules by stylesheet and rule index
+    used_rules: Mutex<HashSet<(usize, usize)>>,
+}
+
+/// Stylesheet whose rules are tracked
+struct TrackedStyleSheet {
+    url: Option<String>,
+    rules: Vec<TrackedRule>,
+}
+
+/// Style rule being tracked
+struct TrackedRule {
+    selector: String,
+    line: Option<usize>,
+    bytes: usize,
+}
+
+/// Style rule that never matched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnusedRule {
+    /// URL of the stylesheet, `None` for inline styles
+    pub stylesheet_url: Option<String>,
+    /// Selector text
+    pub selector: String,
+    /// Line of the rule in the stylesheet source
+    pub line: Option<usize>,
+    /// Size of the rule in bytes
+    pub unused_bytes: usize,
+}
+
+/// CSS coverage report
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// Coverage of each stylesheet, in cascade order
+    pub stylesheets: Vec<StyleSheetCoverage>,
+    /// Rules that never matched
+    pub unused_rules: Vec<UnusedRule>,
+}
+
+/// Coverage of one stylesheet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StyleSheetCoverage {
+    /// URL of the stylesheet, `None` for inline styles
+    pub stylesheet_url: Option<String>,
+    /// Size of the rules that matched in bytes
+    pub used_bytes: usize,
+    /// Size of the rules that never matched in bytes
+    pub unused_bytes: usize,
+    /// Share of the rule bytes that matched, 100 for stylesheets without rules
+    pub coverage_percentage: f64,
+}
+
+impl CssCoverageTracker {
+    /// Create a tracker for the style rules currently in a cascade
+    pub fn new(cascade: &CssCascade) -> Self {
+        let stylesheets = cascade.stylesheets().iter()
+            .map(|stylesheet| TrackedStyleSheet {
+                url: stylesheet.href().map(str::to_string),
+                rules: stylesheet.style_rules().into_iter().map(TrackedRule::new).collect(),
+            })
+            .collect();
+
+        Self {
+            stylesheets,
+            used_rules: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Get the rules that never matched, in cascade order
+    pub fn get_unused_rules(&self) -> Vec<UnusedRule> {
+        let guard = self.used_rules.lock();
+        let used_rules = &*guard;
+        self.stylesheets.iter().enumerate()
+            .flat_map(|(stylesheet_index, stylesheet)| {
+                stylesheet.rules.iter().enumerate()
+                    .filter(move |(rule_index, _)| !used_rules.contains(&(stylesheet_index, *rule_index)))
+                    .map(|(_, rule)| UnusedRule {
+                        stylesheet_url: stylesheet.url.clone(),
+                        selector: rule.selector.clone(),
+                        line: rule.line,
+                        unused_bytes: rule.bytes,
+                    })
+            })
+            .collect()
+    }
+
+    /// Build the coverage report
+    pub fn report(&self) -> CoverageReport {
+        let used_rules = self.used_rules.lock();
+        let stylesheets = self.stylesheets.iter().enumerate()
+            .map(|(stylesheet_index, stylesheet)| {
+                let (used, unused): (Vec<_>, Vec<_>) = stylesheet.rules.iter().enumerate()
+                    .partition(|(rule_index, _)| used_rules.contains(&(stylesheet_index, *rule_index)));
+                let used_bytes = used.iter().map(|(_, rule)| rule.bytes).sum();
+                let unused_bytes = unused.iter().map(|(_, rule)| rule.bytes).sum();
+                StyleSheetCoverage {
+                    stylesheet_url: stylesheet.url.clone(),
+                    used_bytes,
+                    unused_bytes,
+                    coverage_percentage: coverage_percentage(used_bytes, unused_bytes),
+                }
+            })
+            .collect();
+        drop(used_rules);
+
+        CoverageReport {
+            stylesheets,
+            unused_rules: self.get_unused_rules(),
+        }
+    }
+}
+
+impl RuleMatchObserver for CssCoverageTracker {
+    fn rule_matched(&self, stylesheet_index: usize, rule_index: usize) {
+        self.used_rules.lock().insert((stylesheet_index, rule_index));
+    }
+}
+
+impl TrackedRule {
+    /// Track a rule, measuring its serialized text when it has no source position
+    fn new(rule: &CssStyleRule) -> Self {
+        Self {
+            selector: rule.selectors.to_string(),
+            line: rule.source.map(|source| source.line),
+            bytes: rule.source.map_or_else(|| rule.css_text().len(), |source| source.length),
+        }
+    }
+}
+
+/// Percentage of bytes used
+fn coverage_percentage(used_bytes: usize, unused_bytes: usize) -> f64 {
+    let total = used_bytes + unused_bytes;
+    if total == 0 {
+        100.0
+    } else {
+        used_bytes as f64 * 100.0 / total as f64
+    }
+}
@@ -1,5 +1,10 @@
 use crate::error::{Error, Result};
+use crate::styles_inspector::StylesInspector;
+use common::ipc::{Channel, DevToolsRequest, DevToolsResponse, IpcMessage, NavigateTabRequest};
+use common::{TabId, Url};
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use parking_lot::RwLock;
 use serde::{Serialize, Deserialize};
@@ -15,14 +20,40 @@ pub struct ElementsInspector {
     highlighting: Arc<RwLock<ElementHighlighting>>,
     /// Attribute editor
     attribute_editor: Arc<RwLock<AttributeEditor>>,
+    /// Styles inspector providing computed styles
+    styles_inspector: Option<Arc<RwLock<StylesInspector>>>,
+    /// Renderer of the inspected tab
+    renderer: Arc<RwLock<Option<RendererConnection>>>,
+    /// Next DevTools request ID
+    next_request_id: AtomicU64,
     /// Inspector state
     state: InspectorState,
 }
 
+/// IPC channel to the renderer of the inspected tab
+#[derive(Debug, Clone)]
+pub struct RendererConnection {
+    /// Channel to the renderer
+    pub channel: Channel<IpcMessage>,
+    /// Inspected tab
+    pub tab_id: TabId,
+}
+
+/// Screenshot of the inspected page
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// RGBA pixels, row by row
+    pub pixels: Vec<u8>,
+}
+
 /// DOM tree representation
 pub struct DomTree {
-    /// Root element
-    root: Option<ElementNode>,
+    /// Root node ID
+    root: Option<String>,
     /// Element cache by ID
     elements: HashMap<String, ElementNode>,
     /// Node counter
@@ -43,7 +74,7 @@ pub struct ElementNode {
     /// Node value (for text nodes)
     pub node_value: Option<String>,
     /// Attributes
-    pub attributes: HashMap<String, String>,
+    pub attributes: Vec<AttributeInfo>,
     /// Child nodes
     pub children: Vec<String>, // Node IDs
     /// Parent node
@@ -132,6 +163,10 @@ pub struct ElementHighlighting {
     highlight_styles: HighlightStyles,
     /// Is highlighting enabled
     enabled: bool,
+    /// Renderer drawing the overlay
+    renderer: Option<RendererConnection>,
+    /// Element under the overlay
+    overlay_element: Option<String>,
 }
 
 /// Highlight information
@@ -302,33 +337,134 @@ impl ElementsInspector {
             dom_tree: Arc::new(RwLock::new(DomTree::new())),
             highlighting: Arc::new(RwLock::new(ElementHighlighting::new())),
             attribute_editor: Arc::new(RwLock::new(AttributeEditor::new())),
+            styles_inspector: None,
+            renderer: Arc::new(RwLock::new(None)),
+            next_request_id: AtomicU64::new(1),
             state: InspectorState::Idle,
         }
     }
 
-    /// Load DOM tree from page
-    pub async fn load_dom_tree(&self, page_dom: &str) -> Result<()> {
-        let mut dom_tree = self.dom_tree.write();
-        dom_tree.load_from_html(page_dom)?;
-        
-        Ok(())
+    /// Create elements inspector reading computed styles from a styles inspector
+    pub fn with_styles_inspector(styles_inspector: Arc<RwLock<StylesInspector>>) -> Self {
+        Self {
+            styles_inspector: Some(styles_inspector),
+            ..Self::new()
+        }
+    }
+
+    /// Connect to the renderer of the inspected tab
+    pub fn connect_renderer(&self, channel: Channel<IpcMessage>, tab_id: TabId) {
+        let connection = RendererConnection { channel, tab_id };
+        self.highlighting.write().set_renderer(Some(connection.clone()));
+        *self.renderer.write() = Some(connection);
+    }
+
+    /// Check whether a renderer is connected
+    pub fn is_connected(&self) -> bool {
+        self.renderer.read().is_some()
+    }
+
+    /// Load DOM tree from the renderer of the inspected tab
+    ///
+    /// The returned future does not borrow the inspector, so a lock on it can
+    /// be released before awaiting the tree.
+    pub fn load_dom_tree(&self) -> impl Future<Output = Result<()>> + Send + 'static {
+        let reply = self.renderer_request(|request_id, tab_id| DevToolsRequest::GetDomTree { request_id, tab_id });
+        let dom_tree = self.dom_tree.clone();
+
+        async move {
+            match reply.await? {
+                DevToolsResponse::DomTree { tree, .. } => dom_tree.write().load_from_json(&tree),
+                other => Err(Error::inspector(format!("Unexpected reply from renderer: {:?}", other))),
+            }
+        }
+    }
+
+    /// Capture the inspected page
+    pub fn capture_screenshot(&self) -> impl Future<Output = Result<Screenshot>> + Send + 'static {
+        let reply = self.renderer_request(|request_id, tab_id| DevToolsRequest::CaptureScreenshot { request_id, tab_id });
+
+        async move {
+            match reply.await? {
+                DevToolsResponse::Screenshot { width, height, pixels, .. } => Ok(Screenshot { width, height, pixels }),
+                other => Err(Error::inspector(format!("Unexpected reply from renderer: {:?}", other))),
+            }
+        }
+    }
+
+    /// Navigate the inspected tab to a URL
+    pub fn navigate(&self, url: &str) -> Result<()> {
+        let connection = self.renderer.read().clone()
+            .ok_or_else(|| Error::inspector("No renderer connected".to_string()))?;
+        let url = Url::try_from(url).map_err(|e| Error::inspector(format!("Invalid URL '{}': {}", url, e)))?;
+
+        connection.channel.send(IpcMessage::NavigateTab(NavigateTabRequest {
+            tab_id: connection.tab_id,
+            url,
+            reload: false,
+        })).map_err(ipc_error)
+    }
+
+    /// Get the cached DOM tree
+    pub fn dom_tree(&self) -> Arc<RwLock<DomTree>> {
+        self.dom_tree.clone()
+    }
+
+    /// Send a request to the renderer, returning a future for its reply
+    ///
+    /// Error replies resolve to an error.
+    fn renderer_request(
+        &self,
+        request: impl FnOnce(u64, TabId) -> DevToolsRequest,
+    ) -> impl Future<Output = Result<DevToolsResponse>> + Send + 'static {
+        let connection = self.renderer.read().clone();
+        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let request = connection.as_ref().map(|connection| request(request_id, connection.tab_id));
+
+        async move {
+            let (connection, request) = connection.zip(request)
+                .ok_or_else(|| Error::inspector("No renderer connected".to_string()))?;
+            connection.channel.send_async(IpcMessage::DevToolsRequest(request)).await.map_err(ipc_error)?;
+
+            match connection.channel.recv_async().await.map_err(ipc_error)? {
+                IpcMessage::DevToolsResponse(DevToolsResponse::Error { request_id: id, message }) if id == request_id => {
+                    Err(Error::inspector(message))
+                }
+                IpcMessage::DevToolsResponse(response) if response_id(&response) == request_id => Ok(response),
+                other => Err(Error::inspector(format!("Unexpected reply from renderer: {:?}", other))),
+            }
+        }
     }
 
     /// Select element by ID
-    pub async fn select_element(&self, element_id: &str) -> Result<()> {
-        let dom_tree = self.dom_tree.read();
+    ///
+    /// The returned information carries the computed styles known to the
+    /// styles inspector and the element's bounding box.
+    pub async fn select_element(&self, element_id: &str) -> Result<ElementInfo> {
+        let mut info = {
+            let dom_tree = self.dom_tree.read();
+            let element = dom_tree.get_element(element_id)
+                .ok_or_else(|| Error::inspector(format!("Element '{}' not found", element_id)))?;
+            let mut info = ElementInfo::from_element_node(element);
+            info.path = dom_tree.get_element_path(element_id).unwrap_or_default();
+            info
+        };
         
-        if let Some(element) = dom_tree.get_element(element_id) {
-            let mut selected = self.selected_element.write();
-            *selected = Some(ElementInfo::from_element_node(element));
-            
-            // Highlight selected element
-            self.highlight_element(element_id, "selected").await?;
-            
-            Ok(())
-        } else {
-            Err(Error::inspector(format!("Element '{}' not found", element_id)))
+        if let Some(styles_inspector) = &self.styles_inspector {
+            // Styles may not have been computed for the element yet
+            if let Ok(styles) = styles_inspector.read().get_computed_styles(element_id).await {
+                info.computed_styles = styles.styles.into_iter()
+                    .map(|(name, property)| (name, property.value))
+                    .collect();
+            }
         }
+        
+        *self.selected_element.write() = Some(info.clone());
+        
+        // Highlight selected element
+        self.highlight_element(element_id, "selected").await?;
+        
+        Ok(info)
     }
 
     /// Select element by coordinates
@@ -485,28 +621,19 @@ impl DomTree {
         }
     }
 
-    /// Load DOM tree from HTML
-    pub fn load_from_html(&mut self, html: &str) -> Result<()> {
-        // This is a simplified implementation
-        // In a real implementation, you would parse the HTML and build the DOM tree
+    /// Load DOM tree from the JSON serialization sent by a renderer
+    ///
+    /// Elements keep the renderer's ID, their `id` attribute or node ID, so
+    /// they can be highlighted in the rendered page.
+    pub fn load_from_json(&mut self, json: &str) -> Result<()> {
+        let document: serde_json::Value = serde_json::from_str(json)?;
         
-        // Create root document node
+        self.elements.clear();
         let root_id = self.create_node_id();
-        let root_node = ElementNode {
-            id: root_id.clone(),
-            node_type: NodeType::Document,
-            tag_name: None,
-            node_name: "#document".to_string(),
-            node_value: None,
-            attributes: HashMap::new(),
-            children: Vec::new(),
-            parent: None,
-            computed_styles: HashMap::new(),
-            bounding_box: None,
-            is_visible: true,
-            is_selected: false,
-            is_expanded: true,
-        };
+        let mut root_node = ElementNode::new(root_id.clone(), NodeType::Document, "#document".to_string());
+        if let Some(element) = document.get("documentElement") {
+            root_node.children.extend(self.load_node(element, &root_id));
+        }
         
         self.elements.insert(root_id.clone(), root_node);
         self.root = Some(root_id);
@@ -514,6 +641,58 @@ impl DomTree {
         Ok(())
     }
 
+    /// Add a serialized node and its descendants, returning its ID
+    fn load_node(&mut self, node: &serde_json::Value, parent_id: &str) -> Option<String> {
+        let mut element = match node["type"].as_str()? {
+            "element" => {
+                let tag_name = node["tagName"].as_str()?.to_string();
+                let id = node["id"].as_str()
+                    .filter(|id| !self.elements.contains_key(*id))
+                    .or_else(|| node["nodeId"].as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| self.create_node_id());
+                
+                let mut element = ElementNode::new(id, NodeType::Element, tag_name.to_uppercase());
+                element.tag_name = Some(tag_name);
+                if let Some(attributes) = node["attributes"].as_object() {
+                    element.attributes = attributes.iter()
+                        .map(|(name, value)| AttributeInfo {
+                            name: name.clone(),
+                            value: value.as_str().unwrap_or_default().to_string(),
+                            is_editable: true,
+                            is_required: false,
+                        })
+                        .collect();
+                    element.attributes.sort_by(|a, b| a.name.cmp(&b.name));
+                }
+                element.bounding_box = serde_json::from_value(node["boundingBox"].clone()).ok();
+                element
+            }
+            "text" => ElementNode::new(self.create_node_id(), NodeType::Text, "#text".to_string()),
+            "comment" => ElementNode::new(self.create_node_id(), NodeType::Comment, "#comment".to_string()),
+            _ => return None,
+        };
+        element.node_value = node["textContent"].as_str().map(str::to_string);
+        element.parent = Some(parent_id.to_string());
+        
+        let id = element.id.clone();
+        if let Some(children) = node["children"].as_array() {
+            for child in children {
+                if let Some(child_id) = self.load_node(child, &id) {
+                    element.children.push(child_id);
+                }
+            }
+        }
+        
+        self.elements.insert(id.clone(), element);
+        Some(id)
+    }
+
+    /// Get the document node
+    pub fn root(&self) -> Option<&ElementNode> {
+        self.root.as_ref().and_then(|root_id| self.elements.get(root_id))
+    }
+
     /// Get element by ID
     pub fn get_element(&self, element_id: &str) -> Option<&ElementNode> {
         self.elements.get(element_id)
@@ -613,7 +792,15 @@ impl DomTree {
     /// Update attribute
     pub fn update_attribute(&mut self, element_id: &str, attribute_name: &str, new_value: &str) -> Result<()> {
         if let Some(element) = self.elements.get_mut(element_id) {
-            element.attributes.insert(attribute_name.to_string(), new_value.to_string());
+            match element.attributes.iter_mut().find(|attribute| attribute.name == attribute_name) {
+                Some(attribute) => attribute.value = new_value.to_string(),
+                None => element.attributes.push(AttributeInfo {
+                    name: attribute_name.to_string(),
+                    value: new_value.to_string(),
+                    is_editable: true,
+                    is_required: false,
+                }),
+            }
             Ok(())
         } else {
             Err(Error::inspector(format!("Element '{}' not found", element_id)))
@@ -651,8 +838,8 @@ impl DomTree {
         
         // Convert attributes
         let mut attributes = serde_json::Map::new();
-        for (key, value) in &element.attributes {
-            attributes.insert(key.clone(), serde_json::Value::String(value.clone()));
+        for attribute in &element.attributes {
+            attributes.insert(attribute.name.clone(), serde_json::Value::String(attribute.value.clone()));
         }
         element_data.insert("attributes".to_string(), serde_json::Value::Object(attributes));
         
@@ -710,9 +897,39 @@ impl ElementHighlighting {
             highlighted_elements: HashMap::new(),
             highlight_styles: HighlightStyles::default(),
             enabled: true,
+            renderer: None,
+            overlay_element: None,
         }
     }
 
+    /// Set the renderer drawing the overlay
+    pub fn set_renderer(&mut self, renderer: Option<RendererConnection>) {
+        self.renderer = renderer;
+        self.overlay_element = None;
+    }
+
+    /// Draw the overlay over an element in the renderer
+    pub fn highlight(&mut self, element_id: &str) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        
+        self.send_overlay(Some(element_id.to_string()))
+    }
+
+    /// Move the renderer's overlay, `None` removing it
+    fn send_overlay(&mut self, element_id: Option<String>) -> Result<()> {
+        if let Some(renderer) = &self.renderer {
+            renderer.channel.send(IpcMessage::DevToolsRequest(DevToolsRequest::HighlightElement {
+                tab_id: renderer.tab_id,
+                element_id: element_id.clone(),
+            })).map_err(ipc_error)?;
+        }
+        self.overlay_element = element_id;
+        
+        Ok(())
+    }
+
     /// Highlight element
     pub fn highlight_element(&mut self, element_id: &str, highlight_type: &str) -> Result<()> {
         if !self.enabled {
@@ -738,18 +955,24 @@ impl ElementHighlighting {
         
         self.highlighted_elements.insert(element_id.to_string(), highlight_info);
         
-        Ok(())
+        self.highlight(element_id)
     }
 
     /// Remove highlight
     pub fn remove_highlight(&mut self, element_id: &str) -> Result<()> {
         self.highlighted_elements.remove(element_id);
+        if self.overlay_element.as_deref() == Some(element_id) {
+            self.send_overlay(None)?;
+        }
         Ok(())
     }
 
     /// Clear all highlights
     pub fn clear_all(&mut self) -> Result<()> {
         self.highlighted_elements.clear();
+        if self.overlay_element.is_some() {
+            self.send_overlay(None)?;
+        }
         Ok(())
     }
 
@@ -878,24 +1101,35 @@ impl AttributeEditor {
     }
 }
 
+impl ElementNode {
+    /// Create node without attributes or children
+    pub fn new(id: String, node_type: NodeType, node_name: String) -> Self {
+        Self {
+            id,
+            node_type,
+            tag_name: None,
+            node_name,
+            node_value: None,
+            attributes: Vec::new(),
+            children: Vec::new(),
+            parent: None,
+            computed_styles: HashMap::new(),
+            bounding_box: None,
+            is_visible: true,
+            is_selected: false,
+            is_expanded: true,
+        }
+    }
+}
+
 impl ElementInfo {
     /// Create from element node
     pub fn from_element_node(node: &ElementNode) -> Self {
-        let attributes = node.attributes
-            .iter()
-            .map(|(name, value)| AttributeInfo {
-                name: name.clone(),
-                value: value.clone(),
-                is_editable: true,
-                is_required: false,
-            })
-            .collect();
-        
         Self {
             id: node.id.clone(),
             tag_name: node.tag_name.clone().unwrap_or_default(),
             name: node.node_name.clone(),
-            attributes,
+            attributes: node.attributes.clone(),
             computed_styles: node.computed_styles.clone(),
             bounding_box: node.bounding_box.clone(),
             path: Vec::new(), // Will be populated separately
@@ -905,6 +1139,20 @@ impl ElementInfo {
     }
 }
 
+/// Request ID a renderer reply answers
+fn response_id(response: &DevToolsResponse) -> u64 {
+    match response {
+        DevToolsResponse::DomTree { request_id, .. }
+        | DevToolsResponse::Screenshot { request_id, .. }
+        | DevToolsResponse::Error { request_id, .. } => *request_id,
+    }
+}
+
+/// Wrap an IPC failure talking to the renderer
+fn ipc_error(error: common::error::Error) -> Error {
+    Error::inspector(format!("Renderer IPC failed: {}", error))
+}
+
 /// Element statistics
 #[derive(Debug, Clone, Default)]
 pub struct ElementStats {
@@ -277,65 +277,33 @@ impl Error {
     }
     
     /// Get error message
-    pub fn message(&self) -> &str {
+    pub fn message(&self) -> String {
         match self {
-            Error::Inspector(msg) => msg,
-            Error::Dom(msg) => msg,
-            Error::Style(msg) => msg,
-            Error::Console(msg) => msg,
-            Error::Network(msg) => msg,
-            Error::Performance(msg) => msg,
-            Error::Evaluation(msg) => msg,
-            Error::SourceMap(msg) => msg,
-            Error::StackTrace(msg) => msg,
-            Error::Filter(msg) => msg,
-            Error::ElementNotFound(msg) => msg,
-            Error::StyleNotFound(msg) => msg,
-            Error::ConsoleMessageNotFound(msg) => msg,
-            Error::NetworkRequestNotFound(msg) => msg,
-            Error::PerformanceEntryNotFound(msg) => msg,
-            Error::InvalidSelector(msg) => msg,
-            Error::InvalidCssProperty(msg) => msg,
-            Error::InvalidExpression(msg) => msg,
-            Error::InvalidSourceMap(msg) => msg,
-            Error::InvalidStackTrace(msg) => msg,
-            Error::Serialization(msg) => msg,
-            Error::Deserialization(msg) => msg,
-            Error::Io(err) => err.to_string().as_str(),
-            Error::Json(err) => err.to_string().as_str(),
-            Error::Uuid(err) => err.to_string().as_str(),
-        }
-    }
-}
-
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Error::Inspector(msg) => write!(f, "Inspector error: {}", msg),
-            Error::Dom(msg) => write!(f, "DOM error: {}", msg),
-            Error::Style(msg) => write!(f, "Style error: {}", msg),
-            Error::Console(msg) => write!(f, "Console error: {}", msg),
-            Error::Network(msg) => write!(f, "Network error: {}", msg),
-            Error::Performance(msg) => write!(f, "Performance error: {}", msg),
-            Error::Evaluation(msg) => write!(f, "Evaluation error: {}", msg),
-            Error::SourceMap(msg) => write!(f, "Source map error: {}", msg),
-            Error::StackTrace(msg) => write!(f, "Stack trace error: {}", msg),
-            Error::Filter(msg) => write!(f, "Filter error: {}", msg),
-            Error::ElementNotFound(msg) => write!(f, "Element not found: {}", msg),
-            Error::StyleNotFound(msg) => write!(f, "Style not found: {}", msg),
-            Error::ConsoleMessageNotFound(msg) => write!(f, "Console message not found: {}", msg),
-            Error::NetworkRequestNotFound(msg) => write!(f, "Network request not found: {}", msg),
-            Error::PerformanceEntryNotFound(msg) => write!(f, "Performance entry not found: {}", msg),
-            Error::InvalidSelector(msg) => write!(f, "Invalid selector: {}", msg),
-            Error::InvalidCssProperty(msg) => write!(f, "Invalid CSS property: {}", msg),
-            Error::InvalidExpression(msg) => write!(f, "Invalid expression: {}", msg),
-            Error::InvalidSourceMap(msg) => write!(f, "Invalid source map: {}", msg),
-            Error::InvalidStackTrace(msg) => write!(f, "Invalid stack trace: {}", msg),
-            Error::Serialization(msg) => write!(f, "Serialization error: {}", msg),
-            Error::Deserialization(msg) => write!(f, "Deserialization error: {}", msg),
-            Error::Io(err) => write!(f, "IO error: {}", err),
-            Error::Json(err) => write!(f, "JSON error: {}", err),
-            Error::Uuid(err) => write!(f, "UUID error: {}", err),
+            Error::Inspector(msg) => msg.clone(),
+            Error::Dom(msg) => msg.clone(),
+            Error::Style(msg) => msg.clone(),
+            Error::Console(msg) => msg.clone(),
+            Error::Network(msg) => msg.clone(),
+            Error::Performance(msg) => msg.clone(),
+            Error::Evaluation(msg) => msg.clone(),
+            Error::SourceMap(msg) => msg.clone(),
+            Error::StackTrace(msg) => msg.clone(),
+            Error::Filter(msg) => msg.clone(),
+            Error::ElementNotFound(msg) => msg.clone(),
+            Error::StyleNotFound(msg) => msg.clone(),
+            Error::ConsoleMessageNotFound(msg) => msg.clone(),
+            Error::NetworkRequestNotFound(msg) => msg.clone(),
+            Error::PerformanceEntryNotFound(msg) => msg.clone(),
+            Error::InvalidSelector(msg) => msg.clone(),
+            Error::InvalidCssProperty(msg) => msg.clone(),
+            Error::InvalidExpression(msg) => msg.clone(),
+            Error::InvalidSourceMap(msg) => msg.clone(),
+            Error::InvalidStackTrace(msg) => msg.clone(),
+            Error::Serialization(msg) => msg.clone(),
+            Error::Deserialization(msg) => msg.clone(),
+            Error::Io(err) => err.to_string(),
+            Error::Json(err) => err.to_string(),
+            Error::Uuid(err) => err.to_string(),
         }
     }
 }
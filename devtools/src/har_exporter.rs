@@ -0,0 +1,250 @@
+//! HAR (HTTP Archive) export for the Network Inspector
+//!
+//! Produces HAR 1.2 documents from recorded network requests.
+
+use crate::error::{Error, Result};
+use crate::network_inspector::{NetworkRequest, NetworkTiming};
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// HAR format version
+const HAR_VERSION: &str = "1.2";
+
+/// HAR exporter
+pub struct HarExporter;
+
+#[derive(Serialize)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Serialize)]
+struct HarLog {
+    version: &'static str,
+    creator: HarCreator,
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Serialize)]
+struct HarCreator {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarEntry {
+    started_date_time: String,
+    time: f64,
+    request: HarRequest,
+    response: HarResponse,
+    cache: HarCache,
+    timings: HarTimings,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: &'static str,
+    url: String,
+    http_version: String,
+    cookies: Vec<HarNameValue>,
+    headers: Vec<HarNameValue>,
+    query_string: Vec<HarNameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<HarPostData>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarPostData {
+    mime_type: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    status_text: String,
+    http_version: String,
+    cookies: Vec<HarNameValue>,
+    headers: Vec<HarNameValue>,
+    content: HarContent,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarContent {
+    size: i64,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HarCache {}
+
+#[derive(Serialize)]
+struct HarNameValue {
+    name: String,
+    value: String,
+}
+
+/// Phase durations in milliseconds, -1 for phases that did not apply
+#[derive(Serialize)]
+struct HarTimings {
+    blocked: f64,
+    dns: f64,
+    connect: f64,
+    ssl: f64,
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+impl HarExporter {
+    /// Export requests as a HAR 1.2 JSON document
+    pub fn export(requests: &[NetworkRequest]) -> Result<String> {
+        let har = Har {
+            log: HarLog {
+                version: HAR_VERSION,
+                creator: HarCreator {
+                    name: "Matte Browser",
+                    version: env!("CARGO_PKG_VERSION"),
+                },
+                entries: requests.iter().map(Self::entry).collect(),
+            },
+        };
+
+        serde_json::to_string_pretty(&har)
+            .map_err(|e| Error::Network(format!("Failed to serialize HAR: {}", e)))
+    }
+
+    /// Build the entry for a request
+    fn entry(request: &NetworkRequest) -> HarEntry {
+        let request_headers = Self::name_values(&request.request_headers.headers);
+        let response_headers = Self::name_values(&request.response_headers.headers);
+
+        HarEntry {
+            started_date_time: format_iso8601(request.started_at),
+            time: request.timing.total(),
+            request: HarRequest {
+                method: request.method.as_str(),
+                url: request.url.clone(),
+                http_version: request.http_version.clone(),
+                cookies: Vec::new(),
+                query_string: Self::query_string(&request.url),
+                post_data: request.request_body.as_ref().map(|body| HarPostData {
+                    mime_type: Self::header(&request.request_headers.headers, "content-type")
+                        .unwrap_or_default(),
+                    text: body.clone(),
+                }),
+                headers: request_headers,
+                headers_size: -1,
+                body_size: request.request_body.as_ref().map_or(0, |body| body.len() as i64),
+            },
+            response: HarResponse {
+                // HAR uses status 0 for requests without a response
+                status: request.status_code.unwrap_or(0),
+                status_text: request.status_text.clone(),
+                http_version: request.http_version.clone(),
+                cookies: Vec::new(),
+                content: HarContent {
+                    size: request.response_size as i64,
+                    mime_type: request.mime_type.clone().unwrap_or_default(),
+                    text: request.response_body.clone(),
+                },
+                redirect_url: Self::header(&request.response_headers.headers, "location")
+                    .unwrap_or_default(),
+                headers: response_headers,
+                headers_size: -1,
+                body_size: if request.status_code.is_some() { request.response_size as i64 } else { -1 },
+            },
+            cache: HarCache {},
+            timings: Self::timings(&request.timing),
+        }
+    }
+
+    /// Convert `NetworkTiming` to HAR timings
+    fn timings(timing: &NetworkTiming) -> HarTimings {
+        HarTimings {
+            blocked: timing.blocked.unwrap_or(-1.0),
+            dns: timing.dns.unwrap_or(-1.0),
+            connect: timing.connect.unwrap_or(-1.0),
+            ssl: timing.ssl.unwrap_or(-1.0),
+            send: timing.send,
+            wait: timing.wait,
+            receive: timing.receive,
+        }
+    }
+
+    fn name_values(pairs: &[(String, String)]) -> Vec<HarNameValue> {
+        pairs.iter()
+            .map(|(name, value)| HarNameValue { name: name.clone(), value: value.clone() })
+            .collect()
+    }
+
+    /// Find a header value, ignoring case
+    fn header(headers: &[(String, String)], name: &str) -> Option<String> {
+        headers.iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Split the query of a URL into name/value pairs
+    fn query_string(url: &str) -> Vec<HarNameValue> {
+        let Some((_, query)) = url.split_once('?') else {
+            return Vec::new();
+        };
+        let query = query.split('#').next().unwrap_or_default();
+
+        query.split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                HarNameValue { name: name.to_string(), value: value.to_string() }
+            })
+            .collect()
+    }
+}
+
+/// Format a time as an ISO 8601 UTC timestamp with milliseconds
+fn format_iso8601(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis(),
+    )
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) date
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
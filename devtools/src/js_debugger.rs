@@ -0,0 +1,195 @@
+use crate::console_inspector::SourceLocation;
+use crate::error::{Error, Result};
+use common::ipc::{Channel, DebuggerCallFrame, DebuggerEvent, DebuggerRequest, IpcMessage, PauseReason, ScopeVariable};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+
+/// JavaScript debugger
+///
+/// Commands are sent to the JavaScript VM of the inspected renderer, which
+/// reports pauses back as `DebuggerEvent`s.
+pub struct JsDebugger {
+    /// Breakpoints by location
+    breakpoints: HashMap<SourceLocation, BreakpointId>,
+    /// State of the paused script
+    paused_state: Option<PausedState>,
+    /// Channel to the renderer
+    renderer: Option<Channel<IpcMessage>>,
+    /// Next breakpoint ID
+    next_breakpoint_id: u64,
+    /// Next evaluation request ID
+    next_evaluation_id: u64,
+}
+
+/// Breakpoint identifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BreakpointId(pub u64);
+
+/// State of a paused script
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PausedState {
+    /// Call frames, innermost first
+    pub call_frames: Vec<DebuggerCallFrame>,
+    /// Why the script paused
+    pub reason: PauseReason,
+    /// Variables in scope of the innermost frame
+    pub scope_variables: Vec<ScopeVariable>,
+}
+
+impl JsDebugger {
+    /// Create new JavaScript debugger
+    pub fn new() -> Self {
+        Self {
+            breakpoints: HashMap::new(),
+            paused_state: None,
+            renderer: None,
+            next_breakpoint_id: 1,
+            next_evaluation_id: 1,
+        }
+    }
+
+    /// Connect to the renderer of the inspected tab
+    pub fn connect_renderer(&mut self, channel: Channel<IpcMessage>) {
+        self.renderer = Some(channel);
+    }
+
+    /// Set a breakpoint, a column of 0 matching the whole line
+    ///
+    /// Setting a breakpoint twice at the same location returns the existing ID.
+    pub async fn set_breakpoint(&mut self, url: &str, line: u32, column: u32) -> Result<BreakpointId> {
+        let location = SourceLocation {
+            file_url: url.to_string(),
+            line_number: line,
+            column_number: column,
+            function_name: None,
+            script_id: None,
+        };
+        if let Some(breakpoint_id) = self.breakpoints.get(&location) {
+            return Ok(*breakpoint_id);
+        }
+
+        let breakpoint_id = BreakpointId(self.next_breakpoint_id);
+        self.send(DebuggerRequest::SetBreakpoint {
+            breakpoint_id: breakpoint_id.0,
+            url: url.to_string(),
+            line,
+            column,
+        }).await?;
+        self.next_breakpoint_id += 1;
+        self.breakpoints.insert(location, breakpoint_id);
+
+        Ok(breakpoint_id)
+    }
+
+    /// Remove a breakpoint
+    pub async fn remove_breakpoint(&mut self, breakpoint_id: BreakpointId) -> Result<()> {
+        let location = self.breakpoints.iter()
+            .find(|(_, id)| **id == breakpoint_id)
+            .map(|(location, _)| location.clone())
+            .ok_or_else(|| Error::inspector(format!("Breakpoint {} not found", breakpoint_id.0)))?;
+
+        self.send(DebuggerRequest::RemoveBreakpoint { breakpoint_id: breakpoint_id.0 }).await?;
+        self.breakpoints.remove(&location);
+
+        Ok(())
+    }
+
+    /// Get breakpoints by location
+    pub fn get_breakpoints(&self) -> &HashMap<SourceLocation, BreakpointId> {
+        &self.breakpoints
+    }
+
+    /// Continue until the next breakpoint
+    pub async fn resume(&mut self) -> Result<()> {
+        self.continue_execution(DebuggerRequest::Resume).await
+    }
+
+    /// Run to the next line of the paused function
+    pub async fn step_over(&mut self) -> Result<()> {
+        self.continue_execution(DebuggerRequest::StepOver).await
+    }
+
+    /// Run to the next line, entering function calls
+    pub async fn step_into(&mut self) -> Result<()> {
+        self.continue_execution(DebuggerRequest::StepInto).await
+    }
+
+    /// Run until the paused function returns
+    pub async fn step_out(&mut self) -> Result<()> {
+        self.continue_execution(DebuggerRequest::StepOut).await
+    }
+
+    /// Evaluate an expression in the global scope of the inspected page
+    ///
+    /// The returned future does not borrow the debugger, so a lock on it can
+    /// be released before awaiting the result.
+    pub fn evaluate(&mut self, expression: &str) -> impl Future<Output = Result<serde_json::Value>> + Send + 'static {
+        let request_id = self.next_evaluation_id;
+        self.next_evaluation_id += 1;
+        let channel = self.renderer.clone();
+        let expression = expression.to_string();
+
+        async move {
+            let channel = channel.ok_or_else(|| Error::inspector("No renderer connected".to_string()))?;
+            channel.send_async(IpcMessage::DebuggerRequest(DebuggerRequest::Evaluate { request_id, expression })).await
+                .map_err(|e| Error::inspector(format!("Renderer IPC failed: {}", e)))?;
+
+            match channel.recv_async().await.map_err(|e| Error::inspector(format!("Renderer IPC failed: {}", e)))? {
+                IpcMessage::DebuggerEvent(DebuggerEvent::Evaluated { request_id: id, value, error }) if id == request_id => {
+                    match (value, error) {
+                        (_, Some(error)) => Err(Error::evaluation(error)),
+                        (Some(value), None) => Ok(serde_json::from_str(&value)?),
+                        (None, None) => Ok(serde_json::Value::Null),
+                    }
+                }
+                other => Err(Error::inspector(format!("Unexpected reply from renderer: {:?}", other))),
+            }
+        }
+    }
+
+    /// Update the paused state from a renderer notification
+    pub fn handle_event(&mut self, event: DebuggerEvent) {
+        match event {
+            DebuggerEvent::Paused { call_frames, reason } => {
+                let scope_variables = call_frames.first()
+                    .map(|frame| frame.scope.clone())
+                    .unwrap_or_default();
+                self.paused_state = Some(PausedState { call_frames, reason, scope_variables });
+            }
+            DebuggerEvent::Resumed => self.paused_state = None,
+            DebuggerEvent::Evaluated { .. } => {}
+        }
+    }
+
+    /// Get the state of the paused script
+    pub fn get_paused_state(&self) -> Option<&PausedState> {
+        self.paused_state.as_ref()
+    }
+
+    /// Check whether a script is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused_state.is_some()
+    }
+
+    /// Send a command continuing the paused script
+    async fn continue_execution(&mut self, request: DebuggerRequest) -> Result<()> {
+        if self.paused_state.is_none() {
+            return Err(Error::inspector("Script is not paused".to_string()));
+        }
+
+        self.send(request).await?;
+        self.paused_state = None;
+
+        Ok(())
+    }
+
+    /// Send a command to the renderer
+    async fn send(&self, request: DebuggerRequest) -> Result<()> {
+        let channel = self.renderer.as_ref()
+            .ok_or_else(|| Error::inspector("No renderer connected".to_string()))?;
+
+        channel.send_async(IpcMessage::DebuggerRequest(request)).await
+            .map_err(|e| Error::inspector(format!("Renderer IPC failed: {}", e)))
+    }
+}
@@ -8,18 +8,22 @@ pub mod elements_inspector;
 pub mod styles_inspector;
 pub mod console_inspector;
 pub mod network_inspector;
+pub mod har_exporter;
 pub mod performance_tools;
+pub mod js_debugger;
+pub mod coverage;
+pub mod cdp;
 
 pub use error::{Error, Result};
 pub use elements_inspector::{
-    ElementsInspector, DomTree, ElementNode, NodeType, BoundingBox,
+    ElementsInspector, RendererConnection, Screenshot, DomTree, ElementNode, NodeType, BoundingBox,
     ElementInfo, AttributeInfo, ElementHighlighting, HighlightInfo,
     HighlightStyles, AttributeEditor, EditableAttribute, AttributeChange,
     ValidationRule, ValidationRuleType, InspectorState, DomTreeEvent,
     DomTreeEventType, DomTreeEventData, ElementStats,
 };
 pub use styles_inspector::{
-    StylesInspector, ComputedStyles, StyleProperty, PropertyPriority,
+    StylesInspector, ComputedStyles, ConflictingRuleSet, ConflictReason, StyleProperty, PropertyPriority,
     SourceRule, RuleType, StyleSheet, CssRule, CssRuleType, CssProperty,
     CssValue, LengthUnit, StyleEditor, EditableStyle, StyleChange,
     StyleValidationRule, StyleValidationRuleType, AutoComplete,
@@ -33,7 +37,7 @@ pub use console_inspector::{
     ConsoleFilters, RuntimeEvaluator, EvaluationContext, EvaluationScope,
     EvaluationResult, ExpressionAutoComplete, SourceMapManager, SourceMap,
     SourceMapping, MappedLocation, StackTraceParser, ParsedStackTrace,
-    ParsedStackFrame, ConsoleState, FilterType, ConsoleStats,
+    ParsedStackFrame, ConsoleState, FilterType, ConsoleStats, ShortcutConflictConsole,
 };
 pub use network_inspector::{
     NetworkInspector, NetworkRequest, RequestMethod, RequestStatus,
@@ -41,11 +45,17 @@ pub use network_inspector::{
     NetworkResource, ResourceType, NetworkEvent, NetworkEventType,
     NetworkFilters, NetworkStats, NetworkInspectorState,
 };
+pub use har_exporter::HarExporter;
+pub use js_debugger::{JsDebugger, BreakpointId, PausedState};
+pub use coverage::{CssCoverageTracker, UnusedRule, CoverageReport, StyleSheetCoverage};
+pub use cdp::CdpServer;
 pub use performance_tools::{
     PerformanceProfiler, PerformanceMetrics, PerformanceEntry,
     PerformanceEntryType, PerformanceObserver, PerformanceTimeline,
     MemoryProfiler, MemorySnapshot, MemoryUsage, GarbageCollection,
-    PerformanceTools, PerformanceToolsState,
+    PerformanceTools, PerformanceToolsState, PerformanceStats, CpuProfile,
+    Sample, CallFrame, FlameGraph, FlameNode, HeapSnapshot, HeapNode,
+    HeapNodeType, HeapEdge, HeapEdgeType, SnapshotDiff,
 };
 
 /// DevTools manager that combines all inspector tools
@@ -60,22 +70,39 @@ pub struct DevToolsManager {
     network_inspector: Arc<RwLock<NetworkInspector>>,
     /// Performance tools
     performance_tools: Arc<RwLock<PerformanceTools>>,
+    /// JavaScript debugger
+    js_debugger: Arc<RwLock<JsDebugger>>,
+    /// CSS cascade of the inspected page
+    css_cascade: Option<Arc<RwLock<dom::CssCascade>>>,
+    /// CSS coverage being recorded
+    css_coverage: Option<Arc<CssCoverageTracker>>,
+    /// Accessibility tree of the inspected page
+    accessibility_tree: Option<Arc<RwLock<accessibility::AccessibilityTree>>>,
+    /// Remote debugging server
+    cdp_server: Option<CdpServer>,
     /// DevTools state
     state: DevToolsState,
 }
 
 use std::sync::Arc;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 
 impl DevToolsManager {
     /// Create new DevTools manager
     pub fn new() -> Self {
+        let styles_inspector = Arc::new(RwLock::new(StylesInspector::new()));
         Self {
-            elements_inspector: Arc::new(RwLock::new(ElementsInspector::new())),
-            styles_inspector: Arc::new(RwLock::new(StylesInspector::new())),
+            elements_inspector: Arc::new(RwLock::new(ElementsInspector::with_styles_inspector(styles_inspector.clone()))),
+            styles_inspector,
             console_inspector: Arc::new(RwLock::new(ConsoleInspector::new())),
             network_inspector: Arc::new(RwLock::new(NetworkInspector::new())),
             performance_tools: Arc::new(RwLock::new(PerformanceTools::new())),
+            js_debugger: Arc::new(RwLock::new(JsDebugger::new())),
+            css_cascade: None,
+            css_coverage: None,
+            accessibility_tree: None,
+            cdp_server: None,
             state: DevToolsState::Closed,
         }
     }
@@ -105,6 +132,79 @@ impl DevToolsManager {
         self.performance_tools.clone()
     }
 
+    /// Get JavaScript debugger
+    pub fn js_debugger(&self) -> Arc<RwLock<JsDebugger>> {
+        self.js_debugger.clone()
+    }
+
+    /// Attach the CSS cascade of the inspected page
+    pub fn attach_css_cascade(&mut self, cascade: Arc<RwLock<dom::CssCascade>>) {
+        self.css_cascade = Some(cascade);
+    }
+
+    /// Start recording which CSS rules the page uses
+    pub fn start_css_coverage(&mut self) -> Result<()> {
+        let cascade = self.css_cascade.as_ref()
+            .ok_or_else(|| Error::inspector("No CSS cascade attached".to_string()))?;
+        if self.css_coverage.is_some() {
+            return Err(Error::inspector("CSS coverage is already being recorded".to_string()));
+        }
+
+        let mut cascade = cascade.write();
+        let tracker = Arc::new(CssCoverageTracker::new(&cascade));
+        cascade.set_rule_observer(Some(tracker.clone()));
+        self.css_coverage = Some(tracker);
+        Ok(())
+    }
+
+    /// Stop recording CSS coverage and report the rules used
+    pub fn stop_css_coverage(&mut self) -> Result<CoverageReport> {
+        let tracker = self.css_coverage.take()
+            .ok_or_else(|| Error::inspector("CSS coverage is not being recorded".to_string()))?;
+        if let Some(cascade) = &self.css_cascade {
+            cascade.write().set_rule_observer(None);
+        }
+        Ok(tracker.report())
+    }
+
+    /// Attach the accessibility tree of the inspected page
+    pub fn attach_accessibility_tree(&mut self, tree: Arc<RwLock<accessibility::AccessibilityTree>>) {
+        self.accessibility_tree = Some(tree);
+    }
+
+    /// Reporter that writes keyboard shortcut conflicts to the console
+    pub fn shortcut_conflict_reporter(&self) -> Arc<dyn accessibility::ShortcutConflictReporter> {
+        Arc::new(ShortcutConflictConsole::new(self.console_inspector.clone()))
+    }
+
+    /// Audit the inspected page for WCAG violations
+    pub fn run_accessibility_audit(&self) -> Result<Vec<accessibility::AuditViolation>> {
+        let tree = self.accessibility_tree.as_ref()
+            .ok_or_else(|| Error::inspector("No accessibility tree attached".to_string()))?;
+        Ok(accessibility::AccessibilityAuditor::audit(&tree.read()))
+    }
+
+    /// Serve the Chrome DevTools Protocol on a localhost port, 0 for any free port
+    ///
+    /// Returns the address the server listens on.
+    pub async fn start_remote_debugging(&mut self, port: u16) -> Result<std::net::SocketAddr> {
+        if self.cdp_server.is_some() {
+            return Err(Error::inspector("Remote debugging is already running".to_string()));
+        }
+
+        let mut server = CdpServer::new(port, self);
+        let addr = server.start().await?;
+        self.cdp_server = Some(server);
+        Ok(addr)
+    }
+
+    /// Stop the remote debugging server
+    pub fn stop_remote_debugging(&mut self) {
+        if let Some(mut server) = self.cdp_server.take() {
+            server.stop();
+        }
+    }
+
     /// Open DevTools
     pub async fn open_devtools(&mut self) -> Result<()> {
         self.state = DevToolsState::Open;
@@ -120,6 +220,9 @@ impl DevToolsManager {
         self.state = DevToolsState::Closed;
         
         // Clean up resources
+        if self.css_coverage.is_some() {
+            self.stop_css_coverage()?;
+        }
         self.cleanup_inspectors().await?;
         
         Ok(())
@@ -171,7 +274,9 @@ impl DevToolsManager {
         {
             let elements_inspector = self.elements_inspector.read();
             // Load DOM tree from current page
-            elements_inspector.load_dom_tree("").await?;
+            if elements_inspector.is_connected() {
+                elements_inspector.load_dom_tree().await?;
+            }
         }
         
         // Initialize styles inspector
@@ -257,192 +362,6 @@ pub struct DevToolsStats {
     pub performance: PerformanceStats,
 }
 
-/// Performance statistics
-#[derive(Debug, Clone, Default)]
-pub struct PerformanceStats {
-    /// Total performance entries
-    pub total_entries: usize,
-    /// Memory usage
-    pub memory_usage: MemoryUsage,
-    /// Performance metrics
-    pub metrics: PerformanceMetrics,
-}
-
-/// Network statistics
-#[derive(Debug, Clone, Default)]
-pub struct NetworkStats {
-    /// Total requests
-    pub total_requests: usize,
-    /// Successful requests
-    pub successful_requests: usize,
-    /// Failed requests
-    pub failed_requests: usize,
-    /// Total bytes transferred
-    pub total_bytes: usize,
-    /// Average response time
-    pub average_response_time: f64,
-}
-
-/// Network inspector (placeholder)
-pub struct NetworkInspector {
-    // Implementation will be added in the next iteration
-}
-
-impl NetworkInspector {
-    pub fn new() -> Self {
-        Self {}
-    }
-    
-    pub async fn get_network_stats(&self) -> Result<NetworkStats> {
-        Ok(NetworkStats::default())
-    }
-    
-    pub async fn clear_requests(&self) -> Result<()> {
-        Ok(())
-    }
-}
-
-/// Performance tools (placeholder)
-pub struct PerformanceTools {
-    // Implementation will be added in the next iteration
-}
-
-impl PerformanceTools {
-    pub fn new() -> Self {
-        Self {}
-    }
-    
-    pub async fn get_performance_stats(&self) -> Result<PerformanceStats> {
-        Ok(PerformanceStats::default())
-    }
-    
-    pub async fn stop_profiling(&self) -> Result<()> {
-        Ok(())
-    }
-}
-
-/// Performance profiler (placeholder)
-pub struct PerformanceProfiler {
-    // Implementation will be added in the next iteration
-}
-
-/// Performance metrics (placeholder)
-#[derive(Debug, Clone, Default)]
-pub struct PerformanceMetrics {
-    // Implementation will be added in the next iteration
-}
-
-/// Performance entry (placeholder)
-pub struct PerformanceEntry {
-    // Implementation will be added in the next iteration
-}
-
-/// Performance entry type (placeholder)
-pub enum PerformanceEntryType {
-    // Implementation will be added in the next iteration
-}
-
-/// Performance observer (placeholder)
-pub struct PerformanceObserver {
-    // Implementation will be added in the next iteration
-}
-
-/// Performance timeline (placeholder)
-pub struct PerformanceTimeline {
-    // Implementation will be added in the next iteration
-}
-
-/// Memory profiler (placeholder)
-pub struct MemoryProfiler {
-    // Implementation will be added in the next iteration
-}
-
-/// Memory snapshot (placeholder)
-pub struct MemorySnapshot {
-    // Implementation will be added in the next iteration
-}
-
-/// Memory usage (placeholder)
-#[derive(Debug, Clone, Default)]
-pub struct MemoryUsage {
-    // Implementation will be added in the next iteration
-}
-
-/// Garbage collection (placeholder)
-pub struct GarbageCollection {
-    // Implementation will be added in the next iteration
-}
-
-/// Performance tools state (placeholder)
-pub enum PerformanceToolsState {
-    // Implementation will be added in the next iteration
-}
-
-/// Network request (placeholder)
-pub struct NetworkRequest {
-    // Implementation will be added in the next iteration
-}
-
-/// Request method (placeholder)
-pub enum RequestMethod {
-    // Implementation will be added in the next iteration
-}
-
-/// Request status (placeholder)
-pub enum RequestStatus {
-    // Implementation will be added in the next iteration
-}
-
-/// Request type (placeholder)
-pub enum RequestType {
-    // Implementation will be added in the next iteration
-}
-
-/// Request headers (placeholder)
-pub struct RequestHeaders {
-    // Implementation will be added in the next iteration
-}
-
-/// Response headers (placeholder)
-pub struct ResponseHeaders {
-    // Implementation will be added in the next iteration
-}
-
-/// Network timing (placeholder)
-pub struct NetworkTiming {
-    // Implementation will be added in the next iteration
-}
-
-/// Network resource (placeholder)
-pub struct NetworkResource {
-    // Implementation will be added in the next iteration
-}
-
-/// Resource type (placeholder)
-pub enum ResourceType {
-    // Implementation will be added in the next iteration
-}
-
-/// Network event (placeholder)
-pub struct NetworkEvent {
-    // Implementation will be added in the next iteration
-}
-
-/// Network event type (placeholder)
-pub enum NetworkEventType {
-    // Implementation will be added in the next iteration
-}
-
-/// Network filters (placeholder)
-pub struct NetworkFilters {
-    // Implementation will be added in the next iteration
-}
-
-/// Network inspector state (placeholder)
-pub enum NetworkInspectorState {
-    // Implementation will be added in the next iteration
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -510,6 +429,512 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_conflicting_rules_by_specificity() {
+        let stylesheet = dom::CssParser::new()
+            .parse_stylesheet("#intro { color: blue } p { color: red; margin: 0 }")
+            .unwrap();
+        let mut cascade = dom::CssCascade::new();
+        cascade.add_stylesheet(stylesheet);
+        let mut paragraph = dom::Element::new("p".to_string());
+        paragraph.set_attribute("id".to_string(), "intro".to_string());
+        let style = cascade.compute(&paragraph).unwrap();
+
+        let styles_inspector = StylesInspector::new();
+        styles_inspector.update_computed_styles("intro", &style).await.unwrap();
+
+        let conflicts = styles_inspector.get_conflicting_rules("intro").await.unwrap();
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!(conflict.property, "color");
+        assert_eq!(conflict.winning_rule.selector, "#intro");
+        assert_eq!(conflict.losing_rules.iter().map(|rule| rule.selector.as_str()).collect::<Vec<_>>(), ["p"]);
+        assert_eq!(conflict.reason, ConflictReason {
+            specificity_difference: Some((1, 0, -1)),
+            ..ConflictReason::default()
+        });
+
+        // The computed styles carry the winning value and the conflicts
+        let computed = styles_inspector.get_computed_styles("intro").await.unwrap();
+        assert_eq!(computed.styles["color"].value, "blue");
+        assert_eq!(computed.conflicting_rules.len(), 1);
+        assert!(styles_inspector.get_conflicting_rules("missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_select_element_from_renderer_tree() {
+        use common::ipc::{ChannelPair, DevToolsRequest, DevToolsResponse, IpcMessage};
+
+        let (inspector_end, renderer_end) = ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let renderer = std::thread::spawn(move || {
+            let request_id = match renderer_end.recv().unwrap() {
+                IpcMessage::DevToolsRequest(DevToolsRequest::GetDomTree { request_id, .. }) => request_id,
+                other => panic!("Unexpected request: {:?}", other),
+            };
+            let tree = serde_json::json!({
+                "type": "document",
+                "documentElement": {
+                    "type": "element", "nodeId": "n1", "tagName": "html", "id": null, "attributes": {},
+                    "children": [{
+                        "type": "element", "nodeId": "n2", "tagName": "p", "id": "intro",
+                        "attributes": { "id": "intro", "class": "lead" },
+                        "boundingBox": { "x": 8.0, "y": 100.0, "width": 400.0, "height": 20.0 },
+                        "children": [{ "type": "text", "textContent": "Hello" }]
+                    }]
+                }
+            });
+            renderer_end.send(IpcMessage::DevToolsResponse(DevToolsResponse::DomTree {
+                request_id,
+                tree: tree.to_string(),
+            })).unwrap();
+            renderer_end.recv().unwrap()
+        });
+
+        let devtools_manager = DevToolsManager::new();
+        let stylesheet = dom::CssParser::new().parse_stylesheet("#intro { color: blue }").unwrap();
+        let mut cascade = dom::CssCascade::new();
+        cascade.add_stylesheet(stylesheet);
+        let mut paragraph = dom::Element::new("p".to_string());
+        paragraph.set_attribute("id".to_string(), "intro".to_string());
+        let style = cascade.compute(&paragraph).unwrap();
+        devtools_manager.styles_inspector().read().update_computed_styles("intro", &style).await.unwrap();
+
+        let elements_inspector = devtools_manager.elements_inspector();
+        elements_inspector.read().connect_renderer(inspector_end, common::TabId(1));
+        elements_inspector.read().load_dom_tree().await.unwrap();
+
+        let info = elements_inspector.read().select_element("intro").await.unwrap();
+        assert_eq!(info.tag_name, "p");
+        assert_eq!(info.path, ["#document", "HTML", "P"]);
+        assert_eq!(info.attributes.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), ["class", "id"]);
+        assert_eq!(info.bounding_box.unwrap().y, 100.0);
+        assert_eq!(info.computed_styles["color"], "blue");
+        assert_eq!(info.child_count, 1);
+
+        // Selecting the element moves the renderer's overlay onto it
+        match renderer.join().unwrap() {
+            IpcMessage::DevToolsRequest(DevToolsRequest::HighlightElement { element_id, .. }) => {
+                assert_eq!(element_id.as_deref(), Some("intro"));
+            }
+            other => panic!("Unexpected request: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_js_debugger_commands() {
+        use common::ipc::{ChannelPair, DebuggerCallFrame, DebuggerEvent, DebuggerRequest, IpcMessage, PauseReason, ScopeVariable};
+
+        let (devtools_end, renderer_end) = ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let mut debugger = JsDebugger::new();
+        debugger.connect_renderer(devtools_end);
+
+        let breakpoint_id = debugger.set_breakpoint("https://example.com/app.js", 3, 0).await.unwrap();
+        assert_eq!(debugger.set_breakpoint("https://example.com/app.js", 3, 0).await.unwrap(), breakpoint_id);
+        assert!(matches!(
+            renderer_end.recv().unwrap(),
+            IpcMessage::DebuggerRequest(DebuggerRequest::SetBreakpoint { breakpoint_id: 1, line: 3, .. })
+        ));
+
+        // Stepping needs a paused script
+        assert!(debugger.step_over().await.is_err());
+
+        debugger.handle_event(DebuggerEvent::Paused {
+            call_frames: vec![DebuggerCallFrame {
+                function_name: "main".to_string(),
+                url: "https://example.com/app.js".to_string(),
+                line: 3,
+                column: 1,
+                scope: vec![ScopeVariable { name: "count".to_string(), value: "2".to_string() }],
+            }],
+            reason: PauseReason::Breakpoint { breakpoint_id: breakpoint_id.0 },
+        });
+        let paused = debugger.get_paused_state().unwrap();
+        assert_eq!(paused.scope_variables[0].name, "count");
+
+        debugger.step_over().await.unwrap();
+        assert!(!debugger.is_paused());
+        assert!(matches!(renderer_end.recv().unwrap(), IpcMessage::DebuggerRequest(DebuggerRequest::StepOver)));
+
+        debugger.remove_breakpoint(breakpoint_id).await.unwrap();
+        assert!(debugger.get_breakpoints().is_empty());
+        assert!(debugger.remove_breakpoint(breakpoint_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_har() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let inspector = NetworkInspector::new();
+        inspector.add_request(NetworkRequest {
+            id: "1".to_string(),
+            url: "https://example.com/index.html?lang=en".to_string(),
+            method: RequestMethod::Get,
+            status: RequestStatus::Completed,
+            request_type: RequestType::Document,
+            http_version: "HTTP/1.1".to_string(),
+            request_headers: RequestHeaders { headers: vec![("Accept".to_string(), "text/html".to_string())] },
+            request_body: None,
+            status_code: Some(200),
+            status_text: "OK".to_string(),
+            response_headers: ResponseHeaders { headers: vec![("Content-Type".to_string(), "text/html".to_string())] },
+            response_body: Some("<p>Hi</p>".to_string()),
+            mime_type: Some("text/html".to_string()),
+            response_size: 9,
+            started_at: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            timing: NetworkTiming {
+                blocked: Some(1.0),
+                dns: Some(5.0),
+                connect: Some(10.0),
+                ssl: Some(6.0),
+                send: 0.5,
+                wait: 20.0,
+                receive: 3.5,
+            },
+        }).await.unwrap();
+        inspector.add_request(NetworkRequest {
+            id: "2".to_string(),
+            url: "https://example.com/api".to_string(),
+            method: RequestMethod::Post,
+            status: RequestStatus::Failed("Connection reset".to_string()),
+            request_type: RequestType::Fetch,
+            http_version: "HTTP/1.1".to_string(),
+            request_headers: RequestHeaders { headers: vec![("Content-Type".to_string(), "application/json".to_string())] },
+            request_body: Some("{\"id\":1}".to_string()),
+            status_code: None,
+            status_text: String::new(),
+            response_headers: ResponseHeaders::default(),
+            response_body: None,
+            mime_type: None,
+            response_size: 0,
+            started_at: UNIX_EPOCH + Duration::from_millis(1_700_000_001_000),
+            timing: NetworkTiming { send: 1.0, ..NetworkTiming::default() },
+        }).await.unwrap();
+
+        let har: serde_json::Value = serde_json::from_str(&inspector.export_har().await.unwrap()).unwrap();
+        let log = &har["log"];
+        assert_eq!(log["version"], "1.2");
+        assert!(log["creator"]["name"].is_string());
+
+        let entries = log["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        for entry in entries {
+            for field in ["startedDateTime", "time", "request", "response", "cache", "timings"] {
+                assert!(!entry[field].is_null(), "missing {}", field);
+            }
+            for field in ["method", "url", "httpVersion", "cookies", "headers", "queryString", "headersSize", "bodySize"] {
+                assert!(!entry["request"][field].is_null(), "missing request.{}", field);
+            }
+            for field in ["status", "statusText", "httpVersion", "cookies", "headers", "content", "redirectURL", "headersSize", "bodySize"] {
+                assert!(!entry["response"][field].is_null(), "missing response.{}", field);
+            }
+            for field in ["send", "wait", "receive"] {
+                assert!(entry["timings"][field].is_number(), "missing timings.{}", field);
+            }
+        }
+
+        let page = &entries[0];
+        assert_eq!(page["startedDateTime"], "2023-11-14T22:13:20.123Z");
+        assert_eq!(page["time"], 40.0);
+        assert_eq!(page["request"]["method"], "GET");
+        assert_eq!(page["request"]["queryString"][0]["value"], "en");
+        assert_eq!(page["request"]["headers"][0]["name"], "Accept");
+        assert_eq!(page["response"]["status"], 200);
+        assert_eq!(page["response"]["content"]["mimeType"], "text/html");
+        assert_eq!(page["response"]["content"]["text"], "<p>Hi</p>");
+        assert_eq!(page["timings"]["dns"], 5.0);
+
+        // Phases that did not happen are -1 and failed requests have status 0
+        let api = &entries[1];
+        assert_eq!(api["request"]["method"], "POST");
+        assert_eq!(api["request"]["postData"]["mimeType"], "application/json");
+        assert_eq!(api["request"]["postData"]["text"], "{\"id\":1}");
+        assert_eq!(api["response"]["status"], 0);
+        assert_eq!(api["timings"]["blocked"], -1.0);
+        assert_eq!(api["timings"]["connect"], -1.0);
+
+        let stats = inspector.get_network_stats().await.unwrap();
+        assert_eq!((stats.total_requests, stats.successful_requests, stats.failed_requests), (2, 1, 1));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_profile_flame_graph() {
+        use renderer::js_vm::{JavaScriptVmManager, JsStackFrame};
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::time::{Duration, Instant};
+
+        let config = renderer::RendererConfig::default();
+        let js_vm = Arc::new(tokio::sync::RwLock::new(JavaScriptVmManager::new(&config).await.unwrap()));
+        let mut profiler = PerformanceProfiler::new();
+        assert!(profiler.start().is_err());
+        profiler.attach_js_vm(js_vm.clone());
+
+        let frame = |name: &str, line| JsStackFrame {
+            function_name: name.to_string(),
+            url: "https://example.com/app.js".to_string(),
+            line,
+            column: 1,
+        };
+        let done = Arc::new(AtomicBool::new(false));
+        let script = {
+            let js_vm = js_vm.clone();
+            let done = done.clone();
+            std::thread::spawn(move || {
+                let vm = js_vm.blocking_read();
+                vm.enter_function(frame("main", 1));
+                // Tight loop spending nearly all its time in `spin`
+                while !done.load(Ordering::Relaxed) {
+                    vm.enter_function(frame("spin", 10));
+                    let started = Instant::now();
+                    while started.elapsed() < Duration::from_millis(2) {
+                        std::hint::spin_loop();
+                    }
+                    vm.exit_function();
+                    vm.enter_function(frame("tick", 20));
+                    vm.exit_function();
+                }
+                vm.exit_function();
+            })
+        };
+
+        profiler.start().unwrap();
+        assert!(profiler.start().is_err());
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let profile = profiler.stop().unwrap();
+        done.store(true, Ordering::Relaxed);
+        script.join().unwrap();
+
+        assert!(profile.duration >= Duration::from_millis(300));
+        assert!(profile.samples.len() >= 10, "only {} samples", profile.samples.len());
+        assert!(profile.samples.windows(2).all(|pair| pair[0].timestamp <= pair[1].timestamp));
+
+        let flame_graph = profile.to_flame_graph();
+        assert_eq!(flame_graph.root.total_samples, profile.samples.len());
+        let hottest = flame_graph.hottest().unwrap();
+        assert_eq!(hottest.name, "spin");
+        let main = flame_graph.find("main").unwrap();
+        assert!(main.total_samples >= hottest.total_samples);
+        assert!(main.children.iter().any(|child| child.name == "spin"));
+        assert!(profiler.stop().is_err());
+    }
+
+    #[test]
+    fn test_flame_graph_aggregation() {
+        let frame = |name: &str| CallFrame {
+            function_name: name.to_string(),
+            url: "https://example.com/app.js".to_string(),
+            line: 1,
+            column: 1,
+        };
+        let sample = |names: &[&str]| Sample {
+            timestamp: 0,
+            stack: names.iter().map(|name| frame(name)).collect(),
+        };
+        let profile = CpuProfile {
+            duration: std::time::Duration::from_millis(40),
+            samples: vec![
+                sample(&["draw", "main"]),
+                sample(&["draw", "main"]),
+                sample(&["main"]),
+                sample(&["", "main"]),
+                sample(&[]),
+            ],
+        };
+
+        let flame_graph = profile.to_flame_graph();
+        assert_eq!((flame_graph.root.total_samples, flame_graph.root.self_samples), (5, 1));
+        let main = flame_graph.find("main").unwrap();
+        assert_eq!((main.total_samples, main.self_samples), (4, 1));
+        let names: Vec<_> = main.children.iter().map(|child| child.name.as_str()).collect();
+        assert_eq!(names, ["draw", "(anonymous)"]);
+        assert_eq!(flame_graph.hottest().unwrap().name, "draw");
+    }
+
+    #[test]
+    fn test_heap_snapshot_diff() {
+        use common::heap::{HeapInspector, HeapObject};
+
+        /// Heap holding a chain of objects, the first held by a root
+        #[derive(Default)]
+        struct ChainHeap(parking_lot::RwLock<Vec<HeapObject>>);
+
+        impl HeapInspector for ChainHeap {
+            fn live_objects(&self) -> Vec<HeapObject> {
+                self.0.read().clone()
+            }
+
+            fn root_object_ids(&self) -> Vec<u64> {
+                self.0.read().first().map(|object| object.id).into_iter().collect()
+            }
+        }
+
+        let heap = Arc::new(ChainHeap::default());
+        let mut tools = PerformanceTools::new();
+        assert!(tools.take_heap_snapshot().is_err());
+        tools.memory_profiler_mut().attach_heap(heap.clone());
+
+        let empty = tools.take_heap_snapshot().unwrap();
+        *heap.0.write() = (1..=100).map(|id| HeapObject {
+            id,
+            object_type: "Node".to_string(),
+            size: 32,
+            references: if id < 100 { vec![id + 1] } else { Vec::new() },
+        }).collect();
+        let full = tools.take_heap_snapshot().unwrap();
+
+        let diff = tools.diff_heap_snapshots(empty, full).unwrap();
+        assert_eq!(diff.added_nodes.len(), 100);
+        assert_eq!(diff.added_bytes, 3200);
+        assert!(diff.removed_nodes.is_empty());
+        let diff = tools.diff_heap_snapshots(full, empty).unwrap();
+        assert_eq!((diff.removed_nodes.len(), diff.removed_bytes), (100, 3200));
+        assert!(tools.diff_heap_snapshots(empty, 5).is_err());
+
+        // The synthetic root links to the GC root, which heads the chain
+        let snapshot = tools.get_heap_snapshot(full).unwrap();
+        assert_eq!(snapshot.nodes.len(), 101);
+        assert_eq!(snapshot.edges.len(), 100);
+        assert_eq!(snapshot.node_name(&snapshot.nodes[0]), "(GC roots)");
+        assert_eq!(snapshot.nodes[0].edge_count, 1);
+        assert_eq!(snapshot.node_name(&snapshot.nodes[1]), "Node");
+        assert_eq!(snapshot.nodes[1].node_type, HeapNodeType::Object);
+        assert_eq!(snapshot.edges[0].to_node, 1);
+
+        let v8: serde_json::Value = serde_json::from_str(&snapshot.to_v8_json().unwrap()).unwrap();
+        assert_eq!(v8["snapshot"]["node_count"], 101);
+        assert_eq!(v8["nodes"].as_array().unwrap().len(), 101 * 5);
+        assert_eq!(v8["edges"].as_array().unwrap().len(), 100 * 3);
+        assert_eq!(v8["edges"][2], 5);
+    }
+
+    #[test]
+    fn test_css_coverage() {
+        let css = [
+            "p { color: red }",
+            ".lead { font-size: 20px }",
+            "#intro { margin: 0 }",
+            "h1 { color: blue }",
+            "h2 { color: green }",
+            ".card { padding: 4px }",
+            "ul li { margin: 2px }",
+            "a:hover { color: purple }",
+            "footer { display: none }",
+            "table td { border: 1px solid }",
+        ].join("\n");
+        let mut stylesheet = dom::CssParser::new().parse_stylesheet(&css).unwrap();
+        stylesheet.set_href(Some("https://example.com/site.css".to_string()));
+        let mut cascade = dom::CssCascade::new();
+        cascade.add_stylesheet(stylesheet);
+        let cascade = Arc::new(RwLock::new(cascade));
+
+        let mut devtools_manager = DevToolsManager::new();
+        assert!(devtools_manager.start_css_coverage().is_err());
+        devtools_manager.attach_css_cascade(cascade.clone());
+        devtools_manager.start_css_coverage().unwrap();
+
+        // One paragraph matches the first three rules
+        let mut paragraph = dom::Element::new("p".to_string());
+        paragraph.set_attribute("id".to_string(), "intro".to_string());
+        paragraph.set_attribute("class".to_string(), "lead".to_string());
+        cascade.read().compute(&paragraph).unwrap();
+
+        let report = devtools_manager.stop_css_coverage().unwrap();
+        assert!(devtools_manager.stop_css_coverage().is_err());
+        assert_eq!(report.unused_rules.len(), 7);
+        let unused: Vec<_> = report.unused_rules.iter().map(|rule| rule.selector.as_str()).collect();
+        assert!(!unused.contains(&"p") && !unused.contains(&".lead") && !unused.contains(&"#intro"));
+        let footer = report.unused_rules.iter().find(|rule| rule.selector == "footer").unwrap();
+        assert_eq!(footer.line, Some(9));
+        assert_eq!(footer.unused_bytes, "footer { display: none }".len());
+        assert_eq!(footer.stylesheet_url.as_deref(), Some("https://example.com/site.css"));
+
+        let coverage = &report.stylesheets[0];
+        assert_eq!(coverage.used_bytes, css.lines().take(3).map(str::len).sum::<usize>());
+        assert_eq!(coverage.unused_bytes, report.unused_rules.iter().map(|rule| rule.unused_bytes).sum::<usize>());
+        let expected = coverage.used_bytes as f64 * 100.0 / (coverage.used_bytes + coverage.unused_bytes) as f64;
+        assert!((coverage.coverage_percentage - expected).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_run_accessibility_audit() {
+        use accessibility::{AccessibilityNode, AccessibilityRole, AccessibilityTree, WcagRule};
+
+        let mut devtools_manager = DevToolsManager::new();
+        assert!(devtools_manager.run_accessibility_audit().is_err());
+
+        let tree = AccessibilityTree::new();
+        let mut main = AccessibilityNode::new("main".to_string(), AccessibilityRole::Main);
+        main.children = vec!["icon-button".to_string()];
+        main.properties.insert("color".to_string(), "#777".to_string());
+        main.properties.insert("background-color".to_string(), "#fff".to_string());
+        for node in [main, AccessibilityNode::new("icon-button".to_string(), AccessibilityRole::Button)] {
+            tree.add_node(node).await.unwrap();
+        }
+        devtools_manager.attach_accessibility_tree(Arc::new(RwLock::new(tree)));
+
+        let mut violations = devtools_manager.run_accessibility_audit().unwrap();
+        violations.sort_by(|a, b| a.element_id.cmp(&b.element_id));
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].element_id, "icon-button");
+        assert_eq!(violations[0].rule_id, WcagRule::NameRoleValue);
+        assert_eq!(violations[1].element_id, "main");
+        assert_eq!(violations[1].rule_id, WcagRule::ContrastMinimum);
+    }
+
+    #[tokio::test]
+    async fn test_cdp_runtime_evaluate() {
+        use common::ipc::{ChannelPair, DebuggerEvent, DebuggerRequest, IpcMessage};
+        use futures_util::{SinkExt, StreamExt};
+        use serde_json::json;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let (devtools_end, renderer_end) = ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let renderer = std::thread::spawn(move || match renderer_end.recv().unwrap() {
+            IpcMessage::DebuggerRequest(DebuggerRequest::Evaluate { request_id, expression }) => {
+                assert_eq!(expression, "6 * 7");
+                renderer_end.send(IpcMessage::DebuggerEvent(DebuggerEvent::Evaluated {
+                    request_id,
+                    value: Some("42".to_string()),
+                    error: None,
+                })).unwrap();
+            }
+            other => panic!("Unexpected request: {:?}", other),
+        });
+
+        let mut devtools_manager = DevToolsManager::new();
+        devtools_manager.js_debugger().write().connect_renderer(devtools_end);
+        let addr = devtools_manager.start_remote_debugging(0).await.unwrap();
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "Runtime.evaluate",
+            "params": { "expression": "6 * 7" },
+        });
+        client.send(Message::Text(request.to_string())).await.unwrap();
+        let reply: serde_json::Value = match client.next().await.unwrap().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("Unexpected message: {:?}", other),
+        };
+        assert_eq!(reply, json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": { "result": { "type": "number", "value": 42, "description": "42" } },
+        }));
+        renderer.join().unwrap();
+
+        // Unknown methods are JSON-RPC errors
+        client.send(Message::Text(json!({ "id": 2, "method": "Tracing.start" }).to_string())).await.unwrap();
+        let reply: serde_json::Value = match client.next().await.unwrap().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("Unexpected message: {:?}", other),
+        };
+        assert_eq!(reply["id"], 2);
+        assert_eq!(reply["error"]["code"], -32601);
+
+        devtools_manager.stop_remote_debugging();
+    }
+
     #[tokio::test]
     async fn test_devtools_stats() {
         let devtools_manager = DevToolsManager::new();
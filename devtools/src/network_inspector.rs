@@ -1,50 +1,325 @@
 //! Network Inspector module for DevTools
-//! 
-//! This module will provide network request monitoring, request/response inspection,
-//! waterfall display, HAR export, and timing analysis.
+//!
+//! This module provides network request monitoring, request/response inspection
+//! and HAR export. Recorded requests are also published as `NetworkEvent`s to
+//! subscribers such as the remote debugging server.
 
 use crate::error::{Error, Result};
+use crate::har_exporter::HarExporter;
+use std::sync::Arc;
+use std::time::SystemTime;
+use parking_lot::RwLock;
+use serde::{Serialize, Deserialize};
+use tokio::sync::broadcast;
 
-/// Network Inspector (placeholder implementation)
-/// 
-/// This will be fully implemented in the next iteration with:
-/// - Network request monitoring
-/// - Request/response inspection
-/// - Waterfall display
-/// - HAR export
-/// - Timing analysis
+/// Number of events buffered for a subscriber that falls behind
+const EVENT_CAPACITY: usize = 256;
+
+/// Network Inspector
 pub struct NetworkInspector {
-    // Implementation will be added in the next iteration
+    /// Recorded requests, in start order
+    requests: Arc<RwLock<Vec<NetworkRequest>>>,
+    /// Events for subscribers
+    events: broadcast::Sender<NetworkEvent>,
+    /// Inspector state
+    state: NetworkInspectorState,
+}
+
+/// Network request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkRequest {
+    /// Request ID
+    pub id: String,
+    /// Request URL
+    pub url: String,
+    /// Request method
+    pub method: RequestMethod,
+    /// Request status
+    pub status: RequestStatus,
+    /// Request type
+    pub request_type: RequestType,
+    /// HTTP version
+    pub http_version: String,
+    /// Request headers
+    pub request_headers: RequestHeaders,
+    /// Request body
+    pub request_body: Option<String>,
+    /// Response status code
+    pub status_code: Option<u16>,
+    /// Response status text
+    pub status_text: String,
+    /// Response headers
+    pub response_headers: ResponseHeaders,
+    /// Response body
+    pub response_body: Option<String>,
+    /// Response MIME type
+    pub mime_type: Option<String>,
+    /// Decoded response body size in bytes
+    pub response_size: usize,
+    /// Start time
+    pub started_at: SystemTime,
+    /// Timing
+    pub timing: NetworkTiming,
+}
+
+/// Request method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RequestMethod {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Connect,
+    Options,
+    Trace,
+    Patch,
+}
+
+/// Request status
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RequestStatus {
+    /// Waiting for the response
+    Pending,
+    /// Response received
+    Completed,
+    /// Request failed
+    Failed(String),
+    /// Request cancelled
+    Cancelled,
+}
+
+/// Request type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RequestType {
+    Document,
+    Stylesheet,
+    Script,
+    Image,
+    Font,
+    Xhr,
+    Fetch,
+    WebSocket,
+    Other,
+}
+
+/// Request headers, in the order sent
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestHeaders {
+    /// Header names and values
+    pub headers: Vec<(String, String)>,
+}
+
+/// Response headers, in the order received
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponseHeaders {
+    /// Header names and values
+    pub headers: Vec<(String, String)>,
+}
+
+/// Network timing in milliseconds
+///
+/// Phases that did not happen for a request, such as DNS resolution for a
+/// reused connection, are `None`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkTiming {
+    /// Time queued before the request was sent
+    pub blocked: Option<f64>,
+    /// DNS resolution
+    pub dns: Option<f64>,
+    /// Connection setup, including TLS
+    pub connect: Option<f64>,
+    /// TLS handshake
+    pub ssl: Option<f64>,
+    /// Sending the request
+    pub send: f64,
+    /// Waiting for the first response byte
+    pub wait: f64,
+    /// Receiving the response
+    pub receive: f64,
+}
+
+/// Network resource (placeholder)
+pub struct NetworkResource;
+
+/// Resource type (placeholder)
+pub enum ResourceType {}
+
+/// Change to a recorded request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkEvent {
+    /// Event type
+    pub event_type: NetworkEventType,
+    /// Request after the change
+    pub request: NetworkRequest,
+}
+
+/// Network event type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetworkEventType {
+    /// Request is about to be sent
+    RequestWillBeSent,
+    /// Response headers were received
+    ResponseReceived,
+    /// Request failed or was cancelled
+    LoadingFailed,
+}
+
+/// Network filters (placeholder)
+pub struct NetworkFilters;
+
+/// Network inspector state
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NetworkInspectorState {
+    /// Inspector is recording requests
+    Recording,
+    /// Inspector is paused
+    Paused,
+}
+
+/// Network statistics
+#[derive(Debug, Clone, Default)]
+pub struct NetworkStats {
+    /// Total requests
+    pub total_requests: usize,
+    /// Successful requests
+    pub successful_requests: usize,
+    /// Failed requests
+    pub failed_requests: usize,
+    /// Total bytes transferred
+    pub total_bytes: usize,
+    /// Average response time
+    pub average_response_time: f64,
 }
 
 impl NetworkInspector {
     /// Create new network inspector
     pub fn new() -> Self {
-        Self {}
+        Self {
+            requests: Arc::new(RwLock::new(Vec::new())),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            state: NetworkInspectorState::Recording,
+        }
+    }
+
+    /// Record a request
+    pub async fn add_request(&self, request: NetworkRequest) -> Result<()> {
+        if self.state == NetworkInspectorState::Recording {
+            self.publish(NetworkEventType::RequestWillBeSent, &request);
+            self.requests.write().push(request);
+        }
+        Ok(())
+    }
+
+    /// Update a recorded request
+    ///
+    /// Subscribers are notified when the update completes or fails the request.
+    pub async fn update_request(&self, request: NetworkRequest) -> Result<()> {
+        let mut requests = self.requests.write();
+        let recorded = requests.iter_mut()
+            .find(|recorded| recorded.id == request.id)
+            .ok_or_else(|| Error::NetworkRequestNotFound(request.id.clone()))?;
+
+        if recorded.status != request.status {
+            match request.status {
+                RequestStatus::Completed => self.publish(NetworkEventType::ResponseReceived, &request),
+                RequestStatus::Failed(_) | RequestStatus::Cancelled => {
+                    self.publish(NetworkEventType::LoadingFailed, &request)
+                }
+                RequestStatus::Pending => {}
+            }
+        }
+        *recorded = request;
+        Ok(())
+    }
+
+    /// Subscribe to changes of recorded requests
+    pub fn subscribe(&self) -> broadcast::Receiver<NetworkEvent> {
+        self.events.subscribe()
     }
-    
+
+    /// Get recorded requests
+    pub async fn get_requests(&self) -> Result<Vec<NetworkRequest>> {
+        Ok(self.requests.read().clone())
+    }
+
     /// Get network statistics
-    pub async fn get_network_stats(&self) -> Result<super::NetworkStats> {
-        Ok(super::NetworkStats::default())
+    pub async fn get_network_stats(&self) -> Result<NetworkStats> {
+        let requests = self.requests.read();
+        let mut stats = NetworkStats {
+            total_requests: requests.len(),
+            ..NetworkStats::default()
+        };
+
+        let mut total_time = 0.0;
+        for request in requests.iter() {
+            match request.status {
+                RequestStatus::Completed => stats.successful_requests += 1,
+                RequestStatus::Failed(_) => stats.failed_requests += 1,
+                RequestStatus::Pending | RequestStatus::Cancelled => {}
+            }
+            stats.total_bytes += request.response_size;
+            total_time += request.timing.total();
+        }
+        if !requests.is_empty() {
+            stats.average_response_time = total_time / requests.len() as f64;
+        }
+
+        Ok(stats)
+    }
+
+    /// Export recorded requests as an HTTP Archive
+    pub async fn export_har(&self) -> Result<String> {
+        HarExporter::export(&self.requests.read())
     }
-    
+
     /// Clear network requests
     pub async fn clear_requests(&self) -> Result<()> {
+        self.requests.write().clear();
         Ok(())
     }
+
+    /// Notify subscribers of a change to a request
+    fn publish(&self, event_type: NetworkEventType, request: &NetworkRequest) {
+        if self.events.receiver_count() > 0 {
+            let _ = self.events.send(NetworkEvent { event_type, request: request.clone() });
+        }
+    }
+
+    /// Get inspector state
+    pub fn get_state(&self) -> NetworkInspectorState {
+        self.state
+    }
+
+    /// Set inspector state
+    pub fn set_state(&mut self, state: NetworkInspectorState) {
+        self.state = state;
+    }
 }
 
-// Placeholder types that will be implemented in the next iteration
-pub struct NetworkRequest;
-pub enum RequestMethod {}
-pub enum RequestStatus {}
-pub enum RequestType {}
-pub struct RequestHeaders;
-pub struct ResponseHeaders;
-pub struct NetworkTiming;
-pub struct NetworkResource;
-pub enum ResourceType {}
-pub struct NetworkEvent;
-pub enum NetworkEventType {}
-pub struct NetworkFilters;
-pub enum NetworkInspectorState {}
+impl RequestMethod {
+    /// Method name as sent on the wire
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestMethod::Get => "GET",
+            RequestMethod::Head => "HEAD",
+            RequestMethod::Post => "POST",
+            RequestMethod::Put => "PUT",
+            RequestMethod::Delete => "DELETE",
+            RequestMethod::Connect => "CONNECT",
+            RequestMethod::Options => "OPTIONS",
+            RequestMethod::Trace => "TRACE",
+            RequestMethod::Patch => "PATCH",
+        }
+    }
+}
+
+impl NetworkTiming {
+    /// Total time of the request
+    ///
+    /// The TLS handshake is part of the connection time and is not counted twice.
+    pub fn total(&self) -> f64 {
+        [self.blocked, self.dns, self.connect].iter().flatten().sum::<f64>()
+            + self.send + self.wait + self.receive
+    }
+}
@@ -1,48 +1,142 @@
 //! Performance Tools module for DevTools
 //! 
-//! This module will provide performance profiling, flamegraphs, FPS meter,
-//! memory snapshots, and performance timeline.
+//! This module provides CPU profiling with flame graphs and heap snapshots,
+//! and will provide an FPS meter and performance timeline.
+
+pub mod memory_profiler;
+pub mod profiler;
 
 use crate::error::{Error, Result};
+use parking_lot::RwLock;
+pub use memory_profiler::{HeapEdge, HeapEdgeType, HeapNode, HeapNodeType, HeapSnapshot, MemoryProfiler, SnapshotDiff};
+pub use profiler::{CallFrame, CpuProfile, FlameGraph, FlameNode, PerformanceProfiler, Sample};
 
-/// Performance Tools (placeholder implementation)
-/// 
-/// This will be fully implemented in the next iteration with:
-/// - Performance profiling
-/// - Flamegraphs
-/// - FPS meter
-/// - Memory snapshots
-/// - Performance timeline
+/// Performance Tools
 pub struct PerformanceTools {
-    // Implementation will be added in the next iteration
+    /// CPU profiler
+    profiler: PerformanceProfiler,
+    /// Memory profiler
+    memory_profiler: MemoryProfiler,
+    /// Heap snapshots taken, oldest first
+    heap_snapshots: RwLock<Vec<HeapSnapshot>>,
+}
+
+/// Performance statistics
+#[derive(Debug, Clone, Default)]
+pub struct PerformanceStats {
+    /// Total performance entries
+    pub total_entries: usize,
+    /// Memory usage
+    pub memory_usage: MemoryUsage,
+    /// Performance metrics
+    pub metrics: PerformanceMetrics,
 }
 
 impl PerformanceTools {
     /// Create new performance tools
     pub fn new() -> Self {
-        Self {}
+        Self {
+            profiler: PerformanceProfiler::new(),
+            memory_profiler: MemoryProfiler::new(),
+            heap_snapshots: RwLock::new(Vec::new()),
+        }
+    }
+    
+    /// Get CPU profiler
+    pub fn profiler(&self) -> &PerformanceProfiler {
+        &self.profiler
+    }
+    
+    /// Get CPU profiler for attaching a VM
+    pub fn profiler_mut(&mut self) -> &mut PerformanceProfiler {
+        &mut self.profiler
+    }
+    
+    /// Get memory profiler for attaching a heap
+    pub fn memory_profiler_mut(&mut self) -> &mut MemoryProfiler {
+        &mut self.memory_profiler
+    }
+    
+    /// Take a heap snapshot, returning its index
+    pub fn take_heap_snapshot(&self) -> Result<usize> {
+        let snapshot = self.memory_profiler.take_snapshot()?;
+        let mut heap_snapshots = self.heap_snapshots.write();
+        heap_snapshots.push(snapshot);
+        Ok(heap_snapshots.len() - 1)
+    }
+    
+    /// Get a heap snapshot by index
+    pub fn get_heap_snapshot(&self, index: usize) -> Option<HeapSnapshot> {
+        self.heap_snapshots.read().get(index).cloned()
+    }
+    
+    /// Compare two heap snapshots by index
+    pub fn diff_heap_snapshots(&self, before: usize, after: usize) -> Result<SnapshotDiff> {
+        let heap_snapshots = self.heap_snapshots.read();
+        let snapshot = |index: usize| {
+            heap_snapshots.get(index)
+                .ok_or_else(|| Error::Performance(format!("Heap snapshot {} not found", index)))
+        };
+        Ok(MemoryProfiler::diff_snapshots(snapshot(before)?, snapshot(after)?))
     }
     
     /// Get performance statistics
-    pub async fn get_performance_stats(&self) -> Result<super::PerformanceStats> {
-        Ok(super::PerformanceStats::default())
+    pub async fn get_performance_stats(&self) -> Result<PerformanceStats> {
+        Ok(PerformanceStats::default())
     }
     
-    /// Stop profiling
+    /// Stop profiling, discarding the recorded profile
     pub async fn stop_profiling(&self) -> Result<()> {
+        if self.profiler.is_profiling() {
+            self.profiler.stop()?;
+        }
         Ok(())
     }
 }
 
-// Placeholder types that will be implemented in the next iteration
-pub struct PerformanceProfiler;
-pub struct PerformanceMetrics;
-pub struct PerformanceEntry;
-pub enum PerformanceEntryType {}
-pub struct PerformanceObserver;
-pub struct PerformanceTimeline;
-pub struct MemoryProfiler;
-pub struct MemorySnapshot;
-pub struct MemoryUsage;
-pub struct GarbageCollection;
-pub enum PerformanceToolsState {}
+/// Performance metrics (placeholder)
+#[derive(Debug, Clone, Default)]
+pub struct PerformanceMetrics {
+    // Implementation will be added in the next iteration
+}
+
+/// Performance entry (placeholder)
+pub struct PerformanceEntry {
+    // Implementation will be added in the next iteration
+}
+
+/// Performance entry type (placeholder)
+pub enum PerformanceEntryType {
+    // Implementation will be added in the next iteration
+}
+
+/// Performance observer (placeholder)
+pub struct PerformanceObserver {
+    // Implementation will be added in the next iteration
+}
+
+/// Performance timeline (placeholder)
+pub struct PerformanceTimeline {
+    // Implementation will be added in the next iteration
+}
+
+/// Memory snapshot (placeholder)
+pub struct MemorySnapshot {
+    // Implementation will be added in the next iteration
+}
+
+/// Memory usage (placeholder)
+#[derive(Debug, Clone, Default)]
+pub struct MemoryUsage {
+    // Implementation will be added in the next iteration
+}
+
+/// Garbage collection (placeholder)
+pub struct GarbageCollection {
+    // Implementation will be added in the next iteration
+}
+
+/// Performance tools state (placeholder)
+pub enum PerformanceToolsState {
+    // Implementation will be added in the next iteration
+}
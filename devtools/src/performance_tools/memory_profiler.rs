@@ -0,0 +1,276 @@
+//! Heap snapshots of the JavaScript heap
+//!
+//! Snapshots mirror the node and edge layout of V8's `.heapsnapshot` format,
+//! so they can be exported for tools that read it.
+
+use crate::error::{Error, Result};
+use common::heap::{HeapInspector, HeapObject};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// ID of the synthetic root node, which links to the GC roots
+const ROOT_NODE_ID: u64 = 0;
+
+/// Name of the synthetic root node
+const ROOT_NODE_NAME: &str = "(GC roots)";
+
+/// Node types in the order of V8's `node_types` metadata
+const NODE_TYPES: [&str; 14] = [
+    "hidden", "array", "string", "object", "code", "closure", "regexp",
+    "number", "native", "synthetic", "concatenated string", "sliced string",
+    "symbol", "bigint",
+];
+
+/// Edge types in the order of V8's `edge_types` metadata
+const EDGE_TYPES: [&str; 7] = ["context", "element", "property", "internal", "hidden", "shortcut", "weak"];
+
+/// Memory profiler
+pub struct MemoryProfiler {
+    /// Heap to snapshot
+    heap: Option<Arc<dyn HeapInspector>>,
+}
+
+/// Snapshot of the live objects on the heap
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HeapSnapshot {
+    /// Nodes, the synthetic root first
+    pub nodes: Vec<HeapNode>,
+    /// Edges, grouped by source node in node order
+    pub edges: Vec<HeapEdge>,
+    /// String table referenced by node names
+    pub strings: Vec<String>,
+}
+
+/// Object on the heap
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeapNode {
+    /// Node type
+    pub node_type: HeapNodeType,
+    /// Index of the name in the string table
+    pub name: usize,
+    /// Object ID, stable across snapshots
+    pub id: u64,
+    /// Size of the object itself in bytes
+    pub self_size: usize,
+    /// Number of outgoing edges
+    pub edge_count: usize,
+}
+
+/// Reference from one object to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeapEdge {
+    /// Edge type
+    pub edge_type: HeapEdgeType,
+    /// Element index of the reference
+    pub name_or_index: usize,
+    /// Index of the referenced node
+    pub to_node: usize,
+}
+
+/// Heap node type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HeapNodeType {
+    Hidden,
+    Array,
+    String,
+    Object,
+    Code,
+    Closure,
+    Regexp,
+    Number,
+    Native,
+    Synthetic,
+    ConcatenatedString,
+    SlicedString,
+    Symbol,
+    BigInt,
+}
+
+/// Heap edge type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HeapEdgeType {
+    Context,
+    Element,
+    Property,
+    Internal,
+    Hidden,
+    Shortcut,
+    Weak,
+}
+
+/// Difference between two heap snapshots
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    /// IDs of objects only in the later snapshot
+    pub added_nodes: Vec<u64>,
+    /// IDs of objects only in the earlier snapshot
+    pub removed_nodes: Vec<u64>,
+    /// Size of the added objects in bytes
+    pub added_bytes: usize,
+    /// Size of the removed objects in bytes
+    pub removed_bytes: usize,
+}
+
+impl MemoryProfiler {
+    /// Create new memory profiler
+    pub fn new() -> Self {
+        Self { heap: None }
+    }
+
+    /// Attach the heap to snapshot
+    pub fn attach_heap(&mut self, heap: Arc<dyn HeapInspector>) {
+        self.heap = Some(heap);
+    }
+
+    /// Take a snapshot of the live objects
+    pub fn take_snapshot(&self) -> Result<HeapSnapshot> {
+        let heap = self.heap.as_ref()
+            .ok_or_else(|| Error::Performance("No heap attached".to_string()))?;
+
+        Ok(HeapSnapshot::build(&heap.live_objects(), &heap.root_object_ids()))
+    }
+
+    /// Compare two snapshots by object ID
+    pub fn diff_snapshots(before: &HeapSnapshot, after: &HeapSnapshot) -> SnapshotDiff {
+        let before_ids: HashSet<u64> = before.nodes.iter().map(|node| node.id).collect();
+        let after_ids: HashSet<u64> = after.nodes.iter().map(|node| node.id).collect();
+
+        let mut diff = SnapshotDiff::default();
+        for node in after.nodes.iter().filter(|node| !before_ids.contains(&node.id)) {
+            diff.added_nodes.push(node.id);
+            diff.added_bytes += node.self_size;
+        }
+        for node in before.nodes.iter().filter(|node| !after_ids.contains(&node.id)) {
+            diff.removed_nodes.push(node.id);
+            diff.removed_bytes += node.self_size;
+        }
+
+        diff
+    }
+}
+
+impl HeapSnapshot {
+    /// Build a snapshot from live objects and the IDs held by GC roots
+    ///
+    /// References to objects outside the snapshot are dropped.
+    fn build(objects: &[HeapObject], roots: &[u64]) -> Self {
+        let mut snapshot = HeapSnapshot::default();
+        let mut string_indices = HashMap::new();
+
+        // Node 0 is the synthetic root, followed by the objects in order
+        let node_index: HashMap<u64, usize> = objects.iter()
+            .enumerate()
+            .map(|(index, object)| (object.id, index + 1))
+            .collect();
+
+        let root_edges = Self::element_edges(roots, &node_index);
+        let name = snapshot.intern(&mut string_indices, ROOT_NODE_NAME);
+        snapshot.nodes.push(HeapNode {
+            node_type: HeapNodeType::Synthetic,
+            name,
+            id: ROOT_NODE_ID,
+            self_size: 0,
+            edge_count: root_edges.len(),
+        });
+        snapshot.edges.extend(root_edges);
+
+        for object in objects {
+            let edges = Self::element_edges(&object.references, &node_index);
+            let name = snapshot.intern(&mut string_indices, &object.object_type);
+            snapshot.nodes.push(HeapNode {
+                node_type: HeapNodeType::for_object_type(&object.object_type),
+                name,
+                id: object.id,
+                self_size: object.size,
+                edge_count: edges.len(),
+            });
+            snapshot.edges.extend(edges);
+        }
+
+        snapshot
+    }
+
+    /// Edges from a list of referenced IDs, indexed by position
+    fn element_edges(references: &[u64], node_index: &HashMap<u64, usize>) -> Vec<HeapEdge> {
+        references.iter()
+            .enumerate()
+            .filter_map(|(index, id)| {
+                node_index.get(id).map(|&to_node| HeapEdge {
+                    edge_type: HeapEdgeType::Element,
+                    name_or_index: index,
+                    to_node,
+                })
+            })
+            .collect()
+    }
+
+    /// Add a string to the string table, returning its index
+    fn intern(&mut self, indices: &mut HashMap<String, usize>, string: &str) -> usize {
+        *indices.entry(string.to_string()).or_insert_with(|| {
+            self.strings.push(string.to_string());
+            self.strings.len() - 1
+        })
+    }
+
+    /// Get the name of a node
+    pub fn node_name(&self, node: &HeapNode) -> &str {
+        self.strings.get(node.name).map_or("", String::as_str)
+    }
+
+    /// Total size of the objects in bytes
+    pub fn total_size(&self) -> usize {
+        self.nodes.iter().map(|node| node.self_size).sum()
+    }
+
+    /// Serialize in V8's `.heapsnapshot` JSON format
+    pub fn to_v8_json(&self) -> Result<String> {
+        const NODE_FIELD_COUNT: usize = 5;
+
+        let nodes: Vec<u64> = self.nodes.iter()
+            .flat_map(|node| {
+                [node.node_type as u64, node.name as u64, node.id, node.self_size as u64, node.edge_count as u64]
+            })
+            .collect();
+        let edges: Vec<u64> = self.edges.iter()
+            .flat_map(|edge| {
+                [edge.edge_type as u64, edge.name_or_index as u64, (edge.to_node * NODE_FIELD_COUNT) as u64]
+            })
+            .collect();
+
+        let snapshot = serde_json::json!({
+            "snapshot": {
+                "meta": {
+                    "node_fields": ["type", "name", "id", "self_size", "edge_count"],
+                    "node_types": [NODE_TYPES, "string", "number", "number", "number"],
+                    "edge_fields": ["type", "name_or_index", "to_node"],
+                    "edge_types": [EDGE_TYPES, "string_or_number", "node"],
+                },
+                "node_count": self.nodes.len(),
+                "edge_count": self.edges.len(),
+            },
+            "nodes": nodes,
+            "edges": edges,
+            "strings": self.strings,
+        });
+
+        serde_json::to_string(&snapshot)
+            .map_err(|e| Error::Performance(format!("Failed to serialize heap snapshot: {}", e)))
+    }
+}
+
+impl HeapNodeType {
+    /// Node type of a heap object
+    fn for_object_type(object_type: &str) -> Self {
+        match object_type {
+            "array" | "Array" => HeapNodeType::Array,
+            "string" | "String" => HeapNodeType::String,
+            "function" | "closure" | "Function" => HeapNodeType::Closure,
+            "regexp" | "RegExp" => HeapNodeType::Regexp,
+            "number" | "Number" => HeapNodeType::Number,
+            "symbol" | "Symbol" => HeapNodeType::Symbol,
+            "bigint" | "BigInt" => HeapNodeType::BigInt,
+            _ => HeapNodeType::Object,
+        }
+    }
+}
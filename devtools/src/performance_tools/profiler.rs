@@ -0,0 +1,248 @@
+//! Sampling CPU profiler
+//!
+//! Samples the call stack of the JavaScript VM at a fixed rate and
+//! aggregates the samples into a flame graph.
+
+use crate::error::{Error, Result};
+use parking_lot::Mutex;
+use renderer::js_vm::{JavaScriptVmManager, JsStackFrame};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock as AsyncRwLock;
+use tokio::task::JoinHandle;
+
+/// Interval between samples, giving 100 Hz
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Name of the flame graph root
+const ROOT_NAME: &str = "(root)";
+
+/// Name shown for anonymous functions
+const ANONYMOUS_NAME: &str = "(anonymous)";
+
+/// Sampling CPU profiler
+pub struct PerformanceProfiler {
+    /// VM whose call stack is sampled
+    js_vm: Option<Arc<AsyncRwLock<JavaScriptVmManager>>>,
+    /// Profile being recorded
+    session: Mutex<Option<ProfilingSession>>,
+}
+
+/// Profile being recorded
+struct ProfilingSession {
+    started_at: Instant,
+    samples: Arc<Mutex<Vec<Sample>>>,
+    task: JoinHandle<()>,
+}
+
+/// Recorded CPU profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CpuProfile {
+    /// Time between start and stop
+    pub duration: Duration,
+    /// Samples in recording order
+    pub samples: Vec<Sample>,
+}
+
+/// Call stack sampled at one point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sample {
+    /// Microseconds since the profile started
+    pub timestamp: u64,
+    /// Call frames, innermost first, empty when no script was running
+    pub stack: Vec<CallFrame>,
+}
+
+/// Function on a sampled call stack
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallFrame {
+    /// Function name, empty for anonymous functions
+    pub function_name: String,
+    /// Script URL
+    pub url: String,
+    /// Line number
+    pub line: u32,
+    /// Column number
+    pub column: u32,
+}
+
+/// Samples aggregated by call path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlameGraph {
+    /// Root node, whose self samples are the samples without a running script
+    pub root: FlameNode,
+}
+
+/// Function in a flame graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlameNode {
+    /// Function name
+    pub name: String,
+    /// Samples with this function on the call path
+    pub total_samples: usize,
+    /// Samples with this function innermost
+    pub self_samples: usize,
+    /// Functions called from this one
+    pub children: Vec<FlameNode>,
+}
+
+impl PerformanceProfiler {
+    /// Create new performance profiler
+    pub fn new() -> Self {
+        Self {
+            js_vm: None,
+            session: Mutex::new(None),
+        }
+    }
+
+    /// Attach the JavaScript VM to profile
+    pub fn attach_js_vm(&mut self, js_vm: Arc<AsyncRwLock<JavaScriptVmManager>>) {
+        self.js_vm = Some(js_vm);
+    }
+
+    /// Check whether a profile is being recorded
+    pub fn is_profiling(&self) -> bool {
+        self.session.lock().is_some()
+    }
+
+    /// Start sampling the call stack
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn start(&self) -> Result<()> {
+        let js_vm = self.js_vm.clone()
+            .ok_or_else(|| Error::Performance("No JavaScript VM attached".to_string()))?;
+        let mut session = self.session.lock();
+        if session.is_some() {
+            return Err(Error::Performance("Profiler is already running".to_string()));
+        }
+
+        let started_at = Instant::now();
+        let samples = Arc::new(Mutex::new(Vec::new()));
+        let task = tokio::spawn(Self::sample(js_vm, started_at, samples.clone()));
+        *session = Some(ProfilingSession { started_at, samples, task });
+
+        Ok(())
+    }
+
+    /// Stop sampling and return the recorded profile
+    pub fn stop(&self) -> Result<CpuProfile> {
+        let session = self.session.lock().take()
+            .ok_or_else(|| Error::Performance("Profiler is not running".to_string()))?;
+        session.task.abort();
+
+        let duration = session.started_at.elapsed();
+        let samples = std::mem::take(&mut *session.samples.lock());
+        Ok(CpuProfile { duration, samples })
+    }
+
+    /// Sampling loop
+    async fn sample(
+        js_vm: Arc<AsyncRwLock<JavaScriptVmManager>>,
+        started_at: Instant,
+        samples: Arc<Mutex<Vec<Sample>>>,
+    ) {
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            let stack = js_vm.read().await.capture_stack_trace();
+            samples.lock().push(Sample {
+                timestamp: started_at.elapsed().as_micros() as u64,
+                stack: stack.into_iter().map(CallFrame::from).collect(),
+            });
+        }
+    }
+}
+
+impl From<JsStackFrame> for CallFrame {
+    fn from(frame: JsStackFrame) -> Self {
+        Self {
+            function_name: frame.function_name,
+            url: frame.url,
+            line: frame.line,
+            column: frame.column,
+        }
+    }
+}
+
+impl CpuProfile {
+    /// Aggregate the samples into a flame graph
+    ///
+    /// Calls of the same function from the same call path share a node.
+    pub fn to_flame_graph(&self) -> FlameGraph {
+        let mut root = FlameNode::new(ROOT_NAME.to_string());
+        for sample in &self.samples {
+            let mut node = &mut root;
+            node.total_samples += 1;
+            for frame in sample.stack.iter().rev() {
+                node = node.child(frame.display_name());
+                node.total_samples += 1;
+            }
+            node.self_samples += 1;
+        }
+
+        FlameGraph { root }
+    }
+}
+
+impl CallFrame {
+    /// Name of the function as shown in a flame graph
+    fn display_name(&self) -> &str {
+        if self.function_name.is_empty() {
+            ANONYMOUS_NAME
+        } else {
+            &self.function_name
+        }
+    }
+}
+
+impl FlameGraph {
+    /// Find the first node for a function, searching depth first
+    pub fn find(&self, name: &str) -> Option<&FlameNode> {
+        self.root.find(name)
+    }
+
+    /// Get the node with the most self samples
+    pub fn hottest(&self) -> Option<&FlameNode> {
+        self.root.children.iter()
+            .map(FlameNode::hottest)
+            .max_by_key(|node| node.self_samples)
+    }
+}
+
+impl FlameNode {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            total_samples: 0,
+            self_samples: 0,
+            children: Vec::new(),
+        }
+    }
+
+    /// Get or create the child for a function
+    fn child(&mut self, name: &str) -> &mut FlameNode {
+        let index = match self.children.iter().position(|child| child.name == name) {
+            Some(index) => index,
+            None => {
+                self.children.push(FlameNode::new(name.to_string()));
+                self.children.len() - 1
+            }
+        };
+        &mut self.children[index]
+    }
+
+    fn find(&self, name: &str) -> Option<&FlameNode> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(name))
+    }
+
+    fn hottest(&self) -> &FlameNode {
+        self.children.iter()
+            .map(FlameNode::hottest)
+            .fold(self, |hottest, node| if node.self_samples > hottest.self_samples { node } else { hottest })
+    }
+}
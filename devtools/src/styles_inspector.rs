@@ -36,6 +36,36 @@ pub struct ComputedStyles {
     pub author_styles: HashMap<String, StyleProperty>,
     /// Pseudo-element styles
     pub pseudo_styles: HashMap<String, HashMap<String, StyleProperty>>,
+    /// Properties set by more than one rule, by property name
+    pub conflicting_rules: Vec<ConflictingRuleSet>,
+}
+
+/// Rules setting the same property on an element
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictingRuleSet {
+    /// Property name
+    pub property: String,
+    /// Rule whose declaration is used
+    pub winning_rule: SourceRule,
+    /// Overridden rules, strongest first
+    pub losing_rules: Vec<SourceRule>,
+    /// Why the winning rule beat the strongest losing rule
+    pub reason: ConflictReason,
+}
+
+/// Cascade step that decided a conflict
+///
+/// Only the first step in which the two rules differ is set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConflictReason {
+    /// Winning minus losing specificity as (ids, classes, types), when specificity decided
+    pub specificity_difference: Option<(i32, i32, i32)>,
+    /// The rules were in different cascade layers
+    pub layer_order: bool,
+    /// The winning rule came later in the source
+    pub source_order: bool,
+    /// The winning declaration was `!important` and the losing one was not
+    pub importance: bool,
 }
 
 /// Style property
@@ -490,6 +520,55 @@ impl StylesInspector {
         }
     }
 
+    /// Store the styles the cascade computed for an element
+    pub async fn update_computed_styles(&self, element_id: &str, style: &dom::ComputedStyle) -> Result<()> {
+        let mut styles = HashMap::new();
+        let mut conflicting_rules = Vec::new();
+        for (property, chain) in &style.resolution_chains {
+            let Some(winner) = chain.first() else {
+                continue;
+            };
+            let value = style.get(property).map_or(&winner.declaration.value, |computed| computed.value());
+            let is_important = winner.declaration.important;
+            styles.insert(property.clone(), StyleProperty {
+                name: property.clone(),
+                value: css_value_text(value),
+                priority: if is_important { PropertyPriority::AuthorImportant } else { PropertyPriority::Author },
+                source_rule: Some(source_rule(winner)),
+                is_inherited: false,
+                is_important,
+            });
+
+            if let Some(loser) = chain.get(1) {
+                conflicting_rules.push(ConflictingRuleSet {
+                    property: property.clone(),
+                    winning_rule: source_rule(winner),
+                    losing_rules: chain[1..].iter().map(source_rule).collect(),
+                    reason: conflict_reason(winner, loser),
+                });
+            }
+        }
+        conflicting_rules.sort_by(|a, b| a.property.cmp(&b.property));
+
+        let computed = ComputedStyles {
+            element_id: element_id.to_string(),
+            author_styles: styles.clone(),
+            styles,
+            inherited_styles: HashMap::new(),
+            user_agent_styles: HashMap::new(),
+            pseudo_styles: HashMap::new(),
+            conflicting_rules,
+        };
+        self.computed_styles.write().insert(element_id.to_string(), computed);
+
+        Ok(())
+    }
+
+    /// Get the properties of an element set by more than one rule
+    pub async fn get_conflicting_rules(&self, element_id: &str) -> Result<Vec<ConflictingRuleSet>> {
+        Ok(self.get_computed_styles(element_id).await?.conflicting_rules)
+    }
+
     /// Get all computed styles
     pub async fn get_all_computed_styles(&self) -> Result<HashMap<String, ComputedStyles>> {
         let computed_styles = self.computed_styles.read();
@@ -662,6 +741,55 @@ impl StylesInspector {
     }
 }
 
+/// Source rule of a cascaded declaration
+fn source_rule(entry: &dom::CascadeEntry) -> SourceRule {
+    SourceRule {
+        rule_type: RuleType::CssRule,
+        selector: entry.selector.clone(),
+        style_sheet_url: None,
+        line_number: None,
+        column_number: None,
+    }
+}
+
+/// Why one cascaded declaration beat another
+fn conflict_reason(winner: &dom::CascadeEntry, loser: &dom::CascadeEntry) -> ConflictReason {
+    let mut reason = ConflictReason::default();
+    match loser.lost_by {
+        Some(dom::CascadeCriterion::Importance) => reason.importance = true,
+        Some(dom::CascadeCriterion::LayerOrder) => reason.layer_order = true,
+        Some(dom::CascadeCriterion::Specificity) => {
+            let (a, b) = (&winner.specificity, &loser.specificity);
+            reason.specificity_difference = Some((
+                a.id_count as i32 - b.id_count as i32,
+                a.class_count as i32 - b.class_count as i32,
+                a.type_count as i32 - b.type_count as i32,
+            ));
+        }
+        Some(dom::CascadeCriterion::SourceOrder) | None => reason.source_order = true,
+    }
+    reason
+}
+
+/// Text form of a cascaded value for display
+fn css_value_text(value: &dom::CssValue) -> String {
+    match value {
+        dom::CssValue::Keyword(text) | dom::CssValue::String(text) | dom::CssValue::Color(text) => text.clone(),
+        dom::CssValue::Number(number) => number.to_string(),
+        dom::CssValue::Length(length, unit) => format!("{}{}", length, unit),
+        dom::CssValue::Percentage(percentage) => format!("{}%", percentage),
+        dom::CssValue::Url(url) => format!("url({})", url),
+        dom::CssValue::Function(name, args) => {
+            format!("{}({})", name, args.iter().map(css_value_text).collect::<Vec<_>>().join(", "))
+        }
+        dom::CssValue::List(values) => values.iter().map(css_value_text).collect::<Vec<_>>().join(" "),
+        dom::CssValue::Initial => "initial".to_string(),
+        dom::CssValue::Inherit => "inherit".to_string(),
+        dom::CssValue::Unset => "unset".to_string(),
+        dom::CssValue::Revert => "revert".to_string(),
+    }
+}
+
 impl StyleEditor {
     /// Create new style editor
     pub fn new() -> Self {
@@ -830,10 +958,12 @@ impl AutoComplete {
         ];
         
         Self {
-            css_properties,
-            css_values,
-            color_names,
-            units,
+            css_properties: css_properties.into_iter().map(String::from).collect(),
+            css_values: css_values.into_iter()
+                .map(|(property, values)| (property, values.into_iter().map(String::from).collect()))
+                .collect(),
+            color_names: color_names.into_iter().map(String::from).collect(),
+            units: units.into_iter().map(String::from).collect(),
         }
     }
 
@@ -0,0 +1,799 @@
+//! CSS animations and transitions.
+//!
+//! This module provides the animation engine that interpolates property
+//! values over time for `@keyframes` animations and implicit transitions,
+//! and drives `requestAnimationFrame` callbacks from the same frame tick.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use crate::error::{Error, Result};
+use crate::css_at_rules::KeyframeRule;
+use crate::css_property_parser::CssPropertyParser;
+use crate::cssom::{ComputedStyle, ComputedValue, CssValue};
+
+/// Callback invoked with the frame timestamp in milliseconds
+pub type AnimationFrameCallback = Box<dyn FnMut(f64) + Send + Sync>;
+
+/// Easing function applied to animation progress
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimingFunction {
+    /// Constant speed
+    Linear,
+    /// `cubic-bezier(0.25, 0.1, 0.25, 1)`
+    Ease,
+    /// `cubic-bezier(0.42, 0, 1, 1)`
+    EaseIn,
+    /// `cubic-bezier(0, 0, 0.58, 1)`
+    EaseOut,
+    /// `cubic-bezier(0.42, 0, 0.58, 1)`
+    EaseInOut,
+    /// Custom cubic Bézier curve
+    CubicBezier(f32, f32, f32, f32),
+    /// Step function jumping at the end of each step
+    Steps(u32),
+}
+
+impl TimingFunction {
+    /// Parse a timing function such as `ease-in` or `cubic-bezier(0, 0, 1, 1)`
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        match value.as_str() {
+            "linear" => return Some(TimingFunction::Linear),
+            "ease" => return Some(TimingFunction::Ease),
+            "ease-in" => return Some(TimingFunction::EaseIn),
+            "ease-out" => return Some(TimingFunction::EaseOut),
+            "ease-in-out" => return Some(TimingFunction::EaseInOut),
+            "step-start" => return Some(TimingFunction::CubicBezier(0.0, 1.0, 0.0, 1.0)),
+            "step-end" => return Some(TimingFunction::Steps(1)),
+            _ => {}
+        }
+
+        let (name, args) = value.strip_suffix(')')?.split_once('(')?;
+        let args: Vec<&str> = args.split(',').map(str::trim).collect();
+        match (name.trim(), args.as_slice()) {
+            ("cubic-bezier", [x1, y1, x2, y2]) => Some(TimingFunction::CubicBezier(
+                x1.parse().ok()?, y1.parse().ok()?, x2.parse().ok()?, y2.parse().ok()?,
+            )),
+            ("steps", [steps, ..]) => steps.parse().ok().filter(|steps| *steps > 0).map(TimingFunction::Steps),
+            _ => None,
+        }
+    }
+
+    /// Map linear progress in `[0, 1]` to eased progress
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            TimingFunction::Linear => t,
+            TimingFunction::Ease => cubic_bezier(0.25, 0.1, 0.25, 1.0, t),
+            TimingFunction::EaseIn => cubic_bezier(0.42, 0.0, 1.0, 1.0, t),
+            TimingFunction::EaseOut => cubic_bezier(0.0, 0.0, 0.58, 1.0, t),
+            TimingFunction::EaseInOut => cubic_bezier(0.42, 0.0, 0.58, 1.0, t),
+            TimingFunction::CubicBezier(x1, y1, x2, y2) => cubic_bezier(*x1, *y1, *x2, *y2, t),
+            TimingFunction::Steps(steps) => (t * *steps as f32).floor() / *steps as f32,
+        }
+    }
+}
+
+/// Evaluate a cubic Bézier easing curve at `x`
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+    let sample = |a: f32, b: f32, t: f32| {
+        let u = 1.0 - t;
+        3.0 * u * u * t * a + 3.0 * u * t * t * b + t * t * t
+    };
+
+    // Bisect for the curve parameter whose x matches
+    let (mut low, mut high) = (0.0_f32, 1.0_f32);
+    let mut t = x;
+    for _ in 0..32 {
+        let sampled = sample(x1, x2, t);
+        if (sampled - x).abs() < 1e-5 {
+            break;
+        }
+        if sampled < x {
+            low = t;
+        } else {
+            high = t;
+        }
+        t = (low + high) / 2.0;
+    }
+
+    sample(y1, y2, t)
+}
+
+/// How an animation applies its values outside its active interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillMode {
+    /// No effect outside the active interval
+    None,
+    /// Keep the final value after the animation ends
+    Forwards,
+    /// Apply the first value during the delay
+    Backwards,
+    /// Both forwards and backwards
+    Both,
+}
+
+impl FillMode {
+    fn fills_forwards(&self) -> bool {
+        matches!(self, FillMode::Forwards | FillMode::Both)
+    }
+
+    fn fills_backwards(&self) -> bool {
+        matches!(self, FillMode::Backwards | FillMode::Both)
+    }
+}
+
+/// A keyframe of a property animation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe {
+    /// Position in the animation, from 0 to 1
+    pub offset: f32,
+    /// Property value at this keyframe
+    pub value: CssValue,
+}
+
+impl Keyframe {
+    /// Create a new keyframe
+    pub fn new(offset: f32, value: CssValue) -> Self {
+        Self { offset, value }
+    }
+}
+
+/// A running animation of one property on one element
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveAnimation {
+    /// Animated element
+    pub element_id: String,
+    /// Animated property
+    pub property: String,
+    /// Keyframes sorted by offset
+    pub keyframes: Vec<Keyframe>,
+    /// Duration of one iteration
+    pub duration: Duration,
+    /// Easing function
+    pub timing_fn: TimingFunction,
+    /// Fill mode
+    pub fill_mode: FillMode,
+    /// Time since the animation was started
+    pub current_time: Duration,
+    /// Delay before the animation starts
+    pub delay: Duration,
+    /// Number of iterations (may be infinite)
+    pub iteration_count: f32,
+    /// `@keyframes` name, or `None` for transitions
+    pub name: Option<String>,
+    /// Whether iterations run from the last keyframe to the first
+    pub reversed: bool,
+    /// Whether the animation is held at its current time
+    pub paused: bool,
+}
+
+impl ActiveAnimation {
+    /// Create an animation that runs once without delay
+    pub fn new(
+        element_id: &str,
+        property: &str,
+        keyframes: Vec<Keyframe>,
+        duration: Duration,
+        timing_fn: TimingFunction,
+        fill_mode: FillMode,
+    ) -> Self {
+        let mut keyframes = keyframes;
+        keyframes.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+
+        Self {
+            element_id: element_id.to_string(),
+            property: property.to_string(),
+            keyframes,
+            duration,
+            timing_fn,
+            fill_mode,
+            current_time: Duration::ZERO,
+            delay: Duration::ZERO,
+            iteration_count: 1.0,
+            name: None,
+            reversed: false,
+            paused: false,
+        }
+    }
+
+    /// Check whether this animation is an implicit transition
+    pub fn is_transition(&self) -> bool {
+        self.name.is_none()
+    }
+
+    /// Check whether the active interval has ended
+    pub fn is_finished(&self) -> bool {
+        if self.iteration_count.is_infinite() {
+            return false;
+        }
+        self.current_time >= self.delay + self.duration.mul_f32(self.iteration_count.max(0.0))
+    }
+
+    /// Eased progress within the current iteration, or `None` if the animation has no effect
+    pub fn progress(&self) -> Option<f32> {
+        if self.current_time < self.delay {
+            return self.fill_mode.fills_backwards().then(|| self.timing_fn.apply(self.directed(0.0)));
+        }
+
+        if self.is_finished() {
+            if !self.fill_mode.fills_forwards() {
+                return None;
+            }
+            let end = self.iteration_count.fract();
+            return Some(self.timing_fn.apply(self.directed(if end == 0.0 { 1.0 } else { end })));
+        }
+
+        let elapsed = (self.current_time - self.delay).as_secs_f32() / self.duration.as_secs_f32();
+        Some(self.timing_fn.apply(self.directed(elapsed.fract())))
+    }
+
+    /// Apply the playback direction to iteration progress
+    fn directed(&self, progress: f32) -> f32 {
+        if self.reversed { 1.0 - progress } else { progress }
+    }
+
+    /// Current animated value, or `None` if the animation has no effect
+    pub fn value(&self) -> Option<CssValue> {
+        let progress = self.progress()?;
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+
+        if progress <= first.offset {
+            return Some(first.value.clone());
+        }
+        if progress >= last.offset {
+            return Some(last.value.clone());
+        }
+
+        let (from, to) = self.keyframes.windows(2)
+            .map(|pair| (&pair[0], &pair[1]))
+            .find(|(from, to)| from.offset <= progress && progress <= to.offset)?;
+        let span = to.offset - from.offset;
+        let local = if span > 0.0 { (progress - from.offset) / span } else { 1.0 };
+
+        Some(interpolate(&from.value, &to.value, local))
+    }
+}
+
+/// A `transition` entry for one property
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionSpec {
+    /// Transitioned property, or `all`
+    pub property: String,
+    /// Transition duration
+    pub duration: Duration,
+    /// Easing function
+    pub timing_fn: TimingFunction,
+    /// Delay before the transition starts
+    pub delay: Duration,
+}
+
+impl TransitionSpec {
+    /// Parse the `transition` shorthand, e.g. `opacity 0.3s ease-in, transform 1s`
+    pub fn parse_shorthand(value: &str) -> Result<Vec<Self>> {
+        split_top_level_commas(value).into_iter()
+            .filter(|entry| !entry.trim().is_empty())
+            .map(Self::parse_single)
+            .collect()
+    }
+
+    fn parse_single(value: &str) -> Result<Self> {
+        let mut spec = TransitionSpec {
+            property: "all".to_string(),
+            duration: Duration::ZERO,
+            timing_fn: TimingFunction::Ease,
+            delay: Duration::ZERO,
+        };
+        let mut times = 0;
+        let mut property = None;
+
+        for token in split_top_level_whitespace(value) {
+            if let Some(time) = parse_time(token) {
+                match times {
+                    0 => spec.duration = time,
+                    1 => spec.delay = time,
+                    _ => return Err(Error::ParseError(format!("Too many times in transition: {}", value))),
+                }
+                times += 1;
+            } else if let Some(timing_fn) = TimingFunction::parse(token) {
+                spec.timing_fn = timing_fn;
+            } else if property.is_none() {
+                property = Some(token.to_ascii_lowercase());
+            } else {
+                return Err(Error::ParseError(format!("Invalid transition: {}", value)));
+            }
+        }
+
+        if let Some(property) = property {
+            spec.property = property;
+        }
+        Ok(spec)
+    }
+
+    /// Check whether this transition applies to a property
+    pub fn applies_to(&self, property: &str) -> bool {
+        self.property == "all" || self.property == property
+    }
+}
+
+/// Animation engine driving keyframe animations, transitions and animation frame callbacks
+pub struct AnimationEngine {
+    /// Keyframes by `@keyframes` name, then by property
+    keyframes: HashMap<String, HashMap<String, Vec<Keyframe>>>,
+    /// Running animations, in start order
+    animations: Vec<ActiveAnimation>,
+    /// Transitions declared for each element
+    transitions: HashMap<String, Vec<TransitionSpec>>,
+    /// Last cascaded value of each element property, for detecting changes
+    last_values: HashMap<(String, String), CssValue>,
+    /// Pending `requestAnimationFrame` callbacks
+    frame_callbacks: Vec<(u64, AnimationFrameCallback)>,
+    /// Next animation frame callback ID
+    next_callback_id: u64,
+    /// Document timeline
+    current_time: Duration,
+}
+
+impl AnimationEngine {
+    /// Create a new animation engine
+    pub fn new() -> Self {
+        Self {
+            keyframes: HashMap::new(),
+            animations: Vec::new(),
+            transitions: HashMap::new(),
+            last_values: HashMap::new(),
+            frame_callbacks: Vec::new(),
+            next_callback_id: 1,
+            current_time: Duration::ZERO,
+        }
+    }
+
+    /// Register the keyframes of a `@keyframes` rule
+    pub fn register_keyframes(&mut self, name: &str, rules: &[KeyframeRule]) {
+        let mut parser = CssPropertyParser::new();
+        let mut by_property: HashMap<String, Vec<Keyframe>> = HashMap::new();
+
+        for rule in rules {
+            let offsets: Vec<f32> = rule.selectors.iter().filter_map(|selector| parse_keyframe_offset(selector)).collect();
+            for (property, value) in &rule.declarations {
+                let value = match parser.parse_property_value(value) {
+                    Ok(parsed) => parser.to_css_value(&parsed),
+                    Err(_) => CssValue::Keyword(value.trim().to_string()),
+                };
+                for offset in &offsets {
+                    by_property.entry(property.clone()).or_default().push(Keyframe::new(*offset, value.clone()));
+                }
+            }
+        }
+
+        self.keyframes.insert(name.to_string(), by_property);
+    }
+
+    /// Check whether a `@keyframes` rule is registered
+    pub fn has_keyframes(&self, name: &str) -> bool {
+        self.keyframes.contains_key(name)
+    }
+
+    /// Start a `@keyframes` animation on an element
+    ///
+    /// Returns the number of properties animated.
+    pub fn start_animation(
+        &mut self,
+        element_id: &str,
+        name: &str,
+        duration: Duration,
+        timing_fn: TimingFunction,
+        fill_mode: FillMode,
+    ) -> Result<usize> {
+        let by_property = self.keyframes.get(name)
+            .ok_or_else(|| Error::NotFound(format!("No @keyframes rule named {}", name)))?;
+
+        // Restarting an animation replaces the running one
+        self.animations.retain(|animation| {
+            !(animation.element_id == element_id && animation.name.as_deref() == Some(name))
+        });
+
+        let mut properties: Vec<&String> = by_property.keys().collect();
+        properties.sort();
+        for property in &properties {
+            let mut animation = ActiveAnimation::new(
+                element_id, property, by_property[*property].clone(), duration, timing_fn.clone(), fill_mode,
+            );
+            animation.name = Some(name.to_string());
+            self.animations.push(animation);
+        }
+
+        Ok(properties.len())
+    }
+
+    /// Add an animation directly
+    pub fn add_animation(&mut self, animation: ActiveAnimation) {
+        self.animations.push(animation);
+    }
+
+    /// Cancel all animations on an element
+    pub fn cancel_animations(&mut self, element_id: &str) {
+        self.animations.retain(|animation| animation.element_id != element_id);
+    }
+
+    /// Cancel every property animation with a name
+    pub fn cancel_named_animations(&mut self, name: &str) {
+        self.animations.retain(|animation| animation.name.as_deref() != Some(name));
+    }
+
+    /// Get the property animations with a name, for playback control
+    pub fn named_animations_mut<'a>(&'a mut self, name: &'a str) -> impl Iterator<Item = &'a mut ActiveAnimation> + 'a {
+        self.animations.iter_mut().filter(move |animation| animation.name.as_deref() == Some(name))
+    }
+
+    /// Set the `transition` shorthand of an element
+    pub fn set_transition(&mut self, element_id: &str, shorthand: &str) -> Result<()> {
+        let specs = TransitionSpec::parse_shorthand(shorthand)?;
+        self.transitions.insert(element_id.to_string(), specs);
+        Ok(())
+    }
+
+    /// Start transitions for changed properties and override the style with animated values
+    pub fn update_style(&mut self, element_id: &str, style: &mut ComputedStyle) {
+        if let Some(transition) = style.get("transition") {
+            // Invalid declarations are ignored
+            if let Ok(specs) = TransitionSpec::parse_shorthand(&css_value_text(transition.value())) {
+                self.transitions.insert(element_id.to_string(), specs);
+            }
+        }
+
+        let mut properties: Vec<(&String, &ComputedValue)> = style.properties.iter()
+            .filter(|(property, _)| !property.starts_with("transition"))
+            .collect();
+        properties.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (property, computed) in properties {
+            let key = (element_id.to_string(), property.clone());
+            let Some(previous) = self.last_values.insert(key, computed.value().clone()) else {
+                continue;
+            };
+            if &previous == computed.value() {
+                continue;
+            }
+            self.start_transition(element_id, property, previous, computed.value().clone());
+        }
+
+        for animation in self.animations.iter().filter(|animation| animation.element_id == element_id) {
+            if let Some(value) = animation.value() {
+                style.properties.insert(animation.property.clone(), ComputedValue::new(value, false, true));
+            }
+        }
+    }
+
+    /// Start a transition if one is declared for the property
+    fn start_transition(&mut self, element_id: &str, property: &str, from: CssValue, to: CssValue) {
+        let Some(spec) = self.transitions.get(element_id)
+            .and_then(|specs| specs.iter().rev().find(|spec| spec.applies_to(property)))
+            .cloned() else {
+            return;
+        };
+        if spec.duration.is_zero() {
+            return;
+        }
+
+        // An interrupted transition continues from its current value
+        let running = self.animations.iter()
+            .position(|animation| animation.is_transition() && animation.element_id == element_id && animation.property == property);
+        let from = match running {
+            Some(index) => self.animations.remove(index).value().unwrap_or(from),
+            None => from,
+        };
+
+        let mut animation = ActiveAnimation::new(
+            element_id,
+            property,
+            vec![Keyframe::new(0.0, from), Keyframe::new(1.0, to)],
+            spec.duration,
+            spec.timing_fn,
+            FillMode::Backwards,
+        );
+        animation.delay = spec.delay;
+        self.animations.push(animation);
+    }
+
+    /// Current animated value of an element property
+    pub fn animated_value(&self, element_id: &str, property: &str) -> Option<ComputedValue> {
+        self.animations.iter().rev()
+            .filter(|animation| animation.element_id == element_id && animation.property == property)
+            .find_map(|animation| animation.value())
+            .map(|value| ComputedValue::new(value, false, true))
+    }
+
+    /// Get the running animations
+    pub fn active_animations(&self) -> &[ActiveAnimation] {
+        &self.animations
+    }
+
+    /// Check whether another frame is needed
+    pub fn needs_frame(&self) -> bool {
+        !self.frame_callbacks.is_empty() || self.animations.iter().any(|animation| !animation.paused && !animation.is_finished())
+    }
+
+    /// Schedule a callback for the next frame
+    pub fn request_animation_frame(&mut self, callback: AnimationFrameCallback) -> u64 {
+        let callback_id = self.next_callback_id;
+        self.next_callback_id += 1;
+        self.frame_callbacks.push((callback_id, callback));
+        callback_id
+    }
+
+    /// Cancel a scheduled animation frame callback
+    pub fn cancel_animation_frame(&mut self, callback_id: u64) {
+        self.frame_callbacks.retain(|(id, _)| *id != callback_id);
+    }
+
+    /// Get the document timeline
+    pub fn current_time(&self) -> Duration {
+        self.current_time
+    }
+
+    /// Advance all animations by one frame and run animation frame callbacks
+    pub fn tick(&mut self, delta: Duration) {
+        self.current_time += delta;
+        for animation in self.animations.iter_mut().filter(|animation| !animation.paused) {
+            animation.current_time += delta;
+        }
+
+        // Callbacks requested while running belong to the next frame
+        let timestamp = self.current_time.as_secs_f64() * 1000.0;
+        for (_, mut callback) in std::mem::take(&mut self.frame_callbacks) {
+            callback(timestamp);
+        }
+
+        self.animations.retain(|animation| !animation.is_finished() || animation.fill_mode.fills_forwards());
+    }
+}
+
+impl Default for AnimationEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Interpolate between two values; values that cannot be interpolated flip halfway
+pub fn interpolate(from: &CssValue, to: &CssValue, t: f32) -> CssValue {
+    let lerp = |a: f64, b: f64| a + (b - a) * t as f64;
+
+    match (from, to) {
+        (CssValue::Number(a), CssValue::Number(b)) => CssValue::Number(lerp(*a, *b)),
+        (CssValue::Percentage(a), CssValue::Percentage(b)) => CssValue::Percentage(lerp(*a, *b)),
+        (CssValue::Length(a, unit_a), CssValue::Length(b, unit_b)) if unit_a == unit_b => {
+            CssValue::Length(lerp(*a, *b), unit_a.clone())
+        }
+        (CssValue::List(a), CssValue::List(b)) if a.len() == b.len() => {
+            CssValue::List(a.iter().zip(b).map(|(a, b)| interpolate(a, b, t)).collect())
+        }
+        _ => {
+            let colors = color_text(from).and_then(parse_color).zip(color_text(to).and_then(parse_color));
+            match colors {
+                Some((a, b)) => {
+                    let channel = |i: usize| a[i] + (b[i] - a[i]) * t;
+                    let (r, g, b, alpha) = (channel(0).round(), channel(1).round(), channel(2).round(), channel(3));
+                    if alpha >= 1.0 {
+                        CssValue::Color(format!("rgb({}, {}, {})", r, g, b))
+                    } else {
+                        CssValue::Color(format!("rgba({}, {}, {}, {})", r, g, b, alpha))
+                    }
+                }
+                None if t < 0.5 => from.clone(),
+                None => to.clone(),
+            }
+        }
+    }
+}
+
+/// Text of a value that may hold a color
+fn color_text(value: &CssValue) -> Option<&str> {
+    match value {
+        CssValue::Color(color) | CssValue::String(color) | CssValue::Keyword(color) => Some(color),
+        _ => None,
+    }
+}
+
+/// Parse `#rgb`, `#rrggbb`, `rgb()` and `rgba()` colors into RGBA channels
+fn parse_color(color: &str) -> Option<[f32; 4]> {
+    let color = color.trim();
+
+    if let Some(hex) = color.strip_prefix('#') {
+        let channel = |digits: &str| u8::from_str_radix(digits, 16).ok().map(f32::from);
+        return match hex.len() {
+            3 => {
+                let expanded: String = hex.chars().flat_map(|c| [c, c]).collect();
+                parse_color(&format!("#{}", expanded))
+            }
+            6 => Some([channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?, 1.0]),
+            _ => None,
+        };
+    }
+
+    let args = color.strip_prefix("rgba(").or_else(|| color.strip_prefix("rgb("))?.strip_suffix(')')?;
+    let channels: Vec<f32> = args.split(',').map(|arg| arg.trim().parse().ok()).collect::<Option<_>>()?;
+    match channels.as_slice() {
+        [r, g, b] => Some([*r, *g, *b, 1.0]),
+        [r, g, b, a] => Some([*r, *g, *b, *a]),
+        _ => None,
+    }
+}
+
+/// Parse a keyframe selector (`from`, `to` or a percentage) into an offset
+fn parse_keyframe_offset(selector: &str) -> Option<f32> {
+    match selector.trim().to_ascii_lowercase().as_str() {
+        "from" => Some(0.0),
+        "to" => Some(1.0),
+        selector => selector.strip_suffix('%')?.trim().parse::<f32>().ok()
+            .filter(|percent| (0.0..=100.0).contains(percent))
+            .map(|percent| percent / 100.0),
+    }
+}
+
+/// Parse a CSS time such as `300ms` or `0.3s`
+fn parse_time(token: &str) -> Option<Duration> {
+    let seconds = if let Some(ms) = token.strip_suffix("ms") {
+        ms.parse::<f64>().ok()? / 1000.0
+    } else {
+        token.strip_suffix('s')?.parse::<f64>().ok()?
+    };
+    (seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+/// Text form of a declared value, for shorthands parsed by the engine
+fn css_value_text(value: &CssValue) -> String {
+    match value {
+        CssValue::Keyword(text) | CssValue::String(text) | CssValue::Color(text) => text.clone(),
+        CssValue::Number(number) => number.to_string(),
+        CssValue::Length(length, unit) => format!("{}{}", length, unit),
+        CssValue::Percentage(percent) => format!("{}%", percent),
+        CssValue::Url(url) => format!("url({})", url),
+        CssValue::Function(name, args) => {
+            format!("{}({})", name, args.iter().map(css_value_text).collect::<Vec<_>>().join(", "))
+        }
+        CssValue::List(items) => items.iter().map(css_value_text).collect::<Vec<_>>().join(" "),
+        CssValue::Initial => "initial".to_string(),
+        CssValue::Inherit => "inherit".to_string(),
+        CssValue::Unset => "unset".to_string(),
+        CssValue::Revert => "revert".to_string(),
+    }
+}
+
+/// Split on commas outside parentheses
+fn split_top_level_commas(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Split on whitespace outside parentheses
+fn split_top_level_whitespace(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+    for (index, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(token_start) = start.take() {
+                    parts.push(&value[token_start..index]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(index);
+    }
+    if let Some(token_start) = start {
+        parts.push(&value[token_start..]);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn keyframe_rule(selector: &str, property: &str, value: &str) -> KeyframeRule {
+        KeyframeRule {
+            selectors: vec![selector.to_string()],
+            declarations: [(property.to_string(), value.to_string())].into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn test_keyframe_animation_interpolates() {
+        let mut engine = AnimationEngine::new();
+        engine.register_keyframes("fade", &[
+            keyframe_rule("from", "opacity", "0"),
+            keyframe_rule("to", "opacity", "1"),
+        ]);
+
+        let started = engine.start_animation("box", "fade", Duration::from_secs(1), TimingFunction::Linear, FillMode::Forwards);
+        assert_eq!(started.unwrap(), 1);
+
+        engine.tick(Duration::from_millis(250));
+        assert_eq!(engine.animated_value("box", "opacity").unwrap().value(), &CssValue::Number(0.25));
+
+        // Forwards fill keeps the final value
+        engine.tick(Duration::from_secs(2));
+        assert_eq!(engine.animated_value("box", "opacity").unwrap().value(), &CssValue::Number(1.0));
+        assert!(!engine.needs_frame());
+
+        assert!(engine.start_animation("box", "missing", Duration::from_secs(1), TimingFunction::Linear, FillMode::None).is_err());
+    }
+
+    #[test]
+    fn test_transition_on_computed_value_change() {
+        let mut engine = AnimationEngine::new();
+        let mut style = ComputedStyle::default();
+        style.properties.insert("transition".to_string(), ComputedValue::new(CssValue::String("width 1s linear".to_string()), false, true));
+        style.properties.insert("width".to_string(), ComputedValue::new(CssValue::Length(100.0, "px".to_string()), false, true));
+        engine.update_style("box", &mut style);
+        assert!(engine.active_animations().is_empty());
+
+        style.properties.insert("width".to_string(), ComputedValue::new(CssValue::Length(200.0, "px".to_string()), false, true));
+        engine.update_style("box", &mut style);
+        // The cascade result is overridden by the start of the transition
+        assert_eq!(style.get("width").unwrap().value(), &CssValue::Length(100.0, "px".to_string()));
+
+        engine.tick(Duration::from_millis(500));
+        let mut next = style.clone();
+        next.properties.insert("width".to_string(), ComputedValue::new(CssValue::Length(200.0, "px".to_string()), false, true));
+        engine.update_style("box", &mut next);
+        assert_eq!(next.get("width").unwrap().value(), &CssValue::Length(150.0, "px".to_string()));
+
+        engine.tick(Duration::from_millis(600));
+        assert!(engine.active_animations().is_empty());
+    }
+
+    #[test]
+    fn test_request_animation_frame() {
+        let mut engine = AnimationEngine::new();
+        let timestamps = Arc::new(Mutex::new(Vec::new()));
+
+        let sink = timestamps.clone();
+        engine.request_animation_frame(Box::new(move |timestamp| sink.lock().unwrap().push(timestamp)));
+        let cancelled = engine.request_animation_frame(Box::new(|_| panic!("cancelled callback ran")));
+        engine.cancel_animation_frame(cancelled);
+
+        engine.tick(Duration::from_millis(16));
+        engine.tick(Duration::from_millis(16));
+
+        assert_eq!(*timestamps.lock().unwrap(), vec![16.0]);
+    }
+
+    #[test]
+    fn test_timing_functions_and_colors() {
+        assert_eq!(TimingFunction::parse("steps(4)"), Some(TimingFunction::Steps(4)));
+        assert_eq!(TimingFunction::Steps(4).apply(0.3), 0.25);
+        assert!((TimingFunction::parse("cubic-bezier(0, 0, 1, 1)").unwrap().apply(0.3) - 0.3).abs() < 1e-3);
+        assert!(TimingFunction::EaseIn.apply(0.5) < 0.5);
+
+        let red = CssValue::Color("#ff0000".to_string());
+        let blue = CssValue::Color("rgb(0, 0, 255)".to_string());
+        assert_eq!(interpolate(&red, &blue, 0.5), CssValue::Color("rgb(128, 0, 128)".to_string()));
+
+        let specs = TransitionSpec::parse_shorthand("opacity 300ms cubic-bezier(0.1, 0.7, 1, 0.1) 1s, transform 2s").unwrap();
+        assert_eq!(specs.len(), 2);
+        assert_eq!(specs[0].delay, Duration::from_secs(1));
+        assert_eq!(specs[1].property, "transform");
+    }
+}
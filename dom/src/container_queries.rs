@@ -0,0 +1,170 @@
+//! Container query evaluation for the Matte browser.
+//!
+//! This module evaluates the conditions of `@container` rules against the
+//! size of the nearest ancestor that is a query container, rather than the
+//! viewport.
+
+use tracing::debug;
+use crate::css_at_rules::AtRule;
+use crate::cssom::CssRuleVariant;
+use crate::media_queries::MediaQueryEvaluator;
+
+/// Value of the `container-type` property
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContainerType {
+    /// Not a query container
+    #[default]
+    Normal,
+    /// Query container for its inline size
+    InlineSize,
+    /// Query container for both dimensions
+    Size,
+}
+
+impl ContainerType {
+    /// Parse a `container-type` value
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "inline-size" => ContainerType::InlineSize,
+            "size" => ContainerType::Size,
+            _ => ContainerType::Normal,
+        }
+    }
+}
+
+/// Size of a query container at layout time
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerContext {
+    /// Names from the `container-name` property, separated by whitespace
+    pub name: Option<String>,
+    /// Content width of the container
+    pub width: f32,
+    /// Content height of the container
+    pub height: f32,
+}
+
+impl ContainerContext {
+    /// Create a container context
+    pub fn new(name: Option<String>, width: f32, height: f32) -> Self {
+        Self { name, width, height }
+    }
+
+    /// Check if the container has a name
+    pub fn has_name(&self, name: &str) -> bool {
+        self.name.as_deref().is_some_and(|names| names.split_whitespace().any(|candidate| candidate == name))
+    }
+}
+
+/// An `@container` rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerRule {
+    /// Name of the container the rule queries, or `None` for the nearest one
+    pub name: Option<String>,
+    /// Size condition, e.g. `(width < 400px)`
+    pub condition: String,
+    /// Rules that apply while the condition matches
+    pub rules: Vec<CssRuleVariant>,
+}
+
+impl ContainerRule {
+    /// Get the container rule of an at-rule, if it is one
+    pub fn from_at_rule(rule: &AtRule) -> Option<Self> {
+        match rule {
+            AtRule::Container { name, condition, rules } => Some(Self {
+                name: name.clone(),
+                condition: condition.clone(),
+                rules: rules.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Evaluates container queries
+pub struct ContainerQueryEvaluator;
+
+impl ContainerQueryEvaluator {
+    /// Check if a rule's condition holds for a container
+    ///
+    /// A named rule only applies to containers with that name. Size features
+    /// are evaluated like media features, with `inline-size` and `block-size`
+    /// standing for `width` and `height`.
+    pub fn evaluate(rule: &ContainerRule, ctx: &ContainerContext) -> bool {
+        if rule.name.as_deref().is_some_and(|name| !ctx.has_name(name)) {
+            return false;
+        }
+
+        let condition = rule.condition.trim().to_ascii_lowercase()
+            .replace("inline-size", "width")
+            .replace("block-size", "height");
+        if condition.is_empty() {
+            return false;
+        }
+
+        let evaluator = MediaQueryEvaluator::new(ctx.width.max(0.0) as u32, ctx.height.max(0.0) as u32);
+        let matches = evaluator.matches(&condition);
+        debug!("Container query '{}' against {}x{} matches: {}", rule.condition, ctx.width, ctx.height, matches);
+        matches
+    }
+
+    /// Find the container a rule queries among the containers enclosing an element
+    ///
+    /// The containers are given outermost first, so the nearest one is last.
+    pub fn find_container<'a>(rule: &ContainerRule, containers: &'a [ContainerContext]) -> Option<&'a ContainerContext> {
+        containers.iter().rev().find(|ctx| match &rule.name {
+            Some(name) => ctx.has_name(name),
+            None => true,
+        })
+    }
+
+    /// Check if a rule applies to an element enclosed by the given containers
+    pub fn evaluate_nearest(rule: &ContainerRule, containers: &[ContainerContext]) -> bool {
+        Self::find_container(rule, containers).is_some_and(|ctx| Self::evaluate(rule, ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css_at_rules::AtRuleParser;
+
+    fn container_rule(css: &str) -> ContainerRule {
+        let at_rule = AtRuleParser::new().parse_at_rule(css).unwrap();
+        ContainerRule::from_at_rule(&at_rule).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_evaluate_container_rule() {
+        let rule = container_rule("@container card (width < 400px) { .title { font-size: 12px } }");
+        assert_eq!(rule.name.as_deref(), Some("card"));
+        assert_eq!(rule.condition, "(width < 400px)");
+        assert_eq!(rule.rules.len(), 1);
+
+        let narrow = ContainerContext::new(Some("card".to_string()), 320.0, 200.0);
+        let wide = ContainerContext::new(Some("card".to_string()), 640.0, 200.0);
+        let unnamed = ContainerContext::new(None, 320.0, 200.0);
+        assert!(ContainerQueryEvaluator::evaluate(&rule, &narrow));
+        assert!(!ContainerQueryEvaluator::evaluate(&rule, &wide));
+        assert!(!ContainerQueryEvaluator::evaluate(&rule, &unnamed));
+
+        let rule = container_rule("@container (min-inline-size: 400px) and (block-size <= 300px) { p { color: red } }");
+        assert_eq!(rule.name, None);
+        assert!(ContainerQueryEvaluator::evaluate(&rule, &wide));
+        assert!(!ContainerQueryEvaluator::evaluate(&rule, &narrow));
+    }
+
+    #[test]
+    fn test_nearest_matching_container() {
+        let rule = container_rule("@container sidebar (width < 300px) { p { color: red } }");
+        let containers = vec![
+            ContainerContext::new(Some("sidebar layout".to_string()), 250.0, 0.0),
+            ContainerContext::new(Some("card".to_string()), 800.0, 0.0),
+        ];
+        assert_eq!(ContainerQueryEvaluator::find_container(&rule, &containers), Some(&containers[0]));
+        assert!(ContainerQueryEvaluator::evaluate_nearest(&rule, &containers));
+
+        let unnamed = container_rule("@container (width < 300px) { p { color: red } }");
+        assert!(!ContainerQueryEvaluator::evaluate_nearest(&unnamed, &containers));
+        assert!(!ContainerQueryEvaluator::evaluate_nearest(&unnamed, &[]));
+    }
+}
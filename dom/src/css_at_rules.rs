@@ -1,7 +1,11 @@
 use crate::css_tokenizer::{CssTokenizer, CssToken};
-use crate::cssom::{CssRuleVariant, CssStyleSheet};
+use crate::container_queries::ContainerRule;
+use crate::css_parser::CssParser;
+use crate::cssom::{CssRuleVariant, CssStyleRule, CssStyleSheet};
 use crate::error::Result;
+use crate::typography::{FontFamily, FontStretch, FontStyle, FontWeight};
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// Represents different types of CSS at-rules
 #[derive(Debug, Clone, PartialEq)]
@@ -17,9 +21,7 @@ pub enum AtRule {
         rules: Vec<CssRuleVariant>,
     },
     /// @font-face rule
-    FontFace {
-        declarations: HashMap<String, String>,
-    },
+    FontFace(FontFaceDeclaration),
     /// @keyframes rule
     Keyframes {
         name: String,
@@ -63,6 +65,12 @@ pub enum AtRule {
         font_family: String,
         feature_values: HashMap<String, Vec<String>>,
     },
+    /// @container rule
+    Container {
+        name: Option<String>,
+        condition: String,
+        rules: Vec<CssRuleVariant>,
+    },
 }
 
 /// Represents a keyframe rule within @keyframes
@@ -74,6 +82,199 @@ pub struct KeyframeRule {
     pub declarations: HashMap<String, String>,
 }
 
+/// Web font declared by an @font-face rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFaceDeclaration {
+    /// Family name the font is used by
+    pub family: FontFamily,
+    /// Sources of the font, in order of preference
+    pub sources: Vec<FontFaceSource>,
+    /// Font weight
+    pub weight: FontWeight,
+    /// Font style
+    pub style: FontStyle,
+    /// Font stretch
+    pub stretch: FontStretch,
+    /// How text is rendered while the font loads
+    pub display: FontDisplay,
+    /// All descriptors as written
+    pub descriptors: HashMap<String, String>,
+}
+
+/// Source in the `src` descriptor of an @font-face rule
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontFaceSource {
+    /// Font file, with its `format()` hint
+    Url {
+        url: String,
+        format: Option<String>,
+    },
+    /// Installed font, by full name
+    Local(String),
+}
+
+/// Value of the `font-display` descriptor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FontDisplay {
+    #[default]
+    Auto,
+    Block,
+    Swap,
+    Fallback,
+    Optional,
+}
+
+/// Longest block period of `fallback` and `optional` fonts
+const SHORT_BLOCK_PERIOD: Duration = Duration::from_millis(100);
+
+impl FontFaceDeclaration {
+    /// Build a declaration from the descriptors of an @font-face rule
+    ///
+    /// `font-family` and `src` are required, other descriptors default to
+    /// their initial values.
+    pub fn from_descriptors(descriptors: HashMap<String, String>) -> Result<Self> {
+        let family = descriptors.get("font-family")
+            .map(|family| unquote(family))
+            .filter(|family| !family.is_empty())
+            .ok_or_else(|| crate::error::Error::ParseError("@font-face without font-family".to_string()))?;
+        let sources: Vec<FontFaceSource> = descriptors.get("src")
+            .map(|src| split_top_level(src, ',').into_iter().filter_map(FontFaceSource::parse).collect())
+            .unwrap_or_default();
+        if sources.is_empty() {
+            return Err(crate::error::Error::ParseError(format!("@font-face for {} without a valid src", family)));
+        }
+
+        // Ranges such as `font-weight: 100 900` match on their start
+        let first_value = |name: &str| descriptors.get(name)
+            .and_then(|value| value.split_whitespace().next())
+            .map(str::to_ascii_lowercase);
+        let weight = match first_value("font-weight").as_deref() {
+            None | Some("normal") => FontWeight(400),
+            Some("bold") => FontWeight(700),
+            Some(weight) => FontWeight(weight.parse().map_err(|_| {
+                crate::error::Error::ParseError(format!("Invalid font-weight in @font-face: {}", weight))
+            })?),
+        };
+        let style = match first_value("font-style").as_deref() {
+            Some("italic") => FontStyle::Italic,
+            Some("oblique") => FontStyle::Oblique,
+            _ => FontStyle::Normal,
+        };
+        let stretch = match first_value("font-stretch").as_deref() {
+            Some("ultra-condensed") => FontStretch::UltraCondensed,
+            Some("extra-condensed") => FontStretch::ExtraCondensed,
+            Some("condensed") => FontStretch::Condensed,
+            Some("semi-condensed") => FontStretch::SemiCondensed,
+            Some("semi-expanded") => FontStretch::SemiExpanded,
+            Some("expanded") => FontStretch::Expanded,
+            Some("extra-expanded") => FontStretch::ExtraExpanded,
+            Some("ultra-expanded") => FontStretch::UltraExpanded,
+            _ => FontStretch::Normal,
+        };
+        let display = match first_value("font-display").as_deref() {
+            Some("block") => FontDisplay::Block,
+            Some("swap") => FontDisplay::Swap,
+            Some("fallback") => FontDisplay::Fallback,
+            Some("optional") => FontDisplay::Optional,
+            _ => FontDisplay::Auto,
+        };
+
+        Ok(Self {
+            family: FontFamily(family),
+            sources,
+            weight,
+            style,
+            stretch,
+            display,
+            descriptors,
+        })
+    }
+}
+
+impl FontFaceSource {
+    /// Parse one entry of a `src` descriptor
+    fn parse(source: &str) -> Option<Self> {
+        let source = source.trim();
+        if let Some(name) = function_argument(source, "local") {
+            return Some(Self::Local(name));
+        }
+
+        let url = function_argument(source, "url")?;
+        let format = source.find("format(")
+            .and_then(|start| function_argument(&source[start..], "format"))
+            .map(|format| format.to_ascii_lowercase());
+        Some(Self::Url { url, format })
+    }
+}
+
+impl FontDisplay {
+    /// How long text using the font stays invisible while it loads
+    ///
+    /// `block_timeout` is the block period of `auto` and `block` fonts. Text
+    /// is rendered with a fallback font once the period is over.
+    pub fn block_period(self, block_timeout: Duration) -> Duration {
+        match self {
+            FontDisplay::Auto | FontDisplay::Block => block_timeout,
+            FontDisplay::Swap => Duration::ZERO,
+            FontDisplay::Fallback | FontDisplay::Optional => block_timeout.min(SHORT_BLOCK_PERIOD),
+        }
+    }
+}
+
+/// Get the argument of a CSS function such as `url("a.woff2")`, unquoted
+fn function_argument(value: &str, name: &str) -> Option<String> {
+    let rest = value.strip_prefix(name)?.trim_start().strip_prefix('(')?;
+    let mut quote = None;
+    let close = rest.char_indices().find_map(|(i, c)| match c {
+        '"' | '\'' if quote == Some(c) => {
+            quote = None;
+            None
+        }
+        '"' | '\'' if quote.is_none() => {
+            quote = Some(c);
+            None
+        }
+        ')' if quote.is_none() => Some(i),
+        _ => None,
+    })?;
+    Some(unquote(&rest[..close]))
+}
+
+/// Strip whitespace and the quotes around a CSS string
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value.strip_prefix('"').and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Split a value on a delimiter outside of strings and parentheses
+fn split_top_level(value: &str, delimiter: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' | '\'' if quote == Some(c) => quote = None,
+            '"' | '\'' if quote.is_none() => quote = Some(c),
+            _ if quote.is_some() => {}
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ if c == delimiter && depth == 0 => {
+                parts.push(&value[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+
+    parts
+}
+
 /// Parser for CSS at-rules
 pub struct AtRuleParser {
     /// Current position in the token stream
@@ -113,6 +314,7 @@ impl AtRuleParser {
             "document" => self.parse_document_rule(),
             "counter-style" => self.parse_counter_style_rule(),
             "font-feature-values" => self.parse_font_feature_values_rule(),
+            "container" => self.parse_container_rule(),
             _ => Err(crate::error::Error::ParseError(format!("Unknown at-rule: @{}", rule_name))),
         }
     }
@@ -189,7 +391,7 @@ impl AtRuleParser {
         // Expect closing brace
         self.expect_brace('}')?;
         
-        Ok(AtRule::FontFace { declarations })
+        Ok(AtRule::FontFace(FontFaceDeclaration::from_descriptors(declarations)?))
     }
 
     /// Parse @keyframes rule
@@ -243,6 +445,28 @@ impl AtRuleParser {
         Ok(AtRule::Supports { condition, rules })
     }
 
+    /// Parse @container rule
+    fn parse_container_rule(&mut self) -> Result<AtRule> {
+        // An optional container name comes before the size condition
+        let name = match self.tokens.get(self.position) {
+            Some(CssToken::Ident(name)) if !matches!(name.as_str(), "not" | "and" | "or") => {
+                let name = name.clone();
+                self.position += 1;
+                Some(name)
+            }
+            _ => None,
+        };
+        
+        // The condition is terminated by the block, like a media query
+        let condition = self.parse_media_query()?;
+        
+        self.expect_brace('{')?;
+        let rules = self.parse_rule_list()?;
+        self.expect_brace('}')?;
+        
+        Ok(AtRule::Container { name, condition, rules })
+    }
+
     /// Parse @charset rule
     fn parse_charset_rule(&mut self) -> Result<AtRule> {
         // Parse encoding string
@@ -417,7 +641,7 @@ impl AtRuleParser {
                     media_list.push(name.clone());
                     self.position += 1;
                 }
-                CssToken::Delim(',') => {
+                CssToken::Delim(',') | CssToken::Comma => {
                     self.position += 1;
                 }
                 _ => break,
@@ -429,19 +653,16 @@ impl AtRuleParser {
 
     /// Parse media query
     fn parse_media_query(&mut self) -> Result<String> {
-        let mut query = String::new();
+        let start = self.position;
         
         while self.position < self.tokens.len() {
             match &self.tokens[self.position] {
-                CssToken::Delim('{') => break,
-                _ => {
-                    query.push_str(&self.tokens[self.position].to_string());
-                    self.position += 1;
-                }
+                CssToken::Delim('{') | CssToken::LeftBrace => break,
+                _ => self.position += 1,
             }
         }
         
-        Ok(query.trim().to_string())
+        Ok(tokens_to_css(&self.tokens[start..self.position]))
     }
 
     /// Parse rule list
@@ -450,18 +671,14 @@ impl AtRuleParser {
         
         while self.position < self.tokens.len() {
             match &self.tokens[self.position] {
-                CssToken::Delim('}') => break,
+                CssToken::Delim('}') | CssToken::RightBrace | CssToken::Eof => break,
+                CssToken::AtKeyword(_) => {
+                    let at_rule = self.parse_at_rule_inline()?;
+                    rules.push(CssRuleVariant::AtRule(at_rule));
+                }
                 _ => {
-                    // For now, we'll skip individual rules and just consume tokens
-                    // until we find a closing brace or another at-rule
-                    if let CssToken::AtKeyword(_) = &self.tokens[self.position] {
-                        // Parse at-rule
-                        let at_rule = self.parse_at_rule_inline()?;
-                        rules.push(CssRuleVariant::AtRule(at_rule));
-                    } else {
-                        // Skip non-at-rule tokens for now
-                        self.position += 1;
-                    }
+                    let rule = self.parse_style_rule()?;
+                    rules.push(CssRuleVariant::StyleRule(rule));
                 }
             }
         }
@@ -469,6 +686,32 @@ impl AtRuleParser {
         Ok(rules)
     }
 
+    /// Parse a style rule inside a rule list
+    fn parse_style_rule(&mut self) -> Result<CssStyleRule> {
+        let prelude_start = self.position;
+        while self.position < self.tokens.len() && !matches!(self.tokens[self.position], CssToken::Delim('{') | CssToken::LeftBrace) {
+            self.position += 1;
+        }
+        let prelude = tokens_to_css(&self.tokens[prelude_start..self.position]);
+        self.expect_brace('{')?;
+        
+        let body_start = self.position;
+        let mut depth = 0;
+        while self.position < self.tokens.len() {
+            match &self.tokens[self.position] {
+                CssToken::Delim('{') | CssToken::LeftBrace => depth += 1,
+                CssToken::Delim('}') | CssToken::RightBrace if depth == 0 => break,
+                CssToken::Delim('}') | CssToken::RightBrace => depth -= 1,
+                _ => {}
+            }
+            self.position += 1;
+        }
+        let body = tokens_to_css(&self.tokens[body_start..self.position]);
+        self.expect_brace('}')?;
+        
+        CssParser::new().parse_style_rule(&prelude, &body)
+    }
+
     /// Parse at-rule inline (when already inside a rule list)
     fn parse_at_rule_inline(&mut self) -> Result<AtRule> {
         // Get the at-rule name from the current AtKeyword token
@@ -494,6 +737,7 @@ impl AtRuleParser {
             "document" => self.parse_document_rule(),
             "counter-style" => self.parse_counter_style_rule(),
             "font-feature-values" => self.parse_font_feature_values_rule(),
+            "container" => self.parse_container_rule(),
             _ => Err(crate::error::Error::ParseError(format!("Unknown at-rule: @{}", rule_name))),
         }
     }
@@ -504,7 +748,7 @@ impl AtRuleParser {
         
         while self.position < self.tokens.len() {
             match &self.tokens[self.position] {
-                CssToken::Delim('}') => break,
+                CssToken::Delim('}') | CssToken::RightBrace => break,
                 CssToken::Ident(property) => {
                     let property_name = property.clone();
                     self.position += 1;
@@ -512,7 +756,7 @@ impl AtRuleParser {
                     // Expect colon
                     if self.position < self.tokens.len() {
                         match &self.tokens[self.position] {
-                            CssToken::Delim(':') => {
+                            CssToken::Delim(':') | CssToken::Colon => {
                                 self.position += 1;
                             }
                             _ => {
@@ -525,8 +769,10 @@ impl AtRuleParser {
                     let value = self.parse_declaration_value()?;
                     declarations.insert(property_name, value);
                     
-                    // Expect semicolon
-                    self.expect_semicolon()?;
+                    // The last declaration may leave out its semicolon
+                    if !matches!(self.tokens.get(self.position), Some(CssToken::Delim('}') | CssToken::RightBrace)) {
+                        self.expect_semicolon()?;
+                    }
                 }
                 _ => {
                     self.position += 1;
@@ -539,19 +785,16 @@ impl AtRuleParser {
 
     /// Parse declaration value
     fn parse_declaration_value(&mut self) -> Result<String> {
-        let mut value = String::new();
+        let start = self.position;
         
         while self.position < self.tokens.len() {
             match &self.tokens[self.position] {
-                CssToken::Delim(';') | CssToken::Delim('}') => break,
-                _ => {
-                    value.push_str(&self.tokens[self.position].to_string());
-                    self.position += 1;
-                }
+                CssToken::Delim(';') | CssToken::Semicolon | CssToken::Delim('}') | CssToken::RightBrace | CssToken::Eof => break,
+                _ => self.position += 1,
             }
         }
         
-        Ok(value.trim().to_string())
+        Ok(tokens_to_css(&self.tokens[start..self.position]))
     }
 
     /// Parse keyframe list
@@ -560,7 +803,7 @@ impl AtRuleParser {
         
         while self.position < self.tokens.len() {
             match &self.tokens[self.position] {
-                CssToken::Delim('}') => break,
+                CssToken::Delim('}') | CssToken::RightBrace => break,
                 CssToken::Percentage(_) | CssToken::Ident(_) => {
                     // Parse keyframe selectors
                     let selectors = self.parse_keyframe_selectors()?;
@@ -591,7 +834,7 @@ impl AtRuleParser {
         
         while self.position < self.tokens.len() {
             match &self.tokens[self.position] {
-                CssToken::Delim('{') => break,
+                CssToken::Delim('{') | CssToken::LeftBrace => break,
                 CssToken::Percentage(p) => {
                     selectors.push(format!("{}%", p));
                     self.position += 1;
@@ -735,8 +978,12 @@ impl AtRuleParser {
             return Err(crate::error::Error::ParseError("Unexpected end of input".to_string()));
         }
         
-        match &self.tokens[self.position] {
-            CssToken::Delim(c) if *c == brace => {
+        match (&self.tokens[self.position], brace) {
+            (CssToken::Delim(c), _) if *c == brace => {
+                self.position += 1;
+                Ok(())
+            }
+            (CssToken::LeftBrace, '{') | (CssToken::RightBrace, '}') => {
                 self.position += 1;
                 Ok(())
             }
@@ -745,14 +992,38 @@ impl AtRuleParser {
     }
 }
 
+/// Serialize tokens back into CSS text
+///
+/// The tokenizer drops whitespace, so a space is put back between tokens
+/// except just inside brackets and before punctuation.
+fn tokens_to_css(tokens: &[CssToken]) -> String {
+    let mut css = String::new();
+    let mut previous: Option<&CssToken> = None;
+    
+    for token in tokens.iter().filter(|token| !matches!(token, CssToken::Eof | CssToken::Whitespace)) {
+        let after_open = matches!(previous, Some(CssToken::LeftParen | CssToken::LeftBracket | CssToken::Function(_)) | None);
+        let before_close = matches!(token, CssToken::RightParen | CssToken::RightBracket | CssToken::Comma | CssToken::Colon | CssToken::Semicolon)
+            || (matches!(token, CssToken::Delim('=')) && matches!(previous, Some(CssToken::Delim('<' | '>'))));
+        if !after_open && !before_close {
+            css.push(' ');
+        }
+        css.push_str(&token.to_string());
+        previous = Some(token);
+    }
+    
+    css
+}
+
 /// Manager for handling at-rules in a stylesheet
 pub struct AtRuleManager {
     /// Registered at-rule handlers
     handlers: HashMap<String, Box<dyn AtRuleHandler>>,
+    /// Parsed at-rules by the ID of the stylesheet they came from
+    rules: HashMap<String, Vec<AtRule>>,
 }
 
 /// Trait for handling specific at-rules
-pub trait AtRuleHandler {
+pub trait AtRuleHandler: Send + Sync {
     /// Process an at-rule
     fn process(&self, rule: &AtRule, stylesheet: &mut CssStyleSheet) -> Result<()>;
 }
@@ -762,9 +1033,46 @@ impl AtRuleManager {
     pub fn new() -> Self {
         Self {
             handlers: HashMap::new(),
+            rules: HashMap::new(),
         }
     }
 
+    /// Store an at-rule parsed from a stylesheet
+    pub fn add_rule(&mut self, stylesheet_id: &str, rule: AtRule) {
+        self.rules.entry(stylesheet_id.to_string()).or_default().push(rule);
+    }
+
+    /// Get the at-rules parsed from a stylesheet
+    pub fn rules(&self, stylesheet_id: &str) -> &[AtRule] {
+        self.rules.get(stylesheet_id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Get the `@media` rules parsed from a stylesheet
+    pub fn media_rules<'a>(&'a self, stylesheet_id: &str) -> impl Iterator<Item = (&'a str, &'a [CssRuleVariant])> {
+        self.rules(stylesheet_id).iter().filter_map(|rule| match rule {
+            AtRule::Media { media_query, rules } => Some((media_query.as_str(), rules.as_slice())),
+            _ => None,
+        })
+    }
+
+    /// Get the `@container` rules parsed from a stylesheet
+    pub fn container_rules<'a>(&'a self, stylesheet_id: &str) -> impl Iterator<Item = ContainerRule> + 'a {
+        self.rules(stylesheet_id).iter().filter_map(ContainerRule::from_at_rule)
+    }
+
+    /// Get the `@font-face` rules parsed from a stylesheet
+    pub fn font_face_rules<'a>(&'a self, stylesheet_id: &str) -> impl Iterator<Item = &'a FontFaceDeclaration> {
+        self.rules(stylesheet_id).iter().filter_map(|rule| match rule {
+            AtRule::FontFace(declaration) => Some(declaration),
+            _ => None,
+        })
+    }
+
+    /// Forget the at-rules of a stylesheet
+    pub fn remove_rules(&mut self, stylesheet_id: &str) {
+        self.rules.remove(stylesheet_id);
+    }
+
     /// Register an at-rule handler
     pub fn register_handler(&mut self, rule_name: &str, handler: Box<dyn AtRuleHandler>) {
         self.handlers.insert(rule_name.to_string(), handler);
@@ -775,7 +1083,7 @@ impl AtRuleManager {
         let rule_name = match rule {
             AtRule::Import { .. } => "import",
             AtRule::Media { .. } => "media",
-            AtRule::FontFace { .. } => "font-face",
+            AtRule::FontFace(_) => "font-face",
             AtRule::Keyframes { .. } => "keyframes",
             AtRule::Page { .. } => "page",
             AtRule::Supports { .. } => "supports",
@@ -785,6 +1093,7 @@ impl AtRuleManager {
             AtRule::Document { .. } => "document",
             AtRule::CounterStyle { .. } => "counter-style",
             AtRule::FontFeatureValues { .. } => "font-feature-values",
+            AtRule::Container { .. } => "container",
         };
 
         if let Some(handler) = self.handlers.get(rule_name) {
@@ -823,7 +1132,7 @@ mod tests {
         
         if let AtRule::Media { media_query, rules } = result.unwrap() {
             assert_eq!(media_query, "screen and (max-width: 600px)");
-            // Rules parsing is simplified for now
+            assert_eq!(rules.len(), 1);
         } else {
             panic!("Expected media rule");
         }
@@ -835,14 +1144,39 @@ mod tests {
         let result = parser.parse_at_rule("@font-face { font-family: 'Arial'; src: url('arial.ttf'); }");
         assert!(result.is_ok());
         
-        if let AtRule::FontFace { declarations } = result.unwrap() {
-            assert!(declarations.contains_key("font-family"));
-            assert!(declarations.contains_key("src"));
+        if let AtRule::FontFace(declaration) = result.unwrap() {
+            assert_eq!(declaration.family, FontFamily("Arial".to_string()));
+            assert_eq!(declaration.sources, vec![FontFaceSource::Url { url: "arial.ttf".to_string(), format: None }]);
+            assert!(declaration.descriptors.contains_key("src"));
         } else {
             panic!("Expected font-face rule");
         }
     }
 
+    #[test]
+    fn test_parse_font_face_descriptors() {
+        let mut parser = AtRuleParser::new();
+        let result = parser.parse_at_rule(
+            "@font-face { font-family: \"MyFont\"; src: url(\"fonts/my.woff2\") format(\"woff2\"), local(\"My Font\"); \
+             font-weight: bold; font-style: italic; font-display: swap; }"
+        ).unwrap();
+
+        let AtRule::FontFace(declaration) = result else {
+            panic!("Expected font-face rule");
+        };
+        assert_eq!(declaration.family, FontFamily("MyFont".to_string()));
+        assert_eq!(declaration.sources, vec![
+            FontFaceSource::Url { url: "fonts/my.woff2".to_string(), format: Some("woff2".to_string()) },
+            FontFaceSource::Local("My Font".to_string()),
+        ]);
+        assert_eq!(declaration.weight, FontWeight(700));
+        assert_eq!(declaration.style, FontStyle::Italic);
+        assert_eq!(declaration.display, FontDisplay::Swap);
+        assert_eq!(declaration.display.block_period(Duration::from_secs(3)), Duration::ZERO);
+
+        assert!(parser.parse_at_rule("@font-face { font-family: \"MyFont\"; }").is_err());
+    }
+
     #[test]
     fn test_parse_keyframes_rule() {
         let mut parser = AtRuleParser::new();
@@ -0,0 +1,526 @@
+//! CSS style sheet parser with CSS Nesting support.
+//!
+//! This module parses style rules, including rules nested with the `&`
+//! selector, into the CSSOM and registers every selector with a
+//! `SelectorIndex` as it goes.
+
+use tracing::debug;
+use crate::css_at_rules::AtRuleParser;
+use crate::css_property_parser::CssPropertyParser;
+use crate::css_selector::{Combinator, ComplexSelector, CssSelectorParser, SelectorList};
+use crate::cssom::{CssDeclaration, CssRuleVariant, CssStyleRule, CssStyleSheet, CssValue, RuleSource};
+use crate::error::{Error, Result};
+use crate::selector_indexing::SelectorIndex;
+
+/// Item in a block of CSS source
+#[derive(Debug, Clone, PartialEq)]
+enum BlockItem<'a> {
+    /// Text terminated by `;` (a declaration or statement at-rule)
+    Statement(&'a str),
+    /// Prelude followed by a `{ ... }` block
+    Block(&'a str, &'a str),
+}
+
+/// CSS style sheet parser
+pub struct CssParser {
+    /// Index of every selector parsed so far
+    selector_index: SelectorIndex,
+    /// Parser for declaration values
+    property_parser: CssPropertyParser,
+}
+
+impl CssParser {
+    /// Create a new CSS parser
+    pub fn new() -> Self {
+        Self {
+            selector_index: SelectorIndex::new(),
+            property_parser: CssPropertyParser::new(),
+        }
+    }
+
+    /// Get the index of the selectors parsed so far
+    pub fn selector_index(&self) -> &SelectorIndex {
+        &self.selector_index
+    }
+
+    /// Parse a style sheet
+    pub fn parse_stylesheet(&mut self, css: &str) -> Result<CssStyleSheet> {
+        let source = strip_comments(css);
+        let mut stylesheet = CssStyleSheet::new();
+        for rule in self.parse_rule_list(&source, &source)? {
+            stylesheet.add_rule(rule);
+        }
+        Ok(stylesheet)
+    }
+
+    /// Parse a list of top-level rules, such as a style sheet or layer body
+    ///
+    /// `source` is a slice of `stylesheet`, the full source the rule
+    /// positions are relative to.
+    fn parse_rule_list(&mut self, stylesheet: &str, source: &str) -> Result<Vec<CssRuleVariant>> {
+        let mut rules = Vec::new();
+
+        for item in split_block_items(source)? {
+            match item {
+                BlockItem::Block(prelude, body) if is_layer_rule(prelude) => {
+                    rules.push(CssRuleVariant::Layer {
+                        name: prelude["@layer".len()..].trim().to_string(),
+                        rules: self.parse_rule_list(stylesheet, body)?,
+                    });
+                }
+                BlockItem::Block(prelude, body) if prelude.starts_with('@') => {
+                    rules.extend(self.parse_at_rule(&format!("{} {{{}}}", prelude, body)));
+                }
+                BlockItem::Block(prelude, body) => {
+                    let mut rule = self.parse_style_rule(prelude, body)?;
+                    rule.source = Some(rule_source(stylesheet, prelude, body));
+                    rules.push(CssRuleVariant::StyleRule(rule));
+                }
+                BlockItem::Statement(statement) if is_layer_rule(statement) => {
+                    // `@layer a, b;` only fixes the order of the named layers
+                    for name in statement["@layer".len()..].split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                        rules.push(CssRuleVariant::Layer { name: name.to_string(), rules: Vec::new() });
+                    }
+                }
+                BlockItem::Statement(statement) if statement.starts_with('@') => {
+                    rules.extend(self.parse_at_rule(&format!("{};", statement)));
+                }
+                BlockItem::Statement(statement) => {
+                    debug!("Ignoring stray declaration at top level: {}", statement);
+                }
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// Parse a style rule and the rules nested inside it
+    pub fn parse_style_rule(&mut self, prelude: &str, body: &str) -> Result<CssStyleRule> {
+        let selectors = CssSelectorParser::new(prelude)?.parse_selector_list()?;
+        self.selector_index.add_selector_list(&selectors, prelude.to_string());
+
+        let mut rule = CssStyleRule::new(selectors.clone());
+        let mut nested_rules = Vec::new();
+        self.parse_block(body, &selectors, &mut rule.declarations, &mut nested_rules)?;
+        rule.nested_rules = nested_rules;
+        Ok(rule)
+    }
+
+    /// Parse the contents of a style rule block
+    ///
+    /// Rules nested more than one level deep are flattened, with the resolved
+    /// selector of their enclosing rule as their parent selector.
+    fn parse_block(
+        &mut self,
+        body: &str,
+        parent_selector: &SelectorList,
+        declarations: &mut Vec<CssDeclaration>,
+        nested_rules: &mut Vec<CssRuleVariant>,
+    ) -> Result<()> {
+        for item in split_block_items(body)? {
+            match item {
+                BlockItem::Statement(statement) => {
+                    if let Some(declaration) = self.parse_declaration(statement) {
+                        declarations.push(declaration);
+                    }
+                }
+                BlockItem::Block(prelude, body) => {
+                    let child_selector = parse_nested_selector(prelude)?;
+                    let mut nested = CssRuleVariant::Nested {
+                        parent_selector: parent_selector.clone(),
+                        child_selector,
+                        declarations: Vec::new(),
+                    };
+
+                    let resolved = SelectorList { selectors: nested.resolved_selectors() };
+                    self.selector_index.add_selector_list(&resolved, prelude.to_string());
+
+                    let mut nested_declarations = Vec::new();
+                    let mut deeper_rules = Vec::new();
+                    self.parse_block(body, &resolved, &mut nested_declarations, &mut deeper_rules)?;
+                    if let CssRuleVariant::Nested { declarations, .. } = &mut nested {
+                        *declarations = nested_declarations;
+                    }
+
+                    nested_rules.push(nested);
+                    nested_rules.extend(deeper_rules);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parse a single `property: value` declaration
+    fn parse_declaration(&mut self, text: &str) -> Option<CssDeclaration> {
+        let (property, value) = text.split_once(':')?;
+        let property = property.trim();
+        if property.is_empty() {
+            return None;
+        }
+
+        let value = value.trim();
+        let (value, important) = match value.strip_suffix("!important") {
+            Some(value) => (value.trim_end(), true),
+            None => (value, false),
+        };
+
+        let value = match self.property_parser.parse_property_value(value) {
+            Ok(parsed) => self.property_parser.to_css_value(&parsed),
+            Err(_) => CssValue::Keyword(value.to_string()),
+        };
+        Some(CssDeclaration::new(property.to_string(), value, important))
+    }
+
+    /// Parse an at-rule, skipping it if it is invalid
+    fn parse_at_rule(&mut self, text: &str) -> Option<CssRuleVariant> {
+        match AtRuleParser::new().parse_at_rule(text) {
+            Ok(at_rule) => Some(CssRuleVariant::AtRule(at_rule)),
+            Err(e) => {
+                debug!("Skipping at-rule that failed to parse: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl Default for CssParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse the selector of a nested rule into selectors relative to the parent
+///
+/// `& .child` and `.child` relate to the parent with the descendant
+/// combinator, `& > .child` and `> .child` with the given combinator, and
+/// `&.child` qualifies the element matched by the parent.
+fn parse_nested_selector(prelude: &str) -> Result<SelectorList> {
+    let mut selector_list = SelectorList::new();
+
+    for selector in split_top_level(prelude, ',') {
+        let selector = selector.trim();
+        let (rest, attached) = match selector.strip_prefix('&') {
+            Some(rest) => (rest, !rest.starts_with(char::is_whitespace)),
+            None => (selector, false),
+        };
+        let rest = rest.trim_start();
+        if rest.contains('&') {
+            return Err(Error::ParseError(format!("Unsupported nesting selector position in '{}'", selector)));
+        }
+
+        let (combinator, rest) = match rest.chars().next() {
+            Some('>') => (Some(Combinator::Child), &rest[1..]),
+            Some('+') => (Some(Combinator::AdjacentSibling), &rest[1..]),
+            Some('~') => (Some(Combinator::GeneralSibling), &rest[1..]),
+            _ if attached => (None, rest),
+            _ => (Some(Combinator::Descendant), rest),
+        };
+
+        let mut relative = ComplexSelector::new();
+        if let Some(combinator) = combinator {
+            relative.add_combinator(combinator);
+        }
+        if !rest.trim().is_empty() {
+            let parsed = CssSelectorParser::new(rest.trim())?.parse_selector_list()?;
+            for complex in parsed.selectors {
+                relative.parts.extend(complex.parts);
+            }
+        }
+        selector_list.add_selector(relative);
+    }
+
+    Ok(selector_list)
+}
+
+/// Check if a prelude or statement starts an `@layer` rule
+fn is_layer_rule(text: &str) -> bool {
+    text.strip_prefix("@layer")
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+}
+
+/// Blank out `/* ... */` comments
+///
+/// Comments are replaced with spaces, keeping their line breaks, so byte
+/// offsets and line numbers of the remaining source are unchanged.
+fn strip_comments(css: &str) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        output.push_str(&rest[..start]);
+        let (comment, remaining) = match rest[start + 2..].find("*/") {
+            Some(end) => rest[start..].split_at(2 + end + 2),
+            None => (&rest[start..], ""),
+        };
+        for c in comment.chars() {
+            if c == '\n' {
+                output.push('\n');
+            } else {
+                output.extend(std::iter::repeat_n(' ', c.len_utf8()));
+            }
+        }
+        rest = remaining;
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Position of a style rule given its prelude and body, slices of `stylesheet`
+fn rule_source(stylesheet: &str, prelude: &str, body: &str) -> RuleSource {
+    let offset = |slice: &str| slice.as_ptr() as usize - stylesheet.as_ptr() as usize;
+    let start = offset(prelude);
+    // The body is followed by the closing brace
+    let end = offset(body) + body.len() + 1;
+    RuleSource {
+        line: stylesheet[..start].matches('\n').count() + 1,
+        length: end - start,
+    }
+}
+
+/// Split a block into declarations and nested blocks
+fn split_block_items(source: &str) -> Result<Vec<BlockItem<'_>>> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut paren_depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    let mut block_start = 0;
+
+    for (i, c) in source.char_indices() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' => paren_depth += 1,
+            ')' => paren_depth = paren_depth.saturating_sub(1),
+            '{' if paren_depth == 0 => {
+                if depth == 0 {
+                    block_start = i;
+                }
+                depth += 1;
+            }
+            '}' if paren_depth == 0 => {
+                if depth == 0 {
+                    return Err(Error::ParseError("Unexpected '}'".to_string()));
+                }
+                depth -= 1;
+                if depth == 0 {
+                    items.push(BlockItem::Block(source[start..block_start].trim(), &source[block_start + 1..i]));
+                    start = i + 1;
+                }
+            }
+            ';' if depth == 0 && paren_depth == 0 => {
+                let statement = source[start..i].trim();
+                if !statement.is_empty() {
+                    items.push(BlockItem::Statement(statement));
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if depth > 0 {
+        return Err(Error::ParseError("Unterminated block".to_string()));
+    }
+    let trailing = source[start..].trim();
+    if !trailing.is_empty() {
+        items.push(BlockItem::Statement(trailing));
+    }
+
+    Ok(items)
+}
+
+/// Split text at a separator outside parentheses
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            c if c == separator && depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css_selector::Specificity;
+    use crate::cssom::{CascadeCriterion, ComputedStyle, CssCascade};
+    use crate::dom::Element;
+
+    fn element(tag_name: &str, id: &str, class: Option<&str>) -> Element {
+        let mut element = Element::new(tag_name.to_string());
+        element.id = id.to_string();
+        if let Some(class) = class {
+            element.set_attribute("class".to_string(), class.to_string());
+        }
+        element
+    }
+
+    #[test]
+    fn test_nested_rule_matches_both_levels() {
+        let mut parser = CssParser::new();
+        let stylesheet = parser.parse_stylesheet("div { color: blue; & .inner { color: red } }").unwrap();
+
+        let CssRuleVariant::StyleRule(rule) = &stylesheet.rules()[0] else {
+            panic!("expected a style rule");
+        };
+        assert_eq!(rule.declarations().len(), 1);
+        assert_eq!(rule.nested_rules().len(), 1);
+        assert_eq!(parser.selector_index().get_all_selectors().len(), 2);
+
+        let expected_red = parser.parse_declaration("color: red").unwrap().value;
+        let expected_blue = parser.parse_declaration("color: blue").unwrap().value;
+
+        let mut cascade = CssCascade::new();
+        cascade.add_stylesheet(stylesheet);
+
+        let div = element("div", "outer", None);
+        let inner = element("span", "inner", Some("inner"));
+
+        let div_style = cascade.compute(&div).unwrap();
+        assert_eq!(div_style.get("color").unwrap().value(), &expected_blue);
+
+        let inner_style = cascade.compute_with_ancestors(&inner, &[&div], &div_style).unwrap();
+        assert_eq!(inner_style.get("color").unwrap().value(), &expected_red);
+
+        // Without a matching ancestor the nested rule doesn't apply
+        let orphan_style = cascade.compute_with_ancestors(&inner, &[], &ComputedStyle::default()).unwrap();
+        assert!(orphan_style.get("color").is_none());
+    }
+
+    #[test]
+    fn test_nested_selector_specificity() {
+        let mut parser = CssParser::new();
+        let stylesheet = parser.parse_stylesheet("div { &.active { color: red } & > p { color: blue } }").unwrap();
+
+        let CssRuleVariant::StyleRule(rule) = &stylesheet.rules()[0] else {
+            panic!("expected a style rule");
+        };
+        let specificities: Vec<_> = rule.nested_rules().iter()
+            .map(|nested| nested.resolved_selectors()[0].specificity())
+            .collect();
+        assert_eq!(specificities, vec![Specificity::new(0, 1, 1, 0), Specificity::new(0, 0, 2, 0)]);
+    }
+
+    #[test]
+    fn test_layer_order_beats_specificity() {
+        let mut parser = CssParser::new();
+        let stylesheet = parser.parse_stylesheet(
+            "@layer base, theme;
+             @layer theme { p { color: blue; margin: 2px !important } }
+             @layer base { #intro.lead { color: red; margin: 1px !important } }
+             p { padding: 3px }
+             #intro { padding: 4px }",
+        ).unwrap();
+
+        let expected_blue = parser.parse_declaration("color: blue").unwrap().value;
+        let expected_margin = parser.parse_declaration("margin: 1px").unwrap().value;
+        let expected_padding = parser.parse_declaration("padding: 4px").unwrap().value;
+
+        let mut cascade = CssCascade::new();
+        cascade.add_stylesheet(stylesheet);
+        assert_eq!(cascade.layer_order(), ["base", "theme"]);
+
+        let mut paragraph = element("p", "intro", Some("lead"));
+        paragraph.set_attribute("id".to_string(), "intro".to_string());
+        let style = cascade.compute(&paragraph).unwrap();
+
+        // The later layer wins even though the earlier one is more specific
+        assert_eq!(style.get("color").unwrap().value(), &expected_blue);
+        // Important declarations reverse the layer order
+        assert_eq!(style.get("margin").unwrap().value(), &expected_margin);
+        // Unlayered rules still compare by specificity among themselves
+        assert_eq!(style.get("padding").unwrap().value(), &expected_padding);
+
+        // The resolution chain lists the winner first and why each other declaration lost
+        let color = &style.resolution_chains["color"];
+        assert_eq!((color[0].selector.as_str(), color[0].layer.as_deref(), color[0].lost_by), ("p", Some("theme"), None));
+        assert_eq!((color[1].selector.as_str(), color[1].lost_by), ("#intro.lead", Some(CascadeCriterion::LayerOrder)));
+        let padding = &style.resolution_chains["padding"];
+        assert_eq!(padding[0].specificity, Specificity::new(1, 0, 0, 0));
+        assert_eq!(padding[1].lost_by, Some(CascadeCriterion::Specificity));
+        assert_eq!(style.resolution_chains["margin"][1].lost_by, Some(CascadeCriterion::LayerOrder));
+    }
+
+    #[test]
+    fn test_unlayered_rules_beat_layers() {
+        let mut parser = CssParser::new();
+        let stylesheet = parser.parse_stylesheet(
+            "p { color: green } @layer components { @layer card { p.card { color: red } } p.card { color: blue } }",
+        ).unwrap();
+
+        let mut cascade = CssCascade::new();
+        cascade.add_stylesheet(stylesheet);
+        assert_eq!(cascade.layer_order(), ["components", "components.card"]);
+
+        let paragraph = element("p", "p1", Some("card"));
+        let expected_green = parser.parse_declaration("color: green").unwrap().value;
+        let style = cascade.compute(&paragraph).unwrap();
+        assert_eq!(style.get("color").unwrap().value(), &expected_green);
+
+        // Within a layer its own rules win over its sublayers
+        let stylesheet = parser.parse_stylesheet(
+            "@layer components { @layer card { p.card { color: red } } p { color: blue } }",
+        ).unwrap();
+        let mut cascade = CssCascade::new();
+        cascade.add_stylesheet(stylesheet);
+        let expected_blue = parser.parse_declaration("color: blue").unwrap().value;
+        let style = cascade.compute(&paragraph).unwrap();
+        assert_eq!(style.get("color").unwrap().value(), &expected_blue);
+    }
+
+    #[test]
+    fn test_rule_sources_and_match_observer() {
+        use crate::cssom::RuleMatchObserver;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(usize, usize)>>);
+
+        impl RuleMatchObserver for Recorder {
+            fn rule_matched(&self, stylesheet_index: usize, rule_index: usize) {
+                self.0.lock().unwrap().push((stylesheet_index, rule_index));
+            }
+        }
+
+        let css = "/* header\n comment */\np { color: red }\n@layer base {\n  .card { margin: 0 }\n}\ndiv { & .lead { color: blue } }";
+        let mut parser = CssParser::new();
+        let stylesheet = parser.parse_stylesheet(css).unwrap();
+        let sources: Vec<_> = stylesheet.style_rules().iter()
+            .map(|rule| rule.source.map(|source| (source.line, source.length)))
+            .collect();
+        assert_eq!(sources, [Some((3, 16)), Some((5, 19)), Some((7, 31))]);
+
+        let recorder = Arc::new(Recorder::default());
+        let mut cascade = CssCascade::new();
+        cascade.add_stylesheet(stylesheet);
+        cascade.set_rule_observer(Some(recorder.clone()));
+
+        // The `div` rule counts through its nested rule only
+        let div = element("div", "outer", None);
+        let lead = element("span", "lead", Some("lead"));
+        cascade.compute(&element("p", "p1", None)).unwrap();
+        cascade.compute_with_ancestors(&lead, &[&div], &ComputedStyle::default()).unwrap();
+        assert_eq!(*recorder.0.lock().unwrap(), [(0, 0), (0, 2)]);
+
+        cascade.set_rule_observer(None);
+        cascade.compute(&element("p", "p2", None)).unwrap();
+        assert_eq!(recorder.0.lock().unwrap().len(), 2);
+    }
+}
@@ -4,6 +4,8 @@
 //! including simple selectors, compound selectors, complex selectors, and
 //! selector lists with proper specificity calculation.
 
+use std::fmt;
+
 use crate::error::{Error, Result};
 use crate::css_tokenizer::{CssToken, CssTokenizer};
 
@@ -74,8 +76,8 @@ pub enum PseudoClass {
     Is(Box<SimpleSelector>),
     /// :where(selector)
     Where(Box<SimpleSelector>),
-    /// :has(selector)
-    Has(Box<SimpleSelector>),
+    /// :has(relative selector list)
+    Has(Box<SelectorList>),
     /// Custom pseudo-class
     Custom(String),
 }
@@ -269,7 +271,7 @@ impl ComplexSelector {
 }
 
 /// CSS selector list
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SelectorList {
     /// Complex selectors in the list
     pub selectors: Vec<ComplexSelector>,
@@ -295,6 +297,123 @@ impl SelectorList {
     }
 }
 
+impl fmt::Display for PseudoClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PseudoClass::Hover => write!(f, ":hover"),
+            PseudoClass::Active => write!(f, ":active"),
+            PseudoClass::Focus => write!(f, ":focus"),
+            PseudoClass::Visited => write!(f, ":visited"),
+            PseudoClass::Link => write!(f, ":link"),
+            PseudoClass::FirstChild => write!(f, ":first-child"),
+            PseudoClass::LastChild => write!(f, ":last-child"),
+            PseudoClass::NthChild(n) => write!(f, ":nth-child({})", n),
+            PseudoClass::NthLastChild(n) => write!(f, ":nth-last-child({})", n),
+            PseudoClass::Not(selector) => write!(f, ":not({})", selector),
+            PseudoClass::Is(selector) => write!(f, ":is({})", selector),
+            PseudoClass::Where(selector) => write!(f, ":where({})", selector),
+            PseudoClass::Has(selectors) => write!(f, ":has({})", selectors),
+            PseudoClass::Custom(name) => write!(f, ":{}", name),
+        }
+    }
+}
+
+impl fmt::Display for PseudoElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PseudoElement::Before => write!(f, "::before"),
+            PseudoElement::After => write!(f, "::after"),
+            PseudoElement::FirstLine => write!(f, "::first-line"),
+            PseudoElement::FirstLetter => write!(f, "::first-letter"),
+            PseudoElement::Selection => write!(f, "::selection"),
+            PseudoElement::Custom(name) => write!(f, "::{}", name),
+        }
+    }
+}
+
+impl fmt::Display for AttributeSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operator = match self.operator {
+            AttributeOperator::Exists => return write!(f, "[{}]", self.name),
+            AttributeOperator::Equals => "=",
+            AttributeOperator::ContainsWord => "~=",
+            AttributeOperator::StartsWith => "|=",
+            AttributeOperator::StartsWithPrefix => "^=",
+            AttributeOperator::EndsWith => "$=",
+            AttributeOperator::Contains => "*=",
+        };
+        let value = self.value.as_deref().unwrap_or_default().replace('\\', "\\\\").replace('"', "\\\"");
+        let flag = if self.case_sensitive { "" } else { " i" };
+        write!(f, "[{}{}\"{}\"{}]", self.name, operator, value, flag)
+    }
+}
+
+impl fmt::Display for SimpleSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.element_type {
+            Some(element_type) => write!(f, "{}", element_type)?,
+            None if self.specificity().universal_count > 0 => write!(f, "*")?,
+            None => {}
+        }
+        if let Some(id) = &self.id {
+            write!(f, "#{}", id)?;
+        }
+        for class in &self.classes {
+            write!(f, ".{}", class)?;
+        }
+        for attribute in &self.attributes {
+            write!(f, "{}", attribute)?;
+        }
+        for pseudo_class in &self.pseudo_classes {
+            write!(f, "{}", pseudo_class)?;
+        }
+        for pseudo_element in &self.pseudo_elements {
+            write!(f, "{}", pseudo_element)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CompoundSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.simple)
+    }
+}
+
+impl fmt::Display for ComplexSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, part) in self.parts.iter().enumerate() {
+            match part {
+                ComplexSelectorPart::Compound(compound) => write!(f, "{}", compound)?,
+                // A leading descendant combinator of a relative selector is implied
+                ComplexSelectorPart::Combinator(Combinator::Descendant) if index == 0 => {}
+                ComplexSelectorPart::Combinator(combinator) => {
+                    let symbol = match combinator {
+                        Combinator::Descendant => " ",
+                        Combinator::Child => " > ",
+                        Combinator::AdjacentSibling => " + ",
+                        Combinator::GeneralSibling => " ~ ",
+                    };
+                    f.write_str(if index == 0 { symbol.trim_start() } else { symbol })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SelectorList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, selector) in self.selectors.iter().enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", selector)?;
+        }
+        Ok(())
+    }
+}
+
 /// CSS Selector Parser
 pub struct CssSelectorParser {
     /// Tokenizer for parsing CSS
@@ -529,15 +648,25 @@ impl CssSelectorParser {
     
     /// Parse pseudo-class
     fn parse_pseudo_class(&mut self) -> Result<PseudoClass> {
-        let name = {
-            if let Some(&CssToken::Ident(ref pseudo_name)) = self.peek_token() {
-                let name = pseudo_name.clone();
-                self.consume_token();
-                name
-            } else {
-                return Err(Error::ConfigError("Expected pseudo-class name".to_string()));
-            }
+        // Functional pseudo-classes are tokenized as functions with the '(' already consumed
+        let (name, opened) = match self.peek_token() {
+            Some(CssToken::Ident(pseudo_name)) => (pseudo_name.clone(), false),
+            Some(CssToken::Function(pseudo_name)) => (pseudo_name.clone(), true),
+            _ => return Err(Error::ConfigError("Expected pseudo-class name".to_string())),
         };
+        self.consume_token();
+        
+        if name == "has" {
+            if !opened {
+                return Err(Error::ConfigError("Expected '(' for functional pseudo-class".to_string()));
+            }
+            let selectors = self.parse_relative_selector_list()?;
+            return if self.consume_close_paren() {
+                Ok(PseudoClass::Has(Box::new(selectors)))
+            } else {
+                Err(Error::ConfigError("Expected ')' to close pseudo-class".to_string()))
+            };
+        }
         
         match name.as_str() {
             "hover" => Ok(PseudoClass::Hover),
@@ -547,19 +676,16 @@ impl CssSelectorParser {
             "link" => Ok(PseudoClass::Link),
             "first-child" => Ok(PseudoClass::FirstChild),
             "last-child" => Ok(PseudoClass::LastChild),
-            "not" | "is" | "where" | "has" => {
+            "not" | "is" | "where" => {
                 // Parse functional pseudo-class
-                if self.peek_token() == Some(&CssToken::Delim('(')) {
-                    self.consume_token(); // Consume '('
+                if opened || self.consume_open_paren() {
                     let selector = self.parse_simple_selector()?;
                     
-                    if self.peek_token() == Some(&CssToken::Delim(')')) {
-                        self.consume_token(); // Consume ')'
+                    if self.consume_close_paren() {
                         match name.as_str() {
                             "not" => Ok(PseudoClass::Not(Box::new(selector))),
                             "is" => Ok(PseudoClass::Is(Box::new(selector))),
                             "where" => Ok(PseudoClass::Where(Box::new(selector))),
-                            "has" => Ok(PseudoClass::Has(Box::new(selector))),
                             _ => unreachable!(),
                         }
                     } else {
@@ -572,12 +698,10 @@ impl CssSelectorParser {
             _ => {
                 // Check for nth-child patterns
                 if name.starts_with("nth-child") || name.starts_with("nth-last-child") {
-                    if self.peek_token() == Some(&CssToken::Delim('(')) {
-                        self.consume_token(); // Consume '('
+                    if opened || self.consume_open_paren() {
                         let value = self.parse_nth_value()?;
                         
-                        if self.peek_token() == Some(&CssToken::Delim(')')) {
-                            self.consume_token(); // Consume ')'
+                        if self.consume_close_paren() {
                             match name.as_str() {
                                 "nth-child" => Ok(PseudoClass::NthChild(value)),
                                 "nth-last-child" => Ok(PseudoClass::NthLastChild(value)),
@@ -618,6 +742,55 @@ impl CssSelectorParser {
         }
     }
     
+    /// Parse a relative selector list, as used by `:has()`
+    ///
+    /// Each selector may start with a combinator; without one the descendant
+    /// combinator is implied.
+    fn parse_relative_selector_list(&mut self) -> Result<SelectorList> {
+        let mut selector_list = SelectorList::new();
+        
+        loop {
+            self.skip_whitespace();
+            
+            let mut relative = ComplexSelector::new();
+            if let Some(combinator) = self.parse_combinator() {
+                relative.add_combinator(combinator);
+                self.skip_whitespace();
+            }
+            relative.parts.extend(self.parse_complex_selector()?.parts);
+            selector_list.add_selector(relative);
+            
+            if matches!(self.peek_token(), Some(CssToken::Comma) | Some(CssToken::Delim(','))) {
+                self.consume_token();
+                continue;
+            }
+            
+            break;
+        }
+        
+        Ok(selector_list)
+    }
+    
+    /// Consume an opening parenthesis if present
+    fn consume_open_paren(&mut self) -> bool {
+        if matches!(self.peek_token(), Some(CssToken::LeftParen) | Some(CssToken::Delim('('))) {
+            self.consume_token();
+            true
+        } else {
+            false
+        }
+    }
+    
+    /// Consume a closing parenthesis if present
+    fn consume_close_paren(&mut self) -> bool {
+        if matches!(self.peek_token(), Some(CssToken::RightParen) | Some(CssToken::Delim(')'))) {
+            self.consume_token();
+            true
+        } else {
+            false
+        }
+    }
+    
     /// Parse combinator
     fn parse_combinator(&mut self) -> Option<Combinator> {
         match self.peek_token() {
@@ -830,4 +1003,21 @@ mod tests {
             panic!("Expected compound selector");
         }
     }
+
+    #[test]
+    fn test_selector_to_string() {
+        for source in ["div#main.note > p", "a:first-child ~ p::before", "*", "section:has(> img)"] {
+            let mut parser = CssSelectorParser::new(source).unwrap();
+            assert_eq!(parser.parse_selector_list().unwrap().to_string(), source);
+        }
+
+        let mut selector = SimpleSelector::new();
+        selector.attributes.push(AttributeSelector {
+            name: "title".to_string(),
+            operator: AttributeOperator::StartsWithPrefix,
+            value: Some("say \"hi\"".to_string()),
+            case_sensitive: false,
+        });
+        assert_eq!(selector.to_string(), "[title^=\"say \\\"hi\\\"\" i]");
+    }
 }
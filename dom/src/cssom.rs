@@ -3,9 +3,14 @@
 //! This module provides the CSS Object Model for managing CSS rules,
 //! stylesheets, and computed values according to the CSS specification.
 
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use crate::error::{Error, Result};
-use crate::css_selector::SelectorList;
+use crate::css_selector::{ComplexSelector, ComplexSelectorPart, CompoundSelector, Combinator, SelectorList, Specificity};
 use crate::css_at_rules::AtRule;
+use crate::animation::AnimationEngine;
+use crate::dom::Element;
+use crate::selector_matching::SelectorMatcher;
 
 /// CSS rule types
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -36,6 +41,10 @@ pub enum CssRuleType {
     FontFeatureValues,
     /// Region-style rule (e.g., `@region-style { ... }`)
     RegionStyle,
+    /// Cascade layer rule (e.g., `@layer base { ... }`)
+    Layer,
+    /// Container rule (e.g., `@container sidebar (width < 400px) { ... }`)
+    Container,
 }
 
 /// CSS property value types
@@ -176,6 +185,16 @@ impl CssDeclaration {
     pub fn is_important(&self) -> bool {
         self.important
     }
+    
+    /// Check if the declaration sets a custom property (`--name`)
+    pub fn is_custom_property(&self) -> bool {
+        is_custom_property_name(&self.property)
+    }
+}
+
+/// Check if a property name is a custom property name (`--name`)
+pub fn is_custom_property_name(property: &str) -> bool {
+    property.len() > 2 && property.starts_with("--")
 }
 
 /// CSS style rule
@@ -185,8 +204,21 @@ pub struct CssStyleRule {
     pub selectors: SelectorList,
     /// Declarations in this rule
     pub declarations: Vec<CssDeclaration>,
+    /// Rules nested inside this rule with CSS Nesting
+    pub nested_rules: Vec<CssRuleVariant>,
     /// Rule type
     pub rule_type: CssRuleType,
+    /// Where the rule appears in its stylesheet source, if it was parsed
+    pub source: Option<RuleSource>,
+}
+
+/// Position of a rule in its stylesheet source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleSource {
+    /// Line of the rule's selector, starting at 1
+    pub line: usize,
+    /// Length of the rule in bytes, from its selector to its closing brace
+    pub length: usize,
 }
 
 impl CssStyleRule {
@@ -195,7 +227,9 @@ impl CssStyleRule {
         Self {
             selectors,
             declarations: Vec::new(),
+            nested_rules: Vec::new(),
             rule_type: CssRuleType::Style,
+            source: None,
         }
     }
     
@@ -209,6 +243,16 @@ impl CssStyleRule {
         &self.declarations
     }
     
+    /// Add a nested rule to this rule
+    pub fn add_nested_rule(&mut self, rule: CssRuleVariant) {
+        self.nested_rules.push(rule);
+    }
+    
+    /// Get all nested rules
+    pub fn nested_rules(&self) -> &[CssRuleVariant] {
+        &self.nested_rules
+    }
+    
     /// Get a specific declaration by property name
     pub fn get_declaration(&self, property: &str) -> Option<&CssDeclaration> {
         self.declarations.iter().find(|d| d.property == property)
@@ -260,6 +304,26 @@ pub enum CssRuleVariant {
     StyleRule(CssStyleRule),
     /// At-rule
     AtRule(AtRule),
+    /// Style rule nested inside another style rule
+    ///
+    /// Each child selector is relative to the parent selector: a leading
+    /// combinator relates it to the element matched by the parent, and
+    /// without one it further qualifies that element (`&.active`).
+    Nested {
+        /// Selector of the enclosing rule
+        parent_selector: SelectorList,
+        /// Selector relative to the enclosing rule
+        child_selector: SelectorList,
+        /// Declarations in this rule
+        declarations: Vec<CssDeclaration>,
+    },
+    /// Cascade layer (`@layer name { ... }`, or `@layer name;` with no rules)
+    Layer {
+        /// Layer name, with sublayers separated by dots
+        name: String,
+        /// Rules in the layer
+        rules: Vec<CssRuleVariant>,
+    },
 }
 
 impl CssRule for CssStyleRule {
@@ -316,7 +380,7 @@ impl CssRuleVariant {
             CssRuleVariant::AtRule(at_rule) => match at_rule {
                 AtRule::Import { .. } => CssRuleType::Import,
                 AtRule::Media { .. } => CssRuleType::Media,
-                AtRule::FontFace { .. } => CssRuleType::FontFace,
+                AtRule::FontFace(_) => CssRuleType::FontFace,
                 AtRule::Keyframes { .. } => CssRuleType::Keyframes,
                 AtRule::Page { .. } => CssRuleType::Page,
                 AtRule::Supports { .. } => CssRuleType::Supports,
@@ -326,7 +390,10 @@ impl CssRuleVariant {
                 AtRule::Document { .. } => CssRuleType::Document,
                 AtRule::CounterStyle { .. } => CssRuleType::CounterStyle,
                 AtRule::FontFeatureValues { .. } => CssRuleType::FontFeatureValues,
+                AtRule::Container { .. } => CssRuleType::Container,
             },
+            CssRuleVariant::Nested { .. } => CssRuleType::Style,
+            CssRuleVariant::Layer { .. } => CssRuleType::Layer,
         }
     }
     
@@ -351,9 +418,9 @@ impl CssRuleVariant {
                     css.push_str(" }");
                     css
                 }
-                AtRule::FontFace { declarations } => {
+                AtRule::FontFace(declaration) => {
                     let mut css = "@font-face {".to_string();
-                    for (property, value) in declarations {
+                    for (property, value) in &declaration.descriptors {
                         css.push_str(&format!(" {}: {};", property, value));
                     }
                     css.push_str(" }");
@@ -435,9 +502,99 @@ impl CssRuleVariant {
                     css.push_str(" }");
                     css
                 }
+                AtRule::Container { name, condition, rules } => {
+                    let mut css = match name {
+                        Some(name) => format!("@container {} {} {{", name, condition),
+                        None => format!("@container {} {{", condition),
+                    };
+                    for rule in rules {
+                        css.push_str(&format!(" {}", rule.css_text()));
+                    }
+                    css.push_str(" }");
+                    css
+                }
             },
+            CssRuleVariant::Nested { declarations, .. } => {
+                let mut css = "& {".to_string();
+                for declaration in declarations {
+                    css.push_str(&format!(" {}: value;", declaration.property));
+                }
+                css.push_str(" }");
+                css
+            }
+            CssRuleVariant::Layer { name, rules } if rules.is_empty() => format!("@layer {};", name),
+            CssRuleVariant::Layer { name, rules } => {
+                let mut css = if name.is_empty() {
+                    "@layer {".to_string()
+                } else {
+                    format!("@layer {} {{", name)
+                };
+                for rule in rules {
+                    css.push_str(&format!(" {}", rule.css_text()));
+                }
+                css.push_str(" }");
+                css
+            }
         }
     }
+    
+    /// Selectors of a nested rule with the parent selector prepended
+    pub fn resolved_selectors(&self) -> Vec<ComplexSelector> {
+        match self {
+            CssRuleVariant::StyleRule(rule) => rule.selectors.selectors.clone(),
+            CssRuleVariant::Nested { parent_selector, child_selector, .. } => parent_selector.selectors.iter()
+                .flat_map(|parent| child_selector.selectors.iter().map(move |child| resolve_nested_selector(parent, child)))
+                .collect(),
+            CssRuleVariant::AtRule(_) | CssRuleVariant::Layer { .. } => Vec::new(),
+        }
+    }
+}
+
+/// Collect the style rules in a list of rules with the full name of their layer
+fn collect_style_rules<'a>(rules: &'a [CssRuleVariant], layer: Option<&str>, out: &mut Vec<(&'a CssStyleRule, Option<String>)>) {
+    for rule in rules {
+        match rule {
+            CssRuleVariant::StyleRule(style_rule) => out.push((style_rule, layer.map(str::to_string))),
+            CssRuleVariant::Layer { name, rules } => {
+                let name = name.split('.').map(str::trim).collect::<Vec<_>>().join(".");
+                let full_name = match layer {
+                    Some(parent) => format!("{}.{}", parent, name),
+                    None => name,
+                };
+                collect_style_rules(rules, Some(&full_name), out);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Prepend a parent selector to a selector relative to it
+///
+/// A child that starts with a combinator is joined to the parent with it;
+/// otherwise its first compound is merged into the parent's last compound.
+pub fn resolve_nested_selector(parent: &ComplexSelector, child: &ComplexSelector) -> ComplexSelector {
+    let mut resolved = parent.clone();
+    let mut parts = child.parts.iter();
+    
+    if let (Some(ComplexSelectorPart::Compound(first)), Some(ComplexSelectorPart::Compound(last))) =
+        (child.parts.first(), resolved.parts.last_mut())
+    {
+        parts.next();
+        let simple = &first.simple;
+        if last.simple.element_type.is_none() {
+            last.simple.element_type = simple.element_type.clone();
+        }
+        if last.simple.id.is_none() {
+            last.simple.id = simple.id.clone();
+        }
+        last.simple.classes.extend(simple.classes.iter().cloned());
+        last.simple.attributes.extend(simple.attributes.iter().cloned());
+        last.simple.pseudo_classes.extend(simple.pseudo_classes.iter().cloned());
+        last.simple.pseudo_elements.extend(simple.pseudo_elements.iter().cloned());
+    }
+    
+    resolved.parts.extend(parts.cloned());
+    resolved
 }
 
 /// CSS stylesheet
@@ -508,6 +665,15 @@ impl CssStyleSheet {
         &self.rules
     }
     
+    /// Get the style rules, including those in cascade layers, in source order
+    ///
+    /// Positions in this list identify rules to a `RuleMatchObserver`.
+    pub fn style_rules(&self) -> Vec<&CssStyleRule> {
+        let mut rules = Vec::new();
+        collect_style_rules(&self.rules, None, &mut rules);
+        rules.into_iter().map(|(rule, _)| rule).collect()
+    }
+    
     /// Check if the stylesheet is disabled
     pub fn is_disabled(&self) -> bool {
         self.disabled
@@ -586,10 +752,259 @@ impl ComputedValue {
     }
 }
 
+/// Result of substituting `var()` references in a value
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolvedValue {
+    /// The value with all references substituted
+    Value(CssValue),
+    /// A reference could not be resolved (missing without fallback, or circular)
+    Invalid,
+}
+
+impl ComputedValue {
+    /// Substitute `var()` references using the custom properties in effect for an element
+    pub fn resolve_variables(&self, element: &Element, inherited: &CustomPropertyMap) -> Result<ResolvedValue> {
+        let resolved = substitute_variables(&self.value, &mut |name| inherited.get(name).cloned())
+            .map_err(|e| Error::CssError(format!("{} on <{}>", e, element.tag_name)))?;
+        
+        Ok(match resolved {
+            Some(value) => ResolvedValue::Value(value),
+            None => ResolvedValue::Invalid,
+        })
+    }
+}
+
+/// Custom properties in effect for an element
+///
+/// Values are stored with their own `var()` references already substituted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CustomPropertyMap {
+    /// Values by custom property name
+    properties: HashMap<String, CssValue>,
+}
+
+impl CustomPropertyMap {
+    /// Create an empty custom property map
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Build an element's map from its own declarations and its parent's map
+    ///
+    /// Custom properties that take part in a reference cycle, or that refer to
+    /// missing properties without a fallback, are invalid and left out.
+    pub fn cascade(declarations: &[&CssDeclaration], parent: &CustomPropertyMap) -> Self {
+        let declared: HashMap<&str, &CssValue> = declarations.iter()
+            .filter(|declaration| declaration.is_custom_property())
+            .map(|declaration| (declaration.property.as_str(), &declaration.value))
+            .collect();
+        
+        let mut resolver = CustomPropertyResolver {
+            declared: &declared,
+            parent,
+            resolved: HashMap::new(),
+            stack: Vec::new(),
+            cyclic: HashSet::new(),
+        };
+        for name in declared.keys() {
+            resolver.resolve(name);
+        }
+        
+        let mut map = parent.clone();
+        for (name, value) in resolver.resolved {
+            match value {
+                Some(value) => map.properties.insert(name, value),
+                None => map.properties.remove(&name),
+            };
+        }
+        map
+    }
+    
+    /// Get the value of a custom property
+    pub fn get(&self, name: &str) -> Option<&CssValue> {
+        self.properties.get(name)
+    }
+    
+    /// Set the value of a custom property
+    pub fn insert(&mut self, name: String, value: CssValue) {
+        self.properties.insert(name, value);
+    }
+    
+    /// Get the number of custom properties
+    pub fn len(&self) -> usize {
+        self.properties.len()
+    }
+    
+    /// Check if there are no custom properties
+    pub fn is_empty(&self) -> bool {
+        self.properties.is_empty()
+    }
+}
+
+/// Resolves an element's custom property declarations, detecting cycles
+struct CustomPropertyResolver<'a> {
+    /// Custom properties declared on the element
+    declared: &'a HashMap<&'a str, &'a CssValue>,
+    /// Custom properties inherited from the parent
+    parent: &'a CustomPropertyMap,
+    /// Resolved declarations (`None` if invalid)
+    resolved: HashMap<String, Option<CssValue>>,
+    /// Declarations currently being resolved
+    stack: Vec<String>,
+    /// Declarations that are part of a reference cycle
+    cyclic: HashSet<String>,
+}
+
+impl CustomPropertyResolver<'_> {
+    fn resolve(&mut self, name: &str) -> Option<CssValue> {
+        let Some(value) = self.declared.get(name).map(|value| (*value).clone()) else {
+            return self.parent.get(name).cloned();
+        };
+        if let Some(resolved) = self.resolved.get(name) {
+            return resolved.clone();
+        }
+        if let Some(position) = self.stack.iter().position(|entry| entry == name) {
+            self.cyclic.extend(self.stack[position..].iter().cloned());
+            return None;
+        }
+        
+        self.stack.push(name.to_string());
+        let resolved = substitute_variables(&value, &mut |reference| self.resolve(reference)).ok().flatten();
+        self.stack.pop();
+        
+        let resolved = if self.cyclic.contains(name) { None } else { resolved };
+        self.resolved.insert(name.to_string(), resolved.clone());
+        resolved
+    }
+}
+
+/// Substitute `var(--name, fallback)` references in a value
+///
+/// Returns `Ok(None)` if a reference is missing and has no usable fallback.
+fn substitute_variables(value: &CssValue, lookup: &mut dyn FnMut(&str) -> Option<CssValue>) -> Result<Option<CssValue>> {
+    match value {
+        CssValue::Function(name, args) if name.eq_ignore_ascii_case("var") => {
+            let (reference, fallback) = match args.split_first() {
+                Some((CssValue::Keyword(reference), fallback)) if is_custom_property_name(reference) => (reference, fallback),
+                _ => return Err(Error::CssError("Invalid var() reference".to_string())),
+            };
+            
+            if let Some(value) = lookup(reference) {
+                return Ok(Some(value));
+            }
+            match fallback {
+                [] => Ok(None),
+                [fallback] => substitute_variables(fallback, lookup),
+                fallback => substitute_variables(&CssValue::List(fallback.to_vec()), lookup),
+            }
+        }
+        CssValue::Function(name, args) => {
+            let args = args.iter()
+                .map(|arg| substitute_variables(arg, lookup))
+                .collect::<Result<Option<Vec<_>>>>()?;
+            Ok(args.map(|args| CssValue::Function(name.clone(), args)))
+        }
+        CssValue::List(items) => {
+            let items = items.iter()
+                .map(|item| substitute_variables(item, lookup))
+                .collect::<Result<Option<Vec<_>>>>()?;
+            Ok(items.map(CssValue::List))
+        }
+        value => Ok(Some(value.clone())),
+    }
+}
+
+/// Computed style of an element
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComputedStyle {
+    /// Custom properties in effect, including inherited ones
+    pub custom_properties: CustomPropertyMap,
+    /// Cascaded values of the element's own properties, with `var()` substituted
+    pub properties: HashMap<String, ComputedValue>,
+    /// Declarations of each property that applied to the element, the winning one first
+    pub resolution_chains: HashMap<String, Vec<CascadeEntry>>,
+}
+
+/// Cascade criterion that decided between two declarations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CascadeCriterion {
+    /// One declaration was `!important`
+    Importance,
+    /// The declarations were in different cascade layers
+    LayerOrder,
+    /// The selectors had different specificity
+    Specificity,
+    /// The declarations only differed in source order
+    SourceOrder,
+}
+
+/// A declaration that applied to an element, with its place in the cascade
+#[derive(Debug, Clone, PartialEq)]
+pub struct CascadeEntry {
+    /// The declaration
+    pub declaration: CssDeclaration,
+    /// Text of the matching selector with the highest specificity
+    pub selector: String,
+    /// Full name of the cascade layer, if any
+    pub layer: Option<String>,
+    /// Specificity of the matching selector
+    pub specificity: Specificity,
+    /// Position of the rule among the cascade's style rules
+    pub source_order: usize,
+    /// Criterion the declaration lost to the winning one by, `None` for the winner
+    pub lost_by: Option<CascadeCriterion>,
+}
+
+/// A matched declaration with its cascade priority
+struct Candidate<'a> {
+    declaration: &'a CssDeclaration,
+    selector: String,
+    layer: Option<String>,
+    /// Layer rank, reversed for important declarations
+    layer_rank: Vec<usize>,
+    specificity: Specificity,
+    order: usize,
+}
+
+impl Candidate<'_> {
+    /// The first cascade criterion that puts this candidate above another
+    fn wins_by(&self, other: &Candidate<'_>) -> CascadeCriterion {
+        if self.declaration.important != other.declaration.important {
+            CascadeCriterion::Importance
+        } else if self.layer_rank != other.layer_rank {
+            CascadeCriterion::LayerOrder
+        } else if self.specificity != other.specificity {
+            CascadeCriterion::Specificity
+        } else {
+            CascadeCriterion::SourceOrder
+        }
+    }
+}
+
+impl ComputedStyle {
+    /// Get the computed value of a property
+    pub fn get(&self, property: &str) -> Option<&ComputedValue> {
+        self.properties.get(property)
+    }
+}
+
+/// Receives the style rules a cascade matches
+pub trait RuleMatchObserver: Send + Sync {
+    /// Called for each style rule that applies to an element being computed
+    ///
+    /// `rule_index` is the position of the rule in `CssStyleSheet::style_rules`.
+    /// A rule also counts as matched when only one of its nested rules applies.
+    fn rule_matched(&self, stylesheet_index: usize, rule_index: usize);
+}
+
 /// CSS cascade manager
 pub struct CssCascade {
     /// Stylesheets in cascade order
     stylesheets: Vec<CssStyleSheet>,
+    /// Full names of cascade layers in the order they were first declared
+    layer_order: Vec<String>,
+    /// Observer notified of matched rules
+    rule_observer: Option<Arc<dyn RuleMatchObserver>>,
 }
 
 impl CssCascade {
@@ -597,11 +1012,19 @@ impl CssCascade {
     pub fn new() -> Self {
         Self {
             stylesheets: Vec::new(),
+            layer_order: Vec::new(),
+            rule_observer: None,
         }
     }
     
+    /// Set or clear the observer notified of matched rules
+    pub fn set_rule_observer(&mut self, observer: Option<Arc<dyn RuleMatchObserver>>) {
+        self.rule_observer = observer;
+    }
+    
     /// Add a stylesheet to the cascade
     pub fn add_stylesheet(&mut self, stylesheet: CssStyleSheet) {
+        self.declare_layers(&stylesheet.rules, None);
         self.stylesheets.push(stylesheet);
     }
     
@@ -610,6 +1033,56 @@ impl CssCascade {
         &self.stylesheets
     }
     
+    /// Get the cascade layers in declaration order
+    pub fn layer_order(&self) -> &[String] {
+        &self.layer_order
+    }
+    
+    /// Record the layers declared by a list of rules, including sublayers
+    fn declare_layers(&mut self, rules: &[CssRuleVariant], parent: Option<&str>) {
+        for rule in rules {
+            let CssRuleVariant::Layer { name, rules } = rule else {
+                continue;
+            };
+            
+            let mut full_name = parent.map(str::to_string);
+            for segment in name.split('.') {
+                let segment = segment.trim();
+                full_name = Some(match full_name {
+                    Some(prefix) => format!("{}.{}", prefix, segment),
+                    None => segment.to_string(),
+                });
+                let full_name = full_name.as_deref().unwrap_or_default();
+                if !self.layer_order.iter().any(|layer| layer == full_name) {
+                    self.layer_order.push(full_name.to_string());
+                }
+            }
+            
+            self.declare_layers(rules, full_name.as_deref());
+        }
+    }
+    
+    /// Cascade priority of a layer, compared lexicographically
+    ///
+    /// Each entry is the declaration index of one level of the layer name, so
+    /// sibling layers compare by declaration order. Rules placed directly in
+    /// a layer get `usize::MAX` after it and win over its sublayers, just as
+    /// unlayered rules (`[usize::MAX]`) win over every layer.
+    fn layer_rank(&self, layer: Option<&str>) -> Vec<usize> {
+        let mut rank = Vec::new();
+        if let Some(layer) = layer {
+            let mut end = 0;
+            for segment in layer.split('.') {
+                end += segment.len();
+                let prefix = &layer[..end];
+                end += 1;
+                rank.push(self.layer_order.iter().position(|name| name == prefix).unwrap_or(usize::MAX));
+            }
+        }
+        rank.push(usize::MAX);
+        rank
+    }
+    
     /// Compute the final value for a property on an element
     pub fn compute_property_value(&self, _element: &str, _property: &str) -> Option<ComputedValue> {
         // This is a placeholder implementation
@@ -621,6 +1094,175 @@ impl CssCascade {
         None
     }
     
+    /// Compute the style of a root element
+    pub fn compute(&self, element: &Element) -> Result<ComputedStyle> {
+        self.compute_with_parent(element, &ComputedStyle::default())
+    }
+    
+    /// Compute the style of an element, inheriting custom properties from its parent
+    pub fn compute_with_parent(&self, element: &Element, parent: &ComputedStyle) -> Result<ComputedStyle> {
+        self.compute_with_ancestors(element, &[], parent)
+    }
+    
+    /// Compute the style of an element given its ancestors, closest first
+    ///
+    /// The ancestors are needed to match nested rules whose child selector
+    /// relates to the parent selector with a combinator.
+    pub fn compute_with_ancestors(&self, element: &Element, ancestors: &[&Element], parent: &ComputedStyle) -> Result<ComputedStyle> {
+        let candidates = self.cascade_candidates(element, ancestors);
+        
+        // Later candidates win, so keep only the last one for each property
+        let mut resolution_chains: HashMap<String, Vec<CascadeEntry>> = HashMap::new();
+        let mut winners: HashMap<&str, &Candidate> = HashMap::new();
+        let mut declarations = Vec::new();
+        for candidate in candidates.iter().rev() {
+            let property = candidate.declaration.property.as_str();
+            let lost_by = match winners.get(property) {
+                Some(winner) => Some(winner.wins_by(candidate)),
+                None => {
+                    winners.insert(property, candidate);
+                    declarations.push(candidate.declaration);
+                    None
+                }
+            };
+            resolution_chains.entry(property.to_string()).or_default().push(CascadeEntry {
+                declaration: candidate.declaration.clone(),
+                selector: candidate.selector.clone(),
+                layer: candidate.layer.clone(),
+                specificity: candidate.specificity.clone(),
+                source_order: candidate.order,
+                lost_by,
+            });
+        }
+        declarations.reverse();
+        
+        let custom_properties = CustomPropertyMap::cascade(&declarations, &parent.custom_properties);
+        
+        let mut properties = HashMap::new();
+        for declaration in declarations.iter().filter(|declaration| !declaration.is_custom_property()) {
+            let specified = ComputedValue::new(declaration.value.clone(), false, true);
+            // Values that fail substitution are invalid at computed-value time and behave as `unset`
+            let value = match specified.resolve_variables(element, &custom_properties)? {
+                ResolvedValue::Value(value) => value,
+                ResolvedValue::Invalid => CssValue::Unset,
+            };
+            properties.insert(declaration.property.clone(), ComputedValue::new(value, false, true));
+        }
+        
+        Ok(ComputedStyle {
+            custom_properties,
+            properties,
+            resolution_chains,
+        })
+    }
+    
+    /// Declarations that apply to an element, in increasing cascade priority
+    ///
+    /// Declarations are ordered by importance, then cascade layer, then
+    /// specificity, then source order. Important declarations reverse the
+    /// layer order, so earlier layers win among them.
+    fn cascade_candidates<'a>(&'a self, element: &Element, ancestors: &[&Element]) -> Vec<Candidate<'a>> {
+        let mut candidates = Vec::new();
+        let mut rules = Vec::new();
+        for (stylesheet_index, stylesheet) in self.stylesheets.iter().enumerate().filter(|(_, stylesheet)| !stylesheet.is_disabled()) {
+            let mut stylesheet_rules = Vec::new();
+            collect_style_rules(stylesheet.rules(), None, &mut stylesheet_rules);
+            rules.extend(stylesheet_rules.into_iter().enumerate()
+                .map(|(rule_index, (rule, layer))| (stylesheet_index, rule_index, rule, layer)));
+        }
+        
+        let mut order = 0;
+        for (stylesheet_index, rule_index, rule, layer) in rules {
+            let mut rule_matched = false;
+            let layer_rank = self.layer_rank(layer.as_deref());
+            let matched = rule.selectors.selectors.iter()
+                .filter_map(|selector| {
+                    let mut matcher = SelectorMatcher::new();
+                    matcher.add_selector(selector.clone());
+                    let specificity = matcher.match_element(element).into_iter().map(|result| result.specificity).max()?;
+                    Some((specificity, selector))
+                })
+                .max_by(|a, b| a.0.cmp(&b.0));
+            
+            if let Some((specificity, selector)) = matched {
+                rule_matched = true;
+                let selector = selector.to_string();
+                for declaration in rule.declarations() {
+                    candidates.push(Candidate {
+                        declaration,
+                        selector: selector.clone(),
+                        layer: layer.clone(),
+                        layer_rank: layer_rank.clone(),
+                        specificity: specificity.clone(),
+                        order,
+                    });
+                }
+            }
+            order += 1;
+            
+            // Nested rules follow their parent, with the parent selector prepended to theirs
+            for nested in rule.nested_rules() {
+                let CssRuleVariant::Nested { declarations, .. } = nested else {
+                    continue;
+                };
+                let matched = nested.resolved_selectors().into_iter()
+                    .filter(|selector| matches_ancestor_chain(&selector.parts, element, ancestors))
+                    .max_by_key(ComplexSelector::specificity);
+                
+                if let Some(selector) = matched {
+                    rule_matched = true;
+                    let specificity = selector.specificity();
+                    let selector = selector.to_string();
+                    for declaration in declarations {
+                        candidates.push(Candidate {
+                            declaration,
+                            selector: selector.clone(),
+                            layer: layer.clone(),
+                            layer_rank: layer_rank.clone(),
+                            specificity: specificity.clone(),
+                            order,
+                        });
+                    }
+                }
+                order += 1;
+            }
+            
+            if rule_matched {
+                if let Some(observer) = &self.rule_observer {
+                    observer.rule_matched(stylesheet_index, rule_index);
+                }
+            }
+        }
+        
+        for candidate in &mut candidates {
+            if candidate.declaration.important {
+                for index in candidate.layer_rank.iter_mut() {
+                    *index = usize::MAX - *index;
+                }
+            }
+        }
+        candidates.sort_by(|a, b| {
+            (a.declaration.important, &a.layer_rank, &a.specificity, a.order)
+                .cmp(&(b.declaration.important, &b.layer_rank, &b.specificity, b.order))
+        });
+        candidates
+    }
+    
+    /// Pass the `@keyframes` rules of all enabled stylesheets to an animation engine
+    pub fn register_keyframes(&self, engine: &mut AnimationEngine) -> usize {
+        let mut count = 0;
+        let keyframes = self.stylesheets.iter()
+            .filter(|stylesheet| !stylesheet.is_disabled())
+            .flat_map(|stylesheet| stylesheet.rules());
+        for rule in keyframes {
+            if let CssRuleVariant::AtRule(AtRule::Keyframes { name, keyframes }) = rule {
+                engine.register_keyframes(name, keyframes);
+                count += 1;
+            }
+        }
+        count
+    }
+    
     /// Get all matching rules for an element
     pub fn get_matching_rules(&self, _element: &str) -> Vec<&CssStyleRule> {
         // This is a placeholder implementation
@@ -632,6 +1274,36 @@ impl CssCascade {
     }
 }
 
+/// Match the parts of a complex selector against an element and its ancestors, closest first
+///
+/// Sibling combinators need the rest of the tree, so they never match here.
+fn matches_ancestor_chain(parts: &[ComplexSelectorPart], element: &Element, ancestors: &[&Element]) -> bool {
+    let Some((ComplexSelectorPart::Compound(compound), rest)) = parts.split_last() else {
+        return false;
+    };
+    if !matches_compound(element, compound) {
+        return false;
+    }
+    
+    match rest.split_last() {
+        None => true,
+        Some((ComplexSelectorPart::Combinator(Combinator::Child), rest)) => ancestors.split_first()
+            .is_some_and(|(parent, above)| matches_ancestor_chain(rest, parent, above)),
+        Some((ComplexSelectorPart::Combinator(Combinator::Descendant), rest)) => (0..ancestors.len())
+            .any(|i| matches_ancestor_chain(rest, ancestors[i], &ancestors[i + 1..])),
+        Some(_) => false,
+    }
+}
+
+/// Check if a single compound selector matches an element
+fn matches_compound(element: &Element, compound: &CompoundSelector) -> bool {
+    let mut selector = ComplexSelector::new();
+    selector.add_compound(compound.clone());
+    let mut matcher = SelectorMatcher::new();
+    matcher.add_selector(selector);
+    !matcher.match_element(element).is_empty()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -704,6 +1376,80 @@ mod tests {
         assert_eq!(color.as_color(), Some("#ff0000"));
     }
 
+    fn style_rule(selector: &str, declarations: Vec<(&str, CssValue)>) -> CssRuleVariant {
+        let mut parser = CssSelectorParser::new(selector).unwrap();
+        let mut rule = CssStyleRule::new(parser.parse_selector_list().unwrap());
+        for (property, value) in declarations {
+            rule.add_declaration(CssDeclaration::new(property.to_string(), value, false));
+        }
+        CssRuleVariant::StyleRule(rule)
+    }
+
+    fn var(name: &str, fallback: Option<CssValue>) -> CssValue {
+        let mut args = vec![CssValue::Keyword(name.to_string())];
+        args.extend(fallback);
+        CssValue::Function("var".to_string(), args)
+    }
+
+    #[test]
+    fn test_custom_property_substitution() {
+        let mut stylesheet = CssStyleSheet::new();
+        stylesheet.add_rule(style_rule("div", vec![
+            ("--color", CssValue::Color("red".to_string())),
+            ("color", var("--color", None)),
+        ]));
+        let mut cascade = CssCascade::new();
+        cascade.add_stylesheet(stylesheet);
+        
+        let style = cascade.compute(&Element::new("div".to_string())).unwrap();
+        assert_eq!(style.get("color").unwrap().value(), &CssValue::Color("red".to_string()));
+        assert!(CssDeclaration::new("--color".to_string(), CssValue::Keyword("x".to_string()), false).is_custom_property());
+    }
+
+    #[test]
+    fn test_custom_property_inheritance_and_fallback() {
+        let mut stylesheet = CssStyleSheet::new();
+        stylesheet.add_rule(style_rule("section", vec![
+            ("--gap", CssValue::Length(8.0, "px".to_string())),
+        ]));
+        stylesheet.add_rule(style_rule("p", vec![
+            ("--double-gap", CssValue::List(vec![var("--gap", None), var("--gap", None)])),
+            ("margin", var("--double-gap", None)),
+            ("padding", var("--missing", Some(CssValue::Length(1.0, "em".to_string())))),
+            ("border-width", var("--missing", None)),
+        ]));
+        let mut cascade = CssCascade::new();
+        cascade.add_stylesheet(stylesheet);
+        
+        let parent = cascade.compute(&Element::new("section".to_string())).unwrap();
+        let style = cascade.compute_with_parent(&Element::new("p".to_string()), &parent).unwrap();
+        
+        let gap = CssValue::Length(8.0, "px".to_string());
+        assert_eq!(style.get("margin").unwrap().value(), &CssValue::List(vec![gap.clone(), gap]));
+        assert_eq!(style.get("padding").unwrap().value(), &CssValue::Length(1.0, "em".to_string()));
+        assert_eq!(style.get("border-width").unwrap().value(), &CssValue::Unset);
+        assert_eq!(style.custom_properties.len(), 2);
+    }
+
+    #[test]
+    fn test_circular_custom_properties_are_invalid() {
+        let element = Element::new("div".to_string());
+        let a = CssDeclaration::new("--a".to_string(), var("--b", Some(CssValue::Keyword("blue".to_string()))), false);
+        let b = CssDeclaration::new("--b".to_string(), var("--a", None), false);
+        let c = CssDeclaration::new("--c".to_string(), CssValue::Keyword("green".to_string()), false);
+        
+        let map = CustomPropertyMap::cascade(&[&a, &b, &c], &CustomPropertyMap::new());
+        assert!(map.get("--a").is_none());
+        assert!(map.get("--b").is_none());
+        assert_eq!(map.get("--c"), Some(&CssValue::Keyword("green".to_string())));
+        
+        let value = ComputedValue::new(var("--a", None), false, true);
+        assert_eq!(value.resolve_variables(&element, &map).unwrap(), ResolvedValue::Invalid);
+        
+        let malformed = ComputedValue::new(CssValue::Function("var".to_string(), vec![]), false, true);
+        assert!(malformed.resolve_variables(&element, &map).is_err());
+    }
+
     #[test]
     fn test_css_cascade_creation() {
         let cascade = CssCascade::new();
@@ -0,0 +1,229 @@
+//! Custom Elements registry and lifecycle callbacks.
+//!
+//! This module provides the registry behind `customElements.define()` and
+//! invokes the lifecycle callbacks of autonomous custom elements.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::debug;
+use crate::dom::{Element, Node};
+use crate::error::{Error, Result};
+
+/// Callback run when an element is upgraded to a custom element
+pub type ConstructorCallback = Arc<dyn Fn(&mut Element) + Send + Sync>;
+
+/// Callback run when an element is connected to or disconnected from a document
+pub type LifecycleCallback = Arc<dyn Fn(&Element) + Send + Sync>;
+
+/// Callback run with the attribute name, old value and new value
+pub type AttributeChangedCallback = Arc<dyn Fn(&Element, &str, Option<&str>, Option<&str>) + Send + Sync>;
+
+/// Names that contain a hyphen but are reserved by SVG and MathML
+const RESERVED_NAMES: &[&str] = &[
+    "annotation-xml",
+    "color-profile",
+    "font-face",
+    "font-face-src",
+    "font-face-uri",
+    "font-face-format",
+    "font-face-name",
+    "missing-glyph",
+];
+
+/// Definition of a custom element
+#[derive(Clone, Default)]
+pub struct CustomElementDefinition {
+    /// Constructor run when an element is upgraded
+    pub constructor: Option<ConstructorCallback>,
+    /// Callback run when the element is inserted into a document
+    pub connected_callback: Option<LifecycleCallback>,
+    /// Callback run when the element is removed from a document
+    pub disconnected_callback: Option<LifecycleCallback>,
+    /// Callback run when an observed attribute changes
+    pub attribute_changed_callback: Option<AttributeChangedCallback>,
+    /// Attributes that trigger `attribute_changed_callback`
+    pub observed_attributes: Vec<String>,
+}
+
+impl std::fmt::Debug for CustomElementDefinition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomElementDefinition")
+            .field("constructor", &self.constructor.is_some())
+            .field("connected_callback", &self.connected_callback.is_some())
+            .field("disconnected_callback", &self.disconnected_callback.is_some())
+            .field("attribute_changed_callback", &self.attribute_changed_callback.is_some())
+            .field("observed_attributes", &self.observed_attributes)
+            .finish()
+    }
+}
+
+impl CustomElementDefinition {
+    /// Create an empty definition
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the constructor
+    pub fn with_constructor<F>(mut self, constructor: F) -> Self
+    where
+        F: Fn(&mut Element) + Send + Sync + 'static,
+    {
+        self.constructor = Some(Arc::new(constructor));
+        self
+    }
+
+    /// Set the connected callback
+    pub fn with_connected_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Element) + Send + Sync + 'static,
+    {
+        self.connected_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Set the disconnected callback
+    pub fn with_disconnected_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Element) + Send + Sync + 'static,
+    {
+        self.disconnected_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Set the attribute changed callback and the attributes it observes
+    pub fn with_attribute_changed_callback<F>(mut self, observed_attributes: &[&str], callback: F) -> Self
+    where
+        F: Fn(&Element, &str, Option<&str>, Option<&str>) + Send + Sync + 'static,
+    {
+        self.observed_attributes = observed_attributes.iter().map(|name| name.to_string()).collect();
+        self.attribute_changed_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Check if changes to an attribute are reported
+    pub fn observes_attribute(&self, name: &str) -> bool {
+        self.observed_attributes.iter().any(|observed| observed == name)
+    }
+}
+
+/// Registry of custom element definitions
+#[derive(Debug, Clone, Default)]
+pub struct CustomElementRegistry {
+    /// Definitions by element name
+    definitions: HashMap<String, CustomElementDefinition>,
+}
+
+impl CustomElementRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if a name is a valid custom element name
+    pub fn is_valid_name(name: &str) -> bool {
+        name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name.contains('-')
+            && !name.chars().any(|c| c.is_ascii_uppercase())
+            && !RESERVED_NAMES.contains(&name)
+    }
+
+    /// Define a custom element
+    pub fn define(&mut self, name: &str, definition: CustomElementDefinition) -> Result<()> {
+        if !Self::is_valid_name(name) {
+            return Err(Error::DomError(format!("'{}' is not a valid custom element name", name)));
+        }
+        if self.definitions.contains_key(name) {
+            return Err(Error::DomError(format!("Custom element '{}' is already defined", name)));
+        }
+
+        self.definitions.insert(name.to_string(), definition);
+        debug!("Defined custom element {}", name);
+        Ok(())
+    }
+
+    /// Get the definition of a custom element
+    pub fn get(&self, name: &str) -> Option<&CustomElementDefinition> {
+        self.definitions.get(name)
+    }
+
+    /// Check if a custom element is defined
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.definitions.contains_key(name)
+    }
+
+    /// Run the constructors of custom elements in a subtree
+    pub fn upgrade(&self, element: &mut Element) {
+        if let Some(constructor) = self.get(&element.tag_name).and_then(|definition| definition.constructor.clone()) {
+            constructor(element);
+        }
+
+        for child in &mut element.children {
+            if let Node::Element(child) = child {
+                self.upgrade(child);
+            }
+        }
+    }
+
+    /// Run connected callbacks for custom elements in a subtree, in tree order
+    pub fn connected(&self, element: &Element) {
+        self.for_each_definition(element, &mut |definition, element| {
+            if let Some(callback) = &definition.connected_callback {
+                callback(element);
+            }
+        });
+    }
+
+    /// Run disconnected callbacks for custom elements in a subtree, in tree order
+    pub fn disconnected(&self, element: &Element) {
+        self.for_each_definition(element, &mut |definition, element| {
+            if let Some(callback) = &definition.disconnected_callback {
+                callback(element);
+            }
+        });
+    }
+
+    /// Run the attribute changed callback if the element observes the attribute
+    pub fn attribute_changed(&self, element: &Element, name: &str, old_value: Option<&str>, new_value: Option<&str>) {
+        let Some(definition) = self.get(&element.tag_name) else {
+            return;
+        };
+
+        if let Some(callback) = definition.attribute_changed_callback.as_ref().filter(|_| definition.observes_attribute(name)) {
+            callback(element, name, old_value, new_value);
+        }
+    }
+
+    /// Visit each custom element in a subtree with its definition
+    fn for_each_definition(&self, element: &Element, visit: &mut dyn FnMut(&CustomElementDefinition, &Element)) {
+        if let Some(definition) = self.get(&element.tag_name) {
+            visit(definition, element);
+        }
+
+        for child in &element.children {
+            if let Node::Element(child) = child {
+                self.for_each_definition(child, visit);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_element_names() {
+        assert!(CustomElementRegistry::is_valid_name("my-button"));
+        assert!(CustomElementRegistry::is_valid_name("x-1"));
+        assert!(!CustomElementRegistry::is_valid_name("button"));
+        assert!(!CustomElementRegistry::is_valid_name("My-Button"));
+        assert!(!CustomElementRegistry::is_valid_name("-button"));
+        assert!(!CustomElementRegistry::is_valid_name("font-face"));
+
+        let mut registry = CustomElementRegistry::new();
+        assert!(registry.define("mybutton", CustomElementDefinition::new()).is_err());
+        assert!(registry.define("my-button", CustomElementDefinition::new()).is_ok());
+        assert!(registry.define("my-button", CustomElementDefinition::new()).is_err());
+        assert!(registry.is_defined("my-button"));
+    }
+}
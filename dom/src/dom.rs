@@ -5,6 +5,11 @@
 
 use crate::error::{Error, Result};
 use crate::events::{EventManager, EventTarget, EventType, EventListener, Event};
+use crate::custom_elements::{CustomElementDefinition, CustomElementRegistry};
+use crate::html_parser::HtmlParser;
+use crate::layout::LayoutBox;
+use crate::range::{self, NodeId, Range};
+use crate::serializer::DomSerializer;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -91,6 +96,18 @@ impl Element {
         None
     }
 
+    /// Get a mutable element by ID
+    pub fn get_element_by_id_mut(&mut self, id: &str) -> Option<&mut Element> {
+        if self.get_attribute("id").map(String::as_str) == Some(id) {
+            return Some(self);
+        }
+        
+        self.children.iter_mut().find_map(|child| match child {
+            Node::Element(element) => element.get_element_by_id_mut(id),
+            _ => None,
+        })
+    }
+
     /// Get elements by tag name
     pub fn get_elements_by_tag_name(&self, tag_name: &str) -> Vec<&Element> {
         let mut elements = Vec::new();
@@ -168,47 +185,34 @@ impl Element {
 
     /// Get inner HTML
     pub fn inner_html(&self) -> String {
-        let mut html = String::new();
-        
-        for child in &self.children {
-            match child {
-                Node::Text(text_node) => html.push_str(&text_node.content),
-                Node::Element(element) => html.push_str(&element.outer_html()),
-                Node::Comment(comment) => html.push_str(&format!("<!--{}-->", comment.content)),
-                Node::DocumentType(doctype) => html.push_str(&format!("<!DOCTYPE {}>", doctype.name)),
-            }
-        }
-        
-        html
+        DomSerializer::serialize_element(self, false)
+    }
+
+    /// Replace the children of this element with nodes parsed from HTML
+    pub fn set_inner_html(&mut self, html: &str) -> Result<()> {
+        self.children = HtmlParser::parse_fragment_in(html, &self.tag_name)?;
+        Ok(())
     }
 
     /// Get outer HTML
     pub fn outer_html(&self) -> String {
-        let mut html = format!("<{}", self.tag_name);
-        
-        for (name, value) in &self.attributes {
-            html.push_str(&format!(" {}=\"{}\"", name, value));
-        }
-        
-        if self.children.is_empty() && Self::is_self_closing_tag(&self.tag_name) {
-            html.push_str(" />");
-        } else {
-            html.push('>');
-            html.push_str(&self.inner_html());
-            html.push_str(&format!("</{}>", self.tag_name));
-        }
-        
-        html
+        DomSerializer::serialize_element(self, true)
     }
-}
 
-impl Element {
-    /// Check if tag is self-closing
-    fn is_self_closing_tag(tag_name: &str) -> bool {
-        matches!(
-            tag_name,
-            "area" | "base" | "br" | "col" | "embed" | "hr" | "img" | "input" | "link" | "meta" | "param" | "source" | "track" | "wbr"
-        )
+    /// Replace the descendant with an `id` attribute by nodes, returning whether it was found
+    fn replace_descendant(&mut self, id: &str, nodes: &mut Option<Vec<Node>>) -> bool {
+        let position = self.children.iter().position(|child| {
+            matches!(child, Node::Element(element) if element.get_attribute("id").is_some_and(|value| value == id))
+        });
+        if let Some(index) = position {
+            self.children.splice(index..=index, nodes.take().unwrap_or_default());
+            return true;
+        }
+
+        self.children.iter_mut().any(|child| match child {
+            Node::Element(element) => element.replace_descendant(id, nodes),
+            _ => false,
+        })
     }
 }
 
@@ -282,6 +286,36 @@ impl DocumentTypeNode {
     }
 }
 
+/// Document fragment, nodes without a parent
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentFragment {
+    /// Child nodes
+    pub children: Vec<Node>,
+}
+
+impl DocumentFragment {
+    /// Create an empty document fragment
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a child node
+    pub fn append_child(&mut self, child: Node) {
+        self.children.push(child);
+    }
+
+    /// Get text content of the fragment's nodes
+    pub fn text_content(&self) -> String {
+        self.children.iter()
+            .filter_map(|child| match child {
+                Node::Element(element) => Some(element.text_content()),
+                Node::Text(text) => Some(text.content.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
 /// HTML document
 #[derive(Debug, Clone)]
 pub struct Document {
@@ -293,6 +327,8 @@ pub struct Document {
     pub url: Option<String>,
     /// Document character encoding
     pub character_set: String,
+    /// Custom element definitions
+    pub custom_elements: CustomElementRegistry,
 }
 
 impl Document {
@@ -303,6 +339,7 @@ impl Document {
             title: None,
             url: None,
             character_set: "UTF-8".to_string(),
+            custom_elements: CustomElementRegistry::new(),
         }
     }
 
@@ -331,6 +368,16 @@ impl Document {
         self.root.get_element_by_id(id)
     }
 
+    /// Get a mutable element by ID
+    pub fn get_element_by_id_mut(&mut self, id: &str) -> Option<&mut Element> {
+        self.root.get_element_by_id_mut(id)
+    }
+
+    /// Define a custom element; the name must contain a hyphen
+    pub fn define(&mut self, name: &str, definition: CustomElementDefinition) -> Result<()> {
+        self.custom_elements.define(name, definition)
+    }
+
     /// Get elements by tag name
     pub fn get_elements_by_tag_name(&self, tag_name: &str) -> Vec<&Element> {
         self.root.get_elements_by_tag_name(tag_name)
@@ -365,9 +412,50 @@ impl Document {
         None
     }
 
+    /// Replace an element with nodes parsed from HTML, like setting `outerHTML`
+    pub fn set_outer_html(&mut self, id: &str, html: &str) -> Result<()> {
+        let mut nodes = Some(HtmlParser::parse_fragment(html)?);
+        if self.root.replace_descendant(id, &mut nodes) {
+            Ok(())
+        } else {
+            Err(Error::NotFound(format!("No element with id '{}'", id)))
+        }
+    }
+
+    /// Create a range collapsed at the start of the document, as `document.createRange()` does
+    pub fn create_range(&self) -> Range {
+        Range::new()
+    }
+
+    /// Create a range collapsed at a point of a laid out tree, as `caretRangeFromPoint()` does
+    ///
+    /// Within text, the offset assumes the text fills the width of its box on one line.
+    pub fn create_range_from_point(&self, layout_root: &LayoutBox, x: f32, y: f32) -> Option<Range> {
+        let layout_box = range::hit_test(layout_root, x, y)?;
+        let element_id = NodeId::of_element(&self.root, &layout_box.element.id)?;
+        let element = &layout_box.element;
+        let mut range = Range::new();
+
+        let text = element.children.iter().enumerate().find_map(|(index, child)| match child {
+            Node::Text(text) if !text.content.trim().is_empty() => Some((index, text.content.chars().count())),
+            _ => None,
+        });
+        match text {
+            Some((index, length)) => {
+                let width = layout_box.dimensions.total_width();
+                let fraction = if width > 0.0 { ((x - layout_box.position_coords.x) / width).clamp(0.0, 1.0) } else { 0.0 };
+                let offset = (fraction * length as f32).round() as usize;
+                range.set_start(self, element_id.child(index), offset).ok()?;
+            }
+            None => range.set_start(self, element_id, 0).ok()?,
+        }
+        range.collapse(true);
+        Some(range)
+    }
+
     /// Get document HTML as string
     pub fn to_html(&self) -> String {
-        format!("<!DOCTYPE html>\n{}", self.root.outer_html())
+        DomSerializer::serialize_document(self)
     }
 }
 
@@ -579,7 +667,7 @@ mod tests {
         let html = img.outer_html();
         assert!(html.contains("<img"));
         assert!(html.contains("src=\"test.jpg\""));
-        assert!(html.contains("/>"));
+        assert!(!html.contains("/>"));
         assert!(!html.contains("</img>"));
     }
 }
@@ -8,7 +8,8 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 use crate::error::{Error, Result};
-use crate::dom::Document;
+use crate::dom::{Document, Element, Node};
+use crate::shadow_dom::{ShadowDomManager, ShadowRoot};
 
 /// Event phase enumeration
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -264,6 +265,23 @@ pub struct Event {
     pub data: EventData,
     /// Whether this is a trusted event (from user interaction)
     pub is_trusted: bool,
+    /// Whether the event propagates across shadow boundaries
+    pub composed: bool,
+    /// Propagation path of the current dispatch, target first
+    pub path: Vec<EventPathEntry>,
+}
+
+/// Entry in the propagation path of an event
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventPathEntry {
+    /// Element ID of the invocation target
+    pub element_id: String,
+    /// Event target seen by listeners on this element, after shadow retargeting
+    pub target: String,
+    /// Number of shadow roots enclosing the element
+    pub shadow_depth: usize,
+    /// Whether any shadow root enclosing the element is closed
+    pub in_closed_shadow: bool,
 }
 
 impl Event {
@@ -282,6 +300,8 @@ impl Event {
             timestamp: std::time::Instant::now(),
             data: EventData::None,
             is_trusted: false,
+            composed: false,
+            path: Vec::new(),
         }
     }
     
@@ -294,6 +314,7 @@ impl Event {
         button: i32,
     ) -> Self {
         let mut event = Self::new(event_type, target, true, true);
+        event.composed = true;
         event.data = EventData::Mouse(MouseEventData {
             client_x,
             client_y,
@@ -318,6 +339,7 @@ impl Event {
         code: String,
     ) -> Self {
         let mut event = Self::new(event_type, target, true, true);
+        event.composed = true;
         event.data = EventData::Keyboard(KeyboardEventData {
             key,
             code,
@@ -343,6 +365,20 @@ impl Event {
         event
     }
     
+    /// Get the propagation path as seen from the current target
+    ///
+    /// Elements inside closed shadow trees are hidden from listeners outside them.
+    pub fn composed_path(&self) -> Vec<String> {
+        let depth = self.path.iter()
+            .find(|entry| entry.element_id == self.current_target)
+            .map_or(0, |entry| entry.shadow_depth);
+        
+        self.path.iter()
+            .filter(|entry| !entry.in_closed_shadow || entry.shadow_depth <= depth)
+            .map(|entry| entry.element_id.clone())
+            .collect()
+    }
+    
     /// Prevent the default action
     pub fn prevent_default(&mut self) {
         if self.cancelable {
@@ -547,12 +583,166 @@ impl EventManager {
 pub struct EventDispatcher {
     /// Document reference
     document: Arc<RwLock<Document>>,
+    /// Shadow roots of the document, for retargeting across shadow boundaries
+    shadow_dom: Option<Arc<RwLock<ShadowDomManager>>>,
 }
 
 impl EventDispatcher {
     /// Create a new event dispatcher
     pub fn new(document: Arc<RwLock<Document>>) -> Self {
-        Self { document }
+        Self { document, shadow_dom: None }
+    }
+    
+    /// Use a shadow DOM manager to build composed event paths
+    pub fn with_shadow_dom_manager(mut self, shadow_dom: Arc<RwLock<ShadowDomManager>>) -> Self {
+        self.shadow_dom = Some(shadow_dom);
+        self
+    }
+    
+    /// Dispatch an event along its composed path in `tree`
+    ///
+    /// At each shadow boundary crossed during propagation, `event.target` is
+    /// retargeted to the shadow host so listeners outside the shadow tree never
+    /// see its internals.
+    pub async fn dispatch(&self, event: &mut Event, target: &Element, tree: &Document) -> Result<()> {
+        let shadow_dom = match &self.shadow_dom {
+            Some(shadow_dom) => Some(shadow_dom.read().await),
+            None => None,
+        };
+        
+        let scopes = Self::build_composed_scopes(target, tree, shadow_dom.as_deref(), event.composed)?;
+        let total_depth = scopes.len() - 1;
+        
+        let mut path = Vec::new();
+        let mut elements = Vec::new();
+        let mut retarget = target.id.clone();
+        for (index, (chain, shadow_root)) in scopes.iter().enumerate() {
+            let in_closed_shadow = scopes[index..].iter().any(|(_, root)| root.is_some_and(|root| root.is_closed()));
+            for element in chain {
+                path.push(EventPathEntry {
+                    element_id: element.id.clone(),
+                    target: retarget.clone(),
+                    shadow_depth: total_depth - index,
+                    in_closed_shadow,
+                });
+                elements.push(*element);
+            }
+            // Outside this shadow tree the event appears to come from its host
+            if let Some(shadow_root) = shadow_root {
+                retarget = shadow_root.host.id.clone();
+            }
+        }
+        
+        info!("Dispatching event {} to target {} through {} shadow boundaries",
+              event.event_type.as_str(), target.id, total_depth);
+        event.path = path;
+        
+        // Capture phase (root -> target)
+        for (entry, element) in event.path.clone().iter().zip(&elements).rev() {
+            if event.propagation_stopped {
+                break;
+            }
+            event.phase = if entry.element_id == entry.target { EventPhase::Target } else { EventPhase::Capturing };
+            Self::invoke_listeners(event, entry, element, true).await;
+        }
+        
+        // Bubble phase (target -> root); shadow hosts are at-target for their retargeted event
+        for (entry, element) in event.path.clone().iter().zip(&elements) {
+            if event.propagation_stopped {
+                break;
+            }
+            let at_target = entry.element_id == entry.target;
+            if !at_target && !event.bubbles {
+                continue;
+            }
+            event.phase = if at_target { EventPhase::Target } else { EventPhase::Bubbling };
+            Self::invoke_listeners(event, entry, element, false).await;
+        }
+        
+        // After dispatch the event is only observable from the outermost tree
+        if let Some(outermost) = event.path.last() {
+            event.target = outermost.target.clone();
+        }
+        event.current_target.clear();
+        
+        Ok(())
+    }
+    
+    /// Run the listeners of one path entry
+    async fn invoke_listeners(event: &mut Event, entry: &EventPathEntry, element: &Element, use_capture: bool) {
+        let Some(event_manager) = &element.event_manager else {
+            return;
+        };
+        
+        event.target = entry.target.clone();
+        event.current_target = entry.element_id.clone();
+        
+        let listeners = event_manager.read().await.get_event_listeners(&event.event_type, use_capture);
+        for listener in listeners {
+            if event.immediate_propagation_stopped {
+                break;
+            }
+            listener.execute(event);
+        }
+    }
+    
+    /// Ancestor chains of the target for each tree it is nested in, innermost first
+    ///
+    /// Each chain runs from an element up to the root of its tree, paired with the
+    /// shadow root that tree belongs to (`None` for the document tree).
+    fn build_composed_scopes<'a>(
+        target: &Element,
+        tree: &'a Document,
+        shadow_dom: Option<&'a ShadowDomManager>,
+        composed: bool,
+    ) -> Result<Vec<(Vec<&'a Element>, Option<&'a ShadowRoot>)>> {
+        let mut scopes = Vec::new();
+        let mut lookup = target.id.clone();
+        let mut visited = std::collections::HashSet::new();
+        
+        loop {
+            if !visited.insert(lookup.clone()) {
+                warn!("Circular shadow host reference detected at element {}", lookup);
+                break;
+            }
+            
+            if let Some(chain) = ancestor_chain(&tree.root, &lookup) {
+                scopes.push((chain, None));
+                break;
+            }
+            
+            let found = shadow_dom.into_iter()
+                .flat_map(|manager| manager.get_all_shadow_roots().values())
+                .find_map(|shadow_root| {
+                    shadow_root.children.iter()
+                        .filter_map(|child| match child {
+                            Node::Element(element) => ancestor_chain(element, &lookup),
+                            _ => None,
+                        })
+                        .next()
+                        .map(|chain| (chain, shadow_root))
+                });
+            
+            match found {
+                Some((chain, shadow_root)) => {
+                    scopes.push((chain, Some(shadow_root)));
+                    // Non-composed events stop at the shadow root
+                    if !composed {
+                        break;
+                    }
+                    lookup = shadow_root.host.id.clone();
+                }
+                None if scopes.is_empty() => {
+                    return Err(Error::NotFound(format!("Event target {} is not in the document", target.id)));
+                }
+                None => {
+                    warn!("Shadow host {} is not in the document", lookup);
+                    break;
+                }
+            }
+        }
+        
+        Ok(scopes)
     }
     
     /// Dispatch an event through the DOM tree
@@ -665,10 +855,73 @@ impl EventDispatcher {
     }
 }
 
+/// Chain from the element with `target_id` up to `root`, if it is in the subtree
+fn ancestor_chain<'a>(root: &'a Element, target_id: &str) -> Option<Vec<&'a Element>> {
+    if root.id == target_id {
+        return Some(vec![root]);
+    }
+    
+    root.children.iter().find_map(|child| match child {
+        Node::Element(element) => ancestor_chain(element, target_id).map(|mut chain| {
+            chain.push(root);
+            chain
+        }),
+        _ => None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_closed_shadow_root_retargets_to_host() {
+        use crate::shadow_dom::ShadowRootMode;
+        
+        let mut host = Element::new("my-widget".to_string());
+        host.id = "host".to_string();
+        let mut button = Element::new("button".to_string());
+        button.id = "inner-button".to_string();
+        
+        let mut shadow_dom = ShadowDomManager::new();
+        shadow_dom.attach_shadow(&host, ShadowRootMode::Closed).unwrap()
+            .append_child(Node::Element(button.clone()));
+        
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let observed = seen.clone();
+        let listener = EventListener::new(move |event: &Event| {
+            observed.lock().unwrap().push((event.current_target.clone(), event.target.clone(), event.composed_path()));
+        }, false, false, false);
+        host.event_manager.as_ref().unwrap().write().await.add_event_listener(EventType::Click, listener.clone()).unwrap();
+        button.event_manager.as_ref().unwrap().write().await.add_event_listener(EventType::Click, listener).unwrap();
+        
+        let mut body = Element::new("body".to_string());
+        body.id = "body".to_string();
+        body.children.push(Node::Element(host));
+        let mut document = Document::new();
+        document.root.id = "html".to_string();
+        document.root.children.push(Node::Element(body));
+        
+        let dispatcher = EventDispatcher::new(Arc::new(RwLock::new(Document::new())))
+            .with_shadow_dom_manager(Arc::new(RwLock::new(shadow_dom)));
+        let mut event = Event::new_mouse_event(EventType::Click, "inner-button".to_string(), 10.0, 10.0, 0);
+        dispatcher.dispatch(&mut event, &button, &document).await.unwrap();
+        
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        
+        // Inside the shadow tree the real target is visible
+        assert_eq!(seen[0].0, "inner-button");
+        assert_eq!(seen[0].1, "inner-button");
+        assert_eq!(seen[0].2, vec!["inner-button", "host", "body", "html"]);
+        
+        // Outside it the event appears to target the host
+        assert_eq!(seen[1].0, "host");
+        assert_eq!(seen[1].1, "host");
+        assert_eq!(seen[1].2, vec!["host", "body", "html"]);
+        assert_eq!(event.target, "host");
+    }
+
     #[test]
     fn test_event_type_creation() {
         let click_event = EventType::Click;
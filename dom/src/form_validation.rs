@@ -0,0 +1,460 @@
+//! Constraint validation of HTML form controls.
+//!
+//! This module checks `required`, `type`, `pattern`, length, range and step
+//! constraints the way `checkValidity()` does, and reports them as a
+//! `ValidityState`.
+
+use std::collections::HashMap;
+use regex::Regex;
+use crate::dom::{Document, Element, Node};
+
+/// Input types that are never validated
+const BARRED_INPUT_TYPES: &[&str] = &["hidden", "button", "submit", "reset", "image"];
+
+/// Input types the `pattern` attribute applies to
+const PATTERN_INPUT_TYPES: &[&str] = &["text", "search", "url", "tel", "email", "password"];
+
+/// Input types with a date or time value, compared as ISO 8601 strings
+const DATE_INPUT_TYPES: &[&str] = &["date", "month", "week", "time", "datetime-local"];
+
+/// Which constraints a control fails, as the `ValidityState` interface reports
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidityState {
+    /// A required control has no value
+    pub value_missing: bool,
+    /// The value is not an email address or URL
+    pub type_mismatch: bool,
+    /// The value does not match the `pattern` attribute
+    pub pattern_mismatch: bool,
+    /// The value is longer than `maxlength`
+    pub too_long: bool,
+    /// The value is shorter than `minlength`
+    pub too_short: bool,
+    /// The value is greater than `max`
+    pub range_overflow: bool,
+    /// The value is less than `min`
+    pub range_underflow: bool,
+    /// The value is not a multiple of `step` from `min`
+    pub step_mismatch: bool,
+    /// A custom validity message was set
+    pub custom_error: bool,
+}
+
+impl ValidityState {
+    /// Whether the control meets all of its constraints
+    pub fn valid(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Result of validating a form control
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationResult {
+    /// The control meets all of its constraints
+    Valid,
+    /// The control fails a constraint
+    Invalid {
+        /// Validation message for the first failed constraint
+        message: String,
+        /// Every failed constraint
+        validity_state: ValidityState,
+    },
+}
+
+impl ValidationResult {
+    /// Whether the control is valid
+    pub fn is_valid(&self) -> bool {
+        matches!(self, ValidationResult::Valid)
+    }
+}
+
+/// Validator for form controls
+#[derive(Debug, Default)]
+pub struct FormValidator {
+    /// Custom validity messages by element ID, set by `setCustomValidity()`
+    custom_validity: HashMap<String, String>,
+}
+
+impl FormValidator {
+    /// Create a new form validator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a custom validity message; an empty message clears it
+    pub fn set_custom_validity(&mut self, element_id: &str, message: &str) {
+        if message.is_empty() {
+            self.custom_validity.remove(element_id);
+        } else {
+            self.custom_validity.insert(element_id.to_string(), message.to_string());
+        }
+    }
+
+    /// Validate a form control
+    ///
+    /// Elements that are not validated, such as disabled controls and
+    /// buttons, are always valid.
+    pub fn validate_element(&self, element: &Element) -> ValidationResult {
+        self.validate_control(element, element.has_attribute("checked"))
+    }
+
+    /// Validate the controls of a form, in document order
+    ///
+    /// Controls outside the form that name it in their `form` attribute are included.
+    pub fn validate_form(&self, form: &Element, doc: &Document) -> Vec<ValidationResult> {
+        let controls = Self::form_controls(form, doc);
+
+        controls.iter()
+            .map(|control| {
+                // A required radio button is satisfied by any checked button in its group
+                let checked = match (control.get_attribute("type"), control.get_attribute("name")) {
+                    (Some(input_type), Some(name)) if input_type.eq_ignore_ascii_case("radio") => controls.iter().any(|other| {
+                        other.get_attribute("type").is_some_and(|other_type| other_type.eq_ignore_ascii_case("radio"))
+                            && other.get_attribute("name") == Some(name)
+                            && other.has_attribute("checked")
+                    }),
+                    _ => control.has_attribute("checked"),
+                };
+                self.validate_control(control, checked)
+            })
+            .collect()
+    }
+
+    /// Controls that belong to a form, in document order
+    pub fn form_controls<'a>(form: &'a Element, doc: &'a Document) -> Vec<&'a Element> {
+        let form_id = element_id(form);
+        let mut controls = Vec::new();
+        let mut found = false;
+        collect_form_controls(&doc.root, &form_id, false, &mut found, &mut controls);
+
+        // The form is not in the document, so only its descendants belong to it
+        if !found {
+            controls.clear();
+            collect_form_controls(form, &form_id, false, &mut found, &mut controls);
+        }
+        controls
+    }
+
+    fn validate_control(&self, element: &Element, checked: bool) -> ValidationResult {
+        if !is_validated(element) {
+            return ValidationResult::Valid;
+        }
+
+        let input_type = input_type(element);
+        let value = control_value(element);
+        let length = value.chars().count();
+        let mut state = ValidityState::default();
+        let mut messages = Vec::new();
+
+        if element.has_attribute("required") {
+            state.value_missing = match input_type.as_str() {
+                "checkbox" | "radio" => !checked,
+                _ => value.is_empty(),
+            };
+            if state.value_missing {
+                messages.push(match input_type.as_str() {
+                    "checkbox" => "Please check this box if you want to proceed.".to_string(),
+                    "radio" => "Please select one of these options.".to_string(),
+                    _ if element.tag_name == "select" => "Please select an item in the list.".to_string(),
+                    _ => "Please fill out this field.".to_string(),
+                });
+            }
+        }
+
+        if value.is_empty() {
+            return Self::result(state, messages, self.custom_validity.get(&element_id(element)));
+        }
+
+        let values: Vec<&str> = if input_type == "email" && element.has_attribute("multiple") {
+            value.split(',').map(str::trim).collect()
+        } else {
+            vec![value.as_str()]
+        };
+
+        match input_type.as_str() {
+            "email" if !values.iter().all(|value| is_valid_email(value)) => {
+                state.type_mismatch = true;
+                messages.push("Please enter an email address.".to_string());
+            }
+            "url" if !is_valid_url(&value) => {
+                state.type_mismatch = true;
+                messages.push("Please enter a URL.".to_string());
+            }
+            _ => {}
+        }
+
+        if let Some(pattern) = element.get_attribute("pattern") {
+            // An invalid pattern is ignored
+            let regex = Regex::new(&format!("^(?:{})$", pattern)).ok();
+            if let Some(regex) = regex.filter(|_| PATTERN_INPUT_TYPES.contains(&input_type.as_str())) {
+                if !values.iter().all(|value| regex.is_match(value)) {
+                    state.pattern_mismatch = true;
+                    messages.push(match element.get_attribute("title") {
+                        Some(title) => format!("Please match the requested format: {}", title),
+                        None => "Please match the requested format.".to_string(),
+                    });
+                }
+            }
+        }
+
+        if let Some(max_length) = length_attribute(element, "maxlength") {
+            if length > max_length {
+                state.too_long = true;
+                messages.push(format!(
+                    "Please shorten this text to {} characters or less (you are currently using {} characters).",
+                    max_length, length
+                ));
+            }
+        }
+        if let Some(min_length) = length_attribute(element, "minlength") {
+            if length < min_length {
+                state.too_short = true;
+                messages.push(format!(
+                    "Please lengthen this text to {} characters or more (you are currently using {} characters).",
+                    min_length, length
+                ));
+            }
+        }
+
+        if input_type == "number" || input_type == "range" {
+            // Values that aren't numbers are sanitized to the empty string
+            if let Ok(number) = value.parse::<f64>() {
+                Self::check_number(element, number, &mut state, &mut messages);
+            }
+        } else if DATE_INPUT_TYPES.contains(&input_type.as_str()) {
+            if let Some(max) = element.get_attribute("max").filter(|max| value.as_str() > max.as_str()) {
+                state.range_overflow = true;
+                messages.push(format!("Value must be {} or earlier.", max));
+            }
+            if let Some(min) = element.get_attribute("min").filter(|min| value.as_str() < min.as_str()) {
+                state.range_underflow = true;
+                messages.push(format!("Value must be {} or later.", min));
+            }
+        }
+
+        Self::result(state, messages, self.custom_validity.get(&element_id(element)))
+    }
+
+    /// Check the range and step of a number value
+    fn check_number(element: &Element, number: f64, state: &mut ValidityState, messages: &mut Vec<String>) {
+        let attribute = |name: &str| element.get_attribute(name).and_then(|value| value.trim().parse::<f64>().ok());
+        let (min, max) = (attribute("min"), attribute("max"));
+
+        if let Some(max) = max.filter(|max| number > *max) {
+            state.range_overflow = true;
+            messages.push(format!("Value must be less than or equal to {}.", max));
+        }
+        if let Some(min) = min.filter(|min| number < *min) {
+            state.range_underflow = true;
+            messages.push(format!("Value must be greater than or equal to {}.", min));
+        }
+
+        let step = match element.get_attribute("step") {
+            Some(step) if step.eq_ignore_ascii_case("any") => None,
+            Some(step) => Some(step.trim().parse::<f64>().ok().filter(|step| *step > 0.0).unwrap_or(1.0)),
+            None => Some(1.0),
+        };
+        if let Some(step) = step {
+            let base = min.unwrap_or(0.0);
+            let steps = (number - base) / step;
+            if (steps - steps.round()).abs() > 1e-9 {
+                state.step_mismatch = true;
+                let below = base + steps.floor() * step;
+                messages.push(format!(
+                    "Please enter a valid value. The two nearest valid values are {} and {}.",
+                    below,
+                    below + step
+                ));
+            }
+        }
+    }
+
+    fn result(mut state: ValidityState, mut messages: Vec<String>, custom_message: Option<&String>) -> ValidationResult {
+        if let Some(message) = custom_message {
+            state.custom_error = true;
+            messages.insert(0, message.clone());
+        }
+
+        if state.valid() {
+            ValidationResult::Valid
+        } else {
+            ValidationResult::Invalid {
+                message: messages.into_iter().next().unwrap_or_default(),
+                validity_state: state,
+            }
+        }
+    }
+}
+
+/// ID of an element, its `id` attribute or the ID it was created with
+fn element_id(element: &Element) -> String {
+    element.get_attribute("id").cloned().unwrap_or_else(|| element.id.clone())
+}
+
+fn is_form_control(element: &Element) -> bool {
+    matches!(element.tag_name.as_str(), "input" | "select" | "textarea")
+}
+
+/// Collect the controls of a form, tracking whether the walk is inside the form
+fn collect_form_controls<'a>(element: &'a Element, form_id: &str, inside: bool, found: &mut bool, controls: &mut Vec<&'a Element>) {
+    let is_form = element.tag_name == "form" && element_id(element) == form_id;
+    *found |= is_form;
+    let inside = inside || is_form;
+
+    if is_form_control(element) {
+        let belongs = match element.get_attribute("form") {
+            Some(owner) => owner == form_id,
+            None => inside,
+        };
+        if belongs {
+            controls.push(element);
+        }
+    }
+
+    for child in &element.children {
+        if let Node::Element(child) = child {
+            collect_form_controls(child, form_id, inside, found, controls);
+        }
+    }
+}
+
+/// Type of an input, `text` for unknown types
+fn input_type(element: &Element) -> String {
+    if element.tag_name != "input" {
+        return String::new();
+    }
+    element.get_attribute("type").map(|input_type| input_type.to_ascii_lowercase()).unwrap_or_else(|| "text".to_string())
+}
+
+/// Whether constraint validation applies to an element
+fn is_validated(element: &Element) -> bool {
+    // `readonly` does not apply to selects
+    let read_only = element.has_attribute("readonly") && element.tag_name != "select";
+    is_form_control(element)
+        && !element.has_attribute("disabled")
+        && !read_only
+        && !BARRED_INPUT_TYPES.contains(&input_type(element).as_str())
+}
+
+/// Current value of a control
+fn control_value(element: &Element) -> String {
+    match element.tag_name.as_str() {
+        "textarea" => element.get_attribute("value").cloned().unwrap_or_else(|| element.text_content()),
+        "select" => {
+            let options = element.get_elements_by_tag_name("option");
+            let selected = options.iter()
+                .find(|option| option.has_attribute("selected"))
+                .or(if element.has_attribute("multiple") { None } else { options.first() });
+            selected
+                .map(|option| option.get_attribute("value").cloned().unwrap_or_else(|| option.text_content().trim().to_string()))
+                .unwrap_or_default()
+        }
+        _ => element.get_attribute("value").cloned().unwrap_or_default(),
+    }
+}
+
+fn length_attribute(element: &Element, name: &str) -> Option<usize> {
+    element.get_attribute(name).and_then(|value| value.trim().parse().ok())
+}
+
+/// Whether a value is a valid email address, as `<input type="email">` defines it
+fn is_valid_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    let local_valid = !local.is_empty()
+        && local.chars().all(|c| c.is_ascii_alphanumeric() || ".!#$%&'*+/=?^_`{|}~-".contains(c));
+    let domain_valid = domain.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    local_valid && domain_valid
+}
+
+/// Whether a value is an absolute URL
+fn is_valid_url(value: &str) -> bool {
+    let Some((scheme, rest)) = value.split_once(':') else {
+        return false;
+    };
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        && !rest.is_empty()
+        && !value.chars().any(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(attributes: &[(&str, &str)]) -> Element {
+        let mut element = Element::new("input".to_string());
+        for (name, value) in attributes {
+            element.set_attribute(name.to_string(), value.to_string());
+        }
+        element
+    }
+
+    #[test]
+    fn test_validate_element_constraints() {
+        let validator = FormValidator::new();
+
+        match validator.validate_element(&input(&[("required", "")])) {
+            ValidationResult::Invalid { message, validity_state } => {
+                assert!(validity_state.value_missing);
+                assert_eq!(message, "Please fill out this field.");
+            }
+            ValidationResult::Valid => panic!("empty required input is valid"),
+        }
+        assert!(validator.validate_element(&input(&[("required", ""), ("value", "x")])).is_valid());
+        assert!(validator.validate_element(&input(&[("required", ""), ("disabled", "")])).is_valid());
+
+        let state = |attributes: &[(&str, &str)]| match validator.validate_element(&input(attributes)) {
+            ValidationResult::Invalid { validity_state, .. } => validity_state,
+            ValidationResult::Valid => ValidityState::default(),
+        };
+        assert!(state(&[("type", "email"), ("value", "user@")]).type_mismatch);
+        assert!(state(&[("type", "email"), ("value", "user@example.com")]).valid());
+        assert!(state(&[("type", "url"), ("value", "example.com")]).type_mismatch);
+        assert!(state(&[("pattern", "[0-9]{3}"), ("value", "12a")]).pattern_mismatch);
+        assert!(state(&[("pattern", "[0-9]{3}"), ("value", "123")]).valid());
+        assert!(state(&[("maxlength", "3"), ("value", "abcd")]).too_long);
+        assert!(state(&[("minlength", "3"), ("value", "ab")]).too_short);
+        assert!(state(&[("type", "number"), ("max", "10"), ("value", "11")]).range_overflow);
+        assert!(state(&[("type", "number"), ("min", "1"), ("value", "0")]).range_underflow);
+        assert!(state(&[("type", "number"), ("min", "1"), ("step", "2"), ("value", "4")]).step_mismatch);
+        assert!(state(&[("type", "number"), ("step", "any"), ("value", "1.5")]).valid());
+        assert!(state(&[("type", "date"), ("max", "2024-01-31"), ("value", "2024-02-01")]).range_overflow);
+
+        let mut validator = FormValidator::new();
+        validator.set_custom_validity("name", "Name is taken");
+        match validator.validate_element(&input(&[("id", "name"), ("value", "x")])) {
+            ValidationResult::Invalid { message, validity_state } => {
+                assert!(validity_state.custom_error);
+                assert_eq!(message, "Name is taken");
+            }
+            ValidationResult::Valid => panic!("custom error is ignored"),
+        }
+    }
+
+    #[test]
+    fn test_validate_form() {
+        let mut form = Element::new("form".to_string());
+        form.set_attribute("id".to_string(), "signup".to_string());
+        form.append_child(Node::Element(input(&[("type", "radio"), ("name", "plan"), ("required", "")])));
+        form.append_child(Node::Element(input(&[("type", "radio"), ("name", "plan"), ("checked", "")])));
+        form.append_child(Node::Element(input(&[("type", "submit")])));
+        let mut document = Document::new();
+        document.root.append_child(Node::Element(form));
+        document.root.append_child(Node::Element(input(&[("form", "signup"), ("required", "")])));
+        document.root.append_child(Node::Element(input(&[("required", "")])));
+
+        let form = document.get_element_by_id("signup").unwrap();
+        assert_eq!(FormValidator::form_controls(form, &document).len(), 4);
+        let results = FormValidator::new().validate_form(form, &document);
+        assert_eq!(results.iter().map(ValidationResult::is_valid).collect::<Vec<_>>(), vec![true, true, true, false]);
+    }
+}
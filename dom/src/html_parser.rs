@@ -1,579 +1,1888 @@
 //! HTML parser for the Matte browser.
-//! 
-//! This module provides HTML parsing functionality to convert HTML text
-//! into a structured DOM tree.
-
-use crate::error::{Error, Result};
-use crate::dom::{Document, Element, Node, TextNode};
-use std::collections::HashMap;
-
-/// HTML parser state
-#[derive(Debug, Clone, PartialEq)]
-enum ParserState {
-    /// Initial state
+//!
+//! This module implements the tree construction stage of the HTML parsing
+//! algorithm on top of `HtmlTokenizer`. It covers implied `<html>`, `<head>`
+//! and `<body>` elements, implied end tags, the adoption agency algorithm for
+//! misnested formatting elements, foster parenting of content misplaced in
+//! tables, and raw text elements such as `<script>`, `<style>` and
+//! `<textarea>`. Templates, frames and SVG or MathML content are parsed as
+//! ordinary HTML elements.
+
+use crate::dom::{CommentNode, Document, Element, Node, TextNode};
+use crate::error::Result;
+use crate::html_tokenizer::{HtmlToken, HtmlTokenizer, TokenizerState};
+
+/// Index of the document in the parser's node arena
+const DOCUMENT: usize = 0;
+
+/// Elements with special parsing rules
+const SPECIAL_ELEMENTS: &[&str] = &[
+    "address", "applet", "area", "article", "aside", "base", "basefont", "bgsound", "blockquote",
+    "body", "br", "button", "caption", "center", "col", "colgroup", "dd", "details", "dir", "div",
+    "dl", "dt", "embed", "fieldset", "figcaption", "figure", "footer", "form", "frame", "frameset",
+    "h1", "h2", "h3", "h4", "h5", "h6", "head", "header", "hgroup", "hr", "html", "iframe", "img",
+    "input", "keygen", "li", "link", "listing", "main", "marquee", "menu", "meta", "nav", "noembed",
+    "noframes", "noscript", "object", "ol", "p", "param", "plaintext", "pre", "script", "search",
+    "section", "select", "source", "style", "summary", "table", "tbody", "td", "template",
+    "textarea", "tfoot", "th", "thead", "title", "tr", "track", "ul", "wbr", "xmp",
+];
+
+/// Formatting elements, which are reopened when misnested
+const FORMATTING_ELEMENTS: &[&str] = &[
+    "a", "b", "big", "code", "em", "font", "i", "nobr", "s", "small", "strike", "strong", "tt", "u",
+];
+
+/// Block elements whose start tag closes an open `<p>`
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "center", "details", "dialog", "dir", "div", "dl",
+    "fieldset", "figcaption", "figure", "footer", "header", "hgroup", "main", "menu", "nav", "ol",
+    "p", "search", "section", "summary", "ul",
+];
+
+/// Heading elements
+const HEADINGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Elements whose end tags are implied by the start of other content
+const IMPLIED_END_TAGS: &[&str] = &["dd", "dt", "li", "optgroup", "option", "p", "rb", "rp", "rt", "rtc"];
+
+/// Elements that bound the default scope
+const DEFAULT_SCOPE: &[&str] = &["applet", "caption", "html", "table", "td", "th", "marquee", "object", "template"];
+
+/// Deepest an element is nested, deeper content is added to the element at this depth
+const MAX_TREE_DEPTH: usize = 512;
+
+/// Table elements content is foster parented out of
+const TABLE_CONTEXT: &[&str] = &["table", "tbody", "tfoot", "thead", "tr"];
+
+/// HTML parser producing DOM documents
+pub struct HtmlParser;
+
+impl HtmlParser {
+    /// Parse a document
+    ///
+    /// The encoding is taken from a byte order mark, defaulting to UTF-8.
+    /// Parse errors are recovered from as the HTML standard specifies, so
+    /// every input produces a document with `<html>`, `<head>` and `<body>`.
+    pub fn parse(html_bytes: &[u8]) -> Result<Document> {
+        let (html, character_set) = decode(html_bytes);
+        let mut builder = TreeBuilder::new(None);
+        builder.run(&mut HtmlTokenizer::new(&html));
+        Ok(builder.into_document(character_set))
+    }
+
+    /// Parse an HTML fragment as the contents of a `<body>` element
+    pub fn parse_fragment(html: &str) -> Result<Vec<Node>> {
+        Self::parse_fragment_in(html, "body")
+    }
+
+    /// Parse an HTML fragment as the contents of an element with a tag name
+    pub fn parse_fragment_in(html: &str, context: &str) -> Result<Vec<Node>> {
+        let mut builder = TreeBuilder::new(Some(context));
+        builder.run(&mut HtmlTokenizer::new(html));
+        Ok(builder.into_fragment())
+    }
+}
+
+/// Node in the parser's arena
+#[derive(Debug)]
+enum TreeData {
+    Document,
+    Element { name: String, attributes: Vec<(String, String)> },
+    Text(String),
+    Comment(String),
+}
+
+/// Node and its links in the parser's arena
+#[derive(Debug)]
+struct TreeNode {
+    data: TreeData,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// Tree construction insertion modes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InsertionMode {
     Initial,
-    /// Inside a tag
-    Tag,
-    /// Inside an opening tag
-    OpeningTag,
-    /// Inside a closing tag
-    ClosingTag,
-    /// Inside tag name
-    TagName,
-    /// Inside attribute name
-    AttributeName,
-    /// Inside attribute value
-    AttributeValue,
-    /// Inside text content
+    BeforeHtml,
+    BeforeHead,
+    InHead,
+    AfterHead,
+    InBody,
     Text,
-    /// Inside comment
-    Comment,
-    /// Inside DOCTYPE declaration
-    Doctype,
+    InTable,
+    InTableText,
+    InCaption,
+    InColumnGroup,
+    InTableBody,
+    InRow,
+    InCell,
+    InSelect,
+    InSelectInTable,
+    AfterBody,
+    AfterAfterBody,
 }
 
-/// HTML parser for converting HTML text to DOM
-pub struct HtmlParser {
-    state: ParserState,
-    current_tag_name: String,
-    current_attribute_name: String,
-    current_attribute_value: String,
-    current_text: String,
-    stack: Vec<Element>,
-    document: Document,
-    in_quotes: bool,
-    quote_char: Option<char>,
-    pending_attributes: HashMap<String, String>,
-    is_self_closing_context: bool,
+/// Entry in the list of active formatting elements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FormattingEntry {
+    Marker,
+    Element(usize),
 }
 
-impl HtmlParser {
-    /// Create a new HTML parser
-    pub fn new() -> Self {
-        Self {
-            state: ParserState::Initial,
-            current_tag_name: String::new(),
-            current_attribute_name: String::new(),
-            current_attribute_value: String::new(),
-            current_text: String::new(),
-            stack: Vec::new(),
-            document: Document::new(),
-            in_quotes: false,
-            quote_char: None,
-            pending_attributes: HashMap::new(),
-            is_self_closing_context: false,
-        }
-    }
-
-    /// Parse HTML text into a DOM document
-    pub fn parse(&mut self, html: &str) -> Result<Document> {
-        self.reset();
-        
-        for (i, ch) in html.chars().enumerate() {
-            self.process_char(ch, i)?;
-        }
-        
-        // Process any remaining text
-        if !self.current_text.trim().is_empty() {
-            self.add_text_node();
-        }
-        
-        // Close any unclosed tags
-        while let Some(element) = self.stack.pop() {
-            if let Some(parent) = self.stack.last_mut() {
-                parent.children.push(Node::Element(element));
-            } else {
-                self.document.root.children.push(Node::Element(element));
-            }
-        }
-        
-        Ok(self.document.clone())
-    }
-
-    /// Reset parser state
-    fn reset(&mut self) {
-        self.state = ParserState::Initial;
-        self.current_tag_name.clear();
-        self.current_attribute_name.clear();
-        self.current_attribute_value.clear();
-        self.current_text.clear();
-        self.stack.clear();
-        self.document = Document::new();
-        self.in_quotes = false;
-        self.quote_char = None;
-        self.pending_attributes.clear();
-        self.is_self_closing_context = false;
-    }
-
-    /// Process a single character
-    fn process_char(&mut self, ch: char, _position: usize) -> Result<()> {
-        match self.state {
-            ParserState::Initial => self.handle_initial_state(ch)?,
-            ParserState::Tag => self.handle_tag_state(ch)?,
-            ParserState::OpeningTag => self.handle_opening_tag_state(ch)?,
-            ParserState::ClosingTag => self.handle_closing_tag_state(ch)?,
-            ParserState::TagName => self.handle_tag_name_state(ch)?,
-            ParserState::AttributeName => self.handle_attribute_name_state(ch)?,
-            ParserState::AttributeValue => self.handle_attribute_value_state(ch)?,
-            ParserState::Text => self.handle_text_state(ch)?,
-            ParserState::Comment => self.handle_comment_state(ch)?,
-            ParserState::Doctype => self.handle_doctype_state(ch)?,
-        }
-        Ok(())
-    }
-
-    /// Handle initial state
-    fn handle_initial_state(&mut self, ch: char) -> Result<()> {
-        match ch {
-            '<' => {
-                if !self.current_text.trim().is_empty() {
-                    self.add_text_node();
-                }
-                self.state = ParserState::Tag;
+/// Where the adoption agency algorithm puts the new formatting element
+enum Bookmark {
+    Replace,
+    InsertAfter(usize),
+}
+
+/// Scopes for checking whether an element is open
+#[derive(Debug, Clone, Copy)]
+enum Scope {
+    Default,
+    ListItem,
+    Button,
+    Table,
+    Select,
+}
+
+/// Tree construction state
+struct TreeBuilder {
+    /// Nodes of the tree, the document first
+    nodes: Vec<TreeNode>,
+    /// Stack of open elements
+    open: Vec<usize>,
+    /// List of active formatting elements
+    formatting: Vec<FormattingEntry>,
+    /// The `<head>` element
+    head: Option<usize>,
+    /// The open `<form>` element
+    form: Option<usize>,
+    mode: InsertionMode,
+    /// Mode to return to after raw text or table text
+    original_mode: InsertionMode,
+    /// Whether a `<frameset>` may still replace the body
+    frameset_ok: bool,
+    /// Whether content is inserted in front of the current table
+    foster_parenting: bool,
+    /// Whether a newline at the start of the next token is dropped
+    ignore_newline: bool,
+    /// Character data collected in a table
+    pending_table_text: String,
+    /// Context element of a fragment
+    context: Option<String>,
+    /// State the tokenizer is switched to after the current token
+    tokenizer_state: Option<TokenizerState>,
+    /// Whether parsing has stopped
+    done: bool,
+}
+
+impl TreeBuilder {
+    /// Create a tree builder for a document, or for a fragment in a context element
+    fn new(context: Option<&str>) -> Self {
+        let mut builder = Self {
+            nodes: vec![TreeNode {
+                data: TreeData::Document,
+                parent: None,
+                children: Vec::new(),
+            }],
+            open: Vec::new(),
+            formatting: Vec::new(),
+            head: None,
+            form: None,
+            mode: InsertionMode::Initial,
+            original_mode: InsertionMode::Initial,
+            frameset_ok: true,
+            foster_parenting: false,
+            ignore_newline: false,
+            pending_table_text: String::new(),
+            context: context.map(str::to_string),
+            tokenizer_state: None,
+            done: false,
+        };
+
+        if let Some(context) = context {
+            let html = builder.create_element("html", Vec::new());
+            builder.append(DOCUMENT, html);
+            builder.open.push(html);
+            builder.reset_insertion_mode();
+            builder.tokenizer_state = match context {
+                "title" | "textarea" => Some(TokenizerState::Rcdata),
+                "style" | "xmp" | "iframe" | "noembed" | "noframes" => Some(TokenizerState::Rawtext),
+                "script" => Some(TokenizerState::ScriptData),
+                "plaintext" => Some(TokenizerState::Plaintext),
+                _ => None,
+            };
+        }
+        builder
+    }
+
+    /// Feed tokens until the end of input
+    fn run(&mut self, tokenizer: &mut HtmlTokenizer) {
+        while !self.done {
+            if let Some(state) = self.tokenizer_state.take() {
+                tokenizer.set_state(state);
             }
-            _ => {
-                self.current_text.push(ch);
-                self.state = ParserState::Text;
+            let token = tokenizer.next_token();
+            let eof = token == HtmlToken::Eof;
+            self.process(token);
+            if eof {
+                break;
+            }
+        }
+    }
+
+    /// Build the document from the tree
+    fn into_document(self, character_set: &str) -> Document {
+        let mut document = Document::new();
+        document.character_set = character_set.to_string();
+
+        let html = self.nodes[DOCUMENT].children.iter().copied().find(|&node| self.name(node) == "html");
+        if let Some(Node::Element(root)) = html.and_then(|html| self.to_node(html)) {
+            document.root = root;
+        }
+
+        let title = document.get_elements_by_tag_name("title").first().map(|title| {
+            title.text_content().split_whitespace().collect::<Vec<_>>().join(" ")
+        });
+        document.title = title;
+        document
+    }
+
+    /// Build the nodes parsed into the fragment's context element
+    fn into_fragment(self) -> Vec<Node> {
+        let Some(&html) = self.nodes[DOCUMENT].children.first() else {
+            return Vec::new();
+        };
+        self.nodes[html].children.iter().filter_map(|&child| self.to_node(child)).collect()
+    }
+
+    /// Convert an arena node and its descendants to a DOM node
+    fn to_node(&self, index: usize) -> Option<Node> {
+        let node = &self.nodes[index];
+        match &node.data {
+            TreeData::Document => None,
+            TreeData::Element { name, attributes } => {
+                let mut element = Element::new(name.clone());
+                for (name, value) in attributes {
+                    element.set_attribute(name.clone(), value.clone());
+                }
+                element.children = node.children.iter().filter_map(|&child| self.to_node(child)).collect();
+                Some(Node::Element(element))
+            }
+            TreeData::Text(text) => Some(Node::Text(TextNode::new(text.clone()))),
+            TreeData::Comment(comment) => Some(Node::Comment(CommentNode::new(comment.clone()))),
+        }
+    }
+
+    // Tree access
+
+    /// Tag name of an element, or an empty string for other nodes
+    fn name(&self, node: usize) -> &str {
+        match &self.nodes[node].data {
+            TreeData::Element { name, .. } => name,
+            _ => "",
+        }
+    }
+
+    /// Tag name and attributes of an element
+    fn element_token(&self, node: usize) -> (String, Vec<(String, String)>) {
+        match &self.nodes[node].data {
+            TreeData::Element { name, attributes } => (name.clone(), attributes.clone()),
+            _ => (String::new(), Vec::new()),
+        }
+    }
+
+    /// The current node
+    fn current(&self) -> usize {
+        self.open.last().copied().unwrap_or(DOCUMENT)
+    }
+
+    /// Whether the current node has one of the names
+    fn current_is(&self, names: &[&str]) -> bool {
+        names.contains(&self.name(self.current()))
+    }
+
+    /// Create a node without a parent
+    fn create_node(&mut self, data: TreeData) -> usize {
+        self.nodes.push(TreeNode {
+            data,
+            parent: None,
+            children: Vec::new(),
+        });
+        self.nodes.len() - 1
+    }
+
+    /// Create an element without a parent
+    fn create_element(&mut self, name: &str, attributes: Vec<(String, String)>) -> usize {
+        self.create_node(TreeData::Element {
+            name: name.to_string(),
+            attributes,
+        })
+    }
+
+    /// Remove a node from its parent
+    fn detach(&mut self, node: usize) {
+        if let Some(parent) = self.nodes[node].parent.take() {
+            self.nodes[parent].children.retain(|&child| child != node);
+        }
+    }
+
+    /// Append a node to a parent, moving it if it already has one
+    fn append(&mut self, parent: usize, node: usize) {
+        self.insert_at((parent, None), node);
+    }
+
+    /// Insert a node into a parent, before a sibling or at the end
+    fn insert_at(&mut self, (parent, before): (usize, Option<usize>), node: usize) {
+        self.detach(node);
+        let position = before
+            .and_then(|before| self.nodes[parent].children.iter().position(|&child| child == before))
+            .unwrap_or(self.nodes[parent].children.len());
+        self.nodes[parent].children.insert(position, node);
+        self.nodes[node].parent = Some(parent);
+    }
+
+    /// The appropriate place for inserting a node, as a parent and the sibling to insert before
+    fn insertion_place(&self, target: Option<usize>) -> (usize, Option<usize>) {
+        let (parent, before) = self.target_place(target.unwrap_or_else(|| self.current()));
+
+        // Like browsers, flatten pathologically deep trees
+        let mut ancestors = vec![parent];
+        while let Some(ancestor) = self.nodes[ancestors[ancestors.len() - 1]].parent {
+            ancestors.push(ancestor);
+        }
+        if ancestors.len() > MAX_TREE_DEPTH {
+            return (ancestors[ancestors.len() - MAX_TREE_DEPTH], None);
+        }
+        (parent, before)
+    }
+
+    /// The place for inserting a node into a target, with foster parenting
+    fn target_place(&self, target: usize) -> (usize, Option<usize>) {
+        if !self.foster_parenting || !TABLE_CONTEXT.contains(&self.name(target)) {
+            return (target, None);
+        }
+
+        // Foster parenting puts the node in front of the table
+        match self.open.iter().rposition(|&node| self.name(node) == "table") {
+            Some(position) => {
+                let table = self.open[position];
+                match self.nodes[table].parent {
+                    Some(parent) => (parent, Some(table)),
+                    None => (self.open[position - 1], None),
+                }
             }
+            None => (self.open[0], None),
         }
-        Ok(())
     }
 
-    /// Handle tag state
-    fn handle_tag_state(&mut self, ch: char) -> Result<()> {
-        match ch {
-            '/' => {
-                self.state = ParserState::ClosingTag;
+    /// Insert an element at the appropriate place and push it onto the stack
+    fn insert_element(&mut self, name: &str, attributes: Vec<(String, String)>) -> usize {
+        let element = self.create_element(name, attributes);
+        let place = self.insertion_place(None);
+        self.insert_at(place, element);
+        self.open.push(element);
+        element
+    }
+
+    /// Insert character data, merging it with a preceding text node
+    fn insert_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let (parent, before) = self.insertion_place(None);
+        if parent == DOCUMENT {
+            return;
+        }
+
+        let children = &self.nodes[parent].children;
+        let position = before
+            .and_then(|before| children.iter().position(|&child| child == before))
+            .unwrap_or(children.len());
+        if let Some(&previous) = position.checked_sub(1).and_then(|index| children.get(index)) {
+            if let TreeData::Text(existing) = &mut self.nodes[previous].data {
+                existing.push_str(text);
+                return;
             }
-            '!' => {
-                self.state = ParserState::Comment;
+        }
+
+        let node = self.create_node(TreeData::Text(text.to_string()));
+        self.insert_at((parent, before), node);
+    }
+
+    /// Insert a comment at the appropriate place, or as the last child of a node
+    fn insert_comment(&mut self, text: &str, parent: Option<usize>) {
+        let place = match parent {
+            Some(parent) => (parent, None),
+            None => self.insertion_place(None),
+        };
+        let node = self.create_node(TreeData::Comment(text.to_string()));
+        self.insert_at(place, node);
+    }
+
+    /// Add attributes an element does not have yet
+    fn add_missing_attributes(&mut self, element: usize, new_attributes: Vec<(String, String)>) {
+        if let TreeData::Element { attributes, .. } = &mut self.nodes[element].data {
+            for (name, value) in new_attributes {
+                if !attributes.iter().any(|(existing, _)| *existing == name) {
+                    attributes.push((name, value));
+                }
             }
-            '?' => {
-                self.state = ParserState::Doctype;
+        }
+    }
+
+    // Stack of open elements
+
+    /// Whether an element with one of the names is open within a scope
+    fn has_in_scope(&self, names: &[&str], scope: Scope) -> bool {
+        for &node in self.open.iter().rev() {
+            let name = self.name(node);
+            if names.contains(&name) {
+                return true;
             }
-            c if c.is_ascii_alphabetic() => {
-                self.current_tag_name.push(c);
-                self.state = ParserState::TagName;
+            let boundary = match scope {
+                Scope::Default => DEFAULT_SCOPE.contains(&name),
+                Scope::ListItem => DEFAULT_SCOPE.contains(&name) || matches!(name, "ol" | "ul"),
+                Scope::Button => DEFAULT_SCOPE.contains(&name) || name == "button",
+                Scope::Table => matches!(name, "html" | "table" | "template"),
+                Scope::Select => !matches!(name, "optgroup" | "option"),
+            };
+            if boundary {
+                return false;
             }
-            _ => {
-                return Err(Error::ParseError(format!("Unexpected character in tag: {}", ch)));
+        }
+        false
+    }
+
+    /// Whether a particular element is open within the default scope
+    fn has_node_in_scope(&self, target: usize) -> bool {
+        for &node in self.open.iter().rev() {
+            if node == target {
+                return true;
+            }
+            if DEFAULT_SCOPE.contains(&self.name(node)) {
+                return false;
             }
         }
-        Ok(())
+        false
     }
 
-    /// Handle opening tag state
-    fn handle_opening_tag_state(&mut self, ch: char) -> Result<()> {
-        match ch {
-            '>' => {
-                self.finish_opening_tag()?;
-                self.state = ParserState::Initial;
+    /// Pop elements until one with one of the names has been popped
+    fn pop_until(&mut self, names: &[&str]) {
+        while let Some(node) = self.open.pop() {
+            if names.contains(&self.name(node)) {
+                break;
             }
-            ' ' | '\t' | '\n' | '\r' => {
-                self.state = ParserState::AttributeName;
+        }
+    }
+
+    /// Pop elements until the current node has one of the names
+    fn clear_stack_back_to(&mut self, names: &[&str]) {
+        while !self.current_is(names) && self.open.len() > 1 {
+            self.open.pop();
+        }
+    }
+
+    /// Pop elements whose end tags are implied, except one
+    fn generate_implied_end_tags(&mut self, except: Option<&str>) {
+        loop {
+            let name = self.name(self.current());
+            if !IMPLIED_END_TAGS.contains(&name) || Some(name) == except {
+                break;
             }
-                            '/' => {
-                    // Self-closing tag - wait for the '>'
-                    self.state = ParserState::ClosingTag;
+            self.open.pop();
+        }
+    }
+
+    /// Close an open `<p>` element
+    fn close_p(&mut self) {
+        self.generate_implied_end_tags(Some("p"));
+        self.pop_until(&["p"]);
+    }
+
+    /// Close a `<p>` element if one is open in button scope
+    fn close_p_in_button_scope(&mut self) {
+        if self.has_in_scope(&["p"], Scope::Button) {
+            self.close_p();
+        }
+    }
+
+    /// Pick the insertion mode from the stack of open elements
+    fn reset_insertion_mode(&mut self) {
+        for index in (0..self.open.len()).rev() {
+            let last = index == 0;
+            let name = match (&self.context, last) {
+                (Some(context), true) => context.clone(),
+                _ => self.name(self.open[index]).to_string(),
+            };
+            self.mode = match name.as_str() {
+                "select" => {
+                    let in_table = self.open[..index].iter().any(|&node| self.name(node) == "table");
+                    if in_table { InsertionMode::InSelectInTable } else { InsertionMode::InSelect }
                 }
-            c if c.is_ascii_alphabetic() => {
-                self.current_attribute_name.push(c);
-                self.state = ParserState::AttributeName;
+                "td" | "th" if !last => InsertionMode::InCell,
+                "tr" => InsertionMode::InRow,
+                "tbody" | "thead" | "tfoot" => InsertionMode::InTableBody,
+                "caption" => InsertionMode::InCaption,
+                "colgroup" => InsertionMode::InColumnGroup,
+                "table" => InsertionMode::InTable,
+                "head" if !last => InsertionMode::InHead,
+                "body" => InsertionMode::InBody,
+                "html" if self.head.is_none() => InsertionMode::BeforeHead,
+                "html" => InsertionMode::AfterHead,
+                _ if last => InsertionMode::InBody,
+                _ => continue,
+            };
+            return;
+        }
+    }
+
+    // List of active formatting elements
+
+    /// Position of an element in the list of active formatting elements
+    fn formatting_position(&self, element: usize) -> Option<usize> {
+        self.formatting.iter().position(|&entry| entry == FormattingEntry::Element(element))
+    }
+
+    /// Remove an element from the list of active formatting elements
+    fn remove_formatting(&mut self, element: usize) {
+        if let Some(position) = self.formatting_position(element) {
+            self.formatting.remove(position);
+        }
+    }
+
+    /// The last formatting element with a name after the last marker
+    fn formatting_element_named(&self, name: &str) -> Option<usize> {
+        for &entry in self.formatting.iter().rev() {
+            match entry {
+                FormattingEntry::Marker => return None,
+                FormattingEntry::Element(element) if self.name(element) == name => return Some(element),
+                FormattingEntry::Element(_) => {}
             }
-            _ => {
-                // Skip unexpected characters in opening tag
-                // This is more lenient than the original implementation
+        }
+        None
+    }
+
+    /// Add an element to the list of active formatting elements
+    fn push_formatting(&mut self, element: usize) {
+        // At most three identical elements are kept after the last marker
+        let token = self.element_token(element);
+        let mut identical = Vec::new();
+        for (position, &entry) in self.formatting.iter().enumerate().rev() {
+            match entry {
+                FormattingEntry::Marker => break,
+                FormattingEntry::Element(other) if same_token(&self.element_token(other), &token) => identical.push(position),
+                FormattingEntry::Element(_) => {}
             }
         }
-        Ok(())
+        if identical.len() >= 3 {
+            self.formatting.remove(identical[identical.len() - 1]);
+        }
+        self.formatting.push(FormattingEntry::Element(element));
     }
 
-    /// Handle closing tag state
-    fn handle_closing_tag_state(&mut self, ch: char) -> Result<()> {
-        match ch {
-            '>' => {
-                // Check if this is a self-closing tag based on context
-                if self.is_self_closing_context {
-                    // This is a self-closing tag like <img />
-                    self.finish_self_closing_tag()?;
-                } else {
-                    // This is a regular closing tag like </div>
-                    self.finish_closing_tag()?;
+    /// Remove entries up to and including the last marker
+    fn clear_formatting_to_marker(&mut self) {
+        while let Some(entry) = self.formatting.pop() {
+            if entry == FormattingEntry::Marker {
+                break;
+            }
+        }
+    }
+
+    /// Reopen formatting elements that were closed implicitly
+    fn reconstruct_formatting(&mut self) {
+        let is_open_or_marker = |builder: &Self, entry: FormattingEntry| match entry {
+            FormattingEntry::Marker => true,
+            FormattingEntry::Element(element) => builder.open.contains(&element),
+        };
+        match self.formatting.last() {
+            Some(&entry) if !is_open_or_marker(self, entry) => {}
+            _ => return,
+        }
+
+        let mut first = self.formatting.len() - 1;
+        while first > 0 && !is_open_or_marker(self, self.formatting[first - 1]) {
+            first -= 1;
+        }
+
+        for position in first..self.formatting.len() {
+            if let FormattingEntry::Element(element) = self.formatting[position] {
+                let (name, attributes) = self.element_token(element);
+                let reopened = self.insert_element(&name, attributes);
+                self.formatting[position] = FormattingEntry::Element(reopened);
+            }
+        }
+    }
+
+    /// Run the adoption agency algorithm for an end tag
+    ///
+    /// Returns `false` if the end tag is to be handled like any other end tag.
+    fn adoption_agency(&mut self, subject: &str) -> bool {
+        let current = self.current();
+        if self.name(current) == subject && self.formatting_position(current).is_none() {
+            self.open.pop();
+            return true;
+        }
+
+        for _ in 0..8 {
+            let Some(formatting_element) = self.formatting_element_named(subject) else {
+                return false;
+            };
+            let Some(formatting_position) = self.open.iter().position(|&node| node == formatting_element) else {
+                self.remove_formatting(formatting_element);
+                return true;
+            };
+            if !self.has_node_in_scope(formatting_element) {
+                return true;
+            }
+
+            // The furthest block is the first special element below the formatting element
+            let furthest_position = (formatting_position + 1..self.open.len())
+                .find(|&position| SPECIAL_ELEMENTS.contains(&self.name(self.open[position])));
+            let Some(furthest_position) = furthest_position else {
+                self.open.truncate(formatting_position);
+                self.remove_formatting(formatting_element);
+                return true;
+            };
+            let furthest_block = self.open[furthest_position];
+            let common_ancestor = self.open[formatting_position - 1];
+
+            let mut bookmark = Bookmark::Replace;
+            let mut last_node = furthest_block;
+            let mut position = furthest_position;
+            let mut inner_loop = 0;
+            loop {
+                inner_loop += 1;
+                position -= 1;
+                let node = self.open[position];
+                if node == formatting_element {
+                    break;
+                }
+                if inner_loop > 3 {
+                    self.remove_formatting(node);
+                }
+                let Some(list_position) = self.formatting_position(node) else {
+                    self.open.remove(position);
+                    continue;
+                };
+
+                let (name, attributes) = self.element_token(node);
+                let replacement = self.create_element(&name, attributes);
+                self.formatting[list_position] = FormattingEntry::Element(replacement);
+                self.open[position] = replacement;
+                if last_node == furthest_block {
+                    bookmark = Bookmark::InsertAfter(replacement);
                 }
-                self.state = ParserState::Initial;
-                self.is_self_closing_context = false;
+                self.append(replacement, last_node);
+                last_node = replacement;
             }
-            c if c.is_ascii_alphabetic() || c.is_ascii_digit() => {
-                self.current_tag_name.push(c);
+
+            let place = self.insertion_place(Some(common_ancestor));
+            self.insert_at(place, last_node);
+
+            // A copy of the formatting element takes over the furthest block's children
+            let (name, attributes) = self.element_token(formatting_element);
+            let copy = self.create_element(&name, attributes);
+            for child in std::mem::take(&mut self.nodes[furthest_block].children) {
+                self.nodes[child].parent = Some(copy);
+                self.nodes[copy].children.push(child);
             }
-            _ => {
-                // Skip unexpected characters in closing tag
-                // This is more lenient than the original implementation
+            self.append(furthest_block, copy);
+
+            match bookmark {
+                Bookmark::Replace => {
+                    if let Some(list_position) = self.formatting_position(formatting_element) {
+                        self.formatting[list_position] = FormattingEntry::Element(copy);
+                    }
+                }
+                Bookmark::InsertAfter(node) => {
+                    self.remove_formatting(formatting_element);
+                    let list_position = self.formatting_position(node).map_or(self.formatting.len(), |p| p + 1);
+                    self.formatting.insert(list_position, FormattingEntry::Element(copy));
+                }
             }
+
+            self.open.retain(|&node| node != formatting_element);
+            let furthest_position = self.open.iter().position(|&node| node == furthest_block).unwrap_or(self.open.len() - 1);
+            self.open.insert(furthest_position + 1, copy);
         }
-        Ok(())
+        true
     }
 
-    /// Handle tag name state
-    fn handle_tag_name_state(&mut self, ch: char) -> Result<()> {
-        match ch {
-            '>' => {
-                self.finish_opening_tag()?;
-                self.state = ParserState::Initial;
+    // Token processing
+
+    /// Process a token in the current insertion mode
+    fn process(&mut self, token: HtmlToken) {
+        // A newline right after <pre>, <listing> or <textarea> is dropped
+        let token = match token {
+            HtmlToken::Characters(text) if std::mem::take(&mut self.ignore_newline) => {
+                match text.strip_prefix('\n') {
+                    Some("") => return,
+                    Some(rest) => HtmlToken::Characters(rest.to_string()),
+                    None => HtmlToken::Characters(text),
+                }
             }
-            ' ' | '\t' | '\n' | '\r' => {
-                self.state = ParserState::AttributeName;
+            token => {
+                self.ignore_newline = false;
+                token
             }
-            '/' => {
-                // Self-closing tag - wait for the '>'
-                self.is_self_closing_context = true;
-                self.state = ParserState::ClosingTag;
+        };
+        self.step(token);
+    }
+
+    /// Process a token in the current insertion mode, without preprocessing
+    fn step(&mut self, token: HtmlToken) {
+        self.step_in(self.mode, token);
+    }
+
+    /// Process a token using the rules of an insertion mode
+    fn step_in(&mut self, mode: InsertionMode, token: HtmlToken) {
+        match mode {
+            InsertionMode::Initial => self.initial(token),
+            InsertionMode::BeforeHtml => self.before_html(token),
+            InsertionMode::BeforeHead => self.before_head(token),
+            InsertionMode::InHead => self.in_head(token),
+            InsertionMode::AfterHead => self.after_head(token),
+            InsertionMode::InBody => self.in_body(token),
+            InsertionMode::Text => self.text(token),
+            InsertionMode::InTable => self.in_table(token),
+            InsertionMode::InTableText => self.in_table_text(token),
+            InsertionMode::InCaption => self.in_caption(token),
+            InsertionMode::InColumnGroup => self.in_column_group(token),
+            InsertionMode::InTableBody => self.in_table_body(token),
+            InsertionMode::InRow => self.in_row(token),
+            InsertionMode::InCell => self.in_cell(token),
+            InsertionMode::InSelect => self.in_select(token),
+            InsertionMode::InSelectInTable => self.in_select_in_table(token),
+            InsertionMode::AfterBody => self.after_body(token),
+            InsertionMode::AfterAfterBody => self.after_after_body(token),
+        }
+    }
+
+    /// Insert an element whose contents are raw text or RCDATA
+    fn parse_raw_text(&mut self, name: &str, attributes: Vec<(String, String)>, state: TokenizerState) {
+        self.insert_element(name, attributes);
+        self.tokenizer_state = Some(state);
+        self.original_mode = self.mode;
+        self.mode = InsertionMode::Text;
+    }
+
+    fn initial(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Characters(text) => {
+                let rest = text.trim_start_matches(is_whitespace);
+                if !rest.is_empty() {
+                    self.mode = InsertionMode::BeforeHtml;
+                    self.step(HtmlToken::Characters(rest.to_string()));
+                }
             }
-            c if c.is_ascii_alphabetic() || c.is_ascii_digit() => {
-                self.current_tag_name.push(c);
+            HtmlToken::Comment(text) => self.insert_comment(&text, Some(DOCUMENT)),
+            HtmlToken::Doctype { .. } => self.mode = InsertionMode::BeforeHtml,
+            token => {
+                self.mode = InsertionMode::BeforeHtml;
+                self.step(token);
             }
-            _ => {
-                // Skip unexpected characters in tag name
-                // This is more lenient than the original implementation
+        }
+    }
+
+    fn before_html(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Doctype { .. } => {}
+            HtmlToken::Comment(text) => self.insert_comment(&text, Some(DOCUMENT)),
+            HtmlToken::Characters(text) => {
+                let rest = text.trim_start_matches(is_whitespace);
+                if !rest.is_empty() {
+                    self.insert_html(Vec::new());
+                    self.step(HtmlToken::Characters(rest.to_string()));
+                }
+            }
+            HtmlToken::StartTag { name, attributes, .. } if name == "html" => self.insert_html(attributes),
+            HtmlToken::EndTag { name } if !matches!(name.as_str(), "head" | "body" | "html" | "br") => {}
+            token => {
+                self.insert_html(Vec::new());
+                self.step(token);
             }
         }
-        Ok(())
     }
 
-    /// Handle attribute name state
-    fn handle_attribute_name_state(&mut self, ch: char) -> Result<()> {
-        match ch {
-            '=' => {
-                self.state = ParserState::AttributeValue;
+    /// Insert the `<html>` element and switch to the before head mode
+    fn insert_html(&mut self, attributes: Vec<(String, String)>) {
+        let html = self.create_element("html", attributes);
+        self.append(DOCUMENT, html);
+        self.open.push(html);
+        self.mode = InsertionMode::BeforeHead;
+    }
+
+    fn before_head(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Characters(text) => {
+                let rest = text.trim_start_matches(is_whitespace);
+                if !rest.is_empty() {
+                    self.insert_head(Vec::new());
+                    self.step(HtmlToken::Characters(rest.to_string()));
+                }
             }
-            '>' => {
-                // Attribute without value
-                self.finish_attribute()?;
-                self.finish_opening_tag()?;
-                self.state = ParserState::Initial;
+            HtmlToken::Comment(text) => self.insert_comment(&text, None),
+            HtmlToken::Doctype { .. } => {}
+            HtmlToken::StartTag { ref name, .. } if name == "html" => self.in_body(token),
+            HtmlToken::StartTag { name, attributes, .. } if name == "head" => self.insert_head(attributes),
+            HtmlToken::EndTag { name } if !matches!(name.as_str(), "head" | "body" | "html" | "br") => {}
+            token => {
+                self.insert_head(Vec::new());
+                self.step(token);
             }
-            ' ' | '\t' | '\n' | '\r' => {
-                // Attribute without value
-                self.finish_attribute()?;
+        }
+    }
+
+    /// Insert the `<head>` element and switch to the in head mode
+    fn insert_head(&mut self, attributes: Vec<(String, String)>) {
+        self.head = Some(self.insert_element("head", attributes));
+        self.mode = InsertionMode::InHead;
+    }
+
+    fn in_head(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Characters(text) => {
+                let rest = text.trim_start_matches(is_whitespace);
+                self.insert_text(&text[..text.len() - rest.len()]);
+                if !rest.is_empty() {
+                    self.in_head_anything_else(HtmlToken::Characters(rest.to_string()));
+                }
             }
-            '/' => {
-                // Self-closing tag - finish any pending attribute and wait for the '>'
-                self.finish_attribute()?;
-                self.is_self_closing_context = true;
-                self.state = ParserState::ClosingTag;
+            HtmlToken::Comment(text) => self.insert_comment(&text, None),
+            HtmlToken::Doctype { .. } => {}
+            HtmlToken::StartTag { name, attributes, self_closing } => match name.as_str() {
+                "html" => self.in_body(HtmlToken::StartTag { name, attributes, self_closing }),
+                "base" | "basefont" | "bgsound" | "link" | "meta" => {
+                    self.insert_element(&name, attributes);
+                    self.open.pop();
+                }
+                "title" => self.parse_raw_text(&name, attributes, TokenizerState::Rcdata),
+                "noscript" | "noframes" | "style" => self.parse_raw_text(&name, attributes, TokenizerState::Rawtext),
+                "script" => self.parse_raw_text(&name, attributes, TokenizerState::ScriptData),
+                "head" => {}
+                _ => self.in_head_anything_else(HtmlToken::StartTag { name, attributes, self_closing }),
+            },
+            HtmlToken::EndTag { name } => match name.as_str() {
+                "head" => {
+                    self.open.pop();
+                    self.mode = InsertionMode::AfterHead;
+                }
+                "body" | "html" | "br" => self.in_head_anything_else(HtmlToken::EndTag { name }),
+                _ => {}
+            },
+            HtmlToken::Eof => self.in_head_anything_else(HtmlToken::Eof),
+        }
+    }
+
+    fn in_head_anything_else(&mut self, token: HtmlToken) {
+        self.open.pop();
+        self.mode = InsertionMode::AfterHead;
+        self.step(token);
+    }
+
+    fn after_head(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Characters(text) => {
+                let rest = text.trim_start_matches(is_whitespace);
+                self.insert_text(&text[..text.len() - rest.len()]);
+                if !rest.is_empty() {
+                    self.after_head_anything_else(HtmlToken::Characters(rest.to_string()));
+                }
             }
-            c if c.is_ascii_alphabetic() || c == '-' || c == '_' => {
-                self.current_attribute_name.push(c);
+            HtmlToken::Comment(text) => self.insert_comment(&text, None),
+            HtmlToken::Doctype { .. } => {}
+            HtmlToken::StartTag { name, attributes, self_closing } => match name.as_str() {
+                "html" => self.in_body(HtmlToken::StartTag { name, attributes, self_closing }),
+                "body" => {
+                    self.insert_element("body", attributes);
+                    self.frameset_ok = false;
+                    self.mode = InsertionMode::InBody;
+                }
+                "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script" | "style" | "title" => {
+                    // Misplaced head content still goes into the head
+                    let head = self.head.unwrap_or(DOCUMENT);
+                    self.open.push(head);
+                    self.in_head(HtmlToken::StartTag { name, attributes, self_closing });
+                    if let Some(position) = self.open.iter().rposition(|&node| node == head) {
+                        self.open.remove(position);
+                    }
+                }
+                "head" => {}
+                _ => self.after_head_anything_else(HtmlToken::StartTag { name, attributes, self_closing }),
+            },
+            HtmlToken::EndTag { name } if matches!(name.as_str(), "body" | "html" | "br") => {
+                self.after_head_anything_else(HtmlToken::EndTag { name });
             }
+            HtmlToken::EndTag { .. } => {}
+            HtmlToken::Eof => self.after_head_anything_else(HtmlToken::Eof),
+        }
+    }
+
+    fn after_head_anything_else(&mut self, token: HtmlToken) {
+        self.insert_element("body", Vec::new());
+        self.mode = InsertionMode::InBody;
+        self.step(token);
+    }
+
+    fn in_body(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Characters(text) => {
+                let text: String = text.chars().filter(|&c| c != '\0').collect();
+                if text.is_empty() {
+                    return;
+                }
+                self.reconstruct_formatting();
+                self.insert_text(&text);
+                if !text.chars().all(is_whitespace) {
+                    self.frameset_ok = false;
+                }
+            }
+            HtmlToken::Comment(text) => self.insert_comment(&text, None),
+            HtmlToken::Doctype { .. } => {}
+            HtmlToken::StartTag { name, attributes, self_closing } => self.in_body_start_tag(name, attributes, self_closing),
+            HtmlToken::EndTag { name } => self.in_body_end_tag(&name),
+            HtmlToken::Eof => self.done = true,
+        }
+    }
+
+    fn in_body_start_tag(&mut self, name: String, attributes: Vec<(String, String)>, self_closing: bool) {
+        match name.as_str() {
+            "html" => {
+                if let Some(&html) = self.open.first() {
+                    self.add_missing_attributes(html, attributes);
+                }
+            }
+            "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script" | "style" | "title" => {
+                self.in_head(HtmlToken::StartTag { name, attributes, self_closing });
+            }
+            "body" => {
+                if self.open.len() >= 2 && self.name(self.open[1]) == "body" {
+                    self.frameset_ok = false;
+                    self.add_missing_attributes(self.open[1], attributes);
+                }
+            }
+            "frameset" => {}
+            _ if BLOCK_ELEMENTS.contains(&name.as_str()) => {
+                self.close_p_in_button_scope();
+                self.insert_element(&name, attributes);
+            }
+            _ if HEADINGS.contains(&name.as_str()) => {
+                self.close_p_in_button_scope();
+                if self.current_is(HEADINGS) {
+                    self.open.pop();
+                }
+                self.insert_element(&name, attributes);
+            }
+            "pre" | "listing" => {
+                self.close_p_in_button_scope();
+                self.insert_element(&name, attributes);
+                self.ignore_newline = true;
+                self.frameset_ok = false;
+            }
+            "form" => {
+                if self.form.is_none() {
+                    self.close_p_in_button_scope();
+                    self.form = Some(self.insert_element(&name, attributes));
+                }
+            }
+            "li" | "dd" | "dt" => {
+                self.frameset_ok = false;
+                // A new item closes the open item of the same list
+                let closes: &[&str] = if name == "li" { &["li"] } else { &["dd", "dt"] };
+                for index in (0..self.open.len()).rev() {
+                    let node_name = self.name(self.open[index]).to_string();
+                    if closes.contains(&node_name.as_str()) {
+                        self.generate_implied_end_tags(Some(&node_name));
+                        self.pop_until(&[&node_name]);
+                        break;
+                    }
+                    if SPECIAL_ELEMENTS.contains(&node_name.as_str()) && !matches!(node_name.as_str(), "address" | "div" | "p") {
+                        break;
+                    }
+                }
+                self.close_p_in_button_scope();
+                self.insert_element(&name, attributes);
+            }
+            "plaintext" => {
+                self.close_p_in_button_scope();
+                self.insert_element(&name, attributes);
+                self.tokenizer_state = Some(TokenizerState::Plaintext);
+            }
+            "button" => {
+                if self.has_in_scope(&["button"], Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&["button"]);
+                }
+                self.reconstruct_formatting();
+                self.insert_element(&name, attributes);
+                self.frameset_ok = false;
+            }
+            "a" => {
+                if let Some(previous) = self.formatting_element_named("a") {
+                    self.adoption_agency("a");
+                    self.remove_formatting(previous);
+                    self.open.retain(|&node| node != previous);
+                }
+                self.reconstruct_formatting();
+                let element = self.insert_element(&name, attributes);
+                self.push_formatting(element);
+            }
+            "nobr" => {
+                self.reconstruct_formatting();
+                if self.has_in_scope(&["nobr"], Scope::Default) {
+                    self.adoption_agency("nobr");
+                    self.reconstruct_formatting();
+                }
+                let element = self.insert_element(&name, attributes);
+                self.push_formatting(element);
+            }
+            _ if FORMATTING_ELEMENTS.contains(&name.as_str()) => {
+                self.reconstruct_formatting();
+                let element = self.insert_element(&name, attributes);
+                self.push_formatting(element);
+            }
+            "applet" | "marquee" | "object" => {
+                self.reconstruct_formatting();
+                self.insert_element(&name, attributes);
+                self.formatting.push(FormattingEntry::Marker);
+                self.frameset_ok = false;
+            }
+            "table" => {
+                self.close_p_in_button_scope();
+                self.insert_element(&name, attributes);
+                self.frameset_ok = false;
+                self.mode = InsertionMode::InTable;
+            }
+            "area" | "br" | "embed" | "img" | "keygen" | "wbr" | "input" => {
+                let hidden_input = name == "input" && is_hidden_input(&attributes);
+                self.reconstruct_formatting();
+                self.insert_element(&name, attributes);
+                self.open.pop();
+                if !hidden_input {
+                    self.frameset_ok = false;
+                }
+            }
+            "param" | "source" | "track" => {
+                self.insert_element(&name, attributes);
+                self.open.pop();
+            }
+            "hr" => {
+                self.close_p_in_button_scope();
+                self.insert_element(&name, attributes);
+                self.open.pop();
+                self.frameset_ok = false;
+            }
+            "image" => self.step(HtmlToken::StartTag { name: "img".to_string(), attributes, self_closing }),
+            "textarea" => {
+                self.ignore_newline = true;
+                self.frameset_ok = false;
+                self.parse_raw_text(&name, attributes, TokenizerState::Rcdata);
+            }
+            "xmp" => {
+                self.close_p_in_button_scope();
+                self.reconstruct_formatting();
+                self.frameset_ok = false;
+                self.parse_raw_text(&name, attributes, TokenizerState::Rawtext);
+            }
+            "iframe" => {
+                self.frameset_ok = false;
+                self.parse_raw_text(&name, attributes, TokenizerState::Rawtext);
+            }
+            "noembed" | "noscript" => self.parse_raw_text(&name, attributes, TokenizerState::Rawtext),
+            "select" => {
+                self.reconstruct_formatting();
+                self.insert_element(&name, attributes);
+                self.frameset_ok = false;
+                self.mode = match self.mode {
+                    InsertionMode::InTable
+                    | InsertionMode::InCaption
+                    | InsertionMode::InTableBody
+                    | InsertionMode::InRow
+                    | InsertionMode::InCell => InsertionMode::InSelectInTable,
+                    _ => InsertionMode::InSelect,
+                };
+            }
+            "optgroup" | "option" => {
+                if self.current_is(&["option"]) {
+                    self.open.pop();
+                }
+                self.reconstruct_formatting();
+                self.insert_element(&name, attributes);
+            }
+            "rb" | "rtc" | "rp" | "rt" => {
+                if self.has_in_scope(&["ruby"], Scope::Default) {
+                    let except = if matches!(name.as_str(), "rp" | "rt") { Some("rtc") } else { None };
+                    self.generate_implied_end_tags(except);
+                }
+                self.insert_element(&name, attributes);
+            }
+            "caption" | "col" | "colgroup" | "frame" | "head" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr" => {}
             _ => {
-                return Err(Error::ParseError(format!("Unexpected character in attribute name: {}", ch)));
+                self.reconstruct_formatting();
+                self.insert_element(&name, attributes);
+                // Self-closing tags only close foreign elements
+                if self_closing && matches!(name.as_str(), "math" | "svg") {
+                    self.open.pop();
+                }
             }
         }
-        Ok(())
     }
 
-    /// Handle attribute value state
-    fn handle_attribute_value_state(&mut self, ch: char) -> Result<()> {
-        if self.in_quotes {
-            if ch == self.quote_char.unwrap() {
-                self.in_quotes = false;
-                self.quote_char = None;
-                self.finish_attribute()?;
-                self.state = ParserState::AttributeName;
-            } else {
-                self.current_attribute_value.push(ch);
+    fn in_body_end_tag(&mut self, name: &str) {
+        match name {
+            "body" | "html" => {
+                if self.has_in_scope(&["body"], Scope::Default) {
+                    self.mode = InsertionMode::AfterBody;
+                    if name == "html" {
+                        self.step(HtmlToken::EndTag { name: name.to_string() });
+                    }
+                }
             }
-        } else {
-            match ch {
-                '"' | '\'' => {
-                    self.in_quotes = true;
-                    self.quote_char = Some(ch);
+            "address" | "article" | "aside" | "blockquote" | "button" | "center" | "details" | "dialog" | "dir"
+            | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer" | "header" | "hgroup" | "listing"
+            | "main" | "menu" | "nav" | "ol" | "pre" | "search" | "section" | "summary" | "ul" => {
+                if self.has_in_scope(&[name], Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&[name]);
+                }
+            }
+            "form" => {
+                if let Some(form) = self.form.take() {
+                    if self.has_node_in_scope(form) {
+                        self.generate_implied_end_tags(None);
+                        self.open.retain(|&node| node != form);
+                    }
+                }
+            }
+            "p" => {
+                if !self.has_in_scope(&["p"], Scope::Button) {
+                    self.insert_element("p", Vec::new());
+                }
+                self.close_p();
+            }
+            "li" => {
+                if self.has_in_scope(&["li"], Scope::ListItem) {
+                    self.generate_implied_end_tags(Some("li"));
+                    self.pop_until(&["li"]);
+                }
+            }
+            "dd" | "dt" => {
+                if self.has_in_scope(&[name], Scope::Default) {
+                    self.generate_implied_end_tags(Some(name));
+                    self.pop_until(&[name]);
                 }
-                '>' => {
-                    self.finish_attribute()?;
-                    self.finish_opening_tag()?;
-                    self.state = ParserState::Initial;
+            }
+            _ if HEADINGS.contains(&name) => {
+                if self.has_in_scope(HEADINGS, Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(HEADINGS);
+                }
+            }
+            _ if FORMATTING_ELEMENTS.contains(&name) => {
+                if !self.adoption_agency(name) {
+                    self.any_other_end_tag(name);
+                }
+            }
+            "applet" | "marquee" | "object" => {
+                if self.has_in_scope(&[name], Scope::Default) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&[name]);
+                    self.clear_formatting_to_marker();
+                }
+            }
+            "br" => self.in_body_start_tag("br".to_string(), Vec::new(), false),
+            _ => self.any_other_end_tag(name),
+        }
+    }
+
+    /// Close the innermost open element with a name, unless a special element is in the way
+    fn any_other_end_tag(&mut self, name: &str) {
+        for index in (0..self.open.len()).rev() {
+            let node_name = self.name(self.open[index]);
+            if node_name == name {
+                self.generate_implied_end_tags(Some(name));
+                self.open.truncate(index);
+                return;
+            }
+            if SPECIAL_ELEMENTS.contains(&node_name) {
+                return;
+            }
+        }
+    }
+
+    fn text(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Characters(text) => self.insert_text(&text),
+            HtmlToken::Eof => {
+                self.open.pop();
+                self.mode = self.original_mode;
+                self.step(HtmlToken::Eof);
+            }
+            HtmlToken::EndTag { .. } => {
+                self.open.pop();
+                self.mode = self.original_mode;
+            }
+            _ => {}
+        }
+    }
+
+    fn in_table(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Characters(_) if self.current_is(&["table", "tbody", "template", "tfoot", "thead", "tr"]) => {
+                self.pending_table_text.clear();
+                self.original_mode = self.mode;
+                self.mode = InsertionMode::InTableText;
+                self.step(token);
+            }
+            HtmlToken::Comment(text) => self.insert_comment(&text, None),
+            HtmlToken::Doctype { .. } => {}
+            HtmlToken::StartTag { name, attributes, self_closing } => match name.as_str() {
+                "caption" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.formatting.push(FormattingEntry::Marker);
+                    self.insert_element(&name, attributes);
+                    self.mode = InsertionMode::InCaption;
+                }
+                "colgroup" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.insert_element(&name, attributes);
+                    self.mode = InsertionMode::InColumnGroup;
+                }
+                "col" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.insert_element("colgroup", Vec::new());
+                    self.mode = InsertionMode::InColumnGroup;
+                    self.step(HtmlToken::StartTag { name, attributes, self_closing });
+                }
+                "tbody" | "tfoot" | "thead" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.insert_element(&name, attributes);
+                    self.mode = InsertionMode::InTableBody;
+                }
+                "td" | "th" | "tr" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.insert_element("tbody", Vec::new());
+                    self.mode = InsertionMode::InTableBody;
+                    self.step(HtmlToken::StartTag { name, attributes, self_closing });
+                }
+                "table" => {
+                    if self.has_in_scope(&["table"], Scope::Table) {
+                        self.pop_until(&["table"]);
+                        self.reset_insertion_mode();
+                        self.step(HtmlToken::StartTag { name, attributes, self_closing });
+                    }
                 }
-                ' ' | '\t' | '\n' | '\r' => {
-                    self.finish_attribute()?;
-                    self.state = ParserState::AttributeName;
+                "style" | "script" => self.in_head(HtmlToken::StartTag { name, attributes, self_closing }),
+                "input" if is_hidden_input(&attributes) => {
+                    self.insert_element(&name, attributes);
+                    self.open.pop();
                 }
-                '/' => {
-                    self.finish_attribute()?;
-                    // Don't finish the tag yet, wait for the '>'
-                    self.state = ParserState::ClosingTag;
+                "form" => {
+                    if self.form.is_none() {
+                        self.form = Some(self.insert_element(&name, attributes));
+                        self.open.pop();
+                    }
+                }
+                _ => self.in_table_anything_else(HtmlToken::StartTag { name, attributes, self_closing }),
+            },
+            HtmlToken::EndTag { name } => match name.as_str() {
+                "table" => {
+                    if self.has_in_scope(&["table"], Scope::Table) {
+                        self.pop_until(&["table"]);
+                        self.reset_insertion_mode();
+                    }
                 }
-                _ => {
-                    self.current_attribute_value.push(ch);
+                "body" | "caption" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr" => {}
+                _ => self.in_table_anything_else(HtmlToken::EndTag { name }),
+            },
+            HtmlToken::Eof => self.in_body(token),
+            token => self.in_table_anything_else(token),
+        }
+    }
+
+    /// Process misplaced table content with foster parenting
+    fn in_table_anything_else(&mut self, token: HtmlToken) {
+        self.foster_parenting = true;
+        self.in_body(token);
+        self.foster_parenting = false;
+    }
+
+    fn in_table_text(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Characters(text) => self.pending_table_text.extend(text.chars().filter(|&c| c != '\0')),
+            token => {
+                let text = std::mem::take(&mut self.pending_table_text);
+                if text.chars().all(is_whitespace) {
+                    self.insert_text(&text);
+                } else {
+                    self.in_table_anything_else(HtmlToken::Characters(text));
                 }
+                self.mode = self.original_mode;
+                self.step(token);
             }
         }
-        Ok(())
     }
 
-    /// Handle text state
-    fn handle_text_state(&mut self, ch: char) -> Result<()> {
-        match ch {
-            '<' => {
-                if !self.current_text.is_empty() {
-                    self.add_text_node();
+    /// Close the open caption, returning whether there was one
+    fn close_caption(&mut self) -> bool {
+        if !self.has_in_scope(&["caption"], Scope::Table) {
+            return false;
+        }
+        self.generate_implied_end_tags(None);
+        self.pop_until(&["caption"]);
+        self.clear_formatting_to_marker();
+        self.mode = InsertionMode::InTable;
+        true
+    }
+
+    fn in_caption(&mut self, token: HtmlToken) {
+        match &token {
+            HtmlToken::EndTag { name } if name == "caption" => {
+                self.close_caption();
+            }
+            HtmlToken::StartTag { name, .. }
+                if matches!(name.as_str(), "caption" | "col" | "colgroup" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr") =>
+            {
+                if self.close_caption() {
+                    self.step(token);
                 }
-                self.state = ParserState::Tag;
             }
-            _ => {
-                self.current_text.push(ch);
+            HtmlToken::EndTag { name } if name == "table" => {
+                if self.close_caption() {
+                    self.step(token);
+                }
             }
+            HtmlToken::EndTag { name }
+                if matches!(name.as_str(), "body" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr") => {}
+            _ => self.in_body(token),
         }
-        Ok(())
     }
 
-    /// Handle comment state
-    fn handle_comment_state(&mut self, ch: char) -> Result<()> {
-        // Simplified comment handling - just skip until -->
-        if ch == '>' && self.current_text.ends_with("--") {
-            self.current_text.clear();
-            self.state = ParserState::Initial;
-        } else {
-            self.current_text.push(ch);
+    fn in_column_group(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Characters(text) => {
+                let rest = text.trim_start_matches(is_whitespace);
+                self.insert_text(&text[..text.len() - rest.len()]);
+                if !rest.is_empty() {
+                    self.in_column_group_anything_else(HtmlToken::Characters(rest.to_string()));
+                }
+            }
+            HtmlToken::Comment(text) => self.insert_comment(&text, None),
+            HtmlToken::Doctype { .. } => {}
+            HtmlToken::StartTag { ref name, .. } if name == "html" => self.in_body(token),
+            HtmlToken::StartTag { name, attributes, .. } if name == "col" => {
+                self.insert_element(&name, attributes);
+                self.open.pop();
+            }
+            HtmlToken::EndTag { ref name } if name == "colgroup" => {
+                if self.current_is(&["colgroup"]) {
+                    self.open.pop();
+                    self.mode = InsertionMode::InTable;
+                }
+            }
+            HtmlToken::EndTag { ref name } if name == "col" => {}
+            HtmlToken::Eof => self.in_body(token),
+            token => self.in_column_group_anything_else(token),
         }
-        Ok(())
     }
 
-    /// Handle DOCTYPE state
-    fn handle_doctype_state(&mut self, ch: char) -> Result<()> {
-        // Simplified DOCTYPE handling - just skip until >
-        if ch == '>' {
-            self.current_text.clear();
-            self.state = ParserState::Initial;
-        } else {
-            self.current_text.push(ch);
+    fn in_column_group_anything_else(&mut self, token: HtmlToken) {
+        if self.current_is(&["colgroup"]) {
+            self.open.pop();
+            self.mode = InsertionMode::InTable;
+            self.step(token);
         }
-        Ok(())
     }
 
-    /// Finish opening tag
-    fn finish_opening_tag(&mut self) -> Result<()> {
-        // Finish any pending attribute first
-        self.finish_attribute()?;
-        
-        let tag_name = self.current_tag_name.to_lowercase();
-        let mut element = Element::new(tag_name.clone());
-        // Set attributes after creation
-        for (name, value) in &self.pending_attributes {
-            element.set_attribute(name.clone(), value.clone());
+    fn in_table_body(&mut self, token: HtmlToken) {
+        const TABLE_BODY_CONTEXT: &[&str] = &["tbody", "tfoot", "thead", "template", "html"];
+        match &token {
+            HtmlToken::StartTag { name, .. } if name == "tr" => {
+                let HtmlToken::StartTag { name, attributes, .. } = token else { return };
+                self.clear_stack_back_to(TABLE_BODY_CONTEXT);
+                self.insert_element(&name, attributes);
+                self.mode = InsertionMode::InRow;
+            }
+            HtmlToken::StartTag { name, .. } if matches!(name.as_str(), "th" | "td") => {
+                self.clear_stack_back_to(TABLE_BODY_CONTEXT);
+                self.insert_element("tr", Vec::new());
+                self.mode = InsertionMode::InRow;
+                self.step(token);
+            }
+            HtmlToken::EndTag { name } if matches!(name.as_str(), "tbody" | "tfoot" | "thead") => {
+                if self.has_in_scope(&[name.as_str()], Scope::Table) {
+                    self.clear_stack_back_to(TABLE_BODY_CONTEXT);
+                    self.open.pop();
+                    self.mode = InsertionMode::InTable;
+                }
+            }
+            HtmlToken::StartTag { name, .. }
+                if matches!(name.as_str(), "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead") =>
+            {
+                self.close_table_body(token);
+            }
+            HtmlToken::EndTag { name } if name == "table" => self.close_table_body(token),
+            HtmlToken::EndTag { name }
+                if matches!(name.as_str(), "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th" | "tr") => {}
+            _ => self.in_table(token),
         }
+    }
 
-        // Handle self-closing tags
-        if Self::is_self_closing_tag(&tag_name) {
-            self.add_element_to_parent(element);
-        } else {
-            self.stack.push(element);
+    /// Close the open table section and reprocess a token
+    fn close_table_body(&mut self, token: HtmlToken) {
+        if self.has_in_scope(&["tbody", "thead", "tfoot"], Scope::Table) {
+            self.clear_stack_back_to(&["tbody", "tfoot", "thead", "template", "html"]);
+            self.open.pop();
+            self.mode = InsertionMode::InTable;
+            self.step(token);
         }
+    }
 
-        self.current_tag_name.clear();
-        self.pending_attributes.clear();
-        Ok(())
+    /// Close the open row, returning whether there was one
+    fn close_row(&mut self) -> bool {
+        if !self.has_in_scope(&["tr"], Scope::Table) {
+            return false;
+        }
+        self.clear_stack_back_to(&["tr", "template", "html"]);
+        self.open.pop();
+        self.mode = InsertionMode::InTableBody;
+        true
     }
 
-    /// Finish self-closing tag
-    fn finish_self_closing_tag(&mut self) -> Result<()> {
-        // Finish any pending attribute first
-        self.finish_attribute()?;
-        
-        let tag_name = self.current_tag_name.to_lowercase();
-        let mut element = Element::new(tag_name.clone());
-        // Set attributes after creation
-        for (name, value) in &self.pending_attributes {
-            element.set_attribute(name.clone(), value.clone());
+    fn in_row(&mut self, token: HtmlToken) {
+        match &token {
+            HtmlToken::StartTag { name, .. } if matches!(name.as_str(), "th" | "td") => {
+                let HtmlToken::StartTag { name, attributes, .. } = token else { return };
+                self.clear_stack_back_to(&["tr", "template", "html"]);
+                self.insert_element(&name, attributes);
+                self.mode = InsertionMode::InCell;
+                self.formatting.push(FormattingEntry::Marker);
+            }
+            HtmlToken::EndTag { name } if name == "tr" => {
+                self.close_row();
+            }
+            HtmlToken::StartTag { name, .. }
+                if matches!(name.as_str(), "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead" | "tr") =>
+            {
+                if self.close_row() {
+                    self.step(token);
+                }
+            }
+            HtmlToken::EndTag { name } if name == "table" => {
+                if self.close_row() {
+                    self.step(token);
+                }
+            }
+            HtmlToken::EndTag { name } if matches!(name.as_str(), "tbody" | "tfoot" | "thead") => {
+                if self.has_in_scope(&[name.as_str()], Scope::Table) && self.close_row() {
+                    self.step(token);
+                }
+            }
+            HtmlToken::EndTag { name }
+                if matches!(name.as_str(), "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th") => {}
+            _ => self.in_table(token),
         }
+    }
 
-        self.add_element_to_parent(element);
-        self.current_tag_name.clear();
-        self.pending_attributes.clear();
-        Ok(())
+    /// Close the open cell
+    fn close_cell(&mut self) {
+        self.generate_implied_end_tags(None);
+        self.pop_until(&["td", "th"]);
+        self.clear_formatting_to_marker();
+        self.mode = InsertionMode::InRow;
     }
 
-    /// Finish closing tag
-    fn finish_closing_tag(&mut self) -> Result<()> {
-        let tag_name = self.current_tag_name.to_lowercase();
-        
-        // Find matching opening tag
-        while let Some(element) = self.stack.pop() {
-            if element.tag_name == tag_name {
-                self.add_element_to_parent(element);
-                break;
-            } else {
-                // Mismatched tag - add to parent anyway
-                if let Some(parent) = self.stack.last_mut() {
-                    parent.children.push(Node::Element(element));
-                } else {
-                    self.document.root.children.push(Node::Element(element));
+    fn in_cell(&mut self, token: HtmlToken) {
+        match &token {
+            HtmlToken::EndTag { name } if matches!(name.as_str(), "td" | "th") => {
+                if self.has_in_scope(&[name.as_str()], Scope::Table) {
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&[name.as_str()]);
+                    self.clear_formatting_to_marker();
+                    self.mode = InsertionMode::InRow;
+                }
+            }
+            HtmlToken::StartTag { name, .. }
+                if matches!(name.as_str(), "caption" | "col" | "colgroup" | "tbody" | "td" | "tfoot" | "th" | "thead" | "tr") =>
+            {
+                if self.has_in_scope(&["td", "th"], Scope::Table) {
+                    self.close_cell();
+                    self.step(token);
                 }
             }
+            HtmlToken::EndTag { name } if matches!(name.as_str(), "body" | "caption" | "col" | "colgroup" | "html") => {}
+            HtmlToken::EndTag { name } if matches!(name.as_str(), "table" | "tbody" | "tfoot" | "thead" | "tr") => {
+                if self.has_in_scope(&[name.as_str()], Scope::Table) {
+                    self.close_cell();
+                    self.step(token);
+                }
+            }
+            _ => self.in_body(token),
         }
+    }
 
-        self.current_tag_name.clear();
-        Ok(())
+    /// Close the open select element and pick the insertion mode again
+    fn close_select(&mut self) -> bool {
+        if !self.has_in_scope(&["select"], Scope::Select) {
+            return false;
+        }
+        self.pop_until(&["select"]);
+        self.reset_insertion_mode();
+        true
     }
 
-    /// Finish attribute
-    fn finish_attribute(&mut self) -> Result<()> {
-        if !self.current_attribute_name.is_empty() {
-            let name = self.current_attribute_name.clone();
-            let value = self.current_attribute_value.clone();
-            self.pending_attributes.insert(name, value);
+    fn in_select(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Characters(text) => {
+                let text: String = text.chars().filter(|&c| c != '\0').collect();
+                self.insert_text(&text);
+            }
+            HtmlToken::Comment(text) => self.insert_comment(&text, None),
+            HtmlToken::Doctype { .. } => {}
+            HtmlToken::StartTag { name, attributes, self_closing } => match name.as_str() {
+                "html" => self.in_body(HtmlToken::StartTag { name, attributes, self_closing }),
+                "option" | "optgroup" | "hr" => {
+                    if self.current_is(&["option"]) {
+                        self.open.pop();
+                    }
+                    if name != "option" && self.current_is(&["optgroup"]) {
+                        self.open.pop();
+                    }
+                    self.insert_element(&name, attributes);
+                    if name == "hr" {
+                        self.open.pop();
+                    }
+                }
+                "select" => {
+                    self.close_select();
+                }
+                "input" | "keygen" | "textarea" => {
+                    let token = HtmlToken::StartTag { name, attributes, self_closing };
+                    if self.close_select() {
+                        self.step(token);
+                    }
+                }
+                "script" => self.in_head(HtmlToken::StartTag { name, attributes, self_closing }),
+                _ => {}
+            },
+            HtmlToken::EndTag { name } => match name.as_str() {
+                "optgroup" => {
+                    let length = self.open.len();
+                    if self.current_is(&["option"]) && length >= 2 && self.name(self.open[length - 2]) == "optgroup" {
+                        self.open.pop();
+                    }
+                    if self.current_is(&["optgroup"]) {
+                        self.open.pop();
+                    }
+                }
+                "option" if self.current_is(&["option"]) => {
+                    self.open.pop();
+                }
+                "select" => {
+                    self.close_select();
+                }
+                _ => {}
+            },
+            HtmlToken::Eof => self.in_body(token),
         }
-        
-        self.current_attribute_name.clear();
-        self.current_attribute_value.clear();
-        Ok(())
     }
 
-    /// Add element to parent
-    fn add_element_to_parent(&mut self, element: Element) {
-        if let Some(parent) = self.stack.last_mut() {
-            parent.children.push(Node::Element(element));
-        } else {
-            self.document.root.children.push(Node::Element(element));
+    fn in_select_in_table(&mut self, token: HtmlToken) {
+        const TABLE_TAGS: &[&str] = &["caption", "table", "tbody", "tfoot", "thead", "tr", "td", "th"];
+        match &token {
+            HtmlToken::StartTag { name, .. } if TABLE_TAGS.contains(&name.as_str()) => {
+                self.pop_until(&["select"]);
+                self.reset_insertion_mode();
+                self.step(token);
+            }
+            HtmlToken::EndTag { name } if TABLE_TAGS.contains(&name.as_str()) => {
+                if self.has_in_scope(&[name.as_str()], Scope::Table) {
+                    self.pop_until(&["select"]);
+                    self.reset_insertion_mode();
+                    self.step(token);
+                }
+            }
+            _ => self.in_select(token),
         }
     }
 
-    /// Add text node
-    fn add_text_node(&mut self) {
-        if !self.current_text.is_empty() {
-            let text_node = TextNode {
-                content: self.current_text.clone(),
-            };
-            
-            if let Some(parent) = self.stack.last_mut() {
-                parent.children.push(Node::Text(text_node));
-            } else {
-                self.document.root.children.push(Node::Text(text_node));
+    fn after_body(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Characters(ref text) if text.chars().all(is_whitespace) => self.in_body(token),
+            HtmlToken::Comment(text) => {
+                let html = self.open.first().copied().unwrap_or(DOCUMENT);
+                self.insert_comment(&text, Some(html));
+            }
+            HtmlToken::Doctype { .. } => {}
+            HtmlToken::StartTag { ref name, .. } if name == "html" => self.in_body(token),
+            HtmlToken::EndTag { ref name } if name == "html" => {
+                if self.context.is_none() {
+                    self.mode = InsertionMode::AfterAfterBody;
+                }
+            }
+            HtmlToken::Eof => self.done = true,
+            token => {
+                self.mode = InsertionMode::InBody;
+                self.step(token);
             }
         }
-        self.current_text.clear();
     }
 
-    /// Check if tag is self-closing
-    fn is_self_closing_tag(tag_name: &str) -> bool {
-        matches!(
-            tag_name,
-            "area" | "base" | "br" | "col" | "embed" | "hr" | "img" | "input" | "link" | "meta" | "param" | "source" | "track" | "wbr"
-        )
+    fn after_after_body(&mut self, token: HtmlToken) {
+        match token {
+            HtmlToken::Comment(text) => self.insert_comment(&text, Some(DOCUMENT)),
+            HtmlToken::Doctype { .. } => {}
+            HtmlToken::Characters(ref text) if text.chars().all(is_whitespace) => self.in_body(token),
+            HtmlToken::StartTag { ref name, .. } if name == "html" => self.in_body(token),
+            HtmlToken::Eof => self.done = true,
+            token => {
+                self.mode = InsertionMode::InBody;
+                self.step(token);
+            }
+        }
     }
 }
 
-impl Default for HtmlParser {
-    fn default() -> Self {
-        Self::new()
+/// Whether a character is HTML whitespace
+fn is_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ')
+}
+
+/// Whether an `<input>` start tag is a hidden input
+fn is_hidden_input(attributes: &[(String, String)]) -> bool {
+    attributes.iter().any(|(name, value)| name == "type" && value.eq_ignore_ascii_case("hidden"))
+}
+
+/// Whether two elements have the same tag name and attributes
+fn same_token(a: &(String, Vec<(String, String)>), b: &(String, Vec<(String, String)>)) -> bool {
+    a.0 == b.0 && a.1.len() == b.1.len() && a.1.iter().all(|attribute| b.1.contains(attribute))
+}
+
+/// Decode document bytes, returning the text and its character set
+fn decode(bytes: &[u8]) -> (String, &'static str) {
+    let utf16 = |bytes: &[u8], to_unit: fn([u8; 2]) -> u16| -> String {
+        char::decode_utf16(bytes.chunks_exact(2).map(|pair| to_unit([pair[0], pair[1]])))
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect()
+    };
+
+    if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        (String::from_utf8_lossy(rest).into_owned(), "UTF-8")
+    } else if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+        (utf16(rest, u16::from_le_bytes), "UTF-16LE")
+    } else if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+        (utf16(rest, u16::from_be_bytes), "UTF-16BE")
+    } else {
+        (String::from_utf8_lossy(bytes).into_owned(), "UTF-8")
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::serializer::DomSerializer;
+
+    /// Parse a document and serialize its body's contents
+    fn body_html(html: &str) -> String {
+        let document = HtmlParser::parse(html.as_bytes()).unwrap();
+        DomSerializer::serialize_element(document.body().unwrap(), false)
+    }
+
+    /// Tag names of the element children of an element
+    fn child_tags(element: &Element) -> Vec<&str> {
+        element.children.iter().filter_map(|child| match child {
+            Node::Element(element) => Some(element.tag_name.as_str()),
+            _ => None,
+        }).collect()
+    }
 
     #[test]
     fn test_parse_simple_html() {
-        let mut parser = HtmlParser::new();
         let html = "<html><head><title>Test</title></head><body><h1>Hello</h1></body></html>";
-        let document = parser.parse(html).unwrap();
-        
-        assert_eq!(document.root.children.len(), 1);
-        if let Node::Element(html_element) = &document.root.children[0] {
-            assert_eq!(html_element.tag_name, "html");
-            assert_eq!(html_element.children.len(), 2); // head and body
-        }
+        let document = HtmlParser::parse(html.as_bytes()).unwrap();
+
+        assert_eq!(document.root.tag_name, "html");
+        assert_eq!(child_tags(&document.root), ["head", "body"]);
+        assert_eq!(document.title.as_deref(), Some("Test"));
     }
 
     #[test]
     fn test_parse_with_attributes() {
-        let mut parser = HtmlParser::new();
-        let html = r#"<div class="container" id="main">Content</div>"#;
-        let document = parser.parse(html).unwrap();
-        
-        if let Node::Element(div_element) = &document.root.children[0] {
-            assert_eq!(div_element.tag_name, "div");
-            assert_eq!(div_element.attributes.get("class"), Some(&"container".to_string()));
-            assert_eq!(div_element.attributes.get("id"), Some(&"main".to_string()));
-            assert_eq!(div_element.children.len(), 1);
-            
-            if let Node::Text(text_node) = &div_element.children[0] {
-                assert_eq!(text_node.content, "Content");
-            }
-        }
+        let html = r#"<div class="container" id="main" class="ignored" data-x=1>Content</div>"#;
+        let document = HtmlParser::parse(html.as_bytes()).unwrap();
+
+        let div_element = document.get_element_by_id("main").unwrap();
+        assert_eq!(div_element.tag_name, "div");
+        assert_eq!(div_element.attributes.get("class"), Some(&"container".to_string()));
+        assert_eq!(div_element.attributes.get("data-x"), Some(&"1".to_string()));
+        assert_eq!(div_element.text_content(), "Content");
     }
 
     #[test]
     fn test_parse_self_closing_tags() {
-        let mut parser = HtmlParser::new();
-        let html = r#"<img src="test.jpg" alt="Test" /><br />"#;
-        let document = parser.parse(html).unwrap();
-        
+        let body = body_html(r#"<img src="test.jpg" alt="Test" /><br /><div/>text"#);
 
-        
-        assert_eq!(document.root.children.len(), 2);
-        
-        if let Node::Element(img_element) = &document.root.children[0] {
-            assert_eq!(img_element.tag_name, "img");
-            assert_eq!(img_element.attributes.get("src"), Some(&"test.jpg".to_string()));
-            assert_eq!(img_element.attributes.get("alt"), Some(&"Test".to_string()));
-        }
-        
-        if let Node::Element(br_element) = &document.root.children[1] {
-            assert_eq!(br_element.tag_name, "br");
-        }
+        // Only void elements have no contents, a self-closing div is left open
+        assert_eq!(body, r#"<img alt="Test" src="test.jpg"><br><div>text</div>"#);
     }
 
     #[test]
     fn test_parse_nested_elements() {
-        let mut parser = HtmlParser::new();
-        let html = r#"<div><p>Paragraph <strong>bold</strong> text</p></div>"#;
-        let document = parser.parse(html).unwrap();
-        
-        if let Node::Element(div_element) = &document.root.children[0] {
-            assert_eq!(div_element.tag_name, "div");
-            assert_eq!(div_element.children.len(), 1);
-            
-            if let Node::Element(p_element) = &div_element.children[0] {
-                assert_eq!(p_element.tag_name, "p");
-                assert_eq!(p_element.children.len(), 3); // text, strong, text
-                
-                if let Node::Text(text1) = &p_element.children[0] {
-                    assert_eq!(text1.content, "Paragraph ");
-                }
-                
-                if let Node::Element(strong_element) = &p_element.children[1] {
-                    assert_eq!(strong_element.tag_name, "strong");
-                    if let Node::Text(text2) = &strong_element.children[0] {
-                        assert_eq!(text2.content, "bold");
-                    }
-                }
-                
-                if let Node::Text(text3) = &p_element.children[2] {
-                    assert_eq!(text3.content, " text");
-                }
-            }
-        }
+        let body = body_html("<div><p>Paragraph <strong>bold</strong> text</p></div>");
+        assert_eq!(body, "<div><p>Paragraph <strong>bold</strong> text</p></div>");
     }
 
     #[test]
     fn test_parse_with_comments() {
-        let mut parser = HtmlParser::new();
-        let html = r#"<div>Content<!-- This is a comment -->more content</div>"#;
-        let document = parser.parse(html).unwrap();
-        
-        if let Node::Element(div_element) = &document.root.children[0] {
-            assert_eq!(div_element.tag_name, "div");
-            assert_eq!(div_element.children.len(), 2); // text nodes, comment is ignored
-            
-            if let Node::Text(text1) = &div_element.children[0] {
-                assert_eq!(text1.content, "Content");
-            }
-            
-            if let Node::Text(text2) = &div_element.children[1] {
-                assert_eq!(text2.content, "more content");
-            }
+        let document = HtmlParser::parse(b"<div>Content<!-- This is a comment -->more content</div>").unwrap();
+        let body = document.body().unwrap();
+
+        if let Node::Element(div_element) = &body.children[0] {
+            assert_eq!(div_element.children, [
+                Node::Text(TextNode::new("Content".to_string())),
+                Node::Comment(CommentNode::new(" This is a comment ".to_string())),
+                Node::Text(TextNode::new("more content".to_string())),
+            ]);
+        } else {
+            panic!("expected a div");
         }
     }
+
+    #[test]
+    fn test_implied_html_head_and_body() {
+        let document = HtmlParser::parse(b"<title>Implied</title><p>Text").unwrap();
+
+        assert_eq!(child_tags(&document.root), ["head", "body"]);
+        assert_eq!(child_tags(document.head().unwrap()), ["title"]);
+        assert_eq!(DomSerializer::serialize_element(document.body().unwrap(), false), "<p>Text</p>");
+
+        // Empty input still has the document structure
+        let document = HtmlParser::parse(b"").unwrap();
+        assert_eq!(child_tags(&document.root), ["head", "body"]);
+    }
+
+    #[test]
+    fn test_omitted_end_tags() {
+        assert_eq!(
+            body_html("<p>One<p>Two<div>Block</div><ul><li>A<li>B</ul><dl><dt>T<dd>D</dl>"),
+            "<p>One</p><p>Two</p><div>Block</div><ul><li>A</li><li>B</li></ul><dl><dt>T</dt><dd>D</dd></dl>",
+        );
+
+        // A stray end tag creates an empty paragraph
+        assert_eq!(body_html("<div></p>text</div>"), "<div><p></p>text</div>");
+    }
+
+    #[test]
+    fn test_misnested_formatting_elements() {
+        assert_eq!(body_html("<b>1<i>2</b>3</i>4"), "<b>1<i>2</i></b><i>3</i>4");
+
+        // Formatting is carried into the block that interrupted it
+        assert_eq!(body_html("<a href=x>1<p>2</a>3</p>"), r#"<a href="x">1</a><p><a href="x">2</a>3</p>"#);
+        assert_eq!(body_html("<p><b>bold<p>still bold"), "<p><b>bold</b></p><p><b>still bold</b></p>");
+    }
+
+    #[test]
+    fn test_foster_parenting() {
+        assert_eq!(
+            body_html("<table>before<tr><td>cell</td></tr><b>bold</b></table>after"),
+            "before<b>bold</b><table><tbody><tr><td>cell</td></tr></tbody></table>after",
+        );
+        assert_eq!(
+            body_html("<table><tr><td>A<td>B<tr><td>C</table>"),
+            "<table><tbody><tr><td>A</td><td>B</td></tr><tr><td>C</td></tr></tbody></table>",
+        );
+    }
+
+    #[test]
+    fn test_raw_text_elements() {
+        let document = HtmlParser::parse(
+            b"<script>if (a < b && c) { document.write('</p><b>'); }</script><style>p > a { }</style><textarea>\n&lt;b&gt;</textarea>",
+        ).unwrap();
+
+        let script = document.get_elements_by_tag_name("script")[0];
+        assert_eq!(script.text_content(), "if (a < b && c) { document.write('</p><b>'); }");
+        assert_eq!(document.get_elements_by_tag_name("style")[0].text_content(), "p > a { }");
+        assert_eq!(document.get_elements_by_tag_name("textarea")[0].text_content(), "<b>");
+        assert!(document.get_elements_by_tag_name("b").is_empty());
+    }
+
+    #[test]
+    fn test_parse_real_world_document() {
+        let html = "\u{FEFF}<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+  <meta charset=\"utf-8\">
+  <title>Matte &amp; Friends</title>
+  <link rel=\"stylesheet\" href=\"style.css\">
+</head>
+<body class=\"home\">
+  <ul id=\"nav\"><li><a href=\"/\">Home</a><li><a href=\"/about\">About</a></ul>
+  <p>First paragraph
+  <p>Second with <b>bold <i>and italic</b> text</i>
+  <table><tr><td>A<td>B</table>
+  <script>if (1 < 2 && \"</div>\") {}</script>
+</body>
+</html>
+";
+        let document = HtmlParser::parse(html.as_bytes()).unwrap();
+
+        assert_eq!(document.root.get_attribute("lang").map(String::as_str), Some("en"));
+        assert_eq!(child_tags(&document.root), ["head", "body"]);
+        assert_eq!(child_tags(document.head().unwrap()), ["meta", "title", "link"]);
+        assert_eq!(document.title.as_deref(), Some("Matte & Friends"));
+        assert_eq!(document.character_set, "UTF-8");
+
+        let body = document.body().unwrap();
+        assert_eq!(body.get_attribute("class").map(String::as_str), Some("home"));
+        assert_eq!(child_tags(body), ["ul", "p", "p", "table", "script"]);
+
+        let nav = document.get_element_by_id("nav").unwrap();
+        assert_eq!(
+            DomSerializer::serialize_element(nav, false),
+            r#"<li><a href="/">Home</a></li><li><a href="/about">About</a></li>"#,
+        );
+
+        let paragraphs = document.get_elements_by_tag_name("p");
+        assert_eq!(
+            DomSerializer::serialize_element(paragraphs[1], false),
+            "Second with <b>bold <i>and italic</i></b><i> text</i>\n  ",
+        );
+        assert_eq!(
+            DomSerializer::serialize_element(document.get_elements_by_tag_name("table")[0], false),
+            "<tbody><tr><td>A</td><td>B</td></tr></tbody>",
+        );
+        assert_eq!(document.get_elements_by_tag_name("script")[0].text_content(), "if (1 < 2 && \"</div>\") {}");
+    }
+
+    #[test]
+    fn test_deep_nesting_is_flattened() {
+        let document = HtmlParser::parse("<div>".repeat(10_000).as_bytes()).unwrap();
+
+        let mut depth = 0;
+        let mut element = &document.root;
+        while let Some(Node::Element(child)) = element.children.last() {
+            depth += 1;
+            element = child;
+        }
+        assert_eq!(depth, MAX_TREE_DEPTH - 1);
+    }
+
+    #[test]
+    fn test_parse_fragment() {
+        let nodes = HtmlParser::parse_fragment("<li>One<li>Two").unwrap();
+        assert_eq!(nodes.len(), 2);
+
+        let nodes = HtmlParser::parse_fragment("text <b>bold</b>").unwrap();
+        assert_eq!(nodes[0], Node::Text(TextNode::new("text ".to_string())));
+        assert!(matches!(&nodes[1], Node::Element(element) if element.tag_name == "b"));
+
+        // The context element picks the insertion mode and tokenizer state
+        let nodes = HtmlParser::parse_fragment_in("<td>Cell", "tr").unwrap();
+        assert!(matches!(&nodes[0], Node::Element(element) if element.tag_name == "td"));
+        let nodes = HtmlParser::parse_fragment_in("<b>raw</b>", "textarea").unwrap();
+        assert_eq!(nodes, [Node::Text(TextNode::new("<b>raw</b>".to_string()))]);
+    }
 }
@@ -0,0 +1,876 @@
+//! HTML tokenizer.
+//!
+//! This module implements the tokenization stage of the HTML parsing
+//! algorithm. The tree builder drives the tokenizer one token at a time and
+//! switches it into the RCDATA, RAWTEXT, script data or PLAINTEXT state for
+//! elements whose contents are not markup.
+
+use std::collections::VecDeque;
+
+/// HTML token types
+#[derive(Debug, Clone, PartialEq)]
+pub enum HtmlToken {
+    /// DOCTYPE declaration
+    Doctype {
+        name: Option<String>,
+        public_id: Option<String>,
+        system_id: Option<String>,
+        force_quirks: bool,
+    },
+    /// Start tag, with attributes in source order
+    StartTag {
+        name: String,
+        attributes: Vec<(String, String)>,
+        self_closing: bool,
+    },
+    /// End tag
+    EndTag { name: String },
+    /// Comment
+    Comment(String),
+    /// Run of character data, with character references decoded
+    Characters(String),
+    /// End of input
+    Eof,
+}
+
+/// Tokenizer states
+///
+/// Script data is tokenized like RAWTEXT: the escaped states for `<!--`
+/// inside scripts are not implemented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizerState {
+    Data,
+    Rcdata,
+    Rawtext,
+    ScriptData,
+    Plaintext,
+    TagOpen,
+    EndTagOpen,
+    TagName,
+    /// `<` in RCDATA, RAWTEXT or script data, which is the state to return to
+    RawLessThanSign(RawKind),
+    RawEndTagOpen(RawKind),
+    RawEndTagName(RawKind),
+    BeforeAttributeName,
+    AttributeName,
+    AfterAttributeName,
+    BeforeAttributeValue,
+    AttributeValueQuoted(char),
+    AttributeValueUnquoted,
+    AfterAttributeValueQuoted,
+    SelfClosingStartTag,
+    BogusComment,
+    MarkupDeclarationOpen,
+    CommentStart,
+    CommentStartDash,
+    Comment,
+    CommentEndDash,
+    CommentEnd,
+    CommentEndBang,
+    Doctype,
+    BeforeDoctypeName,
+    DoctypeName,
+    AfterDoctypeName,
+    BeforeDoctypeIdentifier(DoctypeIdentifier),
+    DoctypeIdentifierQuoted(DoctypeIdentifier, char),
+    AfterDoctypeIdentifier(DoctypeIdentifier),
+    BogusDoctype,
+}
+
+/// Text content models that end only at an appropriate end tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawKind {
+    Rcdata,
+    Rawtext,
+    ScriptData,
+}
+
+impl RawKind {
+    fn state(self) -> TokenizerState {
+        match self {
+            RawKind::Rcdata => TokenizerState::Rcdata,
+            RawKind::Rawtext => TokenizerState::Rawtext,
+            RawKind::ScriptData => TokenizerState::ScriptData,
+        }
+    }
+}
+
+/// Identifiers of a DOCTYPE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoctypeIdentifier {
+    Public,
+    System,
+}
+
+/// Tag being built
+#[derive(Debug, Default)]
+struct TagBuilder {
+    name: String,
+    is_end: bool,
+    self_closing: bool,
+    attributes: Vec<(String, String)>,
+    attribute_name: String,
+    attribute_value: String,
+    has_attribute: bool,
+}
+
+/// HTML tokenizer
+pub struct HtmlTokenizer {
+    /// Input characters, with newlines normalized
+    input: Vec<char>,
+    /// Position of the next character
+    position: usize,
+    /// Current state
+    state: TokenizerState,
+    /// Tokens ready to be returned
+    pending: VecDeque<HtmlToken>,
+    /// Character data not yet emitted
+    text: String,
+    /// Whether `text` has character references to decode
+    text_has_references: bool,
+    /// Tag being built
+    tag: TagBuilder,
+    /// Comment being built
+    comment: String,
+    /// DOCTYPE being built
+    doctype: (Option<String>, Option<String>, Option<String>, bool),
+    /// Characters consumed while looking for an end tag in raw text
+    temporary_buffer: String,
+    /// Name of the last start tag emitted
+    last_start_tag: Option<String>,
+    /// Whether the end of input has been emitted
+    finished: bool,
+}
+
+impl HtmlTokenizer {
+    /// Create a tokenizer for the given input
+    pub fn new(input: &str) -> Self {
+        // Normalize CRLF and CR to LF
+        let input = input.replace("\r\n", "\n").replace('\r', "\n");
+        Self {
+            input: input.chars().collect(),
+            position: 0,
+            state: TokenizerState::Data,
+            pending: VecDeque::new(),
+            text: String::new(),
+            text_has_references: false,
+            tag: TagBuilder::default(),
+            comment: String::new(),
+            doctype: (None, None, None, false),
+            temporary_buffer: String::new(),
+            last_start_tag: None,
+            finished: false,
+        }
+    }
+
+    /// Switch the tokenizer state, as the tree builder does for raw text elements
+    pub fn set_state(&mut self, state: TokenizerState) {
+        self.state = state;
+    }
+
+    /// Set the last start tag, so raw text in a fragment ends at its end tag
+    pub fn set_last_start_tag(&mut self, name: &str) {
+        self.last_start_tag = Some(name.to_string());
+    }
+
+    /// Tokenize the whole input
+    pub fn tokenize(&mut self) -> Vec<HtmlToken> {
+        let mut tokens = Vec::new();
+        loop {
+            let token = self.next_token();
+            let done = token == HtmlToken::Eof;
+            tokens.push(token);
+            if done {
+                return tokens;
+            }
+        }
+    }
+
+    /// Return the next token
+    pub fn next_token(&mut self) -> HtmlToken {
+        while self.pending.is_empty() {
+            if self.finished {
+                return HtmlToken::Eof;
+            }
+            self.step();
+        }
+        self.pending.pop_front().unwrap_or(HtmlToken::Eof)
+    }
+
+    /// Consume the next character
+    fn consume(&mut self) -> Option<char> {
+        let c = self.input.get(self.position).copied();
+        self.position += 1;
+        c
+    }
+
+    /// Give back the last character, to be consumed again in another state
+    fn reconsume(&mut self, state: TokenizerState) {
+        self.position -= 1;
+        self.state = state;
+    }
+
+    /// Whether the input continues with a string, consuming it if so
+    fn consume_if(&mut self, expected: &str, ignore_case: bool) -> bool {
+        let end = self.position + expected.chars().count();
+        let Some(next) = self.input.get(self.position..end) else {
+            return false;
+        };
+        let matches = next.iter().zip(expected.chars()).all(|(&a, b)| {
+            if ignore_case { a.eq_ignore_ascii_case(&b) } else { a == b }
+        });
+        if matches {
+            self.position = end;
+        }
+        matches
+    }
+
+    /// Append character data
+    fn push_text(&mut self, c: char) {
+        if c == '&' && matches!(self.state, TokenizerState::Data | TokenizerState::Rcdata) {
+            self.text_has_references = true;
+        }
+        self.text.push(c);
+    }
+
+    /// Emit pending character data
+    fn flush_text(&mut self) {
+        if self.text.is_empty() {
+            return;
+        }
+        let text = std::mem::take(&mut self.text);
+        let text = if self.text_has_references { decode_character_references(&text) } else { text };
+        self.text_has_references = false;
+        self.pending.push_back(HtmlToken::Characters(text));
+    }
+
+    /// Emit a token, after any pending character data
+    fn emit(&mut self, token: HtmlToken) {
+        self.flush_text();
+        self.pending.push_back(token);
+    }
+
+    /// Emit the end of input
+    fn emit_eof(&mut self) {
+        self.emit(HtmlToken::Eof);
+        self.finished = true;
+    }
+
+    /// Start a new tag
+    fn start_tag(&mut self, is_end: bool) {
+        self.tag = TagBuilder {
+            is_end,
+            ..TagBuilder::default()
+        };
+    }
+
+    /// Start a new attribute on the current tag
+    fn start_attribute(&mut self) {
+        self.finish_attribute();
+        self.tag.has_attribute = true;
+    }
+
+    /// Add the attribute being built to the tag, unless it is a duplicate
+    fn finish_attribute(&mut self) {
+        if !std::mem::take(&mut self.tag.has_attribute) {
+            return;
+        }
+        let name = std::mem::take(&mut self.tag.attribute_name);
+        let value = std::mem::take(&mut self.tag.attribute_value);
+        if !self.tag.attributes.iter().any(|(existing, _)| *existing == name) {
+            let value = if value.contains('&') { decode_character_references(&value) } else { value };
+            self.tag.attributes.push((name, value));
+        }
+    }
+
+    /// Emit the tag being built
+    fn emit_tag(&mut self) {
+        self.finish_attribute();
+        let tag = std::mem::take(&mut self.tag);
+        if tag.is_end {
+            self.emit(HtmlToken::EndTag { name: tag.name });
+        } else {
+            self.last_start_tag = Some(tag.name.clone());
+            self.emit(HtmlToken::StartTag {
+                name: tag.name,
+                attributes: tag.attributes,
+                self_closing: tag.self_closing,
+            });
+        }
+    }
+
+    /// Emit the comment being built
+    fn emit_comment(&mut self) {
+        let comment = std::mem::take(&mut self.comment);
+        self.emit(HtmlToken::Comment(comment));
+    }
+
+    /// Emit the DOCTYPE being built
+    fn emit_doctype(&mut self, force_quirks: bool) {
+        let (name, public_id, system_id, quirks) = std::mem::take(&mut self.doctype);
+        self.emit(HtmlToken::Doctype {
+            name,
+            public_id,
+            system_id,
+            force_quirks: quirks || force_quirks,
+        });
+    }
+
+    /// Whether the end tag being built closes the last start tag
+    fn is_appropriate_end_tag(&self) -> bool {
+        self.last_start_tag.as_deref() == Some(self.tag.name.as_str())
+    }
+
+    /// Run the current state on the next character
+    fn step(&mut self) {
+        use TokenizerState::*;
+
+        let c = self.consume();
+        match self.state {
+            Data => match c {
+                Some('<') => self.state = TagOpen,
+                Some(c) => self.push_text(c),
+                None => self.emit_eof(),
+            },
+            Rcdata | Rawtext | ScriptData => {
+                let kind = match self.state {
+                    Rcdata => RawKind::Rcdata,
+                    Rawtext => RawKind::Rawtext,
+                    _ => RawKind::ScriptData,
+                };
+                match c {
+                    Some('<') => self.state = RawLessThanSign(kind),
+                    Some('\0') => self.push_text('\u{FFFD}'),
+                    Some(c) => self.push_text(c),
+                    None => self.emit_eof(),
+                }
+            }
+            Plaintext => match c {
+                Some('\0') => self.push_text('\u{FFFD}'),
+                Some(c) => self.push_text(c),
+                None => self.emit_eof(),
+            },
+            TagOpen => match c {
+                Some('!') => self.state = MarkupDeclarationOpen,
+                Some('/') => self.state = EndTagOpen,
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.start_tag(false);
+                    self.reconsume(TagName);
+                }
+                Some('?') => {
+                    self.comment.clear();
+                    self.reconsume(BogusComment);
+                }
+                Some(_) => {
+                    self.push_text('<');
+                    self.reconsume(Data);
+                }
+                None => {
+                    self.push_text('<');
+                    self.emit_eof();
+                }
+            },
+            EndTagOpen => match c {
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.start_tag(true);
+                    self.reconsume(TagName);
+                }
+                Some('>') => self.state = Data,
+                Some(_) => {
+                    self.comment.clear();
+                    self.reconsume(BogusComment);
+                }
+                None => {
+                    self.push_text('<');
+                    self.push_text('/');
+                    self.emit_eof();
+                }
+            },
+            TagName => match c {
+                Some('\t' | '\n' | '\x0C' | ' ') => self.state = BeforeAttributeName,
+                Some('/') => self.state = SelfClosingStartTag,
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_tag();
+                }
+                Some('\0') => self.tag.name.push('\u{FFFD}'),
+                Some(c) => self.tag.name.push(c.to_ascii_lowercase()),
+                None => self.emit_eof(),
+            },
+            RawLessThanSign(kind) => match c {
+                Some('/') => {
+                    self.temporary_buffer.clear();
+                    self.state = RawEndTagOpen(kind);
+                }
+                _ => {
+                    self.push_text('<');
+                    self.reconsume(kind.state());
+                }
+            },
+            RawEndTagOpen(kind) => match c {
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.start_tag(true);
+                    self.reconsume(RawEndTagName(kind));
+                }
+                _ => {
+                    self.push_text('<');
+                    self.push_text('/');
+                    self.reconsume(kind.state());
+                }
+            },
+            RawEndTagName(kind) => match c {
+                Some('\t' | '\n' | '\x0C' | ' ') if self.is_appropriate_end_tag() => self.state = BeforeAttributeName,
+                Some('/') if self.is_appropriate_end_tag() => self.state = SelfClosingStartTag,
+                Some('>') if self.is_appropriate_end_tag() => {
+                    self.state = Data;
+                    self.emit_tag();
+                }
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.tag.name.push(c.to_ascii_lowercase());
+                    self.temporary_buffer.push(c);
+                }
+                _ => {
+                    // Not the end of the raw text, so the characters were text
+                    self.push_text('<');
+                    self.push_text('/');
+                    for c in std::mem::take(&mut self.temporary_buffer).chars() {
+                        self.push_text(c);
+                    }
+                    self.reconsume(kind.state());
+                }
+            },
+            BeforeAttributeName => match c {
+                Some('\t' | '\n' | '\x0C' | ' ') => {}
+                Some('/' | '>') | None => self.reconsume(AfterAttributeName),
+                Some('=') => {
+                    self.start_attribute();
+                    self.tag.attribute_name.push('=');
+                    self.state = AttributeName;
+                }
+                Some(_) => {
+                    self.start_attribute();
+                    self.reconsume(AttributeName);
+                }
+            },
+            AttributeName => match c {
+                Some('\t' | '\n' | '\x0C' | ' ' | '/' | '>') | None => self.reconsume(AfterAttributeName),
+                Some('=') => self.state = BeforeAttributeValue,
+                Some('\0') => self.tag.attribute_name.push('\u{FFFD}'),
+                Some(c) => self.tag.attribute_name.push(c.to_ascii_lowercase()),
+            },
+            AfterAttributeName => match c {
+                Some('\t' | '\n' | '\x0C' | ' ') => {}
+                Some('/') => self.state = SelfClosingStartTag,
+                Some('=') => self.state = BeforeAttributeValue,
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_tag();
+                }
+                Some(_) => {
+                    self.start_attribute();
+                    self.reconsume(AttributeName);
+                }
+                None => self.emit_eof(),
+            },
+            BeforeAttributeValue => match c {
+                Some('\t' | '\n' | '\x0C' | ' ') => {}
+                Some(quote @ ('"' | '\'')) => self.state = AttributeValueQuoted(quote),
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_tag();
+                }
+                _ => self.reconsume(AttributeValueUnquoted),
+            },
+            AttributeValueQuoted(quote) => match c {
+                Some(c) if c == quote => self.state = AfterAttributeValueQuoted,
+                Some('\0') => self.tag.attribute_value.push('\u{FFFD}'),
+                Some(c) => self.tag.attribute_value.push(c),
+                None => self.emit_eof(),
+            },
+            AttributeValueUnquoted => match c {
+                Some('\t' | '\n' | '\x0C' | ' ') => self.state = BeforeAttributeName,
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_tag();
+                }
+                Some('\0') => self.tag.attribute_value.push('\u{FFFD}'),
+                Some(c) => self.tag.attribute_value.push(c),
+                None => self.emit_eof(),
+            },
+            AfterAttributeValueQuoted => match c {
+                Some('\t' | '\n' | '\x0C' | ' ') => self.state = BeforeAttributeName,
+                Some('/') => self.state = SelfClosingStartTag,
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_tag();
+                }
+                Some(_) => self.reconsume(BeforeAttributeName),
+                None => self.emit_eof(),
+            },
+            SelfClosingStartTag => match c {
+                Some('>') => {
+                    self.tag.self_closing = true;
+                    self.state = Data;
+                    self.emit_tag();
+                }
+                Some(_) => self.reconsume(BeforeAttributeName),
+                None => self.emit_eof(),
+            },
+            BogusComment => match c {
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_comment();
+                }
+                Some('\0') => self.comment.push('\u{FFFD}'),
+                Some(c) => self.comment.push(c),
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            MarkupDeclarationOpen => {
+                // Nothing is consumed unless one of the openings matches
+                self.position -= 1;
+                self.comment.clear();
+                if self.consume_if("--", false) {
+                    self.state = CommentStart;
+                } else if self.consume_if("DOCTYPE", true) {
+                    self.state = Doctype;
+                } else if self.consume_if("[CDATA[", false) {
+                    // CDATA sections are only allowed in foreign content
+                    self.comment.push_str("[CDATA[");
+                    self.state = BogusComment;
+                } else {
+                    self.state = BogusComment;
+                }
+            }
+            CommentStart => match c {
+                Some('-') => self.state = CommentStartDash,
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_comment();
+                }
+                _ => self.reconsume(Comment),
+            },
+            CommentStartDash => match c {
+                Some('-') => self.state = CommentEnd,
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_comment();
+                }
+                Some(_) => {
+                    self.comment.push('-');
+                    self.reconsume(Comment);
+                }
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            Comment => match c {
+                Some('-') => self.state = CommentEndDash,
+                Some('\0') => self.comment.push('\u{FFFD}'),
+                Some(c) => self.comment.push(c),
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            CommentEndDash => match c {
+                Some('-') => self.state = CommentEnd,
+                Some(_) => {
+                    self.comment.push('-');
+                    self.reconsume(Comment);
+                }
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            CommentEnd => match c {
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_comment();
+                }
+                Some('!') => self.state = CommentEndBang,
+                Some('-') => self.comment.push('-'),
+                Some(_) => {
+                    self.comment.push_str("--");
+                    self.reconsume(Comment);
+                }
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            CommentEndBang => match c {
+                Some('-') => {
+                    self.comment.push_str("--!");
+                    self.state = CommentEndDash;
+                }
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_comment();
+                }
+                Some(_) => {
+                    self.comment.push_str("--!");
+                    self.reconsume(Comment);
+                }
+                None => {
+                    self.emit_comment();
+                    self.emit_eof();
+                }
+            },
+            Doctype => {
+                self.doctype = (None, None, None, false);
+                match c {
+                    Some('\t' | '\n' | '\x0C' | ' ') => self.state = BeforeDoctypeName,
+                    Some(_) => self.reconsume(BeforeDoctypeName),
+                    None => {
+                        self.emit_doctype(true);
+                        self.emit_eof();
+                    }
+                }
+            }
+            BeforeDoctypeName => match c {
+                Some('\t' | '\n' | '\x0C' | ' ') => {}
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_doctype(true);
+                }
+                Some(c) => {
+                    let c = if c == '\0' { '\u{FFFD}' } else { c.to_ascii_lowercase() };
+                    self.doctype.0 = Some(c.to_string());
+                    self.state = DoctypeName;
+                }
+                None => {
+                    self.emit_doctype(true);
+                    self.emit_eof();
+                }
+            },
+            DoctypeName => match c {
+                Some('\t' | '\n' | '\x0C' | ' ') => self.state = AfterDoctypeName,
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_doctype(false);
+                }
+                Some(c) => {
+                    let c = if c == '\0' { '\u{FFFD}' } else { c.to_ascii_lowercase() };
+                    self.doctype.0.get_or_insert_with(String::new).push(c);
+                }
+                None => {
+                    self.emit_doctype(true);
+                    self.emit_eof();
+                }
+            },
+            AfterDoctypeName => match c {
+                Some('\t' | '\n' | '\x0C' | ' ') => {}
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_doctype(false);
+                }
+                Some(_) => {
+                    self.position -= 1;
+                    if self.consume_if("PUBLIC", true) {
+                        self.state = BeforeDoctypeIdentifier(DoctypeIdentifier::Public);
+                    } else if self.consume_if("SYSTEM", true) {
+                        self.state = BeforeDoctypeIdentifier(DoctypeIdentifier::System);
+                    } else {
+                        self.position += 1;
+                        self.doctype.3 = true;
+                        self.state = BogusDoctype;
+                    }
+                }
+                None => {
+                    self.emit_doctype(true);
+                    self.emit_eof();
+                }
+            },
+            BeforeDoctypeIdentifier(identifier) => match c {
+                Some('\t' | '\n' | '\x0C' | ' ') => {}
+                Some(quote @ ('"' | '\'')) => {
+                    match identifier {
+                        DoctypeIdentifier::Public => self.doctype.1 = Some(String::new()),
+                        DoctypeIdentifier::System => self.doctype.2 = Some(String::new()),
+                    }
+                    self.state = DoctypeIdentifierQuoted(identifier, quote);
+                }
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_doctype(true);
+                }
+                Some(_) => {
+                    self.doctype.3 = true;
+                    self.state = BogusDoctype;
+                }
+                None => {
+                    self.emit_doctype(true);
+                    self.emit_eof();
+                }
+            },
+            DoctypeIdentifierQuoted(identifier, quote) => match c {
+                Some(c) if c == quote => self.state = AfterDoctypeIdentifier(identifier),
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_doctype(true);
+                }
+                Some(c) => {
+                    let c = if c == '\0' { '\u{FFFD}' } else { c };
+                    let value = match identifier {
+                        DoctypeIdentifier::Public => &mut self.doctype.1,
+                        DoctypeIdentifier::System => &mut self.doctype.2,
+                    };
+                    value.get_or_insert_with(String::new).push(c);
+                }
+                None => {
+                    self.emit_doctype(true);
+                    self.emit_eof();
+                }
+            },
+            AfterDoctypeIdentifier(identifier) => match c {
+                Some('\t' | '\n' | '\x0C' | ' ') => {}
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_doctype(false);
+                }
+                Some(quote @ ('"' | '\'')) if identifier == DoctypeIdentifier::Public => {
+                    self.doctype.2 = Some(String::new());
+                    self.state = DoctypeIdentifierQuoted(DoctypeIdentifier::System, quote);
+                }
+                Some(_) => {
+                    self.doctype.3 |= identifier == DoctypeIdentifier::Public;
+                    self.state = BogusDoctype;
+                }
+                None => {
+                    self.emit_doctype(true);
+                    self.emit_eof();
+                }
+            },
+            BogusDoctype => match c {
+                Some('>') => {
+                    self.state = Data;
+                    self.emit_doctype(false);
+                }
+                Some(_) => {}
+                None => {
+                    self.emit_doctype(false);
+                    self.emit_eof();
+                }
+            },
+        }
+    }
+}
+
+/// Decode character references in text and attribute values
+///
+/// Only references terminated by `;` are decoded; anything that is not a
+/// known reference is kept as written.
+pub fn decode_character_references(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let reference = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let character = reference.and_then(|reference| match reference {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => {
+                let (digits, radix) = match reference.strip_prefix("#x").or_else(|| reference.strip_prefix("#X")) {
+                    Some(hex) => (hex, 16),
+                    None => (reference.strip_prefix('#')?, 10),
+                };
+                if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+                    return None;
+                }
+                // NUL, surrogates and out of range code points become replacement characters
+                let code = u32::from_str_radix(digits, radix).ok().filter(|&code| code != 0);
+                Some(code.and_then(char::from_u32).unwrap_or(char::REPLACEMENT_CHARACTER))
+            }
+        });
+
+        match (reference, character) {
+            (Some(reference), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[reference.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start_tag(name: &str, attributes: &[(&str, &str)], self_closing: bool) -> HtmlToken {
+        HtmlToken::StartTag {
+            name: name.to_string(),
+            attributes: attributes.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect(),
+            self_closing,
+        }
+    }
+
+    #[test]
+    fn test_tokenize_markup() {
+        let tokens = HtmlTokenizer::new("<!DOCTYPE html><DIV Class=a id='b' data-x=c hidden>x &amp; y</div><br/><!-- note -->")
+            .tokenize();
+
+        assert_eq!(tokens, [
+            HtmlToken::Doctype { name: Some("html".to_string()), public_id: None, system_id: None, force_quirks: false },
+            start_tag("div", &[("class", "a"), ("id", "b"), ("data-x", "c"), ("hidden", "")], false),
+            HtmlToken::Characters("x & y".to_string()),
+            HtmlToken::EndTag { name: "div".to_string() },
+            start_tag("br", &[], true),
+            HtmlToken::Comment(" note ".to_string()),
+            HtmlToken::Eof,
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_malformed_markup() {
+        // Stray less-than signs are text and duplicate attributes are dropped
+        let tokens = HtmlTokenizer::new("a < b <p a=1 a=2>\r\n</>").tokenize();
+        assert_eq!(tokens, [
+            HtmlToken::Characters("a < b ".to_string()),
+            start_tag("p", &[("a", "1")], false),
+            HtmlToken::Characters("\n".to_string()),
+            HtmlToken::Eof,
+        ]);
+    }
+
+    #[test]
+    fn test_raw_text_state() {
+        let mut tokenizer = HtmlTokenizer::new("a</b>&amp;</script>c");
+        tokenizer.set_state(TokenizerState::ScriptData);
+        tokenizer.set_last_start_tag("script");
+
+        assert_eq!(tokenizer.tokenize(), [
+            HtmlToken::Characters("a</b>&amp;".to_string()),
+            HtmlToken::EndTag { name: "script".to_string() },
+            HtmlToken::Characters("c".to_string()),
+            HtmlToken::Eof,
+        ]);
+    }
+
+    #[test]
+    fn test_decode_character_references() {
+        assert_eq!(decode_character_references("&lt;&#65;&#x42;&nbsp;"), "<AB\u{a0}");
+        assert_eq!(decode_character_references("&#0;&#xD800;&#x110000;"), "\u{FFFD}\u{FFFD}\u{FFFD}");
+        assert_eq!(decode_character_references("&unknown; & &#xZZ;"), "&unknown; & &#xZZ;");
+    }
+}
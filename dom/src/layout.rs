@@ -1,12 +1,16 @@
 //! Layout Engine implementation.
 //! 
 //! This module provides the layout engine for rendering DOM elements
-//! including block and inline formatting contexts, float handling,
+//! including block, inline and grid formatting contexts, float handling,
 //! and absolute/fixed positioning.
 
 use std::collections::HashMap;
+use crate::container_queries::{ContainerContext, ContainerQueryEvaluator, ContainerRule, ContainerType};
+use crate::css_at_rules::AtRule;
 use crate::dom::{Element, Node, Document};
-use crate::cssom::CssCascade;
+use crate::cssom::{CssCascade, CssRuleVariant, CssValue};
+use crate::error::{Error, Result};
+use crate::selector_matching::SelectorMatcher;
 
 /// Layout box types
 #[derive(Debug, Clone, PartialEq)]
@@ -333,6 +337,8 @@ pub struct LayoutBox {
     pub children: Vec<LayoutBox>,
     /// Parent box (if any)
     pub parent: Option<Box<LayoutBox>>,
+    /// Declarations from `@container` rules that apply in the current layout
+    pub container_styles: HashMap<String, String>,
 }
 
 impl LayoutBox {
@@ -408,9 +414,16 @@ impl LayoutBox {
             is_focus_visible: false,
             children: Vec::new(),
             parent: None,
+            container_styles: HashMap::new(),
         }
     }
     
+    /// Get the value of a property from the inline style or an applying `@container` rule
+    pub fn style_property(&self, property: &str) -> Option<String> {
+        style_property(&self.element, property)
+            .or_else(|| self.container_styles.get(property).cloned())
+    }
+    
     /// Add a child box
     pub fn add_child(&mut self, child: LayoutBox) {
         self.children.push(child);
@@ -633,6 +646,426 @@ impl LineBox {
     }
 }
 
+/// Sizing function of a grid track
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrackSize {
+    /// Fixed length in pixels
+    Fixed(f32),
+    /// Flexible length in `fr` units
+    Flexible(f32),
+    /// Largest min-content contribution of the track's items
+    MinContent,
+    /// Largest max-content contribution of the track's items
+    MaxContent,
+    /// Content-sized, stretched to fill leftover space
+    Auto,
+}
+
+/// Grid track (a column or a row)
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridTrack {
+    /// Track sizing function
+    pub size: TrackSize,
+}
+
+impl GridTrack {
+    /// Create a new grid track
+    pub fn new(size: TrackSize) -> Self {
+        Self { size }
+    }
+}
+
+/// Area occupied by a grid item, as zero-based track indices (end exclusive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridPlacement {
+    /// First column
+    pub column_start: usize,
+    /// Column after the last one
+    pub column_end: usize,
+    /// First row
+    pub row_start: usize,
+    /// Row after the last one
+    pub row_end: usize,
+}
+
+/// A value of `grid-column-start` / `grid-row-end` style placement properties
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GridLineValue {
+    /// Automatic placement
+    Auto,
+    /// Zero-based line index
+    Line(usize),
+    /// Number of tracks to span
+    Span(usize),
+}
+
+/// Grid formatting context
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GridFormattingContext {
+    /// Explicit column tracks
+    pub columns: Vec<GridTrack>,
+    /// Explicit row tracks
+    pub rows: Vec<GridTrack>,
+    /// Gap between columns
+    pub gap_col: f32,
+    /// Gap between rows
+    pub gap_row: f32,
+}
+
+impl GridFormattingContext {
+    /// Create a new grid formatting context
+    pub fn new(columns: Vec<GridTrack>, rows: Vec<GridTrack>, gap_col: f32, gap_row: f32) -> Self {
+        Self {
+            columns,
+            rows,
+            gap_col,
+            gap_row,
+        }
+    }
+    
+    /// Create a grid formatting context from the style of a grid container
+    pub fn from_layout_box(container: &LayoutBox) -> Result<Self> {
+        let columns = match container.style_property("grid-template-columns") {
+            Some(value) => parse_track_list(&value)?,
+            None => Vec::new(),
+        };
+        let rows = match container.style_property("grid-template-rows") {
+            Some(value) => parse_track_list(&value)?,
+            None => Vec::new(),
+        };
+        
+        let (mut gap_row, mut gap_col) = match container.style_property("gap") {
+            Some(value) => {
+                let mut gaps = value.split_whitespace();
+                let row = parse_length(gaps.next().unwrap_or("0"))?;
+                let col = gaps.next().map(parse_length).transpose()?.unwrap_or(row);
+                (row, col)
+            }
+            None => (0.0, 0.0),
+        };
+        if let Some(value) = container.style_property("row-gap") {
+            gap_row = parse_length(&value)?;
+        }
+        if let Some(value) = container.style_property("column-gap") {
+            gap_col = parse_length(&value)?;
+        }
+        
+        Ok(Self::new(columns, rows, gap_col, gap_row))
+    }
+    
+    /// Place grid items, honouring `grid-column` / `grid-row` and auto-placing the rest
+    ///
+    /// Auto-placed items fill the first free gap, scanning left to right and
+    /// then top to bottom.
+    pub fn place_items(&self, children: &[LayoutBox]) -> Result<Vec<GridPlacement>> {
+        let mut column_count = self.columns.len().max(1);
+        let mut specs = Vec::with_capacity(children.len());
+        for child in children {
+            let column = match child.style_property("grid-column") {
+                Some(value) => parse_grid_line(&value, self.columns.len())?,
+                None => (None, 1),
+            };
+            let row = match child.style_property("grid-row") {
+                Some(value) => parse_grid_line(&value, self.rows.len())?,
+                None => (None, 1),
+            };
+            column_count = column_count.max(column.0.unwrap_or(0) + column.1);
+            specs.push((column, row));
+        }
+        
+        let mut occupied: Vec<Vec<bool>> = Vec::new();
+        let mut placements: Vec<Option<GridPlacement>> = vec![None; children.len()];
+        
+        // Items with an explicit row go first, then everything else in document order
+        let order = specs.iter().enumerate()
+            .filter(|(_, (_, row))| row.0.is_some())
+            .chain(specs.iter().enumerate().filter(|(_, (_, row))| row.0.is_none()))
+            .map(|(index, spec)| (index, *spec))
+            .collect::<Vec<_>>();
+        
+        for (index, ((column_start, column_span), (row_start, row_span))) in order {
+            let column_span = column_span.min(column_count);
+            let (row, column) = match (row_start, column_start) {
+                (Some(row), Some(column)) => (row, column),
+                (Some(row), None) => {
+                    let column = (0..=column_count - column_span)
+                        .find(|&column| area_is_free(&occupied, row, row_span, column, column_span))
+                        .unwrap_or(0);
+                    (row, column)
+                }
+                (None, fixed_column) => {
+                    let columns = match fixed_column {
+                        Some(column) => column..=column,
+                        None => 0..=column_count - column_span,
+                    };
+                    (0..)
+                        .find_map(|row| {
+                            columns.clone()
+                                .find(|&column| area_is_free(&occupied, row, row_span, column, column_span))
+                                .map(|column| (row, column))
+                        })
+                        .unwrap_or((0, 0))
+                }
+            };
+            
+            if occupied.len() < row + row_span {
+                occupied.resize_with(row + row_span, || vec![false; column_count]);
+            }
+            for cells in &mut occupied[row..row + row_span] {
+                for cell in &mut cells[column..column + column_span] {
+                    *cell = true;
+                }
+            }
+            
+            placements[index] = Some(GridPlacement {
+                column_start: column,
+                column_end: column + column_span,
+                row_start: row,
+                row_end: row + row_span,
+            });
+        }
+        
+        Ok(placements.into_iter().flatten().collect())
+    }
+    
+    /// Resolve column widths for the available inline size
+    pub fn resolve_columns(&self, placements: &[GridPlacement], children: &[LayoutBox], available_width: f32) -> Vec<f32> {
+        let count = placements.iter().map(|placement| placement.column_end).max().unwrap_or(0).max(self.columns.len());
+        let contributions = placements.iter().zip(children)
+            .map(|(placement, child)| (placement.column_start, placement.column_end, child.dimensions.outer_width()))
+            .collect::<Vec<_>>();
+        
+        resolve_tracks(&self.columns, count, &contributions, self.gap_col, Some(available_width))
+    }
+    
+    /// Resolve row heights, with `fr` rows only flexing when the height is definite
+    pub fn resolve_rows(&self, placements: &[GridPlacement], children: &[LayoutBox], available_height: Option<f32>) -> Vec<f32> {
+        let count = placements.iter().map(|placement| placement.row_end).max().unwrap_or(0).max(self.rows.len());
+        let contributions = placements.iter().zip(children)
+            .map(|(placement, child)| (placement.row_start, placement.row_end, child.dimensions.outer_height()))
+            .collect::<Vec<_>>();
+        
+        resolve_tracks(&self.rows, count, &contributions, self.gap_row, available_height)
+    }
+}
+
+/// Size tracks from their sizing functions and the items' size contributions
+fn resolve_tracks(
+    tracks: &[GridTrack],
+    count: usize,
+    contributions: &[(usize, usize, f32)],
+    gap: f32,
+    available: Option<f32>,
+) -> Vec<f32> {
+    // Implicit tracks are auto-sized
+    let sizes: Vec<&TrackSize> = (0..count)
+        .map(|index| tracks.get(index).map_or(&TrackSize::Auto, |track| &track.size))
+        .collect();
+    let is_intrinsic = |size: &TrackSize| {
+        matches!(size, TrackSize::Auto | TrackSize::MinContent | TrackSize::MaxContent)
+            || (available.is_none() && matches!(size, TrackSize::Flexible(_)))
+    };
+    
+    let mut resolved: Vec<f32> = sizes.iter()
+        .map(|size| match size {
+            TrackSize::Fixed(length) => *length,
+            _ => 0.0,
+        })
+        .collect();
+    
+    // Single-track items first, then grow spanned intrinsic tracks for spanning items
+    for &(start, _, size) in contributions.iter().filter(|(start, end, _)| end - start == 1) {
+        if is_intrinsic(sizes[start]) {
+            resolved[start] = resolved[start].max(size);
+        }
+    }
+    for &(start, end, size) in contributions.iter().filter(|(start, end, _)| end - start > 1) {
+        let spanned = resolved[start..end].iter().sum::<f32>() + gap * (end - start - 1) as f32;
+        let growable: Vec<usize> = (start..end).filter(|&index| is_intrinsic(sizes[index])).collect();
+        if size > spanned && !growable.is_empty() {
+            let extra = (size - spanned) / growable.len() as f32;
+            for index in growable {
+                resolved[index] += extra;
+            }
+        }
+    }
+    
+    let Some(available) = available else {
+        return resolved;
+    };
+    
+    let gaps = gap * count.saturating_sub(1) as f32;
+    let free_space = (available - resolved.iter().sum::<f32>() - gaps).max(0.0);
+    let flex_total: f32 = sizes.iter()
+        .map(|size| match size {
+            TrackSize::Flexible(fr) => *fr,
+            _ => 0.0,
+        })
+        .sum();
+    
+    if flex_total > 0.0 {
+        // A total below 1fr leaves part of the free space unused
+        let fr_size = free_space / flex_total.max(1.0);
+        for (track, size) in resolved.iter_mut().zip(&sizes) {
+            if let TrackSize::Flexible(fr) = size {
+                *track = fr * fr_size;
+            }
+        }
+    } else {
+        let auto_tracks = sizes.iter().filter(|size| matches!(size, TrackSize::Auto)).count();
+        if auto_tracks > 0 {
+            let extra = free_space / auto_tracks as f32;
+            for (track, size) in resolved.iter_mut().zip(&sizes) {
+                if matches!(size, TrackSize::Auto) {
+                    *track += extra;
+                }
+            }
+        }
+    }
+    
+    resolved
+}
+
+/// Offset of each track from the start of the grid
+fn track_offsets(sizes: &[f32], gap: f32) -> Vec<f32> {
+    sizes.iter()
+        .scan(0.0, |offset, size| {
+            let start = *offset;
+            *offset += size + gap;
+            Some(start)
+        })
+        .collect()
+}
+
+/// Size of an area spanning tracks `start..end`
+fn span_size(sizes: &[f32], start: usize, end: usize, gap: f32) -> f32 {
+    sizes[start..end].iter().sum::<f32>() + gap * end.saturating_sub(start + 1) as f32
+}
+
+/// Check whether an area is free in the occupancy grid
+fn area_is_free(occupied: &[Vec<bool>], row: usize, row_span: usize, column: usize, column_span: usize) -> bool {
+    (row..row + row_span).all(|row| {
+        occupied.get(row)
+            .is_none_or(|cells| cells[column..column + column_span].iter().all(|cell| !cell))
+    })
+}
+
+/// Read a property from an element's inline `style` attribute
+fn style_property(element: &Element, property: &str) -> Option<String> {
+    element.get_attribute("style")?
+        .split(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case(property))
+        .map(|(_, value)| value.trim().to_string())
+        .last()
+}
+
+/// Serialize a CSSOM value as CSS text
+fn css_value_text(value: &CssValue) -> String {
+    match value {
+        CssValue::Keyword(text) | CssValue::Color(text) => text.clone(),
+        CssValue::String(text) => format!("\"{}\"", text),
+        CssValue::Number(number) => number.to_string(),
+        CssValue::Length(length, unit) => format!("{}{}", length, unit),
+        CssValue::Percentage(percentage) => format!("{}%", percentage),
+        CssValue::Url(url) => format!("url({})", url),
+        CssValue::Function(name, args) => {
+            format!("{}({})", name, args.iter().map(css_value_text).collect::<Vec<_>>().join(", "))
+        }
+        CssValue::List(values) => values.iter().map(css_value_text).collect::<Vec<_>>().join(" "),
+        CssValue::Initial => "initial".to_string(),
+        CssValue::Inherit => "inherit".to_string(),
+        CssValue::Unset => "unset".to_string(),
+        CssValue::Revert => "revert".to_string(),
+    }
+}
+
+/// Parse a pixel length such as `10px` or `0`
+fn parse_length(value: &str) -> Result<f32> {
+    let value = value.trim();
+    value.strip_suffix("px").unwrap_or(value).parse()
+        .map_err(|_| Error::ParseError(format!("Invalid length: {}", value)))
+}
+
+/// Parse a track list such as `100px repeat(2, 1fr) auto`
+fn parse_track_list(value: &str) -> Result<Vec<GridTrack>> {
+    let mut tracks = Vec::new();
+    let mut rest = value.trim();
+    
+    while !rest.is_empty() {
+        if let Some(inner) = rest.strip_prefix("repeat(") {
+            let end = inner.find(')')
+                .ok_or_else(|| Error::ParseError(format!("Unterminated repeat(): {}", value)))?;
+            let (repetitions, track_list) = inner[..end].split_once(',')
+                .ok_or_else(|| Error::ParseError(format!("Invalid repeat(): {}", value)))?;
+            let repetitions: usize = repetitions.trim().parse()
+                .map_err(|_| Error::ParseError(format!("Invalid repeat() count: {}", value)))?;
+            let repeated = parse_track_list(track_list)?;
+            for _ in 0..repetitions {
+                tracks.extend(repeated.iter().cloned());
+            }
+            rest = inner[end + 1..].trim_start();
+            continue;
+        }
+        
+        let (token, remainder) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let size = match token.to_ascii_lowercase().as_str() {
+            "auto" => TrackSize::Auto,
+            "min-content" => TrackSize::MinContent,
+            "max-content" => TrackSize::MaxContent,
+            token => match token.strip_suffix("fr") {
+                Some(fr) => TrackSize::Flexible(fr.parse()
+                    .map_err(|_| Error::ParseError(format!("Invalid flexible length: {}", token)))?),
+                None => TrackSize::Fixed(parse_length(token)?),
+            },
+        };
+        tracks.push(GridTrack::new(size));
+        rest = remainder.trim_start();
+    }
+    
+    Ok(tracks)
+}
+
+/// Parse a `grid-column` / `grid-row` value into a start track and a span
+fn parse_grid_line(value: &str, explicit_tracks: usize) -> Result<(Option<usize>, usize)> {
+    let parse_value = |token: &str| -> Result<GridLineValue> {
+        let token = token.trim();
+        let invalid = || Error::ParseError(format!("Invalid grid line: {}", value));
+        
+        if token.eq_ignore_ascii_case("auto") {
+            return Ok(GridLineValue::Auto);
+        }
+        if let Some(span) = token.strip_prefix("span") {
+            let span: usize = span.trim().parse().map_err(|_| invalid())?;
+            return if span > 0 { Ok(GridLineValue::Span(span)) } else { Err(invalid()) };
+        }
+        
+        // Lines are numbered from 1; negative lines count back from the last explicit line
+        let line: i64 = token.parse().map_err(|_| invalid())?;
+        match line {
+            0 => Err(invalid()),
+            line if line > 0 => Ok(GridLineValue::Line(line as usize - 1)),
+            line => Ok(GridLineValue::Line((explicit_tracks as i64 + 1 + line).max(0) as usize)),
+        }
+    };
+    
+    let (start, end) = match value.split_once('/') {
+        Some((start, end)) => (parse_value(start)?, parse_value(end)?),
+        None => (parse_value(value)?, GridLineValue::Auto),
+    };
+    
+    Ok(match (start, end) {
+        (GridLineValue::Line(start), GridLineValue::Line(end)) => (Some(start.min(end)), start.abs_diff(end).max(1)),
+        (GridLineValue::Line(start), GridLineValue::Span(span)) => (Some(start), span),
+        (GridLineValue::Line(start), GridLineValue::Auto) => (Some(start), 1),
+        (GridLineValue::Span(span), GridLineValue::Line(end)) => (Some(end.saturating_sub(span)), span.min(end.max(1))),
+        (GridLineValue::Span(span), _) => (None, span),
+        (GridLineValue::Auto, GridLineValue::Line(end)) => (Some(end.saturating_sub(1)), 1),
+        (GridLineValue::Auto, GridLineValue::Span(span)) => (None, span),
+        (GridLineValue::Auto, GridLineValue::Auto) => (None, 1),
+    })
+}
+
 /// Layout engine for calculating element positions and dimensions
 pub struct LayoutEngine {
     /// CSS cascade for computing styles
@@ -643,6 +1076,12 @@ pub struct LayoutEngine {
     block_contexts: Vec<BlockFormattingContext>,
     /// Inline formatting contexts
     inline_contexts: Vec<InlineFormattingContext>,
+    /// `@container` rules of the enabled style sheets
+    container_rules: Vec<ContainerRule>,
+    /// Query containers enclosing the box being laid out, outermost first
+    container_stack: Vec<ContainerContext>,
+    /// Size of each query container in the last layout, by element ID
+    container_contexts: HashMap<String, ContainerContext>,
 }
 
 impl LayoutEngine {
@@ -653,9 +1092,17 @@ impl LayoutEngine {
             layout_boxes: HashMap::new(),
             block_contexts: Vec::new(),
             inline_contexts: Vec::new(),
+            container_rules: Vec::new(),
+            container_stack: Vec::new(),
+            container_contexts: HashMap::new(),
         }
     }
     
+    /// Get the size of a query container in the last layout
+    pub fn container_context(&self, element_id: &str) -> Option<&ContainerContext> {
+        self.container_contexts.get(element_id)
+    }
+    
     /// Build the layout tree from a DOM tree
     pub fn build_layout_tree(&mut self, document: &Document) -> LayoutBox {
         let root_element = document.get_element_by_id("root")
@@ -717,6 +1164,11 @@ impl LayoutEngine {
                 box_.box_type = BoxType::Block;
             }
         }
+        
+        if box_.style_property("display").as_deref() == Some("grid") {
+            box_.display = Display::Grid;
+            box_.box_type = BoxType::Grid;
+        }
     }
     
     /// Calculate layout for the entire tree
@@ -724,6 +1176,18 @@ impl LayoutEngine {
         // Reset positioning
         self.reset_positioning(root_box);
         
+        // Container rules are evaluated afresh against the container sizes of this layout
+        self.container_rules = self.cascade.stylesheets().iter()
+            .filter(|stylesheet| !stylesheet.is_disabled())
+            .flat_map(|stylesheet| stylesheet.rules())
+            .filter_map(|rule| match rule {
+                CssRuleVariant::AtRule(at_rule @ AtRule::Container { .. }) => ContainerRule::from_at_rule(at_rule),
+                _ => None,
+            })
+            .collect();
+        self.container_stack.clear();
+        self.container_contexts.clear();
+        
         // Calculate layout recursively
         self.calculate_layout_recursive(root_box, containing_block_width, containing_block_height);
     }
@@ -732,6 +1196,7 @@ impl LayoutEngine {
     fn reset_positioning(&self, box_: &mut LayoutBox) {
         box_.position_coords = Position::default();
         box_.dimensions = Dimensions::default();
+        box_.container_styles.clear();
         
         for child in &mut box_.children {
             self.reset_positioning(child);
@@ -740,6 +1205,67 @@ impl LayoutEngine {
     
     /// Recursively calculate layout
     fn calculate_layout_recursive(&mut self, box_: &mut LayoutBox, containing_block_width: f32, containing_block_height: f32) {
+        self.apply_container_rules(box_);
+        
+        // A query container's descendants are laid out against its size
+        let container_type = box_.style_property("container-type")
+            .map(|value| ContainerType::parse(&value))
+            .unwrap_or_default();
+        let is_container = container_type != ContainerType::Normal;
+        if is_container {
+            let height = match container_type {
+                ContainerType::Size => containing_block_height,
+                _ => 0.0,
+            };
+            let context = ContainerContext::new(box_.style_property("container-name"), containing_block_width, height);
+            self.container_contexts.insert(box_.element.id.clone(), context.clone());
+            self.container_stack.push(context);
+        }
+        
+        self.layout_by_display(box_, containing_block_width, containing_block_height);
+        
+        if is_container {
+            self.container_stack.pop();
+        }
+    }
+    
+    /// Apply the declarations of `@container` rules that match a box and its containers
+    fn apply_container_rules(&self, box_: &mut LayoutBox) {
+        if self.container_stack.is_empty() {
+            return;
+        }
+        
+        for container_rule in &self.container_rules {
+            if !ContainerQueryEvaluator::evaluate_nearest(container_rule, &self.container_stack) {
+                continue;
+            }
+            
+            for rule in &container_rule.rules {
+                let CssRuleVariant::StyleRule(rule) = rule else {
+                    continue;
+                };
+                let matches = rule.selectors.selectors.iter().any(|selector| {
+                    let mut matcher = SelectorMatcher::new();
+                    matcher.add_selector(selector.clone());
+                    !matcher.match_element(&box_.element).is_empty()
+                });
+                if matches {
+                    for declaration in rule.declarations() {
+                        box_.container_styles.insert(declaration.property.clone(), css_value_text(&declaration.value));
+                    }
+                }
+            }
+        }
+        
+        // Rules may switch the box to or from grid layout
+        if box_.style_property("display").as_deref() == Some("grid") {
+            box_.display = Display::Grid;
+            box_.box_type = BoxType::Grid;
+        }
+    }
+    
+    /// Lay out a box according to its display type
+    fn layout_by_display(&mut self, box_: &mut LayoutBox, containing_block_width: f32, containing_block_height: f32) {
         match box_.display {
             Display::Block => {
                 self.calculate_block_layout(box_, containing_block_width, containing_block_height);
@@ -750,6 +1276,9 @@ impl LayoutEngine {
             Display::InlineBlock => {
                 self.calculate_inline_block_layout(box_, containing_block_width, containing_block_height);
             }
+            Display::Grid => {
+                self.calculate_grid_layout(box_, containing_block_width);
+            }
             _ => {
                 // Handle other display types
                 self.calculate_block_layout(box_, containing_block_width, containing_block_height);
@@ -802,6 +1331,62 @@ impl LayoutEngine {
         }
     }
     
+    /// Lay out the children of a grid container
+    ///
+    /// Returns the dimensions of each child, sized to fill its grid area.
+    pub fn layout_grid(&mut self, container: &LayoutBox, children: &[LayoutBox]) -> Result<Vec<Dimensions>> {
+        let context = GridFormattingContext::from_layout_box(container)?;
+        let placements = context.place_items(children)?;
+        
+        let available_height = (container.dimensions.content_height > 0.0).then_some(container.dimensions.content_height);
+        let columns = context.resolve_columns(&placements, children, container.dimensions.content_width);
+        let rows = context.resolve_rows(&placements, children, available_height);
+        
+        Ok(placements.iter().zip(children)
+            .map(|(placement, child)| {
+                let mut dimensions = child.dimensions.clone();
+                let width = span_size(&columns, placement.column_start, placement.column_end, context.gap_col);
+                let height = span_size(&rows, placement.row_start, placement.row_end, context.gap_row);
+                dimensions.content_width = (width - (dimensions.outer_width() - dimensions.content_width)).max(0.0);
+                dimensions.content_height = (height - (dimensions.outer_height() - dimensions.content_height)).max(0.0);
+                dimensions
+            })
+            .collect())
+    }
+    
+    /// Calculate layout for grid containers
+    fn calculate_grid_layout(&mut self, box_: &mut LayoutBox, containing_block_width: f32) {
+        box_.dimensions.content_width = containing_block_width;
+        
+        // Invalid grid declarations are ignored, leaving a single auto column
+        let context = GridFormattingContext::from_layout_box(box_).unwrap_or_default();
+        let Ok(placements) = context.place_items(&box_.children) else {
+            return;
+        };
+        
+        // Columns first, so items can be laid out at their final width to find their heights
+        let columns = context.resolve_columns(&placements, &box_.children, containing_block_width);
+        for (child, placement) in box_.children.iter_mut().zip(&placements) {
+            let width = span_size(&columns, placement.column_start, placement.column_end, context.gap_col);
+            self.calculate_layout_recursive(child, width, 0.0);
+        }
+        let rows = context.resolve_rows(&placements, &box_.children, None);
+        
+        let column_offsets = track_offsets(&columns, context.gap_col);
+        let row_offsets = track_offsets(&rows, context.gap_row);
+        for (child, placement) in box_.children.iter_mut().zip(&placements) {
+            let height = span_size(&rows, placement.row_start, placement.row_end, context.gap_row);
+            let non_content_height = child.dimensions.outer_height() - child.dimensions.content_height;
+            child.dimensions.content_height = child.dimensions.content_height.max(height - non_content_height);
+            child.position_coords = Position {
+                x: box_.position_coords.x + column_offsets[placement.column_start],
+                y: box_.position_coords.y + row_offsets[placement.row_start],
+            };
+        }
+        
+        box_.dimensions.content_height = span_size(&rows, 0, rows.len(), context.gap_row);
+    }
+    
     /// Handle float positioning
     pub fn handle_floats(&mut self, box_: &mut LayoutBox, context: &mut BlockFormattingContext) {
         if box_.is_float {
@@ -840,6 +1425,7 @@ impl LayoutEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::css_parser::CssParser;
     use crate::dom::{Document, Element};
 
     #[test]
@@ -885,6 +1471,88 @@ mod tests {
         assert_eq!(context.current_x, 0.0);
     }
 
+    fn grid_item(style: Option<&str>) -> LayoutBox {
+        let mut element = Element::new("div".to_string());
+        if let Some(style) = style {
+            element.set_attribute("style".to_string(), style.to_string());
+        }
+        LayoutBox::new(element)
+    }
+
+    fn grid_container(style: &str, width: f32) -> LayoutBox {
+        let mut container = grid_item(Some(style));
+        container.dimensions.content_width = width;
+        container
+    }
+
+    #[test]
+    fn test_grid_three_equal_columns() {
+        let container = grid_container("display: grid; grid-template-columns: repeat(3, 1fr)", 300.0);
+        let children: Vec<LayoutBox> = (0..5).map(|_| grid_item(None)).collect();
+        
+        let context = GridFormattingContext::from_layout_box(&container).unwrap();
+        assert_eq!(context.columns, vec![GridTrack::new(TrackSize::Flexible(1.0)); 3]);
+        
+        let placements = context.place_items(&children).unwrap();
+        assert_eq!((placements[3].row_start, placements[3].column_start), (1, 0));
+        assert_eq!((placements[4].row_start, placements[4].column_start), (1, 1));
+        
+        let cascade = CssCascade::new();
+        let mut engine = LayoutEngine::new(cascade);
+        let dimensions = engine.layout_grid(&container, &children).unwrap();
+        assert_eq!(dimensions.len(), 5);
+        assert!(dimensions.iter().all(|d| d.content_width == 100.0));
+    }
+
+    #[test]
+    fn test_grid_fixed_and_flexible_tracks_with_gaps() {
+        let container = grid_container("grid-template-columns: 100px 1fr 2fr; column-gap: 10px", 400.0);
+        let children = vec![grid_item(None), grid_item(None), grid_item(None)];
+        
+        let cascade = CssCascade::new();
+        let mut engine = LayoutEngine::new(cascade);
+        let widths: Vec<f32> = engine.layout_grid(&container, &children).unwrap()
+            .iter().map(|d| d.content_width).collect();
+        
+        // 400 - 100 - 2 * 10 = 280 shared as 1fr : 2fr
+        assert_eq!(widths, vec![100.0, 280.0 / 3.0, 560.0 / 3.0]);
+    }
+
+    #[test]
+    fn test_grid_explicit_placement_and_gap_filling() {
+        let container = grid_container("grid-template-columns: repeat(3, 100px)", 300.0);
+        let children = vec![
+            grid_item(Some("grid-column: 2 / span 2; grid-row: 1")),
+            grid_item(None),
+            grid_item(Some("grid-column: 1 / -1")),
+            grid_item(None),
+        ];
+        
+        let context = GridFormattingContext::from_layout_box(&container).unwrap();
+        let placements = context.place_items(&children).unwrap();
+        
+        assert_eq!(placements[0], GridPlacement { column_start: 1, column_end: 3, row_start: 0, row_end: 1 });
+        // Auto-placed item fills the gap left of the explicitly placed one
+        assert_eq!(placements[1], GridPlacement { column_start: 0, column_end: 1, row_start: 0, row_end: 1 });
+        assert_eq!(placements[2], GridPlacement { column_start: 0, column_end: 3, row_start: 1, row_end: 2 });
+        assert_eq!(placements[3], GridPlacement { column_start: 0, column_end: 1, row_start: 2, row_end: 3 });
+        
+        let cascade = CssCascade::new();
+        let mut engine = LayoutEngine::new(cascade);
+        let dimensions = engine.layout_grid(&container, &children).unwrap();
+        assert_eq!(dimensions[0].content_width, 200.0);
+        assert_eq!(dimensions[2].content_width, 300.0);
+    }
+
+    #[test]
+    fn test_grid_invalid_track_list() {
+        let container = grid_container("grid-template-columns: 1fr bogus", 300.0);
+        
+        let cascade = CssCascade::new();
+        let mut engine = LayoutEngine::new(cascade);
+        assert!(engine.layout_grid(&container, &[]).is_err());
+    }
+
     #[test]
     fn test_layout_engine_creation() {
         let cascade = CssCascade::new();
@@ -906,4 +1574,40 @@ mod tests {
         let root_box = engine.build_layout_tree(&document);
         assert_eq!(root_box.element.tag_name, "html");
     }
+
+    #[test]
+    fn test_container_query_switches_grid_columns() {
+        let css = "@container cards (width >= 400px) { .grid { grid-template-columns: 1fr 1fr } }
+                   @container cards (width < 400px) { .grid { grid-template-columns: 1fr } }";
+        let mut cascade = CssCascade::new();
+        cascade.add_stylesheet(CssParser::new().parse_stylesheet(css).unwrap());
+        let mut engine = LayoutEngine::new(cascade);
+        
+        let mut grid = grid_item(Some("display: grid; column-gap: 20px"));
+        grid.element.id = "grid".to_string();
+        grid.element.set_attribute("class".to_string(), "grid".to_string());
+        for _ in 0..4 {
+            grid.add_child(grid_item(None));
+        }
+        let mut container = grid_item(Some("container-type: inline-size; container-name: cards"));
+        container.element.id = "container".to_string();
+        container.add_child(grid);
+        
+        // Two columns side by side in a wide container
+        engine.calculate_layout(&mut container, 600.0, 400.0);
+        assert_eq!(engine.container_context("container").unwrap().width, 600.0);
+        let grid = &container.children[0];
+        assert_eq!(grid.display, Display::Grid);
+        let widths: Vec<f32> = grid.children.iter().map(|child| child.dimensions.content_width).collect();
+        assert_eq!(widths, vec![290.0; 4]);
+        assert_eq!(grid.children[1].position_coords.x, 310.0);
+        assert_eq!(grid.children[2].position_coords.x, 0.0);
+        
+        // A single column below 400px
+        engine.calculate_layout(&mut container, 360.0, 400.0);
+        let grid = &container.children[0];
+        let widths: Vec<f32> = grid.children.iter().map(|child| child.dimensions.content_width).collect();
+        assert_eq!(widths, vec![360.0; 4]);
+        assert!(grid.children.iter().all(|child| child.position_coords.x == 0.0));
+    }
 }
@@ -5,22 +5,28 @@
 pub mod dom;
 pub mod error;
 pub mod html_parser;
+pub mod html_tokenizer;
 pub mod events;
 pub mod mutation_observer;
 pub mod traversal;
 pub mod css_tokenizer;
 pub mod css_selector;
 pub mod cssom;
+pub mod css_parser;
 
 // Re-export main types
-pub use dom::{Document, Element, Node, TextNode, CommentNode, DocumentTypeNode, DomTraversal};
+pub use dom::{Document, DocumentFragment, Element, Node, TextNode, CommentNode, DocumentTypeNode, DomTraversal};
 pub use html_parser::HtmlParser;
+pub use html_tokenizer::{HtmlToken, HtmlTokenizer};
+pub mod serializer;
+pub use serializer::DomSerializer;
 pub use events::{Event, EventType, EventListener, EventManager, EventDispatcher, EventTarget, EventPhase};
 pub use mutation_observer::{MutationObserver, MutationObserverInit, MutationRecord, MutationType, MutationObserverManager};
 pub use traversal::{NodeIterator, TreeWalker, NodeFilter, NodeFilterFn, BreadthFirstTraversal, DepthFirstTraversal};
 pub use css_tokenizer::{CssToken, CssTokenizer};
 pub use css_selector::{CssSelectorParser, SelectorList, ComplexSelector, SimpleSelector, Specificity, PseudoClass, PseudoElement, AttributeSelector, Combinator};
-pub use cssom::{CssStyleSheet, CssStyleRule, CssDeclaration, CssValue, CssRule, CssRuleType, ComputedValue, CssCascade};
+pub use cssom::{CssStyleSheet, CssStyleRule, CssDeclaration, CssValue, CssRule, CssRuleType, ComputedValue, CssCascade, ComputedStyle, CascadeEntry, CascadeCriterion, CustomPropertyMap, ResolvedValue, RuleSource, RuleMatchObserver};
+pub use css_parser::CssParser;
 
 pub mod selector_matching;
 pub use selector_matching::{SelectorMatcher, FastPathMatcher, AncestorBloomFilter, MatchResult};
@@ -29,13 +35,13 @@ pub mod pseudo_classes;
 pub use pseudo_classes::{PseudoClassEvaluator, PseudoClassEventHandler, ElementState};
 
 pub mod layout;
-pub use layout::{LayoutEngine, LayoutBox, BlockFormattingContext, InlineFormattingContext, LineBox, BoxType, PositionType, Display, Float, Clear, Dimensions, Position};
+pub use layout::{LayoutEngine, LayoutBox, BlockFormattingContext, InlineFormattingContext, GridFormattingContext, GridTrack, TrackSize, GridPlacement, LineBox, BoxType, PositionType, Display, Float, Clear, Dimensions, Position};
 
 pub mod flexbox;
 pub use flexbox::{FlexboxEngine, FlexContainer, FlexItem, FlexLine, FlexDirection, FlexWrap, JustifyContent, AlignItems, AlignContent, AlignSelf, FlexGrow, FlexShrink, FlexBasis, Order};
 
 pub mod typography;
-pub use typography::{FontManager, FontFace, FontFamily, FontWeight, FontStyle, FontStretch, FontMetrics, FontFallback, FontCacheEntry};
+pub use typography::{FontManager, FontFace, FontFamily, FontWeight, FontStyle, FontStretch, FontMetrics, FontFallback, FontCacheEntry, FontLoader};
 
 pub mod text_shaping;
 pub use text_shaping::{TextShaper, ShapedGlyph, ShapedTextRun, CharProperties, CharCategory, BidiClass, TextDirection, LineBreakOpportunity, LineBreakType};
@@ -46,9 +52,21 @@ pub use shadow_dom::{ShadowRoot, ShadowRootMode, ShadowDomManager};
 pub mod css_property_parser;
 pub use css_property_parser::{CssPropertyParser, PropertyValue, LengthUnit, ColorValue};
 pub mod css_at_rules;
-pub use css_at_rules::{AtRule, KeyframeRule, AtRuleParser, AtRuleManager, AtRuleHandler};
+pub use css_at_rules::{AtRule, KeyframeRule, AtRuleParser, AtRuleManager, AtRuleHandler, FontFaceDeclaration, FontFaceSource, FontDisplay};
+pub mod media_queries;
+pub use media_queries::{MediaQueryEvaluator, MediaType, ColorScheme};
+pub mod container_queries;
+pub use container_queries::{ContainerContext, ContainerQueryEvaluator, ContainerRule, ContainerType};
 pub mod selector_indexing;
 pub use selector_indexing::{SelectorIndex, SelectorIndexEntry, SelectorIndexStats, IndexedSelectorMatcher};
 pub mod grid_layout;
 pub use grid_layout::{GridLayoutEngine, GridContainer, GridItem, GridTemplate, GridLine, GridTemplateUnit, GridArea, GridItemPlacement, GridAlignment, GridDirection};
+pub mod animation;
+pub use animation::{AnimationEngine, ActiveAnimation, Keyframe, TimingFunction, FillMode, TransitionSpec};
+pub mod custom_elements;
+pub use custom_elements::{CustomElementRegistry, CustomElementDefinition};
+pub mod form_validation;
+pub use form_validation::{FormValidator, ValidationResult, ValidityState};
+pub mod range;
+pub use range::{DomRect, NodeId, Range};
 pub use error::{Error, Result};
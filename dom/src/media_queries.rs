@@ -0,0 +1,418 @@
+//! Media query evaluation for the Matte browser.
+//!
+//! This module evaluates the conditions of `@media` rules against the
+//! current viewport and user preferences.
+
+use tracing::debug;
+
+/// Font size used to resolve `em` and `rem` in media queries
+const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+/// Preferred color scheme of the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    /// Light color scheme
+    #[default]
+    Light,
+    /// Dark color scheme
+    Dark,
+}
+
+/// Media type a document is rendered for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MediaType {
+    /// Shown on a screen
+    #[default]
+    Screen,
+    /// Laid out on paged media, when printing
+    Print,
+}
+
+/// Comparison in a media feature
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// Parse a range comparison operator
+    fn parse(operator: &str) -> Option<Self> {
+        match operator {
+            "=" => Some(Comparison::Equal),
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            ">" => Some(Comparison::Greater),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            _ => None,
+        }
+    }
+
+    /// The comparison with its operands swapped
+    fn flip(self) -> Self {
+        match self {
+            Comparison::Equal => Comparison::Equal,
+            Comparison::Less => Comparison::Greater,
+            Comparison::LessOrEqual => Comparison::GreaterOrEqual,
+            Comparison::Greater => Comparison::Less,
+            Comparison::GreaterOrEqual => Comparison::LessOrEqual,
+        }
+    }
+
+    /// Compare an actual value against the value in the query
+    fn compare(self, actual: f32, expected: f32) -> bool {
+        match self {
+            Comparison::Equal => (actual - expected).abs() < f32::EPSILON,
+            Comparison::Less => actual < expected,
+            Comparison::LessOrEqual => actual <= expected,
+            Comparison::Greater => actual > expected,
+            Comparison::GreaterOrEqual => actual >= expected,
+        }
+    }
+}
+
+/// Evaluates media queries against the environment of a document
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQueryEvaluator {
+    /// Media type `screen` and `print` queries are checked against
+    pub media_type: MediaType,
+    /// Viewport width in CSS pixels
+    pub viewport_width: u32,
+    /// Viewport height in CSS pixels
+    pub viewport_height: u32,
+    /// Device pixels per CSS pixel
+    pub device_pixel_ratio: f32,
+    /// Preferred color scheme
+    pub prefers_color_scheme: ColorScheme,
+    /// Whether the user prefers reduced motion
+    pub prefers_reduced_motion: bool,
+    /// Whether a forced colors mode such as high contrast is active
+    pub forced_colors: bool,
+}
+
+impl MediaQueryEvaluator {
+    /// Create an evaluator for a viewport
+    pub fn new(viewport_width: u32, viewport_height: u32) -> Self {
+        Self {
+            media_type: MediaType::Screen,
+            viewport_width,
+            viewport_height,
+            device_pixel_ratio: 1.0,
+            prefers_color_scheme: ColorScheme::Light,
+            prefers_reduced_motion: false,
+            forced_colors: false,
+        }
+    }
+
+    /// Update the viewport size
+    pub fn set_viewport_size(&mut self, viewport_width: u32, viewport_height: u32) {
+        self.viewport_width = viewport_width;
+        self.viewport_height = viewport_height;
+    }
+
+    /// Check if a media query list matches
+    ///
+    /// The list matches if any of its comma-separated queries match. An
+    /// empty list matches everything.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_ascii_lowercase();
+        if query.is_empty() {
+            return true;
+        }
+
+        let matches = split_top_level_commas(&query).into_iter().any(|query| self.matches_query(query));
+        debug!("Media query '{}' matches: {}", query, matches);
+        matches
+    }
+
+    /// Check if a single media query matches
+    fn matches_query(&self, query: &str) -> bool {
+        let mut terms = query_terms(query);
+        let negated = terms.first() == Some(&"not");
+        if negated || terms.first() == Some(&"only") {
+            terms.remove(0);
+        }
+        if terms.is_empty() {
+            return false;
+        }
+
+        // Conditions are combined with `and`, or all with `or` (Media Queries Level 4)
+        let uses_or = terms.contains(&"or");
+        let mut conditions = terms.into_iter().filter(|term| *term != "and" && *term != "or");
+        let matches = if uses_or {
+            conditions.any(|condition| self.matches_condition(condition))
+        } else {
+            conditions.all(|condition| self.matches_condition(condition))
+        };
+
+        matches != negated
+    }
+
+    /// Check if a media type or parenthesized feature matches
+    fn matches_condition(&self, condition: &str) -> bool {
+        match condition.strip_prefix('(').and_then(|condition| condition.strip_suffix(')')) {
+            Some(inner) => match inner.trim().strip_prefix("not ") {
+                Some(negated) => !self.matches_condition(negated.trim()),
+                None if inner.trim_start().starts_with('(') => self.matches_query(inner),
+                None => self.matches_feature(inner.trim()),
+            },
+            None => match condition {
+                "all" => true,
+                "screen" => self.media_type == MediaType::Screen,
+                "print" => self.media_type == MediaType::Print,
+                _ => false,
+            },
+        }
+    }
+
+    /// Check if a media feature matches
+    fn matches_feature(&self, feature: &str) -> bool {
+        if let Some((name, value)) = feature.split_once(':') {
+            let name = name.trim();
+            let value = value.trim();
+            return match name.strip_prefix("min-").or_else(|| name.strip_prefix("-webkit-min-")) {
+                Some(name) => self.compare_feature(name, Comparison::GreaterOrEqual, value),
+                None => match name.strip_prefix("max-").or_else(|| name.strip_prefix("-webkit-max-")) {
+                    Some(name) => self.compare_feature(name, Comparison::LessOrEqual, value),
+                    None => self.compare_feature(name.trim_start_matches("-webkit-"), Comparison::Equal, value),
+                },
+            };
+        }
+
+        // Range syntax, e.g. `width >= 600px` or `400px < width <= 800px`
+        let parts: Vec<&str> = feature.split_whitespace().collect();
+        match parts.as_slice() {
+            [name] => self.matches_boolean_feature(name),
+            [left, operator, right] => match Comparison::parse(operator) {
+                Some(comparison) if self.is_range_feature(left) => self.compare_feature(left, comparison, right),
+                Some(comparison) => self.compare_feature(right, comparison.flip(), left),
+                None => false,
+            },
+            [low, low_operator, name, high_operator, high] => {
+                match (Comparison::parse(low_operator), Comparison::parse(high_operator)) {
+                    (Some(low_comparison), Some(high_comparison)) => {
+                        self.compare_feature(name, low_comparison.flip(), low)
+                            && self.compare_feature(name, high_comparison, high)
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Check a feature used without a value, e.g. `(color)`
+    fn matches_boolean_feature(&self, name: &str) -> bool {
+        match name {
+            "color" | "width" | "height" | "hover" | "pointer" => true,
+            "prefers-reduced-motion" => self.prefers_reduced_motion,
+            "forced-colors" => self.forced_colors,
+            _ => false,
+        }
+    }
+
+    /// Check if a name is a feature that can be compared in a range
+    fn is_range_feature(&self, name: &str) -> bool {
+        matches!(name, "width" | "height" | "aspect-ratio" | "resolution" | "device-pixel-ratio")
+    }
+
+    /// Compare a media feature against a value from the query
+    fn compare_feature(&self, name: &str, comparison: Comparison, value: &str) -> bool {
+        let actual = match name {
+            "width" | "device-width" => self.viewport_width as f32,
+            "height" | "device-height" => self.viewport_height as f32,
+            "aspect-ratio" | "device-aspect-ratio" => {
+                if self.viewport_height == 0 {
+                    return false;
+                }
+                self.viewport_width as f32 / self.viewport_height as f32
+            }
+            "resolution" | "device-pixel-ratio" => self.device_pixel_ratio,
+            "orientation" => {
+                let landscape = self.viewport_width > self.viewport_height;
+                return comparison == Comparison::Equal && value == if landscape { "landscape" } else { "portrait" };
+            }
+            "prefers-color-scheme" => {
+                let scheme = match self.prefers_color_scheme {
+                    ColorScheme::Light => "light",
+                    ColorScheme::Dark => "dark",
+                };
+                return comparison == Comparison::Equal && value == scheme;
+            }
+            "prefers-reduced-motion" => {
+                let preference = if self.prefers_reduced_motion { "reduce" } else { "no-preference" };
+                return comparison == Comparison::Equal && value == preference;
+            }
+            "forced-colors" => {
+                let mode = if self.forced_colors { "active" } else { "none" };
+                return comparison == Comparison::Equal && value == mode;
+            }
+            "hover" => return comparison == Comparison::Equal && value == "hover",
+            "pointer" => return comparison == Comparison::Equal && value == "fine",
+            _ => return false,
+        };
+
+        let expected = match name {
+            "aspect-ratio" | "device-aspect-ratio" => parse_ratio(value),
+            "resolution" => parse_resolution(value),
+            "device-pixel-ratio" => value.parse().ok(),
+            _ => parse_length(value),
+        };
+        expected.is_some_and(|expected| comparison.compare(actual, expected))
+    }
+}
+
+impl Default for MediaQueryEvaluator {
+    fn default() -> Self {
+        Self::new(1024, 768)
+    }
+}
+
+/// Split a media query into media types, keywords and parenthesized conditions
+fn query_terms(query: &str) -> Vec<&str> {
+    let mut terms = Vec::new();
+    let mut depth = 0usize;
+    let mut start = None;
+
+    for (i, c) in query.char_indices() {
+        match c {
+            '(' => {
+                if depth == 0 {
+                    start = Some(i);
+                }
+                depth += 1;
+            }
+            ')' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    if let Some(start) = start.take() {
+                        terms.push(&query[start..=i]);
+                    }
+                }
+            }
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(start) = start.take() {
+                    terms.push(&query[start..i]);
+                }
+            }
+            _ if depth == 0 && start.is_none() => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(start) = start {
+        terms.push(&query[start..]);
+    }
+
+    terms
+}
+
+/// Split a media query list at commas outside parentheses
+fn split_top_level_commas(query: &str) -> Vec<&str> {
+    let mut queries = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in query.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                queries.push(query[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    queries.push(query[start..].trim());
+    queries
+}
+
+/// Parse a length in CSS pixels
+fn parse_length(value: &str) -> Option<f32> {
+    let value = value.trim();
+    if let Some(number) = value.strip_suffix("px") {
+        number.trim().parse().ok()
+    } else if let Some(number) = value.strip_suffix("rem").or_else(|| value.strip_suffix("em")) {
+        number.trim().parse::<f32>().ok().map(|number| number * DEFAULT_FONT_SIZE)
+    } else {
+        value.parse().ok().filter(|number: &f32| *number == 0.0)
+    }
+}
+
+/// Parse a ratio such as `16/9`
+fn parse_ratio(value: &str) -> Option<f32> {
+    match value.split_once('/') {
+        Some((width, height)) => {
+            let width: f32 = width.trim().parse().ok()?;
+            let height: f32 = height.trim().parse().ok()?;
+            (height != 0.0).then(|| width / height)
+        }
+        None => value.trim().parse().ok(),
+    }
+}
+
+/// Parse a resolution in device pixels per CSS pixel
+fn parse_resolution(value: &str) -> Option<f32> {
+    let value = value.trim();
+    if let Some(number) = value.strip_suffix("dppx").or_else(|| value.strip_suffix('x')) {
+        number.parse().ok()
+    } else if let Some(number) = value.strip_suffix("dpi") {
+        number.parse::<f32>().ok().map(|dpi| dpi / 96.0)
+    } else if let Some(number) = value.strip_suffix("dpcm") {
+        number.parse::<f32>().ok().map(|dpcm| dpcm * 2.54 / 96.0)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewport_queries() {
+        let mut evaluator = MediaQueryEvaluator::new(1024, 768);
+
+        assert!(evaluator.matches("screen and (min-width: 768px)"));
+        assert!(!evaluator.matches("screen and (max-width: 600px)"));
+        assert!(!evaluator.matches("print"));
+        assert!(evaluator.matches("print, (orientation: landscape)"));
+        assert!(evaluator.matches("not print"));
+        assert!(evaluator.matches("(width >= 1024px)"));
+        assert!(evaluator.matches("(600px < width <= 1200px)"));
+        assert!(evaluator.matches("(min-aspect-ratio: 4/3)"));
+
+        evaluator.set_viewport_size(500, 800);
+        assert!(evaluator.matches("screen and (max-width: 600px)"));
+        assert!(evaluator.matches("(orientation: portrait)"));
+        assert!(evaluator.matches("(max-width: 40em)"));
+
+        evaluator.media_type = MediaType::Print;
+        assert!(evaluator.matches("print and (orientation: portrait)"));
+        assert!(!evaluator.matches("screen"));
+        assert!(evaluator.matches("not screen"));
+    }
+
+    #[test]
+    fn test_preference_queries() {
+        let mut evaluator = MediaQueryEvaluator::default();
+        assert!(evaluator.matches("(prefers-color-scheme: light)"));
+        assert!(evaluator.matches("(prefers-reduced-motion: no-preference)"));
+        assert!(!evaluator.matches("(min-resolution: 2dppx)"));
+
+        evaluator.prefers_color_scheme = ColorScheme::Dark;
+        evaluator.prefers_reduced_motion = true;
+        evaluator.device_pixel_ratio = 2.0;
+        assert!(evaluator.matches("(prefers-color-scheme: dark)"));
+        assert!(evaluator.matches("(prefers-reduced-motion)"));
+        assert!(evaluator.matches("(min-resolution: 2dppx)"));
+        assert!(evaluator.matches("(-webkit-min-device-pixel-ratio: 1.5)"));
+
+        assert!(evaluator.matches("(forced-colors: none)"));
+        evaluator.forced_colors = true;
+        assert!(evaluator.matches("(forced-colors: active)"));
+        assert!(evaluator.matches("(forced-colors)"));
+    }
+}
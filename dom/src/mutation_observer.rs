@@ -3,8 +3,14 @@
 //! This module provides a complete MutationObserver system for monitoring
 //! changes to the DOM tree, including node additions, removals, attribute
 //! changes, and text content modifications.
+//!
+//! Records are batched: notifying a mutation only queues a record on each
+//! interested observer and asks the embedder to schedule a microtask. The
+//! microtask calls `MutationObserverManager::deliver_pending_mutations`, so
+//! every record from the current task reaches the callback in one call.
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::debug;
@@ -83,6 +89,9 @@ pub struct MutationRecord {
 /// Callback function for mutation observer
 pub type MutationCallback = Box<dyn Fn(Vec<MutationRecord>, Arc<MutationObserver>) + Send + Sync>;
 
+/// Hook used to queue a mutation observer microtask on the event loop
+pub type MicrotaskScheduler = Arc<dyn Fn() + Send + Sync>;
+
 /// MutationObserver for monitoring DOM changes
 pub struct MutationObserver {
     /// Unique ID for the observer
@@ -92,7 +101,7 @@ pub struct MutationObserver {
     /// Whether the observer is currently active
     pub active: bool,
     /// Records that have been queued but not yet delivered
-    pub pending_records: Vec<MutationRecord>,
+    pub pending_queue: Vec<MutationRecord>,
 }
 
 impl MutationObserver {
@@ -105,7 +114,7 @@ impl MutationObserver {
             id: format!("observer_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos()),
             callback: Arc::new(Box::new(callback)),
             active: false,
-            pending_records: Vec::new(),
+            pending_queue: Vec::new(),
         }
     }
     
@@ -121,13 +130,13 @@ impl MutationObserver {
     /// Stop observing all targets
     pub fn disconnect(&mut self) {
         self.active = false;
-        self.pending_records.clear();
+        self.pending_queue.clear();
         debug!("Disconnected observer {}", self.id);
     }
     
     /// Take all pending records and clear the queue
     pub fn take_records(&mut self) -> Vec<MutationRecord> {
-        let records = std::mem::take(&mut self.pending_records);
+        let records = std::mem::take(&mut self.pending_queue);
         debug!("Taking {} pending records from observer {}", records.len(), self.id);
         records
     }
@@ -135,14 +144,31 @@ impl MutationObserver {
     /// Add a mutation record to the pending queue
     pub fn add_record(&mut self, record: MutationRecord) {
         if self.active {
-            self.pending_records.push(record);
+            self.pending_queue.push(record);
             debug!("Added mutation record to observer {}", self.id);
         }
     }
     
+    /// Add a mutation record, folding it into a queued record for the same attribute
+    ///
+    /// Without `attributeOldValue` repeated changes to one attribute carry no
+    /// extra information, so they are reported once per batch.
+    pub fn add_coalesced_record(&mut self, record: MutationRecord) {
+        let duplicate = record.mutation_type == MutationType::Attributes
+            && self.pending_queue.iter().any(|queued| {
+                queued.mutation_type == MutationType::Attributes
+                    && queued.target == record.target
+                    && queued.attribute_name == record.attribute_name
+                    && queued.attribute_namespace == record.attribute_namespace
+            });
+        if !duplicate {
+            self.add_record(record);
+        }
+    }
+    
     /// Deliver pending records to the callback
     pub fn deliver_records(&mut self) {
-        if !self.pending_records.is_empty() {
+        if !self.pending_queue.is_empty() {
             let records = self.take_records();
             let record_count = records.len();
             let observer = Arc::new(self.clone());
@@ -158,7 +184,7 @@ impl Clone for MutationObserver {
             id: self.id.clone(),
             callback: self.callback.clone(),
             active: self.active,
-            pending_records: self.pending_records.clone(),
+            pending_queue: self.pending_queue.clone(),
         }
     }
 }
@@ -169,6 +195,12 @@ pub struct MutationObserverManager {
     observers: HashMap<String, Arc<RwLock<MutationObserver>>>,
     /// Target elements being observed
     observed_targets: HashMap<String, Vec<String>>, // target_id -> observer_ids
+    /// Options for each observed target, keyed by (observer_id, target_id)
+    observer_options: HashMap<(String, String), MutationObserverInit>,
+    /// Whether a delivery microtask has been scheduled and not yet run
+    microtask_queued: AtomicBool,
+    /// Hook that schedules the delivery microtask
+    microtask_scheduler: Option<MicrotaskScheduler>,
 }
 
 impl MutationObserverManager {
@@ -177,9 +209,19 @@ impl MutationObserverManager {
         Self {
             observers: HashMap::new(),
             observed_targets: HashMap::new(),
+            observer_options: HashMap::new(),
+            microtask_queued: AtomicBool::new(false),
+            microtask_scheduler: None,
         }
     }
     
+    /// Set the hook that schedules a microtask on the event loop
+    ///
+    /// The scheduled microtask must call `deliver_pending_mutations`.
+    pub fn set_microtask_scheduler(&mut self, scheduler: MicrotaskScheduler) {
+        self.microtask_scheduler = Some(scheduler);
+    }
+    
     /// Register a new observer
     pub fn register_observer(&mut self, observer: MutationObserver) -> String {
         let id = observer.id.clone();
@@ -195,16 +237,22 @@ impl MutationObserverManager {
         for target_observers in self.observed_targets.values_mut() {
             target_observers.retain(|id| id != observer_id);
         }
+        self.observer_options.retain(|(id, _), _| id != observer_id);
         debug!("Unregistered observer {}", observer_id);
     }
     
     /// Start observing a target element
-    pub fn observe_target(&mut self, observer_id: &str, target_id: &str, _options: MutationObserverInit) -> Result<()> {
-        if let Some(_observer) = self.observers.get(observer_id) {
-            // Add to observed targets
-            self.observed_targets.entry(target_id.to_string())
-                .or_insert_with(Vec::new)
-                .push(observer_id.to_string());
+    pub async fn observe_target(&mut self, observer_id: &str, target_id: &str, options: MutationObserverInit) -> Result<()> {
+        if let Some(observer) = self.observers.get(observer_id) {
+            observer.write().await.active = true;
+            
+            // Add to observed targets, replacing the options of an earlier observe call
+            let target_observers = self.observed_targets.entry(target_id.to_string())
+                .or_insert_with(Vec::new);
+            if !target_observers.iter().any(|id| id == observer_id) {
+                target_observers.push(observer_id.to_string());
+            }
+            self.observer_options.insert((observer_id.to_string(), target_id.to_string()), options);
             
             debug!("Started observing target {} with observer {}", target_id, observer_id);
             Ok(())
@@ -218,27 +266,102 @@ impl MutationObserverManager {
         if let Some(target_observers) = self.observed_targets.get_mut(target_id) {
             target_observers.retain(|id| id != observer_id);
         }
+        self.observer_options.remove(&(observer_id.to_string(), target_id.to_string()));
         debug!("Stopped observing target {} with observer {}", target_id, observer_id);
     }
     
-    /// Notify all observers of a mutation
+    /// Queue a mutation record for every observer interested in it
+    ///
+    /// Records are not delivered here; the first record of a batch schedules
+    /// a microtask that delivers them all.
     pub async fn notify_mutation(&self, record: MutationRecord) {
         let target_id = record.target.clone();
+        let mut queued = false;
         
         if let Some(observer_ids) = self.observed_targets.get(&target_id) {
             for observer_id in observer_ids {
+                let key = (observer_id.clone(), target_id.clone());
+                let Some(options) = self.observer_options.get(&key) else {
+                    continue;
+                };
+                if !Self::wants_record(options, &record) {
+                    continue;
+                }
+                
                 if let Some(observer) = self.observers.get(observer_id) {
                     let mut observer = observer.write().await;
-                    observer.add_record(record.clone());
+                    let mut record = record.clone();
+                    let keep_old_value = match record.mutation_type {
+                        MutationType::Attributes => options.attribute_old_value,
+                        MutationType::CharacterData => options.character_data_old_value,
+                        _ => true,
+                    };
+                    if !keep_old_value {
+                        record.old_value = None;
+                    }
+                    
+                    if options.attribute_old_value {
+                        observer.add_record(record);
+                    } else {
+                        observer.add_coalesced_record(record);
+                    }
+                    queued = true;
                 }
             }
         }
         
+        if queued {
+            self.schedule_delivery();
+        }
+        
         debug!("Notified {} observers of mutation on target {}", 
                self.observed_targets.get(&target_id).map(|v| v.len()).unwrap_or(0), 
                target_id);
     }
     
+    /// Check if the options of an observed target cover a record
+    fn wants_record(options: &MutationObserverInit, record: &MutationRecord) -> bool {
+        match record.mutation_type {
+            MutationType::ChildList => options.child_list,
+            MutationType::Attributes => {
+                let attributes = options.attributes || options.attribute_old_value || options.attribute_filter.is_some();
+                let filtered_in = match (&options.attribute_filter, &record.attribute_name) {
+                    (Some(filter), Some(name)) => filter.contains(name),
+                    _ => true,
+                };
+                attributes && filtered_in
+            }
+            MutationType::CharacterData => options.character_data || options.character_data_old_value,
+            MutationType::All => true,
+        }
+    }
+    
+    /// Schedule the delivery microtask unless one is already pending
+    fn schedule_delivery(&self) {
+        if self.microtask_queued.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        
+        match &self.microtask_scheduler {
+            Some(scheduler) => scheduler(),
+            None => debug!("No microtask scheduler set, mutations wait for an explicit delivery"),
+        }
+    }
+    
+    /// Check if a delivery microtask is pending
+    pub fn has_pending_delivery(&self) -> bool {
+        self.microtask_queued.load(Ordering::Acquire)
+    }
+    
+    /// Deliver the records queued since the last delivery
+    ///
+    /// This is the body of the mutation observer microtask: each observer with
+    /// pending records gets a single callback with all of them.
+    pub async fn deliver_pending_mutations(&self) {
+        self.microtask_queued.store(false, Ordering::Release);
+        self.deliver_all_records().await;
+    }
+    
     /// Deliver all pending records
     pub async fn deliver_all_records(&self) {
         for observer in self.observers.values() {
@@ -269,7 +392,7 @@ mod tests {
         });
         
         assert!(!observer.active);
-        assert!(observer.pending_records.is_empty());
+        assert!(observer.pending_queue.is_empty());
     }
 
     #[test]
@@ -335,6 +458,98 @@ mod tests {
         // Test disconnect
         observer.disconnect();
         assert!(!observer.active);
-        assert!(observer.pending_records.is_empty());
+        assert!(observer.pending_queue.is_empty());
+    }
+
+    fn attribute_record(target: &str, name: &str, old_value: &str) -> MutationRecord {
+        MutationRecord {
+            mutation_type: MutationType::Attributes,
+            target: target.to_string(),
+            added_nodes: vec![],
+            removed_nodes: vec![],
+            previous_sibling: None,
+            next_sibling: None,
+            attribute_name: Some(name.to_string()),
+            attribute_namespace: None,
+            old_value: Some(old_value.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mutations_are_batched_into_one_microtask() {
+        use std::sync::Mutex;
+        use tokio::sync::mpsc;
+
+        let deliveries = Arc::new(Mutex::new(Vec::new()));
+        let delivered = deliveries.clone();
+        let observer = MutationObserver::new(move |records, _observer| {
+            delivered.lock().unwrap().push(records.len());
+        });
+
+        let (microtask_tx, mut microtask_rx) = mpsc::unbounded_channel();
+        let mut manager = MutationObserverManager::new();
+        manager.set_microtask_scheduler(Arc::new(move || {
+            microtask_tx.send(()).unwrap();
+        }));
+
+        let observer_id = manager.register_observer(observer);
+        let options = MutationObserverInit { child_list: true, ..Default::default() };
+        manager.observe_target(&observer_id, "list", options).await.unwrap();
+
+        for i in 0..100 {
+            let mut item = Element::new("li".to_string());
+            item.id = format!("item{}", i);
+            let record = MutationRecord {
+                mutation_type: MutationType::ChildList,
+                target: "list".to_string(),
+                added_nodes: vec![Node::Element(item)],
+                removed_nodes: vec![],
+                previous_sibling: None,
+                next_sibling: None,
+                attribute_name: None,
+                attribute_namespace: None,
+                old_value: None,
+            };
+            manager.notify_mutation(record).await;
+        }
+
+        // Nothing is delivered synchronously
+        assert!(deliveries.lock().unwrap().is_empty());
+        assert!(manager.has_pending_delivery());
+
+        // Run the event loop's microtasks
+        while let Ok(()) = microtask_rx.try_recv() {
+            manager.deliver_pending_mutations().await;
+        }
+
+        assert_eq!(*deliveries.lock().unwrap(), vec![100]);
+        assert!(!manager.has_pending_delivery());
+    }
+
+    #[tokio::test]
+    async fn test_attribute_records_coalesce_without_old_value() {
+        let mut manager = MutationObserverManager::new();
+        let coalescing = manager.register_observer(MutationObserver::new(|_, _| {}));
+        let mut keeping = MutationObserver::new(|_, _| {});
+        keeping.id = "keeping".to_string();
+        let keeping = manager.register_observer(keeping);
+
+        let options = MutationObserverInit { attributes: true, ..Default::default() };
+        manager.observe_target(&coalescing, "box", options).await.unwrap();
+        let options = MutationObserverInit { attributes: true, attribute_old_value: true, ..Default::default() };
+        manager.observe_target(&keeping, "box", options).await.unwrap();
+
+        for i in 0..5 {
+            manager.notify_mutation(attribute_record("box", "class", &format!("c{}", i))).await;
+        }
+        manager.notify_mutation(attribute_record("box", "title", "t")).await;
+
+        let coalesced = manager.observers[&coalescing].write().await.take_records();
+        assert_eq!(coalesced.len(), 2);
+        assert!(coalesced.iter().all(|record| record.old_value.is_none()));
+
+        let kept = manager.observers[&keeping].write().await.take_records();
+        assert_eq!(kept.len(), 6);
+        assert_eq!(kept[0].old_value.as_deref(), Some("c0"));
     }
 }
@@ -5,8 +5,8 @@
 
 use std::collections::HashMap;
 use tracing::debug;
-use crate::dom::Element;
-use crate::css_selector::PseudoClass;
+use crate::dom::{Document, Element, Node};
+use crate::css_selector::{AttributeOperator, AttributeSelector, Combinator, ComplexSelector, ComplexSelectorPart, PseudoClass, SelectorList, SimpleSelector};
 
 /// State of an element for pseudo-class evaluation
 #[derive(Debug, Clone, PartialEq)]
@@ -273,10 +273,9 @@ impl PseudoClassEvaluator {
                 // In a real implementation, this would evaluate the selector list
                 true
             }
-            PseudoClass::Has(_) => {
-                // This is a placeholder implementation
-                // In a real implementation, this would evaluate the :has() selector
-                true
+            PseudoClass::Has(selectors) => {
+                // Without a document only the element's own subtree is searched
+                selectors.selectors.iter().any(|selector| self.matches_relative(element, None, selector, None))
             }
             PseudoClass::Custom(_) => {
                 // This is a placeholder implementation
@@ -286,6 +285,91 @@ impl PseudoClassEvaluator {
         }
     }
     
+    /// Evaluate `:has()` for an element in a document
+    ///
+    /// The element's descendants (and following siblings, for `+` and `~`
+    /// relative selectors) are searched depth-first, stopping at the first match.
+    pub fn evaluate_has(&self, element: &Element, arg: &SelectorList, tree: &Document) -> bool {
+        // Locate the element in the tree so sibling combinators can see its parent
+        let located = find_element(&tree.root, None, &|candidate| std::ptr::eq(candidate, element))
+            .or_else(|| find_element(&tree.root, None, &|candidate| candidate.id == element.id));
+        let (subject, parent) = located.unwrap_or((element, None));
+        
+        arg.selectors.iter().any(|selector| self.matches_relative(subject, parent, selector, Some(tree)))
+    }
+    
+    /// Check if a relative selector matches anything relative to an anchor element
+    fn matches_relative(&self, anchor: &Element, parent: Option<&Element>, selector: &ComplexSelector, tree: Option<&Document>) -> bool {
+        match selector.parts.split_first() {
+            Some((ComplexSelectorPart::Combinator(combinator), parts)) => {
+                self.matches_relative_parts(anchor, parent, combinator, parts, tree)
+            }
+            _ => self.matches_relative_parts(anchor, parent, &Combinator::Descendant, &selector.parts, tree),
+        }
+    }
+    
+    /// Match the remaining compound selectors of a relative selector, left to right
+    fn matches_relative_parts(
+        &self,
+        anchor: &Element,
+        parent: Option<&Element>,
+        combinator: &Combinator,
+        parts: &[ComplexSelectorPart],
+        tree: Option<&Document>,
+    ) -> bool {
+        let Some((ComplexSelectorPart::Compound(compound), rest)) = parts.split_first() else {
+            return false;
+        };
+        
+        let visit = |candidate: &Element, candidate_parent: Option<&Element>| {
+            if !self.matches_compound(candidate, &compound.simple, tree) {
+                return false;
+            }
+            match rest.split_first() {
+                None => true,
+                Some((ComplexSelectorPart::Combinator(next), rest)) => {
+                    self.matches_relative_parts(candidate, candidate_parent, next, rest, tree)
+                }
+                Some(_) => false,
+            }
+        };
+        
+        match combinator {
+            Combinator::Child => child_elements(anchor).any(|child| visit(child, Some(anchor))),
+            Combinator::Descendant => any_descendant(anchor, &visit),
+            Combinator::AdjacentSibling => following_siblings(anchor, parent).next().is_some_and(|sibling| visit(sibling, parent)),
+            Combinator::GeneralSibling => following_siblings(anchor, parent).any(|sibling| visit(sibling, parent)),
+        }
+    }
+    
+    /// Check if a compound selector matches an element
+    fn matches_compound(&self, element: &Element, selector: &SimpleSelector, tree: Option<&Document>) -> bool {
+        if selector.element_type.as_ref().is_some_and(|tag_name| tag_name != &element.tag_name) {
+            return false;
+        }
+        if selector.id.as_ref().is_some_and(|id| element.get_attribute("id") != Some(id)) {
+            return false;
+        }
+        
+        let classes = element.get_attribute("class").map(String::as_str).unwrap_or_default();
+        if !selector.classes.iter().all(|class| classes.split_whitespace().any(|c| c == class)) {
+            return false;
+        }
+        if !selector.attributes.iter().all(|attribute| matches_attribute(element, attribute)) {
+            return false;
+        }
+        
+        // Pseudo-elements are not valid inside :has()
+        if !selector.pseudo_elements.is_empty() {
+            return false;
+        }
+        
+        selector.pseudo_classes.iter().all(|pseudo_class| match (pseudo_class, tree) {
+            (PseudoClass::Has(selectors), Some(tree)) => self.evaluate_has(element, selectors, tree),
+            _ => self.evaluate_pseudo_class(element, pseudo_class),
+        })
+    }
+    
     /// Check if an element is the first child
     fn is_first_child(&self, _element: &Element) -> bool {
         // This is a placeholder implementation
@@ -343,6 +427,59 @@ impl PseudoClassEvaluator {
     }
 }
 
+/// Element children of an element
+fn child_elements(element: &Element) -> impl Iterator<Item = &Element> {
+    element.children.iter().filter_map(|child| match child {
+        Node::Element(child) => Some(child),
+        _ => None,
+    })
+}
+
+/// Depth-first search of an element's descendants
+fn any_descendant(element: &Element, visit: &dyn Fn(&Element, Option<&Element>) -> bool) -> bool {
+    child_elements(element).any(|child| visit(child, Some(element)) || any_descendant(child, visit))
+}
+
+/// Element siblings following an element
+fn following_siblings<'a>(element: &'a Element, parent: Option<&'a Element>) -> impl Iterator<Item = &'a Element> {
+    parent.into_iter()
+        .flat_map(child_elements)
+        .skip_while(move |sibling| !std::ptr::eq(*sibling, element))
+        .skip(1)
+}
+
+/// Find an element and its parent in a tree
+fn find_element<'a>(
+    root: &'a Element,
+    parent: Option<&'a Element>,
+    predicate: &dyn Fn(&Element) -> bool,
+) -> Option<(&'a Element, Option<&'a Element>)> {
+    if predicate(root) {
+        return Some((root, parent));
+    }
+    child_elements(root).find_map(|child| find_element(child, Some(root), predicate))
+}
+
+/// Check if an attribute selector matches an element
+fn matches_attribute(element: &Element, selector: &AttributeSelector) -> bool {
+    let Some(value) = element.get_attribute(&selector.name) else {
+        return false;
+    };
+    let Some(expected) = &selector.value else {
+        return selector.operator == AttributeOperator::Exists;
+    };
+
+    match selector.operator {
+        AttributeOperator::Exists => true,
+        AttributeOperator::Equals => value == expected,
+        AttributeOperator::ContainsWord => value.split_whitespace().any(|word| word == expected),
+        AttributeOperator::StartsWith => value == expected || value.starts_with(&format!("{}-", expected)),
+        AttributeOperator::StartsWithPrefix => value.starts_with(expected.as_str()),
+        AttributeOperator::EndsWith => value.ends_with(expected.as_str()),
+        AttributeOperator::Contains => value.contains(expected.as_str()),
+    }
+}
+
 /// Event handler for pseudo-class state changes
 pub struct PseudoClassEventHandler {
     /// The evaluator to update
@@ -478,6 +615,69 @@ mod tests {
         assert!(!evaluator.evaluate_pseudo_class(&element, &PseudoClass::Active));
     }
 
+    fn element(tag_name: &str, id: &str, class: Option<&str>, children: Vec<Element>) -> Element {
+        let mut element = Element::new(tag_name.to_string());
+        element.id = id.to_string();
+        if let Some(class) = class {
+            element.set_attribute("class".to_string(), class.to_string());
+        }
+        element.children = children.into_iter().map(Node::Element).collect();
+        element
+    }
+
+    fn find<'a>(document: &'a Document, id: &str) -> &'a Element {
+        find_element(&document.root, None, &|element| element.id == id).unwrap().0
+    }
+
+    fn has_argument(selector: &str) -> SelectorList {
+        let mut parser = crate::css_selector::CssSelectorParser::new(selector).unwrap();
+        let list = parser.parse_selector_list().unwrap();
+        match &list.selectors[0].parts[0] {
+            ComplexSelectorPart::Compound(compound) => match &compound.simple.pseudo_classes[0] {
+                PseudoClass::Has(argument) => (**argument).clone(),
+                other => panic!("Expected :has(), got {:?}", other),
+            },
+            other => panic!("Expected compound selector, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_has_child_combinator() {
+        let evaluator = PseudoClassEvaluator::new();
+        let mut document = Document::new();
+        document.root = element("html", "html", None, vec![
+            element("body", "body", None, vec![
+                element("div", "direct", None, vec![element("span", "s1", Some("item active"), vec![])]),
+                element("div", "nested", None, vec![
+                    element("p", "p1", None, vec![element("span", "s2", Some("active"), vec![])]),
+                ]),
+                element("div", "none", None, vec![element("span", "s3", Some("item"), vec![])]),
+            ]),
+        ]);
+
+        let child = has_argument("div:has(> .active)");
+        assert!(evaluator.evaluate_has(find(&document, "direct"), &child, &document));
+        assert!(!evaluator.evaluate_has(find(&document, "nested"), &child, &document));
+        assert!(!evaluator.evaluate_has(find(&document, "none"), &child, &document));
+
+        let descendant = has_argument("div:has(.active)");
+        assert!(evaluator.evaluate_has(find(&document, "nested"), &descendant, &document));
+        assert!(!evaluator.evaluate_has(find(&document, "none"), &descendant, &document));
+    }
+
+    #[test]
+    fn test_has_sibling_combinator() {
+        let evaluator = PseudoClassEvaluator::new();
+        let mut document = Document::new();
+        document.root = element("html", "html", None, vec![
+            element("h1", "title", None, vec![]),
+            element("p", "intro", Some("lead"), vec![]),
+        ]);
+
+        assert!(evaluator.evaluate_has(find(&document, "title"), &has_argument("h1:has(+ p.lead)"), &document));
+        assert!(!evaluator.evaluate_has(find(&document, "intro"), &has_argument("p:has(~ h1)"), &document));
+    }
+
     #[test]
     fn test_event_handler() {
         let evaluator = PseudoClassEvaluator::new();
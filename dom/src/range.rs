@@ -0,0 +1,600 @@
+//! DOM Range API.
+//!
+//! A `Range` spans the document between two boundary points. A boundary
+//! point is a container node and an offset into it, a character offset for
+//! text and comment nodes and a child index for elements.
+
+use crate::dom::{CommentNode, Document, DocumentFragment, Element, Node, TextNode};
+use crate::error::{Error, Result};
+use crate::layout::LayoutBox;
+
+/// Node of a document, identified by the child indices leading to it from the root element
+///
+/// Node IDs compare in document order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(pub Vec<usize>);
+
+impl NodeId {
+    /// The root element of a document
+    pub fn root() -> Self {
+        Self(Vec::new())
+    }
+
+    /// A child of this node
+    pub fn child(&self, index: usize) -> Self {
+        let mut path = self.0.clone();
+        path.push(index);
+        Self(path)
+    }
+
+    /// The parent of this node, none for the root element
+    pub fn parent(&self) -> Option<Self> {
+        self.0.split_last().map(|(_, parent)| Self(parent.to_vec()))
+    }
+
+    /// Index of this node among its parent's children
+    pub fn index(&self) -> Option<usize> {
+        self.0.last().copied()
+    }
+
+    /// Whether this node is an ancestor of another node, or the node itself
+    pub fn contains(&self, other: &NodeId) -> bool {
+        other.0.starts_with(&self.0)
+    }
+
+    /// Find the node ID of an element by the ID it was created with
+    pub fn of_element(root: &Element, element_id: &str) -> Option<Self> {
+        if root.id == element_id {
+            return Some(Self::root());
+        }
+        root.children.iter().enumerate().find_map(|(index, child)| match child {
+            Node::Element(child) => Self::of_element(child, element_id).map(|mut id| {
+                id.0.insert(0, index);
+                id
+            }),
+            _ => None,
+        })
+    }
+}
+
+/// Rectangle in client coordinates, as `DOMRect`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DomRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl DomRect {
+    /// Create a rectangle
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Smallest rectangle containing both rectangles
+    pub fn union(&self, other: &DomRect) -> DomRect {
+        let left = self.x.min(other.x);
+        let top = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        DomRect::new(left, top, right - left, bottom - top)
+    }
+}
+
+/// Range of a document between two boundary points
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range {
+    pub start_container: NodeId,
+    pub start_offset: usize,
+    pub end_container: NodeId,
+    pub end_offset: usize,
+}
+
+impl Default for Range {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Range {
+    /// Create a range collapsed at the start of the root element
+    pub fn new() -> Self {
+        Self {
+            start_container: NodeId::root(),
+            start_offset: 0,
+            end_container: NodeId::root(),
+            end_offset: 0,
+        }
+    }
+
+    /// Whether the start and end are the same point
+    pub fn collapsed(&self) -> bool {
+        self.start_container == self.end_container && self.start_offset == self.end_offset
+    }
+
+    /// Deepest node containing both boundary points
+    pub fn common_ancestor_container(&self) -> NodeId {
+        let common = self.start_container.0.iter()
+            .zip(&self.end_container.0)
+            .take_while(|(start, end)| start == end)
+            .count();
+        NodeId(self.start_container.0[..common].to_vec())
+    }
+
+    /// Set the start, collapsing the range if it would end before it starts
+    pub fn set_start(&mut self, doc: &Document, node: NodeId, offset: usize) -> Result<()> {
+        check_boundary(doc, &node, offset)?;
+        self.start_container = node;
+        self.start_offset = offset;
+        let (start, end) = self.point_keys(&doc.root);
+        if start > end {
+            self.collapse(true);
+        }
+        Ok(())
+    }
+
+    /// Set the end, collapsing the range if it would start after it ends
+    pub fn set_end(&mut self, doc: &Document, node: NodeId, offset: usize) -> Result<()> {
+        check_boundary(doc, &node, offset)?;
+        self.end_container = node;
+        self.end_offset = offset;
+        let (start, end) = self.point_keys(&doc.root);
+        if start > end {
+            self.collapse(false);
+        }
+        Ok(())
+    }
+
+    /// Collapse the range to its start or end
+    pub fn collapse(&mut self, to_start: bool) {
+        if to_start {
+            self.end_container = self.start_container.clone();
+            self.end_offset = self.start_offset;
+        } else {
+            self.start_container = self.end_container.clone();
+            self.start_offset = self.end_offset;
+        }
+    }
+
+    /// Select a node, from just before it to just after it
+    pub fn select_node(&mut self, doc: &Document, node: &NodeId) -> Result<()> {
+        let (parent, index) = node.parent().zip(node.index())
+            .ok_or_else(|| Error::InvalidState("InvalidNodeTypeError: the node has no parent".to_string()))?;
+        check_boundary(doc, node, 0)?;
+        self.start_container = parent.clone();
+        self.start_offset = index;
+        self.end_container = parent;
+        self.end_offset = index + 1;
+        Ok(())
+    }
+
+    /// Copy the nodes within the range into a fragment
+    ///
+    /// Nodes partly within the range are copied with only the children and
+    /// text that are.
+    pub fn clone_contents(&self, doc: &Document) -> Result<DocumentFragment> {
+        check_boundary(doc, &self.start_container, self.start_offset)?;
+        check_boundary(doc, &self.end_container, self.end_offset)?;
+        let keys = self.point_keys(&doc.root);
+        let common = self.common_ancestor_container();
+        let mut fragment = DocumentFragment::new();
+
+        match node_at(&doc.root, &common) {
+            Some(Node::Element(element)) => fragment.children = self.clone_children(&keys, element, &common),
+            Some(node) => fragment.children.extend(self.clone_node(&keys, node, &common)),
+            None => fragment.children = self.clone_children(&keys, &doc.root, &common),
+        }
+        Ok(fragment)
+    }
+
+    /// Remove the nodes within the range from the document and collapse it
+    ///
+    /// Text partly within the range is trimmed, and elements partly within
+    /// it keep their children outside it.
+    pub fn delete_contents(&mut self, doc: &mut Document) -> Result<()> {
+        check_boundary(doc, &self.start_container, self.start_offset)?;
+        check_boundary(doc, &self.end_container, self.end_offset)?;
+        if self.collapsed() {
+            return Ok(());
+        }
+
+        // The range collapses to just after the highest partly selected ancestor of the start
+        let (container, offset) = if self.start_container.contains(&self.end_container) {
+            (self.start_container.clone(), self.start_offset)
+        } else {
+            let mut reference = self.start_container.clone();
+            while let Some(parent) = reference.parent().filter(|parent| !parent.contains(&self.end_container)) {
+                reference = parent;
+            }
+            let index = reference.index().unwrap_or_default();
+            (reference.parent().unwrap_or_default(), index + 1)
+        };
+
+        let keys = self.point_keys(&doc.root);
+        self.delete_children(&keys, &mut doc.root, &NodeId::root());
+        self.start_container = container.clone();
+        self.start_offset = offset;
+        self.end_container = container;
+        self.end_offset = offset;
+        Ok(())
+    }
+
+    /// Insert a node at the start of the range
+    ///
+    /// A text start container is split at the offset and the node inserted
+    /// between its halves. A collapsed range grows to contain the node.
+    pub fn insert_node(&mut self, doc: &mut Document, node: Node) -> Result<()> {
+        if matches!(node, Node::DocumentType(_)) {
+            return Err(Error::InvalidState("HierarchyRequestError: cannot insert a document type into a range".to_string()));
+        }
+        check_boundary(doc, &self.start_container, self.start_offset)?;
+        let collapsed = self.collapsed();
+
+        let (parent, index) = match node_at(&doc.root, &self.start_container) {
+            Some(Node::Text(_)) => {
+                let parent = self.start_container.parent().unwrap_or_default();
+                let index = self.start_container.index().unwrap_or_default();
+                let parent_element = element_at_mut(&mut doc.root, &parent)
+                    .ok_or_else(|| Error::NotFound("Range container is not in the document".to_string()))?;
+                let Node::Text(text) = &mut parent_element.children[index] else {
+                    unreachable!("the start container is a text node");
+                };
+                let rest = text.content.split_off(byte_index(&text.content, self.start_offset));
+                parent_element.children.insert(index + 1, node);
+                parent_element.children.insert(index + 2, Node::Text(TextNode::new(rest)));
+
+                // The end moves with the text or siblings after the split
+                if self.end_container == self.start_container && self.end_offset >= self.start_offset {
+                    self.end_container = parent.child(index + 2);
+                    self.end_offset -= self.start_offset;
+                } else {
+                    self.shift_end(&parent, index + 1, 2);
+                }
+                (parent, index + 1)
+            }
+            Some(Node::Comment(_) | Node::DocumentType(_)) => {
+                return Err(Error::InvalidState("HierarchyRequestError: cannot insert into a comment or document type".to_string()));
+            }
+            _ => {
+                let parent = self.start_container.clone();
+                let element = element_at_mut(&mut doc.root, &parent)
+                    .ok_or_else(|| Error::NotFound("Range container is not in the document".to_string()))?;
+                element.children.insert(self.start_offset, node);
+                self.shift_end(&parent, self.start_offset, 1);
+                (parent, self.start_offset)
+            }
+        };
+
+        if collapsed {
+            self.end_container = parent;
+            self.end_offset = index + 1;
+        }
+        Ok(())
+    }
+
+    /// Bounding box of the range's nodes in a laid out tree, as `getBoundingClientRect()`
+    ///
+    /// Text has no boxes of its own, so selected text counts as its parent element's box.
+    pub fn get_bounding_client_rect(&self, doc: &Document, layout_root: &LayoutBox) -> DomRect {
+        let keys = self.point_keys(&doc.root);
+        let mut element_ids = Vec::new();
+        self.collect_boxed_elements(&keys, &doc.root, &NodeId::root(), &mut element_ids);
+
+        element_ids.iter()
+            .filter_map(|element_id| find_layout_box(layout_root, element_id))
+            .map(border_box)
+            .reduce(|rect, other| rect.union(&other))
+            .unwrap_or_default()
+    }
+
+    /// Keys of the start and end points, ordered in document order
+    fn point_keys(&self, root: &Element) -> (PointKey, PointKey) {
+        (
+            point_key(root, &self.start_container, self.start_offset),
+            point_key(root, &self.end_container, self.end_offset),
+        )
+    }
+
+    /// Characters of a text or comment node within the range
+    fn character_range(&self, node: &NodeId, length: usize) -> (usize, usize) {
+        let from = if *node == self.start_container { self.start_offset } else { 0 };
+        let to = if *node == self.end_container { self.end_offset } else { length };
+        (from.min(length), to.min(length).max(from.min(length)))
+    }
+
+    /// Clone the children of an element that are within the range
+    fn clone_children(&self, keys: &(PointKey, PointKey), element: &Element, path: &NodeId) -> Vec<Node> {
+        element.children.iter()
+            .enumerate()
+            .filter(|(index, _)| intersects(keys, &path.child(*index)))
+            .filter_map(|(index, child)| self.clone_node(keys, child, &path.child(index)))
+            .collect()
+    }
+
+    /// Clone the part of a node within the range
+    fn clone_node(&self, keys: &(PointKey, PointKey), node: &Node, path: &NodeId) -> Option<Node> {
+        match node {
+            Node::Text(text) => {
+                let chars: Vec<char> = text.content.chars().collect();
+                let (from, to) = self.character_range(path, chars.len());
+                Some(Node::Text(TextNode::new(chars[from..to].iter().collect())))
+            }
+            Node::Comment(comment) => {
+                let chars: Vec<char> = comment.content.chars().collect();
+                let (from, to) = self.character_range(path, chars.len());
+                Some(Node::Comment(CommentNode::new(chars[from..to].iter().collect())))
+            }
+            Node::Element(element) => {
+                let mut clone = element.clone();
+                clone.children = self.clone_children(keys, element, path);
+                Some(Node::Element(clone))
+            }
+            Node::DocumentType(_) => None,
+        }
+    }
+
+    /// Remove the children of an element within the range, trimming those partly within it
+    fn delete_children(&self, keys: &(PointKey, PointKey), element: &mut Element, path: &NodeId) {
+        let children = std::mem::take(&mut element.children);
+
+        for (index, mut child) in children.into_iter().enumerate() {
+            let child_path = path.child(index);
+            if !intersects(keys, &child_path) {
+                element.children.push(child);
+                continue;
+            }
+            if contains_node(keys, &child_path) {
+                continue;
+            }
+
+            match &mut child {
+                Node::Text(TextNode { content }) | Node::Comment(CommentNode { content }) => {
+                    let chars: Vec<char> = content.chars().collect();
+                    let (from, to) = self.character_range(&child_path, chars.len());
+                    *content = chars[..from].iter().chain(&chars[to..]).collect();
+                }
+                Node::Element(child_element) => self.delete_children(keys, child_element, &child_path),
+                Node::DocumentType(_) => {}
+            }
+            element.children.push(child);
+        }
+    }
+
+    /// Shift the end point after nodes are inserted into a parent at an index
+    fn shift_end(&mut self, parent: &NodeId, index: usize, count: usize) {
+        if self.end_container == *parent {
+            if self.end_offset > index {
+                self.end_offset += count;
+            }
+        } else if self.end_container.0.len() > parent.0.len() && parent.contains(&self.end_container) {
+            let level = parent.0.len();
+            if self.end_container.0[level] >= index {
+                self.end_container.0[level] += count;
+            }
+        }
+    }
+
+    /// Collect the IDs of elements whose boxes cover the range
+    fn collect_boxed_elements(&self, keys: &(PointKey, PointKey), element: &Element, path: &NodeId, element_ids: &mut Vec<String>) {
+        for (index, child) in element.children.iter().enumerate() {
+            let child_path = path.child(index);
+            if !intersects(keys, &child_path) {
+                continue;
+            }
+            match child {
+                Node::Text(_) if !element_ids.contains(&element.id) => element_ids.push(element.id.clone()),
+                Node::Element(child) if contains_node(keys, &child_path) => element_ids.push(child.id.clone()),
+                Node::Element(child) => self.collect_boxed_elements(keys, child, &child_path, element_ids),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Sort key of a boundary point
+///
+/// A point in an element is just before the child at its offset, so it
+/// sorts as that child's ID. Points in text or comments sort after the
+/// node's own ID, by character offset.
+type PointKey = (Vec<usize>, bool, usize);
+
+fn point_key(root: &Element, container: &NodeId, offset: usize) -> PointKey {
+    match node_at(root, container) {
+        Some(Node::Text(_) | Node::Comment(_)) => (container.0.clone(), true, offset),
+        _ => (container.child(offset).0, false, 0),
+    }
+}
+
+/// Whether part of a node is between two points
+fn intersects((start, end): &(PointKey, PointKey), node: &NodeId) -> bool {
+    before_key(node) < *end && *start < after_key(node)
+}
+
+/// Whether all of a node is between two points
+fn contains_node((start, end): &(PointKey, PointKey), node: &NodeId) -> bool {
+    *start <= before_key(node) && after_key(node) <= *end
+}
+
+/// Key of the point just before a node
+fn before_key(node: &NodeId) -> PointKey {
+    (node.0.clone(), false, 0)
+}
+
+/// Key of the point just after a node
+fn after_key(node: &NodeId) -> PointKey {
+    let mut path = node.0.clone();
+    if let Some(index) = path.last_mut() {
+        *index += 1;
+    }
+    (path, false, 0)
+}
+
+/// Find a node by ID; the root element is not a child node, so it is none
+fn node_at<'a>(root: &'a Element, node: &NodeId) -> Option<&'a Node> {
+    let (index, parent) = node.0.split_last()?;
+    element_at(root, &NodeId(parent.to_vec()))?.children.get(*index)
+}
+
+fn element_at<'a>(root: &'a Element, node: &NodeId) -> Option<&'a Element> {
+    node.0.iter().try_fold(root, |element, index| match element.children.get(*index) {
+        Some(Node::Element(child)) => Some(child),
+        _ => None,
+    })
+}
+
+fn element_at_mut<'a>(root: &'a mut Element, node: &NodeId) -> Option<&'a mut Element> {
+    node.0.iter().try_fold(root, |element, index| match element.children.get_mut(*index) {
+        Some(Node::Element(child)) => Some(child),
+        _ => None,
+    })
+}
+
+/// Check that a boundary point is in the document and its offset within the node
+fn check_boundary(doc: &Document, node: &NodeId, offset: usize) -> Result<()> {
+    let length = if node.0.is_empty() {
+        doc.root.children.len()
+    } else {
+        match node_at(&doc.root, node) {
+            Some(Node::Element(element)) => element.children.len(),
+            Some(Node::Text(TextNode { content }) | Node::Comment(CommentNode { content })) => content.chars().count(),
+            Some(Node::DocumentType(_)) => {
+                return Err(Error::InvalidState("InvalidNodeTypeError: a range cannot be in a document type".to_string()));
+            }
+            None => return Err(Error::NotFound(format!("Node {:?} is not in the document", node.0))),
+        }
+    };
+
+    if offset > length {
+        return Err(Error::InvalidState(format!("IndexSizeError: offset {} is greater than the node's length {}", offset, length)));
+    }
+    Ok(())
+}
+
+fn byte_index(text: &str, char_offset: usize) -> usize {
+    text.char_indices().nth(char_offset).map_or(text.len(), |(index, _)| index)
+}
+
+fn find_layout_box<'a>(layout_box: &'a LayoutBox, element_id: &str) -> Option<&'a LayoutBox> {
+    if layout_box.element.id == element_id {
+        return Some(layout_box);
+    }
+    layout_box.children.iter().find_map(|child| find_layout_box(child, element_id))
+}
+
+fn border_box(layout_box: &LayoutBox) -> DomRect {
+    DomRect::new(
+        layout_box.position_coords.x,
+        layout_box.position_coords.y,
+        layout_box.dimensions.total_width(),
+        layout_box.dimensions.total_height(),
+    )
+}
+
+/// Find the deepest box containing a point
+pub(crate) fn hit_test(layout_box: &LayoutBox, x: f32, y: f32) -> Option<&LayoutBox> {
+    let rect = border_box(layout_box);
+    let inside = x >= rect.x && x < rect.x + rect.width && y >= rect.y && y < rect.y + rect.height;
+    let child = layout_box.children.iter().rev().find_map(|child| hit_test(child, x, y));
+    child.or(inside.then_some(layout_box))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `<body><p>Hello world</p><p>Second paragraph</p></body>`
+    fn two_paragraphs() -> Document {
+        let mut body = Element::new("body".to_string());
+        for text in ["Hello world", "Second paragraph"] {
+            let mut paragraph = Element::new("p".to_string());
+            paragraph.append_child(Node::Text(TextNode::new(text.to_string())));
+            body.append_child(Node::Element(paragraph));
+        }
+        let mut document = Document::new();
+        document.root.append_child(Node::Element(body));
+        document
+    }
+
+    fn paragraph_text(document: &Document, index: usize) -> String {
+        match node_at(&document.root, &NodeId(vec![0, index])) {
+            Some(Node::Element(paragraph)) => paragraph.text_content(),
+            _ => panic!("paragraph {} is missing", index),
+        }
+    }
+
+    #[test]
+    fn test_clone_contents_across_paragraphs() {
+        let document = two_paragraphs();
+        let mut range = document.create_range();
+        range.set_end(&document, NodeId(vec![0, 1, 0]), 6).unwrap();
+        range.set_start(&document, NodeId(vec![0, 0, 0]), 6).unwrap();
+        assert_eq!(range.common_ancestor_container(), NodeId(vec![0]));
+
+        let fragment = range.clone_contents(&document).unwrap();
+        assert_eq!(fragment.children.len(), 2);
+        for (child, text) in fragment.children.iter().zip(["world", "Second"]) {
+            let Node::Element(paragraph) = child else {
+                panic!("fragment child is not an element");
+            };
+            assert_eq!(paragraph.tag_name, "p");
+            assert_eq!(paragraph.children, vec![Node::Text(TextNode::new(text.to_string()))]);
+        }
+        assert_eq!(fragment.text_content(), "worldSecond");
+
+        // Cloning leaves the document alone
+        assert_eq!(paragraph_text(&document, 0), "Hello world");
+        assert!(range.set_start(&document, NodeId(vec![0, 0, 0]), 12).is_err());
+    }
+
+    #[test]
+    fn test_delete_contents_and_insert_node() {
+        let mut document = two_paragraphs();
+        let mut range = document.create_range();
+        range.set_end(&document, NodeId(vec![0, 1, 0]), 6).unwrap();
+        range.set_start(&document, NodeId(vec![0, 0, 0]), 6).unwrap();
+        range.delete_contents(&mut document).unwrap();
+        assert_eq!(paragraph_text(&document, 0), "Hello ");
+        assert_eq!(paragraph_text(&document, 1), " paragraph");
+        assert!(range.collapsed());
+        assert_eq!((range.start_container.clone(), range.start_offset), (NodeId(vec![0]), 1));
+
+        let mut range = document.create_range();
+        range.set_start(&document, NodeId(vec![0, 0, 0]), 5).unwrap();
+        range.collapse(true);
+        let mut strong = Element::new("strong".to_string());
+        strong.append_child(Node::Text(TextNode::new("!".to_string())));
+        range.insert_node(&mut document, Node::Element(strong)).unwrap();
+        assert_eq!(paragraph_text(&document, 0), "Hello! ");
+        assert_eq!((range.end_container.clone(), range.end_offset), (NodeId(vec![0, 0]), 2));
+    }
+
+    #[test]
+    fn test_bounding_client_rect() {
+        let document = two_paragraphs();
+        let Node::Element(body) = &document.root.children[0] else {
+            unreachable!();
+        };
+        let mut body_box = LayoutBox::new(body.clone());
+        for (index, child) in body.children.iter().enumerate() {
+            let Node::Element(paragraph) = child else {
+                unreachable!();
+            };
+            let mut paragraph_box = LayoutBox::new(paragraph.clone());
+            paragraph_box.position_coords.y = index as f32 * 20.0;
+            paragraph_box.dimensions.content_width = 200.0 - index as f32 * 100.0;
+            paragraph_box.dimensions.content_height = 20.0;
+            body_box.add_child(paragraph_box);
+        }
+        body_box.dimensions.content_width = 800.0;
+        body_box.dimensions.content_height = 40.0;
+
+        let mut range = document.create_range();
+        range.set_end(&document, NodeId(vec![0, 1, 0]), 6).unwrap();
+        range.set_start(&document, NodeId(vec![0, 0, 0]), 6).unwrap();
+        assert_eq!(range.get_bounding_client_rect(&document, &body_box), DomRect::new(0.0, 0.0, 200.0, 40.0));
+
+        let caret = document.create_range_from_point(&body_box, 50.0, 25.0).unwrap();
+        assert_eq!((caret.start_container, caret.start_offset), (NodeId(vec![0, 1, 0]), 8));
+    }
+}
@@ -1,5 +1,6 @@
-use crate::css_selector::{ComplexSelector, SimpleSelector, SelectorList, AttributeOperator};
-use crate::dom::Element;
+use crate::css_selector::{ComplexSelector, ComplexSelectorPart, PseudoClass, SimpleSelector, SelectorList, AttributeOperator};
+use crate::dom::{Document, Element};
+use crate::pseudo_classes::PseudoClassEvaluator;
 use std::collections::HashMap;
 
 /// Detailed breakdown of selector specificity
@@ -48,6 +49,8 @@ pub struct SelectorIndexEntry {
     pub important: bool,
     /// Source stylesheet or rule information
     pub source: String,
+    /// Whether the selector contains `:has()` and needs the document to match
+    pub has_relational: bool,
 }
 
 /// Index for efficient selector matching
@@ -67,6 +70,8 @@ pub struct SelectorIndex {
     pseudo_element_index: HashMap<String, Vec<SelectorIndexEntry>>,
     /// Universal selectors (apply to all elements)
     universal_selectors: Vec<SelectorIndexEntry>,
+    /// Selectors containing `:has()`, excluded from the fast-path indices
+    relational_selectors: Vec<SelectorIndexEntry>,
     /// All selectors for fallback matching
     all_selectors: Vec<SelectorIndexEntry>,
 }
@@ -82,6 +87,7 @@ impl SelectorIndex {
             pseudo_class_index: HashMap::new(),
             pseudo_element_index: HashMap::new(),
             universal_selectors: Vec::new(),
+            relational_selectors: Vec::new(),
             all_selectors: Vec::new(),
         }
     }
@@ -90,17 +96,25 @@ impl SelectorIndex {
     pub fn add_selector(&mut self, selector: ComplexSelector, source: String) {
         let specificity = self.calculate_specificity(&selector);
         let important = self.is_important(&selector);
+        let has_relational = self.has_relational_pseudo_class(&selector);
         
         let entry = SelectorIndexEntry {
             selector: selector.clone(),
             specificity,
             important,
             source,
+            has_relational,
         };
 
         // Add to all selectors
         self.all_selectors.push(entry.clone());
 
+        // :has() needs the element's subtree, so it can't use the fast path
+        if has_relational {
+            self.relational_selectors.push(entry);
+            return;
+        }
+
         // Index by the rightmost simple selector (for descendant selectors)
         // Clone the rightmost selector to avoid borrow checker issues
         if let Some(rightmost) = self.get_rightmost_simple_selector(&selector) {
@@ -183,6 +197,31 @@ impl SelectorIndex {
         matching_selectors
     }
 
+    /// Find matching selectors for an element, including `:has()` selectors
+    ///
+    /// Indexed selectors use the fast path; relational selectors are matched
+    /// by traversing the document.
+    pub fn find_matching_selectors_in_document(&self, element: &Element, document: &Document) -> Vec<&SelectorIndexEntry> {
+        let mut matching_selectors = self.find_matching_selectors(element);
+        let evaluator = PseudoClassEvaluator::new();
+
+        for entry in &self.relational_selectors {
+            let Some(rightmost) = self.get_rightmost_simple_selector(&entry.selector) else {
+                continue;
+            };
+            let has_matches = rightmost.pseudo_classes.iter().all(|pseudo_class| match pseudo_class {
+                PseudoClass::Has(arg) => evaluator.evaluate_has(element, arg, document),
+                _ => true,
+            });
+            if has_matches && self.matches_simple_selector(element, rightmost) {
+                matching_selectors.push(entry);
+            }
+        }
+
+        matching_selectors.sort_by(|a, b| b.specificity.cmp(&a.specificity));
+        matching_selectors
+    }
+
     /// Get all selectors (for fallback matching)
     pub fn get_all_selectors(&self) -> &[SelectorIndexEntry] {
         &self.all_selectors
@@ -197,6 +236,7 @@ impl SelectorIndex {
         self.pseudo_class_index.clear();
         self.pseudo_element_index.clear();
         self.universal_selectors.clear();
+        self.relational_selectors.clear();
         self.all_selectors.clear();
     }
 
@@ -211,6 +251,7 @@ impl SelectorIndex {
             pseudo_class_selectors: self.pseudo_class_index.values().map(|v| v.len()).sum(),
             pseudo_element_selectors: self.pseudo_element_index.values().map(|v| v.len()).sum(),
             universal_selectors: self.universal_selectors.len(),
+            relational_selectors: self.relational_selectors.len(),
         }
     }
 
@@ -278,6 +319,15 @@ impl SelectorIndex {
         }
     }
 
+    /// Check if any compound selector contains `:has()`
+    fn has_relational_pseudo_class(&self, selector: &ComplexSelector) -> bool {
+        selector.parts.iter().any(|part| match part {
+            ComplexSelectorPart::Compound(compound) => compound.simple.pseudo_classes.iter()
+                .any(|pseudo_class| matches!(pseudo_class, PseudoClass::Has(_))),
+            ComplexSelectorPart::Combinator(_) => false,
+        })
+    }
+
     /// Get the rightmost simple selector from a complex selector
     fn get_rightmost_simple_selector<'a>(&self, selector: &'a ComplexSelector) -> Option<&'a SimpleSelector> {
         // Find the last compound selector in the complex selector
@@ -475,6 +525,7 @@ pub struct SelectorIndexStats {
     pub pseudo_class_selectors: usize,
     pub pseudo_element_selectors: usize,
     pub universal_selectors: usize,
+    pub relational_selectors: usize,
 }
 
 /// Cache entry for selector matching results
@@ -913,6 +964,35 @@ mod tests {
         assert_eq!(stats.class_selectors, 1);
     }
     
+    #[test]
+    fn test_has_selectors_bypass_fast_path() {
+        let mut index = SelectorIndex::new();
+        let mut parser = crate::css_selector::CssSelectorParser::new("div:has(> .active)").unwrap();
+        index.add_selector_list(&parser.parse_selector_list().unwrap(), "test.css".to_string());
+
+        let stats = index.get_stats();
+        assert_eq!(stats.total_selectors, 1);
+        assert_eq!(stats.relational_selectors, 1);
+        assert_eq!(stats.tag_selectors, 0);
+        assert!(index.get_all_selectors()[0].has_relational);
+
+        let mut active = Element::new("span".to_string());
+        active.set_attribute("class".to_string(), "active".to_string());
+        let mut container = Element::new("div".to_string());
+        container.id = "container".to_string();
+        container.children.push(crate::dom::Node::Element(active));
+        let mut empty = Element::new("div".to_string());
+        empty.id = "empty".to_string();
+
+        let mut document = Document::new();
+        document.root.children.push(crate::dom::Node::Element(container.clone()));
+        document.root.children.push(crate::dom::Node::Element(empty.clone()));
+
+        assert!(index.find_matching_selectors(&container).is_empty());
+        assert_eq!(index.find_matching_selectors_in_document(&container, &document).len(), 1);
+        assert!(index.find_matching_selectors_in_document(&empty, &document).is_empty());
+    }
+
     #[test]
     fn test_fast_path_optimizations() {
         let mut matcher = IndexedSelectorMatcher::new();
@@ -0,0 +1,199 @@
+//! HTML serialization of DOM trees.
+//!
+//! This module implements the HTML fragment serialization algorithm used by
+//! `innerHTML` and `outerHTML`.
+
+use crate::dom::{Document, Element, Node};
+
+/// Elements that have no end tag and no children
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img",
+    "input", "keygen", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements whose text children are serialized without escaping
+const RAW_TEXT_ELEMENTS: &[&str] = &[
+    "iframe", "noembed", "noframes", "noscript", "plaintext", "script", "style", "xmp",
+];
+
+/// HTML serializer for DOM nodes
+pub struct DomSerializer;
+
+impl DomSerializer {
+    /// Serialize an element, optionally including its own tags
+    ///
+    /// With `include_self` this produces `outerHTML`, otherwise `innerHTML`.
+    /// Attributes are written in name order, since elements don't keep the
+    /// order they were set in.
+    pub fn serialize_element(element: &Element, include_self: bool) -> String {
+        let mut html = String::new();
+        if include_self {
+            Self::write_element(element, &mut html);
+        } else {
+            Self::write_children(element, &mut html);
+        }
+        html
+    }
+
+    /// Serialize a node
+    pub fn serialize_node(node: &Node) -> String {
+        let mut html = String::new();
+        Self::write_node(node, None, &mut html);
+        html
+    }
+
+    /// Serialize a document, including its doctype
+    pub fn serialize_document(document: &Document) -> String {
+        format!("<!DOCTYPE html>\n{}", Self::serialize_element(&document.root, true))
+    }
+
+    /// Write an element with its start tag, children and end tag
+    fn write_element(element: &Element, html: &mut String) {
+        html.push('<');
+        html.push_str(&element.tag_name);
+
+        let mut attributes: Vec<_> = element.attributes.iter().collect();
+        attributes.sort_by_key(|(name, _)| name.as_str());
+        for (name, value) in attributes {
+            html.push(' ');
+            html.push_str(name);
+            html.push_str("=\"");
+            html.push_str(&escape_attribute(value));
+            html.push('"');
+        }
+        html.push('>');
+
+        if Self::is_void_element(&element.tag_name) {
+            return;
+        }
+
+        Self::write_children(element, html);
+        html.push_str("</");
+        html.push_str(&element.tag_name);
+        html.push('>');
+    }
+
+    /// Write the children of an element
+    fn write_children(element: &Element, html: &mut String) {
+        for child in &element.children {
+            Self::write_node(child, Some(element), html);
+        }
+    }
+
+    /// Write a node whose parent is `parent`
+    fn write_node(node: &Node, parent: Option<&Element>, html: &mut String) {
+        match node {
+            Node::Element(element) => Self::write_element(element, html),
+            Node::Text(text) => {
+                if parent.is_some_and(|parent| RAW_TEXT_ELEMENTS.contains(&parent.tag_name.as_str())) {
+                    html.push_str(&text.content);
+                } else {
+                    html.push_str(&escape_text(&text.content));
+                }
+            }
+            Node::Comment(comment) => {
+                html.push_str("<!--");
+                html.push_str(&comment.content);
+                html.push_str("-->");
+            }
+            Node::DocumentType(doctype) => {
+                html.push_str("<!DOCTYPE ");
+                html.push_str(&doctype.name);
+                html.push('>');
+            }
+        }
+    }
+
+    /// Check if an element is a void element
+    pub fn is_void_element(tag_name: &str) -> bool {
+        VOID_ELEMENTS.contains(&tag_name)
+    }
+}
+
+/// Escape text content
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\u{a0}' => escaped.push_str("&nbsp;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape an attribute value for use in double quotes
+fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\u{a0}' => escaped.push_str("&nbsp;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::TextNode;
+
+    #[test]
+    fn test_serialize_escapes_and_void_elements() {
+        let mut image = Element::new("img".to_string());
+        image.set_attribute("alt".to_string(), "Tom & \"Jerry\"".to_string());
+        image.set_attribute("src".to_string(), "a.png".to_string());
+
+        let mut paragraph = Element::new("p".to_string());
+        paragraph.append_child(Node::Text(TextNode::new("1 < 2 & 3 > 2".to_string())));
+        paragraph.append_child(Node::Element(Element::new("br".to_string())));
+        paragraph.append_child(Node::Element(image));
+
+        let mut script = Element::new("script".to_string());
+        script.append_child(Node::Text(TextNode::new("if (a < b) {}".to_string())));
+        paragraph.append_child(Node::Element(script));
+
+        assert_eq!(
+            DomSerializer::serialize_element(&paragraph, true),
+            "<p>1 &lt; 2 &amp; 3 &gt; 2<br><img alt=\"Tom &amp; &quot;Jerry&quot;\" src=\"a.png\"><script>if (a < b) {}</script></p>"
+        );
+        assert_eq!(
+            DomSerializer::serialize_element(&paragraph, false),
+            "1 &lt; 2 &amp; 3 &gt; 2<br><img alt=\"Tom &amp; &quot;Jerry&quot;\" src=\"a.png\"><script>if (a < b) {}</script>"
+        );
+    }
+
+    #[test]
+    fn test_inner_html_round_trip() {
+        let fragment = "<ul class=\"list\"><li title=\"a &amp; b\">Fish &amp; chips</li><li>1 &lt; 2<br><em>done</em></li></ul>";
+
+        let mut container = Element::new("div".to_string());
+        container.set_inner_html(fragment).unwrap();
+        assert_eq!(container.inner_html(), fragment);
+        assert_eq!(container.outer_html(), format!("<div>{}</div>", fragment));
+        assert_eq!(container.text_content(), "Fish & chips1 < 2done");
+
+        let mut copy = Element::new("div".to_string());
+        copy.set_inner_html(&container.inner_html()).unwrap();
+        assert_eq!(copy.inner_html(), fragment);
+    }
+
+    #[test]
+    fn test_set_outer_html() {
+        let mut document = Document::new();
+        document.root.set_inner_html("<body><p id=\"old\">Old</p><p>Kept</p></body>").unwrap();
+
+        document.set_outer_html("old", "<h1>New</h1><hr>").unwrap();
+        // Parsing into <html> implies the <head>
+        assert_eq!(document.root.inner_html(), "<head></head><body><h1>New</h1><hr><p>Kept</p></body>");
+        assert!(document.set_outer_html("missing", "<p></p>").is_err());
+    }
+}
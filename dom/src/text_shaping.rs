@@ -1,4 +1,8 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ops::Range;
+use unicode_bidi::{BidiDataSource, BidiInfo};
+use crate::error::Error;
 use crate::typography::{FontFace, FontFamily, FontWeight, FontStyle, FontStretch};
 
 /// Unicode character properties
@@ -43,6 +47,10 @@ pub enum BidiClass {
     SegmentSeparator,
     Whitespace,
     OtherNeutral,
+    NonspacingMark,
+    EuropeanTerminator,
+    /// Boundary neutrals, including explicit embedding controls
+    BoundaryNeutral,
 }
 
 /// Shaped glyph information
@@ -58,6 +66,8 @@ pub struct ShapedGlyph {
     pub y_offset: f32,
     /// Advance width
     pub advance_width: f32,
+    /// Vertical advance
+    pub y_advance: f32,
     /// Whether this glyph is a ligature
     pub is_ligature: bool,
     /// Whether this glyph has kerning
@@ -154,6 +164,7 @@ impl TextShaper {
                 x_offset: 0.0,
                 y_offset: 0.0,
                 advance_width: self.get_advance_width(font_face, code_point),
+                y_advance: 0.0,
                 is_ligature: false,
                 has_kerning: false,
                 cluster_start,
@@ -187,14 +198,9 @@ impl TextShaper {
             _ => CharCategory::Other,
         };
         
-        let bidi_class = match code_point {
-            0x0030..=0x0039 => BidiClass::EuropeanNumber, // Digits
-            0x0020 => BidiClass::Whitespace,         // Space
-            0x000A => BidiClass::ParagraphSeparator, // Line feed
-            0x000D => BidiClass::ParagraphSeparator, // Carriage return
-            0x0020..=0x007F => BidiClass::LeftToRight, // Basic Latin
-            _ => BidiClass::OtherNeutral,
-        };
+        let bidi_class = char::from_u32(code_point)
+            .map(|c| Self::convert_bidi_class(unicode_bidi::bidi_class(c)))
+            .unwrap_or(BidiClass::OtherNeutral);
         
         let is_combining = code_point >= 0x0300 && code_point <= 0x036F; // Combining diacritical marks
         let is_whitespace = code_point == 0x0020 || code_point == 0x000A || code_point == 0x000D;
@@ -210,6 +216,27 @@ impl TextShaper {
         }
     }
     
+    /// Convert a Unicode bidi class
+    fn convert_bidi_class(class: unicode_bidi::BidiClass) -> BidiClass {
+        use unicode_bidi::BidiClass::*;
+        match class {
+            L => BidiClass::LeftToRight,
+            R => BidiClass::RightToLeft,
+            AL => BidiClass::ArabicLetter,
+            EN => BidiClass::EuropeanNumber,
+            AN => BidiClass::ArabicNumber,
+            ES => BidiClass::EuropeanSeparator,
+            ET => BidiClass::EuropeanTerminator,
+            CS => BidiClass::CommonSeparator,
+            NSM => BidiClass::NonspacingMark,
+            B => BidiClass::ParagraphSeparator,
+            S => BidiClass::SegmentSeparator,
+            WS => BidiClass::Whitespace,
+            ON => BidiClass::OtherNeutral,
+            BN | LRE | LRO | RLE | RLO | PDF | LRI | RLI | FSI | PDI => BidiClass::BoundaryNeutral,
+        }
+    }
+    
     /// Get glyph ID for a code point
     fn get_glyph_id(&self, _font_face: &FontFace, code_point: u32) -> u16 {
         // This is a simplified implementation
@@ -337,6 +364,113 @@ impl TextShaper {
         }]
     }
     
+    /// Shape a run of text with HarfBuzz
+    ///
+    /// Bidi levels are resolved before shaping and each directional run is
+    /// shaped on its own. Glyphs are returned in visual order, in units of
+    /// the face's em size.
+    pub fn shape_run(
+        &self,
+        text: &str,
+        font: &FontFace,
+        direction: TextDirection,
+    ) -> crate::error::Result<ShapedTextRun> {
+        let data: Cow<'_, [u8]> = match (&font.data, &font.file_path) {
+            (Some(data), _) => Cow::Borrowed(data),
+            (None, Some(path)) => Cow::Owned(std::fs::read(path)
+                .map_err(|e| Error::IoError(format!("{}: {}", path.display(), e)))?),
+            (None, None) => {
+                return Err(Error::NotFound(format!("No font data for {}", font.family.0)));
+            }
+        };
+        let face = rustybuzz::Face::from_slice(&data, font.face_index)
+            .ok_or_else(|| Error::ParseError(format!("Invalid font data for {}", font.family.0)))?;
+        let scale = font.em_size() / face.units_per_em() as f32;
+        
+        let default_level = match direction {
+            TextDirection::LeftToRight => Some(unicode_bidi::LTR_LEVEL),
+            TextDirection::RightToLeft => Some(unicode_bidi::RTL_LEVEL),
+            TextDirection::Auto => None,
+        };
+        let bidi = BidiInfo::new_with_data_source(self, text, default_level);
+        
+        let mut glyphs = Vec::new();
+        for paragraph in &bidi.paragraphs {
+            let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+            for run in runs {
+                let rtl = levels[run.start].is_rtl();
+                Self::shape_level_run(&face, text, run, rtl, scale, &mut glyphs);
+            }
+        }
+        
+        let rtl = match bidi.paragraphs.first() {
+            Some(paragraph) => paragraph.level.is_rtl(),
+            None => direction == TextDirection::RightToLeft,
+        };
+        Ok(ShapedTextRun {
+            font_face: font.clone(),
+            width: glyphs.iter().map(|g| g.advance_width).sum(),
+            glyphs,
+            direction: if rtl { TextDirection::RightToLeft } else { TextDirection::LeftToRight },
+            start_index: 0,
+            end_index: text.len(),
+            height: font.line_height(),
+        })
+    }
+    
+    /// Shape a single-direction run, appending its glyphs
+    fn shape_level_run(
+        face: &rustybuzz::Face<'_>,
+        text: &str,
+        run: Range<usize>,
+        rtl: bool,
+        scale: f32,
+        glyphs: &mut Vec<ShapedGlyph>,
+    ) {
+        let run_text = &text[run.clone()];
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(run_text);
+        buffer.set_direction(if rtl {
+            rustybuzz::Direction::RightToLeft
+        } else {
+            rustybuzz::Direction::LeftToRight
+        });
+        buffer.guess_segment_properties();
+        let output = rustybuzz::shape(face, &[], buffer);
+        
+        // A cluster ends where the next one in logical order starts
+        let mut boundaries: Vec<usize> = output.glyph_infos().iter().map(|info| info.cluster as usize).collect();
+        boundaries.sort_unstable();
+        let mut glyph_counts: HashMap<usize, usize> = HashMap::new();
+        for &cluster in &boundaries {
+            *glyph_counts.entry(cluster).or_insert(0) += 1;
+        }
+        boundaries.dedup();
+        
+        for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+            let cluster = info.cluster as usize;
+            let cluster_end = boundaries.iter()
+                .find(|&&boundary| boundary > cluster)
+                .copied()
+                .unwrap_or(run_text.len());
+            let cluster_text = &run_text[cluster..cluster_end];
+            let nominal_advance = face.glyph_hor_advance(rustybuzz::ttf_parser::GlyphId(info.glyph_id as u16));
+            
+            glyphs.push(ShapedGlyph {
+                code_point: cluster_text.chars().next().map_or(0, |c| c as u32),
+                glyph_id: info.glyph_id as u16,
+                x_offset: position.x_offset as f32 * scale,
+                y_offset: position.y_offset as f32 * scale,
+                advance_width: position.x_advance as f32 * scale,
+                y_advance: position.y_advance as f32 * scale,
+                is_ligature: glyph_counts[&cluster] < cluster_text.chars().count(),
+                has_kerning: nominal_advance.is_some_and(|advance| advance as i32 != position.x_advance),
+                cluster_start: run.start + cluster,
+                cluster_end: run.start + cluster_end,
+            });
+        }
+    }
+    
     /// Add kerning pair to cache
     pub fn add_kerning_pair(&mut self, glyph1: u16, glyph2: u16, kerning: f32) {
         self.kerning_cache.insert((glyph1, glyph2), kerning);
@@ -353,6 +487,28 @@ impl TextShaper {
     }
 }
 
+impl BidiDataSource for TextShaper {
+    fn bidi_class(&self, c: char) -> unicode_bidi::BidiClass {
+        use unicode_bidi::BidiClass::*;
+        match self.get_char_properties(c as u32).bidi_class {
+            BidiClass::LeftToRight => L,
+            BidiClass::RightToLeft => R,
+            BidiClass::ArabicLetter => AL,
+            BidiClass::EuropeanNumber => EN,
+            BidiClass::ArabicNumber => AN,
+            BidiClass::EuropeanSeparator => ES,
+            BidiClass::EuropeanTerminator => ET,
+            BidiClass::CommonSeparator => CS,
+            BidiClass::NonspacingMark => NSM,
+            BidiClass::ParagraphSeparator => B,
+            BidiClass::SegmentSeparator => S,
+            BidiClass::Whitespace => WS,
+            BidiClass::OtherNeutral => ON,
+            BidiClass::BoundaryNeutral => BN,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -384,9 +540,9 @@ mod tests {
         let ltr_text = "Hello World";
         assert_eq!(shaper.determine_text_direction(ltr_text), TextDirection::LeftToRight);
         
-        // Test RTL text (simplified)
+        // Test RTL text
         let rtl_text = "مرحبا بالعالم";
-        assert_eq!(shaper.determine_text_direction(rtl_text), TextDirection::LeftToRight); // Simplified implementation
+        assert_eq!(shaper.determine_text_direction(rtl_text), TextDirection::RightToLeft);
     }
 
     #[test]
@@ -439,6 +595,44 @@ mod tests {
         assert_eq!(runs[0].end_index, text.len());
     }
 
+    #[test]
+    fn test_shape_run_bidi() {
+        let mut manager = crate::typography::FontManager::new();
+        manager.load_system_fonts();
+        let family = FontFamily("DejaVu Sans".to_string());
+        let font_face = match manager.match_font(&family, FontWeight(400), FontStyle::Normal, FontStretch::Normal) {
+            Some(face) if face.family == family => face.clone(),
+            // The test font is not installed
+            _ => return,
+        };
+        let shaper = TextShaper::new();
+        
+        let run = shaper.shape_run("abc", &font_face, TextDirection::Auto).unwrap();
+        assert_eq!(run.direction, TextDirection::LeftToRight);
+        assert_eq!(run.glyphs.len(), 3);
+        assert_eq!(run.glyphs[0].code_point, 'a' as u32);
+        assert!(run.glyphs.iter().all(|g| g.glyph_id != 0 && g.advance_width > 0.0));
+        
+        // The Hebrew run is reversed into visual order after the Latin run
+        let text = "ab שלום";
+        let run = shaper.shape_run(text, &font_face, TextDirection::Auto).unwrap();
+        assert_eq!(run.direction, TextDirection::LeftToRight);
+        let clusters: Vec<usize> = run.glyphs.iter().map(|g| g.cluster_start).collect();
+        assert_eq!(&clusters[..3], &[0, 1, 2]);
+        assert!(clusters[3..].windows(2).all(|pair| pair[0] > pair[1]));
+        assert_eq!(run.glyphs.last().unwrap().code_point, 'ש' as u32);
+        assert_eq!(run.end_index, text.len());
+        
+        let run = shaper.shape_run("שלום", &font_face, TextDirection::Auto).unwrap();
+        assert_eq!(run.direction, TextDirection::RightToLeft);
+        assert!(shaper.shape_run("abc", &FontFace::new(
+            FontFamily("Missing".to_string()),
+            FontWeight(400),
+            FontStyle::Normal,
+            FontStretch::Normal,
+        ), TextDirection::Auto).is_err());
+    }
+
     #[test]
     fn test_kerning_and_ligatures() {
         let mut shaper = TextShaper::new();
@@ -1,6 +1,11 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use crate::css_at_rules::{FontFaceDeclaration, FontFaceSource};
+use crate::error::Error;
 
 /// Font family name
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -91,6 +96,8 @@ pub struct FontFace {
     pub stretch: FontStretch,
     /// Font file path
     pub file_path: Option<PathBuf>,
+    /// Index of the face within a font collection file
+    pub face_index: u32,
     /// Font data (if loaded in memory)
     pub data: Option<Vec<u8>>,
     /// Font metrics
@@ -110,6 +117,7 @@ impl FontFace {
             style,
             stretch,
             file_path: None,
+            face_index: 0,
             data: None,
             metrics: FontMetrics::default(),
             is_loaded: false,
@@ -135,6 +143,11 @@ impl FontFace {
         }
     }
     
+    /// Get the key of this face in the font cache
+    pub fn cache_key(&self) -> FontCacheKey {
+        (self.family.clone(), self.weight, self.style, self.stretch)
+    }
+    
     /// Get the line height for this font
     pub fn line_height(&self) -> f32 {
         self.metrics.ascent - self.metrics.descent + self.metrics.line_gap
@@ -177,6 +190,88 @@ impl FontFallback {
     }
 }
 
+/// Font cache key: family, weight, style, and stretch
+pub type FontCacheKey = (FontFamily, FontWeight, FontStyle, FontStretch);
+
+/// Font faces by family, weight, style, and stretch
+pub type FontCache = HashMap<FontCacheKey, FontFace>;
+
+/// Glyph cache key: font face, character, and size in 1/64 pixels
+pub type GlyphCacheKey = (FontCacheKey, char, u32);
+
+/// Glyph bitmap rendered by FreeType
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterizedGlyph {
+    /// Bitmap width in pixels
+    pub width: u32,
+    /// Bitmap height in pixels
+    pub height: u32,
+    /// Horizontal distance from the pen position to the left of the bitmap
+    pub bearing_x: i32,
+    /// Vertical distance from the baseline to the top of the bitmap
+    pub bearing_y: i32,
+    /// Horizontal advance to the next pen position in pixels
+    pub advance: f32,
+    /// 8-bit coverage values, one byte per pixel, row by row
+    pub bitmap: Vec<u8>,
+}
+
+/// Cache of rasterized glyphs
+///
+/// Clones share the same glyphs, so the cache can be handed to the GPU
+/// texture atlas that uploads them.
+#[derive(Debug, Clone, Default)]
+pub struct GlyphCache {
+    glyphs: Arc<RwLock<HashMap<GlyphCacheKey, RasterizedGlyph>>>,
+}
+
+impl GlyphCache {
+    /// Create an empty glyph cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Get a cached glyph
+    pub fn get(&self, key: &GlyphCacheKey) -> Option<RasterizedGlyph> {
+        self.glyphs.read().unwrap().get(key).cloned()
+    }
+    
+    /// Add a glyph to the cache
+    pub fn insert(&self, key: GlyphCacheKey, glyph: RasterizedGlyph) {
+        self.glyphs.write().unwrap().insert(key, glyph);
+    }
+    
+    /// Get the number of cached glyphs
+    pub fn len(&self) -> usize {
+        self.glyphs.read().unwrap().len()
+    }
+    
+    /// Check if the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// FreeType state of a thread
+///
+/// FreeType objects can't be shared between threads, so each thread opens
+/// its own library and faces.
+struct FreeTypeContext {
+    library: freetype::Library,
+    faces: HashMap<FontCacheKey, freetype::Face>,
+}
+
+thread_local! {
+    static FREETYPE: RefCell<Option<FreeTypeContext>> = const { RefCell::new(None) };
+}
+
+/// Fetches the files of web fonts, such as from the network process
+#[async_trait]
+pub trait FontLoader: Send + Sync {
+    /// Fetch a font file
+    async fn fetch_font(&self, url: &str) -> crate::error::Result<Vec<u8>>;
+}
+
 /// Font cache entry
 #[derive(Debug, Clone)]
 pub struct FontCacheEntry {
@@ -189,16 +284,35 @@ pub struct FontCacheEntry {
 }
 
 /// Font manager for handling font loading, caching, and fallbacks
-#[derive(Debug)]
 pub struct FontManager {
     /// Font faces by family, weight, style, and stretch
-    fonts: HashMap<(FontFamily, FontWeight, FontStyle, FontStretch), FontCacheEntry>,
+    fonts: HashMap<FontCacheKey, FontCacheEntry>,
+    /// Installed font faces, which are loaded on first use
+    font_cache: FontCache,
+    /// Rasterized glyphs
+    glyph_cache: GlyphCache,
     /// Font fallback chains
     fallbacks: HashMap<FontFamily, FontFallback>,
     /// System font directories
     system_font_dirs: Vec<PathBuf>,
     /// Maximum cache size
     max_cache_size: usize,
+    /// Fetches web font files
+    font_loader: Option<Arc<dyn FontLoader>>,
+}
+
+impl std::fmt::Debug for FontManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FontManager")
+            .field("fonts", &self.fonts)
+            .field("font_cache", &self.font_cache)
+            .field("glyph_cache", &self.glyph_cache)
+            .field("fallbacks", &self.fallbacks)
+            .field("system_font_dirs", &self.system_font_dirs)
+            .field("max_cache_size", &self.max_cache_size)
+            .field("font_loader", &self.font_loader.is_some())
+            .finish()
+    }
 }
 
 impl FontManager {
@@ -206,9 +320,12 @@ impl FontManager {
     pub fn new() -> Self {
         let mut manager = Self {
             fonts: HashMap::new(),
+            font_cache: HashMap::new(),
+            glyph_cache: GlyphCache::new(),
             fallbacks: HashMap::new(),
             system_font_dirs: Vec::new(),
             max_cache_size: 1000,
+            font_loader: None,
         };
         
         // Add default system font directories
@@ -276,24 +393,280 @@ impl FontManager {
     ) -> Option<&FontFace> {
         let key = (family.clone(), weight, style, stretch);
         
-        // Check if font is already cached
-        if let Some(entry) = self.fonts.get_mut(&key) {
-            entry.last_access = std::time::Instant::now();
-            entry.access_count += 1;
-            return Some(&entry.face);
+        if !self.fonts.contains_key(&key) {
+            let face = match self.match_font(family, weight, style, stretch).cloned() {
+                Some(mut face) => {
+                    if face.data.is_none() {
+                        let path = face.file_path.clone()?;
+                        face.load_from_file(path).await.ok()?;
+                    }
+                    face
+                }
+                None => self.load_font_face(&key).await?,
+            };
+            self.fonts.insert(key.clone(), FontCacheEntry {
+                face,
+                last_access: std::time::Instant::now(),
+                access_count: 0,
+            });
+            self.cleanup_cache();
         }
         
-        // TODO: Implement font loading without borrow checker issues
-        // For now, return None
-        None
+        let entry = self.fonts.get_mut(&key)?;
+        entry.last_access = std::time::Instant::now();
+        entry.access_count += 1;
+        Some(&entry.face)
     }
     
-
+    /// Enumerate the installed fonts
+    ///
+    /// Uses fontconfig where available, plus the system font directories.
+    /// Returns the number of font faces found.
+    pub fn load_system_fonts(&mut self) -> usize {
+        let mut database = fontdb::Database::new();
+        database.load_system_fonts();
+        for dir in &self.system_font_dirs {
+            database.load_fonts_dir(dir);
+        }
+        
+        for info in database.faces() {
+            let file_path = match &info.source {
+                fontdb::Source::File(path) | fontdb::Source::SharedFile(path, _) => path.clone(),
+                fontdb::Source::Binary(_) => continue,
+            };
+            let Some((family, _)) = info.families.first() else {
+                continue;
+            };
+            
+            let mut face = FontFace::new(
+                FontFamily(family.clone()),
+                FontWeight(info.weight.0),
+                Self::convert_style(info.style),
+                Self::convert_stretch(info.stretch),
+            );
+            face.file_path = Some(file_path);
+            face.face_index = info.index;
+            // Keep the first face found for each key
+            self.font_cache.entry(face.cache_key()).or_insert(face);
+        }
+        
+        self.font_cache.len()
+    }
+    
+    /// Register a font face, such as a web font loaded with `@font-face`
+    pub fn register_font_face(&mut self, face: FontFace) {
+        let key = face.cache_key();
+        self.fonts.remove(&key);
+        self.font_cache.insert(key, face);
+    }
+    
+    /// Set the loader that fetches web font files
+    pub fn set_font_loader(&mut self, loader: Arc<dyn FontLoader>) {
+        self.font_loader = Some(loader);
+    }
+    
+    /// Load a web font declared with `@font-face` and register it
+    ///
+    /// Sources are tried in order. `local()` sources use an installed face,
+    /// and `url()` sources are fetched with the font loader, skipping formats
+    /// that aren't supported and files that aren't fonts.
+    pub async fn load_web_font(&mut self, declaration: &FontFaceDeclaration) -> crate::error::Result<FontFace> {
+        let mut last_error = None;
+        
+        for source in &declaration.sources {
+            let mut face = match source {
+                FontFaceSource::Local(name) => {
+                    let installed = self.font_cache.values()
+                        .find(|face| face.family.0.eq_ignore_ascii_case(name))
+                        .cloned();
+                    match installed {
+                        Some(face) => face,
+                        None => continue,
+                    }
+                }
+                FontFaceSource::Url { url, format } => {
+                    if format.as_deref().is_some_and(|format| !SUPPORTED_FONT_FORMATS.contains(&format)) {
+                        continue;
+                    }
+                    let Some(loader) = self.font_loader.clone() else {
+                        last_error = Some(Error::InvalidState(format!("No font loader to fetch {}", url)));
+                        continue;
+                    };
+                    
+                    let data = match loader.fetch_font(url).await {
+                        Ok(data) if sniff_font_format(&data).is_some() => data,
+                        Ok(_) => {
+                            last_error = Some(Error::ParseError(format!("{} is not a font file", url)));
+                            continue;
+                        }
+                        Err(e) => {
+                            last_error = Some(e);
+                            continue;
+                        }
+                    };
+                    let mut face = FontFace::new(declaration.family.clone(), declaration.weight, declaration.style, declaration.stretch);
+                    face.data = Some(data);
+                    face
+                }
+            };
+            
+            face.family = declaration.family.clone();
+            face.weight = declaration.weight;
+            face.style = declaration.style;
+            face.stretch = declaration.stretch;
+            face.is_loaded = face.data.is_some() || face.file_path.is_some();
+            self.register_font_face(face.clone());
+            return Ok(face);
+        }
+        
+        Err(last_error.unwrap_or_else(|| {
+            Error::NotFound(format!("No usable source for font {}", declaration.family.0))
+        }))
+    }
+    
+    /// Get the families of the installed fonts, sorted by name
+    pub fn available_families(&self) -> Vec<&FontFamily> {
+        let mut families: Vec<_> = self.font_cache.keys().map(|(family, _, _, _)| family).collect();
+        families.sort_by(|a, b| a.0.cmp(&b.0));
+        families.dedup();
+        families
+    }
+    
+    /// Find the installed face that best matches a font description
+    ///
+    /// Families are compared case-insensitively and then tried through their
+    /// fallback chain. Within a family, style and stretch must match before
+    /// the closest weight is picked.
+    pub fn match_font(
+        &self,
+        family: &FontFamily,
+        weight: FontWeight,
+        style: FontStyle,
+        stretch: FontStretch,
+    ) -> Option<&FontFace> {
+        if let Some(face) = self.font_cache.get(&(family.clone(), weight, style, stretch)) {
+            return Some(face);
+        }
+        
+        let families = match self.fallbacks.get(family) {
+            Some(fallback) => fallback.all_families(),
+            None => vec![family],
+        };
+        families.into_iter().find_map(|family| {
+            self.font_cache.values()
+                .filter(|face| face.family.0.eq_ignore_ascii_case(&family.0))
+                .min_by_key(|face| (
+                    face.style != style,
+                    face.stretch != stretch,
+                    (i32::from(face.weight.0) - i32::from(weight.0)).abs(),
+                ))
+        })
+    }
+    
+    /// Get the glyph cache shared with the GPU texture atlas
+    pub fn glyph_cache(&self) -> GlyphCache {
+        self.glyph_cache.clone()
+    }
+    
+    /// Rasterize a glyph with FreeType
+    ///
+    /// Glyphs are cached by face, character, and size. The face is read from
+    /// its data when loaded, or from its file otherwise.
+    pub fn rasterize_glyph(&self, face: &FontFace, codepoint: char, size_px: f32) -> crate::error::Result<RasterizedGlyph> {
+        if !size_px.is_finite() || size_px <= 0.0 {
+            return Err(Error::GraphicsError(format!("Invalid font size: {}", size_px)));
+        }
+        // FreeType sizes are in 26.6 fixed point
+        let size = (size_px * 64.0).round() as u32;
+        let key = (face.cache_key(), codepoint, size);
+        if let Some(glyph) = self.glyph_cache.get(&key) {
+            return Ok(glyph);
+        }
+        
+        let glyph = FREETYPE.with(|context| {
+            let mut context = context.borrow_mut();
+            if context.is_none() {
+                let library = freetype::Library::init().map_err(Self::freetype_error)?;
+                *context = Some(FreeTypeContext { library, faces: HashMap::new() });
+            }
+            let context = context.as_mut().unwrap();
+            
+            if !context.faces.contains_key(&key.0) {
+                let ft_face = match (&face.data, &face.file_path) {
+                    (Some(data), _) => context.library.new_memory_face(data.clone(), face.face_index as isize),
+                    (None, Some(path)) => context.library.new_face(path, face.face_index as isize),
+                    (None, None) => {
+                        return Err(Error::NotFound(format!("No font data for {}", face.family.0)));
+                    }
+                }.map_err(Self::freetype_error)?;
+                context.faces.insert(key.0.clone(), ft_face);
+            }
+            let ft_face = &context.faces[&key.0];
+            
+            ft_face.set_char_size(0, size as isize, 0, 0).map_err(Self::freetype_error)?;
+            ft_face.load_char(codepoint as usize, freetype::face::LoadFlag::RENDER)
+                .map_err(Self::freetype_error)?;
+            
+            let slot = ft_face.glyph();
+            let bitmap = slot.bitmap();
+            let width = bitmap.width().max(0) as usize;
+            let height = bitmap.rows().max(0) as usize;
+            // Rows may be padded, or stored bottom-up when the pitch is negative
+            let pitch = bitmap.pitch().unsigned_abs() as usize;
+            let buffer = bitmap.buffer();
+            let mut pixels = Vec::with_capacity(width * height);
+            for row in 0..height {
+                let row = if bitmap.pitch() < 0 { height - 1 - row } else { row };
+                pixels.extend_from_slice(&buffer[row * pitch..row * pitch + width]);
+            }
+            
+            Ok(RasterizedGlyph {
+                width: width as u32,
+                height: height as u32,
+                bearing_x: slot.bitmap_left(),
+                bearing_y: slot.bitmap_top(),
+                advance: slot.advance().x as f32 / 64.0,
+                bitmap: pixels,
+            })
+        })?;
+        
+        self.glyph_cache.insert(key, glyph.clone());
+        Ok(glyph)
+    }
+    
+    /// Convert a FreeType error
+    fn freetype_error(error: freetype::Error) -> Error {
+        Error::GraphicsError(format!("FreeType error: {}", error))
+    }
+    
+    /// Convert a fontdb style
+    fn convert_style(style: fontdb::Style) -> FontStyle {
+        match style {
+            fontdb::Style::Normal => FontStyle::Normal,
+            fontdb::Style::Italic => FontStyle::Italic,
+            fontdb::Style::Oblique => FontStyle::Oblique,
+        }
+    }
+    
+    /// Convert a fontdb stretch
+    fn convert_stretch(stretch: fontdb::Stretch) -> FontStretch {
+        match stretch {
+            fontdb::Stretch::UltraCondensed => FontStretch::UltraCondensed,
+            fontdb::Stretch::ExtraCondensed => FontStretch::ExtraCondensed,
+            fontdb::Stretch::Condensed => FontStretch::Condensed,
+            fontdb::Stretch::SemiCondensed => FontStretch::SemiCondensed,
+            fontdb::Stretch::Normal => FontStretch::Normal,
+            fontdb::Stretch::SemiExpanded => FontStretch::SemiExpanded,
+            fontdb::Stretch::Expanded => FontStretch::Expanded,
+            fontdb::Stretch::ExtraExpanded => FontStretch::ExtraExpanded,
+            fontdb::Stretch::UltraExpanded => FontStretch::UltraExpanded,
+        }
+    }
     
     /// Load a font face from the system
     async fn load_font_face(
         &self,
-        key: &(FontFamily, FontWeight, FontStyle, FontStretch),
+        key: &FontCacheKey,
     ) -> Option<FontFace> {
         let (family, weight, style, stretch) = key;
         
@@ -406,6 +779,21 @@ impl FontManager {
     }
 }
 
+/// `format()` hints of the web font files that can be loaded
+const SUPPORTED_FONT_FORMATS: &[&str] = &["woff2", "woff", "truetype", "opentype", "collection"];
+
+/// Detect the format of a font file from its signature
+fn sniff_font_format(data: &[u8]) -> Option<&'static str> {
+    match data.get(..4)? {
+        b"wOF2" => Some("woff2"),
+        b"wOFF" => Some("woff"),
+        [0, 1, 0, 0] | b"true" => Some("truetype"),
+        b"OTTO" => Some("opentype"),
+        b"ttcf" => Some("collection"),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -480,4 +868,71 @@ mod tests {
         let (current, max) = manager.get_cache_stats();
         assert!(current <= max);
     }
+
+    #[tokio::test]
+    async fn test_load_web_font() {
+        struct MockLoader;
+        
+        #[async_trait]
+        impl FontLoader for MockLoader {
+            async fn fetch_font(&self, url: &str) -> crate::error::Result<Vec<u8>> {
+                match url {
+                    "https://fonts.example/my.woff2" => Ok(b"wOF2\0\x01\0\0".to_vec()),
+                    "https://fonts.example/page.html" => Ok(b"<html></html>".to_vec()),
+                    _ => Err(Error::NetworkError(format!("{} not found", url))),
+                }
+            }
+        }
+        
+        let mut manager = FontManager::new();
+        manager.set_font_loader(Arc::new(MockLoader));
+        
+        let declaration = crate::css_at_rules::AtRuleParser::new().parse_at_rule(
+            "@font-face { font-family: \"MyFont\"; src: url(\"https://fonts.example/missing.woff2\"), \
+             url(\"https://fonts.example/my.svg\") format(\"svg\"), url(\"https://fonts.example/my.woff2\") format(\"woff2\"); }"
+        ).unwrap();
+        let crate::css_at_rules::AtRule::FontFace(declaration) = declaration else {
+            panic!("Expected font-face rule");
+        };
+        
+        let face = manager.load_web_font(&declaration).await.unwrap();
+        assert!(face.is_loaded);
+        assert_eq!(face.data.as_deref(), Some(&b"wOF2\0\x01\0\0"[..]));
+        
+        let family = FontFamily("MyFont".to_string());
+        assert_eq!(manager.available_families(), vec![&family]);
+        let registered = manager.match_font(&family, FontWeight(400), FontStyle::Normal, FontStretch::Normal).unwrap();
+        assert_eq!(registered.data, face.data);
+        
+        // Files that aren't fonts are rejected
+        let mut not_a_font = declaration.clone();
+        not_a_font.sources = vec![FontFaceSource::Url { url: "https://fonts.example/page.html".to_string(), format: None }];
+        assert!(manager.load_web_font(&not_a_font).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_system_font_rasterization() {
+        let mut manager = FontManager::new();
+        if manager.load_system_fonts() == 0 {
+            // No fonts are installed
+            return;
+        }
+        
+        let family = manager.available_families()[0].clone();
+        let face = manager.match_font(&family, FontWeight(400), FontStyle::Normal, FontStretch::Normal)
+            .unwrap()
+            .clone();
+        assert_eq!(face.family, family);
+        
+        let glyph = manager.rasterize_glyph(&face, 'A', 16.0).unwrap();
+        assert_eq!(glyph.bitmap.len(), (glyph.width * glyph.height) as usize);
+        assert!(glyph.advance > 0.0);
+        assert_eq!(manager.glyph_cache().len(), 1);
+        assert_eq!(manager.rasterize_glyph(&face, 'A', 16.0).unwrap(), glyph);
+        assert_eq!(manager.glyph_cache().len(), 1);
+        assert!(manager.rasterize_glyph(&face, 'A', 0.0).is_err());
+        
+        let loaded = manager.get_font_face(&face.family, face.weight, face.style, face.stretch).await.unwrap();
+        assert!(loaded.is_loaded);
+    }
 }
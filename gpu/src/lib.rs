@@ -1,14 +1,22 @@
 //! GPU process for the Matte browser
 //! 
 //! This module provides the GPU/Compositor process architecture for handling
-//! graphics rendering, compositing, display list management, and tiled rasterization.
+//! graphics rendering, compositing, display list management, tiled rasterization
+//! and WebGL contexts.
+
+pub mod webgl;
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
-use tracing::{debug, info};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
 use common::error::{Error, Result};
+use common::ipc::{Channel, SharedMemorySegment};
 use common::types::TabId;
+use webgl::{WebGlCommand, WebGlContextAttributes, WebGlContextEvent, WebGlContextManager, WebGlResult};
 
 /// GPU process configuration
 #[derive(Debug, Clone)]
@@ -33,6 +41,12 @@ pub struct GpuConfig {
     pub layer_compositing: bool,
     /// Enable display list optimization
     pub display_list_optimization: bool,
+    /// Frame rate cap for hidden tabs with running animations
+    pub background_frame_rate: u32,
+    /// Allow pages to create WebGL contexts
+    pub webgl_enabled: bool,
+    /// Physical pixels per logical pixel of the display being rendered to
+    pub display_dpi: f32,
 }
 
 impl Default for GpuConfig {
@@ -48,6 +62,29 @@ impl Default for GpuConfig {
             tile_size: 256,
             layer_compositing: true,
             display_list_optimization: true,
+            background_frame_rate: 1,
+            webgl_enabled: true,
+            display_dpi: 1.0,
+        }
+    }
+}
+
+/// Largest texture the compositor allocates, whatever the display scale
+pub const HARDWARE_MAX_TEXTURE_SIZE: u32 = 16384;
+
+impl GpuConfig {
+    /// Maximum texture size on the current display, `max_texture_size`
+    /// being the size at scale 1.0
+    pub fn scaled_max_texture_size(&self) -> u32 {
+        ((self.max_texture_size as f32 * self.display_dpi.max(1.0)).ceil() as u32)
+            .min(HARDWARE_MAX_TEXTURE_SIZE)
+    }
+
+    /// Render in sRGB while forced colors mode is active, so the system
+    /// colors chosen for high contrast reach the display unchanged
+    pub fn set_forced_colors(&mut self, active: bool) {
+        if active {
+            self.color_space = ColorSpace::SRGB;
         }
     }
 }
@@ -107,6 +144,12 @@ pub struct GpuStats {
     pub display_list_count: usize,
     /// Compositor layers
     pub compositor_layers: usize,
+    /// Processes throttled because their tab is hidden
+    pub throttled_processes: usize,
+    /// WebGL contexts that are not lost
+    pub webgl_contexts: usize,
+    /// Scale of the display being rendered to
+    pub display_dpi: f32,
 }
 
 /// GPU process manager
@@ -119,10 +162,14 @@ pub struct GpuProcessManager {
     display_list_manager: Arc<RwLock<DisplayListManager>>,
     /// Tiled raster manager
     tiled_raster_manager: Arc<RwLock<TiledRasterManager>>,
+    /// WebGL context manager, created with the first context
+    webgl: Option<WebGlContextManager>,
     /// Process configuration
     config: GpuConfig,
     /// Process statistics
     stats: Arc<RwLock<GpuStats>>,
+    /// Tab visibility, used to throttle hidden tabs
+    tab_visibility: HashMap<TabId, bool>,
     /// Next process ID
     next_process_id: u64,
 }
@@ -135,14 +182,20 @@ impl GpuProcessManager {
         let compositor = Arc::new(RwLock::new(CompositorManager::new(&config).await?));
         let display_list_manager = Arc::new(RwLock::new(DisplayListManager::new(&config).await?));
         let tiled_raster_manager = Arc::new(RwLock::new(TiledRasterManager::new(&config).await?));
+        let stats = GpuStats {
+            display_dpi: config.display_dpi,
+            ..GpuStats::default()
+        };
         
         Ok(Self {
             processes: HashMap::new(),
             compositor,
             display_list_manager,
             tiled_raster_manager,
+            webgl: None,
             config,
-            stats: Arc::new(RwLock::new(GpuStats::default())),
+            stats: Arc::new(RwLock::new(stats)),
+            tab_visibility: HashMap::new(),
             next_process_id: 1,
         })
     }
@@ -152,9 +205,11 @@ impl GpuProcessManager {
         let process_id = format!("gpu_{}", self.next_process_id);
         self.next_process_id += 1;
         
-        let process = GpuProcess::new(process_id.clone(), tab_id, &self.config).await?;
+        let mut process = GpuProcess::new(process_id.clone(), tab_id, &self.config).await?;
+        process.set_visible(self.is_tab_visible(tab_id));
         let process_arc = Arc::new(RwLock::new(process));
         self.processes.insert(process_id.clone(), process_arc);
+        self.update_throttle_stats().await;
         
         info!("Created GPU process {} for tab {}", process_id, tab_id);
         Ok(process_id)
@@ -183,6 +238,84 @@ impl GpuProcessManager {
         Ok(frame)
     }
     
+    /// Answer requests from a renderer over a channel until it closes
+    pub fn serve(manager: Arc<RwLock<Self>>, channel: Channel<GpuReply, GpuRequest>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let request = match channel.recv_async().await {
+                    Ok(request) => request,
+                    Err(e) => {
+                        debug!("GPU channel closed: {}", e);
+                        break;
+                    }
+                };
+                
+                let reply = match request {
+                    GpuRequest::RenderFrame { process_id, display_list } => {
+                        match manager.write().await.render_frame(&process_id, display_list).await {
+                            Ok(frame) => GpuReply::FrameRendered(frame),
+                            Err(e) => GpuReply::Error(e.to_string()),
+                        }
+                    }
+                };
+                
+                if let Err(e) = channel.send_async(reply).await {
+                    warn!("Failed to reply on GPU channel: {}", e);
+                    break;
+                }
+            }
+        })
+    }
+    
+    /// Check whether a process should render a frame now, given its frame pacing
+    pub async fn should_render_frame(&self, process_id: &str) -> Result<bool> {
+        let process_arc = self.processes.get(process_id)
+            .ok_or_else(|| Error::ConfigError(format!("GPU process {} not found", process_id)))?;
+        
+        Ok(process_arc.read().await.frame_pacer().should_render(Instant::now()))
+    }
+    
+    /// Show or hide a tab; hidden tabs render at the background frame rate
+    pub async fn set_tab_visibility(&mut self, tab_id: TabId, visible: bool) {
+        self.tab_visibility.insert(tab_id, visible);
+        
+        for process_arc in self.processes.values() {
+            let mut process = process_arc.write().await;
+            if process.tab_id() == tab_id {
+                process.set_visible(visible);
+            }
+        }
+        
+        self.update_throttle_stats().await;
+        debug!("Tab {} visibility set to {}", tab_id, visible);
+    }
+    
+    /// Check whether a tab is visible (tabs are visible until told otherwise)
+    pub fn is_tab_visible(&self, tab_id: TabId) -> bool {
+        self.tab_visibility.get(&tab_id).copied().unwrap_or(true)
+    }
+    
+    /// Tell a process whether its page is running animations
+    pub async fn set_animations_running(&mut self, process_id: &str, running: bool) -> Result<()> {
+        let process_arc = self.processes.get(process_id)
+            .ok_or_else(|| Error::ConfigError(format!("GPU process {} not found", process_id)))?;
+        
+        process_arc.write().await.set_animations_running(running);
+        Ok(())
+    }
+    
+    /// Recount throttled processes
+    async fn update_throttle_stats(&self) {
+        let mut throttled = 0;
+        for process_arc in self.processes.values() {
+            if !process_arc.read().await.is_visible() {
+                throttled += 1;
+            }
+        }
+        
+        self.stats.write().await.throttled_processes = throttled;
+    }
+    
     /// Composite layers for a process
     pub async fn composite_layers(&mut self, process_id: &str, layers: Vec<CompositorLayer>) -> Result<CompositedFrame> {
         let compositor = self.compositor.read().await;
@@ -193,6 +326,81 @@ impl GpuProcessManager {
         Ok(frame)
     }
     
+    /// Create a WebGL context for a canvas in a tab
+    pub async fn create_webgl_context(&mut self, tab_id: TabId, canvas_id: &str, attributes: WebGlContextAttributes) -> Result<u64> {
+        let context_id = self.webgl_manager().await?.create_context(tab_id, canvas_id, attributes)?;
+        self.update_webgl_stats().await;
+        
+        info!("Created WebGL context {} for tab {}", context_id, tab_id);
+        Ok(context_id)
+    }
+    
+    /// Execute a command in a WebGL context
+    pub async fn execute_webgl_command(&mut self, context_id: u64, command: WebGlCommand) -> Result<WebGlResult> {
+        let result = self.webgl_manager().await?.execute_command(context_id, command).await;
+        self.update_webgl_stats().await;
+        result
+    }
+    
+    /// Resize the drawing buffer of a WebGL context
+    pub async fn resize_webgl_context(&mut self, context_id: u64, width: u32, height: u32) -> Result<()> {
+        self.webgl_manager().await?.resize_context(context_id, width, height)
+    }
+    
+    /// Lose a WebGL context, as `WEBGL_lose_context.loseContext()`
+    pub async fn lose_webgl_context(&mut self, context_id: u64) -> Result<()> {
+        self.webgl_manager().await?.lose_context(context_id)?;
+        self.update_webgl_stats().await;
+        Ok(())
+    }
+    
+    /// Restore a lost WebGL context
+    pub async fn restore_webgl_context(&mut self, context_id: u64) -> Result<()> {
+        self.webgl_manager().await?.restore_context(context_id).await?;
+        self.update_webgl_stats().await;
+        Ok(())
+    }
+    
+    /// Destroy a WebGL context
+    pub async fn destroy_webgl_context(&mut self, context_id: u64) -> Result<()> {
+        self.webgl_manager().await?.destroy_context(context_id)?;
+        self.update_webgl_stats().await;
+        Ok(())
+    }
+    
+    /// Subscribe to WebGL context lost and restored events
+    pub async fn subscribe_webgl_events(&mut self) -> Result<tokio::sync::broadcast::Receiver<WebGlContextEvent>> {
+        Ok(self.webgl_manager().await?.subscribe())
+    }
+    
+    /// Get the WebGL context manager, opening a GPU device for it on first use
+    async fn webgl_manager(&mut self) -> Result<&mut WebGlContextManager> {
+        if !self.config.webgl_enabled {
+            return Err(Error::InvalidState("WebGL is disabled".to_string()));
+        }
+        if self.webgl.is_none() {
+            self.webgl = Some(WebGlContextManager::new(&self.config).await?);
+        }
+        Ok(self.webgl.as_mut().expect("WebGL context manager created above"))
+    }
+    
+    /// Recount active WebGL contexts
+    async fn update_webgl_stats(&mut self) {
+        let active = self.webgl.as_mut().map_or(0, |webgl| webgl.active_contexts());
+        self.stats.write().await.webgl_contexts = active;
+    }
+    
+    /// Render for a display of another scale, as when a window moves to another monitor
+    pub async fn set_display_dpi(&mut self, display_dpi: f32) -> Result<()> {
+        if !display_dpi.is_finite() || display_dpi <= 0.0 {
+            return Err(Error::ConfigError(format!("Invalid display scale {}", display_dpi)));
+        }
+        
+        let mut config = self.config.clone();
+        config.display_dpi = display_dpi;
+        self.update_config(config).await
+    }
+    
     /// Get GPU statistics
     pub async fn get_stats(&self) -> GpuStats {
         self.stats.read().await.clone()
@@ -217,6 +425,19 @@ impl GpuProcessManager {
         tiled_raster_manager.update_config(&new_config).await?;
         drop(tiled_raster_manager);
         
+        // Re-apply frame rate caps
+        for process_arc in self.processes.values() {
+            process_arc.write().await.update_config(&new_config);
+        }
+        
+        self.stats.write().await.display_dpi = new_config.display_dpi;
+        
+        // Disabling WebGL destroys its contexts
+        if !new_config.webgl_enabled {
+            self.webgl = None;
+            self.update_webgl_stats().await;
+        }
+        
         info!("Updated GPU process configuration");
         Ok(())
     }
@@ -227,6 +448,12 @@ impl GpuProcessManager {
         
         // Clear processes
         self.processes.clear();
+        self.tab_visibility.clear();
+        self.webgl = None;
+        let mut stats = self.stats.write().await;
+        stats.throttled_processes = 0;
+        stats.webgl_contexts = 0;
+        drop(stats);
         
         // Shutdown managers
         let mut compositor = self.compositor.write().await;
@@ -264,6 +491,14 @@ pub struct GpuProcess {
     shaders: HashMap<String, Shader>,
     /// Render targets
     render_targets: HashMap<String, RenderTarget>,
+    /// Frame pacer
+    frame_pacer: FramePacer,
+    /// Whether the tab is visible
+    visible: bool,
+    /// Whether the page is running animations
+    animations_running: bool,
+    /// Shared memory frames are written to, reused while the frame size is unchanged
+    frame_buffer: Option<SharedMemorySegment>,
 }
 
 impl GpuProcess {
@@ -280,14 +515,22 @@ impl GpuProcess {
             textures: HashMap::new(),
             shaders: HashMap::new(),
             render_targets: HashMap::new(),
+            frame_pacer: FramePacer::new(config.max_frame_rate),
+            visible: true,
+            animations_running: false,
+            frame_buffer: None,
         })
     }
     
     /// Render a frame
-    pub async fn render_frame(&mut self, _display_list: DisplayList) -> Result<RenderedFrame> {
+    ///
+    /// The pixels are written to the process's shared frame buffer rather than
+    /// returned in the frame; they stay valid until the next frame is rendered.
+    pub async fn render_frame(&mut self, display_list: DisplayList) -> Result<RenderedFrame> {
         self.state = GpuState::Rendering;
         
         let start_time = std::time::Instant::now();
+        self.frame_pacer.record_frame(start_time);
         
         // TODO: Implement actual GPU rendering
         // This would involve:
@@ -298,14 +541,30 @@ impl GpuProcess {
         // 5. Performing anti-aliasing
         // 6. Presenting the frame
         
+        // Placeholder implementation: fill the frame with the clear color
+        let (width, height) = (1920, 1080);
+        let frame_size = width as usize * height as usize * 4; // RGBA
+        if self.frame_buffer.as_ref().map(|buffer| buffer.size()) != Some(frame_size) {
+            self.frame_buffer = Some(SharedMemorySegment::create(frame_size)?);
+        }
+        let frame_buffer = self.frame_buffer.as_mut().expect("frame buffer allocated above");
+        let clear_color = display_list.commands.iter().find_map(|command| match command {
+            DisplayCommand::Clear(color) => Some([color.r, color.g, color.b, color.a]),
+            _ => None,
+        }).unwrap_or_default();
+        // SAFETY: only the GPU process writes frame buffers, other processes map them to read
+        for pixel in unsafe { frame_buffer.as_mut_slice() }.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&clear_color);
+        }
+        
         let render_time = start_time.elapsed();
         
-        // Placeholder implementation
         let frame = RenderedFrame {
             frame_id: format!("frame_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos()),
-            width: 1920,
-            height: 1080,
-            data: vec![0; 1920 * 1080 * 4], // RGBA
+            width,
+            height,
+            data: Vec::new(),
+            shared_memory_name: Some(frame_buffer.name().to_string()),
             render_time,
             gpu_memory_used: 0,
         };
@@ -323,6 +582,99 @@ impl GpuProcess {
     pub fn get_gpu_memory_usage(&self) -> usize {
         self.gpu_memory_mb
     }
+    
+    /// Get the tab this process renders
+    pub fn tab_id(&self) -> TabId {
+        self.tab_id
+    }
+    
+    /// Get the frame pacer
+    pub fn frame_pacer(&self) -> &FramePacer {
+        &self.frame_pacer
+    }
+    
+    /// Check whether the tab is visible
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+    
+    /// Show or hide the tab
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        self.apply_frame_rate();
+    }
+    
+    /// Set whether the page is running animations
+    pub fn set_animations_running(&mut self, running: bool) {
+        self.animations_running = running;
+        self.apply_frame_rate();
+    }
+    
+    /// Update the process configuration
+    pub fn update_config(&mut self, config: &GpuConfig) {
+        self.config = config.clone();
+        self.apply_frame_rate();
+    }
+    
+    /// Cap the frame rate according to visibility and animations
+    fn apply_frame_rate(&mut self) {
+        let frame_rate = if self.visible {
+            self.config.max_frame_rate
+        } else if self.animations_running {
+            self.config.background_frame_rate.min(self.config.max_frame_rate)
+        } else {
+            0
+        };
+        self.frame_pacer.set_frame_rate(frame_rate);
+    }
+}
+
+/// Paces frame production to a target frame rate
+#[derive(Debug, Clone)]
+pub struct FramePacer {
+    /// Target frame rate (0 pauses rendering)
+    frame_rate: u32,
+    /// Time the last frame was rendered
+    last_frame: Option<Instant>,
+}
+
+impl FramePacer {
+    /// Create a frame pacer with a target frame rate
+    pub fn new(frame_rate: u32) -> Self {
+        Self {
+            frame_rate,
+            last_frame: None,
+        }
+    }
+    
+    /// Get the target frame rate
+    pub fn frame_rate(&self) -> u32 {
+        self.frame_rate
+    }
+    
+    /// Set the target frame rate
+    pub fn set_frame_rate(&mut self, frame_rate: u32) {
+        self.frame_rate = frame_rate;
+    }
+    
+    /// Minimum time between frames, or `None` when rendering is paused
+    pub fn frame_interval(&self) -> Option<Duration> {
+        (self.frame_rate > 0).then(|| Duration::from_secs(1) / self.frame_rate)
+    }
+    
+    /// Check whether a frame is due at `now`
+    pub fn should_render(&self, now: Instant) -> bool {
+        match (self.frame_interval(), self.last_frame) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(interval), Some(last_frame)) => now.saturating_duration_since(last_frame) >= interval,
+        }
+    }
+    
+    /// Record that a frame was rendered at `now`
+    pub fn record_frame(&mut self, now: Instant) {
+        self.last_frame = Some(now);
+    }
 }
 
 /// Compositor manager
@@ -360,13 +712,14 @@ impl CompositorManager {
         // 5. Outputting final composited frame
         
         let start_time = std::time::Instant::now();
+        let (width, height) = self.framebuffer_size(&layers);
         
         // Placeholder implementation
         let frame = CompositedFrame {
             frame_id: format!("composited_{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos()),
-            width: 1920,
-            height: 1080,
-            data: vec![0; 1920 * 1080 * 4], // RGBA
+            width,
+            height,
+            data: vec![0; width as usize * height as usize * 4], // RGBA
             composite_time: start_time.elapsed(),
             layer_count: layers.len(),
         };
@@ -374,6 +727,25 @@ impl CompositorManager {
         Ok(frame)
     }
     
+    /// Get the physical size of the framebuffer for layers sized in logical pixels
+    ///
+    /// The logical size is the extent of the largest layer, 1920x1080
+    /// without layers, scaled by the display scale and capped at the
+    /// maximum texture size.
+    pub fn framebuffer_size(&self, layers: &[CompositorLayer]) -> (u32, u32) {
+        let (logical_width, logical_height) = if layers.is_empty() {
+            (1920.0, 1080.0)
+        } else {
+            layers.iter().fold((0.0f32, 0.0f32), |(width, height), layer| {
+                (width.max(layer.width), height.max(layer.height))
+            })
+        };
+        
+        let max_size = self.config.scaled_max_texture_size();
+        let scale = |logical: f32| ((logical * self.config.display_dpi).ceil().max(1.0) as u32).min(max_size);
+        (scale(logical_width), scale(logical_height))
+    }
+    
     /// Update compositor configuration
     pub async fn update_config(&mut self, config: &GpuConfig) -> Result<()> {
         self.config = config.clone();
@@ -517,16 +889,37 @@ impl TiledRasterManager {
     }
 }
 
+// Channel messages
+
+/// Request sent to the GPU process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GpuRequest {
+    /// Render a display list for a GPU process
+    RenderFrame {
+        process_id: String,
+        display_list: DisplayList,
+    },
+}
+
+/// Reply sent by the GPU process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GpuReply {
+    /// The rendered frame
+    FrameRendered(RenderedFrame),
+    /// The request failed
+    Error(String),
+}
+
 // Supporting data structures
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DisplayList {
     pub id: String,
     pub commands: Vec<DisplayCommand>,
     pub bounding_box: Rectangle,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DisplayCommand {
     Clear(Color),
     DrawRectangle(Rectangle, Color),
@@ -536,7 +929,7 @@ pub enum DisplayCommand {
     SetBlendMode(BlendMode),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Rectangle {
     pub x: i32,
     pub y: i32,
@@ -550,7 +943,7 @@ impl Rectangle {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -558,7 +951,7 @@ pub struct Color {
     pub a: u8,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TextCommand {
     pub text: String,
     pub position: Point,
@@ -566,19 +959,19 @@ pub struct TextCommand {
     pub color: Color,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ImageCommand {
     pub image_data: Vec<u8>,
     pub position: Point,
     pub size: Size,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Transform {
     pub matrix: [f32; 16],
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BlendMode {
     Normal,
     Multiply,
@@ -586,19 +979,19 @@ pub enum BlendMode {
     Overlay,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Point {
     pub x: f32,
     pub y: f32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Size {
     pub width: u32,
     pub height: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Font {
     pub family: String,
     pub size: f32,
@@ -606,28 +999,41 @@ pub struct Font {
     pub style: FontStyle,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FontWeight {
     Normal,
     Bold,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FontStyle {
     Normal,
     Italic,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RenderedFrame {
     pub frame_id: String,
     pub width: u32,
     pub height: u32,
+    /// RGBA pixels, empty when they are in shared memory
     pub data: Vec<u8>,
+    /// Shared memory segment holding the RGBA pixels
+    pub shared_memory_name: Option<String>,
     pub render_time: std::time::Duration,
     pub gpu_memory_used: usize,
 }
 
+impl RenderedFrame {
+    /// Map the shared memory holding the pixels, without copying them
+    pub fn map_pixels(&self) -> Result<SharedMemorySegment> {
+        let name = self.shared_memory_name.as_deref().ok_or_else(|| {
+            Error::InvalidState(format!("Frame {} is not in shared memory", self.frame_id))
+        })?;
+        SharedMemorySegment::open(name, self.width as usize * self.height as usize * 4)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CompositedFrame {
     pub frame_id: String,
@@ -642,6 +1048,10 @@ pub struct CompositedFrame {
 pub struct CompositorLayer {
     pub id: String,
     pub z_order: i32,
+    /// Layer width in logical pixels
+    pub width: f32,
+    /// Layer height in logical pixels
+    pub height: f32,
     pub transform: Transform,
     pub blend_mode: BlendMode,
     pub opacity: f32,
@@ -748,6 +1158,16 @@ pub async fn init(config: GpuConfig) -> Result<GpuProcessManager> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common::ipc::ChannelPair;
+
+    #[test]
+    fn test_forced_colors_use_srgb() {
+        let mut config = GpuConfig { color_space: ColorSpace::DisplayP3, ..GpuConfig::default() };
+        config.set_forced_colors(false);
+        assert_eq!(config.color_space, ColorSpace::DisplayP3);
+        config.set_forced_colors(true);
+        assert_eq!(config.color_space, ColorSpace::SRGB);
+    }
 
     #[tokio::test]
     async fn test_gpu_process_manager_creation() {
@@ -789,6 +1209,22 @@ mod tests {
         let frame = frame.unwrap();
         assert_eq!(frame.width, 1920);
         assert_eq!(frame.height, 1080);
+        
+        // Pixels are passed in shared memory rather than in the frame
+        assert!(frame.data.is_empty());
+        let pixels = frame.map_pixels().unwrap();
+        assert_eq!(pixels.size(), 1920 * 1080 * 4);
+        assert!(pixels.read().iter().all(|&byte| byte == 255));
+        
+        // The next frame reuses the buffer, so the mapping sees it
+        let display_list = DisplayList {
+            id: "test_list".to_string(),
+            commands: vec![DisplayCommand::Clear(Color { r: 10, g: 20, b: 30, a: 255 })],
+            bounding_box: Rectangle::new(0, 0, 1920, 1080),
+        };
+        let next = manager.render_frame(&process_id, display_list).await.unwrap();
+        assert_eq!(next.shared_memory_name, frame.shared_memory_name);
+        assert_eq!(&pixels.read()[..8], &[10, 20, 30, 255, 10, 20, 30, 255]);
     }
 
     #[tokio::test]
@@ -800,6 +1236,8 @@ mod tests {
             CompositorLayer {
                 id: "layer1".to_string(),
                 z_order: 1,
+                width: 1920.0,
+                height: 1080.0,
                 transform: Transform { matrix: [1.0; 16] },
                 blend_mode: BlendMode::Normal,
                 opacity: 1.0,
@@ -816,6 +1254,36 @@ mod tests {
         assert_eq!(frame.layer_count, 1);
     }
 
+    #[tokio::test]
+    async fn test_compositing_on_monitors_of_different_scale() {
+        let mut manager = GpuProcessManager::new(GpuConfig::default()).await.unwrap();
+        let layer = CompositorLayer {
+            id: "page".to_string(),
+            z_order: 0,
+            width: 800.0,
+            height: 600.0,
+            transform: Transform { matrix: [1.0; 16] },
+            blend_mode: BlendMode::Normal,
+            opacity: 1.0,
+            content: LayerContent::Solid(Color { r: 255, g: 255, b: 255, a: 255 }),
+        };
+        
+        // Window on the standard monitor
+        let frame = manager.composite_layers("test_process", vec![layer.clone()]).await.unwrap();
+        assert_eq!((frame.width, frame.height), (800, 600));
+        assert_eq!(manager.get_stats().await.display_dpi, 1.0);
+        
+        // Window dragged to the HiDPI monitor
+        manager.set_display_dpi(2.0).await.unwrap();
+        let frame = manager.composite_layers("test_process", vec![layer]).await.unwrap();
+        assert_eq!((frame.width, frame.height), (1600, 1200));
+        assert_eq!(frame.data.len(), 1600 * 1200 * 4);
+        assert_eq!(manager.get_stats().await.display_dpi, 2.0);
+        assert_eq!(manager.config.scaled_max_texture_size(), 16384);
+        
+        assert!(manager.set_display_dpi(0.0).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_display_list_management() {
         let config = GpuConfig::default();
@@ -857,6 +1325,51 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_background_tab_throttling() {
+        let config = GpuConfig::default();
+        let mut manager = GpuProcessManager::new(config).await.unwrap();
+        
+        let hidden_tab = TabId::new(1);
+        let visible_tab = TabId::new(2);
+        let hidden_process = manager.create_process(hidden_tab).await.unwrap();
+        let visible_process = manager.create_process(visible_tab).await.unwrap();
+        
+        manager.set_tab_visibility(hidden_tab, false).await;
+        assert_eq!(manager.get_stats().await.throttled_processes, 1);
+        
+        let process = manager.get_process(&hidden_process).await.unwrap();
+        assert_eq!(process.read().await.frame_pacer().frame_rate(), 0);
+        assert!(!manager.should_render_frame(&hidden_process).await.unwrap());
+        assert!(manager.should_render_frame(&visible_process).await.unwrap());
+        
+        manager.set_animations_running(&hidden_process, true).await.unwrap();
+        assert_eq!(process.read().await.frame_pacer().frame_rate(), 1);
+        
+        // Processes created later for a hidden tab start throttled
+        manager.create_process(hidden_tab).await.unwrap();
+        assert_eq!(manager.get_stats().await.throttled_processes, 2);
+        
+        manager.set_tab_visibility(hidden_tab, true).await;
+        assert_eq!(process.read().await.frame_pacer().frame_rate(), 60);
+        assert_eq!(manager.get_stats().await.throttled_processes, 0);
+    }
+
+    #[test]
+    fn test_frame_pacer() {
+        let mut pacer = FramePacer::new(1);
+        let start = Instant::now();
+        
+        assert!(pacer.should_render(start));
+        pacer.record_frame(start);
+        assert!(!pacer.should_render(start + Duration::from_millis(500)));
+        assert!(pacer.should_render(start + Duration::from_secs(1)));
+        
+        pacer.set_frame_rate(0);
+        assert_eq!(pacer.frame_interval(), None);
+        assert!(!pacer.should_render(start + Duration::from_secs(10)));
+    }
+
     #[tokio::test]
     async fn test_statistics() {
         let config = GpuConfig::default();
@@ -867,4 +1380,70 @@ mod tests {
         assert_eq!(stats.texture_count, 0);
         assert_eq!(stats.shader_count, 0);
     }
+
+    fn sample_display_list() -> DisplayList {
+        DisplayList {
+            id: "page".to_string(),
+            commands: vec![
+                DisplayCommand::Clear(Color { r: 255, g: 255, b: 255, a: 255 }),
+                DisplayCommand::SetBlendMode(BlendMode::Multiply),
+                DisplayCommand::DrawRectangle(Rectangle::new(-4, 8, 120, 40), Color { r: 10, g: 20, b: 30, a: 128 }),
+                DisplayCommand::DrawText(TextCommand {
+                    text: "Grüße".to_string(),
+                    position: Point { x: 12.5, y: 30.0 },
+                    font: Font {
+                        family: "serif".to_string(),
+                        size: 16.0,
+                        weight: FontWeight::Bold,
+                        style: FontStyle::Italic,
+                    },
+                    color: Color { r: 0, g: 0, b: 0, a: 255 },
+                }),
+                DisplayCommand::DrawImage(ImageCommand {
+                    image_data: vec![0, 1, 2, 253, 254, 255],
+                    position: Point { x: 0.0, y: 0.0 },
+                    size: Size { width: 2, height: 1 },
+                }),
+                DisplayCommand::SetTransform(Transform { matrix: [0.5; 16] }),
+            ],
+            bounding_box: Rectangle::new(0, 0, 800, 600),
+        }
+    }
+
+    #[test]
+    fn test_display_list_channel_round_trip() {
+        let (renderer, gpu) = ChannelPair::create::<DisplayList, DisplayList>().unwrap();
+        renderer.send(sample_display_list()).unwrap();
+        assert_eq!(gpu.recv().unwrap(), sample_display_list());
+    }
+
+    #[tokio::test]
+    async fn test_serve_render_requests() {
+        let mut manager = GpuProcessManager::new(GpuConfig::default()).await.unwrap();
+        let process_id = manager.create_process(TabId::new(1)).await.unwrap();
+        let manager = Arc::new(RwLock::new(manager));
+        
+        let (renderer, gpu) = ChannelPair::create::<GpuRequest, GpuReply>().unwrap();
+        let server = GpuProcessManager::serve(manager.clone(), gpu);
+        
+        renderer.send_async(GpuRequest::RenderFrame {
+            process_id,
+            display_list: sample_display_list(),
+        }).await.unwrap();
+        match renderer.recv_async().await.unwrap() {
+            GpuReply::FrameRendered(frame) => assert_eq!((frame.width, frame.height), (1920, 1080)),
+            other => panic!("unexpected reply: {:?}", other),
+        }
+        
+        renderer.send_async(GpuRequest::RenderFrame {
+            process_id: "missing".to_string(),
+            display_list: sample_display_list(),
+        }).await.unwrap();
+        assert!(matches!(renderer.recv_async().await.unwrap(), GpuReply::Error(_)));
+        
+        // The server stops when the renderer goes away
+        drop(renderer);
+        server.await.unwrap();
+        assert_eq!(manager.read().await.get_stats().await.total_frames, 1);
+    }
 }
@@ -0,0 +1,861 @@
+//! WebGL rendering contexts
+//!
+//! Every context draws into an offscreen wgpu texture the size of its
+//! canvas; wgpu surfaces need a window, which the GPU process does not own.
+//! Commands follow WebGL's state machine: clear color, bound program, bound
+//! texture and vertex attributes persist between commands. Program sources
+//! are WGSL with a `main` entry point per stage, as shaders reach the GPU
+//! process already translated from GLSL ES. Fragment shaders can sample the
+//! bound texture through `@group(0) @binding(0)` and `@binding(1)`.
+//!
+//! Losing the device loses every context. Contexts come back through
+//! `restore_context`, empty, as after `webglcontextrestored`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+use wgpu::util::DeviceExt;
+use common::error::{Error, Result};
+use common::types::TabId;
+use crate::GpuConfig;
+
+/// Width of a new drawing buffer, the default canvas width
+pub const DEFAULT_CANVAS_WIDTH: u32 = 300;
+
+/// Height of a new drawing buffer, the default canvas height
+pub const DEFAULT_CANVAS_HEIGHT: u32 = 150;
+
+/// Format of every drawing buffer and texture
+const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Context events a subscriber can fall behind by before older ones are dropped
+const CONTEXT_EVENT_CAPACITY: usize = 64;
+
+/// Entry point of both shader stages
+const SHADER_ENTRY_POINT: &str = "main";
+
+/// Attributes a context is created with, as passed to `getContext`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebGlContextAttributes {
+    /// Whether the drawing buffer has an alpha channel
+    pub alpha: bool,
+    /// Whether the drawing buffer has a depth buffer
+    pub depth: bool,
+    /// Whether the drawing buffer has a stencil buffer
+    pub stencil: bool,
+    /// Whether to antialias
+    pub antialias: bool,
+    /// Whether colors in the drawing buffer have premultiplied alpha
+    pub premultiplied_alpha: bool,
+    /// Whether the drawing buffer keeps its contents once presented
+    pub preserve_drawing_buffer: bool,
+    /// Fail creation when only a software renderer is available
+    pub fail_if_major_performance_caveat: bool,
+}
+
+impl Default for WebGlContextAttributes {
+    fn default() -> Self {
+        Self {
+            alpha: true,
+            depth: true,
+            stencil: false,
+            antialias: true,
+            premultiplied_alpha: true,
+            preserve_drawing_buffer: false,
+            fail_if_major_performance_caveat: false,
+        }
+    }
+}
+
+/// Primitive assembled by `drawArrays`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DrawMode {
+    Points,
+    Lines,
+    LineStrip,
+    Triangles,
+    TriangleStrip,
+}
+
+/// Command sent to a WebGL context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WebGlCommand {
+    /// Set the color `Clear` fills the drawing buffer with
+    ClearColor { red: f32, green: f32, blue: f32, alpha: f32 },
+    /// Clear the drawing buffer to the clear color
+    Clear,
+    /// Fill a buffer with data, creating it on first use
+    BufferData { buffer: u32, data: Vec<u8> },
+    /// Source a vertex attribute from a buffer of 32-bit floats
+    VertexAttribPointer { location: u32, buffer: u32, size: u32, stride: u32, offset: u64 },
+    /// Stop sourcing a vertex attribute from a buffer
+    DisableVertexAttribArray { location: u32 },
+    /// Upload RGBA pixels to a texture, creating it on first use
+    TexImage2D { texture: u32, width: u32, height: u32, pixels: Vec<u8> },
+    /// Bind the texture fragment shaders sample, `None` unbinds it
+    BindTexture { texture: Option<u32> },
+    /// Compile and link a program from WGSL shaders
+    LinkProgram { program: u32, vertex_source: String, fragment_source: String },
+    /// Use a program for drawing, `None` unbinds it
+    UseProgram { program: Option<u32> },
+    /// Draw vertices from the enabled vertex attributes
+    DrawArrays { mode: DrawMode, first: u32, count: u32 },
+    /// Read RGBA pixels, rows from the bottom up as in `readPixels`
+    ReadPixels { x: u32, y: u32, width: u32, height: u32 },
+}
+
+/// Result of a command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebGlResult {
+    /// The command completed without a value
+    None,
+    /// Pixels read by `ReadPixels`
+    Pixels(Vec<u8>),
+    /// The context is lost, so the command was ignored
+    ContextLost,
+}
+
+/// Context lifecycle event, dispatched to the canvas of the context
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebGlContextEvent {
+    /// `webglcontextlost`
+    ContextLost { context_id: u64, tab_id: TabId },
+    /// `webglcontextrestored`
+    ContextRestored { context_id: u64, tab_id: TabId },
+}
+
+/// WebGL rendering context of a canvas
+pub struct WebGlContext {
+    /// Context ID
+    pub context_id: u64,
+    /// Tab owning the canvas
+    pub tab_id: TabId,
+    /// Drawing buffer width
+    pub width: u32,
+    /// Drawing buffer height
+    pub height: u32,
+    /// Attributes the context was created with
+    pub attributes: WebGlContextAttributes,
+    /// Canvas the context draws to
+    canvas_id: String,
+    /// GPU resources, `None` while the context is lost
+    resources: Option<ContextResources>,
+    /// State set by commands
+    state: ContextState,
+}
+
+impl WebGlContext {
+    /// Get the canvas the context draws to
+    pub fn canvas_id(&self) -> &str {
+        &self.canvas_id
+    }
+
+    /// Check whether the context is lost
+    pub fn is_lost(&self) -> bool {
+        self.resources.is_none()
+    }
+}
+
+/// GPU resources of a context
+struct ContextResources {
+    /// Drawing buffer
+    drawing_buffer: wgpu::Texture,
+    /// Buffers by name
+    buffers: HashMap<u32, wgpu::Buffer>,
+    /// Textures by name
+    textures: HashMap<u32, wgpu::Texture>,
+    /// Linked programs by name
+    programs: HashMap<u32, Program>,
+}
+
+/// Linked program
+struct Program {
+    vertex: wgpu::ShaderModule,
+    fragment: wgpu::ShaderModule,
+}
+
+/// Vertex attribute sourced from a buffer
+#[derive(Debug, Clone, Copy)]
+struct VertexAttribute {
+    buffer: u32,
+    size: u32,
+    stride: u32,
+    offset: u64,
+}
+
+/// State set by commands
+#[derive(Debug, Clone, Default)]
+struct ContextState {
+    clear_color: wgpu::Color,
+    program: Option<u32>,
+    texture: Option<u32>,
+    /// Enabled vertex attributes by location
+    vertex_attributes: BTreeMap<u32, VertexAttribute>,
+}
+
+/// Device all contexts render with, and the objects every draw shares
+struct GpuDevice {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    /// Layout of the texture and sampler fragment shaders can use
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    sampler: wgpu::Sampler,
+    /// Texture sampled while none is bound, opaque black as WebGL specifies
+    blank_texture: wgpu::Texture,
+    /// Set by wgpu when the device is lost
+    lost: Arc<AtomicBool>,
+}
+
+impl GpuDevice {
+    async fn new(instance: &wgpu::Instance) -> Result<Self> {
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await
+            .ok_or_else(|| Error::GraphicsError("No GPU adapter available for WebGL".to_string()))?;
+        let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("webgl"),
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+        }, None)
+            .await
+            .map_err(|e| Error::GraphicsError(format!("Failed to open GPU device for WebGL: {}", e)))?;
+        info!("WebGL using {:?}", adapter.get_info());
+
+        // Errors are reported per command through error scopes
+        device.on_uncaptured_error(Box::new(|error| warn!("Uncaptured WebGL GPU error: {}", error)));
+        let lost = Arc::new(AtomicBool::new(false));
+        let lost_flag = lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            if matches!(reason, wgpu::DeviceLostReason::Unknown | wgpu::DeviceLostReason::Destroyed) {
+                warn!("WebGL GPU device lost: {}", message);
+                lost_flag.store(true, Ordering::SeqCst);
+            }
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("webgl texture"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("webgl program"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("webgl texture"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let blank_texture = create_texture(&device, 1, 1, wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST);
+        write_texture(&queue, &blank_texture, 1, 1, &[0, 0, 0, 255]);
+
+        Ok(Self { device, queue, bind_group_layout, pipeline_layout, sampler, blank_texture, lost })
+    }
+
+    fn create_drawing_buffer(&self, width: u32, height: u32) -> wgpu::Texture {
+        create_texture(
+            &self.device,
+            width,
+            height,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST,
+        )
+    }
+}
+
+/// WebGL context manager
+///
+/// All contexts share one GPU device, opened when the manager is created.
+pub struct WebGlContextManager {
+    /// Instance the device is opened from, again after it is lost
+    instance: wgpu::Instance,
+    /// Device the contexts render with
+    device: GpuDevice,
+    /// Contexts by ID
+    contexts: HashMap<u64, WebGlContext>,
+    /// Largest drawing buffer dimension
+    max_texture_size: u32,
+    /// Context lifecycle events
+    events: broadcast::Sender<WebGlContextEvent>,
+    /// Next context ID
+    next_context_id: u64,
+}
+
+impl WebGlContextManager {
+    /// Create a WebGL context manager, opening a GPU device
+    pub async fn new(config: &GpuConfig) -> Result<Self> {
+        info!("Initializing WebGL context manager");
+
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let device = GpuDevice::new(&instance).await?;
+        let max_texture_size = config.max_texture_size.min(device.device.limits().max_texture_dimension_2d);
+        let (events, _) = broadcast::channel(CONTEXT_EVENT_CAPACITY);
+
+        Ok(Self {
+            instance,
+            device,
+            contexts: HashMap::new(),
+            max_texture_size,
+            events,
+            next_context_id: 1,
+        })
+    }
+
+    /// Subscribe to context lost and restored events
+    pub fn subscribe(&self) -> broadcast::Receiver<WebGlContextEvent> {
+        self.events.subscribe()
+    }
+
+    /// Create a context for a canvas, with a drawing buffer of the default canvas size
+    pub fn create_context(&mut self, tab_id: TabId, canvas_id: &str, attributes: WebGlContextAttributes) -> Result<u64> {
+        self.check_device();
+        if self.device.lost.load(Ordering::SeqCst) {
+            return Err(Error::GraphicsError("The GPU device is lost".to_string()));
+        }
+
+        let context_id = self.next_context_id;
+        self.next_context_id += 1;
+
+        let (width, height) = (DEFAULT_CANVAS_WIDTH, DEFAULT_CANVAS_HEIGHT);
+        let resources = ContextResources::new(&self.device, width, height);
+        self.contexts.insert(context_id, WebGlContext {
+            context_id,
+            tab_id,
+            width,
+            height,
+            attributes,
+            canvas_id: canvas_id.to_string(),
+            resources: Some(resources),
+            state: ContextState::default(),
+        });
+
+        debug!("Created WebGL context {} for canvas {} in tab {}", context_id, canvas_id, tab_id);
+        Ok(context_id)
+    }
+
+    /// Get a context
+    pub fn get_context(&self, context_id: u64) -> Option<&WebGlContext> {
+        self.contexts.get(&context_id)
+    }
+
+    /// Resize the drawing buffer of a context, clearing it
+    pub fn resize_context(&mut self, context_id: u64, width: u32, height: u32) -> Result<()> {
+        let (width, height) = (width.clamp(1, self.max_texture_size), height.clamp(1, self.max_texture_size));
+        let context = self.contexts.get_mut(&context_id).ok_or_else(|| context_not_found(context_id))?;
+        context.width = width;
+        context.height = height;
+        if let Some(resources) = context.resources.as_mut() {
+            resources.drawing_buffer = self.device.create_drawing_buffer(width, height);
+        }
+        Ok(())
+    }
+
+    /// Destroy a context
+    pub fn destroy_context(&mut self, context_id: u64) -> Result<()> {
+        self.contexts.remove(&context_id).ok_or_else(|| context_not_found(context_id))?;
+        debug!("Destroyed WebGL context {}", context_id);
+        Ok(())
+    }
+
+    /// Number of contexts that are not lost
+    pub fn active_contexts(&mut self) -> usize {
+        self.check_device();
+        self.contexts.values().filter(|context| !context.is_lost()).count()
+    }
+
+    /// Lose a context, as `WEBGL_lose_context.loseContext()`
+    pub fn lose_context(&mut self, context_id: u64) -> Result<()> {
+        let context = self.contexts.get_mut(&context_id).ok_or_else(|| context_not_found(context_id))?;
+        if context.resources.take().is_some() {
+            let _ = self.events.send(WebGlContextEvent::ContextLost { context_id, tab_id: context.tab_id });
+        }
+        Ok(())
+    }
+
+    /// Restore a lost context with empty resources, opening a new device if the old one is lost
+    pub async fn restore_context(&mut self, context_id: u64) -> Result<()> {
+        self.check_device();
+        if !self.contexts.get(&context_id).ok_or_else(|| context_not_found(context_id))?.is_lost() {
+            return Ok(());
+        }
+        if self.device.lost.load(Ordering::SeqCst) {
+            self.device = GpuDevice::new(&self.instance).await?;
+        }
+
+        let context = self.contexts.get_mut(&context_id).expect("context looked up above");
+        context.resources = Some(ContextResources::new(&self.device, context.width, context.height));
+        context.state = ContextState::default();
+        let _ = self.events.send(WebGlContextEvent::ContextRestored { context_id, tab_id: context.tab_id });
+        info!("Restored WebGL context {}", context_id);
+        Ok(())
+    }
+
+    /// Execute a command in a context
+    ///
+    /// Commands sent to a lost context are ignored.
+    pub async fn execute_command(&mut self, context_id: u64, command: WebGlCommand) -> Result<WebGlResult> {
+        self.check_device();
+        let context = self.contexts.get_mut(&context_id).ok_or_else(|| context_not_found(context_id))?;
+        if context.is_lost() {
+            return Ok(WebGlResult::ContextLost);
+        }
+
+        self.device.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let result = context.execute(&self.device, command);
+        if let Some(error) = self.device.device.pop_error_scope().await {
+            return Err(Error::GraphicsError(format!("WebGL command failed: {}", error)));
+        }
+        result
+    }
+
+    /// Lose every context once the device is lost
+    fn check_device(&mut self) {
+        if !self.device.lost.load(Ordering::SeqCst) {
+            return;
+        }
+        for context in self.contexts.values_mut() {
+            if context.resources.take().is_some() {
+                let _ = self.events.send(WebGlContextEvent::ContextLost {
+                    context_id: context.context_id,
+                    tab_id: context.tab_id,
+                });
+            }
+        }
+    }
+}
+
+impl ContextResources {
+    fn new(device: &GpuDevice, width: u32, height: u32) -> Self {
+        Self {
+            drawing_buffer: device.create_drawing_buffer(width, height),
+            buffers: HashMap::new(),
+            textures: HashMap::new(),
+            programs: HashMap::new(),
+        }
+    }
+}
+
+impl WebGlContext {
+    fn execute(&mut self, device: &GpuDevice, command: WebGlCommand) -> Result<WebGlResult> {
+        let resources = self.resources.as_mut().expect("lost contexts do not execute commands");
+        match command {
+            WebGlCommand::ClearColor { red, green, blue, alpha } => {
+                self.state.clear_color = wgpu::Color {
+                    r: red.clamp(0.0, 1.0) as f64,
+                    g: green.clamp(0.0, 1.0) as f64,
+                    b: blue.clamp(0.0, 1.0) as f64,
+                    a: alpha.clamp(0.0, 1.0) as f64,
+                };
+            }
+            WebGlCommand::Clear => {
+                let mut color = self.state.clear_color;
+                if !self.attributes.alpha {
+                    color.a = 1.0;
+                }
+                let view = resources.drawing_buffer.create_view(&wgpu::TextureViewDescriptor::default());
+                let mut encoder = device.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("webgl clear") });
+                begin_render_pass(&mut encoder, &view, wgpu::LoadOp::Clear(color));
+                device.queue.submit(Some(encoder.finish()));
+            }
+            WebGlCommand::BufferData { buffer, data } => {
+                let contents = device.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("webgl buffer"),
+                    contents: &data,
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                });
+                resources.buffers.insert(buffer, contents);
+            }
+            WebGlCommand::VertexAttribPointer { location, buffer, size, stride, offset } => {
+                if !(1..=4).contains(&size) {
+                    return Err(Error::GraphicsError(format!("Invalid vertex attribute size {}", size)));
+                }
+                if stride % 4 != 0 || offset % 4 != 0 {
+                    return Err(Error::GraphicsError("Vertex attribute stride and offset must be multiples of 4".to_string()));
+                }
+                self.state.vertex_attributes.insert(location, VertexAttribute { buffer, size, stride, offset });
+            }
+            WebGlCommand::DisableVertexAttribArray { location } => {
+                self.state.vertex_attributes.remove(&location);
+            }
+            WebGlCommand::TexImage2D { texture, width, height, pixels } => {
+                if width == 0 || height == 0 || pixels.len() != width as usize * height as usize * 4 {
+                    return Err(Error::GraphicsError(format!("{} bytes are not {}x{} RGBA pixels", pixels.len(), width, height)));
+                }
+                let contents = create_texture(&device.device, width, height, wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST);
+                write_texture(&device.queue, &contents, width, height, &pixels);
+                resources.textures.insert(texture, contents);
+            }
+            WebGlCommand::BindTexture { texture } => {
+                self.state.texture = texture;
+            }
+            WebGlCommand::LinkProgram { program, vertex_source, fragment_source } => {
+                let vertex = device.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("webgl vertex shader"),
+                    source: wgpu::ShaderSource::Wgsl(vertex_source.into()),
+                });
+                let fragment = device.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("webgl fragment shader"),
+                    source: wgpu::ShaderSource::Wgsl(fragment_source.into()),
+                });
+                resources.programs.insert(program, Program { vertex, fragment });
+            }
+            WebGlCommand::UseProgram { program } => {
+                if let Some(program) = program.filter(|program| !resources.programs.contains_key(program)) {
+                    return Err(Error::GraphicsError(format!("Program {} is not linked", program)));
+                }
+                self.state.program = program;
+            }
+            WebGlCommand::DrawArrays { mode, first, count } => {
+                self.draw_arrays(device, mode, first, count)?;
+            }
+            WebGlCommand::ReadPixels { x, y, width, height } => {
+                return self.read_pixels(device, x, y, width, height).map(WebGlResult::Pixels);
+            }
+        }
+        Ok(WebGlResult::None)
+    }
+
+    fn draw_arrays(&self, device: &GpuDevice, mode: DrawMode, first: u32, count: u32) -> Result<()> {
+        let resources = self.resources.as_ref().expect("lost contexts do not execute commands");
+        let program = self.state.program
+            .and_then(|program| resources.programs.get(&program))
+            .ok_or_else(|| Error::GraphicsError("No program in use".to_string()))?;
+        if count == 0 {
+            return Ok(());
+        }
+
+        // One vertex buffer per enabled attribute, each starting at the attribute offset
+        let mut buffers = Vec::new();
+        for (&location, attribute) in &self.state.vertex_attributes {
+            let buffer = resources.buffers.get(&attribute.buffer)
+                .ok_or_else(|| Error::GraphicsError(format!("Buffer {} has no data", attribute.buffer)))?;
+            buffers.push((location, attribute, buffer));
+        }
+        let attributes: Vec<[wgpu::VertexAttribute; 1]> = buffers.iter()
+            .map(|(location, attribute, _)| [wgpu::VertexAttribute {
+                format: match attribute.size {
+                    1 => wgpu::VertexFormat::Float32,
+                    2 => wgpu::VertexFormat::Float32x2,
+                    3 => wgpu::VertexFormat::Float32x3,
+                    _ => wgpu::VertexFormat::Float32x4,
+                },
+                offset: 0,
+                shader_location: *location,
+            }])
+            .collect();
+        let layouts: Vec<wgpu::VertexBufferLayout> = buffers.iter()
+            .zip(&attributes)
+            .map(|((_, attribute, _), attributes)| wgpu::VertexBufferLayout {
+                // A zero stride means tightly packed, as in WebGL
+                array_stride: match attribute.stride {
+                    0 => attribute.size as u64 * 4,
+                    stride => stride as u64,
+                },
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes,
+            })
+            .collect();
+
+        let pipeline = device.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("webgl draw"),
+            layout: Some(&device.pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &program.vertex,
+                entry_point: SHADER_ENTRY_POINT,
+                buffers: &layouts,
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: match mode {
+                    DrawMode::Points => wgpu::PrimitiveTopology::PointList,
+                    DrawMode::Lines => wgpu::PrimitiveTopology::LineList,
+                    DrawMode::LineStrip => wgpu::PrimitiveTopology::LineStrip,
+                    DrawMode::Triangles => wgpu::PrimitiveTopology::TriangleList,
+                    DrawMode::TriangleStrip => wgpu::PrimitiveTopology::TriangleStrip,
+                },
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &program.fragment,
+                entry_point: SHADER_ENTRY_POINT,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: TEXTURE_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        let texture = self.state.texture
+            .and_then(|texture| resources.textures.get(&texture))
+            .unwrap_or(&device.blank_texture);
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("webgl texture"),
+            layout: &device.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&texture_view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&device.sampler) },
+            ],
+        });
+
+        let view = resources.drawing_buffer.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("webgl draw") });
+        {
+            let mut pass = begin_render_pass(&mut encoder, &view, wgpu::LoadOp::Load);
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            for (slot, (_, attribute, buffer)) in buffers.iter().enumerate() {
+                pass.set_vertex_buffer(slot as u32, buffer.slice(attribute.offset..));
+            }
+            pass.draw(first..first + count, 0..1);
+        }
+        device.queue.submit(Some(encoder.finish()));
+        Ok(())
+    }
+
+    fn read_pixels(&self, device: &GpuDevice, x: u32, y: u32, width: u32, height: u32) -> Result<Vec<u8>> {
+        let resources = self.resources.as_ref().expect("lost contexts do not execute commands");
+        if width == 0 || height == 0 {
+            return Ok(Vec::new());
+        }
+        if x.saturating_add(width) > self.width || y.saturating_add(height) > self.height {
+            return Err(Error::GraphicsError(format!(
+                "Cannot read {}x{} pixels at {},{} from a {}x{} drawing buffer",
+                width, height, x, y, self.width, self.height
+            )));
+        }
+
+        // Copied rows are padded to the alignment wgpu requires
+        let row_bytes = width * 4;
+        let padded_row_bytes = row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let readback = device.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("webgl read pixels"),
+            size: padded_row_bytes as u64 * height as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // WebGL counts rows from the bottom, textures from the top
+        let mut encoder = device.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("webgl read pixels") });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &resources.drawing_buffer,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y: self.height - y - height, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+        device.queue.submit(Some(encoder.finish()));
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.device.poll(wgpu::Maintain::Wait);
+        receiver.recv()
+            .map_err(|_| Error::GraphicsError("Pixel readback was dropped".to_string()))?
+            .map_err(|e| Error::GraphicsError(format!("Failed to read pixels: {}", e)))?;
+
+        let mut pixels = Vec::with_capacity((row_bytes * height) as usize);
+        {
+            let mapped = slice.get_mapped_range();
+            for row in mapped.chunks_exact(padded_row_bytes as usize).rev() {
+                pixels.extend_from_slice(&row[..row_bytes as usize]);
+            }
+        }
+        readback.unmap();
+
+        if !self.attributes.alpha {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel[3] = 255;
+            }
+        }
+        Ok(pixels)
+    }
+}
+
+fn create_texture(device: &wgpu::Device, width: u32, height: u32, usage: wgpu::TextureUsages) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("webgl texture"),
+        size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TEXTURE_FORMAT,
+        usage,
+        view_formats: &[],
+    })
+}
+
+fn write_texture(queue: &wgpu::Queue, texture: &wgpu::Texture, width: u32, height: u32, pixels: &[u8]) {
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        pixels,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(width * 4),
+            rows_per_image: Some(height),
+        },
+        wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+    );
+}
+
+fn begin_render_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    view: &'a wgpu::TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("webgl"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations { load, store: wgpu::StoreOp::Store },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    })
+}
+
+fn context_not_found(context_id: u64) -> Error {
+    Error::NotFound(format!("WebGL context {} not found", context_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GpuProcessManager;
+
+    const VERTEX_SHADER: &str = "
+        @vertex
+        fn main(@location(0) position: vec2<f32>) -> @builtin(position) vec4<f32> {
+            return vec4<f32>(position, 0.0, 1.0);
+        }
+    ";
+
+    const FRAGMENT_SHADER: &str = "
+        @fragment
+        fn main() -> @location(0) vec4<f32> {
+            return vec4<f32>(0.0, 1.0, 0.0, 1.0);
+        }
+    ";
+
+    /// GPU process manager with WebGL started
+    async fn manager() -> GpuProcessManager {
+        let mut manager = GpuProcessManager::new(GpuConfig::default()).await.unwrap();
+        manager.subscribe_webgl_events().await.expect("WebGL needs a GPU adapter, a software one is enough");
+        manager
+    }
+
+    async fn execute(manager: &mut GpuProcessManager, context_id: u64, command: WebGlCommand) -> WebGlResult {
+        manager.execute_webgl_command(context_id, command).await.unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a GPU adapter, run with --ignored"]
+    async fn test_clear_to_red_and_read_back() {
+        let mut manager = manager().await;
+        let context_id = manager.create_webgl_context(TabId::new(1), "canvas", WebGlContextAttributes::default()).await.unwrap();
+        assert_eq!(manager.get_stats().await.webgl_contexts, 1);
+        manager.resize_webgl_context(context_id, 4, 2).await.unwrap();
+
+        execute(&mut manager, context_id, WebGlCommand::ClearColor { red: 1.0, green: 0.0, blue: 0.0, alpha: 1.0 }).await;
+        execute(&mut manager, context_id, WebGlCommand::Clear).await;
+        assert_eq!(
+            execute(&mut manager, context_id, WebGlCommand::ReadPixels { x: 0, y: 0, width: 1, height: 1 }).await,
+            WebGlResult::Pixels(vec![255, 0, 0, 255])
+        );
+
+        // Draw the bottom half green; rows are read from the bottom up
+        let quad: Vec<u8> = [-1.0f32, -1.0, 1.0, -1.0, -1.0, 0.0, 1.0, 0.0].iter().flat_map(|value| value.to_le_bytes()).collect();
+        execute(&mut manager, context_id, WebGlCommand::BufferData { buffer: 1, data: quad }).await;
+        execute(&mut manager, context_id, WebGlCommand::VertexAttribPointer { location: 0, buffer: 1, size: 2, stride: 0, offset: 0 }).await;
+        execute(&mut manager, context_id, WebGlCommand::LinkProgram {
+            program: 1,
+            vertex_source: VERTEX_SHADER.to_string(),
+            fragment_source: FRAGMENT_SHADER.to_string(),
+        }).await;
+        execute(&mut manager, context_id, WebGlCommand::UseProgram { program: Some(1) }).await;
+        execute(&mut manager, context_id, WebGlCommand::DrawArrays { mode: DrawMode::TriangleStrip, first: 0, count: 4 }).await;
+        let WebGlResult::Pixels(pixels) = execute(&mut manager, context_id, WebGlCommand::ReadPixels { x: 0, y: 0, width: 4, height: 2 }).await else {
+            panic!("ReadPixels returned no pixels");
+        };
+        assert_eq!(&pixels[..16], [0, 255, 0, 255].repeat(4));
+        assert_eq!(&pixels[16..], [255, 0, 0, 255].repeat(4));
+
+        assert!(manager.execute_webgl_command(context_id, WebGlCommand::UseProgram { program: Some(2) }).await.is_err());
+        assert!(manager.execute_webgl_command(context_id, WebGlCommand::LinkProgram {
+            program: 2,
+            vertex_source: "not wgsl".to_string(),
+            fragment_source: FRAGMENT_SHADER.to_string(),
+        }).await.is_err());
+
+        manager.destroy_webgl_context(context_id).await.unwrap();
+        assert_eq!(manager.get_stats().await.webgl_contexts, 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a GPU adapter, run with --ignored"]
+    async fn test_context_loss_and_restore_are_signaled() {
+        let mut manager = manager().await;
+        let mut events = manager.subscribe_webgl_events().await.unwrap();
+        let tab_id = TabId::new(3);
+        let context_id = manager.create_webgl_context(tab_id, "canvas", WebGlContextAttributes::default()).await.unwrap();
+
+        manager.lose_webgl_context(context_id).await.unwrap();
+        assert_eq!(events.recv().await.unwrap(), WebGlContextEvent::ContextLost { context_id, tab_id });
+        assert_eq!(manager.get_stats().await.webgl_contexts, 0);
+        assert_eq!(execute(&mut manager, context_id, WebGlCommand::Clear).await, WebGlResult::ContextLost);
+
+        manager.restore_webgl_context(context_id).await.unwrap();
+        assert_eq!(events.recv().await.unwrap(), WebGlContextEvent::ContextRestored { context_id, tab_id });
+        assert_eq!(manager.get_stats().await.webgl_contexts, 1);
+
+        // Restored contexts start over with default state
+        execute(&mut manager, context_id, WebGlCommand::Clear).await;
+        assert_eq!(
+            execute(&mut manager, context_id, WebGlCommand::ReadPixels { x: 0, y: 0, width: 1, height: 1 }).await,
+            WebGlResult::Pixels(vec![0, 0, 0, 0])
+        );
+    }
+}
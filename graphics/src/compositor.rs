@@ -150,6 +150,7 @@ pub enum WindowEvent {
     MouseRelease { button: u8, x: f32, y: f32 },
     MouseWheel { delta_x: f32, delta_y: f32 },
     Touch { id: u64, x: f32, y: f32, phase: TouchPhase },
+    ScaleFactorChanged { window_id: u64, scale_factor: f32 },
 }
 
 /// Touch phase
@@ -189,6 +190,120 @@ pub struct WindowConfig {
     pub hardware_acceleration: HardwareAcceleration,
 }
 
+/// Display a window can be placed on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorInfo {
+    /// Monitor ID, its position in the enumeration
+    pub id: u64,
+    /// Monitor name
+    pub name: String,
+    /// Physical pixels per logical pixel
+    pub dpi_scale: f32,
+    /// Monitor bounds in logical pixels, in desktop coordinates
+    pub bounds: Rectangle,
+    /// Whether this is the primary monitor
+    pub is_primary: bool,
+}
+
+impl MonitorInfo {
+    /// Convert a length in logical pixels to physical pixels, rounding up
+    pub fn to_physical(&self, logical: f32) -> u32 {
+        (logical * self.dpi_scale).ceil().max(0.0) as u32
+    }
+}
+
+impl Default for MonitorInfo {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            name: String::new(),
+            dpi_scale: 1.0,
+            bounds: Rectangle::new(0.0, 0.0, 1920.0, 1080.0),
+            is_primary: true,
+        }
+    }
+}
+
+/// Source of the monitors the window manager places windows on
+pub trait MonitorProvider: Send + Sync {
+    /// Get the connected monitors
+    fn monitors(&self) -> Vec<MonitorInfo>;
+}
+
+/// Monitor provider returning a fixed set of monitors, for tests and headless runs
+#[derive(Debug, Clone, Default)]
+pub struct MockMonitorProvider {
+    /// Monitors to report
+    pub monitors: Arc<RwLock<Vec<MonitorInfo>>>,
+}
+
+impl MockMonitorProvider {
+    /// Create a provider reporting the given monitors
+    pub fn new(monitors: Vec<MonitorInfo>) -> Self {
+        Self {
+            monitors: Arc::new(RwLock::new(monitors)),
+        }
+    }
+
+    /// Change the scale of a monitor, as when the user changes display settings
+    pub fn set_dpi_scale(&self, monitor_id: u64, dpi_scale: f32) {
+        if let Some(monitor) = self.monitors.write().iter_mut().find(|m| m.id == monitor_id) {
+            monitor.dpi_scale = dpi_scale;
+        }
+    }
+}
+
+impl MonitorProvider for MockMonitorProvider {
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        self.monitors.read().clone()
+    }
+}
+
+/// Monitor provider backed by the monitor handles of a winit event loop
+///
+/// The handles are queried on every enumeration, so scale and resolution
+/// changes show up without recreating the provider.
+pub struct WinitMonitorProvider {
+    /// Handles of the available monitors
+    handles: Vec<winit::monitor::MonitorHandle>,
+    /// Handle of the primary monitor, if the platform reports one
+    primary: Option<winit::monitor::MonitorHandle>,
+}
+
+impl WinitMonitorProvider {
+    /// Take the monitors of an event loop
+    pub fn new<T>(target: &winit::event_loop::EventLoopWindowTarget<T>) -> Self {
+        Self {
+            handles: target.available_monitors().collect(),
+            primary: target.primary_monitor(),
+        }
+    }
+}
+
+impl MonitorProvider for WinitMonitorProvider {
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        self.handles.iter()
+            .enumerate()
+            .map(|(index, handle)| {
+                let scale = handle.scale_factor();
+                let position = handle.position().to_logical::<f32>(scale);
+                let size = handle.size().to_logical::<f32>(scale);
+                MonitorInfo {
+                    id: index as u64,
+                    name: handle.name().unwrap_or_else(|| format!("Monitor {}", index + 1)),
+                    dpi_scale: scale as f32,
+                    bounds: Rectangle::new(position.x, position.y, size.width, size.height),
+                    // Without a reported primary monitor, the first one is used
+                    is_primary: match &self.primary {
+                        Some(primary) => primary == handle,
+                        None => index == 0,
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
 /// Compositor statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompositorStats {
@@ -445,6 +560,8 @@ pub struct WindowManager {
     active_window: Arc<RwLock<Option<u64>>>,
     /// Window event callbacks
     window_event_callbacks: Arc<RwLock<HashMap<u64, Vec<Box<dyn Fn(WindowEvent) + Send>>>>>,
+    /// Source of the connected monitors
+    monitor_provider: Box<dyn MonitorProvider>,
 }
 
 /// Window
@@ -456,8 +573,10 @@ pub struct Window {
     pub config: WindowConfig,
     /// Window state
     pub state: WindowState,
-    /// Window bounds
+    /// Window bounds in logical pixels
     pub bounds: Rectangle,
+    /// Monitor the window is on
+    pub current_monitor: MonitorInfo,
     /// Window content
     pub content: Option<Arc<Image>>,
     /// Window dirty region
@@ -1001,8 +1120,13 @@ impl VsyncManager {
 }
 
 impl WindowManager {
-    /// Create new window manager
+    /// Create new window manager, with a single 1920x1080 monitor at scale 1.0
     pub fn new() -> Self {
+        Self::with_monitor_provider(Box::new(MockMonitorProvider::new(vec![MonitorInfo::default()])))
+    }
+
+    /// Create new window manager placing windows on the monitors of a provider
+    pub fn with_monitor_provider(monitor_provider: Box<dyn MonitorProvider>) -> Self {
         let (sender, receiver) = mpsc::channel();
         Self {
             windows: Arc::new(RwLock::new(HashMap::new())),
@@ -1011,9 +1135,31 @@ impl WindowManager {
             window_event_receiver: receiver,
             active_window: Arc::new(RwLock::new(None)),
             window_event_callbacks: Arc::new(RwLock::new(HashMap::new())),
+            monitor_provider,
         }
     }
 
+    /// Get the connected monitors
+    pub fn enumerate_monitors(&self) -> Vec<MonitorInfo> {
+        self.monitor_provider.monitors()
+    }
+
+    /// Get the monitor showing most of an area, falling back to the primary monitor
+    fn monitor_for_bounds(&self, bounds: &Rectangle) -> MonitorInfo {
+        let monitors = self.enumerate_monitors();
+        let overlapping = monitors.iter()
+            .filter_map(|monitor| monitor.bounds.intersection(bounds).map(|area| (monitor, area.area())))
+            .filter(|(_, area)| *area > 0.0)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(monitor, _)| monitor);
+
+        overlapping
+            .or_else(|| monitors.iter().find(|monitor| monitor.is_primary))
+            .or_else(|| monitors.first())
+            .cloned()
+            .unwrap_or_default()
+    }
+
     /// Create new window
     pub fn create_window(&self, config: WindowConfig) -> u64 {
         let id = {
@@ -1023,16 +1169,18 @@ impl WindowManager {
             id
         };
         
+        let bounds = Rectangle::new(
+            config.x.unwrap_or(0) as f32,
+            config.y.unwrap_or(0) as f32,
+            config.width as f32,
+            config.height as f32,
+        );
         let window = Window {
             id,
             config: config.clone(),
             state: config.state,
-            bounds: Rectangle::new(
-                config.x.unwrap_or(0) as f32,
-                config.y.unwrap_or(0) as f32,
-                config.width as f32,
-                config.height as f32,
-            ),
+            bounds,
+            current_monitor: self.monitor_for_bounds(&bounds),
             content: None,
             dirty_region: None,
             created: SystemTime::now(),
@@ -1112,6 +1260,10 @@ impl WindowManager {
     /// Process window events
     pub fn process_events(&self) {
         while let Ok(event) = self.window_event_receiver.try_recv() {
+            if let WindowEvent::ScaleFactorChanged { window_id, scale_factor } = event {
+                self.apply_scale_factor(window_id, scale_factor);
+            }
+
             // Call event callbacks
             let callbacks = self.window_event_callbacks.read();
             if let Some(window_callbacks) = callbacks.get(&0) { // Global callbacks
@@ -1127,6 +1279,35 @@ impl WindowManager {
         self.window_event_sender.send(event)
             .map_err(|_| Error::graphics("Failed to send window event".to_string()))
     }
+
+    /// Move a window to the monitor it now shows on, at its new scale
+    ///
+    /// The logical bounds stay the same; the content is dropped and the
+    /// whole window marked dirty, so it is laid out and rasterized again at
+    /// the new physical size.
+    fn apply_scale_factor(&self, window_id: u64, scale_factor: f32) {
+        let mut windows = self.windows.write();
+        let Some(window) = windows.get_mut(&window_id) else {
+            return;
+        };
+
+        let mut monitor = self.monitor_for_bounds(&window.bounds);
+        monitor.dpi_scale = scale_factor;
+        window.current_monitor = monitor;
+        window.content = None;
+        window.dirty_region = Some(Rectangle::new(0.0, 0.0, window.bounds.width, window.bounds.height));
+        window.last_update = SystemTime::now();
+    }
+}
+
+impl Window {
+    /// Get the size of the window framebuffer in physical pixels
+    pub fn physical_size(&self) -> (u32, u32) {
+        (
+            self.current_monitor.to_physical(self.bounds.width),
+            self.current_monitor.to_physical(self.bounds.height),
+        )
+    }
 }
 
 impl Compositor {
@@ -1268,6 +1449,11 @@ impl Compositor {
     pub fn get_stats(&self) -> CompositorStats {
         self.stats.read().clone()
     }
+
+    /// Get the physical framebuffer size of a window, its logical size times the scale of its monitor
+    pub fn framebuffer_size(&self, window_id: u64) -> Option<(u32, u32)> {
+        self.window_manager.get_window(window_id).map(|window| window.physical_size())
+    }
 }
 
 impl CompositorStats {
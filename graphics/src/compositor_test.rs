@@ -0,0 +1,79 @@
+use crate::compositor::*;
+use crate::rendering::Rectangle;
+
+fn window_config(x: i32, width: u32, height: u32) -> WindowConfig {
+    WindowConfig {
+        title: "Test".to_string(),
+        width,
+        height,
+        x: Some(x),
+        y: Some(0),
+        state: WindowState::Normal,
+        resizable: true,
+        decorations: true,
+        transparent: false,
+        always_on_top: false,
+        vsync_mode: VsyncMode::Enabled,
+        hardware_acceleration: HardwareAcceleration::None,
+    }
+}
+
+fn two_monitors() -> MockMonitorProvider {
+    MockMonitorProvider::new(vec![
+        MonitorInfo {
+            id: 0,
+            name: "Standard".to_string(),
+            dpi_scale: 1.0,
+            bounds: Rectangle::new(0.0, 0.0, 1920.0, 1080.0),
+            is_primary: true,
+        },
+        MonitorInfo {
+            id: 1,
+            name: "HiDPI".to_string(),
+            dpi_scale: 2.0,
+            bounds: Rectangle::new(1920.0, 0.0, 1440.0, 900.0),
+            is_primary: false,
+        },
+    ])
+}
+
+#[test]
+fn test_enumerate_monitors() {
+    let manager = WindowManager::with_monitor_provider(Box::new(two_monitors()));
+    let monitors = manager.enumerate_monitors();
+
+    assert_eq!(monitors.len(), 2);
+    assert_eq!(monitors.iter().filter(|monitor| monitor.is_primary).count(), 1);
+    assert_eq!(monitors[1].dpi_scale, 2.0);
+}
+
+#[test]
+fn test_physical_size_doubles_on_hidpi_monitor() {
+    let manager = WindowManager::with_monitor_provider(Box::new(two_monitors()));
+    let standard = manager.create_window(window_config(100, 800, 600));
+    let hidpi = manager.create_window(window_config(2000, 800, 600));
+
+    let standard = manager.get_window(standard).unwrap();
+    let hidpi = manager.get_window(hidpi).unwrap();
+    assert_eq!(standard.current_monitor.id, 0);
+    assert_eq!(hidpi.current_monitor.id, 1);
+    assert_eq!(standard.physical_size(), (800, 600));
+    assert_eq!(hidpi.physical_size(), (1600, 1200));
+}
+
+#[test]
+fn test_scale_factor_changed_relayouts_window() {
+    let monitors = two_monitors();
+    let manager = WindowManager::with_monitor_provider(Box::new(monitors.clone()));
+    let id = manager.create_window(window_config(100, 800, 600));
+
+    monitors.set_dpi_scale(0, 2.0);
+    manager.send_event(WindowEvent::ScaleFactorChanged { window_id: id, scale_factor: 2.0 }).unwrap();
+    manager.process_events();
+
+    let window = manager.get_window(id).unwrap();
+    assert_eq!(window.current_monitor.dpi_scale, 2.0);
+    assert_eq!(window.bounds.width, 800.0);
+    assert_eq!(window.physical_size(), (1600, 1200));
+    assert_eq!(window.dirty_region, Some(Rectangle::new(0.0, 0.0, 800.0, 600.0)));
+}
@@ -3,6 +3,7 @@
 pub mod error;
 pub mod rendering;
 pub mod compositor;
+pub mod svg;
 
 pub use error::{Error, Result};
 pub use rendering::{
@@ -12,12 +13,14 @@ pub use rendering::{
     FontFamily, FontStyle, FontWeight, FontStyleType, FontStretch,
     TextMetrics, TextAlign, TextBaseline, ImageFormat, Image,
     CSSValue, CSSUnit, CSSRule, CSSStylesheet,
-    RenderingContext, GraphicsPrimitives, TextRenderer, ImageDecoder, CSSRenderer,
+    RenderingContext, GraphicsPrimitives, TextRenderer, TextCommand, GlyphAtlas, AtlasRegion,
+    ImageDecoder, CSSRenderer, DisplayCommand,
 };
+pub use svg::SvgRenderer;
 pub use compositor::{
     LayerType, LayerBlendMode, LayerState, Layer, FrameTiming,
     VsyncMode, HardwareAcceleration, WindowState, WindowEvent, TouchPhase,
-    WindowConfig, CompositorStats, LayerManager, LayerEvent,
+    WindowConfig, MonitorInfo, MonitorProvider, MockMonitorProvider, WinitMonitorProvider, CompositorStats, LayerManager, LayerEvent,
     HardwareAccelerator, GpuContext, Shader, Texture, Buffer,
     ShaderType, TextureFormat, BufferType, UniformValue, DrawCall, DrawMode, GpuCapabilities,
     VsyncManager, WindowManager, Window, Compositor, FrameEvent,
@@ -27,3 +30,5 @@ pub use compositor::{
 mod rendering_test;
 #[cfg(test)]
 mod compositor_test;
+#[cfg(test)]
+mod svg_test;
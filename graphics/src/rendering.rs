@@ -1,4 +1,7 @@
+use crate::compositor::Texture;
 use crate::error::{Error, Result};
+use crate::svg::SvgRenderer;
+use dom::typography::{self, FontManager, GlyphCache, GlyphCacheKey, RasterizedGlyph};
 use std::collections::HashMap;
 use std::sync::Arc;
 use parking_lot::RwLock;
@@ -360,6 +363,67 @@ pub struct TextRenderer {
     fonts: Arc<RwLock<HashMap<String, FontFamily>>>,
     /// Text cache
     text_cache: Arc<RwLock<HashMap<String, Arc<Image>>>>,
+    /// Installed fonts and glyph rasterizer
+    font_manager: Arc<RwLock<FontManager>>,
+    /// GPU texture atlas of the rendered glyphs
+    glyph_atlas: Arc<RwLock<GlyphAtlas>>,
+}
+
+/// Text drawing command
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextCommand {
+    pub text: String,
+    /// Top-left corner of the text
+    pub position: Point,
+    pub font_family: String,
+    pub font_size: f32,
+    pub color: Color,
+}
+
+/// Drawing command of a display list
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisplayCommand {
+    /// Filled rectangle
+    DrawRectangle(Rectangle, Color),
+    /// Filled circle
+    DrawCircle(Circle, Color),
+    /// Path filled and stroked with a style
+    DrawPath(Vec<PathSegment>, DrawingStyle),
+    DrawText(TextCommand),
+    /// Decoded image scaled into a rectangle
+    DrawImage(Arc<Image>, Rectangle),
+}
+
+/// Region of a glyph in the glyph atlas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Texture atlas of rasterized glyphs
+///
+/// Reads glyphs from the glyph cache shared with the font manager, and packs
+/// them into rows of a single-channel texture.
+pub struct GlyphAtlas {
+    /// Glyph cache shared with the font manager
+    glyph_cache: GlyphCache,
+    /// Atlas dimensions
+    width: u32,
+    height: u32,
+    /// Coverage values, one byte per pixel
+    data: Vec<u8>,
+    /// Packed glyphs
+    regions: HashMap<GlyphCacheKey, AtlasRegion>,
+    /// Position of the next glyph
+    cursor_x: u32,
+    cursor_y: u32,
+    /// Height of the current row
+    row_height: u32,
+    /// Whether the texture needs to be uploaded again
+    dirty: bool,
 }
 
 /// Image decoder
@@ -368,6 +432,8 @@ pub struct ImageDecoder {
     supported_formats: Vec<ImageFormat>,
     /// Decoder cache
     decoder_cache: Arc<RwLock<HashMap<String, Arc<Image>>>>,
+    /// Converter of SVG images, which stay vector graphics
+    svg_renderer: SvgRenderer,
 }
 
 /// CSS renderer
@@ -1109,11 +1175,26 @@ impl GraphicsPrimitives {
 }
 
 impl TextRenderer {
+    /// Size of the glyph atlas texture
+    const ATLAS_SIZE: u32 = 1024;
+
     /// Create new text renderer
+    ///
+    /// The installed fonts are enumerated once, when the renderer is created.
     pub fn new() -> Self {
+        let mut font_manager = FontManager::new();
+        font_manager.load_system_fonts();
+        Self::with_font_manager(font_manager)
+    }
+
+    /// Create a text renderer that uses the fonts of a font manager
+    pub fn with_font_manager(font_manager: FontManager) -> Self {
+        let glyph_atlas = GlyphAtlas::new(font_manager.glyph_cache(), Self::ATLAS_SIZE, Self::ATLAS_SIZE);
         Self {
             fonts: Arc::new(RwLock::new(HashMap::new())),
             text_cache: Arc::new(RwLock::new(HashMap::new())),
+            font_manager: Arc::new(RwLock::new(font_manager)),
+            glyph_atlas: Arc::new(RwLock::new(glyph_atlas)),
         }
     }
 
@@ -1122,38 +1203,67 @@ impl TextRenderer {
         self.fonts.write().insert(family.name.clone(), family);
     }
 
+    /// Get the font manager
+    pub fn font_manager(&self) -> Arc<RwLock<FontManager>> {
+        self.font_manager.clone()
+    }
+
+    /// Get the glyph atlas
+    pub fn glyph_atlas(&self) -> Arc<RwLock<GlyphAtlas>> {
+        self.glyph_atlas.clone()
+    }
+
     /// Measure text
+    ///
+    /// Text in a family without installed fonts is measured approximately.
     pub fn measure_text(&self, text: &str, font_size: f32, font_family: &str) -> TextMetrics {
-        // TODO: Implement proper text measurement
-        // This is a simplified implementation
-        let char_width = font_size * 0.6; // Approximate character width
-        let width = text.len() as f32 * char_width;
-        let height = font_size;
-        
-        TextMetrics {
-            width,
-            height,
-            baseline: height * 0.8,
-            ascent: height * 0.8,
-            descent: height * 0.2,
-            leading: height * 0.2,
+        match self.rasterize_text(text, font_size, font_family) {
+            Ok(glyphs) => Self::glyph_metrics(&glyphs, font_size),
+            Err(_) => {
+                let char_width = font_size * 0.6; // Approximate character width
+                let width = text.len() as f32 * char_width;
+                let height = font_size;
+                
+                TextMetrics {
+                    width,
+                    height,
+                    baseline: height * 0.8,
+                    ascent: height * 0.8,
+                    descent: height * 0.2,
+                    leading: height * 0.2,
+                }
+            }
         }
     }
 
     /// Render text to image
     pub fn render_text(&self, text: &str, font_size: f32, font_family: &str, color: Color) -> Result<Arc<Image>> {
-        // TODO: Implement proper text rendering
-        // This is a simplified implementation that creates a placeholder image
-        let metrics = self.measure_text(text, font_size, font_family);
-        let width = metrics.width.ceil() as u32;
-        let height = metrics.height.ceil() as u32;
-        
+        let glyphs = self.rasterize_text(text, font_size, font_family)?;
+        let metrics = Self::glyph_metrics(&glyphs, font_size);
+        let width = metrics.width.ceil().max(1.0) as u32;
+        let height = metrics.height.ceil().max(1.0) as u32;
         let mut data = vec![0; (width * height * 4) as usize];
-        let rgba = color.to_rgba();
         
-        // Fill with text color (simplified - just a solid rectangle)
-        for pixel in data.chunks_exact_mut(4) {
-            pixel.copy_from_slice(&rgba);
+        let mut pen_x = 0.0;
+        for glyph in &glyphs {
+            let origin_x = pen_x as i32 + glyph.bearing_x;
+            let origin_y = metrics.baseline as i32 - glyph.bearing_y;
+            for row in 0..glyph.height as i32 {
+                for column in 0..glyph.width as i32 {
+                    let (x, y) = (origin_x + column, origin_y + row);
+                    if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+                        continue;
+                    }
+                    let coverage = glyph.bitmap[(row * glyph.width as i32 + column) as usize];
+                    let pixel = ((y as u32 * width + x as u32) * 4) as usize;
+                    let alpha = (coverage as u32 * color.a as u32 / 255) as u8;
+                    // Overlapping glyphs keep the strongest coverage
+                    if alpha > data[pixel + 3] {
+                        data[pixel..pixel + 4].copy_from_slice(&[color.r, color.g, color.b, alpha]);
+                    }
+                }
+            }
+            pen_x += glyph.advance;
         }
         
         Ok(Arc::new(Image {
@@ -1164,6 +1274,178 @@ impl TextRenderer {
             channels: 4,
         }))
     }
+
+    /// Render a text command onto an RGBA image
+    pub fn render_text_command(&self, command: &TextCommand, target: &mut Image) -> Result<()> {
+        if target.channels != 4 {
+            return Err(Error::graphics("Text can only be drawn onto RGBA images".to_string()));
+        }
+        let text = self.render_text(&command.text, command.font_size, &command.font_family, command.color)?;
+        
+        let left = command.position.x.round() as i64;
+        let top = command.position.y.round() as i64;
+        for row in 0..text.height as i64 {
+            for column in 0..text.width as i64 {
+                let (x, y) = (left + column, top + row);
+                if x < 0 || y < 0 || x >= target.width as i64 || y >= target.height as i64 {
+                    continue;
+                }
+                let source = ((row * text.width as i64 + column) * 4) as usize;
+                let alpha = text.data[source + 3] as f32 / 255.0;
+                if alpha == 0.0 {
+                    continue;
+                }
+                let destination = ((y * target.width as i64 + x) * 4) as usize;
+                let pixel = &mut target.data[destination..destination + 4];
+                let below = Color::rgba(pixel[0], pixel[1], pixel[2], pixel[3]);
+                let mut blended = below.blend(&command.color, alpha);
+                blended.a = (alpha * 255.0 + pixel[3] as f32 * (1.0 - alpha)).round() as u8;
+                pixel.copy_from_slice(&blended.to_rgba());
+            }
+        }
+        
+        Ok(())
+    }
+
+    /// Rasterize the glyphs of a text and add them to the glyph atlas
+    fn rasterize_text(&self, text: &str, font_size: f32, font_family: &str) -> Result<Vec<RasterizedGlyph>> {
+        let font_manager = self.font_manager.read();
+        let face = font_manager.match_font(
+            &typography::FontFamily(font_family.to_string()),
+            typography::FontWeight(400),
+            typography::FontStyle::Normal,
+            typography::FontStretch::Normal,
+        ).ok_or_else(|| Error::graphics(format!("No font installed for family '{}'", font_family)))?;
+        
+        let size = (font_size * 64.0).round() as u32;
+        let mut atlas = self.glyph_atlas.write();
+        text.chars()
+            .map(|character| {
+                let glyph = font_manager.rasterize_glyph(face, character, font_size)
+                    .map_err(|e| Error::graphics(e.to_string()))?;
+                atlas.region(&(face.cache_key(), character, size))?;
+                Ok(glyph)
+            })
+            .collect()
+    }
+
+    /// Get the metrics of a run of glyphs
+    fn glyph_metrics(glyphs: &[RasterizedGlyph], font_size: f32) -> TextMetrics {
+        let width = glyphs.iter().map(|glyph| glyph.advance).sum();
+        let ascent = glyphs.iter()
+            .map(|glyph| glyph.bearing_y as f32)
+            .fold(font_size * 0.8, f32::max);
+        let descent = glyphs.iter()
+            .map(|glyph| glyph.height as f32 - glyph.bearing_y as f32)
+            .fold(font_size * 0.2, f32::max);
+        
+        TextMetrics {
+            width,
+            height: ascent + descent,
+            baseline: ascent,
+            ascent,
+            descent,
+            leading: font_size * 0.2,
+        }
+    }
+}
+
+impl GlyphAtlas {
+    /// Create an empty glyph atlas
+    pub fn new(glyph_cache: GlyphCache, width: u32, height: u32) -> Self {
+        Self {
+            glyph_cache,
+            width,
+            height,
+            data: vec![0; (width * height) as usize],
+            regions: HashMap::new(),
+            cursor_x: 0,
+            cursor_y: 0,
+            row_height: 0,
+            dirty: false,
+        }
+    }
+
+    /// Get the glyph cache the atlas reads from
+    pub fn glyph_cache(&self) -> &GlyphCache {
+        &self.glyph_cache
+    }
+
+    /// Get the atlas dimensions
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Get the coverage values of the atlas
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get the region of a glyph, packing it into the atlas if necessary
+    ///
+    /// Returns `None` for glyphs that have not been rasterized.
+    pub fn region(&mut self, key: &GlyphCacheKey) -> Result<Option<AtlasRegion>> {
+        if let Some(region) = self.regions.get(key) {
+            return Ok(Some(*region));
+        }
+        let Some(glyph) = self.glyph_cache.get(key) else {
+            return Ok(None);
+        };
+        
+        if self.cursor_x + glyph.width > self.width {
+            self.cursor_x = 0;
+            self.cursor_y += self.row_height;
+            self.row_height = 0;
+        }
+        if glyph.width > self.width || self.cursor_y + glyph.height > self.height {
+            return Err(Error::graphics("Glyph atlas is full".to_string()));
+        }
+        
+        let region = AtlasRegion {
+            x: self.cursor_x,
+            y: self.cursor_y,
+            width: glyph.width,
+            height: glyph.height,
+        };
+        for row in 0..glyph.height {
+            let source = (row * glyph.width) as usize;
+            let destination = ((region.y + row) * self.width + region.x) as usize;
+            self.data[destination..destination + glyph.width as usize]
+                .copy_from_slice(&glyph.bitmap[source..source + glyph.width as usize]);
+        }
+        
+        self.cursor_x += glyph.width;
+        self.row_height = self.row_height.max(glyph.height);
+        self.regions.insert(key.clone(), region);
+        self.dirty = true;
+        Ok(Some(region))
+    }
+
+    /// Upload the atlas to an `R8` GPU texture if glyphs were added
+    pub fn upload(&mut self, texture: &mut dyn Texture) -> Result<()> {
+        if self.dirty {
+            texture.update(&self.data, 0, 0, self.width, self.height)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+}
+
+impl ImageFormat {
+    /// Get the format of a MIME type, ignoring its parameters
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        let essence = mime_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        match essence.as_str() {
+            "image/png" => Some(ImageFormat::PNG),
+            "image/jpeg" | "image/jpg" => Some(ImageFormat::JPEG),
+            "image/gif" => Some(ImageFormat::GIF),
+            "image/webp" => Some(ImageFormat::WebP),
+            "image/bmp" => Some(ImageFormat::BMP),
+            "image/x-icon" | "image/vnd.microsoft.icon" => Some(ImageFormat::ICO),
+            "image/svg+xml" => Some(ImageFormat::SVG),
+            _ => None,
+        }
+    }
 }
 
 impl ImageDecoder {
@@ -1180,6 +1462,7 @@ impl ImageDecoder {
                 ImageFormat::SVG,
             ],
             decoder_cache: Arc::new(RwLock::new(HashMap::new())),
+            svg_renderer: SvgRenderer::new(),
         }
     }
 
@@ -1203,6 +1486,19 @@ impl ImageDecoder {
         Ok(image)
     }
 
+    /// Get the display commands drawing an image of a MIME type into a rectangle
+    ///
+    /// SVG images become the commands drawing their shapes and text, other
+    /// images are decoded and drawn scaled.
+    pub fn decode_for_display(&self, data: &[u8], mime_type: &str, rect: Rectangle) -> Result<Vec<DisplayCommand>> {
+        let format = ImageFormat::from_mime_type(mime_type)
+            .ok_or_else(|| Error::graphics(format!("Unsupported image type: {}", mime_type)))?;
+        if format == ImageFormat::SVG {
+            return self.svg_renderer.render(data, rect);
+        }
+        Ok(vec![DisplayCommand::DrawImage(self.decode(data, format)?, rect)])
+    }
+
     /// Decode image from file
     pub fn decode_file(&self, path: &PathBuf) -> Result<Arc<Image>> {
         // TODO: Implement file-based image decoding
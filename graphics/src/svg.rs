@@ -0,0 +1,342 @@
+//! SVG images
+//!
+//! Documents are parsed by usvg, which resolves styles, `use` references and
+//! transforms and turns every shape into a path. Paths that are still an
+//! axis-aligned rectangle or a circle once placed in the viewport become
+//! rectangle and circle commands, other paths keep their segments. Clip
+//! paths, masks, filters and nested raster images are not drawn.
+
+use crate::error::{Error, Result};
+use crate::rendering::{
+    Circle, Color, DisplayCommand, DrawingStyle, FillRule, LineCap, LineJoin, PathSegment, Point, Rectangle, TextCommand,
+};
+use usvg::tiny_skia_path;
+
+/// Distance, relative to the size of a shape, within which path points count as on its outline
+const SHAPE_TOLERANCE: f32 = 0.01;
+
+/// Converter of SVG images into display commands
+pub struct SvgRenderer {
+    /// Parser options, holding the fonts text is laid out with
+    options: usvg::Options<'static>,
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SvgRenderer {
+    /// Create an SVG renderer laying text out with the system fonts
+    pub fn new() -> Self {
+        let mut options = usvg::Options::default();
+        options.fontdb_mut().load_system_fonts();
+        Self { options }
+    }
+
+    /// Get the intrinsic width and height of an SVG image
+    pub fn intrinsic_size(&self, svg_bytes: &[u8]) -> Result<(f32, f32)> {
+        let size = self.parse(svg_bytes)?.size();
+        Ok((size.width(), size.height()))
+    }
+
+    /// Convert an SVG image into the commands drawing it into a viewport
+    ///
+    /// The image is scaled uniformly to fit the viewport and centered in it,
+    /// as `preserveAspectRatio="xMidYMid meet"` places it.
+    pub fn render(&self, svg_bytes: &[u8], viewport: Rectangle) -> Result<Vec<DisplayCommand>> {
+        let tree = self.parse(svg_bytes)?;
+        let size = tree.size();
+        let scale = (viewport.width / size.width()).min(viewport.height / size.height());
+        let placement = tiny_skia_path::Transform::from_row(
+            scale,
+            0.0,
+            0.0,
+            scale,
+            viewport.x + (viewport.width - size.width() * scale) / 2.0,
+            viewport.y + (viewport.height - size.height() * scale) / 2.0,
+        );
+
+        let mut commands = Vec::new();
+        convert_group(tree.root(), placement, 1.0, &mut commands);
+        Ok(commands)
+    }
+
+    fn parse(&self, svg_bytes: &[u8]) -> Result<usvg::Tree> {
+        usvg::Tree::from_data(svg_bytes, &self.options)
+            .map_err(|e| Error::rendering(format!("Failed to parse SVG image: {}", e)))
+    }
+}
+
+fn convert_group(group: &usvg::Group, placement: tiny_skia_path::Transform, opacity: f32, commands: &mut Vec<DisplayCommand>) {
+    let opacity = opacity * group.opacity().get();
+    for node in group.children() {
+        match node {
+            usvg::Node::Group(group) => convert_group(group, placement, opacity, commands),
+            usvg::Node::Path(path) => convert_path(path, placement, opacity, commands),
+            usvg::Node::Text(text) => convert_text(text, placement, opacity, commands),
+            usvg::Node::Image(_) => {}
+        }
+    }
+}
+
+fn convert_path(path: &usvg::Path, placement: tiny_skia_path::Transform, opacity: f32, commands: &mut Vec<DisplayCommand>) {
+    if !path.is_visible() {
+        return;
+    }
+    let transform = placement.pre_concat(path.abs_transform());
+    let Some(data) = path.data().clone().transform(transform) else {
+        return;
+    };
+    let segments = path_segments(&data);
+
+    let fill_color = path.fill().and_then(|fill| paint_color(fill.paint(), fill.opacity().get() * opacity));
+    let stroke = path.stroke().and_then(|stroke| {
+        paint_color(stroke.paint(), stroke.opacity().get() * opacity).map(|color| (stroke, color))
+    });
+
+    match (fill_color, stroke) {
+        (None, None) => {}
+        (Some(color), None) => {
+            if let Some(rect) = as_rectangle(&segments) {
+                commands.push(DisplayCommand::DrawRectangle(rect, color));
+            } else if let Some(circle) = as_circle(&segments) {
+                commands.push(DisplayCommand::DrawCircle(circle, color));
+            } else {
+                let style = DrawingStyle {
+                    fill_color: Some(color),
+                    fill_rule: fill_rule(path),
+                    ..DrawingStyle::default()
+                };
+                commands.push(DisplayCommand::DrawPath(segments, style));
+            }
+        }
+        (fill_color, Some((stroke, stroke_color))) => {
+            let style = DrawingStyle {
+                fill_color,
+                fill_rule: fill_rule(path),
+                stroke_color: Some(stroke_color),
+                stroke_width: stroke.width().get() * transform_scale(transform),
+                line_cap: match stroke.linecap() {
+                    usvg::LineCap::Butt => LineCap::Butt,
+                    usvg::LineCap::Round => LineCap::Round,
+                    usvg::LineCap::Square => LineCap::Square,
+                },
+                line_join: match stroke.linejoin() {
+                    usvg::LineJoin::Miter | usvg::LineJoin::MiterClip => LineJoin::Miter,
+                    usvg::LineJoin::Round => LineJoin::Round,
+                    usvg::LineJoin::Bevel => LineJoin::Bevel,
+                },
+                miter_limit: stroke.miterlimit().get(),
+                ..DrawingStyle::default()
+            };
+            commands.push(DisplayCommand::DrawPath(segments, style));
+        }
+    }
+}
+
+/// Draw every chunk of a text element, a run of text starting at its own position
+///
+/// Text laid out as one chunk is placed at the top-left corner of its
+/// laid out glyphs. Otherwise chunks start at their `x` and `y`, with the
+/// top of the text a font size above the baseline.
+fn convert_text(text: &usvg::Text, placement: tiny_skia_path::Transform, opacity: f32, commands: &mut Vec<DisplayCommand>) {
+    let transform = placement.pre_concat(text.abs_transform());
+    let scale = transform_scale(transform);
+    let single_chunk = text.chunks().len() == 1;
+
+    for chunk in text.chunks() {
+        let Some(span) = chunk.spans().first() else {
+            continue;
+        };
+        let Some(color) = span.fill().and_then(|fill| paint_color(fill.paint(), fill.opacity().get() * opacity)) else {
+            continue;
+        };
+        if !span.is_visible() {
+            continue;
+        }
+
+        let font_size = span.font_size().get();
+        let position = if single_chunk {
+            let bounds = text.abs_bounding_box();
+            map_point(placement, bounds.left(), bounds.top())
+        } else {
+            map_point(transform, chunk.x().unwrap_or(0.0), chunk.y().unwrap_or(0.0) - font_size)
+        };
+        let font_family = match span.font().families().first() {
+            Some(usvg::FontFamily::Named(name)) => name.clone(),
+            Some(generic) => generic.to_string(),
+            None => "serif".to_string(),
+        };
+
+        commands.push(DisplayCommand::DrawText(TextCommand {
+            text: chunk.text().to_string(),
+            position,
+            font_family,
+            font_size: font_size * scale,
+            color,
+        }));
+    }
+}
+
+/// Get the color to paint with, gradients painting with their first stop
+fn paint_color(paint: &usvg::Paint, opacity: f32) -> Option<Color> {
+    let (color, opacity) = match paint {
+        usvg::Paint::Color(color) => (*color, opacity),
+        usvg::Paint::LinearGradient(gradient) => {
+            let stop = gradient.stops().first()?;
+            (stop.color(), opacity * stop.opacity().get())
+        }
+        usvg::Paint::RadialGradient(gradient) => {
+            let stop = gradient.stops().first()?;
+            (stop.color(), opacity * stop.opacity().get())
+        }
+        usvg::Paint::Pattern(_) => return None,
+    };
+    Some(Color::rgba(color.red, color.green, color.blue, (opacity.clamp(0.0, 1.0) * 255.0).round() as u8))
+}
+
+fn fill_rule(path: &usvg::Path) -> FillRule {
+    match path.fill().map(|fill| fill.rule()) {
+        Some(usvg::FillRule::EvenOdd) => FillRule::EvenOdd,
+        _ => FillRule::NonZero,
+    }
+}
+
+/// Factor a transform scales lengths by, on average over both axes
+fn transform_scale(transform: tiny_skia_path::Transform) -> f32 {
+    (transform.sx * transform.sy - transform.kx * transform.ky).abs().sqrt()
+}
+
+fn map_point(transform: tiny_skia_path::Transform, x: f32, y: f32) -> Point {
+    let mut point = tiny_skia_path::Point::from_xy(x, y);
+    transform.map_point(&mut point);
+    Point::new(point.x, point.y)
+}
+
+/// Convert path data, turning quadratic curves into cubic ones
+fn path_segments(data: &tiny_skia_path::Path) -> Vec<PathSegment> {
+    let mut segments = Vec::new();
+    let mut current = Point::new(0.0, 0.0);
+    let mut start = current;
+
+    for segment in data.segments() {
+        match segment {
+            tiny_skia_path::PathSegment::MoveTo(point) => {
+                current = Point::new(point.x, point.y);
+                start = current;
+                segments.push(PathSegment::MoveTo(current));
+            }
+            tiny_skia_path::PathSegment::LineTo(point) => {
+                current = Point::new(point.x, point.y);
+                segments.push(PathSegment::LineTo(current));
+            }
+            tiny_skia_path::PathSegment::QuadTo(control, point) => {
+                let end = Point::new(point.x, point.y);
+                let control1 = Point::new(current.x + (control.x - current.x) * 2.0 / 3.0, current.y + (control.y - current.y) * 2.0 / 3.0);
+                let control2 = Point::new(end.x + (control.x - end.x) * 2.0 / 3.0, end.y + (control.y - end.y) * 2.0 / 3.0);
+                segments.push(PathSegment::CurveTo(control1, control2, end));
+                current = end;
+            }
+            tiny_skia_path::PathSegment::CubicTo(control1, control2, point) => {
+                current = Point::new(point.x, point.y);
+                segments.push(PathSegment::CurveTo(
+                    Point::new(control1.x, control1.y),
+                    Point::new(control2.x, control2.y),
+                    current,
+                ));
+            }
+            tiny_skia_path::PathSegment::Close => {
+                current = start;
+                segments.push(PathSegment::ClosePath);
+            }
+        }
+    }
+    segments
+}
+
+/// Get the rectangle a path outlines, if it is a closed axis-aligned rectangle
+fn as_rectangle(segments: &[PathSegment]) -> Option<Rectangle> {
+    let corners = match segments {
+        [PathSegment::MoveTo(a), PathSegment::LineTo(b), PathSegment::LineTo(c), PathSegment::LineTo(d), rest @ ..] => {
+            match rest {
+                [PathSegment::ClosePath] => [*a, *b, *c, *d],
+                [PathSegment::LineTo(e), PathSegment::ClosePath] if e.distance_to(a) <= SHAPE_TOLERANCE => [*a, *b, *c, *d],
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    let left = corners.iter().map(|p| p.x).fold(f32::INFINITY, f32::min);
+    let right = corners.iter().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max);
+    let top = corners.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+    let bottom = corners.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max);
+    let tolerance = SHAPE_TOLERANCE * (right - left).max(bottom - top).max(1.0);
+
+    // Each side runs along an axis, so consecutive corners share an x or a y
+    let axis_aligned = (0..4).all(|i| {
+        let (from, to) = (corners[i], corners[(i + 1) % 4]);
+        (from.x - to.x).abs() <= tolerance || (from.y - to.y).abs() <= tolerance
+    });
+    let on_bounds = corners.iter().all(|p| {
+        ((p.x - left).abs() <= tolerance || (p.x - right).abs() <= tolerance)
+            && ((p.y - top).abs() <= tolerance || (p.y - bottom).abs() <= tolerance)
+    });
+    if !axis_aligned || !on_bounds {
+        return None;
+    }
+    Some(Rectangle::new(left, top, right - left, bottom - top))
+}
+
+/// Get the circle a path outlines, if it is closed and made of curves all on one circle
+fn as_circle(segments: &[PathSegment]) -> Option<Circle> {
+    let (PathSegment::MoveTo(start), rest) = segments.split_first()? else {
+        return None;
+    };
+    let curves = match rest {
+        [curves @ .., PathSegment::ClosePath] => curves,
+        curves => curves,
+    };
+    if curves.len() < 4 {
+        return None;
+    }
+
+    // Points on the outline: curve ends and curve midpoints
+    let mut points = vec![*start];
+    let mut current = *start;
+    for segment in curves {
+        let PathSegment::CurveTo(control1, control2, end) = segment else {
+            return None;
+        };
+        points.push(cubic_point(current, *control1, *control2, *end, 0.5));
+        points.push(*end);
+        current = *end;
+    }
+
+    let left = points.iter().map(|p| p.x).fold(f32::INFINITY, f32::min);
+    let right = points.iter().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max);
+    let top = points.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+    let bottom = points.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max);
+    let center = Point::new((left + right) / 2.0, (top + bottom) / 2.0);
+    let radius = (right - left) / 2.0;
+    let tolerance = SHAPE_TOLERANCE * radius.max(1.0);
+
+    if current.distance_to(start) > tolerance || ((bottom - top) / 2.0 - radius).abs() > tolerance {
+        return None;
+    }
+    if points.iter().any(|p| (p.distance_to(&center) - radius).abs() > tolerance) {
+        return None;
+    }
+    Some(Circle::new(center, radius))
+}
+
+fn cubic_point(start: Point, control1: Point, control2: Point, end: Point, t: f32) -> Point {
+    let u = 1.0 - t;
+    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+    Point::new(
+        a * start.x + b * control1.x + c * control2.x + d * end.x,
+        a * start.y + b * control1.y + c * control2.y + d * end.y,
+    )
+}
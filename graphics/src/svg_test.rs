@@ -0,0 +1,101 @@
+use crate::rendering::*;
+use crate::svg::SvgRenderer;
+
+const CHART: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50" viewBox="0 0 100 50">
+  <rect x="10" y="5" width="30" height="20" fill="#ff0000"/>
+  <circle cx="70" cy="25" r="10" fill="blue" fill-opacity="0.5"/>
+  <path d="M 0 50 L 50 0 L 100 50" fill="none" stroke="black" stroke-width="2"/>
+  <text x="10" y="45" font-family="DejaVu Sans" font-size="10" fill="green">Chart</text>
+</svg>"##;
+
+/// Bounding box of the shapes the commands draw
+fn bounding_box(commands: &[DisplayCommand]) -> Rectangle {
+    let mut points = Vec::new();
+    for command in commands {
+        match command {
+            DisplayCommand::DrawRectangle(rect, _) => {
+                points.push(Point::new(rect.x, rect.y));
+                points.push(Point::new(rect.x + rect.width, rect.y + rect.height));
+            }
+            DisplayCommand::DrawCircle(circle, _) => {
+                points.push(Point::new(circle.center.x - circle.radius, circle.center.y - circle.radius));
+                points.push(Point::new(circle.center.x + circle.radius, circle.center.y + circle.radius));
+            }
+            DisplayCommand::DrawPath(segments, _) => {
+                for segment in segments {
+                    match segment {
+                        PathSegment::MoveTo(point) | PathSegment::LineTo(point) | PathSegment::CurveTo(_, _, point) => {
+                            points.push(*point)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let left = points.iter().map(|p| p.x).fold(f32::INFINITY, f32::min);
+    let top = points.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+    let right = points.iter().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max);
+    let bottom = points.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max);
+    Rectangle::new(left, top, right - left, bottom - top)
+}
+
+#[test]
+fn test_svg_elements_become_display_commands() {
+    let renderer = SvgRenderer::new();
+    let commands = renderer.render(CHART.as_bytes(), Rectangle::new(0.0, 0.0, 200.0, 100.0)).unwrap();
+
+    assert_eq!(commands[0], DisplayCommand::DrawRectangle(Rectangle::new(20.0, 10.0, 60.0, 40.0), Color::rgb(255, 0, 0)));
+    match &commands[1] {
+        DisplayCommand::DrawCircle(circle, color) => {
+            assert!(circle.center.distance_to(&Point::new(140.0, 50.0)) < 0.01);
+            assert!((circle.radius - 20.0).abs() < 0.01);
+            assert_eq!(*color, Color::rgba(0, 0, 255, 128));
+        }
+        command => panic!("expected a circle, got {:?}", command),
+    }
+    match &commands[2] {
+        DisplayCommand::DrawPath(segments, style) => {
+            assert_eq!(segments[0], PathSegment::MoveTo(Point::new(0.0, 100.0)));
+            assert_eq!(style.fill_color, None);
+            assert_eq!(style.stroke_color, Some(Color::rgb(0, 0, 0)));
+            assert_eq!(style.stroke_width, 4.0);
+        }
+        command => panic!("expected a path, got {:?}", command),
+    }
+    if let Some(DisplayCommand::DrawText(text)) = commands.get(3) {
+        assert_eq!(text.text, "Chart");
+        assert_eq!(text.font_size, 20.0);
+        assert_eq!(text.color, Color::rgb(0, 128, 0));
+    }
+
+    assert_eq!(bounding_box(&commands), Rectangle::new(0.0, 0.0, 200.0, 100.0));
+}
+
+#[test]
+fn test_svg_is_centered_in_the_viewport() {
+    let renderer = SvgRenderer::new();
+    assert_eq!(renderer.intrinsic_size(CHART.as_bytes()).unwrap(), (100.0, 50.0));
+
+    // A square viewport leaves room above and below the wider image
+    let commands = renderer.render(CHART.as_bytes(), Rectangle::new(10.0, 0.0, 200.0, 200.0)).unwrap();
+    assert_eq!(bounding_box(&commands), Rectangle::new(10.0, 50.0, 200.0, 100.0));
+
+    assert!(renderer.render(b"<svg", Rectangle::new(0.0, 0.0, 10.0, 10.0)).is_err());
+}
+
+#[test]
+fn test_image_decoder_delegates_svg() {
+    assert_eq!(ImageFormat::from_mime_type("image/svg+xml; charset=utf-8"), Some(ImageFormat::SVG));
+    assert_eq!(ImageFormat::from_mime_type("text/html"), None);
+
+    let decoder = ImageDecoder::new();
+    let viewport = Rectangle::new(0.0, 0.0, 100.0, 50.0);
+    let commands = decoder.decode_for_display(CHART.as_bytes(), "image/svg+xml", viewport).unwrap();
+    assert!(matches!(commands[0], DisplayCommand::DrawRectangle(..)));
+
+    let commands = decoder.decode_for_display(&[0x89, b'P', b'N', b'G'], "image/png", viewport).unwrap();
+    assert!(matches!(&commands[..], [DisplayCommand::DrawImage(_, rect)] if *rect == viewport));
+}
@@ -12,7 +12,7 @@ pub struct Position {
 }
 
 impl Position {
-    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+    pub const fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
         Self {
             start,
             end,
@@ -394,7 +394,7 @@ pub struct FunctionDeclaration {
     pub params: Vec<Pattern>,
     pub body: BlockStatement,
     pub generator: bool,
-    pub async: bool,
+    pub r#async: bool,
     pub position: Position,
 }
 
@@ -940,6 +940,8 @@ impl AstNode for Identifier {
 pub enum Literal {
     String(String),
     Number(f64),
+    /// BigInt literal, as decimal digits
+    BigInt(String),
     Boolean(bool),
     Null,
     RegExp(RegExpLiteral),
@@ -950,7 +952,8 @@ impl AstNode for Literal {
     fn position(&self) -> &Position {
         // This is a simplified implementation
         // In a real implementation, each literal would store its position
-        &Position::new(0, 0, 0, 0)
+        const UNKNOWN: Position = Position::new(0, 0, 0, 0);
+        &UNKNOWN
     }
 
     fn node_type(&self) -> &str {
@@ -1095,8 +1098,8 @@ impl AstNode for ObjectProperty {
 /// Property
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Property {
-    pub key: Expression,
-    pub value: Expression,
+    pub key: Box<Expression>,
+    pub value: Box<Expression>,
     pub kind: PropertyKind,
     pub method: bool,
     pub shorthand: bool,
@@ -1125,7 +1128,7 @@ pub enum PropertyKind {
 /// Spread element
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpreadElement {
-    pub argument: Expression,
+    pub argument: Box<Expression>,
     pub position: Position,
 }
 
@@ -1146,7 +1149,7 @@ pub struct FunctionExpression {
     pub params: Vec<Pattern>,
     pub body: BlockStatement,
     pub generator: bool,
-    pub async: bool,
+    pub r#async: bool,
     pub position: Position,
 }
 
@@ -1165,7 +1168,7 @@ impl AstNode for FunctionExpression {
 pub struct ArrowFunctionExpression {
     pub params: Vec<Pattern>,
     pub body: ArrowFunctionBody,
-    pub async: bool,
+    pub r#async: bool,
     pub position: Position,
 }
 
@@ -1190,7 +1193,7 @@ pub enum ArrowFunctionBody {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClassExpression {
     pub id: Option<Identifier>,
-    pub super_class: Option<Expression>,
+    pub super_class: Option<Box<Expression>>,
     pub body: ClassBody,
     pub position: Position,
 }
@@ -1208,7 +1211,7 @@ impl AstNode for ClassExpression {
 /// Tagged template expression
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaggedTemplateExpression {
-    pub tag: Expression,
+    pub tag: Box<Expression>,
     pub quasi: TemplateLiteral,
     pub position: Position,
 }
@@ -1226,8 +1229,8 @@ impl AstNode for TaggedTemplateExpression {
 /// Member expression
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemberExpression {
-    pub object: Expression,
-    pub property: Expression,
+    pub object: Box<Expression>,
+    pub property: Box<Expression>,
     pub computed: bool,
     pub optional: bool,
     pub position: Position,
@@ -1280,7 +1283,7 @@ impl AstNode for MetaProperty {
 /// Call expression
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallExpression {
-    pub callee: Expression,
+    pub callee: Box<Expression>,
     pub arguments: Vec<ExpressionOrSpread>,
     pub optional: bool,
     pub position: Position,
@@ -1306,7 +1309,7 @@ pub enum ExpressionOrSpread {
 /// New expression
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewExpression {
-    pub callee: Expression,
+    pub callee: Box<Expression>,
     pub arguments: Vec<ExpressionOrSpread>,
     pub position: Position,
 }
@@ -1325,7 +1328,7 @@ impl AstNode for NewExpression {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateExpression {
     pub operator: UpdateOperator,
-    pub argument: Expression,
+    pub argument: Box<Expression>,
     pub prefix: bool,
     pub position: Position,
 }
@@ -1350,7 +1353,7 @@ pub enum UpdateOperator {
 /// Await expression
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AwaitExpression {
-    pub argument: Expression,
+    pub argument: Box<Expression>,
     pub position: Position,
 }
 
@@ -1368,7 +1371,7 @@ impl AstNode for AwaitExpression {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnaryExpression {
     pub operator: UnaryOperator,
-    pub argument: Expression,
+    pub argument: Box<Expression>,
     pub prefix: bool,
     pub position: Position,
 }
@@ -1399,8 +1402,8 @@ pub enum UnaryOperator {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BinaryExpression {
     pub operator: BinaryOperator,
-    pub left: Expression,
-    pub right: Expression,
+    pub left: Box<Expression>,
+    pub right: Box<Expression>,
     pub position: Position,
 }
 
@@ -1447,8 +1450,8 @@ pub enum BinaryOperator {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogicalExpression {
     pub operator: LogicalOperator,
-    pub left: Expression,
-    pub right: Expression,
+    pub left: Box<Expression>,
+    pub right: Box<Expression>,
     pub position: Position,
 }
 
@@ -1473,9 +1476,9 @@ pub enum LogicalOperator {
 /// Conditional expression
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConditionalExpression {
-    pub test: Expression,
-    pub consequent: Expression,
-    pub alternate: Expression,
+    pub test: Box<Expression>,
+    pub consequent: Box<Expression>,
+    pub alternate: Box<Expression>,
     pub position: Position,
 }
 
@@ -1492,7 +1495,7 @@ impl AstNode for ConditionalExpression {
 /// Yield expression
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct YieldExpression {
-    pub argument: Option<Expression>,
+    pub argument: Option<Box<Expression>>,
     pub delegate: bool,
     pub position: Position,
 }
@@ -1511,8 +1514,8 @@ impl AstNode for YieldExpression {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AssignmentExpression {
     pub operator: AssignmentOperator,
-    pub left: Pattern,
-    pub right: Expression,
+    pub left: Box<Pattern>,
+    pub right: Box<Expression>,
     pub position: Position,
 }
 
@@ -1616,46 +1619,70 @@ impl AstNode for ObjectPattern {
 /// Object pattern property
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ObjectPatternProperty {
-    Property(ObjectPatternProperty),
-    RestElement(RestElement),
+    Single(SingleProperty),
+    Rest(RestProperty),
 }
 
 impl AstNode for ObjectPatternProperty {
     fn position(&self) -> &Position {
         match self {
-            ObjectPatternProperty::Property(prop) => prop.position(),
-            ObjectPatternProperty::RestElement(rest) => rest.position(),
+            ObjectPatternProperty::Single(prop) => prop.position(),
+            ObjectPatternProperty::Rest(rest) => rest.position(),
         }
     }
 
     fn node_type(&self) -> &str {
         match self {
-            ObjectPatternProperty::Property(_) => "ObjectPatternProperty",
-            ObjectPatternProperty::RestElement(_) => "RestElement",
+            ObjectPatternProperty::Single(_) => "Property",
+            ObjectPatternProperty::Rest(_) => "RestElement",
         }
     }
 }
 
-/// Object pattern property
+/// Property of an object pattern bound to a pattern
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ObjectPatternProperty {
-    pub key: Expression,
+pub struct SingleProperty {
+    pub key: PropertyKey,
     pub value: Pattern,
-    pub computed: bool,
     pub shorthand: bool,
     pub position: Position,
 }
 
-impl AstNode for ObjectPatternProperty {
+impl AstNode for SingleProperty {
     fn position(&self) -> &Position {
         &self.position
     }
 
     fn node_type(&self) -> &str {
-        "ObjectPatternProperty"
+        "Property"
     }
 }
 
+/// Rest property of an object pattern
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestProperty {
+    pub argument: Pattern,
+    pub position: Position,
+}
+
+impl AstNode for RestProperty {
+    fn position(&self) -> &Position {
+        &self.position
+    }
+
+    fn node_type(&self) -> &str {
+        "RestElement"
+    }
+}
+
+/// Key of an object pattern property
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PropertyKey {
+    Identifier(Identifier),
+    Literal(Literal),
+    Computed(Box<Expression>),
+}
+
 /// Array pattern
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArrayPattern {
@@ -1676,7 +1703,7 @@ impl AstNode for ArrayPattern {
 /// Rest element
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RestElement {
-    pub argument: Pattern,
+    pub argument: Box<Pattern>,
     pub position: Position,
 }
 
@@ -1693,8 +1720,8 @@ impl AstNode for RestElement {
 /// Assignment pattern
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AssignmentPattern {
-    pub left: Pattern,
-    pub right: Expression,
+    pub left: Box<Pattern>,
+    pub right: Box<Expression>,
     pub position: Position,
 }
 
@@ -1,7 +1,9 @@
 use crate::error::{Error, Result};
-use crate::ast::{FunctionDeclaration, FunctionExpression, ArrowFunctionExpression, Expression, Statement};
-use std::collections::HashMap;
+use crate::ast::{FunctionDeclaration, Expression, ExpressionOrSpread, Pattern, Statement};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
 use std::sync::Arc;
+use parking_lot::Mutex;
 use tokio::sync::{RwLock, mpsc};
 use std::future::Future;
 use std::pin::Pin;
@@ -59,111 +61,238 @@ pub struct AsyncFunctionValue {
     pub environment: HashMap<String, Value>,
 }
 
+/// Callback run after the current task, before the next macrotask
+pub type Microtask = Box<dyn FnOnce() + Send>;
+
+/// Queue of pending microtasks, shared by an event loop and its promises
+#[derive(Clone, Default)]
+pub struct MicrotaskQueue {
+    /// Queued microtasks in FIFO order
+    tasks: Arc<Mutex<VecDeque<Microtask>>>,
+}
+
+impl MicrotaskQueue {
+    /// Create an empty microtask queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a microtask to the back of the queue
+    pub fn enqueue(&self, task: Microtask) {
+        self.tasks.lock().push_back(task);
+    }
+
+    /// Run microtasks until the queue is empty
+    ///
+    /// Microtasks enqueued while draining run in the same drain. Returns the
+    /// number of microtasks run.
+    pub fn drain(&self) -> usize {
+        let mut count = 0;
+        loop {
+            let task = self.tasks.lock().pop_front();
+            match task {
+                Some(task) => {
+                    task();
+                    count += 1;
+                }
+                None => return count,
+            }
+        }
+    }
+
+    /// Get the number of queued microtasks
+    pub fn len(&self) -> usize {
+        self.tasks.lock().len()
+    }
+
+    /// Check if no microtasks are queued
+    pub fn is_empty(&self) -> bool {
+        self.tasks.lock().is_empty()
+    }
+}
+
+impl fmt::Debug for MicrotaskQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MicrotaskQueue").field("len", &self.len()).finish()
+    }
+}
+
+/// Reaction to a promise settling, run as a microtask
+type PromiseReaction = Box<dyn FnOnce(PromiseState) + Send>;
+
+/// Shared state of a promise
+struct PromiseInner {
+    /// Promise state
+    state: PromiseState,
+    /// Reactions waiting for the promise to settle
+    reactions: Vec<PromiseReaction>,
+}
+
 /// Promise implementation
-#[derive(Debug, Clone)]
+///
+/// Cloning a promise gives another handle to the same promise.
+#[derive(Clone)]
 pub struct Promise {
-    /// Promise state
-    pub state: PromiseState,
-    /// Fulfillment handlers
-    pub on_fulfilled: Vec<Box<dyn FnOnce(Value) -> Result<Value> + Send + Sync>>,
-    /// Rejection handlers
-    pub on_rejected: Vec<Box<dyn FnOnce(Value) -> Result<Value> + Send + Sync>>,
-    /// Promise executor
-    pub executor: Option<PromiseExecutor>,
+    /// Shared promise state
+    inner: Arc<Mutex<PromiseInner>>,
+    /// Queue that reactions are scheduled on
+    microtasks: MicrotaskQueue,
 }
 
 /// Promise executor function
 pub type PromiseExecutor = Box<dyn FnOnce(Box<dyn FnOnce(Value) + Send + Sync>, Box<dyn FnOnce(Value) + Send + Sync>) + Send + Sync>;
 
 impl Promise {
-    /// Create a new pending promise
+    /// Create a new pending promise with its own microtask queue
     pub fn new() -> Self {
+        Self::with_queue(MicrotaskQueue::new())
+    }
+
+    /// Create a new pending promise whose reactions run on `microtasks`
+    pub fn with_queue(microtasks: MicrotaskQueue) -> Self {
         Self {
-            state: PromiseState::Pending,
-            on_fulfilled: Vec::new(),
-            on_rejected: Vec::new(),
-            executor: None,
+            inner: Arc::new(Mutex::new(PromiseInner {
+                state: PromiseState::Pending,
+                reactions: Vec::new(),
+            })),
+            microtasks,
         }
     }
 
     /// Create a new promise with an executor
     pub fn with_executor(executor: PromiseExecutor) -> Self {
-        Self {
-            state: PromiseState::Pending,
-            on_fulfilled: Vec::new(),
-            on_rejected: Vec::new(),
-            executor: Some(executor),
-        }
+        Self::with_queue(MicrotaskQueue::new()).run_executor(executor)
+    }
+
+    /// Run an executor straight away
+    ///
+    /// Resolving or rejecting queues the settlement as a microtask; once one
+    /// has settled the promise, later ones are ignored.
+    fn run_executor(self, executor: PromiseExecutor) -> Self {
+        let fulfilled = self.clone();
+        let rejected = self.clone();
+        executor(
+            Box::new(move |value| {
+                let microtasks = fulfilled.microtasks.clone();
+                microtasks.enqueue(Box::new(move || {
+                    let _ = fulfilled.fulfill(value);
+                }));
+            }),
+            Box::new(move |reason| {
+                let microtasks = rejected.microtasks.clone();
+                microtasks.enqueue(Box::new(move || {
+                    let _ = rejected.reject(reason);
+                }));
+            }),
+        );
+        self
+    }
+
+    /// Get the current state of the promise
+    pub fn state(&self) -> PromiseState {
+        self.inner.lock().state.clone()
+    }
+
+    /// Get the microtask queue the promise's reactions run on
+    pub fn microtask_queue(&self) -> &MicrotaskQueue {
+        &self.microtasks
     }
 
     /// Fulfill the promise with a value
-    pub fn fulfill(&mut self, value: Value) -> Result<()> {
-        match self.state {
-            PromiseState::Pending => {
-                self.state = PromiseState::Fulfilled(value.clone());
-                
-                // Execute fulfillment handlers
-                for handler in self.on_fulfilled.drain(..) {
-                    handler(value.clone())?;
-                }
-                
-                Ok(())
+    pub fn fulfill(&self, value: Value) -> Result<()> {
+        self.settle(PromiseState::Fulfilled(value))
+    }
+
+    /// Reject the promise with a reason
+    pub fn reject(&self, reason: Value) -> Result<()> {
+        self.settle(PromiseState::Rejected(reason))
+    }
+
+    /// Settle the promise and schedule its reactions
+    fn settle(&self, state: PromiseState) -> Result<()> {
+        let reactions = {
+            let mut inner = self.inner.lock();
+            if !matches!(inner.state, PromiseState::Pending) {
+                return Err(Error::parsing("Promise already settled".to_string()));
             }
-            _ => Err(Error::parsing("Promise already settled".to_string())),
+            inner.state = state.clone();
+            std::mem::take(&mut inner.reactions)
+        };
+
+        for reaction in reactions {
+            let state = state.clone();
+            self.microtasks.enqueue(Box::new(move || reaction(state)));
         }
+        Ok(())
     }
 
-    /// Reject the promise with a reason
-    pub fn reject(&mut self, reason: Value) -> Result<()> {
-        match self.state {
-            PromiseState::Pending => {
-                self.state = PromiseState::Rejected(reason.clone());
-                
-                // Execute rejection handlers
-                for handler in self.on_rejected.drain(..) {
-                    handler(reason.clone())?;
-                }
-                
-                Ok(())
+    /// Run a reaction once the promise has settled
+    fn react(&self, reaction: PromiseReaction) {
+        let mut inner = self.inner.lock();
+        match &inner.state {
+            PromiseState::Pending => inner.reactions.push(reaction),
+            state => {
+                let state = state.clone();
+                self.microtasks.enqueue(Box::new(move || reaction(state)));
             }
-            _ => Err(Error::parsing("Promise already settled".to_string())),
         }
     }
 
-    /// Add a fulfillment handler
-    pub fn then<F>(&mut self, handler: F) -> Result<()>
-    where
-        F: FnOnce(Value) -> Result<Value> + Send + Sync + 'static,
-    {
-        match &self.state {
-            PromiseState::Pending => {
-                self.on_fulfilled.push(Box::new(handler));
-                Ok(())
+    /// Settle the promise with the result of a handler
+    ///
+    /// A returned promise is adopted, so the promise settles the same way once
+    /// the returned one does.
+    fn resolve_with(&self, result: Result<Value>) {
+        match result {
+            Ok(Value::Promise(returned)) => {
+                let target = self.clone();
+                returned.react(Box::new(move |state| {
+                    let _ = target.settle(state);
+                }));
+            }
+            Ok(value) => {
+                let _ = self.fulfill(value);
             }
-            PromiseState::Fulfilled(value) => {
-                handler(value.clone())?;
-                Ok(())
+            Err(error) => {
+                let _ = self.reject(Value::String(error.to_string()));
             }
-            PromiseState::Rejected(_) => Ok(()),
         }
     }
 
+    /// Add fulfillment and rejection handlers
+    ///
+    /// The handlers run as microtasks once the promise settles. The returned
+    /// promise settles with the result of whichever handler runs.
+    pub fn then<F, R>(&self, on_fulfilled: F, on_rejected: R) -> Promise
+    where
+        F: FnOnce(Value) -> Result<Value> + Send + 'static,
+        R: FnOnce(Value) -> Result<Value> + Send + 'static,
+    {
+        let derived = Promise::with_queue(self.microtasks.clone());
+        let target = derived.clone();
+        self.react(Box::new(move |state| {
+            let result = match state {
+                PromiseState::Fulfilled(value) => on_fulfilled(value),
+                PromiseState::Rejected(reason) => on_rejected(reason),
+                PromiseState::Pending => return,
+            };
+            target.resolve_with(result);
+        }));
+        derived
+    }
+
     /// Add a rejection handler
-    pub fn catch<F>(&mut self, handler: F) -> Result<()>
+    pub fn catch<R>(&self, on_rejected: R) -> Promise
     where
-        F: FnOnce(Value) -> Result<Value> + Send + Sync + 'static,
+        R: FnOnce(Value) -> Result<Value> + Send + 'static,
     {
-        match &self.state {
-            PromiseState::Pending => {
-                self.on_rejected.push(Box::new(handler));
-                Ok(())
-            }
-            PromiseState::Fulfilled(_) => Ok(()),
-            PromiseState::Rejected(reason) => {
-                handler(reason.clone())?;
-                Ok(())
-            }
-        }
+        self.then(Ok, on_rejected)
+    }
+}
+
+impl fmt::Debug for Promise {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Promise").field("state", &self.state()).finish()
     }
 }
 
@@ -206,7 +335,10 @@ pub struct AwaitPoint {
 }
 
 /// Event loop for async execution
+#[derive(Clone)]
 pub struct EventLoop {
+    /// Microtasks, run to exhaustion before the next macrotask
+    microtasks: MicrotaskQueue,
     /// Task queue
     task_queue: mpsc::UnboundedSender<Task>,
     /// Promise queue
@@ -216,18 +348,27 @@ pub struct EventLoop {
 }
 
 /// Task in the event loop
-#[derive(Debug)]
 pub struct Task {
     /// Task ID
     pub id: String,
     /// Task function
-    pub function: Box<dyn Future<Output = Result<Value>> + Send + Sync>,
+    pub function: Pin<Box<dyn Future<Output = Result<Value>> + Send + Sync>>,
     /// Task priority
     pub priority: TaskPriority,
     /// Task state
     pub state: TaskState,
 }
 
+impl fmt::Debug for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Task")
+            .field("id", &self.id)
+            .field("priority", &self.priority)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
 /// Task priority
 #[derive(Debug, Clone, PartialEq)]
 pub enum TaskPriority {
@@ -247,25 +388,23 @@ pub enum TaskState {
     /// Task is running
     Running,
     /// Task is completed
-    Completed(Result<Value>),
-    /// Task failed
-    Failed(Error),
+    Completed(Value),
+    /// Task failed, with the error message
+    Failed(String),
 }
 
 impl AsyncContext {
     /// Create a new async context
     pub fn new() -> Self {
-        let (task_sender, _task_receiver) = mpsc::unbounded_channel();
-        let (promise_sender, _promise_receiver) = mpsc::unbounded_channel();
-        
+        Self::with_event_loop(EventLoop::new())
+    }
+
+    /// Create a new async context that schedules work on `event_loop`
+    pub fn with_event_loop(event_loop: EventLoop) -> Self {
         Self {
             stack: Vec::new(),
             promise_queue: Vec::new(),
-            event_loop: EventLoop {
-                task_queue: task_sender,
-                promise_queue: promise_sender,
-                running_tasks: Arc::new(RwLock::new(HashMap::new())),
-            },
+            event_loop,
             global_env: HashMap::new(),
         }
     }
@@ -273,7 +412,7 @@ impl AsyncContext {
     /// Execute an async function
     pub async fn execute_async_function(&mut self, func: AsyncFunctionValue, args: Vec<Value>) -> Result<Value> {
         // Create execution frame
-        let frame = ExecutionFrame {
+        let mut frame = ExecutionFrame {
             function: func.clone(),
             statement_index: 0,
             locals: HashMap::new(),
@@ -283,8 +422,8 @@ impl AsyncContext {
 
         // Set up arguments
         for (i, param) in func.func.params.iter().enumerate() {
-            if let Some(arg) = args.get(i) {
-                frame.locals.insert(param.name.clone(), arg.clone());
+            if let (Pattern::Identifier(ident), Some(arg)) = (param, args.get(i)) {
+                frame.locals.insert(ident.name.clone(), arg.clone());
             }
         }
 
@@ -304,7 +443,7 @@ impl AsyncContext {
                 
                 match statement {
                     Statement::Expression(expr_stmt) => {
-                        let result = self.evaluate_expression(&expr_stmt.expression, &mut frame).await?;
+                        let result = self.evaluate_expression(&expr_stmt.expression, &frame.locals).await?;
                         
                         // Check if result is a promise that needs to be awaited
                         if let Value::Promise(promise) = result {
@@ -324,7 +463,7 @@ impl AsyncContext {
                     }
                     Statement::Return(return_stmt) => {
                         if let Some(expr) = &return_stmt.argument {
-                            let result = self.evaluate_expression(expr, &mut frame).await?;
+                            let result = self.evaluate_expression(expr, &frame.locals).await?;
                             frame.return_value = Some(result);
                         }
                         break;
@@ -348,60 +487,49 @@ impl AsyncContext {
     }
 
     /// Evaluate an expression
-    async fn evaluate_expression(&self, expr: &Expression, frame: &mut ExecutionFrame) -> Result<Value> {
+    async fn evaluate_expression(&self, expr: &Expression, locals: &HashMap<String, Value>) -> Result<Value> {
         match expr {
             Expression::Await(await_expr) => {
-                let promise_value = self.evaluate_expression(&await_expr.argument, frame).await?;
+                let promise_value = Box::pin(self.evaluate_expression(&await_expr.argument, locals)).await?;
                 
-                if let Value::Promise(mut promise) = promise_value {
-                    // Wait for promise to resolve
-                    match promise.state {
-                        PromiseState::Fulfilled(value) => Ok(value),
-                        PromiseState::Rejected(reason) => Err(Error::parsing(format!("Promise rejected: {:?}", reason))),
-                        PromiseState::Pending => {
-                            // Create a new promise that resolves when the awaited promise resolves
-                            let (resolve_sender, resolve_receiver) = tokio::sync::oneshot::channel();
-                            
-                            promise.then(Box::new(move |value| {
-                                let _ = resolve_sender.send(value);
-                                Ok(Value::Undefined)
-                            }))?;
-                            
-                            promise.catch(Box::new(move |reason| {
-                                let _ = resolve_sender.send(Value::Rejected(reason));
-                                Ok(Value::Undefined)
-                            }))?;
-                            
-                            // Wait for resolution
-                            match resolve_receiver.await {
-                                Ok(value) => Ok(value),
-                                Err(_) => Err(Error::parsing("Promise resolution failed".to_string())),
+                if let Value::Promise(promise) = promise_value {
+                    // Run pending reactions so promise chains can settle, then
+                    // yield until the awaited promise has settled
+                    loop {
+                        promise.microtask_queue().drain();
+                        match promise.state() {
+                            PromiseState::Fulfilled(value) => return Ok(value),
+                            PromiseState::Rejected(reason) => {
+                                return Err(Error::parsing(format!("Promise rejected: {:?}", reason)));
                             }
+                            PromiseState::Pending => tokio::task::yield_now().await,
                         }
                     }
                 } else {
-                    // If not a promise, wrap in resolved promise
-                    Ok(Value::Promise(Promise {
-                        state: PromiseState::Fulfilled(promise_value),
-                        on_fulfilled: Vec::new(),
-                        on_rejected: Vec::new(),
-                        executor: None,
-                    }))
+                    // Awaiting a non-promise value yields the value itself
+                    Ok(promise_value)
                 }
             }
             Expression::Call(call_expr) => {
-                let callee = self.evaluate_expression(&call_expr.callee, frame).await?;
+                let callee = Box::pin(self.evaluate_expression(&call_expr.callee, locals)).await?;
                 let mut args = Vec::new();
                 
                 for arg in &call_expr.arguments {
-                    args.push(self.evaluate_expression(arg, frame).await?);
+                    match arg {
+                        ExpressionOrSpread::Expression(arg) => {
+                            args.push(Box::pin(self.evaluate_expression(arg, locals)).await?);
+                        }
+                        ExpressionOrSpread::Spread(_) => {
+                            return Err(Error::parsing("Spread arguments are not supported in async functions".to_string()));
+                        }
+                    }
                 }
                 
-                self.call_function(callee, args).await
+                Box::pin(self.call_function(callee, args)).await
             }
             Expression::Identifier(ident) => {
                 // Look up in locals, then globals
-                if let Some(value) = frame.locals.get(&ident.name) {
+                if let Some(value) = locals.get(&ident.name) {
                     Ok(value.clone())
                 } else if let Some(value) = self.global_env.get(&ident.name) {
                     Ok(value.clone())
@@ -425,6 +553,14 @@ impl AsyncContext {
         }
     }
 
+    /// Evaluate an expression at the top level of a module
+    ///
+    /// Top-level `await` is allowed, so awaited promises are settled before
+    /// the value is returned.
+    pub async fn evaluate_top_level(&self, expr: &Expression, bindings: &HashMap<String, Value>) -> Result<Value> {
+        self.evaluate_expression(expr, bindings).await
+    }
+
     /// Call a function
     async fn call_function(&self, func: Value, args: Vec<Value>) -> Result<Value> {
         match func {
@@ -432,7 +568,7 @@ impl AsyncContext {
                 // Create new execution frame for function
                 let mut frame = ExecutionFrame {
                     function: AsyncFunctionValue {
-                        func: func_value.func,
+                        func: func_value.func.clone(),
                         environment: func_value.environment,
                     },
                     statement_index: 0,
@@ -443,8 +579,8 @@ impl AsyncContext {
 
                 // Set up arguments
                 for (i, param) in func_value.func.params.iter().enumerate() {
-                    if let Some(arg) = args.get(i) {
-                        frame.locals.insert(param.name.clone(), arg.clone());
+                    if let (Pattern::Identifier(ident), Some(arg)) = (param, args.get(i)) {
+                        frame.locals.insert(ident.name.clone(), arg.clone());
                     }
                 }
 
@@ -469,27 +605,21 @@ impl AsyncContext {
 
     /// Create a new promise
     pub fn create_promise(&self, executor: PromiseExecutor) -> Promise {
-        Promise::with_executor(executor)
+        Promise::with_queue(self.event_loop.microtask_queue().clone()).run_executor(executor)
     }
 
     /// Resolve a value to a promise
     pub fn resolve(&self, value: Value) -> Promise {
-        Promise {
-            state: PromiseState::Fulfilled(value),
-            on_fulfilled: Vec::new(),
-            on_rejected: Vec::new(),
-            executor: None,
-        }
+        let promise = self.event_loop.create_promise();
+        let _ = promise.fulfill(value);
+        promise
     }
 
     /// Reject a value to a promise
     pub fn reject(&self, reason: Value) -> Promise {
-        Promise {
-            state: PromiseState::Rejected(reason),
-            on_fulfilled: Vec::new(),
-            on_rejected: Vec::new(),
-            executor: None,
-        }
+        let promise = self.event_loop.create_promise();
+        let _ = promise.reject(reason);
+        promise
     }
 
     /// Add a task to the event loop
@@ -517,6 +647,7 @@ impl EventLoop {
         let (promise_sender, _promise_receiver) = mpsc::unbounded_channel();
         
         Self {
+            microtasks: MicrotaskQueue::new(),
             task_queue: task_sender,
             promise_queue: promise_sender,
             running_tasks: Arc::new(RwLock::new(HashMap::new())),
@@ -525,11 +656,35 @@ impl EventLoop {
 
     /// Run the event loop
     pub async fn run(&self) -> Result<()> {
-        // In a real implementation, this would process tasks and promises
-        // For now, just return success
+        // Macrotasks aren't processed yet, but any queued microtasks must
+        // run before the loop moves on
+        self.drain_microtasks();
         Ok(())
     }
 
+    /// Queue a microtask, such as a promise reaction
+    pub fn enqueue_microtask(&self, task: Microtask) {
+        self.microtasks.enqueue(task);
+    }
+
+    /// Run queued microtasks until the queue is exhausted
+    ///
+    /// Returns the number of microtasks run, including ones enqueued by other
+    /// microtasks during the drain.
+    pub fn drain_microtasks(&self) -> usize {
+        self.microtasks.drain()
+    }
+
+    /// Get the microtask queue
+    pub fn microtask_queue(&self) -> &MicrotaskQueue {
+        &self.microtasks
+    }
+
+    /// Create a pending promise whose reactions run on this event loop
+    pub fn create_promise(&self) -> Promise {
+        Promise::with_queue(self.microtasks.clone())
+    }
+
     /// Add a microtask (high priority)
     pub fn add_microtask<F>(&self, task: F) -> Result<()>
     where
@@ -537,7 +692,7 @@ impl EventLoop {
     {
         let task = Task {
             id: uuid::Uuid::new_v4().to_string(),
-            function: Box::new(task),
+            function: Box::pin(task),
             priority: TaskPriority::High,
             state: TaskState::Pending,
         };
@@ -554,7 +709,7 @@ impl EventLoop {
     {
         let task = Task {
             id: uuid::Uuid::new_v4().to_string(),
-            function: Box::new(task),
+            function: Box::pin(task),
             priority: TaskPriority::Normal,
             state: TaskState::Pending,
         };
@@ -576,9 +731,10 @@ pub struct AsyncAwaitSystem {
 impl AsyncAwaitSystem {
     /// Create a new async/await system
     pub fn new() -> Self {
+        let event_loop = EventLoop::new();
         Self {
-            context: AsyncContext::new(),
-            event_loop: EventLoop::new(),
+            context: AsyncContext::with_event_loop(event_loop.clone()),
+            event_loop,
         }
     }
 
@@ -602,11 +758,26 @@ impl AsyncAwaitSystem {
         self.context.reject(reason)
     }
 
+    /// Evaluate an expression at the top level of a module
+    pub async fn evaluate_top_level(&self, expr: &Expression, bindings: &HashMap<String, Value>) -> Result<Value> {
+        self.context.evaluate_top_level(expr, bindings).await
+    }
+
     /// Run the event loop
     pub async fn run_event_loop(&self) -> Result<()> {
         self.event_loop.run().await
     }
 
+    /// Run queued microtasks until the queue is exhausted
+    pub fn drain_microtasks(&self) -> usize {
+        self.event_loop.drain_microtasks()
+    }
+
+    /// Get the event loop
+    pub fn get_event_loop(&self) -> &EventLoop {
+        &self.event_loop
+    }
+
     /// Get the async context
     pub fn get_context(&self) -> &AsyncContext {
         &self.context
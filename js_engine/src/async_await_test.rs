@@ -1,7 +1,15 @@
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::async_await::{AsyncAwaitSystem, Promise, PromiseState, Value, AsyncFunctionValue, FunctionDeclaration};
+    use crate::async_await::{AsyncAwaitSystem, AsyncContext, EventLoop, Promise, PromiseState, Value};
+    use crate::error::{Error, Result};
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    /// Rejection handler that keeps the derived promise rejected
+    fn rethrow(reason: Value) -> Result<Value> {
+        Err(Error::parsing(format!("Uncaught (in promise) {:?}", reason)))
+    }
 
     #[tokio::test]
     async fn test_promise_creation() {
@@ -12,7 +20,11 @@ mod tests {
             resolve(Value::String("Hello, World!".to_string()));
         }));
         
-        assert!(matches!(promise.state, PromiseState::Pending));
+        assert!(matches!(promise.state(), PromiseState::Pending));
+        
+        // The resolution settles the promise once microtasks run
+        system.drain_microtasks();
+        assert!(matches!(promise.state(), PromiseState::Fulfilled(Value::String(s)) if s == "Hello, World!"));
     }
 
     #[tokio::test]
@@ -22,9 +34,9 @@ mod tests {
         // Test resolving a promise
         let promise = system.resolve(Value::Number(42.0));
         
-        match promise.state {
+        match promise.state() {
             PromiseState::Fulfilled(value) => {
-                assert!(matches!(value, Value::Number(n) if *n == 42.0));
+                assert!(matches!(value, Value::Number(n) if n == 42.0));
             }
             _ => panic!("Expected fulfilled promise"),
         }
@@ -37,7 +49,7 @@ mod tests {
         // Test rejecting a promise
         let promise = system.reject(Value::String("Error message".to_string()));
         
-        match promise.state {
+        match promise.state() {
             PromiseState::Rejected(reason) => {
                 assert!(matches!(reason, Value::String(s) if s == "Error message"));
             }
@@ -47,12 +59,12 @@ mod tests {
 
     #[tokio::test]
     async fn test_promise_fulfillment() {
-        let mut promise = Promise::new();
+        let promise = Promise::new();
         
         // Test fulfilling a promise
         promise.fulfill(Value::Boolean(true)).unwrap();
         
-        match promise.state {
+        match promise.state() {
             PromiseState::Fulfilled(value) => {
                 assert!(matches!(value, Value::Boolean(true)));
             }
@@ -62,12 +74,12 @@ mod tests {
 
     #[tokio::test]
     async fn test_promise_rejection_handling() {
-        let mut promise = Promise::new();
+        let promise = Promise::new();
         
         // Test rejecting a promise
         promise.reject(Value::String("Test error".to_string())).unwrap();
         
-        match promise.state {
+        match promise.state() {
             PromiseState::Rejected(reason) => {
                 assert!(matches!(reason, Value::String(s) if s == "Test error"));
             }
@@ -77,38 +89,86 @@ mod tests {
 
     #[tokio::test]
     async fn test_promise_then_handler() {
-        let mut promise = Promise::new();
-        let mut handler_called = false;
-        
+        let promise = Promise::new();
+        let handler_called = Arc::new(Mutex::new(false));
+
         // Add a then handler
-        promise.then(Box::new(move |value| {
-            handler_called = true;
+        let called = Arc::clone(&handler_called);
+        promise.then(move |value| {
+            *called.lock() = true;
             assert!(matches!(value, Value::String(s) if s == "test"));
             Ok(Value::Undefined)
-        })).unwrap();
-        
-        // Fulfill the promise
+        }, rethrow);
+
+        // Fulfill the promise; the handler runs as a microtask
         promise.fulfill(Value::String("test".to_string())).unwrap();
-        
-        assert!(handler_called);
+        assert!(!*handler_called.lock());
+
+        promise.microtask_queue().drain();
+        assert!(*handler_called.lock());
     }
 
     #[tokio::test]
     async fn test_promise_catch_handler() {
-        let mut promise = Promise::new();
-        let mut handler_called = false;
-        
+        let promise = Promise::new();
+        let handler_called = Arc::new(Mutex::new(false));
+
         // Add a catch handler
-        promise.catch(Box::new(move |reason| {
-            handler_called = true;
+        let called = Arc::clone(&handler_called);
+        let recovered = promise.catch(move |reason| {
+            *called.lock() = true;
             assert!(matches!(reason, Value::String(s) if s == "error"));
             Ok(Value::Undefined)
-        })).unwrap();
-        
+        });
+
         // Reject the promise
         promise.reject(Value::String("error".to_string())).unwrap();
-        
-        assert!(handler_called);
+        promise.microtask_queue().drain();
+
+        assert!(*handler_called.lock());
+        assert!(matches!(recovered.state(), PromiseState::Fulfilled(Value::Undefined)));
+    }
+
+    #[tokio::test]
+    async fn test_promise_then_chain_runs_as_microtasks() {
+        let system = AsyncAwaitSystem::new();
+        let result = Arc::new(Mutex::new(Value::Undefined));
+
+        // Promise.resolve(1).then(x => x + 1).then(x => result = x)
+        let sink = Arc::clone(&result);
+        system.resolve(Value::Number(1.0))
+            .then(|x| match x {
+                Value::Number(n) => Ok(Value::Number(n + 1.0)),
+                other => Ok(other),
+            }, rethrow)
+            .then(move |x| {
+                *sink.lock() = x;
+                Ok(Value::Undefined)
+            }, rethrow);
+
+        assert!(matches!(*result.lock(), Value::Undefined));
+        assert_eq!(system.drain_microtasks(), 2);
+        assert!(matches!(*result.lock(), Value::Number(n) if n == 2.0));
+    }
+
+    #[tokio::test]
+    async fn test_microtasks_drain_to_exhaustion() {
+        let event_loop = EventLoop::new();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let queue = event_loop.microtask_queue().clone();
+        let log = Arc::clone(&order);
+        event_loop.enqueue_microtask(Box::new(move || {
+            log.lock().push(1);
+            let log = Arc::clone(&log);
+            queue.enqueue(Box::new(move || log.lock().push(3)));
+        }));
+        let log = Arc::clone(&order);
+        event_loop.enqueue_microtask(Box::new(move || log.lock().push(2)));
+
+        assert_eq!(event_loop.drain_microtasks(), 3);
+        assert_eq!(*order.lock(), vec![1, 2, 3]);
+        assert!(event_loop.microtask_queue().is_empty());
     }
 
     #[tokio::test]
@@ -178,14 +238,14 @@ mod tests {
 
     #[tokio::test]
     async fn test_promise_state_transitions() {
-        let mut promise = Promise::new();
+        let promise = Promise::new();
         
         // Initially pending
-        assert!(matches!(promise.state, PromiseState::Pending));
+        assert!(matches!(promise.state(), PromiseState::Pending));
         
         // Fulfill the promise
         promise.fulfill(Value::String("success".to_string())).unwrap();
-        assert!(matches!(promise.state, PromiseState::Fulfilled(_)));
+        assert!(matches!(promise.state(), PromiseState::Fulfilled(_)));
         
         // Try to fulfill again (should fail)
         let result = promise.fulfill(Value::String("another".to_string()));
@@ -199,9 +259,9 @@ mod tests {
         // Test system creation and basic operations
         let promise = system.resolve(Value::Number(100.0));
         
-        match promise.state {
+        match promise.state() {
             PromiseState::Fulfilled(value) => {
-                assert!(matches!(value, Value::Number(n) if *n == 100.0));
+                assert!(matches!(value, Value::Number(n) if n == 100.0));
             }
             _ => panic!("Expected fulfilled promise"),
         }
@@ -1,7 +1,12 @@
+use crate::ast::{BinaryOperator, UnaryOperator};
 use crate::error::{Error, Result};
+use num_bigint::{BigInt, Sign};
 use std::collections::HashMap;
-use std::sync::Arc;
-use parking_lot::RwLock;
+use std::fmt;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use parking_lot::{Condvar, Mutex, RwLock};
+use crate::async_await::MicrotaskQueue;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
 use tokio::time::{sleep, timeout};
@@ -26,7 +31,7 @@ pub enum TypedArrayType {
 }
 
 /// TypedArray implementation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TypedArray {
     /// Array type
     pub array_type: TypedArrayType,
@@ -47,7 +52,7 @@ pub struct TypedArrayConstructor {
     /// Element size in bytes
     element_size: usize,
     /// Constructor function
-    constructor_fn: fn(&[u8], usize, usize) -> TypedArray,
+    constructor_fn: fn(TypedArrayType, &[u8], usize, usize) -> TypedArray,
 }
 
 /// Promise states
@@ -58,17 +63,42 @@ pub enum PromiseState {
     Rejected(Value),
 }
 
+/// Reaction run when a promise settles
+pub type PromiseHandler = Arc<dyn Fn(Value) -> Result<Value> + Send + Sync>;
+
+/// Function passed to the Promise constructor, called with resolve and reject
+pub type PromiseExecutor = Arc<dyn Fn(Box<dyn Fn(Value) + Send + Sync>, Box<dyn Fn(Value) + Send + Sync>) + Send + Sync>;
+
 /// Promise implementation
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Promise {
     /// Promise state
     pub state: PromiseState,
     /// Fulfillment handlers
-    pub on_fulfilled: Vec<Box<dyn Fn(Value) -> Result<Value> + Send + Sync>>,
+    pub on_fulfilled: Vec<PromiseHandler>,
     /// Rejection handlers
-    pub on_rejected: Vec<Box<dyn Fn(Value) -> Result<Value> + Send + Sync>>,
+    pub on_rejected: Vec<PromiseHandler>,
     /// Promise executor
-    pub executor: Option<Box<dyn Fn(Box<dyn Fn(Value) + Send + Sync>, Box<dyn Fn(Value) + Send + Sync>) + Send + Sync>>,
+    pub executor: Option<PromiseExecutor>,
+    /// Settlement requested by the executor, applied by `apply_resolution`
+    resolution: Arc<Mutex<Option<PromiseState>>>,
+}
+
+/// Promises compare by state, since their reactions can't be compared
+impl PartialEq for Promise {
+    fn eq(&self, other: &Self) -> bool {
+        self.state == other.state
+    }
+}
+
+impl fmt::Debug for Promise {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Promise")
+            .field("state", &self.state)
+            .field("on_fulfilled", &self.on_fulfilled.len())
+            .field("on_rejected", &self.on_rejected.len())
+            .finish_non_exhaustive()
+    }
 }
 
 /// Promise constructor
@@ -126,7 +156,7 @@ pub struct FetchAPI {
     /// HTTP client
     client: reqwest::Client,
     /// Request timeout
-    timeout: Duration,
+    pub(crate) timeout: Duration,
     /// Default headers
     default_headers: HashMap<String, String>,
 }
@@ -139,14 +169,14 @@ pub enum TimerType {
 }
 
 /// Timer implementation
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Timer {
     /// Timer ID
     pub id: u64,
     /// Timer type
     pub timer_type: TimerType,
     /// Callback function
-    pub callback: Box<dyn Fn() -> Result<()> + Send + Sync>,
+    pub callback: Arc<dyn Fn() -> Result<()> + Send + Sync>,
     /// Delay in milliseconds
     pub delay: u64,
     /// Whether timer is active
@@ -157,6 +187,17 @@ pub struct Timer {
     pub next_execution: Instant,
 }
 
+impl fmt::Debug for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timer")
+            .field("id", &self.id)
+            .field("timer_type", &self.timer_type)
+            .field("delay", &self.delay)
+            .field("active", &self.active)
+            .finish_non_exhaustive()
+    }
+}
+
 /// Timer manager
 pub struct TimerManager {
     /// Active timers
@@ -196,7 +237,7 @@ pub enum EventType {
 }
 
 /// Event implementation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Event {
     /// Event type
     pub event_type: EventType,
@@ -218,13 +259,16 @@ pub struct Event {
     pub data: HashMap<String, Value>,
 }
 
+/// Callback run when a listened-for event is dispatched
+pub type EventCallback = Arc<dyn Fn(&Event) -> Result<()> + Send + Sync>;
+
 /// Event listener
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct EventListener {
     /// Event type
     pub event_type: EventType,
     /// Callback function
-    pub callback: Box<dyn Fn(&Event) -> Result<()> + Send + Sync>,
+    pub callback: EventCallback,
     /// Whether to capture
     pub capture: bool,
     /// Whether to use once
@@ -233,6 +277,17 @@ pub struct EventListener {
     pub passive: bool,
 }
 
+impl fmt::Debug for EventListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventListener")
+            .field("event_type", &self.event_type)
+            .field("capture", &self.capture)
+            .field("once", &self.once)
+            .field("passive", &self.passive)
+            .finish_non_exhaustive()
+    }
+}
+
 /// Event manager
 pub struct EventManager {
     /// Event listeners by target
@@ -245,6 +300,101 @@ pub struct EventManager {
     event_rx: mpsc::Receiver<Event>,
 }
 
+/// SharedArrayBuffer backed by memory shared between threads
+///
+/// The contents are stored as little-endian 32-bit words, so that 8, 16 and
+/// 32-bit accesses are all atomic. Clones share the same memory.
+#[derive(Clone)]
+pub struct SharedArrayBuffer {
+    /// Buffer contents
+    words: Arc<[AtomicU32]>,
+    /// Length in bytes
+    byte_length: usize,
+    /// Agents blocked in `Atomics.wait` on this buffer
+    waiters: Arc<WaiterList>,
+}
+
+/// Agents waiting on locations in a shared buffer
+#[derive(Default)]
+struct WaiterList {
+    /// Waiter IDs by byte index, in the order they started waiting
+    waiters: Mutex<HashMap<usize, VecDeque<u64>>>,
+    /// Signalled when waiters are removed by `Atomics.notify`
+    condvar: Condvar,
+    /// Next waiter ID
+    next_id: AtomicU64,
+}
+
+/// Integer TypedArray view of a SharedArrayBuffer, as used by `Atomics`
+#[derive(Debug, Clone)]
+pub struct SharedTypedArray {
+    /// Viewed buffer
+    buffer: SharedArrayBuffer,
+    /// Array type
+    array_type: TypedArrayType,
+    /// Byte offset into the buffer
+    byte_offset: usize,
+    /// Length in elements
+    length: usize,
+}
+
+/// Kind of agent running script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentKind {
+    /// The window's main thread, which must never block
+    MainThread,
+    /// A worker thread
+    Worker,
+}
+
+/// Result of `Atomics.wait`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitResult {
+    /// Woken by `Atomics.notify`
+    Ok,
+    /// The value didn't match, so the agent didn't wait
+    NotEqual,
+    /// The timeout elapsed
+    TimedOut,
+}
+
+/// `Atomics` built-in methods
+pub struct Atomics;
+
+/// Shared, mutable JavaScript object
+///
+/// Clones refer to the same object, which is collected once the last
+/// reference to it is dropped.
+#[derive(Debug, Clone, Default)]
+pub struct JsObject {
+    /// Object properties
+    properties: Arc<RwLock<HashMap<String, Value>>>,
+}
+
+/// `WeakRef` holding an object without keeping it alive
+#[derive(Debug, Clone)]
+pub struct WeakRef {
+    /// Weak pointer to the target's properties
+    target: Weak<RwLock<HashMap<String, Value>>>,
+}
+
+/// Object registered with a finalization registry
+struct FinalizationCell {
+    /// Weak pointer to the registered object
+    target: Weak<RwLock<HashMap<String, Value>>>,
+    /// Value passed to the cleanup callback
+    held_value: Value,
+}
+
+/// `FinalizationRegistry` calling back after registered objects are collected
+#[derive(Clone)]
+pub struct FinalizationRegistry {
+    /// Cleanup callback
+    cleanup: Arc<dyn Fn(Value) + Send + Sync>,
+    /// Registered objects
+    cells: Arc<RwLock<Vec<FinalizationCell>>>,
+}
+
 /// Built-in objects manager
 pub struct BuiltinObjects {
     /// TypedArray constructors
@@ -257,10 +407,14 @@ pub struct BuiltinObjects {
     timer_manager: TimerManager,
     /// Event manager
     event_manager: EventManager,
+    /// Properties of the global object
+    globals: HashMap<String, Value>,
+    /// Finalization registries to clean up after garbage collection
+    finalization_registries: RwLock<Vec<FinalizationRegistry>>,
 }
 
 // Placeholder Value type for compilation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Undefined,
     Null,
@@ -270,11 +424,160 @@ pub enum Value {
     Object(HashMap<String, Value>),
     Array(Vec<Value>),
     Function(String),
+    BigInt(Box<BigIntValue>),
     TypedArray(TypedArray),
-    Promise(Promise),
+    Promise(Box<Promise>),
     Event(Event),
 }
 
+/// Arbitrary-precision integer backing JavaScript BigInt values
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BigIntValue {
+    value: BigInt,
+}
+
+impl BigIntValue {
+    /// Create a BigInt value
+    pub fn new(value: BigInt) -> Self {
+        Self { value }
+    }
+
+    /// Parse a BigInt literal, with or without the `n` suffix
+    pub fn parse(literal: &str) -> Result<Self> {
+        let digits = literal.strip_suffix('n').unwrap_or(literal);
+        digits.parse::<BigInt>()
+            .map(Self::new)
+            .map_err(|_| Error::parsing(format!("Invalid BigInt literal: {}", literal)))
+    }
+
+    /// Get the underlying integer
+    pub fn value(&self) -> &BigInt {
+        &self.value
+    }
+
+    /// Check if the value is zero
+    pub fn is_zero(&self) -> bool {
+        self.value.sign() == Sign::NoSign
+    }
+
+    /// Format the value in the given radix, as `toString(radix)` does
+    pub fn to_string_radix(&self, radix: u32) -> Result<String> {
+        if !(2..=36).contains(&radix) {
+            return Err(Error::range_error("toString() radix must be between 2 and 36"));
+        }
+        Ok(self.value.to_str_radix(radix))
+    }
+
+    /// Apply a binary operator to two BigInts
+    ///
+    /// Arithmetic and bitwise operators produce a BigInt; comparisons produce
+    /// a boolean.
+    pub fn binary_op(&self, operator: &BinaryOperator, other: &BigIntValue) -> Result<Value> {
+        let (a, b) = (&self.value, &other.value);
+        let result = match operator {
+            BinaryOperator::Plus => a + b,
+            BinaryOperator::Minus => a - b,
+            BinaryOperator::Multiply => a * b,
+            BinaryOperator::Divide => {
+                if other.is_zero() {
+                    return Err(Error::range_error("Division by zero"));
+                }
+                a / b
+            }
+            BinaryOperator::Modulo => {
+                if other.is_zero() {
+                    return Err(Error::range_error("Division by zero"));
+                }
+                a % b
+            }
+            BinaryOperator::Exponent => {
+                if b.sign() == Sign::Minus {
+                    return Err(Error::range_error("Exponent must be non-negative"));
+                }
+                let exponent = u32::try_from(b)
+                    .map_err(|_| Error::range_error("Maximum BigInt size exceeded"))?;
+                a.pow(exponent)
+            }
+            BinaryOperator::BitwiseAnd => a & b,
+            BinaryOperator::BitwiseOr => a | b,
+            BinaryOperator::BitwiseXor => a ^ b,
+            BinaryOperator::LeftShift => Self::shift_left(a, b)?,
+            BinaryOperator::RightShift => Self::shift_left(a, &-b)?,
+            BinaryOperator::UnsignedRightShift => {
+                return Err(Error::type_error("BigInts have no unsigned right shift, use >> instead"));
+            }
+            BinaryOperator::LessThan => return Ok(Value::Boolean(a < b)),
+            BinaryOperator::LessThanOrEqual => return Ok(Value::Boolean(a <= b)),
+            BinaryOperator::GreaterThan => return Ok(Value::Boolean(a > b)),
+            BinaryOperator::GreaterThanOrEqual => return Ok(Value::Boolean(a >= b)),
+            BinaryOperator::Equal | BinaryOperator::StrictEqual => return Ok(Value::Boolean(a == b)),
+            BinaryOperator::NotEqual | BinaryOperator::StrictNotEqual => return Ok(Value::Boolean(a != b)),
+            _ => return Err(Error::type_error(format!("Unsupported BigInt operator: {:?}", operator))),
+        };
+        Ok(Value::BigInt(Box::new(Self::new(result))))
+    }
+
+    /// Apply a unary operator to a BigInt
+    pub fn unary_op(&self, operator: &UnaryOperator) -> Result<Value> {
+        let result = match operator {
+            UnaryOperator::Minus => -&self.value,
+            UnaryOperator::BitwiseNot => -&self.value - 1,
+            UnaryOperator::LogicalNot => return Ok(Value::Boolean(self.is_zero())),
+            UnaryOperator::TypeOf => return Ok(Value::String("bigint".to_string())),
+            UnaryOperator::Plus => return Err(Error::type_error("Cannot convert a BigInt value to a number")),
+            _ => return Err(Error::type_error(format!("Unsupported BigInt operator: {:?}", operator))),
+        };
+        Ok(Value::BigInt(Box::new(Self::new(result))))
+    }
+
+    /// Shift left by a signed amount, shifting right for negative amounts
+    ///
+    /// Right shifts round towards negative infinity, like `>>` on BigInts.
+    fn shift_left(value: &BigInt, amount: &BigInt) -> Result<BigInt> {
+        let magnitude = usize::try_from(amount.magnitude())
+            .map_err(|_| Error::range_error("Maximum BigInt size exceeded"))?;
+        if amount.sign() == Sign::Minus {
+            Ok(value >> magnitude)
+        } else {
+            Ok(value << magnitude)
+        }
+    }
+}
+
+impl fmt::Display for BigIntValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+impl From<i64> for BigIntValue {
+    fn from(value: i64) -> Self {
+        Self::new(BigInt::from(value))
+    }
+}
+
+impl Value {
+    /// Apply a binary operator where at least one operand is a BigInt
+    ///
+    /// Mixing a BigInt with any other type is a TypeError, except for `+`
+    /// with a string, which concatenates.
+    pub fn bigint_binary_op(&self, operator: &BinaryOperator, other: &Value) -> Result<Value> {
+        match (self, other) {
+            (Value::BigInt(a), Value::BigInt(b)) => a.binary_op(operator, b),
+            (Value::String(s), Value::BigInt(b)) if matches!(operator, BinaryOperator::Plus) => {
+                Ok(Value::String(format!("{}{}", s, b)))
+            }
+            (Value::BigInt(a), Value::String(s)) if matches!(operator, BinaryOperator::Plus) => {
+                Ok(Value::String(format!("{}{}", a, s)))
+            }
+            (Value::BigInt(_), _) | (_, Value::BigInt(_)) => {
+                Err(Error::type_error("Cannot mix BigInt and other types, use explicit conversions"))
+            }
+            _ => Err(Error::type_error("Expected a BigInt operand")),
+        }
+    }
+}
+
 impl TypedArray {
     /// Create a new TypedArray
     pub fn new(array_type: TypedArrayType, length: usize) -> Self {
@@ -305,7 +608,7 @@ impl TypedArray {
     }
 
     /// Get element size for array type
-    fn get_element_size(array_type: TypedArrayType) -> usize {
+    pub fn get_element_size(array_type: TypedArrayType) -> usize {
         match array_type {
             TypedArrayType::Int8Array | TypedArrayType::Uint8Array | TypedArrayType::Uint8ClampedArray => 1,
             TypedArrayType::Int16Array | TypedArrayType::Uint16Array => 2,
@@ -464,16 +767,40 @@ impl Promise {
             on_fulfilled: Vec::new(),
             on_rejected: Vec::new(),
             executor: None,
+            resolution: Arc::new(Mutex::new(None)),
         }
     }
 
-    /// Create a new promise with executor
+    /// Create a new promise and run its executor straight away. Only the first
+    /// call to resolve or reject counts; it is queued and takes effect on
+    /// `apply_resolution`.
     pub fn with_executor(executor: Box<dyn Fn(Box<dyn Fn(Value) + Send + Sync>, Box<dyn Fn(Value) + Send + Sync>) + Send + Sync>) -> Self {
-        Self {
-            state: PromiseState::Pending,
-            on_fulfilled: Vec::new(),
-            on_rejected: Vec::new(),
-            executor: Some(executor),
+        let executor: PromiseExecutor = Arc::from(executor);
+        let mut promise = Self::new();
+        promise.executor = Some(executor.clone());
+
+        let resolved = promise.resolution.clone();
+        let rejected = promise.resolution.clone();
+        executor(
+            Box::new(move |value| {
+                resolved.lock().get_or_insert(PromiseState::Fulfilled(value));
+            }),
+            Box::new(move |reason| {
+                rejected.lock().get_or_insert(PromiseState::Rejected(reason));
+            }),
+        );
+
+        promise
+    }
+
+    /// Settle the promise with whatever its executor resolved or rejected with,
+    /// unless it has been settled some other way since
+    pub fn apply_resolution(&mut self) -> Result<()> {
+        let resolution = self.resolution.lock().take();
+        match resolution {
+            Some(PromiseState::Fulfilled(value)) if self.is_pending() => self.fulfill(value),
+            Some(PromiseState::Rejected(reason)) if self.is_pending() => self.reject(reason),
+            _ => Ok(()),
         }
     }
 
@@ -516,7 +843,7 @@ impl Promise {
     {
         match &self.state {
             PromiseState::Pending => {
-                self.on_fulfilled.push(Box::new(handler));
+                self.on_fulfilled.push(Arc::new(handler));
                 Ok(())
             }
             PromiseState::Fulfilled(value) => {
@@ -534,7 +861,7 @@ impl Promise {
     {
         match &self.state {
             PromiseState::Pending => {
-                self.on_rejected.push(Box::new(handler));
+                self.on_rejected.push(Arc::new(handler));
                 Ok(())
             }
             PromiseState::Fulfilled(_) => Ok(()),
@@ -669,7 +996,7 @@ impl TimerManager {
         let timer = Timer {
             id: timer_id,
             timer_type: TimerType::Timeout,
-            callback: Box::new(callback),
+            callback: Arc::new(callback),
             delay,
             active: true,
             created_at: Instant::now(),
@@ -677,7 +1004,7 @@ impl TimerManager {
         };
 
         let mut timers = self.timers.write();
-        timers.insert(timer_id, timer);
+        timers.insert(timer_id, timer.clone());
 
         // Send timer event
         self.timer_tx.send(TimerEvent::CreateTimer(timer.clone())).await
@@ -698,7 +1025,7 @@ impl TimerManager {
         let timer = Timer {
             id: timer_id,
             timer_type: TimerType::Interval,
-            callback: Box::new(callback),
+            callback: Arc::new(callback),
             delay,
             active: true,
             created_at: Instant::now(),
@@ -706,7 +1033,7 @@ impl TimerManager {
         };
 
         let mut timers = self.timers.write();
-        timers.insert(timer_id, timer);
+        timers.insert(timer_id, timer.clone());
 
         // Send timer event
         self.timer_tx.send(TimerEvent::CreateTimer(timer.clone())).await
@@ -793,7 +1120,7 @@ impl EventManager {
     {
         let listener = EventListener {
             event_type,
-            callback: Box::new(callback),
+            callback: Arc::new(callback),
             capture,
             once: false,
             passive: false,
@@ -836,11 +1163,15 @@ impl EventManager {
     /// Process events
     pub async fn process_events(&mut self) -> Result<()> {
         while let Some(event) = self.event_rx.recv().await {
-            // Find listeners for this event
-            let listeners = {
+            // Find the target's listeners for this event type
+            let listeners: Vec<EventListener> = {
                 let listeners = self.listeners.read();
-                listeners.get(&event.event_type.to_string())
-                    .cloned()
+                event.target.as_ref()
+                    .and_then(|target| listeners.get(target))
+                    .map(|target_listeners| target_listeners.iter()
+                        .filter(|listener| listener.event_type == event.event_type)
+                        .cloned()
+                        .collect())
                     .unwrap_or_default()
             };
 
@@ -864,6 +1195,366 @@ impl EventManager {
     }
 }
 
+impl SharedArrayBuffer {
+    /// Create a zeroed buffer
+    pub fn new(byte_length: usize) -> Self {
+        Self {
+            words: (0..byte_length.div_ceil(4)).map(|_| AtomicU32::new(0)).collect(),
+            byte_length,
+            waiters: Arc::new(WaiterList::default()),
+        }
+    }
+
+    /// Get the length in bytes
+    pub fn byte_length(&self) -> usize {
+        self.byte_length
+    }
+
+    /// Check if two buffers share the same memory
+    pub fn ptr_eq(&self, other: &SharedArrayBuffer) -> bool {
+        Arc::ptr_eq(&self.words, &other.words)
+    }
+}
+
+impl fmt::Debug for SharedArrayBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedArrayBuffer").field("byte_length", &self.byte_length).finish()
+    }
+}
+
+impl WaiterList {
+    /// Block until notified, if `matches` holds
+    ///
+    /// `matches` is checked while holding the waiter list lock, so a notify
+    /// that follows a store can't be missed.
+    fn wait(&self, byte_index: usize, matches: impl FnOnce() -> bool, timeout: Option<Duration>) -> WaitResult {
+        let mut waiters = self.waiters.lock();
+        if !matches() {
+            return WaitResult::NotEqual;
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        waiters.entry(byte_index).or_default().push_back(id);
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+
+        loop {
+            let waiting = waiters.get(&byte_index).is_some_and(|queue| queue.contains(&id));
+            if !waiting {
+                return WaitResult::Ok;
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    if self.condvar.wait_until(&mut waiters, deadline).timed_out() {
+                        let queue = waiters.entry(byte_index).or_default();
+                        let still_waiting = queue.contains(&id);
+                        queue.retain(|waiter| *waiter != id);
+                        if queue.is_empty() {
+                            waiters.remove(&byte_index);
+                        }
+                        return if still_waiting { WaitResult::TimedOut } else { WaitResult::Ok };
+                    }
+                }
+                None => self.condvar.wait(&mut waiters),
+            }
+        }
+    }
+
+    /// Wake up to `count` waiters on a location, oldest first
+    fn notify(&self, byte_index: usize, count: Option<usize>) -> usize {
+        let mut waiters = self.waiters.lock();
+        let Some(queue) = waiters.get_mut(&byte_index) else {
+            return 0;
+        };
+
+        let woken = count.unwrap_or(usize::MAX).min(queue.len());
+        queue.drain(..woken);
+        if queue.is_empty() {
+            waiters.remove(&byte_index);
+        }
+        if woken > 0 {
+            self.condvar.notify_all();
+        }
+        woken
+    }
+}
+
+impl SharedTypedArray {
+    /// Create a view of a shared buffer
+    ///
+    /// Only integer types other than `Uint8ClampedArray` and the BigInt
+    /// arrays are supported.
+    pub fn new(buffer: SharedArrayBuffer, array_type: TypedArrayType, byte_offset: usize, length: usize) -> Result<Self> {
+        if !matches!(array_type,
+            TypedArrayType::Int8Array | TypedArrayType::Uint8Array |
+            TypedArrayType::Int16Array | TypedArrayType::Uint16Array |
+            TypedArrayType::Int32Array | TypedArrayType::Uint32Array) {
+            return Err(Error::type_error(format!("Atomics don't support {:?}", array_type)));
+        }
+
+        let element_size = TypedArray::get_element_size(array_type);
+        if !byte_offset.is_multiple_of(element_size) {
+            return Err(Error::range_error("Byte offset must be a multiple of the element size"));
+        }
+        let end = length.checked_mul(element_size).and_then(|byte_length| byte_length.checked_add(byte_offset));
+        if end.is_none_or(|end| end > buffer.byte_length()) {
+            return Err(Error::range_error("View is out of bounds of the buffer"));
+        }
+
+        Ok(Self { buffer, array_type, byte_offset, length })
+    }
+
+    /// Get the array type
+    pub fn array_type(&self) -> TypedArrayType {
+        self.array_type
+    }
+
+    /// Get the length in elements
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Get the viewed buffer
+    pub fn buffer(&self) -> &SharedArrayBuffer {
+        &self.buffer
+    }
+
+    /// Get the byte index of an element in the buffer
+    fn byte_index(&self, index: usize) -> Result<usize> {
+        if index >= self.length {
+            return Err(Error::range_error(format!("Index {} is out of range", index)));
+        }
+        Ok(self.byte_offset + index * TypedArray::get_element_size(self.array_type))
+    }
+
+    /// Get the word holding an element, and the element's shift and mask in it
+    fn locate(&self, index: usize) -> Result<(&AtomicU32, u32, u32)> {
+        let byte_index = self.byte_index(index)?;
+        Ok((&self.buffer.words[byte_index / 4], (byte_index % 4) as u32 * 8, self.element_mask()))
+    }
+
+    /// Load the raw bits of an element
+    fn load_raw(&self, index: usize) -> Result<u32> {
+        let (word, shift, mask) = self.locate(index)?;
+        Ok((word.load(Ordering::SeqCst) >> shift) & mask)
+    }
+
+    /// Atomically replace an element, returning the raw bits it had
+    fn update_raw(&self, index: usize, f: impl Fn(u32) -> u32) -> Result<u32> {
+        let (word, shift, mask) = self.locate(index)?;
+        let previous = word.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+            let element = f((current >> shift) & mask) & mask;
+            Some((current & !(mask << shift)) | (element << shift))
+        });
+        // The closure always returns `Some`, so the update can't fail
+        let previous = previous.unwrap_or_else(|current| current);
+        Ok((previous >> shift) & mask)
+    }
+
+    /// Convert a number to the raw bits of an element, wrapping like a TypedArray store
+    fn encode(&self, value: i64) -> u32 {
+        value as u32
+    }
+
+    /// Convert the raw bits of an element to a number
+    fn decode(&self, raw: u32) -> i64 {
+        match self.array_type {
+            TypedArrayType::Int8Array => raw as u8 as i8 as i64,
+            TypedArrayType::Int16Array => raw as u16 as i16 as i64,
+            TypedArrayType::Int32Array => raw as i32 as i64,
+            _ => raw as i64,
+        }
+    }
+
+    /// Apply a read-modify-write operation, returning the previous value
+    fn read_modify_write(&self, index: usize, value: i64, f: impl Fn(u32, u32) -> u32) -> Result<i64> {
+        let operand = self.encode(value);
+        let previous = self.update_raw(index, |old| f(old, operand))?;
+        Ok(self.decode(previous))
+    }
+
+    /// Get the mask covering an element's bits
+    fn element_mask(&self) -> u32 {
+        match TypedArray::get_element_size(self.array_type) {
+            1 => 0xff,
+            2 => 0xffff,
+            _ => u32::MAX,
+        }
+    }
+}
+
+impl Atomics {
+    /// `Atomics.load`
+    pub fn load(array: &SharedTypedArray, index: usize) -> Result<i64> {
+        Ok(array.decode(array.load_raw(index)?))
+    }
+
+    /// `Atomics.store`, returning the stored value
+    pub fn store(array: &SharedTypedArray, index: usize, value: i64) -> Result<i64> {
+        let raw = array.encode(value);
+        array.update_raw(index, |_| raw)?;
+        Ok(value)
+    }
+
+    /// `Atomics.add`, returning the previous value
+    pub fn add(array: &SharedTypedArray, index: usize, value: i64) -> Result<i64> {
+        array.read_modify_write(index, value, |old, operand| old.wrapping_add(operand))
+    }
+
+    /// `Atomics.sub`, returning the previous value
+    pub fn sub(array: &SharedTypedArray, index: usize, value: i64) -> Result<i64> {
+        array.read_modify_write(index, value, |old, operand| old.wrapping_sub(operand))
+    }
+
+    /// `Atomics.and`, returning the previous value
+    pub fn and(array: &SharedTypedArray, index: usize, value: i64) -> Result<i64> {
+        array.read_modify_write(index, value, |old, operand| old & operand)
+    }
+
+    /// `Atomics.or`, returning the previous value
+    pub fn or(array: &SharedTypedArray, index: usize, value: i64) -> Result<i64> {
+        array.read_modify_write(index, value, |old, operand| old | operand)
+    }
+
+    /// `Atomics.xor`, returning the previous value
+    pub fn xor(array: &SharedTypedArray, index: usize, value: i64) -> Result<i64> {
+        array.read_modify_write(index, value, |old, operand| old ^ operand)
+    }
+
+    /// `Atomics.exchange`, returning the previous value
+    pub fn exchange(array: &SharedTypedArray, index: usize, value: i64) -> Result<i64> {
+        array.read_modify_write(index, value, |_, operand| operand)
+    }
+
+    /// `Atomics.compareExchange`, returning the previous value
+    ///
+    /// The element is only replaced if it equals `expected`.
+    pub fn compare_exchange(array: &SharedTypedArray, index: usize, expected: i64, replacement: i64) -> Result<i64> {
+        let expected = array.encode(expected) & array.element_mask();
+        let replacement = array.encode(replacement);
+        let previous = array.update_raw(index, |old| if old == expected { replacement } else { old })?;
+        Ok(array.decode(previous))
+    }
+
+    /// `Atomics.wait`
+    ///
+    /// Blocks the calling agent while the element equals `value`, until
+    /// notified or until `timeout` elapses. Only worker agents may block.
+    pub fn wait(agent: AgentKind, array: &SharedTypedArray, index: usize, value: i64, timeout: Option<Duration>) -> Result<WaitResult> {
+        if array.array_type != TypedArrayType::Int32Array {
+            return Err(Error::type_error("Atomics.wait requires an Int32Array"));
+        }
+        let byte_index = array.byte_index(index)?;
+        if agent == AgentKind::MainThread {
+            return Err(Error::type_error("Atomics.wait cannot be called on the main thread"));
+        }
+
+        let expected = array.encode(value);
+        Ok(array.buffer.waiters.wait(byte_index, || array.load_raw(index).is_ok_and(|raw| raw == expected), timeout))
+    }
+
+    /// `Atomics.notify`, returning the number of agents woken
+    ///
+    /// Wakes up to `count` agents waiting on the element, or all of them if
+    /// `count` is `None`.
+    pub fn notify(array: &SharedTypedArray, index: usize, count: Option<usize>) -> Result<usize> {
+        if array.array_type != TypedArrayType::Int32Array {
+            return Err(Error::type_error("Atomics.notify requires an Int32Array"));
+        }
+        let byte_index = array.byte_index(index)?;
+        Ok(array.buffer.waiters.notify(byte_index, count))
+    }
+}
+
+impl JsObject {
+    /// Create an empty object
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get a property
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.properties.read().get(name).cloned()
+    }
+
+    /// Set a property
+    pub fn set(&self, name: &str, value: Value) {
+        self.properties.write().insert(name.to_string(), value);
+    }
+
+    /// Check if two handles refer to the same object
+    pub fn ptr_eq(&self, other: &JsObject) -> bool {
+        Arc::ptr_eq(&self.properties, &other.properties)
+    }
+}
+
+impl WeakRef {
+    /// Create a weak reference to an object
+    pub fn new(target: &JsObject) -> Self {
+        Self {
+            target: Arc::downgrade(&target.properties),
+        }
+    }
+
+    /// Get the target, or `None` if it has been collected
+    pub fn deref(&self) -> Option<JsObject> {
+        self.target.upgrade().map(|properties| JsObject { properties })
+    }
+}
+
+impl FinalizationRegistry {
+    /// Create a registry with a cleanup callback
+    pub fn new<F>(cleanup: F) -> Self
+    where
+        F: Fn(Value) + Send + Sync + 'static,
+    {
+        Self {
+            cleanup: Arc::new(cleanup),
+            cells: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// Call the cleanup callback with `held_value` once `target` is collected
+    pub fn register(&self, target: &JsObject, held_value: Value) {
+        self.cells.write().push(FinalizationCell {
+            target: Arc::downgrade(&target.properties),
+            held_value,
+        });
+    }
+
+    /// Get the number of registered objects that haven't been cleaned up
+    pub fn registered_count(&self) -> usize {
+        self.cells.read().len()
+    }
+
+    /// Queue cleanup callbacks for registered objects that have been collected
+    ///
+    /// Returns the number of callbacks queued.
+    pub fn schedule_cleanup(&self, microtasks: &MicrotaskQueue) -> usize {
+        let collected: Vec<FinalizationCell> = {
+            let mut cells = self.cells.write();
+            let (live, collected) = cells.drain(..).partition(|cell| cell.target.strong_count() > 0);
+            *cells = live;
+            collected
+        };
+
+        for cell in &collected {
+            let cleanup = Arc::clone(&self.cleanup);
+            let held_value = cell.held_value.clone();
+            microtasks.enqueue(Box::new(move || cleanup(held_value)));
+        }
+        collected.len()
+    }
+}
+
+impl fmt::Debug for FinalizationRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FinalizationRegistry")
+            .field("registered", &self.registered_count())
+            .finish()
+    }
+}
+
 impl BuiltinObjects {
     /// Create a new built-in objects manager
     pub fn new() -> Self {
@@ -884,8 +1575,8 @@ impl BuiltinObjects {
             let constructor = TypedArrayConstructor {
                 array_type: *array_type,
                 element_size: TypedArray::get_element_size(*array_type),
-                constructor_fn: |buffer, offset, length| {
-                    TypedArray::from_buffer(*array_type, buffer.to_vec(), offset, length)
+                constructor_fn: |array_type, buffer, offset, length| {
+                    TypedArray::from_buffer(array_type, buffer.to_vec(), offset, length)
                 },
             };
             typed_array_constructors.insert(*array_type, constructor);
@@ -905,9 +1596,42 @@ impl BuiltinObjects {
             fetch_api,
             timer_manager,
             event_manager,
+            globals: HashMap::new(),
+            finalization_registries: RwLock::new(Vec::new()),
         }
     }
 
+    /// Add `WeakRef` and `FinalizationRegistry` to the global object
+    pub fn register_weak_ref_support(&mut self) {
+        for name in ["WeakRef", "FinalizationRegistry"] {
+            self.globals.insert(name.to_string(), Value::Function(name.to_string()));
+        }
+    }
+
+    /// Get a property of the global object
+    pub fn get_global(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    /// Create a finalization registry that is cleaned up after each GC cycle
+    pub fn create_finalization_registry<F>(&self, cleanup: F) -> FinalizationRegistry
+    where
+        F: Fn(Value) + Send + Sync + 'static,
+    {
+        let registry = FinalizationRegistry::new(cleanup);
+        self.finalization_registries.write().push(registry.clone());
+        registry
+    }
+
+    /// Schedule cleanup callbacks for objects collected by a GC cycle
+    ///
+    /// Returns the number of callbacks queued on `microtasks`.
+    pub fn schedule_finalization_cleanup(&self, microtasks: &MicrotaskQueue) -> usize {
+        self.finalization_registries.read().iter()
+            .map(|registry| registry.schedule_cleanup(microtasks))
+            .sum()
+    }
+
     /// Create TypedArray
     pub fn create_typed_array(&self, array_type: TypedArrayType, length: usize) -> Result<TypedArray> {
         Ok(TypedArray::new(array_type, length))
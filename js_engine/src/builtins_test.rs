@@ -3,8 +3,16 @@ mod tests {
     use super::*;
     use crate::builtins::{
         TypedArray, TypedArrayType, Promise, PromiseState, FetchAPI, FetchRequest, FetchResponse,
-        TimerManager, TimerType, EventManager, EventType, Event, BuiltinObjects, Value
+        TimerManager, TimerType, EventManager, EventType, Event, BuiltinObjects, BigIntValue, Value
     };
+    use crate::ast::{BinaryOperator, UnaryOperator};
+    use crate::error::Error;
+    use crate::lexer::{Lexer, TokenType};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use tokio::time::sleep;
 
     #[tokio::test]
     async fn test_typed_array_creation() {
@@ -53,13 +61,13 @@ mod tests {
     async fn test_typed_array_float32() {
         let mut array = TypedArray::new(TypedArrayType::Float32Array, 3);
         
-        array.set(0, Value::Number(3.14)).unwrap();
+        array.set(0, Value::Number(1.25)).unwrap();
         array.set(1, Value::Number(-2.5)).unwrap();
         array.set(2, Value::Number(0.0)).unwrap();
         
         let value0 = array.get(0).unwrap();
         if let Value::Number(n) = value0 {
-            assert!((n - 3.14).abs() < 0.001);
+            assert!((n - 1.25).abs() < 0.001);
         } else {
             panic!("Expected number");
         }
@@ -156,66 +164,73 @@ mod tests {
     #[tokio::test]
     async fn test_promise_then_handler() {
         let mut promise = Promise::new();
-        let mut handler_called = false;
+        let handler_called = Arc::new(AtomicBool::new(false));
+        let called = handler_called.clone();
         
-        promise.then(|value| {
-            handler_called = true;
+        promise.then(move |value| {
+            called.store(true, Ordering::SeqCst);
             assert_eq!(value, Value::String("test".to_string()));
             Ok(Value::Undefined)
         }).unwrap();
         
         // Handler should not be called yet
-        assert!(!handler_called);
+        assert!(!handler_called.load(Ordering::SeqCst));
         
         // Fulfill the promise
         promise.fulfill(Value::String("test".to_string())).unwrap();
         
         // Handler should now be called
-        assert!(handler_called);
+        assert!(handler_called.load(Ordering::SeqCst));
     }
 
     #[tokio::test]
     async fn test_promise_catch_handler() {
         let mut promise = Promise::new();
-        let mut handler_called = false;
+        let handler_called = Arc::new(AtomicBool::new(false));
+        let called = handler_called.clone();
         
-        promise.catch(|reason| {
-            handler_called = true;
+        promise.catch(move |reason| {
+            called.store(true, Ordering::SeqCst);
             assert_eq!(reason, Value::String("error".to_string()));
             Ok(Value::Undefined)
         }).unwrap();
         
         // Handler should not be called yet
-        assert!(!handler_called);
+        assert!(!handler_called.load(Ordering::SeqCst));
         
         // Reject the promise
         promise.reject(Value::String("error".to_string())).unwrap();
         
         // Handler should now be called
-        assert!(handler_called);
+        assert!(handler_called.load(Ordering::SeqCst));
     }
 
     #[tokio::test]
     async fn test_promise_with_executor() {
-        let mut executor_called = false;
-        let mut resolve_called = false;
-        let mut reject_called = false;
-        
-        let promise = Promise::with_executor(Box::new(move |resolve, reject| {
-            executor_called = true;
+        let executor_called = Arc::new(AtomicBool::new(false));
+        let resolve_called = Arc::new(AtomicBool::new(false));
+        let reject_called = Arc::new(AtomicBool::new(false));
+        
+        let (executor_flag, resolve_flag, reject_flag) =
+            (executor_called.clone(), resolve_called.clone(), reject_called.clone());
+        let mut promise = Promise::with_executor(Box::new(move |resolve, reject| {
+            executor_flag.store(true, Ordering::SeqCst);
             
             // Call resolve
             resolve(Value::String("resolved".to_string()));
-            resolve_called = true;
+            resolve_flag.store(true, Ordering::SeqCst);
             
-            // Reject should not be called
+            // Reject comes too late to change the outcome
             reject(Value::String("rejected".to_string()));
-            reject_called = true;
+            reject_flag.store(true, Ordering::SeqCst);
         }));
         
-        assert!(executor_called);
-        assert!(resolve_called);
-        assert!(reject_called);
+        assert!(executor_called.load(Ordering::SeqCst));
+        assert!(resolve_called.load(Ordering::SeqCst));
+        assert!(reject_called.load(Ordering::SeqCst));
+        
+        promise.apply_resolution().unwrap();
+        assert_eq!(promise.state, PromiseState::Fulfilled(Value::String("resolved".to_string())));
     }
 
     #[tokio::test]
@@ -248,10 +263,11 @@ mod tests {
     #[tokio::test]
     async fn test_timer_manager_timeout() {
         let timer_manager = TimerManager::new();
-        let mut callback_called = false;
+        let callback_called = Arc::new(AtomicBool::new(false));
+        let called = callback_called.clone();
         
-        let timer_id = timer_manager.set_timeout(|| {
-            callback_called = true;
+        let timer_id = timer_manager.set_timeout(move || {
+            called.store(true, Ordering::SeqCst);
             Ok(())
         }, 100).await.unwrap();
         
@@ -268,10 +284,11 @@ mod tests {
     #[tokio::test]
     async fn test_timer_manager_interval() {
         let timer_manager = TimerManager::new();
-        let mut callback_count = 0;
+        let callback_count = Arc::new(AtomicUsize::new(0));
+        let count = callback_count.clone();
         
-        let timer_id = timer_manager.set_interval(|| {
-            callback_count += 1;
+        let timer_id = timer_manager.set_interval(move || {
+            count.fetch_add(1, Ordering::SeqCst);
             Ok(())
         }, 50).await.unwrap();
         
@@ -306,10 +323,11 @@ mod tests {
     #[tokio::test]
     async fn test_event_manager_add_listener() {
         let event_manager = EventManager::new();
-        let mut callback_called = false;
+        let callback_called = Arc::new(AtomicBool::new(false));
+        let called = callback_called.clone();
         
-        event_manager.add_event_listener("test", EventType::Click, |event| {
-            callback_called = true;
+        event_manager.add_event_listener("test", EventType::Click, move |event| {
+            called.store(true, Ordering::SeqCst);
             assert_eq!(event.event_type, EventType::Click);
             Ok(())
         }, false).unwrap();
@@ -331,10 +349,11 @@ mod tests {
     #[tokio::test]
     async fn test_event_manager_dispatch() {
         let event_manager = EventManager::new();
-        let mut callback_called = false;
+        let callback_called = Arc::new(AtomicBool::new(false));
+        let called = callback_called.clone();
         
-        event_manager.add_event_listener("test", EventType::Click, |event| {
-            callback_called = true;
+        event_manager.add_event_listener("test", EventType::Click, move |event| {
+            called.store(true, Ordering::SeqCst);
             assert_eq!(event.event_type, EventType::Click);
             assert_eq!(event.target, Some("test".to_string()));
             Ok(())
@@ -464,7 +483,7 @@ mod tests {
             Value::Boolean(true),
             Value::Boolean(false),
             Value::Number(42.0),
-            Value::Number(-3.14),
+            Value::Number(-1.25),
             Value::String("hello".to_string()),
             Value::Object(HashMap::new()),
             Value::Array(vec![]),
@@ -504,57 +523,62 @@ mod tests {
     #[tokio::test]
     async fn test_promise_integration() {
         let builtins = BuiltinObjects::new();
-        let mut promise_resolved = false;
+        let promise_resolved = Arc::new(AtomicBool::new(false));
+        let resolved = promise_resolved.clone();
         
         let mut promise = builtins.create_promise(Box::new(move |resolve, _reject| {
-            promise_resolved = true;
+            resolved.store(true, Ordering::SeqCst);
             resolve(Value::String("success".to_string()));
         }));
         
         // Add then handler
-        let mut then_called = false;
-        promise.then(|value| {
-            then_called = true;
+        let then_called = Arc::new(AtomicBool::new(false));
+        let then_flag = then_called.clone();
+        promise.then(move |value| {
+            then_flag.store(true, Ordering::SeqCst);
             assert_eq!(value, Value::String("success".to_string()));
             Ok(Value::Undefined)
         }).unwrap();
         
         // Add catch handler
-        let mut catch_called = false;
-        promise.catch(|_reason| {
-            catch_called = true;
+        let catch_called = Arc::new(AtomicBool::new(false));
+        let catch_flag = catch_called.clone();
+        promise.catch(move |_reason| {
+            catch_flag.store(true, Ordering::SeqCst);
             Ok(Value::Undefined)
         }).unwrap();
         
         // Verify initial state
         assert!(promise.is_pending());
-        assert!(!then_called);
-        assert!(!catch_called);
+        assert!(!then_called.load(Ordering::SeqCst));
+        assert!(!catch_called.load(Ordering::SeqCst));
         
         // Fulfill promise
         promise.fulfill(Value::String("success".to_string())).unwrap();
         
         // Verify final state
         assert!(promise.is_fulfilled());
-        assert!(then_called);
-        assert!(!catch_called);
+        assert!(then_called.load(Ordering::SeqCst));
+        assert!(!catch_called.load(Ordering::SeqCst));
     }
 
     #[tokio::test]
     async fn test_timer_integration() {
         let builtins = BuiltinObjects::new();
-        let mut timeout_called = false;
-        let mut interval_called = 0;
+        let timeout_called = Arc::new(AtomicBool::new(false));
+        let interval_called = Arc::new(AtomicUsize::new(0));
         
         // Set timeout
-        let timeout_id = builtins.set_timeout(|| {
-            timeout_called = true;
+        let timeout_flag = timeout_called.clone();
+        let timeout_id = builtins.set_timeout(move || {
+            timeout_flag.store(true, Ordering::SeqCst);
             Ok(())
         }, 50).await.unwrap();
         
         // Set interval
-        let interval_id = builtins.set_interval(|| {
-            interval_called += 1;
+        let interval_count = interval_called.clone();
+        let interval_id = builtins.set_interval(move || {
+            interval_count.fetch_add(1, Ordering::SeqCst);
             Ok(())
         }, 25).await.unwrap();
         
@@ -572,19 +596,21 @@ mod tests {
     #[tokio::test]
     async fn test_event_integration() {
         let builtins = BuiltinObjects::new();
-        let mut click_called = false;
-        let mut custom_called = false;
+        let click_called = Arc::new(AtomicBool::new(false));
+        let custom_called = Arc::new(AtomicBool::new(false));
         
         // Add event listeners
-        builtins.add_event_listener("button", EventType::Click, |event| {
-            click_called = true;
+        let click_flag = click_called.clone();
+        builtins.add_event_listener("button", EventType::Click, move |event| {
+            click_flag.store(true, Ordering::SeqCst);
             assert_eq!(event.event_type, EventType::Click);
             assert_eq!(event.target, Some("button".to_string()));
             Ok(())
         }, false).unwrap();
         
-        builtins.add_event_listener("custom", EventType::Custom("test".to_string()), |event| {
-            custom_called = true;
+        let custom_flag = custom_called.clone();
+        builtins.add_event_listener("custom", EventType::Custom("test".to_string()), move |event| {
+            custom_flag.store(true, Ordering::SeqCst);
             assert_eq!(event.event_type, EventType::Custom("test".to_string()));
             Ok(())
         }, false).unwrap();
@@ -641,14 +667,15 @@ mod tests {
             resolve(Value::Number(42.0));
         }));
         
-        let mut promise_result = None;
-        promise.then(|value| {
-            promise_result = Some(value);
+        let promise_result = Arc::new(Mutex::new(None));
+        let result = promise_result.clone();
+        promise.then(move |value| {
+            *result.lock().unwrap() = Some(value);
             Ok(Value::Undefined)
         }).unwrap();
         
         promise.fulfill(Value::Number(42.0)).unwrap();
-        assert_eq!(promise_result, Some(Value::Number(42.0)));
+        assert_eq!(*promise_result.lock().unwrap(), Some(Value::Number(42.0)));
         
         // Test Timer
         let timer_id = builtins.set_timeout(|| Ok(()), 10).await.unwrap();
@@ -661,4 +688,172 @@ mod tests {
         builtins.remove_event_listener("test", EventType::Click).unwrap();
         assert_eq!(builtins.listener_count("test"), 0);
     }
+
+    fn bigint(literal: &str) -> Value {
+        Value::BigInt(Box::new(BigIntValue::parse(literal).unwrap()))
+    }
+
+    fn bigint_text(value: Value) -> String {
+        match value {
+            Value::BigInt(b) => b.to_string(),
+            other => panic!("expected a BigInt, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bigint_literal_exponent() {
+        let mut lexer = Lexer::new("2n ** 64n");
+        let base = lexer.next_token().unwrap();
+        assert_eq!(base.token_type, TokenType::BigIntLiteral("2".to_string()));
+        assert_eq!(base.lexeme, "2n");
+        assert_eq!(lexer.next_token().unwrap().token_type, TokenType::Exponent);
+        let exponent = lexer.next_token().unwrap();
+
+        let result = bigint(&base.lexeme).bigint_binary_op(&BinaryOperator::Exponent, &bigint(&exponent.lexeme)).unwrap();
+        assert_eq!(bigint_text(result), "18446744073709551616");
+
+        assert!(Lexer::new("1.5n").next_token().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bigint_arithmetic_and_bitwise() {
+        let cases = [
+            ("7n", BinaryOperator::Plus, "5n", "12"),
+            ("7n", BinaryOperator::Minus, "10n", "-3"),
+            ("123456789012345678901234567890n", BinaryOperator::Multiply, "10n", "1234567890123456789012345678900"),
+            ("-7n", BinaryOperator::Divide, "2n", "-3"),
+            ("-7n", BinaryOperator::Modulo, "2n", "-1"),
+            ("12n", BinaryOperator::BitwiseAnd, "10n", "8"),
+            ("12n", BinaryOperator::BitwiseOr, "3n", "15"),
+            ("-1n", BinaryOperator::BitwiseXor, "5n", "-6"),
+            ("1n", BinaryOperator::LeftShift, "100n", "1267650600228229401496703205376"),
+            ("-9n", BinaryOperator::RightShift, "1n", "-5"),
+            ("3n", BinaryOperator::LeftShift, "-1n", "1"),
+        ];
+        for (left, operator, right, expected) in cases {
+            let result = bigint(left).bigint_binary_op(&operator, &bigint(right)).unwrap();
+            assert_eq!(bigint_text(result), expected, "{} {:?} {}", left, operator, right);
+        }
+
+        let not = BigIntValue::from(5).unary_op(&UnaryOperator::BitwiseNot).unwrap();
+        assert_eq!(bigint_text(not), "-6");
+        assert_eq!(BigIntValue::from(255).to_string_radix(16).unwrap(), "ff");
+    }
+
+    #[tokio::test]
+    async fn test_bigint_errors() {
+        let mixed = bigint("1n").bigint_binary_op(&BinaryOperator::Plus, &Value::Number(1.0));
+        assert!(matches!(mixed, Err(Error::Type { .. })));
+
+        let shifted = bigint("8n").bigint_binary_op(&BinaryOperator::UnsignedRightShift, &bigint("1n"));
+        assert!(matches!(shifted, Err(Error::Type { .. })));
+
+        let divided = bigint("1n").bigint_binary_op(&BinaryOperator::Divide, &bigint("0n"));
+        assert!(matches!(divided, Err(Error::Range { .. })));
+
+        let unary_plus = BigIntValue::from(1).unary_op(&UnaryOperator::Plus);
+        assert!(matches!(unary_plus, Err(Error::Type { .. })));
+
+        match bigint("10n").bigint_binary_op(&BinaryOperator::Plus, &Value::String("px".to_string())).unwrap() {
+            Value::String(s) => assert_eq!(s, "10px"),
+            other => panic!("expected a string, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_weak_ref_and_finalization_registry() {
+        use crate::async_await::MicrotaskQueue;
+        use crate::builtins::{JsObject, WeakRef};
+        use parking_lot::Mutex;
+        use std::sync::Arc;
+
+        let mut builtins = BuiltinObjects::new();
+        assert!(builtins.get_global("WeakRef").is_none());
+        builtins.register_weak_ref_support();
+        assert!(matches!(builtins.get_global("WeakRef"), Some(Value::Function(name)) if name == "WeakRef"));
+        assert!(builtins.get_global("FinalizationRegistry").is_some());
+
+        let cleaned_up = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&cleaned_up);
+        let registry = builtins.create_finalization_registry(move |held_value| log.lock().push(held_value));
+
+        let target = JsObject::new();
+        target.set("size", Value::Number(3.0));
+        let weak = WeakRef::new(&target);
+        registry.register(&target, Value::String("cache entry".to_string()));
+
+        let microtasks = MicrotaskQueue::new();
+        assert!(weak.deref().is_some_and(|object| object.ptr_eq(&target)));
+        assert_eq!(builtins.schedule_finalization_cleanup(&microtasks), 0);
+
+        // Dropping the last reference collects the target
+        drop(target);
+        assert!(weak.deref().is_none());
+        assert_eq!(builtins.schedule_finalization_cleanup(&microtasks), 1);
+        assert_eq!(registry.registered_count(), 0);
+
+        // The callback runs as a microtask
+        assert!(cleaned_up.lock().is_empty());
+        microtasks.drain();
+        assert!(matches!(cleaned_up.lock().as_slice(), [Value::String(s)] if s == "cache entry"));
+    }
+
+    #[tokio::test]
+    async fn test_atomics_read_modify_write() {
+        use crate::builtins::{Atomics, SharedArrayBuffer, SharedTypedArray};
+
+        let buffer = SharedArrayBuffer::new(8);
+        let ints = SharedTypedArray::new(buffer.clone(), TypedArrayType::Int32Array, 0, 2).unwrap();
+        let bytes = SharedTypedArray::new(buffer, TypedArrayType::Int8Array, 4, 4).unwrap();
+
+        assert_eq!(Atomics::store(&ints, 0, 5).unwrap(), 5);
+        assert_eq!(Atomics::compare_exchange(&ints, 0, 5, 9).unwrap(), 5);
+        assert_eq!(Atomics::load(&ints, 0).unwrap(), 9);
+        assert_eq!(Atomics::compare_exchange(&ints, 0, 5, 1).unwrap(), 9);
+        assert_eq!(Atomics::load(&ints, 0).unwrap(), 9);
+
+        assert_eq!(Atomics::add(&ints, 0, 1).unwrap(), 9);
+        assert_eq!(Atomics::sub(&ints, 0, 12).unwrap(), 10);
+        assert_eq!(Atomics::load(&ints, 0).unwrap(), -2);
+        assert_eq!(Atomics::and(&ints, 0, 0xff).unwrap(), -2);
+        assert_eq!(Atomics::or(&ints, 0, 0x100).unwrap(), 0xfe);
+        assert_eq!(Atomics::xor(&ints, 0, 0x1ff).unwrap(), 0x1fe);
+        assert_eq!(Atomics::exchange(&ints, 0, 7).unwrap(), 1);
+
+        // 8-bit elements share a word with their neighbours without clobbering them
+        Atomics::store(&bytes, 1, 127).unwrap();
+        assert_eq!(Atomics::add(&bytes, 1, 1).unwrap(), 127);
+        assert_eq!(Atomics::load(&bytes, 1).unwrap(), -128);
+        assert_eq!(Atomics::load(&bytes, 0).unwrap(), 0);
+        assert_eq!(Atomics::load(&ints, 1).unwrap(), 0x8000);
+        assert_eq!(Atomics::load(&ints, 0).unwrap(), 7);
+
+        assert!(matches!(Atomics::load(&ints, 2), Err(Error::Range { .. })));
+        let floats = SharedTypedArray::new(SharedArrayBuffer::new(8), TypedArrayType::Float32Array, 0, 2);
+        assert!(matches!(floats, Err(Error::Type { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_atomics_wait_and_notify() {
+        use crate::builtins::{AgentKind, Atomics, SharedArrayBuffer, SharedTypedArray, WaitResult};
+        use std::time::Duration;
+
+        let ints = SharedTypedArray::new(SharedArrayBuffer::new(4), TypedArrayType::Int32Array, 0, 1).unwrap();
+
+        // The main thread may never block
+        let blocked = Atomics::wait(AgentKind::MainThread, &ints, 0, 0, None);
+        assert!(matches!(blocked, Err(Error::Type { .. })));
+
+        assert_eq!(Atomics::wait(AgentKind::Worker, &ints, 0, 1, None).unwrap(), WaitResult::NotEqual);
+        let timed_out = Atomics::wait(AgentKind::Worker, &ints, 0, 0, Some(Duration::from_millis(10))).unwrap();
+        assert_eq!(timed_out, WaitResult::TimedOut);
+
+        let worker_view = ints.clone();
+        let worker = std::thread::spawn(move || Atomics::wait(AgentKind::Worker, &worker_view, 0, 0, None).unwrap());
+        // Retry until the worker has started waiting
+        while Atomics::notify(&ints, 0, None).unwrap() == 0 {
+            std::thread::yield_now();
+        }
+        assert_eq!(worker.join().unwrap(), WaitResult::Ok);
+    }
 }
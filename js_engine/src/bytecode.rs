@@ -1,4 +1,7 @@
+use crate::debugger::Debugger;
 use crate::error::{Error, Result};
+use crate::source_map::OriginalPosition;
+use common::ipc::{DebuggerCallFrame, DebuggerRequest, PauseReason, ScopeVariable};
 use std::collections::HashMap;
 use std::fmt;
 
@@ -189,6 +192,7 @@ pub enum Value {
     Array(Vec<Value>),
     Function(FunctionValue),
     Class(ClassValue),
+    Proxy(Box<JsProxy>),
 }
 
 /// Function value for bytecode
@@ -211,6 +215,33 @@ pub struct ClassValue {
     pub properties: HashMap<String, Value>,
 }
 
+/// Proxy value for bytecode
+///
+/// Operations on the proxy call the matching trap of the handler, or act on
+/// the target when the handler does not define one.
+#[derive(Debug, Clone)]
+pub struct JsProxy {
+    pub target: Value,
+    pub handler: HashMap<String, Value>,
+}
+
+impl JsProxy {
+    /// Create a new proxy
+    pub fn new(target: Value, handler: HashMap<String, Value>) -> Self {
+        Self { target, handler }
+    }
+
+    /// Get the trap the handler defines for an operation
+    ///
+    /// Handler properties that are not callable are ignored.
+    pub fn trap(&self, name: &str) -> Option<Value> {
+        match self.handler.get(name) {
+            Some(trap @ (Value::Function(_) | Value::Proxy(_))) => Some(trap.clone()),
+            _ => None,
+        }
+    }
+}
+
 /// Bytecode function
 #[derive(Debug, Clone)]
 pub struct BytecodeFunction {
@@ -225,6 +256,28 @@ pub struct BytecodeFunction {
 pub struct SourceMap {
     pub mappings: Vec<(usize, usize)>, // (bytecode_offset, source_offset)
     pub source: String,
+    /// Source map of the script itself, for compiled or bundled scripts
+    pub script_map: Option<crate::source_map::SourceMap>,
+}
+
+impl SourceMap {
+    /// Get the position in the script of a bytecode offset
+    pub fn position_at(&self, bytecode_offset: usize) -> Option<crate::ast::Position> {
+        let source_offset = self.mappings.iter()
+            .filter(|(offset, _)| *offset <= bytecode_offset)
+            .max_by_key(|(offset, _)| *offset)
+            .map(|(_, source_offset)| *source_offset)?;
+        let before = self.source.get(..source_offset)?;
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().map_or(0, |text| text.chars().count()) + 1;
+        Some(crate::ast::Position::new(source_offset, source_offset, line, column))
+    }
+
+    /// Get the original source position of a bytecode offset
+    pub fn original_position(&self, bytecode_offset: usize) -> Option<OriginalPosition> {
+        let position = self.position_at(bytecode_offset)?;
+        self.script_map.as_ref()?.apply_to_position(position)
+    }
 }
 
 /// Register file for bytecode execution
@@ -282,6 +335,8 @@ pub struct CallFrame {
     pub locals: Vec<Value>,
     pub return_address: Option<usize>,
     pub this_value: Option<Value>,
+    /// Register of the calling frame that receives the return value
+    pub result_register: Option<Register>,
 }
 
 impl CallFrame {
@@ -295,6 +350,7 @@ impl CallFrame {
             function,
             return_address,
             this_value: None,
+            result_register: None,
         }
     }
 
@@ -331,12 +387,39 @@ impl CallFrame {
     }
 }
 
+/// Maximum number of nested calls before execution is aborted
+const MAX_CALL_DEPTH: usize = 1024;
+
 /// Bytecode execution engine
 pub struct BytecodeEngine {
-    call_stack: Vec<CallFrame>,
-    global_scope: HashMap<String, Value>,
-    constant_pool: Vec<Value>,
-    exception_handler: Option<ExceptionHandler>,
+    pub(crate) call_stack: Vec<CallFrame>,
+    pub(crate) global_scope: HashMap<String, Value>,
+    pub(crate) constant_pool: Vec<Value>,
+    pub(crate) exception_handler: Option<ExceptionHandler>,
+    debugger: Option<Debugger>,
+    /// URL of the script executing under the debugger
+    script_url: String,
+    /// Stack depth below the debuggable execution, while one is running or paused
+    debug_base_depth: Option<usize>,
+    /// Skip the debugger check of the instruction execution was paused at
+    resuming: bool,
+}
+
+/// Outcome of an execution under the debugger
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)] // values are passed by value throughout the engine
+pub enum ExecutionStatus {
+    /// The function returned
+    Completed(Value),
+    /// Execution is suspended and can be continued with `BytecodeEngine::resume`
+    Paused(PauseReason),
+}
+
+/// Outcome of the fetch-decode-execute loop
+#[allow(clippy::large_enum_variant)]
+enum RunOutcome {
+    Returned(Value),
+    Paused(PauseReason),
 }
 
 /// Exception handler
@@ -355,177 +438,454 @@ impl BytecodeEngine {
             global_scope: HashMap::new(),
             constant_pool: Vec::new(),
             exception_handler: None,
+            debugger: None,
+            script_url: String::new(),
+            debug_base_depth: None,
+            resuming: false,
         }
     }
 
+    /// Execute a bytecode function with the given arguments
+    ///
+    /// The arguments are bound to the first locals of the function.
+    pub fn execute_function(&mut self, function: &BytecodeFunction, args: &[Value]) -> Result<Value> {
+        let local_count = function.instructions.iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::LoadLocal(_, index) | Instruction::StoreLocal(index, _) => Some(*index + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+            .max(args.len() as u32);
+
+        let function = FunctionValue {
+            name: "<anonymous>".to_string(),
+            bytecode: function.clone(),
+            param_count: args.len() as u32,
+            local_count,
+            closure: HashMap::new(),
+        };
+        self.execute(function, args.to_vec())
+    }
+
     /// Execute a bytecode function
     pub fn execute(&mut self, function: FunctionValue, args: Vec<Value>) -> Result<Value> {
-        // Create call frame
-        let mut frame = CallFrame::new(function, None);
-        
-        // Set up arguments
-        for (i, arg) in args.into_iter().enumerate() {
-            if i < frame.function.param_count as usize {
-                frame.set_local(i as u32, arg)?;
+        let base_depth = self.call_stack.len();
+        self.push_frame(function, args, None)?;
+
+        let result = self.run(base_depth).map_err(|error| self.locate_error(error));
+        match result {
+            Ok(RunOutcome::Returned(value)) => Ok(value),
+            // Only debuggable executions pause
+            Ok(RunOutcome::Paused(_)) => unreachable!("execution paused outside the debugger"),
+            Err(error) => {
+                // Unwind the frames of this execution so the engine can be reused
+                self.call_stack.truncate(base_depth);
+                Err(error)
             }
         }
+    }
 
-        // Push frame onto call stack
-        self.call_stack.push(frame);
+    /// Attach a debugger to executions started with `execute_debuggable`
+    pub fn attach_debugger(&mut self, debugger: Debugger) {
+        self.debugger = Some(debugger);
+    }
 
-        // Execute until return or error
-        self.run()?;
+    /// Get the attached debugger
+    pub fn debugger_mut(&mut self) -> Option<&mut Debugger> {
+        self.debugger.as_mut()
+    }
 
-        // Get return value from top frame
-        if let Some(frame) = self.call_stack.last() {
-            // For now, return undefined
-            Ok(Value::Undefined)
-        } else {
-            Ok(Value::Undefined)
+    /// Execute a script function that may pause in the debugger
+    ///
+    /// Functions called from native code run to completion without pausing.
+    pub fn execute_debuggable(&mut self, url: &str, function: FunctionValue, args: Vec<Value>) -> Result<ExecutionStatus> {
+        if self.debug_base_depth.is_some() {
+            return Err(Error::parsing("A debuggable execution is already running".to_string()));
+        }
+
+        self.debug_base_depth = Some(self.call_stack.len());
+        self.script_url = url.to_string();
+        self.push_frame(function, args, None)?;
+        self.continue_debuggable()
+    }
+
+    /// Continue a paused execution with a step or resume command
+    pub fn resume(&mut self, request: DebuggerRequest) -> Result<ExecutionStatus> {
+        if self.debug_base_depth.is_none() {
+            return Err(Error::parsing("Execution is not paused".to_string()));
+        }
+        let debugger = self.debugger.as_mut()
+            .ok_or_else(|| Error::parsing("No debugger is attached".to_string()))?;
+        if !debugger.handle_request(request) {
+            return Err(Error::parsing("Command does not continue execution".to_string()));
+        }
+
+        self.resuming = true;
+        self.continue_debuggable()
+    }
+
+    /// Run the debuggable execution until it returns or pauses
+    fn continue_debuggable(&mut self) -> Result<ExecutionStatus> {
+        let base_depth = self.debug_base_depth
+            .ok_or_else(|| Error::parsing("No debuggable execution is running".to_string()))?;
+
+        match self.run(base_depth).map_err(|error| self.locate_error(error)) {
+            Ok(RunOutcome::Paused(reason)) => Ok(ExecutionStatus::Paused(reason)),
+            Ok(RunOutcome::Returned(value)) => {
+                self.debug_base_depth = None;
+                Ok(ExecutionStatus::Completed(value))
+            }
+            Err(error) => {
+                self.call_stack.truncate(base_depth);
+                self.debug_base_depth = None;
+                Err(error)
+            }
+        }
+    }
+
+    /// Ask the debugger whether to pause before the next instruction
+    fn check_debugger(&mut self) -> Option<PauseReason> {
+        if std::mem::take(&mut self.resuming) {
+            return None;
+        }
+        let debugger = self.debugger.as_mut()?;
+        let depth = self.call_stack.len();
+        let frame = self.call_stack.last()?;
+
+        let is_debugger_statement = matches!(frame.current_instruction(), Some(Instruction::DebugBreak));
+        let position = frame.function.bytecode.source_map.as_ref()
+            .and_then(|source_map| source_map.position_at(frame.pc));
+        debugger.check(&self.script_url, depth, position.map(|position| (position.line, position.column)), is_debugger_statement)
+    }
+
+    /// Frames of the paused execution, innermost first
+    pub fn call_frames(&self) -> Vec<DebuggerCallFrame> {
+        let Some(base_depth) = self.debug_base_depth else {
+            return Vec::new();
+        };
+        let innermost = self.call_stack.len().saturating_sub(1);
+
+        self.call_stack.iter().enumerate().skip(base_depth).rev()
+            .map(|(index, frame)| {
+                // Calling frames have already moved past their call instruction
+                let pc = if index == innermost { frame.pc } else { frame.pc.saturating_sub(1) };
+                let position = frame.function.bytecode.source_map.as_ref()
+                    .and_then(|source_map| source_map.position_at(pc));
+
+                let mut scope: Vec<ScopeVariable> = frame.locals.iter().enumerate()
+                    .map(|(index, value)| ScopeVariable {
+                        name: format!("local{}", index),
+                        value: self.debug_string(value),
+                    })
+                    .collect();
+                let mut captured: Vec<_> = frame.function.closure.iter().collect();
+                captured.sort_by(|a, b| a.0.cmp(b.0));
+                scope.extend(captured.into_iter().map(|(name, value)| ScopeVariable {
+                    name: name.clone(),
+                    value: self.debug_string(value),
+                }));
+
+                DebuggerCallFrame {
+                    function_name: frame.function.name.clone(),
+                    url: self.script_url.clone(),
+                    line: position.as_ref().map_or(0, |position| position.line as u32),
+                    column: position.as_ref().map_or(0, |position| position.column as u32),
+                    scope,
+                }
+            })
+            .collect()
+    }
+
+    /// Render a value for the debugger, quoting strings
+    fn debug_string(&self, value: &Value) -> String {
+        match value {
+            Value::String(s) => format!("{:?}", s),
+            value => self.to_js_string(value),
         }
     }
 
-    /// Run the execution engine
-    fn run(&mut self) -> Result<()> {
-        while let Some(frame) = self.call_stack.last_mut() {
-            if frame.pc >= frame.function.bytecode.instructions.len() {
-                // Function completed
-                self.call_stack.pop();
-                continue;
+    /// Attach the source position of the failing instruction to an error
+    ///
+    /// Errors of functions without a source map are returned unchanged, as
+    /// are errors already located by a nested execution.
+    fn locate_error(&self, error: Error) -> Error {
+        if matches!(error, Error::Runtime { .. }) {
+            return error;
+        }
+        let Some(frame) = self.call_stack.last() else {
+            return error;
+        };
+        // The program counter has already moved past the failing instruction
+        let offset = frame.pc.saturating_sub(1);
+        match &frame.function.bytecode.source_map {
+            Some(source_map) if source_map.position_at(offset).is_some() => {
+                Error::runtime(error.to_string(), source_map.original_position(offset))
             }
+            _ => error,
+        }
+    }
 
-            let instruction = frame.current_instruction()
-                .ok_or_else(|| Error::parsing("Invalid program counter".to_string()))?;
+    /// Push a call frame, binding arguments to the first locals
+    fn push_frame(&mut self, function: FunctionValue, args: Vec<Value>, result_register: Option<Register>) -> Result<()> {
+        if self.call_stack.len() >= MAX_CALL_DEPTH {
+            return Err(Error::parsing("Maximum call stack size exceeded".to_string()));
+        }
+
+        let mut frame = CallFrame::new(function, None);
+        let param_count = frame.function.param_count as usize;
+        if frame.locals.len() < param_count {
+            frame.locals.resize(param_count, Value::Undefined);
+        }
+        for (i, arg) in args.into_iter().take(param_count).enumerate() {
+            frame.set_local(i as u32, arg)?;
+        }
+        frame.result_register = result_register;
+
+        self.call_stack.push(frame);
+        Ok(())
+    }
 
-            self.execute_instruction(instruction, frame)?;
+    /// Run the fetch-decode-execute loop until the frame at `base_depth` returns
+    ///
+    /// A debuggable execution stops early when the debugger pauses it,
+    /// leaving its frames on the stack.
+    fn run(&mut self, base_depth: usize) -> Result<RunOutcome> {
+        loop {
+            if self.debug_base_depth == Some(base_depth) {
+                if let Some(reason) = self.check_debugger() {
+                    return Ok(RunOutcome::Paused(reason));
+                }
+            }
+
+            let frame = self.current_frame()?;
+            // Falling off the end of a function returns undefined
+            let instruction = frame.current_instruction().cloned().unwrap_or(Instruction::ReturnUndefined);
             frame.advance_pc();
+
+            if let Some(value) = self.execute_instruction(&instruction)? {
+                let frame = self.call_stack.pop()
+                    .ok_or_else(|| Error::parsing("Call stack underflow".to_string()))?;
+                if self.call_stack.len() <= base_depth {
+                    return Ok(RunOutcome::Returned(value));
+                }
+                if let Some(register) = frame.result_register {
+                    self.current_frame()?.registers.set(register, value)?;
+                }
+            }
         }
+    }
+
+    /// Get the frame of the executing function
+    fn current_frame(&mut self) -> Result<&mut CallFrame> {
+        self.call_stack.last_mut()
+            .ok_or_else(|| Error::parsing("No function is executing".to_string()))
+    }
+
+    /// Read a register of the executing function
+    fn register(&mut self, register: Register) -> Result<Value> {
+        Ok(self.current_frame()?.registers.get(register)?.clone())
+    }
 
+    /// Write a register of the executing function
+    fn set_register(&mut self, register: Register, value: Value) -> Result<()> {
+        self.current_frame()?.registers.set(register, value)
+    }
+
+    /// Jump to a label of the executing function
+    fn jump(&mut self, label: Label) -> Result<()> {
+        let frame = self.current_frame()?;
+        let address = Self::label_address(label, frame)?;
+        frame.jump_to(address);
         Ok(())
     }
 
+    /// Apply a binary operation to two registers, storing the result in a third
+    fn binary_op(&mut self, a: Register, b: Register, result: Register, op: impl Fn(&Self, &Value, &Value) -> Result<Value>) -> Result<()> {
+        let a_val = self.register(a)?;
+        let b_val = self.register(b)?;
+        let result_val = op(self, &a_val, &b_val)?;
+        self.set_register(result, result_val)
+    }
+
     /// Execute a single instruction
-    fn execute_instruction(&mut self, instruction: &Instruction, frame: &mut CallFrame) -> Result<()> {
+    ///
+    /// Returns the return value when the instruction returns from the
+    /// executing function.
+    fn execute_instruction(&mut self, instruction: &Instruction) -> Result<Option<Value>> {
         match instruction {
             Instruction::LoadConstant(reg, idx) => {
                 let constant = self.get_constant(*idx)?;
-                frame.registers.set(*reg, constant.clone())?;
-            }
-            Instruction::LoadUndefined(reg) => {
-                frame.registers.set(*reg, Value::Undefined)?;
-            }
-            Instruction::LoadNull(reg) => {
-                frame.registers.set(*reg, Value::Null)?;
-            }
-            Instruction::LoadTrue(reg) => {
-                frame.registers.set(*reg, Value::Boolean(true))?;
-            }
-            Instruction::LoadFalse(reg) => {
-                frame.registers.set(*reg, Value::Boolean(false))?;
+                self.set_register(*reg, constant)?;
             }
+            Instruction::LoadUndefined(reg) => self.set_register(*reg, Value::Undefined)?,
+            Instruction::LoadNull(reg) => self.set_register(*reg, Value::Null)?,
+            Instruction::LoadTrue(reg) => self.set_register(*reg, Value::Boolean(true))?,
+            Instruction::LoadFalse(reg) => self.set_register(*reg, Value::Boolean(false))?,
             Instruction::LoadGlobal(reg, name) => {
                 let value = self.global_scope.get(name)
                     .cloned()
                     .unwrap_or(Value::Undefined);
-                frame.registers.set(*reg, value)?;
+                self.set_register(*reg, value)?;
             }
             Instruction::StoreGlobal(name, reg) => {
-                let value = frame.registers.get(*reg)?.clone();
+                let value = self.register(*reg)?;
                 self.global_scope.insert(name.clone(), value);
             }
             Instruction::LoadLocal(reg, idx) => {
+                let frame = self.current_frame()?;
                 let value = frame.get_local(*idx)?.clone();
                 frame.registers.set(*reg, value)?;
             }
             Instruction::StoreLocal(idx, reg) => {
+                let frame = self.current_frame()?;
                 let value = frame.registers.get(*reg)?.clone();
                 frame.set_local(*idx, value)?;
             }
-            Instruction::Add(a, b, result) => {
-                let a_val = frame.registers.get(*a)?;
-                let b_val = frame.registers.get(*b)?;
-                let result_val = self.add_values(a_val, b_val)?;
-                frame.registers.set(*result, result_val)?;
-            }
-            Instruction::Subtract(a, b, result) => {
-                let a_val = frame.registers.get(*a)?;
-                let b_val = frame.registers.get(*b)?;
-                let result_val = self.subtract_values(a_val, b_val)?;
-                frame.registers.set(*result, result_val)?;
-            }
-            Instruction::Multiply(a, b, result) => {
-                let a_val = frame.registers.get(*a)?;
-                let b_val = frame.registers.get(*b)?;
-                let result_val = self.multiply_values(a_val, b_val)?;
-                frame.registers.set(*result, result_val)?;
-            }
-            Instruction::Divide(a, b, result) => {
-                let a_val = frame.registers.get(*a)?;
-                let b_val = frame.registers.get(*b)?;
-                let result_val = self.divide_values(a_val, b_val)?;
-                frame.registers.set(*result, result_val)?;
+            Instruction::LoadProperty(obj, prop, result) | Instruction::LoadIndex(obj, prop, result) => {
+                let object = self.register(*obj)?;
+                let key = self.register(*prop)?;
+                let value = self.get_property(&object, &key)?;
+                self.set_register(*result, value)?;
+            }
+            Instruction::StoreProperty(obj, prop, value) | Instruction::StoreIndex(obj, prop, value) => {
+                let key = self.register(*prop)?;
+                let value = self.register(*value)?;
+                let mut object = self.register(*obj)?;
+                self.set_property(&mut object, &key, value)?;
+                self.set_register(*obj, object)?;
+            }
+            Instruction::Add(a, b, result) => self.binary_op(*a, *b, *result, Self::add_values)?,
+            Instruction::Subtract(a, b, result) => self.binary_op(*a, *b, *result, Self::subtract_values)?,
+            Instruction::Multiply(a, b, result) => self.binary_op(*a, *b, *result, Self::multiply_values)?,
+            Instruction::Divide(a, b, result) => self.binary_op(*a, *b, *result, Self::divide_values)?,
+            Instruction::Modulo(a, b, result) => {
+                self.binary_op(*a, *b, *result, |engine, a, b| Ok(Value::Number(engine.to_number(a) % engine.to_number(b))))?;
+            }
+            Instruction::Exponent(a, b, result) => {
+                self.binary_op(*a, *b, *result, |engine, a, b| Ok(Value::Number(engine.to_number(a).powf(engine.to_number(b)))))?;
+            }
+            Instruction::Negate(reg, result) => {
+                let value = self.register(*reg)?;
+                self.set_register(*result, Value::Number(-self.to_number(&value)))?;
+            }
+            Instruction::Increment(reg) => {
+                let value = self.register(*reg)?;
+                self.set_register(*reg, Value::Number(self.to_number(&value) + 1.0))?;
+            }
+            Instruction::Decrement(reg) => {
+                let value = self.register(*reg)?;
+                self.set_register(*reg, Value::Number(self.to_number(&value) - 1.0))?;
             }
             Instruction::Equal(a, b, result) => {
-                let a_val = frame.registers.get(*a)?;
-                let b_val = frame.registers.get(*b)?;
-                let result_val = Value::Boolean(self.equal_values(a_val, b_val));
-                frame.registers.set(*result, result_val)?;
+                self.binary_op(*a, *b, *result, |engine, a, b| Ok(Value::Boolean(engine.equal_values(a, b))))?;
             }
             Instruction::NotEqual(a, b, result) => {
-                let a_val = frame.registers.get(*a)?;
-                let b_val = frame.registers.get(*b)?;
-                let result_val = Value::Boolean(!self.equal_values(a_val, b_val));
-                frame.registers.set(*result, result_val)?;
+                self.binary_op(*a, *b, *result, |engine, a, b| Ok(Value::Boolean(!engine.equal_values(a, b))))?;
+            }
+            Instruction::StrictEqual(a, b, result) => {
+                self.binary_op(*a, *b, *result, |engine, a, b| Ok(Value::Boolean(engine.strict_equal_values(a, b))))?;
+            }
+            Instruction::StrictNotEqual(a, b, result) => {
+                self.binary_op(*a, *b, *result, |engine, a, b| Ok(Value::Boolean(!engine.strict_equal_values(a, b))))?;
+            }
+            Instruction::LessThan(a, b, result) => {
+                self.binary_op(*a, *b, *result, |engine, a, b| Ok(Value::Boolean(engine.compare_values(a, b).is_some_and(|o| o.is_lt()))))?;
+            }
+            Instruction::LessThanEqual(a, b, result) => {
+                self.binary_op(*a, *b, *result, |engine, a, b| Ok(Value::Boolean(engine.compare_values(a, b).is_some_and(|o| o.is_le()))))?;
+            }
+            Instruction::GreaterThan(a, b, result) => {
+                self.binary_op(*a, *b, *result, |engine, a, b| Ok(Value::Boolean(engine.compare_values(a, b).is_some_and(|o| o.is_gt()))))?;
+            }
+            Instruction::GreaterThanEqual(a, b, result) => {
+                self.binary_op(*a, *b, *result, |engine, a, b| Ok(Value::Boolean(engine.compare_values(a, b).is_some_and(|o| o.is_ge()))))?;
+            }
+            Instruction::LogicalAnd(a, b, result) => {
+                // Like `a && b`, the result is one of the operands
+                self.binary_op(*a, *b, *result, |engine, a, b| Ok(if engine.is_truthy(a) { b.clone() } else { a.clone() }))?;
+            }
+            Instruction::LogicalOr(a, b, result) => {
+                self.binary_op(*a, *b, *result, |engine, a, b| Ok(if engine.is_truthy(a) { a.clone() } else { b.clone() }))?;
             }
-            Instruction::Jump(label) => {
-                let address = self.get_label_address(*label, frame)?;
-                frame.jump_to(address);
+            Instruction::LogicalNot(reg, result) => {
+                let value = self.register(*reg)?;
+                self.set_register(*result, Value::Boolean(!self.is_truthy(&value)))?;
             }
+            Instruction::Jump(label) => self.jump(*label)?,
             Instruction::JumpIfTrue(reg, label) => {
-                let value = frame.registers.get(*reg)?;
-                if self.is_truthy(value) {
-                    let address = self.get_label_address(*label, frame)?;
-                    frame.jump_to(address);
+                let value = self.register(*reg)?;
+                if self.is_truthy(&value) {
+                    self.jump(*label)?;
                 }
             }
             Instruction::JumpIfFalse(reg, label) => {
-                let value = frame.registers.get(*reg)?;
-                if !self.is_truthy(value) {
-                    let address = self.get_label_address(*label, frame)?;
-                    frame.jump_to(address);
+                let value = self.register(*reg)?;
+                if !self.is_truthy(&value) {
+                    self.jump(*label)?;
                 }
             }
-            Instruction::Call(target, result, count) => {
-                let target_val = frame.registers.get(*target)?;
-                let result_val = self.call_function(target_val, Vec::new())?;
-                frame.registers.set(*result, result_val)?;
-            }
-            Instruction::Return(reg) => {
-                let return_value = frame.registers.get(*reg)?.clone();
-                // Store return value and pop frame
-                self.call_stack.pop();
-                if let Some(frame) = self.call_stack.last_mut() {
-                    // Set return value in calling frame
-                    frame.registers.set(Register(0), return_value)?;
+            Instruction::JumpIfNull(reg, label) => {
+                if matches!(self.register(*reg)?, Value::Null) {
+                    self.jump(*label)?;
                 }
             }
-            Instruction::ReturnUndefined => {
-                self.call_stack.pop();
+            Instruction::JumpIfUndefined(reg, label) => {
+                if matches!(self.register(*reg)?, Value::Undefined) {
+                    self.jump(*label)?;
+                }
             }
-            Instruction::CreateObject(reg) => {
-                let object = Value::Object(HashMap::new());
-                frame.registers.set(*reg, object)?;
+            Instruction::Call(target, result, count) => {
+                // Arguments are passed in the registers following the target
+                let target_val = self.register(*target)?;
+                let args = (1..=*count)
+                    .map(|offset| self.register(Register(target.0 + offset)))
+                    .collect::<Result<Vec<_>>>()?;
+                match target_val {
+                    Value::Function(function) => self.push_frame(function, args, Some(*result))?,
+                    Value::Proxy(_) => {
+                        let value = self.call_function(&target_val, args)?;
+                        self.set_register(*result, value)?;
+                    }
+                    _ => return Err(Error::parsing("Not a function".to_string())),
+                }
+            }
+            Instruction::CallConstructor(constructor, result, count) => {
+                let constructor_val = self.register(*constructor)?;
+                let args = (1..=*count)
+                    .map(|offset| self.register(Register(constructor.0 + offset)))
+                    .collect::<Result<Vec<_>>>()?;
+                let value = self.construct(&constructor_val, args)?;
+                self.set_register(*result, value)?;
             }
+            Instruction::Return(reg) => return Ok(Some(self.register(*reg)?)),
+            Instruction::ReturnUndefined => return Ok(Some(Value::Undefined)),
+            Instruction::CreateObject(reg) => self.set_register(*reg, Value::Object(HashMap::new()))?,
             Instruction::CreateArray(reg, len) => {
-                let array = Value::Array(vec![Value::Undefined; *len as usize]);
-                frame.registers.set(*reg, array)?;
+                self.set_register(*reg, Value::Array(vec![Value::Undefined; *len as usize]))?;
+            }
+            Instruction::TypeOf(reg, result) => {
+                let value = self.register(*reg)?;
+                self.set_register(*result, Value::String(Self::type_of(&value).to_string()))?;
+            }
+            Instruction::InstanceOf(obj, constructor, result) => {
+                let object = self.register(*obj)?;
+                let constructor = self.register(*constructor)?;
+                let is_instance = self.instance_of(&object, &constructor)?;
+                self.set_register(*result, Value::Boolean(is_instance))?;
+            }
+            Instruction::In(prop, obj, result) => {
+                let key = self.register(*prop)?;
+                let object = self.register(*obj)?;
+                let has = self.has_property(&object, &key)?;
+                self.set_register(*result, Value::Boolean(has))?;
             }
             Instruction::DebugPrint(reg) => {
-                let value = frame.registers.get(*reg)?;
+                let value = self.register(*reg)?;
                 println!("DEBUG: {:?}", value);
             }
-            Instruction::Nop => {
-                // Do nothing
+            Instruction::Nop | Instruction::DebugBreak => {
+                // Do nothing, the debugger pauses before `debugger` statements
             }
             Instruction::Halt => {
                 return Err(Error::parsing("Execution halted".to_string()));
@@ -536,97 +896,426 @@ impl BytecodeEngine {
             }
         }
 
-        Ok(())
+        Ok(None)
     }
 
-    /// Get a constant from the constant pool
-    fn get_constant(&self, index: ConstantIndex) -> Result<&Value> {
-        self.constant_pool.get(index.0 as usize)
+    /// Get a constant of the executing function
+    ///
+    /// Functions without constants of their own use the engine's constant pool.
+    fn get_constant(&mut self, index: ConstantIndex) -> Result<Value> {
+        let frame = self.current_frame()?;
+        let constants = &frame.function.bytecode.constants;
+        let constant = if constants.is_empty() {
+            self.constant_pool.get(index.0 as usize)
+        } else {
+            constants.get(index.0 as usize)
+        };
+        constant.cloned()
             .ok_or_else(|| Error::parsing(format!("Constant {} not found", index.0)))
     }
 
     /// Get label address
-    fn get_label_address(&self, label: Label, frame: &CallFrame) -> Result<usize> {
+    fn label_address(label: Label, frame: &CallFrame) -> Result<usize> {
         frame.function.bytecode.labels.get(&label)
             .copied()
             .ok_or_else(|| Error::parsing(format!("Label {} not found", label.0)))
     }
 
+    /// Get the `typeof` name of a value
+    ///
+    /// Proxies report the type of their target.
+    fn type_of(value: &Value) -> &'static str {
+        match value {
+            Value::Undefined => "undefined",
+            Value::Null | Value::Object(_) | Value::Array(_) => "object",
+            Value::Boolean(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Function(_) | Value::Class(_) => "function",
+            Value::Proxy(proxy) => Self::type_of(&proxy.target),
+        }
+    }
+
     /// Check if a value is truthy
-    fn is_truthy(&self, value: &Value) -> bool {
+    pub(crate) fn is_truthy(&self, value: &Value) -> bool {
         match value {
             Value::Undefined | Value::Null => false,
             Value::Boolean(b) => *b,
             Value::Number(n) => *n != 0.0 && !n.is_nan(),
             Value::String(s) => !s.is_empty(),
-            Value::Object(_) | Value::Array(_) | Value::Function(_) | Value::Class(_) => true,
+            Value::Object(_) | Value::Array(_) | Value::Function(_) | Value::Class(_) | Value::Proxy(_) => true,
+        }
+    }
+
+    /// Convert a value to a number
+    fn to_number(&self, value: &Value) -> f64 {
+        match value {
+            Value::Undefined => f64::NAN,
+            Value::Null => 0.0,
+            Value::Boolean(b) => if *b { 1.0 } else { 0.0 },
+            Value::Number(n) => *n,
+            Value::String(s) => {
+                let s = s.trim();
+                match s {
+                    "" => 0.0,
+                    "Infinity" | "+Infinity" => f64::INFINITY,
+                    "-Infinity" => f64::NEG_INFINITY,
+                    _ => s.parse().unwrap_or(f64::NAN),
+                }
+            }
+            Value::Object(_) | Value::Array(_) | Value::Function(_) | Value::Class(_) => f64::NAN,
+            Value::Proxy(proxy) => self.to_number(&proxy.target),
+        }
+    }
+
+    /// Convert a value to a string
+    fn to_js_string(&self, value: &Value) -> String {
+        match value {
+            Value::Undefined => "undefined".to_string(),
+            Value::Null => "null".to_string(),
+            Value::Boolean(b) => b.to_string(),
+            Value::Number(n) => number_to_string(*n),
+            Value::String(s) => s.clone(),
+            Value::Object(_) => "[object Object]".to_string(),
+            Value::Array(items) => items.iter()
+                .map(|item| match item {
+                    Value::Undefined | Value::Null => String::new(),
+                    item => self.to_js_string(item),
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            Value::Function(function) => format!("function {}() {{ [bytecode] }}", function.name),
+            Value::Class(class) => format!("class {} {{}}", class.name),
+            Value::Proxy(proxy) => self.to_js_string(&proxy.target),
         }
     }
 
     /// Add two values
-    fn add_values(&self, a: &Value, b: &Value) -> Result<Value> {
+    ///
+    /// Strings concatenate; everything else is added as IEEE 754 numbers.
+    pub(crate) fn add_values(&self, a: &Value, b: &Value) -> Result<Value> {
         match (a, b) {
-            (Value::Number(n1), Value::Number(n2)) => Ok(Value::Number(n1 + n2)),
-            (Value::String(s1), Value::String(s2)) => Ok(Value::String(s1.clone() + s2)),
-            (Value::String(s1), Value::Number(n2)) => Ok(Value::String(s1.clone() + &n2.to_string())),
-            (Value::Number(n1), Value::String(s2)) => Ok(Value::String(n1.to_string() + s2)),
-            _ => Ok(Value::Number(f64::NAN)),
+            (Value::String(_), _) | (_, Value::String(_)) => {
+                Ok(Value::String(self.to_js_string(a) + &self.to_js_string(b)))
+            }
+            _ => Ok(Value::Number(self.to_number(a) + self.to_number(b))),
         }
     }
 
     /// Subtract two values
-    fn subtract_values(&self, a: &Value, b: &Value) -> Result<Value> {
-        match (a, b) {
-            (Value::Number(n1), Value::Number(n2)) => Ok(Value::Number(n1 - n2)),
-            _ => Ok(Value::Number(f64::NAN)),
-        }
+    pub(crate) fn subtract_values(&self, a: &Value, b: &Value) -> Result<Value> {
+        Ok(Value::Number(self.to_number(a) - self.to_number(b)))
     }
 
     /// Multiply two values
-    fn multiply_values(&self, a: &Value, b: &Value) -> Result<Value> {
-        match (a, b) {
-            (Value::Number(n1), Value::Number(n2)) => Ok(Value::Number(n1 * n2)),
-            _ => Ok(Value::Number(f64::NAN)),
-        }
+    pub(crate) fn multiply_values(&self, a: &Value, b: &Value) -> Result<Value> {
+        Ok(Value::Number(self.to_number(a) * self.to_number(b)))
     }
 
     /// Divide two values
-    fn divide_values(&self, a: &Value, b: &Value) -> Result<Value> {
+    ///
+    /// Division by zero gives a signed infinity, or `NaN` for `0 / 0`.
+    pub(crate) fn divide_values(&self, a: &Value, b: &Value) -> Result<Value> {
+        Ok(Value::Number(self.to_number(a) / self.to_number(b)))
+    }
+
+    /// Check if two values are loosely equal (`==`)
+    pub(crate) fn equal_values(&self, a: &Value, b: &Value) -> bool {
         match (a, b) {
-            (Value::Number(n1), Value::Number(n2)) => {
-                if *n2 == 0.0 {
-                    Ok(Value::Number(f64::INFINITY))
-                } else {
-                    Ok(Value::Number(n1 / n2))
-                }
+            (Value::Undefined | Value::Null, Value::Undefined | Value::Null) => true,
+            (Value::Undefined | Value::Null, _) | (_, Value::Undefined | Value::Null) => false,
+            (Value::Number(_) | Value::Boolean(_), Value::String(_) | Value::Boolean(_))
+            | (Value::String(_) | Value::Boolean(_), Value::Number(_) | Value::Boolean(_)) => {
+                self.to_number(a) == self.to_number(b)
             }
-            _ => Ok(Value::Number(f64::NAN)),
+            _ => self.strict_equal_values(a, b),
         }
     }
 
-    /// Check if two values are equal
-    fn equal_values(&self, a: &Value, b: &Value) -> bool {
+    /// Check if two values are strictly equal (`===`)
+    ///
+    /// Objects are compared by value, since values have no identity here.
+    fn strict_equal_values(&self, a: &Value, b: &Value) -> bool {
         match (a, b) {
             (Value::Undefined, Value::Undefined) => true,
             (Value::Null, Value::Null) => true,
             (Value::Boolean(b1), Value::Boolean(b2)) => b1 == b2,
             (Value::Number(n1), Value::Number(n2)) => n1 == n2,
             (Value::String(s1), Value::String(s2)) => s1 == s2,
+            (Value::Object(o1), Value::Object(o2)) => {
+                o1.len() == o2.len()
+                    && o1.iter().all(|(key, value)| o2.get(key).is_some_and(|other| self.strict_equal_values(value, other)))
+            }
+            (Value::Array(a1), Value::Array(a2)) => {
+                a1.len() == a2.len() && a1.iter().zip(a2).all(|(x, y)| self.strict_equal_values(x, y))
+            }
+            (Value::Function(f1), Value::Function(f2)) => f1.name == f2.name,
+            (Value::Class(c1), Value::Class(c2)) => c1.name == c2.name,
+            (Value::Proxy(p1), Value::Proxy(p2)) => {
+                self.strict_equal_values(&p1.target, &p2.target)
+                    && self.strict_equal_values(&Value::Object(p1.handler.clone()), &Value::Object(p2.handler.clone()))
+            }
             _ => false,
         }
     }
 
-    /// Call a function
-    fn call_function(&self, target: &Value, args: Vec<Value>) -> Result<Value> {
-        match target {
-            Value::Function(func) => {
-                // For now, return undefined
-                Ok(Value::Undefined)
+    /// Compare two values for the relational operators
+    ///
+    /// Returns `None` when either side is `NaN`, making every comparison false.
+    fn compare_values(&self, a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+        match (a, b) {
+            (Value::String(s1), Value::String(s2)) => Some(s1.cmp(s2)),
+            _ => self.to_number(a).partial_cmp(&self.to_number(b)),
+        }
+    }
+
+    /// Get a property, calling the `get` trap of proxies
+    ///
+    /// Traps receive a copy of the target, since values have no identity here.
+    fn get_property(&mut self, object: &Value, key: &Value) -> Result<Value> {
+        match object {
+            Value::Proxy(proxy) => match proxy.trap("get") {
+                Some(trap) => {
+                    let key = Value::String(self.to_js_string(key));
+                    self.call_function(&trap, vec![proxy.target.clone(), key])
+                }
+                None => self.get_property(&proxy.target, key),
+            },
+            _ => self.reflect_get(object, key),
+        }
+    }
+
+    /// Set a property, calling the `set` trap of proxies
+    fn set_property(&mut self, object: &mut Value, key: &Value, value: Value) -> Result<()> {
+        match object {
+            Value::Proxy(proxy) => match proxy.trap("set") {
+                Some(trap) => {
+                    let key = Value::String(self.to_js_string(key));
+                    self.call_function(&trap, vec![proxy.target.clone(), key, value])?;
+                    Ok(())
+                }
+                None => self.set_property(&mut proxy.target, key, value),
+            },
+            _ => self.reflect_set(object, key, value),
+        }
+    }
+
+    /// Check for a property (`in`), calling the `has` trap of proxies
+    pub fn has_property(&mut self, object: &Value, key: &Value) -> Result<bool> {
+        match object {
+            Value::Proxy(proxy) => match proxy.trap("has") {
+                Some(trap) => {
+                    let key = Value::String(self.to_js_string(key));
+                    let result = self.call_function(&trap, vec![proxy.target.clone(), key])?;
+                    Ok(self.is_truthy(&result))
+                }
+                None => self.has_property(&proxy.target, key),
+            },
+            Value::Object(_) | Value::Array(_) | Value::Function(_) | Value::Class(_) => Ok(self.reflect_has(object, key)),
+            _ => Err(Error::parsing(format!(
+                "Cannot use 'in' operator to search for '{}' in {}", self.to_js_string(key), self.to_js_string(object)
+            ))),
+        }
+    }
+
+    /// Delete a property, calling the `deleteProperty` trap of proxies
+    ///
+    /// Returns whether the property was deleted.
+    pub fn delete_property(&mut self, object: &mut Value, key: &Value) -> Result<bool> {
+        let key_string = self.to_js_string(key);
+        match object {
+            Value::Proxy(proxy) => match proxy.trap("deleteProperty") {
+                Some(trap) => {
+                    let result = self.call_function(&trap, vec![proxy.target.clone(), Value::String(key_string)])?;
+                    Ok(self.is_truthy(&result))
+                }
+                None => self.delete_property(&mut proxy.target, key),
+            },
+            Value::Object(properties) => {
+                properties.remove(&key_string);
+                Ok(true)
+            }
+            Value::Array(items) => {
+                // Deleting an element leaves a hole rather than shifting the rest
+                if let Some(item) = key_string.parse::<usize>().ok().and_then(|index| items.get_mut(index)) {
+                    *item = Value::Undefined;
+                }
+                Ok(key_string != "length")
+            }
+            Value::Class(class) => {
+                class.properties.remove(&key_string);
+                Ok(true)
+            }
+            Value::Undefined | Value::Null => Err(Error::parsing(format!(
+                "Cannot delete property '{}' of {}", key_string, self.to_js_string(object)
+            ))),
+            _ => Ok(true),
+        }
+    }
+
+    /// Get the own property keys of an object, calling the `ownKeys` trap of proxies
+    pub fn own_keys(&mut self, object: &Value) -> Result<Vec<String>> {
+        match object {
+            Value::Proxy(proxy) => match proxy.trap("ownKeys") {
+                Some(trap) => match self.call_function(&trap, vec![proxy.target.clone()])? {
+                    Value::Array(keys) => Ok(keys.iter().map(|key| self.to_js_string(key)).collect()),
+                    _ => Err(Error::parsing("'ownKeys' on proxy: trap result is not an array".to_string())),
+                },
+                None => self.own_keys(&proxy.target),
+            },
+            Value::Object(properties) => {
+                let mut keys: Vec<String> = properties.keys().cloned().collect();
+                keys.sort();
+                Ok(keys)
+            }
+            Value::Array(items) => Ok((0..items.len())
+                .map(|index| index.to_string())
+                .chain(std::iter::once("length".to_string()))
+                .collect()),
+            Value::Class(class) => {
+                let mut keys: Vec<String> = class.properties.keys().cloned().collect();
+                keys.sort();
+                Ok(keys)
+            }
+            Value::Undefined | Value::Null => Err(Error::parsing(format!(
+                "Cannot convert {} to object", self.to_js_string(object)
+            ))),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Get a property of an object or an element of an array (`Reflect.get`)
+    ///
+    /// Proxy traps are not called; the operation acts on the target.
+    pub fn reflect_get(&self, object: &Value, key: &Value) -> Result<Value> {
+        match object {
+            Value::Object(properties) => Ok(properties.get(&self.to_js_string(key)).cloned().unwrap_or(Value::Undefined)),
+            Value::Array(items) => {
+                let key = self.to_js_string(key);
+                if key == "length" {
+                    return Ok(Value::Number(items.len() as f64));
+                }
+                Ok(key.parse::<usize>().ok()
+                    .and_then(|index| items.get(index).cloned())
+                    .unwrap_or(Value::Undefined))
+            }
+            Value::String(s) if self.to_js_string(key) == "length" => Ok(Value::Number(s.encode_utf16().count() as f64)),
+            Value::Class(class) => Ok(class.properties.get(&self.to_js_string(key)).cloned().unwrap_or(Value::Undefined)),
+            Value::Proxy(proxy) => self.reflect_get(&proxy.target, key),
+            Value::Undefined | Value::Null => Err(Error::parsing(format!(
+                "Cannot read property '{}' of {}", self.to_js_string(key), self.to_js_string(object)
+            ))),
+            _ => Ok(Value::Undefined),
+        }
+    }
+
+    /// Set a property of an object or an element of an array (`Reflect.set`)
+    ///
+    /// Proxy traps are not called; the operation acts on the target.
+    pub fn reflect_set(&self, object: &mut Value, key: &Value, value: Value) -> Result<()> {
+        let key = self.to_js_string(key);
+        match object {
+            Value::Object(properties) => {
+                properties.insert(key, value);
+            }
+            Value::Array(items) => {
+                if let Ok(index) = key.parse::<usize>() {
+                    if index >= items.len() {
+                        items.resize(index + 1, Value::Undefined);
+                    }
+                    items[index] = value;
+                }
             }
+            Value::Class(class) => {
+                class.properties.insert(key, value);
+            }
+            Value::Proxy(proxy) => {
+                return self.reflect_set(&mut proxy.target, &Value::String(key), value);
+            }
+            Value::Undefined | Value::Null => {
+                return Err(Error::parsing(format!("Cannot set property '{}' of {}", key, self.to_js_string(object))));
+            }
+            _ => {
+                // Properties of primitives are discarded
+            }
+        }
+        Ok(())
+    }
+
+    /// Check if an object has a property (`Reflect.has`)
+    ///
+    /// Proxy traps are not called; the operation acts on the target.
+    pub fn reflect_has(&self, object: &Value, key: &Value) -> bool {
+        let key = self.to_js_string(key);
+        match object {
+            Value::Object(properties) => properties.contains_key(&key),
+            Value::Array(items) => key == "length" || key.parse::<usize>().is_ok_and(|index| index < items.len()),
+            Value::Class(class) => class.properties.contains_key(&key),
+            Value::Proxy(proxy) => self.reflect_has(&proxy.target, &Value::String(key)),
+            _ => false,
+        }
+    }
+
+    /// Call a function to completion, calling the `apply` trap of proxies
+    pub fn call_function(&mut self, target: &Value, args: Vec<Value>) -> Result<Value> {
+        match target {
+            Value::Function(func) => self.execute(func.clone(), args),
+            Value::Proxy(proxy) => match proxy.trap("apply") {
+                Some(trap) => self.call_function(&trap, vec![proxy.target.clone(), Value::Undefined, Value::Array(args)]),
+                None => self.call_function(&proxy.target, args),
+            },
             _ => Err(Error::parsing("Not a function".to_string())),
         }
     }
 
+    /// Construct an object (`new`), calling the `construct` trap of proxies
+    ///
+    /// Objects remember their constructor in a `constructor` property, which
+    /// `instanceof` checks. A constructor returning an object replaces it.
+    pub fn construct(&mut self, target: &Value, args: Vec<Value>) -> Result<Value> {
+        let constructor = match target {
+            Value::Proxy(proxy) => {
+                return match proxy.trap("construct") {
+                    Some(trap) => match self.call_function(&trap, vec![proxy.target.clone(), Value::Array(args)])? {
+                        Value::Undefined | Value::Null | Value::Boolean(_) | Value::Number(_) | Value::String(_) => {
+                            Err(Error::parsing("'construct' on proxy: trap returned non-object".to_string()))
+                        }
+                        object => Ok(object),
+                    },
+                    None => self.construct(&proxy.target, args),
+                };
+            }
+            Value::Function(function) => Some(function.clone()),
+            Value::Class(class) => class.constructor.clone(),
+            _ => return Err(Error::parsing(format!("{} is not a constructor", self.to_js_string(target)))),
+        };
+
+        let mut properties = HashMap::new();
+        properties.insert("constructor".to_string(), target.clone());
+        let result = match constructor {
+            Some(function) => self.execute(function, args)?,
+            None => Value::Undefined,
+        };
+        match result {
+            Value::Object(_) | Value::Array(_) | Value::Function(_) | Value::Class(_) | Value::Proxy(_) => Ok(result),
+            _ => Ok(Value::Object(properties)),
+        }
+    }
+
+    /// Check if an object was created by a constructor (`instanceof`)
+    ///
+    /// Proxies on either side are transparent.
+    pub fn instance_of(&self, object: &Value, constructor: &Value) -> Result<bool> {
+        match (object, constructor) {
+            (_, Value::Proxy(proxy)) => self.instance_of(object, &proxy.target),
+            (Value::Proxy(proxy), _) => self.instance_of(&proxy.target, constructor),
+            (Value::Object(properties), Value::Function(_) | Value::Class(_)) => Ok(properties.get("constructor")
+                .is_some_and(|own| self.strict_equal_values(own, constructor))),
+            (_, Value::Function(_) | Value::Class(_)) => Ok(false),
+            _ => Err(Error::parsing("Right-hand side of 'instanceof' is not callable".to_string())),
+        }
+    }
+
     /// Get the current call stack depth
     pub fn call_stack_depth(&self) -> usize {
         self.call_stack.len()
@@ -643,13 +1332,26 @@ impl BytecodeEngine {
     }
 }
 
+/// Format a number the way JavaScript converts numbers to strings
+fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "Infinity".to_string() } else { "-Infinity".to_string() }
+    } else if n == 0.0 {
+        "0".to_string()
+    } else {
+        n.to_string()
+    }
+}
+
 /// Bytecode compiler
 pub struct BytecodeCompiler {
-    instructions: Vec<Instruction>,
-    constants: Vec<Value>,
-    labels: HashMap<Label, usize>,
-    next_label: u32,
-    next_register: u32,
+    pub(crate) instructions: Vec<Instruction>,
+    pub(crate) constants: Vec<Value>,
+    pub(crate) labels: HashMap<Label, usize>,
+    pub(crate) next_label: u32,
+    pub(crate) next_register: u32,
 }
 
 impl BytecodeCompiler {
@@ -735,10 +1437,10 @@ impl BytecodeCompiler {
                 let result_reg = self.allocate_register();
 
                 match binary.operator {
-                    crate::ast::BinaryOperator::Add => {
+                    crate::ast::BinaryOperator::Plus => {
                         self.add_instruction(Instruction::Add(left_reg, right_reg, result_reg));
                     }
-                    crate::ast::BinaryOperator::Subtract => {
+                    crate::ast::BinaryOperator::Minus => {
                         self.add_instruction(Instruction::Subtract(left_reg, right_reg, result_reg));
                     }
                     crate::ast::BinaryOperator::Multiply => {
@@ -1,7 +1,8 @@
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::bytecode::{BytecodeEngine, BytecodeCompiler, BytecodeFunction, Register, ConstantIndex, Label, Instruction, Value, FunctionValue, RegisterFile, CallFrame};
+    use crate::bytecode::{BytecodeEngine, BytecodeCompiler, BytecodeFunction, Register, ConstantIndex, Label, Instruction, Value, FunctionValue, JsProxy, RegisterFile, CallFrame};
+    use std::collections::HashMap;
 
     #[tokio::test]
     async fn test_register_creation() {
@@ -434,7 +435,195 @@ mod tests {
         // Execute the function
         let result = engine.execute(function, vec![]).unwrap();
         
-        // For now, the result should be undefined since the execution is simplified
-        assert!(matches!(result, Value::Undefined));
+        assert!(matches!(result, Value::Number(n) if n == 42.0));
+    }
+
+    /// Bytecode for `function fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }`
+    fn fibonacci_function() -> BytecodeFunction {
+        let mut labels = HashMap::new();
+        labels.insert(Label(0), 5);
+        
+        BytecodeFunction {
+            instructions: vec![
+                Instruction::LoadLocal(Register(0), 0),
+                Instruction::LoadConstant(Register(1), ConstantIndex(0)),
+                Instruction::LessThan(Register(0), Register(1), Register(2)),
+                Instruction::JumpIfFalse(Register(2), Label(0)),
+                Instruction::Return(Register(0)),
+                // L0: fib(n - 1), with the argument in the register after the callee
+                Instruction::LoadGlobal(Register(3), "fib".to_string()),
+                Instruction::LoadConstant(Register(5), ConstantIndex(1)),
+                Instruction::Subtract(Register(0), Register(5), Register(4)),
+                Instruction::Call(Register(3), Register(6), 1),
+                // fib(n - 2)
+                Instruction::LoadGlobal(Register(7), "fib".to_string()),
+                Instruction::Subtract(Register(0), Register(1), Register(8)),
+                Instruction::Call(Register(7), Register(9), 1),
+                Instruction::Add(Register(6), Register(9), Register(10)),
+                Instruction::Return(Register(10)),
+            ],
+            constants: vec![Value::Number(2.0), Value::Number(1.0)],
+            labels,
+            source_map: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bytecode_engine_fibonacci() {
+        let mut engine = BytecodeEngine::new();
+        let bytecode = fibonacci_function();
+        engine.set_global("fib".to_string(), Value::Function(FunctionValue {
+            name: "fib".to_string(),
+            bytecode: bytecode.clone(),
+            param_count: 1,
+            local_count: 1,
+            closure: HashMap::new(),
+        }));
+        
+        let result = engine.execute_function(&bytecode, &[Value::Number(20.0)]).unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 6765.0));
+        assert_eq!(engine.call_stack_depth(), 0);
+        
+        let result = engine.execute_function(&bytecode, &[Value::Number(1.0)]).unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 1.0));
+    }
+
+    #[tokio::test]
+    async fn test_bytecode_engine_ieee754_arithmetic() {
+        let bytecode = BytecodeFunction {
+            instructions: vec![
+                Instruction::LoadLocal(Register(0), 0),
+                Instruction::LoadLocal(Register(1), 1),
+                Instruction::Divide(Register(0), Register(1), Register(2)),
+                Instruction::Return(Register(2)),
+            ],
+            constants: vec![],
+            labels: HashMap::new(),
+            source_map: None,
+        };
+        let mut engine = BytecodeEngine::new();
+        let mut divide = |a: f64, b: f64| match engine.execute_function(&bytecode, &[Value::Number(a), Value::Number(b)]).unwrap() {
+            Value::Number(n) => n,
+            other => panic!("Expected number, got {:?}", other),
+        };
+        
+        assert_eq!(divide(1.0, 0.0), f64::INFINITY);
+        assert_eq!(divide(-1.0, 0.0), f64::NEG_INFINITY);
+        assert!(divide(0.0, 0.0).is_nan());
+        assert_eq!(divide(7.0, 2.0), 3.5);
+        
+        // NaN compares unequal to everything, including itself
+        let bytecode = BytecodeFunction {
+            instructions: vec![
+                Instruction::LoadLocal(Register(0), 0),
+                Instruction::LoadLocal(Register(1), 1),
+                Instruction::Equal(Register(0), Register(0), Register(2)),
+                Instruction::LessThan(Register(0), Register(1), Register(3)),
+                Instruction::GreaterThanEqual(Register(0), Register(1), Register(4)),
+                Instruction::LogicalOr(Register(2), Register(3), Register(5)),
+                Instruction::LogicalOr(Register(5), Register(4), Register(5)),
+                Instruction::Return(Register(5)),
+            ],
+            constants: vec![],
+            labels: HashMap::new(),
+            source_map: None,
+        };
+        let mut engine = BytecodeEngine::new();
+        let result = engine.execute_function(&bytecode, &[Value::Number(f64::NAN), Value::Number(1.0)]).unwrap();
+        assert!(matches!(result, Value::Boolean(false)));
+    }
+
+    #[tokio::test]
+    async fn test_bytecode_engine_objects() {
+        let bytecode = BytecodeFunction {
+            instructions: vec![
+                Instruction::CreateObject(Register(0)),
+                Instruction::LoadConstant(Register(1), ConstantIndex(0)),
+                Instruction::LoadConstant(Register(2), ConstantIndex(1)),
+                Instruction::StoreProperty(Register(0), Register(1), Register(2)),
+                Instruction::LoadProperty(Register(0), Register(1), Register(3)),
+                Instruction::Increment(Register(3)),
+                Instruction::Return(Register(3)),
+            ],
+            constants: vec![Value::String("count".to_string()), Value::Number(41.0)],
+            labels: HashMap::new(),
+            source_map: None,
+        };
+        
+        let mut engine = BytecodeEngine::new();
+        let result = engine.execute_function(&bytecode, &[]).unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 42.0));
+    }
+
+    #[tokio::test]
+    async fn test_bytecode_engine_proxy_get_trap() {
+        // handler.get = (target, property) => 42
+        let get_trap = FunctionValue {
+            name: "get".to_string(),
+            bytecode: BytecodeFunction {
+                instructions: vec![
+                    Instruction::LoadConstant(Register(0), ConstantIndex(0)),
+                    Instruction::Return(Register(0)),
+                ],
+                constants: vec![Value::Number(42.0)],
+                labels: HashMap::new(),
+                source_map: None,
+            },
+            param_count: 2,
+            local_count: 2,
+            closure: HashMap::new(),
+        };
+        let mut target = HashMap::new();
+        target.insert("count".to_string(), Value::Number(1.0));
+        let mut handler = HashMap::new();
+        handler.insert("get".to_string(), Value::Function(get_trap));
+        let proxy = Value::Proxy(Box::new(JsProxy::new(Value::Object(target), handler)));
+
+        // Returns [proxy.count, proxy.missing, typeof proxy]
+        let bytecode = BytecodeFunction {
+            instructions: vec![
+                Instruction::LoadLocal(Register(0), 0),
+                Instruction::LoadConstant(Register(1), ConstantIndex(0)),
+                Instruction::LoadConstant(Register(2), ConstantIndex(1)),
+                Instruction::CreateArray(Register(3), 3),
+                Instruction::LoadProperty(Register(0), Register(1), Register(4)),
+                Instruction::LoadProperty(Register(0), Register(2), Register(5)),
+                Instruction::TypeOf(Register(0), Register(6)),
+                Instruction::LoadConstant(Register(7), ConstantIndex(2)),
+                Instruction::StoreIndex(Register(3), Register(7), Register(4)),
+                Instruction::LoadConstant(Register(7), ConstantIndex(3)),
+                Instruction::StoreIndex(Register(3), Register(7), Register(5)),
+                Instruction::LoadConstant(Register(7), ConstantIndex(4)),
+                Instruction::StoreIndex(Register(3), Register(7), Register(6)),
+                Instruction::Return(Register(3)),
+            ],
+            constants: vec![
+                Value::String("count".to_string()),
+                Value::String("missing".to_string()),
+                Value::Number(0.0),
+                Value::Number(1.0),
+                Value::Number(2.0),
+            ],
+            labels: HashMap::new(),
+            source_map: None,
+        };
+
+        let mut engine = BytecodeEngine::new();
+        let result = engine.execute_function(&bytecode, std::slice::from_ref(&proxy)).unwrap();
+        match result {
+            Value::Array(items) => {
+                assert!(matches!(items[0], Value::Number(n) if n == 42.0));
+                assert!(matches!(items[1], Value::Number(n) if n == 42.0));
+                assert!(matches!(&items[2], Value::String(s) if s == "object"));
+            }
+            other => panic!("Expected array, got {:?}", other),
+        }
+        assert_eq!(engine.call_stack_depth(), 0);
+
+        // Reflect.get and the missing `has` trap both act on the target
+        let count = Value::String("count".to_string());
+        assert!(matches!(engine.reflect_get(&proxy, &count).unwrap(), Value::Number(n) if n == 1.0));
+        assert!(engine.has_property(&proxy, &count).unwrap());
+        assert!(!engine.reflect_has(&proxy, &Value::String("missing".to_string())));
     }
 }
@@ -1,5 +1,5 @@
 use crate::error::{Error, Result};
-use crate::ast::{ClassDeclaration, ClassExpression, MethodDefinition, ClassElement, Expression, Statement, Identifier, Literal};
+use crate::ast::{ClassDeclaration, ClassExpression, ClassElement, Expression, Statement, Literal};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -106,20 +106,31 @@ pub struct ClassPrototype {
     /// Prototype object
     pub object: HashMap<String, Value>,
     /// Constructor reference
-    pub constructor: Option<Value>,
+    pub constructor: Option<Box<Value>>,
     /// Superclass prototype (if any)
     pub super_prototype: Option<Box<ClassPrototype>>,
 }
 
+/// Native function behind a class method, called with the arguments and the receiver
+pub type MethodFunction = Arc<dyn Fn(&[Value], &mut ClassInstance) -> Result<Value> + Send + Sync>;
+
 /// Method implementation
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct MethodImplementation {
     /// Function that implements the method
-    pub function: Box<dyn Fn(&[Value], &mut ClassInstance) -> Result<Value> + Send + Sync>,
+    pub function: MethodFunction,
     /// Method signature
     pub signature: MethodSignature,
 }
 
+impl std::fmt::Debug for MethodImplementation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MethodImplementation")
+            .field("signature", &self.signature)
+            .finish_non_exhaustive()
+    }
+}
+
 /// Method signature
 #[derive(Debug, Clone)]
 pub struct MethodSignature {
@@ -327,7 +338,7 @@ impl MethodDefinition {
         };
 
         self.implementation = Some(MethodImplementation {
-            function: Box::new(implementation),
+            function: Arc::new(implementation),
             signature,
         });
     }
@@ -396,7 +407,7 @@ impl ClassInstance {
 
     /// Call a method
     pub fn call_method(&mut self, name: &str, args: &[Value]) -> Result<Value> {
-        if let Some(method) = self.class.get_method(name) {
+        if let Some(method) = self.class.get_method(name).cloned() {
             method.execute(args, self)
         } else {
             // Check prototype chain
@@ -475,7 +486,7 @@ impl ClassPrototype {
 
     /// Set the constructor
     pub fn set_constructor(&mut self, constructor: Value) {
-        self.constructor = Some(constructor);
+        self.constructor = Some(Box::new(constructor));
     }
 
     /// Set the superclass prototype
@@ -532,7 +543,7 @@ impl ClassSystem {
         let mut instance = ClassInstance::new(class_def);
 
         // Call constructor if it exists
-        if let Some(constructor) = &instance.class.constructor {
+        if let Some(constructor) = instance.class.constructor.clone() {
             constructor.execute(args, &mut instance)?;
         }
 
@@ -555,7 +566,7 @@ impl ClassSystem {
         let class_def = self.get_class(class_name).await
             .ok_or_else(|| Error::parsing(format!("Class '{}' not found", class_name)))?;
 
-        if let Some(method) = class_def.get_static_method(method_name) {
+        if let Some(method) = class_def.get_static_method(method_name).cloned() {
             let mut temp_instance = ClassInstance::new(class_def);
             method.execute(args, &mut temp_instance)
         } else {
@@ -612,11 +623,15 @@ impl ClassParser {
 
     /// Parse a class declaration
     pub async fn parse_class_declaration(&self, class_decl: &ClassDeclaration) -> Result<ClassDefinition> {
-        let mut class_def = ClassDefinition::new(class_decl.id.name.clone());
+        let name = class_decl.id.as_ref()
+            .map(|id| id.name.clone())
+            .unwrap_or_else(|| "default".to_string());
+
+        let mut class_def = ClassDefinition::new(name);
 
         // Parse superclass if present
         if let Some(superclass) = &class_decl.super_class {
-            let superclass_def = self.parse_class_expression(superclass).await?;
+            let superclass_def = self.parse_superclass(superclass).await?;
             class_def.set_superclass(superclass_def);
         }
 
@@ -638,7 +653,7 @@ impl ClassParser {
 
         // Parse superclass if present
         if let Some(superclass) = &class_expr.super_class {
-            let superclass_def = self.parse_class_expression(superclass).await?;
+            let superclass_def = self.parse_superclass(superclass).await?;
             class_def.set_superclass(superclass_def);
         }
 
@@ -650,86 +665,97 @@ impl ClassParser {
         Ok(class_def)
     }
 
+    /// Resolve the expression after `extends` to a class definition
+    async fn parse_superclass(&self, superclass: &Expression) -> Result<ClassDefinition> {
+        match superclass {
+            Expression::Class(class_expr) => Box::pin(self.parse_class_expression(class_expr)).await,
+            Expression::Identifier(ident) => self.class_system.get_class(&ident.name).await
+                .ok_or_else(|| Error::parsing(format!("Class '{}' not found", ident.name))),
+            _ => Err(Error::parsing("Unsupported superclass expression".to_string())),
+        }
+    }
+
     /// Parse a class element
     async fn parse_class_element(&self, class_def: &mut ClassDefinition, element: &ClassElement) -> Result<()> {
         match element {
             ClassElement::Method(method_def) => {
-                let method = self.parse_method_definition(method_def).await?;
-                
-                if method.is_constructor() {
-                    class_def.add_constructor(method);
-                } else if method.is_static {
-                    class_def.add_static_method(method);
-                } else {
-                    class_def.add_method(method);
-                }
+                let mut method = MethodDefinition::new(
+                    Self::property_key_name(&method_def.key)?,
+                    Self::method_kind(&method_def.kind),
+                    Vec::new(), // Parse parameters
+                    method_def.value.body.body.clone(),
+                );
+                method.set_static(method_def.static_);
+                Self::add_method_to(class_def, method);
+            }
+            ClassElement::PrivateMethod(method_def) => {
+                let mut method = MethodDefinition::new(
+                    method_def.key.id.name.clone(),
+                    Self::method_kind(&method_def.kind),
+                    Vec::new(), // Parse parameters
+                    method_def.value.body.body.clone(),
+                );
+                method.set_static(method_def.static_);
+                method.set_private(true);
+                Self::add_method_to(class_def, method);
             }
             ClassElement::Property(property_def) => {
-                let property = self.parse_property_definition(property_def).await?;
-                
-                if property_def.is_static {
+                let property = PropertyDefinition {
+                    name: Self::property_key_name(&property_def.key)?,
+                    value: None, // Parse value expression
+                    writable: true,
+                    enumerable: true,
+                    configurable: true,
+                    getter: None,
+                    setter: None,
+                };
+
+                if property_def.static_ {
                     class_def.add_static_property(property);
                 } else {
                     class_def.add_property(property);
                 }
             }
-            ClassElement::PrivateField(field_def) => {
-                let field = self.parse_private_field_definition(field_def).await?;
-                class_def.add_private_field(field);
-            }
-            _ => {
-                // Handle other class elements (getters, setters, etc.)
+            ClassElement::PrivateProperty(field_def) => {
+                class_def.add_private_field(PrivateFieldDefinition {
+                    name: field_def.key.id.name.clone(),
+                    value: None, // Parse value expression
+                    writable: true,
+                });
             }
         }
         Ok(())
     }
 
-    /// Parse a method definition
-    async fn parse_method_definition(&self, method_def: &MethodDefinition) -> Result<MethodDefinition> {
-        let kind = match method_def.kind {
+    /// Add a parsed method where its kind and staticness put it
+    fn add_method_to(class_def: &mut ClassDefinition, method: MethodDefinition) {
+        if method.is_constructor() {
+            class_def.add_constructor(method);
+        } else if method.is_static {
+            class_def.add_static_method(method);
+        } else {
+            class_def.add_method(method);
+        }
+    }
+
+    /// Map a method kind from the AST
+    fn method_kind(kind: &crate::ast::MethodKind) -> MethodKind {
+        match kind {
             crate::ast::MethodKind::Constructor => MethodKind::Constructor,
             crate::ast::MethodKind::Method => MethodKind::Method,
-            crate::ast::MethodKind::Getter => MethodKind::Getter,
-            crate::ast::MethodKind::Setter => MethodKind::Setter,
-        };
-
-        let mut method = MethodDefinition::new(
-            method_def.key.name.clone(),
-            kind,
-            Vec::new(), // Parse parameters
-            method_def.value.body.body.clone(),
-        );
-
-        method.set_static(method_def.is_static);
-        method.set_private(method_def.is_private);
-
-        Ok(method)
-    }
-
-    /// Parse a property definition
-    async fn parse_property_definition(&self, property_def: &crate::ast::PropertyDefinition) -> Result<PropertyDefinition> {
-        let property = PropertyDefinition {
-            name: property_def.key.name.clone(),
-            value: None, // Parse value expression
-            writable: true,
-            enumerable: true,
-            configurable: true,
-            getter: None,
-            setter: None,
-        };
-
-        Ok(property)
+            crate::ast::MethodKind::Get => MethodKind::Getter,
+            crate::ast::MethodKind::Set => MethodKind::Setter,
+        }
     }
 
-    /// Parse a private field definition
-    async fn parse_private_field_definition(&self, field_def: &crate::ast::PrivateFieldDefinition) -> Result<PrivateFieldDefinition> {
-        let field = PrivateFieldDefinition {
-            name: field_def.key.name.clone(),
-            value: None, // Parse value expression
-            writable: true,
-        };
-
-        Ok(field)
+    /// Name of a non-computed class member key
+    fn property_key_name(key: &Expression) -> Result<String> {
+        match key {
+            Expression::Identifier(ident) => Ok(ident.name.clone()),
+            Expression::Literal(Literal::String(s)) => Ok(s.clone()),
+            Expression::Literal(Literal::Number(n)) => Ok(n.to_string()),
+            _ => Err(Error::parsing("Unsupported class member key".to_string())),
+        }
     }
 
     /// Get the class system
@@ -130,7 +130,7 @@ mod tests {
         assert!(!instance.has_property("nonexistent"));
         
         assert!(matches!(instance.get_property("name"), Some(Value::String(s)) if s == "test"));
-        assert!(matches!(instance.get_property("value"), Some(Value::Number(n)) if n == 123.0));
+        assert!(matches!(instance.get_property("value"), Some(Value::Number(n)) if *n == 123.0));
         assert!(instance.get_property("nonexistent").is_none());
     }
 
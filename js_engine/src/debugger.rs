@@ -0,0 +1,7 @@
+//! Breakpoints and step execution for the bytecode engine
+//!
+//! The engine consults the attached `Debugger` before executing each
+//! instruction of a debuggable execution and suspends the current frame
+//! when the debugger asks it to pause.
+
+pub use common::debugger::{Breakpoint, Debugger};
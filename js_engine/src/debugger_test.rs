@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use crate::bytecode::{BytecodeEngine, BytecodeFunction, ConstantIndex, ExecutionStatus, FunctionValue, Instruction, Register, SourceMap, Value};
+    use crate::debugger::Debugger;
+    use common::ipc::{DebuggerRequest, PauseReason};
+    use std::collections::HashMap;
+
+    const URL: &str = "https://example.com/app.js";
+
+    /// `let a = 1;` / `let b = 2;` / `return a + b;`, one statement per line
+    fn three_line_script() -> FunctionValue {
+        let source = "let a = 1;\nlet b = 2;\nreturn a + b;".to_string();
+        FunctionValue {
+            name: "main".to_string(),
+            bytecode: BytecodeFunction {
+                instructions: vec![
+                    Instruction::LoadConstant(Register(0), ConstantIndex(0)),
+                    Instruction::StoreLocal(0, Register(0)),
+                    Instruction::LoadConstant(Register(1), ConstantIndex(1)),
+                    Instruction::StoreLocal(1, Register(1)),
+                    Instruction::LoadLocal(Register(2), 0),
+                    Instruction::LoadLocal(Register(3), 1),
+                    Instruction::Add(Register(2), Register(3), Register(4)),
+                    Instruction::Return(Register(4)),
+                ],
+                constants: vec![Value::Number(1.0), Value::Number(2.0)],
+                labels: HashMap::new(),
+                source_map: Some(SourceMap {
+                    mappings: vec![(0, 0), (2, 11), (4, 22)],
+                    source,
+                    script_map: None,
+                }),
+            },
+            param_count: 0,
+            local_count: 2,
+            closure: HashMap::new(),
+        }
+    }
+
+    fn set_breakpoint(engine: &mut BytecodeEngine, breakpoint_id: u64, line: u32) {
+        engine.debugger_mut().unwrap().handle_request(DebuggerRequest::SetBreakpoint {
+            breakpoint_id,
+            url: URL.to_string(),
+            line,
+            column: 0,
+        });
+    }
+
+    #[test]
+    fn test_breakpoint_pauses_and_step_over() {
+        let mut engine = BytecodeEngine::new();
+        engine.attach_debugger(Debugger::new());
+        set_breakpoint(&mut engine, 1, 2);
+
+        let status = engine.execute_debuggable(URL, three_line_script(), vec![]).unwrap();
+        assert!(matches!(status, ExecutionStatus::Paused(PauseReason::Breakpoint { breakpoint_id: 1 })));
+
+        // Paused before line 2 runs, with `a` already assigned
+        let frames = engine.call_frames();
+        assert_eq!(frames.len(), 1);
+        assert_eq!((frames[0].function_name.as_str(), frames[0].line, frames[0].url.as_str()), ("main", 2, URL));
+        let scope: Vec<_> = frames[0].scope.iter().map(|v| (v.name.as_str(), v.value.as_str())).collect();
+        assert_eq!(scope, [("local0", "1"), ("local1", "undefined")]);
+
+        let status = engine.resume(DebuggerRequest::StepOver).unwrap();
+        assert!(matches!(status, ExecutionStatus::Paused(PauseReason::Step)));
+        let frames = engine.call_frames();
+        assert_eq!(frames[0].line, 3);
+        assert_eq!(frames[0].scope[1].value, "2");
+
+        let status = engine.resume(DebuggerRequest::Resume).unwrap();
+        assert!(matches!(status, ExecutionStatus::Completed(Value::Number(n)) if n == 3.0));
+        assert!(engine.call_frames().is_empty());
+        assert!(engine.resume(DebuggerRequest::Resume).is_err());
+    }
+
+    #[test]
+    fn test_removed_breakpoint_does_not_pause() {
+        let mut engine = BytecodeEngine::new();
+        engine.attach_debugger(Debugger::new());
+        set_breakpoint(&mut engine, 1, 2);
+        engine.debugger_mut().unwrap().handle_request(DebuggerRequest::RemoveBreakpoint { breakpoint_id: 1 });
+        assert_eq!(engine.debugger_mut().unwrap().breakpoint_count(), 0);
+
+        let status = engine.execute_debuggable(URL, three_line_script(), vec![]).unwrap();
+        assert!(matches!(status, ExecutionStatus::Completed(_)));
+    }
+
+    #[test]
+    fn test_debugger_statement_pauses() {
+        let mut function = three_line_script();
+        function.bytecode.instructions.insert(0, Instruction::DebugBreak);
+        function.bytecode.source_map = None;
+
+        let mut engine = BytecodeEngine::new();
+        engine.attach_debugger(Debugger::new());
+        let status = engine.execute_debuggable(URL, function, vec![]).unwrap();
+        assert!(matches!(status, ExecutionStatus::Paused(PauseReason::DebuggerStatement)));
+
+        let status = engine.resume(DebuggerRequest::Resume).unwrap();
+        assert!(matches!(status, ExecutionStatus::Completed(_)));
+    }
+}
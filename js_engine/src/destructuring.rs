@@ -122,26 +122,16 @@ impl DestructuringEngine {
                     }
                     array_index += 1;
                 }
+                // Missing elements are undefined, which nested patterns
+                // reject and assignment patterns replace with their default
                 Some(Pattern::Object(obj_pattern)) => {
-                    if array_index < array.len() {
-                        self.destructure_object(obj_pattern, array[array_index].clone())?;
-                    } else {
-                        // Use default value if available
-                        if let Some(default) = self.context.defaults.get(&obj_pattern.to_string()) {
-                            self.destructure_object(obj_pattern, default.clone())?;
-                        }
-                    }
+                    let value = array.get(array_index).cloned().unwrap_or(Value::Undefined);
+                    self.destructure_object(obj_pattern, value)?;
                     array_index += 1;
                 }
                 Some(Pattern::Array(arr_pattern)) => {
-                    if array_index < array.len() {
-                        self.destructure_array(arr_pattern, array[array_index].clone())?;
-                    } else {
-                        // Use default value if available
-                        if let Some(default) = self.context.defaults.get(&arr_pattern.to_string()) {
-                            self.destructure_array(arr_pattern, default.clone())?;
-                        }
-                    }
+                    let value = array.get(array_index).cloned().unwrap_or(Value::Undefined);
+                    self.destructure_array(arr_pattern, value)?;
                     array_index += 1;
                 }
                 Some(Pattern::Rest(rest_pattern)) => {
@@ -151,28 +141,22 @@ impl DestructuringEngine {
                     } else {
                         Vec::new()
                     };
-                    self.context.rest_params.insert(rest_pattern.argument.name.clone(), rest_values);
-                }
-                Some(Pattern::Assignment(assignment_pattern)) => {
-                    // Handle assignment pattern with default
-                    if array_index < array.len() {
-                        self.destructure_assignment_pattern(assignment_pattern, array[array_index].clone())?;
-                    } else {
-                        // Use default value
-                        if let Some(default) = self.context.defaults.get(&assignment_pattern.to_string()) {
-                            self.destructure_assignment_pattern(assignment_pattern, default.clone())?;
+                    match &*rest_pattern.argument {
+                        Pattern::Identifier(ident) => {
+                            self.context.rest_params.insert(ident.name.clone(), rest_values);
                         }
+                        _ => return Err(Error::parsing("Rest element must be an identifier".to_string())),
                     }
+                }
+                Some(Pattern::Assignment(assignment_pattern)) => {
+                    let value = array.get(array_index).cloned().unwrap_or(Value::Undefined);
+                    self.destructure_assignment_pattern(assignment_pattern, value)?;
                     array_index += 1;
                 }
                 None => {
                     // Skip this position
                     array_index += 1;
                 }
-                _ => {
-                    // Handle other pattern types
-                    array_index += 1;
-                }
             }
         }
 
@@ -246,7 +230,7 @@ impl DestructuringEngine {
     fn destructure_assignment_pattern(&mut self, pattern: &AssignmentPattern, value: Value) -> Result<()> {
         // Check if value is undefined, use default if so
         let final_value = if matches!(value, Value::Undefined) {
-            match &pattern.right {
+            match &*pattern.right {
                 Expression::Literal(literal) => self.parse_literal(literal)?,
                 _ => {
                     // For now, just use undefined for complex expressions
@@ -257,7 +241,7 @@ impl DestructuringEngine {
             value
         };
 
-        match &pattern.left {
+        match &*pattern.left {
             Pattern::Identifier(ident) => {
                 self.context.variables.insert(ident.name.clone(), final_value);
             }
@@ -276,7 +260,7 @@ impl DestructuringEngine {
     }
 
     /// Convert a value to an object
-    fn convert_to_object(&self, value: Value) -> Result<HashMap<String, Value>> {
+    pub(crate) fn convert_to_object(&self, value: Value) -> Result<HashMap<String, Value>> {
         match value {
             Value::String(s) => {
                 let mut obj = HashMap::new();
@@ -309,7 +293,7 @@ impl DestructuringEngine {
     }
 
     /// Convert a value to an array
-    fn convert_to_array(&self, value: Value) -> Result<Vec<Value>> {
+    pub(crate) fn convert_to_array(&self, value: Value) -> Result<Vec<Value>> {
         match value {
             Value::String(s) => {
                 let chars: Vec<Value> = s.chars().map(|c| Value::String(c.to_string())).collect();
@@ -333,7 +317,7 @@ impl DestructuringEngine {
     }
 
     /// Parse a literal expression
-    fn parse_literal(&self, literal: &Literal) -> Result<Value> {
+    pub(crate) fn parse_literal(&self, literal: &Literal) -> Result<Value> {
         match literal {
             Literal::String(s) => Ok(Value::String(s.clone())),
             Literal::Number(n) => Ok(Value::Number(*n)),
@@ -518,7 +502,7 @@ impl DestructuringSystem {
 /// Pattern matcher for complex destructuring patterns
 pub struct PatternMatcher {
     /// Current matching context
-    context: HashMap<String, Value>,
+    pub(crate) context: HashMap<String, Value>,
 }
 
 impl PatternMatcher {
@@ -623,15 +607,19 @@ impl PatternMatcher {
 
     /// Match a rest pattern
     fn match_rest_pattern(&mut self, pattern: &RestElement, value: Value) -> Result<bool> {
-        // For now, just store the value
-        self.context.insert(pattern.argument.name.clone(), value);
-        Ok(true)
+        match &*pattern.argument {
+            Pattern::Identifier(ident) => {
+                self.context.insert(ident.name.clone(), value);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
     }
 
     /// Match an assignment pattern
     fn match_assignment_pattern(&mut self, pattern: &AssignmentPattern, value: Value) -> Result<bool> {
         let final_value = if matches!(value, Value::Undefined) {
-            match &pattern.right {
+            match &*pattern.right {
                 Expression::Literal(literal) => self.parse_literal(literal)?,
                 _ => Value::Undefined,
             }
@@ -643,7 +631,7 @@ impl PatternMatcher {
     }
 
     /// Parse a literal expression
-    fn parse_literal(&self, literal: &Literal) -> Result<Value> {
+    pub(crate) fn parse_literal(&self, literal: &Literal) -> Result<Value> {
         match literal {
             Literal::String(s) => Ok(Value::String(s.clone())),
             Literal::Number(n) => Ok(Value::Number(*n)),
@@ -2,6 +2,7 @@
 mod tests {
     use super::*;
     use crate::destructuring::{DestructuringSystem, DestructuringEngine, SpreadOperator, PatternMatcher, Value};
+    use std::collections::HashMap;
 
     #[tokio::test]
     async fn test_destructuring_engine_creation() {
@@ -69,10 +70,10 @@ mod tests {
         let result = spread_op.create_spread_array(&elements);
         
         assert_eq!(result.len(), 4);
-        assert!(matches!(result[0], Value::Number(n) if n == 1.0));
-        assert!(matches!(result[1], Value::Number(n) if n == 2.0));
-        assert!(matches!(result[2], Value::Number(n) if n == 3.0));
-        assert!(matches!(result[3], Value::Number(n) if n == 4.0));
+        assert!(matches!(&result[0], Value::Number(n) if *n == 1.0));
+        assert!(matches!(&result[1], Value::Number(n) if *n == 2.0));
+        assert!(matches!(&result[2], Value::Number(n) if *n == 3.0));
+        assert!(matches!(&result[3], Value::Number(n) if *n == 4.0));
     }
 
     #[tokio::test]
@@ -168,7 +169,7 @@ mod tests {
         // Test default operations
         context.defaults.insert("default".to_string(), Value::Number(42.0));
         assert!(context.defaults.contains_key("default"));
-        assert!(matches!(context.defaults.get("default"), Some(Value::Number(n)) if n == 42.0));
+        assert!(matches!(context.defaults.get("default"), Some(Value::Number(n)) if *n == 42.0));
         
         // Test rest parameter operations
         context.rest_params.insert("rest".to_string(), vec![Value::Number(1.0), Value::Number(2.0)]);
@@ -189,10 +190,10 @@ mod tests {
         let result = spread_op.spread_arguments(&args);
         
         assert_eq!(result.len(), 4);
-        assert!(matches!(result[0], Value::Number(n) if n == 1.0));
-        assert!(matches!(result[1], Value::Number(n) if n == 2.0));
-        assert!(matches!(result[2], Value::Number(n) if n == 3.0));
-        assert!(matches!(result[3], Value::String(s) if s == "test"));
+        assert!(matches!(&result[0], Value::Number(n) if *n == 1.0));
+        assert!(matches!(&result[1], Value::Number(n) if *n == 2.0));
+        assert!(matches!(&result[2], Value::Number(n) if *n == 3.0));
+        assert!(matches!(&result[3], Value::String(s) if *s == "test"));
     }
 
     #[tokio::test]
@@ -237,9 +238,9 @@ mod tests {
         let string_value = Value::String("abc".to_string());
         let arr_result = engine.convert_to_array(string_value).unwrap();
         assert_eq!(arr_result.len(), 3);
-        assert!(matches!(arr_result[0], Value::String(s) if s == "a"));
-        assert!(matches!(arr_result[1], Value::String(s) if s == "b"));
-        assert!(matches!(arr_result[2], Value::String(s) if s == "c"));
+        assert!(matches!(&arr_result[0], Value::String(s) if *s == "a"));
+        assert!(matches!(&arr_result[1], Value::String(s) if *s == "b"));
+        assert!(matches!(&arr_result[2], Value::String(s) if *s == "c"));
     }
 
     #[tokio::test]
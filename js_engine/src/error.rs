@@ -1,3 +1,4 @@
+use crate::source_map::OriginalPosition;
 use thiserror::Error;
 
 /// JavaScript engine error types
@@ -41,6 +42,34 @@ pub enum Error {
         message: String,
     },
 
+    /// JavaScript TypeError
+    #[error("TypeError: {message}")]
+    Type {
+        message: String,
+    },
+
+    /// JavaScript RangeError
+    #[error("RangeError: {message}")]
+    Range {
+        message: String,
+    },
+
+    /// DOM DataCloneError, thrown for values that can't be structured cloned
+    #[error("DataCloneError: {message}")]
+    DataClone {
+        message: String,
+    },
+
+    /// Error thrown while executing bytecode
+    ///
+    /// Carries the position in the original source when the script has a
+    /// source map.
+    #[error("{message}{}", .original_position.as_ref().map(|position| format!(" ({})", position)).unwrap_or_default())]
+    Runtime {
+        message: String,
+        original_position: Option<OriginalPosition>,
+    },
+
     /// IO error
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -97,4 +126,41 @@ impl Error {
             message: message.into(),
         }
     }
+
+    /// Create a new TypeError
+    pub fn type_error(message: impl Into<String>) -> Self {
+        Self::Type {
+            message: message.into(),
+        }
+    }
+
+    /// Create a new RangeError
+    pub fn range_error(message: impl Into<String>) -> Self {
+        Self::Range {
+            message: message.into(),
+        }
+    }
+
+    /// Create a new DataCloneError
+    pub fn data_clone(message: impl Into<String>) -> Self {
+        Self::DataClone {
+            message: message.into(),
+        }
+    }
+
+    /// Create a new runtime error
+    pub fn runtime(message: impl Into<String>, original_position: Option<OriginalPosition>) -> Self {
+        Self::Runtime {
+            message: message.into(),
+            original_position,
+        }
+    }
+
+    /// Get the original source position of a runtime error
+    pub fn original_position(&self) -> Option<&OriginalPosition> {
+        match self {
+            Self::Runtime { original_position, .. } => original_position.as_ref(),
+            _ => None,
+        }
+    }
 }
@@ -1,7 +1,8 @@
 use crate::error::{Error, Result};
-use crate::ast::{Program, Statement, ImportDeclaration, ExportDeclaration, Identifier, Literal};
+use crate::ast::{Program, Statement, ImportDeclaration, ExportDeclaration, Literal, Declaration, VariableDeclaration};
+use crate::async_await::{AsyncAwaitSystem, Promise, Value};
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -82,6 +83,47 @@ pub enum ModuleValue {
     Object(HashMap<String, ModuleValue>),
 }
 
+impl From<Value> for ModuleValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => ModuleValue::Null,
+            Value::Boolean(b) => ModuleValue::Boolean(b),
+            Value::Number(n) => ModuleValue::Number(n),
+            Value::String(s) => ModuleValue::String(s),
+            Value::Object(properties) => ModuleValue::Object(
+                properties.into_iter().map(|(key, value)| (key, ModuleValue::from(value))).collect(),
+            ),
+            _ => ModuleValue::Undefined,
+        }
+    }
+}
+
+impl From<ModuleValue> for Value {
+    fn from(value: ModuleValue) -> Self {
+        match value {
+            ModuleValue::Undefined => Value::Undefined,
+            ModuleValue::Null => Value::Null,
+            ModuleValue::Boolean(b) => Value::Boolean(b),
+            ModuleValue::Number(n) => Value::Number(n),
+            ModuleValue::String(s) => Value::String(s),
+            // Functions and classes are only named here, not callable
+            ModuleValue::Function(_) | ModuleValue::Class(_) => Value::Undefined,
+            ModuleValue::Object(properties) => Value::Object(
+                properties.into_iter().map(|(key, value)| (key, Value::from(value))).collect(),
+            ),
+        }
+    }
+}
+
+impl ModuleNamespace {
+    /// Convert the namespace to an object value
+    pub fn to_value(&self) -> Value {
+        Value::Object(
+            self.properties.iter().map(|(key, value)| (key.clone(), Value::from(value.clone()))).collect(),
+        )
+    }
+}
+
 /// ES Module loader and resolver
 pub struct ModuleLoader {
     /// Loaded modules cache
@@ -89,7 +131,7 @@ pub struct ModuleLoader {
     /// Module resolution cache
     resolution_cache: Arc<RwLock<HashMap<String, String>>>,
     /// Base URL for resolving relative imports
-    base_url: String,
+    pub(crate) base_url: String,
 }
 
 impl ModuleLoader {
@@ -163,24 +205,29 @@ impl ModuleLoader {
 
     /// Resolve a module specifier to a canonical URL
     async fn resolve_module_specifier(&self, specifier: &str) -> Result<String> {
+        self.resolve_from(specifier, &self.base_url).await
+    }
+
+    /// Resolve a module specifier relative to the URL of the importing module
+    pub async fn resolve_from(&self, specifier: &str, referrer_url: &str) -> Result<String> {
         // Handle different types of specifiers
-        if specifier.starts_with("http://") || specifier.starts_with("https://") {
-            // Absolute URL
-            Ok(specifier.to_string())
-        } else if specifier.starts_with('/') {
-            // Absolute path from base URL
-            let base_url = url::Url::parse(&self.base_url)
+        if specifier.starts_with('/') {
+            // Absolute path from the referrer's origin
+            let base_url = url::Url::parse(referrer_url)
                 .map_err(|e| Error::parsing(format!("Invalid base URL: {}", e)))?;
             let resolved = base_url.join(specifier)
                 .map_err(|e| Error::parsing(format!("Failed to resolve specifier: {}", e)))?;
             Ok(resolved.to_string())
         } else if specifier.starts_with("./") || specifier.starts_with("../") {
             // Relative path
-            let base_url = url::Url::parse(&self.base_url)
+            let base_url = url::Url::parse(referrer_url)
                 .map_err(|e| Error::parsing(format!("Invalid base URL: {}", e)))?;
             let resolved = base_url.join(specifier)
                 .map_err(|e| Error::parsing(format!("Failed to resolve specifier: {}", e)))?;
             Ok(resolved.to_string())
+        } else if let Ok(url) = url::Url::parse(specifier) {
+            // Absolute URL
+            Ok(url.to_string())
         } else {
             // Bare specifier - try to resolve as package
             self.resolve_bare_specifier(specifier).await
@@ -212,12 +259,18 @@ impl ModuleLoader {
             Ok(source)
         } else {
             // Read from file system
-            let path = Path::new(specifier);
+            let path = if specifier.starts_with("file://") {
+                url::Url::parse(specifier).ok()
+                    .and_then(|url| url.to_file_path().ok())
+                    .ok_or_else(|| Error::parsing(format!("Invalid file URL: {}", specifier)))?
+            } else {
+                PathBuf::from(specifier)
+            };
             if !path.exists() {
                 return Err(Error::parsing(format!("Module not found: {}", specifier)));
             }
 
-            let source = tokio::fs::read_to_string(path).await
+            let source = tokio::fs::read_to_string(&path).await
                 .map_err(|e| Error::parsing(format!("Failed to read file: {}", e)))?;
             
             Ok(source)
@@ -226,7 +279,8 @@ impl ModuleLoader {
 
     /// Analyze module for imports and exports
     async fn analyze_module(&self, module: &mut ModuleRecord) -> Result<()> {
-        for statement in &module.ast.body {
+        let body = module.ast.body.clone();
+        for statement in &body {
             match statement {
                 Statement::Import(import_decl) => {
                     self.analyze_import_declaration(module, import_decl).await?;
@@ -424,6 +478,8 @@ pub struct ModuleEvaluator {
     loader: ModuleLoader,
     /// Global scope
     global_scope: HashMap<String, ModuleValue>,
+    /// Evaluates module-level expressions, including top-level `await`
+    async_system: AsyncAwaitSystem,
 }
 
 impl ModuleEvaluator {
@@ -432,6 +488,7 @@ impl ModuleEvaluator {
         Self {
             loader,
             global_scope: HashMap::new(),
+            async_system: AsyncAwaitSystem::new(),
         }
     }
 
@@ -463,7 +520,7 @@ impl ModuleEvaluator {
         // Update module in cache
         {
             let mut modules = self.loader.modules.write().await;
-            modules.insert(specifier.to_string(), module);
+            modules.insert(module.specifier.clone(), module);
         }
 
         Ok(namespace)
@@ -475,12 +532,8 @@ impl ModuleEvaluator {
         module: &mut ModuleRecord,
         namespace: &mut ModuleNamespace,
     ) -> Result<()> {
-        // For now, this is a placeholder implementation
-        // In a real implementation, this would:
-        // 1. Set up module scope
-        // 2. Evaluate declarations
-        // 3. Create export bindings
-        // 4. Handle imports and dependencies
+        // Evaluate top-level variable declarations into the module scope
+        let scope = self.evaluate_declarations(&module.ast).await?;
 
         // Add exports to namespace
         for (name, binding) in &module.export_bindings {
@@ -489,7 +542,8 @@ impl ModuleEvaluator {
             } else if binding.is_reexport {
                 // Handle re-exports
                 if let Some(source) = &binding.source_module {
-                    let source_namespace = self.evaluate_module(source).await?;
+                    let source_url = self.loader.resolve_from(source, &module.specifier).await?;
+                    let source_namespace = Box::pin(self.evaluate_module(&source_url)).await?;
                     if binding.name == "*" {
                         // Re-export all
                         for (key, value) in &source_namespace.properties {
@@ -504,13 +558,52 @@ impl ModuleEvaluator {
                 }
             } else {
                 // Regular export
-                namespace.properties.insert(name.clone(), ModuleValue::Undefined);
+                let value = scope.get(&binding.local_name)
+                    .cloned()
+                    .map(ModuleValue::from)
+                    .unwrap_or(ModuleValue::Undefined);
+                namespace.properties.insert(name.clone(), value);
             }
         }
 
         Ok(())
     }
 
+    /// Evaluate the top-level variable declarations of a module in order
+    async fn evaluate_declarations(&self, program: &Program) -> Result<HashMap<String, Value>> {
+        let mut scope = HashMap::new();
+        for statement in &program.body {
+            let declaration = match statement {
+                Statement::Variable(var_decl) => var_decl,
+                Statement::Export(ExportDeclaration::Named(named_export)) => match &named_export.declaration {
+                    Some(Declaration::Variable(var_decl)) => var_decl,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            self.evaluate_variable_declaration(declaration, &mut scope).await?;
+        }
+        Ok(scope)
+    }
+
+    /// Evaluate the initializers of a variable declaration into `scope`
+    async fn evaluate_variable_declaration(
+        &self,
+        var_decl: &VariableDeclaration,
+        scope: &mut HashMap<String, Value>,
+    ) -> Result<()> {
+        for declarator in &var_decl.declarations {
+            if let crate::ast::Pattern::Identifier(id) = &declarator.id {
+                let value = match &declarator.init {
+                    Some(init) => self.async_system.evaluate_top_level(init, scope).await?,
+                    None => Value::Undefined,
+                };
+                scope.insert(id.name.clone(), value);
+            }
+        }
+        Ok(())
+    }
+
     /// Get global scope
     pub fn get_global_scope(&self) -> &HashMap<String, ModuleValue> {
         &self.global_scope
@@ -547,6 +640,34 @@ impl ESModuleSystem {
         self.evaluator.evaluate_module(specifier).await
     }
 
+    /// Import a module at runtime, as `import(specifier)` does
+    ///
+    /// The specifier is resolved against `referrer_url`. The returned promise
+    /// is fulfilled with the module's namespace object, or rejected if the
+    /// module can't be loaded, parsed or evaluated.
+    pub async fn dynamic_import(&self, specifier: &str, referrer_url: &str) -> Promise {
+        let async_system = &self.evaluator.async_system;
+        match self.import_namespace(specifier, referrer_url).await {
+            Ok(namespace) => async_system.resolve(namespace.to_value()),
+            Err(error) => async_system.reject(Value::String(error.to_string())),
+        }
+    }
+
+    /// Resolve, load and evaluate a dynamically imported module
+    async fn import_namespace(&self, specifier: &str, referrer_url: &str) -> Result<ModuleNamespace> {
+        let resolved = self.loader.resolve_from(specifier, referrer_url).await?;
+
+        // Reuse the module if it has already been evaluated
+        {
+            let modules = self.loader.modules.read().await;
+            if let Some(namespace) = modules.get(&resolved).and_then(|module| module.namespace.clone()) {
+                return Ok(namespace);
+            }
+        }
+
+        self.evaluator.evaluate_module(&resolved).await
+    }
+
     /// Get module loader
     pub fn get_loader(&self) -> &ModuleLoader {
         &self.loader
@@ -97,4 +97,52 @@ mod tests {
             _ => panic!("Expected object value"),
         }
     }
+
+    /// Write a module to a fresh directory and return the directory's URL
+    fn write_module(test_name: &str, file_name: &str, source: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("matte_{}_{}", test_name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(file_name), source).unwrap();
+        format!("file://{}/", dir.display())
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_import_resolves_namespace() {
+        use crate::async_await::{PromiseState, Value};
+
+        let dir_url = write_module("dynamic_import", "answer.js", "export const answer = 42;\nexport const ready = await true;\n");
+        let module_system = ESModuleSystem::new(dir_url.clone());
+        let referrer = format!("{}main.js", dir_url);
+
+        let promise = module_system.dynamic_import("./answer.js", &referrer).await;
+        match promise.state() {
+            PromiseState::Fulfilled(Value::Object(namespace)) => {
+                assert!(matches!(namespace.get("answer"), Some(Value::Number(n)) if *n == 42.0));
+                assert!(matches!(namespace.get("ready"), Some(Value::Boolean(true))));
+            }
+            state => panic!("Expected fulfilled namespace, got {:?}", state),
+        }
+
+        // The second import is served from the module cache
+        let loaded = module_system.get_loader().get_loaded_modules().await;
+        assert_eq!(loaded.len(), 1);
+        assert!(loaded.values().all(|module| module.evaluated));
+        let promise = module_system.dynamic_import("./answer.js", &referrer).await;
+        assert!(matches!(promise.state(), PromiseState::Fulfilled(_)));
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_import_rejects_on_failure() {
+        use crate::async_await::PromiseState;
+
+        let dir_url = write_module("dynamic_import_error", "broken.js", "export const = ;\n");
+        let module_system = ESModuleSystem::new(dir_url.clone());
+        let referrer = format!("{}main.js", dir_url);
+
+        let promise = module_system.dynamic_import("./missing.js", &referrer).await;
+        assert!(matches!(promise.state(), PromiseState::Rejected(_)));
+
+        let promise = module_system.dynamic_import("./broken.js", &referrer).await;
+        assert!(matches!(promise.state(), PromiseState::Rejected(_)));
+    }
 }
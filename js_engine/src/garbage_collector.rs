@@ -1,4 +1,5 @@
 use crate::error::{Error, Result};
+use common::heap::{HeapInspector, HeapObject};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use parking_lot::RwLock;
@@ -30,6 +31,28 @@ pub enum ReferenceState {
     Marked,
 }
 
+/// Tri-color marking state of an object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GCColor {
+    /// Not visited yet; collected if still white after marking
+    White,
+    /// Reachable, but its references haven't been scanned
+    Gray,
+    /// Reachable, with all its references scanned
+    Black,
+}
+
+/// Phase of an incremental collection cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GCPhase {
+    /// No cycle in progress
+    Idle,
+    /// Tracing reachable objects from the roots
+    Mark,
+    /// Freeing objects left white by marking
+    Sweep,
+}
+
 /// Memory object representation
 #[derive(Debug, Clone)]
 pub struct MemoryObject {
@@ -43,6 +66,8 @@ pub struct MemoryObject {
     pub reference_count: u32,
     /// Reference state
     pub state: ReferenceState,
+    /// Marking color in the current collection cycle
+    pub color: GCColor,
     /// Creation timestamp
     pub created_at: Instant,
     /// Last access timestamp
@@ -104,6 +129,14 @@ pub struct GCStats {
     pub dead_objects: u64,
     /// Collection frequency (collections per minute)
     pub collection_frequency: f64,
+    /// Objects freed by the last incremental step
+    pub objects_freed: u64,
+    /// Bytes freed by the last incremental step
+    pub bytes_freed: usize,
+    /// Whether marking of the current cycle has finished
+    pub mark_complete: bool,
+    /// Whether the last incremental step finished a cycle
+    pub sweep_complete: bool,
 }
 
 /// Garbage collection configuration
@@ -121,6 +154,8 @@ pub struct GCConfig {
     pub enabled: bool,
     /// Collection timeout (milliseconds)
     pub collection_timeout_ms: u64,
+    /// Number of objects processed per incremental step
+    pub incremental_slice_size: usize,
     /// Generational GC settings
     pub generational_config: GenerationalConfig,
     /// Incremental GC settings
@@ -161,10 +196,14 @@ pub struct GarbageCollector {
     stats: Arc<RwLock<GCStats>>,
     /// Object ID counter
     next_object_id: Arc<RwLock<u64>>,
-    /// Collection queue for incremental GC
+    /// Gray objects waiting to be scanned
     collection_queue: Arc<RwLock<VecDeque<u64>>>,
-    /// Write barriers for incremental GC
+    /// Objects whose references changed during marking
     write_barriers: Arc<RwLock<HashSet<u64>>>,
+    /// Current phase of the incremental cycle
+    phase: Arc<RwLock<GCPhase>>,
+    /// Objects left to examine in the sweep phase
+    sweep_queue: Arc<RwLock<Vec<u64>>>,
 }
 
 impl Default for GCConfig {
@@ -176,6 +215,7 @@ impl Default for GCConfig {
             max_heap_size: 100 * 1024 * 1024, // 100MB
             enabled: true,
             collection_timeout_ms: 5000, // 5 seconds
+            incremental_slice_size: 100,
             generational_config: GenerationalConfig {
                 generations: 3,
                 promotion_thresholds: vec![1, 10, 100],
@@ -204,6 +244,10 @@ impl GarbageCollector {
             live_objects: 0,
             dead_objects: 0,
             collection_frequency: 0.0,
+            objects_freed: 0,
+            bytes_freed: 0,
+            mark_complete: false,
+            sweep_complete: false,
         };
 
         Self {
@@ -214,16 +258,28 @@ impl GarbageCollector {
             next_object_id: Arc::new(RwLock::new(1)),
             collection_queue: Arc::new(RwLock::new(VecDeque::new())),
             write_barriers: Arc::new(RwLock::new(HashSet::new())),
+            phase: Arc::new(RwLock::new(GCPhase::Idle)),
+            sweep_queue: Arc::new(RwLock::new(Vec::new())),
         }
     }
 
     /// Allocate a new memory object
+    ///
+    /// Objects allocated while marking are black, so the cycle in progress
+    /// doesn't free them.
     pub fn allocate(&self, object_type: &str, size: usize, data: Vec<u8>) -> Result<u64> {
-        let mut objects = self.objects.write();
-        let mut next_id = self.next_object_id.write();
-        
-        let object_id = *next_id;
-        *next_id += 1;
+        let object_id = {
+            let mut next_id = self.next_object_id.write();
+            let object_id = *next_id;
+            *next_id += 1;
+            object_id
+        };
+
+        let color = if *self.phase.read() == GCPhase::Mark {
+            GCColor::Black
+        } else {
+            GCColor::White
+        };
 
         let object = MemoryObject {
             id: object_id,
@@ -231,6 +287,7 @@ impl GarbageCollector {
             size,
             reference_count: 1,
             state: ReferenceState::Reachable,
+            color,
             created_at: Instant::now(),
             last_accessed: Instant::now(),
             generation: 0,
@@ -238,18 +295,18 @@ impl GarbageCollector {
             references: Vec::new(),
         };
 
-        objects.insert(object_id, object);
+        self.objects.write().insert(object_id, object);
         
         // Update statistics
         self.update_heap_stats();
         
-        // Check if collection is needed
-        self.check_collection_needed().await;
-        
         Ok(object_id)
     }
 
     /// Add a reference to an object
+    ///
+    /// While marking, an object that was already scanned is recorded by the
+    /// write barrier so the new reference gets traced before marking ends.
     pub fn add_reference(&self, object_id: u64, reference_id: u64) -> Result<()> {
         let mut objects = self.objects.write();
         
@@ -265,6 +322,11 @@ impl GarbageCollector {
         } else {
             return Err(Error::parsing(format!("Object {} not found", object_id)));
         }
+
+        // Without the barrier set, shade the new target right away
+        if !self.config.incremental_config.use_write_barriers && *self.phase.read() == GCPhase::Mark {
+            self.shade_gray(&mut objects, reference_id);
+        }
         
         Ok(())
     }
@@ -291,6 +353,14 @@ impl GarbageCollector {
 
     /// Add a root reference
     pub fn add_root(&self, root_id: &str, object_ids: Vec<u64>, root_type: RootType) -> Result<()> {
+        // Roots added while marking are traced by the cycle in progress
+        if *self.phase.read() == GCPhase::Mark {
+            let mut objects = self.objects.write();
+            for &object_id in &object_ids {
+                self.shade_gray(&mut objects, object_id);
+            }
+        }
+
         let mut roots = self.roots.write();
         
         let root = RootReference {
@@ -333,78 +403,189 @@ impl GarbageCollector {
 
     /// Mark and sweep garbage collection
     async fn mark_and_sweep(&self) -> Result<()> {
-        // Mark phase: mark all reachable objects
-        self.mark_phase().await?;
-        
-        // Sweep phase: collect unreachable objects
-        self.sweep_phase().await?;
+        // Finish any incremental cycle in progress, then run a full one
+        if self.phase() != GCPhase::Idle {
+            while !self.incremental_step(usize::MAX).sweep_complete {}
+        }
+        while !self.incremental_step(usize::MAX).sweep_complete {}
         
         Ok(())
     }
 
-    /// Mark phase of garbage collection
-    async fn mark_phase(&self) -> Result<()> {
+    /// Perform one step of incremental collection
+    ///
+    /// Each call processes up to `incremental_slice_size` objects, starting a
+    /// new cycle if none is in progress and resuming the current one
+    /// otherwise.
+    pub fn collect_incremental(&self) -> GCStats {
+        let stats = self.incremental_step(self.config.incremental_slice_size);
+        if stats.sweep_complete {
+            self.stats.write().total_collections += 1;
+        }
+        self.get_stats()
+    }
+
+    /// Get the phase of the current collection cycle
+    pub fn phase(&self) -> GCPhase {
+        *self.phase.read()
+    }
+
+    /// Process up to `budget` objects of the current collection cycle
+    fn incremental_step(&self, budget: usize) -> GCStats {
+        let mut budget = budget.max(1);
+        let mut objects_freed = 0;
+        let mut bytes_freed = 0;
+        let mut sweep_complete = false;
+
+        if self.phase() == GCPhase::Idle {
+            self.start_cycle();
+        }
+
+        if self.phase() == GCPhase::Mark {
+            budget -= self.mark_slice(budget);
+            let gray_remaining = !self.collection_queue.read().is_empty();
+            if !gray_remaining && !self.rescan_write_barriers() {
+                self.finish_marking();
+            }
+        }
+
+        if self.phase() == GCPhase::Sweep && budget > 0 {
+            let (freed, bytes, done) = self.sweep_slice(budget);
+            objects_freed = freed;
+            bytes_freed = bytes;
+            sweep_complete = done;
+        }
+
+        self.update_heap_stats();
+
+        let mark_complete = self.phase() != GCPhase::Mark;
+        let mut stats = self.stats.write();
+        stats.total_objects_collected += objects_freed;
+        stats.total_memory_freed += bytes_freed;
+        stats.objects_freed = objects_freed;
+        stats.bytes_freed = bytes_freed;
+        stats.mark_complete = mark_complete;
+        stats.sweep_complete = sweep_complete;
+        stats.clone()
+    }
+
+    /// Start a cycle: whiten every object and shade the roots gray
+    fn start_cycle(&self) {
         let mut objects = self.objects.write();
-        let roots = self.roots.read();
-        
-        // Reset all objects to unreachable
         for object in objects.values_mut() {
+            object.color = GCColor::White;
             object.state = ReferenceState::Unreachable;
         }
-        
-        // Mark objects reachable from roots
+
+        self.collection_queue.write().clear();
+        self.write_barriers.write().clear();
+
+        let roots = self.roots.read();
         for root in roots.iter() {
             for &object_id in &root.object_ids {
-                self.mark_object_recursive(&mut objects, object_id).await?;
+                self.shade_gray(&mut objects, object_id);
             }
         }
-        
-        Ok(())
+
+        *self.phase.write() = GCPhase::Mark;
     }
 
-    /// Recursively mark an object and its references
-    async fn mark_object_recursive(&self, objects: &mut HashMap<u64, MemoryObject>, object_id: u64) -> Result<()> {
+    /// Shade a white object gray and queue it for scanning
+    fn shade_gray(&self, objects: &mut HashMap<u64, MemoryObject>, object_id: u64) {
         if let Some(object) = objects.get_mut(&object_id) {
-            if object.state == ReferenceState::Unreachable {
-                object.state = ReferenceState::Reachable;
-                
-                // Recursively mark referenced objects
-                for &reference_id in &object.references {
-                    self.mark_object_recursive(objects, reference_id).await?;
+            if object.color == GCColor::White {
+                object.color = GCColor::Gray;
+                object.state = ReferenceState::Processing;
+                self.collection_queue.write().push_back(object_id);
+            }
+        }
+    }
+
+    /// Scan up to `budget` gray objects, returning how many were scanned
+    fn mark_slice(&self, budget: usize) -> usize {
+        let mut objects = self.objects.write();
+        let mut scanned = 0;
+
+        while scanned < budget {
+            let Some(object_id) = self.collection_queue.write().pop_front() else {
+                break;
+            };
+
+            let references = match objects.get_mut(&object_id) {
+                Some(object) if object.color == GCColor::Gray => {
+                    object.color = GCColor::Black;
+                    object.state = ReferenceState::Reachable;
+                    object.references.clone()
                 }
+                _ => continue,
+            };
+
+            for reference_id in references {
+                self.shade_gray(&mut objects, reference_id);
             }
+            scanned += 1;
         }
-        
-        Ok(())
+
+        scanned
     }
 
-    /// Sweep phase of garbage collection
-    async fn sweep_phase(&self) -> Result<()> {
+    /// Re-scan black objects whose references changed during marking
+    ///
+    /// Returns whether any object was shaded gray.
+    fn rescan_write_barriers(&self) -> bool {
+        let barriers: Vec<u64> = self.write_barriers.write().drain().collect();
         let mut objects = self.objects.write();
-        let mut stats = self.stats.write();
-        
-        let mut objects_to_remove = Vec::new();
-        let mut memory_freed = 0;
-        let mut objects_collected = 0;
-        
-        for (object_id, object) in objects.iter() {
-            if object.state == ReferenceState::Unreachable {
-                objects_to_remove.push(*object_id);
-                memory_freed += object.size;
-                objects_collected += 1;
+
+        let mut shaded = false;
+        for object_id in barriers {
+            let references = match objects.get(&object_id) {
+                Some(object) if object.color == GCColor::Black => object.references.clone(),
+                _ => continue,
+            };
+            for reference_id in references {
+                if objects.get(&reference_id).is_some_and(|object| object.color == GCColor::White) {
+                    self.shade_gray(&mut objects, reference_id);
+                    shaded = true;
+                }
             }
         }
-        
-        // Remove unreachable objects
-        for object_id in objects_to_remove {
-            objects.remove(&object_id);
+
+        shaded
+    }
+
+    /// End marking and queue every object for the sweep
+    fn finish_marking(&self) {
+        let objects = self.objects.read();
+        *self.sweep_queue.write() = objects.keys().copied().collect();
+        *self.phase.write() = GCPhase::Sweep;
+    }
+
+    /// Examine up to `budget` objects, freeing the white ones
+    ///
+    /// Returns the objects and bytes freed and whether the sweep finished.
+    fn sweep_slice(&self, budget: usize) -> (u64, usize, bool) {
+        let mut objects = self.objects.write();
+        let mut sweep_queue = self.sweep_queue.write();
+
+        let mut objects_freed = 0;
+        let mut bytes_freed = 0;
+        for _ in 0..budget {
+            let Some(object_id) = sweep_queue.pop() else {
+                break;
+            };
+            if objects.get(&object_id).is_some_and(|object| object.color == GCColor::White) {
+                if let Some(object) = objects.remove(&object_id) {
+                    objects_freed += 1;
+                    bytes_freed += object.size;
+                }
+            }
         }
-        
-        // Update statistics
-        stats.total_objects_collected += objects_collected;
-        stats.total_memory_freed += memory_freed;
-        
-        Ok(())
+
+        let done = sweep_queue.is_empty();
+        if done {
+            *self.phase.write() = GCPhase::Idle;
+        }
+        (objects_freed, bytes_freed, done)
     }
 
     /// Generational garbage collection
@@ -421,22 +602,24 @@ impl GarbageCollector {
 
     /// Collect a specific generation
     async fn collect_generation(&self, generation: u8) -> Result<()> {
-        let mut objects = self.objects.write();
-        
-        // Find objects in the specified generation
-        let generation_objects: Vec<u64> = objects
-            .iter()
-            .filter(|(_, obj)| obj.generation == generation)
-            .map(|(id, _)| *id)
-            .collect();
-        
-        // Mark objects in this generation
-        for object_id in generation_objects {
-            if let Some(object) = objects.get_mut(&object_id) {
-                if object.reference_count > 0 {
-                    // Promote to next generation if threshold met
-                    if object.reference_count >= self.config.generational_config.promotion_thresholds[generation as usize] {
-                        object.generation = (generation + 1).min(self.config.generational_config.generations - 1);
+        {
+            let mut objects = self.objects.write();
+
+            // Find objects in the specified generation
+            let generation_objects: Vec<u64> = objects
+                .iter()
+                .filter(|(_, obj)| obj.generation == generation)
+                .map(|(id, _)| *id)
+                .collect();
+
+            // Mark objects in this generation
+            for object_id in generation_objects {
+                if let Some(object) = objects.get_mut(&object_id) {
+                    if object.reference_count > 0 {
+                        // Promote to next generation if threshold met
+                        if object.reference_count >= self.config.generational_config.promotion_thresholds[generation as usize] {
+                            object.generation = (generation + 1).min(self.config.generational_config.generations - 1);
+                        }
                     }
                 }
             }
@@ -450,49 +633,7 @@ impl GarbageCollector {
 
     /// Incremental garbage collection
     async fn incremental_collect(&self) -> Result<()> {
-        let config = &self.config.incremental_config;
-        let mut collection_queue = self.collection_queue.write();
-        
-        // Process objects in chunks
-        let mut processed = 0;
-        let start_time = Instant::now();
-        
-        while !collection_queue.is_empty() && processed < config.objects_per_step {
-            if start_time.elapsed().as_millis() as u64 > config.max_step_time_ms {
-                break;
-            }
-            
-            if let Some(object_id) = collection_queue.pop_front() {
-                self.process_object_incremental(object_id).await?;
-                processed += 1;
-            }
-        }
-        
-        Ok(())
-    }
-
-    /// Process an object during incremental collection
-    async fn process_object_incremental(&self, object_id: u64) -> Result<()> {
-        let mut objects = self.objects.write();
-        
-        if let Some(object) = objects.get_mut(&object_id) {
-            match object.state {
-                ReferenceState::Processing => {
-                    // Mark as reachable and add references to queue
-                    object.state = ReferenceState::Reachable;
-                    let mut collection_queue = self.collection_queue.write();
-                    for &reference_id in &object.references {
-                        collection_queue.push_back(reference_id);
-                    }
-                }
-                ReferenceState::Unreachable => {
-                    // Mark for collection
-                    object.state = ReferenceState::Marked;
-                }
-                _ => {}
-            }
-        }
-        
+        self.incremental_step(self.config.incremental_slice_size);
         Ok(())
     }
 
@@ -504,26 +645,9 @@ impl GarbageCollector {
         Ok(())
     }
 
-    /// Check if garbage collection is needed
-    async fn check_collection_needed(&self) {
-        let stats = self.stats.read();
-        let objects = self.objects.read();
-        
-        let current_heap_size: usize = objects.values().map(|obj| obj.size).sum();
-        let object_count = objects.len() as u64;
-        
-        // Check memory threshold
-        if current_heap_size > self.config.memory_threshold {
-            self.collect_garbage().await.ok();
-        }
-        
-        // Check time threshold (simplified)
-        if stats.total_collections > 0 {
-            let time_since_last = stats.last_collection_time_ms / 1000.0;
-            if time_since_last > self.config.time_threshold {
-                self.collect_garbage().await.ok();
-            }
-        }
+    /// Check if the heap has grown past the collection threshold
+    pub fn needs_collection(&self) -> bool {
+        self.stats.read().current_heap_size > self.config.memory_threshold
     }
 
     /// Update heap statistics
@@ -542,7 +666,7 @@ impl GarbageCollector {
         let mut stats = self.stats.write();
         
         stats.total_collections += 1;
-        stats.last_collection_time_ms = collection_time.as_millis() as f64;
+        stats.last_collection_time_ms = collection_time.as_secs_f64() * 1000.0;
         
         // Update average collection time
         let total_time = stats.avg_collection_time_ms * (stats.total_collections - 1) as f64;
@@ -569,6 +693,21 @@ impl GarbageCollector {
         objects.values().cloned().collect()
     }
 
+    /// Get objects that are still live, ordered by ID
+    ///
+    /// Objects that a finished marking phase left white are garbage waiting
+    /// to be swept and are not included.
+    pub fn get_live_objects(&self) -> Vec<MemoryObject> {
+        let sweeping = *self.phase.read() == GCPhase::Sweep;
+        let objects = self.objects.read();
+        let mut live: Vec<MemoryObject> = objects.values()
+            .filter(|object| !(sweeping && object.color == GCColor::White))
+            .cloned()
+            .collect();
+        live.sort_by_key(|object| object.id);
+        live
+    }
+
     /// Get root references
     pub fn get_roots(&self) -> Vec<RootReference> {
         let roots = self.roots.read();
@@ -598,6 +737,10 @@ impl GarbageCollector {
                 live_objects: 0,
                 dead_objects: 0,
                 collection_frequency: 0.0,
+                objects_freed: 0,
+                bytes_freed: 0,
+                mark_complete: false,
+                sweep_complete: false,
             };
         }
         {
@@ -612,5 +755,32 @@ impl GarbageCollector {
             let mut write_barriers = self.write_barriers.write();
             write_barriers.clear();
         }
+        {
+            let mut phase = self.phase.write();
+            *phase = GCPhase::Idle;
+        }
+        {
+            let mut sweep_queue = self.sweep_queue.write();
+            sweep_queue.clear();
+        }
+    }
+}
+
+impl HeapInspector for GarbageCollector {
+    fn live_objects(&self) -> Vec<HeapObject> {
+        self.get_live_objects().into_iter()
+            .map(|object| HeapObject {
+                id: object.id,
+                object_type: object.object_type,
+                size: object.size,
+                references: object.references,
+            })
+            .collect()
+    }
+
+    fn root_object_ids(&self) -> Vec<u64> {
+        self.get_roots().into_iter()
+            .flat_map(|root| root.object_ids)
+            .collect()
     }
 }
@@ -3,7 +3,7 @@ mod tests {
     use super::*;
     use crate::garbage_collector::{
         GarbageCollector, GCConfig, GCStrategy, MemoryObject, RootReference, RootType,
-        ReferenceState, GCStats, GenerationalConfig, IncrementalConfig
+        ReferenceState, GCStats, GenerationalConfig, IncrementalConfig, GCColor, GCPhase
     };
 
     #[tokio::test]
@@ -274,7 +274,7 @@ mod tests {
     async fn test_memory_threshold_triggering() {
         let mut config = GCConfig::default();
         config.memory_threshold = 200; // Low threshold
-        let gc = GarbageCollector::new(config);
+        let gc = GarbageCollector::new(config.clone());
         
         // Allocate objects to exceed threshold
         let obj1_id = gc.allocate("obj1", 100, vec![1, 2, 3]).unwrap();
@@ -452,4 +452,140 @@ mod tests {
         assert!(gc.get_object(unreferenced1_id).is_none());
         assert!(gc.get_object(unreferenced2_id).is_none());
     }
+
+    /// Run incremental steps until a cycle finishes, returning the last stats
+    fn finish_cycle(gc: &GarbageCollector) -> GCStats {
+        for _ in 0..100 {
+            let stats = gc.collect_incremental();
+            if stats.sweep_complete {
+                return stats;
+            }
+        }
+        panic!("collection cycle did not finish");
+    }
+
+    #[tokio::test]
+    async fn test_incremental_collects_broken_cycle() {
+        let mut config = GCConfig::default();
+        config.incremental_slice_size = 1;
+        let gc = GarbageCollector::new(config);
+
+        let holder = gc.allocate("holder", 10, vec![]).unwrap();
+        let a = gc.allocate("a", 100, vec![]).unwrap();
+        let b = gc.allocate("b", 200, vec![]).unwrap();
+        gc.add_reference(holder, a).unwrap();
+        gc.add_reference(a, b).unwrap();
+        gc.add_reference(b, a).unwrap();
+        gc.add_root("global", vec![holder], RootType::Global).unwrap();
+
+        // The cycle is reachable, so nothing is freed
+        let stats = finish_cycle(&gc);
+        assert_eq!(stats.total_objects_collected, 0);
+        assert!(gc.get_object(a).is_some() && gc.get_object(b).is_some());
+
+        // Once the holder drops it, the cycle is garbage despite referencing itself
+        gc.remove_reference(holder, a).unwrap();
+        let stats = finish_cycle(&gc);
+        assert!(stats.mark_complete);
+        assert_eq!(stats.total_objects_collected, 2);
+        assert_eq!(stats.total_memory_freed, 300);
+        assert!(gc.get_object(a).is_none());
+        assert!(gc.get_object(b).is_none());
+        assert!(gc.get_object(holder).is_some());
+        assert_eq!(gc.get_stats().total_collections, 2);
+    }
+
+    #[tokio::test]
+    async fn test_incremental_slices_resume() {
+        let mut config = GCConfig::default();
+        config.incremental_slice_size = 2;
+        let gc = GarbageCollector::new(config);
+
+        let ids: Vec<u64> = (0..6).map(|i| gc.allocate("node", 10, vec![i]).unwrap()).collect();
+        for pair in ids[..4].windows(2) {
+            gc.add_reference(pair[0], pair[1]).unwrap();
+        }
+        gc.add_root("stack", vec![ids[0]], RootType::Stack).unwrap();
+
+        // Marking four chained objects two at a time takes two steps
+        let first = gc.collect_incremental();
+        assert!(!first.mark_complete);
+        assert_eq!(gc.phase(), GCPhase::Mark);
+        assert_eq!(gc.get_object(ids[0]).unwrap().color, GCColor::Black);
+        assert_eq!(gc.get_object(ids[2]).unwrap().color, GCColor::Gray);
+
+        let mut freed = 0;
+        let mut steps = 1;
+        loop {
+            let stats = gc.collect_incremental();
+            freed += stats.objects_freed;
+            steps += 1;
+            assert!(stats.objects_freed <= 2);
+            if stats.sweep_complete {
+                break;
+            }
+        }
+        assert!(steps >= 4);
+        assert_eq!(freed, 2);
+        assert_eq!(gc.phase(), GCPhase::Idle);
+        assert!(gc.get_object(ids[4]).is_none() && gc.get_object(ids[5]).is_none());
+        assert!(ids[..4].iter().all(|&id| gc.get_object(id).is_some()));
+    }
+
+    #[tokio::test]
+    async fn test_write_barrier_during_marking() {
+        let mut config = GCConfig::default();
+        config.incremental_slice_size = 1;
+        let gc = GarbageCollector::new(config);
+
+        let root = gc.allocate("root", 10, vec![]).unwrap();
+        let child = gc.allocate("child", 10, vec![]).unwrap();
+        let other = gc.allocate("other", 10, vec![]).unwrap();
+        gc.add_reference(root, child).unwrap();
+        gc.add_root("global", vec![root], RootType::Global).unwrap();
+
+        // Scan the root, then link an unvisited object from it mid-cycle
+        gc.collect_incremental();
+        assert_eq!(gc.get_object(root).unwrap().color, GCColor::Black);
+        gc.add_reference(root, other).unwrap();
+
+        finish_cycle(&gc);
+        assert!(gc.get_object(child).is_some());
+        assert!(gc.get_object(other).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_live_objects_exclude_unswept_garbage() {
+        let mut config = GCConfig::default();
+        config.incremental_slice_size = 2;
+        let gc = GarbageCollector::new(config);
+
+        let ids: Vec<u64> = (0..4).map(|i| gc.allocate("node", 10, vec![i]).unwrap()).collect();
+        gc.add_root("global", vec![ids[0]], RootType::Global).unwrap();
+        let live: Vec<u64> = gc.get_live_objects().iter().map(|object| object.id).collect();
+        assert_eq!(live, ids);
+
+        // Marking finishes in the first step, leaving unreachable objects to sweep
+        gc.collect_incremental();
+        assert_eq!(gc.phase(), GCPhase::Sweep);
+        let live: Vec<u64> = gc.get_live_objects().iter().map(|object| object.id).collect();
+        assert_eq!(live, [ids[0]]);
+    }
+
+    #[test]
+    fn test_heap_inspector_lists_live_objects_and_roots() {
+        use common::heap::HeapInspector;
+
+        let gc = GarbageCollector::new(GCConfig::default());
+        let parent = gc.allocate("Array", 24, vec![]).unwrap();
+        let child = gc.allocate("String", 8, vec![]).unwrap();
+        gc.add_reference(parent, child).unwrap();
+        gc.add_root("global", vec![parent], RootType::Global).unwrap();
+
+        let objects = gc.live_objects();
+        assert_eq!(objects.iter().map(|object| object.id).collect::<Vec<_>>(), [parent, child]);
+        assert_eq!((objects[0].object_type.as_str(), objects[0].size), ("Array", 24));
+        assert_eq!(objects[0].references, [child]);
+        assert_eq!(gc.root_object_ids(), [parent]);
+    }
 }
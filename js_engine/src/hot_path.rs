@@ -1,6 +1,7 @@
 use crate::error::{Error, Result};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Instant;
 use parking_lot::RwLock;
 
 /// Hot path identifier
@@ -91,7 +92,7 @@ pub struct OptimizationHint {
 }
 
 /// Optimization hint types
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum OptimizationHintType {
     /// Inline function call
     InlineFunction,
@@ -125,6 +126,8 @@ pub struct HotPathOptimizer {
     config: HotPathConfig,
     /// Optimization engine
     optimizer: Arc<RwLock<OptimizationEngine>>,
+    /// Functions that reached the optimizing tier
+    optimized_functions: Arc<RwLock<HashSet<String>>>,
 }
 
 /// Configuration for hot path optimization
@@ -232,6 +235,7 @@ impl HotPathOptimizer {
             path_trees: Arc::new(RwLock::new(HashMap::new())),
             config,
             optimizer: Arc::new(RwLock::new(optimizer)),
+            optimized_functions: Arc::new(RwLock::new(HashSet::new())),
         }
     }
 
@@ -262,6 +266,36 @@ impl HotPathOptimizer {
         Ok(())
     }
 
+    /// Notify the optimizer that a function reached the optimizing tier
+    ///
+    /// The function's recorded hot paths that aren't optimized yet are queued
+    /// for optimization.
+    pub fn notify_function_optimized(&self, function_id: &str) {
+        if !self.optimized_functions.write().insert(function_id.to_string()) {
+            return;
+        }
+
+        let hot_paths = self.hot_paths.read();
+        let mut optimizer = self.optimizer.write();
+        for stats in hot_paths.values() {
+            if stats.path_id.function_id == function_id && !stats.is_optimized
+                && !optimizer.optimization_queue.contains(&stats.path_id)
+            {
+                optimizer.optimization_queue.push(stats.path_id.clone());
+            }
+        }
+    }
+
+    /// Check if a function reached the optimizing tier
+    pub fn is_function_optimized(&self, function_id: &str) -> bool {
+        self.optimized_functions.read().contains(function_id)
+    }
+
+    /// Get the hot paths waiting to be optimized
+    pub fn get_optimization_queue(&self) -> Vec<HotPathId> {
+        self.optimizer.read().optimization_queue.clone()
+    }
+
     /// Calculate path signature from execution nodes
     fn calculate_path_signature(&self, path_nodes: &[PathNode]) -> u64 {
         use std::collections::hash_map::DefaultHasher;
@@ -311,17 +345,17 @@ impl HotPathOptimizer {
             stability_score: 0.0,
         });
 
+        let previous_execution = stats.last_execution;
         stats.execution_count += 1;
         stats.total_time_us += execution_time;
         stats.avg_time_us = stats.total_time_us / stats.execution_count;
         stats.last_execution = current_time;
         
-        // Calculate frequency (executions per second)
+        // Calculate frequency (executions per second), counting executions
+        // within the same millisecond as a millisecond apart
         if stats.execution_count > 1 {
-            let time_diff = current_time - stats.last_execution;
-            if time_diff > 0 {
-                stats.frequency = 1000.0 / time_diff as f64; // Convert to per-second
-            }
+            let time_diff = current_time.saturating_sub(previous_execution).max(1);
+            stats.frequency = 1000.0 / time_diff as f64; // Convert to per-second
         }
         
         // Calculate stability score based on execution time variance
@@ -359,12 +393,14 @@ impl HotPathOptimizer {
     fn merge_path_nodes(&self, tree: &mut PathNode, path_nodes: &[PathNode]) {
         for path_node in path_nodes {
             // Find or create child node
-            let child = tree.children.iter_mut()
-                .find(|child| child.node_id == path_node.node_id)
-                .unwrap_or_else(|| {
+            let index = match tree.children.iter().position(|child| child.node_id == path_node.node_id) {
+                Some(index) => index,
+                None => {
                     tree.children.push(path_node.clone());
-                    tree.children.last_mut().unwrap()
-                });
+                    tree.children.len() - 1
+                }
+            };
+            let child = &mut tree.children[index];
 
             // Update execution statistics
             child.execution_count += path_node.execution_count;
@@ -414,6 +450,7 @@ impl HotPathOptimizer {
     /// Optimize a hot path
     pub async fn optimize_hot_path(&self, path_id: &HotPathId) -> Result<OptimizedPath> {
         let start_time = self.get_timestamp();
+        let started = Instant::now();
         
         // Get path statistics
         let hot_paths = self.hot_paths.read();
@@ -428,10 +465,12 @@ impl HotPathOptimizer {
         // Apply optimizations
         let optimized_code = self.apply_optimizations(tree, stats.optimization_level).await?;
         
-        let optimization_time = self.get_timestamp() - start_time;
+        // Round up so an optimization never counts as taking no time
+        let optimization_time_us = started.elapsed().as_nanos().div_ceil(1000) as u64;
+        let optimization_level = stats.optimization_level + 1;
         
-        // Calculate improvement factor (simulated)
-        let improvement_factor = match stats.optimization_level {
+        // Calculate improvement factor of the level applied (simulated)
+        let improvement_factor = match optimization_level {
             1 => 1.5,  // 50% improvement
             2 => 2.0,  // 100% improvement
             3 => 3.0,  // 200% improvement
@@ -441,14 +480,14 @@ impl HotPathOptimizer {
         let optimized_path = OptimizedPath {
             original_path_id: path_id.clone(),
             optimized_code,
-            optimization_level: stats.optimization_level + 1,
-            optimization_time,
+            optimization_level,
+            optimization_time: start_time,
             improvement_factor,
             is_valid: true,
         };
 
         // Update statistics
-        self.update_optimization_stats(optimization_time, true).await;
+        self.update_optimization_stats(optimization_time_us, true).await;
         
         // Cache the optimized path
         let mut optimizer = self.optimizer.write();
@@ -567,10 +606,10 @@ impl HotPathOptimizer {
     }
 
     /// Update optimization statistics
-    async fn update_optimization_stats(&self, optimization_time: u64, success: bool) {
+    async fn update_optimization_stats(&self, optimization_time_us: u64, success: bool) {
         let mut optimizer = self.optimizer.write();
         optimizer.stats.optimization_attempts += 1;
-        optimizer.stats.total_optimization_time_us += optimization_time;
+        optimizer.stats.total_optimization_time_us += optimization_time_us;
         
         if success {
             optimizer.stats.successful_optimizations += 1;
@@ -631,6 +670,10 @@ impl HotPathOptimizer {
             optimizer.optimization_queue.clear();
             optimizer.optimized_cache.clear();
         }
+        {
+            let mut optimized_functions = self.optimized_functions.write();
+            optimized_functions.clear();
+        }
     }
 
     /// Get current timestamp
@@ -1,13 +1,16 @@
 use crate::error::{Error, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
-use parking_lot::RwLock;
+use parking_lot::{MappedRwLockReadGuard, RwLock, RwLockReadGuard};
+
+/// Identifier of an object shape (hidden class)
+pub type ShapeId = u64;
 
 /// Cache entry for property access
 #[derive(Debug, Clone)]
 pub struct PropertyCacheEntry {
-    /// Object shape/hidden class identifier
-    pub shape_id: u64,
+    /// Shape of the object when the entry was cached
+    pub shape_id: ShapeId,
     /// Property offset in the object
     pub offset: usize,
     /// Property value
@@ -47,7 +50,7 @@ pub struct GlobalCacheEntry {
 }
 
 /// JavaScript value for cache operations
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Undefined,
     Null,
@@ -61,18 +64,52 @@ pub enum Value {
 }
 
 /// Object value with shape tracking
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ObjectValue {
     /// Object shape identifier
-    pub shape_id: u64,
+    pub shape_id: ShapeId,
     /// Object properties
     pub properties: HashMap<String, Value>,
     /// Object prototype
     pub prototype: Option<Arc<ObjectValue>>,
 }
 
+impl ObjectValue {
+    /// Create an object with the given properties and its shape
+    pub fn new(properties: HashMap<String, Value>, registry: &mut ShapeRegistry) -> Self {
+        let shape_id = registry.shape_for_properties(properties.keys());
+        Self {
+            shape_id,
+            properties,
+            prototype: None,
+        }
+    }
+
+    /// Set a property, moving the object to a new shape if the property is new
+    pub fn set_property(&mut self, name: String, value: Value, registry: &mut ShapeRegistry) {
+        if self.properties.insert(name, value).is_none() {
+            self.shape_id = registry.shape_for_properties(self.properties.keys());
+        }
+    }
+
+    /// Remove a property, moving the object to a new shape if it existed
+    pub fn remove_property(&mut self, name: &str, registry: &mut ShapeRegistry) -> Option<Value> {
+        let removed = self.properties.remove(name);
+        if removed.is_some() {
+            self.shape_id = registry.shape_for_properties(self.properties.keys());
+        }
+        removed
+    }
+
+    /// Look up a property on the object or its prototype chain
+    pub fn find_property(&self, name: &str) -> Option<&Value> {
+        self.properties.get(name)
+            .or_else(|| self.prototype.as_ref().and_then(|prototype| prototype.find_property(name)))
+    }
+}
+
 /// Function value
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FunctionValue {
     pub name: String,
     pub param_count: u32,
@@ -81,7 +118,7 @@ pub struct FunctionValue {
 }
 
 /// Class value
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ClassValue {
     pub name: String,
     pub constructor: Option<FunctionValue>,
@@ -95,6 +132,8 @@ pub struct ClassValue {
 pub struct PropertyCache {
     /// Cache entries indexed by object and property name
     entries: HashMap<(u64, String), PropertyCacheEntry>,
+    /// Shape-guarded entries indexed by property name
+    shape_entries: HashMap<String, PropertyCacheEntry>,
     /// Maximum cache size
     max_size: usize,
     /// Cache hit statistics
@@ -108,6 +147,7 @@ impl PropertyCache {
     pub fn new(max_size: usize) -> Self {
         Self {
             entries: HashMap::new(),
+            shape_entries: HashMap::new(),
             max_size,
             hits: 0,
             misses: 0,
@@ -146,13 +186,63 @@ impl PropertyCache {
         self.entries.insert(key, entry);
     }
 
+    /// Look up a property cached for a specific object shape
+    ///
+    /// An entry cached for a different shape is stale: it is dropped and the
+    /// lookup counts as a miss.
+    pub fn lookup_shape(&mut self, property_name: &str, shape_id: ShapeId) -> Option<&PropertyCacheEntry> {
+        match self.shape_entries.get(property_name) {
+            Some(entry) if entry.shape_id == shape_id => {
+                self.hits += 1;
+            }
+            Some(_) => {
+                self.shape_entries.remove(property_name);
+                self.misses += 1;
+                return None;
+            }
+            None => {
+                self.misses += 1;
+                return None;
+            }
+        }
+
+        let timestamp = self.get_timestamp();
+        let entry = self.shape_entries.get_mut(property_name)?;
+        entry.hit_count += 1;
+        entry.last_access = timestamp;
+        Some(entry)
+    }
+
+    /// Get a shape-guarded entry without touching the statistics
+    pub fn peek_shape(&self, property_name: &str) -> Option<&PropertyCacheEntry> {
+        self.shape_entries.get(property_name)
+    }
+
+    /// Store a property for a specific object shape
+    pub fn store_shape(&mut self, property_name: String, shape_id: ShapeId, offset: usize, value: Value) {
+        if self.shape_entries.len() >= self.max_size && !self.shape_entries.contains_key(&property_name) {
+            self.evict_stale_shapes();
+        }
+
+        let entry = PropertyCacheEntry {
+            shape_id,
+            offset,
+            value,
+            hit_count: 1,
+            last_access: self.get_timestamp(),
+        };
+
+        self.shape_entries.insert(property_name, entry);
+    }
+
     /// Update an existing cache entry
     pub fn update(&mut self, object_id: u64, property_name: &str, value: Value) {
         let key = (object_id, property_name.to_string());
+        let timestamp = self.get_timestamp();
         if let Some(entry) = self.entries.get_mut(&key) {
             entry.value = value;
             entry.hit_count += 1;
-            entry.last_access = self.get_timestamp();
+            entry.last_access = timestamp;
         }
     }
 
@@ -170,7 +260,7 @@ impl PropertyCache {
     /// Get cache statistics
     pub fn get_stats(&self) -> CacheStats {
         CacheStats {
-            size: self.entries.len(),
+            size: self.entries.len() + self.shape_entries.len(),
             max_size: self.max_size,
             hits: self.hits,
             misses: self.misses,
@@ -185,10 +275,26 @@ impl PropertyCache {
     /// Clear the cache
     pub fn clear(&mut self) {
         self.entries.clear();
+        self.shape_entries.clear();
         self.hits = 0;
         self.misses = 0;
     }
 
+    /// Evict the least used half of the shape-guarded entries
+    fn evict_stale_shapes(&mut self) {
+        let target_size = self.max_size / 2;
+
+        let mut entries: Vec<_> = self.shape_entries.drain().collect();
+        entries.sort_by(|(_, a), (_, b)| {
+            b.hit_count.cmp(&a.hit_count)
+                .then(b.last_access.cmp(&a.last_access))
+        });
+
+        for (name, entry) in entries.into_iter().take(target_size) {
+            self.shape_entries.insert(name, entry);
+        }
+    }
+
     /// Evict least used entries
     fn evict_least_used(&mut self) {
         let target_size = self.max_size / 2; // Evict half the cache
@@ -278,10 +384,11 @@ impl MethodCache {
     /// Update an existing cache entry
     pub fn update(&mut self, object_id: u64, method_name: &str, method: FunctionValue) {
         let key = (object_id, method_name.to_string());
+        let timestamp = self.get_timestamp();
         if let Some(entry) = self.entries.get_mut(&key) {
             entry.method = method;
             entry.hit_count += 1;
-            entry.last_access = self.get_timestamp();
+            entry.last_access = timestamp;
         }
     }
 
@@ -400,10 +507,11 @@ impl GlobalCache {
 
     /// Update an existing cache entry
     pub fn update(&mut self, name: &str, value: Value) {
+        let timestamp = self.get_timestamp();
         if let Some(entry) = self.entries.get_mut(name) {
             entry.value = value;
             entry.hit_count += 1;
-            entry.last_access = self.get_timestamp();
+            entry.last_access = timestamp;
         }
     }
 
@@ -488,9 +596,11 @@ pub struct InlineCacheManager {
 #[derive(Debug)]
 pub struct ShapeRegistry {
     /// Next available shape ID
-    next_shape_id: u64,
+    next_shape_id: ShapeId,
     /// Shape definitions
-    shapes: HashMap<u64, ShapeDefinition>,
+    shapes: HashMap<ShapeId, ShapeDefinition>,
+    /// Shapes by their sorted property names
+    shapes_by_properties: HashMap<Vec<String>, ShapeId>,
 }
 
 /// Shape definition for object layout
@@ -512,6 +622,7 @@ impl ShapeRegistry {
         Self {
             next_shape_id: 1,
             shapes: HashMap::new(),
+            shapes_by_properties: HashMap::new(),
         }
     }
 
@@ -536,6 +647,28 @@ impl ShapeRegistry {
         shape_id
     }
 
+    /// Get the shape for a set of property names
+    ///
+    /// Every distinct set of names gets its own shape, with IDs assigned in
+    /// increasing order; the same set always maps to the same shape.
+    pub fn shape_for_properties<I, S>(&mut self, names: I) -> ShapeId
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut properties: Vec<String> = names.into_iter().map(|name| name.as_ref().to_string()).collect();
+        properties.sort();
+        properties.dedup();
+
+        if let Some(&shape_id) = self.shapes_by_properties.get(&properties) {
+            return shape_id;
+        }
+
+        let shape_id = self.create_shape(properties.clone(), None);
+        self.shapes_by_properties.insert(properties, shape_id);
+        shape_id
+    }
+
     /// Get a shape definition
     pub fn get_shape(&self, shape_id: u64) -> Option<&ShapeDefinition> {
         self.shapes.get(&shape_id)
@@ -566,6 +699,7 @@ impl ShapeRegistry {
     /// Clear all shapes
     pub fn clear(&mut self) {
         self.shapes.clear();
+        self.shapes_by_properties.clear();
         self.next_shape_id = 1;
     }
 }
@@ -617,6 +751,28 @@ impl InlineCacheManager {
         cache.store(object_id, property_name, shape_id, offset, value);
     }
 
+    /// Get the cached entry for a property of an object
+    ///
+    /// Returns `None` if the property isn't cached or was cached for a
+    /// different shape than the object has now.
+    pub fn get_property(&self, obj: &ObjectValue, name: &str) -> Option<MappedRwLockReadGuard<'_, PropertyCacheEntry>> {
+        self.property_cache.write().lookup_shape(name, obj.shape_id)?;
+        RwLockReadGuard::try_map(self.property_cache.read(), |cache| {
+            cache.peek_shape(name).filter(|entry| entry.shape_id == obj.shape_id)
+        }).ok()
+    }
+
+    /// Look up a property on an object and cache it for the object's shape
+    pub fn cache_property(&self, obj: &ObjectValue, name: &str) -> Option<Value> {
+        let value = obj.find_property(name)?.clone();
+        let offset = self.shape_registry.read()
+            .get_shape(obj.shape_id)
+            .and_then(|shape| shape.offsets.get(name).copied())
+            .unwrap_or(0);
+        self.property_cache.write().store_shape(name.to_string(), obj.shape_id, offset, value.clone());
+        Some(value)
+    }
+
     /// Look up a method with caching
     pub fn lookup_method(&self, object_id: u64, method_name: &str) -> Option<FunctionValue> {
         let mut cache = self.method_cache.write();
@@ -2,7 +2,7 @@
 mod tests {
     use super::*;
     use crate::inline_cache::{
-        InlineCacheManager, PropertyCache, MethodCache, GlobalCache, ShapeRegistry,
+        InlineCacheManager, PropertyCache, MethodCache, GlobalCache, ShapeRegistry, ShapeId,
         PropertyCacheEntry, MethodCacheEntry, GlobalCacheEntry, Value, ObjectValue, FunctionValue, ClassValue,
         CacheStats, InlineCacheStats, ShapeDefinition
     };
@@ -462,10 +462,12 @@ mod tests {
         assert!(global_value.is_some());
         
         // Test shape registry
-        let shape_registry = manager.shape_registry();
-        let mut registry = shape_registry.write();
-        let shape_id = registry.create_shape(vec!["x".to_string(), "y".to_string()], None);
-        assert!(registry.get_shape(shape_id).is_some());
+        {
+            let shape_registry = manager.shape_registry();
+            let mut registry = shape_registry.write();
+            let shape_id = registry.create_shape(vec!["x".to_string(), "y".to_string()], None);
+            assert!(registry.get_shape(shape_id).is_some());
+        }
         
         // Verify statistics
         let stats = manager.get_stats();
@@ -474,4 +476,56 @@ mod tests {
         assert_eq!(stats.global_cache.size, 1);
         assert_eq!(stats.shape_count, 1);
     }
+
+    #[tokio::test]
+    async fn test_shape_registry_assigns_ids_per_property_set() {
+        let mut registry = ShapeRegistry::new();
+
+        let xy = registry.shape_for_properties(["x", "y"]);
+        let yx = registry.shape_for_properties(["y", "x"]);
+        let xyz = registry.shape_for_properties(["x", "y", "z"]);
+        let empty = registry.shape_for_properties(Vec::<String>::new());
+
+        assert_eq!(xy, yx);
+        assert!(xyz > xy);
+        assert!(empty > xyz);
+        assert_eq!(registry.get_shape(xyz).unwrap().offsets.get("z"), Some(&2));
+    }
+
+    #[tokio::test]
+    async fn test_property_cache_misses_after_shape_change() {
+        let manager = InlineCacheManager::new(100, 50, 25);
+        let shape_registry = manager.shape_registry();
+
+        let mut properties = HashMap::new();
+        properties.insert("x".to_string(), Value::Number(1.0));
+        let mut object = ObjectValue::new(properties, &mut shape_registry.write());
+
+        assert!(manager.get_property(&object, "x").is_none());
+        assert!(manager.cache_property(&object, "x").is_some());
+        let original_shape: ShapeId = manager.get_property(&object, "x").unwrap().shape_id;
+        assert_eq!(original_shape, object.shape_id);
+
+        // Adding a property changes the shape, so the cached entry is stale
+        object.set_property("y".to_string(), Value::Number(2.0), &mut shape_registry.write());
+        assert_ne!(object.shape_id, original_shape);
+        assert!(manager.get_property(&object, "x").is_none());
+
+        manager.cache_property(&object, "x");
+        assert_eq!(manager.get_property(&object, "x").unwrap().shape_id, object.shape_id);
+
+        // So does removing one
+        object.remove_property("y", &mut shape_registry.write());
+        assert_eq!(object.shape_id, original_shape);
+        assert!(manager.get_property(&object, "x").is_none());
+
+        // Overwriting an existing property keeps the shape
+        manager.cache_property(&object, "x");
+        object.set_property("x".to_string(), Value::Number(3.0), &mut shape_registry.write());
+        assert!(manager.get_property(&object, "x").is_some());
+
+        let stats = manager.get_stats().property_cache;
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 3);
+    }
 }
@@ -6,6 +6,8 @@ use std::collections::HashMap;
 pub enum TokenType {
     // Literals
     Number(f64),
+    /// BigInt literal such as `42n`, holding the decimal digits without the suffix
+    BigIntLiteral(String),
     String(String),
     Boolean(bool),
     Null,
@@ -43,7 +45,6 @@ pub enum TokenType {
     Export,
     From,
     As,
-    Default,
     Async,
     Await,
     Yield,
@@ -468,8 +469,8 @@ impl Lexer {
         // Consume the closing quote
         self.advance();
 
-        let lexeme = self.source[start_position..self.position].iter().collect();
-        Ok(Token::new(TokenType::String(lexeme), lexeme, start_position, start_line, start_column))
+        let lexeme: String = self.source[start_position..self.position].iter().collect();
+        Ok(Token::new(TokenType::String(lexeme.clone()), lexeme, start_position, start_line, start_column))
     }
 
     /// Parse a template literal
@@ -495,8 +496,8 @@ impl Lexer {
         // Consume the closing backtick
         self.advance();
 
-        let lexeme = self.source[start_position..self.position].iter().collect();
-        Ok(Token::new(TokenType::String(lexeme), lexeme, start_position, start_line, start_column))
+        let lexeme: String = self.source[start_position..self.position].iter().collect();
+        Ok(Token::new(TokenType::String(lexeme.clone()), lexeme, start_position, start_line, start_column))
     }
 
     /// Parse a number literal
@@ -529,6 +530,20 @@ impl Lexer {
             }
         }
 
+        // BigInt suffix, only allowed on integers
+        if !self.is_at_end() && self.peek() == 'n' {
+            let digits: String = self.source[start_position..self.position].iter().collect();
+            if !digits.chars().all(|c| c.is_ascii_digit()) {
+                return Err(Error::lexical(start_position, format!("Invalid BigInt literal: {}n", digits)));
+            }
+            if digits.len() > 1 && digits.starts_with('0') {
+                return Err(Error::lexical(start_position, format!("BigInt literal with leading zero: {}n", digits)));
+            }
+            self.advance(); // consume the 'n'
+            let lexeme: String = self.source[start_position..self.position].iter().collect();
+            return Ok(Token::new(TokenType::BigIntLiteral(digits), lexeme, start_position, start_line, start_column));
+        }
+
         let lexeme: String = self.source[start_position..self.position].iter().collect();
         let number = lexeme.parse::<f64>().map_err(|_| {
             Error::lexical(start_position, format!("Invalid number: {}", lexeme))
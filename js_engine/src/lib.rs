@@ -13,6 +13,7 @@ pub mod async_await;
 pub mod class_system;
 pub mod destructuring;
 pub mod bytecode;
+pub mod debugger;
 pub mod stack;
 pub mod inline_cache;
 pub mod tiering;
@@ -21,7 +22,10 @@ pub mod garbage_collector;
 pub mod memory_pool;
 pub mod webidl;
 pub mod builtins;
+pub mod structured_clone;
 
+#[cfg(test)]
+mod source_map_test;
 #[cfg(test)]
 mod es_modules_test;
 #[cfg(test)]
@@ -33,6 +37,8 @@ mod destructuring_test;
 #[cfg(test)]
 mod bytecode_test;
 #[cfg(test)]
+mod debugger_test;
+#[cfg(test)]
 mod stack_test;
 #[cfg(test)]
 mod inline_cache_test;
@@ -48,23 +54,27 @@ mod memory_pool_test;
 mod webidl_test;
 #[cfg(test)]
 mod builtins_test;
+#[cfg(test)]
+mod structured_clone_test;
 
 // Re-export main types
 pub use parser::JsParser;
 pub use ast::{AstNode, Program, Statement, Expression, Declaration, Identifier, Literal};
 pub use lexer::{Token, TokenType, Lexer};
 pub use error::{Error, Result};
-pub use source_map::SourceMap;
+pub use source_map::{SourceMap, OriginalPosition};
 pub use es_modules::{ESModuleSystem, ModuleLoader, ModuleEvaluator, ModuleRecord, ModuleNamespace, ModuleValue};
-pub use async_await::{AsyncAwaitSystem, AsyncContext, Promise, PromiseState, Value, AsyncFunctionValue, EventLoop};
+pub use async_await::{AsyncAwaitSystem, AsyncContext, Promise, PromiseState, Value, AsyncFunctionValue, EventLoop, Microtask, MicrotaskQueue};
 pub use class_system::{ClassSystem, ClassParser, ClassDefinition, ClassInstance, MethodDefinition, MethodKind, PropertyDefinition, PrivateFieldDefinition, ClassPrototype};
 pub use destructuring::{DestructuringSystem, DestructuringEngine, SpreadOperator, PatternMatcher, DestructuringContext};
-pub use bytecode::{BytecodeEngine, BytecodeCompiler, BytecodeFunction, Register, ConstantIndex, Label, Instruction, Value as BytecodeValue, FunctionValue, ClassValue, RegisterFile, CallFrame};
+pub use bytecode::{BytecodeEngine, ExecutionStatus, BytecodeCompiler, BytecodeFunction, Register, ConstantIndex, Label, Instruction, Value as BytecodeValue, FunctionValue, ClassValue, JsProxy, RegisterFile, CallFrame};
+pub use debugger::Debugger;
 pub use stack::{StackManager, StackAllocator, StackGuard, OperandStack, CallStack, StackFrame, FunctionValue as StackFunctionValue, ClassValue as StackClassValue, Value as StackValue, ExceptionInfo, StackStats, PoolStats};
-pub use inline_cache::{InlineCacheManager, PropertyCache, MethodCache, GlobalCache, ShapeRegistry, PropertyCacheEntry, MethodCacheEntry, GlobalCacheEntry, Value as CacheValue, ObjectValue, FunctionValue as CacheFunctionValue, ClassValue as CacheClassValue, CacheStats, InlineCacheStats, ShapeDefinition};
+pub use inline_cache::{InlineCacheManager, PropertyCache, MethodCache, GlobalCache, ShapeRegistry, ShapeId, PropertyCacheEntry, MethodCacheEntry, GlobalCacheEntry, Value as CacheValue, ObjectValue, FunctionValue as CacheFunctionValue, ClassValue as CacheClassValue, CacheStats, InlineCacheStats, ShapeDefinition};
 pub use tiering::{TieringManager, TieringConfig, ExecutionTier, FunctionStats, CodeCacheEntry, ExecutionResult, TieringStats, EngineStats};
 pub use hot_path::{HotPathOptimizer, HotPathConfig, HotPathId, HotPathStats, PathNode, PathNodeType, OptimizationHint, OptimizationHintType, OptimizedPath, OptimizationStats};
-pub use garbage_collector::{GarbageCollector, GCConfig, GCStrategy, MemoryObject, RootReference, RootType, ReferenceState, GCStats, GenerationalConfig, IncrementalConfig};
-pub use memory_pool::{MemoryPool, PoolConfig, PoolType, PoolStats, PoolEntry, Nursery, NurseryConfig, NurseryStats, MemoryPoolManager, ManagerConfig, ManagerStats};
-pub use webidl::{WebIDLParser, WebIDLGenerator, FastDOMBinding, WebIDLDefinition, WebIDLInterface, WebIDLMethod, WebIDLProperty, WebIDLArgument, WebIDLType, InterfaceBinding, MethodBinding, PropertyBinding, Value};
-pub use builtins::{TypedArray, TypedArrayType, Promise, PromiseState, FetchAPI, FetchRequest, FetchResponse, TimerManager, TimerType, EventManager, EventType, Event, BuiltinObjects, Value as BuiltinValue};
+pub use garbage_collector::{GarbageCollector, GCConfig, GCStrategy, MemoryObject, RootReference, RootType, ReferenceState, GCStats, GenerationalConfig, IncrementalConfig, GCColor, GCPhase};
+pub use memory_pool::{MemoryPool, PoolConfig, PoolType, PoolStats as MemoryPoolStats, PoolEntry, Nursery, NurseryConfig, NurseryStats, MemoryPoolManager, ManagerConfig, ManagerStats};
+pub use webidl::{WebIDLParser, WebIDLGenerator, FastDOMBinding, WebIDLDefinition, WebIDLInterface, WebIDLMethod, WebIDLProperty, WebIDLArgument, WebIDLType, InterfaceBinding, MethodBinding, PropertyBinding, Value as WebIDLValue};
+pub use builtins::{TypedArray, TypedArrayType, Promise as BuiltinPromise, PromiseState as BuiltinPromiseState, FetchAPI, FetchRequest, FetchResponse, TimerManager, TimerType, EventManager, EventType, Event, BuiltinObjects, BigIntValue, JsObject, WeakRef, FinalizationRegistry, SharedArrayBuffer, SharedTypedArray, Atomics, AgentKind, WaitResult, Value as BuiltinValue};
+pub use structured_clone::{StructuredCloneSerializer, StructuredCloneDeserializer, HeapObject, ObjectHandle, Value as CloneValue};
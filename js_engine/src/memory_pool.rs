@@ -326,11 +326,11 @@ impl MemoryPool {
     pub fn new(config: PoolConfig) -> Self {
         let stats = PoolStats {
             pool_type: config.pool_type,
-            total_pools: 0,
-            total_objects: 0,
+            total_pools: 1,
+            total_objects: config.objects_per_pool,
             objects_in_use: 0,
-            objects_available: 0,
-            total_memory: 0,
+            objects_available: config.objects_per_pool,
+            total_memory: config.objects_per_pool * config.object_size,
             memory_in_use: 0,
             allocation_count: 0,
             deallocation_count: 0,
@@ -367,7 +367,7 @@ impl MemoryPool {
     /// Allocate an object from the pool
     pub fn allocate(&self, data: Vec<u8>) -> Result<u64> {
         if !self.config.enabled {
-            return Err(Error::parsing("Pool is disabled".to_string()));
+            return Err(Error::runtime("Pool is disabled", None));
         }
 
         let start_time = Instant::now();
@@ -397,7 +397,7 @@ impl MemoryPool {
             stats.allocation_count += 1;
             stats.memory_in_use += entry.size;
             
-            let allocation_time = start_time.elapsed().as_micros() as f64;
+            let allocation_time = start_time.elapsed().as_secs_f64() * 1_000_000.0;
             stats.last_allocation_time_us = allocation_time;
             stats.avg_allocation_time_us = 
                 (stats.avg_allocation_time_us * (stats.allocation_count - 1) as f64 + allocation_time) / stats.allocation_count as f64;
@@ -407,7 +407,8 @@ impl MemoryPool {
             // Need to expand pool
             self.expand_pool(&mut entries, &mut stats)?;
             
-            // Try allocation again
+            // Try allocation again, releasing the locks it takes itself
+            drop((entries, stats, next_id));
             self.allocate(data)
         }
     }
@@ -444,7 +445,7 @@ impl MemoryPool {
             return Err(Error::parsing("Maximum number of pools reached".to_string()));
         }
 
-        let new_pool_size = (self.config.objects_per_pool as f64 * self.config.growth_factor) as usize;
+        let new_pool_size = self.config.objects_per_pool;
         
         // Add new entries
         for _ in 0..new_pool_size {
@@ -587,26 +588,27 @@ impl Nursery {
 
         Self {
             pools: Arc::new(RwLock::new(pools)),
+            promotion_threshold: config.promotion_threshold,
             config,
             stats: Arc::new(RwLock::new(stats)),
-            promotion_threshold: config.promotion_threshold,
         }
     }
 
     /// Allocate an object in the nursery
     pub fn allocate(&self, pool_type: PoolType, data: Vec<u8>) -> Result<u64> {
         if !self.config.enabled {
-            return Err(Error::parsing("Nursery is disabled".to_string()));
+            return Err(Error::runtime("Nursery is disabled", None));
         }
 
         let pools = self.pools.read();
         if let Some(pool) = pools.get(&pool_type) {
+            let size = data.len();
             let entry_id = pool.allocate(data)?;
             
             // Update nursery statistics
             let mut stats = self.stats.write();
             stats.total_objects += 1;
-            stats.current_size += data.len();
+            stats.current_size += size;
             stats.peak_size = stats.peak_size.max(stats.current_size);
             
             Ok(entry_id)
@@ -624,7 +626,7 @@ impl Nursery {
                 // For now, we'll just update statistics
                 let mut stats = self.stats.write();
                 stats.promoted_objects += 1;
-                stats.current_size -= entry.size;
+                stats.current_size = stats.current_size.saturating_sub(entry.data.len());
                 stats.promotion_rate = stats.promoted_objects as f64 / stats.total_objects as f64;
                 
                 Ok(entry_id)
@@ -636,6 +638,22 @@ impl Nursery {
         }
     }
 
+    /// Free an object that died young, returning its slot to the nursery pool
+    pub fn deallocate(&self, entry_id: u64, pool_type: PoolType) -> Result<()> {
+        let pools = self.pools.read();
+        if let Some(pool) = pools.get(&pool_type) {
+            let size = pool.get_entry(entry_id).map_or(0, |entry| entry.data.len());
+            pool.deallocate(entry_id)?;
+            
+            let mut stats = self.stats.write();
+            stats.current_size = stats.current_size.saturating_sub(size);
+            
+            Ok(())
+        } else {
+            Err(Error::parsing(format!("Pool type {:?} not found", pool_type)))
+        }
+    }
+
     /// Collect nursery (remove short-lived objects)
     pub async fn collect(&self) -> Result<NurseryStats> {
         let start_time = Instant::now();
@@ -656,11 +674,11 @@ impl Nursery {
         }
         
         // Update statistics
-        stats.collected_objects += collected_count;
-        stats.current_size -= collected_size;
+        stats.collected_objects += collected_count as u64;
+        stats.current_size = stats.current_size.saturating_sub(collected_size);
         stats.collection_count += 1;
         
-        let collection_time = start_time.elapsed().as_millis() as f64;
+        let collection_time = start_time.elapsed().as_secs_f64() * 1_000.0;
         stats.avg_collection_time_ms = 
             (stats.avg_collection_time_ms * (stats.collection_count - 1) as f64 + collection_time) / stats.collection_count as f64;
         
@@ -724,7 +742,7 @@ impl MemoryPoolManager {
     /// Allocate memory from appropriate pool
     pub async fn allocate(&self, pool_type: PoolType, data: Vec<u8>) -> Result<u64> {
         if !self.config.enabled {
-            return Err(Error::parsing("Memory pooling is disabled".to_string()));
+            return Err(Error::runtime("Memory pooling is disabled", None));
         }
 
         let start_time = Instant::now();
@@ -732,7 +750,7 @@ impl MemoryPoolManager {
         // Try nursery first for short-lived objects
         if self.should_use_nursery(&pool_type) {
             let nursery = self.nursery.read();
-            match nursery.allocate(pool_type, data) {
+            match nursery.allocate(pool_type, data.clone()) {
                 Ok(entry_id) => {
                     self.update_manager_stats(start_time, true);
                     return Ok(entry_id);
@@ -756,24 +774,22 @@ impl MemoryPoolManager {
 
     /// Deallocate memory back to pool
     pub fn deallocate(&self, pool_type: PoolType, entry_id: u64) -> Result<()> {
-        // Try nursery first
-        let nursery = self.nursery.read();
-        if let Ok(_) = nursery.promote_object(entry_id, pool_type) {
-            return Ok(());
-        }
+        // Try nursery first, since that's where allocate puts these types
+        let freed_from_nursery = self.should_use_nursery(&pool_type)
+            && self.nursery.read().deallocate(entry_id, pool_type).is_ok();
         
         // Use regular pools
-        let pools = self.pools.read();
-        if let Some(pool) = pools.get(&pool_type) {
+        if !freed_from_nursery {
+            let pools = self.pools.read();
+            let pool = pools.get(&pool_type)
+                .ok_or_else(|| Error::parsing(format!("Pool type {:?} not found", pool_type)))?;
             pool.deallocate(entry_id)?;
-            
-            let mut stats = self.stats.write();
-            stats.total_deallocations += 1;
-            
-            Ok(())
-        } else {
-            Err(Error::parsing(format!("Pool type {:?} not found", pool_type)))
         }
+        
+        let mut stats = self.stats.write();
+        stats.total_deallocations += 1;
+        
+        Ok(())
     }
 
     /// Determine if object should use nursery
@@ -787,7 +803,7 @@ impl MemoryPoolManager {
             let mut stats = self.stats.write();
             stats.total_allocations += 1;
             
-            let allocation_time = start_time.elapsed().as_micros() as f64;
+            let allocation_time = start_time.elapsed().as_secs_f64() * 1_000_000.0;
             stats.avg_allocation_time_us = 
                 (stats.avg_allocation_time_us * (stats.total_allocations - 1) as f64 + allocation_time) / stats.total_allocations as f64;
         }
@@ -804,11 +820,13 @@ impl MemoryPoolManager {
         let nursery = self.nursery.read();
         let pools = self.pools.read();
         
-        let nursery_stats = nursery.get_stats();
-        let total_memory: usize = pools.values().map(|p| p.get_stats().total_memory).sum();
+        let nursery_pools = nursery.pools.read();
+        let all_stats: Vec<PoolStats> = nursery_pools.values().chain(pools.values())
+            .map(|p| p.get_stats())
+            .collect();
         
-        let total_used = nursery_stats.current_size + total_memory;
-        let total_available = nursery_stats.peak_size + total_memory;
+        let total_used: usize = all_stats.iter().map(|s| s.memory_in_use).sum();
+        let total_available: usize = all_stats.iter().map(|s| s.total_memory).sum();
         
         if total_available > 0 {
             total_used as f64 / total_available as f64
@@ -1,13 +1,15 @@
 use crate::error::{Error, Result};
 use crate::lexer::{Lexer, Token, TokenType};
 use crate::ast::*;
-use crate::source_map::SourceMapGenerator;
+use crate::source_map::{OriginalPosition, SourceMap, SourceMapGenerator};
 
 /// JavaScript parser using Pratt parsing technique
 pub struct JsParser {
     lexer: Lexer,
     current_token: Option<Token>,
     source_map_generator: SourceMapGenerator,
+    source_mapping_url: Option<String>,
+    source_map: Option<SourceMap>,
 }
 
 impl JsParser {
@@ -20,11 +22,20 @@ impl JsParser {
             lexer,
             current_token,
             source_map_generator: SourceMapGenerator::new(),
+            source_mapping_url: SourceMap::find_source_mapping_url(source).map(str::to_string),
+            source_map: None,
         }
     }
 
     /// Parse the source code into an AST
+    ///
+    /// A `//# sourceMappingURL=` comment with an inline `data:` URL loads the
+    /// source map of the script. A map that can't be loaded is ignored.
     pub fn parse(&mut self) -> Result<Program> {
+        if let Some(url) = self.source_mapping_url.take() {
+            self.source_map = Self::load_source_map(&url);
+        }
+
         let mut statements = Vec::new();
         
         while !self.is_at_end() {
@@ -39,6 +50,27 @@ impl JsParser {
         })
     }
 
+    /// Get the source map of the parsed script
+    pub fn source_map(&self) -> Option<&SourceMap> {
+        self.source_map.as_ref()
+    }
+
+    /// Map a position in the parsed script to its original source
+    pub fn original_position(&self, position: &Position) -> Option<OriginalPosition> {
+        self.source_map.as_ref()?.apply_to_position(position.clone())
+    }
+
+    /// Load the source map a `sourceMappingURL` comment points to
+    fn load_source_map(url: &str) -> Option<SourceMap> {
+        if !url.starts_with("data:") {
+            log::warn!("Ignoring source map {}: only inline data: URLs are supported", url);
+            return None;
+        }
+        SourceMap::from_data_url(url)
+            .map_err(|e| log::warn!("Ignoring invalid source map: {}", e))
+            .ok()
+    }
+
     /// Parse a statement
     fn parse_statement(&mut self) -> Result<Statement> {
         match self.current_token_type() {
@@ -104,7 +136,7 @@ impl JsParser {
         
         loop {
             let id = self.parse_pattern()?;
-            let init = if self.current_token_type() == TokenType::Assign {
+            let init = if self.check(&TokenType::Assign) {
                 self.advance(); // consume =
                 Some(self.parse_expression()?)
             } else {
@@ -118,7 +150,7 @@ impl JsParser {
                 position,
             });
 
-            if self.current_token_type() != TokenType::Comma {
+            if !self.check(&TokenType::Comma) {
                 break;
             }
             self.advance(); // consume comma
@@ -138,7 +170,7 @@ impl JsParser {
     fn parse_function_declaration(&mut self) -> Result<Statement> {
         self.advance(); // consume 'function'
 
-        let id = if self.current_token_type() == TokenType::Identifier("".to_string()) {
+        let id = if self.is_identifier() {
             let name = self.current_token().lexeme.clone();
             self.advance(); // consume identifier
             Some(Identifier {
@@ -164,7 +196,7 @@ impl JsParser {
                 _ => return Err(Error::syntax(0, "Expected function body")),
             },
             generator: false,
-            async: false,
+            r#async: false,
             position,
         }))
     }
@@ -173,7 +205,7 @@ impl JsParser {
     fn parse_class_declaration(&mut self) -> Result<Statement> {
         self.advance(); // consume 'class'
 
-        let id = if self.current_token_type() == TokenType::Identifier("".to_string()) {
+        let id = if self.is_identifier() {
             let name = self.current_token().lexeme.clone();
             self.advance(); // consume identifier
             Some(Identifier {
@@ -184,7 +216,7 @@ impl JsParser {
             None
         };
 
-        let super_class = if self.current_token_type() == TokenType::Extends {
+        let super_class = if self.check(&TokenType::Extends) {
             self.advance(); // consume 'extends'
             Some(self.parse_expression()?)
         } else {
@@ -208,7 +240,7 @@ impl JsParser {
 
         let mut body = Vec::new();
 
-        while self.current_token_type() != TokenType::RightBrace && !self.is_at_end() {
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
             let element = self.parse_class_element()?;
             body.push(element);
         }
@@ -227,7 +259,7 @@ impl JsParser {
         // Simplified implementation - just parse as method
         let key = self.parse_expression()?;
         
-        if self.current_token_type() == TokenType::LeftParen {
+        if self.check(&TokenType::LeftParen) {
             // Method
             let params = self.parse_parameters()?;
             let function_body = self.parse_block_statement()?;
@@ -240,7 +272,7 @@ impl JsParser {
                     _ => return Err(Error::syntax(0, "Expected function body")),
                 },
                 generator: false,
-                async: false,
+                r#async: false,
                 position: Position::new(0, 0, 1, 1),
             };
 
@@ -255,7 +287,7 @@ impl JsParser {
             }))
         } else {
             // Property
-            let value = if self.current_token_type() == TokenType::Assign {
+            let value = if self.check(&TokenType::Assign) {
                 self.advance(); // consume =
                 Some(self.parse_expression()?)
             } else {
@@ -284,7 +316,7 @@ impl JsParser {
         self.expect(TokenType::RightParen)?;
 
         let consequent = Box::new(self.parse_statement()?);
-        let alternate = if self.current_token_type() == TokenType::Else {
+        let alternate = if self.check(&TokenType::Else) {
             self.advance(); // consume 'else'
             Some(Box::new(self.parse_statement()?))
         } else {
@@ -306,7 +338,7 @@ impl JsParser {
 
         self.expect(TokenType::LeftParen)?;
 
-        let init = if self.current_token_type() != TokenType::Semicolon {
+        let init = if !self.check(&TokenType::Semicolon) {
             Some(Box::new(self.parse_statement()?))
         } else {
             None
@@ -314,7 +346,7 @@ impl JsParser {
 
         self.expect(TokenType::Semicolon)?;
 
-        let test = if self.current_token_type() != TokenType::Semicolon {
+        let test = if !self.check(&TokenType::Semicolon) {
             Some(self.parse_expression()?)
         } else {
             None
@@ -322,7 +354,7 @@ impl JsParser {
 
         self.expect(TokenType::Semicolon)?;
 
-        let update = if self.current_token_type() != TokenType::RightParen {
+        let update = if !self.check(&TokenType::RightParen) {
             Some(self.parse_expression()?)
         } else {
             None
@@ -364,7 +396,7 @@ impl JsParser {
     fn parse_return_statement(&mut self) -> Result<Statement> {
         self.advance(); // consume 'return'
 
-        let argument = if self.current_token_type() != TokenType::Semicolon {
+        let argument = if !self.check(&TokenType::Semicolon) {
             Some(self.parse_expression()?)
         } else {
             None
@@ -385,7 +417,7 @@ impl JsParser {
 
         let specifiers = self.parse_import_specifiers()?;
 
-        if self.current_token_type() == TokenType::From {
+        if self.check(&TokenType::From) {
             self.advance(); // consume 'from'
             let source = self.parse_literal()?;
             self.expect_semicolon()?;
@@ -405,7 +437,7 @@ impl JsParser {
     fn parse_import_specifiers(&mut self) -> Result<Vec<ImportSpecifier>> {
         let mut specifiers = Vec::new();
 
-        if self.current_token_type() == TokenType::Identifier("".to_string()) {
+        if self.is_identifier() {
             // Default import
             let local = Identifier {
                 name: self.current_token().lexeme.clone(),
@@ -418,18 +450,18 @@ impl JsParser {
                 local,
                 position,
             }));
-        } else if self.current_token_type() == TokenType::LeftBrace {
+        } else if self.check(&TokenType::LeftBrace) {
             // Named imports
             self.advance(); // consume '{'
 
-            while self.current_token_type() != TokenType::RightBrace && !self.is_at_end() {
+            while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
                 let local = Identifier {
                     name: self.current_token().lexeme.clone(),
                     position: Position::new(0, 0, 1, 1),
                 };
                 self.advance(); // consume identifier
 
-                let imported = if self.current_token_type() == TokenType::As {
+                let imported = if self.check(&TokenType::As) {
                     self.advance(); // consume 'as'
                     let imported = Identifier {
                         name: self.current_token().lexeme.clone(),
@@ -448,7 +480,7 @@ impl JsParser {
                     position,
                 }));
 
-                if self.current_token_type() == TokenType::Comma {
+                if self.check(&TokenType::Comma) {
                     self.advance(); // consume comma
                 }
             }
@@ -463,7 +495,7 @@ impl JsParser {
     fn parse_export_declaration(&mut self) -> Result<Statement> {
         self.advance(); // consume 'export'
 
-        if self.current_token_type() == TokenType::Default {
+        if self.check(&TokenType::Default) {
             self.advance(); // consume 'default'
             let declaration = self.parse_declaration()?;
             self.expect_semicolon()?;
@@ -475,11 +507,11 @@ impl JsParser {
             })))
         } else {
             // Named export
-            let declaration = if self.current_token_type() == TokenType::Function 
-                || self.current_token_type() == TokenType::Class 
-                || self.current_token_type() == TokenType::Let 
-                || self.current_token_type() == TokenType::Const 
-                || self.current_token_type() == TokenType::Var {
+            let declaration = if self.check(&TokenType::Function) 
+                || self.check(&TokenType::Class) 
+                || self.check(&TokenType::Let) 
+                || self.check(&TokenType::Const) 
+                || self.check(&TokenType::Var) {
                 Some(self.parse_declaration()?)
             } else {
                 None
@@ -491,7 +523,7 @@ impl JsParser {
                 Vec::new()
             };
 
-            let source = if self.current_token_type() == TokenType::From {
+            let source = if self.check(&TokenType::From) {
                 self.advance(); // consume 'from'
                 Some(self.parse_literal()?)
             } else {
@@ -516,17 +548,17 @@ impl JsParser {
     fn parse_export_specifiers(&mut self) -> Result<Vec<ExportSpecifier>> {
         let mut specifiers = Vec::new();
 
-        if self.current_token_type() == TokenType::LeftBrace {
+        if self.check(&TokenType::LeftBrace) {
             self.advance(); // consume '{'
 
-            while self.current_token_type() != TokenType::RightBrace && !self.is_at_end() {
+            while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
                 let local = Identifier {
                     name: self.current_token().lexeme.clone(),
                     position: Position::new(0, 0, 1, 1),
                 };
                 self.advance(); // consume identifier
 
-                let exported = if self.current_token_type() == TokenType::As {
+                let exported = if self.check(&TokenType::As) {
                     self.advance(); // consume 'as'
                     let exported = Identifier {
                         name: self.current_token().lexeme.clone(),
@@ -545,7 +577,7 @@ impl JsParser {
                     position,
                 });
 
-                if self.current_token_type() == TokenType::Comma {
+                if self.check(&TokenType::Comma) {
                     self.advance(); // consume comma
                 }
             }
@@ -562,7 +594,7 @@ impl JsParser {
 
         let mut body = Vec::new();
 
-        while self.current_token_type() != TokenType::RightBrace && !self.is_at_end() {
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
             let statement = self.parse_statement()?;
             body.push(statement);
         }
@@ -618,12 +650,12 @@ impl JsParser {
     fn parse_parameters(&mut self) -> Result<Vec<Pattern>> {
         let mut params = Vec::new();
 
-        if self.current_token_type() != TokenType::RightParen {
+        if !self.check(&TokenType::RightParen) {
             loop {
                 let param = self.parse_pattern()?;
                 params.push(param);
 
-                if self.current_token_type() != TokenType::Comma {
+                if !self.check(&TokenType::Comma) {
                     break;
                 }
                 self.advance(); // consume comma
@@ -636,9 +668,9 @@ impl JsParser {
     /// Parse a pattern
     fn parse_pattern(&mut self) -> Result<Pattern> {
         // Simplified - just parse as identifier
-        if let TokenType::Identifier(name) = &self.current_token_type() {
+        if let TokenType::Identifier(name) = self.current_token_type().clone() {
             let identifier = Identifier {
-                name: name.clone(),
+                name,
                 position: Position::new(0, 0, 1, 1),
             };
             self.advance(); // consume identifier
@@ -665,8 +697,8 @@ impl JsParser {
             let position = Position::new(0, 0, 1, 1);
             Ok(Expression::Assignment(AssignmentExpression {
                 operator,
-                left: self.expression_to_pattern(left)?,
-                right,
+                left: Box::new(self.expression_to_pattern(left)?),
+                right: Box::new(right),
                 position,
             }))
         } else {
@@ -678,7 +710,7 @@ impl JsParser {
     fn parse_logical_or_expression(&mut self) -> Result<Expression> {
         let mut left = self.parse_logical_and_expression()?;
 
-        while self.current_token_type() == TokenType::LogicalOr {
+        while self.check(&TokenType::LogicalOr) {
             let operator = LogicalOperator::LogicalOr;
             self.advance(); // consume ||
             let right = self.parse_logical_and_expression()?;
@@ -686,8 +718,8 @@ impl JsParser {
             let position = Position::new(0, 0, 1, 1);
             left = Expression::Logical(LogicalExpression {
                 operator,
-                left,
-                right,
+                left: Box::new(left),
+                right: Box::new(right),
                 position,
             });
         }
@@ -699,7 +731,7 @@ impl JsParser {
     fn parse_logical_and_expression(&mut self) -> Result<Expression> {
         let mut left = self.parse_equality_expression()?;
 
-        while self.current_token_type() == TokenType::LogicalAnd {
+        while self.check(&TokenType::LogicalAnd) {
             let operator = LogicalOperator::LogicalAnd;
             self.advance(); // consume &&
             let right = self.parse_equality_expression()?;
@@ -707,8 +739,8 @@ impl JsParser {
             let position = Position::new(0, 0, 1, 1);
             left = Expression::Logical(LogicalExpression {
                 operator,
-                left,
-                right,
+                left: Box::new(left),
+                right: Box::new(right),
                 position,
             });
         }
@@ -737,8 +769,8 @@ impl JsParser {
             let position = Position::new(0, 0, 1, 1);
             left = Expression::Binary(BinaryExpression {
                 operator,
-                left,
-                right,
+                left: Box::new(left),
+                right: Box::new(right),
                 position,
             });
         }
@@ -767,8 +799,8 @@ impl JsParser {
             let position = Position::new(0, 0, 1, 1);
             left = Expression::Binary(BinaryExpression {
                 operator,
-                left,
-                right,
+                left: Box::new(left),
+                right: Box::new(right),
                 position,
             });
         }
@@ -792,8 +824,8 @@ impl JsParser {
             let position = Position::new(0, 0, 1, 1);
             left = Expression::Binary(BinaryExpression {
                 operator,
-                left,
-                right,
+                left: Box::new(left),
+                right: Box::new(right),
                 position,
             });
         }
@@ -820,8 +852,8 @@ impl JsParser {
             let position = Position::new(0, 0, 1, 1);
             left = Expression::Binary(BinaryExpression {
                 operator,
-                left,
-                right,
+                left: Box::new(left),
+                right: Box::new(right),
                 position,
             });
         }
@@ -831,7 +863,16 @@ impl JsParser {
 
     /// Parse a unary expression
     fn parse_unary_expression(&mut self) -> Result<Expression> {
-        if self.is_unary_operator() {
+        if self.check(&TokenType::Await) {
+            self.advance(); // consume 'await'
+            let argument = self.parse_unary_expression()?;
+
+            let position = Position::new(0, 0, 1, 1);
+            Ok(Expression::Await(AwaitExpression {
+                argument: Box::new(argument),
+                position,
+            }))
+        } else if self.is_unary_operator() {
             let operator = self.parse_unary_operator()?;
             self.advance(); // consume operator
             let argument = self.parse_unary_expression()?;
@@ -839,7 +880,7 @@ impl JsParser {
             let position = Position::new(0, 0, 1, 1);
             Ok(Expression::Unary(UnaryExpression {
                 operator,
-                argument,
+                argument: Box::new(argument),
                 prefix: true,
                 position,
             }))
@@ -874,7 +915,7 @@ impl JsParser {
                     let position = Position::new(0, 0, 1, 1);
                     expr = Expression::Update(UpdateExpression {
                         operator,
-                        argument: expr,
+                        argument: Box::new(expr),
                         prefix: false,
                         position,
                     });
@@ -898,7 +939,7 @@ impl JsParser {
                     position,
                 }))
             }
-            TokenType::Number(_) | TokenType::String(_) | TokenType::Boolean(_) | TokenType::Null => {
+            TokenType::Number(_) | TokenType::BigIntLiteral(_) | TokenType::String(_) | TokenType::Boolean(_) | TokenType::Null => {
                 let literal = self.parse_literal()?;
                 Ok(Expression::Literal(literal))
             }
@@ -931,12 +972,12 @@ impl JsParser {
 
         let mut arguments = Vec::new();
 
-        if self.current_token_type() != TokenType::RightParen {
+        if !self.check(&TokenType::RightParen) {
             loop {
                 let arg = self.parse_expression()?;
                 arguments.push(ExpressionOrSpread::Expression(arg));
 
-                if self.current_token_type() != TokenType::Comma {
+                if !self.check(&TokenType::Comma) {
                     break;
                 }
                 self.advance(); // consume comma
@@ -947,7 +988,7 @@ impl JsParser {
 
         let position = Position::new(0, 0, 1, 1);
         Ok(Expression::Call(CallExpression {
-            callee,
+            callee: Box::new(callee),
             arguments,
             optional: false,
             position,
@@ -956,28 +997,26 @@ impl JsParser {
 
     /// Parse a member expression
     fn parse_member_expression(&mut self, object: Expression, computed: bool) -> Result<Expression> {
-        if computed {
+        let property = if computed {
             self.advance(); // consume '['
             let property = self.parse_expression()?;
             self.expect(TokenType::RightBracket)?;
+            property
         } else {
             self.advance(); // consume '.'
             let name = self.current_token().lexeme.clone();
             self.advance(); // consume identifier
             let position = Position::new(0, 0, 1, 1);
-            let property = Expression::Identifier(Identifier {
+            Expression::Identifier(Identifier {
                 name,
                 position,
-            });
-        }
+            })
+        };
 
         let position = Position::new(0, 0, 1, 1);
         Ok(Expression::Member(MemberExpression {
-            object,
-            property: Expression::Identifier(Identifier {
-                name: "property".to_string(),
-                position: Position::new(0, 0, 1, 1),
-            }),
+            object: Box::new(object),
+            property: Box::new(property),
             computed,
             optional: false,
             position,
@@ -990,16 +1029,16 @@ impl JsParser {
 
         let mut elements = Vec::new();
 
-        if self.current_token_type() != TokenType::RightBracket {
+        if !self.check(&TokenType::RightBracket) {
             loop {
-                if self.current_token_type() == TokenType::Comma {
+                if self.check(&TokenType::Comma) {
                     elements.push(None);
                 } else {
                     let element = self.parse_expression()?;
                     elements.push(Some(element));
                 }
 
-                if self.current_token_type() != TokenType::Comma {
+                if !self.check(&TokenType::Comma) {
                     break;
                 }
                 self.advance(); // consume comma
@@ -1021,12 +1060,12 @@ impl JsParser {
 
         let mut properties = Vec::new();
 
-        if self.current_token_type() != TokenType::RightBrace {
+        if !self.check(&TokenType::RightBrace) {
             loop {
                 let property = self.parse_object_property()?;
                 properties.push(property);
 
-                if self.current_token_type() != TokenType::Comma {
+                if !self.check(&TokenType::Comma) {
                     break;
                 }
                 self.advance(); // consume comma
@@ -1046,14 +1085,14 @@ impl JsParser {
     fn parse_object_property(&mut self) -> Result<ObjectProperty> {
         let key = self.parse_expression()?;
 
-        if self.current_token_type() == TokenType::Colon {
+        if self.check(&TokenType::Colon) {
             self.advance(); // consume ':'
             let value = self.parse_expression()?;
 
             let position = Position::new(0, 0, 1, 1);
             Ok(ObjectProperty::Property(Property {
-                key,
-                value,
+                key: Box::new(key),
+                value: Box::new(value),
                 kind: PropertyKind::Init,
                 method: false,
                 shorthand: false,
@@ -1064,8 +1103,8 @@ impl JsParser {
             // Shorthand property
             let position = Position::new(0, 0, 1, 1);
             Ok(ObjectProperty::Property(Property {
-                key: key.clone(),
-                value: key,
+                key: Box::new(key.clone()),
+                value: Box::new(key),
                 kind: PropertyKind::Init,
                 method: false,
                 shorthand: true,
@@ -1077,18 +1116,22 @@ impl JsParser {
 
     /// Parse a literal
     fn parse_literal(&mut self) -> Result<Literal> {
-        match self.current_token_type() {
+        match self.current_token_type().clone() {
             TokenType::Number(n) => {
                 self.advance(); // consume number
-                Ok(Literal::Number(*n))
+                Ok(Literal::Number(n))
+            }
+            TokenType::BigIntLiteral(digits) => {
+                self.advance(); // consume bigint
+                Ok(Literal::BigInt(digits))
             }
             TokenType::String(s) => {
                 self.advance(); // consume string
-                Ok(Literal::String(s.clone()))
+                Ok(Literal::String(s))
             }
             TokenType::Boolean(b) => {
                 self.advance(); // consume boolean
-                Ok(Literal::Boolean(*b))
+                Ok(Literal::Boolean(b))
             }
             TokenType::Null => {
                 self.advance(); // consume null
@@ -1146,7 +1189,7 @@ impl JsParser {
 
     /// Expect a specific token type
     fn expect(&mut self, expected: TokenType) -> Result<()> {
-        if self.current_token_type() == expected {
+        if self.check(&expected) {
             self.advance();
             Ok(())
         } else {
@@ -1156,12 +1199,22 @@ impl JsParser {
 
     /// Expect a semicolon
     fn expect_semicolon(&mut self) -> Result<()> {
-        if self.current_token_type() == TokenType::Semicolon {
+        if self.check(&TokenType::Semicolon) {
             self.advance();
         }
         Ok(())
     }
 
+    /// Check whether the current token is of a token type
+    fn check(&self, token_type: &TokenType) -> bool {
+        self.current_token_type() == token_type
+    }
+
+    /// Check whether the current token is an identifier
+    fn is_identifier(&self) -> bool {
+        matches!(self.current_token_type(), TokenType::Identifier(_))
+    }
+
     /// Get current token type
     fn current_token_type(&self) -> &TokenType {
         &self.current_token.as_ref().unwrap().token_type
@@ -1,10 +1,16 @@
+use crate::ast::Position;
 use crate::error::{Error, Result};
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 
 /// Source map version
 pub const SOURCE_MAP_VERSION: u32 = 3;
 
+/// Comment that links a script to its source map
+const SOURCE_MAPPING_URL_PREFIXES: [&str; 2] = ["//# sourceMappingURL=", "//@ sourceMappingURL="];
+
 /// Source map for mapping compiled code back to original source
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SourceMap {
@@ -88,6 +94,40 @@ impl SourceMap {
             Error::source_map(format!("Failed to parse source map: {}", e))
         })
     }
+
+    /// Parse a source map from a base64 `data:` URL
+    pub fn from_data_url(url: &str) -> Result<Self> {
+        let (header, data) = url.strip_prefix("data:")
+            .and_then(|rest| rest.split_once(','))
+            .ok_or_else(|| Error::source_map(format!("Not a data URL: {}", url)))?;
+        if !header.ends_with(";base64") {
+            return Err(Error::source_map("Source map data URL is not base64-encoded"));
+        }
+
+        let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim()).map_err(|e| {
+            Error::source_map(format!("Invalid base64 in source map data URL: {}", e))
+        })?;
+        let json = String::from_utf8(bytes)?;
+        Self::from_json(&json)
+    }
+
+    /// Find the `//# sourceMappingURL=` comment at the end of a script
+    pub fn find_source_mapping_url(source: &str) -> Option<&str> {
+        let last_line = source.lines().rev().find(|line| !line.trim().is_empty())?.trim();
+        SOURCE_MAPPING_URL_PREFIXES.iter()
+            .find_map(|prefix| last_line.strip_prefix(prefix))
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+    }
+
+    /// Map a position in the compiled source to the original source
+    ///
+    /// Positions use 1-based lines and columns, like the lexer.
+    pub fn apply_to_position(&self, compiled_pos: Position) -> Option<OriginalPosition> {
+        let consumer = SourceMapConsumer::new(self.clone()).ok()?;
+        let column = compiled_pos.column.checked_sub(1)?;
+        consumer.original_position_for(compiled_pos.line as u32, column as u32)
+    }
 }
 
 /// Source map generator for building source maps incrementally
@@ -300,7 +340,7 @@ impl SourceMapConsumer {
         let line = line as i32;
         let column = column as i32;
 
-        // Binary search for the mapping
+        // Binary search for the last mapping at or before the position
         let mut left = 0;
         let mut right = self.mappings.len();
 
@@ -309,7 +349,7 @@ impl SourceMapConsumer {
             let mapping = &self.mappings[mid];
 
             if mapping.generated_line < line || 
-               (mapping.generated_line == line && mapping.generated_column < column) {
+               (mapping.generated_line == line && mapping.generated_column <= column) {
                 left = mid + 1;
             } else {
                 right = mid;
@@ -478,7 +518,9 @@ struct DecodedMapping {
 }
 
 /// Original position information
-#[derive(Debug, Clone)]
+///
+/// Lines and columns are 0-based, as stored in the source map.
+#[derive(Debug, Clone, PartialEq)]
 pub struct OriginalPosition {
     pub source: Option<String>,
     pub line: Option<u32>,
@@ -486,6 +528,20 @@ pub struct OriginalPosition {
     pub name: Option<String>,
 }
 
+impl fmt::Display for OriginalPosition {
+    /// Format as `file:line:col` with 1-based lines and columns
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source.as_deref().unwrap_or("<unknown>"))?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line + 1)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column + 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Generated position information
 #[derive(Debug, Clone)]
 pub struct GeneratedPosition {
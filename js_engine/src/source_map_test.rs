@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use crate::ast::Position;
+    use crate::bytecode::{self, BytecodeEngine, BytecodeFunction, Instruction, Register};
+    use crate::error::Error;
+    use crate::source_map::{OriginalPosition, SourceMap};
+    use base64::Engine;
+    use std::collections::HashMap;
+
+    /// Map of `app.js`, bundled from `src/app.ts`
+    fn app_source_map(mappings: &str) -> SourceMap {
+        let mut source_map = SourceMap::new();
+        source_map.set_file("app.js".to_string());
+        source_map.add_source("src/app.ts".to_string());
+        source_map.set_mappings(mappings.to_string());
+        source_map
+    }
+
+    #[tokio::test]
+    async fn test_inline_source_map_maps_positions() {
+        // Column 0 of line 1 maps to line 9, column 4 of the original
+        let json = app_source_map("AASI").to_json().unwrap();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(json);
+        let script = format!("var answer = 42;\n//# sourceMappingURL=data:application/json;base64,{}\n", encoded);
+
+        let url = SourceMap::find_source_mapping_url(&script).unwrap();
+        let source_map = SourceMap::from_data_url(url).unwrap();
+        assert_eq!(source_map.sources, vec!["src/app.ts".to_string()]);
+
+        let original = source_map.apply_to_position(Position::new(4, 10, 1, 5)).unwrap();
+        assert_eq!(original, OriginalPosition {
+            source: Some("src/app.ts".to_string()),
+            line: Some(9),
+            column: Some(4),
+            name: None,
+        });
+        assert_eq!(original.to_string(), "src/app.ts:10:5");
+        assert!(source_map.apply_to_position(Position::new(0, 0, 2, 1)).is_none());
+
+        assert!(SourceMap::find_source_mapping_url("var answer = 42;").is_none());
+        assert!(SourceMap::from_data_url("data:application/json,{}").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_runtime_error_reports_original_position() {
+        // let f;
+        // let y = f();
+        let source = "let f;\nlet y = f();".to_string();
+        let bytecode = BytecodeFunction {
+            instructions: vec![
+                Instruction::LoadUndefined(Register(0)),
+                Instruction::Call(Register(0), Register(1), 0),
+                Instruction::ReturnUndefined,
+            ],
+            constants: vec![],
+            labels: HashMap::new(),
+            source_map: Some(bytecode::SourceMap {
+                mappings: vec![(0, 0), (1, 15)],
+                source,
+                // Column 8 of line 2 maps to line 2, column 2 of the original
+                script_map: Some(app_source_map(";QAEE")),
+            }),
+        };
+
+        let mut engine = BytecodeEngine::new();
+        let error = engine.execute_function(&bytecode, &[]).unwrap_err();
+        assert!(matches!(error, Error::Runtime { .. }));
+        let original = error.original_position().unwrap();
+        assert_eq!(original.to_string(), "src/app.ts:3:3");
+        assert!(error.to_string().ends_with("(src/app.ts:3:3)"));
+        assert_eq!(engine.call_stack_depth(), 0);
+    }
+}
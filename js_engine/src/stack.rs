@@ -2,7 +2,7 @@ use crate::error::{Error, Result};
 use std::collections::VecDeque;
 
 /// JavaScript value for stack operations
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Undefined,
     Null,
@@ -16,7 +16,7 @@ pub enum Value {
 }
 
 /// Function value
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FunctionValue {
     pub name: String,
     pub param_count: u32,
@@ -25,7 +25,7 @@ pub struct FunctionValue {
 }
 
 /// Class value
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ClassValue {
     pub name: String,
     pub constructor: Option<FunctionValue>,
@@ -425,9 +425,9 @@ impl StackAllocator {
     pub fn allocate_frame(&mut self, function: FunctionValue, return_address: Option<usize>) -> StackFrame {
         if let Some(mut frame) = self.frame_pool.pop() {
             // Reuse existing frame
+            frame.locals = vec![Value::Undefined; function.local_count as usize];
             frame.function = function;
             frame.pc = 0;
-            frame.locals = vec![Value::Undefined; function.local_count as usize];
             frame.operand_stack.clear();
             frame.return_address = return_address;
             frame.this_value = None;
@@ -506,10 +506,10 @@ impl StackGuard {
 
     /// Enter a new stack level
     pub fn enter(&mut self) -> Result<()> {
-        self.current_depth += 1;
-        if self.current_depth > self.max_depth {
+        if self.current_depth >= self.max_depth {
             return Err(Error::parsing("Stack overflow detected".to_string()));
         }
+        self.current_depth += 1;
         Ok(())
     }
 
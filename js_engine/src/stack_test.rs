@@ -76,6 +76,7 @@ mod tests {
         stack.dup().unwrap();
         assert_eq!(stack.size(), 3);
         assert_eq!(*stack.peek().unwrap(), Value::Number(2.0));
+        assert_eq!(stack.pop().unwrap(), Value::Number(2.0));
         
         // Test swap
         stack.swap().unwrap();
@@ -0,0 +1,399 @@
+use crate::builtins::{SharedArrayBuffer, TypedArray, TypedArrayType};
+use crate::error::{Error, Result};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Version of the serialization format, written as the first byte
+pub const FORMAT_VERSION: u8 = 1;
+
+// Value tags
+const TAG_UNDEFINED: u8 = 0x00;
+const TAG_NULL: u8 = 0x01;
+const TAG_FALSE: u8 = 0x02;
+const TAG_TRUE: u8 = 0x03;
+const TAG_NUMBER: u8 = 0x04;
+const TAG_STRING: u8 = 0x05;
+const TAG_OBJECT_REFERENCE: u8 = 0x10;
+const TAG_ARRAY: u8 = 0x11;
+const TAG_OBJECT: u8 = 0x12;
+const TAG_MAP: u8 = 0x13;
+const TAG_SET: u8 = 0x14;
+const TAG_ARRAY_BUFFER: u8 = 0x15;
+const TAG_TYPED_ARRAY: u8 = 0x16;
+
+/// TypedArray types, indexed by their serialized tag
+const TYPED_ARRAY_TYPES: [TypedArrayType; 11] = [
+    TypedArrayType::Int8Array,
+    TypedArrayType::Uint8Array,
+    TypedArrayType::Uint8ClampedArray,
+    TypedArrayType::Int16Array,
+    TypedArrayType::Uint16Array,
+    TypedArrayType::Int32Array,
+    TypedArrayType::Uint32Array,
+    TypedArrayType::Float32Array,
+    TypedArrayType::Float64Array,
+    TypedArrayType::BigInt64Array,
+    TypedArrayType::BigUint64Array,
+];
+
+/// Shared handle to a heap object
+pub type ObjectHandle = Arc<RwLock<HeapObject>>;
+
+/// Value that can be passed to the structured clone algorithm
+#[derive(Clone)]
+pub enum Value {
+    /// Undefined value
+    Undefined,
+    /// Null value
+    Null,
+    /// Boolean value
+    Boolean(bool),
+    /// Number value
+    Number(f64),
+    /// String value
+    String(String),
+    /// Reference to a heap object
+    Object(ObjectHandle),
+}
+
+/// Object on the heap, which may be referenced by several values
+#[derive(Debug)]
+pub enum HeapObject {
+    /// Array
+    Array(Vec<Value>),
+    /// Plain object, with properties in insertion order
+    Object(Vec<(String, Value)>),
+    /// Map, with entries in insertion order
+    Map(Vec<(Value, Value)>),
+    /// Set, with values in insertion order
+    Set(Vec<Value>),
+    /// ArrayBuffer
+    ArrayBuffer(Vec<u8>),
+    /// SharedArrayBuffer, which can't be cloned
+    SharedArrayBuffer(SharedArrayBuffer),
+    /// TypedArray viewing an ArrayBuffer
+    TypedArray {
+        /// Array type
+        array_type: TypedArrayType,
+        /// Viewed buffer
+        buffer: ObjectHandle,
+        /// Byte offset into the buffer
+        byte_offset: usize,
+        /// Length in elements
+        length: usize,
+    },
+}
+
+impl Value {
+    /// Allocate a heap object and get a value referring to it
+    pub fn object(object: HeapObject) -> Self {
+        Value::Object(Arc::new(RwLock::new(object)))
+    }
+
+    /// Get the heap object the value refers to
+    pub fn as_object(&self) -> Option<&ObjectHandle> {
+        match self {
+            Value::Object(handle) => Some(handle),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Undefined => write!(f, "Undefined"),
+            Value::Null => write!(f, "Null"),
+            Value::Boolean(b) => write!(f, "Boolean({})", b),
+            Value::Number(n) => write!(f, "Number({})", n),
+            Value::String(s) => write!(f, "String({:?})", s),
+            // Objects may be cyclic, so only their address is shown
+            Value::Object(handle) => write!(f, "Object({:p})", Arc::as_ptr(handle)),
+        }
+    }
+}
+
+/// Serializes values with the structured clone algorithm
+pub struct StructuredCloneSerializer {
+    /// Serialized bytes
+    output: Vec<u8>,
+    /// IDs of the objects written so far, by address
+    object_ids: HashMap<usize, u32>,
+}
+
+impl StructuredCloneSerializer {
+    /// Serialize a value
+    ///
+    /// Objects reachable more than once, including through cycles, are
+    /// written once and referred to by ID afterwards.
+    pub fn serialize(value: &Value) -> Result<Vec<u8>> {
+        let mut serializer = Self {
+            output: vec![FORMAT_VERSION],
+            object_ids: HashMap::new(),
+        };
+        serializer.write_value(value)?;
+        Ok(serializer.output)
+    }
+
+    /// Write a value
+    fn write_value(&mut self, value: &Value) -> Result<()> {
+        match value {
+            Value::Undefined => self.output.push(TAG_UNDEFINED),
+            Value::Null => self.output.push(TAG_NULL),
+            Value::Boolean(false) => self.output.push(TAG_FALSE),
+            Value::Boolean(true) => self.output.push(TAG_TRUE),
+            Value::Number(n) => {
+                self.output.push(TAG_NUMBER);
+                self.output.extend_from_slice(&n.to_le_bytes());
+            }
+            Value::String(s) => {
+                self.output.push(TAG_STRING);
+                self.write_string(s)?;
+            }
+            Value::Object(handle) => self.write_object(handle)?,
+        }
+        Ok(())
+    }
+
+    /// Write a heap object, or a reference to it if it was already written
+    fn write_object(&mut self, handle: &ObjectHandle) -> Result<()> {
+        let address = Arc::as_ptr(handle) as usize;
+        if let Some(&id) = self.object_ids.get(&address) {
+            self.output.push(TAG_OBJECT_REFERENCE);
+            self.output.extend_from_slice(&id.to_le_bytes());
+            return Ok(());
+        }
+
+        let object = handle.read();
+        if let HeapObject::SharedArrayBuffer(_) = &*object {
+            return Err(Error::data_clone("SharedArrayBuffer can't be cloned"));
+        }
+
+        let id = self.object_ids.len() as u32;
+        self.object_ids.insert(address, id);
+
+        match &*object {
+            HeapObject::Array(items) => {
+                self.output.push(TAG_ARRAY);
+                self.write_length(items.len())?;
+                for item in items {
+                    self.write_value(item)?;
+                }
+            }
+            HeapObject::Object(properties) => {
+                self.output.push(TAG_OBJECT);
+                self.write_length(properties.len())?;
+                for (key, value) in properties {
+                    self.write_string(key)?;
+                    self.write_value(value)?;
+                }
+            }
+            HeapObject::Map(entries) => {
+                self.output.push(TAG_MAP);
+                self.write_length(entries.len())?;
+                for (key, value) in entries {
+                    self.write_value(key)?;
+                    self.write_value(value)?;
+                }
+            }
+            HeapObject::Set(values) => {
+                self.output.push(TAG_SET);
+                self.write_length(values.len())?;
+                for value in values {
+                    self.write_value(value)?;
+                }
+            }
+            HeapObject::ArrayBuffer(bytes) => {
+                self.output.push(TAG_ARRAY_BUFFER);
+                self.write_length(bytes.len())?;
+                self.output.extend_from_slice(bytes);
+            }
+            HeapObject::SharedArrayBuffer(_) => unreachable!(),
+            HeapObject::TypedArray { array_type, buffer, byte_offset, length } => {
+                self.output.push(TAG_TYPED_ARRAY);
+                let type_tag = TYPED_ARRAY_TYPES.iter().position(|t| t == array_type).unwrap_or_default();
+                self.output.push(type_tag as u8);
+                self.write_length(*byte_offset)?;
+                self.write_length(*length)?;
+                self.write_object(buffer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a length or offset
+    fn write_length(&mut self, length: usize) -> Result<()> {
+        let length = u32::try_from(length)
+            .map_err(|_| Error::data_clone("Value is too large to clone"))?;
+        self.output.extend_from_slice(&length.to_le_bytes());
+        Ok(())
+    }
+
+    /// Write a length-prefixed UTF-8 string
+    fn write_string(&mut self, s: &str) -> Result<()> {
+        self.write_length(s.len())?;
+        self.output.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Deserializes values written by `StructuredCloneSerializer`
+pub struct StructuredCloneDeserializer<'a> {
+    /// Serialized bytes
+    input: &'a [u8],
+    /// Read position
+    position: usize,
+    /// Objects read so far, indexed by ID
+    objects: Vec<ObjectHandle>,
+}
+
+impl<'a> StructuredCloneDeserializer<'a> {
+    /// Deserialize a value
+    pub fn deserialize(bytes: &'a [u8]) -> Result<Value> {
+        let (&version, input) = bytes.split_first()
+            .ok_or_else(|| Error::data_clone("Serialized data is empty"))?;
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(Error::data_clone(format!("Unsupported structured clone version {}", version)));
+        }
+
+        let mut deserializer = Self {
+            input,
+            position: 0,
+            objects: Vec::new(),
+        };
+        let value = deserializer.read_value()?;
+        if deserializer.position != input.len() {
+            return Err(Error::data_clone("Unexpected data after the serialized value"));
+        }
+        Ok(value)
+    }
+
+    /// Read a value
+    fn read_value(&mut self) -> Result<Value> {
+        let value = match self.read_u8()? {
+            TAG_UNDEFINED => Value::Undefined,
+            TAG_NULL => Value::Null,
+            TAG_FALSE => Value::Boolean(false),
+            TAG_TRUE => Value::Boolean(true),
+            TAG_NUMBER => {
+                let bytes = self.read_bytes(8)?;
+                Value::Number(f64::from_le_bytes(bytes.try_into().unwrap()))
+            }
+            TAG_STRING => Value::String(self.read_string()?),
+            TAG_OBJECT_REFERENCE => {
+                let id = self.read_length()?;
+                let handle = self.objects.get(id)
+                    .ok_or_else(|| Error::data_clone(format!("Reference to unknown object {}", id)))?;
+                Value::Object(Arc::clone(handle))
+            }
+            tag => Value::Object(self.read_object(tag)?),
+        };
+        Ok(value)
+    }
+
+    /// Read a heap object with the given tag
+    ///
+    /// The object is given its ID before its contents are read, so that
+    /// references back to it from inside resolve.
+    fn read_object(&mut self, tag: u8) -> Result<ObjectHandle> {
+        let handle = Arc::new(RwLock::new(HeapObject::Array(Vec::new())));
+        self.objects.push(Arc::clone(&handle));
+
+        let object = match tag {
+            TAG_ARRAY => {
+                let length = self.read_length()?;
+                let mut items = Vec::new();
+                for _ in 0..length {
+                    items.push(self.read_value()?);
+                }
+                HeapObject::Array(items)
+            }
+            TAG_OBJECT => {
+                let length = self.read_length()?;
+                let mut properties = Vec::new();
+                for _ in 0..length {
+                    let key = self.read_string()?;
+                    properties.push((key, self.read_value()?));
+                }
+                HeapObject::Object(properties)
+            }
+            TAG_MAP => {
+                let length = self.read_length()?;
+                let mut entries = Vec::new();
+                for _ in 0..length {
+                    let key = self.read_value()?;
+                    entries.push((key, self.read_value()?));
+                }
+                HeapObject::Map(entries)
+            }
+            TAG_SET => {
+                let length = self.read_length()?;
+                let mut values = Vec::new();
+                for _ in 0..length {
+                    values.push(self.read_value()?);
+                }
+                HeapObject::Set(values)
+            }
+            TAG_ARRAY_BUFFER => {
+                let length = self.read_length()?;
+                HeapObject::ArrayBuffer(self.read_bytes(length)?.to_vec())
+            }
+            TAG_TYPED_ARRAY => {
+                let array_type = *TYPED_ARRAY_TYPES.get(self.read_u8()? as usize)
+                    .ok_or_else(|| Error::data_clone("Unknown TypedArray type"))?;
+                let byte_offset = self.read_length()?;
+                let length = self.read_length()?;
+                let buffer = match self.read_value()? {
+                    Value::Object(buffer) => buffer,
+                    _ => return Err(Error::data_clone("TypedArray without a buffer")),
+                };
+
+                let end = length.checked_mul(TypedArray::get_element_size(array_type))
+                    .and_then(|byte_length| byte_length.checked_add(byte_offset));
+                let in_bounds = match &*buffer.read() {
+                    HeapObject::ArrayBuffer(bytes) => end.is_some_and(|end| end <= bytes.len()),
+                    _ => return Err(Error::data_clone("TypedArray buffer isn't an ArrayBuffer")),
+                };
+                if !in_bounds {
+                    return Err(Error::data_clone("TypedArray is out of bounds of its buffer"));
+                }
+                HeapObject::TypedArray { array_type, buffer, byte_offset, length }
+            }
+            tag => return Err(Error::data_clone(format!("Unknown tag {:#04x}", tag))),
+        };
+
+        *handle.write() = object;
+        Ok(handle)
+    }
+
+    /// Read a byte
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    /// Read a length, offset or object ID
+    fn read_length(&mut self) -> Result<usize> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    }
+
+    /// Read a length-prefixed UTF-8 string
+    fn read_string(&mut self) -> Result<String> {
+        let length = self.read_length()?;
+        let bytes = self.read_bytes(length)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|e| Error::data_clone(format!("Invalid string: {}", e)))
+    }
+
+    /// Read a number of bytes
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        let end = self.position.checked_add(count)
+            .filter(|&end| end <= self.input.len())
+            .ok_or_else(|| Error::data_clone("Unexpected end of serialized data"))?;
+        let bytes = &self.input[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use crate::builtins::{SharedArrayBuffer, TypedArrayType};
+    use crate::error::Error;
+    use crate::structured_clone::{
+        HeapObject, StructuredCloneDeserializer, StructuredCloneSerializer, Value, FORMAT_VERSION,
+    };
+    use std::sync::Arc;
+
+    fn property(object: &Value, name: &str) -> Value {
+        match &*object.as_object().unwrap().read() {
+            HeapObject::Object(properties) => properties.iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+                .unwrap(),
+            other => panic!("expected a plain object, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_object_graph_with_cycle() {
+        let buffer = Value::object(HeapObject::ArrayBuffer(vec![1, 0, 2, 0]));
+        let view = Value::object(HeapObject::TypedArray {
+            array_type: TypedArrayType::Uint16Array,
+            buffer: buffer.as_object().unwrap().clone(),
+            byte_offset: 0,
+            length: 2,
+        });
+        let tags = Value::object(HeapObject::Set(vec![Value::String("a".to_string()), Value::Null]));
+        let lookup = Value::object(HeapObject::Map(vec![(Value::Number(1.0), Value::Boolean(true))]));
+        let root = Value::object(HeapObject::Object(Vec::new()));
+        let list = Value::object(HeapObject::Array(vec![Value::Number(1.5), Value::Undefined, root.clone()]));
+        *root.as_object().unwrap().write() = HeapObject::Object(vec![
+            ("name".to_string(), Value::String("root".to_string())),
+            ("list".to_string(), list),
+            ("tags".to_string(), tags),
+            ("lookup".to_string(), lookup),
+            ("buffer".to_string(), buffer),
+            ("view".to_string(), view),
+            ("self".to_string(), root.clone()),
+        ]);
+
+        let bytes = StructuredCloneSerializer::serialize(&root).unwrap();
+        assert_eq!(bytes[0], FORMAT_VERSION);
+        let clone = StructuredCloneDeserializer::deserialize(&bytes).unwrap();
+
+        let clone_handle = clone.as_object().unwrap();
+        assert!(!Arc::ptr_eq(clone_handle, root.as_object().unwrap()));
+        assert!(matches!(property(&clone, "name"), Value::String(s) if s == "root"));
+
+        // The cycle points back at the clone, not the original
+        let self_ref = property(&clone, "self");
+        assert!(Arc::ptr_eq(self_ref.as_object().unwrap(), clone_handle));
+        match &*property(&clone, "list").as_object().unwrap().read() {
+            HeapObject::Array(items) => {
+                assert!(matches!(items.as_slice(), [Value::Number(n), Value::Undefined, Value::Object(_)] if *n == 1.5));
+                assert!(Arc::ptr_eq(items[2].as_object().unwrap(), clone_handle));
+            }
+            other => panic!("expected an array, got {:?}", other),
+        }
+
+        assert!(matches!(&*property(&clone, "tags").as_object().unwrap().read(),
+            HeapObject::Set(values) if matches!(values.as_slice(), [Value::String(s), Value::Null] if s == "a")));
+        assert!(matches!(&*property(&clone, "lookup").as_object().unwrap().read(),
+            HeapObject::Map(entries) if matches!(entries.as_slice(), [(Value::Number(k), Value::Boolean(true))] if *k == 1.0)));
+
+        // The view still shares its buffer with the cloned buffer
+        let buffer = property(&clone, "buffer");
+        match &*property(&clone, "view").as_object().unwrap().read() {
+            HeapObject::TypedArray { array_type, buffer: view_buffer, byte_offset, length } => {
+                assert_eq!(*array_type, TypedArrayType::Uint16Array);
+                assert_eq!((*byte_offset, *length), (0, 2));
+                assert!(Arc::ptr_eq(view_buffer, buffer.as_object().unwrap()));
+            }
+            other => panic!("expected a typed array, got {:?}", other),
+        }
+        assert!(matches!(&*buffer.as_object().unwrap().read(), HeapObject::ArrayBuffer(bytes) if bytes == &[1, 0, 2, 0]));
+    }
+
+    #[tokio::test]
+    async fn test_shared_array_buffer_throws() {
+        let shared = Value::object(HeapObject::SharedArrayBuffer(SharedArrayBuffer::new(8)));
+        let message = Value::object(HeapObject::Array(vec![Value::Number(1.0), shared]));
+        let result = StructuredCloneSerializer::serialize(&message);
+        assert!(matches!(result, Err(Error::DataClone { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_malformed_data() {
+        let bytes = StructuredCloneSerializer::serialize(&Value::String("hello".to_string())).unwrap();
+        assert!(matches!(StructuredCloneDeserializer::deserialize(&bytes), Ok(Value::String(s)) if s == "hello"));
+
+        let mut newer = bytes.clone();
+        newer[0] = FORMAT_VERSION + 1;
+        assert!(matches!(StructuredCloneDeserializer::deserialize(&newer), Err(Error::DataClone { .. })));
+        assert!(StructuredCloneDeserializer::deserialize(&bytes[..bytes.len() - 1]).is_err());
+        assert!(StructuredCloneDeserializer::deserialize(&[]).is_err());
+    }
+}
@@ -2,6 +2,7 @@ use crate::error::{Error, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
 use parking_lot::RwLock;
+use crate::hot_path::HotPathOptimizer;
 
 /// Execution tier levels
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -23,6 +24,8 @@ pub struct FunctionStats {
     pub current_tier: ExecutionTier,
     /// Number of times executed
     pub execution_count: u64,
+    /// Number of calls recorded with `record_call`
+    pub call_count: u64,
     /// Total execution time in microseconds
     pub total_time_us: u64,
     /// Average execution time in microseconds
@@ -39,7 +42,7 @@ pub struct FunctionStats {
     pub is_optimized: bool,
 }
 
-/// Code cache entry for compiled functions
+/// Code cache entry for compiled functions, keyed by function ID
 #[derive(Debug, Clone)]
 pub struct CodeCacheEntry {
     /// Function identifier
@@ -60,12 +63,14 @@ pub struct CodeCacheEntry {
 pub struct TieringManager {
     /// Function statistics
     function_stats: Arc<RwLock<HashMap<String, FunctionStats>>>,
-    /// Code cache for compiled functions
+    /// Code cache for compiled functions, holding the highest tier compiled
     code_cache: Arc<RwLock<HashMap<String, CodeCacheEntry>>>,
     /// Tiering configuration
     config: TieringConfig,
     /// Execution engine for each tier
     engines: Arc<RwLock<TierEngines>>,
+    /// Optimizer notified when functions reach the optimizing tier
+    hot_path_optimizer: Option<Arc<HotPathOptimizer>>,
 }
 
 /// Configuration for the tiering system
@@ -75,6 +80,10 @@ pub struct TieringConfig {
     pub hot_threshold: u64,
     /// Optimization threshold
     pub optimization_threshold: u64,
+    /// Call count at which `record_call` promotes a function to the baseline tier
+    pub baseline_threshold: u64,
+    /// Call count at which `record_call` promotes a function to the optimizing tier
+    pub optimized_threshold: u64,
     /// Maximum code cache size
     pub max_cache_size: usize,
     /// Whether tiering is enabled
@@ -156,6 +165,8 @@ impl Default for TieringConfig {
         Self {
             hot_threshold: 50,
             optimization_threshold: 500,
+            baseline_threshold: 100,
+            optimized_threshold: 1000,
             max_cache_size: 1000,
             enabled: true,
             promotion_delays,
@@ -209,15 +220,61 @@ impl TieringManager {
             code_cache: Arc::new(RwLock::new(HashMap::new())),
             config,
             engines: Arc::new(RwLock::new(engines)),
+            hot_path_optimizer: None,
+        }
+    }
+
+    /// Set the optimizer to notify when functions reach the optimizing tier
+    pub fn set_hot_path_optimizer(&mut self, optimizer: Arc<HotPathOptimizer>) {
+        self.hot_path_optimizer = Some(optimizer);
+    }
+
+    /// Record a call to a function and get the tier it should run in
+    ///
+    /// The tier changes once the call count reaches `baseline_threshold` and
+    /// again at `optimized_threshold`, which is the caller's cue to compile a
+    /// faster representation of the function.
+    pub fn record_call(&self, function_id: &str) -> ExecutionTier {
+        let (tier, promoted_to_optimizing) = {
+            let mut stats = self.function_stats.write();
+            let function_stats = stats.entry(function_id.to_string())
+                .or_insert_with(|| self.new_function_stats(function_id));
+            function_stats.call_count += 1;
+
+            let tier = self.tier_for_call_count(function_stats.call_count);
+            let promoted = tier == ExecutionTier::Optimizing && function_stats.current_tier != tier;
+            function_stats.current_tier = tier;
+            function_stats.is_hot |= tier != ExecutionTier::Interpreter;
+            function_stats.is_optimized |= tier == ExecutionTier::Optimizing;
+            (tier, promoted)
+        };
+
+        if promoted_to_optimizing {
+            if let Some(optimizer) = &self.hot_path_optimizer {
+                optimizer.notify_function_optimized(function_id);
+            }
+        }
+
+        tier
+    }
+
+    /// Get the tier for a function that has been called `call_count` times
+    fn tier_for_call_count(&self, call_count: u64) -> ExecutionTier {
+        if !self.config.enabled {
+            ExecutionTier::Interpreter
+        } else if call_count >= self.config.optimized_threshold {
+            ExecutionTier::Optimizing
+        } else if call_count >= self.config.baseline_threshold {
+            ExecutionTier::Baseline
+        } else {
+            ExecutionTier::Interpreter
         }
     }
 
     /// Execute a function with tiering
     pub async fn execute_function(&self, function_id: &str, function_code: &str) -> Result<ExecutionResult> {
-        let start_time = self.get_timestamp();
-        
         // Get or create function stats
-        let mut stats = self.get_or_create_function_stats(function_id);
+        let stats = self.get_or_create_function_stats(function_id);
         
         // Determine execution tier
         let tier = self.determine_execution_tier(&stats);
@@ -229,10 +286,8 @@ impl TieringManager {
             ExecutionTier::Optimizing => self.execute_optimizing(function_id, function_code).await?,
         };
         
-        let execution_time = self.get_timestamp() - start_time;
-        
         // Update statistics
-        self.update_function_stats(function_id, execution_time, tier);
+        self.update_function_stats(function_id, result.execution_time_us, tier);
         
         // Check for tier promotion
         self.check_tier_promotion(function_id).await;
@@ -263,13 +318,13 @@ impl TieringManager {
 
     /// Execute function in baseline tier
     async fn execute_baseline(&self, function_id: &str, function_code: &str) -> Result<ExecutionResult> {
-        let mut engines = self.engines.write();
-        engines.baseline.stats.functions_executed += 1;
-        
         // Check if we need to compile
         if !self.is_cached(function_id, ExecutionTier::Baseline).await {
             self.compile_baseline(function_id, function_code).await?;
         }
+
+        let mut engines = self.engines.write();
+        engines.baseline.stats.functions_executed += 1;
         
         // Simulate baseline execution
         let result = ExecutionResult {
@@ -289,13 +344,13 @@ impl TieringManager {
 
     /// Execute function in optimizing tier
     async fn execute_optimizing(&self, function_id: &str, function_code: &str) -> Result<ExecutionResult> {
-        let mut engines = self.engines.write();
-        engines.optimizing.stats.functions_executed += 1;
-        
         // Check if we need to compile
         if !self.is_cached(function_id, ExecutionTier::Optimizing).await {
             self.compile_optimizing(function_id, function_code).await?;
         }
+
+        let mut engines = self.engines.write();
+        engines.optimizing.stats.functions_executed += 1;
         
         // Simulate optimizing execution
         let result = ExecutionResult {
@@ -404,19 +459,24 @@ impl TieringManager {
     /// Check if function is cached for a specific tier
     async fn is_cached(&self, function_id: &str, tier: ExecutionTier) -> bool {
         let cache = self.code_cache.read();
-        cache.contains_key(&format!("{}_{:?}", function_id, tier))
+        cache.get(function_id).is_some_and(|entry| entry.is_valid && entry.tier == tier)
     }
 
     /// Cache compiled code
     async fn cache_code(&self, function_id: &str, tier: ExecutionTier, code: Vec<u8>) {
+        self.store_compiled_code(function_id, tier, code);
+    }
+
+    /// Store the compiled artifact for a function, replacing any earlier tier
+    pub fn store_compiled_code(&self, function_id: &str, tier: ExecutionTier, code: Vec<u8>) {
         let mut cache = self.code_cache.write();
         
         // Check cache size limit
-        if cache.len() >= self.config.max_cache_size {
+        if cache.len() >= self.config.max_cache_size && !cache.contains_key(function_id) {
             self.evict_cache_entries(&mut cache);
         }
         
-        let key = format!("{}_{:?}", function_id, tier);
+        let key = function_id.to_string();
         let entry = CodeCacheEntry {
             function_id: function_id.to_string(),
             tier,
@@ -429,6 +489,14 @@ impl TieringManager {
         cache.insert(key, entry);
     }
 
+    /// Get the compiled artifact for a function
+    pub fn get_compiled_code(&self, function_id: &str) -> Option<CodeCacheEntry> {
+        let mut cache = self.code_cache.write();
+        let entry = cache.get_mut(function_id).filter(|entry| entry.is_valid)?;
+        entry.hit_count += 1;
+        Some(entry.clone())
+    }
+
     /// Evict cache entries when cache is full
     fn evict_cache_entries(&self, cache: &mut HashMap<String, CodeCacheEntry>) {
         // Simple LRU eviction - remove oldest entries
@@ -445,10 +513,18 @@ impl TieringManager {
     /// Get or create function statistics
     fn get_or_create_function_stats(&self, function_id: &str) -> FunctionStats {
         let mut stats = self.function_stats.write();
-        stats.entry(function_id.to_string()).or_insert_with(|| FunctionStats {
+        stats.entry(function_id.to_string())
+            .or_insert_with(|| self.new_function_stats(function_id))
+            .clone()
+    }
+
+    /// Create statistics for a function that hasn't run yet
+    fn new_function_stats(&self, function_id: &str) -> FunctionStats {
+        FunctionStats {
             function_id: function_id.to_string(),
             current_tier: ExecutionTier::Interpreter,
             execution_count: 0,
+            call_count: 0,
             total_time_us: 0,
             avg_time_us: 0,
             hot_threshold: self.config.hot_threshold,
@@ -456,7 +532,7 @@ impl TieringManager {
             last_execution: 0,
             is_hot: false,
             is_optimized: false,
-        }).clone()
+        }
     }
 
     /// Update function statistics
@@ -485,8 +561,13 @@ impl TieringManager {
         let code_cache = self.code_cache.read();
         let engines = self.engines.read();
 
+        let functions_in_tier = |tier| function_stats.values().filter(|stats| stats.current_tier == tier).count();
+
         TieringStats {
             total_functions: function_stats.len(),
+            interpreter_functions: functions_in_tier(ExecutionTier::Interpreter),
+            baseline_functions: functions_in_tier(ExecutionTier::Baseline),
+            optimizing_functions: functions_in_tier(ExecutionTier::Optimizing),
             cached_functions: code_cache.len(),
             interpreter_stats: engines.interpreter.stats.clone(),
             baseline_stats: engines.baseline.stats.clone(),
@@ -561,6 +642,12 @@ pub struct ExecutionResult {
 pub struct TieringStats {
     /// Total number of functions tracked
     pub total_functions: usize,
+    /// Number of functions in the interpreter tier
+    pub interpreter_functions: usize,
+    /// Number of functions in the baseline tier
+    pub baseline_functions: usize,
+    /// Number of functions in the optimizing tier
+    pub optimizing_functions: usize,
     /// Number of cached functions
     pub cached_functions: usize,
     /// Interpreter engine statistics
@@ -5,6 +5,8 @@ mod tests {
         TieringManager, TieringConfig, ExecutionTier, FunctionStats, CodeCacheEntry,
         ExecutionResult, TieringStats, EngineStats
     };
+    use crate::hot_path::{HotPathOptimizer, HotPathConfig, PathNode, PathNodeType};
+    use std::sync::Arc;
 
     #[tokio::test]
     async fn test_tiering_manager_creation() {
@@ -424,4 +426,87 @@ mod tests {
         assert_eq!(overall_stats.total_functions, 1);
         assert!(overall_stats.cached_functions > 0);
     }
+
+    #[tokio::test]
+    async fn test_record_call_promotes_through_tiers() {
+        let manager = TieringManager::new(TieringConfig::default());
+
+        for call in 1..=1000u64 {
+            let tier = manager.record_call("hot_loop");
+            let expected = if call >= 1000 {
+                ExecutionTier::Optimizing
+            } else if call >= 100 {
+                ExecutionTier::Baseline
+            } else {
+                ExecutionTier::Interpreter
+            };
+            assert_eq!(tier, expected, "call {}", call);
+        }
+
+        manager.record_call("warm");
+        for _ in 0..150 {
+            manager.record_call("baseline_func");
+        }
+
+        let function_stats = manager.get_function_stats("hot_loop").unwrap();
+        assert_eq!(function_stats.call_count, 1000);
+        assert!(function_stats.is_optimized);
+
+        let stats = manager.get_stats();
+        assert_eq!(stats.interpreter_functions, 1);
+        assert_eq!(stats.baseline_functions, 1);
+        assert_eq!(stats.optimizing_functions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_compiled_code_keyed_by_function() {
+        let manager = TieringManager::new(TieringConfig::default());
+
+        manager.store_compiled_code("f", ExecutionTier::Baseline, vec![1]);
+        manager.store_compiled_code("f", ExecutionTier::Optimizing, vec![2, 3]);
+        assert!(manager.get_compiled_code("g").is_none());
+
+        let entry = manager.get_compiled_code("f").unwrap();
+        assert_eq!(entry.function_id, "f");
+        assert_eq!(entry.tier, ExecutionTier::Optimizing);
+        assert_eq!(entry.code, vec![2, 3]);
+        assert_eq!(entry.hit_count, 1);
+        assert_eq!(manager.get_stats().cached_functions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_hot_path_optimizer_notified_at_optimized_tier() {
+        let optimizer = Arc::new(HotPathOptimizer::new(HotPathConfig::default()));
+        let path = vec![PathNode {
+            node_id: "loop".to_string(),
+            node_type: PathNodeType::Loop,
+            execution_count: 1,
+            avg_time_us: 10,
+            children: Vec::new(),
+            optimization_hints: Vec::new(),
+        }];
+        optimizer.record_path_execution("hot", path, 10).await.unwrap();
+
+        let mut config = TieringConfig::default();
+        config.baseline_threshold = 2;
+        config.optimized_threshold = 4;
+        let mut manager = TieringManager::new(config);
+        manager.set_hot_path_optimizer(Arc::clone(&optimizer));
+
+        for _ in 0..3 {
+            manager.record_call("hot");
+        }
+        assert!(!optimizer.is_function_optimized("hot"));
+        assert!(optimizer.get_optimization_queue().is_empty());
+
+        assert_eq!(manager.record_call("hot"), ExecutionTier::Optimizing);
+        assert!(optimizer.is_function_optimized("hot"));
+        let queue = optimizer.get_optimization_queue();
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue[0].function_id, "hot");
+
+        // Staying in the optimizing tier doesn't notify again
+        manager.record_call("hot");
+        assert_eq!(optimizer.get_optimization_queue().len(), 1);
+    }
 }
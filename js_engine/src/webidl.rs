@@ -214,23 +214,23 @@ pub struct WebIDLDefinition {
 /// WebIDL parser
 pub struct WebIDLParser {
     /// Current position in the input
-    position: usize,
+    pub(crate) position: usize,
     /// Input string
     input: String,
     /// Current line number
-    line: usize,
+    pub(crate) line: usize,
     /// Current column number
-    column: usize,
+    pub(crate) column: usize,
 }
 
 /// WebIDL generator
 pub struct WebIDLGenerator {
     /// Generated code
-    code: String,
+    pub(crate) code: String,
     /// Indentation level
-    indent_level: usize,
+    pub(crate) indent_level: usize,
     /// Type mappings
-    type_mappings: HashMap<WebIDLType, String>,
+    pub(crate) type_mappings: HashMap<WebIDLType, String>,
 }
 
 /// Fast DOM binding
@@ -558,7 +558,13 @@ impl WebIDLParser {
             return Ok(WebIDLType::Record(Box::new(key_type), Box::new(value_type)));
         }
         
-        let type_name = self.parse_identifier()?;
+        let mut type_name = self.parse_identifier()?;
+        if type_name == "unsigned" || type_name == "unrestricted" {
+            type_name = format!("{} {}", type_name, self.parse_identifier()?);
+        }
+        if type_name.ends_with("long") && self.peek_keyword("long") {
+            type_name = format!("{} {}", type_name, self.parse_identifier()?);
+        }
         let mut base_type = match type_name.as_str() {
             "DOMString" => WebIDLType::DOMString,
             "USVString" => WebIDLType::USVString,
@@ -685,7 +691,7 @@ impl WebIDLParser {
         let mut values = Vec::new();
         
         while !self.peek_char('}') {
-            let value = self.parse_identifier()?;
+            let value = self.parse_string()?;
             values.push(value);
             
             if self.peek_char(',') {
@@ -755,6 +761,20 @@ impl WebIDLParser {
         Ok(self.input[start..self.position].to_string())
     }
 
+    /// Parse a double-quoted string, returning its contents
+    fn parse_string(&mut self) -> Result<String> {
+        self.expect_char('"')?;
+        
+        let start = self.position;
+        while self.position < self.input.len() && !self.input[self.position..].starts_with('"') {
+            self.advance();
+        }
+        let value = self.input[start..self.position].to_string();
+        
+        self.expect_char('"')?;
+        Ok(value)
+    }
+
     /// Parse literal
     fn parse_literal(&mut self) -> Result<String> {
         self.skip_whitespace_and_comments();
@@ -782,13 +802,11 @@ impl WebIDLParser {
             let ch = self.input.chars().nth(self.position).unwrap();
             
             if ch.is_whitespace() {
+                self.advance();
                 if ch == '\n' {
                     self.line += 1;
                     self.column = 1;
-                } else {
-                    self.column += 1;
                 }
-                self.advance();
             } else if ch == '/' && self.peek_next_char() == Some('/') {
                 // Single-line comment
                 while self.position < self.input.len() {
@@ -827,8 +845,9 @@ impl WebIDLParser {
         }
     }
 
-    /// Peek at next character
-    fn peek_char(&self, expected: char) -> bool {
+    /// Peek at the next character after any whitespace
+    fn peek_char(&mut self, expected: char) -> bool {
+        self.skip_whitespace_and_comments();
         if self.position < self.input.len() {
             self.input.chars().nth(self.position) == Some(expected)
         } else {
@@ -845,11 +864,12 @@ impl WebIDLParser {
         }
     }
 
-    /// Peek at keyword
-    fn peek_keyword(&self, keyword: &str) -> bool {
+    /// Peek at the next word after any whitespace, matching only whole words
+    fn peek_keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace_and_comments();
         let end = self.position + keyword.len();
-        if end <= self.input.len() {
-            self.input[self.position..end] == *keyword
+        if end <= self.input.len() && self.input[self.position..end] == *keyword {
+            !self.input[end..].starts_with(|ch: char| ch.is_alphanumeric() || ch == '_')
         } else {
             false
         }
@@ -1083,7 +1103,7 @@ impl WebIDLGenerator {
     }
 
     /// Map WebIDL type to Rust type
-    fn map_type(&self, webidl_type: &WebIDLType) -> Result<String> {
+    pub(crate) fn map_type(&self, webidl_type: &WebIDLType) -> Result<String> {
         match webidl_type {
             WebIDLType::Interface(name) => Ok(name.clone()),
             WebIDLType::Nullable(inner) => {
@@ -1125,7 +1145,7 @@ impl WebIDLGenerator {
     }
 
     /// Get default value for type
-    fn get_default_value(&self, webidl_type: &WebIDLType) -> Result<String> {
+    pub(crate) fn get_default_value(&self, webidl_type: &WebIDLType) -> Result<String> {
         match webidl_type {
             WebIDLType::Boolean => Ok("false".to_string()),
             WebIDLType::Byte | WebIDLType::Octet | WebIDLType::Short | WebIDLType::UnsignedShort |
@@ -1227,7 +1247,7 @@ impl FastDOMBinding {
             stats.cache_hits += 1;
             stats.total_method_calls += 1;
             
-            let call_time = start_time.elapsed().as_micros() as f64;
+            let call_time = start_time.elapsed().as_secs_f64() * 1_000_000.0;
             stats.avg_method_call_time_us = 
                 (stats.avg_method_call_time_us * (stats.total_method_calls - 1) as f64 + call_time) / stats.total_method_calls as f64;
             
@@ -1252,7 +1272,7 @@ impl FastDOMBinding {
             stats.cache_misses += 1;
             stats.total_method_calls += 1;
             
-            let call_time = start_time.elapsed().as_micros() as f64;
+            let call_time = start_time.elapsed().as_secs_f64() * 1_000_000.0;
             stats.avg_method_call_time_us = 
                 (stats.avg_method_call_time_us * (stats.total_method_calls - 1) as f64 + call_time) / stats.total_method_calls as f64;
             
@@ -1279,7 +1299,7 @@ impl FastDOMBinding {
             stats.cache_hits += 1;
             stats.total_property_accesses += 1;
             
-            let access_time = start_time.elapsed().as_micros() as f64;
+            let access_time = start_time.elapsed().as_secs_f64() * 1_000_000.0;
             stats.avg_property_access_time_us = 
                 (stats.avg_property_access_time_us * (stats.total_property_accesses - 1) as f64 + access_time) / stats.total_property_accesses as f64;
             
@@ -1304,7 +1324,7 @@ impl FastDOMBinding {
             stats.cache_misses += 1;
             stats.total_property_accesses += 1;
             
-            let access_time = start_time.elapsed().as_micros() as f64;
+            let access_time = start_time.elapsed().as_secs_f64() * 1_000_000.0;
             stats.avg_property_access_time_us = 
                 (stats.avg_property_access_time_us * (stats.total_property_accesses - 1) as f64 + access_time) / stats.total_property_accesses as f64;
             
@@ -1319,7 +1339,7 @@ impl FastDOMBinding {
     pub async fn set_property_value(&self, interface_name: &str, property_name: &str, value: Value) -> Result<()> {
         if let Some(property_binding) = self.get_property(interface_name, property_name) {
             if property_binding.readonly {
-                return Err(Error::parsing(format!("Property {} is read-only", property_name)));
+                return Err(Error::runtime(format!("Property {} is read-only", property_name), None));
             }
             
             // In a real implementation, this would set the property value
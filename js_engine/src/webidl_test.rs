@@ -5,8 +5,9 @@ mod tests {
         WebIDLParser, WebIDLGenerator, FastDOMBinding, WebIDLDefinition,
         WebIDLInterface, WebIDLMethod, WebIDLProperty, WebIDLArgument,
         WebIDLType, InterfaceBinding, MethodBinding, PropertyBinding,
-        Value
+        WebIDLDictionary, WebIDLDictionaryMember, WebIDLEnum, Value
     };
+    use std::collections::HashMap;
 
     #[tokio::test]
     async fn test_webidl_parser_creation() {
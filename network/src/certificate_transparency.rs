@@ -0,0 +1,323 @@
+//! Certificate Transparency verification for the network process
+//!
+//! Verifies Signed Certificate Timestamps (SCTs) delivered in the TLS
+//! `signed_certificate_timestamp` handshake extension (RFC 6962) against a
+//! set of known CT log public keys.
+
+use base64::Engine;
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use p256::pkcs8::DecodePublicKey;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+use crate::TlsError;
+
+/// Google "Argon2024" log public key (DER, base64)
+pub const GOOGLE_ARGON_2024_KEY: &str = "MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEHblsqctplMVc5ramA7vSuNxUQxcomQwGAVAdnWTAWUYr3MgDHQW0LagJ95lB7QT75Ve6JgT2EVLOFGU7L3YrwA==";
+
+/// Let's Encrypt "Oak2024H1" log public key (DER, base64)
+pub const LETS_ENCRYPT_OAK_2024H1_KEY: &str = "MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEVkPXfnvUcre6qVG9NpO36bWSD+pet0Wjkv3JpTyArBog7yUvuOEg96g6LgeN5uuk4n0kY59Gv5RzUo2Wrqkm/Q==";
+
+/// Minimum number of valid SCTs from distinct logs required for a connection
+pub const MIN_REQUIRED_SCTS: usize = 2;
+
+/// SCT version (RFC 6962 only defines v1)
+const SCT_VERSION_V1: u8 = 0;
+
+/// `SignatureType.certificate_timestamp`
+const SIGNATURE_TYPE_CERTIFICATE_TIMESTAMP: u8 = 0;
+
+/// `LogEntryType.x509_entry`
+const LOG_ENTRY_TYPE_X509: u16 = 0;
+
+/// `HashAlgorithm.sha256`
+const HASH_ALGORITHM_SHA256: u8 = 4;
+
+/// `SignatureAlgorithm.ecdsa`
+const SIGNATURE_ALGORITHM_ECDSA: u8 = 3;
+
+/// A known Certificate Transparency log
+#[derive(Debug, Clone)]
+pub struct CtLog {
+    /// Human readable log name
+    pub name: String,
+    /// Log ID (SHA-256 of the DER-encoded public key)
+    pub log_id: [u8; 32],
+    /// Log verification key
+    key: VerifyingKey,
+}
+
+impl CtLog {
+    /// Create a log from its DER-encoded SubjectPublicKeyInfo
+    pub fn from_der(name: &str, der: &[u8]) -> Result<Self, TlsError> {
+        let key = VerifyingKey::from_public_key_der(der)
+            .map_err(|e| TlsError::InvalidLogKey(format!("{}: {}", name, e)))?;
+        let log_id: [u8; 32] = Sha256::digest(der).into();
+
+        Ok(Self {
+            name: name.to_string(),
+            log_id,
+            key,
+        })
+    }
+
+    /// Create a log from a base64-encoded DER public key
+    pub fn from_base64(name: &str, encoded: &str) -> Result<Self, TlsError> {
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e| TlsError::InvalidLogKey(format!("{}: {}", name, e)))?;
+        Self::from_der(name, &der)
+    }
+}
+
+/// A Signed Certificate Timestamp as carried in the TLS extension
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedCertificateTimestamp {
+    /// SCT version
+    pub version: u8,
+    /// ID of the log that issued the SCT
+    pub log_id: [u8; 32],
+    /// Milliseconds since the UNIX epoch
+    pub timestamp: u64,
+    /// Opaque CT extensions
+    pub extensions: Vec<u8>,
+    /// Hash algorithm of the signature
+    pub hash_algorithm: u8,
+    /// Signature algorithm of the signature
+    pub signature_algorithm: u8,
+    /// DER-encoded signature
+    pub signature: Vec<u8>,
+}
+
+impl SignedCertificateTimestamp {
+    /// Parse a `SignedCertificateTimestampList` from the raw extension data
+    pub fn parse_list(data: &[u8]) -> Result<Vec<Self>, TlsError> {
+        let mut reader = Reader::new(data);
+        let list_len = reader.read_u16()? as usize;
+        let mut list = Reader::new(reader.read_bytes(list_len)?);
+        reader.expect_end()?;
+
+        let mut scts = Vec::new();
+        while !list.is_empty() {
+            let sct_len = list.read_u16()? as usize;
+            scts.push(Self::parse(list.read_bytes(sct_len)?)?);
+        }
+
+        Ok(scts)
+    }
+
+    /// Parse a single serialized SCT
+    pub fn parse(data: &[u8]) -> Result<Self, TlsError> {
+        let mut reader = Reader::new(data);
+        let version = reader.read_u8()?;
+        let mut log_id = [0u8; 32];
+        log_id.copy_from_slice(reader.read_bytes(32)?);
+        let timestamp = reader.read_u64()?;
+        let extensions_len = reader.read_u16()? as usize;
+        let extensions = reader.read_bytes(extensions_len)?.to_vec();
+        let hash_algorithm = reader.read_u8()?;
+        let signature_algorithm = reader.read_u8()?;
+        let signature_len = reader.read_u16()? as usize;
+        let signature = reader.read_bytes(signature_len)?.to_vec();
+        reader.expect_end()?;
+
+        Ok(Self {
+            version,
+            log_id,
+            timestamp,
+            extensions,
+            hash_algorithm,
+            signature_algorithm,
+            signature,
+        })
+    }
+
+    /// Serialize the SCT into its wire format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(47 + self.extensions.len() + self.signature.len());
+        out.push(self.version);
+        out.extend_from_slice(&self.log_id);
+        out.extend_from_slice(&self.timestamp.to_be_bytes());
+        out.extend_from_slice(&(self.extensions.len() as u16).to_be_bytes());
+        out.extend_from_slice(&self.extensions);
+        out.push(self.hash_algorithm);
+        out.push(self.signature_algorithm);
+        out.extend_from_slice(&(self.signature.len() as u16).to_be_bytes());
+        out.extend_from_slice(&self.signature);
+        out
+    }
+
+    /// Build the `digitally-signed` input covered by the log's signature
+    pub fn signed_data(&self, leaf_cert: &[u8]) -> Vec<u8> {
+        let mut data = Vec::with_capacity(15 + leaf_cert.len() + self.extensions.len());
+        data.push(self.version);
+        data.push(SIGNATURE_TYPE_CERTIFICATE_TIMESTAMP);
+        data.extend_from_slice(&self.timestamp.to_be_bytes());
+        data.extend_from_slice(&LOG_ENTRY_TYPE_X509.to_be_bytes());
+        data.extend_from_slice(&(leaf_cert.len() as u32).to_be_bytes()[1..]);
+        data.extend_from_slice(leaf_cert);
+        data.extend_from_slice(&(self.extensions.len() as u16).to_be_bytes());
+        data.extend_from_slice(&self.extensions);
+        data
+    }
+}
+
+/// Result of verifying the SCTs for a certificate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CtVerificationResult {
+    /// Number of valid SCTs from distinct known logs
+    pub valid_sct_count: usize,
+    /// Number of valid SCTs required by policy
+    pub required_sct_count: usize,
+}
+
+impl CtVerificationResult {
+    /// Check if the certificate satisfies the CT policy
+    pub fn is_compliant(&self) -> bool {
+        self.valid_sct_count >= self.required_sct_count
+    }
+}
+
+/// Certificate Transparency verifier
+#[derive(Debug, Clone)]
+pub struct CtVerifier {
+    /// Known CT logs
+    logs: Vec<CtLog>,
+    /// Number of valid SCTs required
+    required_sct_count: usize,
+}
+
+impl CtVerifier {
+    /// Create a verifier trusting the built-in CT logs
+    pub fn new() -> Self {
+        let logs = vec![
+            CtLog::from_base64("Google 'Argon2024' log", GOOGLE_ARGON_2024_KEY)
+                .expect("built-in CT log key is valid"),
+            CtLog::from_base64("Let's Encrypt 'Oak2024H1' log", LETS_ENCRYPT_OAK_2024H1_KEY)
+                .expect("built-in CT log key is valid"),
+        ];
+
+        Self::with_logs(logs)
+    }
+
+    /// Create a verifier trusting the given CT logs
+    pub fn with_logs(logs: Vec<CtLog>) -> Self {
+        Self {
+            logs,
+            required_sct_count: MIN_REQUIRED_SCTS,
+        }
+    }
+
+    /// Get the known CT logs
+    pub fn logs(&self) -> &[CtLog] {
+        &self.logs
+    }
+
+    /// Verify the SCTs presented for a leaf certificate
+    pub fn verify_scts(&self, leaf_cert: &[u8], scts: &[SignedCertificateTimestamp]) -> CtVerificationResult {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+
+        let mut verified_logs = HashSet::new();
+        for sct in scts {
+            match self.verify_sct(leaf_cert, sct, now_ms) {
+                Ok(log) => {
+                    debug!("Valid SCT from {}", log.name);
+                    verified_logs.insert(log.log_id);
+                }
+                Err(e) => warn!("Rejected SCT: {}", e),
+            }
+        }
+
+        CtVerificationResult {
+            valid_sct_count: verified_logs.len(),
+            required_sct_count: self.required_sct_count,
+        }
+    }
+
+    /// Verify a single SCT, returning the log that issued it
+    fn verify_sct(&self, leaf_cert: &[u8], sct: &SignedCertificateTimestamp, now_ms: u64) -> Result<&CtLog, TlsError> {
+        if sct.version != SCT_VERSION_V1 {
+            return Err(TlsError::InvalidSct(format!("unsupported version {}", sct.version)));
+        }
+
+        if sct.hash_algorithm != HASH_ALGORITHM_SHA256 || sct.signature_algorithm != SIGNATURE_ALGORITHM_ECDSA {
+            return Err(TlsError::InvalidSct("unsupported signature algorithm".to_string()));
+        }
+
+        if sct.timestamp > now_ms {
+            return Err(TlsError::InvalidSct("timestamp is in the future".to_string()));
+        }
+
+        let log = self.logs.iter()
+            .find(|log| log.log_id == sct.log_id)
+            .ok_or_else(|| TlsError::InvalidSct("unknown log".to_string()))?;
+
+        let signature = Signature::from_der(&sct.signature)
+            .map_err(|e| TlsError::InvalidSct(format!("malformed signature: {}", e)))?;
+
+        log.key.verify(&sct.signed_data(leaf_cert), &signature)
+            .map_err(|_| TlsError::InvalidSct(format!("bad signature from {}", log.name)))?;
+
+        Ok(log)
+    }
+}
+
+impl Default for CtVerifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Minimal big-endian reader for TLS wire structures
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], TlsError> {
+        let end = self.pos.checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| TlsError::InvalidSct("truncated SCT data".to_string()))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, TlsError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, TlsError> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, TlsError> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.read_bytes(8)?);
+        Ok(u64::from_be_bytes(buf))
+    }
+
+    fn expect_end(&self) -> Result<(), TlsError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(TlsError::InvalidSct("trailing SCT data".to_string()))
+        }
+    }
+}
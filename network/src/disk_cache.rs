@@ -0,0 +1,386 @@
+//! Persistent HTTP response cache for the network process
+//!
+//! Responses are stored in a `sled` database at `cache_dir/responses.sled`,
+//! keyed by the SHA-256 hash of the request URL. A second tree records the
+//! last access of every entry so that the cache can be trimmed in LRU order
+//! once the stored entries grow past its size budget. With a storage cipher
+//! set, entries are sealed with the key of the response's origin.
+
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::{Duration, SystemTime};
+
+use common::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use storage::StorageCipher;
+use tracing::{debug, info, warn};
+
+use crate::NetworkResponse;
+
+/// Name of the sled database inside the cache directory
+const DATABASE_NAME: &str = "responses.sled";
+
+/// Tree holding the last access counter of each entry
+const ACCESS_TREE: &str = "access";
+
+/// Lifetime of responses without an explicit `max-age`
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Fraction of `max_size` the eviction pass trims the cache down to
+const EVICTION_LOW_WATER_PERCENT: usize = 75;
+
+/// A response stored in the disk cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedEntry {
+    /// Cached response
+    pub response: NetworkResponse,
+    /// Time after which the entry is stale
+    pub expires_at: SystemTime,
+    /// `ETag` validator
+    pub etag: Option<String>,
+    /// `Last-Modified` validator
+    pub last_modified: Option<String>,
+}
+
+impl CachedEntry {
+    /// Build a cache entry from the response's caching headers
+    ///
+    /// Returns `None` if the response must not be stored.
+    pub fn from_response(response: &NetworkResponse) -> Option<Self> {
+        let cache_control = header(response, "cache-control").unwrap_or_default().to_ascii_lowercase();
+        if cache_control.split(',').any(|d| d.trim() == "no-store") {
+            return None;
+        }
+
+        let ttl = cache_control
+            .split(',')
+            .filter_map(|d| d.trim().strip_prefix("max-age="))
+            .find_map(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TTL);
+
+        Some(Self {
+            response: response.clone(),
+            expires_at: SystemTime::now() + ttl,
+            etag: header(response, "etag"),
+            last_modified: header(response, "last-modified"),
+        })
+    }
+
+    /// Check if the entry has expired
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= SystemTime::now()
+    }
+}
+
+/// Disk-backed response cache
+pub struct DiskCache {
+    /// Cache directory
+    cache_dir: PathBuf,
+    /// Maximum size on disk in bytes
+    max_size: usize,
+    /// Response database
+    db: Arc<CacheDatabase>,
+    /// Last access counter per key
+    access: sled::Tree,
+    /// Cipher sealing entries, when encryption is enabled
+    cipher: Option<Arc<StorageCipher>>,
+}
+
+impl DiskCache {
+    /// Create a disk cache in the default cache directory
+    pub async fn new(max_size_mb: usize) -> Result<Self> {
+        let cache_dir = std::env::temp_dir().join("matte-browser-cache");
+        Self::with_dir(cache_dir, max_size_mb).await
+    }
+
+    /// Create a disk cache in the given directory
+    pub async fn with_dir(cache_dir: impl Into<PathBuf>, max_size_mb: usize) -> Result<Self> {
+        let cache_dir = cache_dir.into();
+        std::fs::create_dir_all(&cache_dir)?;
+
+        let db = open_database(&cache_dir.join(DATABASE_NAME))?;
+        let access = db.open_tree(ACCESS_TREE).map_err(db_error)?;
+
+        info!("Opened disk cache at {:?}", cache_dir);
+        Ok(Self {
+            cache_dir,
+            max_size: max_size_mb * 1024 * 1024,
+            db,
+            access,
+            cipher: None,
+        })
+    }
+
+    /// Encrypt entries written from now on, or stop with `None`
+    pub fn set_cipher(&mut self, cipher: Option<Arc<StorageCipher>>) {
+        self.cipher = cipher;
+    }
+
+    /// Get the cache directory
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    /// Get a cached response
+    pub async fn get(&self, url: &str) -> Result<Option<NetworkResponse>> {
+        Ok(self.get_entry(url).await?.map(|entry| entry.response))
+    }
+
+    /// Get a cached entry, removing it if it has expired
+    pub async fn get_entry(&self, url: &str) -> Result<Option<CachedEntry>> {
+        let key = cache_key(url);
+        let Some(bytes) = self.db.get(key).map_err(db_error)? else {
+            return Ok(None);
+        };
+
+        let bytes = match &self.cipher {
+            Some(cipher) => match cipher.decrypt(&entry_origin(url), &bytes) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    warn!("Dropping undecryptable disk cache entry for {}: {}", url, e);
+                    self.remove_key(&key)?;
+                    return Ok(None);
+                }
+            },
+            None => bytes.to_vec(),
+        };
+
+        let entry: CachedEntry = match bincode::deserialize(&bytes) {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Dropping corrupt disk cache entry for {}: {}", url, e);
+                self.remove_key(&key)?;
+                return Ok(None);
+            }
+        };
+
+        if entry.is_expired() {
+            debug!("Disk cache entry for {} expired", url);
+            self.remove_key(&key)?;
+            return Ok(None);
+        }
+
+        self.touch(&key)?;
+        Ok(Some(entry))
+    }
+
+    /// Store a response using its caching headers
+    pub async fn put(&self, url: &str, response: &NetworkResponse) -> Result<()> {
+        match CachedEntry::from_response(response) {
+            Some(entry) => self.put_entry(url, &entry).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Store a cache entry
+    pub async fn put_entry(&self, url: &str, entry: &CachedEntry) -> Result<()> {
+        let key = cache_key(url);
+        let bytes = bincode::serialize(entry)
+            .map_err(|e| Error::IoError(format!("Failed to serialize cache entry: {}", e)))?;
+        let bytes = match &self.cipher {
+            Some(cipher) => cipher.encrypt(&entry_origin(url), &bytes)
+                .map_err(|e| Error::IoError(format!("Failed to encrypt cache entry: {}", e)))?,
+            None => bytes,
+        };
+
+        let stored = entry_size(&key, &bytes);
+        let previous = self.db.insert(key, bytes).map_err(db_error)?;
+        self.db.grow(stored);
+        if let Some(previous) = previous {
+            self.db.shrink(entry_size(&key, &previous));
+        }
+        self.touch(&key)?;
+
+        if self.size() > self.max_size {
+            self.evict()?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove a cached response
+    pub async fn remove(&self, url: &str) -> Result<()> {
+        self.remove_key(&cache_key(url))
+    }
+
+    /// Remove every cached response
+    pub async fn clear(&self) -> Result<()> {
+        self.db.clear().map_err(db_error)?;
+        self.db.stored_size.store(0, Ordering::Relaxed);
+        self.access.clear().map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Size of the stored entries in bytes
+    ///
+    /// Unlike the database's size on disk, this shrinks as entries are removed.
+    pub fn size(&self) -> usize {
+        self.db.stored_size.load(Ordering::Relaxed)
+    }
+
+    /// Number of cached responses
+    pub fn len(&self) -> usize {
+        self.db.len()
+    }
+
+    /// Check if the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.db.is_empty()
+    }
+
+    /// Update the size budget
+    pub async fn update_size(&mut self, max_size_mb: usize) -> Result<()> {
+        self.max_size = max_size_mb * 1024 * 1024;
+        if self.size() > self.max_size {
+            self.evict()?;
+        }
+        Ok(())
+    }
+
+    /// Flush pending writes to disk
+    pub async fn shutdown(&mut self) -> Result<()> {
+        self.db.flush_async().await.map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Record an access to a key
+    fn touch(&self, key: &[u8]) -> Result<()> {
+        let counter = self.db.generate_id().map_err(db_error)?;
+        self.access.insert(key, &counter.to_be_bytes()).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn remove_key(&self, key: &[u8]) -> Result<()> {
+        if let Some(previous) = self.db.remove(key).map_err(db_error)? {
+            self.db.shrink(entry_size(key, &previous));
+        }
+        self.access.remove(key).map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Evict least recently used entries until the payload fits the low water mark
+    fn evict(&self) -> Result<()> {
+        let mut sizes = HashMap::new();
+        let mut total = 0usize;
+        for item in self.db.iter() {
+            let (key, value) = item.map_err(db_error)?;
+            total += entry_size(&key, &value);
+            sizes.insert(key.to_vec(), entry_size(&key, &value));
+        }
+
+        let mut by_access = Vec::with_capacity(sizes.len());
+        for item in self.access.iter() {
+            let (key, counter) = item.map_err(db_error)?;
+            let counter = counter.as_ref().try_into().map(u64::from_be_bytes).unwrap_or(0);
+            by_access.push((counter, key.to_vec()));
+        }
+        by_access.sort();
+
+        let target = self.max_size * EVICTION_LOW_WATER_PERCENT / 100;
+        let mut evicted = 0;
+        for (_, key) in by_access {
+            if total <= target {
+                break;
+            }
+            total -= sizes.get(&key).copied().unwrap_or(0);
+            self.remove_key(&key)?;
+            evicted += 1;
+        }
+
+        debug!("Disk cache evicted {} entries", evicted);
+        Ok(())
+    }
+}
+
+/// Response database shared by the caches of one directory
+struct CacheDatabase {
+    db: sled::Db,
+    /// Size of the stored keys and entries in bytes
+    ///
+    /// Updates wrap, so a removal counted before the insert it undoes
+    /// evens out once the insert is counted.
+    stored_size: AtomicUsize,
+}
+
+impl CacheDatabase {
+    fn open(path: &Path) -> Result<Self> {
+        let db = sled::open(path).map_err(db_error)?;
+        let mut stored_size = 0;
+        for item in db.iter() {
+            let (key, value) = item.map_err(db_error)?;
+            stored_size += entry_size(&key, &value);
+        }
+        Ok(Self { db, stored_size: AtomicUsize::new(stored_size) })
+    }
+
+    fn grow(&self, bytes: usize) {
+        self.stored_size.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn shrink(&self, bytes: usize) {
+        self.stored_size.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+impl Deref for CacheDatabase {
+    type Target = sled::Db;
+
+    fn deref(&self) -> &sled::Db {
+        &self.db
+    }
+}
+
+/// Bytes a stored entry takes up
+fn entry_size(key: &[u8], value: &[u8]) -> usize {
+    key.len() + value.len()
+}
+
+/// Hash a URL into a cache key
+fn cache_key(url: &str) -> [u8; 32] {
+    Sha256::digest(url.as_bytes()).into()
+}
+
+/// Origin whose key seals the entry of a URL
+fn entry_origin(url: &str) -> String {
+    url::Url::parse(url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|_| url.to_string())
+}
+
+/// Look up a response header case-insensitively
+fn header(response: &NetworkResponse, name: &str) -> Option<String> {
+    response.headers.iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.clone())
+}
+
+fn db_error(err: sled::Error) -> Error {
+    Error::IoError(format!("Disk cache error: {}", err))
+}
+
+/// Open a database, sharing handles between caches in the same process
+///
+/// sled holds an exclusive lock on its directory, so every `DiskCache`
+/// pointing at the same path must reuse one handle.
+fn open_database(path: &Path) -> Result<Arc<CacheDatabase>> {
+    static OPEN_DATABASES: OnceLock<Mutex<HashMap<PathBuf, Weak<CacheDatabase>>>> = OnceLock::new();
+
+    let mut open = OPEN_DATABASES
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .map_err(|_| Error::InvalidState("Disk cache registry poisoned".to_string()))?;
+
+    if let Some(db) = open.get(path).and_then(Weak::upgrade) {
+        return Ok(db);
+    }
+
+    let db = Arc::new(CacheDatabase::open(path)?);
+    open.retain(|_, db| db.strong_count() > 0);
+    open.insert(path.to_path_buf(), Arc::downgrade(&db));
+    Ok(db)
+}
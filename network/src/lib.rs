@@ -4,11 +4,33 @@
 //! TLS connections, caching, and network security policies.
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
-use tracing::{debug, info};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
 use common::error::{Error, Result};
+use common::ipc::{Channel, IpcMessage, NetworkErrorMessage, NetworkRequestMessage, NetworkResponseMessage, WebSocketOpenMessage};
 use common::types::TabId;
+use storage::cookie_store::{self, CookieStore, RequestSite, SetCookie};
+use storage::{CachedResponse, FetchRequest, StorageCipher};
+use url::Url;
+
+pub mod certificate_transparency;
+pub mod disk_cache;
+pub mod sse;
+pub mod webrtc_ice;
+pub mod websocket;
+
+pub use certificate_transparency::{CtLog, CtVerificationResult, CtVerifier, SignedCertificateTimestamp};
+pub use disk_cache::{CachedEntry, DiskCache};
+pub use sse::{SseConnection, SseEvent, SseParser, SseReceiver};
+pub use webrtc_ice::{
+    DataChannel, IceAgent, IceAgentId, IceCandidate, IceCandidateType, IceConfig, IceConnectionState, IceCredentials, TurnServer,
+};
+pub use websocket::{WebSocketConfig, WebSocketConnection, WebSocketId, WsMessage};
 
 /// Network process configuration
 #[derive(Debug, Clone)]
@@ -33,6 +55,16 @@ pub struct NetworkConfig {
     pub memory_cache_enabled: bool,
     /// TLS configuration
     pub tls_config: TlsConfig,
+    /// Headers for WebSocket upgrade requests
+    pub websocket_config: WebSocketConfig,
+    /// Directory for persistent state such as cookies, `None` to keep it in memory
+    pub data_directory: Option<PathBuf>,
+    /// Which cookies third-party requests send and set
+    pub third_party_cookie_blocking: ThirdPartyCookiePolicy,
+    /// Cipher sealing disk cache entries, `None` to store them in plaintext
+    pub storage_cipher: Option<Arc<StorageCipher>>,
+    /// STUN and TURN servers for WebRTC ICE candidate gathering
+    pub ice_config: IceConfig,
 }
 
 impl Default for NetworkConfig {
@@ -48,10 +80,27 @@ impl Default for NetworkConfig {
             disk_cache_enabled: true,
             memory_cache_enabled: true,
             tls_config: TlsConfig::default(),
+            websocket_config: WebSocketConfig::default(),
+            data_directory: None,
+            third_party_cookie_blocking: ThirdPartyCookiePolicy::default(),
+            storage_cipher: None,
+            ice_config: IceConfig::default(),
         }
     }
 }
 
+/// Cookie policy for requests to a site other than the top-level site
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ThirdPartyCookiePolicy {
+    /// Send and set all cookies
+    #[default]
+    Allow,
+    /// Send and set no cookies
+    BlockAll,
+    /// Only send and set `Partitioned` cookies, keyed by the top-level site
+    AllowPartitioned,
+}
+
 /// TLS configuration
 #[derive(Debug, Clone)]
 pub struct TlsConfig {
@@ -63,6 +112,8 @@ pub struct TlsConfig {
     pub certificate_pinning: bool,
     /// Enable OCSP stapling
     pub ocsp_stapling: bool,
+    /// Enforce Certificate Transparency (SCT) requirements
+    pub certificate_transparency: bool,
     /// Custom CA certificates
     pub custom_ca_certs: Vec<Vec<u8>>,
 }
@@ -74,6 +125,7 @@ impl Default for TlsConfig {
             max_version: TlsVersion::Tls13,
             certificate_pinning: true,
             ocsp_stapling: true,
+            certificate_transparency: true,
             custom_ca_certs: Vec::new(),
         }
     }
@@ -88,6 +140,25 @@ pub enum TlsVersion {
     Tls13,
 }
 
+/// TLS errors surfaced by the TLS manager
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TlsError {
+    #[error("Insufficient certificate transparency: {valid} valid SCTs, {required} required")]
+    InsufficientCertificateTransparency { valid: usize, required: usize },
+
+    #[error("Invalid signed certificate timestamp: {0}")]
+    InvalidSct(String),
+
+    #[error("Invalid CT log key: {0}")]
+    InvalidLogKey(String),
+}
+
+impl From<TlsError> for Error {
+    fn from(err: TlsError) -> Self {
+        Error::SecurityError(err.to_string())
+    }
+}
+
 /// Network request state
 #[derive(Debug, Clone)]
 pub enum RequestState {
@@ -122,6 +193,12 @@ pub struct NetworkRequest {
     pub headers: HashMap<String, String>,
     /// Request body
     pub body: Option<Vec<u8>>,
+    /// URL of the document that made the request, `None` for requests
+    /// started by the browser
+    pub initiator: Option<String>,
+    /// URL of the top-level document of the frame that made the request,
+    /// `None` for top-level navigations and requests started by the browser
+    pub top_level_url: Option<String>,
     /// Request state
     pub state: RequestState,
     /// Request start time
@@ -131,11 +208,11 @@ pub struct NetworkRequest {
 }
 
 /// Network response information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkResponse {
     /// HTTP status code
     pub status_code: u16,
-    /// Response headers
+    /// Response headers, repeated headers such as `Set-Cookie` joined by newlines
     pub headers: HashMap<String, String>,
     /// Response body
     pub body: Vec<u8>,
@@ -147,6 +224,41 @@ pub struct NetworkResponse {
     pub response_time: std::time::Duration,
 }
 
+impl From<&NetworkRequest> for FetchRequest {
+    fn from(request: &NetworkRequest) -> Self {
+        Self {
+            url: request.url.clone(),
+            method: request.method.clone(),
+            headers: request.headers.clone(),
+        }
+    }
+}
+
+impl From<NetworkResponse> for CachedResponse {
+    fn from(response: NetworkResponse) -> Self {
+        Self {
+            status_code: response.status_code,
+            headers: response.headers,
+            body: response.body,
+            content_type: response.content_type,
+        }
+    }
+}
+
+impl From<CachedResponse> for NetworkResponse {
+    /// Responses served from a cache take no time
+    fn from(response: CachedResponse) -> Self {
+        Self {
+            status_code: response.status_code,
+            headers: response.headers,
+            content_length: response.body.len(),
+            body: response.body,
+            content_type: response.content_type,
+            response_time: std::time::Duration::ZERO,
+        }
+    }
+}
+
 /// Network process statistics
 #[derive(Debug, Default, Clone)]
 pub struct NetworkStats {
@@ -166,6 +278,16 @@ pub struct NetworkStats {
     pub avg_response_time: std::time::Duration,
     /// Active connections
     pub active_connections: usize,
+    /// Open WebSocket connections
+    pub active_websockets: Vec<WebSocketId>,
+}
+
+/// Handler answering requests before they reach the network, such as a
+/// service worker
+#[async_trait]
+pub trait FetchInterceptor: Send + Sync {
+    /// Synthesize a response for a request, `None` to send it to the network
+    async fn intercept_fetch(&self, request: &NetworkRequest) -> Option<NetworkResponse>;
 }
 
 /// Network process manager
@@ -184,6 +306,18 @@ pub struct NetworkProcessManager {
     stats: Arc<RwLock<NetworkStats>>,
     /// Next request ID
     next_request_id: u64,
+    /// Open WebSocket connections
+    websockets: HashMap<WebSocketId, WebSocketConnection>,
+    /// Channels to the renderers of tabs, for forwarding WebSocket frames
+    renderer_channels: HashMap<TabId, Channel<IpcMessage>>,
+    /// Next WebSocket ID
+    next_websocket_id: u64,
+    /// WebRTC ICE agents
+    ice_agents: HashMap<IceAgentId, IceAgent>,
+    /// Next ICE agent ID
+    next_ice_agent_id: u64,
+    /// Handler consulted before requests are sent
+    fetch_interceptor: Option<Arc<dyn FetchInterceptor>>,
 }
 
 impl NetworkProcessManager {
@@ -203,9 +337,20 @@ impl NetworkProcessManager {
             config,
             stats: Arc::new(RwLock::new(NetworkStats::default())),
             next_request_id: 1,
+            websockets: HashMap::new(),
+            renderer_channels: HashMap::new(),
+            next_websocket_id: 1,
+            ice_agents: HashMap::new(),
+            next_ice_agent_id: 1,
+            fetch_interceptor: None,
         })
     }
     
+    /// Set the handler consulted before requests are sent
+    pub fn set_fetch_interceptor(&mut self, interceptor: Arc<dyn FetchInterceptor>) {
+        self.fetch_interceptor = Some(interceptor);
+    }
+    
     /// Create a new network request
     pub async fn create_request(&mut self, tab_id: TabId, url: String, method: String) -> Result<String> {
         let request_id = format!("req_{}", self.next_request_id);
@@ -218,6 +363,8 @@ impl NetworkProcessManager {
             method: method.clone(),
             headers: HashMap::new(),
             body: None,
+            initiator: None,
+            top_level_url: None,
             state: RequestState::Preparing,
             start_time: std::time::Instant::now(),
             response: None,
@@ -240,10 +387,27 @@ impl NetworkProcessManager {
         
         info!("Executing network request {} for URL: {}", request_id, request.url);
         
+        // A service worker may answer without going to the network
+        if let Some(interceptor) = &self.fetch_interceptor {
+            if let Some(response) = interceptor.intercept_fetch(&request).await {
+                request.state = RequestState::Completed;
+                request.response = Some(response.clone());
+                
+                info!("Request {} answered by fetch interceptor", request_id);
+                return Ok(response);
+            }
+        }
+        
+        // Only GET responses are cacheable
+        let cacheable = request.method.eq_ignore_ascii_case("GET");
+        
         // Check cache first
-        let mut cache_manager = self.cache_manager.write().await;
-        if let Some(cached_response) = cache_manager.get(&request.url).await? {
-            drop(cache_manager);
+        let cached = if cacheable {
+            self.cache_manager.write().await.get(&request.url).await?
+        } else {
+            None
+        };
+        if let Some(cached_response) = cached {
             let mut stats = self.stats.write().await;
             stats.cache_hits += 1;
             drop(stats);
@@ -254,12 +418,12 @@ impl NetworkProcessManager {
             info!("Cache hit for request {}", request_id);
             return Ok(cached_response);
         }
-        drop(cache_manager);
         
         // Cache miss, make actual request
-        let mut stats = self.stats.write().await;
-        stats.cache_misses += 1;
-        drop(stats);
+        if cacheable {
+            let mut stats = self.stats.write().await;
+            stats.cache_misses += 1;
+        }
         
         // Execute HTTP request
         let http_client = self.http_client.read().await;
@@ -267,9 +431,10 @@ impl NetworkProcessManager {
         drop(http_client);
         
         // Cache the response
-        let mut cache_manager = self.cache_manager.write().await;
-        cache_manager.put(&request.url, &response).await?;
-        drop(cache_manager);
+        if cacheable {
+            let mut cache_manager = self.cache_manager.write().await;
+            cache_manager.put(&request.url, &response).await?;
+        }
         
         // Update request state
         request.state = RequestState::Completed;
@@ -286,6 +451,279 @@ impl NetworkProcessManager {
         Ok(response)
     }
     
+    /// Establish a TLS session with a server, enforcing the TLS policy
+    pub async fn establish_tls_session(&self, host: &str, leaf_cert: &[u8], sct_list: &[u8]) -> Result<TlsSession> {
+        let mut tls_manager = self.tls_manager.write().await;
+        match tls_manager.establish_session(host, leaf_cert, sct_list) {
+            Ok(session) => Ok(session),
+            Err(e) => {
+                drop(tls_manager);
+                let mut stats = self.stats.write().await;
+                stats.failed_requests += 1;
+                Err(e.into())
+            }
+        }
+    }
+    
+    /// POST a JSON report (e.g. a CSP violation report) to a reporting endpoint
+    pub async fn send_report(&mut self, tab_id: TabId, report_uri: &str, report: &serde_json::Value) -> Result<NetworkResponse> {
+        let request_id = self.create_request(tab_id, report_uri.to_string(), "POST".to_string()).await?;
+        
+        if let Some(request_arc) = self.requests.get(&request_id) {
+            let mut request = request_arc.write().await;
+            request.headers.insert("Content-Type".to_string(), "application/csp-report".to_string());
+            request.body = Some(serde_json::to_vec(report)?);
+        }
+        
+        debug!("Sending report to {}", report_uri);
+        self.execute_request(&request_id).await
+    }
+    
+    /// Answer network requests from a renderer over a channel until it closes
+    ///
+    /// Each `NetworkRequest` message is answered with a `NetworkResponse` or
+    /// `NetworkError` message carrying the same request ID. A `WebSocketOpen`
+    /// message is answered with `WebSocketOpened` or `NetworkError`, after
+    /// which `WebSocketFrame` and `WebSocketClosed` messages flow both ways.
+    pub fn serve(manager: Arc<RwLock<Self>>, channel: Channel<IpcMessage>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let message = match channel.recv_async().await {
+                    Ok(message) => message,
+                    Err(e) => {
+                        debug!("Network channel closed: {}", e);
+                        break;
+                    }
+                };
+                
+                let reply = match message {
+                    IpcMessage::NetworkRequest(request) => {
+                        let (request_id, tab_id) = (request.request_id, request.tab_id);
+                        match manager.write().await.execute_request_message(request).await {
+                            Ok(response) => IpcMessage::NetworkResponse(NetworkResponseMessage {
+                                request_id,
+                                tab_id,
+                                status_code: response.status_code,
+                                headers: response.headers,
+                                body: response.body,
+                                content_type: Some(response.content_type),
+                            }),
+                            Err(e) => IpcMessage::NetworkError(NetworkErrorMessage {
+                                request_id,
+                                tab_id,
+                                error: e.to_string(),
+                            }),
+                        }
+                    }
+                    IpcMessage::WebSocketOpen(open) => {
+                        let (request_id, tab_id) = (open.request_id, open.tab_id);
+                        let mut manager = manager.write().await;
+                        manager.register_renderer_channel(tab_id, channel.clone());
+                        // The connection sends the `WebSocketOpened` reply itself
+                        match manager.open_websocket_message(open).await {
+                            Ok(_) => continue,
+                            Err(e) => IpcMessage::NetworkError(NetworkErrorMessage {
+                                request_id,
+                                tab_id,
+                                error: e.to_string(),
+                            }),
+                        }
+                    }
+                    IpcMessage::WebSocketFrame(frame) => {
+                        let id = WebSocketId(frame.socket_id);
+                        if let Err(e) = manager.write().await.send_websocket_message(id, frame.message).await {
+                            warn!("Failed to send on WebSocket {}: {}", id, e);
+                        }
+                        continue;
+                    }
+                    IpcMessage::WebSocketClosed(closed) => {
+                        let id = WebSocketId(closed.socket_id);
+                        if let Err(e) = manager.write().await.close_websocket(id).await {
+                            warn!("Failed to close WebSocket {}: {}", id, e);
+                        }
+                        continue;
+                    }
+                    other => {
+                        warn!("Ignoring unexpected message on network channel: {:?}", other);
+                        continue;
+                    }
+                };
+                
+                if let Err(e) = channel.send_async(reply).await {
+                    warn!("Failed to reply on network channel: {}", e);
+                    break;
+                }
+            }
+        })
+    }
+    
+    /// Create and execute a request received over IPC
+    async fn execute_request_message(&mut self, message: NetworkRequestMessage) -> Result<NetworkResponse> {
+        let request_id = self.create_request(message.tab_id, message.url.to_string(), message.method).await?;
+        
+        if let Some(request_arc) = self.requests.get(&request_id) {
+            let mut request = request_arc.write().await;
+            request.headers = message.headers;
+            request.body = message.body;
+        }
+        
+        self.execute_request(&request_id).await
+    }
+    
+    /// Register the channel WebSocket frames for a tab are forwarded over
+    pub fn register_renderer_channel(&mut self, tab_id: TabId, channel: Channel<IpcMessage>) {
+        self.renderer_channels.insert(tab_id, channel);
+    }
+    
+    /// Open a WebSocket for a tab
+    ///
+    /// Received frames are forwarded over the tab's renderer channel, which
+    /// must have been registered with `register_renderer_channel`.
+    pub async fn open_websocket(&mut self, tab_id: TabId, url: &str) -> Result<WebSocketId> {
+        self.connect_websocket(tab_id, url, None).await
+    }
+    
+    /// Open a WebSocket requested over IPC
+    async fn open_websocket_message(&mut self, message: WebSocketOpenMessage) -> Result<WebSocketId> {
+        self.connect_websocket(message.tab_id, &message.url, Some(message.request_id)).await
+    }
+    
+    /// Perform the upgrade handshake and track the connection
+    async fn connect_websocket(&mut self, tab_id: TabId, url: &str, open_request_id: Option<u64>) -> Result<WebSocketId> {
+        let channel = self.renderer_channels.get(&tab_id)
+            .cloned()
+            .ok_or_else(|| Error::InvalidState(format!("No renderer channel registered for tab {}", tab_id)))?;
+        
+        let id = WebSocketId(self.next_websocket_id);
+        self.next_websocket_id += 1;
+        
+        let timeout = std::time::Duration::from_secs(self.config.connection_timeout);
+        let connection = match WebSocketConnection::connect(id, tab_id, url, &self.config.websocket_config, timeout, channel, open_request_id).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                let mut stats = self.stats.write().await;
+                stats.failed_requests += 1;
+                return Err(e);
+            }
+        };
+        
+        self.websockets.insert(id, connection);
+        Ok(id)
+    }
+    
+    /// Open a Server-Sent Events stream for a tab
+    ///
+    /// The stream reconnects by itself if the server sent a `retry:` hint.
+    pub async fn open_sse(&self, tab_id: TabId, url: &str, last_event_id: Option<String>) -> Result<SseReceiver> {
+        let timeout = std::time::Duration::from_secs(self.config.connection_timeout);
+        match SseConnection::open(tab_id, url, last_event_id, timeout).await {
+            Ok(receiver) => Ok(receiver),
+            Err(e) => {
+                let mut stats = self.stats.write().await;
+                stats.failed_requests += 1;
+                Err(e)
+            }
+        }
+    }
+    
+    /// Send a message on a WebSocket
+    pub async fn send_websocket_message(&mut self, id: WebSocketId, msg: WsMessage) -> Result<()> {
+        let connection = self.websockets.get(&id)
+            .ok_or_else(|| Error::NotFound(format!("WebSocket {} not found", id)))?;
+        
+        if connection.is_closed() {
+            self.websockets.remove(&id);
+            return Err(Error::InvalidState(format!("WebSocket {} is closed", id)));
+        }
+        
+        connection.send(msg).await
+    }
+    
+    /// Close a WebSocket
+    pub async fn close_websocket(&mut self, id: WebSocketId) -> Result<()> {
+        let connection = self.websockets.remove(&id)
+            .ok_or_else(|| Error::NotFound(format!("WebSocket {} not found", id)))?;
+        
+        info!("Closing WebSocket {}", id);
+        if connection.is_closed() {
+            return Ok(());
+        }
+        connection.close().await
+    }
+    
+    /// Create a WebRTC ICE agent for a tab
+    pub fn create_ice_agent(&mut self, tab_id: TabId) -> Result<IceAgentId> {
+        let id = IceAgentId(self.next_ice_agent_id);
+        self.next_ice_agent_id += 1;
+        
+        self.ice_agents.insert(id, IceAgent::new(tab_id));
+        debug!("Created ICE agent {} for tab {}", id, tab_id);
+        Ok(id)
+    }
+    
+    /// Get an ICE agent
+    pub fn ice_agent(&self, agent_id: IceAgentId) -> Option<&IceAgent> {
+        self.ice_agents.get(&agent_id)
+    }
+    
+    /// Gather the candidates of an ICE agent from the configured STUN and TURN servers
+    pub async fn gather_ice_candidates(&mut self, agent_id: IceAgentId) -> Result<Vec<IceCandidate>> {
+        let timeout = std::time::Duration::from_secs(self.config.connection_timeout);
+        let ice_config = self.config.ice_config.clone();
+        let agent = self.ice_agent_mut(agent_id)?;
+        
+        tokio::time::timeout(timeout, agent.gather_candidates(ice_config)).await
+            .map_err(|_| Error::Timeout(format!("Gathering ICE candidates for {} timed out", agent_id)))?
+    }
+    
+    /// Get the credentials an ICE agent sends to its peer
+    pub fn ice_credentials(&self, agent_id: IceAgentId) -> Result<IceCredentials> {
+        self.ice_agents.get(&agent_id)
+            .map(|agent| agent.credentials().clone())
+            .ok_or_else(|| Error::NotFound(format!("ICE agent {} not found", agent_id)))
+    }
+    
+    /// Set the credentials received from the peer of an ICE agent
+    pub fn set_remote_ice_credentials(&mut self, agent_id: IceAgentId, credentials: IceCredentials) -> Result<()> {
+        self.ice_agent_mut(agent_id)?.set_remote_credentials(credentials);
+        Ok(())
+    }
+    
+    /// Add a candidate received from the peer of an ICE agent
+    pub fn add_remote_candidate(&mut self, agent_id: IceAgentId, candidate: IceCandidate) -> Result<()> {
+        self.ice_agent_mut(agent_id)?.add_remote_candidate(candidate)
+    }
+    
+    /// Connect an ICE agent to its peer
+    ///
+    /// Candidates must have been gathered and the remote credentials set.
+    pub async fn connect(&mut self, agent_id: IceAgentId) -> Result<DataChannel> {
+        let timeout = std::time::Duration::from_secs(self.config.connection_timeout);
+        let agent = self.ice_agent_mut(agent_id)?;
+        
+        match tokio::time::timeout(timeout, agent.connect()).await {
+            Ok(result) => result,
+            Err(_) => {
+                agent.state = IceConnectionState::Failed;
+                Err(Error::Timeout(format!("ICE agent {} did not connect", agent_id)))
+            }
+        }
+    }
+    
+    /// Close an ICE agent
+    pub async fn close_ice_agent(&mut self, agent_id: IceAgentId) -> Result<()> {
+        let mut agent = self.ice_agents.remove(&agent_id)
+            .ok_or_else(|| Error::NotFound(format!("ICE agent {} not found", agent_id)))?;
+        
+        info!("Closing ICE agent {}", agent_id);
+        agent.close().await
+    }
+    
+    fn ice_agent_mut(&mut self, agent_id: IceAgentId) -> Result<&mut IceAgent> {
+        self.ice_agents.get_mut(&agent_id)
+            .ok_or_else(|| Error::NotFound(format!("ICE agent {} not found", agent_id)))
+    }
+    
     /// Get a network request by ID
     pub async fn get_request(&self, request_id: &str) -> Option<Arc<RwLock<NetworkRequest>>> {
         self.requests.get(request_id).cloned()
@@ -303,7 +741,13 @@ impl NetworkProcessManager {
     
     /// Get network statistics
     pub async fn get_stats(&self) -> NetworkStats {
-        self.stats.read().await.clone()
+        let mut stats = self.stats.read().await.clone();
+        stats.active_websockets = self.websockets.iter()
+            .filter(|(_, connection)| !connection.is_closed())
+            .map(|(id, _)| *id)
+            .collect();
+        stats.active_websockets.sort();
+        stats
     }
     
     /// Update network configuration
@@ -341,6 +785,21 @@ impl NetworkProcessManager {
         // Clear requests
         self.requests.clear();
         
+        // Close WebSockets
+        for id in self.websockets.keys().copied().collect::<Vec<_>>() {
+            if let Err(e) = self.close_websocket(id).await {
+                warn!("Failed to close WebSocket {}: {}", id, e);
+            }
+        }
+        self.renderer_channels.clear();
+        
+        // Close ICE agents
+        for id in self.ice_agents.keys().copied().collect::<Vec<_>>() {
+            if let Err(e) = self.close_ice_agent(id).await {
+                warn!("Failed to close ICE agent {}: {}", id, e);
+            }
+        }
+        
         // Shutdown managers
         let mut http_client = self.http_client.write().await;
         http_client.shutdown().await?;
@@ -365,6 +824,8 @@ pub struct HttpClientManager {
     connections: HashMap<String, ConnectionInfo>,
     /// Connection pool
     connection_pool: ConnectionPool,
+    /// Cookies sent with requests and set by responses
+    cookie_store: Arc<CookieStore>,
     /// Configuration
     config: NetworkConfig,
 }
@@ -374,16 +835,31 @@ impl HttpClientManager {
     pub async fn new(config: &NetworkConfig) -> Result<Self> {
         info!("Initializing HTTP client manager");
         
+        let cookie_store = match &config.data_directory {
+            Some(directory) => CookieStore::open(directory),
+            None => CookieStore::in_memory(),
+        }
+        .map_err(|e| Error::IoError(format!("Failed to open cookie store: {}", e)))?;
+        
         Ok(Self {
             connections: HashMap::new(),
             connection_pool: ConnectionPool::new(config).await?,
+            cookie_store: Arc::new(cookie_store),
             config: config.clone(),
         })
     }
     
+    /// Get the cookie store
+    pub fn cookie_store(&self) -> Arc<CookieStore> {
+        self.cookie_store.clone()
+    }
+    
     /// Execute an HTTP request
     pub async fn execute_request(&self, request: &NetworkRequest) -> Result<NetworkResponse> {
-        debug!("Executing HTTP request: {} {}", request.method, request.url);
+        let url = Url::parse(&request.url)
+            .map_err(|e| Error::NetworkError(format!("Invalid URL {}: {}", request.url, e)))?;
+        let headers = self.request_headers(request, &url);
+        debug!("Executing HTTP request: {} {} with {} headers", request.method, request.url, headers.len());
         
         // TODO: Implement actual HTTP request execution
         // This would involve:
@@ -404,9 +880,75 @@ impl HttpClientManager {
             response_time: std::time::Duration::from_millis(100),
         };
         
+        self.store_cookies(request, &url, &response);
+        
         Ok(response)
     }
     
+    /// Headers to send with a request, adding its cookies
+    pub fn request_headers(&self, request: &NetworkRequest, url: &Url) -> HashMap<String, String> {
+        let mut headers = request.headers.clone();
+        if headers.keys().any(|name| name.eq_ignore_ascii_case("cookie")) {
+            return headers;
+        }
+        
+        let site = RequestSite::for_request(url.as_str(), request.initiator.as_deref(), false);
+        let (partition_key, third_party) = cookie_partition(request, url);
+        let mut cookies = self.cookie_store.get_for_request_from(url.as_str(), is_secure(url), site, partition_key.as_deref());
+        if third_party {
+            match self.config.third_party_cookie_blocking {
+                ThirdPartyCookiePolicy::Allow => {}
+                ThirdPartyCookiePolicy::BlockAll => cookies.clear(),
+                ThirdPartyCookiePolicy::AllowPartitioned => cookies.retain(|cookie| cookie.partitioned),
+            }
+        }
+        if let Some(cookie_header) = cookie_store::cookie_header(&cookies) {
+            headers.insert("Cookie".to_string(), cookie_header);
+        }
+        
+        headers
+    }
+    
+    /// Store the cookies set by the response to a request
+    pub fn store_cookies(&self, request: &NetworkRequest, url: &Url, response: &NetworkResponse) {
+        let Some(host) = url.host_str() else {
+            return;
+        };
+        let (partition_key, third_party) = cookie_partition(request, url);
+        if third_party && self.config.third_party_cookie_blocking == ThirdPartyCookiePolicy::BlockAll {
+            return;
+        }
+        
+        let set_cookie_headers = response.headers.iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+            .flat_map(|(_, value)| value.lines());
+        for header in set_cookie_headers {
+            let Some(mut cookie) = SetCookie::parse(header) else {
+                continue;
+            };
+            // Insecure origins cannot set Secure cookies
+            if cookie.options.secure && !is_secure(url) {
+                continue;
+            }
+            if third_party
+                && self.config.third_party_cookie_blocking == ThirdPartyCookiePolicy::AllowPartitioned
+                && !cookie.options.partitioned
+            {
+                debug!("Blocking unpartitioned third-party cookie {} from {}", cookie.name, host);
+                continue;
+            }
+            let Some(domain) = cookie.domain_for(host) else {
+                warn!("Ignoring cookie {} for a domain {} is not in", cookie.name, host);
+                continue;
+            };
+            
+            cookie.options.path.get_or_insert_with(|| cookie_store::default_path(url.path()));
+            if let Err(e) = self.cookie_store.set(&domain, &cookie.name, &cookie.value, cookie.options, partition_key.as_deref()) {
+                warn!("Ignoring cookie {} from {}: {}", cookie.name, host, e);
+            }
+        }
+    }
+    
     /// Update HTTP client configuration
     pub async fn update_config(&mut self, config: &NetworkConfig) -> Result<()> {
         self.config = config.clone();
@@ -431,6 +973,10 @@ pub struct TlsManager {
     certificate_store: CertificateStore,
     /// Active TLS sessions
     sessions: HashMap<String, TlsSession>,
+    /// Certificate Transparency verifier
+    ct_verifier: CtVerifier,
+    /// Next session ID
+    next_session_id: u64,
 }
 
 impl TlsManager {
@@ -442,9 +988,48 @@ impl TlsManager {
             config: config.clone(),
             certificate_store: CertificateStore::new().await?,
             sessions: HashMap::new(),
+            ct_verifier: CtVerifier::new(),
+            next_session_id: 1,
         })
     }
     
+    /// Establish a TLS session, verifying the SCTs from the handshake extension
+    pub fn establish_session(&mut self, host: &str, leaf_cert: &[u8], sct_list: &[u8]) -> std::result::Result<TlsSession, TlsError> {
+        if self.config.certificate_transparency {
+            let scts = if sct_list.is_empty() {
+                Vec::new()
+            } else {
+                SignedCertificateTimestamp::parse_list(sct_list)?
+            };
+            
+            let result = self.ct_verifier.verify_scts(leaf_cert, &scts);
+            if !result.is_compliant() {
+                warn!("Rejecting TLS session for {}: {} of {} required SCTs valid",
+                      host, result.valid_sct_count, result.required_sct_count);
+                return Err(TlsError::InsufficientCertificateTransparency {
+                    valid: result.valid_sct_count,
+                    required: result.required_sct_count,
+                });
+            }
+        }
+        
+        let session = TlsSession {
+            session_id: format!("tls_{}", self.next_session_id),
+            host: host.to_string(),
+            protocol_version: self.config.max_version.clone(),
+        };
+        self.next_session_id += 1;
+        self.sessions.insert(host.to_string(), session.clone());
+        
+        info!("Established TLS session {} for {}", session.session_id, host);
+        Ok(session)
+    }
+    
+    /// Replace the Certificate Transparency verifier
+    pub fn set_ct_verifier(&mut self, verifier: CtVerifier) {
+        self.ct_verifier = verifier;
+    }
+    
     /// Update TLS configuration
     pub async fn update_config(&mut self, config: &TlsConfig) -> Result<()> {
         self.config = config.clone();
@@ -476,7 +1061,9 @@ impl CacheManager {
         
         let memory_cache = MemoryCache::new(config.max_cache_size_mb).await?;
         let disk_cache = if config.disk_cache_enabled {
-            Some(DiskCache::new(config.max_cache_size_mb).await?)
+            let mut disk_cache = DiskCache::new(config.max_cache_size_mb).await?;
+            disk_cache.set_cipher(config.storage_cipher.clone());
+            Some(disk_cache)
         } else {
             None
         };
@@ -528,10 +1115,15 @@ impl CacheManager {
         self.memory_cache.update_size(config.max_cache_size_mb).await?;
         
         // Update disk cache if needed
-        if config.disk_cache_enabled && self.disk_cache.is_none() {
-            self.disk_cache = Some(DiskCache::new(config.max_cache_size_mb).await?);
-        } else if !config.disk_cache_enabled {
+        if !config.disk_cache_enabled {
             self.disk_cache = None;
+        } else if let Some(ref mut disk_cache) = self.disk_cache {
+            disk_cache.update_size(config.max_cache_size_mb).await?;
+            disk_cache.set_cipher(config.storage_cipher.clone());
+        } else {
+            let mut disk_cache = DiskCache::new(config.max_cache_size_mb).await?;
+            disk_cache.set_cipher(config.storage_cipher.clone());
+            self.disk_cache = Some(disk_cache);
         }
         
         Ok(())
@@ -627,44 +1219,26 @@ impl MemoryCache {
     }
 }
 
-pub struct DiskCache {
-    cache_dir: std::path::PathBuf,
-    max_size: usize,
-}
-
-impl DiskCache {
-    pub async fn new(max_size_mb: usize) -> Result<Self> {
-        let cache_dir = std::env::temp_dir().join("matte-browser-cache");
-        std::fs::create_dir_all(&cache_dir)?;
-        
-        Ok(Self {
-            cache_dir,
-            max_size: max_size_mb * 1024 * 1024,
-        })
-    }
-    
-    pub async fn get(&self, url: &str) -> Result<Option<NetworkResponse>> {
-        // TODO: Implement disk cache retrieval
-        Ok(None)
-    }
-    
-    pub async fn put(&self, url: &str, response: &NetworkResponse) -> Result<()> {
-        // TODO: Implement disk cache storage
-        Ok(())
-    }
-    
-    pub async fn shutdown(&mut self) -> Result<()> {
-        // TODO: Implement disk cache cleanup
-        Ok(())
-    }
-}
-
 /// Initialize the network process
 pub async fn init(config: NetworkConfig) -> Result<NetworkProcessManager> {
     info!("Initializing network process");
     NetworkProcessManager::new(config).await
 }
 
+/// Check if a URL is loaded over a secure connection
+fn is_secure(url: &Url) -> bool {
+    matches!(url.scheme(), "https" | "wss")
+}
+
+/// Partition key of the top-level site a request is made under, and whether
+/// the request goes to a different site
+fn cookie_partition(request: &NetworkRequest, url: &Url) -> (Option<String>, bool) {
+    let partition_key = request.top_level_url.as_deref().and_then(cookie_store::partition_key);
+    let third_party = partition_key.as_ref()
+        .is_some_and(|partition_key| cookie_store::partition_key(url.as_str()).as_ref() != Some(partition_key));
+    (partition_key, third_party)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -704,6 +1278,96 @@ mod tests {
         assert_eq!(response.status_code, 200);
     }
 
+    #[tokio::test]
+    async fn test_request_cookies() {
+        let client = HttpClientManager::new(&NetworkConfig::default()).await.unwrap();
+        let url = Url::parse("https://shop.example/cart/items").unwrap();
+        
+        let response = NetworkResponse {
+            status_code: 200,
+            headers: HashMap::from([(
+                "Set-Cookie".to_string(),
+                "session=abc; SameSite=Strict\ntheme=dark; Path=/; SameSite=None; Secure\nid=1; Domain=other.example".to_string(),
+            )]),
+            body: Vec::new(),
+            content_type: "text/html".to_string(),
+            content_length: 0,
+            response_time: std::time::Duration::from_millis(0),
+        };
+        let mut request = NetworkRequest {
+            request_id: "req_1".to_string(),
+            tab_id: TabId::new(1),
+            url: url.to_string(),
+            method: "GET".to_string(),
+            headers: HashMap::new(),
+            body: None,
+            initiator: None,
+            top_level_url: None,
+            state: RequestState::Preparing,
+            start_time: std::time::Instant::now(),
+            response: None,
+        };
+        client.store_cookies(&request, &url, &response);
+        
+        let headers = client.request_headers(&request, &url);
+        assert_eq!(headers.get("Cookie").map(String::as_str), Some("session=abc; theme=dark"));
+        
+        // SameSite=Strict cookies are left out of cross-site requests
+        request.initiator = Some("https://evil.example/".to_string());
+        let headers = client.request_headers(&request, &url);
+        assert_eq!(headers.get("Cookie").map(String::as_str), Some("theme=dark"));
+    }
+
+    #[tokio::test]
+    async fn test_partitioned_cookies_isolated_per_top_level_site() {
+        let config = NetworkConfig {
+            third_party_cookie_blocking: ThirdPartyCookiePolicy::AllowPartitioned,
+            ..Default::default()
+        };
+        let client = HttpClientManager::new(&config).await.unwrap();
+        let url = Url::parse("https://widget.example/embed").unwrap();
+        
+        let response = NetworkResponse {
+            status_code: 200,
+            headers: HashMap::from([(
+                "Set-Cookie".to_string(),
+                "chat=1; Path=/; SameSite=None; Secure; Partitioned\ntracker=1; Path=/; SameSite=None; Secure".to_string(),
+            )]),
+            body: Vec::new(),
+            content_type: "text/html".to_string(),
+            content_length: 0,
+            response_time: std::time::Duration::from_millis(0),
+        };
+        // The widget is embedded in an iframe on news.example
+        let mut request = NetworkRequest {
+            request_id: "req_1".to_string(),
+            tab_id: TabId::new(1),
+            url: url.to_string(),
+            method: "GET".to_string(),
+            headers: HashMap::new(),
+            body: None,
+            initiator: Some("https://news.example/".to_string()),
+            top_level_url: Some("https://news.example/article".to_string()),
+            state: RequestState::Preparing,
+            start_time: std::time::Instant::now(),
+            response: None,
+        };
+        client.store_cookies(&request, &url, &response);
+        
+        let headers = client.request_headers(&request, &url);
+        assert_eq!(headers.get("Cookie").map(String::as_str), Some("chat=1"));
+        
+        // The same iframe on another top-level site gets a fresh partition
+        request.initiator = Some("https://shop.example/".to_string());
+        request.top_level_url = Some("https://shop.example/".to_string());
+        assert_eq!(client.request_headers(&request, &url).get("Cookie"), None);
+        
+        // Visiting the widget itself does not see the partitioned cookie either
+        request.initiator = None;
+        request.top_level_url = None;
+        assert_eq!(client.request_headers(&request, &url).get("Cookie"), None);
+    }
+
     #[tokio::test]
     async fn test_cache_management() {
         let config = NetworkConfig::default();
@@ -755,4 +1419,532 @@ mod tests {
         assert_eq!(stats.successful_requests, 0);
         assert_eq!(stats.failed_requests, 0);
     }
+
+    fn test_ct_log(seed: u8) -> (p256::ecdsa::SigningKey, CtLog) {
+        use p256::pkcs8::EncodePublicKey;
+        
+        let signing_key = p256::ecdsa::SigningKey::from_bytes(&[seed; 32].into()).unwrap();
+        let der = signing_key.verifying_key().to_public_key_der().unwrap();
+        let log = CtLog::from_der(&format!("test log {}", seed), der.as_bytes()).unwrap();
+        (signing_key, log)
+    }
+
+    fn sign_sct(signing_key: &p256::ecdsa::SigningKey, log: &CtLog, leaf_cert: &[u8]) -> SignedCertificateTimestamp {
+        use p256::ecdsa::signature::Signer;
+        
+        let mut sct = SignedCertificateTimestamp {
+            version: 0,
+            log_id: log.log_id,
+            timestamp: 1_700_000_000_000,
+            extensions: Vec::new(),
+            hash_algorithm: 4,
+            signature_algorithm: 3,
+            signature: Vec::new(),
+        };
+        let signature: p256::ecdsa::Signature = signing_key.sign(&sct.signed_data(leaf_cert));
+        sct.signature = signature.to_der().as_bytes().to_vec();
+        sct
+    }
+
+    fn encode_sct_list(scts: &[SignedCertificateTimestamp]) -> Vec<u8> {
+        let mut list = Vec::new();
+        for sct in scts {
+            let bytes = sct.to_bytes();
+            list.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+            list.extend_from_slice(&bytes);
+        }
+        let mut data = (list.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(&list);
+        data
+    }
+
+    #[test]
+    fn test_builtin_ct_logs() {
+        let verifier = CtVerifier::new();
+        assert_eq!(verifier.logs().len(), 2);
+    }
+
+    #[test]
+    fn test_ct_verification() {
+        let leaf_cert = b"leaf certificate";
+        let (key_a, log_a) = test_ct_log(1);
+        let (key_b, log_b) = test_ct_log(2);
+        let sct_a = sign_sct(&key_a, &log_a, leaf_cert);
+        let sct_b = sign_sct(&key_b, &log_b, leaf_cert);
+        let verifier = CtVerifier::with_logs(vec![log_a, log_b]);
+        
+        let result = verifier.verify_scts(leaf_cert, &[sct_a.clone(), sct_b.clone()]);
+        assert_eq!(result, CtVerificationResult { valid_sct_count: 2, required_sct_count: 2 });
+        assert!(result.is_compliant());
+        
+        // Duplicate SCTs from one log only count once
+        let result = verifier.verify_scts(leaf_cert, &[sct_a.clone(), sct_a]);
+        assert_eq!(result.valid_sct_count, 1);
+        
+        // SCTs for a different certificate are rejected
+        let result = verifier.verify_scts(b"other certificate", &[sct_b]);
+        assert_eq!(result.valid_sct_count, 0);
+    }
+
+    #[test]
+    fn test_sct_list_roundtrip() {
+        let (key, log) = test_ct_log(3);
+        let sct = sign_sct(&key, &log, b"leaf");
+        
+        let parsed = SignedCertificateTimestamp::parse_list(&encode_sct_list(&[sct.clone()])).unwrap();
+        assert_eq!(parsed, vec![sct]);
+        
+        assert!(SignedCertificateTimestamp::parse_list(&[0, 10, 1, 2]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_insufficient_certificate_transparency() {
+        let config = NetworkConfig::default();
+        let manager = NetworkProcessManager::new(config).await.unwrap();
+        
+        let leaf_cert = b"leaf certificate";
+        let (key_a, log_a) = test_ct_log(4);
+        let (key_b, log_b) = test_ct_log(5);
+        let sct_a = sign_sct(&key_a, &log_a, leaf_cert);
+        let sct_b = sign_sct(&key_b, &log_b, leaf_cert);
+        manager.tls_manager.write().await.set_ct_verifier(CtVerifier::with_logs(vec![log_a, log_b]));
+        
+        let mut tls_manager = manager.tls_manager.write().await;
+        let result = tls_manager.establish_session("example.com", leaf_cert, &encode_sct_list(&[sct_a.clone()]));
+        assert_eq!(result.unwrap_err(), TlsError::InsufficientCertificateTransparency { valid: 1, required: 2 });
+        drop(tls_manager);
+        
+        let result = manager.establish_tls_session("example.com", leaf_cert, &[]).await;
+        assert!(matches!(result, Err(Error::SecurityError(_))));
+        assert_eq!(manager.get_stats().await.failed_requests, 1);
+        
+        let session = manager.establish_tls_session("example.com", leaf_cert, &encode_sct_list(&[sct_a, sct_b])).await;
+        assert_eq!(session.unwrap().host, "example.com");
+    }
+
+    fn test_response(body: &[u8], cache_control: &str) -> NetworkResponse {
+        let mut headers = HashMap::new();
+        headers.insert("Cache-Control".to_string(), cache_control.to_string());
+        headers.insert("ETag".to_string(), "\"v1\"".to_string());
+        NetworkResponse {
+            status_code: 200,
+            headers,
+            body: body.to_vec(),
+            content_type: "text/plain".to_string(),
+            content_length: body.len(),
+            response_time: std::time::Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        
+        let mut cache = DiskCache::with_dir(dir.path(), 10).await.unwrap();
+        cache.put("https://example.com/a", &test_response(b"persisted", "max-age=600")).await.unwrap();
+        cache.shutdown().await.unwrap();
+        drop(cache);
+        
+        let cache = DiskCache::with_dir(dir.path(), 10).await.unwrap();
+        assert!(dir.path().join("responses.sled").exists());
+        let entry = cache.get_entry("https://example.com/a").await.unwrap().unwrap();
+        assert_eq!(entry.response.body, b"persisted");
+        assert_eq!(entry.etag.as_deref(), Some("\"v1\""));
+        assert!(cache.get("https://example.com/b").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_encrypted_at_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let cipher = Arc::new(StorageCipher::new([42u8; 32]));
+        
+        let mut cache = DiskCache::with_dir(dir.path(), 10).await.unwrap();
+        cache.set_cipher(Some(cipher.clone()));
+        cache.put("https://example.com/a", &test_response(b"top secret body", "max-age=600")).await.unwrap();
+        cache.shutdown().await.unwrap();
+        drop(cache);
+        
+        let db = sled::open(dir.path().join("responses.sled")).unwrap();
+        let (_, raw) = db.iter().next().unwrap().unwrap();
+        assert!(!raw.windows(b"top secret body".len()).any(|window| window == b"top secret body"));
+        let entry: CachedEntry = bincode::deserialize(&cipher.decrypt("https://example.com", &raw).unwrap()).unwrap();
+        assert_eq!(entry.response.body, b"top secret body");
+        assert!(cipher.decrypt("https://other.example", &raw).is_err());
+        drop(db);
+        
+        let mut cache = DiskCache::with_dir(dir.path(), 10).await.unwrap();
+        cache.set_cipher(Some(cipher));
+        let response = cache.get("https://example.com/a").await.unwrap().unwrap();
+        assert_eq!(response.body, b"top secret body");
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_expiry() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::with_dir(dir.path(), 10).await.unwrap();
+        
+        let mut entry = CachedEntry::from_response(&test_response(b"stale", "max-age=600")).unwrap();
+        entry.expires_at = std::time::SystemTime::now() - std::time::Duration::from_secs(1);
+        cache.put_entry("https://example.com/stale", &entry).await.unwrap();
+        assert_eq!(cache.len(), 1);
+        
+        assert!(cache.get("https://example.com/stale").await.unwrap().is_none());
+        assert!(cache.is_empty());
+        
+        // no-store responses are never written
+        cache.put("https://example.com/private", &test_response(b"secret", "no-store")).await.unwrap();
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_lru_eviction() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::with_dir(dir.path(), 1).await.unwrap();
+        
+        let body = vec![0u8; 256 * 1024];
+        for i in 0..8 {
+            let url = format!("https://example.com/{}", i);
+            cache.put(&url, &test_response(&body, "max-age=600")).await.unwrap();
+            // Keep the first entry hot
+            cache.get("https://example.com/0").await.unwrap();
+        }
+        
+        assert!(cache.len() < 8);
+        assert!(cache.get("https://example.com/0").await.unwrap().is_some());
+        assert!(cache.get("https://example.com/7").await.unwrap().is_some());
+        assert!(cache.get("https://example.com/1").await.unwrap().is_none());
+        
+        // The tracked size shrinks with evictions, so a small entry fits without evicting
+        assert!(cache.size() <= 1024 * 1024);
+        let len = cache.len();
+        cache.put("https://example.com/small", &test_response(b"small", "max-age=600")).await.unwrap();
+        assert_eq!(cache.len(), len + 1);
+        
+        let size = cache.size();
+        cache.remove("https://example.com/small").await.unwrap();
+        assert!(cache.size() < size);
+        cache.clear().await.unwrap();
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_send_report() {
+        let config = NetworkConfig {
+            disk_cache_enabled: false,
+            ..Default::default()
+        };
+        let mut manager = NetworkProcessManager::new(config).await.unwrap();
+        
+        let report = serde_json::json!({ "csp-report": { "blocked-uri": "inline" } });
+        let response = manager.send_report(TabId::new(1), "https://example.com/csp-reports", &report).await.unwrap();
+        assert_eq!(response.status_code, 200);
+        
+        // Reports are never served from or stored in the cache
+        let stats = manager.get_stats().await;
+        assert_eq!(stats.cache_hits, 0);
+        assert_eq!(stats.cache_misses, 0);
+    }
+
+    #[tokio::test]
+    async fn test_serve_network_requests() {
+        let manager = Arc::new(RwLock::new(NetworkProcessManager::new(NetworkConfig::default()).await.unwrap()));
+        let (renderer, network) = common::ipc::ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let server = NetworkProcessManager::serve(manager.clone(), network);
+        
+        renderer.send_async(IpcMessage::NetworkRequest(NetworkRequestMessage {
+            request_id: 7,
+            tab_id: TabId::new(3),
+            url: common::types::Url::new("https".to_string(), "example.com".to_string()),
+            method: "GET".to_string(),
+            headers: HashMap::from([("Accept".to_string(), "text/html".to_string())]),
+            body: None,
+        })).await.unwrap();
+        
+        match renderer.recv_async().await.unwrap() {
+            IpcMessage::NetworkResponse(response) => {
+                assert_eq!(response.request_id, 7);
+                assert_eq!(response.tab_id, TabId::new(3));
+                assert_eq!(response.status_code, 200);
+                assert_eq!(response.content_type.as_deref(), Some("text/html"));
+            }
+            other => panic!("unexpected reply: {:?}", other),
+        }
+        
+        drop(renderer);
+        server.await.unwrap();
+        // The response may come from the disk cache of an earlier run
+        let stats = manager.read().await.get_stats().await;
+        assert_eq!(stats.successful_requests + stats.cache_hits, 1);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::result_large_err)] // the handshake callback's error type is fixed by tungstenite
+    async fn test_websocket_over_ipc() {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+        use tokio_tungstenite::tungstenite::Message;
+        
+        // Echo server recording the upgrade request headers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (headers_tx, headers_rx) = tokio::sync::oneshot::channel();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_hdr_async(stream, |request: &Request, mut response: Response| {
+                let header = |name: &str| request.headers().get(name).map(|value| value.to_str().unwrap().to_string());
+                headers_tx.send((header("Cookie"), header("Sec-WebSocket-Protocol"))).unwrap();
+                response.headers_mut().insert("Sec-WebSocket-Protocol", "chat".parse().unwrap());
+                Ok(response)
+            }).await.unwrap();
+            
+            socket.send(Message::Text("hello".to_string())).await.unwrap();
+            while let Some(Ok(message)) = socket.next().await {
+                if message.is_binary() {
+                    socket.send(message).await.unwrap();
+                }
+            }
+        });
+        
+        let mut config = NetworkConfig::default();
+        config.websocket_config.protocols = vec!["chat".to_string(), "superchat".to_string()];
+        config.websocket_config.cookies.insert("127.0.0.1".to_string(), "session=abc".to_string());
+        let manager = Arc::new(RwLock::new(NetworkProcessManager::new(config).await.unwrap()));
+        let (renderer, network) = common::ipc::ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let network_server = NetworkProcessManager::serve(manager.clone(), network);
+        
+        renderer.send_async(IpcMessage::WebSocketOpen(WebSocketOpenMessage {
+            request_id: 9,
+            tab_id: TabId::new(3),
+            url: format!("ws://127.0.0.1:{}/feed", port),
+        })).await.unwrap();
+        
+        let socket_id = match renderer.recv_async().await.unwrap() {
+            IpcMessage::WebSocketOpened(opened) => {
+                assert_eq!(opened.request_id, 9);
+                assert_eq!(opened.protocol.as_deref(), Some("chat"));
+                opened.socket_id
+            }
+            other => panic!("unexpected reply: {:?}", other),
+        };
+        let (cookie, protocols) = headers_rx.await.unwrap();
+        assert_eq!(cookie.as_deref(), Some("session=abc"));
+        assert_eq!(protocols.as_deref(), Some("chat, superchat"));
+        assert_eq!(manager.read().await.get_stats().await.active_websockets, vec![WebSocketId(socket_id)]);
+        
+        // Frames from the server are forwarded to the renderer
+        match renderer.recv_async().await.unwrap() {
+            IpcMessage::WebSocketFrame(frame) => {
+                assert_eq!(frame.socket_id, socket_id);
+                assert_eq!(frame.tab_id, TabId::new(3));
+                assert_eq!(frame.message, WsMessage::Text("hello".to_string()));
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+        
+        renderer.send_async(IpcMessage::WebSocketFrame(common::ipc::WebSocketFrameMessage {
+            socket_id,
+            tab_id: TabId::new(3),
+            message: WsMessage::Binary(vec![1, 2, 3]),
+        })).await.unwrap();
+        match renderer.recv_async().await.unwrap() {
+            IpcMessage::WebSocketFrame(frame) => assert_eq!(frame.message, WsMessage::Binary(vec![1, 2, 3])),
+            other => panic!("unexpected message: {:?}", other),
+        }
+        
+        renderer.send_async(IpcMessage::WebSocketClosed(common::ipc::WebSocketClosedMessage {
+            socket_id,
+            tab_id: TabId::new(3),
+            code: None,
+            reason: String::new(),
+        })).await.unwrap();
+        match renderer.recv_async().await.unwrap() {
+            IpcMessage::WebSocketClosed(closed) => assert_eq!(closed.socket_id, socket_id),
+            other => panic!("unexpected message: {:?}", other),
+        }
+        assert!(manager.read().await.get_stats().await.active_websockets.is_empty());
+        
+        server.await.unwrap();
+        drop(renderer);
+        network_server.await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_open_websocket_requires_renderer_channel() {
+        let mut manager = NetworkProcessManager::new(NetworkConfig::default()).await.unwrap();
+        let result = manager.open_websocket(TabId::new(1), "ws://127.0.0.1:9/").await;
+        assert!(matches!(result, Err(Error::InvalidState(_))));
+        
+        let result = manager.send_websocket_message(WebSocketId(1), WsMessage::Text("hi".to_string())).await;
+        assert!(matches!(result, Err(Error::NotFound(_))));
+    }
+
+    #[test]
+    fn test_sse_parser() {
+        let stream = "\u{FEFF}: comment\r\ndata: first\r\ndata:second line\r\n\r\n\
+                      event: update\rid: 42\rdata: {\"n\":1}\r\r\
+                      retry: 1500\nid\ndata\n\nevent: empty\n\ndata: unterminated";
+        
+        // Feeding byte by byte must give the same events as feeding at once
+        let mut parser = SseParser::new(Some("7".to_string()));
+        let events = parser.feed(stream.as_bytes());
+        let mut split_parser = SseParser::new(Some("7".to_string()));
+        let split_events: Vec<SseEvent> = stream.as_bytes().chunks(1).flat_map(|b| split_parser.feed(b)).collect();
+        assert_eq!(events, split_events);
+        
+        assert_eq!(events, vec![
+            SseEvent { event_type: "message".to_string(), data: "first\nsecond line".to_string(), last_event_id: Some("7".to_string()) },
+            SseEvent { event_type: "update".to_string(), data: "{\"n\":1}".to_string(), last_event_id: Some("42".to_string()) },
+            // An empty `id` resets the last event ID, an empty `data` gives an empty line
+            SseEvent { event_type: "message".to_string(), data: String::new(), last_event_id: None },
+        ]);
+        assert_eq!(parser.retry(), Some(std::time::Duration::from_millis(1500)));
+    }
+    
+    #[test]
+    fn test_chunked_decoding() {
+        let body = b"5\r\nhello\r\nb;ext=1\r\n, chunked!\n\r\n0\r\nX-Trailer: yes\r\n\r\n";
+        for split in [1, 3, body.len()] {
+            let mut decoder = sse::ChunkedDecoder::new();
+            let decoded: Vec<u8> = body.chunks(split).flat_map(|chunk| decoder.decode(chunk).unwrap()).collect();
+            assert_eq!(decoded, b"hello, chunked!\n");
+            assert!(decoder.is_done());
+        }
+        
+        assert!(sse::ChunkedDecoder::new().decode(b"zz\r\n").is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_sse_chunked_stream_reconnects() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            let bodies: [&[&str]; 2] = [
+                &["retry: 10\nid: 1\nevent: tick\nda", "ta: one\n\n", "id: 2\ndata: two\n\n"],
+                &["data: three\n\n"],
+            ];
+            for chunks in bodies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = vec![0; 1024];
+                let read = socket.read(&mut request).await.unwrap();
+                requests.push(String::from_utf8_lossy(&request[..read]).to_string());
+                
+                socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\n\r\n").await.unwrap();
+                for chunk in chunks {
+                    socket.write_all(format!("{:x}\r\n{}\r\n", chunk.len(), chunk).as_bytes()).await.unwrap();
+                    socket.flush().await.unwrap();
+                }
+                socket.write_all(b"0\r\n\r\n").await.unwrap();
+            }
+            requests
+        });
+        
+        let manager = NetworkProcessManager::new(NetworkConfig::default()).await.unwrap();
+        let url = format!("http://127.0.0.1:{}/events?topic=a", port);
+        let mut events = manager.open_sse(TabId::new(1), &url, None).await.unwrap();
+        
+        let event = events.recv().await.unwrap();
+        assert_eq!((event.event_type.as_str(), event.data.as_str(), event.last_event_id.as_deref()), ("tick", "one", Some("1")));
+        let event = events.recv().await.unwrap();
+        assert_eq!((event.event_type.as_str(), event.data.as_str(), event.last_event_id.as_deref()), ("message", "two", Some("2")));
+        
+        // The stream reconnects after the body ends, resuming from the last ID
+        let event = events.recv().await.unwrap();
+        assert_eq!((event.data.as_str(), event.last_event_id.as_deref()), ("three", Some("2")));
+        
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /events?topic=a HTTP/1.1\r\n"));
+        assert!(requests[0].contains("Accept: text/event-stream\r\n"));
+        assert!(!requests[0].contains("Last-Event-ID"));
+        assert!(requests[1].contains("Last-Event-ID: 2\r\n"));
+        
+        // Dropping the receiver stops the reconnection attempts
+        drop(events);
+        
+        let result = manager.open_sse(TabId::new(1), "https://example.com/events", None).await;
+        assert!(matches!(result, Err(Error::NotImplemented(_))));
+    }
+    
+    /// Rejects TURN allocations, leaving only STUN binding requests answered
+    struct StunOnly;
+    
+    impl turn::auth::AuthHandler for StunOnly {
+        fn auth_handle(&self, _username: &str, _realm: &str, _src_addr: std::net::SocketAddr) -> std::result::Result<Vec<u8>, turn::Error> {
+            Err(turn::Error::ErrNoSuchUser)
+        }
+    }
+    
+    /// Gather candidates for a new ICE agent
+    async fn gathered_ice_agent(manager: &mut NetworkProcessManager, tab_id: TabId) -> IceAgentId {
+        let id = manager.create_ice_agent(tab_id).unwrap();
+        let candidates = manager.gather_ice_candidates(id).await.unwrap();
+        
+        // The server reflexive address is the loopback address the STUN server saw
+        assert!(candidates.iter().any(|candidate| {
+            candidate.candidate_type == IceCandidateType::ServerReflexive && candidate.address == "127.0.0.1"
+        }));
+        assert_eq!(manager.ice_agent(id).unwrap().local_candidates, candidates);
+        id
+    }
+    
+    #[tokio::test]
+    async fn test_ice_agents_connect_over_loopback_stun() {
+        let socket = Arc::new(tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let stun_port = socket.local_addr().unwrap().port();
+        let stun_server = turn::server::Server::new(turn::server::config::ServerConfig {
+            conn_configs: vec![turn::server::config::ConnConfig {
+                conn: socket,
+                relay_addr_generator: Box::new(turn::relay::relay_static::RelayAddressGeneratorStatic {
+                    relay_address: "127.0.0.1".parse().unwrap(),
+                    address: "127.0.0.1".to_string(),
+                    net: Arc::new(webrtc_util::vnet::net::Net::new(None)),
+                }),
+            }],
+            realm: "matte.test".to_string(),
+            auth_handler: Arc::new(StunOnly),
+            channel_bind_timeout: std::time::Duration::from_secs(0),
+            alloc_close_notify: None,
+        }).await.unwrap();
+        
+        // Two browsers, each with the STUN server configured
+        let config = NetworkConfig {
+            disk_cache_enabled: false,
+            ice_config: IceConfig {
+                stun_servers: vec![format!("stun:127.0.0.1:{}", stun_port)],
+                turn_servers: Vec::new(),
+            },
+            ..Default::default()
+        };
+        let mut alice = NetworkProcessManager::new(config.clone()).await.unwrap();
+        let mut bob = NetworkProcessManager::new(config).await.unwrap();
+        let alice_agent = gathered_ice_agent(&mut alice, TabId::new(1)).await;
+        let bob_agent = gathered_ice_agent(&mut bob, TabId::new(2)).await;
+        assert_eq!(alice.ice_agent(alice_agent).unwrap().state, IceConnectionState::New);
+        
+        // Signalling: exchange credentials and candidates
+        alice.set_remote_ice_credentials(alice_agent, bob.ice_credentials(bob_agent).unwrap()).unwrap();
+        bob.set_remote_ice_credentials(bob_agent, alice.ice_credentials(alice_agent).unwrap()).unwrap();
+        for candidate in bob.ice_agent(bob_agent).unwrap().local_candidates.clone() {
+            alice.add_remote_candidate(alice_agent, IceCandidate::parse(&candidate.candidate).unwrap()).unwrap();
+        }
+        for candidate in alice.ice_agent(alice_agent).unwrap().local_candidates.clone() {
+            bob.add_remote_candidate(bob_agent, candidate).unwrap();
+        }
+        
+        let (alice_channel, bob_channel) = tokio::join!(alice.connect(alice_agent), bob.connect(bob_agent));
+        let (alice_channel, bob_channel) = (alice_channel.unwrap(), bob_channel.unwrap());
+        assert_eq!(alice.ice_agent(alice_agent).unwrap().state, IceConnectionState::Connected);
+        assert_eq!(bob.ice_agent(bob_agent).unwrap().state, IceConnectionState::Connected);
+        
+        alice_channel.send(b"hello").await.unwrap();
+        assert_eq!(bob_channel.recv().await.unwrap(), b"hello");
+        
+        alice.close_ice_agent(alice_agent).await.unwrap();
+        assert!(alice.ice_agent(alice_agent).is_none());
+        assert!(matches!(alice.connect(alice_agent).await, Err(Error::NotFound(_))));
+        bob.shutdown().await.unwrap();
+        stun_server.close().await.unwrap();
+    }
 }
@@ -0,0 +1,454 @@
+//! Server-Sent Events for the network process
+//!
+//! An `SseConnection` issues a GET request with `Accept: text/event-stream`
+//! and parses the streamed body into events as it arrives. Events are
+//! delivered over an mpsc channel. If the server sent a `retry:` hint, the
+//! connection is re-established after that delay when it drops, sending the
+//! last event ID seen in a `Last-Event-ID` header.
+
+use std::time::Duration;
+
+use common::error::{Error, Result};
+use common::types::TabId;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Number of events buffered for a slow receiver
+const EVENT_BUFFER: usize = 64;
+
+/// Maximum size of a response head
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// Size of socket reads
+const READ_BUFFER_BYTES: usize = 8 * 1024;
+
+/// An event received from an event stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Event type, `message` unless the server set one
+    pub event_type: String,
+    /// Event data, with lines joined by `\n`
+    pub data: String,
+    /// Last event ID at the time of the event
+    pub last_event_id: Option<String>,
+}
+
+/// Incremental parser for the `text/event-stream` format
+#[derive(Debug, Default)]
+pub struct SseParser {
+    /// Bytes of the current, incomplete line
+    line: Vec<u8>,
+    /// Whether the last byte fed was a CR, so a following LF ends no line
+    after_cr: bool,
+    /// Whether a line has been read, after which no byte order mark is expected
+    started: bool,
+    /// Data of the event being built
+    data: String,
+    /// Type of the event being built
+    event_type: String,
+    /// Last event ID, kept across events and reconnections
+    last_event_id: Option<String>,
+    /// Reconnection delay requested by the server
+    retry: Option<Duration>,
+}
+
+impl SseParser {
+    /// Create a parser resuming from a last event ID
+    pub fn new(last_event_id: Option<String>) -> Self {
+        Self {
+            last_event_id,
+            ..Self::default()
+        }
+    }
+
+    /// Last event ID seen
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// Reconnection delay requested by the server
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// Start parsing a new stream, keeping the last event ID and retry delay
+    pub fn reset(&mut self) {
+        *self = Self {
+            last_event_id: self.last_event_id.take(),
+            retry: self.retry,
+            ..Self::default()
+        };
+    }
+
+    /// Feed bytes of the stream, returning the events they complete
+    pub fn feed(&mut self, mut bytes: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        while let Some(&byte) = bytes.first() {
+            bytes = &bytes[1..];
+            match byte {
+                b'\n' if self.after_cr => self.after_cr = false,
+                b'\r' | b'\n' => {
+                    self.after_cr = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    let line = String::from_utf8_lossy(&line);
+                    // The stream may start with a byte order mark
+                    let line = if self.started { &line[..] } else { line.trim_start_matches('\u{FEFF}') };
+                    self.started = true;
+                    events.extend(self.process_line(line));
+                }
+                _ => {
+                    self.after_cr = false;
+                    self.line.push(byte);
+                }
+            }
+        }
+        events
+    }
+
+    /// Apply a complete line, returning the event it dispatches
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "event" => self.event_type = value.to_string(),
+            "id" if !value.contains('\0') => {
+                self.last_event_id = (!value.is_empty()).then(|| value.to_string());
+            }
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Dispatch the event being built, unless it has no data
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event_type = std::mem::take(&mut self.event_type);
+        let mut data = std::mem::take(&mut self.data);
+        if data.is_empty() {
+            return None;
+        }
+        data.pop();
+
+        Some(SseEvent {
+            event_type: if event_type.is_empty() { "message".to_string() } else { event_type },
+            data,
+            last_event_id: self.last_event_id.clone(),
+        })
+    }
+}
+
+/// State of a chunked body decoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkState {
+    /// Reading a chunk size line
+    Size,
+    /// Reading chunk data, with this many bytes left
+    Data(usize),
+    /// Reading the CRLF after chunk data
+    DataEnd,
+    /// Reading trailer lines after the last chunk
+    Trailer,
+    /// The body is complete
+    Done,
+}
+
+/// Incremental decoder for `Transfer-Encoding: chunked` bodies
+#[derive(Debug)]
+pub(crate) struct ChunkedDecoder {
+    state: ChunkState,
+    line: Vec<u8>,
+}
+
+impl ChunkedDecoder {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: ChunkState::Size,
+            line: Vec::new(),
+        }
+    }
+
+    /// Whether the last chunk has been read
+    pub(crate) fn is_done(&self) -> bool {
+        self.state == ChunkState::Done
+    }
+
+    /// Decode received bytes, returning the body data they hold
+    pub(crate) fn decode(&mut self, mut bytes: &[u8]) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        while !bytes.is_empty() && self.state != ChunkState::Done {
+            match self.state {
+                ChunkState::Data(remaining) => {
+                    let take = remaining.min(bytes.len());
+                    body.extend_from_slice(&bytes[..take]);
+                    bytes = &bytes[take..];
+                    self.state = if take == remaining { ChunkState::DataEnd } else { ChunkState::Data(remaining - take) };
+                }
+                ChunkState::Size | ChunkState::DataEnd | ChunkState::Trailer => {
+                    let Some(end) = bytes.iter().position(|&b| b == b'\n') else {
+                        self.line.extend_from_slice(bytes);
+                        break;
+                    };
+                    self.line.extend_from_slice(&bytes[..end]);
+                    bytes = &bytes[end + 1..];
+                    let line = std::mem::take(&mut self.line);
+                    let line = String::from_utf8_lossy(&line);
+                    let line = line.trim_end_matches('\r');
+                    self.state = match self.state {
+                        ChunkState::Size => {
+                            // Chunk extensions follow a ';'
+                            let size = line.split(';').next().unwrap_or_default().trim();
+                            match usize::from_str_radix(size, 16) {
+                                Ok(0) => ChunkState::Trailer,
+                                Ok(size) => ChunkState::Data(size),
+                                Err(_) => return Err(Error::NetworkError(format!("Invalid chunk size: {:?}", size))),
+                            }
+                        }
+                        ChunkState::DataEnd if line.is_empty() => ChunkState::Size,
+                        ChunkState::DataEnd => return Err(Error::NetworkError("Missing CRLF after chunk data".to_string())),
+                        _ if line.is_empty() => ChunkState::Done,
+                        _ => ChunkState::Trailer,
+                    };
+                }
+                ChunkState::Done => unreachable!(),
+            }
+        }
+        Ok(body)
+    }
+}
+
+/// Receiver of the events of an event stream
+///
+/// Dropping the receiver closes the connection.
+pub struct SseReceiver {
+    events: mpsc::Receiver<SseEvent>,
+    task: JoinHandle<()>,
+}
+
+impl SseReceiver {
+    /// Receive the next event, or `None` once the stream has ended for good
+    pub async fn recv(&mut self) -> Option<SseEvent> {
+        self.events.recv().await
+    }
+}
+
+impl Drop for SseReceiver {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Body of an open event stream response
+struct SseStream {
+    socket: TcpStream,
+    /// Decoder for chunked bodies, `None` if the body runs to EOF
+    chunked: Option<ChunkedDecoder>,
+    /// Body bytes read along with the response head
+    initial: Vec<u8>,
+}
+
+/// A Server-Sent Events connection
+pub struct SseConnection {
+    /// Tab that opened the stream
+    pub tab_id: TabId,
+    /// Stream URL
+    pub url: url::Url,
+    /// Timeout for connecting and receiving the response head
+    timeout: Duration,
+    /// Parser state, kept across reconnections for the last event ID
+    parser: SseParser,
+    /// Channel events are delivered on
+    sender: mpsc::Sender<SseEvent>,
+}
+
+impl SseConnection {
+    /// Create a connection delivering events on `sender`
+    pub fn new(tab_id: TabId, url: &str, last_event_id: Option<String>, timeout: Duration, sender: mpsc::Sender<SseEvent>) -> Result<Self> {
+        let url = url::Url::parse(url).map_err(|e| Error::ConfigError(format!("Invalid event stream URL {}: {}", url, e)))?;
+        if url.scheme() != "http" {
+            return Err(Error::NotImplemented(format!(
+                "Event streams with scheme {} are not supported",
+                url.scheme()
+            )));
+        }
+
+        Ok(Self {
+            tab_id,
+            url,
+            timeout,
+            parser: SseParser::new(last_event_id),
+            sender,
+        })
+    }
+
+    /// Connect and start delivering events
+    ///
+    /// The first connection attempt is made before returning so that its
+    /// failure is reported to the caller.
+    pub async fn open(tab_id: TabId, url: &str, last_event_id: Option<String>, timeout: Duration) -> Result<SseReceiver> {
+        let (sender, events) = mpsc::channel(EVENT_BUFFER);
+        let connection = Self::new(tab_id, url, last_event_id, timeout, sender)?;
+        let stream = connection.connect().await?;
+        info!("Opened event stream {} for tab {}", connection.url, tab_id);
+
+        let task = tokio::spawn(connection.run(stream));
+        Ok(SseReceiver { events, task })
+    }
+
+    /// Deliver events, reconnecting after disconnects while the server allows
+    async fn run(mut self, mut stream: SseStream) {
+        loop {
+            if let Err(e) = self.read_events(stream).await {
+                debug!("Event stream {} disconnected: {}", self.url, e);
+            }
+            if self.sender.is_closed() {
+                return;
+            }
+
+            // Only reconnect if the server asked for it
+            let Some(delay) = self.parser.retry() else {
+                info!("Event stream {} ended", self.url);
+                return;
+            };
+
+            stream = loop {
+                tokio::time::sleep(delay).await;
+                self.parser.reset();
+                match self.connect().await {
+                    Ok(stream) => break stream,
+                    // Network failures are retried, bad responses end the stream
+                    Err(e @ (Error::IoError(_) | Error::Timeout(_))) => {
+                        debug!("Reconnecting to event stream {} failed: {}", self.url, e);
+                    }
+                    Err(e) => {
+                        warn!("Event stream {} failed: {}", self.url, e);
+                        return;
+                    }
+                }
+            };
+            info!("Reconnected to event stream {}", self.url);
+        }
+    }
+
+    /// Issue the request and read the response head
+    async fn connect(&self) -> Result<SseStream> {
+        let host = self.url.host_str()
+            .ok_or_else(|| Error::ConfigError(format!("Event stream URL {} has no host", self.url)))?;
+        let port = self.url.port_or_known_default().unwrap_or(80);
+        let path = &self.url[url::Position::BeforePath..url::Position::AfterQuery];
+
+        let mut head = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: text/event-stream\r\nCache-Control: no-cache\r\n",
+            path,
+            &self.url[url::Position::BeforeHost..url::Position::AfterPort]
+        );
+        if let Some(last_event_id) = self.parser.last_event_id() {
+            head.push_str(&format!("Last-Event-ID: {}\r\n", last_event_id));
+        }
+        head.push_str("\r\n");
+
+        let request = async {
+            let mut socket = TcpStream::connect((host, port)).await?;
+            socket.write_all(head.as_bytes()).await?;
+
+            let mut received = Vec::new();
+            let mut buffer = [0; READ_BUFFER_BYTES];
+            let head_end = loop {
+                if let Some(end) = received.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break end + 4;
+                }
+                if received.len() > MAX_HEAD_BYTES {
+                    return Err(Error::NetworkError("Event stream response head is too large".to_string()));
+                }
+                let read = socket.read(&mut buffer).await?;
+                if read == 0 {
+                    return Err(Error::IoError("Connection closed before the response head".to_string()));
+                }
+                received.extend_from_slice(&buffer[..read]);
+            };
+            Ok((socket, received, head_end))
+        };
+        let (socket, mut received, head_end) = tokio::time::timeout(self.timeout, request)
+            .await
+            .map_err(|_| Error::Timeout(format!("Connecting to event stream {} timed out", self.url)))??;
+
+        let initial = received.split_off(head_end);
+        let head = String::from_utf8_lossy(&received);
+        let mut lines = head.split("\r\n");
+
+        // Status line: HTTP/1.1 200 OK
+        let status = lines.next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| Error::NetworkError(format!("Invalid response from event stream {}", self.url)))?;
+        if status != 200 {
+            return Err(Error::NetworkError(format!("Event stream {} returned status {}", self.url, status)));
+        }
+
+        let mut content_type = None;
+        let mut chunked = false;
+        for line in lines {
+            let Some((name, value)) = line.split_once(':') else { continue };
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.to_ascii_lowercase());
+            } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                chunked = value.to_ascii_lowercase().split(',').any(|coding| coding.trim() == "chunked");
+            }
+        }
+        if !content_type.is_some_and(|content_type| content_type.starts_with("text/event-stream")) {
+            return Err(Error::NetworkError(format!("Event stream {} has the wrong content type", self.url)));
+        }
+
+        Ok(SseStream {
+            socket,
+            chunked: chunked.then(ChunkedDecoder::new),
+            initial,
+        })
+    }
+
+    /// Read and deliver events until the response body ends
+    async fn read_events(&mut self, mut stream: SseStream) -> Result<()> {
+        let mut received = std::mem::take(&mut stream.initial);
+        let mut buffer = [0; READ_BUFFER_BYTES];
+        loop {
+            let body = match stream.chunked.as_mut() {
+                Some(decoder) => decoder.decode(&received)?,
+                None => received,
+            };
+            for event in self.parser.feed(&body) {
+                if self.sender.send(event).await.is_err() {
+                    return Ok(());
+                }
+            }
+            if stream.chunked.as_ref().is_some_and(ChunkedDecoder::is_done) {
+                return Ok(());
+            }
+
+            let read = stream.socket.read(&mut buffer).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            received = buffer[..read].to_vec();
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! WebRTC ICE candidate gathering for the network process
+//!
+//! An ICE agent gathers the addresses a peer can reach the browser at: host
+//! addresses of the local interfaces, server reflexive addresses reported by
+//! STUN servers and relayed addresses allocated on TURN servers. The page
+//! exchanges candidates and credentials with the peer over its own signalling
+//! channel, then both agents check candidate pairs until one connects.
+//!
+//! This is groundwork for WebRTC: a connected agent offers a datagram channel
+//! over the selected candidate pair, without the DTLS and SCTP layers that
+//! `RTCDataChannel` runs on.
+
+use std::sync::Arc;
+
+use common::error::{Error, Result};
+use common::types::TabId;
+use ice::agent::agent_config::AgentConfig;
+use ice::agent::Agent;
+use ice::candidate::candidate_base::unmarshal_candidate;
+use ice::candidate::{Candidate, CandidateType};
+use ice::mdns::MulticastDnsMode;
+use ice::network_type::NetworkType;
+use ice::state::ConnectionState;
+use ice::url::Url;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+use webrtc_util::Conn;
+
+/// Largest datagram received on a data channel
+const MAX_DATAGRAM_SIZE: usize = 1500;
+
+/// Identifier of an ICE agent in the network process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct IceAgentId(pub u64);
+
+impl std::fmt::Display for IceAgentId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ice_{}", self.0)
+    }
+}
+
+/// TURN server to allocate relayed candidates on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnServer {
+    /// `turn:` or `turns:` URL of the server
+    pub url: String,
+    /// Username for the long-term credential
+    pub username: String,
+    /// Password for the long-term credential
+    pub credential: String,
+}
+
+/// Servers ICE agents gather candidates from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IceConfig {
+    /// `stun:` URLs of servers reporting server reflexive addresses
+    pub stun_servers: Vec<String>,
+    /// Servers relayed addresses are allocated on
+    pub turn_servers: Vec<TurnServer>,
+}
+
+/// Username fragment and password authenticating connectivity checks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IceCredentials {
+    /// `ice-ufrag` of the agent
+    pub username_fragment: String,
+    /// `ice-pwd` of the agent
+    pub password: String,
+}
+
+/// Kind of address a candidate offers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IceCandidateType {
+    /// Address of a local interface
+    Host,
+    /// Address of the host as seen by a STUN server
+    ServerReflexive,
+    /// Address of the peer as seen by connectivity checks
+    PeerReflexive,
+    /// Address allocated on a TURN server
+    Relay,
+}
+
+/// Address an ICE agent can be reached at
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IceCandidate {
+    /// Value of the SDP `candidate` attribute, as exchanged with the peer
+    pub candidate: String,
+    /// Kind of address
+    pub candidate_type: IceCandidateType,
+    /// IP address
+    pub address: String,
+    /// Port
+    pub port: u16,
+    /// Priority of the candidate, higher being preferred
+    pub priority: u32,
+}
+
+impl IceCandidate {
+    /// Parse the value of an SDP `candidate` attribute received from the peer
+    pub fn parse(candidate: &str) -> Result<Self> {
+        let candidate = candidate.strip_prefix("candidate:").unwrap_or(candidate);
+        let parsed = unmarshal_candidate(candidate)
+            .map_err(|e| Error::ParseError(format!("Invalid ICE candidate {:?}: {}", candidate, e)))?;
+        Self::from_candidate(&parsed)
+    }
+
+    fn from_candidate(candidate: &dyn Candidate) -> Result<Self> {
+        let candidate_type = match candidate.candidate_type() {
+            CandidateType::Host => IceCandidateType::Host,
+            CandidateType::ServerReflexive => IceCandidateType::ServerReflexive,
+            CandidateType::PeerReflexive => IceCandidateType::PeerReflexive,
+            CandidateType::Relay => IceCandidateType::Relay,
+            CandidateType::Unspecified => {
+                return Err(Error::ParseError(format!("ICE candidate {} has no type", candidate.marshal())));
+            }
+        };
+
+        Ok(Self {
+            candidate: candidate.marshal(),
+            candidate_type,
+            address: candidate.address(),
+            port: candidate.port(),
+            priority: candidate.priority(),
+        })
+    }
+}
+
+/// State of the connection of an ICE agent to its peer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IceConnectionState {
+    /// Gathering candidates or waiting for the peer
+    #[default]
+    New,
+    /// Checking candidate pairs
+    Checking,
+    /// A candidate pair connected
+    Connected,
+    /// Checks finished with a connected pair
+    Completed,
+    /// No candidate pair connected
+    Failed,
+    /// The connected pair stopped responding
+    Disconnected,
+    /// The agent was closed
+    Closed,
+}
+
+impl From<ConnectionState> for IceConnectionState {
+    fn from(state: ConnectionState) -> Self {
+        match state {
+            ConnectionState::Unspecified | ConnectionState::New => IceConnectionState::New,
+            ConnectionState::Checking => IceConnectionState::Checking,
+            ConnectionState::Connected => IceConnectionState::Connected,
+            ConnectionState::Completed => IceConnectionState::Completed,
+            ConnectionState::Failed => IceConnectionState::Failed,
+            ConnectionState::Disconnected => IceConnectionState::Disconnected,
+            ConnectionState::Closed => IceConnectionState::Closed,
+        }
+    }
+}
+
+/// ICE agent of a tab
+pub struct IceAgent {
+    /// Tab that created the agent
+    pub tab_id: TabId,
+    /// Candidates gathered for this browser
+    pub local_candidates: Vec<IceCandidate>,
+    /// Candidates received from the peer
+    pub remote_candidates: Vec<IceCandidate>,
+    /// State of the connection to the peer
+    pub state: IceConnectionState,
+    /// Credentials of this agent
+    credentials: IceCredentials,
+    /// Credentials of the peer
+    remote_credentials: Option<IceCredentials>,
+    /// Agent running gathering and connectivity checks, created by gathering
+    agent: Option<Arc<Agent>>,
+}
+
+impl IceAgent {
+    /// Create an agent with fresh credentials
+    pub fn new(tab_id: TabId) -> Self {
+        Self {
+            tab_id,
+            local_candidates: Vec::new(),
+            remote_candidates: Vec::new(),
+            state: IceConnectionState::New,
+            credentials: IceCredentials {
+                username_fragment: ice::rand::generate_ufrag(),
+                password: ice::rand::generate_pwd(),
+            },
+            remote_credentials: None,
+            agent: None,
+        }
+    }
+
+    /// Get the credentials to send to the peer
+    pub fn credentials(&self) -> &IceCredentials {
+        &self.credentials
+    }
+
+    /// Set the credentials received from the peer
+    pub fn set_remote_credentials(&mut self, credentials: IceCredentials) {
+        self.remote_credentials = Some(credentials);
+    }
+
+    /// Gather host, server reflexive and relayed candidates
+    ///
+    /// Host candidates carry the IP addresses of the local interfaces rather
+    /// than mDNS names.
+    pub async fn gather_candidates(&mut self, config: IceConfig) -> Result<Vec<IceCandidate>> {
+        if self.agent.is_some() {
+            return Err(Error::InvalidState(format!("ICE candidates for tab {} were already gathered", self.tab_id)));
+        }
+
+        let mut urls = Vec::new();
+        for server in &config.stun_servers {
+            urls.push(Url::parse_url(server).map_err(|e| Error::ConfigError(format!("Invalid STUN server {}: {}", server, e)))?);
+        }
+        for server in &config.turn_servers {
+            let mut url = Url::parse_url(&server.url)
+                .map_err(|e| Error::ConfigError(format!("Invalid TURN server {}: {}", server.url, e)))?;
+            url.username = server.username.clone();
+            url.password = server.credential.clone();
+            urls.push(url);
+        }
+
+        let agent = Arc::new(Agent::new(AgentConfig {
+            urls,
+            local_ufrag: self.credentials.username_fragment.clone(),
+            local_pwd: self.credentials.password.clone(),
+            network_types: vec![NetworkType::Udp4, NetworkType::Udp6],
+            multicast_dns_mode: MulticastDnsMode::Disabled,
+            ..Default::default()
+        }).await.map_err(ice_error)?);
+
+        let tab_id = self.tab_id;
+        agent.on_connection_state_change(Box::new(move |state: ConnectionState| {
+            debug!("ICE connection of tab {} is {:?}", tab_id, IceConnectionState::from(state));
+            Box::pin(async {})
+        }));
+
+        // The handler is called with each candidate, then with `None` once gathering is done
+        let (candidate_tx, mut candidate_rx) = mpsc::unbounded_channel();
+        agent.on_candidate(Box::new(move |candidate: Option<Arc<dyn Candidate + Send + Sync>>| {
+            let _ = candidate_tx.send(candidate);
+            Box::pin(async {})
+        }));
+        agent.gather_candidates().map_err(ice_error)?;
+
+        while let Some(Some(candidate)) = candidate_rx.recv().await {
+            self.local_candidates.push(IceCandidate::from_candidate(candidate.as_ref())?);
+        }
+        info!("Gathered {} ICE candidates for tab {}", self.local_candidates.len(), self.tab_id);
+
+        for candidate in &self.remote_candidates {
+            add_candidate(&agent, candidate)?;
+        }
+        self.agent = Some(agent);
+
+        Ok(self.local_candidates.clone())
+    }
+
+    /// Add a candidate received from the peer
+    pub fn add_remote_candidate(&mut self, candidate: IceCandidate) -> Result<()> {
+        if let Some(agent) = &self.agent {
+            add_candidate(agent, &candidate)?;
+        }
+        self.remote_candidates.push(candidate);
+        Ok(())
+    }
+
+    /// Check candidate pairs until one connects
+    ///
+    /// The agent with the lower username fragment takes the controlling role,
+    /// so the peers agree on roles without signalling them.
+    pub async fn connect(&mut self) -> Result<DataChannel> {
+        let agent = self.agent.clone()
+            .ok_or_else(|| Error::InvalidState(format!("ICE candidates for tab {} were not gathered", self.tab_id)))?;
+        let remote = self.remote_credentials.clone()
+            .ok_or_else(|| Error::InvalidState(format!("No remote ICE credentials for tab {}", self.tab_id)))?;
+
+        self.state = IceConnectionState::Checking;
+        let controlling = self.credentials.username_fragment < remote.username_fragment;
+
+        // Checks are cancelled when the sender is dropped, so it has to outlive them
+        let (_cancel_tx, cancel_rx) = mpsc::channel(1);
+        let conn = if controlling {
+            agent.dial(cancel_rx, remote.username_fragment, remote.password).await
+                .map(|conn| conn as Arc<dyn Conn + Send + Sync>)
+        } else {
+            agent.accept(cancel_rx, remote.username_fragment, remote.password).await
+                .map(|conn| conn as Arc<dyn Conn + Send + Sync>)
+        };
+
+        match conn {
+            Ok(conn) => {
+                info!("ICE connection of tab {} established", self.tab_id);
+                self.state = IceConnectionState::Connected;
+                Ok(DataChannel { conn })
+            }
+            Err(e) => {
+                self.state = IceConnectionState::Failed;
+                Err(ice_error(e))
+            }
+        }
+    }
+
+    /// Stop the agent and close its connection
+    pub async fn close(&mut self) -> Result<()> {
+        if let Some(agent) = self.agent.take() {
+            agent.close().await.map_err(ice_error)?;
+        }
+        self.state = IceConnectionState::Closed;
+        Ok(())
+    }
+}
+
+/// Datagram channel to the peer over the selected candidate pair
+#[derive(Clone)]
+pub struct DataChannel {
+    conn: Arc<dyn Conn + Send + Sync>,
+}
+
+impl DataChannel {
+    /// Send a datagram
+    pub async fn send(&self, data: &[u8]) -> Result<()> {
+        self.conn.send(data).await.map_err(connection_error)?;
+        Ok(())
+    }
+
+    /// Wait for the next datagram
+    pub async fn recv(&self) -> Result<Vec<u8>> {
+        let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+        let length = self.conn.recv(&mut buffer).await.map_err(connection_error)?;
+        buffer.truncate(length);
+        Ok(buffer)
+    }
+
+    /// Close the channel
+    pub async fn close(&self) -> Result<()> {
+        self.conn.close().await.map_err(connection_error)
+    }
+}
+
+/// Add a candidate of the peer to an agent
+fn add_candidate(agent: &Agent, candidate: &IceCandidate) -> Result<()> {
+    let parsed = unmarshal_candidate(&candidate.candidate)
+        .map_err(|e| Error::ParseError(format!("Invalid ICE candidate {:?}: {}", candidate.candidate, e)))?;
+    let parsed: Arc<dyn Candidate + Send + Sync> = Arc::new(parsed);
+    agent.add_remote_candidate(&parsed).map_err(ice_error)
+}
+
+fn ice_error(e: ice::Error) -> Error {
+    Error::NetworkError(format!("ICE error: {}", e))
+}
+
+fn connection_error(e: webrtc_util::Error) -> Error {
+    Error::NetworkError(format!("ICE connection error: {}", e))
+}
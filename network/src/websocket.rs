@@ -0,0 +1,212 @@
+//! WebSocket connections for the network process
+//!
+//! A connection is opened with an HTTP upgrade handshake carrying the
+//! configured subprotocols and cookies. A task reads frames from the server
+//! and forwards them to the renderer of the tab that opened the socket as
+//! `WebSocketFrame` messages, followed by a `WebSocketClosed` message once the
+//! connection ends.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use common::error::{Error, Result};
+use common::ipc::{Channel, IpcMessage, WebSocketClosedMessage, WebSocketFrameMessage, WebSocketOpenedMessage};
+use common::types::TabId;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info, warn};
+
+pub use common::ipc::WsMessage;
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Identifier of a WebSocket connection in the network process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct WebSocketId(pub u64);
+
+impl std::fmt::Display for WebSocketId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ws_{}", self.0)
+    }
+}
+
+/// Headers added to WebSocket upgrade requests
+#[derive(Debug, Clone, Default)]
+pub struct WebSocketConfig {
+    /// Subprotocols offered in `Sec-WebSocket-Protocol`, in order of preference
+    pub protocols: Vec<String>,
+    /// `Cookie` header values by host
+    pub cookies: HashMap<String, String>,
+}
+
+/// An open WebSocket connection
+pub struct WebSocketConnection {
+    /// Connection ID
+    pub id: WebSocketId,
+    /// Tab that opened the connection
+    pub tab_id: TabId,
+    /// URL the connection was opened to
+    pub url: String,
+    /// Subprotocol selected by the server
+    pub protocol: Option<String>,
+    /// Write half of the connection
+    sink: Mutex<SplitSink<WsStream, Message>>,
+    /// Task forwarding received frames to the renderer
+    reader: JoinHandle<()>,
+}
+
+impl WebSocketConnection {
+    /// Perform the upgrade handshake and start forwarding frames over `channel`
+    ///
+    /// With an `open_request_id` the renderer is first sent a `WebSocketOpened`
+    /// reply to that request, so it learns the socket ID before any frame.
+    pub async fn connect(
+        id: WebSocketId,
+        tab_id: TabId,
+        url: &str,
+        config: &WebSocketConfig,
+        timeout: Duration,
+        channel: Channel<IpcMessage>,
+        open_request_id: Option<u64>,
+    ) -> Result<Self> {
+        let mut request = url.into_client_request().map_err(websocket_error)?;
+        let host = request.uri().host().unwrap_or_default().to_string();
+        let headers = request.headers_mut();
+        if !config.protocols.is_empty() {
+            headers.insert("Sec-WebSocket-Protocol", header_value(&config.protocols.join(", "))?);
+        }
+        if let Some(cookie) = config.cookies.get(&host) {
+            headers.insert("Cookie", header_value(cookie)?);
+        }
+
+        let (stream, response) = tokio::time::timeout(timeout, tokio_tungstenite::connect_async(request))
+            .await
+            .map_err(|_| Error::Timeout(format!("WebSocket handshake with {} timed out", url)))?
+            .map_err(websocket_error)?;
+        let protocol = response
+            .headers()
+            .get("Sec-WebSocket-Protocol")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        info!("Opened WebSocket {} to {}", id, url);
+        let (sink, stream) = stream.split();
+        let opened = open_request_id.map(|request_id| IpcMessage::WebSocketOpened(WebSocketOpenedMessage {
+            request_id,
+            tab_id,
+            socket_id: id.0,
+            protocol: protocol.clone(),
+        }));
+        let reader = tokio::spawn(forward_frames(id, tab_id, stream, channel, opened));
+
+        Ok(Self {
+            id,
+            tab_id,
+            url: url.to_string(),
+            protocol,
+            sink: Mutex::new(sink),
+            reader,
+        })
+    }
+
+    /// Send a message to the server
+    pub async fn send(&self, message: WsMessage) -> Result<()> {
+        let message = match message {
+            WsMessage::Text(text) => Message::Text(text),
+            WsMessage::Binary(data) => Message::Binary(data),
+        };
+        self.sink.lock().await.send(message).await.map_err(websocket_error)
+    }
+
+    /// Start the closing handshake
+    ///
+    /// The renderer is sent `WebSocketClosed` once the server acknowledges.
+    pub async fn close(&self) -> Result<()> {
+        self.sink.lock().await.close().await.map_err(websocket_error)
+    }
+
+    /// Whether the connection has ended
+    pub fn is_closed(&self) -> bool {
+        self.reader.is_finished()
+    }
+}
+
+/// Forward frames from the server to the renderer until the connection ends
+async fn forward_frames(
+    id: WebSocketId,
+    tab_id: TabId,
+    mut stream: SplitStream<WsStream>,
+    channel: Channel<IpcMessage>,
+    opened: Option<IpcMessage>,
+) {
+    if let Some(opened) = opened {
+        if let Err(e) = channel.send_async(opened).await {
+            warn!("Failed to report WebSocket {} open: {}", id, e);
+            return;
+        }
+    }
+
+    let mut code = None;
+    let mut reason = String::new();
+
+    // Keep reading after a close frame so the reply is flushed
+    while let Some(frame) = stream.next().await {
+        let message = match frame {
+            Ok(Message::Text(text)) => WsMessage::Text(text),
+            Ok(Message::Binary(data)) => WsMessage::Binary(data),
+            Ok(Message::Close(frame)) => {
+                if let Some(frame) = frame {
+                    code = Some(u16::from(frame.code));
+                    reason = frame.reason.into_owned();
+                }
+                continue;
+            }
+            // Pings are answered by tungstenite
+            Ok(_) => continue,
+            Err(e) => {
+                warn!("WebSocket {} failed: {}", id, e);
+                reason = e.to_string();
+                break;
+            }
+        };
+
+        let frame = IpcMessage::WebSocketFrame(WebSocketFrameMessage {
+            socket_id: id.0,
+            tab_id,
+            message,
+        });
+        if let Err(e) = channel.send_async(frame).await {
+            warn!("Failed to forward WebSocket {} frame: {}", id, e);
+            return;
+        }
+    }
+
+    debug!("WebSocket {} closed", id);
+    let closed = IpcMessage::WebSocketClosed(WebSocketClosedMessage {
+        socket_id: id.0,
+        tab_id,
+        code,
+        reason,
+    });
+    if let Err(e) = channel.send_async(closed).await {
+        warn!("Failed to report WebSocket {} close: {}", id, e);
+    }
+}
+
+/// Build a header value from configuration
+fn header_value(value: &str) -> Result<HeaderValue> {
+    HeaderValue::from_str(value).map_err(|e| Error::ConfigError(format!("Invalid WebSocket header value: {}", e)))
+}
+
+/// Convert a tungstenite error
+fn websocket_error(e: tokio_tungstenite::tungstenite::Error) -> Error {
+    Error::NetworkError(format!("WebSocket error: {}", e))
+}
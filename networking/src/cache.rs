@@ -243,7 +243,7 @@ pub struct CacheManager {
 /// Cache warming manager
 pub struct CacheWarmingManager {
     /// Warming queue
-    queue: Arc<RwLock<VecDeque<CacheWarmingEntry>>>,
+    pub(crate) queue: Arc<RwLock<VecDeque<CacheWarmingEntry>>>,
     /// Warming workers
     workers: Vec<tokio::task::JoinHandle<()>>,
     /// Configuration
@@ -779,6 +779,7 @@ impl DiskCache {
     /// Create new disk cache
     pub fn new(config: CacheConfig) -> Result<Self> {
         let cache_dir = config.cache_directory
+            .clone()
             .unwrap_or_else(|| PathBuf::from("./cache"));
         let index_file = cache_dir.join("index.json");
         
@@ -838,7 +839,7 @@ impl DiskCache {
         // Update statistics
         let mut stats = self.stats.write();
         stats.total_size = index.values().map(|e| e.size).sum();
-        stats.total_entries = index.len() as u64;
+        stats.total_entries = index.len();
         
         Ok(())
     }
@@ -907,14 +908,12 @@ impl DiskCache {
 
     /// Clean expired entries
     pub fn clean_expired(&self) -> Result<usize> {
-        let mut index = self.index.write();
-        let mut expired_keys = Vec::new();
-        
-        for (key, entry) in index.iter() {
-            if entry.is_expired() {
-                expired_keys.push(key.clone());
-            }
-        }
+        // Collect keys first; remove() takes the index lock itself
+        let expired_keys: Vec<String> = self.index.read()
+            .iter()
+            .filter(|(_, entry)| entry.is_expired())
+            .map(|(key, _)| key.clone())
+            .collect();
         
         let expired_count = expired_keys.len();
         
@@ -923,10 +922,11 @@ impl DiskCache {
         }
         
         // Update statistics
+        let index = self.index.read();
         let mut stats = self.stats.write();
         stats.expirations += expired_count as u64;
         stats.total_size = index.values().map(|e| e.size).sum();
-        stats.total_entries = index.len() as u64;
+        stats.total_entries = index.len();
         
         Ok(expired_count)
     }
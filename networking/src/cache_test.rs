@@ -27,8 +27,8 @@ mod tests {
         assert_eq!(entry.key, "https://example.com/page");
         assert_eq!(entry.data, b"<html>Hello World</html>");
         assert_eq!(entry.status_code, 200);
-        assert_eq!(entry.content_length, 25);
-        assert_eq!(entry.size, 25);
+        assert_eq!(entry.content_length, 24);
+        assert_eq!(entry.size, 24);
         assert_eq!(entry.access_count, 0);
         assert!(entry.is_fresh());
         assert!(!entry.is_stale());
@@ -563,7 +563,7 @@ mod tests {
         let mut config = CacheConfig::default();
         config.cache_directory = Some(temp_dir.path().to_path_buf());
         
-        let manager = CacheManager::new(config).unwrap();
+        let manager = CacheManager::new(config.clone()).unwrap();
         
         let entry = CacheEntry::new(
             "https://example.com/page".to_string(),
@@ -840,7 +840,7 @@ mod tests {
         
         // Clean expired entries
         let expired_count = manager.clean_expired().unwrap();
-        assert!(expired_count >= 0);
+        assert_eq!(expired_count, 0);
         
         // Verify entries are still available (they shouldn't be expired)
         assert!(manager.get("https://example.com/page1").is_some());
@@ -26,6 +26,8 @@ pub enum Error {
     Dns(String),
     /// Configuration errors
     Config(String),
+    /// Security policy errors
+    Security(String),
 }
 
 impl fmt::Display for Error {
@@ -42,6 +44,7 @@ impl fmt::Display for Error {
             Error::Ssl(msg) => write!(f, "SSL/TLS error: {}", msg),
             Error::Dns(msg) => write!(f, "DNS error: {}", msg),
             Error::Config(msg) => write!(f, "Configuration error: {}", msg),
+            Error::Security(msg) => write!(f, "Security error: {}", msg),
         }
     }
 }
@@ -79,6 +82,18 @@ impl From<std::str::Utf8Error> for Error {
     }
 }
 
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Parsing(format!("JSON error: {}", err))
+    }
+}
+
+impl From<getrandom::Error> for Error {
+    fn from(err: getrandom::Error) -> Self {
+        Error::Ssl(format!("Random generation error: {}", err))
+    }
+}
+
 impl Error {
     /// Create parsing error
     pub fn parsing<T: Into<String>>(msg: T) -> Self {
@@ -129,6 +144,11 @@ impl Error {
     pub fn config<T: Into<String>>(msg: T) -> Self {
         Error::Config(msg.into())
     }
+
+    /// Create security error
+    pub fn security<T: Into<String>>(msg: T) -> Self {
+        Error::Security(msg.into())
+    }
 }
 
 /// Result type for networking operations
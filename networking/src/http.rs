@@ -1,12 +1,12 @@
 use crate::error::{Error, Result};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::io::{Read, Write, BufRead, BufReader};
 use std::net::{TcpStream, SocketAddr, ToSocketAddrs};
 use std::time::{Duration, Instant};
 use tokio::net::TcpStream as TokioTcpStream;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncReadExt, AsyncWriteExt};
 use tokio::time::{sleep, timeout};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use parking_lot::RwLock;
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -71,7 +71,8 @@ pub enum HttpStatus {
 /// HTTP headers
 #[derive(Debug, Clone)]
 pub struct HttpHeaders {
-    headers: HashMap<String, String>,
+    /// Headers keyed by lowercase name, keeping the name as it was set
+    headers: HashMap<String, (String, String)>,
 }
 
 /// HTTP request
@@ -166,15 +167,15 @@ pub struct HttpClient {
     /// Connection pool
     connection_pool: HttpConnectionPool,
     /// Default timeout
-    default_timeout: Duration,
+    pub(crate) default_timeout: Duration,
     /// Default user agent
-    user_agent: String,
+    pub(crate) user_agent: String,
     /// Default headers
     default_headers: HttpHeaders,
     /// Whether to follow redirects by default
-    follow_redirects: bool,
+    pub(crate) follow_redirects: bool,
     /// Maximum redirects to follow by default
-    max_redirects: usize,
+    pub(crate) max_redirects: usize,
 }
 
 /// HTTP/2 specific structures
@@ -269,14 +270,29 @@ pub struct Http2Connection {
     established: bool,
 }
 
+/// HPACK dynamic table (RFC 7541 section 2.3.2)
+///
+/// Entries are kept newest first, so the entry at position 0 has HPACK index 62.
+#[derive(Debug, Clone)]
+pub struct DynamicTable {
+    /// Header fields, newest first
+    entries: VecDeque<(String, String)>,
+    /// Size of the entries as defined by RFC 7541 section 4.1
+    size: usize,
+    /// Maximum size, as last set by a dynamic table size update
+    max_size: usize,
+    /// Upper bound of the maximum size, from SETTINGS_HEADER_TABLE_SIZE
+    limit: usize,
+    /// Whether the encoder has to signal a new maximum size
+    size_update_pending: bool,
+}
+
 /// HPACK encoder/decoder
 pub struct HpackCodec {
-    /// Dynamic table
-    dynamic_table: Vec<(String, String)>,
-    /// Maximum table size
-    max_table_size: usize,
-    /// Current table size
-    current_table_size: usize,
+    /// Dynamic table of header blocks sent to the peer
+    pub encoder_table: DynamicTable,
+    /// Dynamic table of header blocks received from the peer
+    pub decoder_table: DynamicTable,
 }
 
 impl HttpMethod {
@@ -410,17 +426,17 @@ impl HttpHeaders {
 
     /// Set header
     pub fn set(&mut self, name: &str, value: &str) {
-        self.headers.insert(name.to_lowercase(), value.to_string());
+        self.headers.insert(name.to_lowercase(), (name.to_string(), value.to_string()));
     }
 
     /// Get header
     pub fn get(&self, name: &str) -> Option<&String> {
-        self.headers.get(&name.to_lowercase())
+        self.headers.get(&name.to_lowercase()).map(|(_, value)| value)
     }
 
     /// Remove header
     pub fn remove(&mut self, name: &str) -> Option<String> {
-        self.headers.remove(&name.to_lowercase())
+        self.headers.remove(&name.to_lowercase()).map(|(_, value)| value)
     }
 
     /// Check if header exists
@@ -429,8 +445,8 @@ impl HttpHeaders {
     }
 
     /// Get all headers
-    pub fn iter(&self) -> std::collections::hash_map::Iter<String, String> {
-        self.headers.iter()
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.headers.values().map(|(name, value)| (name, value))
     }
 
     /// Get content length
@@ -598,6 +614,10 @@ impl HttpResponse {
     /// Set header
     pub fn header(mut self, name: &str, value: &str) -> Self {
         self.headers.set(name, value);
+        if let Some(length) = self.headers.content_length() {
+            self.content_length = Some(length);
+        }
+        self.chunked = self.headers.is_chunked();
         self
     }
 
@@ -639,13 +659,22 @@ impl HttpResponse {
 
     /// Parse response from bytes
     pub fn parse(data: &[u8]) -> Result<Self> {
-        let mut lines = data.split(|&b| b == b'\n');
+        // Split the head from the body at the first blank line
+        let (head, body) = match data.windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(pos) => (&data[..pos], Some(&data[pos + 4..])),
+            None => match data.windows(2).position(|w| w == b"\n\n") {
+                Some(pos) => (&data[..pos], Some(&data[pos + 2..])),
+                None => (data, None),
+            },
+        };
+        let mut lines = head.split(|&b| b == b'\n');
         
         // Parse status line
         let status_line = lines.next()
             .ok_or_else(|| Error::parsing("Missing status line".to_string()))?;
         let status_line = std::str::from_utf8(status_line)
-            .map_err(|e| Error::parsing(format!("Invalid status line: {}", e)))?;
+            .map_err(|e| Error::parsing(format!("Invalid status line: {}", e)))?
+            .trim_end_matches('\r');
         
         let parts: Vec<&str> = status_line.split_whitespace().collect();
         if parts.len() < 3 {
@@ -697,13 +726,7 @@ impl HttpResponse {
         response.status_text = status_text;
         
         // Parse headers
-        let mut body_start = 0;
-        for (i, line) in lines.enumerate() {
-            if line.is_empty() || (line.len() == 1 && line[0] == b'\r') {
-                body_start = i + 1;
-                break;
-            }
-            
+        for line in lines {
             let line = std::str::from_utf8(line)
                 .map_err(|e| Error::parsing(format!("Invalid header line: {}", e)))?;
             
@@ -715,8 +738,7 @@ impl HttpResponse {
         }
         
         // Parse body
-        if body_start > 0 {
-            let body_data = &data[body_start..];
+        if let Some(body_data) = body {
             response.body = body_data.to_vec();
             response.content_length = Some(response.body.len());
         }
@@ -975,7 +997,7 @@ impl HttpClient {
     }
 
     /// Parse URI to extract host and port
-    fn parse_uri(&self, uri: &str) -> Result<(String, u16)> {
+    pub(crate) fn parse_uri(&self, uri: &str) -> Result<(String, u16)> {
         if uri.starts_with("http://") {
             let uri = &uri[7..];
             if let Some(colon_pos) = uri.find(':') {
@@ -1080,18 +1102,18 @@ impl Http2Frame {
 
     /// Serialize frame
     pub fn serialize(&self) -> Vec<u8> {
-        let mut frame = Vec::new();
-        
         // Frame header (9 bytes)
-        frame.extend_from_slice(&(self.length >> 16) as u8);
-        frame.extend_from_slice(&(self.length >> 8) as u8);
-        frame.extend_from_slice(&(self.length) as u8);
-        frame.push(self.frame_type as u8);
-        frame.push(self.flags);
-        frame.extend_from_slice(&(self.stream_id >> 24) as u8);
-        frame.extend_from_slice(&(self.stream_id >> 16) as u8);
-        frame.extend_from_slice(&(self.stream_id >> 8) as u8);
-        frame.extend_from_slice(&(self.stream_id) as u8);
+        let mut frame = vec![
+            (self.length >> 16) as u8,
+            (self.length >> 8) as u8,
+            self.length as u8,
+            self.frame_type as u8,
+            self.flags,
+            (self.stream_id >> 24) as u8,
+            (self.stream_id >> 16) as u8,
+            (self.stream_id >> 8) as u8,
+            self.stream_id as u8,
+        ];
         
         // Frame payload
         frame.extend_from_slice(&self.payload);
@@ -1140,79 +1162,527 @@ impl Http2Frame {
     }
 }
 
+/// Overhead added to the length of name and value to get the size of a
+/// dynamic table entry (RFC 7541 section 4.1)
+const HPACK_ENTRY_OVERHEAD: usize = 32;
+
+/// HPACK static table (RFC 7541 Appendix A), where entry `i` has index `i + 1`
+const HPACK_STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// HPACK Huffman code (RFC 7541 Appendix B) of each byte, followed by EOS,
+/// as (code, length in bits)
+const HPACK_HUFFMAN_CODES: [(u32, u8); 257] = [
+    (0x1ff8, 13), (0x7fffd8, 23), (0xfffffe2, 28), (0xfffffe3, 28), (0xfffffe4, 28), (0xfffffe5, 28),
+    (0xfffffe6, 28), (0xfffffe7, 28), (0xfffffe8, 28), (0xffffea, 24), (0x3ffffffc, 30), (0xfffffe9, 28),
+    (0xfffffea, 28), (0x3ffffffd, 30), (0xfffffeb, 28), (0xfffffec, 28), (0xfffffed, 28), (0xfffffee, 28),
+    (0xfffffef, 28), (0xffffff0, 28), (0xffffff1, 28), (0xffffff2, 28), (0x3ffffffe, 30), (0xffffff3, 28),
+    (0xffffff4, 28), (0xffffff5, 28), (0xffffff6, 28), (0xffffff7, 28), (0xffffff8, 28), (0xffffff9, 28),
+    (0xffffffa, 28), (0xffffffb, 28), (0x14, 6), (0x3f8, 10), (0x3f9, 10), (0xffa, 12),
+    (0x1ff9, 13), (0x15, 6), (0xf8, 8), (0x7fa, 11), (0x3fa, 10), (0x3fb, 10),
+    (0xf9, 8), (0x7fb, 11), (0xfa, 8), (0x16, 6), (0x17, 6), (0x18, 6),
+    (0x0, 5), (0x1, 5), (0x2, 5), (0x19, 6), (0x1a, 6), (0x1b, 6),
+    (0x1c, 6), (0x1d, 6), (0x1e, 6), (0x1f, 6), (0x5c, 7), (0xfb, 8),
+    (0x7ffc, 15), (0x20, 6), (0xffb, 12), (0x3fc, 10), (0x1ffa, 13), (0x21, 6),
+    (0x5d, 7), (0x5e, 7), (0x5f, 7), (0x60, 7), (0x61, 7), (0x62, 7),
+    (0x63, 7), (0x64, 7), (0x65, 7), (0x66, 7), (0x67, 7), (0x68, 7),
+    (0x69, 7), (0x6a, 7), (0x6b, 7), (0x6c, 7), (0x6d, 7), (0x6e, 7),
+    (0x6f, 7), (0x70, 7), (0x71, 7), (0x72, 7), (0xfc, 8), (0x73, 7),
+    (0xfd, 8), (0x1ffb, 13), (0x7fff0, 19), (0x1ffc, 13), (0x3ffc, 14), (0x22, 6),
+    (0x7ffd, 15), (0x3, 5), (0x23, 6), (0x4, 5), (0x24, 6), (0x5, 5),
+    (0x25, 6), (0x26, 6), (0x27, 6), (0x6, 5), (0x74, 7), (0x75, 7),
+    (0x28, 6), (0x29, 6), (0x2a, 6), (0x7, 5), (0x2b, 6), (0x76, 7),
+    (0x2c, 6), (0x8, 5), (0x9, 5), (0x2d, 6), (0x77, 7), (0x78, 7),
+    (0x79, 7), (0x7a, 7), (0x7b, 7), (0x7ffe, 15), (0x7fc, 11), (0x3ffd, 14),
+    (0x1ffd, 13), (0xffffffc, 28), (0xfffe6, 20), (0x3fffd2, 22), (0xfffe7, 20), (0xfffe8, 20),
+    (0x3fffd3, 22), (0x3fffd4, 22), (0x3fffd5, 22), (0x7fffd9, 23), (0x3fffd6, 22), (0x7fffda, 23),
+    (0x7fffdb, 23), (0x7fffdc, 23), (0x7fffdd, 23), (0x7fffde, 23), (0xffffeb, 24), (0x7fffdf, 23),
+    (0xffffec, 24), (0xffffed, 24), (0x3fffd7, 22), (0x7fffe0, 23), (0xffffee, 24), (0x7fffe1, 23),
+    (0x7fffe2, 23), (0x7fffe3, 23), (0x7fffe4, 23), (0x1fffdc, 21), (0x3fffd8, 22), (0x7fffe5, 23),
+    (0x3fffd9, 22), (0x7fffe6, 23), (0x7fffe7, 23), (0xffffef, 24), (0x3fffda, 22), (0x1fffdd, 21),
+    (0xfffe9, 20), (0x3fffdb, 22), (0x3fffdc, 22), (0x7fffe8, 23), (0x7fffe9, 23), (0x1fffde, 21),
+    (0x7fffea, 23), (0x3fffdd, 22), (0x3fffde, 22), (0xfffff0, 24), (0x1fffdf, 21), (0x3fffdf, 22),
+    (0x7fffeb, 23), (0x7fffec, 23), (0x1fffe0, 21), (0x1fffe1, 21), (0x3fffe0, 22), (0x1fffe2, 21),
+    (0x7fffed, 23), (0x3fffe1, 22), (0x7fffee, 23), (0x7fffef, 23), (0xfffea, 20), (0x3fffe2, 22),
+    (0x3fffe3, 22), (0x3fffe4, 22), (0x7ffff0, 23), (0x3fffe5, 22), (0x3fffe6, 22), (0x7ffff1, 23),
+    (0x3ffffe0, 26), (0x3ffffe1, 26), (0xfffeb, 20), (0x7fff1, 19), (0x3fffe7, 22), (0x7ffff2, 23),
+    (0x3fffe8, 22), (0x1ffffec, 25), (0x3ffffe2, 26), (0x3ffffe3, 26), (0x3ffffe4, 26), (0x7ffffde, 27),
+    (0x7ffffdf, 27), (0x3ffffe5, 26), (0xfffff1, 24), (0x1ffffed, 25), (0x7fff2, 19), (0x1fffe3, 21),
+    (0x3ffffe6, 26), (0x7ffffe0, 27), (0x7ffffe1, 27), (0x3ffffe7, 26), (0x7ffffe2, 27), (0xfffff2, 24),
+    (0x1fffe4, 21), (0x1fffe5, 21), (0x3ffffe8, 26), (0x3ffffe9, 26), (0xffffffd, 28), (0x7ffffe3, 27),
+    (0x7ffffe4, 27), (0x7ffffe5, 27), (0xfffec, 20), (0xfffff3, 24), (0xfffed, 20), (0x1fffe6, 21),
+    (0x3fffe9, 22), (0x1fffe7, 21), (0x1fffe8, 21), (0x7ffff3, 23), (0x3fffea, 22), (0x3fffeb, 22),
+    (0x1ffffee, 25), (0x1ffffef, 25), (0xfffff4, 24), (0xfffff5, 24), (0x3ffffea, 26), (0x7ffff4, 23),
+    (0x3ffffeb, 26), (0x7ffffe6, 27), (0x3ffffec, 26), (0x3ffffed, 26), (0x7ffffe7, 27), (0x7ffffe8, 27),
+    (0x7ffffe9, 27), (0x7ffffea, 27), (0x7ffffeb, 27), (0xffffffe, 28), (0x7ffffec, 27), (0x7ffffed, 27),
+    (0x7ffffee, 27), (0x7ffffef, 27), (0x7fffff0, 27), (0x3ffffee, 26), (0x3fffffff, 30),
+];
+
+/// Symbol of the Huffman code that ends a string, which must never be decoded
+const HPACK_HUFFMAN_EOS: u16 = 256;
+
+/// Node of the Huffman decoding tree
+#[derive(Debug, Clone, Copy)]
+enum HuffmanNode {
+    /// Nodes reached by a 0 and a 1 bit, where 0 stands for no node yet
+    Branch([usize; 2]),
+    /// Decoded symbol
+    Leaf(u16),
+}
+
+/// Huffman decoding tree, rooted at node 0
+fn huffman_tree() -> &'static [HuffmanNode] {
+    static TREE: OnceLock<Vec<HuffmanNode>> = OnceLock::new();
+    TREE.get_or_init(|| {
+        let mut tree = vec![HuffmanNode::Branch([0, 0])];
+        for (symbol, &(code, length)) in HPACK_HUFFMAN_CODES.iter().enumerate() {
+            let mut node = 0;
+            for shift in (0..length).rev() {
+                let bit = ((code >> shift) & 1) as usize;
+                let next = match tree[node] {
+                    HuffmanNode::Branch(children) if children[bit] != 0 => children[bit],
+                    HuffmanNode::Branch(mut children) => {
+                        let next = tree.len();
+                        tree.push(match shift {
+                            0 => HuffmanNode::Leaf(symbol as u16),
+                            _ => HuffmanNode::Branch([0, 0]),
+                        });
+                        children[bit] = next;
+                        tree[node] = HuffmanNode::Branch(children);
+                        next
+                    }
+                    HuffmanNode::Leaf(_) => unreachable!("Huffman codes are prefix free"),
+                };
+                node = next;
+            }
+        }
+        tree
+    })
+}
+
+/// Length of a string once Huffman encoded, in bytes
+fn huffman_encoded_len(data: &[u8]) -> usize {
+    let bits: usize = data.iter().map(|&byte| HPACK_HUFFMAN_CODES[byte as usize].1 as usize).sum();
+    bits.div_ceil(8)
+}
+
+/// Huffman encode a string, padding the last byte with the most significant bits of EOS
+fn huffman_encode(data: &[u8], out: &mut Vec<u8>) {
+    let mut bits: u64 = 0;
+    let mut bit_count = 0;
+
+    for &byte in data {
+        let (code, length) = HPACK_HUFFMAN_CODES[byte as usize];
+        bits = (bits << length) | code as u64;
+        bit_count += length as u32;
+        while bit_count >= 8 {
+            bit_count -= 8;
+            out.push((bits >> bit_count) as u8);
+        }
+        bits &= (1 << bit_count) - 1;
+    }
+
+    if bit_count > 0 {
+        out.push(((bits << (8 - bit_count)) | (0xFF >> bit_count)) as u8);
+    }
+}
+
+/// Decode a Huffman encoded string
+fn huffman_decode(data: &[u8]) -> Result<Vec<u8>> {
+    let tree = huffman_tree();
+    let mut decoded = Vec::with_capacity(data.len() * 8 / 5);
+    let mut node = 0;
+    // Bits read since the last symbol, which may only be EOS padding
+    let mut padding_bits = 0;
+    let mut padding_is_ones = true;
+
+    for &byte in data {
+        for shift in (0..8).rev() {
+            let bit = ((byte >> shift) & 1) as usize;
+            let HuffmanNode::Branch(children) = tree[node] else {
+                unreachable!("decoding restarts at the root after each symbol");
+            };
+            node = children[bit];
+            padding_bits += 1;
+            padding_is_ones &= bit == 1;
+
+            if let HuffmanNode::Leaf(symbol) = tree[node] {
+                if symbol == HPACK_HUFFMAN_EOS {
+                    return Err(Error::parsing("Huffman encoded string contains EOS".to_string()));
+                }
+                decoded.push(symbol as u8);
+                node = 0;
+                padding_bits = 0;
+                padding_is_ones = true;
+            }
+        }
+    }
+
+    if padding_bits > 7 || !padding_is_ones {
+        return Err(Error::parsing("Invalid Huffman padding".to_string()));
+    }
+
+    Ok(decoded)
+}
+
+/// Encode an integer with an N-bit prefix (RFC 7541 section 5.1), setting `flags`
+/// in the bits of the first byte above the prefix
+fn encode_integer(value: usize, prefix_bits: u8, flags: u8, out: &mut Vec<u8>) {
+    let max_prefix = (1usize << prefix_bits) - 1;
+    if value < max_prefix {
+        out.push(flags | value as u8);
+        return;
+    }
+
+    out.push(flags | max_prefix as u8);
+    let mut value = value - max_prefix;
+    while value >= 0x80 {
+        out.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Decode an integer with an N-bit prefix (RFC 7541 section 5.1), advancing `pos` past it
+fn decode_integer(data: &[u8], pos: &mut usize, prefix_bits: u8) -> Result<usize> {
+    let max_prefix = (1usize << prefix_bits) - 1;
+    let first = *data.get(*pos).ok_or_else(|| Error::parsing("Truncated HPACK integer".to_string()))?;
+    *pos += 1;
+
+    let mut value = first as usize & max_prefix;
+    if value < max_prefix {
+        return Ok(value);
+    }
+
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos).ok_or_else(|| Error::parsing("Truncated HPACK integer".to_string()))?;
+        *pos += 1;
+        if shift > 28 {
+            return Err(Error::parsing("HPACK integer overflow".to_string()));
+        }
+        value += ((byte & 0x7F) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+/// Encode a string literal (RFC 7541 section 5.2), Huffman encoded when that is shorter
+fn encode_string(value: &str, out: &mut Vec<u8>) {
+    let huffman_len = huffman_encoded_len(value.as_bytes());
+    if huffman_len < value.len() {
+        encode_integer(huffman_len, 7, 0x80, out);
+        huffman_encode(value.as_bytes(), out);
+    } else {
+        encode_integer(value.len(), 7, 0x00, out);
+        out.extend_from_slice(value.as_bytes());
+    }
+}
+
+/// Decode a string literal (RFC 7541 section 5.2), advancing `pos` past it
+fn decode_string(data: &[u8], pos: &mut usize) -> Result<String> {
+    let huffman = data.get(*pos).is_some_and(|byte| byte & 0x80 != 0);
+    let length = decode_integer(data, pos, 7)?;
+    let end = pos.checked_add(length)
+        .filter(|&end| end <= data.len())
+        .ok_or_else(|| Error::parsing("Truncated HPACK string".to_string()))?;
+
+    let bytes = match huffman {
+        true => huffman_decode(&data[*pos..end])?,
+        false => data[*pos..end].to_vec(),
+    };
+    *pos = end;
+
+    String::from_utf8(bytes).map_err(|e| Error::parsing(format!("Invalid header string: {}", e)))
+}
+
+impl DynamicTable {
+    /// Create an empty dynamic table bounded by SETTINGS_HEADER_TABLE_SIZE
+    pub fn new(limit: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            size: 0,
+            max_size: limit,
+            limit,
+            size_update_pending: false,
+        }
+    }
+
+    /// Apply a new SETTINGS_HEADER_TABLE_SIZE, evicting entries that no longer fit
+    ///
+    /// The table takes the new limit as its maximum size, which the encoder
+    /// signals at the start of its next header block.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.max_size = limit;
+        self.size_update_pending = true;
+        self.evict(0);
+    }
+
+    /// Apply a dynamic table size update received from the peer
+    pub fn resize(&mut self, max_size: usize) -> Result<()> {
+        if max_size > self.limit {
+            return Err(Error::protocol(format!(
+                "Dynamic table size update to {} exceeds the limit of {}",
+                max_size, self.limit
+            )));
+        }
+        self.max_size = max_size;
+        self.evict(0);
+        Ok(())
+    }
+
+    /// Add a header field, evicting the oldest entries to make room
+    ///
+    /// An entry larger than the maximum size empties the table and is not added.
+    pub fn insert(&mut self, name: String, value: String) {
+        let entry_size = name.len() + value.len() + HPACK_ENTRY_OVERHEAD;
+        if entry_size > self.max_size {
+            self.entries.clear();
+            self.size = 0;
+            return;
+        }
+
+        self.evict(entry_size);
+        self.entries.push_front((name, value));
+        self.size += entry_size;
+    }
+
+    /// Get a header field by its position in the table, 0 being the newest
+    pub fn get(&self, position: usize) -> Option<&(String, String)> {
+        self.entries.get(position)
+    }
+
+    /// Number of header fields in the table
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the table has no header fields
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Size of the entries as defined by RFC 7541 section 4.1
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Maximum size of the entries
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Upper bound of the maximum size, from SETTINGS_HEADER_TABLE_SIZE
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Evict the oldest entries until `additional` bytes fit
+    fn evict(&mut self, additional: usize) {
+        while self.size + additional > self.max_size {
+            let Some((name, value)) = self.entries.pop_back() else {
+                break;
+            };
+            self.size -= name.len() + value.len() + HPACK_ENTRY_OVERHEAD;
+        }
+    }
+}
+
 impl HpackCodec {
     /// Create new HPACK codec
     pub fn new(max_table_size: usize) -> Self {
         Self {
-            dynamic_table: Vec::new(),
-            max_table_size,
-            current_table_size: 0,
+            encoder_table: DynamicTable::new(max_table_size),
+            decoder_table: DynamicTable::new(max_table_size),
         }
     }
 
-    /// Encode headers
-    pub fn encode(&mut self, headers: &HttpHeaders) -> Result<Vec<u8>> {
+    /// Encode a header block (RFC 7541 section 6), adding its fields to the dynamic table
+    ///
+    /// Names are lowercased as HTTP/2 requires. Credentials are sent as
+    /// never-indexed literals so intermediaries do not compress them either.
+    pub fn encode(headers: &[(String, String)], dynamic_table: &mut DynamicTable) -> Vec<u8> {
         let mut encoded = Vec::new();
-        
-        for (name, value) in headers.iter() {
-            // Simple literal encoding for now
-            encoded.extend_from_slice(&[0x40]); // Literal header field with incremental indexing
-            encoded.extend_from_slice(&(name.len() as u8));
-            encoded.extend_from_slice(name.as_bytes());
-            encoded.extend_from_slice(&(value.len() as u8));
-            encoded.extend_from_slice(value.as_bytes());
+
+        if dynamic_table.size_update_pending {
+            encode_integer(dynamic_table.max_size, 5, 0x20, &mut encoded);
+            dynamic_table.size_update_pending = false;
         }
-        
-        Ok(encoded)
+
+        for (name, value) in headers {
+            let name = name.to_ascii_lowercase();
+            let sensitive = name == "authorization" || name == "proxy-authorization";
+
+            let (name_index, matches_value) = match Self::find(&name, value, dynamic_table) {
+                Some((index, matches_value)) => (index, matches_value),
+                None => (0, false),
+            };
+
+            if matches_value && !sensitive {
+                // Indexed header field
+                encode_integer(name_index, 7, 0x80, &mut encoded);
+                continue;
+            }
+
+            if sensitive {
+                // Literal header field never indexed
+                encode_integer(name_index, 4, 0x10, &mut encoded);
+            } else {
+                // Literal header field with incremental indexing
+                encode_integer(name_index, 6, 0x40, &mut encoded);
+            }
+            if name_index == 0 {
+                encode_string(&name, &mut encoded);
+            }
+            encode_string(value, &mut encoded);
+
+            if !sensitive {
+                dynamic_table.insert(name, value.clone());
+            }
+        }
+
+        encoded
     }
 
-    /// Decode headers
-    pub fn decode(&mut self, data: &[u8]) -> Result<HttpHeaders> {
-        let mut headers = HttpHeaders::new();
-        let mut i = 0;
-        
-        while i < data.len() {
-            let byte = data[i];
-            i += 1;
-            
+    /// Decode a header block (RFC 7541 section 6), adding its indexed fields to the dynamic table
+    pub fn decode(data: &[u8], dynamic_table: &mut DynamicTable) -> Result<Vec<(String, String)>> {
+        let mut headers = Vec::new();
+        let mut pos = 0;
+
+        while pos < data.len() {
+            let byte = data[pos];
+
             if byte & 0x80 != 0 {
                 // Indexed header field
-                let index = byte & 0x7F;
-                // For now, just skip indexed headers
-                continue;
+                let index = decode_integer(data, &mut pos, 7)?;
+                headers.push(Self::entry(index, dynamic_table)?);
             } else if byte & 0x40 != 0 {
                 // Literal header field with incremental indexing
-                let name_len = data[i] as usize;
-                i += 1;
-                let name = std::str::from_utf8(&data[i..i + name_len])
-                    .map_err(|e| Error::parsing(format!("Invalid header name: {}", e)))?;
-                i += name_len;
-                
-                let value_len = data[i] as usize;
-                i += 1;
-                let value = std::str::from_utf8(&data[i..i + value_len])
-                    .map_err(|e| Error::parsing(format!("Invalid header value: {}", e)))?;
-                i += value_len;
-                
-                headers.set(name, value);
+                let (name, value) = Self::decode_literal(data, &mut pos, 6, dynamic_table)?;
+                dynamic_table.insert(name.clone(), value.clone());
+                headers.push((name, value));
+            } else if byte & 0x20 != 0 {
+                // Dynamic table size update, only allowed before the first field
+                if !headers.is_empty() {
+                    return Err(Error::protocol("Dynamic table size update after a header field".to_string()));
+                }
+                let max_size = decode_integer(data, &mut pos, 5)?;
+                dynamic_table.resize(max_size)?;
             } else {
-                // Literal header field without indexing
-                let name_len = data[i] as usize;
-                i += 1;
-                let name = std::str::from_utf8(&data[i..i + name_len])
-                    .map_err(|e| Error::parsing(format!("Invalid header name: {}", e)))?;
-                i += name_len;
-                
-                let value_len = data[i] as usize;
-                i += 1;
-                let value = std::str::from_utf8(&data[i..i + value_len])
-                    .map_err(|e| Error::parsing(format!("Invalid header value: {}", e)))?;
-                i += value_len;
-                
-                headers.set(name, value);
+                // Literal header field without indexing or never indexed
+                headers.push(Self::decode_literal(data, &mut pos, 4, dynamic_table)?);
             }
         }
-        
+
         Ok(headers)
     }
+
+    /// Find the index of a header field, and whether its value matches too
+    ///
+    /// Full matches are preferred over entries matching only the name.
+    fn find(name: &str, value: &str, dynamic_table: &DynamicTable) -> Option<(usize, bool)> {
+        let static_entries = HPACK_STATIC_TABLE.iter().map(|&(name, value)| (name, value));
+        let dynamic_entries = dynamic_table.entries.iter().map(|(name, value)| (name.as_str(), value.as_str()));
+
+        let mut name_match = None;
+        for (position, (entry_name, entry_value)) in static_entries.chain(dynamic_entries).enumerate() {
+            if entry_name != name {
+                continue;
+            }
+            if entry_value == value {
+                return Some((position + 1, true));
+            }
+            name_match.get_or_insert((position + 1, false));
+        }
+        name_match
+    }
+
+    /// Get the header field at an index of the static and dynamic tables
+    fn entry(index: usize, dynamic_table: &DynamicTable) -> Result<(String, String)> {
+        match index {
+            0 => Err(Error::parsing("HPACK index 0 is not used".to_string())),
+            1..=61 => {
+                let (name, value) = HPACK_STATIC_TABLE[index - 1];
+                Ok((name.to_string(), value.to_string()))
+            }
+            _ => dynamic_table.get(index - HPACK_STATIC_TABLE.len() - 1)
+                .cloned()
+                .ok_or_else(|| Error::parsing(format!("HPACK index {} is out of range", index))),
+        }
+    }
+
+    /// Decode a literal header field whose name index has an N-bit prefix
+    fn decode_literal(data: &[u8], pos: &mut usize, prefix_bits: u8, dynamic_table: &DynamicTable) -> Result<(String, String)> {
+        let name_index = decode_integer(data, pos, prefix_bits)?;
+        let name = match name_index {
+            0 => decode_string(data, pos)?,
+            _ => Self::entry(name_index, dynamic_table)?.0,
+        };
+        let value = decode_string(data, pos)?;
+        Ok((name, value))
+    }
 }
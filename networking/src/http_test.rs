@@ -4,10 +4,17 @@ mod tests {
     use crate::http::{
         HttpMethod, HttpVersion, HttpStatus, HttpHeaders, HttpRequest, HttpResponse,
         HttpConnection, HttpConnectionPool, HttpClient, Http2Settings, Http2Frame,
-        Http2FrameType, HpackCodec
+        Http2FrameType, Http2Stream, Http2StreamState, DynamicTable, HpackCodec
     };
     use std::time::Duration;
 
+    /// Open a loopback connection; the listener must outlive the stream
+    fn mock_stream() -> (std::net::TcpListener, std::net::TcpStream) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        (listener, stream)
+    }
+
     #[test]
     fn test_http_method_conversion() {
         assert_eq!(HttpMethod::GET.as_str(), "GET");
@@ -267,7 +274,7 @@ mod tests {
         assert_eq!(response.headers.get("content-type"), Some(&"text/html".to_string()));
         assert_eq!(response.headers.get("content-length"), Some(&"1234".to_string()));
         assert_eq!(response.body, b"<html>Hello</html>");
-        assert_eq!(response.content_length, Some(17));
+        assert_eq!(response.content_length, Some(18));
     }
 
     #[test]
@@ -484,55 +491,131 @@ mod tests {
         assert!(Http2Frame::parse(&unknown_type).is_err());
     }
 
+    fn headers(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+        fields.iter().map(|&(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
     #[test]
     fn test_hpack_codec_creation() {
         let codec = HpackCodec::new(4096);
         
-        assert_eq!(codec.max_table_size, 4096);
-        assert_eq!(codec.current_table_size, 0);
-        assert!(codec.dynamic_table.is_empty());
+        assert_eq!(codec.encoder_table.limit(), 4096);
+        assert_eq!(codec.encoder_table.size(), 0);
+        assert!(codec.encoder_table.is_empty());
+        assert!(codec.decoder_table.is_empty());
     }
 
     #[test]
-    fn test_hpack_codec_encoding() {
+    fn test_hpack_codec_round_trip() {
         let mut codec = HpackCodec::new(4096);
-        let mut headers = HttpHeaders::new();
-        
-        headers.set("Content-Type", "text/html");
-        headers.set("Content-Length", "1234");
-        
-        let encoded = codec.encode(&headers).unwrap();
-        
-        // Should contain the header data
-        assert!(!encoded.is_empty());
-        assert!(encoded.len() > 0);
+        let request = headers(&[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":path", "/search?q=matte"),
+            (":authority", "www.example.com"),
+            ("Accept-Encoding", "gzip, deflate"),
+            ("user-agent", "Matte/0.1 (X11; Linux x86_64)"),
+            ("authorization", "Bearer secret"),
+            ("x-empty", ""),
+            ("x-unicode", "caf\u{e9} \u{1f600}"),
+        ]);
+
+        let encoded = HpackCodec::encode(&request, &mut codec.encoder_table);
+        let decoded = HpackCodec::decode(&encoded, &mut codec.decoder_table).unwrap();
+
+        let expected: Vec<(String, String)> = request.iter()
+            .map(|(name, value)| (name.to_ascii_lowercase(), value.clone()))
+            .collect();
+        assert_eq!(decoded, expected);
+
+        // Credentials are never added to the dynamic tables
+        assert_eq!(codec.encoder_table.len(), 5);
+        assert_eq!(codec.decoder_table.len(), 5);
+        assert_eq!(codec.encoder_table.size(), codec.decoder_table.size());
     }
 
     #[test]
-    fn test_hpack_codec_decoding() {
-        let mut codec = HpackCodec::new(4096);
-        
-        // Simple literal encoding
+    fn test_hpack_huffman_decoding() {
+        let mut table = DynamicTable::new(4096);
+
+        // RFC 7541 C.4.1
         let encoded = [
-            0x40, // Literal header field with incremental indexing
-            0x0C, // Name length: 12
-            0x43, 0x6F, 0x6E, 0x74, 0x65, 0x6E, 0x74, 0x2D, 0x54, 0x79, 0x70, 0x65, // "Content-Type"
-            0x09, // Value length: 9
-            0x74, 0x65, 0x78, 0x74, 0x2F, 0x68, 0x74, 0x6D, 0x6C, // "text/html"
+            0x82, 0x86, 0x84, 0x41, 0x8c, 0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff,
         ];
-        
-        let headers = codec.decode(&encoded).unwrap();
-        
-        assert_eq!(headers.get("content-type"), Some(&"text/html".to_string()));
+        let decoded = HpackCodec::decode(&encoded, &mut table).unwrap();
+
+        assert_eq!(decoded, headers(&[
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "www.example.com"),
+        ]));
+        assert_eq!(table.size(), 57);
+
+        // Encoding the same block gives the bytes of the RFC
+        let mut encoder_table = DynamicTable::new(4096);
+        assert_eq!(HpackCodec::encode(&decoded, &mut encoder_table), encoded);
     }
 
     #[test]
-    fn test_hpack_codec_decode_invalid() {
+    fn test_hpack_dynamic_table_growth() {
         let mut codec = HpackCodec::new(4096);
+        let first = headers(&[
+            (":method", "GET"),
+            (":path", "/"),
+            (":authority", "www.example.com"),
+            ("cookie", "session=abc123"),
+        ]);
+        let second = headers(&[
+            (":method", "GET"),
+            (":path", "/style.css"),
+            (":authority", "www.example.com"),
+            ("cookie", "session=abc123"),
+        ]);
+
+        let first_encoded = HpackCodec::encode(&first, &mut codec.encoder_table);
+        assert_eq!(HpackCodec::decode(&first_encoded, &mut codec.decoder_table).unwrap(), first);
+        assert_eq!(codec.decoder_table.len(), 2);
+
+        // Repeated fields become one-byte references into the dynamic table
+        let second_encoded = HpackCodec::encode(&second, &mut codec.encoder_table);
+        assert!(second_encoded.len() < first_encoded.len());
+        assert_eq!(HpackCodec::decode(&second_encoded, &mut codec.decoder_table).unwrap(), second);
+        assert_eq!(codec.decoder_table.len(), 3);
+        assert_eq!(codec.decoder_table.get(0), Some(&(":path".to_string(), "/style.css".to_string())));
+
+        // Entries are evicted oldest first once the table is full
+        let mut table = DynamicTable::new(100);
+        table.insert("a".to_string(), "1".repeat(20));
+        table.insert("b".to_string(), "2".repeat(20));
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.get(0).unwrap().0, "b");
+        assert_eq!(table.size(), 53);
+
+        // Lowering SETTINGS_HEADER_TABLE_SIZE evicts entries and is signalled to the peer
+        codec.encoder_table.set_limit(64);
+        assert_eq!(codec.encoder_table.len(), 1);
+        let encoded = HpackCodec::encode(&headers(&[("accept", "*/*")]), &mut codec.encoder_table);
+        assert_eq!(encoded[0], 0x3f);
+        codec.decoder_table.set_limit(64);
+        assert_eq!(HpackCodec::decode(&encoded, &mut codec.decoder_table).unwrap(), headers(&[("accept", "*/*")]));
+        assert_eq!(codec.decoder_table.max_size(), 64);
+    }
+
+    #[test]
+    fn test_hpack_codec_decode_invalid() {
+        let mut table = DynamicTable::new(4096);
         
-        // Invalid encoding (too short)
-        let invalid_data = [0x40, 0x0C];
-        assert!(codec.decode(&invalid_data).is_err());
+        // Truncated string
+        assert!(HpackCodec::decode(&[0x40, 0x0C], &mut table).is_err());
+        // Index 0 and indexes past the dynamic table
+        assert!(HpackCodec::decode(&[0x80], &mut table).is_err());
+        assert!(HpackCodec::decode(&[0xBE], &mut table).is_err());
+        // Size update above SETTINGS_HEADER_TABLE_SIZE
+        assert!(HpackCodec::decode(&[0x3F, 0xE2, 0x1F], &mut table).is_err());
+        // Huffman padding longer than 7 bits, and padding with zeros
+        assert!(HpackCodec::decode(&[0x00, 0x81, 0xFF, 0x00], &mut table).is_err());
+        assert!(HpackCodec::decode(&[0x00, 0x81, 0x00, 0x00], &mut table).is_err());
     }
 
     #[tokio::test]
@@ -558,7 +641,7 @@ mod tests {
         );
         
         // Create a mock connection (this would normally come from get_connection)
-        let mock_stream = std::net::TcpStream::connect("127.0.0.1:0").unwrap();
+        let (_listener, mock_stream) = mock_stream();
         let connection = HttpConnection::new(
             mock_stream,
             "127.0.0.1:80".parse().unwrap(),
@@ -580,7 +663,7 @@ mod tests {
         );
         
         // Create and return a connection
-        let mock_stream = std::net::TcpStream::connect("127.0.0.1:0").unwrap();
+        let (_listener, mock_stream) = mock_stream();
         let connection = HttpConnection::new(
             mock_stream,
             "127.0.0.1:80".parse().unwrap(),
@@ -601,7 +684,7 @@ mod tests {
 
     #[test]
     fn test_http_connection_expiration() {
-        let mock_stream = std::net::TcpStream::connect("127.0.0.1:0").unwrap();
+        let (_listener, mock_stream) = mock_stream();
         let connection = HttpConnection::new(
             mock_stream,
             "127.0.0.1:80".parse().unwrap(),
@@ -612,12 +695,13 @@ mod tests {
         assert!(!connection.is_expired(Duration::from_secs(30)));
         
         // Connection should be expired after a long time
+        std::thread::sleep(Duration::from_millis(5));
         assert!(connection.is_expired(Duration::from_millis(1)));
     }
 
     #[test]
     fn test_http_connection_id_management() {
-        let mock_stream = std::net::TcpStream::connect("127.0.0.1:0").unwrap();
+        let (_listener, mock_stream) = mock_stream();
         let mut connection = HttpConnection::new(
             mock_stream,
             "127.0.0.1:80".parse().unwrap(),
@@ -643,14 +727,13 @@ mod tests {
         assert!(!request_data.is_empty());
         
         // Create response
-        let mut response = HttpResponse::new(HttpVersion::Http1_1, HttpStatus::Ok);
-        response.headers.set("Content-Type", "application/json");
-        response.headers.set("Content-Length", "25");
-        response.body = b"{\"status\":\"success\"}".to_vec();
+        let response = HttpResponse::new(HttpVersion::Http1_1, HttpStatus::Ok)
+            .header("Content-Type", "application/json")
+            .body(b"{\"status\":\"success\"}".to_vec());
         
         // Verify response
         assert!(response.is_success());
-        assert_eq!(response.content_length, Some(25));
+        assert_eq!(response.content_length, Some(20));
         assert_eq!(response.headers.get("content-type"), Some(&"application/json".to_string()));
     }
 
@@ -669,14 +752,14 @@ mod tests {
 
     #[test]
     fn test_http_chunked_encoding() {
-        let mut response = HttpResponse::new(HttpVersion::Http1_1, HttpStatus::Ok);
-        response.headers.set("Transfer-Encoding", "chunked");
+        let response = HttpResponse::new(HttpVersion::Http1_1, HttpStatus::Ok)
+            .header("Transfer-Encoding", "chunked");
         
         assert!(response.headers.is_chunked());
         assert!(response.chunked);
         
-        let normal_response = HttpResponse::new(HttpVersion::Http1_1, HttpStatus::Ok);
-        response.headers.set("Transfer-Encoding", "gzip");
+        let mut normal_response = HttpResponse::new(HttpVersion::Http1_1, HttpStatus::Ok);
+        normal_response.headers.set("Transfer-Encoding", "gzip");
         
         assert!(!normal_response.headers.is_chunked());
     }
@@ -688,7 +771,7 @@ mod tests {
         
         assert!(response.headers.is_keep_alive());
         
-        let close_response = HttpResponse::new(HttpVersion::Http1_1, HttpStatus::Ok);
+        let mut close_response = HttpResponse::new(HttpVersion::Http1_1, HttpStatus::Ok);
         close_response.headers.set("Connection", "close");
         
         assert!(!close_response.headers.is_keep_alive());
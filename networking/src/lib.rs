@@ -9,7 +9,7 @@ pub use http::{
     HttpMethod, HttpVersion, HttpStatus, HttpHeaders, HttpRequest, HttpResponse,
     HttpConnection, HttpConnectionPool, ConnectionPoolStats, HttpClient,
     Http2Settings, Http2FrameType, Http2Frame, Http2Stream, Http2StreamState,
-    Http2Connection, HpackCodec,
+    Http2Connection, DynamicTable, HpackCodec,
 };
 pub use tls::{
     TlsVersion, TlsCipherSuite, TlsSignatureAlgorithm, TlsCertificate, CertificateValidationResult,
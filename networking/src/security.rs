@@ -315,8 +315,8 @@ impl MixedContentPolicy {
             return false;
         }
 
-        // If source is insecure and target is secure, this is mixed content
-        if !source_secure && target_secure {
+        // A secure page loading an insecure resource is mixed content
+        if source_secure && !target_secure {
             return self.blocked_content_types.contains(&content_type);
         }
 
@@ -371,22 +371,14 @@ impl CorbPolicy {
             return false;
         }
 
-        // Check if origin is allowed
-        if !self.allowed_origins.is_empty() && !self.allowed_origins.contains(&origin.to_string()) {
-            return true;
-        }
-
-        // Check if MIME type is blocked
-        if self.blocked_mime_types.contains(&mime_type.to_string()) {
-            return true;
-        }
-
-        // Check if content type is blocked
-        if self.blocked_content_types.contains(&content_type) {
-            return true;
+        // Same-origin and explicitly allowed responses are never blocked
+        if SecurityUtils::is_same_origin(origin, url) || self.allowed_origins.contains(&origin.to_string()) {
+            return false;
         }
 
-        false
+        // Block protected MIME types loaded into a blockable context
+        self.blocked_mime_types.contains(&mime_type.to_string())
+            && self.blocked_content_types.contains(&content_type)
     }
 }
 
@@ -732,7 +724,10 @@ impl SecurityUtils {
 
     /// Check if origins are same
     pub fn is_same_origin(origin1: &str, origin2: &str) -> bool {
-        origin1 == origin2
+        match (Self::extract_origin(origin1), Self::extract_origin(origin2)) {
+            (Ok(origin1), Ok(origin2)) => origin1 == origin2,
+            _ => origin1 == origin2,
+        }
     }
 
     /// Check if origins are cross-origin
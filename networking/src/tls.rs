@@ -282,7 +282,7 @@ pub struct TlsServer {
 /// OCSP responder
 pub struct OcspResponder {
     /// OCSP responder URL
-    url: String,
+    pub(crate) url: String,
     /// OCSP responder certificate
     certificate: TlsCertificate,
     /// OCSP response cache
@@ -422,11 +422,12 @@ impl TlsCertificate {
             if san == domain {
                 return true;
             }
-            // Check wildcard certificates
-            if san.starts_with("*.") {
-                let wildcard_domain = &san[2..];
-                if domain.ends_with(wildcard_domain) {
-                    return true;
+            // Check wildcard certificates, which cover exactly one label
+            if let Some(wildcard_domain) = san.strip_prefix("*.") {
+                if let Some(label) = domain.strip_suffix(wildcard_domain).and_then(|d| d.strip_suffix('.')) {
+                    if !label.is_empty() && !label.contains('.') {
+                        return true;
+                    }
                 }
             }
         }
@@ -667,7 +668,7 @@ impl TlsConnection {
             TlsCipherSuite::TLS_AES_256_GCM_SHA384.value(),
             TlsCipherSuite::TLS_CHACHA20_POLY1305_SHA256.value(),
         ];
-        client_hello.extend_from_slice(&(cipher_suites.len() * 2).to_be_bytes());
+        client_hello.extend_from_slice(&((cipher_suites.len() * 2) as u16).to_be_bytes());
         for suite in cipher_suites {
             client_hello.extend_from_slice(&suite.to_be_bytes());
         }
@@ -680,31 +681,31 @@ impl TlsConnection {
         let mut extensions = Vec::new();
         
         // Supported groups extension
-        let supported_groups = vec![0x0017, 0x0018, 0x0019]; // secp256r1, secp384r1, secp521r1
-        extensions.extend_from_slice(&0x000A.to_be_bytes()); // Extension type: supported_groups
-        extensions.extend_from_slice(&(supported_groups.len() * 2 + 2).to_be_bytes());
-        extensions.extend_from_slice(&(supported_groups.len() * 2).to_be_bytes());
+        let supported_groups = vec![0x0017u16, 0x0018, 0x0019]; // secp256r1, secp384r1, secp521r1
+        extensions.extend_from_slice(&0x000Au16.to_be_bytes()); // Extension type: supported_groups
+        extensions.extend_from_slice(&((supported_groups.len() * 2 + 2) as u16).to_be_bytes());
+        extensions.extend_from_slice(&((supported_groups.len() * 2) as u16).to_be_bytes());
         for group in supported_groups {
             extensions.extend_from_slice(&group.to_be_bytes());
         }
         
         // Signature algorithms extension
         let sig_algs = vec![
-            0x0403, // ecdsa_secp256r1_sha256
+            0x0403u16, // ecdsa_secp256r1_sha256
             0x0503, // ecdsa_secp384r1_sha384
             0x0804, // rsa_pss_rsae_sha256
             0x0805, // rsa_pss_rsae_sha384
             0x0807, // ed25519
         ];
-        extensions.extend_from_slice(&0x000D.to_be_bytes()); // Extension type: signature_algorithms
-        extensions.extend_from_slice(&(sig_algs.len() * 2 + 2).to_be_bytes());
-        extensions.extend_from_slice(&(sig_algs.len() * 2).to_be_bytes());
+        extensions.extend_from_slice(&0x000Du16.to_be_bytes()); // Extension type: signature_algorithms
+        extensions.extend_from_slice(&((sig_algs.len() * 2 + 2) as u16).to_be_bytes());
+        extensions.extend_from_slice(&((sig_algs.len() * 2) as u16).to_be_bytes());
         for alg in sig_algs {
             extensions.extend_from_slice(&alg.to_be_bytes());
         }
         
         // Add extensions to client hello
-        client_hello.extend_from_slice(&extensions.len().to_be_bytes());
+        client_hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
         client_hello.extend_from_slice(&extensions);
         
         Ok(client_hello)
@@ -719,6 +720,9 @@ impl TlsConnection {
         // Parse handshake message
         let msg_type = data[0];
         let length = u32::from_be_bytes([0, data[1], data[2], data[3]]) as usize;
+        if data.len() < 4 + length {
+            return Err(Error::protocol("Truncated handshake message".to_string()));
+        }
         
         match msg_type {
             0x02 => { // ServerHello
@@ -743,7 +747,7 @@ impl TlsConnection {
 
     /// Process ServerHello message
     fn process_server_hello(&mut self, data: &[u8]) -> Result<()> {
-        if data.len() < 34 {
+        if data.len() < 35 {
             return Err(Error::protocol("Invalid ServerHello message".to_string()));
         }
         
@@ -751,8 +755,14 @@ impl TlsConnection {
         let version = u16::from_be_bytes([data[0], data[1]]);
         self.version = TlsVersion::from_version_number(version);
         
+        // Skip random (32 bytes) and session ID
+        let cipher_offset = 35 + data[34] as usize;
+        if data.len() < cipher_offset + 2 {
+            return Err(Error::protocol("Invalid ServerHello message".to_string()));
+        }
+        
         // Parse cipher suite
-        let cipher_suite = u16::from_be_bytes([data[34], data[35]]);
+        let cipher_suite = u16::from_be_bytes([data[cipher_offset], data[cipher_offset + 1]]);
         self.cipher_suite = TlsCipherSuite::from_value(cipher_suite);
         
         Ok(())
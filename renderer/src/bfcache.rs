@@ -0,0 +1,147 @@
+//! Back-forward cache for renderer processes
+//!
+//! When a page is navigated away from, its renderer state is frozen into a
+//! snapshot so that a later back/forward navigation can restore it without
+//! running the full load pipeline.
+
+use crate::js_vm::FrozenJsHeap;
+use common::TabId;
+use dom::Document;
+use std::collections::{HashMap, VecDeque};
+use tracing::debug;
+
+/// Key identifying a cached page
+pub type BfCacheKey = (TabId, String);
+
+/// Frozen renderer state for a page
+#[derive(Debug)]
+pub struct RendererSnapshot {
+    /// Tab the page was loaded in
+    pub tab_id: TabId,
+
+    /// Page URL
+    pub url: String,
+
+    /// Frozen DOM
+    pub document: Option<Document>,
+
+    /// JavaScript heap, with its functions, event listeners and timers
+    pub js_heap: FrozenJsHeap,
+
+    /// Time the snapshot was taken
+    pub created_at: std::time::SystemTime,
+}
+
+/// Reason a page cannot enter the back-forward cache
+#[derive(Debug, Clone, PartialEq)]
+pub enum BfCacheIneligibility {
+    /// The main resource was served with `Cache-Control: no-store`
+    NoStore,
+
+    /// The page is controlled by an active service worker
+    ActiveServiceWorker,
+
+    /// The cache is disabled
+    Disabled,
+}
+
+/// Back-forward cache
+pub struct BackForwardCache {
+    /// Cached snapshots
+    entries: HashMap<BfCacheKey, RendererSnapshot>,
+
+    /// Insertion order, oldest first
+    order: VecDeque<BfCacheKey>,
+
+    /// Maximum number of cached pages
+    capacity: usize,
+}
+
+impl BackForwardCache {
+    /// Create a new back-forward cache
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Check whether a page may be cached
+    pub fn check_eligibility(
+        &self,
+        response_headers: &HashMap<String, String>,
+        active_service_workers: usize,
+    ) -> std::result::Result<(), BfCacheIneligibility> {
+        if self.capacity == 0 {
+            return Err(BfCacheIneligibility::Disabled);
+        }
+
+        let no_store = response_headers.iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("cache-control"))
+            .any(|(_, value)| value.split(',').any(|d| d.trim().eq_ignore_ascii_case("no-store")));
+        if no_store {
+            return Err(BfCacheIneligibility::NoStore);
+        }
+
+        if active_service_workers > 0 {
+            return Err(BfCacheIneligibility::ActiveServiceWorker);
+        }
+
+        Ok(())
+    }
+
+    /// Store a snapshot, evicting the oldest entries when full
+    pub fn insert(&mut self, snapshot: RendererSnapshot) {
+        let key = (snapshot.tab_id, snapshot.url.clone());
+
+        self.order.retain(|k| k != &key);
+        self.order.push_back(key.clone());
+        self.entries.insert(key, snapshot);
+
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                debug!("Evicting {} from back-forward cache", evicted.1);
+                self.entries.remove(&evicted);
+            }
+        }
+    }
+
+    /// Take a snapshot out of the cache
+    pub fn take(&mut self, tab_id: TabId, url: &str) -> Option<RendererSnapshot> {
+        let key = (tab_id, url.to_string());
+        self.order.retain(|k| k != &key);
+        self.entries.remove(&key)
+    }
+
+    /// Check if a page is cached
+    pub fn contains(&self, tab_id: TabId, url: &str) -> bool {
+        self.entries.contains_key(&(tab_id, url.to_string()))
+    }
+
+    /// Drop all snapshots for a tab
+    pub fn evict_tab(&mut self, tab_id: TabId) {
+        self.order.retain(|(t, _)| *t != tab_id);
+        self.entries.retain(|(t, _), _| *t != tab_id);
+    }
+
+    /// Change the cache capacity
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.entries.remove(&evicted);
+            }
+        }
+    }
+
+    /// Number of cached pages
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
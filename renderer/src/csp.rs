@@ -0,0 +1,373 @@
+//! Content Security Policy parsing and matching
+
+use serde::{Deserialize, Serialize};
+
+use crate::sri::SriAlgorithm;
+
+/// CSP fetch directives enforced by the renderer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CspDirective {
+    DefaultSrc,
+    ScriptSrc,
+    StyleSrc,
+    ImgSrc,
+    ConnectSrc,
+    FontSrc,
+    FrameSrc,
+    MediaSrc,
+    ObjectSrc,
+}
+
+impl CspDirective {
+    /// Directive name as written in a policy
+    pub fn name(&self) -> &'static str {
+        match self {
+            CspDirective::DefaultSrc => "default-src",
+            CspDirective::ScriptSrc => "script-src",
+            CspDirective::StyleSrc => "style-src",
+            CspDirective::ImgSrc => "img-src",
+            CspDirective::ConnectSrc => "connect-src",
+            CspDirective::FontSrc => "font-src",
+            CspDirective::FrameSrc => "frame-src",
+            CspDirective::MediaSrc => "media-src",
+            CspDirective::ObjectSrc => "object-src",
+        }
+    }
+
+    /// Parse a directive name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "default-src" => Some(CspDirective::DefaultSrc),
+            "script-src" => Some(CspDirective::ScriptSrc),
+            "style-src" => Some(CspDirective::StyleSrc),
+            "img-src" => Some(CspDirective::ImgSrc),
+            "connect-src" => Some(CspDirective::ConnectSrc),
+            "font-src" => Some(CspDirective::FontSrc),
+            "frame-src" => Some(CspDirective::FrameSrc),
+            "media-src" => Some(CspDirective::MediaSrc),
+            "object-src" => Some(CspDirective::ObjectSrc),
+            _ => None,
+        }
+    }
+}
+
+/// Sub-resource types subject to CSP checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CspResourceType {
+    Script,
+    Style,
+    Image,
+    Connect,
+    Font,
+    Frame,
+    Media,
+    Object,
+}
+
+impl CspResourceType {
+    /// Directive governing this resource type
+    pub fn directive(&self) -> CspDirective {
+        match self {
+            CspResourceType::Script => CspDirective::ScriptSrc,
+            CspResourceType::Style => CspDirective::StyleSrc,
+            CspResourceType::Image => CspDirective::ImgSrc,
+            CspResourceType::Connect => CspDirective::ConnectSrc,
+            CspResourceType::Font => CspDirective::FontSrc,
+            CspResourceType::Frame => CspDirective::FrameSrc,
+            CspResourceType::Media => CspDirective::MediaSrc,
+            CspResourceType::Object => CspDirective::ObjectSrc,
+        }
+    }
+}
+
+/// Result of a CSP check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CspResult {
+    /// The load is allowed
+    Allowed,
+
+    /// The load is blocked by the given directive
+    Blocked(CspDirective),
+}
+
+impl CspResult {
+    /// Check if the load is allowed
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, CspResult::Allowed)
+    }
+}
+
+/// A parsed Content Security Policy
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CspPolicy {
+    pub default_src: Option<Vec<String>>,
+    pub script_src: Option<Vec<String>>,
+    pub style_src: Option<Vec<String>>,
+    pub img_src: Option<Vec<String>>,
+    pub connect_src: Option<Vec<String>>,
+    pub font_src: Option<Vec<String>>,
+    pub frame_src: Option<Vec<String>>,
+    pub media_src: Option<Vec<String>>,
+    pub object_src: Option<Vec<String>>,
+
+    /// Endpoint violation reports are sent to
+    pub report_uri: Option<String>,
+
+    /// Policy text as delivered
+    pub source: String,
+}
+
+impl CspPolicy {
+    /// Parse a single serialized policy
+    pub fn parse(policy: &str) -> Self {
+        let mut parsed = CspPolicy {
+            source: policy.trim().to_string(),
+            ..Default::default()
+        };
+
+        for directive in policy.split(';') {
+            let mut tokens = directive.split_ascii_whitespace();
+            let Some(name) = tokens.next() else { continue };
+            let values: Vec<String> = tokens.map(str::to_string).collect();
+
+            if name.eq_ignore_ascii_case("report-uri") {
+                if parsed.report_uri.is_none() {
+                    parsed.report_uri = values.into_iter().next();
+                }
+                continue;
+            }
+
+            // The first occurrence of a directive wins
+            if let Some(kind) = CspDirective::from_name(name) {
+                let slot = parsed.directive_mut(kind);
+                if slot.is_none() {
+                    *slot = Some(values);
+                }
+            }
+        }
+
+        parsed
+    }
+
+    /// Parse a `Content-Security-Policy` header, which may hold several policies
+    pub fn parse_header(header: &str) -> Vec<Self> {
+        header.split(',')
+            .filter(|p| !p.trim().is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+
+    /// Get the source list of a directive
+    pub fn directive(&self, directive: CspDirective) -> Option<&Vec<String>> {
+        match directive {
+            CspDirective::DefaultSrc => self.default_src.as_ref(),
+            CspDirective::ScriptSrc => self.script_src.as_ref(),
+            CspDirective::StyleSrc => self.style_src.as_ref(),
+            CspDirective::ImgSrc => self.img_src.as_ref(),
+            CspDirective::ConnectSrc => self.connect_src.as_ref(),
+            CspDirective::FontSrc => self.font_src.as_ref(),
+            CspDirective::FrameSrc => self.frame_src.as_ref(),
+            CspDirective::MediaSrc => self.media_src.as_ref(),
+            CspDirective::ObjectSrc => self.object_src.as_ref(),
+        }
+    }
+
+    fn directive_mut(&mut self, directive: CspDirective) -> &mut Option<Vec<String>> {
+        match directive {
+            CspDirective::DefaultSrc => &mut self.default_src,
+            CspDirective::ScriptSrc => &mut self.script_src,
+            CspDirective::StyleSrc => &mut self.style_src,
+            CspDirective::ImgSrc => &mut self.img_src,
+            CspDirective::ConnectSrc => &mut self.connect_src,
+            CspDirective::FontSrc => &mut self.font_src,
+            CspDirective::FrameSrc => &mut self.frame_src,
+            CspDirective::MediaSrc => &mut self.media_src,
+            CspDirective::ObjectSrc => &mut self.object_src,
+        }
+    }
+
+    /// Resolve the directive that applies to a request, falling back to `default-src`
+    pub fn effective_directive(&self, directive: CspDirective) -> Option<(CspDirective, &Vec<String>)> {
+        self.directive(directive)
+            .map(|sources| (directive, sources))
+            .or_else(|| self.default_src.as_ref().map(|sources| (CspDirective::DefaultSrc, sources)))
+    }
+
+    /// Check a sub-resource URL against the policy
+    pub fn check_url(&self, self_origin: &url::Url, resource_url: &str, resource_type: CspResourceType) -> CspResult {
+        let Some((directive, sources)) = self.effective_directive(resource_type.directive()) else {
+            return CspResult::Allowed;
+        };
+
+        let allowed = url::Url::parse(resource_url)
+            .map(|url| sources.iter().any(|source| source_matches_url(source, &url, self_origin)))
+            .unwrap_or(false);
+
+        if allowed {
+            CspResult::Allowed
+        } else {
+            CspResult::Blocked(directive)
+        }
+    }
+
+    /// Check an inline `<script>` or `<style>` block against the policy
+    pub fn check_inline(&self, resource_type: CspResourceType, content: &str, nonce: Option<&str>) -> CspResult {
+        let Some((directive, sources)) = self.effective_directive(resource_type.directive()) else {
+            return CspResult::Allowed;
+        };
+
+        let has_hash_or_nonce = sources.iter().any(|s| {
+            let s = s.to_ascii_lowercase();
+            s.starts_with("'nonce-") || s.starts_with("'sha256-") || s.starts_with("'sha384-") || s.starts_with("'sha512-")
+        });
+
+        let nonce_matches = nonce.is_some_and(|nonce| {
+            !nonce.is_empty() && sources.iter().any(|s| s.strip_prefix("'nonce-").and_then(|s| s.strip_suffix('\'')) == Some(nonce))
+        });
+
+        let hash_matches = sources.iter().any(|s| hash_source_matches(s, content));
+
+        // 'unsafe-inline' is ignored when a hash or nonce is present
+        let unsafe_inline = !has_hash_or_nonce && sources.iter().any(|s| s.eq_ignore_ascii_case("'unsafe-inline'"));
+
+        if nonce_matches || hash_matches || unsafe_inline {
+            CspResult::Allowed
+        } else {
+            CspResult::Blocked(directive)
+        }
+    }
+}
+
+/// Check a single source expression against a URL
+fn source_matches_url(source: &str, url: &url::Url, self_origin: &url::Url) -> bool {
+    let source_lower = source.to_ascii_lowercase();
+
+    match source_lower.as_str() {
+        "'none'" => return false,
+        "'self'" => {
+            let same_scheme = url.scheme() == self_origin.scheme()
+                || (self_origin.scheme() == "http" && url.scheme() == "https");
+            return same_scheme
+                && url.host_str() == self_origin.host_str()
+                && (url.port_or_known_default() == self_origin.port_or_known_default()
+                    || url.scheme() != self_origin.scheme());
+        }
+        "*" => return matches!(url.scheme(), "http" | "https" | "ws" | "wss"),
+        _ => {}
+    }
+
+    // Keyword sources never match URLs
+    if source_lower.starts_with('\'') {
+        return false;
+    }
+
+    // Scheme source, e.g. `https:` or `data:`
+    if let Some(scheme) = source_lower.strip_suffix(':') {
+        if !scheme.contains('/') {
+            return url.scheme() == scheme || (scheme == "http" && url.scheme() == "https");
+        }
+    }
+
+    host_source_matches(&source_lower, url)
+}
+
+/// Match a host source (`[scheme://]host[:port][/path]`)
+fn host_source_matches(source: &str, url: &url::Url) -> bool {
+    let (scheme, rest) = match source.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, source),
+    };
+
+    match scheme {
+        Some(scheme) => {
+            if url.scheme() != scheme && !(scheme == "http" && url.scheme() == "https") {
+                return false;
+            }
+        }
+        None => {
+            if !matches!(url.scheme(), "http" | "https" | "ws" | "wss") {
+                return false;
+            }
+        }
+    }
+
+    let (host_port, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], Some(&rest[index..])),
+        None => (rest, None),
+    };
+    let (host, port) = match host_port.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (host_port, None),
+    };
+
+    let Some(url_host) = url.host_str() else { return false };
+    let host_matches = match host.strip_prefix("*.") {
+        Some(suffix) => url_host.ends_with(&format!(".{}", suffix)),
+        None => url_host == host,
+    };
+    if !host_matches {
+        return false;
+    }
+
+    let port_matches = match port {
+        Some("*") => true,
+        Some(port) => port.parse::<u16>().ok() == url.port_or_known_default(),
+        None => url.port().is_none(),
+    };
+    if !port_matches {
+        return false;
+    }
+
+    match path {
+        Some(path) if path.ends_with('/') => url.path().starts_with(path),
+        Some(path) => url.path() == path,
+        None => true,
+    }
+}
+
+/// Check a `'sha*-…'` source against inline content
+fn hash_source_matches(source: &str, content: &str) -> bool {
+    let Some(inner) = source.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) else {
+        return false;
+    };
+    let Some((algorithm, expected)) = inner.split_once('-') else {
+        return false;
+    };
+
+    SriAlgorithm::from_name(algorithm)
+        .is_some_and(|algorithm| algorithm.digest_base64(content.as_bytes()) == expected)
+}
+
+/// A CSP violation report in the `application/csp-report` format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CspViolationReport {
+    /// URL of the document the violation occurred in
+    pub document_uri: String,
+
+    /// URL of the blocked resource, or `inline`
+    pub blocked_uri: String,
+
+    /// Directive that was violated
+    pub violated_directive: String,
+
+    /// Directive whose enforcement caused the violation
+    pub effective_directive: String,
+
+    /// Full policy text
+    pub original_policy: String,
+
+    /// `enforce` or `report`
+    pub disposition: String,
+
+    /// Endpoint the report should be sent to
+    #[serde(skip)]
+    pub report_uri: Option<String>,
+}
+
+impl CspViolationReport {
+    /// Build the JSON body to POST to the report endpoint
+    pub fn to_report_body(&self) -> serde_json::Value {
+        serde_json::json!({ "csp-report": self })
+    }
+}
@@ -1,10 +1,58 @@
 //! DOM integration for renderer processes
 
+pub mod content_editable;
+pub mod intersection_observer;
+pub mod resize_observer;
+pub mod selection;
+pub mod web_animations;
+
 use common::error::Result;
-use dom::{Document, Element, Node, TextNode};
+use common::ipc::{
+    BrowserEvent, BrowserRequest, Channel, ClipboardReadMessage, ClipboardWriteMessage, FileHandleInfo, FileSystemRequest,
+    FileSystemResponse, GeolocationError, GeolocationRequestMessage, IpcMessage,
+};
+use common::types::TabId;
+use dom::animation::AnimationEngine;
+use dom::events::KeyboardEventData;
+use dom::{CustomElementDefinition, Document, Element, FormValidator, HtmlParser, Node, ValidationResult};
 use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use storage::StorageEvent;
+use tokio::sync::{broadcast, watch};
 use tracing::{debug, error, info, warn};
 
+use crate::find_in_page::is_searchable_element;
+use crate::rendering_pipeline::{LayoutGeometry, Point};
+use crate::scroll_animator::SmoothScroll;
+use crate::sri::SriResult;
+use content_editable::{ContentEditableEditor, DomMutation};
+use intersection_observer::{
+    IntersectionObserverEntry, IntersectionObserverInit, IntersectionObserverManager, ObserverId,
+};
+use selection::{Selection, SelectionAlter, SelectionBoundary, SelectionDirection, SelectionGranularity};
+use web_animations::{Animation, AnimationId, DocumentAnimations};
+
+/// Event target ID of the document's `window`
+pub const WINDOW_TARGET: &str = "window";
+
+/// Event target ID of the `document`
+pub const DOCUMENT_TARGET: &str = "document";
+
+/// Size of a video element not laid out yet, the default size of replaced elements
+const DEFAULT_VIDEO_SIZE: (u32, u32) = (300, 150);
+
+/// ID of the next clipboard request
+static NEXT_CLIPBOARD_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// ID of the next File System Access request
+static NEXT_FILE_SYSTEM_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// ID of the next geolocation request
+static NEXT_GEOLOCATION_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// `PositionOptions.timeout` of pages that wait for a position forever
+const NO_POSITION_TIMEOUT: u64 = 0xFFFF_FFFF;
+
 /// DOM integration manager
 pub struct DomIntegrationManager {
     /// Current document
@@ -19,6 +67,39 @@ pub struct DomIntegrationManager {
     /// DOM mutation observers
     mutation_observers: Vec<MutationObserver>,
     
+    /// Intersection observers
+    intersection_observers: IntersectionObserverManager,
+    
+    /// Messages logged to the developer console
+    console_messages: Vec<ConsoleMessage>,
+    
+    /// Web Animations of the document
+    animations: DocumentAnimations,
+    
+    /// Selection of the document
+    selection: Selection,
+    
+    /// Geometry of the last layout pass, used to move the selection by line
+    layout_updates: Option<watch::Receiver<LayoutGeometry>>,
+    
+    /// Changes other tabs made to the `localStorage` of the document's origin
+    storage_events: Option<broadcast::Receiver<StorageEvent>>,
+    
+    /// Constraint validation of form controls
+    form_validator: FormValidator,
+    
+    /// Tab of the document and the channel to its browser window
+    browser: Option<(TabId, Channel<IpcMessage>)>,
+    
+    /// Element shown fullscreen
+    fullscreen_element: Option<String>,
+    
+    /// Video element shown Picture-in-Picture
+    picture_in_picture_element: Option<String>,
+    
+    /// Smooth scroll of the viewport, advanced by the rendering pipeline
+    smooth_scroll: SmoothScroll,
+    
     /// DOM query cache
     query_cache: std::collections::HashMap<String, Vec<String>>,
 }
@@ -56,6 +137,24 @@ pub struct MutationObserver {
     pub active: bool,
 }
 
+/// Console message level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleLevel {
+    Log,
+    Warning,
+    Error,
+}
+
+/// Message logged to the developer console
+#[derive(Debug, Clone)]
+pub struct ConsoleMessage {
+    /// Message level
+    pub level: ConsoleLevel,
+    
+    /// Message text
+    pub message: String,
+}
+
 /// Mutation type
 #[derive(Debug, Clone)]
 pub enum MutationType {
@@ -100,6 +199,23 @@ pub struct MutationRecord {
     pub old_value: Option<String>,
 }
 
+impl MutationRecord {
+    /// Create an empty record for a target element
+    fn new(mutation_type: MutationType, target_element_id: &str) -> Self {
+        Self {
+            mutation_type,
+            target_element_id: target_element_id.to_string(),
+            added_nodes: Vec::new(),
+            removed_nodes: Vec::new(),
+            previous_sibling_id: None,
+            next_sibling_id: None,
+            attribute_name: None,
+            attribute_namespace: None,
+            old_value: None,
+        }
+    }
+}
+
 impl DomIntegrationManager {
     /// Create a new DOM integration manager
     pub async fn new() -> Result<Self> {
@@ -110,6 +226,17 @@ impl DomIntegrationManager {
             document_url: None,
             event_listeners: Vec::new(),
             mutation_observers: Vec::new(),
+            intersection_observers: IntersectionObserverManager::new(),
+            console_messages: Vec::new(),
+            animations: DocumentAnimations::new(),
+            selection: Selection::new(),
+            layout_updates: None,
+            storage_events: None,
+            form_validator: FormValidator::new(),
+            browser: None,
+            fullscreen_element: None,
+            picture_in_picture_element: None,
+            smooth_scroll: SmoothScroll::new(),
             query_cache: std::collections::HashMap::new(),
         })
     }
@@ -132,11 +259,8 @@ impl DomIntegrationManager {
     pub async fn parse_html(&mut self, url: &str) -> Result<()> {
         info!("Parsing HTML for URL: {}", url);
         
-        // Create a new document
-        let mut document = Document::new();
-        
         // TODO: Fetch HTML content from URL
-        // For now, create a simple test document
+        // For now, parse a simple test document
         let html_content = format!(
             r#"<!DOCTYPE html>
 <html>
@@ -154,17 +278,114 @@ impl DomIntegrationManager {
             url
         );
         
-        // Parse the HTML content
-        // TODO: Use the actual HTML parser from the dom crate
-        // For now, create a simple document structure
-        self.create_test_document(&html_content).await?;
-        
+        self.document = Some(HtmlParser::parse(html_content.as_bytes())?);
+        self.query_cache.clear();
+        self.selection.remove_all_ranges();
         self.document_url = Some(url.to_string());
         
         info!("HTML parsed successfully for URL: {}", url);
         Ok(())
     }
     
+    /// Take a frozen copy of the current document
+    pub fn snapshot_document(&self) -> (Option<Document>, Option<String>) {
+        (self.document.clone(), self.document_url.clone())
+    }
+    
+    /// Restore a document frozen by `snapshot_document`
+    pub fn restore_document(&mut self, document: Option<Document>, document_url: Option<String>) {
+        self.document = document;
+        self.document_url = document_url;
+        self.query_cache.clear();
+        self.selection.remove_all_ranges();
+    }
+    
+    /// Define a custom element in the current document
+    pub async fn define_custom_element(&mut self, name: &str, definition: CustomElementDefinition) -> Result<()> {
+        let document = self.document.as_mut().ok_or_else(|| {
+            common::error::Error::ConfigError("No document loaded".to_string())
+        })?;
+        
+        document.define(name, definition)?;
+        
+        debug!("Defined custom element {}", name);
+        Ok(())
+    }
+    
+    /// Insert an element as the last child of a parent, or of the body if no parent is given
+    ///
+    /// Custom elements in the inserted subtree are upgraded and their connected callbacks run.
+    pub async fn insert_element(&mut self, parent_id: Option<&str>, mut element: Element) -> Result<()> {
+        let document = self.document.as_mut().ok_or_else(|| {
+            common::error::Error::ConfigError("No document loaded".to_string())
+        })?;
+        
+        document.custom_elements.upgrade(&mut element);
+        let added_id = element.get_attribute("id").cloned().unwrap_or_else(|| element.id.clone());
+        
+        let parent = match parent_id {
+            Some(parent_id) => document.root.get_element_by_id_mut(parent_id),
+            None => Self::body_mut(&mut document.root),
+        }.ok_or_else(|| {
+            common::error::Error::NotFound(format!("Parent element {} not found", parent_id.unwrap_or("body")))
+        })?;
+        let target_id = parent.get_attribute("id").cloned().unwrap_or_else(|| parent.id.clone());
+        
+        parent.append_child(Node::Element(element));
+        if let Some(Node::Element(inserted)) = parent.children.last() {
+            document.custom_elements.connected(inserted);
+        }
+        self.query_cache.clear();
+        
+        let mut record = MutationRecord::new(MutationType::ChildList, &target_id);
+        record.added_nodes.push(added_id);
+        self.notify_mutation_observers(vec![record]).await
+    }
+    
+    /// Remove an element from the document
+    ///
+    /// Disconnected callbacks run for custom elements in the removed subtree.
+    pub async fn remove_element(&mut self, element_id: &str) -> Result<Element> {
+        let document = self.document.as_mut().ok_or_else(|| {
+            common::error::Error::ConfigError("No document loaded".to_string())
+        })?;
+        
+        let (parent_id, removed) = Self::detach_element(&mut document.root, element_id).ok_or_else(|| {
+            common::error::Error::NotFound(format!("Element {} not found", element_id))
+        })?;
+        document.custom_elements.disconnected(&removed);
+        self.query_cache.clear();
+        self.refresh_selection();
+        
+        let mut record = MutationRecord::new(MutationType::ChildList, &parent_id);
+        record.removed_nodes.push(element_id.to_string());
+        self.notify_mutation_observers(vec![record]).await?;
+        
+        Ok(removed)
+    }
+    
+    /// Set an attribute on an element
+    pub async fn set_attribute(&mut self, element_id: &str, name: &str, value: &str) -> Result<()> {
+        let old_value = self.element_mut(element_id)?.attributes.insert(name.to_string(), value.to_string());
+        
+        let mut record = MutationRecord::new(MutationType::Attributes, element_id);
+        record.attribute_name = Some(name.to_string());
+        record.old_value = old_value;
+        self.notify_mutation_observers(vec![record]).await
+    }
+    
+    /// Remove an attribute from an element
+    pub async fn remove_attribute(&mut self, element_id: &str, name: &str) -> Result<()> {
+        let Some(old_value) = self.element_mut(element_id)?.remove_attribute(name) else {
+            return Ok(());
+        };
+        
+        let mut record = MutationRecord::new(MutationType::Attributes, element_id);
+        record.attribute_name = Some(name.to_string());
+        record.old_value = Some(old_value);
+        self.notify_mutation_observers(vec![record]).await
+    }
+    
     /// Get the current DOM tree as JSON
     pub async fn get_dom_tree(&self) -> Result<Value> {
         if let Some(document) = &self.document {
@@ -320,16 +541,638 @@ impl DomIntegrationManager {
         Ok(())
     }
     
+    /// Create an intersection observer
+    pub async fn create_intersection_observer<F>(&mut self, config: IntersectionObserverInit, callback: F) -> Result<ObserverId>
+    where
+        F: Fn(Vec<IntersectionObserverEntry>) + Send + Sync + 'static,
+    {
+        let observer_id = self.intersection_observers.create_observer(config, callback)?;
+        
+        debug!("Created intersection observer {}", observer_id);
+        Ok(observer_id)
+    }
+    
+    /// Start observing an element with an intersection observer
+    pub async fn observe_intersection(&mut self, observer_id: ObserverId, element_id: &str) -> Result<()> {
+        self.intersection_observers.observe(observer_id, element_id)
+    }
+    
+    /// Stop observing an element with an intersection observer
+    pub async fn unobserve_intersection(&mut self, observer_id: ObserverId, element_id: &str) -> Result<()> {
+        self.intersection_observers.unobserve(observer_id, element_id)
+    }
+    
+    /// Disconnect an intersection observer
+    pub async fn disconnect_intersection_observer(&mut self, observer_id: ObserverId) -> Result<()> {
+        self.intersection_observers.disconnect(observer_id)?;
+        
+        debug!("Disconnected intersection observer {}", observer_id);
+        Ok(())
+    }
+    
+    /// Subscribe intersection observers and the selection to layout geometry updates
+    pub fn subscribe_layout_updates(&mut self, layout_updates: watch::Receiver<LayoutGeometry>) {
+        self.layout_updates = Some(layout_updates.clone());
+        self.intersection_observers.subscribe(layout_updates);
+    }
+    
+    /// Listen for the `localStorage` changes other tabs of the document's origin make
+    pub fn subscribe_storage_events(&mut self, storage_events: broadcast::Receiver<StorageEvent>) {
+        self.storage_events = Some(storage_events);
+    }
+    
+    /// Fire a `storage` event at the window for each `localStorage` change
+    /// received since the last call, returning how many were fired
+    pub fn process_storage_events(&mut self) -> usize {
+        let Some(storage_events) = &mut self.storage_events else {
+            return 0;
+        };
+        
+        let mut events = Vec::new();
+        loop {
+            match storage_events.try_recv() {
+                Ok(event) => events.push(event),
+                Err(broadcast::error::TryRecvError::Lagged(missed)) => {
+                    warn!("Dropped {} storage events the document fell behind on", missed);
+                }
+                Err(_) => break,
+            }
+        }
+        
+        for event in &events {
+            let event_data = serde_json::json!({
+                "key": event.key,
+                "oldValue": event.old_value,
+                "newValue": event.new_value,
+                "url": event.url,
+                "storageArea": event.storage_area,
+            });
+            self.run_event_listeners(WINDOW_TARGET, "storage", &event_data);
+        }
+        events.len()
+    }
+    
+    /// Get the smooth scroll of the viewport, for the rendering pipeline to advance
+    pub fn smooth_scroll(&self) -> SmoothScroll {
+        self.smooth_scroll.clone()
+    }
+    
+    /// Scroll the viewport to a position with an animation, as
+    /// `window.scrollTo({ behavior: 'smooth' })`
+    ///
+    /// Scrolling during an animation moves its target.
+    pub fn smooth_scroll_to(&self, x: f32, y: f32) {
+        let target = Point { x: x.max(0.0), y: y.max(0.0) };
+        self.smooth_scroll.scroll_to(self.scroll_position(), target, std::time::Instant::now());
+        debug!("Smooth scrolling to ({}, {})", target.x, target.y);
+    }
+    
+    /// Scroll the viewport by an offset with an animation, as a wheel event or
+    /// `window.scrollBy({ behavior: 'smooth' })`
+    ///
+    /// Offsets add up with the target of the running animation.
+    pub fn smooth_scroll_by(&self, delta_x: f32, delta_y: f32) {
+        let from = self.smooth_scroll.target().unwrap_or_else(|| self.scroll_position());
+        self.smooth_scroll_to(from.x + delta_x, from.y + delta_y);
+    }
+    
+    /// Scroll position of the viewport in the last layout pass
+    fn scroll_position(&self) -> Point {
+        self.layout_updates.as_ref()
+            .map(|updates| {
+                let viewport = updates.borrow().viewport;
+                Point { x: viewport.x, y: viewport.y }
+            })
+            .unwrap_or(Point { x: 0.0, y: 0.0 })
+    }
+    
+    /// Send requests for the browser window, such as fullscreen, over a channel
+    pub fn connect_browser(&mut self, tab_id: TabId, channel: Channel<IpcMessage>) {
+        self.browser = Some((tab_id, channel));
+    }
+    
+    /// Show an element fullscreen, as `Element.requestFullscreen()`
+    ///
+    /// Asks the browser to make the window fullscreen, then fires
+    /// `fullscreenchange` at the element and the document. Requesting
+    /// fullscreen for the fullscreen element does nothing.
+    pub async fn request_fullscreen(&mut self, element_id: &str) -> Result<()> {
+        let document = self.document.as_ref().ok_or_else(|| {
+            common::error::Error::ConfigError("No document loaded".to_string())
+        })?;
+        if document.get_element_by_id(element_id).is_none() {
+            return Err(common::error::Error::NotFound(format!("Element {} not found", element_id)));
+        }
+        if self.fullscreen_element.as_deref() == Some(element_id) {
+            return Ok(());
+        }
+        
+        let tab_id = self.send_browser_request(|tab_id| BrowserRequest::EnterFullscreen {
+            tab_id,
+            element_id: element_id.to_string(),
+        }).await?;
+        
+        let previous = self.fullscreen_element.replace(element_id.to_string());
+        if let Some(previous) = previous {
+            self.fire_fullscreen_change(&previous);
+        }
+        self.fire_fullscreen_change(element_id);
+        
+        debug!("Element {} of tab {} is fullscreen", element_id, tab_id);
+        Ok(())
+    }
+    
+    /// Leave fullscreen, as `document.exitFullscreen()`
+    pub async fn exit_fullscreen(&mut self) -> Result<()> {
+        let Some(element_id) = self.fullscreen_element.clone() else {
+            return Err(common::error::Error::InvalidState("Document is not fullscreen".to_string()));
+        };
+        
+        self.send_browser_request(|tab_id| BrowserRequest::ExitFullscreen { tab_id }).await?;
+        self.fullscreen_element = None;
+        self.fire_fullscreen_change(&element_id);
+        
+        debug!("Element {} left fullscreen", element_id);
+        Ok(())
+    }
+    
+    /// Apply a change the browser made to the window on its own
+    ///
+    /// When the user leaves fullscreen, the fullscreen element is cleared
+    /// and `fullscreenchange` fires as for `document.exitFullscreen()`.
+    pub fn handle_browser_event(&mut self, event: BrowserEvent) {
+        match event {
+            BrowserEvent::FullscreenExited { tab_id } => {
+                if self.browser.as_ref().is_some_and(|(own_tab, _)| *own_tab != tab_id) {
+                    warn!("Ignoring the fullscreen exit of tab {}", tab_id);
+                    return;
+                }
+                if let Some(element_id) = self.fullscreen_element.take() {
+                    self.fire_fullscreen_change(&element_id);
+                    debug!("Element {} left fullscreen with the window", element_id);
+                }
+            }
+        }
+    }
+    
+    /// Get the element shown fullscreen, as `document.fullscreenElement`
+    pub fn fullscreen_element(&self) -> Option<&str> {
+        self.fullscreen_element.as_deref()
+    }
+    
+    /// Show a video element in a floating window, as `HTMLVideoElement.requestPictureInPicture()`
+    ///
+    /// The browser opens a window of the video's laid out size, then
+    /// `enterpictureinpicture` is fired at the video. A video shown before
+    /// gets `leavepictureinpicture`.
+    pub async fn request_picture_in_picture(&mut self, element_id: &str) -> Result<()> {
+        let document = self.document.as_ref().ok_or_else(|| {
+            common::error::Error::ConfigError("No document loaded".to_string())
+        })?;
+        let element = document.get_element_by_id(element_id).ok_or_else(|| {
+            common::error::Error::NotFound(format!("Element {} not found", element_id))
+        })?;
+        if !element.tag_name.eq_ignore_ascii_case("video") {
+            return Err(common::error::Error::InvalidState(format!("Element {} is not a video", element_id)));
+        }
+        if self.picture_in_picture_element.as_deref() == Some(element_id) {
+            return Ok(());
+        }
+        
+        let (width, height) = self.video_size(element);
+        self.send_browser_request(|tab_id| BrowserRequest::EnterPictureInPicture {
+            tab_id,
+            element_id: element_id.to_string(),
+            width,
+            height,
+        }).await?;
+        
+        if let Some(previous) = self.picture_in_picture_element.replace(element_id.to_string()) {
+            self.fire_picture_in_picture_event(&previous, "leavepictureinpicture");
+        }
+        self.fire_picture_in_picture_event(element_id, "enterpictureinpicture");
+        
+        debug!("Video {} is shown Picture-in-Picture", element_id);
+        Ok(())
+    }
+    
+    /// Close the Picture-in-Picture window, as `document.exitPictureInPicture()`
+    pub async fn exit_picture_in_picture(&mut self) -> Result<()> {
+        let Some(element_id) = self.picture_in_picture_element.clone() else {
+            return Err(common::error::Error::InvalidState("No video is shown Picture-in-Picture".to_string()));
+        };
+        
+        self.send_browser_request(|tab_id| BrowserRequest::ExitPictureInPicture { tab_id }).await?;
+        self.picture_in_picture_element = None;
+        self.fire_picture_in_picture_event(&element_id, "leavepictureinpicture");
+        
+        debug!("Video {} left Picture-in-Picture", element_id);
+        Ok(())
+    }
+    
+    /// Get the video shown Picture-in-Picture, as `document.pictureInPictureElement`
+    pub fn picture_in_picture_element(&self) -> Option<&str> {
+        self.picture_in_picture_element.as_deref()
+    }
+    
+    /// Size of a video: its laid out box, else its `width` and `height` attributes
+    fn video_size(&self, element: &Element) -> (u32, u32) {
+        let element_id = element.get_attribute("id").map(String::as_str).unwrap_or_default();
+        if let Some(rect) = self.layout_updates.as_ref().and_then(|updates| updates.borrow().elements.get(element_id).cloned()) {
+            if rect.width >= 1.0 && rect.height >= 1.0 {
+                return (rect.width.round() as u32, rect.height.round() as u32);
+            }
+        }
+        
+        let attribute = |name: &str, default: u32| {
+            element.get_attribute(name).and_then(|value| value.trim().parse().ok()).filter(|&value| value > 0).unwrap_or(default)
+        };
+        (attribute("width", DEFAULT_VIDEO_SIZE.0), attribute("height", DEFAULT_VIDEO_SIZE.1))
+    }
+    
+    /// Fire `enterpictureinpicture` or `leavepictureinpicture` at a video
+    fn fire_picture_in_picture_event(&self, element_id: &str, event_type: &str) {
+        let event_data = serde_json::json!({
+            "target": element_id,
+            "pictureInPictureElement": self.picture_in_picture_element,
+        });
+        self.run_event_listeners(element_id, event_type, &event_data);
+    }
+    
+    /// Send a request to the browser window of the tab, returning the tab ID
+    async fn send_browser_request(&self, request: impl FnOnce(TabId) -> BrowserRequest) -> Result<TabId> {
+        let (tab_id, channel) = self.browser.as_ref().ok_or_else(|| {
+            common::error::Error::InvalidState("Not connected to a browser window".to_string())
+        })?;
+        channel.send_async(IpcMessage::BrowserRequest(request(*tab_id))).await?;
+        Ok(*tab_id)
+    }
+    
+    /// Read text from the clipboard, as `navigator.clipboard.readText()`
+    ///
+    /// The browser reads the clipboard if the page's origin has the
+    /// `clipboard-read` permission.
+    pub async fn read_clipboard_text(&self) -> Result<String> {
+        let text = self.send_clipboard_request(|request_id, tab_id| {
+            IpcMessage::ClipboardRead(ClipboardReadMessage { request_id, tab_id })
+        }).await?;
+        Ok(text.unwrap_or_default())
+    }
+    
+    /// Write text to the clipboard, as `navigator.clipboard.writeText()`
+    pub async fn write_clipboard_text(&self, text: &str) -> Result<()> {
+        self.send_clipboard_request(|request_id, tab_id| {
+            IpcMessage::ClipboardWrite(ClipboardWriteMessage { request_id, tab_id, text: text.to_string() })
+        }).await?;
+        Ok(())
+    }
+    
+    /// Send a clipboard request to the browser and wait for its answer
+    async fn send_clipboard_request(&self, request: impl FnOnce(u64, TabId) -> IpcMessage) -> Result<Option<String>> {
+        let (tab_id, channel) = self.browser.as_ref().ok_or_else(|| {
+            common::error::Error::InvalidState("Not connected to a browser window".to_string())
+        })?;
+        let request_id = NEXT_CLIPBOARD_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+        channel.send_async(request(request_id, *tab_id)).await?;
+        
+        loop {
+            match channel.recv_async().await? {
+                IpcMessage::ClipboardResponse(response) if response.request_id == request_id => {
+                    return match response.error {
+                        Some(error) => Err(common::error::Error::PermissionDenied(format!("Clipboard request failed: {}", error))),
+                        None => Ok(response.text),
+                    };
+                }
+                message => debug!("Ignoring {:?} while waiting for the clipboard", std::mem::discriminant(&message)),
+            }
+        }
+    }
+    
+    /// Let the user pick files to open, as `window.showOpenFilePicker(options)`
+    ///
+    /// The browser shows the picker if the page's origin may read files.
+    pub async fn show_open_file_picker(&self, options: Value) -> Result<Vec<FileHandleInfo>> {
+        let response = self.send_file_system_request(|request_id, tab_id| {
+            FileSystemRequest::ShowOpenFilePicker { request_id, tab_id, options }
+        }).await?;
+        Ok(response.handles)
+    }
+    
+    /// Let the user pick a file to save to, as `window.showSaveFilePicker(options)`
+    pub async fn show_save_file_picker(&self, options: Value) -> Result<FileHandleInfo> {
+        let response = self.send_file_system_request(|request_id, tab_id| {
+            FileSystemRequest::ShowSaveFilePicker { request_id, tab_id, options }
+        }).await?;
+        response.handles.into_iter().next().ok_or_else(|| {
+            common::error::Error::InvalidState("The browser picked no file".to_string())
+        })
+    }
+    
+    /// Read a picked file, as `FileSystemFileHandle.getFile()`
+    pub async fn read_file_handle(&self, handle_id: u64) -> Result<Vec<u8>> {
+        let response = self.send_file_system_request(|request_id, tab_id| {
+            FileSystemRequest::ReadFile { request_id, tab_id, handle_id }
+        }).await?;
+        Ok(response.data.unwrap_or_default())
+    }
+    
+    /// Replace the contents of a picked file, as closing a `FileSystemWritableFileStream`
+    pub async fn write_file_handle(&self, handle_id: u64, data: Vec<u8>) -> Result<()> {
+        self.send_file_system_request(|request_id, tab_id| {
+            FileSystemRequest::WriteFile { request_id, tab_id, handle_id, data }
+        }).await?;
+        Ok(())
+    }
+    
+    /// Send a File System Access request to the browser and wait for its answer
+    async fn send_file_system_request(&self, request: impl FnOnce(u64, TabId) -> FileSystemRequest) -> Result<FileSystemResponse> {
+        let (tab_id, channel) = self.browser.as_ref().ok_or_else(|| {
+            common::error::Error::InvalidState("Not connected to a browser window".to_string())
+        })?;
+        let request_id = NEXT_FILE_SYSTEM_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+        channel.send_async(IpcMessage::FileSystemRequest(request(request_id, *tab_id))).await?;
+        
+        loop {
+            match channel.recv_async().await? {
+                IpcMessage::FileSystemResponse(response) if response.request_id == request_id => {
+                    return match response.error {
+                        Some(error) => Err(common::error::Error::PermissionDenied(format!("File system request failed: {}", error))),
+                        None => Ok(response),
+                    };
+                }
+                message => debug!("Ignoring {:?} while waiting for the file system", std::mem::discriminant(&message)),
+            }
+        }
+    }
+    
+    /// Get the device's position, as `navigator.geolocation.getCurrentPosition()`
+    ///
+    /// `options` is the page's `PositionOptions` dictionary. The browser
+    /// queries the location service if the page's origin has the
+    /// `geolocation` permission, and the position is returned in its
+    /// JavaScript form.
+    pub async fn get_current_position(&self, options: &Value) -> Result<Value> {
+        let (tab_id, channel) = self.browser.as_ref().ok_or_else(|| {
+            common::error::Error::InvalidState("Not connected to a browser window".to_string())
+        })?;
+        let request_id = NEXT_GEOLOCATION_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+        let timeout = options["timeout"].as_u64().unwrap_or(NO_POSITION_TIMEOUT);
+        channel.send_async(IpcMessage::GeolocationRequest(GeolocationRequestMessage {
+            request_id,
+            tab_id: *tab_id,
+            enable_high_accuracy: options["enableHighAccuracy"].as_bool().unwrap_or(false),
+            timeout: (timeout < NO_POSITION_TIMEOUT).then_some(timeout),
+            maximum_age: options["maximumAge"].as_u64().unwrap_or(0),
+        })).await?;
+        
+        loop {
+            match channel.recv_async().await? {
+                IpcMessage::GeolocationResponse(response) if response.request_id == request_id => {
+                    if let Some(GeolocationError { code, message }) = response.error {
+                        return Err(match code {
+                            GeolocationError::PERMISSION_DENIED => common::error::Error::PermissionDenied(message),
+                            GeolocationError::TIMEOUT => common::error::Error::Timeout(message),
+                            _ => common::error::Error::PlatformError(message),
+                        });
+                    }
+                    return response.position.ok_or_else(|| {
+                        common::error::Error::PlatformError("The browser sent no position".to_string())
+                    });
+                }
+                message => debug!("Ignoring {:?} while waiting for a position", std::mem::discriminant(&message)),
+            }
+        }
+    }
+    
+    /// Fire `fullscreenchange` at an element entering or leaving fullscreen, then at the document
+    fn fire_fullscreen_change(&self, element_id: &str) {
+        let event_data = serde_json::json!({
+            "target": element_id,
+            "fullscreenElement": self.fullscreen_element,
+        });
+        self.run_event_listeners(element_id, "fullscreenchange", &event_data);
+        self.run_event_listeners(DOCUMENT_TARGET, "fullscreenchange", &event_data);
+    }
+    
+    /// Get the selection, as `document.getSelection()` does
+    pub fn get_selection(&self) -> &Selection {
+        &self.selection
+    }
+    
+    /// Select from a start boundary to an end boundary
+    ///
+    /// Boundaries are elements with text and character offsets into it.
+    pub fn set_selection_range(&mut self, start_node: &str, start_offset: usize, end_node: &str, end_offset: usize) -> Result<()> {
+        self.refresh_selection();
+        self.selection.set_base_and_extent(
+            SelectionBoundary { node_id: start_node.to_string(), offset: start_offset },
+            SelectionBoundary { node_id: end_node.to_string(), offset: end_offset },
+        )
+    }
+    
+    /// Move or extend the selection, as `Selection.modify()` does
+    pub fn modify_selection(&mut self, alter: SelectionAlter, direction: SelectionDirection, granularity: SelectionGranularity) {
+        self.refresh_selection();
+        if let Some(layout_updates) = &self.layout_updates {
+            self.selection.update_layout(&layout_updates.borrow());
+        }
+        self.selection.modify(alter, direction, granularity);
+    }
+    
+    /// Remove the selection
+    pub fn clear_selection(&mut self) {
+        self.selection.remove_all_ranges();
+    }
+    
+    /// Bring the selection up to date with the text of the document
+    fn refresh_selection(&mut self) {
+        let mut texts = Vec::new();
+        if let Some(document) = &self.document {
+            Self::collect_element_texts(&document.root, true, false, &mut texts);
+        }
+        self.selection.update_text(&texts);
+    }
+    
+    /// Handle a key press on an element, editing it if it is `contenteditable`
+    ///
+    /// Returns the mutation made, or `None` if the element isn't editable.
+    /// The caret is left after the edit as a collapsed selection.
+    pub async fn handle_key_event(&mut self, element_id: &str, event: &KeyboardEventData) -> Result<Option<DomMutation>> {
+        self.refresh_selection();
+        let document = self.document.as_ref().ok_or_else(|| {
+            common::error::Error::ConfigError("No document loaded".to_string())
+        })?;
+        if content_editable::editing_host(&document.root, element_id).is_none() {
+            return Ok(None);
+        }
+    
+        let mutation = ContentEditableEditor::new(&document.root, &self.selection).handle_key(element_id, event)?;
+        self.apply_dom_mutations(std::slice::from_ref(&mutation)).await?;
+        Ok(Some(mutation))
+    }
+    
+    /// Run an editing command on the selection, as `document.execCommand()` does
+    ///
+    /// Returns whether the command changed the document.
+    pub async fn exec_command(&mut self, command: &str) -> Result<bool> {
+        self.refresh_selection();
+        let document = self.document.as_ref().ok_or_else(|| {
+            common::error::Error::ConfigError("No document loaded".to_string())
+        })?;
+    
+        let mutations = ContentEditableEditor::new(&document.root, &self.selection).exec_command(command)?;
+        self.apply_dom_mutations(&mutations).await?;
+        Ok(!mutations.is_empty())
+    }
+    
+    /// Apply editing mutations, move the selection and notify mutation observers
+    async fn apply_dom_mutations(&mut self, mutations: &[DomMutation]) -> Result<()> {
+        let document = self.document.as_mut().ok_or_else(|| {
+            common::error::Error::ConfigError("No document loaded".to_string())
+        })?;
+    
+        let mut records = Vec::new();
+        let mut selection: Option<(SelectionBoundary, SelectionBoundary)> = None;
+        for mutation in mutations {
+            let applied = mutation.apply(&mut document.root)?;
+            records.extend(applied.records);
+            if let Some((anchor, focus)) = applied.selection {
+                selection = Some(match selection {
+                    Some((first, _)) => (first, focus),
+                    None => (anchor, focus),
+                });
+            }
+        }
+        if records.is_empty() {
+            return Ok(());
+        }
+    
+        self.query_cache.clear();
+        self.refresh_selection();
+        if let Some((anchor, focus)) = selection {
+            self.selection.set_base_and_extent(anchor, focus)?;
+        }
+        self.notify_mutation_observers(records).await
+    }
+    
+    /// Update intersection observers after a layout pass
+    pub async fn process_intersections(&mut self) -> Vec<IntersectionObserverEntry> {
+        self.intersection_observers.poll_layout_updates()
+    }
+    
+    /// Decide whether a fetched script or stylesheet may run after its integrity check
+    ///
+    /// A failed check logs a console error and blocks the resource.
+    pub fn check_subresource_integrity(&mut self, resource_url: &str, result: &SriResult) -> bool {
+        if let SriResult::Invalid { expected, got } = result {
+            self.console_error(format!(
+                "Failed to find a valid digest in the 'integrity' attribute for resource '{}' with computed integrity '{}' (expected '{}'). The resource has been blocked.",
+                resource_url, got, expected
+            ));
+            return false;
+        }
+        
+        true
+    }
+    
+    /// Animate an element with keyframes, as `Element.animate()`
+    ///
+    /// `options` is a duration in milliseconds or an `EffectTiming` dictionary.
+    pub fn animate(
+        &mut self,
+        element_id: &str,
+        keyframes: &Value,
+        options: &Value,
+        engine: &mut AnimationEngine,
+    ) -> Result<&mut Animation> {
+        self.element_mut(element_id)?;
+        self.animations.animate(element_id, keyframes, options, engine)
+    }
+    
+    /// Get the animations of the document, as `Document.getAnimations()`
+    pub fn get_animations(&self) -> Vec<Animation> {
+        self.animations.get_animations()
+    }
+    
+    /// Get an animation by ID
+    pub fn animation_mut(&mut self, animation_id: AnimationId) -> Option<&mut Animation> {
+        self.animations.animation_mut(animation_id)
+    }
+    
+    /// Resolve the `finished` promises of animations that ended in the last frame
+    pub fn update_animations(&mut self, engine: &AnimationEngine) -> Vec<AnimationId> {
+        self.animations.update(engine)
+    }
+    
+    /// Log an error to the developer console
+    pub fn console_error(&mut self, message: String) {
+        error!("[Console] {}", message);
+        self.console_messages.push(ConsoleMessage {
+            level: ConsoleLevel::Error,
+            message,
+        });
+    }
+    
+    /// Get the messages logged to the developer console
+    pub fn console_messages(&self) -> &[ConsoleMessage] {
+        &self.console_messages
+    }
+    
     /// Trigger a DOM event
+    ///
+    /// A `submit` event is cancelled if the form fails constraint validation.
     pub async fn trigger_event(&self, element_id: &str, event_type: &str, event_data: serde_json::Value) -> Result<()> {
+        if event_type == "submit" && !self.check_form_validity(element_id).await? {
+            debug!("Cancelled submit event for invalid form {}", element_id);
+            return Ok(());
+        }
+        
+        self.run_event_listeners(element_id, event_type, &event_data);
+        
+        debug!("Triggered event {} for element {}", event_type, element_id);
+        Ok(())
+    }
+    
+    /// Call the listeners of an event on an element
+    fn run_event_listeners(&self, element_id: &str, event_type: &str, event_data: &serde_json::Value) {
         for listener in &self.event_listeners {
             if listener.active && listener.element_id == element_id && listener.event_type == event_type {
                 (listener.callback)(event_data.clone());
             }
         }
+    }
+    
+    /// Check the validity of a form's controls, as `form.checkValidity()` does
+    ///
+    /// An `invalid` event is fired at each invalid control. Forms with
+    /// `novalidate` and elements that aren't forms are always valid.
+    pub async fn check_form_validity(&self, form_id: &str) -> Result<bool> {
+        let Some(document) = &self.document else {
+            return Ok(true);
+        };
+        let Some(form) = document.get_element_by_id(form_id).filter(|form| form.tag_name == "form") else {
+            return Ok(true);
+        };
+        if form.has_attribute("novalidate") {
+            return Ok(true);
+        }
         
-        debug!("Triggered event {} for element {}", event_type, element_id);
-        Ok(())
+        let controls = FormValidator::form_controls(form, document);
+        let results = self.form_validator.validate_form(form, document);
+        let mut valid = true;
+        for (control, result) in controls.iter().zip(results) {
+            if let ValidationResult::Invalid { message, .. } = result {
+                valid = false;
+                let control_id = control.get_attribute("id").cloned().unwrap_or_else(|| control.id.clone());
+                self.run_event_listeners(&control_id, "invalid", &serde_json::json!({ "validationMessage": message }));
+            }
+        }
+        Ok(valid)
+    }
+    
+    /// Set a custom validity message on a form control, as `setCustomValidity()` does
+    pub fn set_custom_validity(&mut self, element_id: &str, message: &str) {
+        self.form_validator.set_custom_validity(element_id, message);
     }
     
     /// Notify mutation observers
@@ -348,42 +1191,96 @@ impl DomIntegrationManager {
             }
         }
         
+        // Custom elements react to changes of their observed attributes
+        if let Some(document) = &self.document {
+            for record in mutation_records.iter().filter(|record| matches!(record.mutation_type, MutationType::Attributes)) {
+                let (Some(name), Some(element)) = (&record.attribute_name, document.get_element_by_id(&record.target_element_id)) else {
+                    continue;
+                };
+                let new_value = element.get_attribute(name).map(String::as_str);
+                document.custom_elements.attribute_changed(element, name, record.old_value.as_deref(), new_value);
+            }
+        }
+        
         debug!("Notified {} mutation observers", self.mutation_observers.len());
         Ok(())
     }
     
-    /// Create a test document (placeholder implementation)
-    async fn create_test_document(&mut self, html_content: &str) -> Result<()> {
-        // TODO: Use the actual HTML parser from the dom crate
-        // For now, create a simple document structure
-        
-        let mut document = Document::new();
-        
-        // Create head element
-        let mut head_element = Element::new("head".to_string());
-        
-        // Create title element
-        let mut title_element = Element::new("title".to_string());
-        let title_text = Node::Text(TextNode::new("Test Page".to_string()));
-        title_element.append_child(title_text);
-        head_element.append_child(Node::Element(title_element));
-        
-        // Create body element
-        let mut body_element = Element::new("body".to_string());
-        
-        // Create h1 element
-        let mut h1_element = Element::new("h1".to_string());
-        let h1_text = Node::Text(TextNode::new("Hello, World!".to_string()));
-        h1_element.append_child(h1_text);
-        body_element.append_child(Node::Element(h1_element));
-        
-        // Add head and body to root
-        document.root.append_child(Node::Element(head_element));
-        document.root.append_child(Node::Element(body_element));
+    /// Rendered text of each element, in document order
+    ///
+    /// The text of an element is its text node children joined together,
+    /// with a `\n` for each `<br>`. Elements without text and elements whose
+    /// text is not rendered are skipped.
+    pub fn element_texts(&self) -> Result<Vec<(String, String)>> {
+        let document = self.document.as_ref().ok_or_else(|| {
+            common::error::Error::ConfigError("No document loaded".to_string())
+        })?;
+    
+        let mut texts = Vec::new();
+        Self::collect_element_texts(&document.root, false, false, &mut texts);
+        Ok(texts)
+    }
+    
+    /// Collect the texts of an element and its descendants
+    ///
+    /// With `keep_editable`, editable elements are kept even without text so a caret can be placed in them.
+    fn collect_element_texts(element: &Element, keep_editable: bool, editable: bool, texts: &mut Vec<(String, String)>) {
+        if !is_searchable_element(&element.tag_name) {
+            return;
+        }
+    
+        let editable = match element.get_attribute("contenteditable").map(|value| value.to_ascii_lowercase()).as_deref() {
+            Some("" | "true" | "plaintext-only") => true,
+            Some("false") => false,
+            _ => editable,
+        };
+        let text = element_text(element);
+        if !text.trim().is_empty() || (keep_editable && editable) {
+            let element_id = element.get_attribute("id").cloned().unwrap_or_else(|| element.id.clone());
+            texts.push((element_id, text));
+        }
+    
+        for child in &element.children {
+            if let Node::Element(child) = child {
+                Self::collect_element_texts(child, keep_editable, editable, texts);
+            }
+        }
+    }
+    
+    /// Find an element in the current document for mutation
+    fn element_mut(&mut self, element_id: &str) -> Result<&mut Element> {
+        self.document.as_mut()
+            .ok_or_else(|| common::error::Error::ConfigError("No document loaded".to_string()))?
+            .get_element_by_id_mut(element_id)
+            .ok_or_else(|| common::error::Error::NotFound(format!("Element {} not found", element_id)))
+    }
+    
+    /// Find the body element under the document root
+    fn body_mut(root: &mut Element) -> Option<&mut Element> {
+        root.children.iter_mut().find_map(|child| match child {
+            Node::Element(element) if element.tag_name == "body" => Some(element),
+            _ => None,
+        })
+    }
+    
+    /// Detach an element from the tree, returning its parent's ID and the element
+    fn detach_element(parent: &mut Element, element_id: &str) -> Option<(String, Element)> {
+        let index = parent.children.iter().position(|child| {
+            matches!(child, Node::Element(element) if element.get_attribute("id").map(String::as_str) == Some(element_id))
+        });
         
-        self.document = Some(document);
+        if let Some(index) = index {
+            let parent_id = parent.get_attribute("id").cloned().unwrap_or_else(|| parent.id.clone());
+            return match parent.remove_child(index) {
+                Some(Node::Element(element)) => Some((parent_id, element)),
+                _ => None,
+            };
+        }
         
-        Ok(())
+        parent.children.iter_mut().find_map(|child| match child {
+            Node::Element(element) => Self::detach_element(element, element_id),
+            _ => None,
+        })
     }
     
     /// Serialize document to JSON
@@ -398,6 +1295,7 @@ impl DomIntegrationManager {
     fn serialize_element(&self, element: &Element) -> Value {
         serde_json::json!({
             "type": "element",
+            "nodeId": element.id.clone(),
             "tagName": element.tag_name.clone(),
             "id": element.get_attribute("id").cloned(),
             "className": element.get_attribute("class").cloned(),
@@ -428,6 +1326,17 @@ impl DomIntegrationManager {
     }
 }
 
+/// Text of an element's own text node children, with a `\n` for each `<br>`
+pub(crate) fn element_text(element: &Element) -> String {
+    element.children.iter()
+        .filter_map(|child| match child {
+            Node::Text(text) => Some(text.text_content()),
+            Node::Element(child) if child.tag_name == "br" => Some("\n"),
+            _ => None,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -455,6 +1364,13 @@ mod tests {
         
         let dom_tree = manager.get_dom_tree().await;
         assert!(dom_tree.is_ok());
+
+        let (document, _) = manager.snapshot_document();
+        let document = document.unwrap();
+        assert_eq!(document.title.as_deref(), Some("Test Page"));
+        assert_eq!(document.get_elements_by_tag_name("p").len(), 2);
+        let content = document.get_element_by_id("content").unwrap();
+        assert!(content.text_content().contains("Content goes here"));
     }
 
     #[tokio::test]
@@ -471,6 +1387,44 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_storage_event_fired_in_other_tabs() {
+        let storage_directory = std::env::temp_dir().join(format!("matte-storage-{}", uuid::Uuid::new_v4()));
+        let web_storage = storage::WebStorageManager::new(storage_directory.clone()).unwrap();
+        let origin = "https://example.com";
+        
+        let mut tabs = Vec::new();
+        for tab_id in [1, 2] {
+            let mut manager = DomIntegrationManager::new().await.unwrap();
+            manager.initialize().await.unwrap();
+            manager.subscribe_storage_events(web_storage.subscribe(origin, tab_id).unwrap());
+            
+            let fired = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+            let listener_events = fired.clone();
+            manager.add_event_listener(WINDOW_TARGET, "storage", move |event| {
+                listener_events.lock().unwrap().push(event);
+            }).await.unwrap();
+            tabs.push((manager, fired));
+        }
+        
+        web_storage.set_local_storage_item_from(origin, "cart", "3", 1, "https://example.com/shop").await.unwrap();
+        
+        let (tab1, tab1_events) = &mut tabs[0];
+        assert_eq!(tab1.process_storage_events(), 0);
+        assert!(tab1_events.lock().unwrap().is_empty());
+        
+        let (tab2, tab2_events) = &mut tabs[1];
+        assert_eq!(tab2.process_storage_events(), 1);
+        let events = tab2_events.lock().unwrap();
+        assert_eq!(events[0]["key"], "cart");
+        assert_eq!(events[0]["oldValue"], Value::Null);
+        assert_eq!(events[0]["newValue"], "3");
+        assert_eq!(events[0]["url"], "https://example.com/shop");
+        assert_eq!(events[0]["storageArea"], "localStorage");
+        
+        let _ = std::fs::remove_dir_all(storage_directory);
+    }
+
     #[tokio::test]
     async fn test_mutation_observer_management() {
         let mut manager = DomIntegrationManager::new().await.unwrap();
@@ -488,4 +1442,458 @@ mod tests {
         let result = manager.remove_mutation_observer(&observer_id.unwrap()).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_intersection_observer_layout_updates() {
+        use crate::rendering_pipeline::Rectangle;
+        
+        let mut manager = DomIntegrationManager::new().await.unwrap();
+        manager.initialize().await.unwrap();
+        
+        let (sender, receiver) = watch::channel(LayoutGeometry::default());
+        manager.subscribe_layout_updates(receiver);
+        
+        let observer_id = manager.create_intersection_observer(IntersectionObserverInit::default(), |_| {}).await.unwrap();
+        manager.observe_intersection(observer_id, "hero").await.unwrap();
+        
+        // No layout pass yet
+        assert!(manager.process_intersections().await.is_empty());
+        
+        let mut geometry = LayoutGeometry {
+            generation: 1,
+            viewport: Rectangle { x: 0.0, y: 0.0, width: 800.0, height: 600.0 },
+            ..Default::default()
+        };
+        geometry.elements.insert("hero".to_string(), Rectangle { x: 0.0, y: 0.0, width: 100.0, height: 100.0 });
+        sender.send_replace(geometry);
+        
+        let entries = manager.process_intersections().await;
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].is_intersecting);
+        
+        assert!(manager.disconnect_intersection_observer(observer_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_custom_element_lifecycle() {
+        use std::sync::{Arc, Mutex};
+        
+        let mut manager = DomIntegrationManager::new().await.unwrap();
+        manager.parse_html("https://example.com").await.unwrap();
+        
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (connected, disconnected, changed) = (log.clone(), log.clone(), log.clone());
+        let definition = CustomElementDefinition::new()
+            .with_constructor(|element| element.set_attribute("role".to_string(), "button".to_string()))
+            .with_connected_callback(move |element| {
+                connected.lock().unwrap().push(format!("connected role={}", element.get_attribute("role").unwrap()));
+            })
+            .with_disconnected_callback(move |_| disconnected.lock().unwrap().push("disconnected".to_string()))
+            .with_attribute_changed_callback(&["disabled"], move |_, name, old, new| {
+                changed.lock().unwrap().push(format!("{} {:?} -> {:?}", name, old, new));
+            });
+        
+        assert!(manager.define_custom_element("mybutton", definition.clone()).await.is_err());
+        manager.define_custom_element("my-button", definition).await.unwrap();
+        
+        let observed = Arc::new(Mutex::new(0));
+        let counter = observed.clone();
+        manager.add_mutation_observer("submit", vec![MutationType::Attributes], move |records| {
+            *counter.lock().unwrap() += records.len();
+        }).await.unwrap();
+        
+        let mut container = Element::new("div".to_string());
+        container.set_attribute("id".to_string(), "app".to_string());
+        manager.insert_element(None, container).await.unwrap();
+        
+        let mut button = Element::new("my-button".to_string());
+        button.set_attribute("id".to_string(), "submit".to_string());
+        manager.insert_element(Some("app"), button).await.unwrap();
+        
+        manager.set_attribute("submit", "disabled", "").await.unwrap();
+        manager.set_attribute("submit", "title", "Send").await.unwrap();
+        manager.remove_attribute("submit", "disabled").await.unwrap();
+        
+        let removed = manager.remove_element("submit").await.unwrap();
+        assert_eq!(removed.get_attribute("role").map(String::as_str), Some("button"));
+        
+        assert_eq!(*log.lock().unwrap(), vec![
+            "connected role=button".to_string(),
+            "disabled None -> Some(\"\")".to_string(),
+            "disabled Some(\"\") -> None".to_string(),
+            "disconnected".to_string(),
+        ]);
+        assert_eq!(*observed.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_element_animate() {
+        use serde_json::json;
+        use web_animations::AnimationPlayState;
+        
+        let mut manager = DomIntegrationManager::new().await.unwrap();
+        manager.parse_html("https://example.com").await.unwrap();
+        let mut element = Element::new("div".to_string());
+        element.set_attribute("id".to_string(), "box".to_string());
+        manager.insert_element(None, element).await.unwrap();
+        let mut engine = AnimationEngine::new();
+        
+        let animation = manager.animate("box", &json!([{"opacity": 0}, {"opacity": 1}]), &json!(500), &mut engine).unwrap();
+        assert_eq!(animation.play_state, AnimationPlayState::Running);
+        assert_eq!(animation.effect.target, "box");
+        assert_eq!(animation.effect.options.duration, std::time::Duration::from_millis(500));
+        let (id, finished) = (animation.id, animation.finished.clone());
+        
+        let animations = manager.get_animations();
+        assert_eq!(animations.len(), 1);
+        assert_eq!(animations[0].id, id);
+        
+        engine.tick(std::time::Duration::from_millis(250));
+        assert_eq!(engine.animated_value("box", "opacity").unwrap().value(), &dom::CssValue::Number(0.5));
+        
+        engine.tick(std::time::Duration::from_millis(300));
+        assert_eq!(manager.update_animations(&engine), vec![id]);
+        assert_eq!(finished.wait().await.unwrap(), id);
+        assert!(manager.get_animations().is_empty());
+        
+        assert!(manager.animate("missing", &json!([{"opacity": 0}]), &json!(500), &mut engine).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_content_editable_typing_and_backspace() {
+        use std::sync::{Arc, Mutex};
+        
+        let mut manager = DomIntegrationManager::new().await.unwrap();
+        manager.parse_html("https://example.com").await.unwrap();
+        let mut editor = Element::new("div".to_string());
+        editor.set_attribute("id".to_string(), "editor".to_string());
+        editor.set_attribute("contenteditable".to_string(), "true".to_string());
+        manager.insert_element(None, editor).await.unwrap();
+        
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let log = observed.clone();
+        manager.add_mutation_observer("editor", vec![MutationType::CharacterData], move |records| {
+            log.lock().unwrap().extend(records.into_iter().map(|record| record.old_value));
+        }).await.unwrap();
+        
+        let key = |key: &str| dom::events::KeyboardEventData {
+            key: key.to_string(),
+            code: String::new(),
+            key_code: 0,
+            ctrl_key: false,
+            shift_key: false,
+            alt_key: false,
+            meta_key: false,
+            repeat: false,
+            is_composing: false,
+        };
+        
+        for character in ["H", "i", "!"] {
+            manager.handle_key_event("editor", &key(character)).await.unwrap();
+        }
+        let text = |manager: &DomIntegrationManager| {
+            manager.element_texts().unwrap().into_iter().find(|(id, _)| id == "editor").map(|(_, text)| text)
+        };
+        assert_eq!(text(&manager).as_deref(), Some("Hi!"));
+        assert!(manager.get_selection().is_collapsed());
+        assert_eq!(manager.get_selection().focus, Some(SelectionBoundary { node_id: "editor".to_string(), offset: 3 }));
+        
+        // Typing at a caret moved into the text inserts there
+        manager.set_selection_range("editor", 1, "editor", 1).unwrap();
+        let mutation = manager.handle_key_event("editor", &key("e")).await.unwrap();
+        assert_eq!(mutation, Some(DomMutation::InsertText { element_id: "editor".to_string(), offset: 1, text: "e".to_string() }));
+        assert_eq!(text(&manager).as_deref(), Some("Hei!"));
+        
+        let mutation = manager.handle_key_event("editor", &key("Backspace")).await.unwrap();
+        assert_eq!(mutation, Some(DomMutation::DeleteText { element_id: "editor".to_string(), offset: 1, text: "e".to_string() }));
+        assert_eq!(text(&manager).as_deref(), Some("Hi!"));
+        assert_eq!(manager.get_selection().focus, Some(SelectionBoundary { node_id: "editor".to_string(), offset: 1 }));
+        
+        assert_eq!(*observed.lock().unwrap(), vec![
+            Some(String::new()),
+            Some("H".to_string()),
+            Some("Hi".to_string()),
+            Some("Hi!".to_string()),
+            Some("Hei!".to_string()),
+        ]);
+        
+        // Bold wraps the selection in <strong>
+        manager.set_selection_range("editor", 0, "editor", 2).unwrap();
+        assert!(manager.exec_command("bold").await.unwrap());
+        assert!(!manager.exec_command("unknown").await.unwrap());
+        let (document, _) = manager.snapshot_document();
+        let document = document.unwrap();
+        let strong = document.get_elements_by_tag_name("strong");
+        assert_eq!(strong.len(), 1);
+        assert_eq!(strong[0].text_content(), "Hi");
+        assert_eq!(manager.get_selection().to_string(), "Hi");
+        
+        // Elements outside contenteditable are not edited
+        assert_eq!(manager.handle_key_event("content", &key("x")).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_submit_cancelled_for_invalid_form() {
+        use std::sync::{Arc, Mutex};
+        
+        let mut manager = DomIntegrationManager::new().await.unwrap();
+        manager.parse_html("https://example.com").await.unwrap();
+        let mut form = Element::new("form".to_string());
+        form.set_attribute("id".to_string(), "signup".to_string());
+        manager.insert_element(None, form).await.unwrap();
+        let mut email = Element::new("input".to_string());
+        email.set_attribute("id".to_string(), "email".to_string());
+        email.set_attribute("required".to_string(), String::new());
+        manager.insert_element(Some("signup"), email).await.unwrap();
+        
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let (submitted, invalid) = (events.clone(), events.clone());
+        manager.add_event_listener("signup", "submit", move |_| submitted.lock().unwrap().push("submit".to_string())).await.unwrap();
+        manager.add_event_listener("email", "invalid", move |data| {
+            invalid.lock().unwrap().push(data["validationMessage"].as_str().unwrap().to_string());
+        }).await.unwrap();
+        
+        manager.trigger_event("signup", "submit", serde_json::json!({})).await.unwrap();
+        assert_eq!(*events.lock().unwrap(), vec!["Please fill out this field.".to_string()]);
+        
+        manager.set_attribute("email", "value", "user@example.com").await.unwrap();
+        manager.trigger_event("signup", "submit", serde_json::json!({})).await.unwrap();
+        assert_eq!(events.lock().unwrap().last().map(String::as_str), Some("submit"));
+        
+        manager.set_custom_validity("email", "Address is taken");
+        assert!(!manager.check_form_validity("signup").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_request_fullscreen_asks_browser_and_fires_fullscreenchange() {
+        let (renderer_end, browser_end) = common::ipc::ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let mut manager = DomIntegrationManager::new().await.unwrap();
+        manager.parse_html("https://example.com").await.unwrap();
+        manager.connect_browser(TabId::new(7), renderer_end);
+        
+        let mut video = Element::new("video".to_string());
+        video.set_attribute("id".to_string(), "player".to_string());
+        manager.insert_element(None, video).await.unwrap();
+        
+        let fired = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        for target in ["player", DOCUMENT_TARGET] {
+            let fired = fired.clone();
+            manager.add_event_listener(target, "fullscreenchange", move |event| {
+                fired.lock().unwrap().push((target, event["fullscreenElement"].clone()));
+            }).await.unwrap();
+        }
+        
+        assert!(manager.request_fullscreen("missing").await.is_err());
+        manager.request_fullscreen("player").await.unwrap();
+        assert_eq!(manager.fullscreen_element(), Some("player"));
+        match browser_end.recv_async().await.unwrap() {
+            IpcMessage::BrowserRequest(request) => assert_eq!(request, BrowserRequest::EnterFullscreen {
+                tab_id: TabId::new(7),
+                element_id: "player".to_string(),
+            }),
+            other => panic!("Unexpected message {:?}", other),
+        }
+        
+        manager.exit_fullscreen().await.unwrap();
+        assert_eq!(manager.fullscreen_element(), None);
+        match browser_end.recv_async().await.unwrap() {
+            IpcMessage::BrowserRequest(request) => assert_eq!(request, BrowserRequest::ExitFullscreen { tab_id: TabId::new(7) }),
+            other => panic!("Unexpected message {:?}", other),
+        }
+        assert!(manager.exit_fullscreen().await.is_err());
+        
+        let fired = fired.lock().unwrap();
+        assert_eq!(*fired, vec![
+            ("player", Value::from("player")),
+            (DOCUMENT_TARGET, Value::from("player")),
+            ("player", Value::Null),
+            (DOCUMENT_TARGET, Value::Null),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_requests_go_to_browser() {
+        let (renderer_end, browser_end) = common::ipc::ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let mut manager = DomIntegrationManager::new().await.unwrap();
+        manager.connect_browser(TabId::new(3), renderer_end);
+        
+        let browser = tokio::spawn(async move {
+            let IpcMessage::ClipboardWrite(write) = browser_end.recv_async().await.unwrap() else {
+                panic!("Expected a clipboard write");
+            };
+            assert_eq!((write.tab_id, write.text.as_str()), (TabId::new(3), "copied"));
+            browser_end.send_async(IpcMessage::ClipboardResponse(common::ipc::ClipboardResponseMessage {
+                request_id: write.request_id,
+                text: None,
+                error: None,
+            })).await.unwrap();
+            
+            let IpcMessage::ClipboardRead(read) = browser_end.recv_async().await.unwrap() else {
+                panic!("Expected a clipboard read");
+            };
+            browser_end.send_async(IpcMessage::ClipboardResponse(common::ipc::ClipboardResponseMessage {
+                request_id: read.request_id,
+                text: None,
+                error: Some("Clipboard read is not allowed".to_string()),
+            })).await.unwrap();
+        });
+        
+        manager.write_clipboard_text("copied").await.unwrap();
+        assert!(matches!(manager.read_clipboard_text().await, Err(common::error::Error::PermissionDenied(_))));
+        browser.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_system_requests_go_to_browser() {
+        let (renderer_end, browser_end) = common::ipc::ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let mut manager = DomIntegrationManager::new().await.unwrap();
+        manager.connect_browser(TabId::new(5), renderer_end);
+        
+        let browser = tokio::spawn(async move {
+            let IpcMessage::FileSystemRequest(FileSystemRequest::ShowOpenFilePicker { request_id, tab_id, options }) = browser_end.recv_async().await.unwrap() else {
+                panic!("Expected an open file picker request");
+            };
+            assert_eq!((tab_id, options["multiple"].as_bool()), (TabId::new(5), Some(true)));
+            browser_end.send_async(IpcMessage::FileSystemResponse(FileSystemResponse {
+                request_id,
+                handles: vec![FileHandleInfo { handle_id: 9, name: "notes.txt".to_string() }],
+                data: None,
+                error: None,
+            })).await.unwrap();
+            
+            let IpcMessage::FileSystemRequest(FileSystemRequest::ReadFile { request_id, handle_id, .. }) = browser_end.recv_async().await.unwrap() else {
+                panic!("Expected a file read");
+            };
+            assert_eq!(handle_id, 9);
+            browser_end.send_async(IpcMessage::FileSystemResponse(FileSystemResponse {
+                request_id,
+                handles: Vec::new(),
+                data: Some(b"hello".to_vec()),
+                error: None,
+            })).await.unwrap();
+        });
+        
+        let handles = manager.show_open_file_picker(serde_json::json!({ "multiple": true })).await.unwrap();
+        assert_eq!(handles, vec![FileHandleInfo { handle_id: 9, name: "notes.txt".to_string() }]);
+        assert_eq!(manager.read_file_handle(9).await.unwrap(), b"hello");
+        browser.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_geolocation_requests_go_to_browser() {
+        let (renderer_end, browser_end) = common::ipc::ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let mut manager = DomIntegrationManager::new().await.unwrap();
+        manager.connect_browser(TabId::new(4), renderer_end);
+        
+        let browser = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in [
+                serde_json::json!({ "coords": { "latitude": 35.6586, "longitude": 139.7454 }, "timestamp": 1 }),
+                Value::Null,
+            ] {
+                let IpcMessage::GeolocationRequest(request) = browser_end.recv_async().await.unwrap() else {
+                    panic!("Expected a geolocation request");
+                };
+                let (position, error) = match response {
+                    Value::Null => (None, Some(GeolocationError { code: GeolocationError::PERMISSION_DENIED, message: "denied".to_string() })),
+                    position => (Some(position), None),
+                };
+                browser_end.send_async(IpcMessage::GeolocationResponse(common::ipc::GeolocationResponseMessage {
+                    request_id: request.request_id,
+                    position,
+                    error,
+                })).await.unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+        
+        let position = manager.get_current_position(&serde_json::json!({ "enableHighAccuracy": true, "timeout": 5000 })).await.unwrap();
+        assert_eq!(position["coords"]["latitude"], 35.6586);
+        let denied = manager.get_current_position(&serde_json::json!({})).await;
+        assert!(matches!(denied, Err(common::error::Error::PermissionDenied(_))));
+        
+        let requests = browser.await.unwrap();
+        assert_eq!(requests[0].tab_id, TabId::new(4));
+        assert_eq!((requests[0].enable_high_accuracy, requests[0].timeout), (true, Some(5000)));
+        assert_eq!((requests[1].enable_high_accuracy, requests[1].timeout, requests[1].maximum_age), (false, None, 0));
+    }
+
+    #[tokio::test]
+    async fn test_request_picture_in_picture_sends_video_size() {
+        let (renderer_end, browser_end) = common::ipc::ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let (sender, receiver) = watch::channel(LayoutGeometry::default());
+        let mut manager = DomIntegrationManager::new().await.unwrap();
+        manager.parse_html("https://example.com").await.unwrap();
+        manager.connect_browser(TabId::new(2), renderer_end);
+        manager.subscribe_layout_updates(receiver);
+        
+        for (id, tag) in [("player", "video"), ("poster", "img")] {
+            let mut element = Element::new(tag.to_string());
+            element.set_attribute("id".to_string(), id.to_string());
+            manager.insert_element(None, element).await.unwrap();
+        }
+        let mut geometry = LayoutGeometry::default();
+        geometry.elements.insert("player".to_string(), crate::rendering_pipeline::Rectangle { x: 0.0, y: 40.0, width: 640.0, height: 360.0 });
+        sender.send_replace(geometry);
+        
+        let fired = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        for event_type in ["enterpictureinpicture", "leavepictureinpicture"] {
+            let fired = fired.clone();
+            manager.add_event_listener("player", event_type, move |event| {
+                fired.lock().unwrap().push((event_type, event["pictureInPictureElement"].clone()));
+            }).await.unwrap();
+        }
+        
+        assert!(manager.request_picture_in_picture("poster").await.is_err());
+        manager.request_picture_in_picture("player").await.unwrap();
+        assert_eq!(manager.picture_in_picture_element(), Some("player"));
+        match browser_end.recv_async().await.unwrap() {
+            IpcMessage::BrowserRequest(request) => assert_eq!(request, BrowserRequest::EnterPictureInPicture {
+                tab_id: TabId::new(2),
+                element_id: "player".to_string(),
+                width: 640,
+                height: 360,
+            }),
+            other => panic!("Unexpected message {:?}", other),
+        }
+        
+        manager.exit_picture_in_picture().await.unwrap();
+        match browser_end.recv_async().await.unwrap() {
+            IpcMessage::BrowserRequest(request) => assert_eq!(request, BrowserRequest::ExitPictureInPicture { tab_id: TabId::new(2) }),
+            other => panic!("Unexpected message {:?}", other),
+        }
+        assert!(manager.exit_picture_in_picture().await.is_err());
+        
+        let fired = fired.lock().unwrap();
+        assert_eq!(*fired, vec![
+            ("enterpictureinpicture", Value::from("player")),
+            ("leavepictureinpicture", Value::Null),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_smooth_scroll_moves_viewport_each_frame() {
+        let config = crate::RendererConfig::default();
+        let mut pipeline = crate::rendering_pipeline::RenderingPipeline::new(&config).await.unwrap();
+        pipeline.initialize().await.unwrap();
+        let mut manager = DomIntegrationManager::new().await.unwrap();
+        manager.subscribe_layout_updates(pipeline.subscribe_layout_updates());
+        pipeline.set_smooth_scroll(manager.smooth_scroll());
+        
+        manager.smooth_scroll_to(0.0, 1000.0);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        pipeline.render_page().await.unwrap();
+        
+        // Part of the way there after the first frame
+        let scrolled = pipeline.layout_geometry().viewport.y;
+        assert!(scrolled > 0.0 && scrolled < 1000.0);
+        
+        // Wheel ticks add to the target of the running animation
+        manager.smooth_scroll_by(0.0, 100.0);
+        assert_eq!(manager.smooth_scroll().target(), Some(Point { x: 0.0, y: 1100.0 }));
+        
+        manager.smooth_scroll_to(0.0, -50.0);
+        assert_eq!(manager.smooth_scroll().target(), Some(Point { x: 0.0, y: 0.0 }));
+    }
 }
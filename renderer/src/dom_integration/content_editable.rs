@@ -0,0 +1,583 @@
+//! Editing of `contenteditable` regions
+//!
+//! Keyboard events on an editable element are turned into a `DomMutation`
+//! at the caret, the collapsed selection. Offsets are into the element's
+//! text as the selection counts it, with each `<br>` as a line break.
+
+use common::error::{Error, Result};
+use dom::events::KeyboardEventData;
+use dom::{Element, Node, TextNode};
+
+use super::selection::{Selection, SelectionBoundary};
+use super::{MutationRecord, MutationType};
+
+/// Elements that Enter splits instead of inserting a `<br>`
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p", "div", "h1", "h2", "h3", "h4", "h5", "h6", "li", "blockquote", "pre", "section", "article",
+];
+
+/// Change to the document made by an editing action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DomMutation {
+    /// Text typed at an offset
+    InsertText {
+        element_id: String,
+        offset: usize,
+        text: String,
+    },
+
+    /// Text removed from an offset, `\n` for a removed `<br>`
+    DeleteText {
+        element_id: String,
+        offset: usize,
+        text: String,
+    },
+
+    /// Selected text typed over
+    ReplaceText {
+        element_id: String,
+        offset: usize,
+        removed: String,
+        inserted: String,
+    },
+
+    /// `<br>` inserted at an offset
+    InsertLineBreak {
+        element_id: String,
+        offset: usize,
+    },
+
+    /// Block element split at an offset, the rest moving into a new sibling
+    SplitBlock {
+        element_id: String,
+        offset: usize,
+    },
+
+    /// Text between two offsets wrapped in an inline element, such as `<strong>`
+    WrapText {
+        element_id: String,
+        start: usize,
+        end: usize,
+        tag_name: String,
+    },
+
+    /// The event did not change the document
+    None,
+}
+
+/// Mutation records of an applied mutation, and where the selection goes
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AppliedMutation {
+    pub records: Vec<MutationRecord>,
+    pub selection: Option<(SelectionBoundary, SelectionBoundary)>,
+}
+
+/// Editor for the `contenteditable` regions of a document
+pub struct ContentEditableEditor<'a> {
+    /// Root of the document
+    root: &'a Element,
+
+    /// Selection of the document, collapsed to a caret while typing
+    selection: &'a Selection,
+}
+
+impl<'a> ContentEditableEditor<'a> {
+    /// Create an editor over a document and its selection
+    pub fn new(root: &'a Element, selection: &'a Selection) -> Self {
+        Self { root, selection }
+    }
+
+    /// Turn a key press on an editable element into a mutation
+    ///
+    /// Printable characters type over the selection, Backspace and Delete
+    /// remove it or the character next to the caret, and Enter splits block
+    /// elements or inserts a `<br>`. Without a caret in the element, editing
+    /// happens at the end of its text.
+    pub fn handle_key(&self, element_id: &str, event: &KeyboardEventData) -> Result<DomMutation> {
+        let element = find_element(self.root, element_id)
+            .ok_or_else(|| Error::NotFound(format!("Element {} not found", element_id)))?;
+        let host = editing_host(self.root, element_id)
+            .ok_or_else(|| Error::InvalidState(format!("Element {} is not editable", element_id)))?;
+
+        if event.ctrl_key || event.meta_key || event.alt_key || event.is_composing {
+            return Ok(DomMutation::None);
+        }
+
+        let text: Vec<char> = super::element_text(element).chars().collect();
+        let (start, end) = self.caret_range(element_id, text.len());
+        let element_id = element_id.to_string();
+        let selected: String = text[start..end].iter().collect();
+
+        let mutation = match event.key.as_str() {
+            "Backspace" | "Delete" if start < end => DomMutation::DeleteText { element_id, offset: start, text: selected },
+            "Backspace" if start > 0 => DomMutation::DeleteText {
+                element_id,
+                offset: start - 1,
+                text: text[start - 1].to_string(),
+            },
+            "Delete" if start < text.len() => DomMutation::DeleteText {
+                element_id,
+                offset: start,
+                text: text[start].to_string(),
+            },
+            "Enter" => {
+                let is_host = element_key(element) == element_key(host);
+                if event.shift_key || is_host || !BLOCK_ELEMENTS.contains(&element.tag_name.as_str()) {
+                    DomMutation::InsertLineBreak { element_id, offset: start }
+                } else {
+                    DomMutation::SplitBlock { element_id, offset: start }
+                }
+            }
+            key if key.chars().count() == 1 && !key.chars().any(char::is_control) => {
+                if start < end {
+                    DomMutation::ReplaceText { element_id, offset: start, removed: selected, inserted: key.to_string() }
+                } else {
+                    DomMutation::InsertText { element_id, offset: start, text: key.to_string() }
+                }
+            }
+            _ => DomMutation::None,
+        };
+
+        Ok(mutation)
+    }
+
+    /// Run a formatting command, as `document.execCommand()` does
+    ///
+    /// `bold` and `italic` wrap the selected text of editable elements in
+    /// `<strong>` and `<em>`. Unsupported commands make no mutations.
+    pub fn exec_command(&self, command: &str) -> Result<Vec<DomMutation>> {
+        let tag_name = match command.to_ascii_lowercase().as_str() {
+            "bold" => "strong",
+            "italic" => "em",
+            _ => return Ok(Vec::new()),
+        };
+
+        Ok(self.selection.selected_ranges()
+            .into_iter()
+            .filter(|(element_id, _, _)| editing_host(self.root, element_id).is_some())
+            .map(|(element_id, start, end)| DomMutation::WrapText {
+                element_id: element_id.to_string(),
+                start,
+                end,
+                tag_name: tag_name.to_string(),
+            })
+            .collect())
+    }
+
+    /// Selected offsets in an element, or the caret at the end of its text
+    fn caret_range(&self, element_id: &str, len: usize) -> (usize, usize) {
+        let in_element = |boundary: &Option<SelectionBoundary>| {
+            boundary.as_ref().filter(|boundary| boundary.node_id == element_id).map(|boundary| boundary.offset.min(len))
+        };
+
+        match (in_element(&self.selection.anchor), in_element(&self.selection.focus)) {
+            (Some(anchor), Some(focus)) => (anchor.min(focus), anchor.max(focus)),
+            (_, Some(focus)) => (focus, focus),
+            _ => (len, len),
+        }
+    }
+}
+
+impl DomMutation {
+    /// Apply the mutation to a document
+    pub(crate) fn apply(&self, root: &mut Element) -> Result<AppliedMutation> {
+        let caret = |element_id: &str, offset: usize| {
+            let boundary = SelectionBoundary { node_id: element_id.to_string(), offset };
+            Some((boundary.clone(), boundary))
+        };
+
+        let applied = match self {
+            DomMutation::InsertText { element_id, offset, text } => {
+                let element = find_element_mut(root, element_id)?;
+                let old_text = super::element_text(element);
+                insert_text(element, *offset, text)?;
+                AppliedMutation {
+                    records: vec![character_data_record(element_id, old_text)],
+                    selection: caret(element_id, offset + text.chars().count()),
+                }
+            }
+            DomMutation::DeleteText { element_id, offset, text } => {
+                let element = find_element_mut(root, element_id)?;
+                let old_text = super::element_text(element);
+                delete_text(element, *offset, text.chars().count())?;
+                AppliedMutation {
+                    records: vec![character_data_record(element_id, old_text)],
+                    selection: caret(element_id, *offset),
+                }
+            }
+            DomMutation::ReplaceText { element_id, offset, removed, inserted } => {
+                let element = find_element_mut(root, element_id)?;
+                let old_text = super::element_text(element);
+                delete_text(element, *offset, removed.chars().count())?;
+                insert_text(element, *offset, inserted)?;
+                AppliedMutation {
+                    records: vec![character_data_record(element_id, old_text)],
+                    selection: caret(element_id, offset + inserted.chars().count()),
+                }
+            }
+            DomMutation::InsertLineBreak { element_id, offset } => {
+                let element = find_element_mut(root, element_id)?;
+                let index = split_at(element, *offset)?;
+                let line_break = Element::new("br".to_string());
+                let mut record = MutationRecord::new(MutationType::ChildList, element_id);
+                record.added_nodes.push(line_break.id.clone());
+                element.children.insert(index, Node::Element(line_break));
+                AppliedMutation {
+                    records: vec![record],
+                    selection: caret(element_id, offset + 1),
+                }
+            }
+            DomMutation::SplitBlock { element_id, offset } => {
+                let (parent, index) = find_parent_mut(root, element_id)
+                    .ok_or_else(|| Error::NotFound(format!("Parent of element {} not found", element_id)))?;
+                let Node::Element(block) = &mut parent.children[index] else {
+                    unreachable!("find_parent_mut returns the index of an element");
+                };
+                let at = split_at(block, *offset)?;
+                let mut new_block = Element::new(block.tag_name.clone());
+                new_block.attributes = block.attributes.clone();
+                new_block.set_attribute("id".to_string(), new_block.id.clone());
+                new_block.children = block.children.split_off(at);
+                let new_block_id = new_block.id.clone();
+
+                let mut record = MutationRecord::new(MutationType::ChildList, &element_key(parent));
+                record.added_nodes.push(new_block_id.clone());
+                record.previous_sibling_id = Some(element_id.clone());
+                parent.children.insert(index + 1, Node::Element(new_block));
+                AppliedMutation {
+                    records: vec![record],
+                    selection: caret(&new_block_id, 0),
+                }
+            }
+            DomMutation::WrapText { element_id, start, end, tag_name } => {
+                let element = find_element_mut(root, element_id)?;
+                let wrapper_ids = wrap_text(element, *start, *end, tag_name)?;
+                let mut record = MutationRecord::new(MutationType::ChildList, element_id);
+                record.added_nodes = wrapper_ids.clone();
+
+                // Select the wrapped text
+                let selection = match (wrapper_ids.first(), wrapper_ids.last()) {
+                    (Some(first), Some(last)) => {
+                        let last_len = find_element(element, last).map_or(0, |last| super::element_text(last).chars().count());
+                        Some((
+                            SelectionBoundary { node_id: first.clone(), offset: 0 },
+                            SelectionBoundary { node_id: last.clone(), offset: last_len },
+                        ))
+                    }
+                    _ => None,
+                };
+                AppliedMutation {
+                    records: vec![record],
+                    selection,
+                }
+            }
+            DomMutation::None => AppliedMutation::default(),
+        };
+
+        Ok(applied)
+    }
+}
+
+/// Find the editing host of an element, the outermost editable element containing it
+pub fn editing_host<'a>(root: &'a Element, element_id: &str) -> Option<&'a Element> {
+    let path = element_path(root, element_id)?;
+    let mut host = None;
+
+    for element in path {
+        match element.get_attribute("contenteditable").map(|value| value.to_ascii_lowercase()).as_deref() {
+            Some("" | "true" | "plaintext-only") => {
+                host = host.or(Some(element));
+            }
+            Some("false") => host = None,
+            _ => {}
+        }
+    }
+
+    host
+}
+
+/// ID of an element, its `id` attribute or the ID it was created with
+pub(crate) fn element_key(element: &Element) -> String {
+    element.get_attribute("id").cloned().unwrap_or_else(|| element.id.clone())
+}
+
+fn find_element<'a>(element: &'a Element, element_id: &str) -> Option<&'a Element> {
+    if element_key(element) == element_id {
+        return Some(element);
+    }
+    element.children.iter().find_map(|child| match child {
+        Node::Element(child) => find_element(child, element_id),
+        _ => None,
+    })
+}
+
+fn find_element_mut<'a>(element: &'a mut Element, element_id: &str) -> Result<&'a mut Element> {
+    fn find<'a>(element: &'a mut Element, element_id: &str) -> Option<&'a mut Element> {
+        if element_key(element) == element_id {
+            return Some(element);
+        }
+        element.children.iter_mut().find_map(|child| match child {
+            Node::Element(child) => find(child, element_id),
+            _ => None,
+        })
+    }
+
+    find(element, element_id).ok_or_else(|| Error::NotFound(format!("Element {} not found", element_id)))
+}
+
+/// Find the parent of an element and the element's index among its children
+fn find_parent_mut<'a>(element: &'a mut Element, element_id: &str) -> Option<(&'a mut Element, usize)> {
+    let index = element.children.iter().position(|child| {
+        matches!(child, Node::Element(child) if element_key(child) == element_id)
+    });
+    match index {
+        Some(index) => Some((element, index)),
+        None => element.children.iter_mut().find_map(|child| match child {
+            Node::Element(child) => find_parent_mut(child, element_id),
+            _ => None,
+        }),
+    }
+}
+
+/// Elements from the root down to an element
+fn element_path<'a>(element: &'a Element, element_id: &str) -> Option<Vec<&'a Element>> {
+    if element_key(element) == element_id {
+        return Some(vec![element]);
+    }
+    element.children.iter().find_map(|child| match child {
+        Node::Element(child) => element_path(child, element_id).map(|mut path| {
+            path.insert(0, element);
+            path
+        }),
+        _ => None,
+    })
+}
+
+fn character_data_record(element_id: &str, old_text: String) -> MutationRecord {
+    let mut record = MutationRecord::new(MutationType::CharacterData, element_id);
+    record.old_value = Some(old_text);
+    record
+}
+
+fn index_size_error(offset: usize) -> Error {
+    Error::InvalidState(format!("IndexSizeError: offset {} is past the end of the text", offset))
+}
+
+fn byte_index(text: &str, char_offset: usize) -> usize {
+    text.char_indices().nth(char_offset).map_or(text.len(), |(index, _)| index)
+}
+
+/// Insert text at an offset, into the text node there or a new one
+fn insert_text(element: &mut Element, offset: usize, text: &str) -> Result<()> {
+    let mut position = 0;
+
+    for index in 0..element.children.len() {
+        match &mut element.children[index] {
+            Node::Text(node) => {
+                let len = node.content.chars().count();
+                if offset <= position + len {
+                    let byte = byte_index(&node.content, offset - position);
+                    node.content.insert_str(byte, text);
+                    return Ok(());
+                }
+                position += len;
+            }
+            Node::Element(child) if child.tag_name == "br" => {
+                if offset == position {
+                    element.children.insert(index, Node::Text(TextNode::new(text.to_string())));
+                    return Ok(());
+                }
+                position += 1;
+            }
+            _ => {}
+        }
+    }
+
+    if offset != position {
+        return Err(index_size_error(offset));
+    }
+    element.children.push(Node::Text(TextNode::new(text.to_string())));
+    Ok(())
+}
+
+/// Remove characters from an offset, dropping emptied text nodes and removed `<br>`s
+fn delete_text(element: &mut Element, offset: usize, length: usize) -> Result<()> {
+    let end = offset + length;
+    let mut removed = 0;
+    let mut position = 0;
+    let mut index = 0;
+
+    while index < element.children.len() && position < end {
+        match &mut element.children[index] {
+            Node::Text(node) => {
+                let chars: Vec<char> = node.content.chars().collect();
+                let from = offset.saturating_sub(position).min(chars.len());
+                let to = end.saturating_sub(position).min(chars.len());
+                position += chars.len();
+                if from < to {
+                    removed += to - from;
+                    node.content = chars[..from].iter().chain(&chars[to..]).collect();
+                    if node.content.is_empty() {
+                        element.children.remove(index);
+                        continue;
+                    }
+                }
+            }
+            Node::Element(child) if child.tag_name == "br" => {
+                let at = position;
+                position += 1;
+                if at >= offset {
+                    removed += 1;
+                    element.children.remove(index);
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+
+    if removed != length {
+        return Err(index_size_error(end));
+    }
+    Ok(())
+}
+
+/// Split the children at an offset, returning the index a node can be inserted at
+fn split_at(element: &mut Element, offset: usize) -> Result<usize> {
+    let mut position = 0;
+
+    for index in 0..element.children.len() {
+        match &mut element.children[index] {
+            Node::Text(node) => {
+                let len = node.content.chars().count();
+                if offset == position {
+                    return Ok(index);
+                }
+                if offset == position + len {
+                    return Ok(index + 1);
+                }
+                if offset < position + len {
+                    let rest = node.content.split_off(byte_index(&node.content, offset - position));
+                    element.children.insert(index + 1, Node::Text(TextNode::new(rest)));
+                    return Ok(index + 1);
+                }
+                position += len;
+            }
+            Node::Element(child) if child.tag_name == "br" => {
+                if offset == position {
+                    return Ok(index);
+                }
+                position += 1;
+            }
+            _ => {}
+        }
+    }
+
+    if offset != position {
+        return Err(index_size_error(offset));
+    }
+    Ok(element.children.len())
+}
+
+/// Wrap the text between two offsets in new elements, one per text node
+fn wrap_text(element: &mut Element, start: usize, end: usize, tag_name: &str) -> Result<Vec<String>> {
+    let from = split_at(element, start)?;
+    let to = split_at(element, end)?;
+    let mut wrapper_ids = Vec::new();
+
+    for child in &mut element.children[from..to] {
+        if let Node::Text(text) = child {
+            let mut wrapper = Element::new(tag_name.to_string());
+            wrapper.set_attribute("id".to_string(), wrapper.id.clone());
+            wrapper_ids.push(wrapper.id.clone());
+            wrapper.append_child(Node::Text(text.clone()));
+            *child = Node::Element(wrapper);
+        }
+    }
+
+    Ok(wrapper_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: &str) -> KeyboardEventData {
+        KeyboardEventData {
+            key: key.to_string(),
+            code: String::new(),
+            key_code: 0,
+            ctrl_key: false,
+            shift_key: false,
+            alt_key: false,
+            meta_key: false,
+            repeat: false,
+            is_composing: false,
+        }
+    }
+
+    fn editor_root() -> Element {
+        let mut paragraph = Element::new("p".to_string());
+        paragraph.set_attribute("id".to_string(), "para".to_string());
+        paragraph.append_child(Node::Text(TextNode::new("Helo".to_string())));
+        let mut host = Element::new("div".to_string());
+        host.set_attribute("id".to_string(), "editor".to_string());
+        host.set_attribute("contenteditable".to_string(), "true".to_string());
+        host.append_child(Node::Element(paragraph));
+        let mut body = Element::new("body".to_string());
+        body.append_child(Node::Element(host));
+        body
+    }
+
+    fn caret(root: &Element, element_id: &str, offset: usize) -> Selection {
+        let mut selection = Selection::new();
+        selection.update_text(&[(element_id.to_string(), super::super::element_text(find_element(root, element_id).unwrap()))]);
+        selection.collapse(SelectionBoundary { node_id: element_id.to_string(), offset }).unwrap();
+        selection
+    }
+
+    #[test]
+    fn test_line_breaks_and_splits() {
+        let mut root = editor_root();
+        assert_eq!(element_key(editing_host(&root, "para").unwrap()), "editor");
+
+        let selection = caret(&root, "para", 2);
+        let mutation = ContentEditableEditor::new(&root, &selection).handle_key("para", &key("Enter")).unwrap();
+        assert_eq!(mutation, DomMutation::SplitBlock { element_id: "para".to_string(), offset: 2 });
+        let applied = mutation.apply(&mut root).unwrap();
+        let (caret, _) = applied.selection.unwrap();
+        assert_eq!(super::super::element_text(find_element(&root, "para").unwrap()), "He");
+        assert_eq!(super::super::element_text(find_element(&root, &caret.node_id).unwrap()), "lo");
+
+        let mut shift_enter = key("Enter");
+        shift_enter.shift_key = true;
+        let selection = self::caret(&root, "para", 1);
+        let mutation = ContentEditableEditor::new(&root, &selection).handle_key("para", &shift_enter).unwrap();
+        assert_eq!(mutation, DomMutation::InsertLineBreak { element_id: "para".to_string(), offset: 1 });
+        mutation.apply(&mut root).unwrap();
+        assert_eq!(super::super::element_text(find_element(&root, "para").unwrap()), "H\ne");
+
+        // Backspace after a line break removes the <br>
+        let selection = self::caret(&root, "para", 2);
+        let mutation = ContentEditableEditor::new(&root, &selection).handle_key("para", &key("Backspace")).unwrap();
+        assert_eq!(mutation, DomMutation::DeleteText { element_id: "para".to_string(), offset: 1, text: "\n".to_string() });
+        mutation.apply(&mut root).unwrap();
+        let paragraph = find_element(&root, "para").unwrap();
+        assert_eq!(super::super::element_text(paragraph), "He");
+        assert!(paragraph.children.iter().all(|child| matches!(child, Node::Text(_))));
+    }
+
+    #[test]
+    fn test_uneditable_elements_are_rejected() {
+        let mut root = editor_root();
+        root.set_attribute("id".to_string(), "body".to_string());
+        let selection = Selection::new();
+        assert!(ContentEditableEditor::new(&root, &selection).handle_key("body", &key("a")).is_err());
+
+        if let Node::Element(host) = &mut root.children[0] {
+            host.set_attribute("contenteditable".to_string(), "false".to_string());
+        }
+        assert!(editing_host(&root, "para").is_none());
+    }
+}
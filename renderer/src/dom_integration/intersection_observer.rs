@@ -0,0 +1,465 @@
+//! Intersection Observer for renderer processes
+//!
+//! Observers are evaluated after every layout pass published by the
+//! `RenderingPipeline`, reporting how much of each target is visible within
+//! its root (an ancestor element or the viewport).
+
+use common::error::{Error, Result};
+use std::collections::HashMap;
+use tokio::sync::watch;
+use tracing::debug;
+
+use crate::rendering_pipeline::{LayoutGeometry, Rectangle};
+
+/// Intersection observer identifier
+pub type ObserverId = u64;
+
+/// Element identifier
+pub type ElementId = String;
+
+/// Callback invoked with the entries of an observer
+pub type JsCallback = Box<dyn Fn(Vec<IntersectionObserverEntry>) + Send + Sync>;
+
+/// Options for creating an intersection observer
+#[derive(Debug, Clone)]
+pub struct IntersectionObserverInit {
+    /// Root element (the viewport if `None`)
+    pub root: Option<ElementId>,
+
+    /// Margin around the root, in CSS margin shorthand (`px` or `%`)
+    pub root_margin: String,
+
+    /// Intersection ratios at which the callback fires
+    pub thresholds: Vec<f64>,
+}
+
+impl Default for IntersectionObserverInit {
+    fn default() -> Self {
+        Self {
+            root: None,
+            root_margin: "0px".to_string(),
+            thresholds: vec![0.0],
+        }
+    }
+}
+
+/// Intersection observer
+pub struct IntersectionObserver {
+    /// Observer ID
+    pub observer_id: ObserverId,
+
+    /// Root element (the viewport if `None`)
+    pub root: Option<ElementId>,
+
+    /// Margin around the root
+    pub root_margin: String,
+
+    /// Sorted intersection thresholds
+    pub thresholds: Vec<f64>,
+
+    /// Callback function
+    pub callback: JsCallback,
+
+    /// Parsed root margin (top, right, bottom, left)
+    margin: [MarginValue; 4],
+
+    /// Observed targets
+    targets: HashMap<ElementId, TargetState>,
+}
+
+/// Intersection observer entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntersectionObserverEntry {
+    /// Observer that produced the entry
+    pub observer_id: ObserverId,
+
+    /// Observed element
+    pub target: ElementId,
+
+    /// Layout pass the entry was computed for
+    pub time: u64,
+
+    /// Root rectangle after applying the root margin
+    pub root_bounds: Rectangle,
+
+    /// Target border box
+    pub bounding_client_rect: Rectangle,
+
+    /// Visible part of the target
+    pub intersection_rect: Rectangle,
+
+    /// Visible fraction of the target
+    pub intersection_ratio: f64,
+
+    /// Whether the target intersects the root
+    pub is_intersecting: bool,
+}
+
+/// Last reported state of an observed target
+#[derive(Debug, Clone, Copy, Default)]
+struct TargetState {
+    /// Index of the first threshold above the last ratio (`None` before the first report)
+    threshold_index: Option<usize>,
+
+    /// Whether the target was intersecting at the last report
+    is_intersecting: bool,
+}
+
+/// A root margin component
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MarginValue {
+    Pixels(f32),
+    Percent(f32),
+}
+
+impl MarginValue {
+    fn resolve(&self, reference: f32) -> f32 {
+        match self {
+            MarginValue::Pixels(px) => *px,
+            MarginValue::Percent(pct) => reference * pct / 100.0,
+        }
+    }
+}
+
+/// Intersection observer manager
+pub struct IntersectionObserverManager {
+    /// Registered observers
+    observers: HashMap<ObserverId, IntersectionObserver>,
+
+    /// Layout geometry subscription
+    layout_updates: Option<watch::Receiver<LayoutGeometry>>,
+
+    /// Next observer ID
+    next_observer_id: ObserverId,
+}
+
+impl IntersectionObserverManager {
+    /// Create a new intersection observer manager
+    pub fn new() -> Self {
+        Self {
+            observers: HashMap::new(),
+            layout_updates: None,
+            next_observer_id: 1,
+        }
+    }
+
+    /// Subscribe to layout geometry updates from the rendering pipeline
+    pub fn subscribe(&mut self, layout_updates: watch::Receiver<LayoutGeometry>) {
+        self.layout_updates = Some(layout_updates);
+    }
+
+    /// Create an observer
+    pub fn create_observer<F>(&mut self, init: IntersectionObserverInit, callback: F) -> Result<ObserverId>
+    where
+        F: Fn(Vec<IntersectionObserverEntry>) + Send + Sync + 'static,
+    {
+        let margin = parse_root_margin(&init.root_margin)?;
+
+        let mut thresholds = if init.thresholds.is_empty() { vec![0.0] } else { init.thresholds };
+        if thresholds.iter().any(|t| !(0.0..=1.0).contains(t)) {
+            return Err(Error::DomError("Threshold values must be in the range [0, 1]".to_string()));
+        }
+        thresholds.sort_by(|a, b| a.total_cmp(b));
+
+        let observer_id = self.next_observer_id;
+        self.next_observer_id += 1;
+
+        self.observers.insert(observer_id, IntersectionObserver {
+            observer_id,
+            root: init.root,
+            root_margin: init.root_margin,
+            thresholds,
+            callback: Box::new(callback),
+            margin,
+            targets: HashMap::new(),
+        });
+
+        Ok(observer_id)
+    }
+
+    /// Start observing an element
+    pub fn observe(&mut self, observer_id: ObserverId, target: &str) -> Result<()> {
+        self.observer_mut(observer_id)?
+            .targets
+            .entry(target.to_string())
+            .or_default();
+        Ok(())
+    }
+
+    /// Stop observing an element
+    pub fn unobserve(&mut self, observer_id: ObserverId, target: &str) -> Result<()> {
+        self.observer_mut(observer_id)?.targets.remove(target);
+        Ok(())
+    }
+
+    /// Stop observing all elements and remove the observer
+    pub fn disconnect(&mut self, observer_id: ObserverId) -> Result<()> {
+        self.observers.remove(&observer_id)
+            .map(|_| ())
+            .ok_or_else(|| Error::NotFound(format!("Intersection observer {} not found", observer_id)))
+    }
+
+    /// Number of registered observers
+    pub fn observer_count(&self) -> usize {
+        self.observers.len()
+    }
+
+    /// Process a pending layout update, if any
+    pub fn poll_layout_updates(&mut self) -> Vec<IntersectionObserverEntry> {
+        let geometry = match self.layout_updates.as_mut() {
+            Some(receiver) if receiver.has_changed().unwrap_or(false) => receiver.borrow_and_update().clone(),
+            _ => return Vec::new(),
+        };
+        self.process_layout(&geometry)
+    }
+
+    /// Compute intersections for a layout pass and fire callbacks
+    pub fn process_layout(&mut self, geometry: &LayoutGeometry) -> Vec<IntersectionObserverEntry> {
+        let mut all_entries = Vec::new();
+
+        let mut observer_ids: Vec<ObserverId> = self.observers.keys().copied().collect();
+        observer_ids.sort_unstable();
+
+        for observer_id in observer_ids {
+            let Some(observer) = self.observers.get_mut(&observer_id) else { continue };
+            let entries = observer.compute_entries(geometry);
+            if !entries.is_empty() {
+                debug!("Intersection observer {} produced {} entries", observer_id, entries.len());
+                (observer.callback)(entries.clone());
+                all_entries.extend(entries);
+            }
+        }
+
+        all_entries
+    }
+
+    fn observer_mut(&mut self, observer_id: ObserverId) -> Result<&mut IntersectionObserver> {
+        self.observers.get_mut(&observer_id)
+            .ok_or_else(|| Error::NotFound(format!("Intersection observer {} not found", observer_id)))
+    }
+}
+
+impl Default for IntersectionObserverManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IntersectionObserver {
+    /// Compute entries for targets whose threshold or intersection state changed
+    fn compute_entries(&mut self, geometry: &LayoutGeometry) -> Vec<IntersectionObserverEntry> {
+        let root_rect = match &self.root {
+            Some(root) => match geometry.elements.get(root) {
+                Some(rect) => *rect,
+                // Root is not laid out; nothing can intersect it
+                None => return Vec::new(),
+            },
+            None => geometry.viewport,
+        };
+        let root_bounds = self.apply_margin(root_rect);
+
+        let mut targets: Vec<&ElementId> = self.targets.keys().collect();
+        targets.sort();
+
+        let mut entries = Vec::new();
+        let mut updates = Vec::new();
+        for target in targets {
+            let Some(target_rect) = geometry.elements.get(target) else { continue };
+
+            let intersection = intersect(&root_bounds, target_rect);
+            let is_intersecting = intersection.is_some();
+            let intersection_rect = intersection.unwrap_or_default();
+
+            let target_area = target_rect.width as f64 * target_rect.height as f64;
+            let intersection_ratio = if !is_intersecting {
+                0.0
+            } else if target_area > 0.0 {
+                (intersection_rect.width as f64 * intersection_rect.height as f64 / target_area).min(1.0)
+            } else {
+                1.0
+            };
+
+            let threshold_index = if is_intersecting {
+                self.thresholds.iter().take_while(|t| **t <= intersection_ratio).count()
+            } else {
+                0
+            };
+
+            let state = self.targets.get(target).copied().unwrap_or_default();
+            if state.threshold_index == Some(threshold_index) && state.is_intersecting == is_intersecting {
+                continue;
+            }
+
+            updates.push((target.clone(), TargetState {
+                threshold_index: Some(threshold_index),
+                is_intersecting,
+            }));
+            entries.push(IntersectionObserverEntry {
+                observer_id: self.observer_id,
+                target: target.clone(),
+                time: geometry.generation,
+                root_bounds,
+                bounding_client_rect: *target_rect,
+                intersection_rect,
+                intersection_ratio,
+                is_intersecting,
+            });
+        }
+
+        self.targets.extend(updates);
+        entries
+    }
+
+    /// Grow (or shrink) the root rectangle by the root margin
+    fn apply_margin(&self, rect: Rectangle) -> Rectangle {
+        let [top, right, bottom, left] = self.margin;
+        let top = top.resolve(rect.height);
+        let right = right.resolve(rect.width);
+        let bottom = bottom.resolve(rect.height);
+        let left = left.resolve(rect.width);
+
+        Rectangle {
+            x: rect.x - left,
+            y: rect.y - top,
+            width: (rect.width + left + right).max(0.0),
+            height: (rect.height + top + bottom).max(0.0),
+        }
+    }
+}
+
+/// Intersect two rectangles, treating edge-adjacent rectangles as intersecting
+fn intersect(a: &Rectangle, b: &Rectangle) -> Option<Rectangle> {
+    let left = a.x.max(b.x);
+    let top = a.y.max(b.y);
+    let right = (a.x + a.width).min(b.x + b.width);
+    let bottom = (a.y + a.height).min(b.y + b.height);
+
+    if right < left || bottom < top {
+        return None;
+    }
+
+    Some(Rectangle {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    })
+}
+
+/// Parse a root margin in CSS margin shorthand
+fn parse_root_margin(margin: &str) -> Result<[MarginValue; 4]> {
+    let values = margin.split_ascii_whitespace()
+        .map(|token| {
+            if let Some(px) = token.strip_suffix("px") {
+                px.parse().map(MarginValue::Pixels).ok()
+            } else if let Some(pct) = token.strip_suffix('%') {
+                pct.parse().map(MarginValue::Percent).ok()
+            } else if token == "0" {
+                Some(MarginValue::Pixels(0.0))
+            } else {
+                None
+            }
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| Error::DomError(format!("Invalid root margin: {}", margin)))?;
+
+    match values.as_slice() {
+        [] => Ok([MarginValue::Pixels(0.0); 4]),
+        [all] => Ok([*all; 4]),
+        [vertical, horizontal] => Ok([*vertical, *horizontal, *vertical, *horizontal]),
+        [top, horizontal, bottom] => Ok([*top, *horizontal, *bottom, *horizontal]),
+        [top, right, bottom, left] => Ok([*top, *right, *bottom, *left]),
+        _ => Err(Error::DomError(format!("Invalid root margin: {}", margin))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Rectangle {
+        Rectangle { x, y, width, height }
+    }
+
+    fn geometry(generation: u64, elements: &[(&str, Rectangle)]) -> LayoutGeometry {
+        LayoutGeometry {
+            generation,
+            viewport: rect(0.0, 0.0, 1000.0, 800.0),
+            elements: elements.iter().map(|(id, r)| (id.to_string(), *r)).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_threshold_crossing() {
+        let mut manager = IntersectionObserverManager::new();
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let sink = fired.clone();
+        let observer = manager.create_observer(
+            IntersectionObserverInit { thresholds: vec![0.0, 0.5, 1.0], ..Default::default() },
+            move |entries| sink.lock().unwrap().extend(entries),
+        ).unwrap();
+        manager.observe(observer, "img").unwrap();
+
+        // Below the fold: initial notification reports not intersecting
+        let entries = manager.process_layout(&geometry(1, &[("img", rect(0.0, 900.0, 100.0, 100.0))]));
+        assert_eq!(entries.len(), 1);
+        assert!(!entries[0].is_intersecting);
+
+        // No change, no entry
+        assert!(manager.process_layout(&geometry(2, &[("img", rect(0.0, 900.0, 100.0, 100.0))])).is_empty());
+
+        // Scrolled 75% into view
+        let entries = manager.process_layout(&geometry(3, &[("img", rect(0.0, 725.0, 100.0, 100.0))]));
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].is_intersecting);
+        assert!((entries[0].intersection_ratio - 0.75).abs() < 1e-6);
+
+        // Still between 0.5 and 1.0
+        assert!(manager.process_layout(&geometry(4, &[("img", rect(0.0, 710.0, 100.0, 100.0))])).is_empty());
+
+        // Fully visible
+        let entries = manager.process_layout(&geometry(5, &[("img", rect(0.0, 100.0, 100.0, 100.0))]));
+        assert_eq!(entries[0].intersection_ratio, 1.0);
+
+        assert_eq!(fired.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_root_element_and_margin() {
+        let mut manager = IntersectionObserverManager::new();
+        let observer = manager.create_observer(
+            IntersectionObserverInit {
+                root: Some("scroller".to_string()),
+                root_margin: "0px 0px 200px 0px".to_string(),
+                thresholds: vec![0.0],
+            },
+            |_| {},
+        ).unwrap();
+        manager.observe(observer, "lazy").unwrap();
+
+        // 100px below the scroller, but within the 200px bottom margin
+        let entries = manager.process_layout(&geometry(1, &[
+            ("scroller", rect(0.0, 0.0, 300.0, 300.0)),
+            ("lazy", rect(0.0, 400.0, 100.0, 100.0)),
+        ]));
+        assert!(entries[0].is_intersecting);
+        assert_eq!(entries[0].root_bounds, rect(0.0, 0.0, 300.0, 500.0));
+
+        manager.unobserve(observer, "lazy").unwrap();
+        manager.disconnect(observer).unwrap();
+        assert_eq!(manager.observer_count(), 0);
+        assert!(manager.observe(observer, "lazy").is_err());
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let mut manager = IntersectionObserverManager::new();
+        let init = IntersectionObserverInit { root_margin: "10em".to_string(), ..Default::default() };
+        assert!(manager.create_observer(init, |_| {}).is_err());
+
+        let init = IntersectionObserverInit { thresholds: vec![1.5], ..Default::default() };
+        assert!(manager.create_observer(init, |_| {}).is_err());
+    }
+}
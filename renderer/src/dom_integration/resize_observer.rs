@@ -0,0 +1,352 @@
+//! Resize Observer for renderer processes
+//!
+//! After every layout pass the `RenderingPipeline` compares the content box of
+//! each observed element with the size last reported for it. Changed sizes
+//! are queued as entries and delivered together in a microtask.
+
+use common::error::{Error, Result};
+use dom::mutation_observer::MicrotaskScheduler;
+use dom::Dimensions;
+use std::collections::HashMap;
+use tracing::debug;
+
+use super::intersection_observer::ElementId;
+use crate::rendering_pipeline::LayoutGeometry;
+
+/// Resize observer identifier
+pub type ObserverId = u64;
+
+/// Callback invoked with the entries of an observer
+pub type JsCallback = Box<dyn Fn(Vec<ResizeObserverEntry>) + Send + Sync>;
+
+/// A `DOMRectReadOnly`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DomRectReadOnly {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl DomRectReadOnly {
+    /// Create a rectangle
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Content rectangle of a box, relative to its padding box
+    pub fn content_rect(dimensions: &Dimensions) -> Self {
+        Self::new(
+            dimensions.padding_left,
+            dimensions.padding_top,
+            dimensions.content_width,
+            dimensions.content_height,
+        )
+    }
+
+    pub fn top(&self) -> f32 {
+        self.y.min(self.y + self.height)
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x.max(self.x + self.width)
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y.max(self.y + self.height)
+    }
+
+    pub fn left(&self) -> f32 {
+        self.x.min(self.x + self.width)
+    }
+}
+
+/// Size of a box in logical dimensions
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResizeObserverSize {
+    /// Size in the inline direction
+    pub inline_size: f32,
+
+    /// Size in the block direction
+    pub block_size: f32,
+}
+
+/// Resize observer entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResizeObserverEntry {
+    /// Observer that produced the entry
+    pub observer_id: ObserverId,
+
+    /// Observed element
+    pub target: ElementId,
+
+    /// Content rectangle of the target
+    pub content_rect: DomRectReadOnly,
+
+    /// Size of the target's content box
+    pub content_box_size: ResizeObserverSize,
+
+    /// Size of the target's border box
+    pub border_box_size: ResizeObserverSize,
+}
+
+impl ResizeObserverEntry {
+    /// Entry for a target laid out with the given dimensions
+    fn new(observer_id: ObserverId, target: &str, dimensions: &Dimensions) -> Self {
+        Self {
+            observer_id,
+            target: target.to_string(),
+            content_rect: DomRectReadOnly::content_rect(dimensions),
+            content_box_size: ResizeObserverSize {
+                inline_size: dimensions.content_width,
+                block_size: dimensions.content_height,
+            },
+            border_box_size: ResizeObserverSize {
+                inline_size: dimensions.total_width(),
+                block_size: dimensions.total_height(),
+            },
+        }
+    }
+}
+
+/// Resize observer
+pub struct ResizeObserver {
+    /// Observer ID
+    pub observer_id: ObserverId,
+
+    /// Callback function
+    pub callback: JsCallback,
+
+    /// Observed targets with the last entry reported for each
+    ///
+    /// Targets start at a zero size, so elements that are laid out empty are
+    /// not reported.
+    pub observed: HashMap<ElementId, ResizeObserverEntry>,
+
+    /// Entries queued for the next delivery
+    pending: Vec<ResizeObserverEntry>,
+}
+
+/// Resize observer manager
+pub struct ResizeObserverManager {
+    /// Registered observers
+    observers: HashMap<ObserverId, ResizeObserver>,
+
+    /// Next observer ID
+    next_observer_id: ObserverId,
+
+    /// Whether a delivery microtask has been scheduled and not yet run
+    microtask_queued: bool,
+
+    /// Hook that schedules the delivery microtask
+    microtask_scheduler: Option<MicrotaskScheduler>,
+}
+
+impl ResizeObserverManager {
+    /// Create a new resize observer manager
+    pub fn new() -> Self {
+        Self {
+            observers: HashMap::new(),
+            next_observer_id: 1,
+            microtask_queued: false,
+            microtask_scheduler: None,
+        }
+    }
+
+    /// Set the hook that schedules a microtask on the event loop
+    ///
+    /// The scheduled microtask must call `deliver_pending_entries`.
+    pub fn set_microtask_scheduler(&mut self, scheduler: MicrotaskScheduler) {
+        self.microtask_scheduler = Some(scheduler);
+    }
+
+    /// Create an observer
+    pub fn create_observer<F>(&mut self, callback: F) -> ObserverId
+    where
+        F: Fn(Vec<ResizeObserverEntry>) + Send + Sync + 'static,
+    {
+        let observer_id = self.next_observer_id;
+        self.next_observer_id += 1;
+
+        self.observers.insert(observer_id, ResizeObserver {
+            observer_id,
+            callback: Box::new(callback),
+            observed: HashMap::new(),
+            pending: Vec::new(),
+        });
+
+        observer_id
+    }
+
+    /// Start observing an element
+    pub fn observe(&mut self, observer_id: ObserverId, target: &str) -> Result<()> {
+        let observer = self.observer_mut(observer_id)?;
+        observer.observed
+            .entry(target.to_string())
+            .or_insert_with(|| ResizeObserverEntry::new(observer_id, target, &Dimensions::default()));
+        Ok(())
+    }
+
+    /// Stop observing an element
+    pub fn unobserve(&mut self, observer_id: ObserverId, target: &str) -> Result<()> {
+        let observer = self.observer_mut(observer_id)?;
+        observer.observed.remove(target);
+        observer.pending.retain(|entry| entry.target != target);
+        Ok(())
+    }
+
+    /// Stop observing all elements and remove the observer
+    pub fn disconnect(&mut self, observer_id: ObserverId) -> Result<()> {
+        self.observers.remove(&observer_id)
+            .map(|_| ())
+            .ok_or_else(|| Error::NotFound(format!("Resize observer {} not found", observer_id)))
+    }
+
+    /// Number of registered observers
+    pub fn observer_count(&self) -> usize {
+        self.observers.len()
+    }
+
+    /// Queue entries for observed elements whose size changed in a layout pass
+    ///
+    /// Returns the number of entries queued.
+    pub fn process_layout(&mut self, geometry: &LayoutGeometry) -> usize {
+        let mut queued = 0;
+
+        for observer in self.observers.values_mut() {
+            let mut targets: Vec<ElementId> = observer.observed.keys().cloned().collect();
+            targets.sort();
+
+            for target in targets {
+                // Elements that are not laid out keep their last size
+                let Some(dimensions) = geometry.dimensions.get(&target) else { continue };
+                let entry = ResizeObserverEntry::new(observer.observer_id, &target, dimensions);
+                // Only the observed content box is compared
+                if observer.observed[&target].content_box_size == entry.content_box_size {
+                    continue;
+                }
+
+                observer.pending.retain(|pending| pending.target != target);
+                observer.pending.push(entry.clone());
+                observer.observed.insert(target, entry);
+                queued += 1;
+            }
+        }
+
+        if queued > 0 {
+            self.schedule_delivery();
+        }
+        queued
+    }
+
+    /// Schedule the delivery microtask unless one is already pending
+    fn schedule_delivery(&mut self) {
+        if std::mem::replace(&mut self.microtask_queued, true) {
+            return;
+        }
+
+        match &self.microtask_scheduler {
+            Some(scheduler) => scheduler(),
+            None => debug!("No microtask scheduler set, resize entries wait for an explicit delivery"),
+        }
+    }
+
+    /// Check if a delivery microtask is pending
+    pub fn has_pending_delivery(&self) -> bool {
+        self.microtask_queued
+    }
+
+    /// Deliver the entries queued since the last delivery
+    ///
+    /// This is the body of the resize observer microtask: each observer with
+    /// pending entries gets a single callback with all of them.
+    pub fn deliver_pending_entries(&mut self) -> Vec<ResizeObserverEntry> {
+        self.microtask_queued = false;
+        let mut all_entries = Vec::new();
+
+        let mut observer_ids: Vec<ObserverId> = self.observers.keys().copied().collect();
+        observer_ids.sort_unstable();
+
+        for observer_id in observer_ids {
+            let Some(observer) = self.observers.get_mut(&observer_id) else { continue };
+            let entries = std::mem::take(&mut observer.pending);
+            if !entries.is_empty() {
+                debug!("Resize observer {} delivered {} entries", observer_id, entries.len());
+                (observer.callback)(entries.clone());
+                all_entries.extend(entries);
+            }
+        }
+
+        all_entries
+    }
+
+    fn observer_mut(&mut self, observer_id: ObserverId) -> Result<&mut ResizeObserver> {
+        self.observers.get_mut(&observer_id)
+            .ok_or_else(|| Error::NotFound(format!("Resize observer {} not found", observer_id)))
+    }
+}
+
+impl Default for ResizeObserverManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    fn geometry(elements: &[(&str, f32, f32)]) -> LayoutGeometry {
+        let mut geometry = LayoutGeometry::default();
+        for (id, width, height) in elements {
+            let dimensions = Dimensions {
+                content_width: *width,
+                content_height: *height,
+                padding_left: 4.0,
+                border_left: 1.0,
+                ..Default::default()
+            };
+            geometry.dimensions.insert(id.to_string(), dimensions);
+        }
+        geometry
+    }
+
+    #[test]
+    fn test_size_changes_are_batched_into_a_microtask() {
+        let mut manager = ResizeObserverManager::new();
+        let scheduled = Arc::new(AtomicUsize::new(0));
+        let counter = scheduled.clone();
+        manager.set_microtask_scheduler(Arc::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let sink = delivered.clone();
+        let observer = manager.create_observer(move |entries| sink.lock().unwrap().push(entries));
+        manager.observe(observer, "panel").unwrap();
+        manager.observe(observer, "empty").unwrap();
+
+        // Empty elements match the initial size and are not reported
+        assert_eq!(manager.process_layout(&geometry(&[("panel", 100.0, 50.0), ("empty", 0.0, 0.0)])), 1);
+        assert_eq!(manager.process_layout(&geometry(&[("panel", 120.0, 50.0), ("empty", 0.0, 0.0)])), 1);
+        assert_eq!(scheduled.load(Ordering::SeqCst), 1);
+        assert!(delivered.lock().unwrap().is_empty());
+
+        // Only the latest size of a target is delivered
+        let entries = manager.deliver_pending_entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].content_rect, DomRectReadOnly::new(4.0, 0.0, 120.0, 50.0));
+        assert_eq!(entries[0].border_box_size.inline_size, 125.0);
+        assert_eq!(delivered.lock().unwrap().len(), 1);
+        assert!(!manager.has_pending_delivery());
+
+        assert_eq!(manager.process_layout(&geometry(&[("panel", 120.0, 50.0)])), 0);
+        manager.unobserve(observer, "panel").unwrap();
+        assert_eq!(manager.process_layout(&geometry(&[("panel", 10.0, 10.0)])), 0);
+
+        manager.disconnect(observer).unwrap();
+        assert!(manager.observe(observer, "panel").is_err());
+    }
+}
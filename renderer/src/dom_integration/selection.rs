@@ -0,0 +1,452 @@
+//! Selection API for renderer processes
+//!
+//! Text nodes have no IDs, so a selection boundary is an element and a
+//! character offset into the element's text, the text of its text node
+//! children joined together. Elements are visited in tree order, so a
+//! selection can start in one element and end in a later one.
+
+use common::error::{Error, Result};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::find_in_page::{chars_per_line, text_rects};
+use crate::rendering_pipeline::{LayoutGeometry, Rectangle};
+
+/// One end of a selection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectionBoundary {
+    /// Element holding the text
+    pub node_id: String,
+
+    /// Offset in the element's text, in characters
+    pub offset: usize,
+}
+
+/// Whether `modify` moves the whole selection or only its focus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionAlter {
+    /// Collapse the selection at the new position
+    Move,
+
+    /// Keep the anchor and move the focus
+    Extend,
+}
+
+/// Direction `modify` moves in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionDirection {
+    Forward,
+    Backward,
+}
+
+/// Unit `modify` moves by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionGranularity {
+    Character,
+    Word,
+    Line,
+    Paragraph,
+}
+
+/// The document's selection, as returned by `document.getSelection()`
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// Where the selection started
+    pub anchor: Option<SelectionBoundary>,
+
+    /// Where the selection ends, moved by `extend` and `modify`
+    pub focus: Option<SelectionBoundary>,
+
+    /// Text of the elements with text, in tree order
+    texts: Vec<(String, Vec<char>)>,
+
+    /// Characters per wrapped line of the laid out elements
+    line_lengths: HashMap<String, usize>,
+}
+
+/// Position in the texts of a selection: element index and character offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Position {
+    index: usize,
+    offset: usize,
+}
+
+impl Selection {
+    /// Create an empty selection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if nothing is selected, either no range or an empty one
+    pub fn is_collapsed(&self) -> bool {
+        self.anchor == self.focus
+    }
+
+    /// Number of ranges, 0 or 1
+    pub fn range_count(&self) -> usize {
+        usize::from(self.anchor.is_some())
+    }
+
+    /// Select from an anchor to a focus, which may come before the anchor
+    pub fn set_base_and_extent(&mut self, anchor: SelectionBoundary, focus: SelectionBoundary) -> Result<()> {
+        self.position(&anchor)?;
+        self.position(&focus)?;
+        self.anchor = Some(anchor);
+        self.focus = Some(focus);
+        Ok(())
+    }
+
+    /// Collapse the selection at a boundary
+    pub fn collapse(&mut self, boundary: SelectionBoundary) -> Result<()> {
+        self.set_base_and_extent(boundary.clone(), boundary)
+    }
+
+    /// Move the focus, keeping the anchor
+    pub fn extend(&mut self, focus: SelectionBoundary) -> Result<()> {
+        if self.anchor.is_none() {
+            return Err(Error::InvalidState("Cannot extend an empty selection".to_string()));
+        }
+        self.position(&focus)?;
+        self.focus = Some(focus);
+        Ok(())
+    }
+
+    /// Remove the selected range
+    pub fn remove_all_ranges(&mut self) {
+        self.anchor = None;
+        self.focus = None;
+    }
+
+    /// Move or extend the selection from its focus
+    pub fn modify(&mut self, alter: SelectionAlter, direction: SelectionDirection, granularity: SelectionGranularity) {
+        let Some(focus) = self.focus.as_ref().and_then(|focus| self.position(focus).ok()) else {
+            return;
+        };
+
+        let moved = match (granularity, direction) {
+            (SelectionGranularity::Character, SelectionDirection::Forward) => self.next_character(focus),
+            (SelectionGranularity::Character, SelectionDirection::Backward) => self.previous_character(focus),
+            (SelectionGranularity::Word, SelectionDirection::Forward) => self.next_word_end(focus),
+            (SelectionGranularity::Word, SelectionDirection::Backward) => self.previous_word_start(focus),
+            (SelectionGranularity::Line, SelectionDirection::Forward) => self.next_line(focus),
+            (SelectionGranularity::Line, SelectionDirection::Backward) => self.previous_line(focus),
+            (SelectionGranularity::Paragraph, SelectionDirection::Forward) => self.next_paragraph_end(focus),
+            (SelectionGranularity::Paragraph, SelectionDirection::Backward) => self.previous_paragraph_start(focus),
+        };
+
+        let boundary = self.boundary(moved);
+        if alter == SelectionAlter::Move {
+            self.anchor = Some(boundary.clone());
+        }
+        self.focus = Some(boundary);
+    }
+
+    /// Selected text of each element, as `(element ID, start offset, end offset)`
+    pub fn selected_ranges(&self) -> Vec<(&str, usize, usize)> {
+        let Some((start, end)) = self.ordered_positions() else {
+            return Vec::new();
+        };
+
+        (start.index..=end.index)
+            .map(|index| {
+                let (element_id, text) = &self.texts[index];
+                let from = if index == start.index { start.offset } else { 0 };
+                let to = if index == end.index { end.offset } else { text.len() };
+                (element_id.as_str(), from, to)
+            })
+            .filter(|(_, from, to)| from < to)
+            .collect()
+    }
+
+    /// Highlight boxes over the selected text of elements that were laid out
+    pub fn highlights(&self, geometry: &LayoutGeometry) -> Vec<Rectangle> {
+        self.selected_ranges()
+            .into_iter()
+            .filter_map(|(element_id, from, to)| {
+                let element_rect = geometry.elements.get(element_id)?;
+                Some(text_rects(from, to - from, element_rect))
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Replace the text the selection is made over, given in tree order as `(element ID, text)`
+    ///
+    /// Boundaries in elements that are gone are dropped and offsets past the
+    /// end of a shortened text are clamped.
+    pub(crate) fn update_text(&mut self, texts: &[(String, String)]) {
+        self.texts = texts.iter()
+            .map(|(element_id, text)| (element_id.clone(), text.chars().collect()))
+            .collect();
+
+        let clamp = |boundary: Option<SelectionBoundary>, texts: &[(String, Vec<char>)]| {
+            let mut boundary = boundary?;
+            let (_, text) = texts.iter().find(|(element_id, _)| *element_id == boundary.node_id)?;
+            boundary.offset = boundary.offset.min(text.len());
+            Some(boundary)
+        };
+        self.anchor = clamp(self.anchor.take(), &self.texts);
+        self.focus = clamp(self.focus.take(), &self.texts);
+        if self.anchor.is_none() || self.focus.is_none() {
+            self.remove_all_ranges();
+        }
+    }
+
+    /// Take the line lengths used to move by line from a layout pass
+    pub(crate) fn update_layout(&mut self, geometry: &LayoutGeometry) {
+        self.line_lengths = geometry.elements.iter()
+            .map(|(element_id, rect)| (element_id.clone(), chars_per_line(rect)))
+            .collect();
+    }
+
+    /// Find a boundary in the texts
+    fn position(&self, boundary: &SelectionBoundary) -> Result<Position> {
+        let index = self.texts.iter()
+            .position(|(element_id, _)| *element_id == boundary.node_id)
+            .ok_or_else(|| Error::NotFound(format!("No text in element {}", boundary.node_id)))?;
+        if boundary.offset > self.texts[index].1.len() {
+            return Err(Error::InvalidState(format!(
+                "IndexSizeError: offset {} is past the end of the text of {}", boundary.offset, boundary.node_id
+            )));
+        }
+        Ok(Position { index, offset: boundary.offset })
+    }
+
+    fn boundary(&self, position: Position) -> SelectionBoundary {
+        SelectionBoundary {
+            node_id: self.texts[position.index].0.clone(),
+            offset: position.offset,
+        }
+    }
+
+    /// Start and end of the selection in tree order
+    fn ordered_positions(&self) -> Option<(Position, Position)> {
+        let anchor = self.position(self.anchor.as_ref()?).ok()?;
+        let focus = self.position(self.focus.as_ref()?).ok()?;
+        Some((anchor.min(focus), anchor.max(focus)))
+    }
+
+    fn len(&self, index: usize) -> usize {
+        self.texts[index].1.len()
+    }
+
+    fn chars_per_line(&self, index: usize) -> usize {
+        self.line_lengths.get(&self.texts[index].0).copied().unwrap_or(usize::MAX)
+    }
+
+    /// The end of one element and the start of the next are a character apart
+    fn next_character(&self, position: Position) -> Position {
+        if position.offset < self.len(position.index) {
+            Position { offset: position.offset + 1, ..position }
+        } else if position.index + 1 < self.texts.len() {
+            Position { index: position.index + 1, offset: 0 }
+        } else {
+            position
+        }
+    }
+
+    fn previous_character(&self, position: Position) -> Position {
+        if position.offset > 0 {
+            Position { offset: position.offset - 1, ..position }
+        } else if position.index > 0 {
+            Position { index: position.index - 1, offset: self.len(position.index - 1) }
+        } else {
+            position
+        }
+    }
+
+    fn next_word_end(&self, position: Position) -> Position {
+        let position = if position.offset == self.len(position.index) {
+            match self.next_character(position) {
+                next if next == position => return position,
+                next => next,
+            }
+        } else {
+            position
+        };
+
+        let text = &self.texts[position.index].1;
+        let mut offset = position.offset;
+        while offset < text.len() && !is_word_char(text[offset]) {
+            offset += 1;
+        }
+        while offset < text.len() && is_word_char(text[offset]) {
+            offset += 1;
+        }
+        Position { offset, ..position }
+    }
+
+    fn previous_word_start(&self, position: Position) -> Position {
+        let position = if position.offset == 0 {
+            match self.previous_character(position) {
+                previous if previous == position => return position,
+                previous => previous,
+            }
+        } else {
+            position
+        };
+
+        let text = &self.texts[position.index].1;
+        let mut offset = position.offset;
+        while offset > 0 && !is_word_char(text[offset - 1]) {
+            offset -= 1;
+        }
+        while offset > 0 && is_word_char(text[offset - 1]) {
+            offset -= 1;
+        }
+        Position { offset, ..position }
+    }
+
+    /// Move to the same column of the next wrapped line
+    fn next_line(&self, position: Position) -> Position {
+        let line_length = self.chars_per_line(position.index);
+        let len = self.len(position.index);
+        let column = position.offset % line_length;
+
+        if position.offset / line_length < len.saturating_sub(1) / line_length {
+            Position { offset: (position.offset + line_length).min(len), ..position }
+        } else if position.index + 1 < self.texts.len() {
+            let index = position.index + 1;
+            Position { index, offset: column.min(self.len(index)) }
+        } else {
+            Position { offset: len, ..position }
+        }
+    }
+
+    /// Move to the same column of the previous wrapped line
+    fn previous_line(&self, position: Position) -> Position {
+        let line_length = self.chars_per_line(position.index);
+        let column = position.offset % line_length;
+
+        if position.offset >= line_length {
+            Position { offset: position.offset - line_length, ..position }
+        } else if position.index > 0 {
+            let index = position.index - 1;
+            let len = self.len(index);
+            let line_length = self.chars_per_line(index);
+            let last_line_start = len.saturating_sub(1) / line_length * line_length;
+            Position { index, offset: last_line_start.saturating_add(column).min(len) }
+        } else {
+            Position { offset: 0, ..position }
+        }
+    }
+
+    /// Each element with text is a paragraph
+    fn next_paragraph_end(&self, position: Position) -> Position {
+        if position.offset < self.len(position.index) {
+            Position { offset: self.len(position.index), ..position }
+        } else if position.index + 1 < self.texts.len() {
+            let index = position.index + 1;
+            Position { index, offset: self.len(index) }
+        } else {
+            position
+        }
+    }
+
+    fn previous_paragraph_start(&self, position: Position) -> Position {
+        if position.offset > 0 {
+            Position { offset: 0, ..position }
+        } else if position.index > 0 {
+            Position { index: position.index - 1, offset: 0 }
+        } else {
+            position
+        }
+    }
+}
+
+/// Text between the anchor and the focus, as `Selection.toString()` returns it
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (element_id, from, to) in self.selected_ranges() {
+            let (_, text) = self.texts.iter()
+                .find(|(id, _)| id == element_id)
+                .expect("selected ranges come from the selection's texts");
+            for c in &text[from..to] {
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether a character is part of a word
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '\''
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selection() -> Selection {
+        let mut selection = Selection::new();
+        selection.update_text(&[
+            ("title".to_string(), "Rich text".to_string()),
+            ("intro".to_string(), "Editors need selections, don't they?".to_string()),
+            ("outro".to_string(), "The end".to_string()),
+        ]);
+        selection
+    }
+
+    fn boundary(node_id: &str, offset: usize) -> SelectionBoundary {
+        SelectionBoundary { node_id: node_id.to_string(), offset }
+    }
+
+    #[test]
+    fn test_selection_to_string() {
+        let mut selection = selection();
+        assert_eq!(selection.to_string(), "");
+        assert_eq!(selection.range_count(), 0);
+
+        selection.set_base_and_extent(boundary("intro", 8), boundary("intro", 23)).unwrap();
+        assert_eq!(selection.to_string(), "need selections");
+
+        // A backward selection spanning elements
+        selection.set_base_and_extent(boundary("outro", 3), boundary("title", 5)).unwrap();
+        assert_eq!(selection.to_string(), "textEditors need selections, don't they?The");
+        assert!(!selection.is_collapsed());
+
+        assert!(selection.set_base_and_extent(boundary("intro", 99), boundary("intro", 0)).is_err());
+        assert!(selection.collapse(boundary("missing", 0)).is_err());
+    }
+
+    #[test]
+    fn test_selection_modify() {
+        use SelectionAlter::*;
+        use SelectionDirection::*;
+        use SelectionGranularity::*;
+
+        let mut selection = selection();
+        selection.collapse(boundary("intro", 0)).unwrap();
+
+        selection.modify(Extend, Forward, Word);
+        selection.modify(Extend, Forward, Word);
+        assert_eq!(selection.to_string(), "Editors need");
+        selection.modify(Extend, Forward, Character);
+        assert_eq!(selection.to_string(), "Editors need ");
+        selection.modify(Extend, Backward, Word);
+        assert_eq!(selection.to_string(), "Editors ");
+
+        selection.modify(Move, Forward, Paragraph);
+        assert!(selection.is_collapsed());
+        assert_eq!(selection.focus, Some(boundary("intro", 36)));
+        selection.modify(Extend, Forward, Character);
+        selection.modify(Extend, Forward, Word);
+        assert_eq!(selection.focus, Some(boundary("outro", 3)));
+
+        selection.modify(Move, Backward, Paragraph);
+        selection.modify(Extend, Backward, Paragraph);
+        assert_eq!(selection.focus, Some(boundary("intro", 0)));
+
+        // Lines wrap at the element's width
+        let mut geometry = LayoutGeometry::default();
+        geometry.elements.insert("intro".to_string(), Rectangle { x: 0.0, y: 0.0, width: 80.0, height: 72.0 });
+        selection.update_layout(&geometry);
+        selection.collapse(boundary("intro", 2)).unwrap();
+        selection.modify(Extend, Forward, Line);
+        assert_eq!(selection.to_string(), "itors need");
+        selection.modify(Move, Backward, Line);
+        selection.modify(Move, Backward, Line);
+        assert_eq!(selection.focus, Some(boundary("title", 2)));
+    }
+}
@@ -0,0 +1,609 @@
+//! Web Animations API for renderer processes
+//!
+//! `Element.animate()` creates an `Animation` whose keyframe effect runs on the
+//! `AnimationEngine` alongside CSS animations. Each property of the effect
+//! becomes one engine animation, named after the animation ID, so playback
+//! control can pause, reverse or cancel them together.
+
+use common::error::{Error, Result};
+use dom::animation::{ActiveAnimation, AnimationEngine, FillMode, Keyframe as EngineKeyframe, TimingFunction};
+use dom::{CssPropertyParser, CssValue};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use super::intersection_observer::ElementId;
+
+/// Animation identifier
+pub type AnimationId = u64;
+
+/// Playback state of an animation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationPlayState {
+    /// Not playing and without effect
+    Idle,
+    /// Playing
+    Running,
+    /// Held at its current time
+    Paused,
+    /// Reached the end of its active interval
+    Finished,
+}
+
+/// Timeline an animation is synchronized to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AnimationTimeline {
+    /// Engine time at which the timeline started
+    pub origin_time: Duration,
+}
+
+impl AnimationTimeline {
+    /// Current time of the timeline
+    pub fn current_time(&self, engine: &AnimationEngine) -> Duration {
+        engine.current_time().saturating_sub(self.origin_time)
+    }
+}
+
+/// A keyframe passed to `Element.animate()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe {
+    /// Position from 0 to 1, or `None` to space keyframes evenly
+    pub offset: Option<f32>,
+    /// Property values at this keyframe, by CSS property name
+    pub properties: BTreeMap<String, CssValue>,
+}
+
+impl Keyframe {
+    /// Parse keyframes in array form, e.g. `[{"opacity": 0}, {"opacity": 1}]`
+    ///
+    /// Property names may be camel-cased. Per-keyframe `easing` and
+    /// `composite` are ignored.
+    pub fn parse_list(keyframes: &Value) -> Result<Vec<Self>> {
+        let list = keyframes.as_array()
+            .ok_or_else(|| Error::ParseError("Keyframes must be an array".to_string()))?;
+        let mut parser = CssPropertyParser::new();
+
+        let keyframes = list.iter().map(|keyframe| {
+            let members = keyframe.as_object()
+                .ok_or_else(|| Error::ParseError(format!("Invalid keyframe: {}", keyframe)))?;
+            let mut parsed = Keyframe { offset: None, properties: BTreeMap::new() };
+
+            for (name, value) in members {
+                match name.as_str() {
+                    "offset" => {
+                        let offset = value.as_f64()
+                            .filter(|offset| (0.0..=1.0).contains(offset))
+                            .ok_or_else(|| Error::ParseError(format!("Invalid keyframe offset: {}", value)))?;
+                        parsed.offset = Some(offset as f32);
+                    }
+                    "easing" | "composite" => {}
+                    _ => {
+                        let value = match value {
+                            Value::Number(number) => CssValue::Number(number.as_f64().unwrap_or_default()),
+                            Value::String(text) => match parser.parse_property_value(text) {
+                                Ok(parsed) => parser.to_css_value(&parsed),
+                                Err(_) => CssValue::Keyword(text.trim().to_string()),
+                            },
+                            _ => return Err(Error::ParseError(format!("Invalid value for {}: {}", name, value))),
+                        };
+                        parsed.properties.insert(css_property_name(name), value);
+                    }
+                }
+            }
+            Ok(parsed)
+        }).collect::<Result<Vec<_>>>()?;
+
+        let offsets: Vec<f32> = keyframes.iter().filter_map(|keyframe| keyframe.offset).collect();
+        if offsets.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(Error::ParseError("Keyframe offsets must be in order".to_string()));
+        }
+        Ok(keyframes)
+    }
+}
+
+/// Timing of a keyframe effect
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectTiming {
+    /// Delay before the effect starts
+    pub delay: Duration,
+    /// Duration of one iteration
+    pub duration: Duration,
+    /// Number of iterations (may be infinite)
+    pub iterations: f32,
+    /// Easing applied to each iteration
+    pub easing: TimingFunction,
+    /// Fill mode
+    pub fill: FillMode,
+}
+
+impl Default for EffectTiming {
+    fn default() -> Self {
+        Self {
+            delay: Duration::ZERO,
+            duration: Duration::ZERO,
+            iterations: 1.0,
+            easing: TimingFunction::Linear,
+            fill: FillMode::None,
+        }
+    }
+}
+
+impl EffectTiming {
+    /// Parse the options of `Element.animate()`: a duration in milliseconds or a timing dictionary
+    pub fn from_options(options: &Value) -> Result<Self> {
+        let mut timing = EffectTiming::default();
+        let invalid = |name: &str, value: &Value| Error::ParseError(format!("Invalid {}: {}", name, value));
+
+        match options {
+            Value::Null => {}
+            Value::Number(_) => timing.duration = parse_millis(options).ok_or_else(|| invalid("duration", options))?,
+            Value::Object(members) => {
+                for (name, value) in members {
+                    match name.as_str() {
+                        "duration" => timing.duration = parse_millis(value).ok_or_else(|| invalid(name, value))?,
+                        "delay" => timing.delay = parse_millis(value).ok_or_else(|| invalid(name, value))?,
+                        "iterations" => {
+                            timing.iterations = match value {
+                                Value::String(text) if text == "Infinity" => f32::INFINITY,
+                                _ => value.as_f64().filter(|count| *count >= 0.0).ok_or_else(|| invalid(name, value))? as f32,
+                            };
+                        }
+                        "easing" => {
+                            timing.easing = value.as_str()
+                                .and_then(TimingFunction::parse)
+                                .ok_or_else(|| invalid(name, value))?;
+                        }
+                        "fill" => {
+                            timing.fill = match value.as_str() {
+                                Some("none" | "auto") => FillMode::None,
+                                Some("forwards") => FillMode::Forwards,
+                                Some("backwards") => FillMode::Backwards,
+                                Some("both") => FillMode::Both,
+                                _ => return Err(invalid(name, value)),
+                            };
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => return Err(invalid("animation options", options)),
+        }
+
+        Ok(timing)
+    }
+
+    /// Length of all iterations, or `None` if the effect repeats forever
+    pub fn active_duration(&self) -> Option<Duration> {
+        self.iterations.is_finite().then(|| self.duration.mul_f64(self.iterations as f64))
+    }
+}
+
+/// Keyframes animating one element
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyframeEffect {
+    /// Animated element
+    pub target: ElementId,
+    /// Keyframes in offset order
+    pub keyframes: Vec<Keyframe>,
+    /// Effect timing
+    pub options: EffectTiming,
+}
+
+impl KeyframeEffect {
+    /// Create a keyframe effect
+    pub fn new(target: &str, keyframes: Vec<Keyframe>, options: EffectTiming) -> Self {
+        Self {
+            target: target.to_string(),
+            keyframes,
+            options,
+        }
+    }
+
+    /// Offsets of the keyframes, with missing offsets spaced evenly between their neighbours
+    pub fn computed_offsets(&self) -> Vec<f32> {
+        let count = self.keyframes.len();
+        let mut offsets: Vec<Option<f32>> = self.keyframes.iter().map(|keyframe| keyframe.offset).collect();
+        if count > 1 {
+            offsets[0].get_or_insert(0.0);
+            offsets[count - 1].get_or_insert(1.0);
+        } else if let Some(offset) = offsets.first_mut() {
+            offset.get_or_insert(1.0);
+        }
+
+        let mut start = 0;
+        for end in 1..count {
+            let Some(end_offset) = offsets[end] else {
+                continue;
+            };
+            let start_offset = offsets[start].unwrap_or_default();
+            for index in start + 1..end {
+                let t = (index - start) as f32 / (end - start) as f32;
+                offsets[index] = Some(start_offset + (end_offset - start_offset) * t);
+            }
+            start = end;
+        }
+
+        offsets.into_iter().map(Option::unwrap_or_default).collect()
+    }
+
+    /// Engine animations for each animated property
+    fn engine_animations(&self, name: &str) -> Vec<ActiveAnimation> {
+        let offsets = self.computed_offsets();
+        let properties: BTreeSet<&String> = self.keyframes.iter()
+            .flat_map(|keyframe| keyframe.properties.keys())
+            .collect();
+
+        properties.into_iter().map(|property| {
+            let keyframes = self.keyframes.iter().zip(&offsets)
+                .filter_map(|(keyframe, offset)| {
+                    keyframe.properties.get(property).map(|value| EngineKeyframe::new(*offset, value.clone()))
+                })
+                .collect();
+            let mut animation = ActiveAnimation::new(
+                &self.target,
+                property,
+                keyframes,
+                self.options.duration,
+                self.options.easing.clone(),
+                self.options.fill,
+            );
+            animation.delay = self.options.delay;
+            animation.iteration_count = self.options.iterations;
+            animation.name = Some(name.to_string());
+            animation
+        }).collect()
+    }
+}
+
+/// State of a promise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromiseState {
+    Pending,
+    Resolved,
+    Rejected,
+}
+
+/// The `finished` promise of an animation
+///
+/// Resolves when the animation finishes and rejects with an `AbortError`
+/// when it is cancelled. Playing a settled animation again makes it pending.
+#[derive(Debug, Clone)]
+pub struct FinishedPromise {
+    /// Animation the promise resolves with
+    animation_id: AnimationId,
+    /// Settlement state, shared by all clones
+    state: Arc<watch::Sender<PromiseState>>,
+}
+
+impl FinishedPromise {
+    fn new(animation_id: AnimationId) -> Self {
+        Self {
+            animation_id,
+            state: Arc::new(watch::channel(PromiseState::Pending).0),
+        }
+    }
+
+    /// Get the settlement state
+    pub fn state(&self) -> PromiseState {
+        *self.state.borrow()
+    }
+
+    /// Wait for the promise to settle, resolving with the animation ID
+    pub async fn wait(&self) -> Result<AnimationId> {
+        let mut receiver = self.state.subscribe();
+        let state = *receiver.wait_for(|state| *state != PromiseState::Pending).await
+            .map_err(|_| Error::InvalidState("Animation was dropped".to_string()))?;
+        match state {
+            PromiseState::Resolved => Ok(self.animation_id),
+            _ => Err(Error::InvalidState(format!("AbortError: animation {} was cancelled", self.animation_id))),
+        }
+    }
+
+    fn settle(&self, state: PromiseState) {
+        self.state.send_replace(state);
+    }
+}
+
+/// A Web Animations `Animation`
+#[derive(Debug, Clone)]
+pub struct Animation {
+    /// Animation ID
+    pub id: AnimationId,
+    /// Keyframe effect
+    pub effect: KeyframeEffect,
+    /// Timeline the animation runs on
+    pub timeline: AnimationTimeline,
+    /// Playback state
+    pub play_state: AnimationPlayState,
+    /// Promise settled when the animation finishes or is cancelled
+    pub finished: FinishedPromise,
+    /// Whether playback runs backwards
+    reversed: bool,
+}
+
+impl Animation {
+    /// Create an idle animation
+    pub fn new(id: AnimationId, effect: KeyframeEffect, timeline: AnimationTimeline) -> Self {
+        Self {
+            id,
+            effect,
+            timeline,
+            play_state: AnimationPlayState::Idle,
+            finished: FinishedPromise::new(id),
+            reversed: false,
+        }
+    }
+
+    /// Name of the engine animations driven by this animation
+    fn engine_name(&self) -> String {
+        format!("web-animation-{}", self.id)
+    }
+
+    /// Start or resume playback
+    ///
+    /// Idle and finished animations restart from the beginning.
+    pub fn play(&mut self, engine: &mut AnimationEngine) {
+        let name = self.engine_name();
+        match self.play_state {
+            AnimationPlayState::Running => return,
+            AnimationPlayState::Paused => {
+                for animation in engine.named_animations_mut(&name) {
+                    animation.paused = false;
+                }
+            }
+            AnimationPlayState::Idle | AnimationPlayState::Finished => {
+                engine.cancel_named_animations(&name);
+                for mut animation in self.effect.engine_animations(&name) {
+                    animation.reversed = self.reversed;
+                    engine.add_animation(animation);
+                }
+                if self.finished.state() != PromiseState::Pending {
+                    self.finished = FinishedPromise::new(self.id);
+                }
+            }
+        }
+        self.play_state = AnimationPlayState::Running;
+    }
+
+    /// Hold the animation at its current time
+    pub fn pause(&mut self, engine: &mut AnimationEngine) {
+        match self.play_state {
+            AnimationPlayState::Paused => return,
+            AnimationPlayState::Idle => self.play(engine),
+            _ => {}
+        }
+        for animation in engine.named_animations_mut(&self.engine_name()) {
+            animation.paused = true;
+        }
+        self.play_state = AnimationPlayState::Paused;
+    }
+
+    /// Stop the animation and remove its effect
+    pub fn cancel(&mut self, engine: &mut AnimationEngine) {
+        if self.play_state == AnimationPlayState::Idle {
+            return;
+        }
+        engine.cancel_named_animations(&self.engine_name());
+        if self.finished.state() == PromiseState::Pending {
+            self.finished.settle(PromiseState::Rejected);
+        }
+        self.play_state = AnimationPlayState::Idle;
+    }
+
+    /// Play the animation backwards from its current time
+    ///
+    /// Fails for animations that repeat forever, which have no end to start from.
+    pub fn reverse(&mut self, engine: &mut AnimationEngine) -> Result<()> {
+        let active_duration = self.effect.options.active_duration()
+            .ok_or_else(|| Error::InvalidState("Cannot reverse an infinite animation".to_string()))?;
+        self.reversed = !self.reversed;
+
+        if matches!(self.play_state, AnimationPlayState::Idle | AnimationPlayState::Finished) {
+            self.play_state = AnimationPlayState::Idle;
+            self.play(engine);
+            return Ok(());
+        }
+
+        // Continue from the mirrored point of the active interval
+        let delay = self.effect.options.delay;
+        for animation in engine.named_animations_mut(&self.engine_name()) {
+            let elapsed = animation.current_time.saturating_sub(delay).min(active_duration);
+            animation.current_time = delay + (active_duration - elapsed);
+            animation.reversed = self.reversed;
+            animation.paused = false;
+        }
+        self.play_state = AnimationPlayState::Running;
+        Ok(())
+    }
+
+    /// Time since the animation started, or `None` while idle
+    pub fn current_time(&self, engine: &AnimationEngine) -> Option<Duration> {
+        if self.play_state == AnimationPlayState::Idle {
+            return None;
+        }
+        let name = self.engine_name();
+        engine.active_animations().iter()
+            .find(|animation| animation.name.as_deref() == Some(name.as_str()))
+            .map(|animation| animation.current_time)
+            .or_else(|| self.effect.options.active_duration().map(|duration| self.effect.options.delay + duration))
+    }
+
+    /// Mark the animation finished once its engine animations have ended
+    ///
+    /// Returns `true` if the animation finished during this update.
+    pub fn update(&mut self, engine: &AnimationEngine) -> bool {
+        if self.play_state != AnimationPlayState::Running {
+            return false;
+        }
+        let name = self.engine_name();
+        let running = engine.active_animations().iter()
+            .any(|animation| animation.name.as_deref() == Some(name.as_str()) && !animation.is_finished());
+        if running {
+            return false;
+        }
+
+        self.play_state = AnimationPlayState::Finished;
+        self.finished.settle(PromiseState::Resolved);
+        true
+    }
+}
+
+/// Web Animations of a document
+#[derive(Debug)]
+pub struct DocumentAnimations {
+    /// Animations in creation order
+    animations: Vec<Animation>,
+    /// Document timeline
+    timeline: AnimationTimeline,
+    /// Next animation ID
+    next_id: AnimationId,
+}
+
+impl DocumentAnimations {
+    /// Create an empty animation list
+    pub fn new() -> Self {
+        Self {
+            animations: Vec::new(),
+            timeline: AnimationTimeline::default(),
+            next_id: 1,
+        }
+    }
+
+    /// `Element.animate()`: create an animation on an element and play it
+    pub fn animate(
+        &mut self,
+        target: &str,
+        keyframes: &Value,
+        options: &Value,
+        engine: &mut AnimationEngine,
+    ) -> Result<&mut Animation> {
+        let effect = KeyframeEffect::new(target, Keyframe::parse_list(keyframes)?, EffectTiming::from_options(options)?);
+        let mut animation = Animation::new(self.next_id, effect, self.timeline);
+        self.next_id += 1;
+
+        animation.play(engine);
+        self.animations.push(animation);
+        Ok(self.animations.last_mut().unwrap())
+    }
+
+    /// `Document.getAnimations()`: animations that are playing, paused, or holding their final value
+    pub fn get_animations(&self) -> Vec<Animation> {
+        self.animations.iter()
+            .filter(|animation| match animation.play_state {
+                AnimationPlayState::Running | AnimationPlayState::Paused => true,
+                AnimationPlayState::Finished => matches!(animation.effect.options.fill, FillMode::Forwards | FillMode::Both),
+                AnimationPlayState::Idle => false,
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Get an animation by ID
+    pub fn animation_mut(&mut self, animation_id: AnimationId) -> Option<&mut Animation> {
+        self.animations.iter_mut().find(|animation| animation.id == animation_id)
+    }
+
+    /// Settle animations that finished in the last frame, returning their IDs
+    pub fn update(&mut self, engine: &AnimationEngine) -> Vec<AnimationId> {
+        self.animations.iter_mut()
+            .filter_map(|animation| animation.update(engine).then_some(animation.id))
+            .collect()
+    }
+}
+
+impl Default for DocumentAnimations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse a non-negative time in milliseconds
+fn parse_millis(value: &Value) -> Option<Duration> {
+    value.as_f64()
+        .filter(|millis| millis.is_finite() && *millis >= 0.0)
+        .map(|millis| Duration::from_secs_f64(millis / 1000.0))
+}
+
+/// Convert a camel-cased IDL attribute name such as `backgroundColor` to a CSS property name
+fn css_property_name(name: &str) -> String {
+    if name == "cssFloat" {
+        return "float".to_string();
+    }
+    let mut property = String::with_capacity(name.len() + 2);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            property.push('-');
+            property.push(c.to_ascii_lowercase());
+        } else {
+            property.push(c);
+        }
+    }
+    property
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_keyframe_offsets_and_timing() {
+        let keyframes = Keyframe::parse_list(&json!([
+            {"backgroundColor": "red"},
+            {"opacity": 0.5},
+            {"opacity": 1, "offset": 0.8},
+            {"opacity": 0},
+        ])).unwrap();
+        assert!(keyframes[0].properties.contains_key("background-color"));
+
+        let timing = EffectTiming::from_options(&json!({"duration": 200, "iterations": 2, "fill": "forwards"})).unwrap();
+        assert_eq!(timing.active_duration(), Some(Duration::from_millis(400)));
+        let effect = KeyframeEffect::new("box", keyframes, timing);
+        assert_eq!(effect.computed_offsets(), vec![0.0, 0.4, 0.8, 1.0]);
+
+        assert!(Keyframe::parse_list(&json!([{"opacity": 0, "offset": 0.6}, {"opacity": 1, "offset": 0.2}])).is_err());
+        assert!(EffectTiming::from_options(&json!({"fill": "sideways"})).is_err());
+        assert_eq!(EffectTiming::from_options(&json!({"iterations": "Infinity"})).unwrap().active_duration(), None);
+    }
+
+    #[tokio::test]
+    async fn test_playback_control() {
+        let mut engine = AnimationEngine::new();
+        let mut animations = DocumentAnimations::new();
+        let animation = animations.animate("box", &json!([{"opacity": 0}, {"opacity": 1}]), &json!(1000), &mut engine).unwrap();
+        let id = animation.id;
+        let opacity = |engine: &AnimationEngine| engine.animated_value("box", "opacity").map(|value| value.value().clone());
+
+        engine.tick(Duration::from_millis(250));
+        animation.pause(&mut engine);
+        engine.tick(Duration::from_millis(500));
+        assert_eq!(animation.play_state, AnimationPlayState::Paused);
+        assert_eq!(animation.current_time(&engine), Some(Duration::from_millis(250)));
+        assert!(!engine.needs_frame());
+
+        // Reversing from a quarter of the way plays back towards the start
+        animation.reverse(&mut engine).unwrap();
+        assert_eq!(animation.play_state, AnimationPlayState::Running);
+        assert_eq!(opacity(&engine), Some(CssValue::Number(0.25)));
+        engine.tick(Duration::from_millis(125));
+        assert_eq!(opacity(&engine), Some(CssValue::Number(0.125)));
+
+        let finished = animation.finished.clone();
+        engine.tick(Duration::from_millis(200));
+        assert_eq!(animations.update(&engine), vec![id]);
+        assert_eq!(finished.wait().await.unwrap(), id);
+        assert!(animations.get_animations().is_empty());
+
+        // Cancelling rejects the promise of the restarted animation
+        let animation = animations.animation_mut(id).unwrap();
+        animation.play(&mut engine);
+        let finished = animation.finished.clone();
+        assert_eq!(finished.state(), PromiseState::Pending);
+        animation.cancel(&mut engine);
+        assert_eq!(animation.play_state, AnimationPlayState::Idle);
+        assert!(finished.wait().await.is_err());
+        assert!(engine.active_animations().is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+//! Find in page for renderer processes
+//!
+//! A search walks the text of the document in tree order and records every
+//! match by the element holding the text. Matches are drawn as highlight
+//! boxes on top of the page, with the current match in a stronger color.
+
+use crate::rendering_pipeline::{Color, LayoutGeometry, Rectangle};
+
+/// Horizontal advance of a character, until text layout reports glyph positions
+const CHAR_ADVANCE: f32 = 8.0;
+
+/// Height of a line of text
+pub(crate) const LINE_HEIGHT: f32 = 18.0;
+
+/// Elements whose text is not rendered
+const HIDDEN_ELEMENTS: &[&str] = &["head", "title", "script", "style", "template", "noscript"];
+
+/// Options for a search
+#[derive(Debug, Clone, Default)]
+pub struct FindOptions {
+    /// Match letter case exactly
+    pub case_sensitive: bool,
+}
+
+/// Outcome of a search step
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FindResult {
+    /// Number of matches in the document
+    pub total_matches: usize,
+
+    /// Index of the current match, 0 if there are none
+    pub current_index: usize,
+}
+
+/// A match in the text of an element
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FindMatch {
+    /// Element holding the text
+    pub element_id: String,
+
+    /// Offset of the match in the element's text, in characters
+    pub char_offset: usize,
+
+    /// Length of the match, in characters
+    pub length: usize,
+}
+
+/// An active search in a document
+#[derive(Debug, Clone)]
+pub struct FindInPageSession {
+    /// Text searched for
+    pub query: String,
+
+    /// Whether letter case must match
+    pub case_sensitive: bool,
+
+    /// Matches in document order
+    pub matches: Vec<FindMatch>,
+
+    /// Index of the current match
+    pub current_index: usize,
+}
+
+impl FindInPageSession {
+    /// Search the text of elements, given in document order as `(element ID, text)`
+    ///
+    /// Matches do not overlap and do not span elements.
+    pub fn new(query: &str, options: &FindOptions, texts: &[(String, String)]) -> Self {
+        let needle = fold(query, options.case_sensitive);
+        let mut matches = Vec::new();
+
+        if !needle.is_empty() {
+            for (element_id, text) in texts {
+                let haystack = fold(text, options.case_sensitive);
+                let mut offset = 0;
+                while offset + needle.len() <= haystack.len() {
+                    if haystack[offset..offset + needle.len()] == needle[..] {
+                        matches.push(FindMatch {
+                            element_id: element_id.clone(),
+                            char_offset: offset,
+                            length: needle.len(),
+                        });
+                        offset += needle.len();
+                    } else {
+                        offset += 1;
+                    }
+                }
+            }
+        }
+
+        Self {
+            query: query.to_string(),
+            case_sensitive: options.case_sensitive,
+            matches,
+            current_index: 0,
+        }
+    }
+
+    /// Number of matches and the current one
+    pub fn result(&self) -> FindResult {
+        FindResult {
+            total_matches: self.matches.len(),
+            current_index: self.current_index,
+        }
+    }
+
+    /// The current match
+    pub fn current_match(&self) -> Option<&FindMatch> {
+        self.matches.get(self.current_index)
+    }
+
+    /// Move to the next match, wrapping to the first
+    pub fn find_next(&mut self) -> FindResult {
+        if !self.matches.is_empty() {
+            self.current_index = (self.current_index + 1) % self.matches.len();
+        }
+        self.result()
+    }
+
+    /// Move to the previous match, wrapping to the last
+    pub fn find_previous(&mut self) -> FindResult {
+        if !self.matches.is_empty() {
+            self.current_index = (self.current_index + self.matches.len() - 1) % self.matches.len();
+        }
+        self.result()
+    }
+
+    /// Highlight boxes for the matches of elements that were laid out
+    pub fn highlights(&self, geometry: &LayoutGeometry) -> Vec<(Rectangle, Color)> {
+        self.matches
+            .iter()
+            .enumerate()
+            .filter_map(|(index, find_match)| {
+                let element_rect = geometry.elements.get(&find_match.element_id)?;
+                let color = if index == self.current_index { current_match_color() } else { match_color() };
+                let rects = text_rects(find_match.char_offset, find_match.length, element_rect);
+                Some(rects.into_iter().map(move |rect| (rect, color.clone())))
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+/// Whether the text of an element is rendered
+pub fn is_searchable_element(tag_name: &str) -> bool {
+    !HIDDEN_ELEMENTS.contains(&tag_name)
+}
+
+/// Characters that fit on a line of an element's text
+pub(crate) fn chars_per_line(element_rect: &Rectangle) -> usize {
+    ((element_rect.width / CHAR_ADVANCE).floor() as usize).max(1)
+}
+
+/// Boxes covering a run of an element's text, one per line the run is wrapped over
+pub(crate) fn text_rects(char_offset: usize, length: usize, element_rect: &Rectangle) -> Vec<Rectangle> {
+    let chars_per_line = chars_per_line(element_rect);
+    let mut rects = Vec::new();
+    let mut offset = char_offset;
+    let end = char_offset + length;
+
+    while offset < end {
+        let line = offset / chars_per_line;
+        let column = offset % chars_per_line;
+        let run = (chars_per_line - column).min(end - offset);
+        rects.push(Rectangle {
+            x: element_rect.x + column as f32 * CHAR_ADVANCE,
+            y: element_rect.y + line as f32 * LINE_HEIGHT,
+            width: run as f32 * CHAR_ADVANCE,
+            height: LINE_HEIGHT,
+        });
+        offset += run;
+    }
+
+    rects
+}
+
+/// Characters of a text, lowercased unless the search is case sensitive
+///
+/// Each character folds to exactly one character so offsets into the folded
+/// text are offsets into the original.
+fn fold(text: &str, case_sensitive: bool) -> Vec<char> {
+    text.chars()
+        .map(|c| if case_sensitive { c } else { c.to_lowercase().next().unwrap_or(c) })
+        .collect()
+}
+
+/// Color of the matches
+fn match_color() -> Color {
+    Color { red: 255, green: 255, blue: 0, alpha: 128 }
+}
+
+/// Color of the current match
+fn current_match_color() -> Color {
+    Color { red: 255, green: 150, blue: 50, alpha: 160 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts() -> Vec<(String, String)> {
+        vec![
+            ("heading".to_string(), "Rust in the Browser".to_string()),
+            ("intro".to_string(), "RUST, rust and Rustaceans: trust the borrow checker".to_string()),
+            ("footer".to_string(), "Straße — no match here".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_case_insensitive_matches() {
+        let session = FindInPageSession::new("rust", &FindOptions::default(), &texts());
+        let offsets: Vec<(&str, usize)> = session.matches.iter()
+            .map(|m| (m.element_id.as_str(), m.char_offset))
+            .collect();
+        assert_eq!(offsets, [("heading", 0), ("intro", 0), ("intro", 6), ("intro", 15), ("intro", 28)]);
+        assert!(session.matches.iter().all(|m| m.length == 4));
+
+        let session = FindInPageSession::new("Rust", &FindOptions { case_sensitive: true }, &texts());
+        assert_eq!(session.result(), FindResult { total_matches: 2, current_index: 0 });
+
+        // Offsets count characters, not bytes
+        let session = FindInPageSession::new("no", &FindOptions::default(), &texts());
+        assert_eq!(session.matches[0].char_offset, 9);
+
+        assert!(FindInPageSession::new("", &FindOptions::default(), &texts()).matches.is_empty());
+    }
+
+    #[test]
+    fn test_next_and_previous_wrap() {
+        let mut session = FindInPageSession::new("rust", &FindOptions::default(), &texts());
+        assert_eq!(session.find_previous().current_index, 4);
+        assert_eq!(session.find_next().current_index, 0);
+        assert_eq!(session.find_next().current_index, 1);
+        assert_eq!(session.current_match().unwrap().element_id, "intro");
+
+        let mut empty = FindInPageSession::new("absent", &FindOptions::default(), &texts());
+        assert_eq!(empty.find_next(), FindResult { total_matches: 0, current_index: 0 });
+    }
+
+    #[test]
+    fn test_highlights_wrap_over_lines() {
+        let texts = vec![("narrow".to_string(), "abcdefghij".to_string())];
+        let session = FindInPageSession::new("efgh", &FindOptions::default(), &texts);
+
+        let mut geometry = LayoutGeometry::default();
+        geometry.elements.insert("narrow".to_string(), Rectangle { x: 10.0, y: 20.0, width: 48.0, height: 40.0 });
+
+        // Six characters fit on a line
+        let rects: Vec<Rectangle> = session.highlights(&geometry).into_iter().map(|(rect, _)| rect).collect();
+        assert_eq!(rects, [
+            Rectangle { x: 42.0, y: 20.0, width: 16.0, height: LINE_HEIGHT },
+            Rectangle { x: 10.0, y: 38.0, width: 16.0, height: LINE_HEIGHT },
+        ]);
+
+        // Matches in elements without layout are not drawn
+        assert!(session.highlights(&LayoutGeometry::default()).is_empty());
+    }
+}
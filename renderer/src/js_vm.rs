@@ -1,9 +1,69 @@
 //! JavaScript VM for renderer processes
 
 use common::error::Result;
+use common::debugger::{Breakpoint, Debugger};
+use common::ipc::{DebuggerCallFrame, DebuggerEvent, DebuggerRequest, PauseReason};
+use crate::wasm_engine::{WasmEngine, WasmImports, WasmInstance, WasmModule};
 use serde_json::Value;
 use tracing::{debug, error, info, warn};
 
+/// WebIDL of the `Geolocation` interface exposed as `navigator.geolocation`
+///
+/// Calls are forwarded to the browser process through
+/// `DomIntegrationManager::get_current_position`, and the browser asks for the
+/// `geolocation` permission before querying the platform location service.
+pub const GEOLOCATION_IDL: &str = r#"
+[Exposed=Window]
+interface Geolocation {
+  undefined getCurrentPosition(PositionCallback successCallback,
+                               optional PositionErrorCallback? errorCallback = null,
+                               optional PositionOptions options = {});
+  long watchPosition(PositionCallback successCallback,
+                     optional PositionErrorCallback? errorCallback = null,
+                     optional PositionOptions options = {});
+  undefined clearWatch(long watchId);
+};
+
+callback PositionCallback = undefined (GeolocationPosition position);
+callback PositionErrorCallback = undefined (GeolocationPositionError positionError);
+
+dictionary PositionOptions {
+  boolean enableHighAccuracy = false;
+  [Clamp] unsigned long timeout = 0xFFFFFFFF;
+  [Clamp] unsigned long maximumAge = 0;
+};
+
+[Exposed=Window, SecureContext]
+interface GeolocationPosition {
+  readonly attribute GeolocationCoordinates coords;
+  readonly attribute EpochTimeStamp timestamp;
+};
+
+[Exposed=Window, SecureContext]
+interface GeolocationCoordinates {
+  readonly attribute double accuracy;
+  readonly attribute double latitude;
+  readonly attribute double longitude;
+  readonly attribute double? altitude;
+  readonly attribute double? altitudeAccuracy;
+  readonly attribute double? heading;
+  readonly attribute double? speed;
+};
+
+[Exposed=Window]
+interface GeolocationPositionError {
+  const unsigned short PERMISSION_DENIED = 1;
+  const unsigned short POSITION_UNAVAILABLE = 2;
+  const unsigned short TIMEOUT = 3;
+  readonly attribute unsigned short code;
+  readonly attribute DOMString message;
+};
+
+partial interface Navigator {
+  [SameObject] readonly attribute Geolocation geolocation;
+};
+"#;
+
 /// JavaScript VM manager
 pub struct JavaScriptVmManager {
     /// VM configuration
@@ -23,6 +83,42 @@ pub struct JavaScriptVmManager {
     
     /// Next timer ID
     next_timer_id: u64,
+    
+    /// Whether scripts may run
+    scripting_enabled: bool,
+    
+    /// Breakpoints and step state set by DevTools
+    debugger: Debugger,
+    
+    /// Frames of the script paused in the debugger
+    paused_frames: Option<Vec<DebuggerCallFrame>>,
+    
+    /// Command continuing the paused script
+    resume_command: Option<DebuggerRequest>,
+    
+    /// Functions being executed, outermost first
+    ///
+    /// Behind a mutex so profilers can sample it while a script runs.
+    call_stack: std::sync::Mutex<Vec<JsStackFrame>>,
+    
+    /// Engine behind the `WebAssembly` global, `None` when WebAssembly is disabled
+    wasm_engine: Option<WasmEngine>,
+}
+
+/// Function being executed by the VM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsStackFrame {
+    /// Function name, empty for anonymous functions
+    pub function_name: String,
+    
+    /// Script URL
+    pub url: String,
+    
+    /// Line number, starting at 1
+    pub line: u32,
+    
+    /// Column number, starting at 1
+    pub column: u32,
 }
 
 /// JavaScript VM configuration
@@ -69,6 +165,37 @@ pub struct ScriptContext {
     pub functions: std::collections::HashMap<String, JsFunction>,
 }
 
+/// JavaScript heap of a page frozen in the back-forward cache
+///
+/// Event listeners and timers hold native callbacks, so the heap is moved out
+/// of the VM rather than copied. Timers keep the time left until they fire.
+pub struct FrozenJsHeap {
+    /// Global scope
+    global_scope: Value,
+    
+    /// Script contexts with their variables and functions
+    script_contexts: std::collections::HashMap<String, ScriptContext>,
+    
+    /// Event listeners
+    event_listeners: Vec<JsEventListener>,
+    
+    /// Timers and the time left until they fire
+    timers: std::collections::HashMap<String, (Timer, std::time::Duration)>,
+    
+    /// Next timer ID
+    next_timer_id: u64,
+}
+
+impl std::fmt::Debug for FrozenJsHeap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrozenJsHeap")
+            .field("script_contexts", &self.script_contexts.len())
+            .field("event_listeners", &self.event_listeners.len())
+            .field("timers", &self.timers.len())
+            .finish_non_exhaustive()
+    }
+}
+
 /// Execution state
 #[derive(Debug, Clone)]
 pub enum ExecutionState {
@@ -161,13 +288,29 @@ impl JavaScriptVmManager {
             console_logging: true,
         };
         
+        let mut global_scope = Self::create_global_scope().await?;
+        let wasm_engine = if config.wasm_enabled {
+            Some(WasmEngine::new(config.memory_limit_mb))
+        } else {
+            if let Some(scope) = global_scope.as_object_mut() {
+                scope.remove("WebAssembly");
+            }
+            None
+        };
+        
         Ok(Self {
             config: js_config,
-            global_scope: Self::create_global_scope().await?,
+            global_scope,
             script_contexts: std::collections::HashMap::new(),
             event_listeners: Vec::new(),
             timers: std::collections::HashMap::new(),
             next_timer_id: 1,
+            scripting_enabled: true,
+            debugger: Debugger::new(),
+            paused_frames: None,
+            resume_command: None,
+            call_stack: std::sync::Mutex::new(Vec::new()),
+            wasm_engine,
         })
     }
     
@@ -185,8 +328,29 @@ impl JavaScriptVmManager {
         Ok(())
     }
     
+    /// Allow or forbid running scripts
+    ///
+    /// Disabling scripting also cancels pending timers and drops event listeners.
+    pub fn set_scripting_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.timers.clear();
+            self.event_listeners.clear();
+        }
+        self.scripting_enabled = enabled;
+    }
+    
+    /// Whether scripts may run
+    pub fn scripting_enabled(&self) -> bool {
+        self.scripting_enabled
+    }
+    
     /// Execute scripts in the current document
     pub async fn execute_scripts(&mut self) -> Result<()> {
+        if !self.scripting_enabled {
+            debug!("Scripting is disabled, skipping document scripts");
+            return Ok(());
+        }
+        
         info!("Executing JavaScript scripts");
         
         // TODO: Find and execute all script tags in the DOM
@@ -205,6 +369,13 @@ impl JavaScriptVmManager {
     
     /// Execute a JavaScript script
     pub async fn execute_script(&self, script: &str) -> Result<Value> {
+        if !self.scripting_enabled {
+            return Err(common::error::Error::SecurityError("Scripting is disabled".to_string()));
+        }
+        if self.is_paused() {
+            return Err(common::error::Error::InvalidState("Scripts are paused in the debugger".to_string()));
+        }
+        
         debug!("Executing JavaScript script");
         
         // TODO: Implement actual JavaScript execution
@@ -224,6 +395,33 @@ impl JavaScriptVmManager {
         Ok(result)
     }
     
+    /// Compile a WebAssembly module, as `WebAssembly.compile()`
+    pub async fn webassembly_compile(&self, wasm_bytes: &[u8]) -> Result<WasmModule> {
+        let module = self.wasm_engine()?.compile_module(wasm_bytes)?;
+        debug!("Compiled WebAssembly module of {} bytes", wasm_bytes.len());
+        Ok(module)
+    }
+    
+    /// Compile and instantiate a WebAssembly module, as `WebAssembly.instantiate()`
+    pub async fn webassembly_instantiate(&self, wasm_bytes: &[u8], imports: WasmImports) -> Result<(WasmModule, WasmInstance)> {
+        let module = self.webassembly_compile(wasm_bytes).await?;
+        let instance = module.instantiate(imports)?;
+        Ok((module, instance))
+    }
+    
+    /// Check if bytes are a valid WebAssembly module, as `WebAssembly.validate()`
+    pub fn webassembly_validate(&self, wasm_bytes: &[u8]) -> Result<bool> {
+        Ok(self.wasm_engine()?.validate(wasm_bytes))
+    }
+    
+    fn wasm_engine(&self) -> Result<&WasmEngine> {
+        if !self.scripting_enabled {
+            return Err(common::error::Error::SecurityError("Scripting is disabled".to_string()));
+        }
+        self.wasm_engine.as_ref()
+            .ok_or_else(|| common::error::Error::InvalidState("WebAssembly is disabled".to_string()))
+    }
+    
     /// Set a timeout
     pub async fn set_timeout<F>(&mut self, callback: F, delay_ms: u64) -> Result<String>
     where
@@ -310,11 +508,9 @@ impl JavaScriptVmManager {
     
     /// Trigger an event
     pub async fn trigger_event(&self, event_type: &str, event_data: Value) -> Result<()> {
-        for listener in &self.event_listeners {
-            if listener.active && listener.event_type == event_type {
-                if let Err(e) = (listener.callback)(event_data.clone()) {
-                    warn!("Error in event listener for {}: {}", event_type, e);
-                }
+        for result in self.dispatch_event(event_type, event_data).await {
+            if let Err(e) = result {
+                warn!("Error in event listener for {}: {}", event_type, e);
             }
         }
         
@@ -322,6 +518,15 @@ impl JavaScriptVmManager {
         Ok(())
     }
     
+    /// Dispatch an event, returning what each of its listeners returned
+    pub async fn dispatch_event(&self, event_type: &str, event_data: Value) -> Vec<Result<Value>> {
+        self.event_listeners
+            .iter()
+            .filter(|listener| listener.active && listener.event_type == event_type)
+            .map(|listener| (listener.callback)(event_data.clone()))
+            .collect()
+    }
+    
     /// Update timers
     pub async fn update_timers(&mut self) -> Result<()> {
         let now = std::time::Instant::now();
@@ -360,6 +565,77 @@ impl JavaScriptVmManager {
         Ok(())
     }
     
+    /// Apply a debugger command from DevTools
+    ///
+    /// Resume and step commands continue the paused script and are answered
+    /// with `DebuggerEvent::Resumed`.
+    pub fn handle_debugger_request(&mut self, request: DebuggerRequest) -> Result<Option<DebuggerEvent>> {
+        if matches!(request, DebuggerRequest::Evaluate { .. }) {
+            return Err(common::error::Error::InvalidState("Expressions are evaluated with evaluate()".to_string()));
+        }
+        
+        if !self.debugger.handle_request(request.clone()) {
+            return Ok(None);
+        }
+        if self.paused_frames.take().is_none() {
+            return Err(common::error::Error::InvalidState("Scripts are not paused".to_string()));
+        }
+        self.resume_command = Some(request);
+        Ok(Some(DebuggerEvent::Resumed))
+    }
+    
+    /// Evaluate an expression for DevTools, returning the `Evaluated` notification
+    pub async fn evaluate(&self, request_id: u64, expression: &str) -> DebuggerEvent {
+        match self.execute_script(expression).await {
+            Ok(value) => DebuggerEvent::Evaluated { request_id, value: Some(value.to_string()), error: None },
+            Err(e) => DebuggerEvent::Evaluated { request_id, value: None, error: Some(e.to_string()) },
+        }
+    }
+    
+    /// Suspend the running script, returning the notification for DevTools
+    pub fn pause(&mut self, call_frames: Vec<DebuggerCallFrame>, reason: PauseReason) -> DebuggerEvent {
+        info!("Script paused: {:?}", reason);
+        self.paused_frames = Some(call_frames.clone());
+        DebuggerEvent::Paused { call_frames, reason }
+    }
+    
+    /// Whether a script is paused in the debugger
+    pub fn is_paused(&self) -> bool {
+        self.paused_frames.is_some()
+    }
+    
+    /// Take the command that continued the paused script
+    pub fn take_resume_command(&mut self) -> Option<DebuggerRequest> {
+        self.resume_command.take()
+    }
+    
+    /// Get the breakpoints set by DevTools
+    pub fn breakpoints(&self) -> &std::collections::HashMap<u64, Breakpoint> {
+        self.debugger.breakpoints()
+    }
+    
+    /// Record a call, pushing its frame onto the call stack
+    pub fn enter_function(&self, frame: JsStackFrame) {
+        self.lock_call_stack().push(frame);
+    }
+    
+    /// Record a return, popping the innermost frame
+    pub fn exit_function(&self) -> Option<JsStackFrame> {
+        self.lock_call_stack().pop()
+    }
+    
+    /// Capture the current call stack, innermost frame first
+    pub fn capture_stack_trace(&self) -> Vec<JsStackFrame> {
+        self.lock_call_stack().iter().rev().cloned().collect()
+    }
+    
+    /// Lock the call stack
+    ///
+    /// The stack stays valid if a holder panicked, so poisoning is ignored.
+    fn lock_call_stack(&self) -> std::sync::MutexGuard<'_, Vec<JsStackFrame>> {
+        self.call_stack.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    
     /// Get VM statistics
     pub async fn get_stats(&self) -> Result<Value> {
         let stats = serde_json::json!({
@@ -374,6 +650,44 @@ impl JavaScriptVmManager {
         Ok(stats)
     }
     
+    /// Move the heap of the current page out of the VM for the back-forward cache
+    ///
+    /// Timers stop running until the heap is restored.
+    pub fn freeze_heap(&mut self) -> FrozenJsHeap {
+        let now = std::time::Instant::now();
+        let timers = std::mem::take(&mut self.timers).into_iter()
+            .map(|(timer_id, timer)| {
+                let remaining = timer.next_execution.saturating_duration_since(now);
+                (timer_id, (timer, remaining))
+            })
+            .collect();
+        
+        FrozenJsHeap {
+            global_scope: self.global_scope.clone(),
+            script_contexts: std::mem::take(&mut self.script_contexts),
+            event_listeners: std::mem::take(&mut self.event_listeners),
+            timers,
+            next_timer_id: self.next_timer_id,
+        }
+    }
+    
+    /// Restore a heap produced by `freeze_heap`, resuming its timers
+    pub fn thaw_heap(&mut self, heap: FrozenJsHeap) {
+        let now = std::time::Instant::now();
+        self.global_scope = heap.global_scope;
+        self.script_contexts = heap.script_contexts;
+        self.event_listeners = heap.event_listeners;
+        self.timers = heap.timers.into_iter()
+            .map(|(timer_id, (mut timer, remaining))| {
+                timer.next_execution = now + remaining;
+                (timer_id, timer)
+            })
+            .collect();
+        self.next_timer_id = heap.next_timer_id;
+        
+        debug!("Restored JavaScript heap with {} script contexts", self.script_contexts.len());
+    }
+    
     /// Create global scope
     async fn create_global_scope() -> Result<Value> {
         let global_scope = serde_json::json!({
@@ -394,7 +708,12 @@ impl JavaScriptVmManager {
                 "navigator": {
                     "userAgent": "Matte Browser/1.0",
                     "language": "en-US",
-                    "platform": "MacIntel"
+                    "platform": "MacIntel",
+                    "geolocation": {
+                        "getCurrentPosition": "function",
+                        "watchPosition": "function",
+                        "clearWatch": "function"
+                    }
                 },
                 "document": {
                     "title": "Matte Browser",
@@ -413,7 +732,12 @@ impl JavaScriptVmManager {
             "clearTimeout": "function",
             "clearInterval": "function",
             "fetch": "function",
-            "XMLHttpRequest": "function"
+            "XMLHttpRequest": "function",
+            "WebAssembly": {
+                "compile": "function",
+                "instantiate": "function",
+                "validate": "function"
+            }
         });
         
         Ok(global_scope)
@@ -491,6 +815,7 @@ impl JavaScriptVmManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::wasm_engine::WasmValue;
 
     #[tokio::test]
     async fn test_js_vm_manager_creation() {
@@ -554,6 +879,76 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_navigator_geolocation_binding() {
+        let config = crate::RendererConfig::default();
+        let manager = JavaScriptVmManager::new(&config).await.unwrap();
+        
+        let geolocation = &manager.global_scope["window"]["navigator"]["geolocation"];
+        for method in ["getCurrentPosition", "watchPosition", "clearWatch"] {
+            assert_eq!(geolocation[method], "function");
+            assert!(GEOLOCATION_IDL.contains(method));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_debugger_requests() {
+        let config = crate::RendererConfig::default();
+        let mut manager = JavaScriptVmManager::new(&config).await.unwrap();
+        
+        let set = DebuggerRequest::SetBreakpoint {
+            breakpoint_id: 1,
+            url: "https://example.com/app.js".to_string(),
+            line: 3,
+            column: 0,
+        };
+        assert!(manager.handle_debugger_request(set).unwrap().is_none());
+        assert_eq!(manager.breakpoints()[&1].line, 3);
+        
+        // Stepping needs a paused script
+        assert!(manager.handle_debugger_request(DebuggerRequest::StepOver).is_err());
+        
+        let frame = DebuggerCallFrame {
+            function_name: "main".to_string(),
+            url: "https://example.com/app.js".to_string(),
+            line: 3,
+            column: 1,
+            scope: Vec::new(),
+        };
+        let event = manager.pause(vec![frame], PauseReason::Breakpoint { breakpoint_id: 1 });
+        assert!(matches!(event, DebuggerEvent::Paused { ref call_frames, .. } if call_frames.len() == 1));
+        assert!(manager.execute_script("1 + 1").await.is_err());
+        
+        let event = manager.handle_debugger_request(DebuggerRequest::StepOver).unwrap();
+        assert!(matches!(event, Some(DebuggerEvent::Resumed)));
+        assert!(!manager.is_paused());
+        assert!(matches!(manager.take_resume_command(), Some(DebuggerRequest::StepOver)));
+        
+        manager.handle_debugger_request(DebuggerRequest::RemoveBreakpoint { breakpoint_id: 1 }).unwrap();
+        assert!(manager.breakpoints().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_capture_stack_trace() {
+        let config = crate::RendererConfig::default();
+        let manager = JavaScriptVmManager::new(&config).await.unwrap();
+        assert!(manager.capture_stack_trace().is_empty());
+        
+        let frame = |name: &str, line| JsStackFrame {
+            function_name: name.to_string(),
+            url: "https://example.com/app.js".to_string(),
+            line,
+            column: 1,
+        };
+        manager.enter_function(frame("main", 1));
+        manager.enter_function(frame("render", 10));
+        let names: Vec<_> = manager.capture_stack_trace().into_iter().map(|f| f.function_name).collect();
+        assert_eq!(names, ["render", "main"]);
+        
+        assert_eq!(manager.exit_function(), Some(frame("render", 10)));
+        assert_eq!(manager.capture_stack_trace(), [frame("main", 1)]);
+    }
+
     #[tokio::test]
     async fn test_vm_stats() {
         let config = crate::RendererConfig::default();
@@ -565,4 +960,31 @@ mod tests {
         let stats = stats.unwrap();
         assert!(stats["jitEnabled"].as_bool().unwrap());
     }
+
+    #[tokio::test]
+    async fn test_webassembly_instantiate() {
+        // (module (func (export "add") (param i32 i32) (result i32) local.get 0 local.get 1 i32.add))
+        let add_module = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+            0x01, 0x07, 0x01, 0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f,
+            0x03, 0x02, 0x01, 0x00,
+            0x07, 0x07, 0x01, 0x03, b'a', b'd', b'd', 0x00, 0x00,
+            0x0a, 0x09, 0x01, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b,
+        ];
+        
+        let config = crate::RendererConfig::default();
+        let manager = JavaScriptVmManager::new(&config).await.unwrap();
+        assert!(manager.global_scope["WebAssembly"]["instantiate"].is_string());
+        assert!(manager.webassembly_validate(&add_module).unwrap());
+        
+        let (module, mut instance) = manager.webassembly_instantiate(&add_module, WasmImports::new()).await.unwrap();
+        assert_eq!(module.exports(), [("add".to_string(), crate::wasm_engine::WasmExportKind::Function)]);
+        let result = instance.call("add", &[WasmValue::I32(40), WasmValue::I32(2)]).unwrap();
+        assert_eq!(result, [WasmValue::I32(42)]);
+        
+        let config = crate::RendererConfig { wasm_enabled: false, ..Default::default() };
+        let manager = JavaScriptVmManager::new(&config).await.unwrap();
+        assert!(manager.global_scope.get("WebAssembly").is_none());
+        assert!(manager.webassembly_compile(&add_module).await.is_err());
+    }
 }
@@ -1,10 +1,21 @@
 //! Renderer process for the Matte browser
 
-use common::{error::Result, TabId};
+use common::ipc::{
+    BrowserEvent, Channel, ChannelPair, DebuggerRequest, DevToolsRequest, DevToolsResponse, DomPropertyQueryMessage,
+    DomPropertyResponseMessage, IpcMessage, PrintRequest, PrintResponse, RendererCrashedRequest,
+};
+use common::platform::{set_process_priority, ProcessPriority};
+use common::{error::Result, RendererId, TabId};
+use network::NetworkProcessManager;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
 use tracing::{debug, error, info, warn};
 
 pub mod site_isolation;
@@ -12,12 +23,39 @@ pub mod dom_integration;
 pub mod style_engine;
 pub mod js_vm;
 pub mod rendering_pipeline;
+pub mod bfcache;
+pub mod csp;
+pub mod sri;
+pub mod find_in_page;
+pub mod reader_mode;
+pub mod service_worker;
+pub mod wasm_engine;
+pub mod scroll_animator;
+pub mod print;
+pub mod web_audio;
 
 use site_isolation::SiteIsolationManager;
 use dom_integration::DomIntegrationManager;
+use dom_integration::selection::{SelectionAlter, SelectionDirection, SelectionGranularity};
 use style_engine::StyleEngineManager;
 use js_vm::JavaScriptVmManager;
-use rendering_pipeline::RenderingPipeline;
+use rendering_pipeline::{Rectangle, RenderingPipeline};
+use bfcache::{BackForwardCache, RendererSnapshot};
+use find_in_page::{FindInPageSession, FindOptions, FindResult};
+use reader_mode::{ReaderModeExtractor, READER_STYLESHEET};
+use print::{PageLayout, PageMargins, PaperSize, PrintStyles, RenderedPage};
+
+/// Number of crashes within `CRASH_WINDOW` after which a tab is no longer reloaded
+const MAX_CRASHES_IN_WINDOW: usize = 3;
+
+/// Window over which tab crashes are counted
+const CRASH_WINDOW: Duration = Duration::from_secs(30);
+
+/// Interval at which `init` checks renderer processes for crashes
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Work run on the message loop of a renderer process
+pub type RendererTask = Box<dyn FnOnce(Arc<RwLock<RendererProcess>>) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
 
 /// Renderer process configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +83,9 @@ pub struct RendererConfig {
     
     /// Enable WebGPU
     pub webgpu_enabled: bool,
+    
+    /// Maximum number of pages kept in the back-forward cache
+    pub bfcache_size: usize,
 }
 
 impl Default for RendererConfig {
@@ -58,6 +99,7 @@ impl Default for RendererConfig {
             wasm_enabled: true,
             webgl_enabled: true,
             webgpu_enabled: false, // Disabled by default for security
+            bfcache_size: 6,
         }
     }
 }
@@ -92,6 +134,9 @@ pub struct RendererProcess {
     /// Process state
     pub state: RendererState,
     
+    /// URL of the last page loaded in the process
+    pub current_url: Option<String>,
+    
     /// Site isolation manager
     pub site_isolation: Arc<RwLock<SiteIsolationManager>>,
     
@@ -107,9 +152,27 @@ pub struct RendererProcess {
     /// Rendering pipeline
     pub rendering_pipeline: Arc<RwLock<RenderingPipeline>>,
     
+    /// Site isolation manager of the embedding document (iframe processes only)
+    pub embedder_site_isolation: Option<Arc<RwLock<SiteIsolationManager>>>,
+    
+    /// Active service workers controlling the page
+    pub active_service_workers: usize,
+    
+    /// Active find-in-page search
+    pub find_session: Option<FindInPageSession>,
+    
+    /// Network process, which sends CSP violation reports
+    pub network: Option<Arc<RwLock<NetworkProcessManager>>>,
+    
     /// Process configuration
     pub config: RendererConfig,
     
+    /// Operating system process ID, if the renderer runs in its own process
+    pub os_process_id: Option<u32>,
+    
+    /// CPU scheduling priority
+    pub priority: ProcessPriority,
+    
     /// Memory usage (in bytes)
     pub memory_usage: usize,
     
@@ -128,13 +191,58 @@ pub struct RendererProcessManager {
     /// Process configuration
     config: RendererConfig,
     
+    /// Out-of-process iframe links, keyed by child process ID
+    iframe_links: HashMap<u64, IframeLink>,
+    
     /// Next process ID
     next_process_id: u64,
     
+    /// Next DOM property query ID
+    next_query_id: u64,
+    
+    /// Back-forward cache
+    bfcache: BackForwardCache,
+    
+    /// Message loops of the renderer processes, used to detect crashes
+    process_tasks: HashMap<u64, JoinHandle<()>>,
+    
+    /// Work queues of the renderer process message loops
+    process_queues: HashMap<u64, mpsc::UnboundedSender<RendererTask>>,
+    
+    /// Recent crash times per tab
+    crash_history: HashMap<TabId, VecDeque<Instant>>,
+    
+    /// Network process handed to new renderer processes
+    network: Option<Arc<RwLock<NetworkProcessManager>>>,
+    
+    /// IPC channel to the browser UI
+    browser_channel: Channel<IpcMessage>,
+    
+    /// Browser UI end of the IPC channel
+    browser_endpoint: Channel<IpcMessage>,
+    
     /// Process statistics
     stats: RendererStats,
 }
 
+/// Link between an embedding renderer process and an out-of-process iframe
+pub struct IframeLink {
+    /// Embedding (parent) process ID
+    pub parent_process_id: u64,
+    
+    /// Iframe (child) process ID
+    pub child_process_id: u64,
+    
+    /// Iframe URL
+    pub iframe_url: String,
+    
+    /// Parent end of the IPC channel between the processes
+    pub parent_channel: Channel<IpcMessage>,
+    
+    /// Child end of the IPC channel between the processes
+    pub child_channel: Channel<IpcMessage>,
+}
+
 /// Renderer process statistics
 #[derive(Debug, Default)]
 pub struct RendererStats {
@@ -144,6 +252,15 @@ pub struct RendererStats {
     /// Current active processes
     pub active_processes: usize,
     
+    /// Current out-of-process iframe processes
+    pub iframe_processes: usize,
+    
+    /// Back-forward cache hits
+    pub bfcache_hits: usize,
+    
+    /// Back-forward cache misses
+    pub bfcache_misses: usize,
+    
     /// Total crashes
     pub crashes: usize,
     
@@ -159,11 +276,22 @@ impl RendererProcessManager {
     pub async fn new(config: RendererConfig) -> Result<Self> {
         info!("Initializing renderer process manager");
         
+        let (browser_channel, browser_endpoint) = ChannelPair::create()?;
+        
         Ok(Self {
             processes: HashMap::new(),
             site_process_map: HashMap::new(),
+            bfcache: BackForwardCache::new(config.bfcache_size),
             config,
+            iframe_links: HashMap::new(),
             next_process_id: 1,
+            next_query_id: 1,
+            process_tasks: HashMap::new(),
+            process_queues: HashMap::new(),
+            crash_history: HashMap::new(),
+            network: None,
+            browser_channel,
+            browser_endpoint,
             stats: RendererStats::default(),
         })
     }
@@ -179,26 +307,7 @@ impl RendererProcessManager {
             ));
         }
         
-        let process_id = self.next_process_id;
-        self.next_process_id += 1;
-        
-        // Create the renderer process
-        let process = RendererProcess {
-            process_id,
-            tab_id,
-            state: RendererState::Ready,
-            site_isolation: Arc::new(RwLock::new(SiteIsolationManager::new(site_url).await?)),
-            dom_integration: Arc::new(RwLock::new(DomIntegrationManager::new().await?)),
-            style_engine: Arc::new(RwLock::new(StyleEngineManager::new().await?)),
-            js_vm: Arc::new(RwLock::new(JavaScriptVmManager::new(&self.config).await?)),
-            rendering_pipeline: Arc::new(RwLock::new(RenderingPipeline::new(&self.config).await?)),
-            config: self.config.clone(),
-            memory_usage: 0,
-            cpu_usage: 0.0,
-        };
-        
-        // Store the process
-        self.processes.insert(process_id, Arc::new(RwLock::new(process)));
+        let process_id = self.spawn_process(tab_id, site_url, None).await?;
         
         // Map site to process (if site isolation is enabled)
         if self.config.site_isolation_enabled {
@@ -235,15 +344,377 @@ impl RendererProcessManager {
         self.create_process(tab_id, site_url).await
     }
     
+    /// Create a renderer process for a cross-origin iframe
+    ///
+    /// Same-origin iframes stay in the parent's process, so the parent's
+    /// process ID is returned for them.
+    pub async fn create_process_for_iframe(&mut self, parent_process_id: u64, iframe_url: &str) -> Result<u64> {
+        let parent = self.processes.get(&parent_process_id).cloned().ok_or_else(|| {
+            common::error::Error::NotFound(format!("Renderer process {} not found", parent_process_id))
+        })?;
+        
+        let (tab_id, parent_site_isolation) = {
+            let parent_guard = parent.read().await;
+            (parent_guard.tab_id, parent_guard.site_isolation.clone())
+        };
+        
+        if !parent_site_isolation.read().await.is_cross_origin_to(iframe_url) {
+            debug!("Iframe {} is same-origin with process {}", iframe_url, parent_process_id);
+            return Ok(parent_process_id);
+        }
+        
+        info!("Creating out-of-process iframe for {} in process {}", iframe_url, parent_process_id);
+        
+        if self.processes.len() >= self.config.max_processes {
+            return Err(common::error::Error::ConfigError(
+                "Maximum number of renderer processes reached".to_string()
+            ));
+        }
+        
+        let child_process_id = self.spawn_process(tab_id, iframe_url, Some(parent_site_isolation)).await?;
+        
+        let (parent_channel, child_channel) = ChannelPair::create()?;
+        
+        self.iframe_links.insert(child_process_id, IframeLink {
+            parent_process_id,
+            child_process_id,
+            iframe_url: iframe_url.to_string(),
+            parent_channel,
+            child_channel,
+        });
+        
+        self.stats.total_processes += 1;
+        self.stats.active_processes += 1;
+        self.stats.iframe_processes += 1;
+        
+        info!("Iframe process {} created for parent {}", child_process_id, parent_process_id);
+        Ok(child_process_id)
+    }
+    
+    /// Get the iframe link for a child process
+    pub fn get_iframe_link(&self, child_process_id: u64) -> Option<&IframeLink> {
+        self.iframe_links.get(&child_process_id)
+    }
+    
+    /// Get the iframe processes embedded by a parent process
+    pub fn get_iframe_processes(&self, parent_process_id: u64) -> Vec<u64> {
+        let mut children: Vec<u64> = self.iframe_links.values()
+            .filter(|link| link.parent_process_id == parent_process_id)
+            .map(|link| link.child_process_id)
+            .collect();
+        children.sort_unstable();
+        children
+    }
+    
+    /// Query a DOM element property in an iframe process over its IPC channel
+    pub async fn query_iframe_dom_property(
+        &mut self,
+        child_process_id: u64,
+        element_id: &str,
+        property: &str,
+    ) -> Result<Option<serde_json::Value>> {
+        let query_id = self.next_query_id;
+        self.next_query_id += 1;
+        
+        let link = self.iframe_links.get(&child_process_id).ok_or_else(|| {
+            common::error::Error::NotFound(format!("Iframe process {} not found", child_process_id))
+        })?;
+        let parent_channel = link.parent_channel.clone();
+        let child_channel = link.child_channel.clone();
+        
+        // Parent side: send the query
+        parent_channel.send_async(IpcMessage::DomPropertyQuery(DomPropertyQueryMessage {
+            query_id,
+            element_id: element_id.to_string(),
+            property: property.to_string(),
+        })).await?;
+        
+        // Child side: answer the query from the iframe's document
+        self.service_iframe_channel(child_process_id, &child_channel).await?;
+        
+        // Parent side: collect the response
+        match parent_channel.recv_async().await? {
+            IpcMessage::DomPropertyResponse(response) if response.query_id == query_id => Ok(response.value),
+            other => Err(common::error::Error::IpcError(format!(
+                "Unexpected reply from iframe process {} for query {}: {:?}", child_process_id, query_id, other
+            ))),
+        }
+    }
+    
+    /// Answer the next DOM property query on the child end of an iframe channel
+    async fn service_iframe_channel(&self, child_process_id: u64, channel: &Channel<IpcMessage>) -> Result<()> {
+        let child = self.processes.get(&child_process_id).cloned().ok_or_else(|| {
+            common::error::Error::NotFound(format!("Renderer process {} not found", child_process_id))
+        })?;
+        
+        loop {
+            if let IpcMessage::DomPropertyQuery(query) = channel.recv_async().await? {
+                let element = {
+                    let child_guard = child.read().await;
+                    let dom_integration = child_guard.dom_integration.read().await;
+                    dom_integration.get_element_by_id(&query.element_id).await.unwrap_or(None)
+                };
+                let value = element.and_then(|element| element.get(&query.property).cloned());
+                
+                return channel.send_async(IpcMessage::DomPropertyResponse(DomPropertyResponseMessage {
+                    query_id: query.query_id,
+                    value,
+                })).await;
+            }
+        }
+    }
+    
+    /// Freeze a page into the back-forward cache before navigating away
+    ///
+    /// Returns `false` if the page is not eligible for caching.
+    pub async fn store_in_bfcache(&mut self, process_id: u64, response_headers: &HashMap<String, String>) -> Result<bool> {
+        let process = self.processes.get(&process_id).cloned().ok_or_else(|| {
+            common::error::Error::NotFound(format!("Renderer process {} not found", process_id))
+        })?;
+        let process_guard = process.read().await;
+        
+        if let Err(reason) = self.bfcache.check_eligibility(response_headers, process_guard.active_service_workers) {
+            debug!("Process {} not eligible for back-forward cache: {:?}", process_id, reason);
+            return Ok(false);
+        }
+        
+        let (document, document_url) = process_guard.dom_integration.read().await.snapshot_document();
+        let url = match document_url {
+            Some(url) => url,
+            None => process_guard.site_isolation.read().await.site_url().to_string(),
+        };
+        let js_heap = process_guard.js_vm.write().await.freeze_heap();
+        
+        self.bfcache.insert(RendererSnapshot {
+            tab_id: process_guard.tab_id,
+            url: url.clone(),
+            document,
+            js_heap,
+            created_at: std::time::SystemTime::now(),
+        });
+        
+        info!("Stored {} from process {} in back-forward cache", url, process_id);
+        Ok(true)
+    }
+    
+    /// Restore a page from the back-forward cache into a new renderer process
+    pub async fn restore_from_bfcache(&mut self, tab_id: TabId, url: &str) -> Result<u64> {
+        let Some(snapshot) = self.bfcache.take(tab_id, url) else {
+            self.stats.bfcache_misses += 1;
+            return Err(common::error::Error::NotFound(format!(
+                "No back-forward cache entry for {} in tab {}", url, tab_id
+            )));
+        };
+        
+        let process_id = self.create_process(tab_id, url).await?;
+        let process = self.processes.get(&process_id).cloned().ok_or_else(|| {
+            common::error::Error::InvalidState(format!("Renderer process {} vanished", process_id))
+        })?;
+        
+        {
+            let process_guard = process.read().await;
+            process_guard.dom_integration.write().await
+                .restore_document(snapshot.document, Some(snapshot.url.clone()));
+            process_guard.js_vm.write().await.thaw_heap(snapshot.js_heap);
+        }
+        
+        self.stats.bfcache_hits += 1;
+        info!("Restored {} from back-forward cache into process {}", url, process_id);
+        Ok(process_id)
+    }
+    
+    /// Get the back-forward cache
+    pub fn bfcache(&self) -> &BackForwardCache {
+        &self.bfcache
+    }
+    
+    /// Set the network process that renderer processes created from now on use
+    pub fn set_network(&mut self, network: Arc<RwLock<NetworkProcessManager>>) {
+        self.network = Some(network);
+    }
+    
+    /// Get the browser UI end of the IPC channel, which receives crash notifications
+    pub fn browser_endpoint(&self) -> Channel<IpcMessage> {
+        self.browser_endpoint.clone()
+    }
+    
+    /// Run work on the message loop of a renderer process
+    ///
+    /// A panic in the work crashes the process, which the next health check recovers.
+    pub fn post_task<F, Fut>(&self, process_id: u64, task: F) -> Result<()>
+    where
+        F: FnOnce(Arc<RwLock<RendererProcess>>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let queue = self.process_queues.get(&process_id).ok_or_else(|| {
+            common::error::Error::NotFound(format!("Renderer process {} not found", process_id))
+        })?;
+        let task: RendererTask = Box::new(move |process| Box::pin(task(process)));
+        queue.send(task).map_err(|_| {
+            common::error::Error::InvalidState(format!("Renderer process {} is not running", process_id))
+        })
+    }
+    
+    /// Check for crashed renderer processes every `interval` until the manager is dropped
+    pub fn spawn_health_monitor(manager: Weak<RwLock<Self>>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let Some(manager) = manager.upgrade() else { break };
+                let result = manager.write().await.check_process_health().await;
+                if let Err(e) = result {
+                    error!("Failed to recover crashed renderer processes: {}", e);
+                }
+            }
+        })
+    }
+    
+    /// Kill a renderer process without shutting it down
+    pub fn kill_process(&mut self, process_id: u64) -> Result<()> {
+        let task = self.process_tasks.get(&process_id).ok_or_else(|| {
+            common::error::Error::NotFound(format!("Renderer process {} not found", process_id))
+        })?;
+        task.abort();
+        
+        warn!("Renderer process {} killed", process_id);
+        Ok(())
+    }
+    
+    /// Detect renderer processes whose task has died and recover their tabs
+    ///
+    /// Returns the IDs of the replacement processes.
+    pub async fn check_process_health(&mut self) -> Result<Vec<u64>> {
+        let mut crashed: Vec<u64> = self.process_tasks.iter()
+            .filter(|(_, task)| task.is_finished())
+            .map(|(process_id, _)| *process_id)
+            .collect();
+        crashed.sort_unstable();
+        
+        let mut replacements = Vec::new();
+        for process_id in crashed {
+            let Some(task) = self.process_tasks.remove(&process_id) else { continue };
+            let reason = match task.await {
+                Ok(()) => "Renderer process exited unexpectedly".to_string(),
+                Err(e) if e.is_panic() => {
+                    let payload = e.into_panic();
+                    payload.downcast_ref::<&str>().map(|msg| msg.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "Renderer process panicked".to_string())
+                }
+                Err(_) => "Renderer process was killed".to_string(),
+            };
+            
+            if let Some(process) = self.processes.get(&process_id) {
+                error!("Renderer process {} crashed: {}", process_id, reason);
+                process.write().await.state = RendererState::Crashed(reason);
+            }
+            
+            replacements.push(self.handle_crash(process_id).await?);
+        }
+        
+        Ok(replacements)
+    }
+    
+    /// Replace a crashed renderer process and reload its page
+    ///
+    /// The browser UI is notified with a `RendererCrashed` message. Tabs that
+    /// crash too often get a fresh process without reloading the page.
+    pub async fn handle_crash(&mut self, process_id: u64) -> Result<u64> {
+        let process = self.processes.get(&process_id).cloned().ok_or_else(|| {
+            common::error::Error::NotFound(format!("Renderer process {} not found", process_id))
+        })?;
+        
+        let (tab_id, current_url, site_url, reason) = {
+            let process_guard = process.read().await;
+            let reason = match &process_guard.state {
+                RendererState::Crashed(reason) => reason.clone(),
+                _ => "Renderer process crashed".to_string(),
+            };
+            let site_url = process_guard.site_isolation.read().await.site_url().to_string();
+            (process_guard.tab_id, process_guard.current_url.clone(), site_url, reason)
+        };
+        
+        self.stats.crashes += 1;
+        let can_reload = self.record_crash(tab_id);
+        
+        self.terminate_process(process_id).await?;
+        
+        let url = current_url.clone().unwrap_or(site_url);
+        let replacement_id = self.create_process(tab_id, &url).await?;
+        let replacement = self.processes.get(&replacement_id).cloned().ok_or_else(|| {
+            common::error::Error::InvalidState(format!("Renderer process {} vanished", replacement_id))
+        })?;
+        
+        {
+            let mut replacement_guard = replacement.write().await;
+            replacement_guard.initialize().await?;
+            if let (true, Some(url)) = (can_reload, &current_url) {
+                replacement_guard.load_url(url).await?;
+            }
+        }
+        
+        self.browser_channel.send_async(IpcMessage::RendererCrashed(RendererCrashedRequest {
+            renderer_id: RendererId::new(process_id),
+            tab_id,
+            reason,
+            can_reload,
+        })).await?;
+        
+        info!("Replaced crashed renderer process {} with {} (reload: {})", process_id, replacement_id, can_reload);
+        Ok(replacement_id)
+    }
+    
+    /// Record a crash for a tab and return whether the tab may be reloaded
+    fn record_crash(&mut self, tab_id: TabId) -> bool {
+        let now = Instant::now();
+        let history = self.crash_history.entry(tab_id).or_default();
+        while history.front().is_some_and(|time| now.duration_since(*time) > CRASH_WINDOW) {
+            history.pop_front();
+        }
+        history.push_back(now);
+        
+        history.len() < MAX_CRASHES_IN_WINDOW
+    }
+    
     /// Get a renderer process by ID
     pub async fn get_process(&self, process_id: u64) -> Option<Arc<RwLock<RendererProcess>>> {
         self.processes.get(&process_id).cloned()
     }
     
-    /// Terminate a renderer process
+    /// Terminate a renderer process and any iframe processes it embeds
     pub async fn terminate_process(&mut self, process_id: u64) -> Result<()> {
         info!("Terminating renderer process {}", process_id);
         
+        // Collect embedded iframe processes, children first
+        let mut to_terminate = vec![process_id];
+        let mut index = 0;
+        while index < to_terminate.len() {
+            let children = self.get_iframe_processes(to_terminate[index]);
+            to_terminate.extend(children);
+            index += 1;
+        }
+        
+        for id in to_terminate.into_iter().rev() {
+            self.terminate_single_process(id).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Terminate one renderer process
+    async fn terminate_single_process(&mut self, process_id: u64) -> Result<()> {
+        if let Some(task) = self.process_tasks.remove(&process_id) {
+            task.abort();
+        }
+        self.process_queues.remove(&process_id);
+        
+        // Dropping the link closes its channel
+        if self.iframe_links.remove(&process_id).is_some() {
+            self.stats.iframe_processes -= 1;
+        }
+        
         if let Some(process) = self.processes.remove(&process_id) {
             let mut process_guard = process.write().await;
             process_guard.state = RendererState::ShuttingDown;
@@ -251,9 +722,11 @@ impl RendererProcessManager {
             // Clean up site mapping
             let site_key = {
                 let site_isolation = process_guard.site_isolation.read().await;
-                site_isolation.site_url().to_string()
+                self.extract_site_key(site_isolation.site_url())
             };
-            self.site_process_map.remove(&self.extract_site_key(&site_key));
+            if self.site_process_map.get(&site_key) == Some(&process_id) {
+                self.site_process_map.remove(&site_key);
+            }
             
             // Update statistics
             self.stats.active_processes -= 1;
@@ -300,6 +773,77 @@ impl RendererProcessManager {
         Ok(())
     }
     
+    /// Create and register a renderer process
+    async fn spawn_process(
+        &mut self,
+        tab_id: TabId,
+        site_url: &str,
+        embedder_site_isolation: Option<Arc<RwLock<SiteIsolationManager>>>,
+    ) -> Result<u64> {
+        let process_id = self.next_process_id;
+        self.next_process_id += 1;
+        
+        let mut dom_integration = DomIntegrationManager::new().await?;
+        dom_integration.connect_browser(tab_id, self.browser_channel.clone());
+        
+        // Create the renderer process
+        let process = RendererProcess {
+            process_id,
+            tab_id,
+            state: RendererState::Ready,
+            current_url: None,
+            site_isolation: Arc::new(RwLock::new(SiteIsolationManager::new(site_url).await?)),
+            dom_integration: Arc::new(RwLock::new(dom_integration)),
+            style_engine: Arc::new(RwLock::new(StyleEngineManager::new().await?)),
+            js_vm: Arc::new(RwLock::new(JavaScriptVmManager::new(&self.config).await?)),
+            rendering_pipeline: Arc::new(RwLock::new(RenderingPipeline::new(&self.config).await?)),
+            embedder_site_isolation,
+            active_service_workers: 0,
+            find_session: None,
+            network: self.network.clone(),
+            config: self.config.clone(),
+            os_process_id: None,
+            priority: ProcessPriority::Normal,
+            memory_usage: 0,
+            cpu_usage: 0.0,
+        };
+        
+        // Store the process
+        let process = Arc::new(RwLock::new(process));
+        self.processes.insert(process_id, process.clone());
+        
+        // The message loop only finishes if the process dies
+        let (queue, tasks) = mpsc::unbounded_channel();
+        self.process_queues.insert(process_id, queue);
+        self.process_tasks.insert(process_id, tokio::spawn(run_process(process, tasks)));
+        
+        Ok(process_id)
+    }
+    
+    /// Lower the CPU priority of a tab's renderer processes while it is hidden
+    ///
+    /// Renderers sharing the browser process only record the priority.
+    pub async fn set_tab_visibility(&mut self, tab_id: TabId, visible: bool) {
+        let priority = if visible { ProcessPriority::Normal } else { ProcessPriority::Low };
+        
+        for process in self.processes.values() {
+            let mut process_guard = process.write().await;
+            if process_guard.tab_id != tab_id || process_guard.priority == priority {
+                continue;
+            }
+            
+            process_guard.priority = priority;
+            if let Some(pid) = process_guard.os_process_id {
+                // Priority is an optimization, a failure leaves the process usable
+                if let Err(e) = set_process_priority(pid, priority) {
+                    warn!("Failed to set priority of renderer process {}: {}", process_guard.process_id, e);
+                }
+            }
+        }
+        
+        debug!("Tab {} is now {}", tab_id, if visible { "visible" } else { "hidden" });
+    }
+    
     /// Extract site key from URL for site isolation
     fn extract_site_key(&self, url: &str) -> String {
         // Simple site key extraction - in a real implementation, this would be more sophisticated
@@ -363,6 +907,18 @@ impl RendererProcess {
             rendering_pipeline.initialize().await?;
         }
         
+        // Feed layout geometry to intersection observers
+        {
+            let layout_updates = self.rendering_pipeline.read().await.subscribe_layout_updates();
+            self.dom_integration.write().await.subscribe_layout_updates(layout_updates);
+        }
+        
+        // Advance smooth scrolls started by the page every frame
+        {
+            let smooth_scroll = self.dom_integration.read().await.smooth_scroll();
+            self.rendering_pipeline.write().await.set_smooth_scroll(smooth_scroll);
+        }
+        
         self.state = RendererState::Ready;
         info!("Renderer process {} initialized successfully", self.process_id);
         
@@ -371,14 +927,39 @@ impl RendererProcess {
     
     /// Load a URL in the renderer process
     pub async fn load_url(&mut self, url: &str) -> Result<()> {
+        self.load_document(url, &HashMap::new()).await
+    }
+    
+    /// Load a document with the headers of its response
+    ///
+    /// A `Content-Security-Policy` header applies to the new document only.
+    pub async fn load_document(&mut self, url: &str, response_headers: &HashMap<String, String>) -> Result<()> {
         info!("Loading URL {} in renderer process {}", url, self.process_id);
         
         self.state = RendererState::Rendering;
+        self.current_url = Some(url.to_string());
+        common::crash::CrashReporter::register_metadata("tab_url", url);
+        
+        // A new document ends the search in the old one
+        self.find_session = None;
+        self.rendering_pipeline.write().await.set_highlights(Vec::new());
+        
+        // and leaves fullscreen
+        if self.dom_integration.read().await.fullscreen_element().is_some() {
+            self.exit_fullscreen().await?;
+        }
         
         // Load URL in site isolation
         {
             let mut site_isolation = self.site_isolation.write().await;
             site_isolation.load_url(url).await?;
+            
+            let csp = response_headers.iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-security-policy"))
+                .map(|(_, value)| value);
+            if let Some(csp) = csp {
+                site_isolation.set_csp_header(csp).await?;
+            }
         }
         
         // Parse HTML and create DOM
@@ -389,13 +970,18 @@ impl RendererProcess {
         
         // Apply styles
         {
+            let (document, _) = self.dom_integration.read().await.snapshot_document();
             let mut style_engine = self.style_engine.write().await;
+            if let Some(document) = document {
+                style_engine.set_document(document.root);
+            }
             style_engine.apply_styles().await?;
         }
         
         // Execute JavaScript
         {
             let mut js_vm = self.js_vm.write().await;
+            js_vm.set_scripting_enabled(true);
             js_vm.execute_scripts().await?;
         }
         
@@ -405,37 +991,418 @@ impl RendererProcess {
             rendering_pipeline.render_page().await?;
         }
         
+        // Update intersection observers with the new layout
+        {
+            let mut dom_integration = self.dom_integration.write().await;
+            dom_integration.process_intersections().await;
+        }
+        
+        // Run the resize observer microtask queued by the layout pass
+        {
+            let mut rendering_pipeline = self.rendering_pipeline.write().await;
+            if rendering_pipeline.resize_observers().has_pending_delivery() {
+                rendering_pipeline.resize_observers_mut().deliver_pending_entries();
+            }
+        }
+        
+        // Settle Web Animations that ended during the frame
+        {
+            let rendering_pipeline = self.rendering_pipeline.read().await;
+            let mut dom_integration = self.dom_integration.write().await;
+            dom_integration.update_animations(rendering_pipeline.animation_engine());
+        }
+        
         self.state = RendererState::Ready;
         info!("URL {} loaded successfully in renderer process {}", url, self.process_id);
         
         Ok(())
     }
     
-    /// Execute JavaScript in the renderer process
-    pub async fn execute_script(&self, script: &str) -> Result<serde_json::Value> {
-        let js_vm = self.js_vm.read().await;
-        js_vm.execute_script(script).await
-    }
-    
-    /// Get the current DOM tree
-    pub async fn get_dom_tree(&self) -> Result<serde_json::Value> {
-        let dom_integration = self.dom_integration.read().await;
-        dom_integration.get_dom_tree().await
+    /// Resize the window of the renderer process
+    ///
+    /// Media queries are re-evaluated against the new viewport before the
+    /// page is rendered again.
+    pub async fn resize(&mut self, width: u32, height: u32, device_pixel_ratio: f32) -> Result<()> {
+        info!("Resizing renderer process {} to {}x{}", self.process_id, width, height);
+        
+        {
+            let mut style_engine = self.style_engine.write().await;
+            style_engine.set_viewport_size(width, height);
+            style_engine.media_evaluator_mut().device_pixel_ratio = device_pixel_ratio;
+            style_engine.apply_styles().await?;
+        }
+        
+        {
+            let mut rendering_pipeline = self.rendering_pipeline.write().await;
+            let viewport = rendering_pipeline.layout_geometry().viewport;
+            rendering_pipeline.set_viewport(Rectangle {
+                width: width as f32,
+                height: height as f32,
+                ..viewport
+            });
+            rendering_pipeline.render_page().await?;
+        }
+        
+        Ok(())
     }
     
-    /// Get computed styles for an element
-    pub async fn get_computed_styles(&self, element_id: &str) -> Result<serde_json::Value> {
-        let style_engine = self.style_engine.read().await;
-        style_engine.get_computed_styles(element_id).await
+    /// Show an element fullscreen, as `Element.requestFullscreen()`
+    ///
+    /// The browser is asked to make the window fullscreen and the element
+    /// is laid out over the whole viewport.
+    pub async fn request_fullscreen(&mut self, element_id: &str) -> Result<()> {
+        self.dom_integration.write().await.request_fullscreen(element_id).await?;
+        
+        let mut rendering_pipeline = self.rendering_pipeline.write().await;
+        rendering_pipeline.set_fullscreen_element(Some(element_id));
+        rendering_pipeline.render_page().await
     }
     
-    /// Take a screenshot of the current page
-    pub async fn take_screenshot(&self) -> Result<Vec<u8>> {
-        let rendering_pipeline = self.rendering_pipeline.read().await;
-        rendering_pipeline.take_screenshot().await
+    /// Leave fullscreen, as `document.exitFullscreen()`
+    pub async fn exit_fullscreen(&mut self) -> Result<()> {
+        self.dom_integration.write().await.exit_fullscreen().await?;
+        
+        let mut rendering_pipeline = self.rendering_pipeline.write().await;
+        rendering_pipeline.set_fullscreen_element(None);
+        rendering_pipeline.render_page().await
     }
     
-    /// Update memory and CPU usage
+    /// Apply a change the browser made to the window on its own, such as leaving fullscreen
+    pub async fn handle_browser_event(&mut self, event: BrowserEvent) -> Result<()> {
+        self.dom_integration.write().await.handle_browser_event(event);
+        
+        let fullscreen_element = self.dom_integration.read().await.fullscreen_element().map(str::to_string);
+        let mut rendering_pipeline = self.rendering_pipeline.write().await;
+        if rendering_pipeline.fullscreen_element() != fullscreen_element.as_deref() {
+            rendering_pipeline.set_fullscreen_element(fullscreen_element.as_deref());
+            rendering_pipeline.render_page().await?;
+        }
+        Ok(())
+    }
+    
+    /// Check a sub-resource load against the page's security policies
+    pub async fn load_subresource(&self, url: &str, resource_type: csp::CspResourceType) -> Result<()> {
+        let result = self.site_isolation.write().await.check_csp(url, resource_type).await;
+        match result {
+            csp::CspResult::Allowed => Ok(()),
+            csp::CspResult::Blocked(directive) => {
+                self.send_csp_reports().await;
+                Err(common::error::Error::SecurityError(format!(
+                    "Refused to load {} because it violates the Content Security Policy directive '{}'",
+                    url, directive.name()
+                )))
+            }
+        }
+    }
+    
+    /// POST pending CSP violation reports to their `report-uri` through the network process
+    ///
+    /// Reports stay queued while the process has no network process.
+    async fn send_csp_reports(&self) {
+        let Some(network) = &self.network else { return };
+        let reports = self.site_isolation.write().await.take_csp_violation_reports();
+        
+        for report in reports {
+            let Some(report_uri) = &report.report_uri else { continue };
+            // A report that fails to send does not affect the page
+            if let Err(e) = network.write().await.send_report(self.tab_id, report_uri, &report.to_report_body()).await {
+                warn!("Failed to send CSP violation report to {}: {}", report_uri, e);
+            }
+        }
+    }
+    
+    /// Run a fetched `<script>` or stylesheet after CSP and integrity checks
+    pub async fn execute_subresource(
+        &self,
+        url: &str,
+        resource_type: csp::CspResourceType,
+        body: &[u8],
+        integrity: Option<&str>,
+    ) -> Result<()> {
+        self.load_subresource(url, resource_type).await?;
+        
+        if let Some(integrity) = integrity {
+            let result = self.site_isolation.write().await.verify_sri(url, body, integrity).await;
+            if !self.dom_integration.write().await.check_subresource_integrity(url, &result) {
+                return Err(common::error::Error::SecurityError(format!(
+                    "Blocked {} because it failed its integrity check", url
+                )));
+            }
+        }
+        
+        let content = String::from_utf8_lossy(body);
+        match resource_type {
+            csp::CspResourceType::Script => {
+                self.js_vm.read().await.execute_script(&content).await?;
+            }
+            csp::CspResourceType::Style => {
+                self.style_engine.write().await.add_style_sheet(&content, Some(url)).await?;
+            }
+            _ => {}
+        }
+        
+        Ok(())
+    }
+    
+    /// Replace the page with a clean rendering of its main article
+    ///
+    /// The page's style sheets are replaced by the reader stylesheet and
+    /// scripts stop running until the next load.
+    pub async fn enter_reader_mode(&mut self) -> Result<()> {
+        let (document, document_url) = self.dom_integration.read().await.snapshot_document();
+        let document = document.ok_or_else(|| common::error::Error::ConfigError("No document loaded".to_string()))?;
+        let article = ReaderModeExtractor::extract(&document).ok_or_else(|| {
+            common::error::Error::NotFound("No article found in the page".to_string())
+        })?;
+        info!("Entering reader mode for {:?} in renderer process {}", article.title, self.process_id);
+        
+        self.js_vm.write().await.set_scripting_enabled(false);
+        self.find_session = None;
+        
+        let reader_document = article.to_document();
+        let root = reader_document.root.clone();
+        self.dom_integration.write().await.restore_document(Some(reader_document), document_url);
+        
+        {
+            let mut style_engine = self.style_engine.write().await;
+            style_engine.clear_style_sheets().await;
+            style_engine.add_style_sheet(READER_STYLESHEET, None).await?;
+            style_engine.set_document(root);
+            style_engine.apply_styles().await?;
+        }
+        
+        let mut rendering_pipeline = self.rendering_pipeline.write().await;
+        rendering_pipeline.set_highlights(Vec::new());
+        rendering_pipeline.set_inspector_highlight(None);
+        rendering_pipeline.render_page().await
+    }
+    
+    /// Search the text of the page and highlight the matches
+    ///
+    /// The first match becomes the current one. An empty query ends the search.
+    pub async fn find_text(&mut self, query: &str, options: FindOptions) -> Result<FindResult> {
+        if query.is_empty() {
+            self.stop_finding().await?;
+            return Ok(FindResult::default());
+        }
+        
+        let texts = self.dom_integration.read().await.element_texts()?;
+        let session = FindInPageSession::new(query, &options, &texts);
+        let result = session.result();
+        debug!("Found {} matches for {:?} in renderer process {}", result.total_matches, query, self.process_id);
+        
+        self.find_session = Some(session);
+        self.render_find_highlights().await?;
+        Ok(result)
+    }
+    
+    /// Make the next match current, wrapping to the first
+    pub async fn find_next(&mut self) -> Result<FindResult> {
+        let result = self.active_find_session()?.find_next();
+        self.render_find_highlights().await?;
+        Ok(result)
+    }
+    
+    /// Make the previous match current, wrapping to the last
+    pub async fn find_previous(&mut self) -> Result<FindResult> {
+        let result = self.active_find_session()?.find_previous();
+        self.render_find_highlights().await?;
+        Ok(result)
+    }
+    
+    /// End the search and remove its highlights
+    pub async fn stop_finding(&mut self) -> Result<()> {
+        if self.find_session.take().is_some() {
+            self.render_find_highlights().await?;
+        }
+        Ok(())
+    }
+    
+    fn active_find_session(&mut self) -> Result<&mut FindInPageSession> {
+        self.find_session.as_mut().ok_or_else(|| {
+            common::error::Error::InvalidState("No find in page search is active".to_string())
+        })
+    }
+    
+    /// Draw the highlights of the search over the page
+    async fn render_find_highlights(&self) -> Result<()> {
+        let mut rendering_pipeline = self.rendering_pipeline.write().await;
+        let highlights = match &self.find_session {
+            Some(session) => session.highlights(rendering_pipeline.layout_geometry()),
+            None => Vec::new(),
+        };
+        rendering_pipeline.set_highlights(highlights);
+        rendering_pipeline.render_page().await
+    }
+    
+    /// Select text from a start boundary to an end boundary and highlight it
+    pub async fn set_selection_range(&mut self, start_node: &str, start_offset: usize, end_node: &str, end_offset: usize) -> Result<()> {
+        self.dom_integration.write().await.set_selection_range(start_node, start_offset, end_node, end_offset)?;
+        self.render_selection_highlights().await
+    }
+    
+    /// Move or extend the selection and highlight it
+    pub async fn modify_selection(&mut self, alter: SelectionAlter, direction: SelectionDirection, granularity: SelectionGranularity) -> Result<()> {
+        self.dom_integration.write().await.modify_selection(alter, direction, granularity);
+        self.render_selection_highlights().await
+    }
+    
+    /// Draw the selection over the page
+    async fn render_selection_highlights(&self) -> Result<()> {
+        let mut rendering_pipeline = self.rendering_pipeline.write().await;
+        let highlights = self.dom_integration.read().await.get_selection().highlights(rendering_pipeline.layout_geometry());
+        rendering_pipeline.set_selection_highlights(highlights);
+        rendering_pipeline.render_page().await
+    }
+    
+    /// Execute JavaScript in the renderer process
+    pub async fn execute_script(&self, script: &str) -> Result<serde_json::Value> {
+        let js_vm = self.js_vm.read().await;
+        js_vm.execute_script(script).await
+    }
+    
+    /// Get the current DOM tree
+    ///
+    /// Laid out elements carry their border box as `boundingBox`.
+    pub async fn get_dom_tree(&self) -> Result<serde_json::Value> {
+        let mut tree = self.dom_integration.read().await.get_dom_tree().await?;
+        let rendering_pipeline = self.rendering_pipeline.read().await;
+        attach_bounding_boxes(&mut tree, &rendering_pipeline.layout_geometry().elements);
+        Ok(tree)
+    }
+    
+    /// Answer a DevTools request for this renderer's tab
+    ///
+    /// Returns the reply to send back, if the request has one.
+    pub async fn handle_devtools_request(&self, request: DevToolsRequest) -> Result<Option<DevToolsResponse>> {
+        match request {
+            DevToolsRequest::GetDomTree { request_id, tab_id } => {
+                if tab_id != self.tab_id {
+                    return Ok(Some(DevToolsResponse::Error {
+                        request_id,
+                        message: format!("Tab {:?} is not rendered by process {}", tab_id, self.process_id),
+                    }));
+                }
+                let response = match self.get_dom_tree().await {
+                    Ok(tree) => DevToolsResponse::DomTree { request_id, tree: tree.to_string() },
+                    Err(e) => DevToolsResponse::Error { request_id, message: e.to_string() },
+                };
+                Ok(Some(response))
+            }
+            DevToolsRequest::HighlightElement { tab_id, element_id } => {
+                if tab_id != self.tab_id {
+                    return Ok(None);
+                }
+                let mut rendering_pipeline = self.rendering_pipeline.write().await;
+                let rect = element_id.and_then(|id| rendering_pipeline.layout_geometry().elements.get(&id).copied());
+                rendering_pipeline.set_inspector_highlight(rect);
+                rendering_pipeline.render_page().await?;
+                Ok(None)
+            }
+            DevToolsRequest::CaptureScreenshot { request_id, tab_id } => {
+                if tab_id != self.tab_id {
+                    return Ok(Some(DevToolsResponse::Error {
+                        request_id,
+                        message: format!("Tab {:?} is not rendered by process {}", tab_id, self.process_id),
+                    }));
+                }
+                let response = match self.take_screenshot().await {
+                    Ok(pixels) => DevToolsResponse::Screenshot {
+                        request_id,
+                        width: rendering_pipeline::SCREENSHOT_WIDTH,
+                        height: rendering_pipeline::SCREENSHOT_HEIGHT,
+                        pixels,
+                    },
+                    Err(e) => DevToolsResponse::Error { request_id, message: e.to_string() },
+                };
+                Ok(Some(response))
+            }
+        }
+    }
+    
+    /// Answer the next DevTools or debugger request on a channel from DevTools
+    pub async fn service_devtools_channel(&self, channel: &Channel<IpcMessage>) -> Result<()> {
+        loop {
+            match channel.recv_async().await? {
+                IpcMessage::DevToolsRequest(request) => {
+                    if let Some(response) = self.handle_devtools_request(request).await? {
+                        channel.send_async(IpcMessage::DevToolsResponse(response)).await?;
+                    }
+                    return Ok(());
+                }
+                IpcMessage::DebuggerRequest(DebuggerRequest::Evaluate { request_id, expression }) => {
+                    let event = self.js_vm.read().await.evaluate(request_id, &expression).await;
+                    channel.send_async(IpcMessage::DebuggerEvent(event)).await?;
+                    return Ok(());
+                }
+                IpcMessage::DebuggerRequest(request) => {
+                    let event = self.js_vm.write().await.handle_debugger_request(request)?;
+                    if let Some(event) = event {
+                        channel.send_async(IpcMessage::DebuggerEvent(event)).await?;
+                    }
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+    }
+    
+    /// Get computed styles for an element
+    pub async fn get_computed_styles(&self, element_id: &str) -> Result<serde_json::Value> {
+        let style_engine = self.style_engine.read().await;
+        style_engine.get_computed_styles(element_id).await
+    }
+    
+    /// Take a screenshot of the current page
+    pub async fn take_screenshot(&self) -> Result<Vec<u8>> {
+        let rendering_pipeline = self.rendering_pipeline.read().await;
+        rendering_pipeline.take_screenshot().await
+    }
+    
+    /// Print the document onto pages of a paper size, as for `window.print()`
+    ///
+    /// Styles are applied for the `print` media type and a viewport the size
+    /// of the page content, then restored for the screen.
+    pub async fn render_for_print(&self, page_size: PaperSize, margins: PageMargins) -> Result<Vec<RenderedPage>> {
+        info!("Printing the document of renderer process {}", self.process_id);
+        
+        let page = PageLayout::new(page_size, margins)?;
+        let (document, _) = self.dom_integration.read().await.snapshot_document();
+        let document = document.ok_or_else(|| common::error::Error::ConfigError("No document loaded".to_string()))?;
+        
+        let print_styles = {
+            let mut style_engine = self.style_engine.write().await;
+            let screen = style_engine.media_evaluator().clone();
+            let evaluator = style_engine.media_evaluator_mut();
+            evaluator.media_type = dom::MediaType::Print;
+            evaluator.set_viewport_size(page.content.width as u32, page.content.height as u32);
+            let applied = style_engine.apply_styles().await;
+            let print_styles = PrintStyles::new(style_engine.applied_rules());
+            
+            *style_engine.media_evaluator_mut() = screen;
+            style_engine.apply_styles().await?;
+            applied?;
+            print_styles
+        };
+        
+        let pages = {
+            let rendering_pipeline = self.rendering_pipeline.read().await;
+            print::paginate(&document, &print_styles, &rendering_pipeline, &page)
+        };
+        debug!("Printing {} pages from renderer process {}", pages.len(), self.process_id);
+        print::rasterize_pages(pages, &page).await
+    }
+    
+    /// Print the document for a request from the browser
+    pub async fn handle_print_request(&self, request: PrintRequest) -> PrintResponse {
+        let (pages, error) = match self.render_for_print(request.paper_size, request.margins).await {
+            Ok(pages) => (pages, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        PrintResponse { request_id: request.request_id, tab_id: request.tab_id, pages, error }
+    }
+    
+    /// Update memory and CPU usage
     pub async fn update_usage_stats(&mut self) -> Result<()> {
         // TODO: Implement actual usage monitoring
         self.memory_usage = 1024 * 1024; // 1MB placeholder
@@ -444,10 +1411,47 @@ impl RendererProcess {
     }
 }
 
-/// Initialize the renderer process
-pub async fn init(config: RendererConfig) -> Result<RendererProcessManager> {
+/// Initialize the renderer process, recovering crashed renderer processes in the background
+pub async fn init(config: RendererConfig) -> Result<Arc<RwLock<RendererProcessManager>>> {
     info!("Initializing renderer process");
-    RendererProcessManager::new(config).await
+    let manager = Arc::new(RwLock::new(RendererProcessManager::new(config).await?));
+    RendererProcessManager::spawn_health_monitor(Arc::downgrade(&manager), HEALTH_CHECK_INTERVAL);
+    Ok(manager)
+}
+
+/// Message loop of a renderer process, running posted work in order
+async fn run_process(process: Arc<RwLock<RendererProcess>>, mut tasks: mpsc::UnboundedReceiver<RendererTask>) {
+    while let Some(task) = tasks.recv().await {
+        task(process.clone()).await;
+    }
+}
+
+/// Add the laid out border box of each element in a serialized DOM tree
+///
+/// Layout keys elements by their `id` attribute, falling back to the node ID.
+fn attach_bounding_boxes(node: &mut serde_json::Value, geometry: &HashMap<String, Rectangle>) {
+    if let Some(element) = node.get_mut("documentElement") {
+        attach_bounding_boxes(element, geometry);
+        return;
+    }
+    
+    let key = node.get("id").and_then(|id| id.as_str())
+        .or_else(|| node.get("nodeId").and_then(|id| id.as_str()))
+        .map(str::to_string);
+    if let Some(rect) = key.and_then(|key| geometry.get(&key)) {
+        node["boundingBox"] = serde_json::json!({
+            "x": rect.x,
+            "y": rect.y,
+            "width": rect.width,
+            "height": rect.height,
+        });
+    }
+    
+    if let Some(children) = node.get_mut("children").and_then(|children| children.as_array_mut()) {
+        for child in children {
+            attach_bounding_boxes(child, geometry);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -514,4 +1518,666 @@ mod tests {
         let process_id2 = manager.create_process(tab_id2, "https://different.com").await;
         assert!(process_id2.is_err());
     }
+
+    #[tokio::test]
+    async fn test_out_of_process_iframe() {
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let tab_id = TabId::new(1);
+        let parent_id = manager.create_process(tab_id, "https://example.com").await.unwrap();
+        
+        // Same-origin iframes stay in the parent process
+        let same_origin = manager.create_process_for_iframe(parent_id, "https://example.com/frame").await.unwrap();
+        assert_eq!(same_origin, parent_id);
+        assert_eq!(manager.get_stats().iframe_processes, 0);
+        
+        let child_id = manager.create_process_for_iframe(parent_id, "https://ads.example.net/frame").await.unwrap();
+        assert_ne!(child_id, parent_id);
+        assert_eq!(manager.get_stats().iframe_processes, 1);
+        assert_eq!(manager.get_iframe_processes(parent_id), vec![child_id]);
+        
+        let child = manager.get_process(child_id).await.unwrap();
+        assert!(child.read().await.embedder_site_isolation.is_some());
+        
+        // DOM property queries round-trip over the iframe channel
+        child.write().await.initialize().await.unwrap();
+        let value = manager.query_iframe_dom_property(child_id, "missing", "tagName").await.unwrap();
+        assert!(value.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_iframe_processes_terminate_with_parent() {
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let tab_id = TabId::new(1);
+        let parent_id = manager.create_process(tab_id, "https://example.com").await.unwrap();
+        let child_id = manager.create_process_for_iframe(parent_id, "https://widgets.example.org").await.unwrap();
+        let grandchild_id = manager.create_process_for_iframe(child_id, "https://cdn.example.io").await.unwrap();
+        
+        let channel = manager.get_iframe_link(child_id).unwrap().parent_channel.clone();
+        
+        manager.terminate_process(parent_id).await.unwrap();
+        
+        assert!(manager.get_process(parent_id).await.is_none());
+        assert!(manager.get_process(child_id).await.is_none());
+        assert!(manager.get_process(grandchild_id).await.is_none());
+        assert!(channel.recv_async().await.is_err());
+        assert_eq!(manager.get_stats().iframe_processes, 0);
+        assert_eq!(manager.get_stats().active_processes, 0);
+    }
+
+    #[tokio::test]
+    async fn test_bfcache_restore() {
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let tab_id = TabId::new(1);
+        let process_id = manager.create_process(tab_id, "https://example.com/article").await.unwrap();
+        let process = manager.get_process(process_id).await.unwrap();
+        process.write().await.initialize().await.unwrap();
+        process.write().await.load_url("https://example.com/article").await.unwrap();
+        let dom_tree = process.read().await.get_dom_tree().await.unwrap();
+        {
+            let process_guard = process.read().await;
+            let mut js_vm = process_guard.js_vm.write().await;
+            js_vm.add_event_listener("click", None, |_| Ok(serde_json::json!("clicked"))).await.unwrap();
+            js_vm.set_timeout(|| {}, 60_000).await.unwrap();
+        }
+        
+        assert!(manager.store_in_bfcache(process_id, &HashMap::new()).await.unwrap());
+        manager.terminate_process(process_id).await.unwrap();
+        
+        let restored_id = manager.restore_from_bfcache(tab_id, "https://example.com/article").await.unwrap();
+        assert_ne!(restored_id, process_id);
+        
+        let restored = manager.get_process(restored_id).await.unwrap();
+        assert_eq!(restored.read().await.get_dom_tree().await.unwrap(), dom_tree);
+        {
+            // Listeners and timers survive the round trip
+            let restored_guard = restored.read().await;
+            let js_vm = restored_guard.js_vm.read().await;
+            let results = js_vm.dispatch_event("click", serde_json::Value::Null).await;
+            assert_eq!(results.into_iter().map(|result| result.unwrap()).collect::<Vec<_>>(), ["clicked"]);
+            let stats = js_vm.get_stats().await.unwrap();
+            assert_eq!(stats["activeTimers"], 1);
+        }
+        assert_eq!(manager.get_stats().bfcache_hits, 1);
+        
+        // Entries are consumed on restore
+        assert!(manager.restore_from_bfcache(tab_id, "https://example.com/article").await.is_err());
+        assert_eq!(manager.get_stats().bfcache_misses, 1);
+    }
+
+    #[tokio::test]
+    async fn test_bfcache_eligibility() {
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let tab_id = TabId::new(1);
+        let process_id = manager.create_process(tab_id, "https://example.com").await.unwrap();
+        
+        let mut headers = HashMap::new();
+        headers.insert("Cache-Control".to_string(), "private, no-store".to_string());
+        assert!(!manager.store_in_bfcache(process_id, &headers).await.unwrap());
+        
+        let process = manager.get_process(process_id).await.unwrap();
+        process.write().await.active_service_workers = 1;
+        assert!(!manager.store_in_bfcache(process_id, &HashMap::new()).await.unwrap());
+        
+        assert!(manager.bfcache().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bfcache_capacity() {
+        let config = RendererConfig {
+            bfcache_size: 2,
+            ..Default::default()
+        };
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let tab_id = TabId::new(1);
+        for url in ["https://a.example.com", "https://b.example.com", "https://c.example.com"] {
+            let process_id = manager.create_process(tab_id, url).await.unwrap();
+            assert!(manager.store_in_bfcache(process_id, &HashMap::new()).await.unwrap());
+            manager.terminate_process(process_id).await.unwrap();
+        }
+        
+        assert_eq!(manager.bfcache().len(), 2);
+        assert!(!manager.bfcache().contains(tab_id, "https://a.example.com"));
+        assert!(manager.bfcache().contains(tab_id, "https://c.example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_subresource_integrity_blocks_tampered_script() {
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let process_id = manager.create_process(TabId::new(1), "https://example.com").await.unwrap();
+        let process = manager.get_process(process_id).await.unwrap();
+        let process = process.read().await;
+        // sha256 of "alert(1)"
+        let integrity = Some("sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI=");
+        
+        let result = process.execute_subresource(
+            "https://cdn.example.net/a.js", csp::CspResourceType::Script, b"alert(1)", integrity,
+        ).await;
+        assert!(result.is_ok());
+        
+        let result = process.execute_subresource(
+            "https://cdn.example.net/a.js", csp::CspResourceType::Script, b"alert(2)", integrity,
+        ).await;
+        assert!(result.is_err());
+        
+        let dom_integration = process.dom_integration.read().await;
+        let messages = dom_integration.console_messages();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].level, dom_integration::ConsoleLevel::Error);
+        assert!(messages[0].message.contains("https://cdn.example.net/a.js"));
+    }
+
+    #[tokio::test]
+    async fn test_csp_header_applies_per_document_and_reports_violations() {
+        use std::sync::Mutex;
+        
+        /// Records the reports POSTed to the reporting endpoint
+        #[derive(Default)]
+        struct ReportCollector(Mutex<Vec<(String, String, serde_json::Value)>>);
+        
+        #[async_trait::async_trait]
+        impl network::FetchInterceptor for ReportCollector {
+            async fn intercept_fetch(&self, request: &network::NetworkRequest) -> Option<network::NetworkResponse> {
+                let body = serde_json::from_slice(request.body.as_deref()?).ok()?;
+                self.0.lock().unwrap().push((request.method.clone(), request.url.clone(), body));
+                Some(network::NetworkResponse {
+                    status_code: 204,
+                    headers: HashMap::new(),
+                    body: Vec::new(),
+                    content_type: String::new(),
+                    content_length: 0,
+                    response_time: Duration::ZERO,
+                })
+            }
+        }
+        
+        let collector = Arc::new(ReportCollector::default());
+        let mut network = NetworkProcessManager::new(network::NetworkConfig::default()).await.unwrap();
+        network.set_fetch_interceptor(collector.clone());
+        
+        let mut manager = RendererProcessManager::new(RendererConfig::default()).await.unwrap();
+        manager.set_network(Arc::new(RwLock::new(network)));
+        let process_id = manager.create_process(TabId::new(1), "https://example.com").await.unwrap();
+        let process = manager.get_process(process_id).await.unwrap();
+        let mut process = process.write().await;
+        process.initialize().await.unwrap();
+        
+        let headers = HashMap::from([(
+            "content-security-policy".to_string(),
+            "script-src 'self'; report-uri /csp-reports".to_string(),
+        )]);
+        process.load_document("https://example.com/page", &headers).await.unwrap();
+        assert!(process.load_subresource("https://example.com/app.js", csp::CspResourceType::Script).await.is_ok());
+        assert!(process.load_subresource("https://evil.example/x.js", csp::CspResourceType::Script).await.is_err());
+        
+        let reports = collector.0.lock().unwrap().clone();
+        assert_eq!(reports.len(), 1);
+        let (method, url, body) = &reports[0];
+        assert_eq!((method.as_str(), url.as_str()), ("POST", "https://example.com/csp-reports"));
+        assert_eq!(body["csp-report"]["blocked-uri"], "https://evil.example/x.js");
+        assert_eq!(body["csp-report"]["document-uri"], "https://example.com/page");
+        
+        // The next document without a policy loads anything
+        process.load_url("https://example.com/other").await.unwrap();
+        assert!(process.site_isolation.read().await.security_context().csp.is_none());
+        assert!(process.load_subresource("https://evil.example/x.js", csp::CspResourceType::Script).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_crash_recovery() {
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let tab_id = TabId::new(1);
+        let process_id = manager.create_process(tab_id, "https://example.com").await.unwrap();
+        let process = manager.get_process(process_id).await.unwrap();
+        process.write().await.initialize().await.unwrap();
+        process.write().await.load_url("https://example.com/page").await.unwrap();
+        
+        // Healthy processes are left alone
+        assert!(manager.check_process_health().await.unwrap().is_empty());
+        
+        manager.kill_process(process_id).unwrap();
+        tokio::task::yield_now().await;
+        
+        let replacements = manager.check_process_health().await.unwrap();
+        assert_eq!(replacements.len(), 1);
+        assert!(manager.get_process(process_id).await.is_none());
+        assert!(matches!(process.read().await.state, RendererState::ShuttingDown));
+        
+        let replacement = manager.get_process(replacements[0]).await.unwrap();
+        assert_eq!(replacement.read().await.current_url.as_deref(), Some("https://example.com/page"));
+        assert_eq!(manager.get_stats().crashes, 1);
+        assert_eq!(manager.get_stats().active_processes, 1);
+        
+        match manager.browser_endpoint().recv_async().await.unwrap() {
+            IpcMessage::RendererCrashed(crashed) => {
+                assert_eq!(crashed.renderer_id, RendererId::new(process_id));
+                assert_eq!(crashed.tab_id, tab_id);
+                assert_eq!(crashed.reason, "Renderer process was killed");
+                assert!(crashed.can_reload);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_panicking_renderer_work_is_recovered() {
+        let tab_id = TabId::new(1);
+        let manager = init(RendererConfig::default()).await.unwrap();
+        let (process_id, browser_endpoint) = {
+            let mut manager = manager.write().await;
+            let process_id = manager.create_process(tab_id, "https://example.com").await.unwrap();
+            (process_id, manager.browser_endpoint())
+        };
+        
+        // Work runs on the process's message loop
+        let (loaded_tx, loaded_rx) = tokio::sync::oneshot::channel();
+        manager.read().await.post_task(process_id, |process| async move {
+            let mut process = process.write().await;
+            process.initialize().await.unwrap();
+            process.load_url("https://example.com/page").await.unwrap();
+            loaded_tx.send(()).unwrap();
+        }).unwrap();
+        loaded_rx.await.unwrap();
+        
+        manager.read().await.post_task(process_id, |process| async move {
+            let _process = process.write().await;
+            panic!("layout overflowed");
+        }).unwrap();
+        
+        // The health monitor notices the crash without being asked
+        let crashed = tokio::time::timeout(Duration::from_secs(10), browser_endpoint.recv_async()).await.unwrap().unwrap();
+        match crashed {
+            IpcMessage::RendererCrashed(crashed) => {
+                assert_eq!(crashed.renderer_id, RendererId::new(process_id));
+                assert_eq!(crashed.tab_id, tab_id);
+                assert_eq!(crashed.reason, "layout overflowed");
+                assert!(crashed.can_reload);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+        
+        let manager = manager.read().await;
+        assert!(manager.get_process(process_id).await.is_none());
+        assert_eq!(manager.get_stats().crashes, 1);
+        let replacement = manager.get_active_processes().await.pop().unwrap();
+        assert_eq!(replacement.read().await.current_url.as_deref(), Some("https://example.com/page"));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_crashes_disable_reload() {
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let tab_id = TabId::new(1);
+        let mut process_id = manager.create_process(tab_id, "https://example.com").await.unwrap();
+        let process = manager.get_process(process_id).await.unwrap();
+        process.write().await.initialize().await.unwrap();
+        process.write().await.load_url("https://example.com/page").await.unwrap();
+        
+        let mut can_reload = Vec::new();
+        for _ in 0..3 {
+            process_id = manager.handle_crash(process_id).await.unwrap();
+            if let IpcMessage::RendererCrashed(crashed) = manager.browser_endpoint().recv_async().await.unwrap() {
+                can_reload.push(crashed.can_reload);
+            }
+        }
+        
+        assert_eq!(can_reload, vec![true, true, false]);
+        assert_eq!(manager.get_stats().crashes, 3);
+        
+        // The last replacement was not reloaded
+        let process = manager.get_process(process_id).await.unwrap();
+        assert!(process.read().await.current_url.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_selection_highlights_selected_text() {
+        use rendering_pipeline::DisplayCommand;
+        
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let process_id = manager.create_process(TabId::new(1), "https://example.com").await.unwrap();
+        let process = manager.get_process(process_id).await.unwrap();
+        let mut process = process.write().await;
+        process.initialize().await.unwrap();
+        process.load_url("https://example.com/page").await.unwrap();
+        
+        for (id, text) in [("first", "Select this text"), ("second", "and this too")] {
+            let mut paragraph = dom::Element::new("p".to_string());
+            paragraph.set_attribute("id".to_string(), id.to_string());
+            paragraph.append_child(dom::Node::Text(dom::TextNode::new(text.to_string())));
+            process.dom_integration.write().await.insert_element(None, paragraph).await.unwrap();
+        }
+        {
+            let mut rendering_pipeline = process.rendering_pipeline.write().await;
+            rendering_pipeline.set_element_geometry("first", Rectangle { x: 0.0, y: 100.0, width: 400.0, height: 20.0 });
+            rendering_pipeline.set_element_geometry("second", Rectangle { x: 0.0, y: 120.0, width: 400.0, height: 20.0 });
+        }
+        
+        process.set_selection_range("first", 7, "second", 8).await.unwrap();
+        assert_eq!(process.dom_integration.read().await.get_selection().to_string(), "this textand this");
+        
+        let highlights = |process: &RendererProcess| {
+            let rendering_pipeline = process.rendering_pipeline.try_read().unwrap();
+            rendering_pipeline.display_list().commands().iter()
+                .filter_map(|command| match command {
+                    DisplayCommand::Highlight(rect, _) => Some((rect.x, rect.y, rect.width)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(highlights(&process), [(56.0, 100.0, 72.0), (0.0, 120.0, 64.0)]);
+        
+        process.modify_selection(SelectionAlter::Extend, SelectionDirection::Backward, SelectionGranularity::Word).await.unwrap();
+        assert_eq!(process.dom_integration.read().await.get_selection().to_string(), "this textand ");
+        
+        process.modify_selection(SelectionAlter::Move, SelectionDirection::Forward, SelectionGranularity::Character).await.unwrap();
+        assert!(highlights(&process).is_empty());
+        
+        assert!(process.set_selection_range("first", 99, "second", 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_escape_in_browser_leaves_fullscreen() {
+        let mut manager = RendererProcessManager::new(RendererConfig::default()).await.unwrap();
+        let process_id = manager.create_process(TabId::new(1), "https://example.com").await.unwrap();
+        let process = manager.get_process(process_id).await.unwrap();
+        let mut process = process.write().await;
+        process.initialize().await.unwrap();
+        process.load_url("https://example.com/video").await.unwrap();
+        
+        let (renderer_end, browser_end) = ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let mut video = dom::Element::new("video".to_string());
+        video.set_attribute("id".to_string(), "player".to_string());
+        {
+            let mut dom_integration = process.dom_integration.write().await;
+            dom_integration.connect_browser(TabId::new(1), renderer_end.clone());
+            dom_integration.insert_element(None, video).await.unwrap();
+        }
+        let player = Rectangle { x: 20.0, y: 300.0, width: 640.0, height: 360.0 };
+        process.rendering_pipeline.write().await.set_element_geometry("player", player);
+        
+        let changes = Arc::new(std::sync::Mutex::new(0));
+        let counted = changes.clone();
+        process.dom_integration.write().await.add_event_listener("document", "fullscreenchange", move |_| {
+            *counted.lock().unwrap() += 1;
+        }).await.unwrap();
+        
+        process.request_fullscreen("player").await.unwrap();
+        assert!(matches!(browser_end.recv_async().await.unwrap(), IpcMessage::BrowserRequest(_)));
+        
+        // The user presses Escape, so the browser leaves fullscreen and tells the renderer
+        browser_end.send_async(IpcMessage::BrowserEvent(BrowserEvent::FullscreenExited { tab_id: TabId::new(1) })).await.unwrap();
+        let IpcMessage::BrowserEvent(event) = renderer_end.recv_async().await.unwrap() else {
+            panic!("Expected a browser event");
+        };
+        process.handle_browser_event(event).await.unwrap();
+        
+        assert_eq!(process.dom_integration.read().await.fullscreen_element(), None);
+        assert_eq!(*changes.lock().unwrap(), 2);
+        let rendering_pipeline = process.rendering_pipeline.read().await;
+        assert_eq!(rendering_pipeline.fullscreen_element(), None);
+        assert_eq!(rendering_pipeline.layout_geometry().elements["player"], player);
+    }
+
+    #[tokio::test]
+    async fn test_render_for_print_uses_print_styles() {
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let process_id = manager.create_process(TabId::new(1), "https://example.com").await.unwrap();
+        let process = manager.get_process(process_id).await.unwrap();
+        let mut process = process.write().await;
+        process.initialize().await.unwrap();
+        process.load_url("https://example.com/page").await.unwrap();
+        process.style_engine.write().await
+            .add_style_sheet("@media print { h1 { page-break-after: always } }", Some("print.css")).await.unwrap();
+        
+        // The heading is printed on a page of its own
+        let pages = process.render_for_print(PaperSize::A4, PageMargins::default()).await.unwrap();
+        assert_eq!(pages.len(), 2);
+        assert!(pages.iter().all(|page| (page.width, page.height) == (794, 1123)));
+        assert_eq!(pages[1].pixels.len(), 794 * 1123 * 4);
+        
+        let style_engine = process.style_engine.read().await;
+        assert_eq!(style_engine.media_evaluator().media_type, dom::MediaType::Screen);
+        assert!(style_engine.applied_rules().iter().all(|rule| !matches!(rule.rule_type, style_engine::CssRuleType::Media)));
+        drop(style_engine);
+        
+        let response = process.handle_print_request(PrintRequest {
+            request_id: 7,
+            tab_id: TabId::new(1),
+            paper_size: PaperSize::LETTER,
+            margins: PageMargins::uniform(400.0),
+        }).await;
+        assert_eq!(response.request_id, 7);
+        assert!(response.pages.is_empty() && response.error.is_some());
+    }
+    
+    #[tokio::test]
+    async fn test_find_in_page_highlights_matches() {
+        use rendering_pipeline::DisplayCommand;
+        
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let process_id = manager.create_process(TabId::new(1), "https://example.com").await.unwrap();
+        let process = manager.get_process(process_id).await.unwrap();
+        let mut process = process.write().await;
+        process.initialize().await.unwrap();
+        process.load_url("https://example.com/page").await.unwrap();
+        
+        let mut intro = dom::Element::new("p".to_string());
+        intro.set_attribute("id".to_string(), "intro".to_string());
+        intro.append_child(dom::Node::Text(dom::TextNode::new("Say hello, HELLO!".to_string())));
+        process.dom_integration.write().await.insert_element(None, intro).await.unwrap();
+        process.rendering_pipeline.write().await
+            .set_element_geometry("intro", Rectangle { x: 0.0, y: 100.0, width: 400.0, height: 20.0 });
+        
+        // The heading matches too but has no layout to highlight
+        let result = process.find_text("hello", find_in_page::FindOptions::default()).await.unwrap();
+        assert_eq!(result, FindResult { total_matches: 3, current_index: 0 });
+        
+        let highlights = |process: &RendererProcess| {
+            let rendering_pipeline = process.rendering_pipeline.try_read().unwrap();
+            rendering_pipeline.display_list().commands().iter()
+                .filter_map(|command| match command {
+                    DisplayCommand::Highlight(rect, color) => Some((rect.x, color.green)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(highlights(&process), [(32.0, 255), (88.0, 255)]);
+        
+        // The current match is drawn in a different color
+        assert_eq!(process.find_next().await.unwrap().current_index, 1);
+        assert_eq!(highlights(&process), [(32.0, 150), (88.0, 255)]);
+        assert_eq!(process.find_previous().await.unwrap().current_index, 0);
+        assert_eq!(process.find_previous().await.unwrap().current_index, 2);
+        assert_eq!(highlights(&process), [(32.0, 255), (88.0, 150)]);
+        
+        process.stop_finding().await.unwrap();
+        assert!(highlights(&process).is_empty());
+        assert!(process.find_next().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_devtools_dom_tree_and_highlight() {
+        use rendering_pipeline::DisplayCommand;
+        
+        fn find_node<'a>(node: &'a serde_json::Value, id: &str) -> Option<&'a serde_json::Value> {
+            if node["id"] == id {
+                return Some(node);
+            }
+            node["children"].as_array()?.iter().find_map(|child| find_node(child, id))
+        }
+        
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let process_id = manager.create_process(TabId::new(1), "https://example.com").await.unwrap();
+        let process = manager.get_process(process_id).await.unwrap();
+        let mut process = process.write().await;
+        process.initialize().await.unwrap();
+        process.load_url("https://example.com/page").await.unwrap();
+        
+        let mut intro = dom::Element::new("p".to_string());
+        intro.set_attribute("id".to_string(), "intro".to_string());
+        process.dom_integration.write().await.insert_element(None, intro).await.unwrap();
+        process.rendering_pipeline.write().await
+            .set_element_geometry("intro", Rectangle { x: 8.0, y: 100.0, width: 400.0, height: 20.0 });
+        
+        let (inspector, renderer) = ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        
+        // The tree comes back as JSON with the layout boxes attached
+        inspector.send_async(IpcMessage::DevToolsRequest(DevToolsRequest::GetDomTree {
+            request_id: 7,
+            tab_id: TabId::new(1),
+        })).await.unwrap();
+        process.service_devtools_channel(&renderer).await.unwrap();
+        let tree = match inspector.recv_async().await.unwrap() {
+            IpcMessage::DevToolsResponse(DevToolsResponse::DomTree { request_id: 7, tree }) => {
+                serde_json::from_str::<serde_json::Value>(&tree).unwrap()
+            }
+            other => panic!("Unexpected reply: {:?}", other),
+        };
+        let node = find_node(&tree["documentElement"], "intro").unwrap();
+        assert_eq!(node["tagName"], "p");
+        assert_eq!(node["boundingBox"], serde_json::json!({ "x": 8.0, "y": 100.0, "width": 400.0, "height": 20.0 }));
+        
+        // Other tabs are refused
+        inspector.send_async(IpcMessage::DevToolsRequest(DevToolsRequest::GetDomTree {
+            request_id: 8,
+            tab_id: TabId::new(2),
+        })).await.unwrap();
+        process.service_devtools_channel(&renderer).await.unwrap();
+        assert!(matches!(
+            inspector.recv_async().await.unwrap(),
+            IpcMessage::DevToolsResponse(DevToolsResponse::Error { request_id: 8, .. })
+        ));
+        
+        let overlays = |process: &RendererProcess| {
+            let rendering_pipeline = process.rendering_pipeline.try_read().unwrap();
+            rendering_pipeline.display_list().commands().iter()
+                .filter_map(|command| match command {
+                    DisplayCommand::Highlight(rect, color) => Some((rect.y, color.alpha)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        
+        // Highlighting draws a translucent overlay over the element
+        for element_id in [Some("intro".to_string()), None] {
+            inspector.send_async(IpcMessage::DevToolsRequest(DevToolsRequest::HighlightElement {
+                tab_id: TabId::new(1),
+                element_id: element_id.clone(),
+            })).await.unwrap();
+            process.service_devtools_channel(&renderer).await.unwrap();
+            
+            let expected = if element_id.is_some() { vec![(100.0, 102)] } else { Vec::new() };
+            assert_eq!(overlays(&process), expected);
+        }
+        
+        // Screenshots are read while they are sent, as they outgrow the socket buffer
+        inspector.send_async(IpcMessage::DevToolsRequest(DevToolsRequest::CaptureScreenshot {
+            request_id: 9,
+            tab_id: TabId::new(1),
+        })).await.unwrap();
+        let (serviced, reply) = tokio::join!(process.service_devtools_channel(&renderer), inspector.recv_async());
+        serviced.unwrap();
+        match reply.unwrap() {
+            IpcMessage::DevToolsResponse(DevToolsResponse::Screenshot { request_id: 9, width, height, pixels }) => {
+                assert_eq!(pixels.len(), width as usize * height as usize * 4);
+            }
+            other => panic!("Unexpected reply: {:?}", other),
+        }
+        
+        // Expressions are evaluated by the JavaScript VM
+        inspector.send_async(IpcMessage::DebuggerRequest(DebuggerRequest::Evaluate {
+            request_id: 10,
+            expression: "1 + 1".to_string(),
+        })).await.unwrap();
+        process.service_devtools_channel(&renderer).await.unwrap();
+        assert!(matches!(
+            inspector.recv_async().await.unwrap(),
+            IpcMessage::DebuggerEvent(common::ipc::DebuggerEvent::Evaluated { request_id: 10, value: Some(_), error: None })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_enter_reader_mode() {
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let process_id = manager.create_process(TabId::new(1), "https://example.com").await.unwrap();
+        let process = manager.get_process(process_id).await.unwrap();
+        let mut process = process.write().await;
+        process.initialize().await.unwrap();
+        process.load_url("https://example.com/post").await.unwrap();
+        
+        // The placeholder page is too short to read
+        assert!(process.enter_reader_mode().await.is_err());
+        
+        let paragraph = "Readers skip the chrome, keep the prose, and enjoy the article in peace. ";
+        let page = format!(
+            "<html><head><title>Long Read</title></head><body><nav class=\"menu\"><a href=\"/\">Home</a></nav>\
+             <div class=\"post\"><p>{}</p><p>{}</p><p>{}</p><p>{}</p></div></body></html>",
+            paragraph, paragraph, paragraph, paragraph,
+        );
+        let document = dom::HtmlParser::parse(page.as_bytes()).unwrap();
+        process.dom_integration.write().await.restore_document(Some(document), Some("https://example.com/post".to_string()));
+        
+        process.enter_reader_mode().await.unwrap();
+        let tree = process.get_dom_tree().await.unwrap().to_string();
+        assert!(tree.contains("\"article\""));
+        assert!(tree.contains("Readers skip the chrome"));
+        assert!(!tree.contains("Home"));
+        assert!(process.execute_script("alert(1)").await.is_err());
+        
+        // Scripts run again on the next page
+        process.load_url("https://example.com/next").await.unwrap();
+        assert!(process.execute_script("1 + 1").await.is_ok());
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hidden_tabs_get_low_priority() {
+        let config = RendererConfig::default();
+        let mut manager = RendererProcessManager::new(config).await.unwrap();
+        
+        let hidden_id = manager.create_process(TabId::new(1), "https://example.com").await.unwrap();
+        let visible_id = manager.create_process(TabId::new(2), "https://example.org").await.unwrap();
+        
+        // Stand in for an out-of-process renderer
+        let mut child = std::process::Command::new("sleep").arg("10").spawn().unwrap();
+        manager.get_process(hidden_id).await.unwrap().write().await.os_process_id = Some(child.id());
+        
+        manager.set_tab_visibility(TabId::new(1), false).await;
+        let hidden = manager.get_process(hidden_id).await.unwrap();
+        let visible = manager.get_process(visible_id).await.unwrap();
+        assert_eq!(hidden.read().await.priority, ProcessPriority::Low);
+        assert_eq!(visible.read().await.priority, ProcessPriority::Normal);
+        
+        #[cfg(target_os = "linux")]
+        {
+            let stat = std::fs::read_to_string(format!("/proc/{}/stat", child.id())).unwrap();
+            let fields: Vec<&str> = stat.rsplit(')').next().unwrap().split_whitespace().collect();
+            assert_eq!(fields[16], "10");
+        }
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
 }
@@ -0,0 +1,394 @@
+//! Printing for renderer processes
+//!
+//! A document is printed by laying it out again at the width of the page
+//! content with the `@media print` rules applied, splitting the layout into
+//! pages, and rasterizing every page. Lines of text and embedded content are
+//! never split; pages break between them where a page runs out, at forced
+//! `break-before` and `break-after` breaks, and before a box that avoids
+//! breaks inside if it would fit on the next page. Until layout measures
+//! text, lines are measured with the metrics find in page uses.
+
+use std::collections::HashMap;
+
+use common::error::{Error, Result};
+use dom::{CssCascade, CssSelectorParser, Document, Element, LayoutBox, LayoutEngine, Node, SelectorMatcher};
+use tracing::{debug, warn};
+
+pub use common::ipc::{PageMargins, PaperSize, RenderedPage};
+
+use crate::dom_integration::element_text;
+use crate::find_in_page::{chars_per_line, is_searchable_element, LINE_HEIGHT};
+use crate::rendering_pipeline::{
+    Color, DisplayCommand, Font, FontStyle, FontWeight, PathSegment, Point, Rectangle, RenderingPipeline, TextCommand,
+};
+use crate::style_engine::{CssRule, CssValue};
+
+/// CSS pixels per point, at 96 CSS pixels and 72 points to the inch
+pub const CSS_PX_PER_PT: f32 = 96.0 / 72.0;
+
+/// Size of printed text, in CSS pixels
+const PRINT_FONT_SIZE: f32 = 16.0;
+
+/// Area of a page in CSS pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageLayout {
+    /// Paper the pages are printed on
+    pub paper_size: PaperSize,
+    /// Width of the page in pixels
+    pub width: u32,
+    /// Height of the page in pixels
+    pub height: u32,
+    /// Box the content is printed in, inside the margins
+    pub content: Rectangle,
+}
+
+impl PageLayout {
+    /// Lay out pages of a paper size with margins
+    pub fn new(paper_size: PaperSize, margins: PageMargins) -> Result<Self> {
+        let content = Rectangle {
+            x: margins.left * CSS_PX_PER_PT,
+            y: margins.top * CSS_PX_PER_PT,
+            width: (paper_size.width - margins.left - margins.right) * CSS_PX_PER_PT,
+            height: (paper_size.height - margins.top - margins.bottom) * CSS_PX_PER_PT,
+        };
+        if content.width < 1.0 || content.height < LINE_HEIGHT {
+            return Err(Error::ConfigError(format!(
+                "Margins {:?} leave no room for content on {}x{}pt paper",
+                margins, paper_size.width, paper_size.height
+            )));
+        }
+
+        Ok(Self {
+            paper_size,
+            width: (paper_size.width * CSS_PX_PER_PT).round() as u32,
+            height: (paper_size.height * CSS_PX_PER_PT).round() as u32,
+            content,
+        })
+    }
+}
+
+/// Declarations of the rules applied for print, matched against elements
+pub struct PrintStyles {
+    rules: Vec<(SelectorMatcher, HashMap<String, String>)>,
+}
+
+impl PrintStyles {
+    /// Collect the declarations of the rules applied by a style pass for the `print` media type
+    pub fn new(rules: &[CssRule]) -> Self {
+        let rules = rules.iter()
+            .filter(|rule| !rule.properties.is_empty())
+            .filter_map(|rule| {
+                let mut matcher = SelectorMatcher::new();
+                for selector in &rule.selectors {
+                    match CssSelectorParser::new(selector).and_then(|mut parser| parser.parse_selector_list()) {
+                        Ok(list) => list.selectors.into_iter().for_each(|selector| matcher.add_selector(selector)),
+                        Err(e) => warn!("Ignoring selector {} in print styles: {}", selector, e),
+                    }
+                }
+                let properties: HashMap<String, String> = rule.properties.iter()
+                    .filter_map(|(name, value)| Some((name.clone(), keyword(value)?.to_ascii_lowercase())))
+                    .collect();
+                (!properties.is_empty()).then_some((matcher, properties))
+            })
+            .collect();
+
+        Self { rules }
+    }
+
+    /// Get a property of a box from its `style` attribute or the last rule matching it
+    pub fn property(&self, layout_box: &LayoutBox, name: &str) -> Option<String> {
+        layout_box.style_property(name).map(|value| value.trim().to_ascii_lowercase()).or_else(|| {
+            self.rules.iter().rev()
+                .filter(|(_, properties)| properties.contains_key(name))
+                .find(|(matcher, _)| !matcher.match_element(&layout_box.element).is_empty())
+                .map(|(_, properties)| properties[name].clone())
+        })
+    }
+
+    fn is_hidden(&self, layout_box: &LayoutBox) -> bool {
+        self.property(layout_box, "display").as_deref() == Some("none")
+    }
+
+    fn breaks_before(&self, layout_box: &LayoutBox) -> bool {
+        self.forces_break(layout_box, "break-before", "page-break-before")
+    }
+
+    fn breaks_after(&self, layout_box: &LayoutBox) -> bool {
+        self.forces_break(layout_box, "break-after", "page-break-after")
+    }
+
+    fn forces_break(&self, layout_box: &LayoutBox, property: &str, legacy_property: &str) -> bool {
+        let forced = self.property(layout_box, property)
+            .is_some_and(|value| matches!(value.as_str(), "page" | "left" | "right" | "recto" | "verso" | "always"));
+        forced || self.property(layout_box, legacy_property)
+            .is_some_and(|value| matches!(value.as_str(), "always" | "left" | "right"))
+    }
+
+    fn avoids_break_inside(&self, layout_box: &LayoutBox) -> bool {
+        let avoid = self.property(layout_box, "break-inside")
+            .is_some_and(|value| matches!(value.as_str(), "avoid" | "avoid-page"));
+        avoid || self.property(layout_box, "page-break-inside").as_deref() == Some("avoid")
+    }
+}
+
+/// Content of the document in print order, with its break opportunities
+#[derive(Debug)]
+enum FlowItem {
+    /// Forced page break before the next content
+    Break,
+    /// Box that should start a new page rather than break inside, by its top and height
+    KeepTogether(f32, f32),
+    /// Content never split between pages, by its top and height and the
+    /// commands drawing it relative to the top of the content area
+    Content(f32, f32, Vec<DisplayCommand>),
+}
+
+/// Lay a document out for print and split it into pages of display commands
+///
+/// Each page's commands draw onto a surface the size of the page.
+pub fn paginate(document: &Document, styles: &PrintStyles, pipeline: &RenderingPipeline, page: &PageLayout) -> Vec<Vec<DisplayCommand>> {
+    let mut root = layout_tree(&document.root);
+    LayoutEngine::new(CssCascade::new()).calculate_layout(&mut root, page.content.width, page.content.height);
+
+    let mut flow = Vec::new();
+    let mut bottom = 0.0;
+    flow_box(&root, styles, pipeline, page.content.width, &mut bottom, &mut flow);
+
+    // Tops of the pages in the flow, and their content
+    let mut pages: Vec<(f32, Vec<DisplayCommand>)> = Vec::new();
+    let mut pending_break = false;
+    for item in flow {
+        match item {
+            FlowItem::Break => pending_break = true,
+            FlowItem::KeepTogether(top, height) => {
+                if let Some((page_top, _)) = pages.last() {
+                    if top + height > page_top + page.content.height && height <= page.content.height {
+                        pages.push((top, Vec::new()));
+                    }
+                }
+            }
+            FlowItem::Content(top, height, commands) => {
+                let starts_page = match pages.last() {
+                    Some((page_top, commands)) => {
+                        (pending_break && !commands.is_empty()) || top + height > page_top + page.content.height
+                    }
+                    None => true,
+                };
+                if starts_page {
+                    pages.push((top, Vec::new()));
+                }
+                pending_break = false;
+
+                let (page_top, page_commands) = pages.last_mut().unwrap();
+                let (dx, dy) = (page.content.x, page.content.y - *page_top);
+                page_commands.extend(commands.iter().map(|command| translate_command(command, dx, dy)));
+            }
+        }
+    }
+    debug!("Paginated {}px of content onto {} pages", bottom, pages.len().max(1));
+
+    // An empty document still prints a blank page
+    if pages.is_empty() {
+        pages.push((0.0, Vec::new()));
+    }
+    pages.into_iter()
+        .map(|(_, commands)| {
+            let mut page_commands = vec![DisplayCommand::Clear(Color { red: 255, green: 255, blue: 255, alpha: 255 })];
+            page_commands.extend(commands);
+            page_commands
+        })
+        .collect()
+}
+
+/// Rasterize paginated display commands
+pub async fn rasterize_pages(pages: Vec<Vec<DisplayCommand>>, page: &PageLayout) -> Result<Vec<RenderedPage>> {
+    let mut rendered = Vec::with_capacity(pages.len());
+    for commands in pages {
+        rendered.push(RenderedPage {
+            paper_size: page.paper_size,
+            width: page.width,
+            height: page.height,
+            pixels: RenderingPipeline::rasterize(&commands, page.width, page.height).await?,
+        });
+    }
+    Ok(rendered)
+}
+
+/// Build layout boxes for an element and its descendant elements
+fn layout_tree(element: &Element) -> LayoutBox {
+    let mut layout_box = LayoutBox::new(element.clone());
+    for child in &element.children {
+        if let Node::Element(child) = child {
+            layout_box.add_child(layout_tree(child));
+        }
+    }
+    layout_box
+}
+
+/// Stack a box's text, embedded content and children below `bottom`, recording them in the flow
+///
+/// The box's own text comes before its children.
+fn flow_box(layout_box: &LayoutBox, styles: &PrintStyles, pipeline: &RenderingPipeline, width: f32, bottom: &mut f32, flow: &mut Vec<FlowItem>) {
+    let element = &layout_box.element;
+    if !is_searchable_element(&element.tag_name) || styles.is_hidden(layout_box) {
+        return;
+    }
+
+    let top = *bottom;
+    if styles.breaks_before(layout_box) {
+        flow.push(FlowItem::Break);
+    }
+    let keep_together = styles.avoids_break_inside(layout_box).then(|| {
+        flow.push(FlowItem::KeepTogether(top, 0.0));
+        flow.len() - 1
+    });
+
+    let x = layout_box.position_coords.x;
+    let width = if layout_box.dimensions.content_width > 0.0 { layout_box.dimensions.content_width } else { width };
+    let text = element_text(element).split_whitespace().collect::<Vec<_>>().join(" ");
+    if !text.is_empty() {
+        let chars: Vec<char> = text.chars().collect();
+        for line in chars.chunks(chars_per_line(&Rectangle { width, ..Rectangle::default() })) {
+            let command = DisplayCommand::DrawText(TextCommand {
+                text: line.iter().collect(),
+                position: Point { x, y: *bottom },
+                font: Font { family: "serif".to_string(), size: PRINT_FONT_SIZE, weight: FontWeight::Normal, style: FontStyle::Normal },
+                color: Color { red: 0, green: 0, blue: 0, alpha: 255 },
+            });
+            flow.push(FlowItem::Content(*bottom, LINE_HEIGHT, vec![command]));
+            *bottom += LINE_HEIGHT;
+        }
+    }
+
+    // Vector content keeps its size on screen, moved to where the box is printed
+    let element_id = element.get_attribute("id").cloned().unwrap_or_else(|| element.id.clone());
+    let embedded = pipeline.embedded_content().iter().find(|(id, _)| *id == element_id);
+    if let (Some((_, commands)), Some(rect)) = (embedded, pipeline.layout_geometry().elements.get(&element_id)) {
+        let commands = commands.iter().map(|command| translate_command(command, x - rect.x, *bottom - rect.y)).collect();
+        flow.push(FlowItem::Content(*bottom, rect.height, commands));
+        *bottom += rect.height;
+    }
+
+    for child in &layout_box.children {
+        flow_box(child, styles, pipeline, width, bottom, flow);
+    }
+    *bottom = bottom.max(top + layout_box.dimensions.outer_height());
+
+    if let Some(index) = keep_together {
+        flow[index] = FlowItem::KeepTogether(top, *bottom - top);
+    }
+    if styles.breaks_after(layout_box) {
+        flow.push(FlowItem::Break);
+    }
+}
+
+/// Move a display command by an offset
+fn translate_command(command: &DisplayCommand, dx: f32, dy: f32) -> DisplayCommand {
+    let point = |point: &Point| Point { x: point.x + dx, y: point.y + dy };
+    let rect = |rect: &Rectangle| Rectangle { x: rect.x + dx, y: rect.y + dy, ..*rect };
+    match command {
+        DisplayCommand::DrawRectangle(r, color) => DisplayCommand::DrawRectangle(rect(r), color.clone()),
+        DisplayCommand::DrawCircle(center, radius, color) => DisplayCommand::DrawCircle(point(center), *radius, color.clone()),
+        DisplayCommand::DrawPath(segments, color) => DisplayCommand::DrawPath(
+            segments.iter().map(|segment| match segment {
+                PathSegment::MoveTo(to) => PathSegment::MoveTo(point(to)),
+                PathSegment::LineTo(to) => PathSegment::LineTo(point(to)),
+                PathSegment::CurveTo(c1, c2, to) => PathSegment::CurveTo(point(c1), point(c2), point(to)),
+                PathSegment::ClosePath => PathSegment::ClosePath,
+            }).collect(),
+            color.clone(),
+        ),
+        DisplayCommand::DrawText(text) => DisplayCommand::DrawText(TextCommand { position: point(&text.position), ..text.clone() }),
+        DisplayCommand::DrawImage(image) => {
+            let mut image = image.clone();
+            image.position = point(&image.position);
+            DisplayCommand::DrawImage(image)
+        }
+        DisplayCommand::Clip(r) => DisplayCommand::Clip(rect(r)),
+        DisplayCommand::Highlight(r, color) => DisplayCommand::Highlight(rect(r), color.clone()),
+        command => command.clone(),
+    }
+}
+
+/// Text of a keyword or string value
+fn keyword(value: &CssValue) -> Option<&str> {
+    match value {
+        CssValue::Keyword(text) | CssValue::String(text) => Some(text),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style_engine::StyleEngineManager;
+    use dom::HtmlParser;
+
+    async fn print_styles(css: &str) -> PrintStyles {
+        let mut style_engine = StyleEngineManager::new().await.unwrap();
+        style_engine.add_style_sheet(css, Some("print.css")).await.unwrap();
+        style_engine.media_evaluator_mut().media_type = dom::MediaType::Print;
+        style_engine.apply_styles().await.unwrap();
+        PrintStyles::new(style_engine.applied_rules())
+    }
+
+    fn text_tops(commands: &[DisplayCommand]) -> Vec<(String, f32)> {
+        commands.iter()
+            .filter_map(|command| match command {
+                DisplayCommand::DrawText(text) => Some((text.text.clone(), text.position.y.round())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_pages_break_at_print_rules() {
+        let document = HtmlParser::parse(
+            br#"<html><head><title>Report</title></head><body>
+                <nav>Menu</nav>
+                <h1>Title</h1>
+                <section class="chapter"><p>One</p></section>
+                <section class="chapter" id="long"><p>Two</p></section>
+            </body></html>"#,
+        ).unwrap();
+        let styles = print_styles(
+            "@media screen { h1 { display: none } } \
+             @media print { nav { display: none } .chapter { page-break-before: always } }",
+        ).await;
+        let pipeline = RenderingPipeline::new(&crate::RendererConfig::default()).await.unwrap();
+        let page = PageLayout::new(PaperSize::LETTER, PageMargins::default()).unwrap();
+        assert_eq!((page.width, page.height), (816, 1056));
+
+        let pages = paginate(&document, &styles, &pipeline, &page);
+        assert_eq!(pages.len(), 3);
+        assert_eq!(text_tops(&pages[0]), vec![("Title".to_string(), 48.0)]);
+        assert_eq!(text_tops(&pages[1]), vec![("One".to_string(), 48.0)]);
+        assert_eq!(text_tops(&pages[2]), vec![("Two".to_string(), 48.0)]);
+
+        let rendered = rasterize_pages(pages, &page).await.unwrap();
+        assert_eq!(rendered[0].pixels.len(), 816 * 1056 * 4);
+        assert_eq!(&rendered[0].pixels[..4], &[255, 255, 255, 255]);
+    }
+
+    #[tokio::test]
+    async fn test_long_content_flows_onto_next_page() {
+        let paragraph = "word ".repeat(400);
+        let html = format!(
+            r#"<html><body><p>{paragraph}</p><div class="figure"><p>{short}</p><p>{short}</p></div></body></html>"#,
+            short = "caption ".repeat(10),
+        );
+        let document = HtmlParser::parse(html.as_bytes()).unwrap();
+        let styles = print_styles("@media print { .figure { break-inside: avoid } }").await;
+        let pipeline = RenderingPipeline::new(&crate::RendererConfig::default()).await.unwrap();
+
+        // A 240x180px page holds 10 lines of 30 characters
+        let paper_size = PaperSize { width: 180.0, height: 135.0 };
+        let page = PageLayout::new(paper_size, PageMargins::uniform(0.0)).unwrap();
+        let pages = paginate(&document, &styles, &pipeline, &page);
+
+        // 1999 characters make 67 lines over 7 pages, then the 6 lines of the figure don't fit below them
+        let lines: Vec<usize> = pages.iter().map(|commands| text_tops(commands).len()).collect();
+        assert_eq!(lines, vec![10, 10, 10, 10, 10, 10, 7, 6]);
+        assert!(text_tops(&pages[7])[0].0.starts_with("caption"));
+    }
+}
@@ -0,0 +1,423 @@
+//! Reader mode for renderer processes
+//!
+//! The main content of an article is found with heuristics in the spirit of
+//! Readability: paragraphs score their parent and grandparent by length and
+//! comma count, class names like "article" or "sidebar" adjust the scores,
+//! and link-heavy containers are penalized. The winning element is cleaned
+//! of navigation chrome and rebuilt into a plain `<article>` document.
+
+use dom::{Document, DomSerializer, Element, HtmlParser, Node, TextNode};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Stylesheet of the reader mode document
+pub const READER_STYLESHEET: &str = r#"
+body {
+    margin: 0 auto;
+    max-width: 680px;
+    padding: 32px 16px;
+    font-family: Georgia, serif;
+    font-size: 20px;
+    line-height: 1.6;
+    color: #222222;
+    background-color: #fbfaf7;
+}
+h1 { font-size: 34px; line-height: 1.25; }
+.byline { color: #666666; font-size: 16px; }
+img { max-width: 100%; }
+a { color: #1a5fb4; }
+"#;
+
+/// Least number of characters in the main content of an article
+const MIN_ARTICLE_CHARS: usize = 250;
+
+/// Least number of characters for a paragraph to count towards its container
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+/// Reading speed used for the reading time estimate
+const WORDS_PER_MINUTE: usize = 200;
+
+/// Tags never part of the main content
+const UNLIKELY_TAGS: &[&str] = &[
+    "nav", "aside", "footer", "script", "style", "noscript", "form", "iframe", "button", "svg", "template",
+];
+
+/// Class and ID words of main content containers
+const POSITIVE_WORDS: &[&str] = &[
+    "article", "blog", "body", "content", "entry", "hentry", "main", "post", "story", "text",
+];
+
+/// Class and ID words of page chrome
+const NEGATIVE_WORDS: &[&str] = &[
+    "ad", "ads", "advert", "banner", "breadcrumb", "comment", "comments", "cookie", "footer", "header",
+    "masthead", "menu", "nav", "navigation", "popup", "promo", "related", "share", "sidebar", "social",
+    "sponsor", "widget",
+];
+
+/// Attributes kept on elements of the extracted content
+const KEPT_ATTRIBUTES: &[&str] = &["href", "src", "alt", "title"];
+
+/// Main content of a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReaderArticle {
+    /// Article title
+    pub title: String,
+
+    /// Author, if the page names one
+    pub author: Option<String>,
+
+    /// Publication date as written in the page
+    pub publish_date: Option<String>,
+
+    /// Cleaned HTML of the content
+    pub content_html: String,
+
+    /// Time to read the content
+    pub estimated_reading_time: Duration,
+}
+
+impl ReaderArticle {
+    /// Build the reader mode document showing the article
+    pub fn to_document(&self) -> Document {
+        let mut document = Document::new();
+        document.set_title(self.title.clone());
+
+        let mut head = Element::new("head".to_string());
+        head.append_child(Node::Element(text_element("title", &self.title)));
+
+        let mut article = Element::new("article".to_string());
+        article.append_child(Node::Element(text_element("h1", &self.title)));
+
+        let byline: Vec<&str> = [self.author.as_deref(), self.publish_date.as_deref()].into_iter().flatten().collect();
+        if !byline.is_empty() {
+            let mut paragraph = text_element("p", &byline.join(" · "));
+            paragraph.set_attribute("class".to_string(), "byline".to_string());
+            article.append_child(Node::Element(paragraph));
+        }
+
+        // The content was serialized from parsed elements, so it parses again
+        for node in HtmlParser::parse_fragment(&self.content_html).unwrap_or_default() {
+            article.append_child(node);
+        }
+
+        let mut body = Element::new("body".to_string());
+        body.append_child(Node::Element(article));
+
+        document.root.append_child(Node::Element(head));
+        document.root.append_child(Node::Element(body));
+        document
+    }
+}
+
+/// Finds the main content of a page
+pub struct ReaderModeExtractor;
+
+impl ReaderModeExtractor {
+    /// Extract the article of a page, or `None` if it has no long-form content
+    pub fn extract(dom: &Document) -> Option<ReaderArticle> {
+        let content = Self::find_main_content(&dom.root)?;
+        let cleaned = clean_element(content);
+        let text = inner_text(&cleaned);
+        if text.chars().count() < MIN_ARTICLE_CHARS {
+            return None;
+        }
+
+        let words = text.split_whitespace().count();
+        let minutes = words.div_ceil(WORDS_PER_MINUTE).max(1);
+
+        Some(ReaderArticle {
+            title: Self::title(dom, content),
+            author: Self::author(&dom.root),
+            publish_date: Self::publish_date(&dom.root),
+            content_html: DomSerializer::serialize_element(&cleaned, false),
+            estimated_reading_time: Duration::from_secs(minutes as u64 * 60),
+        })
+    }
+
+    /// Element with the highest content score
+    fn find_main_content(root: &Element) -> Option<&Element> {
+        let mut candidates = HashMap::new();
+        score_paragraphs(root, &mut Vec::new(), &mut candidates);
+
+        candidates
+            .into_values()
+            .map(|(element, score)| (element, score * (1.0 - link_density(element))))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(element, _)| element)
+    }
+
+    fn title(dom: &Document, content: &Element) -> String {
+        let title = find_meta(&dom.root, "og:title")
+            .or_else(|| find_element(&dom.root, &|e| e.tag_name == "title").map(inner_text))
+            .map(|title| match title.split_once(" | ") {
+                Some((title, _site)) => title.trim().to_string(),
+                None => title,
+            })
+            .filter(|title| !title.is_empty());
+
+        title
+            .or_else(|| dom.title.clone())
+            .or_else(|| find_element(content, &|e| e.tag_name == "h1").map(inner_text))
+            .unwrap_or_default()
+    }
+
+    fn author(root: &Element) -> Option<String> {
+        find_meta(root, "author").or_else(|| {
+            let byline = find_element(root, &|e| {
+                e.get_attribute("rel").map(String::as_str) == Some("author")
+                    || class_words(e).iter().any(|word| word == "author" || word == "byline")
+            })?;
+            let text = inner_text(byline);
+            let text = text.strip_prefix("By ").or_else(|| text.strip_prefix("by ")).unwrap_or(&text);
+            Some(text.trim().to_string()).filter(|author| !author.is_empty())
+        })
+    }
+
+    fn publish_date(root: &Element) -> Option<String> {
+        find_meta(root, "article:published_time")
+            .or_else(|| find_meta(root, "date"))
+            .or_else(|| {
+                let time = find_element(root, &|e| e.tag_name == "time")?;
+                time.get_attribute("datetime").cloned().or_else(|| Some(inner_text(time)))
+            })
+            .filter(|date| !date.is_empty())
+    }
+}
+
+/// Credit every paragraph's score to its parent and, halved, its grandparent
+fn score_paragraphs<'a>(
+    element: &'a Element,
+    ancestors: &mut Vec<&'a Element>,
+    candidates: &mut HashMap<*const Element, (&'a Element, f64)>,
+) {
+    if is_unlikely(element) {
+        return;
+    }
+
+    if matches!(element.tag_name.as_str(), "p" | "pre" | "blockquote" | "td") {
+        let text = inner_text(element);
+        let length = text.chars().count();
+        if length >= MIN_PARAGRAPH_CHARS {
+            let score = 1.0 + text.matches(',').count() as f64 + (length as f64 / 100.0).min(3.0);
+            let mut credit = |candidate: &'a Element, share: f64| {
+                candidates
+                    .entry(std::ptr::from_ref(candidate))
+                    .or_insert_with(|| (candidate, initial_score(candidate)))
+                    .1 += score * share;
+            };
+            if let Some(parent) = ancestors.last() {
+                credit(parent, 1.0);
+            }
+            if let Some(grandparent) = ancestors.len().checked_sub(2).map(|index| ancestors[index]) {
+                credit(grandparent, 0.5);
+            }
+        }
+    }
+
+    ancestors.push(element);
+    for child in &element.children {
+        if let Node::Element(child) = child {
+            score_paragraphs(child, ancestors, candidates);
+        }
+    }
+    ancestors.pop();
+}
+
+/// Score of a container before its paragraphs are counted
+fn initial_score(element: &Element) -> f64 {
+    let tag_score = match element.tag_name.as_str() {
+        "article" => 10.0,
+        "main" | "div" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    tag_score + class_weight(element)
+}
+
+/// Bonus or penalty for the class and ID of an element
+fn class_weight(element: &Element) -> f64 {
+    let words = class_words(element);
+    let mut weight = 0.0;
+    if words.iter().any(|word| POSITIVE_WORDS.contains(&word.as_str())) {
+        weight += 25.0;
+    }
+    if words.iter().any(|word| NEGATIVE_WORDS.contains(&word.as_str())) {
+        weight -= 25.0;
+    }
+    weight
+}
+
+/// Lowercase words of the class and ID attributes
+fn class_words(element: &Element) -> Vec<String> {
+    ["class", "id"]
+        .iter()
+        .filter_map(|name| element.get_attribute(name))
+        .flat_map(|value| value.split(|c: char| !c.is_ascii_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect()
+}
+
+/// Whether an element is page chrome rather than content
+fn is_unlikely(element: &Element) -> bool {
+    UNLIKELY_TAGS.contains(&element.tag_name.as_str()) || class_weight(element) < 0.0
+}
+
+/// Share of an element's text inside links
+fn link_density(element: &Element) -> f64 {
+    fn link_chars(element: &Element) -> usize {
+        if element.tag_name == "a" {
+            return inner_text(element).chars().count();
+        }
+        element.children.iter()
+            .map(|child| match child {
+                Node::Element(child) if !is_unlikely(child) => link_chars(child),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    let total = inner_text(element).chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+    link_chars(element) as f64 / total as f64
+}
+
+/// Text of an element without page chrome, with whitespace collapsed
+fn inner_text(element: &Element) -> String {
+    fn collect(element: &Element, text: &mut String) {
+        for child in &element.children {
+            match child {
+                Node::Text(node) => {
+                    text.push_str(node.text_content());
+                    text.push(' ');
+                }
+                Node::Element(child) if !is_unlikely(child) => collect(child, text),
+                _ => {}
+            }
+        }
+    }
+
+    let mut text = String::new();
+    collect(element, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Copy of an element without chrome, comments and presentational attributes
+fn clean_element(element: &Element) -> Element {
+    let mut cleaned = Element::new(element.tag_name.clone());
+    for name in KEPT_ATTRIBUTES {
+        if let Some(value) = element.get_attribute(name) {
+            cleaned.set_attribute(name.to_string(), value.clone());
+        }
+    }
+
+    for child in &element.children {
+        match child {
+            Node::Element(child) if !is_unlikely(child) => cleaned.append_child(Node::Element(clean_element(child))),
+            Node::Text(text) => cleaned.append_child(Node::Text(text.clone())),
+            _ => {}
+        }
+    }
+    cleaned
+}
+
+/// First element in tree order matching a predicate
+fn find_element<'a>(element: &'a Element, predicate: &dyn Fn(&Element) -> bool) -> Option<&'a Element> {
+    element.children.iter().find_map(|child| match child {
+        Node::Element(child) if predicate(child) => Some(child),
+        Node::Element(child) => find_element(child, predicate),
+        _ => None,
+    })
+}
+
+/// Content of a `<meta>` element by `name` or `property`
+fn find_meta(root: &Element, key: &str) -> Option<String> {
+    let meta = find_element(root, &|e| {
+        e.tag_name == "meta"
+            && (e.get_attribute("name").map(String::as_str) == Some(key)
+                || e.get_attribute("property").map(String::as_str) == Some(key))
+    })?;
+    meta.get_attribute("content").map(|content| content.trim().to_string())
+}
+
+fn text_element(tag_name: &str, text: &str) -> Element {
+    let mut element = Element::new(tag_name.to_string());
+    element.append_child(Node::Text(TextNode::new(text.to_string())));
+    element
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE_PAGE: &str = r#"<html>
+<head>
+    <title>Why Rust Ownership Matters | The Systems Journal</title>
+    <meta name="author" content="Ferris Crab">
+    <meta property="article:published_time" content="2024-03-14">
+</head>
+<body>
+    <header class="site-header"><a href="/">The Systems Journal</a></header>
+    <div class="layout">
+        <div class="sidebar">
+            <ul>
+                <li><a href="/news">News, opinion, analysis and reviews from all over</a></li>
+                <li><a href="/about">About the journal, its editors, contributors and history</a></li>
+            </ul>
+            <p>Subscribe to our newsletter for weekly updates, offers, deals, and more.</p>
+        </div>
+        <div class="article-content">
+            <h2>Ownership in practice</h2>
+            <p>Ownership is the feature that sets Rust apart, and it shapes how every program is written, tested, and maintained.</p>
+            <p>Each value has a single owner, and when the owner goes out of scope, the value is dropped, freeing its resources deterministically.</p>
+            <p>Borrowing lets code use a value without taking ownership, while the compiler checks that references never outlive the data they point to.</p>
+            <p>Together, these rules remove whole classes of bugs, such as use after free, double free, and data races, at compile time.</p>
+            <aside class="share">Share this on social media, tell your friends, and follow us</aside>
+        </div>
+    </div>
+    <footer>Copyright, all rights reserved, terms and privacy policy apply</footer>
+</body>
+</html>"#;
+
+    #[test]
+    fn test_extract_excludes_sidebar() {
+        let document = HtmlParser::parse(ARTICLE_PAGE.as_bytes()).unwrap();
+        let article = ReaderModeExtractor::extract(&document).unwrap();
+
+        assert_eq!(article.title, "Why Rust Ownership Matters");
+        assert_eq!(article.author.as_deref(), Some("Ferris Crab"));
+        assert_eq!(article.publish_date.as_deref(), Some("2024-03-14"));
+        assert_eq!(article.estimated_reading_time, Duration::from_secs(60));
+
+        assert!(article.content_html.contains("<h2>Ownership in practice</h2>"));
+        assert!(article.content_html.contains("Borrowing lets code use a value"));
+        assert!(!article.content_html.contains("newsletter"));
+        assert!(!article.content_html.contains("/about"));
+        assert!(!article.content_html.contains("Share this"));
+        assert!(!article.content_html.contains("Copyright"));
+        assert!(!article.content_html.contains("class="));
+    }
+
+    #[test]
+    fn test_reader_document() {
+        let document = HtmlParser::parse(ARTICLE_PAGE.as_bytes()).unwrap();
+        let reader_document = ReaderModeExtractor::extract(&document).unwrap().to_document();
+
+        let body = find_element(&reader_document.root, &|e| e.tag_name == "body").unwrap();
+        let article = find_element(body, &|e| e.tag_name == "article").unwrap();
+        assert_eq!(inner_text(find_element(article, &|e| e.tag_name == "h1").unwrap()), "Why Rust Ownership Matters");
+        assert_eq!(inner_text(find_element(article, &|e| e.tag_name == "p").unwrap()), "Ferris Crab · 2024-03-14");
+        assert!(inner_text(article).contains("data races, at compile time."));
+    }
+
+    #[test]
+    fn test_short_page_has_no_article() {
+        let document =
+            HtmlParser::parse(b"<html><body><nav><a href=\"/\">Home</a></nav><p>Page not found.</p></body></html>").unwrap();
+        assert!(ReaderModeExtractor::extract(&document).is_none());
+    }
+}
@@ -4,6 +4,15 @@ use common::error::Result;
 use serde_json::Value;
 use tracing::{debug, error, info, warn};
 
+use crate::dom_integration::resize_observer::{DomRectReadOnly, ResizeObserverManager};
+use crate::scroll_animator::SmoothScroll;
+
+/// Width of screenshots in pixels
+pub const SCREENSHOT_WIDTH: u32 = 1024;
+
+/// Height of screenshots in pixels
+pub const SCREENSHOT_HEIGHT: u32 = 768;
+
 /// Rendering pipeline
 pub struct RenderingPipeline {
     /// Pipeline configuration
@@ -23,6 +32,55 @@ pub struct RenderingPipeline {
     
     /// Rendering statistics
     stats: RenderingStats,
+    
+    /// Layout geometry produced by the last layout pass
+    layout_geometry: LayoutGeometry,
+    
+    /// Publisher for layout geometry updates
+    layout_updates: tokio::sync::watch::Sender<LayoutGeometry>,
+    
+    /// CSS animations, transitions and animation frame callbacks
+    animation_engine: dom::animation::AnimationEngine,
+    
+    /// Time of the last animation tick
+    last_animation_tick: Option<std::time::Instant>,
+    
+    /// Resize observers, checked after every layout pass
+    resize_observers: ResizeObserverManager,
+    
+    /// Boxes over the selected text
+    selection_highlights: Vec<Rectangle>,
+    
+    /// Highlight boxes drawn over the page
+    highlights: Vec<(Rectangle, Color)>,
+    
+    /// DevTools overlay over the inspected element
+    inspector_highlight: Option<Rectangle>,
+    
+    /// Element shown fullscreen, with the box layout gave it
+    fullscreen_element: Option<(String, Option<Rectangle>)>,
+    
+    /// Commands drawing the vector content of elements, such as SVG images, by element ID
+    embedded_content: Vec<(String, Vec<DisplayCommand>)>,
+    
+    /// Smooth scroll of the viewport, advanced every frame
+    smooth_scroll: SmoothScroll,
+}
+
+/// Element geometry produced by a layout pass
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayoutGeometry {
+    /// Layout pass counter
+    pub generation: u64,
+    
+    /// Viewport rectangle
+    pub viewport: Rectangle,
+    
+    /// Border boxes of laid out elements, keyed by element ID
+    pub elements: std::collections::HashMap<String, Rectangle>,
+    
+    /// Box dimensions of laid out elements, keyed by element ID
+    pub dimensions: std::collections::HashMap<String, dom::Dimensions>,
 }
 
 /// Rendering configuration
@@ -120,6 +178,12 @@ pub enum DisplayCommand {
     /// Draw rectangle command
     DrawRectangle(Rectangle, Color),
     
+    /// Draw a filled circle, by its center and radius
+    DrawCircle(Point, f32, Color),
+    
+    /// Fill a path with the non-zero rule
+    DrawPath(Vec<PathSegment>, Color),
+    
     /// Draw text command
     DrawText(TextCommand),
     
@@ -134,6 +198,20 @@ pub enum DisplayCommand {
     
     /// Blend command
     Blend(BlendMode),
+    
+    /// Translucent box drawn over the page, such as selected text, a
+    /// find-in-page match or the element inspected in DevTools
+    Highlight(Rectangle, Color),
+}
+
+/// Segment of a path outline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+    MoveTo(Point),
+    LineTo(Point),
+    /// Cubic curve, by its two control points and end point
+    CurveTo(Point, Point, Point),
+    ClosePath,
 }
 
 /// Text command
@@ -354,7 +432,7 @@ pub struct RenderingStats {
 }
 
 /// Basic geometric types
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Point {
     pub x: f32,
     pub y: f32,
@@ -366,7 +444,7 @@ pub struct Size {
     pub height: f32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Rectangle {
     pub x: f32,
     pub y: f32,
@@ -432,6 +510,14 @@ impl RenderingPipeline {
             },
         };
         
+        let layout_geometry = LayoutGeometry {
+            generation: 0,
+            viewport: Rectangle { x: 0.0, y: 0.0, width: 1024.0, height: 768.0 },
+            elements: std::collections::HashMap::new(),
+            dimensions: std::collections::HashMap::new(),
+        };
+        let (layout_updates, _) = tokio::sync::watch::channel(layout_geometry.clone());
+        
         Ok(Self {
             config: rendering_config,
             display_list: DisplayList::new(),
@@ -439,6 +525,17 @@ impl RenderingPipeline {
             compositor: Compositor::new(),
             frame_buffer: None,
             stats: RenderingStats::default(),
+            layout_geometry,
+            layout_updates,
+            animation_engine: dom::animation::AnimationEngine::new(),
+            last_animation_tick: None,
+            resize_observers: ResizeObserverManager::new(),
+            selection_highlights: Vec::new(),
+            highlights: Vec::new(),
+            inspector_highlight: None,
+            fullscreen_element: None,
+            embedded_content: Vec::new(),
+            smooth_scroll: SmoothScroll::new(),
         })
     }
     
@@ -463,9 +560,19 @@ impl RenderingPipeline {
     pub async fn render_page(&mut self) -> Result<()> {
         info!("Rendering page");
         
+        // Advance animations and run animation frame callbacks before painting
+        self.tick_animations();
+        self.tick_smooth_scroll();
+        
         // Build display list
         self.build_display_list().await?;
         
+        // Publish the geometry of this layout pass
+        self.publish_layout_geometry();
+        
+        // Queue resize observations for elements whose size changed
+        self.resize_observers.process_layout(&self.layout_geometry);
+        
         // Render display list
         self.render_display_list().await?;
         
@@ -482,15 +589,15 @@ impl RenderingPipeline {
         Ok(())
     }
     
-    /// Take a screenshot of the current page
+    /// Take a screenshot of the current page as RGBA pixels
     pub async fn take_screenshot(&self) -> Result<Vec<u8>> {
         debug!("Taking screenshot");
         
         // TODO: Implement actual screenshot capture
         // For now, return a placeholder image
         
-        let width = 1024;
-        let height = 768;
+        let width = SCREENSHOT_WIDTH;
+        let height = SCREENSHOT_HEIGHT;
         let mut image_data = Vec::new();
         
         // Generate a simple gradient image
@@ -531,6 +638,191 @@ impl RenderingPipeline {
         &self.stats
     }
     
+    /// Subscribe to layout geometry updates
+    pub fn subscribe_layout_updates(&self) -> tokio::sync::watch::Receiver<LayoutGeometry> {
+        self.layout_updates.subscribe()
+    }
+    
+    /// Get the geometry of the last layout pass
+    pub fn layout_geometry(&self) -> &LayoutGeometry {
+        &self.layout_geometry
+    }
+    
+    /// Record the border box of an element for the next layout pass
+    pub fn set_element_geometry(&mut self, element_id: &str, rect: Rectangle) {
+        self.layout_geometry.elements.insert(element_id.to_string(), rect);
+    }
+    
+    /// Remove an element from the layout geometry
+    pub fn remove_element_geometry(&mut self, element_id: &str) {
+        self.layout_geometry.elements.remove(element_id);
+        self.layout_geometry.dimensions.remove(element_id);
+    }
+    
+    /// Record the boxes of a tree laid out by the `LayoutEngine` for the next layout pass
+    pub fn apply_layout(&mut self, root: &dom::LayoutBox) {
+        let element = &root.element;
+        let element_id = element.get_attribute("id").cloned().unwrap_or_else(|| element.id.clone());
+        let dimensions = &root.dimensions;
+        
+        // Boxes are positioned by their margin edge
+        let rect = Rectangle {
+            x: root.position_coords.x + dimensions.margin_left,
+            y: root.position_coords.y + dimensions.margin_top,
+            width: dimensions.total_width(),
+            height: dimensions.total_height(),
+        };
+        let rect = match &mut self.fullscreen_element {
+            Some((fullscreen_id, laid_out)) if *fullscreen_id == element_id => {
+                *laid_out = Some(rect);
+                self.layout_geometry.viewport
+            }
+            _ => rect,
+        };
+        self.layout_geometry.elements.insert(element_id.clone(), rect);
+        self.layout_geometry.dimensions.insert(element_id, dimensions.clone());
+        
+        for child in &root.children {
+            self.apply_layout(child);
+        }
+    }
+    
+    /// Border box of an element relative to the viewport, as `Element.getBoundingClientRect()`
+    pub fn get_bounding_client_rect(&self, element_id: &str) -> Option<DomRectReadOnly> {
+        let viewport = &self.layout_geometry.viewport;
+        self.layout_geometry.elements.get(element_id)
+            .map(|rect| DomRectReadOnly::new(rect.x - viewport.x, rect.y - viewport.y, rect.width, rect.height))
+    }
+    
+    /// Get the resize observers
+    pub fn resize_observers(&self) -> &ResizeObserverManager {
+        &self.resize_observers
+    }
+    
+    /// Get the resize observers mutably
+    pub fn resize_observers_mut(&mut self) -> &mut ResizeObserverManager {
+        &mut self.resize_observers
+    }
+    
+    /// Replace the boxes drawn over the selected text from the next frame
+    pub fn set_selection_highlights(&mut self, highlights: Vec<Rectangle>) {
+        self.selection_highlights = highlights;
+    }
+    
+    /// Replace the highlight boxes drawn over the page from the next frame
+    pub fn set_highlights(&mut self, highlights: Vec<(Rectangle, Color)>) {
+        self.highlights = highlights;
+    }
+    
+    /// Draw the vector content of an element, such as a decoded SVG image,
+    /// from the next frame, or remove it when there are no commands
+    pub fn set_embedded_content(&mut self, element_id: &str, commands: Vec<DisplayCommand>) {
+        let existing = self.embedded_content.iter().position(|(id, _)| id == element_id);
+        match (existing, commands.is_empty()) {
+            (Some(index), true) => {
+                self.embedded_content.remove(index);
+            }
+            (Some(index), false) => self.embedded_content[index].1 = commands,
+            (None, true) => {}
+            (None, false) => self.embedded_content.push((element_id.to_string(), commands)),
+        }
+    }
+    
+    /// Get the vector content drawn for elements, by element ID
+    pub fn embedded_content(&self) -> &[(String, Vec<DisplayCommand>)] {
+        &self.embedded_content
+    }
+    
+    /// Draw the DevTools overlay over a box from the next frame, or remove it
+    pub fn set_inspector_highlight(&mut self, rect: Option<Rectangle>) {
+        self.inspector_highlight = rect;
+    }
+    
+    /// Get the display list of the last frame
+    pub fn display_list(&self) -> &DisplayList {
+        &self.display_list
+    }
+    
+    /// Scroll or resize the viewport
+    pub fn set_viewport(&mut self, viewport: Rectangle) {
+        self.layout_geometry.viewport = viewport;
+        if let Some((element_id, _)) = &self.fullscreen_element {
+            self.layout_geometry.elements.insert(element_id.clone(), viewport);
+        }
+    }
+    
+    /// Lay an element out over the whole viewport, or give the fullscreen
+    /// element back its box, and publish the new geometry
+    pub fn set_fullscreen_element(&mut self, element_id: Option<&str>) {
+        if let Some((previous_id, laid_out)) = self.fullscreen_element.take() {
+            match laid_out {
+                Some(rect) => self.layout_geometry.elements.insert(previous_id, rect),
+                None => self.layout_geometry.elements.remove(&previous_id),
+            };
+        }
+        
+        if let Some(element_id) = element_id {
+            let viewport = self.layout_geometry.viewport;
+            let laid_out = self.layout_geometry.elements.insert(element_id.to_string(), viewport);
+            self.fullscreen_element = Some((element_id.to_string(), laid_out));
+        }
+        self.publish_layout_geometry();
+    }
+    
+    /// Get the element laid out over the whole viewport
+    pub fn fullscreen_element(&self) -> Option<&str> {
+        self.fullscreen_element.as_ref().map(|(element_id, _)| element_id.as_str())
+    }
+    
+    /// Finish a layout pass and notify subscribers
+    pub fn publish_layout_geometry(&mut self) {
+        self.layout_geometry.generation += 1;
+        self.layout_updates.send_replace(self.layout_geometry.clone());
+    }
+    
+    /// Advance the viewport along the smooth scroll the DOM integration started
+    pub fn set_smooth_scroll(&mut self, smooth_scroll: SmoothScroll) {
+        self.smooth_scroll = smooth_scroll;
+    }
+    
+    /// Move the viewport to the smooth scroll position of this frame
+    fn tick_smooth_scroll(&mut self) {
+        if let Some(position) = self.smooth_scroll.tick(std::time::Instant::now()) {
+            let viewport = Rectangle { x: position.x, y: position.y, ..self.layout_geometry.viewport };
+            self.set_viewport(viewport);
+        }
+    }
+    
+    /// Get the animation engine
+    pub fn animation_engine(&self) -> &dom::animation::AnimationEngine {
+        &self.animation_engine
+    }
+    
+    /// Get the animation engine mutably
+    pub fn animation_engine_mut(&mut self) -> &mut dom::animation::AnimationEngine {
+        &mut self.animation_engine
+    }
+    
+    /// Schedule a callback for the next rendered frame
+    pub fn request_animation_frame(&mut self, callback: dom::animation::AnimationFrameCallback) -> u64 {
+        self.animation_engine.request_animation_frame(callback)
+    }
+    
+    /// Cancel a scheduled animation frame callback
+    pub fn cancel_animation_frame(&mut self, callback_id: u64) {
+        self.animation_engine.cancel_animation_frame(callback_id);
+    }
+    
+    /// Advance the animation engine by the time since the last frame
+    fn tick_animations(&mut self) {
+        let now = std::time::Instant::now();
+        let delta = self.last_animation_tick.map(|last| now - last).unwrap_or_default();
+        self.last_animation_tick = Some(now);
+        
+        debug!("Ticking animations by {:?}", delta);
+        self.animation_engine.tick(delta);
+    }
+    
     /// Initialize rendering surface
     async fn initialize_rendering_surface(&mut self) -> Result<()> {
         debug!("Initializing rendering surface");
@@ -602,6 +894,21 @@ impl RenderingPipeline {
         
         self.display_list.commands.push(clear_command);
         
+        for (_, commands) in &self.embedded_content {
+            self.display_list.commands.extend(commands.iter().cloned());
+        }
+        
+        // Overlays are drawn on top of the page content
+        for rect in &self.selection_highlights {
+            self.display_list.commands.push(DisplayCommand::Highlight(*rect, selection_highlight_color()));
+        }
+        for (rect, color) in &self.highlights {
+            self.display_list.commands.push(DisplayCommand::Highlight(*rect, color.clone()));
+        }
+        if let Some(rect) = self.inspector_highlight {
+            self.display_list.commands.push(DisplayCommand::Highlight(rect, inspector_highlight_color()));
+        }
+        
         Ok(())
     }
     
@@ -619,6 +926,22 @@ impl RenderingPipeline {
         Ok(())
     }
     
+    /// Rasterize display commands onto a transparent surface of their own, as RGBA pixels
+    pub async fn rasterize(commands: &[DisplayCommand], width: u32, height: u32) -> Result<Vec<u8>> {
+        let mut surface = RenderingSurface {
+            surface_id: "rasterize".to_string(),
+            width,
+            height,
+            pixel_format: PixelFormat::RGBA8,
+            buffer: vec![0; width as usize * height as usize * 4],
+        };
+        for command in commands {
+            Self::execute_display_command(command, &mut surface).await?;
+        }
+        
+        Ok(surface.buffer)
+    }
+    
     /// Execute a display command
     async fn execute_display_command(command: &DisplayCommand, surface: &mut RenderingSurface) -> Result<()> {
         match command {
@@ -628,12 +951,21 @@ impl RenderingPipeline {
             DisplayCommand::DrawRectangle(rect, color) => {
                 Self::draw_rectangle(surface, rect, color).await?;
             }
+            DisplayCommand::DrawCircle(center, radius, color) => {
+                Self::draw_circle(surface, *center, *radius, color);
+            }
+            DisplayCommand::DrawPath(segments, color) => {
+                Self::draw_path(surface, segments, color);
+            }
             DisplayCommand::DrawText(text_cmd) => {
                 Self::draw_text(surface, text_cmd).await?;
             }
             DisplayCommand::DrawImage(image_cmd) => {
                 Self::draw_image(surface, image_cmd).await?;
             }
+            DisplayCommand::Highlight(rect, color) => {
+                Self::draw_highlight(surface, rect, color).await?;
+            }
             _ => {
                 // TODO: Implement other display commands
                 debug!("Display command not yet implemented: {:?}", command);
@@ -677,6 +1009,76 @@ impl RenderingPipeline {
         Ok(())
     }
     
+    /// Blend a translucent box over the surface
+    async fn draw_highlight(surface: &mut RenderingSurface, rect: &Rectangle, color: &Color) -> Result<()> {
+        let start_x = rect.x.max(0.0) as u32;
+        let start_y = rect.y.max(0.0) as u32;
+        let end_x = ((rect.x + rect.width).max(0.0) as u32).min(surface.width);
+        let end_y = ((rect.y + rect.height).max(0.0) as u32).min(surface.height);
+        let alpha = color.alpha as u32;
+        let blend = |under: u8, over: u8| ((over as u32 * alpha + under as u32 * (255 - alpha)) / 255) as u8;
+        
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                let index = ((y * surface.width + x) * 4) as usize;
+                surface.buffer[index] = blend(surface.buffer[index], color.red);
+                surface.buffer[index + 1] = blend(surface.buffer[index + 1], color.green);
+                surface.buffer[index + 2] = blend(surface.buffer[index + 2], color.blue);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Fill the pixels whose centers are inside a circle
+    fn draw_circle(surface: &mut RenderingSurface, center: Point, radius: f32, color: &Color) {
+        let start_y = (center.y - radius).max(0.0) as u32;
+        let end_y = ((center.y + radius).max(0.0).ceil() as u32).min(surface.height);
+        
+        for y in start_y..end_y {
+            let dy = y as f32 + 0.5 - center.y;
+            if dy.abs() > radius {
+                continue;
+            }
+            let dx = (radius * radius - dy * dy).sqrt();
+            fill_span(surface, y, center.x - dx, center.x + dx, color);
+        }
+    }
+    
+    /// Fill the pixels whose centers are inside a path, by the non-zero rule
+    fn draw_path(surface: &mut RenderingSurface, segments: &[PathSegment], color: &Color) {
+        let edges = flatten_path(segments);
+        let Some(top) = edges.iter().map(|(a, b)| a.y.min(b.y)).reduce(f32::min) else {
+            return;
+        };
+        let bottom = edges.iter().map(|(a, b)| a.y.max(b.y)).fold(top, f32::max);
+        let start_y = top.max(0.0) as u32;
+        let end_y = (bottom.max(0.0).ceil() as u32).min(surface.height);
+        
+        let mut crossings = Vec::new();
+        for y in start_y..end_y {
+            let sample_y = y as f32 + 0.5;
+            crossings.clear();
+            for (from, to) in &edges {
+                let (upper, lower, winding) = if from.y < to.y { (from, to, 1) } else { (to, from, -1) };
+                if sample_y < upper.y || sample_y >= lower.y {
+                    continue;
+                }
+                let x = upper.x + (sample_y - upper.y) / (lower.y - upper.y) * (lower.x - upper.x);
+                crossings.push((x, winding));
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+            
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                if winding != 0 {
+                    fill_span(surface, y, pair[0].0, pair[1].0, color);
+                }
+            }
+        }
+    }
+    
     /// Draw text
     async fn draw_text(_surface: &mut RenderingSurface, text_cmd: &TextCommand) -> Result<()> {
         // TODO: Implement text rendering
@@ -763,6 +1165,11 @@ impl DisplayList {
         self.commands.push(command);
     }
     
+    /// Get the display commands
+    pub fn commands(&self) -> &[DisplayCommand] {
+        &self.commands
+    }
+    
     /// Clear all commands
     pub fn clear(&mut self) {
         self.commands.clear();
@@ -800,6 +1207,79 @@ impl Layer {
     }
 }
 
+/// Segments a curve is flattened into before filling
+const CURVE_SEGMENTS: usize = 16;
+
+/// Blend a color over the pixels of a row whose centers are between two x positions
+fn fill_span(surface: &mut RenderingSurface, y: u32, start_x: f32, end_x: f32, color: &Color) {
+    let first = (start_x - 0.5).ceil().max(0.0) as u32;
+    let last = ((end_x - 0.5).ceil().max(0.0) as u32).min(surface.width);
+    let alpha = color.alpha as u32;
+    let blend = |under: u8, over: u8| ((over as u32 * alpha + under as u32 * (255 - alpha)) / 255) as u8;
+    
+    for x in first..last {
+        let index = ((y * surface.width + x) * 4) as usize;
+        surface.buffer[index] = blend(surface.buffer[index], color.red);
+        surface.buffer[index + 1] = blend(surface.buffer[index + 1], color.green);
+        surface.buffer[index + 2] = blend(surface.buffer[index + 2], color.blue);
+        surface.buffer[index + 3] = surface.buffer[index + 3].max(color.alpha);
+    }
+}
+
+/// Get the edges of a path, with curves flattened into lines and every subpath closed
+fn flatten_path(segments: &[PathSegment]) -> Vec<(Point, Point)> {
+    let mut edges = Vec::new();
+    let mut start = Point { x: 0.0, y: 0.0 };
+    let mut current = start;
+    
+    for segment in segments {
+        match *segment {
+            PathSegment::MoveTo(point) => {
+                edges.push((current, start));
+                start = point;
+                current = point;
+            }
+            PathSegment::LineTo(point) => {
+                edges.push((current, point));
+                current = point;
+            }
+            PathSegment::CurveTo(control1, control2, end) => {
+                let mut previous = current;
+                for step in 1..=CURVE_SEGMENTS {
+                    let t = step as f32 / CURVE_SEGMENTS as f32;
+                    let u = 1.0 - t;
+                    let point = Point {
+                        x: u * u * u * current.x + 3.0 * u * u * t * control1.x + 3.0 * u * t * t * control2.x + t * t * t * end.x,
+                        y: u * u * u * current.y + 3.0 * u * u * t * control1.y + 3.0 * u * t * t * control2.y + t * t * t * end.y,
+                    };
+                    edges.push((previous, point));
+                    previous = point;
+                }
+                current = end;
+            }
+            PathSegment::ClosePath => {
+                edges.push((current, start));
+                current = start;
+            }
+        }
+    }
+    edges.push((current, start));
+    
+    // Horizontal edges never cross a row of samples
+    edges.retain(|(from, to)| from.y != to.y);
+    edges
+}
+
+/// Fill of selected text, a translucent blue
+fn selection_highlight_color() -> Color {
+    Color { red: 51, green: 144, blue: 255, alpha: 96 }
+}
+
+/// Fill of the DevTools overlay, a translucent blue
+fn inspector_highlight_color() -> Color {
+    Color { red: 111, green: 168, blue: 220, alpha: 102 }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -829,6 +1309,130 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_render_page_runs_animation_frame_callbacks() {
+        let config = crate::RendererConfig::default();
+        let mut pipeline = RenderingPipeline::new(&config).await.unwrap();
+        pipeline.initialize().await.unwrap();
+        
+        let frames = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = frames.clone();
+        pipeline.request_animation_frame(Box::new(move |_| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }));
+        
+        pipeline.render_page().await.unwrap();
+        pipeline.render_page().await.unwrap();
+        
+        assert_eq!(frames.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(!pipeline.animation_engine().needs_frame());
+    }
+
+    #[tokio::test]
+    async fn test_resize_observer_fires_when_flex_container_grows() {
+        use dom::{CssCascade, Display, Element, LayoutBox, LayoutEngine};
+        
+        let config = crate::RendererConfig::default();
+        let mut pipeline = RenderingPipeline::new(&config).await.unwrap();
+        pipeline.initialize().await.unwrap();
+        
+        let mut container = Element::new("div".to_string());
+        container.set_attribute("id".to_string(), "toolbar".to_string());
+        let mut flex_box = LayoutBox::new(container);
+        flex_box.display = Display::Flex;
+        flex_box.add_child(LayoutBox::new(Element::new("span".to_string())));
+        let mut engine = LayoutEngine::new(CssCascade::new());
+        
+        let delivered = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = delivered.clone();
+        let observer = pipeline.resize_observers_mut().create_observer(move |entries| sink.lock().unwrap().extend(entries));
+        pipeline.resize_observers_mut().observe(observer, "toolbar").unwrap();
+        
+        engine.calculate_layout(&mut flex_box, 400.0, 300.0);
+        pipeline.apply_layout(&flex_box);
+        pipeline.render_page().await.unwrap();
+        assert_eq!(pipeline.resize_observers_mut().deliver_pending_entries().len(), 1);
+        
+        // Relayout at the same size reports nothing
+        engine.calculate_layout(&mut flex_box, 400.0, 300.0);
+        pipeline.apply_layout(&flex_box);
+        pipeline.render_page().await.unwrap();
+        assert!(!pipeline.resize_observers().has_pending_delivery());
+        
+        // The container grows with its containing block
+        engine.calculate_layout(&mut flex_box, 640.0, 300.0);
+        pipeline.apply_layout(&flex_box);
+        pipeline.render_page().await.unwrap();
+        assert!(pipeline.resize_observers().has_pending_delivery());
+        pipeline.resize_observers_mut().deliver_pending_entries();
+        
+        let delivered = delivered.lock().unwrap();
+        assert_eq!(delivered.len(), 2);
+        assert_eq!(delivered[1].target, "toolbar");
+        assert_eq!(delivered[1].content_rect.width, 640.0);
+        assert_eq!(pipeline.get_bounding_client_rect("toolbar").unwrap().width, 640.0);
+    }
+
+    #[tokio::test]
+    async fn test_fullscreen_element_fills_viewport() {
+        let config = crate::RendererConfig::default();
+        let mut pipeline = RenderingPipeline::new(&config).await.unwrap();
+        pipeline.initialize().await.unwrap();
+        
+        let player = Rectangle { x: 20.0, y: 300.0, width: 640.0, height: 360.0 };
+        pipeline.set_element_geometry("player", player);
+        pipeline.set_viewport(Rectangle { x: 0.0, y: 250.0, width: 1024.0, height: 768.0 });
+        
+        pipeline.set_fullscreen_element(Some("player"));
+        assert_eq!(pipeline.fullscreen_element(), Some("player"));
+        let rect = pipeline.get_bounding_client_rect("player").unwrap();
+        assert_eq!((rect.x, rect.y, rect.width, rect.height), (0.0, 0.0, 1024.0, 768.0));
+        
+        // Fullscreen elements follow the viewport
+        pipeline.set_viewport(Rectangle { x: 0.0, y: 250.0, width: 1920.0, height: 1080.0 });
+        assert_eq!(pipeline.get_bounding_client_rect("player").unwrap().width, 1920.0);
+        
+        pipeline.set_fullscreen_element(None);
+        assert_eq!(pipeline.fullscreen_element(), None);
+        assert_eq!(pipeline.layout_geometry().elements["player"], player);
+    }
+
+    #[tokio::test]
+    async fn test_embedded_content_is_drawn() {
+        let config = crate::RendererConfig::default();
+        let mut pipeline = RenderingPipeline::new(&config).await.unwrap();
+        pipeline.initialize().await.unwrap();
+        
+        let red = Color { red: 255, green: 0, blue: 0, alpha: 255 };
+        let blue = Color { red: 0, green: 0, blue: 255, alpha: 255 };
+        let triangle = vec![
+            PathSegment::MoveTo(Point { x: 100.0, y: 100.0 }),
+            PathSegment::LineTo(Point { x: 140.0, y: 100.0 }),
+            PathSegment::LineTo(Point { x: 100.0, y: 140.0 }),
+            PathSegment::ClosePath,
+        ];
+        pipeline.set_embedded_content("logo", vec![
+            DisplayCommand::DrawCircle(Point { x: 50.0, y: 50.0 }, 10.0, red.clone()),
+            DisplayCommand::DrawPath(triangle, blue.clone()),
+        ]);
+        pipeline.render_page().await.unwrap();
+        
+        assert!(pipeline.display_list().commands().iter().any(|command| matches!(command, DisplayCommand::DrawCircle(..))));
+        let surface = pipeline.rendering_surface.as_ref().unwrap();
+        let pixel = |x: u32, y: u32| {
+            let index = ((y * surface.width + x) * 4) as usize;
+            (surface.buffer[index], surface.buffer[index + 1], surface.buffer[index + 2])
+        };
+        assert_eq!(pixel(50, 50), (255, 0, 0));
+        assert_ne!(pixel(50, 62), (255, 0, 0));
+        assert_eq!(pixel(105, 105), (0, 0, 255));
+        assert_ne!(pixel(135, 135), (0, 0, 255));
+        
+        pipeline.set_embedded_content("logo", Vec::new());
+        pipeline.render_page().await.unwrap();
+        assert!(!pipeline.display_list().commands().iter().any(|command| matches!(command, DisplayCommand::DrawCircle(..))));
+    }
+
     #[tokio::test]
     async fn test_screenshot() {
         let config = crate::RendererConfig::default();
@@ -0,0 +1,236 @@
+//! Smooth scrolling
+//!
+//! Smooth scrolls move the viewport with a damped spring pulling it towards
+//! the target position. The defaults are close to critically damped, so the
+//! viewport does not overshoot and settles in about half a second for
+//! scrolls of up to a few screens. Scrolling again while an animation runs
+//! moves its target and keeps its velocity, so wheel ticks in a row add up
+//! to one continuous motion.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::rendering_pipeline::Point;
+
+/// Default stiffness of the spring, in 1/s²
+pub const DEFAULT_SPRING_STIFFNESS: f32 = 500.0;
+
+/// Default damping of the spring, in 1/s
+pub const DEFAULT_SPRING_DAMPING: f32 = 45.0;
+
+/// Step the spring is integrated with, short enough to stay stable at any frame rate
+const INTEGRATION_STEP: Duration = Duration::from_millis(1);
+
+/// Distance to the target, in pixels, within which an animation can settle
+const SETTLE_DISTANCE: f32 = 0.5;
+
+/// Speed, in pixels per second, below which an animation can settle
+const SETTLE_SPEED: f32 = 20.0;
+
+/// Spring animation of a scroll position towards a target
+#[derive(Debug, Clone)]
+pub struct ScrollAnimator {
+    /// Scroll position of the last frame
+    pub current_position: Point,
+
+    /// Scroll position the animation ends at
+    pub target_position: Point,
+
+    /// Velocity in pixels per second
+    pub velocity: Point,
+
+    /// Time the animation started
+    pub animation_start: Instant,
+
+    /// Stiffness of the spring, in 1/s²
+    pub spring_stiffness: f32,
+
+    /// Damping of the spring, in 1/s
+    pub spring_damping: f32,
+
+    /// Time the position was last advanced to
+    last_tick: Instant,
+}
+
+impl ScrollAnimator {
+    /// Start animating from a position at rest towards a target
+    pub fn new(current_position: Point, target_position: Point, now: Instant) -> Self {
+        Self {
+            current_position,
+            target_position,
+            velocity: Point { x: 0.0, y: 0.0 },
+            animation_start: now,
+            spring_stiffness: DEFAULT_SPRING_STIFFNESS,
+            spring_damping: DEFAULT_SPRING_DAMPING,
+            last_tick: now,
+        }
+    }
+
+    /// Use a spring of another stiffness and damping
+    pub fn with_spring(mut self, spring_stiffness: f32, spring_damping: f32) -> Self {
+        self.spring_stiffness = spring_stiffness;
+        self.spring_damping = spring_damping;
+        self
+    }
+
+    /// Move the target, keeping the current position and velocity
+    pub fn retarget(&mut self, target_position: Point) {
+        self.target_position = target_position;
+    }
+
+    /// Advance the animation to a frame time and get the scroll position of the frame
+    ///
+    /// The position snaps to the target once the animation has settled.
+    pub fn tick(&mut self, now: Instant) -> Point {
+        let mut elapsed = now.saturating_duration_since(self.last_tick);
+        self.last_tick = self.last_tick.max(now);
+
+        while !elapsed.is_zero() && !self.is_finished() {
+            let step = elapsed.min(INTEGRATION_STEP);
+            elapsed -= step;
+
+            // Semi-implicit Euler: update the velocity first, then move with it
+            let dt = step.as_secs_f32();
+            let acceleration_x = self.spring_stiffness * (self.target_position.x - self.current_position.x) - self.spring_damping * self.velocity.x;
+            let acceleration_y = self.spring_stiffness * (self.target_position.y - self.current_position.y) - self.spring_damping * self.velocity.y;
+            self.velocity.x += acceleration_x * dt;
+            self.velocity.y += acceleration_y * dt;
+            self.current_position.x += self.velocity.x * dt;
+            self.current_position.y += self.velocity.y * dt;
+
+            if self.is_settled() {
+                self.current_position = self.target_position;
+                self.velocity = Point { x: 0.0, y: 0.0 };
+            }
+        }
+
+        self.current_position
+    }
+
+    /// Whether the position reached the target and stopped
+    pub fn is_finished(&self) -> bool {
+        self.current_position == self.target_position && self.velocity == Point { x: 0.0, y: 0.0 }
+    }
+
+    /// Get the time since the animation started
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.animation_start)
+    }
+
+    fn is_settled(&self) -> bool {
+        let distance = (self.target_position.x - self.current_position.x).hypot(self.target_position.y - self.current_position.y);
+        let speed = self.velocity.x.hypot(self.velocity.y);
+        distance <= SETTLE_DISTANCE && speed <= SETTLE_SPEED
+    }
+}
+
+/// Smooth scroll of the viewport, shared by the DOM integration starting it
+/// and the rendering pipeline advancing it each frame
+///
+/// Clones share the animation.
+#[derive(Debug, Clone, Default)]
+pub struct SmoothScroll {
+    animator: Arc<Mutex<Option<ScrollAnimator>>>,
+}
+
+impl SmoothScroll {
+    /// Create a smooth scroll without an animation
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Animate the viewport from its scroll position towards a target,
+    /// or move the target of the running animation
+    pub fn scroll_to(&self, current_position: Point, target_position: Point, now: Instant) {
+        let mut animator = self.animator.lock().unwrap();
+        match animator.as_mut() {
+            Some(animator) => animator.retarget(target_position),
+            None => *animator = Some(ScrollAnimator::new(current_position, target_position, now)),
+        }
+    }
+
+    /// Get the target of the running animation
+    pub fn target(&self) -> Option<Point> {
+        self.animator.lock().unwrap().as_ref().map(|animator| animator.target_position)
+    }
+
+    /// Whether an animation is running
+    pub fn is_animating(&self) -> bool {
+        self.animator.lock().unwrap().is_some()
+    }
+
+    /// Stop the running animation where it is
+    pub fn cancel(&self) {
+        self.animator.lock().unwrap().take();
+    }
+
+    /// Advance the running animation to a frame time and get the scroll position of the frame
+    ///
+    /// The animation ends on the frame it reaches its target.
+    pub fn tick(&self, now: Instant) -> Option<Point> {
+        let mut animator = self.animator.lock().unwrap();
+        let position = animator.as_mut()?.tick(now);
+        if animator.as_ref().is_some_and(ScrollAnimator::is_finished) {
+            *animator = None;
+        }
+        Some(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+    #[test]
+    fn test_scroll_converges_within_500ms() {
+        let start = Instant::now();
+        let target = Point { x: 200.0, y: 1000.0 };
+        let mut animator = ScrollAnimator::new(Point { x: 0.0, y: 0.0 }, target, start);
+
+        let mut previous_y = 0.0;
+        let mut finished_at = None;
+        for frame in 1..=60 {
+            let now = start + FRAME * frame;
+            let position = animator.tick(now);
+
+            // Damped enough to move towards the target without overshooting
+            assert!(position.y >= previous_y && position.y <= target.y);
+            previous_y = position.y;
+            if finished_at.is_none() && animator.is_finished() {
+                finished_at = Some(animator.elapsed(now));
+            }
+        }
+
+        assert_eq!(animator.current_position, target);
+        assert!(finished_at.unwrap() <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_scroll_to_during_animation_keeps_velocity() {
+        let start = Instant::now();
+        let smooth_scroll = SmoothScroll::new();
+        assert_eq!(smooth_scroll.tick(start), None);
+
+        smooth_scroll.scroll_to(Point { x: 0.0, y: 0.0 }, Point { x: 0.0, y: 100.0 }, start);
+        let moving = smooth_scroll.tick(start + FRAME * 5).unwrap();
+        assert!(moving.y > 0.0 && moving.y < 100.0);
+
+        // A second wheel tick moves the target instead of restarting from rest
+        smooth_scroll.scroll_to(Point { x: 0.0, y: 0.0 }, Point { x: 0.0, y: 200.0 }, start + FRAME * 5);
+        let next = smooth_scroll.tick(start + FRAME * 6).unwrap();
+        assert!(next.y > moving.y);
+
+        let mut frame = 6;
+        while smooth_scroll.is_animating() {
+            frame += 1;
+            smooth_scroll.tick(start + FRAME * frame);
+        }
+        assert!(frame <= 6 + 30);
+
+        let mut slow = ScrollAnimator::new(Point { x: 0.0, y: 0.0 }, Point { x: 0.0, y: 100.0 }, start).with_spring(50.0, 15.0);
+        let mut fast = ScrollAnimator::new(Point { x: 0.0, y: 0.0 }, Point { x: 0.0, y: 100.0 }, start);
+        assert!(slow.tick(start + FRAME * 3).y < fast.tick(start + FRAME * 3).y);
+    }
+}
@@ -0,0 +1,447 @@
+//! Service workers
+//!
+//! Each registration runs its worker script in a dedicated JavaScript VM.
+//! Once the worker is activated it answers fetches in its scope through the
+//! `fetch` event, before the request reaches the network. Workers share a
+//! Cache API database, each seeing the caches of its own origin.
+
+use crate::js_vm::JavaScriptVmManager;
+use crate::RendererConfig;
+use async_trait::async_trait;
+use common::error::{Error, Result};
+use network::{FetchInterceptor, NetworkRequest, NetworkResponse};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use storage::CacheApiManager;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+use url::Url;
+
+/// Service worker manager
+pub struct ServiceWorkerManager {
+    /// Configuration for worker VMs
+    config: RendererConfig,
+
+    /// Registrations by scope URL
+    registrations: RwLock<HashMap<String, ServiceWorkerRegistration>>,
+
+    /// Cache API database of every origin
+    caches: CacheApiManager,
+}
+
+/// Registered service worker
+///
+/// Clones share the same worker.
+#[derive(Clone)]
+pub struct ServiceWorkerRegistration {
+    inner: Arc<RegistrationInner>,
+}
+
+struct RegistrationInner {
+    /// URLs starting with the scope are controlled by the worker
+    scope: Url,
+
+    /// Worker script
+    script_url: Url,
+
+    /// Lifecycle state
+    state: Mutex<ServiceWorkerState>,
+
+    /// VM running the worker script
+    vm: RwLock<JavaScriptVmManager>,
+
+    /// Caches of the worker's origin, as its `caches` global
+    caches: CacheApiManager,
+}
+
+/// Service worker lifecycle state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceWorkerState {
+    /// Script evaluated
+    Parsed,
+
+    /// `install` event dispatched
+    Installing,
+
+    /// Installed, waiting to activate
+    Installed,
+
+    /// `activate` event dispatched
+    Activating,
+
+    /// Handling fetches
+    Activated,
+
+    /// Failed to install, replaced or unregistered
+    Redundant,
+}
+
+/// Response a `fetch` listener returns, as passed to `respondWith`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchEventResponse {
+    #[serde(default = "default_status")]
+    status: u16,
+
+    #[serde(default)]
+    headers: HashMap<String, String>,
+
+    #[serde(default)]
+    body: String,
+
+    content_type: Option<String>,
+}
+
+impl ServiceWorkerManager {
+    /// Create a service worker manager whose caches are deleted when it is dropped
+    pub fn new(config: &RendererConfig) -> Result<Self> {
+        let caches = CacheApiManager::in_memory()
+            .map_err(|e| Error::IoError(format!("Failed to open cache storage: {}", e)))?;
+        Ok(Self::with_caches(config, caches))
+    }
+
+    /// Create a service worker manager keeping caches in a Cache API database
+    pub fn with_caches(config: &RendererConfig, caches: CacheApiManager) -> Self {
+        Self {
+            config: config.clone(),
+            registrations: RwLock::new(HashMap::new()),
+            caches,
+        }
+    }
+
+    /// Get the caches of an origin
+    pub fn caches(&self, origin: &str) -> CacheApiManager {
+        self.caches.for_origin(origin)
+    }
+
+    /// Register a service worker, installing and activating it
+    ///
+    /// The scope is resolved against the script URL and may not be outside
+    /// the script's directory. Registering the same script for a scope again
+    /// returns the existing registration.
+    pub async fn register(&self, script_url: &str, scope: &str) -> Result<ServiceWorkerRegistration> {
+        let script_url = Url::parse(script_url)
+            .map_err(|e| Error::ParseError(format!("Invalid service worker script URL {}: {}", script_url, e)))?;
+        let scope = script_url.join(scope)
+            .map_err(|e| Error::ParseError(format!("Invalid service worker scope {}: {}", scope, e)))?;
+
+        if !is_potentially_trustworthy(&script_url) {
+            return Err(Error::SecurityError(format!("Service worker {} is not from a secure origin", script_url)));
+        }
+        if scope.origin() != script_url.origin() {
+            return Err(Error::SecurityError(format!("Scope {} is not same-origin with {}", scope, script_url)));
+        }
+        let max_scope = script_url.join("./")
+            .map_err(|e| Error::ParseError(format!("Invalid service worker script URL {}: {}", script_url, e)))?;
+        if !scope.as_str().starts_with(max_scope.as_str()) {
+            return Err(Error::SecurityError(format!("Scope {} is outside the maximum scope {}", scope, max_scope)));
+        }
+
+        if let Some(registration) = self.registrations.read().await.get(scope.as_str()) {
+            if registration.inner.script_url == script_url {
+                return Ok(registration.clone());
+            }
+        }
+
+        info!("Registering service worker {} for scope {}", script_url, scope);
+
+        let mut vm = JavaScriptVmManager::new(&self.config).await?;
+        vm.initialize().await?;
+        vm.execute_script(&format!("importScripts({});", Value::from(script_url.as_str()))).await?;
+
+        let registration = ServiceWorkerRegistration {
+            inner: Arc::new(RegistrationInner {
+                scope: scope.clone(),
+                script_url,
+                state: Mutex::new(ServiceWorkerState::Parsed),
+                vm: RwLock::new(vm),
+                caches: self.caches(&scope.origin().ascii_serialization()),
+            }),
+        };
+        registration.install().await?;
+        registration.activate().await?;
+
+        let replaced = self.registrations.write().await.insert(scope.to_string(), registration.clone());
+        if let Some(replaced) = replaced {
+            replaced.set_state(ServiceWorkerState::Redundant);
+        }
+
+        Ok(registration)
+    }
+
+    /// Unregister the service worker of a scope
+    pub async fn unregister(&self, scope: &str) -> bool {
+        match self.registrations.write().await.remove(scope) {
+            Some(registration) => {
+                registration.set_state(ServiceWorkerState::Redundant);
+                info!("Unregistered service worker for scope {}", scope);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the active registration controlling a URL, the one with the longest scope
+    pub async fn registration_for(&self, url: &str) -> Option<ServiceWorkerRegistration> {
+        self.registrations.read().await
+            .values()
+            .filter(|registration| url.starts_with(registration.inner.scope.as_str()))
+            .filter(|registration| registration.state() == ServiceWorkerState::Activated)
+            .max_by_key(|registration| registration.inner.scope.as_str().len())
+            .cloned()
+    }
+
+    /// Let the controlling service worker answer a request
+    ///
+    /// Returns `None` when no active worker controls the URL or no `fetch`
+    /// listener responded, so the request goes to the network.
+    pub async fn intercept_fetch(&self, request: &NetworkRequest) -> Option<NetworkResponse> {
+        let registration = self.registration_for(&request.url).await?;
+        registration.dispatch_fetch(request).await
+    }
+}
+
+#[async_trait]
+impl FetchInterceptor for ServiceWorkerManager {
+    async fn intercept_fetch(&self, request: &NetworkRequest) -> Option<NetworkResponse> {
+        ServiceWorkerManager::intercept_fetch(self, request).await
+    }
+}
+
+impl ServiceWorkerRegistration {
+    /// Get the scope URL
+    pub fn scope(&self) -> &Url {
+        &self.inner.scope
+    }
+
+    /// Get the worker script URL
+    pub fn script_url(&self) -> &Url {
+        &self.inner.script_url
+    }
+
+    /// Get the caches of the worker's origin, for listeners calling `caches.open()`
+    pub fn caches(&self) -> &CacheApiManager {
+        &self.inner.caches
+    }
+
+    /// Get the lifecycle state
+    pub fn state(&self) -> ServiceWorkerState {
+        *self.inner.state.lock().unwrap()
+    }
+
+    /// Add an event listener to the worker's global scope
+    pub async fn add_event_listener<F>(&self, event_type: &str, callback: F) -> Result<()>
+    where
+        F: Fn(Value) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.inner.vm.write().await.add_event_listener(event_type, None, callback).await
+    }
+
+    /// Dispatch the `install` event
+    ///
+    /// A failing listener makes the worker redundant.
+    async fn install(&self) -> Result<()> {
+        self.set_state(ServiceWorkerState::Installing);
+        if let Err(e) = self.dispatch_lifecycle_event("install").await {
+            self.set_state(ServiceWorkerState::Redundant);
+            return Err(e);
+        }
+
+        self.set_state(ServiceWorkerState::Installed);
+        Ok(())
+    }
+
+    /// Dispatch the `activate` event
+    async fn activate(&self) -> Result<()> {
+        self.set_state(ServiceWorkerState::Activating);
+        if let Err(e) = self.dispatch_lifecycle_event("activate").await {
+            self.set_state(ServiceWorkerState::Redundant);
+            return Err(e);
+        }
+
+        self.set_state(ServiceWorkerState::Activated);
+        Ok(())
+    }
+
+    async fn dispatch_lifecycle_event(&self, event_type: &str) -> Result<()> {
+        debug!("Dispatching {} to service worker {}", event_type, self.inner.script_url);
+
+        let vm = self.inner.vm.read().await;
+        for result in vm.dispatch_event(event_type, serde_json::json!({ "type": event_type })).await {
+            result.map_err(|e| {
+                Error::JsError(format!("Service worker {} failed to {}: {}", self.inner.script_url, event_type, e))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Dispatch the `fetch` event, returning the first response a listener gave
+    async fn dispatch_fetch(&self, request: &NetworkRequest) -> Option<NetworkResponse> {
+        let started = Instant::now();
+        let event = serde_json::json!({
+            "type": "fetch",
+            "request": {
+                "url": request.url,
+                "method": request.method,
+                "headers": request.headers,
+            },
+        });
+
+        let vm = self.inner.vm.read().await;
+        for result in vm.dispatch_event("fetch", event).await {
+            match result {
+                // The listener did not call respondWith
+                Ok(Value::Null) => continue,
+                Ok(value) => match serde_json::from_value::<FetchEventResponse>(value) {
+                    Ok(response) => {
+                        debug!("Service worker {} answered {}", self.inner.script_url, request.url);
+                        return Some(response.into_network_response(started.elapsed()));
+                    }
+                    Err(e) => warn!("Service worker {} gave an invalid response: {}", self.inner.script_url, e),
+                },
+                Err(e) => warn!("Error in fetch listener of {}: {}", self.inner.script_url, e),
+            }
+        }
+
+        None
+    }
+
+    fn set_state(&self, state: ServiceWorkerState) {
+        *self.inner.state.lock().unwrap() = state;
+    }
+}
+
+impl FetchEventResponse {
+    fn into_network_response(self, response_time: Duration) -> NetworkResponse {
+        let content_type = self.content_type
+            .or_else(|| {
+                self.headers.iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                    .map(|(_, value)| value.clone())
+            })
+            .unwrap_or_else(|| "text/plain;charset=UTF-8".to_string());
+        let body = self.body.into_bytes();
+
+        NetworkResponse {
+            status_code: self.status,
+            headers: self.headers,
+            content_length: body.len(),
+            body,
+            content_type,
+            response_time,
+        }
+    }
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+/// Check if a URL is from an origin service workers may be registered on
+fn is_potentially_trustworthy(url: &Url) -> bool {
+    url.scheme() == "https"
+        || matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::types::TabId;
+    use network::{NetworkConfig, NetworkProcessManager};
+    use storage::FetchRequest;
+
+    #[tokio::test]
+    async fn test_service_worker_intercepts_fetch() {
+        let manager = Arc::new(ServiceWorkerManager::new(&RendererConfig::default()).unwrap());
+        let registration = manager.register("https://app.example/sw.js", "/app/").await.unwrap();
+        assert_eq!(registration.scope().as_str(), "https://app.example/app/");
+        assert_eq!(registration.state(), ServiceWorkerState::Activated);
+
+        registration.add_event_listener("fetch", |event| {
+            if event["request"]["url"].as_str().is_some_and(|url| url.ends_with("/offline")) {
+                Ok(Value::Null)
+            } else {
+                Ok(serde_json::json!({ "status": 200, "body": "from service worker" }))
+            }
+        }).await.unwrap();
+
+        let mut network = NetworkProcessManager::new(NetworkConfig::default()).await.unwrap();
+        network.set_fetch_interceptor(manager.clone());
+
+        let tab_id = TabId::new(1);
+        let request_id = network.create_request(tab_id, "https://app.example/app/data.json".to_string(), "GET".to_string()).await.unwrap();
+        let response = network.execute_request(&request_id).await.unwrap();
+        assert_eq!(response.body, b"from service worker");
+        assert_eq!(response.content_type, "text/plain;charset=UTF-8");
+
+        // Requests outside the scope, or that the worker does not answer, go to the network
+        for url in ["https://app.example/other", "https://app.example/app/offline"] {
+            let request_id = network.create_request(tab_id, url.to_string(), "GET".to_string()).await.unwrap();
+            let response = network.execute_request(&request_id).await.unwrap();
+            assert_ne!(response.body, b"from service worker");
+        }
+
+        assert!(manager.unregister("https://app.example/app/").await);
+        assert_eq!(registration.state(), ServiceWorkerState::Redundant);
+        let request_id = network.create_request(tab_id, "https://app.example/app/data.json".to_string(), "GET".to_string()).await.unwrap();
+        assert_ne!(network.execute_request(&request_id).await.unwrap().body, b"from service worker");
+    }
+
+    #[tokio::test]
+    async fn test_register_rejects_invalid_scopes() {
+        let manager = ServiceWorkerManager::new(&RendererConfig::default()).unwrap();
+
+        assert!(manager.register("http://app.example/sw.js", "/").await.is_err());
+        assert!(manager.register("https://app.example/app/sw.js", "/").await.is_err());
+        assert!(manager.register("https://app.example/sw.js", "https://other.example/").await.is_err());
+        assert!(manager.register("http://localhost/sw.js", "/").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_listener_answers_from_cache() {
+        let manager = Arc::new(ServiceWorkerManager::new(&RendererConfig::default()).unwrap());
+        let registration = manager.register("https://app.example/sw.js", "/").await.unwrap();
+
+        let cached = NetworkResponse {
+            status_code: 200,
+            headers: HashMap::new(),
+            body: b"cached page".to_vec(),
+            content_type: "text/html".to_string(),
+            content_length: 11,
+            response_time: Duration::from_millis(40),
+        };
+        registration.caches().open("offline-v1").unwrap()
+            .put(FetchRequest::get("https://app.example/index.html"), cached)
+            .unwrap();
+
+        // caches.open("offline-v1").then(cache => cache.match(event.request))
+        let caches = registration.caches().clone();
+        registration.add_event_listener("fetch", move |event| {
+            let request = FetchRequest::get(event["request"]["url"].as_str().unwrap_or_default());
+            let cache = caches.open("offline-v1").map_err(|e| Error::JsError(e.to_string()))?;
+            Ok(match cache.match_request(&request).map_err(|e| Error::JsError(e.to_string()))? {
+                Some(response) => serde_json::json!({
+                    "status": response.status_code,
+                    "body": String::from_utf8_lossy(&response.body),
+                    "contentType": response.content_type,
+                }),
+                None => Value::Null,
+            })
+        }).await.unwrap();
+
+        let mut network = NetworkProcessManager::new(NetworkConfig::default()).await.unwrap();
+        network.set_fetch_interceptor(manager.clone());
+        let request_id = network.create_request(TabId::new(1), "https://app.example/index.html".to_string(), "GET".to_string()).await.unwrap();
+        let response = network.execute_request(&request_id).await.unwrap();
+        assert_eq!(response.body, b"cached page");
+        assert_eq!(response.content_type, "text/html");
+
+        // Other origins do not see the cache
+        assert!(manager.caches("https://other.example").keys().unwrap().is_empty());
+        assert_eq!(manager.caches("https://app.example").keys().unwrap(), ["offline-v1"]);
+    }
+}
@@ -2,6 +2,8 @@
 
 use common::error::Result;
 use serde::{Deserialize, Serialize};
+use crate::csp::{CspPolicy, CspResourceType, CspResult, CspViolationReport};
+use crate::sri::{self, SriResult};
 use std::collections::HashMap;
 use tracing::{debug, error, info, warn};
 
@@ -129,6 +131,12 @@ pub struct SiteIsolationManager {
     
     /// Security violations
     security_violations: Vec<SecurityViolation>,
+    
+    /// Enforced Content Security Policies
+    csp_policies: Vec<CspPolicy>,
+    
+    /// CSP violation reports waiting to be sent
+    pending_csp_reports: Vec<CspViolationReport>,
 }
 
 /// Cross-origin communication channel
@@ -197,6 +205,8 @@ impl SiteIsolationManager {
             site_settings: HashMap::new(),
             cross_origin_channels: HashMap::new(),
             security_violations: Vec::new(),
+            csp_policies: Vec::new(),
+            pending_csp_reports: Vec::new(),
         })
     }
     
@@ -230,6 +240,10 @@ impl SiteIsolationManager {
         // Update site URL
         self.site_url = url.to_string();
         
+        // A new document starts without a declared policy
+        self.csp_policies.clear();
+        self.security_context.csp = None;
+        
         // Update security context if needed
         self.update_security_context(url).await?;
         
@@ -242,6 +256,15 @@ impl SiteIsolationManager {
         &self.site_url
     }
     
+    /// Check if a URL is cross-origin to the current site
+    pub fn is_cross_origin_to(&self, other: &str) -> bool {
+        match (Self::extract_origin(&self.site_url), Self::extract_origin(other)) {
+            (Ok(origin), Ok(other_origin)) => origin != other_origin,
+            // Opaque origins are never same-origin with anything
+            _ => true,
+        }
+    }
+    
     /// Get the security context
     pub fn security_context(&self) -> &SiteSecurityContext {
         &self.security_context
@@ -326,6 +349,85 @@ impl SiteIsolationManager {
         Ok(())
     }
     
+    /// Apply the `Content-Security-Policy` header of the current document
+    pub async fn set_csp_header(&mut self, header: &str) -> Result<()> {
+        self.security_context.csp = Some(header.to_string());
+        self.apply_csp(header).await
+    }
+    
+    /// Get the enforced Content Security Policies
+    pub fn csp_policies(&self) -> &[CspPolicy] {
+        &self.csp_policies
+    }
+    
+    /// Check a sub-resource load against the document's CSP
+    pub async fn check_csp(&mut self, resource_url: &str, resource_type: CspResourceType) -> CspResult {
+        let Ok(self_origin) = url::Url::parse(&self.site_url) else {
+            return CspResult::Allowed;
+        };
+        
+        let blocked = self.csp_policies.iter()
+            .map(|policy| (policy, policy.check_url(&self_origin, resource_url, resource_type)))
+            .find(|(_, result)| !result.is_allowed())
+            .map(|(policy, result)| (policy.clone(), result));
+        
+        match blocked {
+            Some((policy, result)) => {
+                if let CspResult::Blocked(directive) = result {
+                    self.report_csp_violation(&policy, directive, resource_type, resource_url).await;
+                }
+                result
+            }
+            None => CspResult::Allowed,
+        }
+    }
+    
+    /// Check whether an inline `<script>` or `<style>` block may execute
+    pub async fn check_inline_csp(&mut self, resource_type: CspResourceType, content: &str, nonce: Option<&str>) -> CspResult {
+        let blocked = self.csp_policies.iter()
+            .map(|policy| (policy, policy.check_inline(resource_type, content, nonce)))
+            .find(|(_, result)| !result.is_allowed())
+            .map(|(policy, result)| (policy.clone(), result));
+        
+        match blocked {
+            Some((policy, result)) => {
+                if let CspResult::Blocked(directive) = result {
+                    self.report_csp_violation(&policy, directive, resource_type, "inline").await;
+                }
+                result
+            }
+            None => CspResult::Allowed,
+        }
+    }
+    
+    /// Verify a fetched sub-resource against its `integrity` attribute
+    pub async fn verify_sri(&mut self, resource_url: &str, body: &[u8], integrity_attr: &str) -> SriResult {
+        let result = sri::verify_integrity(body, integrity_attr);
+        
+        match &result {
+            SriResult::Invalid { expected, got } => {
+                warn!("Integrity check failed for {}: expected {}, got {}", resource_url, expected, got);
+                self.security_violations.push(SecurityViolation {
+                    violation_type: ViolationType::ResourceAccessViolation,
+                    details: format!("Integrity mismatch for {}: expected {}, got {}", resource_url, expected, got),
+                    timestamp: std::time::SystemTime::now(),
+                    blocked: true,
+                });
+            }
+            SriResult::Unsupported => {
+                debug!("No supported integrity hashes for {}", resource_url);
+            }
+            SriResult::Valid => {}
+        }
+        
+        result
+    }
+    
+    /// Take the CSP violation reports waiting to be sent to their `report-uri`
+    pub fn take_csp_violation_reports(&mut self) -> Vec<CspViolationReport> {
+        std::mem::take(&mut self.pending_csp_reports)
+    }
+    
     /// Get security violations
     pub fn get_security_violations(&self) -> &[SecurityViolation] {
         &self.security_violations
@@ -386,8 +488,8 @@ impl SiteIsolationManager {
         debug!("Applying security policies for {}", self.site_url);
         
         // Apply content security policy
-        if let Some(csp) = &self.security_context.csp {
-            self.apply_csp(csp).await?;
+        if let Some(csp) = self.security_context.csp.clone() {
+            self.apply_csp(&csp).await?;
         }
         
         // Apply cross-origin restrictions
@@ -482,12 +584,59 @@ impl SiteIsolationManager {
     }
     
     /// Apply content security policy
-    async fn apply_csp(&self, csp: &str) -> Result<()> {
+    async fn apply_csp(&mut self, csp: &str) -> Result<()> {
         debug!("Applying CSP: {}", csp);
-        // TODO: Implement CSP parsing and enforcement
+        self.csp_policies = CspPolicy::parse_header(csp);
+        
+        // Reports go to `report-uri` resolved against the document URL
+        let document_url = url::Url::parse(&self.site_url).ok();
+        for policy in &mut self.csp_policies {
+            policy.report_uri = policy.report_uri.take().and_then(|report_uri| {
+                let resolved = match &document_url {
+                    Some(base) => base.join(&report_uri),
+                    None => url::Url::parse(&report_uri),
+                };
+                match resolved {
+                    Ok(resolved) => Some(resolved.to_string()),
+                    Err(e) => {
+                        warn!("Ignoring invalid CSP report-uri {}: {}", report_uri, e);
+                        None
+                    }
+                }
+            });
+        }
         Ok(())
     }
     
+    /// Record a CSP violation and queue a report for it
+    async fn report_csp_violation(
+        &mut self,
+        policy: &CspPolicy,
+        directive: crate::csp::CspDirective,
+        resource_type: CspResourceType,
+        blocked_uri: &str,
+    ) {
+        let effective_directive = resource_type.directive().name().to_string();
+        
+        self.record_violation(
+            ViolationType::CspViolation,
+            format!("Refused to load {} because it violates '{}'", blocked_uri, directive.name()),
+            true,
+        ).await;
+        
+        if policy.report_uri.is_some() {
+            self.pending_csp_reports.push(CspViolationReport {
+                document_uri: self.site_url.clone(),
+                blocked_uri: blocked_uri.to_string(),
+                violated_directive: directive.name().to_string(),
+                effective_directive,
+                original_policy: policy.source.clone(),
+                disposition: "enforce".to_string(),
+                report_uri: policy.report_uri.clone(),
+            });
+        }
+    }
+    
     /// Apply cross-origin restrictions
     async fn apply_cross_origin_restrictions(&self) -> Result<()> {
         debug!("Applying cross-origin restrictions");
@@ -595,4 +744,102 @@ mod tests {
         let origin = SiteIsolationManager::extract_origin("https://example.com/path").unwrap();
         assert_eq!(origin, "https://example.com");
     }
+
+    #[tokio::test]
+    async fn test_csp_script_src_none_blocks_inline_scripts() {
+        let mut manager = SiteIsolationManager::new("https://example.com").await.unwrap();
+        manager.set_csp_header("script-src 'none'; report-uri /csp-reports").await.unwrap();
+        
+        let result = manager.check_inline_csp(CspResourceType::Script, "alert(1)", None).await;
+        assert_eq!(result, CspResult::Blocked(crate::csp::CspDirective::ScriptSrc));
+        
+        let reports = manager.take_csp_violation_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].blocked_uri, "inline");
+        assert_eq!(reports[0].violated_directive, "script-src");
+        assert_eq!(reports[0].report_uri.as_deref(), Some("https://example.com/csp-reports"));
+        assert!(reports[0].to_report_body()["csp-report"]["document-uri"].is_string());
+        assert!(matches!(manager.get_security_violations()[0].violation_type, ViolationType::CspViolation));
+    }
+
+    #[tokio::test]
+    async fn test_csp_inline_nonce_and_hash() {
+        let mut manager = SiteIsolationManager::new("https://example.com").await.unwrap();
+        // sha256 of "alert(1)"
+        manager.set_csp_header(
+            "script-src 'unsafe-inline' 'nonce-abc123' 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='"
+        ).await.unwrap();
+        
+        assert!(manager.check_inline_csp(CspResourceType::Script, "console.log(1)", Some("abc123")).await.is_allowed());
+        assert!(manager.check_inline_csp(CspResourceType::Script, "alert(1)", None).await.is_allowed());
+        // 'unsafe-inline' is ignored when a nonce or hash is present
+        assert!(!manager.check_inline_csp(CspResourceType::Script, "console.log(1)", None).await.is_allowed());
+        
+        // No style-src or default-src, so inline styles are unrestricted
+        assert!(manager.check_inline_csp(CspResourceType::Style, "body {}", None).await.is_allowed());
+    }
+
+    #[tokio::test]
+    async fn test_csp_subresource_checks() {
+        let mut manager = SiteIsolationManager::new("https://example.com/page").await.unwrap();
+        manager.set_csp_header("default-src 'self'; img-src 'self' https://*.cdn.net data:; connect-src https://api.example.org:8443/v1/").await.unwrap();
+        
+        assert!(manager.check_csp("https://example.com/app.js", CspResourceType::Script).await.is_allowed());
+        assert_eq!(
+            manager.check_csp("https://evil.com/app.js", CspResourceType::Script).await,
+            CspResult::Blocked(crate::csp::CspDirective::DefaultSrc)
+        );
+        assert!(manager.check_csp("https://img.cdn.net/a.png", CspResourceType::Image).await.is_allowed());
+        assert!(manager.check_csp("data:image/png;base64,AAAA", CspResourceType::Image).await.is_allowed());
+        assert!(!manager.check_csp("https://cdn.net/a.png", CspResourceType::Image).await.is_allowed());
+        assert!(manager.check_csp("https://api.example.org:8443/v1/items", CspResourceType::Connect).await.is_allowed());
+        assert!(!manager.check_csp("https://api.example.org/v1/items", CspResourceType::Connect).await.is_allowed());
+        
+        // No report-uri, so no reports are queued
+        assert!(manager.take_csp_violation_reports().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sri_sha256() {
+        let mut manager = SiteIsolationManager::new("https://example.com").await.unwrap();
+        // sha256 of "alert(1)"
+        let integrity = "sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI=";
+        
+        assert_eq!(manager.verify_sri("https://cdn.example.net/a.js", b"alert(1)", integrity).await, SriResult::Valid);
+        assert!(manager.get_security_violations().is_empty());
+        
+        let result = manager.verify_sri("https://cdn.example.net/a.js", b"alert(2)", integrity).await;
+        match result {
+            SriResult::Invalid { expected, got } => {
+                assert_eq!(expected, integrity);
+                assert!(got.starts_with("sha256-"));
+                assert_ne!(got, expected);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(manager.get_security_violations()[0].violation_type, ViolationType::ResourceAccessViolation));
+    }
+
+    #[tokio::test]
+    async fn test_sri_strongest_algorithm_and_unsupported() {
+        let mut manager = SiteIsolationManager::new("https://example.com").await.unwrap();
+        
+        // A bogus sha256 hash is ignored when a sha384 hash is present
+        let sha384 = crate::sri::SriAlgorithm::Sha384.digest_base64(b"body {}");
+        let integrity = format!("sha256-AAAA sha384-{}?ct=text/css", sha384);
+        assert_eq!(manager.verify_sri("https://example.com/a.css", b"body {}", &integrity).await, SriResult::Valid);
+        
+        assert_eq!(manager.verify_sri("https://example.com/a.css", b"body {}", "md5-AAAA").await, SriResult::Unsupported);
+    }
+
+    #[tokio::test]
+    async fn test_is_cross_origin_to() {
+        let manager = SiteIsolationManager::new("https://example.com/page").await.unwrap();
+        
+        assert!(!manager.is_cross_origin_to("https://example.com/frame"));
+        assert!(manager.is_cross_origin_to("https://ads.example.net/frame"));
+        assert!(manager.is_cross_origin_to("http://example.com/frame"));
+        assert!(manager.is_cross_origin_to("https://example.com:8443/frame"));
+        assert!(manager.is_cross_origin_to("about:blank"));
+    }
 }
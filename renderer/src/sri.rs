@@ -0,0 +1,96 @@
+//! Subresource Integrity (SRI) checking
+
+use base64::Engine;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/// Hash algorithms supported in `integrity` metadata, weakest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SriAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl SriAlgorithm {
+    /// Parse an algorithm token such as `sha384`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "sha256" => Some(SriAlgorithm::Sha256),
+            "sha384" => Some(SriAlgorithm::Sha384),
+            "sha512" => Some(SriAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    /// Algorithm token as used in `integrity` metadata
+    pub fn name(&self) -> &'static str {
+        match self {
+            SriAlgorithm::Sha256 => "sha256",
+            SriAlgorithm::Sha384 => "sha384",
+            SriAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// Base64-encoded digest of `body`
+    pub fn digest_base64(&self, body: &[u8]) -> String {
+        let digest = match self {
+            SriAlgorithm::Sha256 => Sha256::digest(body).to_vec(),
+            SriAlgorithm::Sha384 => Sha384::digest(body).to_vec(),
+            SriAlgorithm::Sha512 => Sha512::digest(body).to_vec(),
+        };
+        base64::engine::general_purpose::STANDARD.encode(digest)
+    }
+}
+
+/// Result of an integrity check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SriResult {
+    /// The body matches the integrity metadata
+    Valid,
+
+    /// The body does not match; hashes are in `<algorithm>-<base64>` form
+    Invalid { expected: String, got: String },
+
+    /// The integrity metadata has no supported hashes, so it is ignored
+    Unsupported,
+}
+
+impl SriResult {
+    /// Check if the resource may be used
+    pub fn is_allowed(&self) -> bool {
+        !matches!(self, SriResult::Invalid { .. })
+    }
+}
+
+/// Check `body` against the value of an `integrity` attribute
+///
+/// Only the strongest algorithm present is considered; the body is valid if
+/// it matches any hash for that algorithm.
+pub fn verify_integrity(body: &[u8], integrity_attr: &str) -> SriResult {
+    let hashes: Vec<(SriAlgorithm, &str)> = integrity_attr.split_ascii_whitespace()
+        .filter_map(|token| {
+            // Options after `?` are reserved and ignored
+            let token = token.split_once('?').map_or(token, |(hash, _)| hash);
+            let (algorithm, expected) = token.split_once('-')?;
+            Some((SriAlgorithm::from_name(algorithm)?, expected))
+        })
+        .collect();
+
+    let Some(strongest) = hashes.iter().map(|(algorithm, _)| *algorithm).max() else {
+        return SriResult::Unsupported;
+    };
+
+    let actual = strongest.digest_base64(body);
+    let mut expected = hashes.iter()
+        .filter(|(algorithm, _)| *algorithm == strongest)
+        .map(|(_, hash)| *hash);
+
+    if expected.clone().any(|hash| hash == actual) {
+        SriResult::Valid
+    } else {
+        SriResult::Invalid {
+            expected: format!("{}-{}", strongest.name(), expected.next().unwrap_or_default()),
+            got: format!("{}-{}", strongest.name(), actual),
+        }
+    }
+}
@@ -1,16 +1,33 @@
 //! Style engine for renderer processes
 
-use common::error::Result;
+use async_trait::async_trait;
+use common::error::{Error, Result};
+use common::types::TabId;
 use css::{CssToken, CssTokenizer};
+use dom::container_queries::{ContainerContext, ContainerQueryEvaluator, ContainerRule};
+use dom::css_at_rules::{AtRuleManager, AtRuleParser, FontFaceDeclaration, FontFaceSource};
+use dom::css_selector::{Combinator, ComplexSelector, ComplexSelectorPart};
+use dom::cssom::CssRuleVariant;
+use dom::media_queries::MediaQueryEvaluator;
+use dom::typography::{FontFamily, FontLoader, FontManager};
+use dom::{Element, Node};
+use network::NetworkProcessManager;
 use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
+/// How long text waits for a web font with `font-display: auto` or `block`
+pub const DEFAULT_FONT_BLOCK_TIMEOUT: Duration = Duration::from_secs(3);
+
 /// Style engine manager
 pub struct StyleEngineManager {
     /// CSS tokenizer
     tokenizer: CssTokenizer,
     
-    /// Parsed CSS rules
+    /// CSS rules applied by the last style pass
     css_rules: Vec<CssRule>,
     
     /// Computed styles cache
@@ -21,6 +38,36 @@ pub struct StyleEngineManager {
     
     /// CSS variables
     css_variables: std::collections::HashMap<String, String>,
+    
+    /// At-rules of the style sheets, keyed by style sheet ID
+    at_rule_manager: AtRuleManager,
+    
+    /// Evaluator for `@media` conditions
+    media_evaluator: MediaQueryEvaluator,
+    
+    /// Root element of the document being styled
+    document_root: Option<Element>,
+    
+    /// Sizes of the query containers from the last layout, by element ID
+    container_contexts: std::collections::HashMap<String, ContainerContext>,
+    
+    /// Installed fonts and the web fonts loaded with `@font-face`
+    font_manager: Arc<RwLock<FontManager>>,
+    
+    /// Web font loads, keyed by family and sources
+    font_loads: std::collections::HashMap<String, JoinHandle<()>>,
+    
+    /// Block period of `font-display: auto` and `block` fonts
+    font_block_timeout: Duration,
+}
+
+/// Fetches web fonts through the network process
+pub struct NetworkFontLoader {
+    /// Network process manager
+    network: Arc<RwLock<NetworkProcessManager>>,
+    
+    /// Tab the fonts are loaded for
+    tab_id: TabId,
 }
 
 /// CSS rule
@@ -59,6 +106,9 @@ pub enum CssRuleType {
     
     /// Keyframes rule
     Keyframes,
+    
+    /// Container rule
+    Container,
 }
 
 /// CSS value
@@ -193,6 +243,13 @@ impl StyleEngineManager {
             computed_styles_cache: std::collections::HashMap::new(),
             style_sheets: Vec::new(),
             css_variables: std::collections::HashMap::new(),
+            at_rule_manager: AtRuleManager::new(),
+            media_evaluator: MediaQueryEvaluator::default(),
+            document_root: None,
+            container_contexts: std::collections::HashMap::new(),
+            font_manager: Arc::new(RwLock::new(FontManager::new())),
+            font_loads: std::collections::HashMap::new(),
+            font_block_timeout: DEFAULT_FONT_BLOCK_TIMEOUT,
         })
     }
     
@@ -216,6 +273,7 @@ impl StyleEngineManager {
         
         // Clear computed styles cache
         self.computed_styles_cache.clear();
+        self.css_rules.clear();
         
         // Process all style sheets
         let style_sheets = self.style_sheets.clone();
@@ -225,6 +283,14 @@ impl StyleEngineManager {
             }
         }
         
+        // Replace author colors with system colors in forced colors mode
+        if self.media_evaluator.forced_colors {
+            self.apply_forced_colors();
+        }
+        
+        // Wait for the web fonts text is set in
+        self.load_web_fonts().await;
+        
         // Apply CSS variables
         self.apply_css_variables().await?;
         
@@ -255,6 +321,7 @@ impl StyleEngineManager {
         
         // Parse CSS content
         let rules = self.parse_css(css_content).await?;
+        self.parse_at_rules(&style_sheet_id, css_content);
         
         let style_sheet = StyleSheet {
             id: style_sheet_id.clone(),
@@ -274,6 +341,7 @@ impl StyleEngineManager {
         info!("Removing style sheet {}", style_sheet_id);
         
         self.style_sheets.retain(|sheet| sheet.id != style_sheet_id);
+        self.at_rule_manager.remove_rules(style_sheet_id);
         
         // Clear computed styles cache since rules may have changed
         self.computed_styles_cache.clear();
@@ -282,6 +350,16 @@ impl StyleEngineManager {
         Ok(())
     }
     
+    /// Remove all style sheets
+    pub async fn clear_style_sheets(&mut self) {
+        info!("Removing all style sheets");
+        
+        for style_sheet in self.style_sheets.drain(..) {
+            self.at_rule_manager.remove_rules(&style_sheet.id);
+        }
+        self.computed_styles_cache.clear();
+    }
+    
     /// Set CSS variable
     pub async fn set_css_variable(&mut self, variable_name: &str, value: &str) -> Result<()> {
         debug!("Setting CSS variable {} = {}", variable_name, value);
@@ -299,6 +377,127 @@ impl StyleEngineManager {
         Ok(self.css_variables.get(variable_name).cloned())
     }
     
+    /// Get the CSS rules applied by the last style pass
+    pub fn applied_rules(&self) -> &[CssRule] {
+        &self.css_rules
+    }
+    
+    /// Get the media query evaluator
+    pub fn media_evaluator(&self) -> &MediaQueryEvaluator {
+        &self.media_evaluator
+    }
+    
+    /// Get the media query evaluator mutably
+    pub fn media_evaluator_mut(&mut self) -> &mut MediaQueryEvaluator {
+        &mut self.media_evaluator
+    }
+    
+    /// Turn forced colors mode on or off, e.g. when the system enables high contrast
+    pub fn set_forced_colors(&mut self, active: bool) {
+        debug!("Setting forced colors mode to {}", active);
+        
+        self.media_evaluator.forced_colors = active;
+        
+        // Clear computed styles cache since the colors of every rule change
+        self.computed_styles_cache.clear();
+    }
+    
+    /// Update the viewport used to evaluate media queries
+    pub fn set_viewport_size(&mut self, width: u32, height: u32) {
+        debug!("Setting style viewport to {}x{}", width, height);
+        
+        self.media_evaluator.set_viewport_size(width, height);
+        
+        // Clear computed styles cache since other media rules may apply
+        self.computed_styles_cache.clear();
+    }
+    
+    /// Set the document whose element tree `@container` rules are evaluated against
+    pub fn set_document(&mut self, root: Element) {
+        self.document_root = Some(root);
+        self.computed_styles_cache.clear();
+    }
+    
+    /// Set the size of a query container, as found by layout
+    pub fn set_container_context(&mut self, element_id: &str, context: ContainerContext) {
+        self.container_contexts.insert(element_id.to_string(), context);
+        
+        // Clear computed styles cache since other container rules may apply
+        self.computed_styles_cache.clear();
+    }
+    
+    /// Forget the sizes of all query containers
+    pub fn clear_container_contexts(&mut self) {
+        self.container_contexts.clear();
+        self.computed_styles_cache.clear();
+    }
+    
+    /// Get the font manager, which holds the loaded web fonts
+    pub fn font_manager(&self) -> Arc<RwLock<FontManager>> {
+        self.font_manager.clone()
+    }
+    
+    /// Set the loader that fetches the files of `@font-face` rules
+    pub async fn set_font_loader(&mut self, loader: Arc<dyn FontLoader>) {
+        self.font_manager.write().await.set_font_loader(loader);
+    }
+    
+    /// Set how long text waits for a web font with `font-display: auto` or `block`
+    pub fn set_font_block_timeout(&mut self, timeout: Duration) {
+        self.font_block_timeout = timeout;
+    }
+    
+    /// Start loading the `@font-face` fonts of the enabled style sheets
+    ///
+    /// Rendering is blocked on fonts that text is set in until they load or
+    /// their `font-display` block period is over. Fonts keep loading in the
+    /// background after that and are used once registered.
+    async fn load_web_fonts(&mut self) {
+        let declarations: Vec<FontFaceDeclaration> = self.style_sheets.iter()
+            .filter(|style_sheet| style_sheet.enabled)
+            .flat_map(|style_sheet| {
+                self.at_rule_manager.font_face_rules(&style_sheet.id)
+                    .map(|declaration| resolve_font_sources(declaration, style_sheet.url.as_deref()))
+            })
+            .collect();
+        
+        for declaration in declarations {
+            let blocks_text = self.text_uses_font_family(&declaration.family);
+            let block_period = declaration.display.block_period(self.font_block_timeout);
+            let family = declaration.family.0.clone();
+            
+            let load = self.font_loads.entry(font_load_key(&declaration)).or_insert_with(|| {
+                debug!("Loading web font {}", declaration.family.0);
+                let font_manager = self.font_manager.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = font_manager.write().await.load_web_font(&declaration).await {
+                        warn!("Failed to load web font {}: {}", declaration.family.0, e);
+                    }
+                })
+            });
+            
+            if !blocks_text || load.is_finished() {
+                continue;
+            }
+            if tokio::time::timeout(block_period, load).await.is_err() {
+                debug!("Rendering text with a fallback font while {} loads", family);
+            }
+        }
+    }
+    
+    /// Check if text in the document is set in a font family
+    ///
+    /// Rules aren't matched against elements yet, so any applied rule naming
+    /// the family counts, as do the `style` attributes of elements with text.
+    fn text_uses_font_family(&self, family: &FontFamily) -> bool {
+        let in_rules = self.css_rules.iter()
+            .filter_map(|rule| rule.properties.get("font-family"))
+            .any(|value| font_family_names(value).iter().any(|name| name.eq_ignore_ascii_case(&family.0)));
+        
+        in_rules || self.document_root.as_ref()
+            .is_some_and(|root| text_in_font_family(root, false, family))
+    }
+    
     /// Parse CSS content
     async fn parse_css(&mut self, css_content: &str) -> Result<Vec<CssRule>> {
         debug!("Parsing CSS content");
@@ -325,6 +524,16 @@ impl StyleEngineManager {
         Ok(rules)
     }
     
+    /// Parse the top-level `@media`, `@container`, and `@font-face` rules of a style sheet
+    fn parse_at_rules(&mut self, style_sheet_id: &str, css_content: &str) {
+        for at_rule in top_level_at_rules(css_content) {
+            match AtRuleParser::new().parse_at_rule(at_rule) {
+                Ok(rule) => self.at_rule_manager.add_rule(style_sheet_id, rule),
+                Err(e) => warn!("Failed to parse at-rule in style sheet {}: {}", style_sheet_id, e),
+            }
+        }
+    }
+    
     /// Process a style sheet
     async fn process_style_sheet(&mut self, style_sheet: &StyleSheet) -> Result<()> {
         debug!("Processing style sheet {}", style_sheet.id);
@@ -333,9 +542,79 @@ impl StyleEngineManager {
             self.process_css_rule(rule).await?;
         }
         
+        // Only apply the rules of @media wrappers whose condition matches
+        let media_rules: Vec<CssRule> = self.at_rule_manager.media_rules(&style_sheet.id)
+            .filter(|(media_query, _)| {
+                let matches = self.media_evaluator.matches(media_query);
+                if !matches {
+                    debug!("Skipping @media {} in style sheet {}", media_query, style_sheet.id);
+                }
+                matches
+            })
+            .flat_map(|(_, rules)| rules.iter().filter_map(|rule| conditional_css_rule(rule, CssRuleType::Media)))
+            .collect();
+        
+        for rule in &media_rules {
+            self.process_css_rule(rule).await?;
+        }
+        
+        // Only apply the rules of @container wrappers that match for some element
+        let container_rules: Vec<ContainerRule> = self.at_rule_manager.container_rules(&style_sheet.id).collect();
+        if !container_rules.is_empty() {
+            let mut enabled = vec![false; container_rules.len()];
+            if let Some(root) = &self.document_root {
+                self.enable_container_rules(root, &mut Vec::new(), &container_rules, &mut enabled);
+            }
+            
+            let container_css_rules: Vec<CssRule> = container_rules.iter().zip(&enabled)
+                .filter(|(container_rule, enabled)| {
+                    if !**enabled {
+                        debug!("Skipping @container {} in style sheet {}", container_rule.condition, style_sheet.id);
+                    }
+                    **enabled
+                })
+                .flat_map(|(container_rule, _)| {
+                    container_rule.rules.iter().filter_map(|rule| conditional_css_rule(rule, CssRuleType::Container))
+                })
+                .collect();
+            
+            for rule in &container_css_rules {
+                self.process_css_rule(rule).await?;
+            }
+        }
+        
         Ok(())
     }
     
+    /// Walk the element tree, enabling the `@container` rules that match for an element
+    ///
+    /// Each element is evaluated against the query containers among its
+    /// ancestors, so a container's own size never affects its own style.
+    fn enable_container_rules(&self, element: &Element, containers: &mut Vec<ContainerContext>, rules: &[ContainerRule], enabled: &mut [bool]) {
+        if !containers.is_empty() {
+            for (rule, enabled) in rules.iter().zip(enabled.iter_mut()) {
+                if !*enabled {
+                    *enabled = ContainerQueryEvaluator::evaluate_nearest(rule, containers);
+                }
+            }
+        }
+        
+        let context = self.container_contexts.get(&element.id);
+        if let Some(context) = context {
+            containers.push(context.clone());
+        }
+        
+        for child in &element.children {
+            if let Node::Element(child) = child {
+                self.enable_container_rules(child, containers, rules, enabled);
+            }
+        }
+        
+        if context.is_some() {
+            containers.pop();
+        }
+    }
+    
     /// Process a CSS rule
     async fn process_css_rule(&mut self, rule: &CssRule) -> Result<()> {
         debug!("Processing CSS rule with {} selectors", rule.selectors.len());
@@ -347,6 +626,8 @@ impl StyleEngineManager {
         // 3. Applying properties to matched elements
         // 4. Updating computed styles cache
         
+        self.css_rules.push(rule.clone());
+        
         Ok(())
     }
     
@@ -415,6 +696,23 @@ impl StyleEngineManager {
         Ok(())
     }
     
+    /// Override the colors of the applied rules with system color keywords
+    fn apply_forced_colors(&mut self) {
+        for rule in &mut self.css_rules {
+            let (foreground, background) = forced_color_pair(&rule.selectors);
+            for (property, value) in rule.properties.iter_mut() {
+                let system_color = match property.as_str() {
+                    "color" | "border-color" | "outline-color" | "text-decoration-color" | "caret-color" => foreground,
+                    "background-color" | "background" => background,
+                    "box-shadow" | "text-shadow" | "background-image" => "none",
+                    _ if property.starts_with("border-") && property.ends_with("-color") => foreground,
+                    _ => continue,
+                };
+                *value = CssValue::Keyword(system_color.to_string());
+            }
+        }
+    }
+    
     /// Apply CSS variables
     async fn apply_css_variables(&mut self) -> Result<()> {
         debug!("Applying CSS variables");
@@ -488,6 +786,207 @@ impl StyleEngineManager {
     }
 }
 
+/// Convert a style rule from an `@media` or `@container` wrapper into a CSS rule
+fn conditional_css_rule(rule: &CssRuleVariant, rule_type: CssRuleType) -> Option<CssRule> {
+    let CssRuleVariant::StyleRule(rule) = rule else {
+        return None;
+    };
+    
+    let specificity = rule.selectors.max_specificity();
+    Some(CssRule {
+        rule_type,
+        selectors: rule.selectors.selectors.iter().map(selector_text).collect(),
+        properties: rule.declarations().iter()
+            .map(|declaration| (declaration.property().to_string(), css_value(declaration.value())))
+            .collect(),
+        specificity: Specificity {
+            id_selectors: specificity.id_count,
+            class_selectors: specificity.class_count,
+            element_selectors: specificity.type_count,
+        },
+        source_location: None,
+    })
+}
+
+/// Get the foreground and background system colors for the elements selectors match
+fn forced_color_pair(selectors: &[String]) -> (&'static str, &'static str) {
+    let element_type = |selector: &String| {
+        let subject = selector.rsplit([' ', '>', '+', '~']).next().unwrap_or_default();
+        subject.split(['#', '.', ':', '[']).next().unwrap_or_default().to_ascii_lowercase()
+    };
+    
+    match selectors.first().map(element_type).as_deref() {
+        Some("button") => ("ButtonText", "ButtonFace"),
+        Some("input" | "select" | "textarea") => ("FieldText", "Field"),
+        Some("a") => ("LinkText", "Canvas"),
+        _ => ("CanvasText", "Canvas"),
+    }
+}
+
+/// Serialize a parsed selector
+fn selector_text(selector: &ComplexSelector) -> String {
+    let mut text = String::new();
+    for part in &selector.parts {
+        match part {
+            ComplexSelectorPart::Compound(compound) => {
+                let simple = &compound.simple;
+                if let Some(element_type) = &simple.element_type {
+                    text.push_str(element_type);
+                }
+                if let Some(id) = &simple.id {
+                    text.push_str(&format!("#{}", id));
+                }
+                for class in &simple.classes {
+                    text.push_str(&format!(".{}", class));
+                }
+                if text.is_empty() || text.ends_with(' ') {
+                    text.push('*');
+                }
+            }
+            ComplexSelectorPart::Combinator(combinator) => text.push_str(match combinator {
+                Combinator::Descendant => " ",
+                Combinator::Child => " > ",
+                Combinator::AdjacentSibling => " + ",
+                Combinator::GeneralSibling => " ~ ",
+            }),
+        }
+    }
+    text
+}
+
+/// Convert a CSSOM value into a style engine value
+fn css_value(value: &dom::cssom::CssValue) -> CssValue {
+    use dom::cssom::CssValue as CssomValue;
+    
+    match value {
+        CssomValue::Number(number) => CssValue::Number(*number),
+        CssomValue::Percentage(percentage) => CssValue::Length(*percentage, LengthUnit::Percent),
+        CssomValue::Length(length, unit) => match unit.as_str() {
+            "px" => CssValue::Length(*length, LengthUnit::Px),
+            "em" => CssValue::Length(*length, LengthUnit::Em),
+            "rem" => CssValue::Length(*length, LengthUnit::Rem),
+            "vw" => CssValue::Length(*length, LengthUnit::Vw),
+            "vh" => CssValue::Length(*length, LengthUnit::Vh),
+            _ => CssValue::Keyword(format!("{}{}", length, unit)),
+        },
+        CssomValue::String(text) => CssValue::String(text.clone()),
+        CssomValue::Function(name, args) => CssValue::Function(name.clone(), args.iter().map(css_value).collect()),
+        CssomValue::List(values) => CssValue::List(values.iter().map(css_value).collect()),
+        CssomValue::Keyword(text) | CssomValue::Color(text) => CssValue::Keyword(text.clone()),
+        CssomValue::Url(url) => CssValue::Function("url".to_string(), vec![CssValue::String(url.clone())]),
+        CssomValue::Initial => CssValue::Keyword("initial".to_string()),
+        CssomValue::Inherit => CssValue::Keyword("inherit".to_string()),
+        CssomValue::Unset => CssValue::Keyword("unset".to_string()),
+        CssomValue::Revert => CssValue::Keyword("revert".to_string()),
+    }
+}
+
+/// Find the top-level `@media`, `@container`, and `@font-face` rules in a style sheet
+fn top_level_at_rules(css_content: &str) -> Vec<&str> {
+    let mut rules = Vec::new();
+    let mut depth = 0usize;
+    let mut rule_start = None;
+    
+    for (i, c) in css_content.char_indices() {
+        match c {
+            '@' if depth == 0 && ["@media", "@container", "@font-face"].iter().any(|rule| css_content[i..].starts_with(rule)) => {
+                rule_start = Some(i);
+            }
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    if let Some(start) = rule_start.take() {
+                        rules.push(&css_content[start..=i]);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    rules
+}
+
+/// Resolve the `url()` sources of an `@font-face` rule against its style sheet
+fn resolve_font_sources(declaration: &FontFaceDeclaration, style_sheet_url: Option<&str>) -> FontFaceDeclaration {
+    let mut declaration = declaration.clone();
+    let Some(base) = style_sheet_url.and_then(|url| url::Url::parse(url).ok()) else {
+        return declaration;
+    };
+    
+    for source in &mut declaration.sources {
+        if let FontFaceSource::Url { url, .. } = source {
+            if let Ok(resolved) = base.join(url) {
+                *url = resolved.to_string();
+            }
+        }
+    }
+    declaration
+}
+
+/// Key identifying the load of an `@font-face` rule
+fn font_load_key(declaration: &FontFaceDeclaration) -> String {
+    let sources: Vec<String> = declaration.sources.iter()
+        .map(|source| match source {
+            FontFaceSource::Url { url, .. } => format!("url({})", url),
+            FontFaceSource::Local(name) => format!("local({})", name),
+        })
+        .collect();
+    format!("{}:{}:{:?}:{:?}:{}", declaration.family.0, declaration.weight.0, declaration.style, declaration.stretch, sources.join(","))
+}
+
+/// Get the family names in a `font-family` value
+fn font_family_names(value: &CssValue) -> Vec<String> {
+    match value {
+        CssValue::Keyword(names) | CssValue::String(names) => names.split(',')
+            .map(|name| name.trim().trim_matches(['"', '\'']).to_string())
+            .filter(|name| !name.is_empty())
+            .collect(),
+        CssValue::List(values) => values.iter().flat_map(font_family_names).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Check if an element has text in a font family set by its own or inherited `style` attribute
+fn text_in_font_family(element: &Element, inherited: bool, family: &FontFamily) -> bool {
+    let in_family = element.get_attribute("style")
+        .and_then(|style| {
+            style.split(';')
+                .filter_map(|declaration| declaration.split_once(':'))
+                .find(|(property, _)| property.trim().eq_ignore_ascii_case("font-family"))
+                .map(|(_, value)| font_family_names(&CssValue::Keyword(value.to_string())))
+        })
+        .map_or(inherited, |names| names.iter().any(|name| name.eq_ignore_ascii_case(&family.0)));
+    
+    element.children.iter().any(|child| match child {
+        Node::Text(text) => in_family && !text.content.trim().is_empty(),
+        Node::Element(child) => text_in_font_family(child, in_family, family),
+        _ => false,
+    })
+}
+
+impl NetworkFontLoader {
+    /// Create a font loader that fetches through the network process
+    pub fn new(network: Arc<RwLock<NetworkProcessManager>>, tab_id: TabId) -> Self {
+        Self { network, tab_id }
+    }
+}
+
+#[async_trait]
+impl FontLoader for NetworkFontLoader {
+    async fn fetch_font(&self, url: &str) -> Result<Vec<u8>> {
+        let mut network = self.network.write().await;
+        let request_id = network.create_request(self.tab_id, url.to_string(), "GET".to_string()).await?;
+        let response = network.execute_request(&request_id).await?;
+        
+        if !(200..300).contains(&response.status_code) {
+            return Err(Error::NetworkError(format!("Failed to load font {}: HTTP {}", url, response.status_code)));
+        }
+        Ok(response.body)
+    }
+}
+
 impl Default for Specificity {
     fn default() -> Self {
         Self {
@@ -541,6 +1040,153 @@ mod tests {
         assert_eq!(value.unwrap(), Some("#ff0000".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_media_rules_follow_viewport() {
+        let mut manager = StyleEngineManager::new().await.unwrap();
+        manager.set_viewport_size(1024, 768);
+        
+        let css_content = "body { color: black; } @media screen and (max-width: 600px) { .sidebar { display: none; } }";
+        manager.add_style_sheet(css_content, Some("responsive.css")).await.unwrap();
+        
+        manager.apply_styles().await.unwrap();
+        assert!(manager.applied_rules().iter().all(|rule| !matches!(rule.rule_type, CssRuleType::Media)));
+        
+        manager.set_viewport_size(480, 800);
+        manager.apply_styles().await.unwrap();
+        let media_rule = manager.applied_rules().iter()
+            .find(|rule| matches!(rule.rule_type, CssRuleType::Media))
+            .expect("the @media rule should apply to a narrow viewport");
+        assert_eq!(media_rule.selectors, vec![".sidebar".to_string()]);
+        assert!(media_rule.properties.contains_key("display"));
+    }
+
+    #[tokio::test]
+    async fn test_forced_colors_override_author_colors() {
+        let mut manager = StyleEngineManager::new().await.unwrap();
+        
+        let css_content = "@media all { button { color: #336699; background-color: #eeeeee; border-top-color: red; margin: 0; } \
+                           .notice { color: gray; background: white; box-shadow: 0 0 4px black; } }";
+        manager.add_style_sheet(css_content, Some("theme.css")).await.unwrap();
+        
+        let keyword = |manager: &StyleEngineManager, selector: &str, property: &str| manager.applied_rules().iter()
+            .find(|rule| rule.selectors.iter().any(|text| text == selector))
+            .and_then(|rule| match rule.properties.get(property) {
+                Some(CssValue::Keyword(keyword)) => Some(keyword.clone()),
+                _ => None,
+            });
+        
+        manager.apply_styles().await.unwrap();
+        assert_ne!(keyword(&manager, "button", "color").as_deref(), Some("ButtonText"));
+        
+        manager.set_forced_colors(true);
+        manager.apply_styles().await.unwrap();
+        assert!(manager.media_evaluator().matches("(forced-colors: active)"));
+        assert_eq!(keyword(&manager, "button", "color").as_deref(), Some("ButtonText"));
+        assert_eq!(keyword(&manager, "button", "background-color").as_deref(), Some("ButtonFace"));
+        assert_eq!(keyword(&manager, "button", "border-top-color").as_deref(), Some("ButtonText"));
+        assert_eq!(keyword(&manager, ".notice", "color").as_deref(), Some("CanvasText"));
+        assert_eq!(keyword(&manager, ".notice", "background").as_deref(), Some("Canvas"));
+        assert_eq!(keyword(&manager, ".notice", "box-shadow").as_deref(), Some("none"));
+        
+        manager.set_forced_colors(false);
+        manager.apply_styles().await.unwrap();
+        assert_ne!(keyword(&manager, "button", "color").as_deref(), Some("ButtonText"));
+    }
+
+    #[tokio::test]
+    async fn test_container_rules_follow_container_size() {
+        let mut manager = StyleEngineManager::new().await.unwrap();
+        
+        let css_content = ".grid { display: grid; grid-template-columns: 1fr 1fr; } \
+                           @container cards (width < 400px) { .grid { grid-template-columns: 1fr; } }";
+        manager.add_style_sheet(css_content, Some("cards.css")).await.unwrap();
+        
+        let mut grid = Element::new("div".to_string());
+        grid.set_attribute("class".to_string(), "grid".to_string());
+        let mut container = Element::new("section".to_string());
+        container.id = "cards".to_string();
+        container.append_child(Node::Element(grid));
+        let mut body = Element::new("body".to_string());
+        body.append_child(Node::Element(container));
+        manager.set_document(body);
+        
+        let container_rules = |manager: &StyleEngineManager| manager.applied_rules().iter()
+            .filter(|rule| matches!(rule.rule_type, CssRuleType::Container))
+            .count();
+        
+        // Without a layout there is no container to query
+        manager.apply_styles().await.unwrap();
+        assert_eq!(container_rules(&manager), 0);
+        
+        manager.set_container_context("cards", ContainerContext::new(Some("cards".to_string()), 640.0, 0.0));
+        manager.apply_styles().await.unwrap();
+        assert_eq!(container_rules(&manager), 0);
+        
+        manager.set_container_context("cards", ContainerContext::new(Some("cards".to_string()), 320.0, 0.0));
+        manager.apply_styles().await.unwrap();
+        assert_eq!(container_rules(&manager), 1);
+        let rule = manager.applied_rules().iter()
+            .find(|rule| matches!(rule.rule_type, CssRuleType::Container))
+            .unwrap();
+        assert_eq!(rule.selectors, vec![".grid".to_string()]);
+        assert!(rule.properties.contains_key("grid-template-columns"));
+    }
+
+    #[tokio::test]
+    async fn test_web_fonts_load_before_rendering() {
+        struct WebFontServer;
+        
+        #[async_trait]
+        impl network::FetchInterceptor for WebFontServer {
+            async fn intercept_fetch(&self, request: &network::NetworkRequest) -> Option<network::NetworkResponse> {
+                if request.url != "https://fonts.example/fonts/my.woff2" {
+                    return None;
+                }
+                let body = b"wOF2\0\x01\0\0".to_vec();
+                Some(network::NetworkResponse {
+                    status_code: 200,
+                    headers: std::collections::HashMap::new(),
+                    content_length: body.len(),
+                    body,
+                    content_type: "font/woff2".to_string(),
+                    response_time: Duration::ZERO,
+                })
+            }
+        }
+        
+        let mut network = NetworkProcessManager::new(network::NetworkConfig::default()).await.unwrap();
+        network.set_fetch_interceptor(Arc::new(WebFontServer));
+        let network = Arc::new(RwLock::new(network));
+        
+        let mut manager = StyleEngineManager::new().await.unwrap();
+        manager.set_font_loader(Arc::new(NetworkFontLoader::new(network, TabId::new(1)))).await;
+        
+        let css_content = "@font-face { font-family: \"MyFont\"; src: url(\"fonts/my.woff2\") format(\"woff2\"); font-weight: bold; } \
+                           p { margin: 0; }";
+        manager.add_style_sheet(css_content, Some("https://fonts.example/site.css")).await.unwrap();
+        
+        let mut paragraph = Element::new("p".to_string());
+        paragraph.set_attribute("style".to_string(), "font-family: MyFont, sans-serif".to_string());
+        paragraph.append_child(Node::Text(dom::TextNode::new("Hello".to_string())));
+        let mut body = Element::new("body".to_string());
+        body.append_child(Node::Element(paragraph));
+        manager.set_document(body);
+        
+        let family = FontFamily("MyFont".to_string());
+        assert!(manager.text_uses_font_family(&family));
+        
+        manager.apply_styles().await.unwrap();
+        
+        let font_manager = manager.font_manager();
+        let font_manager = font_manager.read().await;
+        let face = font_manager.match_font(&family, dom::FontWeight(700), dom::FontStyle::Normal, dom::FontStretch::Normal)
+            .expect("the web font should be registered before styles are applied");
+        assert_eq!(face.family, family);
+        assert_eq!(face.weight, dom::FontWeight(700));
+        assert!(face.is_loaded);
+        assert_eq!(face.data.as_deref(), Some(&b"wOF2\0\x01\0\0"[..]));
+    }
+
     #[tokio::test]
     async fn test_computed_styles() {
         let manager = StyleEngineManager::new().await.unwrap();
@@ -0,0 +1,595 @@
+//! WebAssembly engine for renderer processes
+//!
+//! Modules are validated and compiled by wasmtime. Modules may only import
+//! functions, which are provided by the embedder as host functions, and
+//! linear memory never grows past the memory limit of the renderer.
+
+use common::error::{Error, Result};
+use std::collections::HashMap;
+use std::sync::Arc;
+use wasmtime::{Engine, Extern, ExternType, Func, FuncType, Instance, Memory, Module, ResourceLimiter, Store, Trap, Val, ValType};
+
+/// Size of a linear memory page
+const PAGE_SIZE: usize = 65536;
+
+/// Most pages a 32-bit memory can have
+const MAX_PAGES: u32 = 65536;
+
+/// Most entries a table may have
+const MAX_TABLE_SIZE: usize = 1 << 20;
+
+/// Type of a WebAssembly value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasmValueType {
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl WasmValueType {
+    fn to_val_type(self) -> ValType {
+        match self {
+            WasmValueType::I32 => ValType::I32,
+            WasmValueType::I64 => ValType::I64,
+            WasmValueType::F32 => ValType::F32,
+            WasmValueType::F64 => ValType::F64,
+        }
+    }
+
+    fn from_val_type(val_type: &ValType) -> Option<Self> {
+        match val_type {
+            ValType::I32 => Some(WasmValueType::I32),
+            ValType::I64 => Some(WasmValueType::I64),
+            ValType::F32 => Some(WasmValueType::F32),
+            ValType::F64 => Some(WasmValueType::F64),
+            _ => None,
+        }
+    }
+}
+
+/// WebAssembly value passed to and returned from functions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WasmValue {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl WasmValue {
+    /// Get the type of the value
+    pub fn value_type(&self) -> WasmValueType {
+        match self {
+            WasmValue::I32(_) => WasmValueType::I32,
+            WasmValue::I64(_) => WasmValueType::I64,
+            WasmValue::F32(_) => WasmValueType::F32,
+            WasmValue::F64(_) => WasmValueType::F64,
+        }
+    }
+
+    /// Get the value if it is an `i32`
+    pub fn as_i32(&self) -> Option<i32> {
+        match self {
+            WasmValue::I32(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get the value if it is an `i64`
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            WasmValue::I64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get the value if it is an `f32`
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            WasmValue::F32(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get the value if it is an `f64`
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            WasmValue::F64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn to_val(self) -> Val {
+        match self {
+            WasmValue::I32(value) => Val::I32(value),
+            WasmValue::I64(value) => Val::I64(value),
+            WasmValue::F32(value) => Val::F32(value.to_bits()),
+            WasmValue::F64(value) => Val::F64(value.to_bits()),
+        }
+    }
+
+    fn from_val(val: &Val) -> Option<Self> {
+        match val {
+            Val::I32(value) => Some(WasmValue::I32(*value)),
+            Val::I64(value) => Some(WasmValue::I64(*value)),
+            Val::F32(bits) => Some(WasmValue::F32(f32::from_bits(*bits))),
+            Val::F64(bits) => Some(WasmValue::F64(f64::from_bits(*bits))),
+            _ => None,
+        }
+    }
+}
+
+/// Parameter and result types of a function
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WasmFuncType {
+    /// Parameter types
+    pub params: Vec<WasmValueType>,
+    /// Result types
+    pub results: Vec<WasmValueType>,
+}
+
+impl WasmFuncType {
+    /// Create a function type
+    pub fn new(params: Vec<WasmValueType>, results: Vec<WasmValueType>) -> Self {
+        Self { params, results }
+    }
+
+    fn to_func_type(&self, engine: &Engine) -> FuncType {
+        FuncType::new(
+            engine,
+            self.params.iter().map(|param| param.to_val_type()),
+            self.results.iter().map(|result| result.to_val_type()),
+        )
+    }
+
+    /// Type of a wasmtime function, if all its values are numbers
+    fn from_func_type(func_type: &FuncType) -> Option<Self> {
+        Some(Self {
+            params: func_type.params().map(|param| WasmValueType::from_val_type(&param)).collect::<Option<_>>()?,
+            results: func_type.results().map(|result| WasmValueType::from_val_type(&result)).collect::<Option<_>>()?,
+        })
+    }
+}
+
+/// Kind of an exported entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasmExportKind {
+    Function,
+    Table,
+    Memory,
+    Global,
+}
+
+impl WasmExportKind {
+    fn from_extern_type(extern_type: &ExternType) -> Self {
+        match extern_type {
+            ExternType::Func(_) => WasmExportKind::Function,
+            ExternType::Table(_) => WasmExportKind::Table,
+            ExternType::Memory(_) => WasmExportKind::Memory,
+            ExternType::Global(_) => WasmExportKind::Global,
+        }
+    }
+}
+
+/// Function provided by the embedder, such as a JavaScript function in the import object
+pub type WasmHostFunction = Arc<dyn Fn(&[WasmValue]) -> Result<Vec<WasmValue>> + Send + Sync>;
+
+/// Functions made available to a module, as the import object of `WebAssembly.instantiate()`
+#[derive(Clone, Default)]
+pub struct WasmImports {
+    /// Functions by module and field name
+    functions: HashMap<(String, String), (WasmFuncType, WasmHostFunction)>,
+}
+
+impl WasmImports {
+    /// Create an empty import object
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Provide a function for the import `module.name`
+    pub fn define<F>(&mut self, module: &str, name: &str, func_type: WasmFuncType, function: F) -> &mut Self
+    where
+        F: Fn(&[WasmValue]) -> Result<Vec<WasmValue>> + Send + Sync + 'static,
+    {
+        self.functions.insert((module.to_string(), name.to_string()), (func_type, Arc::new(function)));
+        self
+    }
+}
+
+/// WebAssembly engine, compiling modules for a renderer
+#[derive(Clone)]
+pub struct WasmEngine {
+    /// Compiler and runtime shared by all modules
+    engine: Engine,
+    /// Most pages the linear memory of an instance may have
+    max_memory_pages: u32,
+}
+
+impl std::fmt::Debug for WasmEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmEngine")
+            .field("max_memory_pages", &self.max_memory_pages)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WasmEngine {
+    /// Create an engine whose instances use at most `memory_limit_mb` of linear memory
+    pub fn new(memory_limit_mb: usize) -> Self {
+        let pages = memory_limit_mb.saturating_mul(1024 * 1024) / PAGE_SIZE;
+        Self {
+            engine: Engine::default(),
+            max_memory_pages: pages.min(MAX_PAGES as usize) as u32,
+        }
+    }
+
+    /// Get the most pages the linear memory of an instance may have
+    pub fn max_memory_pages(&self) -> u32 {
+        self.max_memory_pages
+    }
+
+    /// Validate and compile a module
+    pub fn compile_module(&self, wasm_bytes: &[u8]) -> Result<WasmModule> {
+        let module = Module::from_binary(&self.engine, wasm_bytes).map_err(compile_error)?;
+        Ok(WasmModule {
+            module,
+            max_memory_pages: self.max_memory_pages,
+        })
+    }
+
+    /// Check if bytes are a valid module, as `WebAssembly.validate()`
+    pub fn validate(&self, wasm_bytes: &[u8]) -> bool {
+        Module::validate(&self.engine, wasm_bytes).is_ok()
+    }
+}
+
+/// Compiled module, as `WebAssembly.Module`
+///
+/// Clones share the compiled code.
+#[derive(Clone)]
+pub struct WasmModule {
+    /// Compiled module
+    module: Module,
+    /// Memory limit of the engine that compiled the module, in pages
+    max_memory_pages: u32,
+}
+
+impl std::fmt::Debug for WasmModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmModule")
+            .field("imports", &self.imports())
+            .field("exports", &self.exports())
+            .finish()
+    }
+}
+
+impl WasmModule {
+    /// Get the imports as module and field names, as `WebAssembly.Module.imports()`
+    pub fn imports(&self) -> Vec<(String, String)> {
+        self.module.imports().map(|import| (import.module().to_string(), import.name().to_string())).collect()
+    }
+
+    /// Get the export names and kinds, in name order, as `WebAssembly.Module.exports()`
+    pub fn exports(&self) -> Vec<(String, WasmExportKind)> {
+        let mut exports: Vec<_> = self.module.exports()
+            .map(|export| (export.name().to_string(), WasmExportKind::from_extern_type(&export.ty())))
+            .collect();
+        exports.sort_by(|(a, _), (b, _)| a.cmp(b));
+        exports
+    }
+
+    /// Link the module against its imports and run its start function
+    pub fn instantiate(&self, mut imports: WasmImports) -> Result<WasmInstance> {
+        let engine = self.module.engine();
+        let mut store = Store::new(engine, MemoryLimiter {
+            max_memory_bytes: self.max_memory_pages as usize * PAGE_SIZE,
+            initial_memory_denied: false,
+        });
+        store.limiter(|limiter| limiter);
+
+        let mut externs = Vec::new();
+        for import in self.module.imports() {
+            let key = (import.module().to_string(), import.name().to_string());
+            let ExternType::Func(expected) = import.ty() else {
+                return Err(link_error(format!("import {}.{} is not a function", key.0, key.1)));
+            };
+            let (func_type, function) = imports.functions.remove(&key)
+                .ok_or_else(|| link_error(format!("import {}.{} is not defined", key.0, key.1)))?;
+            if WasmFuncType::from_func_type(&expected).as_ref() != Some(&func_type) {
+                return Err(link_error(format!("imported function {}.{} has the wrong type", key.0, key.1)));
+            }
+            externs.push(Extern::Func(host_func(&mut store, &func_type, function)));
+        }
+
+        let instance = match Instance::new(&mut store, &self.module, &externs) {
+            Ok(instance) => instance,
+            Err(_) if store.data().initial_memory_denied => {
+                return Err(Error::MemoryError(format!(
+                    "WebAssembly module needs more memory than the limit of {} pages",
+                    self.max_memory_pages
+                )));
+            }
+            Err(e) => return Err(runtime_error(e)),
+        };
+
+        let memory = instance.exports(&mut store).find_map(|export| export.into_memory());
+        Ok(WasmInstance { store, instance, memory })
+    }
+}
+
+/// Wrap a host function for a store
+fn host_func(store: &mut Store<MemoryLimiter>, func_type: &WasmFuncType, function: WasmHostFunction) -> Func {
+    let ty = func_type.to_func_type(store.engine());
+    let results_type = func_type.results.clone();
+    Func::new(store, ty, move |_caller, params, results| {
+        let args = params.iter().map(|param| WasmValue::from_val(param).expect("host functions only take numbers")).collect::<Vec<_>>();
+        let values = function(&args).map_err(|e| wasmtime::Error::msg(e.to_string()))?;
+        if values.len() != results.len() || values.iter().zip(&results_type).any(|(value, result)| value.value_type() != *result) {
+            return Err(wasmtime::Error::msg(format!("TypeError: host function returned {:?}, expected {:?}", values, results_type)));
+        }
+        for (slot, value) in results.iter_mut().zip(values) {
+            *slot = value.to_val();
+        }
+        Ok(())
+    })
+}
+
+fn compile_error(message: impl std::fmt::Display) -> Error {
+    Error::JsError(format!("CompileError: {:#}", message))
+}
+
+fn link_error(message: impl std::fmt::Display) -> Error {
+    Error::JsError(format!("LinkError: {}", message))
+}
+
+/// Error of running WebAssembly code, a trap or an error thrown by a host function
+fn runtime_error(error: wasmtime::Error) -> Error {
+    match error.downcast_ref::<Trap>() {
+        Some(trap) => Error::JsError(format!("RuntimeError: {}", trap)),
+        None => Error::JsError(error.root_cause().to_string()),
+    }
+}
+
+/// Keeps the linear memory and tables of an instance within the limits of the renderer
+struct MemoryLimiter {
+    max_memory_bytes: usize,
+    /// Whether the memory a module starts with was over the limit
+    initial_memory_denied: bool,
+}
+
+impl ResourceLimiter for MemoryLimiter {
+    fn memory_growing(&mut self, current: usize, desired: usize, _maximum: Option<usize>) -> wasmtime::Result<bool> {
+        let allowed = desired <= self.max_memory_bytes;
+        if !allowed && current == 0 {
+            self.initial_memory_denied = true;
+        }
+        Ok(allowed)
+    }
+
+    fn table_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> wasmtime::Result<bool> {
+        Ok(desired <= MAX_TABLE_SIZE)
+    }
+}
+
+/// Instantiated module, as `WebAssembly.Instance`
+pub struct WasmInstance {
+    /// Store owning the instance and its memory
+    store: Store<MemoryLimiter>,
+    instance: Instance,
+    /// First exported linear memory
+    memory: Option<Memory>,
+}
+
+impl std::fmt::Debug for WasmInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmInstance")
+            .field("memory_pages", &self.memory_pages())
+            .finish_non_exhaustive()
+    }
+}
+
+impl WasmInstance {
+    /// Call an exported function
+    pub fn call(&mut self, export_name: &str, args: &[WasmValue]) -> Result<Vec<WasmValue>> {
+        let function = match self.instance.get_export(&mut self.store, export_name) {
+            Some(Extern::Func(function)) => function,
+            Some(_) => return Err(Error::JsError(format!("TypeError: export {} is not a function", export_name))),
+            None => return Err(Error::NotFound(format!("WebAssembly export {} not found", export_name))),
+        };
+
+        let func_type = function.ty(&self.store);
+        let Some(func_type) = WasmFuncType::from_func_type(&func_type) else {
+            return Err(Error::JsError(format!("TypeError: export {} takes or returns references", export_name)));
+        };
+        let arg_types: Vec<_> = args.iter().map(|arg| arg.value_type()).collect();
+        if arg_types != func_type.params {
+            return Err(Error::JsError(format!(
+                "TypeError: {} takes {:?}, called with {:?}",
+                export_name, func_type.params, args
+            )));
+        }
+
+        let params: Vec<Val> = args.iter().map(|arg| arg.to_val()).collect();
+        let mut results = vec![Val::I32(0); func_type.results.len()];
+        function.call(&mut self.store, &params, &mut results).map_err(runtime_error)?;
+        Ok(results.iter().filter_map(WasmValue::from_val).collect())
+    }
+
+    /// Get the value of an exported global
+    pub fn get_global(&mut self, export_name: &str) -> Option<WasmValue> {
+        let global = self.instance.get_global(&mut self.store, export_name)?;
+        WasmValue::from_val(&global.get(&mut self.store))
+    }
+
+    /// Get the exported linear memory, empty if the module exports none
+    pub fn memory(&self) -> &[u8] {
+        self.memory.map_or(&[], |memory| memory.data(&self.store))
+    }
+
+    /// Get the exported linear memory for writing, as the buffer of `WebAssembly.Memory`
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        match self.memory {
+            Some(memory) => memory.data_mut(&mut self.store),
+            None => &mut [],
+        }
+    }
+
+    /// Get the size of the exported linear memory in pages
+    pub fn memory_pages(&self) -> u32 {
+        self.memory.map_or(0, |memory| memory.size(&self.store) as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(id: u8, contents: &[u8]) -> Vec<u8> {
+        assert!(contents.len() < 0x80);
+        let mut section = vec![id, contents.len() as u8];
+        section.extend_from_slice(contents);
+        section
+    }
+
+    fn module(sections: &[Vec<u8>]) -> Vec<u8> {
+        let mut module = b"\0asm\x01\0\0\0".to_vec();
+        for section in sections {
+            module.extend_from_slice(section);
+        }
+        module
+    }
+
+    /// Module with one function of type `func_type`, exported as `name`
+    fn single_function(func_type: &[u8], name: &str, body: &[u8]) -> Vec<u8> {
+        let mut types = vec![0x01, 0x60];
+        types.extend_from_slice(func_type);
+        let mut exports = vec![0x01, name.len() as u8];
+        exports.extend_from_slice(name.as_bytes());
+        exports.extend_from_slice(&[0x00, 0x00]);
+        let mut code = vec![0x01, body.len() as u8];
+        code.extend_from_slice(body);
+
+        module(&[section(1, &types), section(3, &[0x01, 0x00]), section(7, &exports), section(10, &code)])
+    }
+
+    #[test]
+    fn test_add_two_integers() {
+        let add = single_function(&[0x02, 0x7f, 0x7f, 0x01, 0x7f], "add", &[0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b]);
+        let engine = WasmEngine::new(16);
+        let module = engine.compile_module(&add).unwrap();
+        let mut instance = module.instantiate(WasmImports::new()).unwrap();
+
+        assert_eq!(instance.call("add", &[WasmValue::I32(2), WasmValue::I32(3)]).unwrap(), [WasmValue::I32(5)]);
+        assert_eq!(instance.call("add", &[WasmValue::I32(i32::MAX), WasmValue::I32(1)]).unwrap(), [WasmValue::I32(i32::MIN)]);
+        assert!(instance.call("add", &[WasmValue::I64(2), WasmValue::I32(3)]).is_err());
+        assert!(instance.call("sub", &[]).is_err());
+    }
+
+    #[test]
+    fn test_loops_and_recursion() {
+        // Sums n down to 1 in a loop, branching out of the enclosing block when n reaches zero
+        let sum = single_function(&[0x01, 0x7f, 0x01, 0x7f], "sum", &[
+            0x01, 0x01, 0x7f,
+            0x02, 0x40, 0x03, 0x40,
+            0x20, 0x00, 0x45, 0x0d, 0x01,
+            0x20, 0x01, 0x20, 0x00, 0x6a, 0x21, 0x01,
+            0x20, 0x00, 0x41, 0x01, 0x6b, 0x21, 0x00,
+            0x0c, 0x00, 0x0b, 0x0b,
+            0x20, 0x01, 0x0b,
+        ]);
+        // Recursive i64 factorial
+        let factorial = single_function(&[0x01, 0x7e, 0x01, 0x7e], "factorial", &[
+            0x00, 0x20, 0x00, 0x50,
+            0x04, 0x7e, 0x42, 0x01,
+            0x05, 0x20, 0x00, 0x20, 0x00, 0x42, 0x01, 0x7d, 0x10, 0x00, 0x7e,
+            0x0b, 0x0b,
+        ]);
+
+        let engine = WasmEngine::new(16);
+        let mut instance = engine.compile_module(&sum).unwrap().instantiate(WasmImports::new()).unwrap();
+        assert_eq!(instance.call("sum", &[WasmValue::I32(100)]).unwrap(), [WasmValue::I32(5050)]);
+
+        let mut instance = engine.compile_module(&factorial).unwrap().instantiate(WasmImports::new()).unwrap();
+        assert_eq!(instance.call("factorial", &[WasmValue::I64(20)]).unwrap(), [WasmValue::I64(2432902008176640000)]);
+    }
+
+    #[test]
+    fn test_imported_function() {
+        let run = module(&[
+            section(1, &[0x01, 0x60, 0x01, 0x7f, 0x01, 0x7f]),
+            section(2, &[0x01, 0x03, b'e', b'n', b'v', 0x06, b'd', b'o', b'u', b'b', b'l', b'e', 0x00, 0x00]),
+            section(3, &[0x01, 0x00]),
+            section(7, &[0x01, 0x03, b'r', b'u', b'n', 0x00, 0x01]),
+            section(10, &[0x01, 0x09, 0x00, 0x20, 0x00, 0x10, 0x00, 0x41, 0x01, 0x6a, 0x0b]),
+        ]);
+        let engine = WasmEngine::new(16);
+        let module = engine.compile_module(&run).unwrap();
+        assert_eq!(module.imports(), [("env".to_string(), "double".to_string())]);
+
+        let error = module.instantiate(WasmImports::new()).unwrap_err();
+        assert!(error.to_string().contains("LinkError"));
+
+        let double_type = WasmFuncType::new(vec![WasmValueType::I32], vec![WasmValueType::I32]);
+        let mut imports = WasmImports::new();
+        imports.define("env", "double", double_type, |args| Ok(vec![WasmValue::I32(args[0].as_i32().unwrap() * 2)]));
+        let mut instance = module.instantiate(imports).unwrap();
+        assert_eq!(instance.call("run", &[WasmValue::I32(20)]).unwrap(), [WasmValue::I32(41)]);
+    }
+
+    #[test]
+    fn test_memory_is_bounded_by_the_limit() {
+        let memory_module = |min_pages: u8| module(&[
+            section(1, &[0x01, 0x60, 0x01, 0x7f, 0x01, 0x7f]),
+            section(3, &[0x01, 0x00]),
+            section(5, &[0x01, 0x00, min_pages]),
+            section(7, &[0x02, 0x04, b'g', b'r', b'o', b'w', 0x00, 0x00, 0x03, b'm', b'e', b'm', 0x02, 0x00]),
+            section(10, &[0x01, 0x06, 0x00, 0x20, 0x00, 0x40, 0x00, 0x0b]),
+            section(11, &[0x01, 0x00, 0x41, 0x00, 0x0b, 0x02, b'h', b'i']),
+        ]);
+
+        // One megabyte holds 16 pages
+        let engine = WasmEngine::new(1);
+        assert_eq!(engine.max_memory_pages(), 16);
+
+        let mut instance = engine.compile_module(&memory_module(2)).unwrap().instantiate(WasmImports::new()).unwrap();
+        assert_eq!(&instance.memory()[..2], b"hi");
+        assert_eq!(instance.call("grow", &[WasmValue::I32(14)]).unwrap(), [WasmValue::I32(2)]);
+        assert_eq!(instance.call("grow", &[WasmValue::I32(1)]).unwrap(), [WasmValue::I32(-1)]);
+        assert_eq!(instance.memory_pages(), 16);
+
+        let module = engine.compile_module(&memory_module(17)).unwrap();
+        assert!(matches!(module.instantiate(WasmImports::new()), Err(Error::MemoryError(_))));
+    }
+
+    #[test]
+    fn test_traps() {
+        let div = single_function(&[0x02, 0x7f, 0x7f, 0x01, 0x7f], "div", &[0x00, 0x20, 0x00, 0x20, 0x01, 0x6d, 0x0b]);
+        let engine = WasmEngine::new(16);
+        let mut instance = engine.compile_module(&div).unwrap().instantiate(WasmImports::new()).unwrap();
+
+        assert_eq!(instance.call("div", &[WasmValue::I32(-7), WasmValue::I32(2)]).unwrap(), [WasmValue::I32(-3)]);
+        let error = instance.call("div", &[WasmValue::I32(1), WasmValue::I32(0)]).unwrap_err();
+        assert!(error.to_string().contains("integer divide by zero"));
+        let error = instance.call("div", &[WasmValue::I32(i32::MIN), WasmValue::I32(-1)]).unwrap_err();
+        assert!(error.to_string().contains("integer overflow"));
+    }
+
+    #[test]
+    fn test_invalid_modules_are_rejected() {
+        let engine = WasmEngine::new(16);
+        assert!(!engine.validate(b"\0asm\x02\0\0\0"));
+        assert!(!engine.validate(b"not wasm"));
+
+        // Returns an i64 from a function declared to return an i32
+        let mismatch = single_function(&[0x00, 0x01, 0x7f], "f", &[0x00, 0x42, 0x01, 0x0b]);
+        let error = engine.compile_module(&mismatch).unwrap_err();
+        assert!(error.to_string().contains("CompileError"));
+
+        // Branches to a label that does not exist
+        let bad_label = single_function(&[0x00, 0x00], "f", &[0x00, 0x0c, 0x01, 0x0b]);
+        assert!(!engine.validate(&bad_label));
+        assert!(engine.validate(&single_function(&[0x00, 0x00], "f", &[0x00, 0x0c, 0x00, 0x0b])));
+    }
+}
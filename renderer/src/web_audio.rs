@@ -0,0 +1,994 @@
+//! Web Audio
+//!
+//! An `AudioContext` owns a graph of audio nodes. Source nodes (oscillators
+//! and buffer sources) connect to the destination node, which mixes them. A
+//! blocking task renders the graph in quanta of `RENDER_QUANTUM_FRAMES`
+//! frames, paced to the sample rate, and writes the interleaved samples to
+//! an `AudioOutput`: the OS audio device with the `audio-output` feature, or
+//! nothing without it.
+//!
+//! Node handles share the graph of their context, so changing an oscillator's
+//! frequency or starting a buffer source takes effect from the next quantum.
+//!
+//! A context connected to the browser reports its tab as playing while its
+//! sources make sound, and scales its output by the gain the browser sets,
+//! which is how muting a tab silences it.
+
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use common::error::{Error, Result};
+use common::ipc::{AudioControl, AudioEvent, Channel, IpcMessage};
+use common::TabId;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Frames rendered at a time, as in the Web Audio specification
+pub const RENDER_QUANTUM_FRAMES: usize = 128;
+
+/// Channels of the destination node
+pub const DESTINATION_CHANNELS: usize = 2;
+
+/// How far rendering may run ahead of the wall clock
+const MAX_RENDER_AHEAD: Duration = Duration::from_millis(20);
+
+/// How often a suspended context checks whether it was resumed
+const SUSPENDED_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Silence after which a context reports its tab stopped playing, so short
+/// pauses between sounds do not flicker the tab's speaker icon
+const STOPPED_AFTER_SILENCE: Duration = Duration::from_millis(200);
+
+/// ID of the next audio node, unique across contexts
+static NEXT_NODE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Identifier of an audio node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AudioNodeId(u64);
+
+impl AudioNodeId {
+    fn next() -> Self {
+        Self(NEXT_NODE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// State of an audio context
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioContextState {
+    /// Not rendering, the clock is stopped
+    Suspended,
+    /// Rendering to the output
+    Running,
+    /// Released its output, and cannot be resumed
+    Closed,
+}
+
+/// Shape of an oscillator's waveform
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OscillatorType {
+    #[default]
+    Sine,
+    Square,
+    Sawtooth,
+    Triangle,
+}
+
+impl OscillatorType {
+    /// Sample of the waveform at a phase in [0, 1)
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            OscillatorType::Sine => (TAU * phase).sin(),
+            OscillatorType::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            OscillatorType::Sawtooth => if phase < 0.5 { 2.0 * phase } else { 2.0 * phase - 2.0 },
+            OscillatorType::Triangle => {
+                if phase < 0.25 {
+                    4.0 * phase
+                } else if phase < 0.75 {
+                    2.0 - 4.0 * phase
+                } else {
+                    4.0 * phase - 4.0
+                }
+            }
+        }
+    }
+}
+
+/// Decoded audio, one vector of samples in [-1, 1] per channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioBuffer {
+    /// Sample rate of the audio, in Hz
+    pub sample_rate: f32,
+    /// Samples of each channel
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl AudioBuffer {
+    /// Decode interleaved 16-bit PCM
+    pub fn from_pcm_i16(samples: &[i16], channel_count: usize, sample_rate: f32) -> Result<Self> {
+        if channel_count == 0 || !samples.len().is_multiple_of(channel_count) {
+            return Err(Error::ParseError(format!(
+                "{} PCM samples do not split into {} channels",
+                samples.len(),
+                channel_count
+            )));
+        }
+        if sample_rate <= 0.0 {
+            return Err(Error::ParseError(format!("Invalid PCM sample rate {}", sample_rate)));
+        }
+
+        let mut channels = vec![Vec::with_capacity(samples.len() / channel_count); channel_count];
+        for frame in samples.chunks_exact(channel_count) {
+            for (channel, &sample) in channels.iter_mut().zip(frame) {
+                channel.push(sample as f32 / 32768.0);
+            }
+        }
+
+        Ok(Self { sample_rate, channels })
+    }
+
+    /// Number of frames
+    pub fn length(&self) -> usize {
+        self.channels.first().map_or(0, Vec::len)
+    }
+
+    /// Duration of the audio, in seconds
+    pub fn duration(&self) -> f64 {
+        self.length() as f64 / self.sample_rate as f64
+    }
+}
+
+/// Sink for rendered audio
+pub trait AudioOutput {
+    /// Write interleaved samples, blocking while the output is full
+    fn write(&mut self, samples: &[f32]) -> Result<()>;
+}
+
+/// Output discarding the audio, used when no device is available
+pub struct NullOutput;
+
+impl AudioOutput for NullOutput {
+    fn write(&mut self, _samples: &[f32]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Node of an audio graph
+pub trait AudioNode {
+    /// Get the ID of the node
+    fn id(&self) -> AudioNodeId;
+
+    /// Number of inputs other nodes can connect to
+    fn number_of_inputs(&self) -> usize;
+
+    /// Connect the output of this node to an input of a node of the same context
+    fn connect(&self, destination: &dyn AudioNode) -> Result<()>;
+
+    /// Remove all connections from the output of this node
+    fn disconnect(&self);
+}
+
+/// Audio context owning a graph of nodes and rendering it to an output
+pub struct AudioContext {
+    /// Sample rate of the graph and the output, in Hz
+    pub sample_rate: f32,
+    /// Node mixing everything connected to it into the output
+    pub destination: AudioDestinationNode,
+    /// Graph shared with the node handles and the render task
+    graph: SharedGraph,
+    /// Task rendering the graph
+    render_task: Option<JoinHandle<()>>,
+    /// Task applying the browser's gain changes
+    control_task: Option<JoinHandle<()>>,
+}
+
+impl AudioContext {
+    /// Create a running context playing to the default audio device
+    ///
+    /// Without the `audio-output` feature, or if the device cannot be opened,
+    /// the audio is rendered and discarded.
+    pub fn new(sample_rate: f32) -> Result<Self> {
+        Self::start(sample_rate, move || match open_device_output(sample_rate, DESTINATION_CHANNELS) {
+            Ok(output) => output,
+            Err(e) => {
+                warn!("Playing audio without an output: {}", e);
+                Box::new(NullOutput)
+            }
+        })
+    }
+
+    /// Create a running context playing to an output
+    pub fn with_output(sample_rate: f32, output: Box<dyn AudioOutput + Send>) -> Result<Self> {
+        Self::start(sample_rate, move || output)
+    }
+
+    /// Start the render task, creating the output on its thread
+    fn start<F>(sample_rate: f32, open_output: F) -> Result<Self>
+    where
+        F: FnOnce() -> Box<dyn AudioOutput> + Send + 'static,
+    {
+        if !sample_rate.is_finite() || sample_rate <= 0.0 {
+            return Err(Error::ConfigError(format!("Invalid sample rate {}", sample_rate)));
+        }
+
+        let destination_id = AudioNodeId::next();
+        let graph = Arc::new(Mutex::new(AudioGraph {
+            sample_rate,
+            state: AudioContextState::Running,
+            current_frame: 0,
+            destination: destination_id,
+            sources: HashMap::new(),
+            connections: Vec::new(),
+            gain: 1.0,
+            silent_frames: u64::MAX,
+            browser: None,
+        }));
+
+        let render_graph = graph.clone();
+        let render_task = tokio::task::spawn_blocking(move || render_loop(render_graph, open_output()));
+        info!("Started audio context at {} Hz", sample_rate);
+
+        Ok(Self {
+            sample_rate,
+            destination: AudioDestinationNode { handle: NodeHandle { id: destination_id, graph: graph.clone() } },
+            graph,
+            render_task: Some(render_task),
+            control_task: None,
+        })
+    }
+
+    /// Play the audio as a tab's, over a channel to the browser
+    ///
+    /// The browser is sent `AudioEvent::Playing` and `AudioEvent::Stopped`
+    /// as the sources start and stop making sound, and its
+    /// `AudioControl::SetGain` for the tab scales the output.
+    pub fn connect_browser(&mut self, tab_id: TabId, channel: Channel<IpcMessage>) {
+        lock(&self.graph).browser = Some((tab_id, channel.clone()));
+
+        let graph = self.graph.clone();
+        if let Some(control_task) = self.control_task.replace(tokio::spawn(async move {
+            loop {
+                match channel.recv_async().await {
+                    Ok(IpcMessage::AudioControl(AudioControl::SetGain { tab_id: target, gain })) if target == tab_id => {
+                        debug!("Audio gain of tab {} is now {}", tab_id, gain);
+                        lock(&graph).gain = gain.max(0.0);
+                    }
+                    Ok(message) => debug!("Ignoring {:?} sent to the audio of tab {}", std::mem::discriminant(&message), tab_id),
+                    Err(e) => {
+                        debug!("Audio channel of tab {} closed: {}", tab_id, e);
+                        return;
+                    }
+                }
+            }
+        })) {
+            control_task.abort();
+        }
+    }
+
+    /// Get the state of the context
+    pub fn state(&self) -> AudioContextState {
+        lock(&self.graph).state
+    }
+
+    /// Time of the next frame to render, in seconds since the context started
+    pub fn current_time(&self) -> f64 {
+        let graph = lock(&self.graph);
+        graph.current_frame as f64 / graph.sample_rate as f64
+    }
+
+    /// Create an oscillator playing a 440 Hz sine wave once started
+    pub fn create_oscillator(&self) -> OscillatorNode {
+        let handle = self.add_source(Source::Oscillator(Oscillator {
+            oscillator_type: OscillatorType::Sine,
+            frequency: 440.0,
+            phase: 0.0,
+            schedule: Schedule::default(),
+        }));
+        OscillatorNode { handle }
+    }
+
+    /// Create a source playing an audio buffer once started
+    pub fn create_buffer_source(&self) -> AudioBufferSourceNode {
+        let handle = self.add_source(Source::Buffer(BufferSource {
+            buffer: None,
+            looping: false,
+            position: 0.0,
+            schedule: Schedule::default(),
+        }));
+        AudioBufferSourceNode { handle }
+    }
+
+    /// Resume rendering
+    pub fn resume(&self) -> Result<()> {
+        self.set_state(AudioContextState::Running)
+    }
+
+    /// Stop rendering and the clock until resumed
+    pub fn suspend(&self) -> Result<()> {
+        self.set_state(AudioContextState::Suspended)
+    }
+
+    /// Stop rendering and release the output
+    pub async fn close(&mut self) -> Result<()> {
+        lock(&self.graph).state = AudioContextState::Closed;
+        if let Some(control_task) = self.control_task.take() {
+            control_task.abort();
+        }
+        if let Some(render_task) = self.render_task.take() {
+            render_task.await.map_err(|e| Error::InvalidState(format!("Audio render task failed: {}", e)))?;
+        }
+        info!("Closed audio context");
+        Ok(())
+    }
+
+    fn set_state(&self, state: AudioContextState) -> Result<()> {
+        let mut graph = lock(&self.graph);
+        if graph.state == AudioContextState::Closed {
+            return Err(Error::InvalidState("Audio context is closed".to_string()));
+        }
+        graph.state = state;
+        Ok(())
+    }
+
+    fn add_source(&self, source: Source) -> NodeHandle {
+        let id = AudioNodeId::next();
+        lock(&self.graph).sources.insert(id, source);
+        NodeHandle { id, graph: self.graph.clone() }
+    }
+}
+
+impl Drop for AudioContext {
+    fn drop(&mut self) {
+        // The render task exits once it sees the context closed
+        lock(&self.graph).state = AudioContextState::Closed;
+        if let Some(control_task) = &self.control_task {
+            control_task.abort();
+        }
+    }
+}
+
+/// Node mixing its inputs into the output of the context
+pub struct AudioDestinationNode {
+    handle: NodeHandle,
+}
+
+impl AudioDestinationNode {
+    /// Number of channels of the output
+    pub fn channel_count(&self) -> usize {
+        DESTINATION_CHANNELS
+    }
+}
+
+/// Node generating a periodic waveform
+pub struct OscillatorNode {
+    handle: NodeHandle,
+}
+
+impl OscillatorNode {
+    /// Set the waveform
+    pub fn set_type(&self, oscillator_type: OscillatorType) {
+        self.handle.with_source(|source| {
+            if let Source::Oscillator(oscillator) = source {
+                oscillator.oscillator_type = oscillator_type;
+            }
+        });
+    }
+
+    /// Set the frequency, in Hz
+    pub fn set_frequency(&self, frequency: f32) {
+        self.handle.with_source(|source| {
+            if let Source::Oscillator(oscillator) = source {
+                oscillator.frequency = frequency;
+            }
+        });
+    }
+
+    /// Start playing at a context time, in seconds
+    pub fn start(&self, when: f64) -> Result<()> {
+        self.handle.start(when)
+    }
+
+    /// Stop playing at a context time, in seconds
+    pub fn stop(&self, when: f64) -> Result<()> {
+        self.handle.stop(when)
+    }
+}
+
+/// Node playing an audio buffer
+pub struct AudioBufferSourceNode {
+    handle: NodeHandle,
+}
+
+impl AudioBufferSourceNode {
+    /// Set the audio to play, resampled to the context's sample rate
+    pub fn set_buffer(&self, buffer: AudioBuffer) {
+        self.handle.with_source(|source| {
+            if let Source::Buffer(buffer_source) = source {
+                buffer_source.buffer = Some(buffer);
+            }
+        });
+    }
+
+    /// Set whether playback restarts from the beginning at the end of the buffer
+    pub fn set_loop(&self, looping: bool) {
+        self.handle.with_source(|source| {
+            if let Source::Buffer(buffer_source) = source {
+                buffer_source.looping = looping;
+            }
+        });
+    }
+
+    /// Start playing at a context time, in seconds
+    pub fn start(&self, when: f64) -> Result<()> {
+        self.handle.start(when)
+    }
+
+    /// Stop playing at a context time, in seconds
+    pub fn stop(&self, when: f64) -> Result<()> {
+        self.handle.stop(when)
+    }
+}
+
+impl AudioNode for AudioDestinationNode {
+    fn id(&self) -> AudioNodeId {
+        self.handle.id
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn connect(&self, _destination: &dyn AudioNode) -> Result<()> {
+        Err(Error::InvalidState("The destination node has no output".to_string()))
+    }
+
+    fn disconnect(&self) {}
+}
+
+impl AudioNode for OscillatorNode {
+    fn id(&self) -> AudioNodeId {
+        self.handle.id
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn connect(&self, destination: &dyn AudioNode) -> Result<()> {
+        self.handle.connect(destination)
+    }
+
+    fn disconnect(&self) {
+        self.handle.disconnect();
+    }
+}
+
+impl AudioNode for AudioBufferSourceNode {
+    fn id(&self) -> AudioNodeId {
+        self.handle.id
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn connect(&self, destination: &dyn AudioNode) -> Result<()> {
+        self.handle.connect(destination)
+    }
+
+    fn disconnect(&self) {
+        self.handle.disconnect();
+    }
+}
+
+type SharedGraph = Arc<Mutex<AudioGraph>>;
+
+/// Lock a graph, which stays consistent even if a holder panicked
+fn lock(graph: &SharedGraph) -> MutexGuard<'_, AudioGraph> {
+    graph.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Handle of a node in the graph of a context
+struct NodeHandle {
+    id: AudioNodeId,
+    graph: SharedGraph,
+}
+
+impl NodeHandle {
+    fn connect(&self, destination: &dyn AudioNode) -> Result<()> {
+        let mut graph = lock(&self.graph);
+        let destination_id = destination.id();
+        if destination_id != graph.destination && !graph.sources.contains_key(&destination_id) {
+            return Err(Error::InvalidState("Audio nodes belong to different contexts".to_string()));
+        }
+        if destination.number_of_inputs() == 0 {
+            return Err(Error::InvalidState("Audio node has no inputs to connect to".to_string()));
+        }
+
+        if !graph.connections.contains(&(self.id, destination_id)) {
+            graph.connections.push((self.id, destination_id));
+        }
+        Ok(())
+    }
+
+    fn disconnect(&self) {
+        lock(&self.graph).connections.retain(|&(source, _)| source != self.id);
+    }
+
+    fn with_source(&self, f: impl FnOnce(&mut Source)) {
+        if let Some(source) = lock(&self.graph).sources.get_mut(&self.id) {
+            f(source);
+        }
+    }
+
+    fn start(&self, when: f64) -> Result<()> {
+        let mut graph = lock(&self.graph);
+        let frame = graph.frame_at(when)?;
+        let schedule = graph.sources.get_mut(&self.id).map(Source::schedule_mut)
+            .ok_or_else(|| Error::NotFound("Audio node not found".to_string()))?;
+        if schedule.start.is_some() {
+            return Err(Error::InvalidState("Audio source was already started".to_string()));
+        }
+        schedule.start = Some(frame);
+        Ok(())
+    }
+
+    fn stop(&self, when: f64) -> Result<()> {
+        let mut graph = lock(&self.graph);
+        let frame = graph.frame_at(when)?;
+        let schedule = graph.sources.get_mut(&self.id).map(Source::schedule_mut)
+            .ok_or_else(|| Error::NotFound("Audio node not found".to_string()))?;
+        if schedule.start.is_none() {
+            return Err(Error::InvalidState("Audio source was not started".to_string()));
+        }
+        schedule.stop = Some(frame);
+        Ok(())
+    }
+}
+
+/// Frames a source plays between
+#[derive(Debug, Clone, Copy, Default)]
+struct Schedule {
+    start: Option<u64>,
+    stop: Option<u64>,
+}
+
+impl Schedule {
+    fn is_playing(&self, frame: u64) -> bool {
+        self.start.is_some_and(|start| frame >= start) && self.stop.is_none_or(|stop| frame < stop)
+    }
+}
+
+struct Oscillator {
+    oscillator_type: OscillatorType,
+    frequency: f32,
+    /// Phase of the next sample, in [0, 1)
+    phase: f32,
+    schedule: Schedule,
+}
+
+struct BufferSource {
+    buffer: Option<AudioBuffer>,
+    looping: bool,
+    /// Position of the next sample in the buffer, in buffer frames
+    position: f64,
+    schedule: Schedule,
+}
+
+enum Source {
+    Oscillator(Oscillator),
+    Buffer(BufferSource),
+}
+
+impl Source {
+    fn schedule_mut(&mut self) -> &mut Schedule {
+        match self {
+            Source::Oscillator(oscillator) => &mut oscillator.schedule,
+            Source::Buffer(buffer_source) => &mut buffer_source.schedule,
+        }
+    }
+
+    /// Add a quantum of the source, starting at a frame, into interleaved output
+    fn render_into(&mut self, first_frame: u64, sample_rate: f32, output: &mut [f32]) {
+        for (index, frame) in output.chunks_exact_mut(DESTINATION_CHANNELS).enumerate() {
+            let frame_number = first_frame + index as u64;
+            match self {
+                Source::Oscillator(oscillator) => {
+                    if !oscillator.schedule.is_playing(frame_number) {
+                        continue;
+                    }
+                    let sample = oscillator.oscillator_type.sample(oscillator.phase);
+                    frame.iter_mut().for_each(|out| *out += sample);
+                    oscillator.phase = (oscillator.phase + oscillator.frequency / sample_rate).rem_euclid(1.0);
+                }
+                Source::Buffer(buffer_source) => {
+                    if !buffer_source.schedule.is_playing(frame_number) {
+                        continue;
+                    }
+                    let Some(buffer) = &buffer_source.buffer else {
+                        continue;
+                    };
+                    let length = buffer.length();
+                    if buffer_source.looping && length > 0 {
+                        buffer_source.position %= length as f64;
+                    }
+                    if buffer_source.position >= length as f64 {
+                        continue;
+                    }
+
+                    // Linear interpolation between the buffer frames around the position
+                    let index = buffer_source.position as usize;
+                    let fraction = (buffer_source.position - index as f64) as f32;
+                    let next = if index + 1 < length { index + 1 } else if buffer_source.looping { 0 } else { index };
+                    for (channel, out) in frame.iter_mut().enumerate() {
+                        // Mono is played on every channel, other layouts channel by channel
+                        let samples = match buffer.channels.len() {
+                            1 => &buffer.channels[0],
+                            _ => match buffer.channels.get(channel) {
+                                Some(samples) => samples,
+                                None => continue,
+                            },
+                        };
+                        *out += samples[index] + (samples[next] - samples[index]) * fraction;
+                    }
+                    buffer_source.position += (buffer.sample_rate / sample_rate) as f64;
+                }
+            }
+        }
+    }
+}
+
+/// Nodes and connections of a context
+struct AudioGraph {
+    sample_rate: f32,
+    state: AudioContextState,
+    /// Frame the next quantum starts at
+    current_frame: u64,
+    destination: AudioNodeId,
+    sources: HashMap<AudioNodeId, Source>,
+    /// Connections from the output of a node to the input of another
+    connections: Vec<(AudioNodeId, AudioNodeId)>,
+    /// Scale of the output, set by the browser
+    gain: f32,
+    /// Frames rendered since the sources last made sound, before the gain
+    silent_frames: u64,
+    /// Tab the audio is played for, and the channel to the browser
+    browser: Option<(TabId, Channel<IpcMessage>)>,
+}
+
+impl AudioGraph {
+    /// Frame of a context time, times in the past meaning now
+    fn frame_at(&self, when: f64) -> Result<u64> {
+        if !when.is_finite() || when < 0.0 {
+            return Err(Error::InvalidState(format!("Invalid audio time {}", when)));
+        }
+        Ok(((when * self.sample_rate as f64).round() as u64).max(self.current_frame))
+    }
+
+    /// Render the next quantum of the destination, interleaved
+    fn render_quantum(&mut self) -> Vec<f32> {
+        let mut output = vec![0.0; RENDER_QUANTUM_FRAMES * DESTINATION_CHANNELS];
+        for &(source, destination) in &self.connections {
+            if destination != self.destination {
+                continue;
+            }
+            if let Some(source) = self.sources.get_mut(&source) {
+                source.render_into(self.current_frame, self.sample_rate, &mut output);
+            }
+        }
+
+        // Muted sources still count as playing
+        if output.iter().any(|&sample| sample != 0.0) {
+            self.silent_frames = 0;
+        } else {
+            self.silent_frames = self.silent_frames.saturating_add(RENDER_QUANTUM_FRAMES as u64);
+        }
+        for sample in &mut output {
+            *sample = (*sample * self.gain).clamp(-1.0, 1.0);
+        }
+        self.current_frame += RENDER_QUANTUM_FRAMES as u64;
+        output
+    }
+}
+
+/// Render the graph while the context is open, paced to its sample rate
+fn render_loop(graph: SharedGraph, mut output: Box<dyn AudioOutput>) {
+    let sample_rate = lock(&graph).sample_rate;
+    let quantum_duration = Duration::from_secs_f64(RENDER_QUANTUM_FRAMES as f64 / sample_rate as f64);
+    let stopped_after_frames = (STOPPED_AFTER_SILENCE.as_secs_f64() * sample_rate as f64) as u64;
+    // Wall clock time the next quantum is due at
+    let mut next_quantum = Instant::now();
+    // Whether the browser was told the tab is playing
+    let mut playing = false;
+
+    loop {
+        let (state, quantum, sounding, browser) = {
+            let mut graph = lock(&graph);
+            let quantum = (graph.state == AudioContextState::Running).then(|| graph.render_quantum());
+            let sounding = quantum.is_some() && graph.silent_frames < stopped_after_frames;
+            (graph.state, quantum, sounding, graph.browser.clone())
+        };
+
+        if sounding != playing {
+            playing = sounding;
+            if let Some((tab_id, channel)) = browser {
+                let event = if playing { AudioEvent::Playing { tab_id } } else { AudioEvent::Stopped { tab_id } };
+                if let Err(e) = channel.send(IpcMessage::AudioEvent(event)) {
+                    warn!("Failed to report the audio of tab {}: {}", tab_id, e);
+                }
+            }
+        }
+
+        let Some(quantum) = quantum else {
+            if state == AudioContextState::Closed {
+                break;
+            }
+            std::thread::sleep(SUSPENDED_POLL_INTERVAL);
+            next_quantum = Instant::now();
+            continue;
+        };
+
+        if let Err(e) = output.write(&quantum) {
+            warn!("Stopping audio output: {}", e);
+            output = Box::new(NullOutput);
+        }
+
+        next_quantum += quantum_duration;
+        let now = Instant::now();
+        if next_quantum > now + MAX_RENDER_AHEAD {
+            std::thread::sleep(next_quantum - now - MAX_RENDER_AHEAD);
+        } else if now > next_quantum + MAX_RENDER_AHEAD {
+            // Fell behind, for example while the output was blocked, so skip ahead
+            next_quantum = now;
+        }
+    }
+
+    debug!("Audio render task stopped");
+}
+
+/// Open the default audio device
+#[cfg(feature = "audio-output")]
+fn open_device_output(sample_rate: f32, channels: usize) -> Result<Box<dyn AudioOutput>> {
+    Ok(Box::new(device::DeviceOutput::open(sample_rate, channels)?))
+}
+
+/// Open the default audio device, which needs the `audio-output` feature
+#[cfg(not(feature = "audio-output"))]
+fn open_device_output(_sample_rate: f32, _channels: usize) -> Result<Box<dyn AudioOutput>> {
+    Err(Error::NotImplemented("Audio output requires the audio-output feature".to_string()))
+}
+
+#[cfg(feature = "audio-output")]
+mod device {
+    //! Audio output to the OS through cpal
+
+    use std::collections::VecDeque;
+    use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use tracing::warn;
+
+    use super::{AudioOutput, RENDER_QUANTUM_FRAMES};
+    use common::error::{Error, Result};
+
+    /// Quanta queued for the device before writes block
+    const QUEUED_QUANTA: usize = 8;
+
+    /// Output stream of the default device
+    ///
+    /// The stream callback drains a bounded queue of quanta, so writes block
+    /// at the pace the device plays them.
+    pub(super) struct DeviceOutput {
+        sender: SyncSender<Vec<f32>>,
+        _stream: cpal::Stream,
+    }
+
+    impl DeviceOutput {
+        pub(super) fn open(sample_rate: f32, channels: usize) -> Result<Self> {
+            let device = cpal::default_host().default_output_device()
+                .ok_or_else(|| Error::NotFound("No audio output device".to_string()))?;
+            let config = cpal::StreamConfig {
+                channels: channels as cpal::ChannelCount,
+                sample_rate: cpal::SampleRate(sample_rate as u32),
+                buffer_size: cpal::BufferSize::Default,
+            };
+
+            let (sender, receiver) = sync_channel(QUEUED_QUANTA);
+            let mut queue = DeviceQueue { receiver, pending: VecDeque::with_capacity(RENDER_QUANTUM_FRAMES * channels) };
+            let stream = device.build_output_stream(
+                &config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| queue.fill(data),
+                |e| warn!("Audio output error: {}", e),
+                None,
+            ).map_err(|e| Error::PlatformError(format!("Failed to open audio output: {}", e)))?;
+            stream.play().map_err(|e| Error::PlatformError(format!("Failed to start audio output: {}", e)))?;
+
+            Ok(Self { sender, _stream: stream })
+        }
+    }
+
+    impl AudioOutput for DeviceOutput {
+        fn write(&mut self, samples: &[f32]) -> Result<()> {
+            self.sender.send(samples.to_vec())
+                .map_err(|_| Error::PlatformError("Audio output stream stopped".to_string()))
+        }
+    }
+
+    /// Samples waiting for the device, filled from rendered quanta
+    struct DeviceQueue {
+        receiver: Receiver<Vec<f32>>,
+        pending: VecDeque<f32>,
+    }
+
+    impl DeviceQueue {
+        /// Fill a device buffer, with silence where rendering fell behind
+        fn fill(&mut self, data: &mut [f32]) {
+            for sample in data {
+                if self.pending.is_empty() {
+                    if let Ok(quantum) = self.receiver.try_recv() {
+                        self.pending.extend(quantum);
+                    }
+                }
+                *sample = self.pending.pop_front().unwrap_or(0.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Quanta of sound captured by `CaptureOutput`
+    const CAPTURED_QUANTA: usize = 40;
+
+    /// Output keeping the first `CAPTURED_QUANTA` quanta written to it once it hears sound
+    ///
+    /// Quanta rendered before the sources were started are silent and skipped.
+    struct CaptureOutput {
+        samples: Vec<f32>,
+        captured: Option<tokio::sync::oneshot::Sender<Vec<f32>>>,
+    }
+
+    impl AudioOutput for CaptureOutput {
+        fn write(&mut self, samples: &[f32]) -> Result<()> {
+            if self.captured.is_none() || (self.samples.is_empty() && samples.iter().all(|&sample| sample == 0.0)) {
+                return Ok(());
+            }
+            self.samples.extend_from_slice(samples);
+            if self.samples.len() == CAPTURED_QUANTA * RENDER_QUANTUM_FRAMES * DESTINATION_CHANNELS {
+                let _ = self.captured.take().unwrap().send(std::mem::take(&mut self.samples));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oscillator_renders_waveform() {
+        let (sender, captured) = tokio::sync::oneshot::channel();
+        let output = CaptureOutput { samples: Vec::new(), captured: Some(sender) };
+        let mut context = AudioContext::with_output(48000.0, Box::new(output)).unwrap();
+        assert_eq!(context.state(), AudioContextState::Running);
+
+        let oscillator = context.create_oscillator();
+        oscillator.set_frequency(1000.0);
+        oscillator.connect(&context.destination).unwrap();
+        oscillator.start(0.0).unwrap();
+        assert!(oscillator.start(0.0).is_err());
+
+        let samples = captured.await.unwrap();
+        context.close().await.unwrap();
+        assert_eq!(context.state(), AudioContextState::Closed);
+        assert!(context.resume().is_err());
+
+        // A full-scale 1 kHz sine wave starting at a quantum, the same on both channels
+        let left: Vec<f32> = samples.iter().step_by(DESTINATION_CHANNELS).copied().collect();
+        assert_eq!(left.len(), CAPTURED_QUANTA * RENDER_QUANTUM_FRAMES);
+        assert!(samples.chunks_exact(DESTINATION_CHANNELS).all(|frame| frame[0] == frame[1]));
+        assert_eq!(left[0], 0.0);
+        assert!((left[12] - 1.0).abs() < 1e-6 && (left[36] + 1.0).abs() < 1e-6);
+        assert!(left.iter().all(|sample| sample.abs() <= 1.0));
+        let rising_zero_crossings = left.windows(2).filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0).count();
+        assert_eq!(rising_zero_crossings, left.len() / 48);
+    }
+
+    /// Output passing each quantum written to it on
+    struct QuantumOutput(tokio::sync::mpsc::UnboundedSender<Vec<f32>>);
+
+    impl AudioOutput for QuantumOutput {
+        fn write(&mut self, samples: &[f32]) -> Result<()> {
+            let _ = self.0.send(samples.to_vec());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_browser_sees_playing_and_mutes_the_tab() {
+        let (sender, mut quanta) = tokio::sync::mpsc::unbounded_channel();
+        let mut context = AudioContext::with_output(48000.0, Box::new(QuantumOutput(sender))).unwrap();
+        let (renderer_end, browser_end) = common::ipc::ChannelPair::create::<IpcMessage, IpcMessage>().unwrap();
+        let tab_id = TabId::new(3);
+        context.connect_browser(tab_id, renderer_end);
+
+        let oscillator = context.create_oscillator();
+        oscillator.connect(&context.destination).unwrap();
+        oscillator.start(0.0).unwrap();
+        match browser_end.recv_async().await.unwrap() {
+            IpcMessage::AudioEvent(event) => assert_eq!(event, AudioEvent::Playing { tab_id }),
+            other => panic!("Unexpected message {:?}", other),
+        }
+
+        // Gains for other tabs are ignored, the tab's own silences it
+        for target in [TabId::new(4), tab_id] {
+            browser_end.send_async(IpcMessage::AudioControl(AudioControl::SetGain { tab_id: target, gain: 0.0 })).await.unwrap();
+        }
+        let mut audible_quanta = 0;
+        while quanta.recv().await.unwrap().iter().any(|&sample| sample != 0.0) {
+            audible_quanta += 1;
+            assert!(audible_quanta < 1000, "the gain was never applied");
+        }
+        for _ in 0..8 {
+            assert!(quanta.recv().await.unwrap().iter().all(|&sample| sample == 0.0));
+        }
+
+        // Muted sound still plays, so the tab only stops once the oscillator does
+        oscillator.stop(0.0).unwrap();
+        match browser_end.recv_async().await.unwrap() {
+            IpcMessage::AudioEvent(event) => assert_eq!(event, AudioEvent::Stopped { tab_id }),
+            other => panic!("Unexpected message {:?}", other),
+        }
+        context.close().await.unwrap();
+    }
+
+    #[test]
+    fn test_graph_renders_scheduled_sources() {
+        let destination = AudioNodeId::next();
+        let mut graph = AudioGraph {
+            sample_rate: 8000.0,
+            state: AudioContextState::Running,
+            current_frame: 0,
+            destination,
+            sources: HashMap::new(),
+            connections: Vec::new(),
+            gain: 1.0,
+            silent_frames: u64::MAX,
+            browser: None,
+        };
+
+        // Stereo PCM at half the context's rate, played from the second quantum
+        let pcm: Vec<i16> = (0..64).flat_map(|i| [i * 256, -16384]).collect();
+        let buffer = AudioBuffer::from_pcm_i16(&pcm, 2, 4000.0).unwrap();
+        assert_eq!((buffer.length(), buffer.duration()), (64, 0.016));
+        assert!(AudioBuffer::from_pcm_i16(&pcm[..3], 2, 4000.0).is_err());
+
+        let source = AudioNodeId::next();
+        graph.sources.insert(source, Source::Buffer(BufferSource {
+            buffer: Some(buffer),
+            looping: false,
+            position: 0.0,
+            schedule: Schedule { start: Some(RENDER_QUANTUM_FRAMES as u64), stop: None },
+        }));
+        graph.connections.push((source, destination));
+
+        assert!(graph.render_quantum().iter().all(|&sample| sample == 0.0));
+        let quantum = graph.render_quantum();
+        // Every other frame falls between two buffer frames
+        assert_eq!(&quantum[..6], &[0.0, -0.5, 256.0 / 32768.0 / 2.0, -0.5, 256.0 / 32768.0, -0.5]);
+        assert!(quantum.chunks_exact(2).all(|frame| frame[1] == -0.5));
+
+        // The buffer ran out after 128 frames at the context's rate
+        assert!(graph.render_quantum().iter().all(|&sample| sample == 0.0));
+
+        for (oscillator_type, quarter_period) in [
+            (OscillatorType::Sine, 1.0),
+            (OscillatorType::Square, 1.0),
+            (OscillatorType::Sawtooth, 0.5),
+            (OscillatorType::Triangle, 1.0),
+        ] {
+            assert!((oscillator_type.sample(0.25) - quarter_period).abs() < 1e-6);
+            assert!(oscillator_type.sample(0.75) < 0.0);
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Cache API used by service workers
+//!
+//! Every cache is a sled tree of request and response pairs, named after
+//! the origin owning the cache and the cache name. An open cache keeps its
+//! entries in memory too and writes them through to its tree.
+
+use crate::error::{Error, Result};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// First component of the name of every cache tree
+const CACHE_TREE_PREFIX: &str = "cache";
+
+/// Request as the Cache API stores and matches it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FetchRequest {
+    /// Request URL
+    pub url: String,
+    /// HTTP method
+    pub method: String,
+    /// Request headers
+    pub headers: HashMap<String, String>,
+}
+
+impl FetchRequest {
+    /// Create a GET request without headers
+    pub fn get(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            method: "GET".to_string(),
+            headers: HashMap::new(),
+        }
+    }
+
+    /// Key the request is matched by
+    pub fn key(&self) -> RequestKey {
+        let url = match url::Url::parse(&self.url) {
+            Ok(mut url) => {
+                url.set_fragment(None);
+                url.to_string()
+            }
+            Err(_) => self.url.split('#').next().unwrap_or_default().to_string(),
+        };
+        RequestKey {
+            method: self.method.to_ascii_uppercase(),
+            url,
+        }
+    }
+}
+
+/// What cached requests are matched by: the method and the URL without its fragment
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RequestKey {
+    /// HTTP method, uppercased
+    pub method: String,
+    /// URL without its fragment
+    pub url: String,
+}
+
+impl RequestKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        format!("{} {}", self.method, self.url).into_bytes()
+    }
+}
+
+/// Response stored in a cache
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    /// HTTP status code
+    pub status_code: u16,
+    /// Response headers
+    pub headers: HashMap<String, String>,
+    /// Response body
+    pub body: Vec<u8>,
+    /// Content type
+    pub content_type: String,
+}
+
+/// Cached request and response, as stored in the tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Position in the order entries were put
+    sequence: u64,
+    /// Request the response was stored for
+    request: FetchRequest,
+    /// Stored response
+    response: CachedResponse,
+}
+
+/// One named cache
+pub struct CacheStorage {
+    /// Entries by request
+    entries: HashMap<RequestKey, CacheEntry>,
+    /// Tree the entries persist in
+    tree: sled::Tree,
+    /// Sequence number of the next entry put
+    next_sequence: u64,
+}
+
+impl CacheStorage {
+    /// Load a cache from its tree
+    fn load(tree: sled::Tree) -> Result<Self> {
+        let mut entries = HashMap::new();
+        for item in tree.iter() {
+            let (_, value) = item.map_err(db_error)?;
+            let entry: CacheEntry = serde_json::from_slice(&value)
+                .map_err(|e| Error::deserialization(format!("Failed to parse cache entry: {}", e)))?;
+            entries.insert(entry.request.key(), entry);
+        }
+        let next_sequence = entries.values().map(|entry| entry.sequence + 1).max().unwrap_or(0);
+        Ok(Self { entries, tree, next_sequence })
+    }
+}
+
+/// Handle to an open cache, as `caches.open()` resolves to
+///
+/// Clones share the same cache.
+#[derive(Clone)]
+pub struct CacheHandle {
+    /// Cache name
+    name: String,
+    /// Cache contents
+    storage: Arc<RwLock<CacheStorage>>,
+}
+
+impl CacheHandle {
+    /// Get the cache name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Store a response for a request, replacing the one stored before
+    ///
+    /// Only GET requests to HTTP(S) URLs can be cached, and partial
+    /// responses cannot.
+    pub fn put(&self, request: FetchRequest, response: impl Into<CachedResponse>) -> Result<()> {
+        let response = response.into();
+        if !request.method.eq_ignore_ascii_case("GET") {
+            return Err(Error::invalid_value(format!("Cannot cache {} requests", request.method)));
+        }
+        if !url::Url::parse(&request.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+            return Err(Error::invalid_value(format!("Cannot cache request to {}", request.url)));
+        }
+        if response.status_code == 206 {
+            return Err(Error::invalid_value("Cannot cache partial responses".to_string()));
+        }
+
+        let mut storage = self.storage.write();
+        let key = request.key();
+        let entry = CacheEntry {
+            sequence: storage.next_sequence,
+            request,
+            response,
+        };
+        let value = serde_json::to_vec(&entry)
+            .map_err(|e| Error::serialization(format!("Failed to serialize cache entry: {}", e)))?;
+        storage.tree.insert(key.to_bytes(), value).map_err(db_error)?;
+        storage.entries.insert(key, entry);
+        storage.next_sequence += 1;
+        Ok(())
+    }
+
+    /// Get the response stored for a request
+    pub fn match_request(&self, request: &FetchRequest) -> Result<Option<CachedResponse>> {
+        Ok(self.storage.read().entries.get(&request.key()).map(|entry| entry.response.clone()))
+    }
+
+    /// Delete the response stored for a request, returning whether there was one
+    pub fn delete(&self, request: &FetchRequest) -> Result<bool> {
+        let mut storage = self.storage.write();
+        let key = request.key();
+        storage.tree.remove(key.to_bytes()).map_err(db_error)?;
+        Ok(storage.entries.remove(&key).is_some())
+    }
+
+    /// Get the cached requests, in the order they were put
+    pub fn keys(&self) -> Result<Vec<FetchRequest>> {
+        let storage = self.storage.read();
+        let mut entries: Vec<&CacheEntry> = storage.entries.values().collect();
+        entries.sort_by_key(|entry| entry.sequence);
+        Ok(entries.into_iter().map(|entry| entry.request.clone()).collect())
+    }
+}
+
+/// Named caches of an origin, as the `caches` global exposes them
+///
+/// Clones, and the managers `for_origin` returns, share one database.
+#[derive(Clone)]
+pub struct CacheApiManager {
+    /// Database holding one tree per cache
+    db: sled::Db,
+    /// Origin owning the caches
+    origin: String,
+    /// Open caches by tree name
+    caches: Arc<RwLock<HashMap<String, Arc<RwLock<CacheStorage>>>>>,
+}
+
+impl CacheApiManager {
+    /// Open the cache database at a path
+    pub fn new(path: &Path) -> Result<Self> {
+        let db = sled::open(path)
+            .map_err(|e| Error::database(format!("Failed to open cache database: {}", e)))?;
+        Ok(Self::with_database(db))
+    }
+
+    /// Create a cache database that is deleted when dropped
+    pub fn in_memory() -> Result<Self> {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .map_err(|e| Error::database(format!("Failed to open cache database: {}", e)))?;
+        Ok(Self::with_database(db))
+    }
+
+    fn with_database(db: sled::Db) -> Self {
+        Self {
+            db,
+            origin: String::new(),
+            caches: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Get the caches of an origin, in the same database
+    pub fn for_origin(&self, origin: &str) -> Self {
+        Self {
+            db: self.db.clone(),
+            origin: origin.to_string(),
+            caches: self.caches.clone(),
+        }
+    }
+
+    /// Get the origin owning the caches
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// Open a cache, creating it if it does not exist
+    pub fn open(&self, cache_name: &str) -> Result<CacheHandle> {
+        let tree_name = self.tree_name(cache_name);
+        let mut caches = self.caches.write();
+        let storage = match caches.get(&tree_name) {
+            Some(storage) => storage.clone(),
+            None => {
+                let tree = self.db.open_tree(&tree_name).map_err(db_error)?;
+                let storage = Arc::new(RwLock::new(CacheStorage::load(tree)?));
+                caches.insert(tree_name, storage.clone());
+                storage
+            }
+        };
+
+        Ok(CacheHandle {
+            name: cache_name.to_string(),
+            storage,
+        })
+    }
+
+    /// Check if a cache exists
+    pub fn has(&self, cache_name: &str) -> Result<bool> {
+        Ok(self.keys()?.iter().any(|name| name == cache_name))
+    }
+
+    /// Delete a cache, returning whether it existed
+    pub fn delete(&self, cache_name: &str) -> Result<bool> {
+        let tree_name = self.tree_name(cache_name);
+        self.caches.write().remove(&tree_name);
+        self.db.drop_tree(&tree_name).map_err(db_error)
+    }
+
+    /// Get the names of the caches, in name order
+    pub fn keys(&self) -> Result<Vec<String>> {
+        let prefix = self.tree_name("");
+        Ok(self.db.tree_names()
+            .into_iter()
+            .filter_map(|name| {
+                std::str::from_utf8(&name).ok()
+                    .and_then(|name| name.strip_prefix(&prefix))
+                    .map(str::to_string)
+            })
+            .collect())
+    }
+
+    /// Get the response stored for a request in any cache, looking at the caches in name order
+    pub fn match_request(&self, request: &FetchRequest) -> Result<Option<CachedResponse>> {
+        for cache_name in self.keys()? {
+            if let Some(response) = self.open(&cache_name)?.match_request(request)? {
+                return Ok(Some(response));
+            }
+        }
+        Ok(None)
+    }
+
+    fn tree_name(&self, cache_name: &str) -> String {
+        format!("{}\0{}\0{}", CACHE_TREE_PREFIX, self.origin, cache_name)
+    }
+}
+
+fn db_error(err: sled::Error) -> Error {
+    Error::database(format!("Cache storage error: {}", err))
+}
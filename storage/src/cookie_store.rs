@@ -0,0 +1,600 @@
+//! Cookie storage
+//!
+//! Cookies persist in a `sled` database and are matched to requests
+//! following RFC 6265.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// File name of the cookie database in the data directory
+const DATABASE_NAME: &str = "cookies.sled";
+
+/// Cookie store
+pub struct CookieStore {
+    /// Cookies keyed by domain, path, name and partition key
+    db: sled::Db,
+}
+
+/// Stored cookie
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cookie {
+    /// Cookie name
+    pub name: String,
+    /// Cookie value
+    pub value: String,
+    /// Domain, without a leading dot
+    pub domain: String,
+    /// Only sent to `domain` itself, not its subdomains
+    pub host_only: bool,
+    /// Path prefix the cookie is sent for
+    pub path: String,
+    /// Expiry in seconds since the Unix epoch, `None` for session cookies
+    pub expires: Option<u64>,
+    /// Only sent over secure connections
+    pub secure: bool,
+    /// Hidden from scripts
+    pub http_only: bool,
+    /// Cross-site policy
+    pub same_site: SameSite,
+    /// Partitioned by top-level site (CHIPS)
+    pub partitioned: bool,
+    /// Top-level site a partitioned cookie was set under, `None` for
+    /// unpartitioned cookies
+    #[serde(default)]
+    pub partition_key: Option<String>,
+    /// Creation time in microseconds since the Unix epoch
+    pub created: u64,
+}
+
+/// `SameSite` cookie attribute
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SameSite {
+    /// Only sent with same-site requests
+    Strict,
+    /// Also sent with cross-site top-level navigations
+    #[default]
+    Lax,
+    /// Sent with all requests
+    None,
+}
+
+/// Options for setting a cookie
+#[derive(Debug, Clone, Default)]
+pub struct CookieOptions {
+    /// Path prefix, `/` if unset
+    pub path: Option<String>,
+    /// Expiry time, `None` for a session cookie
+    pub expires: Option<SystemTime>,
+    /// Lifetime in seconds, takes precedence over `expires`
+    pub max_age: Option<i64>,
+    /// Only send over secure connections
+    pub secure: bool,
+    /// Hide from scripts
+    pub http_only: bool,
+    /// Cross-site policy
+    pub same_site: SameSite,
+    /// Partition by top-level site
+    pub partitioned: bool,
+}
+
+/// Relation between a request and the site that made it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestSite {
+    /// Same-site request, or a navigation started by the browser
+    SameSite,
+    /// Cross-site top-level navigation
+    CrossSiteNavigation,
+    /// Other cross-site request
+    CrossSite,
+}
+
+/// Parsed `Set-Cookie` header
+#[derive(Debug, Clone)]
+pub struct SetCookie {
+    /// Cookie name
+    pub name: String,
+    /// Cookie value
+    pub value: String,
+    /// `Domain` attribute, without a leading dot
+    pub domain: Option<String>,
+    /// Remaining attributes
+    pub options: CookieOptions,
+}
+
+impl CookieStore {
+    /// Open the cookie store in a data directory
+    pub fn open(data_directory: &Path) -> Result<Self> {
+        let path = data_directory.join(DATABASE_NAME);
+        let db = sled::open(&path)
+            .map_err(|e| Error::database(format!("Failed to open cookie database {:?}: {}", path, e)))?;
+
+        Ok(Self { db })
+    }
+
+    /// Create a cookie store that forgets its cookies when dropped
+    pub fn in_memory() -> Result<Self> {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .map_err(|e| Error::database(format!("Failed to open cookie database: {}", e)))?;
+
+        Ok(Self { db })
+    }
+
+    /// Insert or update a cookie
+    ///
+    /// A domain with a leading dot also matches its subdomains, otherwise the
+    /// cookie is host-only. Cookies that are already expired delete the
+    /// stored cookie instead. `Partitioned` cookies are stored under the
+    /// partition key of the top-level site, or of their own site when set in a
+    /// top-level context; other cookies ignore the partition key.
+    pub fn set(&self, domain: &str, name: &str, value: &str, options: CookieOptions, partition_key: Option<&str>) -> Result<()> {
+        if name.is_empty() || name.contains(|c: char| c == ';' || c == '=' || c.is_whitespace()) {
+            return Err(Error::invalid_key(format!("Invalid cookie name '{}'", name)));
+        }
+        if options.same_site == SameSite::None && !options.secure {
+            return Err(Error::invalid_value(format!("Cookie '{}' with SameSite=None must be Secure", name)));
+        }
+        if options.partitioned && !options.secure {
+            return Err(Error::invalid_value(format!("Partitioned cookie '{}' must be Secure", name)));
+        }
+
+        let host_only = !domain.starts_with('.');
+        let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+        let path = options.path.filter(|path| path.starts_with('/')).unwrap_or_else(|| "/".to_string());
+        let partition_key = options.partitioned
+            .then(|| partition_key.map_or_else(|| registrable_domain(&domain).to_string(), str::to_ascii_lowercase));
+        let key = cookie_key(&domain, &path, name, partition_key.as_deref());
+
+        let now = now_micros();
+        let expires = match options.max_age {
+            Some(max_age) => Some((now / 1_000_000).saturating_add_signed(max_age)),
+            None => options.expires.map(|expires| {
+                expires.duration_since(UNIX_EPOCH).map_or(0, |since_epoch| since_epoch.as_secs())
+            }),
+        };
+        if expires.is_some_and(|expires| expires <= now / 1_000_000) {
+            self.db.remove(key).map_err(db_error)?;
+            return Ok(());
+        }
+
+        // An updated cookie keeps its creation time
+        let created = self.db.get(&key).map_err(db_error)?
+            .and_then(|cookie| serde_json::from_slice::<Cookie>(&cookie).ok())
+            .map_or(now, |cookie| cookie.created);
+
+        let cookie = Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain,
+            host_only,
+            path,
+            expires,
+            secure: options.secure,
+            http_only: options.http_only,
+            same_site: options.same_site,
+            partitioned: options.partitioned,
+            partition_key,
+            created,
+        };
+        self.db.insert(key, serde_json::to_vec(&cookie)?).map_err(db_error)?;
+
+        Ok(())
+    }
+
+    /// Delete a cookie, from a partition if it is partitioned
+    pub fn remove(&self, domain: &str, path: &str, name: &str, partition_key: Option<&str>) -> Result<()> {
+        let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+        let partition_key = partition_key.map(str::to_ascii_lowercase);
+        self.db.remove(cookie_key(&domain, path, name, partition_key.as_deref())).map_err(db_error)?;
+
+        Ok(())
+    }
+
+    /// Get the cookies to send with a same-site request
+    pub fn get_for_request(&self, url: &str, is_secure: bool, partition_key: Option<&str>) -> Vec<Cookie> {
+        self.get_for_request_from(url, is_secure, RequestSite::SameSite, partition_key)
+    }
+
+    /// Get the cookies to send with a request
+    ///
+    /// Cookies are matched by domain and path, `Secure` cookies need a secure
+    /// connection and `SameSite` cookies are left out of cross-site requests
+    /// they do not allow. Partitioned cookies are only sent under the top-level
+    /// site they were set under. Longer paths come first, then older cookies.
+    pub fn get_for_request_from(&self, url: &str, is_secure: bool, site: RequestSite, partition_key: Option<&str>) -> Vec<Cookie> {
+        let Ok(url) = Url::parse(url) else {
+            return Vec::new();
+        };
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+            return Vec::new();
+        };
+        let partition_key = partition_key.map(str::to_ascii_lowercase);
+
+        let mut cookies: Vec<Cookie> = self.live_cookies()
+            .into_iter()
+            .filter(|cookie| cookie.domain_matches(&host) && cookie.path_matches(url.path()))
+            .filter(|cookie| is_secure || !cookie.secure)
+            .filter(|cookie| cookie.partition_key.is_none() || cookie.partition_key == partition_key)
+            .filter(|cookie| match cookie.same_site {
+                SameSite::Strict => site == RequestSite::SameSite,
+                SameSite::Lax => site != RequestSite::CrossSite,
+                SameSite::None => true,
+            })
+            .collect();
+
+        cookies.sort_by(|a, b| b.path.len().cmp(&a.path.len()).then(a.created.cmp(&b.created)));
+        cookies
+    }
+
+    /// Get the cookies scripts in a document can read, for `document.cookie`
+    /// and the Cookie Store API
+    pub fn get_for_document(&self, url: &str, partition_key: Option<&str>) -> Vec<Cookie> {
+        let is_secure = Url::parse(url).is_ok_and(|url| url.scheme() == "https");
+
+        self.get_for_request(url, is_secure, partition_key)
+            .into_iter()
+            .filter(|cookie| !cookie.http_only)
+            .collect()
+    }
+
+    /// Read all cookies, purging the expired ones
+    fn live_cookies(&self) -> Vec<Cookie> {
+        let now = now_micros() / 1_000_000;
+        let mut cookies = Vec::new();
+
+        for entry in self.db.iter() {
+            let (key, cookie) = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    log::warn!("Failed to read cookie: {}", e);
+                    continue;
+                }
+            };
+
+            match serde_json::from_slice::<Cookie>(&cookie) {
+                Ok(cookie) if cookie.expires.is_none_or(|expires| expires > now) => cookies.push(cookie),
+                _ => {
+                    if let Err(e) = self.db.remove(key) {
+                        log::warn!("Failed to purge cookie: {}", e);
+                    }
+                }
+            }
+        }
+
+        cookies
+    }
+}
+
+impl Cookie {
+    /// Check if the cookie is sent to a host
+    fn domain_matches(&self, host: &str) -> bool {
+        if self.host_only {
+            return host == self.domain;
+        }
+
+        host == self.domain
+            || (host.ends_with(&self.domain)
+                && host[..host.len() - self.domain.len()].ends_with('.')
+                && host.parse::<std::net::IpAddr>().is_err())
+    }
+
+    /// Check if the cookie is sent for a request path
+    fn path_matches(&self, request_path: &str) -> bool {
+        request_path == self.path
+            || (request_path.starts_with(&self.path)
+                && (self.path.ends_with('/') || request_path[self.path.len()..].starts_with('/')))
+    }
+}
+
+/// Partition key of the top-level site of a document, its registrable domain
+pub fn partition_key(top_level_url: &str) -> Option<String> {
+    let url = Url::parse(top_level_url).ok()?;
+    Some(registrable_domain(url.host_str()?).to_ascii_lowercase())
+}
+
+impl RequestSite {
+    /// Relation between a request URL and the URL of the document making it
+    ///
+    /// Requests without an initiator are started by the browser and count as
+    /// same-site.
+    pub fn for_request(url: &str, initiator: Option<&str>, top_level_navigation: bool) -> Self {
+        let Some(initiator) = initiator else {
+            return RequestSite::SameSite;
+        };
+
+        let site = |url: &str| Url::parse(url).ok().and_then(|url| {
+            Some((url.scheme().to_string(), registrable_domain(url.host_str()?).to_ascii_lowercase()))
+        });
+        match (site(url), site(initiator)) {
+            (Some(request_site), Some(initiator_site)) if request_site == initiator_site => RequestSite::SameSite,
+            _ if top_level_navigation => RequestSite::CrossSiteNavigation,
+            _ => RequestSite::CrossSite,
+        }
+    }
+}
+
+impl SetCookie {
+    /// Parse a `Set-Cookie` header value
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut cookie = SetCookie {
+            name: name.to_string(),
+            value: value.trim().trim_matches('"').to_string(),
+            domain: None,
+            options: CookieOptions::default(),
+        };
+
+        for attribute in parts {
+            let (attribute, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+            match attribute.trim().to_ascii_lowercase().as_str() {
+                "domain" => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                    cookie.domain = (!domain.is_empty()).then_some(domain);
+                }
+                "path" if value.starts_with('/') => cookie.options.path = Some(value.to_string()),
+                "expires" => cookie.options.expires = parse_http_date(value).or(cookie.options.expires),
+                "max-age" => cookie.options.max_age = value.parse().ok().or(cookie.options.max_age),
+                "secure" => cookie.options.secure = true,
+                "httponly" => cookie.options.http_only = true,
+                "samesite" => {
+                    cookie.options.same_site = match value.to_ascii_lowercase().as_str() {
+                        "strict" => SameSite::Strict,
+                        "none" => SameSite::None,
+                        _ => SameSite::Lax,
+                    };
+                }
+                "partitioned" => cookie.options.partitioned = true,
+                _ => {}
+            }
+        }
+
+        Some(cookie)
+    }
+
+    /// Domain to store the cookie under for a response from a host
+    ///
+    /// Returns `None` when the `Domain` attribute does not cover the host.
+    pub fn domain_for(&self, host: &str) -> Option<String> {
+        let host = host.to_ascii_lowercase();
+        let Some(domain) = &self.domain else {
+            return Some(host);
+        };
+
+        let covers_host = host == *domain
+            || (host.ends_with(&format!(".{}", domain)) && host.parse::<std::net::IpAddr>().is_err());
+        // Single-label domains such as `com` are public suffixes
+        (covers_host && (domain.contains('.') || host == *domain)).then(|| format!(".{}", domain))
+    }
+}
+
+/// Default cookie path for a request path, its directory
+pub fn default_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => request_path[..index].to_string(),
+    }
+}
+
+/// Build the `Cookie` request header value
+pub fn cookie_header(cookies: &[Cookie]) -> Option<String> {
+    if cookies.is_empty() {
+        return None;
+    }
+
+    Some(
+        cookies.iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; "),
+    )
+}
+
+/// Key of a cookie, its domain, path, name and partition key separated by NULs
+fn cookie_key(domain: &str, path: &str, name: &str, partition_key: Option<&str>) -> Vec<u8> {
+    match partition_key {
+        Some(partition_key) => format!("{}\0{}\0{}\0{}", domain, path, name, partition_key).into_bytes(),
+        None => format!("{}\0{}\0{}", domain, path, name).into_bytes(),
+    }
+}
+
+/// Approximate the registrable domain of a host by its last two labels
+fn registrable_domain(host: &str) -> &str {
+    if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') {
+        return host;
+    }
+
+    match host.rmatch_indices('.').nth(1) {
+        Some((index, _)) => &host[index + 1..],
+        None => host,
+    }
+}
+
+/// Parse an HTTP date such as `Wed, 21 Oct 2015 07:28:00 GMT`
+///
+/// Follows the lenient cookie date algorithm of RFC 6265 section 5.1.1.
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+    let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+    for token in value.split(|c: char| !c.is_ascii_alphanumeric() && c != ':').filter(|token| !token.is_empty()) {
+        if time.is_none() && token.contains(':') {
+            let fields: Vec<u64> = token.split(':').filter_map(|field| field.parse().ok()).collect();
+            if let [hours, minutes, seconds] = fields[..] {
+                time = Some(hours * 3600 + minutes * 60 + seconds);
+            }
+        } else if day.is_none() && token.len() <= 2 && token.chars().all(|c| c.is_ascii_digit()) {
+            day = token.parse::<u32>().ok();
+        } else if month.is_none() && token.len() >= 3 {
+            let prefix = token[..3].to_ascii_lowercase();
+            month = MONTHS.iter().position(|month| *month == prefix).map(|index| index as u32 + 1);
+        } else if year.is_none() && token.chars().all(|c| c.is_ascii_digit()) {
+            year = token.parse::<i64>().ok().map(|year| match year {
+                70..=99 => year + 1900,
+                0..=69 => year + 2000,
+                _ => year,
+            });
+        }
+    }
+
+    let (time, day, month, year) = (time?, day?, month?, year?);
+    if !(1..=31).contains(&day) || year < 1970 || time >= 24 * 3600 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    Some(UNIX_EPOCH + std::time::Duration::from_secs(days as u64 * 86_400 + time))
+}
+
+/// Convert a (year, month, day) date to days since 1970-01-01
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_index = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn now_micros() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since_epoch| since_epoch.as_micros() as u64)
+}
+
+fn db_error(err: sled::Error) -> Error {
+    Error::database(format!("Cookie store error: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(cookies: &[Cookie]) -> Vec<&str> {
+        cookies.iter().map(|cookie| cookie.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_same_site_strict_excluded_cross_site() {
+        let store = CookieStore::in_memory().unwrap();
+        let strict = CookieOptions { same_site: SameSite::Strict, ..Default::default() };
+        let none = CookieOptions { same_site: SameSite::None, secure: true, ..Default::default() };
+        store.set("shop.example", "strict", "1", strict, None).unwrap();
+        store.set("shop.example", "lax", "2", CookieOptions::default(), None).unwrap();
+        store.set("shop.example", "none", "3", none, None).unwrap();
+
+        let url = "https://shop.example/cart";
+        let same_site = RequestSite::for_request(url, Some("https://www.shop.example/"), false);
+        let cross_site = RequestSite::for_request(url, Some("https://evil.example/"), false);
+        let navigation = RequestSite::for_request(url, Some("https://evil.example/"), true);
+        assert_eq!(same_site, RequestSite::SameSite);
+        assert_eq!(cross_site, RequestSite::CrossSite);
+
+        assert_eq!(names(&store.get_for_request_from(url, true, same_site, None)), ["strict", "lax", "none"]);
+        assert_eq!(names(&store.get_for_request_from(url, true, navigation, None)), ["lax", "none"]);
+        assert_eq!(names(&store.get_for_request_from(url, true, cross_site, None)), ["none"]);
+    }
+
+    #[test]
+    fn test_partitioned_cookies_keyed_by_top_level_site() {
+        let store = CookieStore::in_memory().unwrap();
+        let partitioned = CookieOptions { secure: true, same_site: SameSite::None, partitioned: true, ..Default::default() };
+        let news = partition_key("https://www.news.example/article").unwrap();
+        let shop = partition_key("https://shop.example/").unwrap();
+        assert_eq!(news, "news.example");
+
+        store.set("widget.example", "chat", "news", partitioned.clone(), Some(&news)).unwrap();
+        store.set("widget.example", "chat", "shop", partitioned.clone(), Some(&shop)).unwrap();
+        let shared = CookieOptions { secure: true, same_site: SameSite::None, ..Default::default() };
+        store.set("widget.example", "shared", "1", shared, Some(&news)).unwrap();
+
+        let url = "https://widget.example/";
+        let values = |partition_key: Option<&str>| -> Vec<String> {
+            store.get_for_request_from(url, true, RequestSite::CrossSite, partition_key)
+                .iter()
+                .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+                .collect()
+        };
+        assert_eq!(values(Some(&news)), ["chat=news", "shared=1"]);
+        assert_eq!(values(Some(&shop)), ["chat=shop", "shared=1"]);
+        assert_eq!(values(Some("blog.example")), ["shared=1"]);
+        assert_eq!(values(None), ["shared=1"]);
+
+        store.remove("widget.example", "/", "chat", Some(&news)).unwrap();
+        assert_eq!(values(Some(&news)), ["shared=1"]);
+        assert_eq!(values(Some(&shop)), ["chat=shop", "shared=1"]);
+
+        // Partitioned cookies set by a top-level document fall in its own partition
+        store.set("widget.example", "own", "1", partitioned, None).unwrap();
+        assert_eq!(names(&store.get_for_request(url, true, Some("widget.example"))), ["shared", "own"]);
+    }
+
+    #[test]
+    fn test_domain_path_and_secure_matching() {
+        let store = CookieStore::in_memory().unwrap();
+        store.set("example.com", "host", "1", CookieOptions::default(), None).unwrap();
+        store.set(".example.com", "domain", "2", CookieOptions::default(), None).unwrap();
+        let docs = CookieOptions { path: Some("/docs".to_string()), ..Default::default() };
+        store.set(".example.com", "docs", "3", docs, None).unwrap();
+        store.set("example.com", "secure", "4", CookieOptions { secure: true, ..Default::default() }, None).unwrap();
+
+        assert_eq!(names(&store.get_for_request("https://example.com/docs/a", true, None)), ["docs", "host", "domain", "secure"]);
+        assert_eq!(names(&store.get_for_request("http://example.com/docs", false, None)), ["docs", "host", "domain"]);
+        assert_eq!(names(&store.get_for_request("https://www.example.com/docsearch", true, None)), ["domain"]);
+        assert!(store.get_for_request("https://badexample.com/", true, None).is_empty());
+
+        let header = cookie_header(&store.get_for_request("http://example.com/", false, None));
+        assert_eq!(header.as_deref(), Some("host=1; domain=2"));
+    }
+
+    #[test]
+    fn test_expired_cookies_purged() {
+        let store = CookieStore::in_memory().unwrap();
+        store.set("example.com", "session", "1", CookieOptions::default(), None).unwrap();
+        store.set("example.com", "old", "2", CookieOptions { max_age: Some(60), ..Default::default() }, None).unwrap();
+
+        // Max-Age=0 deletes the stored cookie
+        store.set("example.com", "old", "2", CookieOptions { max_age: Some(0), ..Default::default() }, None).unwrap();
+        assert_eq!(names(&store.get_for_request("http://example.com/", false, None)), ["session"]);
+
+        // Cookies stored before they expired are purged on access
+        let mut expired = store.get_for_request("http://example.com/", false, None).remove(0);
+        expired.name = "expired".to_string();
+        expired.expires = Some(1);
+        let key = cookie_key(&expired.domain, &expired.path, &expired.name, None);
+        store.db.insert(key.clone(), serde_json::to_vec(&expired).unwrap()).unwrap();
+        assert_eq!(names(&store.get_for_request("http://example.com/", false, None)), ["session"]);
+        assert!(store.db.get(key).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_set_cookie_parsing() {
+        let cookie = SetCookie::parse(
+            "id=a3fWa; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Domain=.Example.com; Path=/app; Secure; HttpOnly; SameSite=Strict",
+        ).unwrap();
+        assert_eq!((cookie.name.as_str(), cookie.value.as_str()), ("id", "a3fWa"));
+        assert_eq!(cookie.options.expires, Some(UNIX_EPOCH + std::time::Duration::from_secs(1_445_412_480)));
+        assert_eq!(cookie.options.path.as_deref(), Some("/app"));
+        assert!(cookie.options.secure && cookie.options.http_only);
+        assert_eq!(cookie.options.same_site, SameSite::Strict);
+
+        assert_eq!(cookie.domain_for("www.example.com").as_deref(), Some(".example.com"));
+        assert_eq!(cookie.domain_for("example.org"), None);
+        assert_eq!(SetCookie::parse("a=b; Domain=com").unwrap().domain_for("example.com"), None);
+        assert_eq!(SetCookie::parse("a=b").unwrap().domain_for("Example.com").as_deref(), Some("example.com"));
+        assert!(SetCookie::parse("novalue").is_none());
+
+        assert_eq!(default_path("/app/page"), "/app");
+        assert_eq!(default_path("/page"), "/");
+    }
+}
@@ -0,0 +1,559 @@
+//! Encryption at rest for origin data
+//!
+//! Values are sealed with AES-256-GCM under a key derived for each origin
+//! from a master key with HKDF-SHA256. A sealed blob is the 12-byte nonce
+//! followed by the ciphertext and its tag. The master key either lives in
+//! the OS keychain or is derived from a master password.
+
+use crate::error::{Error, Result};
+use parking_lot::Mutex;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{hkdf, pbkdf2};
+use std::fmt;
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::Arc;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Length of the master key and origin keys in bytes
+pub const KEY_LEN: usize = 32;
+
+/// Keychain service the master key is stored under
+pub const KEYCHAIN_SERVICE: &str = "Matte Browser Storage";
+
+/// Keychain account the master key is stored under
+const KEYCHAIN_ACCOUNT: &str = "master-key";
+
+/// HKDF salt, fixed so origin keys stay the same across restarts
+const HKDF_SALT: &[u8] = b"matte-browser storage encryption v1";
+
+/// PBKDF2-HMAC-SHA256 iterations, as OWASP recommends
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// File in the storage directory holding the master password salt
+const PASSWORD_SALT_FILE: &str = "encryption_salt";
+
+/// Length of the master password salt in bytes
+const PASSWORD_SALT_LEN: usize = 16;
+
+/// Secret text that is wiped from memory on drop and kept out of logs
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Wrap a secret
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(secret.into())
+    }
+
+    /// Get the secret text
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self(secret.to_string())
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString([REDACTED])")
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// How a master password is turned into the master key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyDerivation {
+    /// PBKDF2-HMAC-SHA256
+    #[default]
+    Pbkdf2,
+    /// Argon2id, not available in this build
+    Argon2,
+}
+
+/// Encryption at rest configuration
+#[derive(Debug, Clone, Default)]
+pub struct StorageEncryptionConfig {
+    /// Whether origin data is encrypted on disk
+    pub enabled: bool,
+    /// How the master password is turned into the master key
+    pub key_derivation: KeyDerivation,
+    /// Password the master key is derived from, `None` to keep a random key in the OS keychain
+    pub master_password: Option<SecretString>,
+}
+
+/// Derive the key of an origin from the master key with HKDF-SHA256
+pub fn derive_key(origin: &str, master_key: &[u8; KEY_LEN]) -> [u8; KEY_LEN] {
+    let info = [origin.as_bytes()];
+    let mut key = [0u8; KEY_LEN];
+    hkdf::Salt::new(hkdf::HKDF_SHA256, HKDF_SALT)
+        .extract(master_key)
+        .expand(&info, hkdf::HKDF_SHA256)
+        .and_then(|okm| okm.fill(&mut key))
+        .expect("HKDF-SHA256 output fits one hash block");
+    key
+}
+
+/// Seals and opens origin data with per-origin AES-256-GCM keys
+pub struct StorageCipher {
+    /// Master key the origin keys are derived from
+    master_key: Zeroizing<[u8; KEY_LEN]>,
+    /// Nonce source
+    rng: SystemRandom,
+}
+
+impl fmt::Debug for StorageCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StorageCipher").finish_non_exhaustive()
+    }
+}
+
+impl StorageCipher {
+    /// Create a cipher from a master key
+    pub fn new(master_key: [u8; KEY_LEN]) -> Self {
+        Self {
+            master_key: Zeroizing::new(master_key),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    /// Create a cipher with the master key a configuration asks for
+    ///
+    /// Without a master password the key is loaded from `key_store`, and
+    /// generated and stored there on first use.
+    pub fn from_config(config: &StorageEncryptionConfig, storage_directory: &Path, key_store: &dyn MasterKeyStore) -> Result<Self> {
+        let master_key = match &config.master_password {
+            Some(password) => password_key(password, config.key_derivation, storage_directory)?,
+            None => match key_store.load()? {
+                Some(key) => Zeroizing::new(key),
+                None => {
+                    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+                    SystemRandom::new().fill(key.as_mut())
+                        .map_err(|_| Error::encryption("Failed to generate master key".to_string()))?;
+                    key_store.store(&key)?;
+                    key
+                }
+            },
+        };
+        Ok(Self::new(*master_key))
+    }
+
+    /// Encrypt data of an origin into a nonce-prefixed blob
+    pub fn encrypt(&self, origin: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce)
+            .map_err(|_| Error::encryption("Failed to generate nonce".to_string()))?;
+
+        let mut in_out = plaintext.to_vec();
+        self.origin_key(origin)?
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(origin.as_bytes()), &mut in_out)
+            .map_err(|_| Error::encryption(format!("Failed to encrypt data of {}", origin)))?;
+
+        let mut blob = Vec::with_capacity(NONCE_LEN + in_out.len());
+        blob.extend_from_slice(&nonce);
+        blob.extend_from_slice(&in_out);
+        Ok(blob)
+    }
+
+    /// Decrypt a blob produced by `encrypt` for the same origin
+    pub fn decrypt(&self, origin: &str, blob: &[u8]) -> Result<Vec<u8>> {
+        if blob.len() < NONCE_LEN {
+            return Err(Error::encryption(format!("Encrypted data of {} is truncated", origin)));
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| Error::encryption("Invalid nonce".to_string()))?;
+
+        let mut in_out = ciphertext.to_vec();
+        let plaintext = self.origin_key(origin)?
+            .open_in_place(nonce, Aad::from(origin.as_bytes()), &mut in_out)
+            .map_err(|_| Error::encryption(format!("Failed to decrypt data of {}", origin)))?;
+        Ok(plaintext.to_vec())
+    }
+
+    fn origin_key(&self, origin: &str) -> Result<LessSafeKey> {
+        let key = Zeroizing::new(derive_key(origin, &self.master_key));
+        UnboundKey::new(&AES_256_GCM, key.as_ref())
+            .map(LessSafeKey::new)
+            .map_err(|_| Error::encryption("Invalid origin key".to_string()))
+    }
+}
+
+/// Seal file contents of an origin when encryption is on
+pub(crate) fn seal(cipher: Option<&StorageCipher>, origin: &str, bytes: Vec<u8>) -> Result<Vec<u8>> {
+    match cipher {
+        Some(cipher) => cipher.encrypt(origin, &bytes),
+        None => Ok(bytes),
+    }
+}
+
+/// Open file contents written by `seal`
+///
+/// JSON written before encryption was turned on is returned as is, so it
+/// gets sealed on its next write.
+pub(crate) fn open(cipher: Option<&StorageCipher>, origin: &str, bytes: Vec<u8>) -> Result<Vec<u8>> {
+    let Some(cipher) = cipher else {
+        return Ok(bytes);
+    };
+    match cipher.decrypt(origin, &bytes) {
+        Ok(plaintext) => Ok(plaintext),
+        Err(_) if serde_json::from_slice::<serde_json::Value>(&bytes).is_ok() => Ok(bytes),
+        Err(e) => Err(e),
+    }
+}
+
+/// Derive the master key from the master password, with a salt kept in the storage directory
+fn password_key(password: &SecretString, key_derivation: KeyDerivation, storage_directory: &Path) -> Result<Zeroizing<[u8; KEY_LEN]>> {
+    if key_derivation == KeyDerivation::Argon2 {
+        return Err(Error::encryption("Argon2 key derivation is not available".to_string()));
+    }
+
+    let salt_path = storage_directory.join(PASSWORD_SALT_FILE);
+    let salt = if salt_path.exists() {
+        fs::read(&salt_path)
+            .map_err(|e| Error::storage(format!("Failed to read encryption salt: {}", e)))?
+    } else {
+        let mut salt = vec![0u8; PASSWORD_SALT_LEN];
+        SystemRandom::new().fill(&mut salt)
+            .map_err(|_| Error::encryption("Failed to generate encryption salt".to_string()))?;
+        fs::write(&salt_path, &salt)
+            .map_err(|e| Error::storage(format!("Failed to write encryption salt: {}", e)))?;
+        salt
+    };
+
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ITERATIONS).expect("iteration count is not zero"),
+        &salt,
+        password.expose_secret().as_bytes(),
+        key.as_mut(),
+    );
+    Ok(key)
+}
+
+/// Place the master key is kept between sessions
+pub trait MasterKeyStore: Send + Sync {
+    /// Load the master key, `None` if none was stored yet
+    fn load(&self) -> Result<Option<[u8; KEY_LEN]>>;
+    /// Store the master key
+    fn store(&self, key: &[u8; KEY_LEN]) -> Result<()>;
+}
+
+/// Master key store keeping the key in memory, for tests and unsupported platforms
+#[derive(Default)]
+pub struct MemoryMasterKeyStore {
+    /// Stored key
+    key: Mutex<Option<Zeroizing<[u8; KEY_LEN]>>>,
+}
+
+impl MasterKeyStore for MemoryMasterKeyStore {
+    fn load(&self) -> Result<Option<[u8; KEY_LEN]>> {
+        Ok(self.key.lock().as_deref().copied())
+    }
+
+    fn store(&self, key: &[u8; KEY_LEN]) -> Result<()> {
+        *self.key.lock() = Some(Zeroizing::new(*key));
+        Ok(())
+    }
+}
+
+/// Master key store backed by the keychain of the current platform
+pub fn platform_master_key_store() -> Arc<dyn MasterKeyStore> {
+    #[cfg(target_os = "linux")]
+    {
+        Arc::new(linux::SecretServiceKeyStore)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Arc::new(macos::KeychainKeyStore)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        Arc::new(windows::CredentialManagerKeyStore)
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    {
+        Arc::new(MemoryMasterKeyStore::default())
+    }
+}
+
+/// Convert a stored secret into a master key
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn master_key_from_bytes(bytes: &[u8]) -> Result<[u8; KEY_LEN]> {
+    bytes.try_into()
+        .map_err(|_| Error::encryption(format!("Stored master key is {} bytes long", bytes.len())))
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::*;
+    use std::collections::HashMap;
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+    const SERVICE: &str = "org.freedesktop.secrets";
+    const SERVICE_PATH: &str = "/org/freedesktop/secrets";
+    const DEFAULT_COLLECTION_PATH: &str = "/org/freedesktop/secrets/aliases/default";
+
+    /// Secret as the Secret Service transfers it: session, parameters, value and content type
+    type Secret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+    /// Master key store backed by the freedesktop Secret Service
+    pub struct SecretServiceKeyStore;
+
+    impl SecretServiceKeyStore {
+        /// Lookup attributes of the master key item
+        fn attributes() -> HashMap<&'static str, &'static str> {
+            HashMap::from([("service", KEYCHAIN_SERVICE), ("account", KEYCHAIN_ACCOUNT)])
+        }
+
+        /// Open an unencrypted session, which only travels over the session bus
+        fn open_session(connection: &Connection) -> Result<(Proxy<'_>, OwnedObjectPath)> {
+            let service = Proxy::new(connection, SERVICE, SERVICE_PATH, "org.freedesktop.Secret.Service")
+                .map_err(dbus_error)?;
+            let (_, session) = service.call::<_, _, (OwnedValue, OwnedObjectPath)>("OpenSession", &("plain", Value::from("")))
+                .map_err(dbus_error)?;
+            Ok((service, session))
+        }
+    }
+
+    impl MasterKeyStore for SecretServiceKeyStore {
+        fn load(&self) -> Result<Option<[u8; KEY_LEN]>> {
+            let connection = Connection::session().map_err(dbus_error)?;
+            let (service, session) = Self::open_session(&connection)?;
+
+            let (unlocked, locked) = service.call::<_, _, (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>)>("SearchItems", &(Self::attributes(),))
+                .map_err(dbus_error)?;
+            if unlocked.is_empty() {
+                return match locked.is_empty() {
+                    true => Ok(None),
+                    false => Err(Error::encryption("The keyring holding the storage master key is locked".to_string())),
+                };
+            }
+
+            let secrets = service.call::<_, _, HashMap<OwnedObjectPath, Secret>>("GetSecrets", &(&unlocked[..1], &session))
+                .map_err(dbus_error)?;
+            secrets.into_values()
+                .next()
+                .map(|(_, _, value, _)| master_key_from_bytes(&Zeroizing::new(value)))
+                .transpose()
+        }
+
+        fn store(&self, key: &[u8; KEY_LEN]) -> Result<()> {
+            let connection = Connection::session().map_err(dbus_error)?;
+            let (_service, session) = Self::open_session(&connection)?;
+            let collection = Proxy::new(&connection, SERVICE, DEFAULT_COLLECTION_PATH, "org.freedesktop.Secret.Collection")
+                .map_err(dbus_error)?;
+
+            let properties = HashMap::from([
+                ("org.freedesktop.Secret.Item.Label", Value::from("Matte Browser storage master key")),
+                ("org.freedesktop.Secret.Item.Attributes", Value::from(Self::attributes())),
+            ]);
+            let secret = (session, Vec::<u8>::new(), key.to_vec(), "application/octet-stream");
+            let (_, prompt) = collection.call::<_, _, (OwnedObjectPath, OwnedObjectPath)>("CreateItem", &(properties, secret, true))
+                .map_err(dbus_error)?;
+            // A prompt path other than "/" means the collection is locked
+            if prompt.as_str() != "/" {
+                return Err(Error::encryption("The keyring for the storage master key is locked".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    fn dbus_error(error: impl std::fmt::Display) -> Error {
+        Error::encryption(format!("Secret Service error: {}", error))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::*;
+    use core_foundation::base::{CFType, CFTypeRef, OSStatus, TCFType};
+    use core_foundation::boolean::CFBoolean;
+    use core_foundation::data::{CFData, CFDataRef};
+    use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+    use core_foundation::string::{CFString, CFStringRef};
+
+    #[link(name = "Security", kind = "framework")]
+    extern "C" {
+        static kSecClass: CFStringRef;
+        static kSecClassGenericPassword: CFStringRef;
+        static kSecAttrService: CFStringRef;
+        static kSecAttrAccount: CFStringRef;
+        static kSecValueData: CFStringRef;
+        static kSecReturnData: CFStringRef;
+
+        fn SecItemCopyMatching(query: CFDictionaryRef, result: *mut CFTypeRef) -> OSStatus;
+        fn SecItemAdd(attributes: CFDictionaryRef, result: *mut CFTypeRef) -> OSStatus;
+    }
+
+    const ERR_SEC_SUCCESS: OSStatus = 0;
+    const ERR_SEC_ITEM_NOT_FOUND: OSStatus = -25300;
+
+    /// Master key store backed by the login keychain
+    pub struct KeychainKeyStore;
+
+    impl KeychainKeyStore {
+        /// Attributes of the master key item, plus extra entries
+        fn query(extra: &[(CFStringRef, CFType)]) -> CFDictionary<CFString, CFType> {
+            // SAFETY: the Security framework constants are immutable CFStrings
+            let mut pairs = unsafe {
+                vec![
+                    (CFString::wrap_under_get_rule(kSecClass), CFString::wrap_under_get_rule(kSecClassGenericPassword).as_CFType()),
+                    (CFString::wrap_under_get_rule(kSecAttrService), CFString::new(KEYCHAIN_SERVICE).as_CFType()),
+                    (CFString::wrap_under_get_rule(kSecAttrAccount), CFString::new(KEYCHAIN_ACCOUNT).as_CFType()),
+                ]
+            };
+            for (key, value) in extra {
+                // SAFETY: as above
+                pairs.push((unsafe { CFString::wrap_under_get_rule(*key) }, value.clone()));
+            }
+            CFDictionary::from_CFType_pairs(&pairs)
+        }
+    }
+
+    impl MasterKeyStore for KeychainKeyStore {
+        fn load(&self) -> Result<Option<[u8; KEY_LEN]>> {
+            // SAFETY: kSecReturnData is an immutable CFString
+            let query = Self::query(&[(unsafe { kSecReturnData }, CFBoolean::true_value().as_CFType())]);
+            let mut result: CFTypeRef = std::ptr::null();
+            // SAFETY: the query is a valid dictionary and `result` receives an owned CFData
+            let status = unsafe { SecItemCopyMatching(query.as_concrete_TypeRef(), &mut result) };
+            match status {
+                ERR_SEC_SUCCESS => {
+                    // SAFETY: SecItemCopyMatching returns a +1 CFData for kSecReturnData
+                    let data = unsafe { CFData::wrap_under_create_rule(result as CFDataRef) };
+                    master_key_from_bytes(data.bytes()).map(Some)
+                }
+                ERR_SEC_ITEM_NOT_FOUND => Ok(None),
+                status => Err(keychain_error("SecItemCopyMatching", status)),
+            }
+        }
+
+        fn store(&self, key: &[u8; KEY_LEN]) -> Result<()> {
+            // SAFETY: kSecValueData is an immutable CFString
+            let attributes = Self::query(&[(unsafe { kSecValueData }, CFData::from_buffer(key).as_CFType())]);
+            // SAFETY: the attributes are a valid dictionary and no result is requested
+            let status = unsafe { SecItemAdd(attributes.as_concrete_TypeRef(), std::ptr::null_mut()) };
+            match status {
+                ERR_SEC_SUCCESS => Ok(()),
+                status => Err(keychain_error("SecItemAdd", status)),
+            }
+        }
+    }
+
+    fn keychain_error(function: &str, status: OSStatus) -> Error {
+        Error::encryption(format!("{} failed with status {}", function, status))
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use super::*;
+    use windows_sys::Win32::Foundation::{GetLastError, ERROR_NOT_FOUND, FILETIME};
+    use windows_sys::Win32::Security::Credentials::{
+        CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC,
+    };
+
+    /// Master key store backed by the Windows Credential Manager
+    pub struct CredentialManagerKeyStore;
+
+    /// Credential target name as a null-terminated UTF-16 string
+    fn target_name() -> Vec<u16> {
+        format!("{}/{}", KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect()
+    }
+
+    impl MasterKeyStore for CredentialManagerKeyStore {
+        fn load(&self) -> Result<Option<[u8; KEY_LEN]>> {
+            let target = target_name();
+            let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+            // SAFETY: the target is null-terminated and `credential` receives a
+            // buffer that is released with CredFree
+            unsafe {
+                if CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) == 0 {
+                    return match GetLastError() {
+                        ERROR_NOT_FOUND => Ok(None),
+                        error => Err(Error::encryption(format!("CredReadW failed with error {}", error))),
+                    };
+                }
+                let blob = match (*credential).CredentialBlob.is_null() {
+                    true => &[][..],
+                    false => std::slice::from_raw_parts((*credential).CredentialBlob, (*credential).CredentialBlobSize as usize),
+                };
+                let key = master_key_from_bytes(blob);
+                CredFree(credential as *const _);
+                key.map(Some)
+            }
+        }
+
+        fn store(&self, key: &[u8; KEY_LEN]) -> Result<()> {
+            let mut target = target_name();
+            let mut blob = Zeroizing::new(*key);
+            let credential = CREDENTIALW {
+                Flags: 0,
+                Type: CRED_TYPE_GENERIC,
+                TargetName: target.as_mut_ptr(),
+                Comment: std::ptr::null_mut(),
+                LastWritten: FILETIME { dwLowDateTime: 0, dwHighDateTime: 0 },
+                CredentialBlobSize: KEY_LEN as u32,
+                CredentialBlob: blob.as_mut_ptr(),
+                Persist: CRED_PERSIST_LOCAL_MACHINE,
+                AttributeCount: 0,
+                Attributes: std::ptr::null_mut(),
+                TargetAlias: std::ptr::null_mut(),
+                UserName: std::ptr::null_mut(),
+            };
+            // SAFETY: every pointer in the credential outlives the call
+            if unsafe { CredWriteW(&credential, 0) } == 0 {
+                return Err(Error::encryption(format!(
+                    "CredWriteW failed: {}",
+                    std::io::Error::last_os_error()
+                )));
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_keys_are_separate() {
+        let master_key = [7u8; KEY_LEN];
+        let cipher = StorageCipher::new(master_key);
+        assert_eq!(derive_key("https://a.example", &master_key), derive_key("https://a.example", &master_key));
+        assert_ne!(derive_key("https://a.example", &master_key), derive_key("https://b.example", &master_key));
+
+        let blob = cipher.encrypt("https://a.example", b"secret").unwrap();
+        assert_eq!(blob.len(), NONCE_LEN + b"secret".len() + AES_256_GCM.tag_len());
+        assert_eq!(cipher.decrypt("https://a.example", &blob).unwrap(), b"secret");
+        assert!(matches!(cipher.decrypt("https://b.example", &blob), Err(Error::Encryption(_))));
+        assert_eq!(format!("{:?}", SecretString::from("hunter2")), "SecretString([REDACTED])");
+    }
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use thiserror::Error;
 
 /// Storage error type
@@ -79,6 +80,10 @@ pub enum Error {
     #[error("Connection error: {0}")]
     Connection(String),
     
+    /// Encryption error
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+    
     /// IO error
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -191,6 +196,11 @@ impl Error {
         Error::Connection(message)
     }
     
+    /// Create an encryption error
+    pub fn encryption(message: String) -> Self {
+        Error::Encryption(message)
+    }
+    
     /// Check if error is retryable
     pub fn is_retryable(&self) -> bool {
         match self {
@@ -234,6 +244,7 @@ impl Error {
             Error::ConstraintViolation(_) => "CONSTRAINT_VIOLATION",
             Error::Timeout(_) => "TIMEOUT",
             Error::Connection(_) => "CONNECTION_ERROR",
+            Error::Encryption(_) => "ENCRYPTION_ERROR",
             Error::Io(_) => "IO_ERROR",
             Error::Json(_) => "JSON_ERROR",
             Error::Uuid(_) => "UUID_ERROR",
@@ -241,59 +252,31 @@ impl Error {
     }
     
     /// Get error message
-    pub fn message(&self) -> &str {
-        match self {
-            Error::Storage(msg) => msg,
-            Error::Database(msg) => msg,
-            Error::QuotaExceeded(msg) => msg,
-            Error::Transaction(msg) => msg,
-            Error::Index(msg) => msg,
-            Error::Serialization(msg) => msg,
-            Error::Deserialization(msg) => msg,
-            Error::FileSystem(msg) => msg,
-            Error::Permission(msg) => msg,
-            Error::InvalidKey(msg) => msg,
-            Error::InvalidValue(msg) => msg,
-            Error::KeyNotFound(msg) => msg,
-            Error::DatabaseNotFound(msg) => msg,
-            Error::ObjectStoreNotFound(msg) => msg,
-            Error::IndexNotFound(msg) => msg,
-            Error::Version(msg) => msg,
-            Error::ConstraintViolation(msg) => msg,
-            Error::Timeout(msg) => msg,
-            Error::Connection(msg) => msg,
-            Error::Io(err) => err.to_string().as_str(),
-            Error::Json(err) => err.to_string().as_str(),
-            Error::Uuid(err) => err.to_string().as_str(),
-        }
-    }
-}
-
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    pub fn message(&self) -> Cow<'_, str> {
         match self {
-            Error::Storage(msg) => write!(f, "Storage error: {}", msg),
-            Error::Database(msg) => write!(f, "Database error: {}", msg),
-            Error::QuotaExceeded(msg) => write!(f, "Storage quota exceeded: {}", msg),
-            Error::Transaction(msg) => write!(f, "Transaction error: {}", msg),
-            Error::Index(msg) => write!(f, "Index error: {}", msg),
-            Error::Serialization(msg) => write!(f, "Serialization error: {}", msg),
-            Error::Deserialization(msg) => write!(f, "Deserialization error: {}", msg),
-            Error::FileSystem(msg) => write!(f, "File system error: {}", msg),
-            Error::Permission(msg) => write!(f, "Permission error: {}", msg),
-            Error::InvalidKey(msg) => write!(f, "Invalid key: {}", msg),
-            Error::InvalidValue(msg) => write!(f, "Invalid value: {}", msg),
-            Error::KeyNotFound(msg) => write!(f, "Key not found: {}", msg),
-            Error::DatabaseNotFound(msg) => write!(f, "Database not found: {}", msg),
-            Error::ObjectStoreNotFound(msg) => write!(f, "Object store not found: {}", msg),
-            Error::IndexNotFound(msg) => write!(f, "Index not found: {}", msg),
-            Error::Version(msg) => write!(f, "Version error: {}", msg),
-            Error::ConstraintViolation(msg) => write!(f, "Constraint violation: {}", msg),
-            Error::Timeout(msg) => write!(f, "Operation timeout: {}", msg),
-            Error::Connection(msg) => write!(f, "Connection error: {}", msg),
-            Error::Io(err) => write!(f, "IO error: {}", err),
-            Error::Json(err) => write!(f, "JSON error: {}", err),
-            Error::Uuid(err) => write!(f, "UUID error: {}", err),
+            Error::Storage(msg) => Cow::Borrowed(msg),
+            Error::Database(msg) => Cow::Borrowed(msg),
+            Error::QuotaExceeded(msg) => Cow::Borrowed(msg),
+            Error::Transaction(msg) => Cow::Borrowed(msg),
+            Error::Index(msg) => Cow::Borrowed(msg),
+            Error::Serialization(msg) => Cow::Borrowed(msg),
+            Error::Deserialization(msg) => Cow::Borrowed(msg),
+            Error::FileSystem(msg) => Cow::Borrowed(msg),
+            Error::Permission(msg) => Cow::Borrowed(msg),
+            Error::InvalidKey(msg) => Cow::Borrowed(msg),
+            Error::InvalidValue(msg) => Cow::Borrowed(msg),
+            Error::KeyNotFound(msg) => Cow::Borrowed(msg),
+            Error::DatabaseNotFound(msg) => Cow::Borrowed(msg),
+            Error::ObjectStoreNotFound(msg) => Cow::Borrowed(msg),
+            Error::IndexNotFound(msg) => Cow::Borrowed(msg),
+            Error::Version(msg) => Cow::Borrowed(msg),
+            Error::ConstraintViolation(msg) => Cow::Borrowed(msg),
+            Error::Timeout(msg) => Cow::Borrowed(msg),
+            Error::Connection(msg) => Cow::Borrowed(msg),
+            Error::Encryption(msg) => Cow::Borrowed(msg),
+            Error::Io(err) => Cow::Owned(err.to_string()),
+            Error::Json(err) => Cow::Owned(err.to_string()),
+            Error::Uuid(err) => Cow::Owned(err.to_string()),
         }
     }
 }
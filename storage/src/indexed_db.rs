@@ -1,4 +1,7 @@
+use crate::encryption::{self, StorageCipher};
 use crate::error::{Error, Result};
+use crate::web_storage::{StorageQuotaConfig, StorageQuotaManager};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::sync::Arc;
 use parking_lot::RwLock;
@@ -6,6 +9,7 @@ use serde::{Serialize, Deserialize};
 use std::path::{Path, PathBuf};
 use std::fs;
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, OwnedMutexGuard};
 use uuid::Uuid;
 
 /// IndexedDB manager
@@ -18,10 +22,16 @@ pub struct IndexedDBManager {
     version_manager: Arc<RwLock<DatabaseVersionManager>>,
     /// Transaction manager
     transaction_manager: Arc<RwLock<TransactionManager>>,
+    /// Storage quota manager
+    quota_manager: Arc<RwLock<StorageQuotaManager>>,
+    /// Cipher sealing database files, when encryption is enabled
+    cipher: Option<Arc<StorageCipher>>,
 }
 
 /// IndexedDB database
 pub struct IndexedDatabase {
+    /// Origin that owns the database
+    origin: String,
     /// Database name
     name: String,
     /// Database version
@@ -34,9 +44,14 @@ pub struct IndexedDatabase {
     metadata: DatabaseMetadata,
     /// Database state
     state: DatabaseState,
+    /// Lock held by writers, so read-write transactions do not interleave
+    write_lock: Arc<Mutex<()>>,
+    /// Cipher sealing the database file, when encryption is enabled
+    cipher: Option<Arc<StorageCipher>>,
 }
 
 /// Object store
+#[derive(Clone)]
 pub struct ObjectStore {
     /// Store name
     name: String,
@@ -53,6 +68,7 @@ pub struct ObjectStore {
 }
 
 /// Index
+#[derive(Clone)]
 pub struct Index {
     /// Index name
     name: String,
@@ -155,6 +171,8 @@ pub struct TransactionManager {
 pub struct Transaction {
     /// Transaction ID
     id: String,
+    /// Database the transaction runs against
+    database_name: String,
     /// Transaction mode
     mode: TransactionMode,
     /// Transaction object stores
@@ -165,6 +183,13 @@ pub struct Transaction {
     created: u64,
     /// Transaction timeout
     timeout: u64,
+    /// Requests in the order they were queued
+    requests: Vec<IndexedDBRequest>,
+    /// Object stores in scope as they were when the transaction began,
+    /// `None` for stores that did not exist
+    snapshot: HashMap<String, Option<ObjectStore>>,
+    /// Database write lock, held by writing transactions until they finish
+    write_guard: Option<OwnedMutexGuard<()>>,
 }
 
 /// Transaction mode
@@ -187,6 +212,8 @@ pub enum TransactionState {
     Committed,
     /// Transaction aborted
     Aborted,
+    /// Request failed, transaction is being aborted
+    AbortPending,
     /// Transaction error
     Error,
 }
@@ -197,6 +224,8 @@ pub struct IndexedDBRequest {
     id: String,
     /// Request type
     request_type: RequestType,
+    /// Object store the request targets
+    object_store: String,
     /// Request data
     data: RequestData,
     /// Request state
@@ -301,6 +330,21 @@ pub struct IndexedDBCursor {
     data: Vec<StoreRecord>,
 }
 
+/// Range of record keys, as `IDBKeyRange`
+///
+/// Keys compare in IndexedDB order: numbers, then strings, then arrays.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeyRange {
+    /// Lower bound, `None` for no lower bound
+    pub lower: Option<serde_json::Value>,
+    /// Upper bound, `None` for no upper bound
+    pub upper: Option<serde_json::Value>,
+    /// Whether the lower bound itself is left out
+    pub lower_open: bool,
+    /// Whether the upper bound itself is left out
+    pub upper_open: bool,
+}
+
 /// Cursor source
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum CursorSource {
@@ -326,6 +370,12 @@ pub enum CursorDirection {
 impl IndexedDBManager {
     /// Create new IndexedDB manager
     pub fn new(database_directory: PathBuf) -> Result<Self> {
+        let quota_manager = StorageQuotaManager::open(&database_directory.join("quota"), StorageQuotaConfig::default())?;
+        Self::with_quota_manager(database_directory, Arc::new(RwLock::new(quota_manager)))
+    }
+
+    /// Create IndexedDB manager sharing a quota manager with other storage
+    pub fn with_quota_manager(database_directory: PathBuf, quota_manager: Arc<RwLock<StorageQuotaManager>>) -> Result<Self> {
         // Create database directory if it doesn't exist
         fs::create_dir_all(&database_directory)
             .map_err(|e| Error::storage(format!("Failed to create database directory: {}", e)))?;
@@ -338,11 +388,18 @@ impl IndexedDBManager {
             database_directory,
             version_manager,
             transaction_manager,
+            quota_manager,
+            cipher: None,
         })
     }
 
-    /// Open database
-    pub async fn open_database(&self, name: &str, version: Option<u32>) -> Result<Arc<RwLock<IndexedDatabase>>> {
+    /// Encrypt database files opened from now on, or stop with `None`
+    pub fn set_cipher(&mut self, cipher: Option<Arc<StorageCipher>>) {
+        self.cipher = cipher;
+    }
+
+    /// Open database owned by an origin
+    pub async fn open_database(&self, origin: &str, name: &str, version: Option<u32>) -> Result<Arc<RwLock<IndexedDatabase>>> {
         let mut databases = self.databases.write();
         
         if let Some(database) = databases.get(name) {
@@ -362,10 +419,12 @@ impl IndexedDBManager {
         }
         
         // Create new database
-        let database = Arc::new(RwLock::new(IndexedDatabase::new(
+        let database = Arc::new(RwLock::new(IndexedDatabase::with_cipher(
+            origin,
             name,
             version.unwrap_or(1),
             &self.database_directory,
+            self.cipher.clone(),
         )?));
         
         databases.insert(name.to_string(), database.clone());
@@ -380,6 +439,7 @@ impl IndexedDBManager {
         if let Some(database) = databases.remove(name) {
             let db_guard = database.read();
             db_guard.delete()?;
+            self.quota_manager.write().release(&db_guard.origin, db_guard.calculate_size())?;
         }
         
         Ok(())
@@ -406,12 +466,17 @@ impl IndexedDBManager {
         let database = self.get_database(database_name).await?;
         let mut db_guard = database.write();
         
+        let size = db_guard.calculate_size();
         db_guard.delete_object_store(store_name)?;
+        self.release_freed(&db_guard, size)?;
         
         Ok(())
     }
 
     /// Add record
+    ///
+    /// Fails with `Error::QuotaExceeded` when the record does not fit in the
+    /// origin's quota.
     pub async fn add_record(
         &self,
         database_name: &str,
@@ -420,14 +485,16 @@ impl IndexedDBManager {
         value: serde_json::Value,
     ) -> Result<()> {
         let database = self.get_database(database_name).await?;
+        let _write_guard = Self::lock_writes(&database).await;
         let mut db_guard = database.write();
         
-        db_guard.add_record(store_name, key, value)?;
-        
-        Ok(())
+        self.write_record(&mut db_guard, store_name, key, value, false)
     }
 
     /// Put record
+    ///
+    /// Fails with `Error::QuotaExceeded` when the record does not fit in the
+    /// origin's quota.
     pub async fn put_record(
         &self,
         database_name: &str,
@@ -436,11 +503,10 @@ impl IndexedDBManager {
         value: serde_json::Value,
     ) -> Result<()> {
         let database = self.get_database(database_name).await?;
+        let _write_guard = Self::lock_writes(&database).await;
         let mut db_guard = database.write();
         
-        db_guard.put_record(store_name, key, value)?;
-        
-        Ok(())
+        self.write_record(&mut db_guard, store_name, key, value, true)
     }
 
     /// Get record
@@ -459,9 +525,12 @@ impl IndexedDBManager {
     /// Delete record
     pub async fn delete_record(&self, database_name: &str, store_name: &str, key: &str) -> Result<()> {
         let database = self.get_database(database_name).await?;
+        let _write_guard = Self::lock_writes(&database).await;
         let mut db_guard = database.write();
         
+        let size = db_guard.calculate_size();
         db_guard.delete_record(store_name, key)?;
+        self.release_freed(&db_guard, size)?;
         
         Ok(())
     }
@@ -469,9 +538,12 @@ impl IndexedDBManager {
     /// Clear store
     pub async fn clear_store(&self, database_name: &str, store_name: &str) -> Result<()> {
         let database = self.get_database(database_name).await?;
+        let _write_guard = Self::lock_writes(&database).await;
         let mut db_guard = database.write();
         
+        let size = db_guard.calculate_size();
         db_guard.clear_store(store_name)?;
+        self.release_freed(&db_guard, size)?;
         
         Ok(())
     }
@@ -484,6 +556,36 @@ impl IndexedDBManager {
         Ok(db_guard.count_records(store_name))
     }
 
+    /// Get the records with keys in a range, in cursor order, as `getAll()`
+    ///
+    /// Returns at most `limit` records, all matching records if `None`.
+    pub async fn get_all_in_range(
+        &self,
+        database_name: &str,
+        store_name: &str,
+        key_range: KeyRange,
+        direction: CursorDirection,
+        limit: Option<usize>,
+    ) -> Result<Vec<StoreRecord>> {
+        let database = self.get_database(database_name).await?;
+        let db_guard = database.read();
+        
+        Ok(db_guard.get_object_store(store_name)?.records_in_range(&key_range, direction, limit))
+    }
+
+    /// Open a cursor over the records with keys in a range, as `openCursor()`
+    pub async fn open_cursor(
+        &self,
+        database_name: &str,
+        store_name: &str,
+        key_range: KeyRange,
+        direction: CursorDirection,
+    ) -> Result<IndexedDBCursor> {
+        let records = self.get_all_in_range(database_name, store_name, key_range, direction, None).await?;
+        
+        Ok(IndexedDBCursor::new(CursorSource::ObjectStore, direction, records))
+    }
+
     /// Create index
     pub async fn create_index(
         &self,
@@ -512,6 +614,47 @@ impl IndexedDBManager {
         Ok(())
     }
 
+    /// Add or put a record, charging its size to the database's origin
+    ///
+    /// Fails with `Error::QuotaExceeded` when the record does not fit in the
+    /// origin's quota.
+    fn write_record(
+        &self,
+        database: &mut IndexedDatabase,
+        store_name: &str,
+        key: &str,
+        value: serde_json::Value,
+        overwrite: bool,
+    ) -> Result<()> {
+        let old_size = database.record_size(store_name, key);
+        let new_size = record_size(key, &value);
+        self.quota_manager.write().resize(&database.origin, old_size, new_size)?;
+        
+        let result = if overwrite {
+            database.put_record(store_name, key, value)
+        } else {
+            database.add_record(store_name, key, value)
+        };
+        if let Err(e) = result {
+            self.quota_manager.write().resize(&database.origin, new_size, old_size)?;
+            return Err(e);
+        }
+        
+        Ok(())
+    }
+
+    /// Wait until no read-write transaction holds the database
+    async fn lock_writes(database: &Arc<RwLock<IndexedDatabase>>) -> OwnedMutexGuard<()> {
+        let write_lock = database.read().write_lock.clone();
+        write_lock.lock_owned().await
+    }
+
+    /// Release the bytes a database shrank by since it had `old_size` bytes
+    fn release_freed(&self, database: &IndexedDatabase, old_size: usize) -> Result<()> {
+        let freed = old_size.saturating_sub(database.calculate_size());
+        self.quota_manager.write().release(&database.origin, freed)
+    }
+
     /// Get database
     async fn get_database(&self, name: &str) -> Result<Arc<RwLock<IndexedDatabase>>> {
         let databases = self.databases.read();
@@ -552,44 +695,195 @@ impl IndexedDBManager {
     }
 
     /// Create transaction
+    ///
+    /// Read-write and version change transactions wait until no other writer
+    /// holds the database, and keep it until they commit or abort.
     pub async fn create_transaction(
         &self,
         database_name: &str,
         object_stores: Vec<String>,
         mode: TransactionMode,
     ) -> Result<String> {
-        let mut transaction_manager = self.transaction_manager.write();
+        let database = self.get_database(database_name).await?;
         
         let transaction_id = Uuid::new_v4().to_string();
-        let transaction = Transaction::new(transaction_id.clone(), mode, object_stores);
+        let mut transaction = Transaction::new(transaction_id.clone(), database_name, mode, object_stores);
         
+        if mode != TransactionMode::ReadOnly {
+            transaction.write_guard = Some(Self::lock_writes(&database).await);
+            transaction.snapshot = database.read().snapshot_object_stores(&transaction.object_stores);
+        }
+        
+        let mut transaction_manager = self.transaction_manager.write();
         transaction_manager.transactions.insert(transaction_id.clone(), transaction);
         
         Ok(transaction_id)
     }
 
-    /// Commit transaction
-    pub async fn commit_transaction(&self, transaction_id: &str) -> Result<()> {
+    /// Queue a request in a transaction, returning the request ID
+    pub async fn queue_request(&self, transaction_id: &str, request: IndexedDBRequest) -> Result<String> {
         let mut transaction_manager = self.transaction_manager.write();
+        let transaction = transaction_manager.get_transaction_mut(transaction_id)?;
         
-        if let Some(transaction) = transaction_manager.transactions.get_mut(transaction_id) {
-            transaction.commit()?;
+        if transaction.state != TransactionState::Active {
+            return Err(Error::transaction("Transaction is not active".to_string()));
+        }
+        if !transaction.object_stores.contains(&request.object_store) {
+            return Err(Error::transaction(format!(
+                "Object store '{}' is not in the transaction's scope",
+                request.object_store,
+            )));
+        }
+        if transaction.mode == TransactionMode::ReadOnly && request.request_type.is_write() {
+            return Err(Error::transaction(format!(
+                "{:?} request in a read-only transaction",
+                request.request_type,
+            )));
+        }
+        
+        let request_id = request.id.clone();
+        transaction.requests.push(request);
+        
+        Ok(request_id)
+    }
+
+    /// Run the pending requests of a transaction in order
+    ///
+    /// The first failing request aborts the transaction, rolling back every
+    /// change made since it began.
+    pub async fn run_transaction(&self, transaction_id: &str) -> Result<()> {
+        let database_name = self.transaction_manager.write().get_transaction_mut(transaction_id)?.database_name.clone();
+        let database = self.get_database(&database_name).await?;
+        
+        let failure = {
+            let mut transaction_manager = self.transaction_manager.write();
+            let transaction = transaction_manager.get_transaction_mut(transaction_id)?;
+            if transaction.state != TransactionState::Active {
+                return Err(Error::transaction("Transaction is not active".to_string()));
+            }
+            
+            let mut db_guard = database.write();
+            let mut failure = None;
+            for request in transaction.requests.iter_mut().filter(|request| request.state == RequestState::Pending) {
+                request.state = RequestState::Processing;
+                match self.execute_request(&mut db_guard, request) {
+                    Ok(result) => {
+                        request.state = RequestState::Completed;
+                        request.result = Some(result);
+                    }
+                    Err(e) => {
+                        failure = Some((request.id.clone(), e));
+                        break;
+                    }
+                }
+            }
+            
+            failure.map(|(request_id, error)| {
+                let message = format!("Request {} failed: {}", request_id, error);
+                transaction.on_error(&request_id, error);
+                message
+            })
+        };
+        
+        if let Some(message) = failure {
+            self.abort_transaction(transaction_id).await?;
+            return Err(Error::transaction(message));
         }
         
         Ok(())
     }
 
-    /// Abort transaction
+    /// Commit transaction, running its pending requests first
+    pub async fn commit_transaction(&self, transaction_id: &str) -> Result<()> {
+        if !self.transaction_manager.read().transactions.contains_key(transaction_id) {
+            return Ok(());
+        }
+        
+        self.run_transaction(transaction_id).await?;
+        
+        let mut transaction_manager = self.transaction_manager.write();
+        transaction_manager.get_transaction_mut(transaction_id)?.commit()?;
+        
+        Ok(())
+    }
+
+    /// Abort transaction, rolling back the changes made since it began
     pub async fn abort_transaction(&self, transaction_id: &str) -> Result<()> {
+        let database_name = match self.transaction_manager.read().transactions.get(transaction_id) {
+            Some(transaction) => transaction.database_name.clone(),
+            None => return Ok(()),
+        };
+        let database = self.get_database(&database_name).await?;
+        
         let mut transaction_manager = self.transaction_manager.write();
+        let transaction = transaction_manager.get_transaction_mut(transaction_id)?;
+        transaction.abort()?;
         
-        if let Some(transaction) = transaction_manager.transactions.get_mut(transaction_id) {
-            transaction.abort()?;
-        }
+        let mut db_guard = database.write();
+        let size = db_guard.calculate_size();
+        db_guard.restore_object_stores(std::mem::take(&mut transaction.snapshot));
+        let restored_size = db_guard.calculate_size();
+        self.quota_manager.write().resize(&db_guard.origin, size, restored_size)?;
         
         Ok(())
     }
 
+    /// Get state of a transaction
+    pub async fn transaction_state(&self, transaction_id: &str) -> Option<TransactionState> {
+        let transaction_manager = self.transaction_manager.read();
+        
+        transaction_manager.transactions.get(transaction_id).map(|transaction| transaction.state)
+    }
+
+    /// Get state of a request queued in a transaction
+    pub async fn request_state(&self, transaction_id: &str, request_id: &str) -> Option<RequestState> {
+        let transaction_manager = self.transaction_manager.read();
+        
+        transaction_manager
+            .transactions
+            .get(transaction_id)?
+            .requests
+            .iter()
+            .find(|request| request.id == request_id)
+            .map(|request| request.state)
+    }
+
+    /// Execute a request against a database
+    fn execute_request(&self, database: &mut IndexedDatabase, request: &IndexedDBRequest) -> Result<RequestResult> {
+        let store_name = request.object_store.as_str();
+        
+        match (request.request_type, &request.data) {
+            (RequestType::AddRecord | RequestType::PutRecord, RequestData::KeyValue { key, value }) => {
+                let overwrite = request.request_type == RequestType::PutRecord;
+                self.write_record(database, store_name, key, value.clone(), overwrite)?;
+                Ok(RequestResult::String(key.clone()))
+            }
+            (RequestType::GetRecord, RequestData::String(key)) => {
+                Ok(database.get_record(store_name, key).map_or(RequestResult::None, RequestResult::Json))
+            }
+            (RequestType::DeleteRecord, RequestData::String(key)) => {
+                let size = database.calculate_size();
+                database.delete_record(store_name, key)?;
+                self.release_freed(database, size)?;
+                Ok(RequestResult::None)
+            }
+            (RequestType::ClearStore, _) => {
+                let size = database.calculate_size();
+                database.clear_store(store_name)?;
+                self.release_freed(database, size)?;
+                Ok(RequestResult::None)
+            }
+            (RequestType::CountRecords, _) => {
+                Ok(RequestResult::Number(database.count_records(store_name) as f64))
+            }
+            (request_type, data) => Err(Error::invalid_value(format!(
+                "{:?} request with {:?} cannot run in a transaction",
+                request_type,
+                data,
+            ))),
+        }
+    }
+
     /// Get database list
     pub async fn get_database_list(&self) -> Result<Vec<String>> {
         let databases = self.databases.read();
@@ -625,11 +919,22 @@ pub struct DatabaseStats {
 
 impl IndexedDatabase {
     /// Create new database
-    pub fn new(name: &str, version: u32, database_directory: &Path) -> Result<Self> {
+    pub fn new(origin: &str, name: &str, version: u32, database_directory: &Path) -> Result<Self> {
+        Self::with_cipher(origin, name, version, database_directory, None)
+    }
+
+    /// Create new database whose file is sealed by a cipher
+    pub fn with_cipher(
+        origin: &str,
+        name: &str,
+        version: u32,
+        database_directory: &Path,
+        cipher: Option<Arc<StorageCipher>>,
+    ) -> Result<Self> {
         let file_path = database_directory.join(format!("{}.json", name));
         
         let metadata = if file_path.exists() {
-            Self::load_metadata(&file_path)?
+            Self::load_metadata(&file_path, origin, cipher.as_deref())?
         } else {
             DatabaseMetadata::new()
         };
@@ -641,12 +946,15 @@ impl IndexedDatabase {
         };
         
         Ok(Self {
+            origin: origin.to_string(),
             name: name.to_string(),
             version,
             object_stores,
             file_path,
             metadata,
             state: DatabaseState::Open,
+            write_lock: Arc::new(Mutex::new(())),
+            cipher,
         })
     }
 
@@ -682,7 +990,7 @@ impl IndexedDatabase {
         let store = self.get_object_store_mut(store_name)?;
         
         if store.data.contains_key(key) {
-            return Err(Error::storage("Key already exists".to_string()));
+            return Err(Error::constraint_violation(format!("Key '{}' already exists", key)));
         }
         
         store.add_record(key, value)?;
@@ -699,9 +1007,40 @@ impl IndexedDatabase {
         Ok(())
     }
 
+    /// Copy object stores so they can be restored later
+    fn snapshot_object_stores(&self, names: &[String]) -> HashMap<String, Option<ObjectStore>> {
+        names
+            .iter()
+            .map(|name| (name.clone(), self.object_stores.get(name).cloned()))
+            .collect()
+    }
+
+    /// Restore object stores from a snapshot
+    fn restore_object_stores(&mut self, snapshot: HashMap<String, Option<ObjectStore>>) {
+        for (name, store) in snapshot {
+            match store {
+                Some(store) => self.object_stores.insert(name, store),
+                None => self.object_stores.remove(&name),
+            };
+        }
+    }
+
+    /// Get origin that owns the database
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// Get size of a record, 0 if it does not exist
+    fn record_size(&self, store_name: &str, key: &str) -> usize {
+        self.object_stores
+            .get(store_name)
+            .and_then(|store| store.data.get(key))
+            .map_or(0, |record| record.size)
+    }
+
     /// Get record
     pub fn get_record(&self, store_name: &str, key: &str) -> Option<serde_json::Value> {
-        let store = self.get_object_store(store_name)?;
+        let store = self.get_object_store(store_name).ok()?;
         
         store.get_record(key)
     }
@@ -726,9 +1065,7 @@ impl IndexedDatabase {
 
     /// Count records
     pub fn count_records(&self, store_name: &str) -> usize {
-        let store = self.get_object_store(store_name)?;
-        
-        store.data.len()
+        self.get_object_store(store_name).map_or(0, |store| store.data.len())
     }
 
     /// Create index
@@ -782,19 +1119,21 @@ impl IndexedDatabase {
             size: self.calculate_size(),
         };
         
-        let content = serde_json::to_string_pretty(&metadata)
+        let content = serde_json::to_vec_pretty(&metadata)
             .map_err(|e| Error::storage(format!("Failed to serialize metadata: {}", e)))?;
+        let content = encryption::seal(self.cipher.as_deref(), &self.origin, content)?;
         
         fs::write(&self.file_path, content)
             .map_err(|e| Error::storage(format!("Failed to write metadata file: {}", e)))
     }
 
     /// Load metadata
-    fn load_metadata(file_path: &Path) -> Result<DatabaseMetadata> {
-        let content = fs::read_to_string(file_path)
+    fn load_metadata(file_path: &Path, origin: &str, cipher: Option<&StorageCipher>) -> Result<DatabaseMetadata> {
+        let content = fs::read(file_path)
             .map_err(|e| Error::storage(format!("Failed to read metadata file: {}", e)))?;
+        let content = encryption::open(cipher, origin, content)?;
         
-        serde_json::from_str(&content)
+        serde_json::from_slice(&content)
             .map_err(|e| Error::storage(format!("Failed to parse metadata file: {}", e)))
     }
 
@@ -837,6 +1176,114 @@ impl IndexedDatabase {
     }
 }
 
+impl KeyRange {
+    /// Range of a single key, as `IDBKeyRange.only()`
+    pub fn only(key: serde_json::Value) -> Self {
+        Self::bound(key.clone(), key, false, false)
+    }
+
+    /// Range between two keys, as `IDBKeyRange.bound()`
+    pub fn bound(lower: serde_json::Value, upper: serde_json::Value, lower_open: bool, upper_open: bool) -> Self {
+        Self { lower: Some(lower), upper: Some(upper), lower_open, upper_open }
+    }
+
+    /// Range of keys from a lower bound, as `IDBKeyRange.lowerBound()`
+    pub fn lower_bound(lower: serde_json::Value, open: bool) -> Self {
+        Self { lower: Some(lower), lower_open: open, ..Self::default() }
+    }
+
+    /// Range of keys up to an upper bound, as `IDBKeyRange.upperBound()`
+    pub fn upper_bound(upper: serde_json::Value, open: bool) -> Self {
+        Self { upper: Some(upper), upper_open: open, ..Self::default() }
+    }
+
+    /// Check if a key is in the range
+    pub fn includes(&self, key: &serde_json::Value) -> bool {
+        let above_lower = self.lower.as_ref().is_none_or(|lower| match compare_keys(key, lower) {
+            Ordering::Greater => true,
+            Ordering::Equal => !self.lower_open,
+            Ordering::Less => false,
+        });
+        let below_upper = self.upper.as_ref().is_none_or(|upper| match compare_keys(key, upper) {
+            Ordering::Less => true,
+            Ordering::Equal => !self.upper_open,
+            Ordering::Greater => false,
+        });
+        above_lower && below_upper
+    }
+}
+
+impl IndexedDBCursor {
+    /// Create a cursor over records already ordered for its direction,
+    /// positioned on the first of them
+    pub fn new(source: CursorSource, direction: CursorDirection, data: Vec<StoreRecord>) -> Self {
+        let mut cursor = Self {
+            id: Uuid::new_v4().to_string(),
+            source,
+            direction,
+            key: None,
+            value: None,
+            primary_key: None,
+            position: 0,
+            data,
+        };
+        cursor.update_current();
+        cursor
+    }
+
+    /// Get the key of the current record, `None` once the cursor is past the end
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Get the value of the current record
+    pub fn value(&self) -> Option<&serde_json::Value> {
+        self.value.as_ref()
+    }
+
+    /// Get the primary key of the current record
+    pub fn primary_key(&self) -> Option<&str> {
+        self.primary_key.as_deref()
+    }
+
+    /// Get the cursor ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Get what the cursor iterates over
+    pub fn source(&self) -> CursorSource {
+        self.source
+    }
+
+    /// Get the cursor direction
+    pub fn direction(&self) -> CursorDirection {
+        self.direction
+    }
+
+    /// Move the cursor forward by a number of records, as `advance()`
+    ///
+    /// Skips straight to the record instead of stepping over the ones in
+    /// between. Returns whether the cursor is still on a record.
+    pub fn advance(&mut self, count: usize) -> Result<bool> {
+        if count == 0 {
+            return Err(Error::invalid_value("Cursor advance count must be greater than zero".to_string()));
+        }
+        
+        self.position = self.position.saturating_add(count).min(self.data.len());
+        self.update_current();
+        Ok(self.key.is_some())
+    }
+
+    /// Load the key and value of the record at the cursor position
+    fn update_current(&mut self) {
+        let record = self.data.get(self.position);
+        self.key = record.map(|record| record.key.clone());
+        self.primary_key = self.key.clone();
+        self.value = record.map(|record| record.value.clone());
+    }
+}
+
 impl ObjectStore {
     /// Create new object store
     pub fn new(name: &str, key_path: KeyPath, auto_increment: bool) -> Self {
@@ -873,7 +1320,7 @@ impl ObjectStore {
             value: value.clone(),
             created: current_time,
             modified: current_time,
-            size: key.len() + serde_json::to_string(&value).unwrap().len(),
+            size: record_size(key, &value),
         };
         
         self.data.insert(key.to_string(), record);
@@ -897,7 +1344,7 @@ impl ObjectStore {
             value: value.clone(),
             created: current_time,
             modified: current_time,
-            size: key.len() + serde_json::to_string(&value).unwrap().len(),
+            size: record_size(key, &value),
         };
         
         self.data.insert(key.to_string(), record);
@@ -914,6 +1361,26 @@ impl ObjectStore {
         self.data.get(key).map(|record| record.value.clone())
     }
 
+    /// Get the records with keys in a range, ordered for a cursor direction
+    pub fn records_in_range(&self, key_range: &KeyRange, direction: CursorDirection, limit: Option<usize>) -> Vec<StoreRecord> {
+        let mut records: Vec<(serde_json::Value, &StoreRecord)> = self.data.values()
+            .filter_map(|record| {
+                let key = key_value(&record.key);
+                key_range.includes(&key).then_some((key, record))
+            })
+            .collect();
+        
+        records.sort_unstable_by(|(a, _), (b, _)| compare_keys(a, b));
+        if matches!(direction, CursorDirection::Prev | CursorDirection::PrevUnique) {
+            records.reverse();
+        }
+        
+        records.into_iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|(_, record)| record.clone())
+            .collect()
+    }
+
     /// Delete record
     pub fn delete_record(&mut self, key: &str) -> Result<()> {
         if let Some(record) = self.data.remove(key) {
@@ -1111,11 +1578,17 @@ impl TransactionManager {
             transaction_counter: 0,
         }
     }
+
+    fn get_transaction_mut(&mut self, transaction_id: &str) -> Result<&mut Transaction> {
+        self.transactions
+            .get_mut(transaction_id)
+            .ok_or_else(|| Error::transaction(format!("Transaction '{}' not found", transaction_id)))
+    }
 }
 
 impl Transaction {
     /// Create new transaction
-    pub fn new(id: String, mode: TransactionMode, object_stores: Vec<String>) -> Self {
+    pub fn new(id: String, database_name: &str, mode: TransactionMode, object_stores: Vec<String>) -> Self {
         let current_time = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -1123,14 +1596,23 @@ impl Transaction {
         
         Self {
             id,
+            database_name: database_name.to_string(),
             mode,
             object_stores,
             state: TransactionState::Active,
             created: current_time,
             timeout: 5000, // 5 seconds
+            requests: Vec::new(),
+            snapshot: HashMap::new(),
+            write_guard: None,
         }
     }
 
+    /// Get transaction state
+    pub fn state(&self) -> TransactionState {
+        self.state
+    }
+
     /// Commit transaction
     pub fn commit(&mut self) -> Result<()> {
         if self.state != TransactionState::Active {
@@ -1138,18 +1620,145 @@ impl Transaction {
         }
         
         self.state = TransactionState::Committed;
+        self.finish();
         
         Ok(())
     }
 
     /// Abort transaction
+    ///
+    /// Rolling back changes is up to `IndexedDBManager::abort_transaction`.
     pub fn abort(&mut self) -> Result<()> {
-        if self.state != TransactionState::Active {
+        if !matches!(self.state, TransactionState::Active | TransactionState::AbortPending) {
             return Err(Error::storage("Transaction is not active".to_string()));
         }
         
         self.state = TransactionState::Aborted;
+        for request in &mut self.requests {
+            if matches!(request.state, RequestState::Pending | RequestState::Processing) {
+                request.fail(Error::transaction("Transaction aborted".to_string()));
+            }
+        }
+        self.finish();
         
         Ok(())
     }
+
+    /// Handle a failed request
+    ///
+    /// Marks the transaction for abort and fails the request along with every
+    /// request still queued.
+    pub fn on_error(&mut self, request_id: &str, error: Error) {
+        self.state = TransactionState::AbortPending;
+        
+        let mut error = Some(error);
+        for request in &mut self.requests {
+            if request.id == request_id {
+                if let Some(error) = error.take() {
+                    request.fail(error);
+                }
+            } else if matches!(request.state, RequestState::Pending | RequestState::Processing) {
+                request.fail(Error::transaction("Transaction aborted".to_string()));
+            }
+        }
+    }
+
+    /// Release the database write lock
+    fn finish(&mut self) {
+        self.write_guard = None;
+    }
+}
+
+impl IndexedDBRequest {
+    /// Create new request against an object store
+    pub fn new(request_type: RequestType, object_store: &str, data: RequestData) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            request_type,
+            object_store: object_store.to_string(),
+            data,
+            state: RequestState::Pending,
+            result: None,
+            error: None,
+        }
+    }
+
+    /// Get request ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Get request state
+    pub fn state(&self) -> RequestState {
+        self.state
+    }
+
+    /// Get request result
+    pub fn result(&self) -> Option<&RequestResult> {
+        self.result.as_ref()
+    }
+
+    /// Get request error
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    fn fail(&mut self, error: Error) {
+        self.state = RequestState::Error;
+        self.error = Some(error);
+    }
+}
+
+impl RequestType {
+    /// Check if requests of this type modify data
+    pub fn is_write(self) -> bool {
+        matches!(
+            self,
+            RequestType::CreateObjectStore
+                | RequestType::DeleteObjectStore
+                | RequestType::AddRecord
+                | RequestType::PutRecord
+                | RequestType::DeleteRecord
+                | RequestType::ClearStore
+                | RequestType::CreateIndex
+                | RequestType::DeleteIndex
+        )
+    }
+}
+
+/// Key of a record as an IndexedDB key: a number, a JSON string or array, or the text itself
+fn key_value(key: &str) -> serde_json::Value {
+    serde_json::from_str::<serde_json::Value>(key)
+        .ok()
+        .filter(|value| value.is_number() || value.is_string() || value.is_array())
+        .unwrap_or_else(|| serde_json::Value::String(key.to_string()))
+}
+
+/// Compare two keys in IndexedDB order, numbers before strings before arrays
+fn compare_keys(a: &serde_json::Value, b: &serde_json::Value) -> Ordering {
+    use serde_json::Value;
+    
+    let rank = |value: &Value| match value {
+        Value::Number(_) => 0,
+        Value::String(_) => 1,
+        Value::Array(_) => 2,
+        _ => 3,
+    };
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => {
+            a.as_f64().unwrap_or(f64::NAN).total_cmp(&b.as_f64().unwrap_or(f64::NAN))
+        }
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Array(a), Value::Array(b)) => a.iter()
+            .zip(b)
+            .map(|(a, b)| compare_keys(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Size of a record, its key plus its serialized value
+fn record_size(key: &str, value: &serde_json::Value) -> usize {
+    key.len() + serde_json::to_string(value).map_or(0, |value| value.len())
 }
@@ -1,16 +1,20 @@
 //! Storage module for Matte Browser
 //! 
-//! This module provides Web Storage (localStorage, sessionStorage) and IndexedDB
-//! implementations for the browser.
+//! This module provides Web Storage (localStorage, sessionStorage), IndexedDB,
+//! the Origin Private File System and cookie storage for the browser.
 
 pub mod error;
 pub mod web_storage;
 pub mod indexed_db;
+pub mod cookie_store;
+pub mod encryption;
+pub mod cache_api;
+pub mod opfs;
 
 pub use error::{Error, Result};
 pub use web_storage::{
     WebStorageManager, LocalStorage, SessionStorage, StorageItem,
-    StorageQuotaManager, StoragePartitioningManager, StoragePartition,
+    StorageQuotaManager, StorageQuotaConfig, DEFAULT_PER_ORIGIN_QUOTA_MB, StoragePartitioningManager, StoragePartition,
     PartitionPolicy, PartitionPolicyType, PartitionRule,
     StorageEvent, StorageEventType, StorageStats,
 };
@@ -20,9 +24,25 @@ pub use indexed_db::{
     DatabaseState, DatabaseVersionManager, TransactionManager,
     Transaction, TransactionMode, TransactionState,
     IndexedDBRequest, RequestType, RequestData, RequestState, RequestResult,
-    IndexedDBCursor, CursorSource, CursorDirection,
+    IndexedDBCursor, CursorSource, CursorDirection, KeyRange,
     DatabaseStats,
 };
+pub use cookie_store::{Cookie, CookieOptions, CookieStore, RequestSite, SameSite, SetCookie};
+pub use cache_api::{CacheApiManager, CacheHandle, CacheStorage, CachedResponse, FetchRequest, RequestKey};
+pub use opfs::{OpfsDirectoryHandle, OpfsEntryKind, OpfsFileHandle, OpfsManager, SyncFileHandle};
+pub use encryption::{
+    KeyDerivation, MasterKeyStore, MemoryMasterKeyStore, SecretString, StorageCipher,
+    StorageEncryptionConfig, platform_master_key_store,
+};
+
+/// Storage configuration
+#[derive(Debug, Clone, Default)]
+pub struct StorageConfig {
+    /// Per-origin quotas
+    pub quota: StorageQuotaConfig,
+    /// Encryption at rest
+    pub encryption: StorageEncryptionConfig,
+}
 
 /// Storage manager that combines Web Storage and IndexedDB
 pub struct StorageManager {
@@ -30,10 +50,17 @@ pub struct StorageManager {
     web_storage: Arc<RwLock<WebStorageManager>>,
     /// IndexedDB manager
     indexed_db: Arc<RwLock<IndexedDBManager>>,
+    /// Storage quota manager shared by Web Storage and IndexedDB
+    quota_manager: Arc<RwLock<StorageQuotaManager>>,
     /// Storage directory
     storage_directory: PathBuf,
+    /// Cipher sealing origin data on disk, when encryption is enabled
+    cipher: Option<Arc<StorageCipher>>,
+    /// Origin private file systems, by origin
+    opfs: Arc<RwLock<HashMap<String, OpfsManager>>>,
 }
 
+use std::collections::HashMap;
 use std::sync::Arc;
 use parking_lot::RwLock;
 use std::path::PathBuf;
@@ -41,16 +68,58 @@ use std::path::PathBuf;
 impl StorageManager {
     /// Create new storage manager
     pub async fn new(storage_directory: PathBuf) -> Result<Self> {
-        let web_storage = Arc::new(RwLock::new(WebStorageManager::new(storage_directory.clone())?));
-        let indexed_db = Arc::new(RwLock::new(IndexedDBManager::new(storage_directory.join("indexeddb"))?));
+        Self::with_quota_config(storage_directory, StorageQuotaConfig::default()).await
+    }
+
+    /// Create new storage manager with a quota configuration
+    pub async fn with_quota_config(storage_directory: PathBuf, quota_config: StorageQuotaConfig) -> Result<Self> {
+        Self::with_config(storage_directory, StorageConfig { quota: quota_config, ..Default::default() }).await
+    }
+
+    /// Create new storage manager, keeping the encryption master key in the OS keychain
+    pub async fn with_config(storage_directory: PathBuf, config: StorageConfig) -> Result<Self> {
+        Self::with_master_key_store(storage_directory, config, platform_master_key_store()).await
+    }
+
+    /// Create new storage manager keeping the encryption master key in a key store
+    pub async fn with_master_key_store(
+        storage_directory: PathBuf,
+        config: StorageConfig,
+        key_store: Arc<dyn MasterKeyStore>,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(&storage_directory)
+            .map_err(|e| Error::storage(format!("Failed to create storage directory: {}", e)))?;
+        
+        let cipher = match config.encryption.enabled {
+            true => Some(Arc::new(StorageCipher::from_config(&config.encryption, &storage_directory, key_store.as_ref())?)),
+            false => None,
+        };
+        let quota_manager = Arc::new(RwLock::new(StorageQuotaManager::open(&storage_directory.join("quota"), config.quota)?));
+        let mut web_storage = WebStorageManager::with_quota_manager(storage_directory.clone(), quota_manager.clone())?;
+        web_storage.set_cipher(cipher.clone());
+        let mut indexed_db = IndexedDBManager::with_quota_manager(storage_directory.join("indexeddb"), quota_manager.clone())?;
+        indexed_db.set_cipher(cipher.clone());
         
         Ok(Self {
-            web_storage,
-            indexed_db,
+            web_storage: Arc::new(RwLock::new(web_storage)),
+            indexed_db: Arc::new(RwLock::new(indexed_db)),
+            quota_manager,
             storage_directory,
+            cipher,
+            opfs: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 
+    /// Derive the encryption key of an origin from the master key, using HKDF with the origin as info
+    pub fn derive_key(origin: &str, master_key: &[u8; 32]) -> [u8; 32] {
+        encryption::derive_key(origin, master_key)
+    }
+
+    /// Get the cipher sealing origin data on disk, `None` if encryption is disabled
+    pub fn cipher(&self) -> Option<Arc<StorageCipher>> {
+        self.cipher.clone()
+    }
+
     /// Get Web Storage manager
     pub fn web_storage(&self) -> Arc<RwLock<WebStorageManager>> {
         self.web_storage.clone()
@@ -61,6 +130,23 @@ impl StorageManager {
         self.indexed_db.clone()
     }
 
+    /// Get the origin private file system of an origin
+    pub fn opfs(&self, origin: &str) -> Result<OpfsManager> {
+        let mut file_systems = self.opfs.write();
+        if let Some(file_system) = file_systems.get(origin) {
+            return Ok(file_system.clone());
+        }
+
+        let file_system = OpfsManager::new(&self.storage_directory, origin)?;
+        file_systems.insert(origin.to_string(), file_system.clone());
+        Ok(file_system)
+    }
+
+    /// Get storage quota manager
+    pub fn quota_manager(&self) -> Arc<RwLock<StorageQuotaManager>> {
+        self.quota_manager.clone()
+    }
+
     /// Get storage directory
     pub fn storage_directory(&self) -> &PathBuf {
         &self.storage_directory
@@ -92,10 +178,13 @@ impl StorageManager {
             }
         };
         
+        let total_size = web_storage_stats.total_size + indexed_db_stats.total_size;
+        
         Ok(CombinedStorageStats {
             web_storage: web_storage_stats,
             indexed_db: indexed_db_stats,
-            total_size: web_storage_stats.total_size + indexed_db_stats.total_size,
+            origin_usage: self.quota_manager.read().usage_by_origin(),
+            total_size,
         })
     }
 
@@ -133,6 +222,8 @@ pub struct CombinedStorageStats {
     pub web_storage: StorageStats,
     /// IndexedDB statistics
     pub indexed_db: IndexedDBStats,
+    /// Bytes counted against the quota of each origin
+    pub origin_usage: HashMap<String, usize>,
     /// Total storage size
     pub total_size: usize,
 }
@@ -198,7 +289,7 @@ mod tests {
         let store_name = "test_store";
         
         // Open database
-        let db = indexed_db.read().open_database(db_name, Some(1)).await;
+        let db = indexed_db.read().open_database("https://example.com", db_name, Some(1)).await;
         assert!(db.is_ok());
         
         // Create object store
@@ -244,4 +335,262 @@ mod tests {
         assert_eq!(stats.web_storage.item_count, 0);
         assert_eq!(stats.indexed_db.database_count, 0);
     }
+
+    #[tokio::test]
+    async fn test_local_storage_events_reach_other_tabs() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage_manager = StorageManager::new(temp_dir.path().to_path_buf()).await.unwrap();
+        let web_storage = storage_manager.web_storage();
+        let web_storage = web_storage.read();
+        
+        let origin = "https://example.com";
+        let mut tab1 = web_storage.subscribe(origin, 1).unwrap();
+        let mut tab2 = web_storage.subscribe(origin, 2).unwrap();
+        let mut other_origin = web_storage.subscribe("https://other.example", 3).unwrap();
+        
+        web_storage.set_local_storage_item_from(origin, "theme", "dark", 1, "https://example.com/settings").await.unwrap();
+        
+        let event = tab2.try_recv().unwrap();
+        assert_eq!(event.event_type, StorageEventType::Set);
+        assert_eq!(event.key.as_deref(), Some("theme"));
+        assert_eq!(event.old_value, None);
+        assert_eq!(event.new_value.as_deref(), Some("dark"));
+        assert_eq!(event.url, "https://example.com/settings");
+        assert_eq!(event.storage_area, "localStorage");
+        assert!(tab1.try_recv().is_err());
+        assert!(other_origin.try_recv().is_err());
+        
+        web_storage.remove_local_storage_item_from(origin, "theme", 2, "https://example.com/").await.unwrap();
+        let event = tab1.try_recv().unwrap();
+        assert_eq!(event.event_type, StorageEventType::Remove);
+        assert_eq!(event.old_value.as_deref(), Some("dark"));
+        assert!(tab2.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_storage_quota_per_origin() {
+        let temp_dir = TempDir::new().unwrap();
+        let quota_config = StorageQuotaConfig { per_origin_quota_mb: 1 };
+        let storage_manager = StorageManager::with_quota_config(temp_dir.path().to_path_buf(), quota_config).await.unwrap();
+        let web_storage = storage_manager.web_storage();
+        let indexed_db = storage_manager.indexed_db();
+        
+        let origin = "https://example.com";
+        let quota = 1024 * 1024;
+        
+        // Fill the origin's quota exactly
+        let value = "x".repeat(quota / 2 - 4);
+        web_storage.read().set_local_storage_item(origin, "key1", &value).await.unwrap();
+        web_storage.read().set_local_storage_item(origin, "key2", &value).await.unwrap();
+        assert_eq!(storage_manager.quota_manager().read().origin_usage(origin), quota);
+        
+        // One more byte does not fit
+        let result = web_storage.read().set_local_storage_item(origin, "key3", "x").await;
+        assert!(matches!(result, Err(Error::QuotaExceeded(_))));
+        assert_eq!(web_storage.read().get_local_storage_item(origin, "key3").await.unwrap(), None);
+        
+        // IndexedDB shares the origin's quota
+        indexed_db.read().open_database(origin, "db", Some(1)).await.unwrap();
+        indexed_db.read().create_object_store("db", "store", KeyPath::String("id".to_string()), false).await.unwrap();
+        let result = indexed_db.read().put_record("db", "store", "id", serde_json::json!({"id": "id"})).await;
+        assert!(matches!(result, Err(Error::QuotaExceeded(_))));
+        
+        // Other origins are unaffected
+        web_storage.read().set_local_storage_item("https://other.example", "key", "value").await.unwrap();
+        
+        // Removing an item frees its space
+        web_storage.read().remove_local_storage_item(origin, "key2").await.unwrap();
+        indexed_db.read().put_record("db", "store", "id", serde_json::json!({"id": "id"})).await.unwrap();
+        
+        let stats = storage_manager.get_storage_stats().await.unwrap();
+        assert_eq!(stats.origin_usage.len(), 2);
+        assert_eq!(stats.origin_usage["https://other.example"], "keyvalue".len());
+        assert!(stats.origin_usage[origin] < quota);
+    }
+
+    #[tokio::test]
+    async fn test_indexed_db_get_all_in_range() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage_manager = StorageManager::new(temp_dir.path().to_path_buf()).await.unwrap();
+        let indexed_db = storage_manager.indexed_db();
+        let indexed_db = indexed_db.read();
+        
+        indexed_db.open_database("https://example.com", "db", Some(1)).await.unwrap();
+        indexed_db.create_object_store("db", "store", KeyPath::String("id".to_string()), false).await.unwrap();
+        for id in 0..1000 {
+            indexed_db.put_record("db", "store", &id.to_string(), serde_json::json!({ "id": id })).await.unwrap();
+        }
+        
+        let keys = |records: Vec<StoreRecord>| records.into_iter().map(|record| record.key).collect::<Vec<_>>();
+        let range = KeyRange::bound(serde_json::json!(95), serde_json::json!(120), true, false);
+        let records = indexed_db.get_all_in_range("db", "store", range.clone(), CursorDirection::Next, None).await.unwrap();
+        assert_eq!(keys(records), (96..=120).map(|id: i32| id.to_string()).collect::<Vec<_>>());
+        
+        let records = indexed_db.get_all_in_range("db", "store", range.clone(), CursorDirection::Prev, Some(3)).await.unwrap();
+        assert_eq!(keys(records), ["120", "119", "118"]);
+        
+        let records = indexed_db.get_all_in_range("db", "store", KeyRange::lower_bound(serde_json::json!(998), false), CursorDirection::Next, None).await.unwrap();
+        assert_eq!(keys(records), ["998", "999"]);
+        
+        // Advancing by more than one record skips straight to it
+        let mut cursor = indexed_db.open_cursor("db", "store", range, CursorDirection::Next).await.unwrap();
+        assert_eq!(cursor.key(), Some("96"));
+        assert!(cursor.advance(10).unwrap());
+        assert_eq!(cursor.key(), Some("106"));
+        assert_eq!(cursor.value(), Some(&serde_json::json!({ "id": 106 })));
+        assert!(!cursor.advance(100).unwrap());
+        assert_eq!(cursor.key(), None);
+        assert!(cursor.advance(0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_indexed_db_transaction_abort_on_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage_manager = StorageManager::new(temp_dir.path().to_path_buf()).await.unwrap();
+        let indexed_db = storage_manager.indexed_db();
+        let indexed_db = indexed_db.read();
+        
+        let origin = "https://example.com";
+        indexed_db.open_database(origin, "db", Some(1)).await.unwrap();
+        indexed_db.create_object_store("db", "store", KeyPath::String("id".to_string()), false).await.unwrap();
+        
+        let transaction_id = indexed_db
+            .create_transaction("db", vec!["store".to_string()], TransactionMode::ReadWrite)
+            .await
+            .unwrap();
+        
+        // The second add reuses the first key and violates the key constraint
+        let mut request_ids = Vec::new();
+        for key in ["first", "first", "second"] {
+            let request = IndexedDBRequest::new(
+                RequestType::AddRecord,
+                "store",
+                RequestData::KeyValue { key: key.to_string(), value: serde_json::json!({"id": key}) },
+            );
+            request_ids.push(indexed_db.queue_request(&transaction_id, request).await.unwrap());
+        }
+        
+        let result = indexed_db.commit_transaction(&transaction_id).await;
+        assert!(matches!(result, Err(Error::Transaction(_))));
+        assert_eq!(indexed_db.transaction_state(&transaction_id).await, Some(TransactionState::Aborted));
+        for request_id in &request_ids[1..] {
+            assert_eq!(indexed_db.request_state(&transaction_id, request_id).await, Some(RequestState::Error));
+        }
+        
+        // Neither record persists and the write lock is released
+        assert_eq!(indexed_db.get_record("db", "store", "first").await.unwrap(), None);
+        assert_eq!(indexed_db.get_record("db", "store", "second").await.unwrap(), None);
+        assert_eq!(storage_manager.quota_manager().read().origin_usage(origin), 0);
+        indexed_db.put_record("db", "store", "first", serde_json::json!({"id": "first"})).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_local_storage_encrypted_at_rest() {
+        let temp_dir = TempDir::new().unwrap();
+        let key_store: Arc<dyn MasterKeyStore> = Arc::new(MemoryMasterKeyStore::default());
+        let config = StorageConfig {
+            encryption: StorageEncryptionConfig { enabled: true, ..Default::default() },
+            ..Default::default()
+        };
+        let origin = "https://example.com";
+
+        let storage_manager = StorageManager::with_master_key_store(temp_dir.path().to_path_buf(), config.clone(), key_store.clone())
+            .await
+            .unwrap();
+        storage_manager.web_storage().read().set_local_storage_item(origin, "token", "hunter2").await.unwrap();
+
+        let raw = std::fs::read(temp_dir.path().join("local_storage_https_example.com.json")).unwrap();
+        assert!(!raw.windows(b"hunter2".len()).any(|window| window == b"hunter2"));
+        let plaintext = storage_manager.cipher().unwrap().decrypt(origin, &raw).unwrap();
+        let items: HashMap<String, StorageItem> = serde_json::from_slice(&plaintext).unwrap();
+        assert_eq!(items["token"].value, "hunter2");
+
+        let master_key = key_store.load().unwrap().unwrap();
+        assert_ne!(StorageManager::derive_key(origin, &master_key), StorageManager::derive_key("https://other.example", &master_key));
+
+        drop(storage_manager);
+        let reopened = StorageManager::with_master_key_store(temp_dir.path().to_path_buf(), config, key_store).await.unwrap();
+        let value = reopened.web_storage().read().get_local_storage_item(origin, "token").await.unwrap();
+        assert_eq!(value, Some("hunter2".to_string()));
+    }
+
+    #[test]
+    fn test_cache_api_matches_stored_response() {
+        let temp_dir = TempDir::new().unwrap();
+        let response = CachedResponse {
+            status_code: 200,
+            headers: HashMap::from([("content-type".to_string(), "text/css".to_string())]),
+            body: b"body { color: red }".to_vec(),
+            content_type: "text/css".to_string(),
+        };
+
+        let caches = CacheApiManager::new(&temp_dir.path().join("caches")).unwrap().for_origin("https://app.example");
+        let cache = caches.open("static-v1").unwrap();
+        cache.put(FetchRequest::get("https://app.example/style.css"), response.clone()).unwrap();
+        cache.put(FetchRequest::get("https://app.example/app.js"), response.clone()).unwrap();
+        assert!(cache.put(FetchRequest { method: "POST".to_string(), ..FetchRequest::get("https://app.example/api") }, response.clone()).is_err());
+
+        // Fragments are ignored, and the method has to match
+        assert_eq!(cache.match_request(&FetchRequest::get("https://app.example/style.css#top")).unwrap(), Some(response.clone()));
+        assert_eq!(cache.match_request(&FetchRequest { method: "HEAD".to_string(), ..FetchRequest::get("https://app.example/style.css") }).unwrap(), None);
+        assert_eq!(caches.match_request(&FetchRequest::get("https://app.example/app.js")).unwrap(), Some(response.clone()));
+        assert_eq!(
+            cache.keys().unwrap().iter().map(|request| request.url.as_str()).collect::<Vec<_>>(),
+            ["https://app.example/style.css", "https://app.example/app.js"]
+        );
+
+        // Caches of other origins are separate
+        let other = caches.for_origin("https://other.example");
+        assert!(other.keys().unwrap().is_empty());
+        assert_eq!(other.open("static-v1").unwrap().match_request(&FetchRequest::get("https://app.example/style.css")).unwrap(), None);
+
+        assert!(cache.delete(&FetchRequest::get("https://app.example/app.js")).unwrap());
+        assert!(!cache.delete(&FetchRequest::get("https://app.example/app.js")).unwrap());
+        drop((cache, other, caches));
+
+        // Entries survive reopening the database
+        let caches = CacheApiManager::new(&temp_dir.path().join("caches")).unwrap().for_origin("https://app.example");
+        assert_eq!(caches.keys().unwrap(), ["static-v1"]);
+        let cache = caches.open("static-v1").unwrap();
+        assert_eq!(cache.keys().unwrap().len(), 1);
+        assert!(caches.delete("static-v1").unwrap());
+        assert!(!caches.has("static-v1").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_opfs_file_persists_after_reopening() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage_manager = StorageManager::new(temp_dir.path().to_path_buf()).await.unwrap();
+        let opfs = storage_manager.opfs("https://app.example").unwrap();
+
+        let file = opfs.get_directory("/projects/notes").unwrap().get_file("todo.txt", true).unwrap();
+        let mut handle = file.create_sync_access_handle().unwrap();
+        assert_eq!(handle.write(b"buy milk", None).unwrap(), 8);
+        assert_eq!(handle.write(b", eggs", None).unwrap(), 6);
+        handle.write(b"M", Some(4)).unwrap();
+        // A file has one sync access handle at a time
+        assert!(file.create_sync_access_handle().is_err());
+        handle.close().unwrap();
+        drop(storage_manager);
+
+        let storage_manager = StorageManager::new(temp_dir.path().to_path_buf()).await.unwrap();
+        let opfs = storage_manager.opfs("https://app.example").unwrap();
+        let directory = opfs.get_directory("projects/notes").unwrap();
+        assert_eq!(directory.entries().unwrap(), [("todo.txt".to_string(), OpfsEntryKind::File)]);
+        let mut handle = directory.get_file("todo.txt", false).unwrap().create_sync_access_handle().unwrap();
+        assert_eq!(handle.get_size().unwrap(), 14);
+        let mut buffer = [0; 32];
+        let read = handle.read(&mut buffer, None).unwrap();
+        assert_eq!(&buffer[..read], b"buy Milk, eggs");
+        handle.truncate(3).unwrap();
+        drop(handle);
+        assert_eq!(directory.get_file("todo.txt", false).unwrap().read().unwrap(), b"buy");
+
+        // Entry names cannot leave the directory, and other origins see their own root
+        assert!(directory.get_file("../todo.txt", true).is_err());
+        assert!(opfs.get_directory("projects/..").is_err());
+        let other = storage_manager.opfs("https://other.example").unwrap();
+        assert!(other.root().entries().unwrap().is_empty());
+        assert!(other.root().get_directory_handle("projects", false).is_err());
+    }
 }
@@ -0,0 +1,344 @@
+//! Origin Private File System
+//!
+//! Every origin gets a private directory tree under `opfs/` in the storage
+//! directory, named after the origin. Handles only reach entries below their
+//! origin's root: entry names are single path components, so `..` and path
+//! separators are rejected. Files are not sealed by the storage cipher, since
+//! sync access handles read and write at arbitrary offsets.
+
+use crate::error::{Error, Result};
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Directory, inside the storage directory, of the origin roots
+const OPFS_DIRECTORY: &str = "opfs";
+
+/// Kind of an entry in a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpfsEntryKind {
+    /// A file
+    File,
+    /// A directory
+    Directory,
+}
+
+/// Origin private file system of one origin, as `navigator.storage.getDirectory()` exposes it
+///
+/// Clones share the locks of the files opened for synchronous access.
+#[derive(Clone)]
+pub struct OpfsManager {
+    /// Origin owning the file system
+    origin: String,
+    /// Directory all entries of the origin live in
+    root: PathBuf,
+    /// Files with an open sync access handle
+    locked_files: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl OpfsManager {
+    /// Open the file system of an origin, creating its root directory
+    pub fn new(storage_directory: &Path, origin: &str) -> Result<Self> {
+        let root = storage_directory.join(OPFS_DIRECTORY).join(origin_directory_name(origin));
+        fs::create_dir_all(&root)
+            .map_err(|e| Error::file_system(format!("Failed to create OPFS root for {}: {}", origin, e)))?;
+
+        Ok(Self {
+            origin: origin.to_string(),
+            root,
+            locked_files: Arc::new(Mutex::new(HashSet::new())),
+        })
+    }
+
+    /// Get the origin owning the file system
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// Get the root directory
+    pub fn root(&self) -> OpfsDirectoryHandle {
+        OpfsDirectoryHandle {
+            file_system: self.clone(),
+            path: self.root.clone(),
+        }
+    }
+
+    /// Get a directory by its `/` separated path from the root, creating the directories missing
+    pub fn get_directory(&self, path: &str) -> Result<OpfsDirectoryHandle> {
+        let mut directory = self.root();
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            directory = directory.get_directory_handle(name, true)?;
+        }
+        Ok(directory)
+    }
+
+    fn is_locked(&self, path: &Path) -> bool {
+        self.locked_files.lock().iter().any(|locked| locked.starts_with(path))
+    }
+}
+
+/// Directory in an origin private file system, as `FileSystemDirectoryHandle`
+#[derive(Clone)]
+pub struct OpfsDirectoryHandle {
+    /// File system the directory belongs to
+    file_system: OpfsManager,
+    /// Path of the directory on disk
+    path: PathBuf,
+}
+
+impl OpfsDirectoryHandle {
+    /// Get the directory name, empty for the root
+    pub fn name(&self) -> String {
+        entry_name(&self.file_system, &self.path)
+    }
+
+    /// Get a subdirectory, creating it if `create` is set
+    pub fn get_directory_handle(&self, name: &str, create: bool) -> Result<OpfsDirectoryHandle> {
+        let path = self.entry_path(name)?;
+        match entry_kind(&path)? {
+            Some(OpfsEntryKind::Directory) => {}
+            Some(OpfsEntryKind::File) => {
+                return Err(Error::file_system(format!("{} is a file, not a directory", name)));
+            }
+            None if create => fs::create_dir(&path).map_err(io_error)?,
+            None => return Err(Error::file_system(format!("Directory {} not found", name))),
+        }
+
+        Ok(OpfsDirectoryHandle {
+            file_system: self.file_system.clone(),
+            path,
+        })
+    }
+
+    /// Get a file, creating an empty one if `create` is set
+    pub fn get_file(&self, name: &str, create: bool) -> Result<OpfsFileHandle> {
+        let path = self.entry_path(name)?;
+        match entry_kind(&path)? {
+            Some(OpfsEntryKind::File) => {}
+            Some(OpfsEntryKind::Directory) => {
+                return Err(Error::file_system(format!("{} is a directory, not a file", name)));
+            }
+            None if create => {
+                File::create(&path).map_err(io_error)?;
+            }
+            None => return Err(Error::file_system(format!("File {} not found", name))),
+        }
+
+        Ok(OpfsFileHandle {
+            file_system: self.file_system.clone(),
+            path,
+        })
+    }
+
+    /// Remove an entry, which has to be empty if it is a directory unless `recursive` is set
+    ///
+    /// Entries holding a file open for synchronous access cannot be removed.
+    pub fn remove_entry(&self, name: &str, recursive: bool) -> Result<()> {
+        let path = self.entry_path(name)?;
+        if self.file_system.is_locked(&path) {
+            return Err(Error::file_system(format!("{} is open for synchronous access", name)));
+        }
+
+        match entry_kind(&path)? {
+            Some(OpfsEntryKind::File) => fs::remove_file(&path).map_err(io_error),
+            Some(OpfsEntryKind::Directory) if recursive => fs::remove_dir_all(&path).map_err(io_error),
+            Some(OpfsEntryKind::Directory) => fs::remove_dir(&path).map_err(io_error),
+            None => Err(Error::file_system(format!("Entry {} not found", name))),
+        }
+    }
+
+    /// Get the names and kinds of the entries, in name order
+    pub fn entries(&self) -> Result<Vec<(String, OpfsEntryKind)>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&self.path).map_err(io_error)? {
+            let entry = entry.map_err(io_error)?;
+            let kind = match entry.file_type().map_err(io_error)?.is_dir() {
+                true => OpfsEntryKind::Directory,
+                false => OpfsEntryKind::File,
+            };
+            entries.push((entry.file_name().to_string_lossy().into_owned(), kind));
+        }
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(entries)
+    }
+
+    fn entry_path(&self, name: &str) -> Result<PathBuf> {
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '\0']) {
+            return Err(Error::invalid_value(format!("Invalid OPFS entry name: {:?}", name)));
+        }
+        Ok(self.path.join(name))
+    }
+}
+
+/// File in an origin private file system, as `FileSystemFileHandle`
+#[derive(Clone)]
+pub struct OpfsFileHandle {
+    /// File system the file belongs to
+    file_system: OpfsManager,
+    /// Path of the file on disk
+    path: PathBuf,
+}
+
+impl OpfsFileHandle {
+    /// Get the file name
+    pub fn name(&self) -> String {
+        entry_name(&self.file_system, &self.path)
+    }
+
+    /// Read the whole file
+    pub fn read(&self) -> Result<Vec<u8>> {
+        fs::read(&self.path).map_err(io_error)
+    }
+
+    /// Replace the contents of the file
+    pub fn write(&self, data: &[u8]) -> Result<()> {
+        if self.file_system.is_locked(&self.path) {
+            return Err(Error::file_system(format!("{} is open for synchronous access", self.name())));
+        }
+        fs::write(&self.path, data).map_err(io_error)
+    }
+
+    /// Open the file for synchronous reads and writes, as `createSyncAccessHandle()`
+    ///
+    /// Pages only get this from a dedicated worker. The handle locks the
+    /// file until it is closed or dropped, so a file has at most one.
+    pub fn create_sync_access_handle(&self) -> Result<SyncFileHandle> {
+        if !self.file_system.locked_files.lock().insert(self.path.clone()) {
+            return Err(Error::file_system(format!("{} already has a sync access handle", self.name())));
+        }
+
+        match OpenOptions::new().read(true).write(true).open(&self.path) {
+            Ok(file) => Ok(SyncFileHandle {
+                file: Some(file),
+                path: self.path.clone(),
+                locked_files: self.file_system.locked_files.clone(),
+                position: 0,
+            }),
+            Err(e) => {
+                self.file_system.locked_files.lock().remove(&self.path);
+                Err(io_error(e))
+            }
+        }
+    }
+}
+
+/// Synchronous access to an OPFS file, as `FileSystemSyncAccessHandle`
+///
+/// Reads and writes without an offset continue where the last one stopped.
+pub struct SyncFileHandle {
+    /// Open file, `None` once the handle is closed
+    file: Option<File>,
+    /// Path of the file, the key of its lock
+    path: PathBuf,
+    /// Locks of the file system the file belongs to
+    locked_files: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Offset of the next read or write without an offset
+    position: u64,
+}
+
+impl SyncFileHandle {
+    /// Read into a buffer, from an offset or the current position, returning the bytes read
+    pub fn read(&mut self, buffer: &mut [u8], at: Option<u64>) -> Result<usize> {
+        let offset = at.unwrap_or(self.position);
+        let file = self.file()?;
+        file.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+
+        let mut read = 0;
+        while read < buffer.len() {
+            match file.read(&mut buffer[read..]) {
+                Ok(0) => break,
+                Ok(count) => read += count,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(io_error(e)),
+            }
+        }
+
+        self.position = offset + read as u64;
+        Ok(read)
+    }
+
+    /// Write data, at an offset or the current position, returning the bytes written
+    pub fn write(&mut self, data: &[u8], at: Option<u64>) -> Result<usize> {
+        let offset = at.unwrap_or(self.position);
+        let file = self.file()?;
+        file.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+        file.write_all(data).map_err(io_error)?;
+
+        self.position = offset + data.len() as u64;
+        Ok(data.len())
+    }
+
+    /// Resize the file, filling it with zeros when it grows
+    pub fn truncate(&mut self, size: u64) -> Result<()> {
+        self.file()?.set_len(size).map_err(io_error)?;
+        self.position = self.position.min(size);
+        Ok(())
+    }
+
+    /// Get the size of the file in bytes
+    pub fn get_size(&mut self) -> Result<u64> {
+        Ok(self.file()?.metadata().map_err(io_error)?.len())
+    }
+
+    /// Write the file contents through to disk
+    pub fn flush(&mut self) -> Result<()> {
+        self.file()?.sync_data().map_err(io_error)
+    }
+
+    /// Flush and close the file, releasing its lock
+    ///
+    /// Closing a closed handle does nothing.
+    pub fn close(&mut self) -> Result<()> {
+        let Some(file) = self.file.take() else {
+            return Ok(());
+        };
+        self.locked_files.lock().remove(&self.path);
+        file.sync_data().map_err(io_error)
+    }
+
+    fn file(&mut self) -> Result<&mut File> {
+        self.file.as_mut().ok_or_else(|| Error::file_system("Sync access handle is closed".to_string()))
+    }
+}
+
+impl Drop for SyncFileHandle {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            self.locked_files.lock().remove(&self.path);
+        }
+    }
+}
+
+/// Name of the root directory of an origin, with every byte but ASCII
+/// letters, digits, `-` and `_` percent-encoded
+fn origin_directory_name(origin: &str) -> String {
+    origin.bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn entry_name(file_system: &OpfsManager, path: &Path) -> String {
+    if path == file_system.root {
+        return String::new();
+    }
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn entry_kind(path: &Path) -> Result<Option<OpfsEntryKind>> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => Ok(Some(OpfsEntryKind::Directory)),
+        Ok(_) => Ok(Some(OpfsEntryKind::File)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(io_error(e)),
+    }
+}
+
+fn io_error(err: std::io::Error) -> Error {
+    Error::file_system(format!("OPFS error: {}", err))
+}
@@ -1,3 +1,4 @@
+use crate::encryption::{self, StorageCipher};
 use crate::error::{Error, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -6,19 +7,35 @@ use serde::{Serialize, Deserialize};
 use std::path::{Path, PathBuf};
 use std::fs;
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+/// Directory, inside the storage directory, of the persistent handle files
+const HANDLES_DIRECTORY: &str = "handles";
+
+/// Storage events a subscriber can fall behind by before older ones are dropped
+const STORAGE_EVENT_CAPACITY: usize = 64;
+
+/// Senders of the tabs subscribed to an origin's storage events, with their tab IDs
+type StorageSubscribers = HashMap<String, Vec<(u64, broadcast::Sender<StorageEvent>)>>;
 
 /// Web Storage manager
 pub struct WebStorageManager {
     /// Local storage instances
-    local_storage: Arc<RwLock<HashMap<String, LocalStorage>>>,
+    local_storage: Arc<RwLock<HashMap<String, Arc<RwLock<LocalStorage>>>>>,
     /// Session storage instances
-    session_storage: Arc<RwLock<HashMap<String, SessionStorage>>>,
+    session_storage: Arc<RwLock<HashMap<String, Arc<RwLock<SessionStorage>>>>>,
+    /// Persistent handle stores, by origin
+    handle_storage: Arc<RwLock<HashMap<String, Arc<RwLock<LocalStorage>>>>>,
     /// Storage quota manager
     quota_manager: Arc<RwLock<StorageQuotaManager>>,
     /// Storage partitioning manager
     partitioning_manager: Arc<RwLock<StoragePartitioningManager>>,
     /// Storage directory
     storage_directory: PathBuf,
+    /// Tabs listening for `localStorage` changes, by origin
+    subscribers: Arc<RwLock<StorageSubscribers>>,
+    /// Cipher sealing `localStorage` files, when encryption is enabled
+    cipher: Option<Arc<StorageCipher>>,
 }
 
 /// Local storage
@@ -33,6 +50,8 @@ pub struct LocalStorage {
     last_modified: u64,
     /// Storage size in bytes
     size: usize,
+    /// Cipher sealing the storage file, when encryption is enabled
+    cipher: Option<Arc<StorageCipher>>,
 }
 
 /// Session storage
@@ -64,20 +83,31 @@ pub struct StorageItem {
     pub size: usize,
 }
 
+/// Default per-origin storage quota in megabytes
+pub const DEFAULT_PER_ORIGIN_QUOTA_MB: usize = 50;
+
 /// Storage quota manager
+///
+/// Bytes used by each origin are persisted, so usage of data that outlives
+/// the process is still counted after a restart.
 pub struct StorageQuotaManager {
     /// Global storage quota
     global_quota: usize,
     /// Per-origin storage quota
     per_origin_quota: usize,
-    /// Current global usage
-    global_usage: usize,
-    /// Per-origin usage
-    origin_usage: HashMap<String, usize>,
+    /// Bytes used by each origin
+    origin_usage: sled::Db,
     /// Quota exceeded origins
     quota_exceeded: HashMap<String, bool>,
 }
 
+/// Storage quota configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageQuotaConfig {
+    /// Per-origin storage quota in megabytes
+    pub per_origin_quota_mb: usize,
+}
+
 /// Storage partitioning manager
 pub struct StoragePartitioningManager {
     /// Storage partitions
@@ -161,6 +191,8 @@ pub struct StorageEvent {
     pub url: String,
     /// Storage origin
     pub origin: String,
+    /// Storage area changed, `localStorage` or `sessionStorage`
+    pub storage_area: String,
     /// Event timestamp
     pub timestamp: u64,
 }
@@ -195,25 +227,46 @@ pub struct StorageStats {
     pub quota_usage_percentage: f64,
 }
 
+impl Default for StorageQuotaConfig {
+    fn default() -> Self {
+        Self {
+            per_origin_quota_mb: DEFAULT_PER_ORIGIN_QUOTA_MB,
+        }
+    }
+}
+
 impl WebStorageManager {
     /// Create new web storage manager
     pub fn new(storage_directory: PathBuf) -> Result<Self> {
+        let quota_manager = StorageQuotaManager::open(&storage_directory.join("quota"), StorageQuotaConfig::default())?;
+        Self::with_quota_manager(storage_directory, Arc::new(RwLock::new(quota_manager)))
+    }
+
+    /// Create web storage manager sharing a quota manager with other storage
+    pub fn with_quota_manager(storage_directory: PathBuf, quota_manager: Arc<RwLock<StorageQuotaManager>>) -> Result<Self> {
         // Create storage directory if it doesn't exist
         fs::create_dir_all(&storage_directory)
             .map_err(|e| Error::storage(format!("Failed to create storage directory: {}", e)))?;
         
-        let quota_manager = Arc::new(RwLock::new(StorageQuotaManager::new()));
         let partitioning_manager = Arc::new(RwLock::new(StoragePartitioningManager::new()));
         
         Ok(Self {
             local_storage: Arc::new(RwLock::new(HashMap::new())),
             session_storage: Arc::new(RwLock::new(HashMap::new())),
+            handle_storage: Arc::new(RwLock::new(HashMap::new())),
             quota_manager,
             partitioning_manager,
             storage_directory,
+            subscribers: Arc::new(RwLock::new(HashMap::new())),
+            cipher: None,
         })
     }
 
+    /// Encrypt `localStorage` files opened from now on, or stop with `None`
+    pub fn set_cipher(&mut self, cipher: Option<Arc<StorageCipher>>) {
+        self.cipher = cipher;
+    }
+
     /// Get local storage for origin
     pub async fn get_local_storage(&self, origin: &str) -> Result<Arc<RwLock<LocalStorage>>> {
         let mut storage = self.local_storage.write();
@@ -223,12 +276,47 @@ impl WebStorageManager {
         }
         
         // Create new local storage
-        let local_storage = Arc::new(RwLock::new(LocalStorage::new(origin, &self.storage_directory)?));
+        let local_storage = Arc::new(RwLock::new(LocalStorage::with_cipher(origin, &self.storage_directory, self.cipher.clone())?));
         storage.insert(origin.to_string(), local_storage.clone());
         
         Ok(local_storage)
     }
 
+    /// Get the persistent handle store of an origin
+    ///
+    /// Handles are kept apart from `localStorage`, so pages cannot read or
+    /// change them through the Web Storage API.
+    async fn get_handle_storage(&self, origin: &str) -> Result<Arc<RwLock<LocalStorage>>> {
+        let mut storage = self.handle_storage.write();
+        
+        if let Some(handle_storage) = storage.get(origin) {
+            return Ok(handle_storage.clone());
+        }
+        
+        let directory = self.storage_directory.join(HANDLES_DIRECTORY);
+        fs::create_dir_all(&directory)
+            .map_err(|e| Error::storage(format!("Failed to create handle directory: {}", e)))?;
+        let handle_storage = Arc::new(RwLock::new(LocalStorage::with_cipher(origin, &directory, self.cipher.clone())?));
+        storage.insert(origin.to_string(), handle_storage.clone());
+        
+        Ok(handle_storage)
+    }
+
+    /// Persist a serialized handle, such as a file system handle, for an origin
+    pub async fn set_persistent_handle(&self, origin: &str, key: &str, handle: &str) -> Result<()> {
+        self.get_handle_storage(origin).await?.write().set_item(key, handle)
+    }
+
+    /// Get a handle persisted for an origin
+    pub async fn get_persistent_handle(&self, origin: &str, key: &str) -> Result<Option<String>> {
+        Ok(self.get_handle_storage(origin).await?.read().get_item(key))
+    }
+
+    /// Remove a handle persisted for an origin
+    pub async fn remove_persistent_handle(&self, origin: &str, key: &str) -> Result<()> {
+        self.get_handle_storage(origin).await?.write().remove_item(key)
+    }
+
     /// Get session storage for origin
     pub async fn get_session_storage(&self, origin: &str, session_id: &str) -> Result<Arc<RwLock<SessionStorage>>> {
         let key = format!("{}:{}", origin, session_id);
@@ -245,19 +333,53 @@ impl WebStorageManager {
         Ok(session_storage)
     }
 
+    /// Subscribe a tab to the `storage` events of an origin's `localStorage`
+    ///
+    /// The tab receives the changes made by every other tab of the origin.
+    pub fn subscribe(&self, origin: &str, tab_id: u64) -> Result<broadcast::Receiver<StorageEvent>> {
+        let (sender, receiver) = broadcast::channel(STORAGE_EVENT_CAPACITY);
+        self.subscribers.write()
+            .entry(origin.to_string())
+            .or_default()
+            .push((tab_id, sender));
+        
+        Ok(receiver)
+    }
+
     /// Set local storage item
+    ///
+    /// Fails with `Error::QuotaExceeded` when the item does not fit in the
+    /// origin's quota.
     pub async fn set_local_storage_item(&self, origin: &str, key: &str, value: &str) -> Result<()> {
+        self.write_local_storage_item(origin, key, value, None).await
+    }
+
+    /// Set local storage item from a document in a tab, sending a `storage`
+    /// event to the origin's other tabs
+    pub async fn set_local_storage_item_from(&self, origin: &str, key: &str, value: &str, tab_id: u64, url: &str) -> Result<()> {
+        self.write_local_storage_item(origin, key, value, Some((tab_id, url))).await
+    }
+
+    async fn write_local_storage_item(&self, origin: &str, key: &str, value: &str, source: Option<(u64, &str)>) -> Result<()> {
         let storage = self.get_local_storage(origin).await?;
         let mut storage_guard = storage.write();
         
-        // Check quota
-        self.check_quota(origin, key, value).await?;
+        // Only the growth of a replaced item counts against the quota
+        let old_value = storage_guard.get_item(key);
+        let old_size = storage_guard.data.get(key).map_or(0, |item| item.size);
+        let new_size = key.len() + value.len();
+        self.quota_manager.write().resize(origin, old_size, new_size)?;
         
-        // Set item
-        storage_guard.set_item(key, value)?;
+        if let Err(e) = storage_guard.set_item(key, value) {
+            self.quota_manager.write().resize(origin, new_size, old_size)?;
+            return Err(e);
+        }
+        drop(storage_guard);
         
-        // Update quota usage
-        self.update_quota_usage(origin, key, value).await?;
+        // Setting an item to its current value changes nothing
+        if old_value.as_deref() != Some(value) {
+            self.dispatch_storage_event(origin, StorageEventType::Set, Some(key), old_value, Some(value.to_string()), source);
+        }
         
         Ok(())
     }
@@ -272,38 +394,106 @@ impl WebStorageManager {
 
     /// Remove local storage item
     pub async fn remove_local_storage_item(&self, origin: &str, key: &str) -> Result<()> {
+        self.delete_local_storage_item(origin, key, None).await
+    }
+
+    /// Remove local storage item from a document in a tab, sending a
+    /// `storage` event to the origin's other tabs
+    pub async fn remove_local_storage_item_from(&self, origin: &str, key: &str, tab_id: u64, url: &str) -> Result<()> {
+        self.delete_local_storage_item(origin, key, Some((tab_id, url))).await
+    }
+
+    async fn delete_local_storage_item(&self, origin: &str, key: &str, source: Option<(u64, &str)>) -> Result<()> {
         let storage = self.get_local_storage(origin).await?;
         let mut storage_guard = storage.write();
         
+        let old_value = storage_guard.get_item(key);
+        let size = storage_guard.data.get(key).map_or(0, |item| item.size);
         storage_guard.remove_item(key)?;
+        self.quota_manager.write().release(origin, size)?;
+        drop(storage_guard);
+        
+        if old_value.is_some() {
+            self.dispatch_storage_event(origin, StorageEventType::Remove, Some(key), old_value, None, source);
+        }
         
         Ok(())
     }
 
     /// Clear local storage
     pub async fn clear_local_storage(&self, origin: &str) -> Result<()> {
+        self.clear_local_storage_items(origin, None).await
+    }
+
+    /// Clear local storage from a document in a tab, sending a `storage`
+    /// event to the origin's other tabs
+    pub async fn clear_local_storage_from(&self, origin: &str, tab_id: u64, url: &str) -> Result<()> {
+        self.clear_local_storage_items(origin, Some((tab_id, url))).await
+    }
+
+    async fn clear_local_storage_items(&self, origin: &str, source: Option<(u64, &str)>) -> Result<()> {
         let storage = self.get_local_storage(origin).await?;
         let mut storage_guard = storage.write();
         
+        let was_empty = storage_guard.data.is_empty();
+        let size = storage_guard.size;
         storage_guard.clear()?;
+        self.quota_manager.write().release(origin, size)?;
+        drop(storage_guard);
+        
+        if !was_empty {
+            self.dispatch_storage_event(origin, StorageEventType::Clear, None, None, None, source);
+        }
         
         Ok(())
     }
 
+    /// Send a `localStorage` change to the subscribed tabs of an origin other
+    /// than the tab that made it, dropping tabs that stopped listening
+    fn dispatch_storage_event(
+        &self,
+        origin: &str,
+        event_type: StorageEventType,
+        key: Option<&str>,
+        old_value: Option<String>,
+        new_value: Option<String>,
+        source: Option<(u64, &str)>,
+    ) {
+        let mut subscribers = self.subscribers.write();
+        let Some(tabs) = subscribers.get_mut(origin) else {
+            return;
+        };
+        tabs.retain(|(_, sender)| sender.receiver_count() > 0);
+        
+        let event = StorageEvent {
+            event_type,
+            key: key.map(str::to_string),
+            old_value,
+            new_value,
+            url: source.map_or(origin, |(_, url)| url).to_string(),
+            origin: origin.to_string(),
+            storage_area: "localStorage".to_string(),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        };
+        for (tab_id, sender) in tabs.iter() {
+            if source.is_some_and(|(source_tab, _)| source_tab == *tab_id) {
+                continue;
+            }
+            // A tab that closed since the check above just misses the event
+            let _ = sender.send(event.clone());
+        }
+    }
+
     /// Set session storage item
+    ///
+    /// Session storage is not persisted, so it does not count against the
+    /// origin's quota.
     pub async fn set_session_storage_item(&self, origin: &str, session_id: &str, key: &str, value: &str) -> Result<()> {
         let storage = self.get_session_storage(origin, session_id).await?;
         let mut storage_guard = storage.write();
         
-        // Check quota
-        self.check_quota(origin, key, value).await?;
-        
-        // Set item
         storage_guard.set_item(key, value)?;
         
-        // Update quota usage
-        self.update_quota_usage(origin, key, value).await?;
-        
         Ok(())
     }
 
@@ -365,7 +555,7 @@ impl WebStorageManager {
         }
         
         let quota_usage_percentage = if quota_manager.global_quota > 0 {
-            (quota_manager.global_usage as f64 / quota_manager.global_quota as f64) * 100.0
+            (quota_manager.global_usage() as f64 / quota_manager.global_quota as f64) * 100.0
         } else {
             0.0
         };
@@ -380,41 +570,6 @@ impl WebStorageManager {
         })
     }
 
-    /// Check storage quota
-    async fn check_quota(&self, origin: &str, key: &str, value: &str) -> Result<()> {
-        let quota_manager = self.quota_manager.read();
-        
-        let item_size = key.len() + value.len();
-        let current_usage = quota_manager.origin_usage.get(origin).unwrap_or(&0);
-        let new_usage = current_usage + item_size;
-        
-        if new_usage > quota_manager.per_origin_quota {
-            return Err(Error::storage("Storage quota exceeded".to_string()));
-        }
-        
-        if quota_manager.global_usage + item_size > quota_manager.global_quota {
-            return Err(Error::storage("Global storage quota exceeded".to_string()));
-        }
-        
-        Ok(())
-    }
-
-    /// Update quota usage
-    async fn update_quota_usage(&self, origin: &str, key: &str, value: &str) -> Result<()> {
-        let mut quota_manager = self.quota_manager.write();
-        
-        let item_size = key.len() + value.len();
-        
-        // Update origin usage
-        let current_usage = quota_manager.origin_usage.get(origin).unwrap_or(&0);
-        quota_manager.origin_usage.insert(origin.to_string(), current_usage + item_size);
-        
-        // Update global usage
-        quota_manager.global_usage += item_size;
-        
-        Ok(())
-    }
-
     /// Get storage partition for origin
     pub async fn get_storage_partition(&self, origin: &str) -> Result<String> {
         let partitioning_manager = self.partitioning_manager.read();
@@ -491,10 +646,15 @@ impl WebStorageManager {
 impl LocalStorage {
     /// Create new local storage
     pub fn new(origin: &str, storage_directory: &Path) -> Result<Self> {
+        Self::with_cipher(origin, storage_directory, None)
+    }
+
+    /// Create new local storage whose file is sealed by a cipher
+    pub fn with_cipher(origin: &str, storage_directory: &Path, cipher: Option<Arc<StorageCipher>>) -> Result<Self> {
         let file_path = storage_directory.join(format!("local_storage_{}.json", origin.replace("://", "_")));
         
         let data = if file_path.exists() {
-            Self::load_from_file(&file_path)?
+            Self::load_from_file(&file_path, origin, cipher.as_deref())?
         } else {
             HashMap::new()
         };
@@ -511,6 +671,7 @@ impl LocalStorage {
             file_path,
             last_modified,
             size,
+            cipher,
         })
     }
 
@@ -591,22 +752,24 @@ impl LocalStorage {
     }
 
     /// Load from file
-    fn load_from_file(file_path: &Path) -> Result<HashMap<String, StorageItem>> {
+    fn load_from_file(file_path: &Path, origin: &str, cipher: Option<&StorageCipher>) -> Result<HashMap<String, StorageItem>> {
         if !file_path.exists() {
             return Ok(HashMap::new());
         }
         
-        let content = fs::read_to_string(file_path)
+        let content = fs::read(file_path)
             .map_err(|e| Error::storage(format!("Failed to read storage file: {}", e)))?;
+        let content = encryption::open(cipher, origin, content)?;
         
-        serde_json::from_str(&content)
+        serde_json::from_slice(&content)
             .map_err(|e| Error::storage(format!("Failed to parse storage file: {}", e)))
     }
 
     /// Save to file
     fn save_to_file(&self) -> Result<()> {
-        let content = serde_json::to_string_pretty(&self.data)
+        let content = serde_json::to_vec_pretty(&self.data)
             .map_err(|e| Error::storage(format!("Failed to serialize storage data: {}", e)))?;
+        let content = encryption::seal(self.cipher.as_deref(), &self.origin, content)?;
         
         fs::write(&self.file_path, content)
             .map_err(|e| Error::storage(format!("Failed to write storage file: {}", e)))
@@ -704,17 +867,100 @@ impl SessionStorage {
 }
 
 impl StorageQuotaManager {
-    /// Create new storage quota manager
-    pub fn new() -> Self {
+    /// Open the quota manager persisting usage in a directory
+    pub fn open(path: &Path, config: StorageQuotaConfig) -> Result<Self> {
+        let origin_usage = sled::open(path)
+            .map_err(|e| Error::database(format!("Failed to open quota database {:?}: {}", path, e)))?;
+        
+        Ok(Self::with_database(origin_usage, config))
+    }
+
+    /// Create a quota manager that forgets usage when dropped
+    pub fn in_memory(config: StorageQuotaConfig) -> Result<Self> {
+        let origin_usage = sled::Config::new()
+            .temporary(true)
+            .open()
+            .map_err(|e| Error::database(format!("Failed to open quota database: {}", e)))?;
+        
+        Ok(Self::with_database(origin_usage, config))
+    }
+
+    fn with_database(origin_usage: sled::Db, config: StorageQuotaConfig) -> Self {
         Self {
             global_quota: 100 * 1024 * 1024, // 100 MB
-            per_origin_quota: 10 * 1024 * 1024, // 10 MB
-            global_usage: 0,
-            origin_usage: HashMap::new(),
+            per_origin_quota: config.per_origin_quota_mb * 1024 * 1024,
+            origin_usage,
             quota_exceeded: HashMap::new(),
         }
     }
 
+    /// Reserve bytes for an origin
+    ///
+    /// Fails with `Error::QuotaExceeded` without reserving anything when the
+    /// origin or global quota would be exceeded.
+    pub fn reserve(&mut self, origin: &str, bytes: usize) -> Result<()> {
+        let usage = self.origin_usage(origin);
+        
+        if usage + bytes > self.per_origin_quota {
+            self.quota_exceeded.insert(origin.to_string(), true);
+            return Err(Error::quota_exceeded(format!(
+                "{} needs {} bytes but has {} of {} bytes left",
+                origin,
+                bytes,
+                self.per_origin_quota.saturating_sub(usage),
+                self.per_origin_quota,
+            )));
+        }
+        
+        if self.global_usage() + bytes > self.global_quota {
+            self.quota_exceeded.insert(origin.to_string(), true);
+            return Err(Error::quota_exceeded(format!(
+                "Global storage quota of {} bytes exceeded",
+                self.global_quota,
+            )));
+        }
+        
+        self.set_origin_usage(origin, usage + bytes)
+    }
+
+    /// Release bytes previously reserved for an origin
+    pub fn release(&mut self, origin: &str, bytes: usize) -> Result<()> {
+        let usage = self.origin_usage(origin).saturating_sub(bytes);
+        self.quota_exceeded.remove(origin);
+        
+        self.set_origin_usage(origin, usage)
+    }
+
+    /// Reserve or release the difference when stored data changes size
+    pub fn resize(&mut self, origin: &str, old_size: usize, new_size: usize) -> Result<()> {
+        if new_size > old_size {
+            self.reserve(origin, new_size - old_size)
+        } else {
+            self.release(origin, old_size - new_size)
+        }
+    }
+
+    /// Get usage of every origin with stored data
+    pub fn usage_by_origin(&self) -> HashMap<String, usize> {
+        self.origin_usage
+            .iter()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|(origin, usage)| {
+                Some((String::from_utf8(origin.to_vec()).ok()?, decode_usage(&usage)))
+            })
+            .collect()
+    }
+
+    fn set_origin_usage(&self, origin: &str, usage: usize) -> Result<()> {
+        let result = if usage == 0 {
+            self.origin_usage.remove(origin).map(|_| ())
+        } else {
+            self.origin_usage.insert(origin, &(usage as u64).to_be_bytes()).map(|_| ())
+        };
+        
+        result.map_err(|e| Error::database(format!("Failed to update quota usage for {}: {}", origin, e)))
+    }
+
     /// Set global quota
     pub fn set_global_quota(&mut self, quota: usize) {
         self.global_quota = quota;
@@ -737,12 +983,23 @@ impl StorageQuotaManager {
 
     /// Get global usage
     pub fn global_usage(&self) -> usize {
-        self.global_usage
+        self.origin_usage
+            .iter()
+            .values()
+            .filter_map(|usage| usage.ok())
+            .map(|usage| decode_usage(&usage))
+            .sum()
     }
 
     /// Get origin usage
     pub fn origin_usage(&self, origin: &str) -> usize {
-        self.origin_usage.get(origin).unwrap_or(&0).clone()
+        match self.origin_usage.get(origin) {
+            Ok(usage) => usage.map_or(0, |usage| decode_usage(&usage)),
+            Err(e) => {
+                log::warn!("Failed to read quota usage for {}: {}", origin, e);
+                0
+            }
+        }
     }
 
     /// Check if quota exceeded
@@ -804,3 +1061,8 @@ impl StoragePartitioningManager {
         self.policies.get(policy_name)
     }
 }
+
+/// Decode usage stored as a big-endian byte count
+fn decode_usage(bytes: &[u8]) -> usize {
+    bytes.try_into().map_or(0, |bytes| u64::from_be_bytes(bytes) as usize)
+}